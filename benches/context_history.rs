@@ -0,0 +1,67 @@
+//! 每轮对话准备历史记录的开销基准
+//!
+//! 500 条消息（部分内联了较大的文件内容），对比旧的做法
+//! （每轮重新序列化全部消息计算 token + 整体拷贝历史）与使用缓存的做法。
+//!
+//! 运行：`cargo bench --bench context_history`
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use oxide::context::{ContextManager, SerializableMessage};
+use oxide::token_counter::count_messages_tokens;
+use rig::completion::Message;
+use std::hint::black_box;
+use tempfile::TempDir;
+
+const HISTORY_LEN: usize = 500;
+
+fn build_history(dir: &TempDir) -> ContextManager {
+    let file_content = "fn example() -> usize { 42 }\n".repeat(200);
+    let mut manager = ContextManager::new(dir.path(), "bench".to_string())
+        .unwrap()
+        .with_max_messages(HISTORY_LEN);
+    for i in 0..HISTORY_LEN {
+        if i % 2 == 0 {
+            manager.add_message(Message::user(format!("@src/file_{}.rs\n{}", i, file_content)));
+        } else {
+            manager.add_message(Message::assistant(format!("answer {}", i)));
+        }
+    }
+    manager
+}
+
+fn bench_history(c: &mut Criterion) {
+    let temp_dir = TempDir::new().unwrap();
+    let manager = build_history(&temp_dir);
+
+    let mut group = c.benchmark_group("history_500_messages");
+
+    group.bench_function("legacy_reserialize_and_clone", |b| {
+        b.iter(|| {
+            let messages = manager.get_messages();
+            let tokens = count_messages_tokens(
+                &messages
+                    .iter()
+                    .map(|m| {
+                        let serializable = SerializableMessage::from(m);
+                        (serializable.role, serializable.content)
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            let history = manager.get_messages().to_vec();
+            black_box((tokens, history))
+        })
+    });
+
+    group.bench_function("cached_tokens_and_prompt_history", |b| {
+        b.iter(|| {
+            let tokens = manager.estimated_input_tokens();
+            let history = manager.prompt_history();
+            black_box((tokens, history))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_history);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+//! grep_search 性能基准
+//!
+//! 生成约 50k 个文件的目录树，对比单线程遍历（旧实现的行为）与并行遍历。
+//!
+//! 运行：`cargo bench --bench grep_search`
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use oxide::tools::grep_search::{search_files, SearchOptions};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const DIRS: usize = 500;
+const FILES_PER_DIR: usize = 100;
+
+/// 生成测试目录树，每 97 个文件中有一个包含匹配行
+fn generate_tree(base: &Path) {
+    let filler = "fn helper(value: usize) -> usize { value * 2 }\n".repeat(40);
+    for d in 0..DIRS {
+        let dir = base.join(format!("module_{:03}", d));
+        fs::create_dir_all(&dir).unwrap();
+        for f in 0..FILES_PER_DIR {
+            let index = d * FILES_PER_DIR + f;
+            let content = if index % 97 == 0 {
+                format!("{}// NEEDLE_MARKER {}\n", filler, index)
+            } else {
+                filler.clone()
+            };
+            fs::write(dir.join(format!("file_{:03}.rs", f)), content).unwrap();
+        }
+    }
+}
+
+fn bench_grep(c: &mut Criterion) {
+    let temp_dir = TempDir::new().unwrap();
+    generate_tree(temp_dir.path());
+    let root = temp_dir.path().to_string_lossy().to_string();
+
+    let options = SearchOptions::default();
+    let mut group = c.benchmark_group("grep_search_50k_files");
+    group.sample_size(10);
+
+    group.bench_function("single_thread", |b| {
+        b.iter(|| search_files(&root, "NEEDLE_MARKER", &options, 1000, 1, true).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| search_files(&root, "NEEDLE_MARKER", &options, 1000, 0, true).unwrap())
+    });
+    group.bench_function("parallel_early_exit", |b| {
+        b.iter(|| search_files(&root, "NEEDLE_MARKER", &options, 10, 0, true).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_grep);
+criterion_main!(benches);
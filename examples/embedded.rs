@@ -0,0 +1,85 @@
+//! 在自己的程序中嵌入 oxide 的 Agent 循环
+//!
+//! 用 mockito 模拟一个 Anthropic Messages API（返回固定的流式回复），
+//! 通过 `OxideSession` 跑一轮对话并打印事件流、历史和用量。
+//!
+//! 运行：`cargo run --example embedded`
+
+use futures::StreamExt;
+use oxide::agent::stream_event::StreamEvent;
+use oxide::config::{Config, Secret};
+use oxide::session::OxideSession;
+
+/// 模拟服务返回的 SSE 回复
+const MOCK_RESPONSE: &str = concat!(
+    "event: message_start\n",
+    r#"data: {"type":"message_start","message":{"id":"msg_mock","type":"message","role":"assistant","model":"mock-model","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":42,"output_tokens":1}}}"#,
+    "\n\n",
+    "event: content_block_start\n",
+    r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+    "\n\n",
+    "event: content_block_delta\n",
+    r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"The change looks good."}}"#,
+    "\n\n",
+    "event: content_block_delta\n",
+    r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" Ship it."}}"#,
+    "\n\n",
+    "event: content_block_stop\n",
+    r#"data: {"type":"content_block_stop","index":0}"#,
+    "\n\n",
+    "event: message_delta\n",
+    r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":8}}"#,
+    "\n\n",
+    "event: message_stop\n",
+    r#"data: {"type":"message_stop"}"#,
+    "\n\n",
+);
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", mockito::Matcher::Any)
+        .with_header("content-type", "text/event-stream")
+        .with_body(MOCK_RESPONSE)
+        .create_async()
+        .await;
+
+    // 地址中包含 `/anthropic` 时使用 Anthropic 客户端
+    let config = Config {
+        base_url: format!("{}/anthropic", server.url()),
+        auth_token: Secret::new("mock-key".to_string()),
+        model: Some("mock-model".to_string()),
+        max_tokens: 1024,
+        stream_chars_per_tick: 0,
+        stream_animation: false,
+        debug: false,
+    };
+    let workdir = tempfile::tempdir()?;
+    let mut session = OxideSession::builder(config)
+        .with_working_dir(workdir.path())
+        .build()?;
+
+    let mut events = session.send("Review the staged changes");
+    while let Some(event) = events.next().await {
+        match event {
+            StreamEvent::TextDelta { text } => print!("{}", text),
+            StreamEvent::Error { message } => anyhow::bail!("turn failed: {}", message),
+            _ => {}
+        }
+    }
+    // 事件流借用了 session，结束后释放
+    drop(events);
+    println!();
+
+    mock.assert_async().await;
+    let usage = session.usage();
+    println!(
+        "session {}: {} messages, {} input / {} output tokens",
+        session.session_id(),
+        session.history().len(),
+        usage.input_tokens,
+        usage.output_tokens
+    );
+    Ok(())
+}
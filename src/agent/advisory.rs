@@ -0,0 +1,253 @@
+//! 会话质量提示
+//!
+//! 长会话的回复质量会悄悄下降。每轮结束后检查几项启发式指标：上下文用量越过
+//! 70% / 85% / 95%、本会话的轮数、同一工具反复以相同错误失败。越过阈值时在终端
+//! 显示一次提示（不阻塞输入），每个阈值在一个会话内最多提示一次；
+//! 用 `[behavior] advisories = false` 关闭。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::config::{BehaviorConfig, ConfigLoader};
+
+/// 上下文用量提示阈值（百分比，升序）
+const CONTEXT_THRESHOLDS: [usize; 3] = [70, 85, 95];
+
+/// 会话轮数达到该值时建议开始新会话
+const LONG_SESSION_TURNS: usize = 40;
+
+/// 同一工具以相同错误连续失败的次数达到该值时提示
+const REPEATED_FAILURES: usize = 3;
+
+/// 工具出错时 rig 写入工具结果的前缀
+const TOOL_ERROR: &str = "ToolCallError: ";
+
+/// 一条会话质量提示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Advisory {
+    /// 上下文用量越过阈值
+    ContextUsage { percent: usize },
+    /// 会话轮数较多
+    LongSession { turns: usize },
+    /// 同一工具反复以相同错误失败
+    RepeatedFailure { tool: String, count: usize },
+}
+
+impl Advisory {
+    pub fn text(&self) -> String {
+        match self {
+            Advisory::ContextUsage { percent } => {
+                t!("cli.advisory.context", percent = percent)
+            }
+            Advisory::LongSession { turns } => t!("cli.advisory.long_session", turns = turns),
+            Advisory::RepeatedFailure { tool, count } => {
+                t!("cli.advisory.repeated_failure", tool = tool, count = count)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct AdvisoryState {
+    /// 本会话的轮数
+    turns: usize,
+    /// 已提示过的上下文阈值个数
+    context_fired: usize,
+    long_session_fired: bool,
+    /// 每个工具最近一次失败的错误及连续次数
+    failures: HashMap<String, (String, usize)>,
+    /// 已提示过的（工具，错误）
+    failures_fired: HashSet<(String, String)>,
+    /// 本轮内触发、等待本轮结束时显示的提示
+    pending: Vec<Advisory>,
+}
+
+/// 跨轮次追踪会话质量指标
+#[derive(Debug, Clone)]
+pub struct AdvisoryTracker {
+    state: Arc<Mutex<AdvisoryState>>,
+    enabled: bool,
+}
+
+impl AdvisoryTracker {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(AdvisoryState::default())),
+            enabled,
+        }
+    }
+
+    /// 按 `[behavior] advisories` 创建
+    pub fn from_config() -> Self {
+        let behavior: BehaviorConfig = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.behavior)
+            .unwrap_or_default();
+        Self::new(behavior.advisories)
+    }
+
+    /// 记录工具结果：同一工具以相同错误连续失败时提示，成功后重新计数
+    pub fn record_tool_result(&self, tool: &str, result: &str) {
+        if !self.enabled {
+            return;
+        }
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let Some(error) = tool_error(result) else {
+            state.failures.remove(tool);
+            return;
+        };
+        let count = match state.failures.get_mut(tool) {
+            Some((last, count)) if *last == error => {
+                *count += 1;
+                *count
+            }
+            _ => {
+                state.failures.insert(tool.to_string(), (error.clone(), 1));
+                1
+            }
+        };
+        if count >= REPEATED_FAILURES && state.failures_fired.insert((tool.to_string(), error)) {
+            state.pending.push(Advisory::RepeatedFailure {
+                tool: tool.to_string(),
+                count,
+            });
+        }
+    }
+
+    /// 本轮结束，返回新触发的提示（`context_percent` 为下一轮请求的上下文用量）
+    pub fn end_turn(&self, context_percent: usize) -> Vec<Advisory> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        state.turns += 1;
+        let mut advisories = Vec::new();
+
+        // 一次越过多个阈值时只提示最高的一个
+        let crossed = CONTEXT_THRESHOLDS
+            .iter()
+            .filter(|threshold| context_percent >= **threshold)
+            .count();
+        if crossed > state.context_fired {
+            state.context_fired = crossed;
+            advisories.push(Advisory::ContextUsage {
+                percent: context_percent,
+            });
+        }
+
+        if state.turns >= LONG_SESSION_TURNS && !state.long_session_fired {
+            state.long_session_fired = true;
+            advisories.push(Advisory::LongSession { turns: state.turns });
+        }
+
+        advisories.append(&mut state.pending);
+        advisories
+    }
+
+    /// 开始新会话（`/clear`、`/load`），所有阈值重新计算
+    pub fn reset(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = AdvisoryState::default();
+        }
+    }
+}
+
+/// 工具出错时返回错误信息的第一行
+fn tool_error(result: &str) -> Option<String> {
+    let (_, error) = result.rsplit_once(TOOL_ERROR)?;
+    Some(error.lines().next().unwrap_or("").trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOT_FOUND: &str = "ToolCallError: ToolCallError: old_string not found in src/main.rs";
+
+    #[test]
+    fn test_context_thresholds_fire_once() {
+        let tracker = AdvisoryTracker::new(true);
+        assert!(tracker.end_turn(40).is_empty());
+        assert_eq!(
+            tracker.end_turn(72),
+            vec![Advisory::ContextUsage { percent: 72 }]
+        );
+        assert!(tracker.end_turn(75).is_empty());
+        // 回退后再次越过同一阈值不重复提示
+        assert!(tracker.end_turn(50).is_empty());
+        assert!(tracker.end_turn(80).is_empty());
+        // 一次越过 85% 和 95% 只提示一次
+        assert_eq!(
+            tracker.end_turn(96),
+            vec![Advisory::ContextUsage { percent: 96 }]
+        );
+        assert!(tracker.end_turn(99).is_empty());
+
+        tracker.reset();
+        assert_eq!(
+            tracker.end_turn(90),
+            vec![Advisory::ContextUsage { percent: 90 }]
+        );
+    }
+
+    #[test]
+    fn test_long_session_fires_once() {
+        let tracker = AdvisoryTracker::new(true);
+        for _ in 1..LONG_SESSION_TURNS {
+            assert!(tracker.end_turn(0).is_empty());
+        }
+        assert_eq!(
+            tracker.end_turn(0),
+            vec![Advisory::LongSession {
+                turns: LONG_SESSION_TURNS
+            }]
+        );
+        assert!(tracker.end_turn(0).is_empty());
+    }
+
+    #[test]
+    fn test_repeated_failures() {
+        let tracker = AdvisoryTracker::new(true);
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        tracker.record_tool_result("read_file", "{\"content\":\"fn main() {}\"}");
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        assert!(tracker.end_turn(0).is_empty());
+
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        assert_eq!(
+            tracker.end_turn(0),
+            vec![Advisory::RepeatedFailure {
+                tool: "edit_file".to_string(),
+                count: 3
+            }]
+        );
+
+        // 同一错误不再重复提示；成功后重新计数
+        tracker.record_tool_result("edit_file", "{\"success\":true}");
+        for _ in 0..REPEATED_FAILURES {
+            tracker.record_tool_result("edit_file", NOT_FOUND);
+        }
+        assert!(tracker.end_turn(0).is_empty());
+
+        // 不同的错误重新计数
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        tracker.record_tool_result("edit_file", "ToolCallError: Permission denied: /etc");
+        tracker.record_tool_result("edit_file", NOT_FOUND);
+        assert!(tracker.end_turn(0).is_empty());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let tracker = AdvisoryTracker::new(false);
+        for _ in 0..REPEATED_FAILURES {
+            tracker.record_tool_result("edit_file", NOT_FOUND);
+        }
+        assert!(tracker.end_turn(99).is_empty());
+    }
+}
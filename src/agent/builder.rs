@@ -2,24 +2,48 @@
 //!
 //! 根据不同的 Agent 类型创建具有相应工具权限的 Agent 实例。
 
-use crate::agent::{HitlIntegration, MaybeHitlTool};
+use crate::agent::context_usage::RequestPreview;
+use crate::agent::loop_guard;
+use crate::agent::replay;
+use crate::agent::superseded_reads;
+use crate::agent::stream_event::TurnStream;
+use crate::agent::structured_output::{openai_text_format, StructuredOutputTool};
+use crate::agent::tool_results::{self, ResultBudget, TOOL_RESULTS_DIR};
+use crate::agent::turn_summary::MAX_OUTPUT_TOKENS;
+use crate::agent::{HitlIntegration, MaybeHitlTool, PromptBuilder, PromptMode, PromptPart};
 use crate::agent::types::AgentType;
 use crate::config::secret::Secret;
+use crate::config::OutputConfig;
+use crate::mcp::McpManager;
+use crate::tools::edit_batch;
+use crate::tools::network;
+use crate::tools::tool_args::LenientArgs;
+use crate::tools::web_cache::{self, WebCache};
 use crate::tools::{
     WrappedAskUserQuestionTool, WrappedCreateDirectoryTool, WrappedDeleteFileTool,
     WrappedEditFileTool, WrappedGlobTool, WrappedGrepSearchTool, WrappedReadFileTool,
     WrappedScanCodebaseTool, WrappedWriteFileTool, WrappedShellExecuteTool,
     WrappedSearchReplaceTool, WrappedEnterPlanModeTool, WrappedExitPlanModeTool,
     WrappedTaskCreateTool, WrappedTaskUpdateTool, WrappedTaskListTool, WrappedTaskGetTool,
+    WrappedTaskStopTool,
+    WrappedRunTestsTool, WrappedOutlineTool, WrappedDiagnosticsTool, WrappedDepsTool,
+    WrappedHttpRequestTool, WrappedMemoryTool, WrappedApplyPatchTool, WrappedDiffTool,
+    PlanModeManager, ToolSettings,
 };
 use anyhow::Result;
-use rig::agent::Agent;
+use rig::agent::{Agent, StreamingPromptHook};
 use rig::client::CompletionClient;
+use rig::completion::{Message, ToolDefinition};
 use rig::providers::{anthropic, openai};
+use rig::streaming::StreamingPrompt;
+use rig::tool::ToolDyn;
 use std::sync::Arc;
 
 use crate::agent::workflow::observation::ObservationCollector;
 
+/// 一轮对话中最多连续执行多少次工具调用
+pub const MAX_TOOL_ROUNDS: usize = 20;
+
 /// Agent 构建器
 ///
 /// 根据指定的 Agent 类型创建相应的 Agent 实例,配置对应的系统提示词和工具权限。
@@ -38,6 +62,27 @@ pub struct AgentBuilder {
 
     /// 观察数据收集器 (可选)
     observation_collector: Option<ObservationCollector>,
+
+    /// MCP 服务器 (可选)
+    mcp: Option<Arc<McpManager>>,
+
+    /// 对话模式（决定 Main Agent 的系统提示词）
+    mode: PromptMode,
+
+    /// 回复风格（`None` 时读取 `[output]` 配置）
+    output: Option<OutputConfig>,
+
+    /// 追加到 Main Agent 系统提示词末尾的部分（会话模板）
+    prompt_addition: Option<PromptPart>,
+
+    /// 工具默认设置（`[tools]` 配置）
+    tool_settings: ToolSettings,
+
+    /// 本会话的计划模式状态（只有 Main Agent 注册计划模式工具，子 Agent 用不到）
+    plan_mode: PlanModeManager,
+
+    /// 最终回答须符合的 JSON Schema（`--output-schema`，只作用于 Main Agent）
+    output_schema: Option<serde_json::Value>,
 }
 
 impl AgentBuilder {
@@ -49,9 +94,64 @@ impl AgentBuilder {
             model,
             hitl: None,
             observation_collector: None,
+            mcp: None,
+            mode: PromptMode::default(),
+            output: None,
+            prompt_addition: None,
+            tool_settings: ToolSettings::default(),
+            plan_mode: PlanModeManager::new(),
+            output_schema: None,
         }
     }
 
+    /// 设置对话模式
+    pub fn with_mode(mut self, mode: PromptMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 切换对话模式（之后构建的 Main Agent 使用新模式的提示词）
+    pub fn set_mode(&mut self, mode: PromptMode) {
+        self.mode = mode;
+    }
+
+    /// 切换回复风格（之后构建的 Main Agent 使用新的风格说明）
+    pub fn set_output(&mut self, output: OutputConfig) {
+        self.output = Some(output);
+    }
+
+    /// 设置或清除追加到系统提示词末尾的部分（之后构建的 Main Agent 生效）
+    pub fn set_prompt_addition(&mut self, addition: Option<PromptPart>) {
+        self.prompt_addition = addition;
+    }
+
+    /// 设置工具默认设置（同时按 `result_max_tokens` 设置工具结果的预算，
+    /// 按 `max_identical_calls` 设置重复调用的熔断，按 `network` 设置工具的网络访问策略，
+    /// 按 `web_cache_*` 设置联网工具的缓存）
+    pub fn with_tool_settings(mut self, settings: ToolSettings) -> Self {
+        // 摘要中给出绝对路径，设置了 `[tools.root]` 时 read_file 也能读到
+        let dir = std::env::current_dir()
+            .map(|cwd| cwd.join(TOOL_RESULTS_DIR))
+            .unwrap_or_else(|_| TOOL_RESULTS_DIR.into());
+        tool_results::configure(ResultBudget::new(settings.result_max_tokens).with_dir(dir));
+        loop_guard::configure(settings.max_identical_calls);
+        network::configure(settings.network.clone());
+        web_cache::configure(WebCache::new(
+            settings.web_cache_ttl,
+            settings.web_cache_max_bytes,
+        ));
+        superseded_reads::configure(&settings);
+        self.tool_settings = settings;
+        self
+    }
+
+    /// 共享已有的计划模式状态（默认每个构建器一份新的状态）
+    #[allow(dead_code)]
+    pub fn with_plan_mode(mut self, plan_mode: PlanModeManager) -> Self {
+        self.plan_mode = plan_mode;
+        self
+    }
+
     /// 设置 HITL 集成
     pub fn with_hitl(mut self, hitl: Arc<HitlIntegration>) -> Self {
         self.hitl = Some(hitl);
@@ -64,15 +164,132 @@ impl AgentBuilder {
         self
     }
 
+    /// 设置 MCP 服务器（其工具只注册给 Main Agent）
+    pub fn with_mcp(mut self, mcp: Arc<McpManager>) -> Self {
+        self.mcp = Some(mcp);
+        self
+    }
+
+    /// 要求最终回答符合 `schema`：Anthropic 注册提交回答的工具，OpenAI 兼容的服务商约束输出格式
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// 已连接的 MCP 服务器提供的工具，以及 Anthropic 下提交结构化回答的工具
+    fn dynamic_tools(&self) -> Vec<Box<dyn ToolDyn>> {
+        let mut tools: Vec<Box<dyn ToolDyn>> = self
+            .mcp
+            .as_ref()
+            .map(|mcp| {
+                mcp.agent_tools(self.hitl.clone())
+                    .into_iter()
+                    .map(|tool| Box::new(tool) as Box<dyn ToolDyn>)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let (Some(schema), true) = (&self.output_schema, self.is_anthropic()) {
+            tools.push(Box::new(StructuredOutputTool::new(schema.clone())));
+        }
+        tools
+    }
+
+    /// 联网的工具，网络访问策略为 `none` 时不注册
+    fn network_tools(&self, http_request: WrappedHttpRequestTool) -> Vec<Box<dyn ToolDyn>> {
+        if !self.tool_settings.network.tools_enabled() {
+            return Vec::new();
+        }
+        vec![Box::new(MaybeHitlTool::new(
+            http_request,
+            self.hitl.clone(),
+        ))]
+    }
+
+    fn is_anthropic(&self) -> bool {
+        self.base_url.contains("/anthropic") || self.base_url.contains("anthropic.com")
+    }
+
+    /// Main Agent 的系统提示词
+    fn main_prompt(&self) -> PromptBuilder {
+        let prompt = PromptBuilder::new(self.mode)
+            .with_roots(self.tool_settings.roots.list())
+            .with_addition(self.prompt_addition.clone());
+        match self.output {
+            Some(output) => prompt.with_output(output),
+            None => prompt,
+        }
+    }
+
+    /// Main Agent 发送给模型的工具定义
+    pub async fn main_tool_definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = Vec::new();
+        for tool in self.main_tools() {
+            definitions.push(tool.definition(String::new()).await);
+        }
+        definitions
+    }
+
+    /// Main Agent 的全部工具（回放录制时按名称重新执行工具调用）
+    ///
+    /// 与 `build_main` 注册的工具一一对应，修改其中一处时需要同步另一处。
+    pub fn main_tools(&self) -> Vec<Box<dyn ToolDyn>> {
+        let tools = self.create_tools();
+        let mut main: Vec<Box<dyn ToolDyn>> = vec![
+            Box::new(MaybeHitlTool::new(tools.read_file, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.write_file, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.edit_file, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.apply_patch, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.delete_file, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.shell_execute, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.run_tests, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.diagnostics, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.memory, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.scan_codebase, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.outline, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.diff, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.deps, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.make_dir, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.grep_find, self.hitl.clone())),
+            Box::new(MaybeHitlTool::new(tools.glob, self.hitl.clone())),
+        ];
+        if !self.is_anthropic() {
+            main.push(Box::new(MaybeHitlTool::new(
+                tools.search_replace,
+                self.hitl.clone(),
+            )));
+        }
+        main.push(Box::new(LenientArgs::new(tools.enter_plan_mode)));
+        main.push(Box::new(LenientArgs::new(tools.exit_plan_mode)));
+        main.push(Box::new(LenientArgs::new(tools.ask_user_question)));
+        main.push(Box::new(LenientArgs::new(tools.task_create)));
+        main.push(Box::new(LenientArgs::new(tools.task_update)));
+        main.push(Box::new(LenientArgs::new(tools.task_list)));
+        main.push(Box::new(LenientArgs::new(tools.task_get)));
+        main.push(Box::new(LenientArgs::new(tools.task_stop)));
+        main.extend(self.network_tools(tools.http_request));
+        main.extend(self.dynamic_tools());
+        main
+    }
+
+    /// 预览 Main Agent 下一轮请求包含的内容（不创建客户端，也不发送请求）
+    pub async fn preview_main(&self, history: &[Message]) -> RequestPreview {
+        RequestPreview {
+            system: self.main_prompt().parts(),
+            tools: self.main_tool_definitions().await,
+            messages: history.to_vec(),
+        }
+    }
+
     /// 构建 Main Agent(拥有所有工具)
     pub fn build_main(&self) -> Result<AgentEnum> {
         let tools = self.create_tools();
+        let preamble = self.main_prompt().build();
         let model_name = self
             .model
             .clone()
             .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
 
-        if self.base_url.contains("/anthropic") || self.base_url.contains("anthropic.com") {
+        if self.is_anthropic() {
             let client = anthropic::Client::builder()
                 .api_key(self.auth_token.expose_secret())
                 .base_url(&self.base_url)
@@ -80,61 +297,34 @@ impl AgentBuilder {
 
             let agent = client
                 .agent(&model_name)
-                .preamble(r#"Your name is Oxide. You are a helpful AI code assistant with comprehensive file system and command execution access. You can read, write, edit (with patches or search/replace), and delete files, execute bash commands, scan codebase structures, search text in the codebase and create directories. Use edit_file for precise small changes with diffs. Use search_replace for block replacements where you match content rather than lines (robust to line number shifts). search_replace is preferred for modifying functions or blocks of code. Please provide clear and concise responses and be careful when modifying files or executing commands.
-
-【Tool Usage Strategy】
-- ✅ WHEN to use tools: When users explicitly request file operations, code search, command execution, or system interactions
-- ❌ WHEN NOT to use tools: For general conversation, capability questions, or questions that can be answered directly from your knowledge
-- 🤖 Answer directly: Questions about your capabilities, features, technical concepts, or general programming questions should be answered directly without calling tools
-- 📋 Read first: ALWAYS read files before attempting to edit them to ensure you have the current content
-
-【Plan Mode】
-Use enter_plan_mode proactively when you're about to start a non-trivial implementation task:
-- New feature implementation requiring architectural decisions
-- Multiple valid approaches exist for the task
-- Code modifications affecting existing behavior
-- Multi-file changes (more than 2-3 files)
-- Unclear requirements needing exploration
-
-In plan mode:
-1. Explore the codebase using read, grep, glob tools
-2. Design your implementation approach
-3. Use exit_plan_mode to present your plan and request user approval
-4. Only proceed with implementation after user approves
-
-Skip plan mode for simple tasks like typo fixes, single-line changes, or tasks with very specific instructions.
-
-【User Interaction】
-Use ask_user_question when you need to:
-- Gather user preferences or requirements during execution
-- Clarify ambiguous instructions
-- Get decisions on implementation choices
-- Offer choices about what direction to take
-Users can always select "Other" to provide custom input. Use multiSelect: true to allow multiple answers.
-
-【Task Management】
-Use task management tools (task_create, task_update, task_list, task_get) to track progress on complex tasks:
-- Create tasks for multi-step work to show progress to the user
-- Update task status as you work (pending → in_progress → completed)
-- Use task dependencies (blocks/blocked_by) to manage task ordering
-- Mark tasks as completed when done, or deleted if no longer needed"#)
-                .max_tokens(4096)
+                .preamble(&preamble)
+                .max_tokens(MAX_OUTPUT_TOKENS)
                 .tool(MaybeHitlTool::new(tools.read_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.write_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.edit_file, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.apply_patch, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.delete_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.shell_execute, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.run_tests, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.diagnostics, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.memory, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.scan_codebase, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.outline, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.diff, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.deps, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.make_dir, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.grep_find, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.glob, self.hitl.clone()))
-                .tool(tools.enter_plan_mode)
-                .tool(tools.exit_plan_mode)
-                .tool(tools.ask_user_question)
-                .tool(tools.task_create)
-                .tool(tools.task_update)
-                .tool(tools.task_list)
-                .tool(tools.task_get)
+                .tool(LenientArgs::new(tools.enter_plan_mode))
+                .tool(LenientArgs::new(tools.exit_plan_mode))
+                .tool(LenientArgs::new(tools.ask_user_question))
+                .tool(LenientArgs::new(tools.task_create))
+                .tool(LenientArgs::new(tools.task_update))
+                .tool(LenientArgs::new(tools.task_list))
+                .tool(LenientArgs::new(tools.task_get))
+                .tool(LenientArgs::new(tools.task_stop))
+                .tools(self.network_tools(tools.http_request))
+                .tools(self.dynamic_tools())
                 .build();
 
             Ok(AgentEnum::Anthropic(agent))
@@ -144,64 +334,41 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
                 .base_url(&self.base_url)
                 .build()?;
 
-            let agent = client
+            let mut agent = client
                 .agent(&model_name)
-                .preamble(r#"Your name is Oxide. You are a helpful AI code assistant with comprehensive file system and command execution access. You can read, write, edit (with patches or search/replace), and delete files, execute bash commands, scan codebase structures, search text in the codebase and create directories. Use edit_file for precise small changes with diffs. Use search_replace for block replacements where you match content rather than lines (robust to line number shifts). search_replace is preferred for modifying functions or blocks of code. Please provide clear and concise responses and be careful when modifying files or executing commands.
-
-【Tool Usage Strategy】
-- ✅ WHEN to use tools: When users explicitly request file operations, code search, command execution, or system interactions
-- ❌ WHEN NOT to use tools: For general conversation, capability questions, or questions that can be answered directly from your knowledge
-- 🤖 Answer directly: Questions about your capabilities, features, technical concepts, or general programming questions should be answered directly without calling tools
-- 📋 Read first: ALWAYS read files before attempting to edit them to ensure you have the current content
-
-【Plan Mode】
-Use enter_plan_mode proactively when you're about to start a non-trivial implementation task:
-- New feature implementation requiring architectural decisions
-- Multiple valid approaches exist for the task
-- Code modifications affecting existing behavior
-- Multi-file changes (more than 2-3 files)
-- Unclear requirements needing exploration
-
-In plan mode:
-1. Explore the codebase using read, grep, glob tools
-2. Design your implementation approach
-3. Use exit_plan_mode to present your plan and request user approval
-4. Only proceed with implementation after user approves
-
-Skip plan mode for simple tasks like typo fixes, single-line changes, or tasks with very specific instructions.
-
-【User Interaction】
-Use ask_user_question when you need to:
-- Gather user preferences or requirements during execution
-- Clarify ambiguous instructions
-- Get decisions on implementation choices
-- Offer choices about what direction to take
-Users can always select "Other" to provide custom input. Use multiSelect: true to allow multiple answers.
-
-【Task Management】
-Use task management tools (task_create, task_update, task_list, task_get) to track progress on complex tasks:
-- Create tasks for multi-step work to show progress to the user
-- Update task status as you work (pending → in_progress → completed)
-- Use task dependencies (blocks/blocked_by) to manage task ordering
-- Mark tasks as completed when done, or deleted if no longer needed"#)
-                .max_tokens(4096)
+                .preamble(&preamble)
+                .max_tokens(MAX_OUTPUT_TOKENS);
+            if let Some(schema) = &self.output_schema {
+                agent = agent.additional_params(openai_text_format(schema));
+            }
+            let agent = agent
                 .tool(MaybeHitlTool::new(tools.read_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.write_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.edit_file, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.apply_patch, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.delete_file, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.shell_execute, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.run_tests, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.diagnostics, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.memory, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.scan_codebase, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.outline, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.diff, self.hitl.clone()))
+                .tool(MaybeHitlTool::new(tools.deps, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.make_dir, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.grep_find, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.glob, self.hitl.clone()))
                 .tool(MaybeHitlTool::new(tools.search_replace, self.hitl.clone()))
-                .tool(tools.enter_plan_mode)
-                .tool(tools.exit_plan_mode)
-                .tool(tools.ask_user_question)
-                .tool(tools.task_create)
-                .tool(tools.task_update)
-                .tool(tools.task_list)
-                .tool(tools.task_get)
+                .tool(LenientArgs::new(tools.enter_plan_mode))
+                .tool(LenientArgs::new(tools.exit_plan_mode))
+                .tool(LenientArgs::new(tools.ask_user_question))
+                .tool(LenientArgs::new(tools.task_create))
+                .tool(LenientArgs::new(tools.task_update))
+                .tool(LenientArgs::new(tools.task_list))
+                .tool(LenientArgs::new(tools.task_get))
+                .tool(LenientArgs::new(tools.task_stop))
+                .tools(self.network_tools(tools.http_request))
+                .tools(self.dynamic_tools())
                 .build();
 
             Ok(AgentEnum::OpenAI(agent))
@@ -225,12 +392,13 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
 
             let agent = client
                 .agent(&model_name)
-                .preamble("You are an Explore Agent specialized in codebase exploration and analysis. Your capabilities are limited to read-only operations: reading files, searching text, and scanning the codebase structure. When exploring a codebase: 1. Start by getting an overview of the project structure 2. Identify key files and directories 3. Search for relevant code patterns 4. Provide concise summaries of your findings. Use Glob for file pattern matching and Grep for content searching.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .preamble("You are an Explore Agent specialized in codebase exploration and analysis. Your capabilities are limited to read-only operations: reading files, searching text, and scanning the codebase structure. When exploring a codebase: 1. Start by getting an overview of the project structure 2. Identify key files and directories 3. Search for relevant code patterns 4. Provide concise summaries of your findings. Use Glob for file pattern matching and Grep for content searching. Use outline to see a file's structure before reading it in full.")
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::Anthropic(agent))
@@ -242,12 +410,13 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
 
             let agent = client
                 .agent(&model_name)
-                .preamble("You are an Explore Agent specialized in codebase exploration and analysis. Your capabilities are limited to read-only operations: reading files, searching text, and scanning the codebase structure. When exploring a codebase: 1. Start by getting an overview of the project structure 2. Identify key files and directories 3. Search for relevant code patterns 4. Provide concise summaries of your findings. Use Glob for file pattern matching and Grep for content searching.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .preamble("You are an Explore Agent specialized in codebase exploration and analysis. Your capabilities are limited to read-only operations: reading files, searching text, and scanning the codebase structure. When exploring a codebase: 1. Start by getting an overview of the project structure 2. Identify key files and directories 3. Search for relevant code patterns 4. Provide concise summaries of your findings. Use Glob for file pattern matching and Grep for content searching. Use outline to see a file's structure before reading it in full.")
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::OpenAI(agent))
@@ -272,11 +441,12 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Plan Agent specialized in software architecture and implementation planning. Your role is to: 1. Analyze requirements and explore the codebase 2. Design implementation strategies 3. Break down complex tasks into manageable steps 4. Identify potential issues and trade-offs 5. Create clear, actionable plans. When planning, be thorough but focus on practical, implementable solutions.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::Anthropic(agent))
@@ -289,11 +459,12 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Plan Agent specialized in software architecture and implementation planning. Your role is to: 1. Analyze requirements and explore the codebase 2. Design implementation strategies 3. Break down complex tasks into manageable steps 4. Identify potential issues and trade-offs 5. Create clear, actionable plans. When planning, be thorough but focus on practical, implementable solutions.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::OpenAI(agent))
@@ -318,11 +489,13 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Code Reviewer Agent specialized in code quality analysis and security review. Your responsibilities include: 1. Reviewing code for bugs and logic errors 2. Identifying security vulnerabilities (OWASP Top 10, injection attacks, etc.) 3. Checking for code quality issues and maintainability problems 4. Verifying adherence to project conventions 5. Suggesting improvements and best practices. Focus on high-priority issues that truly matter. Be constructive and specific in your feedback.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.diff))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::Anthropic(agent))
@@ -335,11 +508,13 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Code Reviewer Agent specialized in code quality analysis and security review. Your responsibilities include: 1. Reviewing code for bugs and logic errors 2. Identifying security vulnerabilities (OWASP Top 10, injection attacks, etc.) 3. Checking for code quality issues and maintainability problems 4. Verifying adherence to project conventions 5. Suggesting improvements and best practices. Focus on high-priority issues that truly matter. Be constructive and specific in your feedback.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.grep_find)
-                .tool(tools.scan_codebase)
-                .tool(tools.glob)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.scan_codebase))
+                .tool(LenientArgs::new(tools.outline))
+                .tool(LenientArgs::new(tools.diff))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::OpenAI(agent))
@@ -364,13 +539,13 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Frontend Developer Agent specialized in building modern, production-grade user interfaces. Your expertise includes: - React, Next.js, Vue, Svelte, and other modern frameworks - Tailwind CSS, shadcn/ui, and component libraries - Responsive design and accessibility - Performance optimization - Creating polished, maintainable code that avoids generic AI aesthetics. When building UI components, prioritize user experience, maintainability, and web standards compliance. Use search_replace for safe block replacements when strict line numbers are unknown.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.write_file)
-                .tool(tools.edit_file)
-                .tool(tools.shell_execute)
-                .tool(tools.grep_find)
-                .tool(tools.glob)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.write_file))
+                .tool(LenientArgs::new(tools.edit_file))
+                .tool(LenientArgs::new(tools.shell_execute))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.glob))
                 .build();
 
             Ok(AgentEnum::Anthropic(agent))
@@ -383,14 +558,14 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
             let agent = client
                 .agent(&model_name)
                 .preamble("You are a Frontend Developer Agent specialized in building modern, production-grade user interfaces. Your expertise includes: - React, Next.js, Vue, Svelte, and other modern frameworks - Tailwind CSS, shadcn/ui, and component libraries - Responsive design and accessibility - Performance optimization - Creating polished, maintainable code that avoids generic AI aesthetics. When building UI components, prioritize user experience, maintainability, and web standards compliance. Use search_replace for safe block replacements when strict line numbers are unknown.")
-                .max_tokens(4096)
-                .tool(tools.read_file)
-                .tool(tools.write_file)
-                .tool(tools.edit_file)
-                .tool(tools.shell_execute)
-                .tool(tools.grep_find)
-                .tool(tools.glob)
-                .tool(tools.search_replace)
+                .max_tokens(MAX_OUTPUT_TOKENS)
+                .tool(LenientArgs::new(tools.read_file))
+                .tool(LenientArgs::new(tools.write_file))
+                .tool(LenientArgs::new(tools.edit_file))
+                .tool(LenientArgs::new(tools.shell_execute))
+                .tool(LenientArgs::new(tools.grep_find))
+                .tool(LenientArgs::new(tools.glob))
+                .tool(LenientArgs::new(tools.search_replace))
                 .build();
 
             Ok(AgentEnum::OpenAI(agent))
@@ -412,25 +587,35 @@ Use task management tools (task_create, task_update, task_list, task_get) to tra
 
     /// 创建所有可用的工具
     fn create_tools(&self) -> AllTools {
+        let settings = &self.tool_settings;
         let tools = AllTools {
-            read_file: WrappedReadFileTool::new(),
-            write_file: WrappedWriteFileTool::new(),
-            edit_file: WrappedEditFileTool::new(),
-            delete_file: WrappedDeleteFileTool::new(),
-            shell_execute: WrappedShellExecuteTool::new(),
+            read_file: WrappedReadFileTool::new(settings),
+            write_file: WrappedWriteFileTool::new(settings),
+            edit_file: WrappedEditFileTool::new(settings),
+            apply_patch: WrappedApplyPatchTool::new(settings),
+            delete_file: WrappedDeleteFileTool::new(settings),
+            shell_execute: WrappedShellExecuteTool::new(settings),
+            run_tests: WrappedRunTestsTool::new(settings),
+            diagnostics: WrappedDiagnosticsTool::new(settings),
+            http_request: WrappedHttpRequestTool::new(),
+            memory: WrappedMemoryTool::new(),
             scan_codebase: WrappedScanCodebaseTool::new(),
-            make_dir: WrappedCreateDirectoryTool::new(),
-            grep_find: WrappedGrepSearchTool::new(),
-            glob: WrappedGlobTool::new(),
-            search_replace: WrappedSearchReplaceTool::new(),
-            enter_plan_mode: WrappedEnterPlanModeTool::new(),
-            exit_plan_mode: WrappedExitPlanModeTool::new(),
+            outline: WrappedOutlineTool::new(settings),
+            diff: WrappedDiffTool::new(settings),
+            deps: WrappedDepsTool::new(),
+            make_dir: WrappedCreateDirectoryTool::new(settings),
+            grep_find: WrappedGrepSearchTool::new(settings),
+            glob: WrappedGlobTool::new(settings),
+            search_replace: WrappedSearchReplaceTool::new(settings),
+            enter_plan_mode: WrappedEnterPlanModeTool::new(self.plan_mode.clone()),
+            exit_plan_mode: WrappedExitPlanModeTool::new(self.plan_mode.clone()),
             ask_user_question: WrappedAskUserQuestionTool::new(),
             // 任务管理工具
             task_create: WrappedTaskCreateTool::new(),
             task_update: WrappedTaskUpdateTool::new(),
             task_list: WrappedTaskListTool::new(),
             task_get: WrappedTaskGetTool::new(),
+            task_stop: WrappedTaskStopTool::new(),
         };
 
         // 如果启用了 HITL，则包装工具
@@ -450,9 +635,17 @@ struct AllTools {
     read_file: WrappedReadFileTool,
     write_file: WrappedWriteFileTool,
     edit_file: WrappedEditFileTool,
+    apply_patch: WrappedApplyPatchTool,
     delete_file: WrappedDeleteFileTool,
     shell_execute: WrappedShellExecuteTool,
+    run_tests: WrappedRunTestsTool,
+    diagnostics: WrappedDiagnosticsTool,
+    http_request: WrappedHttpRequestTool,
+    memory: WrappedMemoryTool,
     scan_codebase: WrappedScanCodebaseTool,
+    outline: WrappedOutlineTool,
+    diff: WrappedDiffTool,
+    deps: WrappedDepsTool,
     make_dir: WrappedCreateDirectoryTool,
     grep_find: WrappedGrepSearchTool,
     glob: WrappedGlobTool,
@@ -465,6 +658,7 @@ struct AllTools {
     task_update: WrappedTaskUpdateTool,
     task_list: WrappedTaskListTool,
     task_get: WrappedTaskGetTool,
+    task_stop: WrappedTaskStopTool,
 }
 
 /// Agent 枚举 - 支持多种客户端
@@ -478,6 +672,62 @@ pub enum AgentEnum {
     OpenAI(Agent<openai::responses_api::ResponsesCompletionModel>),
 }
 
+impl AgentEnum {
+    /// 服务商名称（记录在会话元数据中）
+    pub fn provider(&self) -> &'static str {
+        match self {
+            AgentEnum::Anthropic(_) => "anthropic",
+            AgentEnum::OpenAI(_) => "openai",
+        }
+    }
+
+    /// 发送一轮对话，返回与服务商无关的事件流
+    ///
+    /// `prompt` 是本轮实际发送的内容，`history` 不包含它（见 `ContextManager::prompt_history`）。
+    /// `oxide serve` 和 `OxideSession`（REPL、`-p` 与嵌入场景）都通过这里驱动 Agent 循环。
+    /// 录制（`--record`）和回放（`--replay`）也在这里接入，见 `replay`。
+    pub async fn stream_turn<H>(&self, prompt: &str, history: Vec<Message>, hook: H) -> TurnStream
+    where
+        H: StreamingPromptHook<anthropic::completion::CompletionModel>
+            + StreamingPromptHook<openai::responses_api::ResponsesCompletionModel>
+            + 'static,
+    {
+        tool_results::begin_turn();
+        edit_batch::begin_turn();
+        let recorder = replay::recorder();
+        if let Some(provider) = replay::provider() {
+            return provider.stream_turn(prompt, &history, recorder);
+        }
+        if let Some(recorder) = &recorder {
+            recorder.request(prompt, &history);
+        }
+        let stream = match self {
+            AgentEnum::Anthropic(agent) => {
+                let stream = agent
+                    .stream_prompt(prompt)
+                    .with_hook(hook)
+                    .multi_turn(MAX_TOOL_ROUNDS)
+                    .with_history(history)
+                    .await;
+                TurnStream::new(stream)
+            }
+            AgentEnum::OpenAI(agent) => {
+                let stream = agent
+                    .stream_prompt(prompt)
+                    .with_hook(hook)
+                    .multi_turn(MAX_TOOL_ROUNDS)
+                    .with_history(history)
+                    .await;
+                TurnStream::new(stream)
+            }
+        };
+        match recorder {
+            Some(recorder) => stream.with_recorder(recorder),
+            None => stream,
+        }
+    }
+}
+
 // 手动实现 Debug，避免暴露内部 Agent 细节
 impl std::fmt::Debug for AgentEnum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -531,5 +781,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_plan_mode_is_scoped_to_builder() {
+        let first = AgentBuilder::new(
+            "https://api.anthropic.com".to_string(),
+            Secret::new("test-key".to_string()),
+            None,
+        );
+        let second = AgentBuilder::new(
+            "https://api.anthropic.com".to_string(),
+            Secret::new("test-key".to_string()),
+            None,
+        );
+
+        first.plan_mode.enter().unwrap();
+        assert!(first.plan_mode.is_active());
+        assert!(!second.plan_mode.is_active());
+
+        // 重建 Agent 时工具拿到的是同一份状态
+        let shared = second.with_plan_mode(first.plan_mode.clone());
+        assert!(shared.plan_mode.is_active());
+    }
+
+    #[test]
+    fn test_offline_tools_are_excluded() {
+        let mut builder = AgentBuilder::new(
+            "https://api.anthropic.com".to_string(),
+            Secret::new("test-key".to_string()),
+            None,
+        );
+        let names = |builder: &AgentBuilder| -> Vec<String> {
+            builder
+                .main_tools()
+                .iter()
+                .map(|tool| tool.name())
+                .collect()
+        };
+        assert!(names(&builder).contains(&"http_request".to_string()));
+
+        // 直接设置字段，不改动其他测试共用的全局策略
+        builder.tool_settings.network = network::NetworkPolicy::offline();
+        let offline = names(&builder);
+        assert!(!offline.contains(&"http_request".to_string()));
+        assert!(offline.contains(&"deps".to_string()));
+    }
+
     // 注意: 实际的 build 测试需要有效的 API 凭据,这里我们只测试结构
 }
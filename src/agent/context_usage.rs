@@ -0,0 +1,425 @@
+//! 上下文占用分析（`/context`）
+//!
+//! 把下一轮请求拆成系统提示词的各个部分、工具定义和历史消息（按用户文本、助手文本、
+//! 工具调用、工具结果分组）分别计算 token，用于找出占用上下文窗口的主要来源。
+//! 整个请求单独计算一次，与各部分之和的差值记为消息格式开销，因此各行相加等于总数。
+
+use rig::completion::message::{AssistantContent, UserContent};
+use rig::completion::{Message, ToolDefinition};
+use serde::Serialize;
+
+use crate::agent::PromptPart;
+use crate::token_counter::{count_messages_tokens, count_tokens};
+
+/// 工具结果占比达到该值时建议清理
+const TOOL_RESULTS_SHARE: f64 = 25.0;
+/// 对话历史占比达到该值时建议清理
+const HISTORY_SHARE: f64 = 50.0;
+/// 工具定义占比达到该值时建议停用不需要的 MCP 服务器
+const TOOL_DEFINITIONS_SHARE: f64 = 30.0;
+/// 上下文窗口使用率达到该值时提醒
+const WINDOW_USAGE: f64 = 80.0;
+
+/// 下一轮请求的内容（由 `AgentBuilder::preview_main` 组装，不发送）
+#[derive(Debug, Clone)]
+pub struct RequestPreview {
+    pub system: Vec<PromptPart>,
+    pub tools: Vec<ToolDefinition>,
+    pub messages: Vec<Message>,
+}
+
+/// 请求的组成部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    SystemPrompt,
+    ToolDefinitions,
+    UserText,
+    AssistantText,
+    ToolCalls,
+    ToolResults,
+    /// 图片、文档等非文本的用户内容
+    Attachments,
+    /// 角色标记、分隔符等消息格式开销
+    Formatting,
+}
+
+impl Component {
+    /// 是否属于对话历史
+    fn is_history(self) -> bool {
+        matches!(
+            self,
+            Component::UserText
+                | Component::AssistantText
+                | Component::ToolCalls
+                | Component::ToolResults
+                | Component::Attachments
+        )
+    }
+}
+
+/// 分析结果中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub component: Component,
+    /// 系统提示词部分的名称
+    pub name: Option<String>,
+    /// 该行包含的条目数（工具定义数、消息内容块数）
+    pub items: usize,
+    pub tokens: usize,
+}
+
+/// 值得提示用户的情况
+#[derive(Debug, Clone, PartialEq)]
+pub enum Suggestion {
+    ToolResults { count: usize, percent: f64 },
+    History { percent: f64 },
+    ToolDefinitions { count: usize, percent: f64 },
+    NearlyFull { percent: f64 },
+}
+
+impl Suggestion {
+    pub fn text(&self) -> String {
+        match self {
+            Suggestion::ToolResults { count, percent } => t!(
+                "cli.context_usage.tool_results",
+                count = count,
+                percent = format!("{:.0}", percent)
+            ),
+            Suggestion::History { percent } => t!(
+                "cli.context_usage.history",
+                percent = format!("{:.0}", percent)
+            ),
+            Suggestion::ToolDefinitions { count, percent } => t!(
+                "cli.context_usage.tool_definitions",
+                count = count,
+                percent = format!("{:.0}", percent)
+            ),
+            Suggestion::NearlyFull { percent } => t!(
+                "cli.context_usage.nearly_full",
+                percent = format!("{:.0}", percent)
+            ),
+        }
+    }
+}
+
+/// 按组成部分统计的 token 占用
+#[derive(Debug, Clone)]
+pub struct ContextBreakdown {
+    /// 按 token 数从大到小排列
+    rows: Vec<Row>,
+    total: usize,
+}
+
+impl ContextBreakdown {
+    pub fn from_preview(preview: &RequestPreview) -> Self {
+        let mut rows: Vec<Row> = preview
+            .system
+            .iter()
+            .map(|part| Row {
+                component: Component::SystemPrompt,
+                name: Some(part.name.clone()),
+                items: 1,
+                tokens: count_tokens(&part.text),
+            })
+            .collect();
+
+        let tool_tokens: usize = preview
+            .tools
+            .iter()
+            .map(|tool| count_tokens(&to_json(tool)))
+            .sum();
+        add(
+            &mut rows,
+            Component::ToolDefinitions,
+            preview.tools.len(),
+            tool_tokens,
+        );
+
+        for message in &preview.messages {
+            for (component, text) in content_blocks(message) {
+                add(&mut rows, component, 1, count_tokens(&text));
+            }
+        }
+
+        let counted: usize = rows.iter().map(|row| row.tokens).sum();
+        let total = request_tokens(preview).max(counted);
+        add(&mut rows, Component::Formatting, 0, total - counted);
+
+        rows.retain(|row| row.tokens > 0 || row.component == Component::SystemPrompt);
+        rows.sort_by_key(|row| std::cmp::Reverse(row.tokens));
+        Self { rows, total }
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 占总数的百分比
+    pub fn percent(&self, tokens: usize) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            tokens as f64 * 100.0 / self.total as f64
+        }
+    }
+
+    fn sum(&self, filter: impl Fn(Component) -> bool) -> (usize, usize) {
+        self.rows
+            .iter()
+            .filter(|row| filter(row.component))
+            .fold((0, 0), |(items, tokens), row| {
+                (items + row.items, tokens + row.tokens)
+            })
+    }
+
+    /// 根据各部分占比和上下文窗口（token）给出建议
+    pub fn suggestions(&self, window: usize) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+        let (count, tokens) = self.sum(|component| component == Component::ToolResults);
+        let tool_results = self.percent(tokens);
+        if tool_results >= TOOL_RESULTS_SHARE {
+            suggestions.push(Suggestion::ToolResults {
+                count,
+                percent: tool_results,
+            });
+        } else {
+            let (_, tokens) = self.sum(Component::is_history);
+            let history = self.percent(tokens);
+            if history >= HISTORY_SHARE {
+                suggestions.push(Suggestion::History { percent: history });
+            }
+        }
+
+        let (count, tokens) = self.sum(|component| component == Component::ToolDefinitions);
+        let tool_definitions = self.percent(tokens);
+        if tool_definitions >= TOOL_DEFINITIONS_SHARE {
+            suggestions.push(Suggestion::ToolDefinitions {
+                count,
+                percent: tool_definitions,
+            });
+        }
+
+        if window > 0 {
+            let usage = self.total as f64 * 100.0 / window as f64;
+            if usage >= WINDOW_USAGE {
+                suggestions.push(Suggestion::NearlyFull { percent: usage });
+            }
+        }
+        suggestions
+    }
+}
+
+/// 累加到同类的行上（系统提示词每个部分单独一行）
+fn add(rows: &mut Vec<Row>, component: Component, items: usize, tokens: usize) {
+    match rows.iter_mut().find(|row| row.component == component) {
+        Some(row) => {
+            row.items += items;
+            row.tokens += tokens;
+        }
+        None => rows.push(Row {
+            component,
+            name: None,
+            items,
+            tokens,
+        }),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// 消息中的各个内容块及其分类
+fn content_blocks(message: &Message) -> Vec<(Component, String)> {
+    match message {
+        Message::User { content, .. } => content
+            .iter()
+            .map(|block| match block {
+                UserContent::Text(text) => (Component::UserText, text.text.clone()),
+                UserContent::ToolResult(result) => (Component::ToolResults, to_json(result)),
+                other => (Component::Attachments, to_json(other)),
+            })
+            .collect(),
+        Message::Assistant { content, .. } => content
+            .iter()
+            .map(|block| match block {
+                AssistantContent::Text(text) => (Component::AssistantText, text.text.clone()),
+                AssistantContent::ToolCall(call) => (Component::ToolCalls, to_json(call)),
+                other => (Component::AssistantText, to_json(other)),
+            })
+            .collect(),
+    }
+}
+
+/// 整个请求的 token 预估
+///
+/// 系统提示词和每条消息按消息格式计算（含角色标记等开销），工具定义按 JSON 计算。
+fn request_tokens(preview: &RequestPreview) -> usize {
+    let system = preview
+        .system
+        .iter()
+        .map(|part| part.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let mut messages = vec![("system".to_string(), system)];
+    messages.extend(preview.messages.iter().map(|message| {
+        let role = match message {
+            Message::User { .. } => "user",
+            Message::Assistant { .. } => "assistant",
+        };
+        let content = content_blocks(message)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        (role.to_string(), content)
+    }));
+    count_messages_tokens(&messages)
+        + preview
+            .tools
+            .iter()
+            .map(|tool| count_tokens(&to_json(tool)))
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::completion::message::ToolResultContent;
+    use rig::OneOrMany;
+
+    fn part(name: &str, text: &str) -> PromptPart {
+        PromptPart {
+            name: name.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    fn tool_call(id: &str) -> Message {
+        Message::Assistant {
+            id: None,
+            content: OneOrMany::one(AssistantContent::tool_call(
+                id,
+                "read_file",
+                serde_json::json!({ "path": "src/main.rs" }),
+            )),
+        }
+    }
+
+    fn tool_result(id: &str, output: &str) -> Message {
+        Message::User {
+            content: OneOrMany::one(UserContent::tool_result(
+                id,
+                OneOrMany::one(ToolResultContent::text(output)),
+            )),
+        }
+    }
+
+    fn preview() -> RequestPreview {
+        let file = "fn main() {\n    println!(\"hello\");\n}\n".repeat(60);
+        RequestPreview {
+            system: vec![
+                part(
+                    "identity",
+                    "Your name is Oxide. You are a helpful assistant.",
+                ),
+                part("mode: normal", "Plan before large changes."),
+            ],
+            tools: vec![ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Read a file from disk.".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": { "path": { "type": "string" } },
+                    "required": ["path"]
+                }),
+            }],
+            messages: vec![
+                Message::user("Why does main print twice?"),
+                tool_call("call_1"),
+                tool_result("call_1", &file),
+                tool_call("call_2"),
+                tool_result("call_2", &file),
+                Message::assistant("It is called from two places."),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_rows_sum_to_total() {
+        let preview = preview();
+        let breakdown = ContextBreakdown::from_preview(&preview);
+        let sum: usize = breakdown.rows().iter().map(|row| row.tokens).sum();
+        assert_eq!(sum, breakdown.total());
+        assert_eq!(breakdown.total(), request_tokens(&preview));
+        let percent: f64 = breakdown
+            .rows()
+            .iter()
+            .map(|row| breakdown.percent(row.tokens))
+            .sum();
+        assert!((percent - 100.0).abs() < 1e-9);
+
+        // 按 token 数从大到小排列，工具结果最多
+        assert!(breakdown
+            .rows()
+            .windows(2)
+            .all(|pair| pair[0].tokens >= pair[1].tokens));
+        let first = &breakdown.rows()[0];
+        assert_eq!(first.component, Component::ToolResults);
+        assert_eq!(first.items, 2);
+
+        let names: Vec<_> = breakdown
+            .rows()
+            .iter()
+            .filter_map(|row| row.name.as_deref())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"identity") && names.contains(&"mode: normal"));
+        for component in [
+            Component::ToolDefinitions,
+            Component::UserText,
+            Component::AssistantText,
+            Component::ToolCalls,
+            Component::Formatting,
+        ] {
+            assert!(breakdown
+                .rows()
+                .iter()
+                .any(|row| row.component == component && row.tokens > 0));
+        }
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let breakdown = ContextBreakdown::from_preview(&preview());
+        let suggestions = breakdown.suggestions(200_000);
+        assert!(matches!(
+            suggestions.as_slice(),
+            [Suggestion::ToolResults { count: 2, percent }] if *percent > 50.0
+        ));
+        let suggestions = breakdown.suggestions(breakdown.total());
+        assert!(matches!(
+            suggestions.last(),
+            Some(Suggestion::NearlyFull { percent }) if (*percent - 100.0).abs() < 1e-9
+        ));
+
+        let mut preview = preview();
+        preview.messages.clear();
+        let breakdown = ContextBreakdown::from_preview(&preview);
+        assert!(breakdown
+            .rows()
+            .iter()
+            .all(|row| !row.component.is_history()));
+        assert_eq!(
+            breakdown.suggestions(200_000),
+            vec![Suggestion::ToolDefinitions {
+                count: 1,
+                percent: breakdown.percent(breakdown.rows()[0].tokens),
+            }]
+        );
+    }
+}
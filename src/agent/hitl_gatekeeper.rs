@@ -2,6 +2,7 @@
 //!
 //! 在工具调用前进行智能决策，判断是否需要人工确认。
 
+use crate::permissions::{PermissionRule, PermissionRules, RuleAction, SessionRules};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -11,12 +12,16 @@ use std::sync::Arc;
 pub struct HitlConfig {
     /// 信任度设置
     pub trust: TrustConfig,
+
+    /// 用户配置的权限规则（`[permissions.rules]`），优先于内置规则
+    pub rules: PermissionRules,
 }
 
 impl Default for HitlConfig {
     fn default() -> Self {
         Self {
             trust: TrustConfig::default(),
+            rules: PermissionRules::default(),
         }
     }
 }
@@ -139,6 +144,10 @@ pub struct HitlGatekeeper {
     config: HitlConfig,
     trust_score: Arc<tokio::sync::Mutex<f32>>,
     operation_history: Arc<tokio::sync::Mutex<Vec<String>>>,
+    /// 权限规则（`/permissions import` 后替换）
+    rules: std::sync::RwLock<PermissionRules>,
+    /// 确认时选择在本次会话中记住的规则
+    session_rules: std::sync::RwLock<SessionRules>,
 }
 
 impl HitlGatekeeper {
@@ -146,19 +155,48 @@ impl HitlGatekeeper {
     #[allow(dead_code)]
     pub fn new(config: HitlConfig) -> Result<Self, HitlError> {
         let initial_score = config.trust.initial_score;
+        let rules = std::sync::RwLock::new(config.rules.clone());
         Ok(Self {
             config,
             trust_score: Arc::new(tokio::sync::Mutex::new(initial_score)),
             operation_history: Arc::new(tokio::sync::Mutex::new(Vec::new())),
+            rules,
+            session_rules: std::sync::RwLock::new(SessionRules::default()),
         })
     }
 
+    /// 替换权限规则，之后的工具调用按新规则决策
+    pub fn set_rules(&self, rules: PermissionRules) {
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    /// 在本次会话中放行命中 `rule` 的调用（配置中的 deny 规则仍然优先）
+    pub fn allow_for_session(&self, rule: PermissionRule) {
+        self.session_rules
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .add(rule);
+    }
+
+    /// 本次会话中记住的规则
+    pub fn session_rules(&self) -> SessionRules {
+        self.session_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
     #[allow(dead_code)]
     /// 评估工具调用是否需要人工确认
     pub async fn evaluate_tool_call(
         &self,
         request: ToolCallRequest,
     ) -> Result<HitlDecision, HitlError> {
+        // 0. 用户配置的权限规则
+        if let Some(decision) = self.rule_decision(&request) {
+            return Ok(decision);
+        }
+
         // 1. 快速路径：已知的低风险操作
         if let Some(decision) = self.quick_path(&request).await {
             return Ok(decision);
@@ -170,7 +208,7 @@ impl HitlGatekeeper {
             // 高信任度：对于中低风险操作自动批准
             if self.is_low_risk_tool(&request.tool_name) {
                 return Ok(HitlDecision::ExecuteDirectly {
-                    reason: format!("信任分数较高 ({:.2})，自动批准低风险操作", trust_score),
+                    reason: t!("hitl.reason.trusted", score = format!("{:.2}", trust_score)),
                 });
             }
         }
@@ -203,12 +241,53 @@ impl HitlGatekeeper {
         *score = (*score - self.config.trust.decrement).max(0.0);
     }
 
+    /// 按 `[permissions.rules]` 和本次会话中记住的规则决策（allow 规则不放行危险命令）
+    ///
+    /// 会话规则是用户在确认时明确选择的，优先于配置中的 ask 规则，但不覆盖 deny 规则。
+    fn rule_decision(&self, request: &ToolCallRequest) -> Option<HitlDecision> {
+        let command = request.args.get("command").and_then(|c| c.as_str());
+        let dangerous =
+            request.tool_name == "shell_execute" && command.is_some_and(is_dangerous_command);
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        let decided = rules.decide(&request.tool_name, &request.args);
+        if let Some((RuleAction::Deny, rule)) = decided {
+            return Some(HitlDecision::Reject {
+                reason: t!("hitl.reason.rule_deny", rule = rule),
+                suggestion: None,
+            });
+        }
+        if !dangerous {
+            let session = self.session_rules.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(rule) = session.allowing(&request.tool_name, &request.args) {
+                return Some(HitlDecision::ExecuteDirectly {
+                    reason: t!("hitl.reason.session_allow", rule = rule),
+                });
+            }
+        }
+        match decided? {
+            (RuleAction::Ask, rule) => Some(HitlDecision::RequireConfirmation {
+                reason: t!("hitl.reason.rule_ask", rule = rule),
+                warning_level: WarningLevel::Medium,
+            }),
+            (RuleAction::Allow, _) if dangerous => None,
+            (RuleAction::Allow, rule) => Some(HitlDecision::ExecuteDirectly {
+                reason: t!("hitl.reason.rule_allow", rule = rule),
+            }),
+            (RuleAction::Deny, _) => None,
+        }
+    }
+
     /// 快速路径：已知的低风险操作
     async fn quick_path(&self, request: &ToolCallRequest) -> Option<HitlDecision> {
         match request.tool_name.as_str() {
-            "read_file" | "glob" | "grep_search" | "scan_codebase" => {
+            "read_file" | "glob" | "grep_search" | "scan_codebase" | "outline" | "diff" | "deps" => {
                 Some(HitlDecision::ExecuteDirectly {
-                    reason: "只读操作，无风险".to_string(),
+                    reason: t!("hitl.reason.read_only").to_string(),
+                })
+            }
+            "memory" if request.args.get("action").and_then(|a| a.as_str()) == Some("list") => {
+                Some(HitlDecision::ExecuteDirectly {
+                    reason: t!("hitl.reason.read_only").to_string(),
                 })
             }
             "shell_execute" => {
@@ -216,7 +295,7 @@ impl HitlGatekeeper {
                 if let Some(cmd) = request.args.get("command").and_then(|c| c.as_str()) {
                     if self.is_safe_readonly_command(cmd) {
                         return Some(HitlDecision::ExecuteDirectly {
-                            reason: "安全的只读命令".to_string(),
+                            reason: t!("hitl.reason.safe_command").to_string(),
                         });
                     }
                 }
@@ -257,7 +336,7 @@ impl HitlGatekeeper {
     fn is_low_risk_tool(&self, tool_name: &str) -> bool {
         matches!(tool_name,
             "read_file" | "write_file" | "edit_file" |
-            "glob" | "grep_search" | "scan_codebase"
+            "glob" | "grep_search" | "scan_codebase" | "outline" | "diff" | "diagnostics" | "deps" | "memory"
         )
     }
 
@@ -267,27 +346,27 @@ impl HitlGatekeeper {
             "delete_file" => {
                 // 删除文件总是需要确认
                 HitlDecision::RequireConfirmation {
-                    reason: "即将删除文件".to_string(),
+                    reason: t!("hitl.reason.delete_file").to_string(),
                     warning_level: WarningLevel::High,
                 }
             }
             "shell_execute" => {
                 // 检查是否是危险命令
                 if let Some(cmd) = request.args.get("command").and_then(|c| c.as_str()) {
-                    if self.is_dangerous_command(cmd) {
+                    if is_dangerous_command(cmd) {
                         HitlDecision::Reject {
-                            reason: "检测到危险命令".to_string(),
-                            suggestion: Some("请考虑使用更安全的替代方案".to_string()),
+                            reason: t!("hitl.reason.dangerous_command").to_string(),
+                            suggestion: Some(t!("hitl.reason.safer_alternative").to_string()),
                         }
                     } else {
                         HitlDecision::RequireConfirmation {
-                            reason: format!("即将执行命令: {}", cmd),
+                            reason: t!("hitl.reason.run_command", command = cmd),
                             warning_level: WarningLevel::Medium,
                         }
                     }
                 } else {
                     HitlDecision::RequireConfirmation {
-                        reason: "即将执行命令".to_string(),
+                        reason: t!("hitl.reason.run_command_unknown").to_string(),
                         warning_level: WarningLevel::Medium,
                     }
                 }
@@ -296,48 +375,84 @@ impl HitlGatekeeper {
                 // 编辑文件 (Wrapper 版本) 已经内置了 diff 预览和确认
                 // 因此这里不需要再次确认，避免双重确认
                 HitlDecision::ExecuteDirectly {
-                    reason: "工具内置确认".to_string(),
+                    reason: t!("hitl.reason.built_in_confirmation").to_string(),
+                }
+            }
+            "run_tests" => {
+                // 运行测试会执行项目代码
+                HitlDecision::RequireConfirmation {
+                    reason: t!("hitl.reason.run_tests").to_string(),
+                    warning_level: WarningLevel::Low,
+                }
+            }
+            "diagnostics" => {
+                // 编译检查会执行构建脚本和过程宏
+                HitlDecision::RequireConfirmation {
+                    reason: t!("hitl.reason.diagnostics").to_string(),
+                    warning_level: WarningLevel::Low,
+                }
+            }
+            "http_request" => {
+                // 以“方法 + 主机”作为操作描述；只读方法风险较低
+                let descriptor = crate::tools::http_request::operation_descriptor(&request.args);
+                let read_only = ["GET ", "HEAD ", "OPTIONS "]
+                    .iter()
+                    .any(|method| descriptor.starts_with(method));
+                HitlDecision::RequireConfirmation {
+                    reason: t!("hitl.reason.http_request", request = descriptor),
+                    warning_level: if read_only { WarningLevel::Low } else { WarningLevel::Medium },
+                }
+            }
+            "memory" => {
+                // 写入项目记忆只需轻量确认；信任分数较高时自动批准
+                let text = request.args.get("text").and_then(|t| t.as_str()).unwrap_or_default();
+                let reason = match request.args.get("action").and_then(|a| a.as_str()) {
+                    Some("add") => t!("hitl.reason.remember", text = text),
+                    _ => t!("hitl.reason.forget").to_string(),
+                };
+                HitlDecision::RequireConfirmation {
+                    reason,
+                    warning_level: WarningLevel::Info,
+                }
+            }
+            "apply_patch" => {
+                // 补丁中删除文件时按删除文件的风险确认
+                let patch = request.args.get("patch").and_then(|p| p.as_str()).unwrap_or_default();
+                if patch.lines().any(|line| line.trim_end() == "+++ /dev/null") {
+                    HitlDecision::RequireConfirmation {
+                        reason: t!("hitl.reason.delete_file").to_string(),
+                        warning_level: WarningLevel::High,
+                    }
+                } else {
+                    HitlDecision::RequireConfirmation {
+                        reason: t!("hitl.reason.modify_file").to_string(),
+                        warning_level: WarningLevel::Low,
+                    }
                 }
             }
             "write_file" | "multiedit" => {
                 // 其他修改文件的工具需要确认
                 HitlDecision::RequireConfirmation {
-                    reason: "即将修改文件".to_string(),
+                    reason: t!("hitl.reason.modify_file").to_string(),
                     warning_level: WarningLevel::Low,
                 }
             }
+            name if name.starts_with("mcp__") => {
+                // 外部 MCP 服务器的工具行为未知，需要确认
+                HitlDecision::RequireConfirmation {
+                    reason: t!("hitl.reason.mcp_tool", tool = name),
+                    warning_level: WarningLevel::Medium,
+                }
+            }
             _ => {
                 // 其他工具：根据上下文判断
                 HitlDecision::ExecuteDirectly {
-                    reason: "未知工具，默认执行".to_string(),
+                    reason: t!("hitl.reason.unknown_tool").to_string(),
                 }
             }
         }
     }
 
-    /// 检查是否是危险命令
-    fn is_dangerous_command(&self, cmd: &str) -> bool {
-        let cmd_lower = cmd.trim().to_lowercase();
-        let dangerous_patterns = [
-            "rm -rf",
-            "rm -fr",
-            ":(){:|:&};:", // fork bomb
-            "dd if=/dev/zero",
-            "mkfs",
-            "format",
-            "shutdown",
-            "reboot",
-            "kill -9",
-        ];
-
-        for pattern in &dangerous_patterns {
-            if cmd_lower.contains(pattern) {
-                return true;
-            }
-        }
-        false
-    }
-
     /// 获取当前信任分数
     #[allow(dead_code)]
     pub async fn trust_score(&self) -> f32 {
@@ -345,6 +460,29 @@ impl HitlGatekeeper {
     }
 }
 
+/// 检查是否是危险命令（模型调用 shell_execute 时拒绝执行，`!` 直接执行时同样拦截）
+pub fn is_dangerous_command(cmd: &str) -> bool {
+    let cmd_lower = cmd.trim().to_lowercase();
+    let dangerous_patterns = [
+        "rm -rf",
+        "rm -fr",
+        ":(){:|:&};:", // fork bomb
+        "dd if=/dev/zero",
+        "mkfs",
+        "format",
+        "shutdown",
+        "reboot",
+        "kill -9",
+    ];
+
+    for pattern in &dangerous_patterns {
+        if cmd_lower.contains(pattern) {
+            return true;
+        }
+    }
+    false
+}
+
 /// HITL 错误类型
 #[derive(Debug, thiserror::Error)]
 pub enum HitlError {
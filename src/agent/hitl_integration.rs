@@ -4,13 +4,63 @@
 
 #![allow(dead_code)]
 
-use crate::agent::hitl_gatekeeper::{HitlConfig, HitlDecision, HitlGatekeeper, ToolCallRequest, OperationContext, WarningLevel};
-use crate::tools::ask_user_question::{WrappedAskUserQuestionTool, QuestionOption};
+use crate::agent::{interaction, loop_guard, reminder, tool_status};
+use crate::agent::reminder::Reminded;
+use crate::agent::tool_results::{self, ToolResultOutput};
+use crate::tools::redactor::{self, Redacted};
+use crate::tools::verbose_output::verbose_tools;
+use crate::tools::tool_args::{self, ArgsOutput};
+use crate::telemetry;
+use crate::agent::hitl_gatekeeper::{HitlConfig, HitlDecision, HitlGatekeeper, ToolCallRequest, OperationContext, UserChoice, WarningLevel};
+use crate::tools::ask_user_question::{AskUserQuestionTool, WrappedAskUserQuestionTool, QuestionOption};
+use crate::permissions::PermissionRule;
 use rig::tool::Tool;
 use colored::*;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 use serde::{Serialize, Deserialize};
 use anyhow::Result;
+use futures::future::BoxFuture;
+
+/// 需要用户决定的工具调用
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionRequest {
+    pub tool: String,
+    /// 工具参数（已隐藏认证信息）
+    pub args: serde_json::Value,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning_level: Option<WarningLevel>,
+    /// 多选一时的选项，为空表示是/否确认
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<UserChoice>,
+}
+
+/// 终端之外的权限确认方（如 `oxide serve` 的客户端）
+pub trait PermissionPrompter: Send + Sync {
+    fn prompt(&self, request: PermissionRequest) -> BoxFuture<'_, HitlResult>;
+}
+
+/// 不询问任何人、固定回答的权限策略（嵌入 oxide 时使用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PermissionPolicy {
+    /// 拒绝所有需要确认的操作
+    #[default]
+    RejectAll,
+    /// 批准所有需要确认的操作（危险命令仍被拦截）
+    ApproveAll,
+}
+
+impl PermissionPrompter for PermissionPolicy {
+    fn prompt(&self, _request: PermissionRequest) -> BoxFuture<'_, HitlResult> {
+        let result = match self {
+            PermissionPolicy::RejectAll => HitlResult::Rejected,
+            PermissionPolicy::ApproveAll => HitlResult::Approved,
+        };
+        Box::pin(async move { result })
+    }
+}
 
 /// HITL 集成示例
 ///
@@ -18,6 +68,8 @@ use anyhow::Result;
 pub struct HitlIntegration {
     pub gatekeeper: HitlGatekeeper,
     pub ask_user_tool: WrappedAskUserQuestionTool,
+    /// 设置后需要确认的操作交给它决定，不在终端提问
+    prompter: Option<Arc<dyn PermissionPrompter>>,
 }
 
 impl HitlIntegration {
@@ -25,6 +77,7 @@ impl HitlIntegration {
     pub fn new() -> Result<Self> {
         let config = HitlConfig {
             trust: crate::agent::hitl_gatekeeper::TrustConfig::default(),
+            rules: crate::permissions::PermissionRules::load()?,
         };
         let gatekeeper = HitlGatekeeper::new(config)?;
         let ask_user_tool = WrappedAskUserQuestionTool::new();
@@ -32,9 +85,16 @@ impl HitlIntegration {
         Ok(Self {
             gatekeeper,
             ask_user_tool,
+            prompter: None,
         })
     }
 
+    /// 由 `prompter` 确认操作，替代终端提问
+    pub fn with_prompter(mut self, prompter: Arc<dyn PermissionPrompter>) -> Self {
+        self.prompter = Some(prompter);
+        self
+    }
+
     /// 在工具调用前进行 HITL 检查
     ///
     /// # 示例
@@ -65,6 +125,16 @@ impl HitlIntegration {
         &self,
         request: ToolCallRequest,
     ) -> Result<HitlResult, HitlIntegrationError> {
+        self.evaluate_with_outcome(request)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// 与 [`Self::evaluate_and_confirm`] 相同，另外返回权限结果（自动放行的依据、用户批准或拒绝）
+    pub async fn evaluate_with_outcome(
+        &self,
+        request: ToolCallRequest,
+    ) -> Result<(HitlResult, tool_status::PermissionOutcome), HitlIntegrationError> {
         // 1. 使用 Gatekeeper 评估
         let decision = self.gatekeeper
             .evaluate_tool_call(request.clone())
@@ -72,29 +142,73 @@ impl HitlIntegration {
             .map_err(|e| HitlIntegrationError::GatekeeperError(e.to_string()))?;
 
         // 2. 根据决策处理
-        match decision {
-            HitlDecision::ExecuteDirectly { reason: _ } => {
+        let (reason, result) = match decision {
+            HitlDecision::ExecuteDirectly { reason } => {
                 // 对齐 Claude Code 行为：自动批准时静默执行，不输出提示
-                Ok(HitlResult::Approved)
+                return Ok((
+                    HitlResult::Approved,
+                    tool_status::PermissionOutcome::AutoAllowed { reason },
+                ));
             }
 
             HitlDecision::RequireConfirmation { reason, warning_level } => {
-                self.request_confirmation(&reason, &warning_level).await
+                let result = match &self.prompter {
+                    Some(prompter) => Ok(prompter
+                        .prompt(PermissionRequest {
+                            tool: request.tool_name.clone(),
+                            args: crate::tools::redactor::redact_tool_args(&request.args),
+                            reason: reason.clone(),
+                            warning_level: Some(warning_level),
+                            options: Vec::new(),
+                        })
+                        .await),
+                    None => {
+                        self.request_confirmation(&request, &reason, &warning_level)
+                            .await
+                    }
+                };
+                (reason, result)
             }
 
             HitlDecision::RequireChoice { question, options, default } => {
-                self.request_choice(&question, &options, &default).await
+                let result = match &self.prompter {
+                    Some(prompter) => Ok(prompter
+                        .prompt(PermissionRequest {
+                            tool: request.tool_name.clone(),
+                            args: crate::tools::redactor::redact_tool_args(&request.args),
+                            reason: question.clone(),
+                            warning_level: None,
+                            options,
+                        })
+                        .await),
+                    None => self.request_choice(&question, &options, &default).await,
+                };
+                (question, result)
             }
 
             HitlDecision::Reject { reason, suggestion } => {
-                self.handle_rejection(&reason, suggestion.as_deref()).await
+                let result = self.handle_rejection(&reason, suggestion.as_deref()).await;
+                (reason, result)
             }
-        }
+        };
+        trace_permission(&request.tool_name, &reason, &result);
+        result.map(|result| {
+            let outcome = match result {
+                HitlResult::Approved => tool_status::PermissionOutcome::Approved,
+                _ => tool_status::PermissionOutcome::Denied { reason },
+            };
+            (result, outcome)
+        })
     }
 
     /// 请求用户确认
+    ///
+    /// 确认和可能的反馈输入作为一次交互排队，中间不会插入其他工具的提问。
+    /// 除了允许一次，还可以在本次会话中记住一个范围（命令前缀或目录，见 `permissions::scope_rules`），
+    /// 或把最窄的范围作为 allow 规则写入项目配置；选项中显示将要记住的规则原文。
     async fn request_confirmation(
         &self,
+        request: &ToolCallRequest,
         reason: &str,
         warning_level: &WarningLevel,
     ) -> Result<HitlResult, HitlIntegrationError> {
@@ -105,63 +219,141 @@ impl HitlIntegration {
             WarningLevel::High => ("🚨", "red"),
             WarningLevel::Critical => ("🔴", "bright_red"),
         };
+        let reason = reason.to_string();
+        let scopes = crate::permissions::scope_rules(&request.tool_name, &request.args);
+        // 标签 → 选择后记住的规则和是否写入项目配置
+        let mut remembered: Vec<(String, PermissionRule, bool)> = scopes
+            .iter()
+            .map(|rule| {
+                let label = t!("hitl.confirm.allow_session", rule = rule);
+                (label, rule.clone(), false)
+            })
+            .collect();
+        if let Some(narrowest) = scopes.first() {
+            let label = t!("hitl.confirm.allow_always", rule = narrowest);
+            remembered.push((label, narrowest.clone(), true));
+        }
 
-        println!();
-        println!("{} {}", icon, reason.bright_white());
-
-        // 使用 AskUserQuestion 工具
-        let args = crate::tools::ask_user_question::AskUserQuestionArgs {
-            questions: vec![crate::tools::ask_user_question::Question {
-                question: format!("确认执行此操作？"),
-                header: "确认".to_string(),
-                options: vec![
-                    QuestionOption {
-                        label: "确认".to_string(),
-                        description: "继续执行操作".to_string(),
-                    },
-                    QuestionOption {
-                        label: "取消".to_string(),
-                        description: "取消此操作".to_string(),
-                    },
-                    QuestionOption {
-                        label: "提供反馈".to_string(),
-                        description: "提供纠正建议或反馈".to_string(),
-                    },
-                ],
-                multi_select: false,
-            }],
-        };
-
-        match self.ask_user_tool.call(args).await {
-            Ok(output) => {
-                if let Some(answer) = output.answers.get("确认") {
-                    let answer_str = answer.as_str().unwrap_or("");
-                    if answer_str == "确认" || answer_str == "是" {
-                        return Ok(HitlResult::Approved);
-                    } else if answer_str == "提供反馈" {
-                        // 如果用户选择提供反馈，尝试获取反馈内容
-                        // 这里我们可以复用 ask_user_tool 来获取输入
-                        let feedback_args = crate::tools::ask_user_question::AskUserQuestionArgs {
-                            questions: vec![crate::tools::ask_user_question::Question {
-                                question: "请输入您的纠正建议:".to_string(),
-                                header: "路径纠正反馈".to_string(),
-                                options: vec![], // 空选项表示允许自由文本输入
-                                multi_select: false,
-                            }],
-                        };
-                        if let Ok(feedback_output) = self.ask_user_tool.call(feedback_args).await {
-                            if let Some(feedback) = feedback_output.answers.get("路径纠正反馈") {
-                                if let Some(feedback_text) = feedback.as_str() {
-                                    return Ok(HitlResult::Suggested(feedback_text.to_string()));
-                                }
-                            }
-                        }
-                    }
+        let answer = interaction::interact(move || {
+            println!();
+            println!("{} {}", icon, reason.bright_white());
+
+            let mut options = vec![QuestionOption {
+                label: t!("hitl.confirm.approve").to_string(),
+                description: t!("hitl.confirm.approve_description").to_string(),
+            }];
+            for (label, _, persist) in &remembered {
+                let description = if *persist {
+                    t!(
+                        "hitl.confirm.allow_always_description",
+                        path = crate::config::ConfigLoader::new()
+                            .project_config_path()
+                            .display()
+                    )
+                } else {
+                    t!("hitl.confirm.allow_session_description").to_string()
+                };
+                options.push(QuestionOption {
+                    label: label.clone(),
+                    description,
+                });
+            }
+            options.extend([
+                QuestionOption {
+                    label: t!("hitl.confirm.cancel").to_string(),
+                    description: t!("hitl.confirm.cancel_description").to_string(),
+                },
+                QuestionOption {
+                    label: t!("hitl.confirm.feedback").to_string(),
+                    description: t!("hitl.confirm.feedback_description").to_string(),
+                },
+            ]);
+
+            // 使用 AskUserQuestion 工具
+            let args = crate::tools::ask_user_question::AskUserQuestionArgs {
+                questions: vec![crate::tools::ask_user_question::Question {
+                    question: t!("hitl.confirm.question").to_string(),
+                    header: t!("hitl.confirm.header").to_string(),
+                    options,
+                    multi_select: false,
+                    allow_other: false,
+                }],
+            };
+
+            let output = AskUserQuestionTool::ask_all(&args);
+            let Some(answer) = output.answers.get(t!("hitl.confirm.header")) else {
+                return (HitlResult::Rejected, None);
+            };
+            let answer_str = answer.as_str().unwrap_or("");
+            if answer_str == t!("hitl.confirm.approve") || answer_str == t!("common.yes") {
+                return (HitlResult::Approved, None);
+            }
+            if let Some((_, rule, persist)) = remembered
+                .into_iter()
+                .find(|(label, _, _)| label == answer_str)
+            {
+                return (HitlResult::Approved, Some((rule, persist)));
+            }
+            if answer_str == t!("hitl.confirm.feedback") {
+                // 复用 AskUserQuestion 获取反馈内容
+                let feedback_args = crate::tools::ask_user_question::AskUserQuestionArgs {
+                    questions: vec![crate::tools::ask_user_question::Question {
+                        question: t!("hitl.feedback.question").to_string(),
+                        header: t!("hitl.feedback.header").to_string(),
+                        options: vec![], // 空选项表示允许自由文本输入
+                        multi_select: false,
+                        allow_other: false,
+                    }],
+                };
+                let feedback_output = AskUserQuestionTool::ask_all(&feedback_args);
+                if let Some(feedback_text) = feedback_output
+                    .answers
+                    .get(t!("hitl.feedback.header"))
+                    .and_then(|feedback| feedback.as_str())
+                {
+                    return (HitlResult::Suggested(feedback_text.to_string()), None);
                 }
-                Ok(HitlResult::Rejected)
             }
-            Err(_) => Ok(HitlResult::Rejected),
+            (HitlResult::Rejected, None)
+        })
+        .await
+        .map_err(|e| HitlIntegrationError::UserInteractionError(e.to_string()))?;
+
+        let (result, remember) = answer;
+        if let Some((rule, persist)) = remember {
+            self.remember(rule, persist).await;
         }
+        Ok(result)
+    }
+
+    /// 在本次会话中记住确认时选择的规则，`persist` 时同时写入项目配置
+    async fn remember(&self, rule: PermissionRule, persist: bool) {
+        self.gatekeeper.allow_for_session(rule.clone());
+        let (icon, message) = if !persist {
+            ("✓".green(), t!("hitl.remembered_session", rule = rule))
+        } else {
+            match crate::permissions::save_allow_rule(&rule) {
+                Ok(()) => (
+                    "✓".green(),
+                    t!(
+                        "hitl.remembered_always",
+                        rule = rule,
+                        path = crate::config::ConfigLoader::new()
+                            .project_config_path()
+                            .display()
+                    ),
+                ),
+                Err(e) => (
+                    "⚠️".yellow(),
+                    t!(
+                        "hitl.remember_failed",
+                        rule = rule,
+                        error = format!("{:#}", e)
+                    ),
+                ),
+            }
+        };
+        interaction::exclusive(|| println!("{} {}", icon, message.dimmed())).await;
     }
 
     /// 请求用户选择
@@ -171,9 +363,7 @@ impl HitlIntegration {
         options: &[crate::agent::hitl_gatekeeper::UserChoice],
         _default: &str,
     ) -> Result<HitlResult, HitlIntegrationError> {
-        println!();
-        println!("{}", question.bright_white());
-        println!();
+        let question = question.to_string();
 
         // 将选项转换为 AskUserQuestion 格式
         let ask_options = options.iter().map(|opt| {
@@ -185,24 +375,27 @@ impl HitlIntegration {
 
         let args = crate::tools::ask_user_question::AskUserQuestionArgs {
             questions: vec![crate::tools::ask_user_question::Question {
-                question: "请选择:".to_string(),
-                header: "选择".to_string(),
+                question: t!("hitl.choice.question").to_string(),
+                header: t!("hitl.choice.header").to_string(),
                 options: ask_options,
                 multi_select: false,
+                allow_other: false,
             }],
         };
 
-        match self.ask_user_tool.call(args).await {
-            Ok(output) => {
-                if let Some(answer) = output.answers.get("选择") {
-                    if !answer.is_null() {
-                        return Ok(HitlResult::Approved);
-                    }
-                }
-                Ok(HitlResult::Rejected)
+        interaction::interact(move || {
+            println!();
+            println!("{}", question.bright_white());
+            println!();
+
+            let output = AskUserQuestionTool::ask_all(&args);
+            match output.answers.get(t!("hitl.choice.header")) {
+                Some(answer) if !answer.is_null() => HitlResult::Approved,
+                _ => HitlResult::Rejected,
             }
-            Err(_) => Ok(HitlResult::Rejected),
-        }
+        })
+        .await
+        .map_err(|e| HitlIntegrationError::UserInteractionError(e.to_string()))
     }
 
     /// 处理拒绝
@@ -211,17 +404,25 @@ impl HitlIntegration {
         reason: &str,
         suggestion: Option<&str>,
     ) -> Result<HitlResult, HitlIntegrationError> {
-        println!();
-        println!("{}", "❌ 操作被拒绝".bright_red());
-        println!("{}", reason.bright_white());
-
-        if let Some(suggestion) = suggestion {
-            println!();
-            println!("{}", "💡 建议:".bright_cyan());
-            println!("  {}", suggestion);
+        // `-p` 和纯文本模式下不在 stdout 显示（见 `verbose_tools`），模型仍会收到拒绝原因
+        if verbose_tools().is_hidden() {
+            return Ok(HitlResult::Rejected);
         }
+        // 与提问一样排队输出，避免插进其他工具的提问中间
+        interaction::exclusive(|| {
+            println!();
+            println!("{}", t!("hitl.rejected").bright_red());
+            println!("{}", reason.bright_white());
+
+            if let Some(suggestion) = suggestion {
+                println!();
+                println!("{}", t!("hitl.suggestion").bright_cyan());
+                println!("  {}", suggestion);
+            }
 
-        println!();
+            println!();
+        })
+        .await;
         Ok(HitlResult::Rejected)
     }
 
@@ -241,6 +442,21 @@ impl HitlIntegration {
     }
 }
 
+/// 记录权限确认事件
+fn trace_permission(
+    tool_name: &str,
+    reason: &str,
+    result: &Result<HitlResult, HitlIntegrationError>,
+) {
+    let decision = match result {
+        Ok(HitlResult::Approved) => "approved",
+        Ok(HitlResult::Rejected) => "rejected",
+        Ok(HitlResult::Suggested(_)) => "suggested",
+        Err(_) => "error",
+    };
+    telemetry::permission_prompt(tool_name, decision, reason);
+}
+
 /// HITL 结果
 #[derive(Debug, Clone, PartialEq)]
 pub enum HitlResult {
@@ -285,7 +501,11 @@ pub fn build_operation_context(
 /// 可见性更高的 HITL 包装工具
 /// 
 /// 包装任何 rig::Tool，在执行前进行 HITL 评估和确认。
-/// 如果 hitl 为 None，则直接执行。
+/// 如果 hitl 为 None，则直接执行。结果中的密钥在这里统一脱敏（见 [`redact_result`]），
+/// 超过 token 预算的结果换成摘要（见 [`tool_results`]），
+/// 本轮重复多次的相同调用直接返回错误（见 [`loop_guard`]）。
+/// 参数在评估前按工具的 schema 解析，必要时做一次类型转换（见 [`tool_args`]）。
+/// 违反规则的调用在结果上附加系统提醒（见 [`reminder::attach`]）。
 pub struct MaybeHitlTool<T: Tool> {
     pub inner: T,
     pub hitl: Option<Arc<HitlIntegration>>,
@@ -306,22 +526,60 @@ where
     const NAME: &'static str = T::NAME;
 
     type Error = T::Error;
-    type Args = T::Args;
-    type Output = T::Output;
+    type Args = serde_json::Value;
+    type Output = Reminded<ArgsOutput<ToolResultOutput<Redacted<T::Output>>>>;
 
     async fn definition(&self, prompt: String) -> rig::completion::ToolDefinition {
         self.inner.definition(prompt).await
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        // 参数无法解析时不请求确认，直接返回指出字段的错误
+        let parsed = match tool_args::parse_for(&self.inner, args.clone()).await {
+            Ok(parsed) => parsed,
+            Err(failure) => {
+                let redacted = crate::tools::redactor::redact_tool_args(&args);
+                let call_id = tool_status::emit_started(T::NAME, &redacted);
+                tool_status::emit_failed(call_id, T::NAME, Duration::ZERO, &failure.message);
+                return Err(crate::tools::FileToolError::from(failure).into());
+            }
+        };
+        let result = self
+            .call_parsed(parsed.args)
+            .await
+            .map(|output| tool_args::annotate(output, &parsed.coercions));
+        reminder::attach(T::NAME, result)
+    }
+}
+
+impl<T: Tool + Send + Sync> MaybeHitlTool<T>
+where
+    T::Args: Serialize + for<'de> Deserialize<'de> + Send + Sync,
+    T::Output: Serialize + Send + Sync,
+    T::Error: From<crate::tools::FileToolError> + Send + Sync,
+{
+    /// 用解析后的参数执行：重复调用检查、HITL 评估，然后执行工具
+    async fn call_parsed(
+        &self,
+        args: T::Args,
+    ) -> Result<ToolResultOutput<Redacted<T::Output>>, T::Error> {
+        // 本轮重复的相同调用不再执行，也不再请求确认（见 `loop_guard`）
+        let args_json = serde_json::to_value(&args).unwrap_or(serde_json::Value::Null);
+        if let Err(failure) = loop_guard::check(T::NAME, &args_json) {
+            let redacted = crate::tools::redactor::redact_tool_args(&args_json);
+            let call_id = tool_status::emit_started(T::NAME, &redacted);
+            tool_status::emit_failed(call_id, T::NAME, Duration::ZERO, &failure.message);
+            return Err(crate::tools::FileToolError::from(failure).into());
+        }
+
+        let call_id = tool_status::next_call_id();
         let hitl = match &self.hitl {
             Some(h) => h,
-            None => return self.inner.call(args).await,
+            None => return self.call_with_status(call_id, args).await,
         };
 
         // 1. 构建工具调用请求
         let tool_name = T::NAME.to_string();
-        let args_json = serde_json::to_value(&args).unwrap_or(serde_json::Value::Null);
 
         // 获取当前任务上下文 (暂时使用默认值，后续可以从全局状态获取)
         let context = OperationContext {
@@ -331,6 +589,7 @@ where
             git_branch: None,
         };
 
+        let redacted = crate::tools::redactor::redact_tool_args(&args_json);
         let request = ToolCallRequest {
             tool_name: tool_name.clone(),
             args: args_json,
@@ -338,32 +597,109 @@ where
         };
 
         // 2. HITL 评估
-        match hitl.evaluate_and_confirm(request).await {
+        let evaluated = hitl
+            .evaluate_with_outcome(request)
+            .await
+            .map(|(result, outcome)| {
+                tool_status::emit_permission(call_id, T::NAME, &redacted, outcome);
+                result
+            });
+        match evaluated {
             Ok(HitlResult::Approved) => {
-                let result = self.inner.call(args).await;
+                let result = self.call_with_status(call_id, args).await;
                 if result.is_ok() {
                     hitl.record_success(tool_name).await;
                 }
                 result
             }
             Ok(HitlResult::Rejected) => {
-                println!("{} {} 操作已被用户取消", "🚫".red(), T::NAME);
+                verbose_tools().println(format_args!(
+                    "{} {}",
+                    "🚫".red(),
+                    t!("hitl.cancelled_by_user", tool = T::NAME)
+                ));
                 // 使用内部方法创建取消错误。如果工具支持，则返回具体的取消错误。
                 Err(self.create_cancellation_error())
             }
             Ok(HitlResult::Suggested(suggestion)) => {
-                println!("{} 用户提供了改进建议: {}", "💡".cyan(), suggestion);
+                verbose_tools().println(format_args!(
+                    "{} {}",
+                    "💡".cyan(),
+                    t!("hitl.user_suggested", suggestion = suggestion)
+                ));
                 // 收到建议时，我们停止当前工具调用，以便 Agent 反思建议。
                 Err(self.create_cancellation_error())
             }
             Err(e) => {
-                println!("{} HITL 系统错误: {}", "❌".red(), e);
-                self.inner.call(args).await
+                verbose_tools().println(format_args!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("hitl.system_error", error = e)
+                ));
+                self.inner.call(args).await.map(|output| {
+                    let mut output_json =
+                        serde_json::to_value(&output).unwrap_or(serde_json::Value::Null);
+                    ToolResultOutput::Full(redact_result(output, &mut output_json))
+                })
             }
         }
     }
 }
 
+impl<T: Tool> MaybeHitlTool<T>
+where
+    T::Args: Serialize,
+    T::Output: Serialize,
+{
+    /// 执行工具并发出开始/完成/失败状态事件（`call_id` 与权限事件相同）
+    ///
+    /// 状态事件中是完整输出，返回给模型的结果先脱敏，再按预算截取。
+    async fn call_with_status(
+        &self,
+        call_id: u64,
+        args: T::Args,
+    ) -> Result<ToolResultOutput<Redacted<T::Output>>, T::Error> {
+        // trace 和状态行中不出现 Authorization 等认证信息
+        let args_json = serde_json::to_value(&args).unwrap_or(serde_json::Value::Null);
+        let args_json = crate::tools::redactor::redact_tool_args(&args_json);
+        let span = telemetry::tool_span(T::NAME, &args_json);
+        tool_status::emit_started_as(call_id, T::NAME, &args_json);
+        let start = Instant::now();
+
+        let result = self.inner.call(args).instrument(span.clone()).await;
+        telemetry::record_tool_result(&span, start.elapsed(), result.is_ok());
+
+        match result {
+            Ok(output) => {
+                let mut output_json =
+                    serde_json::to_value(&output).unwrap_or(serde_json::Value::Null);
+                tool_status::emit_completed(call_id, T::NAME, start.elapsed(), &output_json);
+                let output = redact_result(output, &mut output_json);
+                Ok(tool_results::fit_to_budget(T::NAME, output, &output_json))
+            }
+            Err(e) => {
+                tool_status::emit_failed(call_id, T::NAME, start.elapsed(), &e.to_string());
+                Err(e)
+            }
+        }
+    }
+}
+
+/// 工具结果返回给模型之前脱敏（见 [`redactor::redact_output`]），有内容被替换时提示用户
+///
+/// `json` 为结果序列化后的 JSON，返回时已换成脱敏后的结果。MCP 工具的结果也经过这里。
+pub(crate) fn redact_result<O>(output: O, json: &mut serde_json::Value) -> Redacted<O> {
+    let output = redactor::redact_output(output, json);
+    if let Some(note) = output.note() {
+        verbose_tools().println(format_args!(
+            "  {} {}",
+            "🔒".bright_yellow(),
+            note.bright_yellow()
+        ));
+    }
+    output
+}
+
 impl<T: Tool> MaybeHitlTool<T> 
 where
     T::Error: From<crate::tools::FileToolError> + Send + Sync,
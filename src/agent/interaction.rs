@@ -0,0 +1,324 @@
+//! 终端交互代理
+//!
+//! 工具的权限确认和提问可能在流式输出、工具状态行刷新期间发起，也可能同时来自多个工具。
+//! 所有终端交互都通过 `interact` 排队，一次只显示一个：显示前暂停已注册的动态输出
+//! （工具状态行等），定时刷新的输出（spinner、状态栏）通过 `draw` 在提问期间跳过；
+//! 提问前后丢弃缓冲的按键，保证提问期间的输入只被提问读取，不会漏进输入框。
+//! 调用方开启了 raw mode 时提问期间暂时关闭，结束后恢复，避免方向键被回显为转义序列。
+//! stdout 或 stdin 不是终端（`oxide | tee log.txt`）时，提问期间把它们接到 `/dev/tty`，
+//! 提问和回答不会写进管道；没有可用的终端时立即返回 [`NoTerminal`]，不会卡住等待输入。
+
+use crossterm::{event, terminal};
+use once_cell::sync::Lazy;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
+
+/// 提问期间需要暂停的动态输出
+pub trait Pausable: Send + Sync {
+    fn pause(&self);
+    fn resume(&self);
+}
+
+/// 等待显示的交互（tokio 的互斥锁按申请顺序唤醒，即先到先显示）
+static QUEUE: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// 是否正在提问；`draw` 持有该锁绘制，提问开始后不会再有新的一帧写出
+static PROMPTING: Mutex<bool> = Mutex::new(false);
+
+/// 已注册的动态输出
+static PAUSABLES: Lazy<RwLock<Vec<Arc<dyn Pausable>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 注册提问期间需要暂停的动态输出
+pub fn register(pausable: Arc<dyn Pausable>) {
+    if let Ok(mut pausables) = PAUSABLES.write() {
+        pausables.push(pausable);
+    }
+}
+
+fn prompting() -> MutexGuard<'static, bool> {
+    PROMPTING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 是否正在显示交互
+pub fn is_prompting() -> bool {
+    *prompting()
+}
+
+/// 未在提问时执行一次绘制，返回是否执行
+///
+/// 定时刷新的输出（spinner、状态栏、流式文本的节拍输出）用它包住写终端的代码。
+pub fn draw<F: FnOnce()>(f: F) -> bool {
+    let prompting = prompting();
+    if *prompting {
+        return false;
+    }
+    f();
+    true
+}
+
+/// 需要用户回答，但没有可用的终端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("no terminal available to ask the user: stdin/stdout are not terminals and /dev/tty cannot be opened")]
+pub struct NoTerminal;
+
+/// 排队执行一次终端交互，返回 `prompt` 的结果
+///
+/// `prompt` 可以直接使用 inquire 或读取 stdin；多线程运行时中通过 `block_in_place` 执行，
+/// 不会占住其他任务的工作线程。
+pub async fn interact<T, F>(prompt: F) -> Result<T, NoTerminal>
+where
+    F: FnOnce() -> T,
+{
+    let _slot = QUEUE.lock().await;
+    let _tty = TtyAttachment::attach()?;
+    Ok(run_paused(prompt))
+}
+
+/// 排队输出一段不需要用户回答的内容（如拒绝说明），不会插进其他提问中间
+pub async fn exclusive<T, F>(output: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let _slot = QUEUE.lock().await;
+    run_paused(output)
+}
+
+fn run_paused<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let _pause = Pause::begin();
+    match tokio::runtime::Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(tokio::runtime::RuntimeFlavor::MultiThread) => tokio::task::block_in_place(f),
+        _ => f(),
+    }
+}
+
+/// 提问期间接到 `/dev/tty` 的 stdin/stdout，析构时恢复原来的文件描述符
+struct TtyAttachment {
+    /// (被替换的描述符, 原描述符的副本)
+    restore: Vec<(i32, i32)>,
+}
+
+impl TtyAttachment {
+    fn attach() -> Result<Self, NoTerminal> {
+        let terminals = [
+            (0, io::stdin().is_terminal()),
+            (1, io::stdout().is_terminal()),
+        ];
+        let detached: Vec<i32> = terminals
+            .into_iter()
+            .filter(|(_, is_terminal)| !is_terminal)
+            .map(|(fd, _)| fd)
+            .collect();
+        if detached.is_empty() {
+            return Ok(Self {
+                restore: Vec::new(),
+            });
+        }
+        Self::attach_tty(&detached)
+    }
+
+    #[cfg(unix)]
+    fn attach_tty(detached: &[i32]) -> Result<Self, NoTerminal> {
+        use std::os::fd::AsRawFd;
+
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|_| NoTerminal)?;
+        let _ = io::stdout().flush();
+        let mut restore = Vec::new();
+        for &fd in detached {
+            // SAFETY: 0/1 在进程内始终有效；副本在析构时换回并关闭
+            unsafe {
+                let saved = libc::dup(fd);
+                if saved < 0 {
+                    continue;
+                }
+                if libc::dup2(tty.as_raw_fd(), fd) < 0 {
+                    libc::close(saved);
+                    continue;
+                }
+                restore.push((fd, saved));
+            }
+        }
+        if restore.len() < detached.len() {
+            drop(Self { restore });
+            return Err(NoTerminal);
+        }
+        Ok(Self { restore })
+    }
+
+    #[cfg(not(unix))]
+    fn attach_tty(_detached: &[i32]) -> Result<Self, NoTerminal> {
+        Err(NoTerminal)
+    }
+}
+
+impl Drop for TtyAttachment {
+    fn drop(&mut self) {
+        if self.restore.is_empty() {
+            return;
+        }
+        let _ = io::stdout().flush();
+        #[cfg(unix)]
+        for &(fd, saved) in self.restore.iter().rev() {
+            // SAFETY: saved 是 attach 时 dup 出的副本，只在这里使用和关闭
+            unsafe {
+                libc::dup2(saved, fd);
+                libc::close(saved);
+            }
+        }
+    }
+}
+
+/// 一次提问期间的暂停状态，析构时恢复
+struct Pause {
+    /// 提问前终端处于 raw mode
+    raw_mode: bool,
+}
+
+impl Pause {
+    fn begin() -> Self {
+        *prompting() = true;
+        for pausable in PAUSABLES.read().map(|p| p.clone()).unwrap_or_default() {
+            pausable.pause();
+        }
+        if io::stdout().is_terminal() {
+            // 从干净的一行开始（清掉 spinner 等留下的内容）
+            let mut stdout = io::stdout();
+            let _ = write!(stdout, "\r\x1b[2K");
+            let _ = stdout.flush();
+        }
+        discard_pending_input();
+        let raw_mode =
+            io::stdin().is_terminal() && terminal::is_raw_mode_enabled().unwrap_or(false);
+        if raw_mode {
+            let _ = terminal::disable_raw_mode();
+        }
+        Pause { raw_mode }
+    }
+}
+
+impl Drop for Pause {
+    fn drop(&mut self) {
+        discard_pending_input();
+        if self.raw_mode {
+            let _ = terminal::enable_raw_mode();
+        }
+        for pausable in PAUSABLES.read().map(|p| p.clone()).unwrap_or_default() {
+            pausable.resume();
+        }
+        *prompting() = false;
+    }
+}
+
+/// 丢弃终端中尚未读取的按键
+fn discard_pending_input() {
+    if !io::stdin().is_terminal() {
+        return;
+    }
+    let was_raw = terminal::is_raw_mode_enabled().unwrap_or(false);
+    if !was_raw && terminal::enable_raw_mode().is_err() {
+        return;
+    }
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        if event::read().is_err() {
+            break;
+        }
+    }
+    if !was_raw {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Recorder {
+        paused: AtomicUsize,
+        resumed: AtomicUsize,
+    }
+
+    impl Pausable for Recorder {
+        fn pause(&self) {
+            self.paused.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn resume(&self) {
+            self.resumed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_prompts_are_serialized() {
+        let recorder = Arc::new(Recorder::default());
+        register(recorder.clone());
+        let transcript = Arc::new(Mutex::new(String::new()));
+
+        let requests: Vec<_> = (0..3)
+            .map(|i| {
+                let transcript = Arc::clone(&transcript);
+                let recorder = Arc::clone(&recorder);
+                tokio::spawn(async move {
+                    exclusive(move || {
+                        assert!(is_prompting());
+                        assert!(!draw(|| panic!("drawn during a prompt")));
+                        let paused = recorder.paused.load(Ordering::SeqCst);
+                        assert_eq!(paused, recorder.resumed.load(Ordering::SeqCst) + 1);
+
+                        // 逐字符写出提问和回答，交错时会被打乱
+                        for ch in format!("[confirm {}? y]", i).chars() {
+                            transcript.lock().unwrap().push(ch);
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                        i
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        let mut answers = Vec::new();
+        for request in requests {
+            answers.push(request.await.unwrap());
+        }
+        answers.sort();
+        assert_eq!(answers, vec![0, 1, 2]);
+
+        let transcript = transcript.lock().unwrap().clone();
+        let mut prompts: Vec<&str> = transcript.split_inclusive(']').collect();
+        prompts.sort();
+        assert_eq!(
+            prompts,
+            vec!["[confirm 0? y]", "[confirm 1? y]", "[confirm 2? y]"]
+        );
+
+        assert_eq!(recorder.paused.load(Ordering::SeqCst), 3);
+        assert_eq!(recorder.resumed.load(Ordering::SeqCst), 3);
+        assert!(!is_prompting());
+        assert!(draw(|| {}));
+    }
+
+    #[tokio::test]
+    async fn test_interact_fails_fast_without_terminal() {
+        let attached = io::stdin().is_terminal() && io::stdout().is_terminal();
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .is_ok();
+        let result = interact(|| 42).await;
+        if attached || (cfg!(unix) && tty) {
+            assert_eq!(result, Ok(42));
+        } else {
+            assert_eq!(result, Err(NoTerminal));
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! 重复工具调用的熔断
+//!
+//! 模型偶尔会陷入用相同参数反复调用同一个工具的循环（例如反复读取同一个文件，期望内容变化），
+//! 白白消耗轮次和费用。一轮对话中同一调用（工具名 + 规范化后的参数）超过
+//! `[tools] max_identical_calls` 次（默认 3，0 关闭）后，[`MaybeHitlTool`](super::MaybeHitlTool)
+//! 不再执行，而是返回一个错误让模型换个做法；第二次触发时由 `SessionIdHook` 结束本轮，
+//! 并告诉用户原因。
+//!
+//! 计数按会话 ID 分开保存，`oxide serve` 中并发的会话互不影响。工具调用计入最近发出
+//! 模型请求或工具调用的会话（由 `SessionIdHook` 登记，见 [`activate`]）。
+//!
+//! 轮询类的工具（如 task_output）本来就需要重复调用，不参与计数。
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::tools::settings::DEFAULT_MAX_IDENTICAL_CALLS;
+use crate::tools::{ErrorCode, ToolFailure};
+
+/// 可以合理重复调用的工具（轮询后台任务的状态和输出）
+const REPEATABLE_TOOLS: &[&str] = &["task_output", "task_get", "task_list"];
+
+/// 第几次触发熔断时结束本轮
+const TRIPS_BEFORE_STOP: usize = 2;
+
+/// 同一调用允许的次数（`[tools] max_identical_calls`）
+static MAX_IDENTICAL: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_IDENTICAL_CALLS);
+
+/// 各会话本轮的调用计数，按会话 ID 区分
+static GUARDS: Lazy<Mutex<HashMap<String, LoopGuard>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 工具调用计入的会话
+static ACTIVE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// 一次调用的检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// 正常执行
+    Allow,
+    /// 重复次数超限，不执行
+    Block,
+}
+
+/// 一轮对话中的调用计数
+#[derive(Debug, Clone)]
+pub struct LoopGuard {
+    /// 同一调用允许的次数，0 表示不限制
+    max_identical: usize,
+    counts: HashMap<u64, usize>,
+    trips: usize,
+}
+
+impl LoopGuard {
+    pub fn new(max_identical: usize) -> Self {
+        Self {
+            max_identical,
+            counts: HashMap::new(),
+            trips: 0,
+        }
+    }
+
+    /// 开始新一轮，清空计数（保留上限）
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        self.trips = 0;
+    }
+
+    /// 记录一次调用，判断是否执行
+    pub fn check(&mut self, tool_name: &str, args: &Value) -> Verdict {
+        if self.max_identical == 0 || REPEATABLE_TOOLS.contains(&tool_name) {
+            return Verdict::Allow;
+        }
+        let count = self.counts.entry(call_hash(tool_name, args)).or_insert(0);
+        *count += 1;
+        if *count > self.max_identical {
+            self.trips += 1;
+            Verdict::Block
+        } else {
+            Verdict::Allow
+        }
+    }
+
+    /// 熔断已触发两次，本轮应当结束
+    pub fn should_stop(&self) -> bool {
+        self.trips >= TRIPS_BEFORE_STOP
+    }
+}
+
+/// 工具名和规范化后参数的哈希
+fn call_hash(tool_name: &str, args: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tool_name.hash(&mut hasher);
+    normalize(args).to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 对象的键排序并去掉 null 字段，字符串去掉首尾空白，使等价的参数得到相同的哈希
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, _)| k)
+                .collect();
+            keys.sort();
+            let normalized = keys
+                .into_iter()
+                .map(|k| (k.clone(), normalize(&fields[k])))
+                .collect();
+            Value::Object(normalized)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        Value::String(s) => Value::String(s.trim().to_string()),
+        other => other.clone(),
+    }
+}
+
+fn guards() -> std::sync::MutexGuard<'static, HashMap<String, LoopGuard>> {
+    GUARDS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 设置同一调用允许的次数（`[tools] max_identical_calls`，之后开始的一轮生效）
+pub fn configure(max_identical: usize) {
+    MAX_IDENTICAL.store(max_identical, Ordering::Relaxed);
+}
+
+/// 会话开始新一轮对话，清空它上一轮的计数
+pub fn begin_turn(session_id: &str) {
+    let max_identical = MAX_IDENTICAL.load(Ordering::Relaxed);
+    let mut guards = guards();
+    let guard = guards
+        .entry(session_id.to_string())
+        .or_insert_with(|| LoopGuard::new(max_identical));
+    guard.max_identical = max_identical;
+    guard.reset();
+}
+
+/// 之后的工具调用计入该会话
+pub fn activate(session_id: &str) {
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(session_id.to_string());
+}
+
+/// 记录一次工具调用，重复次数超限时返回给模型的错误（没有进行中的一轮时不计数）
+pub fn check(tool_name: &str, args: &Value) -> Result<(), ToolFailure> {
+    let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let verdict = active
+        .and_then(|id| {
+            guards()
+                .get_mut(&id)
+                .map(|guard| guard.check(tool_name, args))
+        })
+        .unwrap_or(Verdict::Allow);
+    match verdict {
+        Verdict::Allow => Ok(()),
+        Verdict::Block => Err(ToolFailure::new(
+            ErrorCode::Blocked,
+            "Repeated identical call detected",
+        )
+        .with_suggestion(
+            "the same call already ran with these arguments; change your approach or explain why a retry is needed",
+        )),
+    }
+}
+
+/// 会话的这一轮是否因重复调用应当结束
+pub fn should_stop(session_id: &str) -> bool {
+    guards().get(session_id).is_some_and(LoopGuard::should_stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_breaker_trips_after_limit() {
+        let mut guard = LoopGuard::new(3);
+        let args = json!({"file_path": "src/lib.rs", "offset": null});
+        for _ in 0..3 {
+            assert_eq!(guard.check("read_file", &args), Verdict::Allow);
+        }
+        // null 字段和首尾空白不影响判断
+        assert_eq!(
+            guard.check("read_file", &json!({"file_path": " src/lib.rs "})),
+            Verdict::Block
+        );
+        assert!(!guard.should_stop());
+
+        // 其他调用不受影响，第二次触发后本轮应当结束
+        assert_eq!(
+            guard.check("read_file", &json!({"file_path": "src/main.rs"})),
+            Verdict::Allow
+        );
+        assert_eq!(guard.check("read_file", &args), Verdict::Block);
+        assert!(guard.should_stop());
+    }
+
+    #[test]
+    fn test_repeatable_tools_and_reset() {
+        let mut guard = LoopGuard::new(1);
+        let poll = json!({"task_id": "t1"});
+        for _ in 0..5 {
+            assert_eq!(guard.check("task_output", &poll), Verdict::Allow);
+        }
+
+        let args = json!({"command": "cargo test"});
+        assert_eq!(guard.check("shell_execute", &args), Verdict::Allow);
+        assert_eq!(guard.check("shell_execute", &args), Verdict::Block);
+        guard.reset();
+        assert_eq!(guard.check("shell_execute", &args), Verdict::Allow);
+        assert!(!guard.should_stop());
+
+        let mut disabled = LoopGuard::new(0);
+        for _ in 0..5 {
+            assert_eq!(disabled.check("shell_execute", &args), Verdict::Allow);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_sessions_count_separately() {
+        let (a, b) = ("loop-guard-test-a", "loop-guard-test-b");
+        let args = json!({"command": "cargo build"});
+        begin_turn(a);
+        begin_turn(b);
+
+        activate(a);
+        let mut allowed = 0;
+        while check("shell_execute", &args).is_ok() {
+            allowed += 1;
+            assert!(allowed < 100, "the guard never blocked");
+        }
+
+        // b 开始新一轮不清空 a 的计数，b 的相同调用也不受 a 影响
+        begin_turn(b);
+        activate(b);
+        for _ in 0..allowed {
+            assert!(check("shell_execute", &args).is_ok());
+        }
+        activate(a);
+        assert!(check("shell_execute", &args).is_err());
+        assert!(should_stop(a));
+        assert!(!should_stop(b));
+
+        begin_turn(a);
+        assert!(!should_stop(a));
+    }
+}
@@ -1,15 +1,37 @@
+pub mod advisory;
 pub mod types;
 pub mod subagent;
 pub mod builder;
+pub mod context_usage;
+pub mod prompt;
+pub mod reminder;
+pub mod replay;
+pub mod session_learning;
+pub mod turn_recap;
+pub mod turn_summary;
 pub mod hitl_gatekeeper;
 pub mod hitl_integration;
+pub mod interaction;
+pub mod loop_guard;
+pub mod tool_activity;
+pub mod tool_results;
+pub mod tool_status;
+pub mod stream_event;
+pub mod stream_recovery;
+pub mod structured_output;
+pub mod superseded_reads;
+pub mod time_box;
 pub mod workflow;
 
 pub use types::AgentType as NewAgentType;
 pub use subagent::SubagentManager;
 pub use builder::AgentBuilder;
+pub use prompt::{PromptBuilder, PromptMode, PromptPart};
+pub use reminder::ReminderTracker;
+pub use advisory::AdvisoryTracker;
+pub use time_box::TurnDeadline;
 #[allow(unused_imports)]
-pub use hitl_integration::{HitlResult, MaybeHitlTool, HitlIntegration, build_operation_context};
+pub use hitl_integration::{HitlResult, MaybeHitlTool, HitlIntegration, PermissionPolicy, PermissionPrompter, PermissionRequest, build_operation_context};
 #[allow(unused_imports)]
 pub use hitl_gatekeeper::{HitlGatekeeper, ToolCallRequest, OperationContext, HitlConfig, HitlDecision, WarningLevel};
 #[allow(unused_imports)]
@@ -0,0 +1,449 @@
+//! 系统提示词
+//!
+//! Main Agent 的提示词由固定部分（身份、工具使用策略）和模式部分组成：
+//! Normal 包含完整的规划、交互与任务管理说明，Fast 要求简洁作答，
+//! Plan 约定只读探索并通过 exit_plan_mode 提交计划。
+//! `~/.oxide/prompts/{normal,fast,plan}.md` 可以覆盖或扩展对应的模式部分。
+//! 最后是按 `[output]` 配置生成的回复风格与语言说明；会话有多个工作区根目录时再列出它们。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{OutputConfig, OutputLanguage, OutputStyle};
+use crate::tools::WorkspaceRoot;
+
+/// 身份与工具说明（所有模式共用）
+const IDENTITY: &str = r#"Your name is Oxide. You are a helpful AI code assistant with comprehensive file system and command execution access. You can read, write, edit (with patches or search/replace), and delete files, execute bash commands, scan codebase structures, search text in the codebase and create directories. Use edit_file for precise small changes with diffs. Use search_replace for block replacements where you match content rather than lines (robust to line number shifts). search_replace is preferred for modifying functions or blocks of code. Use apply_patch to apply a unified diff that spans several files: it changes all of them or none. Use run_tests instead of shell_execute to run the project's tests: it returns the failing tests and their messages. After editing code, use diagnostics to check that the project still compiles (pass path "session" to only see problems in files you changed). Use outline to list the functions and types of a large file with their line ranges before reading it. Use diff to compare two files, or a file against its version at a git ref, instead of reading both and comparing them yourself. Use deps to look up declared and locked dependency versions (with check_latest to find outdated ones) instead of reading manifests and lockfiles by hand. Use http_request rather than curl in shell_execute to call HTTP APIs. When the user asks you to remember something about the project, record it with memory; saved notes are shown to you at the start of each turn. Please provide clear responses and be careful when modifying files or executing commands."#;
+
+/// 工具使用策略（所有模式共用）
+const TOOL_USAGE: &str = r#"【Tool Usage Strategy】
+- ✅ WHEN to use tools: When users explicitly request file operations, code search, command execution, or system interactions
+- ❌ WHEN NOT to use tools: For general conversation, capability questions, or questions that can be answered directly from your knowledge
+- 🤖 Answer directly: Questions about your capabilities, features, technical concepts, or general programming questions should be answered directly without calling tools
+- 📋 Read first: ALWAYS read files before attempting to edit them to ensure you have the current content
+- 🧯 Tool errors: a failed tool returns JSON {code, message, details, retryable, suggestion}; follow the suggestion, and only repeat the same call when retryable is true"#;
+
+/// 主动进入 Plan 模式的说明
+const PLAN_MODE_GUIDANCE: &str = r#"【Plan Mode】
+Use enter_plan_mode proactively when you're about to start a non-trivial implementation task:
+- New feature implementation requiring architectural decisions
+- Multiple valid approaches exist for the task
+- Code modifications affecting existing behavior
+- Multi-file changes (more than 2-3 files)
+- Unclear requirements needing exploration
+
+In plan mode:
+1. Explore the codebase using read, grep, glob tools
+2. Design your implementation approach
+3. Use exit_plan_mode to present your plan and request user approval
+4. Only proceed with implementation after user approves
+
+Skip plan mode for simple tasks like typo fixes, single-line changes, or tasks with very specific instructions."#;
+
+/// 用户交互说明
+const USER_INTERACTION: &str = r#"【User Interaction】
+Use ask_user_question when you need to:
+- Gather user preferences or requirements during execution
+- Clarify ambiguous instructions
+- Get decisions on implementation choices
+- Offer choices about what direction to take
+Users can always select "Other" to provide custom input. Use multiSelect: true to allow multiple answers."#;
+
+/// 任务管理说明
+const TASK_MANAGEMENT: &str = r#"【Task Management】
+Use task management tools (task_create, task_update, task_list, task_get) to track progress on complex tasks:
+- Create tasks for multi-step work to show progress to the user
+- Update task status as you work (pending → in_progress → completed)
+- Use task dependencies (blocks/blocked_by) to manage task ordering
+- Mark tasks as completed when done, or deleted if no longer needed
+- Use task_stop to stop a background process task; it escalates from SIGTERM to SIGKILL after grace_ms"#;
+
+/// Fast 模式
+const FAST_MODE: &str = r#"【Fast Mode】
+The user wants quick answers:
+- Answer concisely: lead with the answer, skip preamble and recaps
+- Avoid tool use unless necessary; answer from the conversation and your knowledge when you can
+- When a change is needed, make the smallest edit that solves the problem without planning or task tracking"#;
+
+/// Plan 模式约定
+const PLAN_MODE_CONTRACT: &str = r#"【Plan Mode】
+You are in plan mode. This is a read-only session:
+- Do NOT create, edit or delete files, and do not run commands that change the system
+- Explore the codebase with read, grep, glob and outline to understand the task
+- Produce a concrete implementation plan: the files to change, the steps in order, and the risks
+- When the plan is ready, call exit_plan_mode to present it for user approval; do not start implementing"#;
+
+/// 回复风格与语言说明
+fn output_part(output: OutputConfig) -> String {
+    let style = match output.style {
+        OutputStyle::Concise => "Be concise: lead with the answer, keep explanations to a sentence or two, and do not recap what you did.",
+        OutputStyle::Detailed => "Be thorough: explain what you found, what you changed and why, and mention follow-up steps when relevant.",
+        OutputStyle::Explanatory => "Explain as you go: describe the reasoning behind your choices and the relevant concepts and trade-offs in this codebase, so the user learns from the answer.",
+    };
+    let language = match output.language {
+        OutputLanguage::Auto => "Reply in the language the user writes in.",
+        OutputLanguage::En => "Always reply in English, even if the user writes in another language.",
+        OutputLanguage::Zh => "Always reply in Simplified Chinese, even if the user writes in another language. Keep code, identifiers, commands and file paths unchanged.",
+    };
+    format!("【Output Style】\n- {}\n- {}", style, language)
+}
+
+/// 工作区根目录说明（只有一个根目录时不需要）
+fn roots_part(roots: &[WorkspaceRoot]) -> Option<String> {
+    if roots.len() < 2 {
+        return None;
+    }
+    let list: Vec<String> = roots
+        .iter()
+        .enumerate()
+        .map(|(i, root)| {
+            let note = if i == 0 { " (working directory)" } else { "" };
+            format!("- {}: {}{}", root.alias, root.path.display(), note)
+        })
+        .collect();
+    Some(format!(
+        "【Workspace Roots】\nThis session spans several directories; files outside them cannot be accessed:\n{}\n\
+         Pass root (an alias, or \"all\") to glob and grep_search to search another root. \
+         Use absolute paths for files outside the working directory.",
+        list.join("\n")
+    ))
+}
+
+/// 用户覆盖文件中代表内置模式部分的占位符
+const DEFAULT_PLACEHOLDER: &str = "{default}";
+
+/// 对话模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptMode {
+    #[default]
+    Normal,
+    Fast,
+    Plan,
+}
+
+impl PromptMode {
+    /// 所有模式（`/mode` 列表和 Tab 切换的顺序）
+    pub const ALL: [PromptMode; 3] = [PromptMode::Normal, PromptMode::Fast, PromptMode::Plan];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PromptMode::Normal => "normal",
+            PromptMode::Fast => "fast",
+            PromptMode::Plan => "plan",
+        }
+    }
+
+    /// 一行说明：模式的行为以及对工具和确认的影响
+    pub fn description(self) -> &'static str {
+        match self {
+            PromptMode::Normal => t!("cli.mode.describe_normal"),
+            PromptMode::Fast => t!("cli.mode.describe_fast"),
+            PromptMode::Plan => t!("cli.mode.describe_plan"),
+        }
+    }
+
+    /// 按 [`PromptMode::ALL`] 的顺序循环到下一个模式
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// 内置的模式部分
+    fn default_part(self) -> String {
+        match self {
+            PromptMode::Normal => {
+                [PLAN_MODE_GUIDANCE, USER_INTERACTION, TASK_MANAGEMENT].join("\n\n")
+            }
+            PromptMode::Fast => FAST_MODE.to_string(),
+            PromptMode::Plan => [PLAN_MODE_CONTRACT, USER_INTERACTION].join("\n\n"),
+        }
+    }
+}
+
+impl std::str::FromStr for PromptMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "normal" => Ok(PromptMode::Normal),
+            "fast" => Ok(PromptMode::Fast),
+            "plan" => Ok(PromptMode::Plan),
+            other => Err(format!("unknown mode: {}", other)),
+        }
+    }
+}
+
+/// 用户提示词目录（`~/.oxide/prompts`）
+pub fn user_prompts_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".oxide").join("prompts"))
+}
+
+/// 系统提示词中的一个部分（`/context` 按部分统计 token）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptPart {
+    pub name: String,
+    pub text: String,
+}
+
+impl PromptPart {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// 各部分之间的分隔
+const PART_SEPARATOR: &str = "\n\n";
+
+/// 按模式组装 Main Agent 的系统提示词
+#[derive(Debug, Clone)]
+pub struct PromptBuilder {
+    mode: PromptMode,
+    working_dir: PathBuf,
+    prompts_dir: Option<PathBuf>,
+    output: OutputConfig,
+    roots: Vec<WorkspaceRoot>,
+    addition: Option<PromptPart>,
+}
+
+impl PromptBuilder {
+    pub fn new(mode: PromptMode) -> Self {
+        Self {
+            mode,
+            working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            prompts_dir: user_prompts_dir(),
+            output: OutputConfig::load(),
+            roots: Vec::new(),
+            addition: None,
+        }
+    }
+
+    /// 设置回复风格与语言（默认读取 `[output]` 配置）
+    pub fn with_output(mut self, output: OutputConfig) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// 设置模板变量 `{working_dir}` 的值
+    pub fn with_working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = working_dir.into();
+        self
+    }
+
+    /// 设置工作区根目录（多于一个时在提示词中列出）
+    pub fn with_roots(mut self, roots: Vec<WorkspaceRoot>) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    /// 在末尾追加一个部分（如会话模板的系统提示词补充）
+    pub fn with_addition(mut self, addition: Option<PromptPart>) -> Self {
+        self.addition = addition;
+        self
+    }
+
+    /// 设置用户提示词目录（`None` 时只使用内置提示词）
+    pub fn with_prompts_dir(mut self, prompts_dir: Option<PathBuf>) -> Self {
+        self.prompts_dir = prompts_dir;
+        self
+    }
+
+    /// 组装提示词
+    ///
+    /// 用户文件 `<prompts_dir>/<mode>.md` 存在时替换内置的模式部分；
+    /// 文件中的 `{default}` 会展开为内置部分，用于在其基础上追加内容。
+    /// 支持的模板变量：`{working_dir}`、`{mode}`。
+    pub fn build(&self) -> String {
+        self.parts()
+            .iter()
+            .map(|part| part.text.as_str())
+            .collect::<Vec<_>>()
+            .join(PART_SEPARATOR)
+    }
+
+    /// 按部分返回提示词，各部分以空行连接即为 [`build`](Self::build) 的结果
+    pub fn parts(&self) -> Vec<PromptPart> {
+        let default_part = self.mode.default_part();
+        let (mode_name, mode_part) = match self.user_override() {
+            Some(content) => (
+                format!("mode: {} (~/.oxide/prompts)", self.mode.as_str()),
+                self.render(&content.replace(DEFAULT_PLACEHOLDER, &default_part)),
+            ),
+            None => (format!("mode: {}", self.mode.as_str()), default_part),
+        };
+        let mut parts = vec![
+            PromptPart::new("identity", IDENTITY),
+            PromptPart::new("tool usage", TOOL_USAGE),
+            PromptPart::new(mode_name, mode_part.trim()),
+            PromptPart::new("output style", output_part(self.output)),
+        ];
+        if let Some(roots) = roots_part(&self.roots) {
+            parts.push(PromptPart::new("workspace roots", roots));
+        }
+        parts.extend(self.addition.clone());
+        parts
+    }
+
+    fn user_override(&self) -> Option<String> {
+        let path = self
+            .prompts_dir
+            .as_deref()?
+            .join(format!("{}.md", self.mode.as_str()));
+        read_prompt_file(&path)
+    }
+
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{working_dir}", &self.working_dir.display().to_string())
+            .replace("{mode}", self.mode.as_str())
+    }
+}
+
+fn read_prompt_file(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    (!content.trim().is_empty()).then_some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn build(mode: PromptMode, prompts_dir: Option<&Path>) -> String {
+        PromptBuilder::new(mode)
+            .with_working_dir("/work/repo")
+            .with_prompts_dir(prompts_dir.map(Path::to_path_buf))
+            .with_output(OutputConfig::default())
+            .build()
+    }
+
+    #[test]
+    fn test_mode_parts() {
+        let normal = build(PromptMode::Normal, None);
+        assert!(normal.starts_with("Your name is Oxide."));
+        assert!(normal.contains("Use enter_plan_mode proactively"));
+        assert!(normal.contains("【Task Management】"));
+        assert!(!normal.contains("【Fast Mode】"));
+
+        let fast = build(PromptMode::Fast, None);
+        assert!(fast.contains("Answer concisely"));
+        assert!(fast.contains("【Tool Usage Strategy】"));
+        assert!(!fast.contains("【Task Management】"));
+        assert!(!fast.contains("enter_plan_mode"));
+
+        let plan = build(PromptMode::Plan, None);
+        assert!(plan.contains("read-only"));
+        assert!(plan.contains("call exit_plan_mode"));
+        assert!(!plan.contains("Use enter_plan_mode proactively"));
+        assert!(!plan.contains("【Task Management】"));
+    }
+
+    #[test]
+    fn test_every_mode_is_described() {
+        let mut mode = PromptMode::Normal;
+        for expected in PromptMode::ALL {
+            assert_eq!(mode, expected);
+            assert_eq!(mode.as_str().parse::<PromptMode>(), Ok(mode));
+            // 缺少翻译时 t! 原样返回键名
+            assert!(!mode.description().starts_with("cli.mode."));
+            mode = mode.next();
+        }
+        assert_eq!(mode, PromptMode::Normal);
+
+        let descriptions: std::collections::HashSet<_> =
+            PromptMode::ALL.iter().map(|m| m.description()).collect();
+        assert_eq!(descriptions.len(), PromptMode::ALL.len());
+    }
+
+    #[test]
+    fn test_user_override_wins() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("fast.md"),
+            "Reply in one sentence. Repository: {working_dir} ({mode})\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("plan.md"),
+            "{default}\n\nAlways list test cases.",
+        )
+        .unwrap();
+        fs::write(dir.path().join("normal.md"), "  \n").unwrap();
+
+        let fast = build(PromptMode::Fast, Some(dir.path()));
+        assert!(fast
+            .contains("Reply in one sentence. Repository: /work/repo (fast)\n\n【Output Style】"));
+        assert!(!fast.contains("【Fast Mode】"));
+
+        let plan = build(PromptMode::Plan, Some(dir.path()));
+        assert!(plan.contains("call exit_plan_mode"));
+        assert!(plan.contains("Always list test cases.\n\n【Output Style】"));
+
+        // 空文件不覆盖内置提示词
+        assert_eq!(
+            build(PromptMode::Normal, Some(dir.path())),
+            build(PromptMode::Normal, None)
+        );
+    }
+
+    #[test]
+    fn test_output_style_combinations() {
+        let styles = [
+            (OutputStyle::Concise, "Be concise"),
+            (OutputStyle::Detailed, "Be thorough"),
+            (OutputStyle::Explanatory, "Explain as you go"),
+        ];
+        let languages = [
+            (OutputLanguage::Auto, "language the user writes in."),
+            (OutputLanguage::En, "Always reply in English"),
+            (OutputLanguage::Zh, "Always reply in Simplified Chinese"),
+        ];
+        for (style, style_text) in styles {
+            for (language, language_text) in languages {
+                let prompt = PromptBuilder::new(PromptMode::Normal)
+                    .with_prompts_dir(None)
+                    .with_output(OutputConfig { style, language })
+                    .build();
+                let output = prompt.split("【Output Style】").nth(1).unwrap();
+                assert!(output.contains(style_text), "{style:?}/{language:?}");
+                assert!(output.contains(language_text), "{style:?}/{language:?}");
+                for (other, text) in styles {
+                    if other != style {
+                        assert!(!output.contains(text), "{style:?} contains {other:?}");
+                    }
+                }
+                for (other, text) in languages {
+                    if other != language {
+                        assert!(!output.contains(text), "{language:?} contains {other:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_workspace_roots_part() {
+        let root = |alias: &str, path: &str| WorkspaceRoot {
+            alias: alias.to_string(),
+            path: PathBuf::from(path),
+        };
+        let prompt = |roots| {
+            PromptBuilder::new(PromptMode::Normal)
+                .with_prompts_dir(None)
+                .with_roots(roots)
+                .parts()
+        };
+
+        let single = prompt(vec![root("web", "/work/web")]);
+        assert!(single.iter().all(|part| part.name != "workspace roots"));
+
+        let multi = prompt(vec![root("web", "/work/web"), root("api", "/work/api")]);
+        let part = multi.last().unwrap();
+        assert_eq!(part.name, "workspace roots");
+        assert!(part
+            .text
+            .contains("- web: /work/web (working directory)\n- api: /work/api\n"));
+    }
+}
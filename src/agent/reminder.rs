@@ -0,0 +1,430 @@
+//! 系统提醒（system-reminder）
+//!
+//! 在关键时刻给发往模型的消息附加简短的 `<system-reminder>` 块：Plan 模式下的每一轮、
+//! 距离上一轮超过设定时间时附在用户消息前；工具调用违反规则时附在该调用的结果上
+//! （见 [`attach`]），模型在本轮接下来的请求中就能看到。
+//! 提醒只随请求发送，不写入会话记录（会话记录不保存工具结果），因此不会出现在 `/export` 中，
+//! 也不计入会话的 token 估算。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{BehaviorConfig, ConfigLoader};
+use crate::tools::{ErrorCode, FileToolError, ToolFailure};
+
+/// 修改文件的工具，Plan 模式下不应调用
+const WRITE_TOOLS: &[&str] = &[
+    "write_file",
+    "edit_file",
+    "apply_patch",
+    "search_replace",
+    "multi_edit",
+    "notebook_edit",
+    "delete_file",
+    "create_directory",
+];
+
+/// 权限类错误消息的前缀（见 `FileToolError::PermissionDenied`）
+const PERMISSION_DENIED: &str = "Permission denied: ";
+
+/// 违规说明的最大长度（字符）
+const MAX_DETAIL_CHARS: usize = 200;
+
+/// 一条系统提醒
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reminder {
+    /// 当前处于 Plan 模式
+    PlanMode,
+    /// 工具调用违反了规则
+    RuleViolation { tool: String, detail: String },
+    /// 距离上一轮已经过去较长时间
+    IdleGap { minutes: u64 },
+}
+
+impl Reminder {
+    pub fn text(&self) -> String {
+        match self {
+            Reminder::PlanMode => "You are in plan mode: do not modify files or run commands that change the system. Explore the codebase and present your plan with exit_plan_mode.".to_string(),
+            Reminder::RuleViolation { tool, detail } => format!(
+                "This {} call broke a rule: {}. Do not retry it the same way; change your approach or ask the user.",
+                tool, detail
+            ),
+            Reminder::IdleGap { minutes } => format!(
+                "{} minutes have passed since the last turn. The user may have changed files in the meantime; re-read files before editing them.",
+                minutes
+            ),
+        }
+    }
+}
+
+/// 把提醒渲染为 `<system-reminder>` 块（没有提醒时返回 `None`）
+pub fn render(reminders: &[Reminder]) -> Option<String> {
+    if reminders.is_empty() {
+        return None;
+    }
+    let blocks: Vec<String> = reminders
+        .iter()
+        .map(|reminder| format!("<system-reminder>\n{}\n</system-reminder>", reminder.text()))
+        .collect();
+    Some(blocks.join("\n"))
+}
+
+/// 在发送给模型的消息前插入提醒
+pub fn with_reminders(reminders: &[Reminder], input: &str) -> String {
+    match render(reminders) {
+        Some(blocks) => format!("{}\n\n{}", blocks, input),
+        None => input.to_string(),
+    }
+}
+
+/// 检查一次工具调用是否违反规则
+pub fn detect_violation(tool: &str, result: &str, plan_mode: bool) -> Option<Reminder> {
+    if plan_mode && WRITE_TOOLS.contains(&tool) {
+        return Some(Reminder::RuleViolation {
+            tool: tool.to_string(),
+            detail: "file-modifying tools must not be used in plan mode".to_string(),
+        });
+    }
+    // oxide 工具返回结构化错误，其他工具（如 MCP）返回纯文本
+    let message = match ToolFailure::parse(result) {
+        Some(failure) if failure.code == ErrorCode::PermissionDenied => failure.message,
+        Some(_) => return None,
+        None => result.split_once(PERMISSION_DENIED)?.1.to_string(),
+    };
+    let detail = message.strip_prefix(PERMISSION_DENIED).unwrap_or(&message);
+    let detail = detail.lines().next().unwrap_or("").trim();
+    Some(Reminder::RuleViolation {
+        tool: tool.to_string(),
+        detail: format!(
+            "permission denied ({})",
+            detail.chars().take(MAX_DETAIL_CHARS).collect::<String>()
+        ),
+    })
+}
+
+/// 附带系统提醒的工具结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Reminded<O> {
+    Plain(O),
+    WithReminder { result: O, system_reminder: String },
+}
+
+/// 正在进行的一轮的追踪器（见 [`ReminderTracker::activate`]）
+///
+/// 是进程级状态，`oxide serve` 中并发的会话以最近发出请求的一轮为准。
+static ACTIVE: Lazy<Mutex<Option<ReminderTracker>>> = Lazy::new(|| Mutex::new(None));
+
+/// 工具调用违反规则时把提醒附在它的结果上
+///
+/// 成功的结果包成 `{ result, system_reminder }`，失败的结果在结构化错误中加上
+/// `system_reminder` 字段。没有正在进行的一轮时结果不变。
+pub fn attach<O, E>(tool: &str, result: Result<O, E>) -> Result<Reminded<O>, E>
+where
+    O: Serialize,
+    E: Display + From<FileToolError>,
+{
+    let tracker = ACTIVE.lock().ok().and_then(|active| active.clone());
+    attach_with(tracker.as_ref(), tool, result)
+}
+
+fn attach_with<O, E>(
+    tracker: Option<&ReminderTracker>,
+    tool: &str,
+    result: Result<O, E>,
+) -> Result<Reminded<O>, E>
+where
+    O: Serialize,
+    E: Display + From<FileToolError>,
+{
+    let Some(tracker) = tracker else {
+        return result.map(Reminded::Plain);
+    };
+    match result {
+        Ok(output) => {
+            let text = serde_json::to_string(&output).unwrap_or_default();
+            Ok(match tracker.record_tool_result(tool, &text) {
+                Some(reminder) => Reminded::WithReminder {
+                    result: output,
+                    system_reminder: render(&[reminder]).unwrap_or_default(),
+                },
+                None => Reminded::Plain(output),
+            })
+        }
+        Err(error) => {
+            let text = error.to_string();
+            let Some(reminder) = tracker.record_tool_result(tool, &text) else {
+                return Err(error);
+            };
+            let failure = ToolFailure::parse(&text)
+                .unwrap_or_else(|| ToolFailure::new(ErrorCode::PermissionDenied, text))
+                .with_system_reminder(render(&[reminder]).unwrap_or_default());
+            Err(FileToolError::from(failure).into())
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    plan_mode: bool,
+    last_activity: Option<Instant>,
+    /// 本轮已经提醒过的违规
+    reported: Vec<Reminder>,
+}
+
+/// 跨轮次追踪生成提醒所需的状态
+///
+/// 违规提醒在工具调用所在的一轮中附到结果上，同一违规每轮只提醒一次。
+#[derive(Debug, Clone)]
+pub struct ReminderTracker {
+    state: Arc<Mutex<TrackerState>>,
+    /// 超过该间隔时提醒重新读取文件（`None` 表示关闭）
+    idle_gap: Option<Duration>,
+}
+
+impl ReminderTracker {
+    pub fn new(idle_gap: Option<Duration>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TrackerState::default())),
+            idle_gap,
+        }
+    }
+
+    /// 按 `[behavior] idle_reminder_minutes` 创建（0 表示关闭）
+    pub fn from_config() -> Self {
+        let behavior: BehaviorConfig = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.behavior)
+            .unwrap_or_default();
+        let minutes = behavior.idle_reminder_minutes;
+        Self::new((minutes > 0).then(|| Duration::from_secs(minutes * 60)))
+    }
+
+    /// 开始新的一轮，返回需要附加到本轮消息上的提醒
+    pub fn begin_turn(&self, plan_mode: bool) -> Vec<Reminder> {
+        self.begin_turn_at(plan_mode, Instant::now())
+    }
+
+    fn begin_turn_at(&self, plan_mode: bool, now: Instant) -> Vec<Reminder> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let mut reminders = Vec::new();
+        if plan_mode {
+            reminders.push(Reminder::PlanMode);
+        }
+        if let (Some(gap), Some(last)) = (self.idle_gap, state.last_activity) {
+            let elapsed = now.saturating_duration_since(last);
+            if elapsed >= gap {
+                reminders.push(Reminder::IdleGap {
+                    minutes: elapsed.as_secs() / 60,
+                });
+            }
+        }
+        state.reported.clear();
+        state.plan_mode = plan_mode;
+        state.last_activity = Some(now);
+        reminders
+    }
+
+    /// 本轮结束（空闲时间从这里开始计算）
+    pub fn end_turn(&self) {
+        self.end_turn_at(Instant::now());
+        if let Ok(mut active) = ACTIVE.lock() {
+            if active
+                .as_ref()
+                .is_some_and(|tracker| Arc::ptr_eq(&tracker.state, &self.state))
+            {
+                *active = None;
+            }
+        }
+    }
+
+    /// 之后的工具结果由这个追踪器检查（每次模型请求开始时由 `SessionIdHook` 调用）
+    pub fn activate(&self) {
+        if let Ok(mut active) = ACTIVE.lock() {
+            *active = Some(self.clone());
+        }
+    }
+
+    fn end_turn_at(&self, now: Instant) {
+        if let Ok(mut state) = self.state.lock() {
+            state.last_activity = Some(now);
+        }
+    }
+
+    /// 记录工具结果，违反规则且本轮还没有提醒过时返回提醒
+    pub fn record_tool_result(&self, tool: &str, result: &str) -> Option<Reminder> {
+        let mut state = self.state.lock().ok()?;
+        let violation = detect_violation(tool, result, state.plan_mode)?;
+        if state.reported.contains(&violation) {
+            return None;
+        }
+        state.reported.push(violation.clone());
+        Some(violation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAP: Duration = Duration::from_secs(30 * 60);
+
+    #[test]
+    fn test_plan_mode_reminder_every_turn() {
+        let tracker = ReminderTracker::new(Some(GAP));
+        let start = Instant::now();
+        assert_eq!(tracker.begin_turn_at(true, start), vec![Reminder::PlanMode]);
+        tracker.end_turn_at(start + Duration::from_secs(60));
+        assert_eq!(
+            tracker.begin_turn_at(true, start + Duration::from_secs(120)),
+            vec![Reminder::PlanMode]
+        );
+        assert!(tracker
+            .begin_turn_at(false, start + Duration::from_secs(180))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_idle_gap_counts_from_end_of_turn() {
+        let tracker = ReminderTracker::new(Some(GAP));
+        let start = Instant::now();
+        // 第一轮没有上一轮，不提醒
+        assert!(tracker.begin_turn_at(false, start).is_empty());
+        // 一轮本身跑了 40 分钟，结束后马上提问不算空闲
+        let end = start + Duration::from_secs(40 * 60);
+        tracker.end_turn_at(end);
+        assert!(tracker
+            .begin_turn_at(false, end + Duration::from_secs(60))
+            .is_empty());
+
+        tracker.end_turn_at(end + Duration::from_secs(120));
+        let later = end + Duration::from_secs(120 + 45 * 60);
+        assert_eq!(
+            tracker.begin_turn_at(true, later),
+            vec![Reminder::PlanMode, Reminder::IdleGap { minutes: 45 }]
+        );
+
+        let disabled = ReminderTracker::new(None);
+        disabled.begin_turn_at(false, start);
+        assert!(disabled.begin_turn_at(false, later).is_empty());
+    }
+
+    #[test]
+    fn test_violations_are_reported_once_per_turn() {
+        let tracker = ReminderTracker::new(Some(GAP));
+        let start = Instant::now();
+        tracker.begin_turn_at(true, start);
+        assert_eq!(
+            tracker.record_tool_result("read_file", "fn main() {}"),
+            None
+        );
+        let violation = tracker.record_tool_result("write_file", "Wrote 12 bytes");
+        assert!(matches!(
+            &violation,
+            Some(Reminder::RuleViolation { tool, .. }) if tool == "write_file"
+        ));
+        assert_eq!(
+            tracker.record_tool_result("write_file", "Wrote 3 bytes"),
+            None
+        );
+        // 违规不再排队到下一轮
+        assert!(!tracker
+            .begin_turn_at(false, start + Duration::from_secs(60))
+            .iter()
+            .any(|reminder| matches!(reminder, Reminder::RuleViolation { .. })));
+
+        // 退出 Plan 模式后写文件不算违规，权限错误仍然提醒
+        assert_eq!(
+            tracker.record_tool_result("write_file", "Wrote 12 bytes"),
+            None
+        );
+        assert_eq!(
+            tracker.record_tool_result(
+                "read_file",
+                "Toolset error: Permission denied: .env (matches sensitive path pattern '.env')",
+            ),
+            Some(Reminder::RuleViolation {
+                tool: "read_file".to_string(),
+                detail: "permission denied (.env (matches sensitive path pattern '.env'))"
+                    .to_string(),
+            })
+        );
+
+        // 结构化错误按 code 判断，其他错误码不算违规
+        tracker.begin_turn_at(false, start + Duration::from_secs(120));
+        let denied = ToolFailure::new(
+            ErrorCode::PermissionDenied,
+            "Permission denied: /etc/shadow",
+        );
+        assert_eq!(
+            tracker.record_tool_result("read_file", &format!("ToolCallError: {}", denied)),
+            Some(Reminder::RuleViolation {
+                tool: "read_file".to_string(),
+                detail: "permission denied (/etc/shadow)".to_string(),
+            })
+        );
+        let stale = ToolFailure::new(ErrorCode::Stale, "Permission denied: in a message");
+        assert_eq!(
+            tracker.record_tool_result("edit_file", &format!("ToolCallError: {}", stale)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_violation_is_attached_to_the_offending_result() {
+        let tracker = ReminderTracker::new(None);
+        tracker.begin_turn_at(true, Instant::now());
+
+        // Plan 模式下写文件：提醒附在本次调用的结果上
+        let written = attach_with(
+            Some(&tracker),
+            "write_file",
+            Ok::<_, FileToolError>(serde_json::json!({ "success": true })),
+        )
+        .unwrap();
+        let value = serde_json::to_value(&written).unwrap();
+        assert_eq!(value["result"]["success"], true);
+        let reminder = value["system_reminder"].as_str().unwrap();
+        assert!(reminder.starts_with("<system-reminder>\nThis write_file call broke a rule"));
+
+        // 权限错误：提醒加在结构化错误中，错误码和消息不变
+        let denied: Result<(), FileToolError> =
+            Err(FileToolError::PermissionDenied(".env".to_string()));
+        let error = attach_with(Some(&tracker), "read_file", denied).unwrap_err();
+        let failure = error.failure();
+        assert_eq!(failure.code, ErrorCode::PermissionDenied);
+        assert_eq!(failure.message, "Permission denied: .env");
+        assert!(failure
+            .system_reminder
+            .unwrap()
+            .contains("This read_file call broke a rule: permission denied (.env)"));
+
+        // 普通结果和没有进行中的一轮时不变
+        let read = attach_with(
+            Some(&tracker),
+            "read_file",
+            Ok::<_, FileToolError>("fn main() {}"),
+        );
+        assert_eq!(read.unwrap(), Reminded::Plain("fn main() {}"));
+        let untracked = attach_with(None, "write_file", Ok::<_, FileToolError>("ok"));
+        assert_eq!(untracked.unwrap(), Reminded::Plain("ok"));
+    }
+
+    #[test]
+    fn test_with_reminders_marks_blocks() {
+        assert_eq!(with_reminders(&[], "hello"), "hello");
+        let message = with_reminders(
+            &[Reminder::PlanMode, Reminder::IdleGap { minutes: 5 }],
+            "hello",
+        );
+        assert!(message.starts_with("<system-reminder>\nYou are in plan mode"));
+        assert_eq!(message.matches("</system-reminder>").count(), 2);
+        assert!(message.ends_with("</system-reminder>\n\nhello"));
+    }
+}
@@ -0,0 +1,535 @@
+//! 录制与回放 Agent 运行
+//!
+//! `--record <file>` 把每轮发送的请求（本轮输入和历史）、事件流、工具调用的输入输出和最终回复
+//! 逐行写入 JSONL 录制文件。录制文件用于附在问题报告中，写入前总是脱敏密钥（不受 `redact_secrets` 影响）。
+//!
+//! `--replay <file>` 用 [`ReplayProvider`] 代替服务商：每轮按顺序返回录制的事件流，不访问网络。
+//! 本轮的请求与录制不一致（代码改动后发送了不同的内容、录制已用完）时以 [`ReplayError::Divergence`] 结束本轮。
+//! 录制中的工具调用默认重新执行，输出与录制不同时同样视为分歧；`--replay-tools` 直接使用录制的结果，
+//! 不执行任何工具。
+//!
+//! 回放在整轮的层面进行：模型请求和工具调用的顺序来自录制，而不是由 Agent 循环重新决定。
+
+use futures::stream::{self, Stream};
+use rig::agent::FinalResponse;
+use rig::completion::Message;
+use rig::tool::ToolDyn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::stream_event::{StreamEvent, TurnStream};
+use crate::tools::redactor::redact;
+
+/// 录制文件格式版本
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+static PROVIDER: OnceLock<ReplayProvider> = OnceLock::new();
+
+/// 录制文件中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayEntry {
+    /// 文件头
+    Header { version: u32, provider: String },
+    /// 一轮请求开始：本轮输入和之前的历史
+    Request { prompt: String, history: Value },
+    /// 本轮发出的事件
+    Event { event: StreamEvent },
+    /// 一次工具调用的参数和结果
+    ToolResult {
+        name: String,
+        args: String,
+        output: String,
+    },
+    /// 本轮的最终回复（rig 的 `FinalResponse`）
+    Response { response: Value },
+}
+
+/// 读取录制或回放时的错误
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Failed to read replay file {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Invalid replay file at line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("Unsupported replay format version {0} (expected {REPLAY_FORMAT_VERSION})")]
+    Version(u32),
+
+    #[error("Replay diverged from the recording: {0}")]
+    Divergence(String),
+}
+
+/// 写入录制文件（克隆出的 Recorder 写同一个文件）
+#[derive(Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    /// 创建录制文件并写入文件头，已存在时覆盖
+    pub fn create(path: &Path, provider: &str) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let recorder = Self {
+            file: Arc::new(Mutex::new(File::create(path)?)),
+        };
+        recorder.write(&ReplayEntry::Header {
+            version: REPLAY_FORMAT_VERSION,
+            provider: provider.to_string(),
+        });
+        Ok(recorder)
+    }
+
+    /// 记录一轮请求
+    pub fn request(&self, prompt: &str, history: &[Message]) {
+        self.write(&ReplayEntry::Request {
+            prompt: prompt.to_string(),
+            history: serde_json::to_value(history).unwrap_or(Value::Null),
+        });
+    }
+
+    /// 记录一个事件
+    pub fn event(&self, event: &StreamEvent) {
+        self.write(&ReplayEntry::Event {
+            event: event.clone(),
+        });
+    }
+
+    /// 记录一次工具调用
+    pub fn tool_result(&self, name: &str, args: &str, output: &str) {
+        self.write(&ReplayEntry::ToolResult {
+            name: name.to_string(),
+            args: args.to_string(),
+            output: output.to_string(),
+        });
+    }
+
+    /// 记录本轮的最终回复
+    pub fn response(&self, response: &FinalResponse) {
+        self.write(&ReplayEntry::Response {
+            response: serde_json::to_value(response).unwrap_or(Value::Null),
+        });
+    }
+
+    /// 脱敏后写入一行；写入失败只记录日志，不影响会话
+    fn write(&self, entry: &ReplayEntry) {
+        let line = serde_json::to_value(entry)
+            .map(redact_value)
+            .and_then(|value| serde_json::to_string(&value));
+        let result = match (line, self.file.lock()) {
+            (Ok(line), Ok(mut file)) => writeln!(file, "{}", line),
+            (Err(e), _) => Err(io::Error::other(e)),
+            (_, Err(_)) => Err(io::Error::other("replay recorder lock poisoned")),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to write replay entry: {}", e);
+        }
+    }
+}
+
+/// 录制中的一轮
+#[derive(Debug, Clone)]
+struct RecordedTurn {
+    prompt: String,
+    history: Value,
+    steps: Vec<ReplayStep>,
+    response: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+enum ReplayStep {
+    Event(StreamEvent),
+    ToolResult {
+        name: String,
+        args: String,
+        output: String,
+    },
+}
+
+/// 按顺序返回录制内容的服务商
+pub struct ReplayProvider {
+    turns: Mutex<VecDeque<RecordedTurn>>,
+    /// 录制的总轮数（用于分歧说明中的轮次）
+    total: usize,
+    /// 重新执行工具调用时使用的工具，为 `None` 时直接使用录制的结果
+    tools: Option<Arc<HashMap<String, Box<dyn ToolDyn>>>>,
+}
+
+impl ReplayProvider {
+    /// 读取录制文件
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let content = fs::read_to_string(path).map_err(|source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&content)
+    }
+
+    fn parse(content: &str) -> Result<Self, ReplayError> {
+        let mut turns: Vec<RecordedTurn> = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line_number = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: ReplayEntry =
+                serde_json::from_str(line).map_err(|e| ReplayError::Parse {
+                    line: line_number,
+                    message: e.to_string(),
+                })?;
+            let step = match entry {
+                ReplayEntry::Header { version, .. } if version != REPLAY_FORMAT_VERSION => {
+                    return Err(ReplayError::Version(version));
+                }
+                ReplayEntry::Header { .. } => continue,
+                ReplayEntry::Request { prompt, history } => {
+                    turns.push(RecordedTurn {
+                        prompt,
+                        history,
+                        steps: Vec::new(),
+                        response: None,
+                    });
+                    continue;
+                }
+                ReplayEntry::Response { response } => {
+                    if let Some(turn) = turns.last_mut() {
+                        turn.response = Some(response);
+                    }
+                    continue;
+                }
+                ReplayEntry::Event { event } => ReplayStep::Event(event),
+                ReplayEntry::ToolResult { name, args, output } => {
+                    ReplayStep::ToolResult { name, args, output }
+                }
+            };
+            let turn = turns.last_mut().ok_or_else(|| ReplayError::Parse {
+                line: line_number,
+                message: "entry before the first request".to_string(),
+            })?;
+            turn.steps.push(step);
+        }
+        Ok(Self {
+            total: turns.len(),
+            turns: Mutex::new(turns.into()),
+            tools: None,
+        })
+    }
+
+    /// 回放时重新执行录制中的工具调用（不设置时使用录制的结果）
+    pub fn with_tools(mut self, tools: Vec<Box<dyn ToolDyn>>) -> Self {
+        let tools = tools.into_iter().map(|tool| (tool.name(), tool)).collect();
+        self.tools = Some(Arc::new(tools));
+        self
+    }
+
+    /// 尚未回放的轮数
+    pub fn remaining(&self) -> usize {
+        self.turns.lock().map(|turns| turns.len()).unwrap_or(0)
+    }
+
+    /// 回放下一轮：请求与录制一致时按顺序发出录制的事件，否则发出分歧错误
+    ///
+    /// 设置了 `recorder` 时回放的内容同样写入录制文件，与原录制逐字节相同。
+    pub fn stream_turn(
+        &self,
+        prompt: &str,
+        history: &[Message],
+        recorder: Option<Recorder>,
+    ) -> TurnStream {
+        if let Some(recorder) = &recorder {
+            recorder.request(prompt, history);
+        }
+        let stream = match self.next_turn(prompt, history) {
+            Ok((number, turn)) => {
+                let response = turn
+                    .response
+                    .and_then(|response| serde_json::from_value(response).ok())
+                    .unwrap_or_else(FinalResponse::empty);
+                let events = replay_steps(number, turn.steps, self.tools.clone(), recorder.clone());
+                TurnStream::replayed(Box::pin(events), response)
+            }
+            Err(e) => TurnStream::replayed(
+                stream::iter(vec![StreamEvent::Error {
+                    message: e.to_string(),
+                }]),
+                FinalResponse::empty(),
+            ),
+        };
+        match recorder {
+            Some(recorder) => stream.with_recorder(recorder),
+            None => stream,
+        }
+    }
+
+    /// 取出下一轮并检查请求是否一致，返回轮次（从 1 开始）
+    fn next_turn(
+        &self,
+        prompt: &str,
+        history: &[Message],
+    ) -> Result<(usize, RecordedTurn), ReplayError> {
+        let mut turns = self
+            .turns
+            .lock()
+            .map_err(|_| ReplayError::Divergence("replay state lock poisoned".to_string()))?;
+        let number = self.total - turns.len() + 1;
+        let turn = turns.pop_front().ok_or_else(|| {
+            ReplayError::Divergence(format!(
+                "turn {} was requested but the recording has only {} turn(s)",
+                number, self.total
+            ))
+        })?;
+        if redact(prompt).text != turn.prompt {
+            return Err(ReplayError::Divergence(format!(
+                "turn {} sent a different prompt than recorded",
+                number
+            )));
+        }
+        let history = redact_value(serde_json::to_value(history).unwrap_or(Value::Null));
+        if history != turn.history {
+            let recorded = turn.history.as_array().map_or(0, Vec::len);
+            return Err(ReplayError::Divergence(format!(
+                "turn {} sent a different history than recorded ({} messages, recorded {})",
+                number,
+                history.as_array().map_or(0, Vec::len),
+                recorded
+            )));
+        }
+        Ok((number, turn))
+    }
+}
+
+/// 按顺序发出录制的事件，遇到工具调用时按需重新执行并比较结果
+fn replay_steps(
+    number: usize,
+    steps: Vec<ReplayStep>,
+    tools: Option<Arc<HashMap<String, Box<dyn ToolDyn>>>>,
+    recorder: Option<Recorder>,
+) -> impl Stream<Item = StreamEvent> + Send {
+    stream::unfold(Some(steps.into_iter()), move |steps| {
+        let tools = tools.clone();
+        let recorder = recorder.clone();
+        async move {
+            let mut steps = steps?;
+            loop {
+                match steps.next()? {
+                    ReplayStep::Event(event) => return Some((event, Some(steps))),
+                    ReplayStep::ToolResult { name, args, output } => {
+                        let output = match &tools {
+                            Some(tools) => {
+                                let actual = run_tool(tools, &name, &args).await;
+                                if actual != output {
+                                    let message = ReplayError::Divergence(format!(
+                                        "turn {}: tool `{}` returned a different result than recorded",
+                                        number, name
+                                    ))
+                                    .to_string();
+                                    return Some((StreamEvent::Error { message }, None));
+                                }
+                                actual
+                            }
+                            None => output,
+                        };
+                        if let Some(recorder) = &recorder {
+                            recorder.tool_result(&name, &args, &output);
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// 执行一次工具调用，返回与录制时相同形式（脱敏后）的结果
+async fn run_tool(tools: &HashMap<String, Box<dyn ToolDyn>>, name: &str, args: &str) -> String {
+    let output = match tools.get(name) {
+        Some(tool) => match tool.call(args.to_string()).await {
+            Ok(output) => output,
+            Err(e) => e.to_string(),
+        },
+        None => format!("tool `{}` is not available", name),
+    };
+    redact(&output).text
+}
+
+/// 对 JSON 中的所有字符串脱敏
+fn redact_value(value: Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redact(&text).text),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact_value).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, redact_value(value)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// 开始录制（`--record`），之后每轮对话都写入录制文件
+pub fn start_recording(recorder: Recorder) {
+    let _ = RECORDER.set(recorder);
+}
+
+/// 正在录制时返回录制文件
+pub fn recorder() -> Option<Recorder> {
+    RECORDER.get().cloned()
+}
+
+/// 进入回放模式（`--replay`），之后每轮对话都由录制内容回答
+pub fn start_replay(provider: ReplayProvider) {
+    let _ = PROVIDER.set(provider);
+}
+
+/// 回放模式下返回回放的服务商
+pub fn provider() -> Option<&'static ReplayProvider> {
+    PROVIDER.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::turn_summary::StopReason;
+    use futures::StreamExt;
+    use rig::agent::MultiTurnStreamItem;
+    use rig::completion::message::{AssistantContent, Text, ToolResultContent, UserContent};
+    use rig::streaming::{StreamedAssistantContent, StreamedUserContent};
+    use rig::OneOrMany;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    type Item = Result<MultiTurnStreamItem<()>, String>;
+
+    fn text(s: &str) -> Item {
+        Ok(MultiTurnStreamItem::StreamAssistantItem(
+            StreamedAssistantContent::Text(Text {
+                text: s.to_string(),
+            }),
+        ))
+    }
+
+    fn tool_call(id: &str) -> Item {
+        let AssistantContent::ToolCall(call) =
+            AssistantContent::tool_call(id, "read_file", json!({ "file_path": ".env" }))
+        else {
+            unreachable!();
+        };
+        Ok(MultiTurnStreamItem::StreamAssistantItem(
+            StreamedAssistantContent::ToolCall(call),
+        ))
+    }
+
+    fn tool_result(id: &str) -> Item {
+        let UserContent::ToolResult(result) =
+            UserContent::tool_result(id, OneOrMany::one(ToolResultContent::text(SECRET_FILE)))
+        else {
+            unreachable!();
+        };
+        Ok(MultiTurnStreamItem::StreamUserItem(
+            StreamedUserContent::ToolResult(result),
+        ))
+    }
+
+    const SECRET_FILE: &str = "OPENAI_API_KEY=sk-proj-abcdefghijklmnopqrstuvwxyz012345";
+
+    async fn drain(stream: &mut TurnStream) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        stream.take_final_response();
+        events
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let recorded = temp_dir.path().join("session.jsonl");
+        let replayed = temp_dir.path().join("replayed.jsonl");
+        let history = vec![Message::user("earlier question")];
+
+        // 模拟服务商：读取文件后回答；工具结果由 hook 录制，时机与真实的 Agent 循环相同
+        let recorder = Recorder::create(&recorded, "mock").unwrap();
+        recorder.request("what is in .env?", &history);
+        let hook = recorder.clone();
+        let items = futures::stream::iter(vec![
+            text("Let me look."),
+            tool_call("toolu_1"),
+            tool_result("toolu_1"),
+            text("It holds an API key."),
+            Ok(MultiTurnStreamItem::FinalResponse(FinalResponse::empty())),
+        ])
+        .inspect(move |item| {
+            if let Ok(MultiTurnStreamItem::StreamUserItem(_)) = item {
+                hook.tool_result("read_file", r#"{"file_path":".env"}"#, SECRET_FILE);
+            }
+        });
+        let mut live = TurnStream::new(items).with_recorder(recorder.clone());
+        let live_events = drain(&mut live).await;
+        assert_eq!(live_events.len(), 10);
+        assert_eq!(
+            live_events[5],
+            StreamEvent::MessageStop {
+                stop_reason: StopReason::ToolUse
+            }
+        );
+
+        let content = fs::read_to_string(&recorded).unwrap();
+        assert!(!content.contains("sk-proj-"));
+        assert!(content.contains("[REDACTED:openai_api_key]"));
+
+        // 回放不访问服务商也不执行工具，再次录制的文件与原录制逐字节相同
+        let provider = ReplayProvider::load(&recorded).unwrap();
+        let recorder = Recorder::create(&replayed, "mock").unwrap();
+        let mut replay = provider.stream_turn("what is in .env?", &history, Some(recorder));
+        assert_eq!(drain(&mut replay).await, live_events);
+        assert_eq!(provider.remaining(), 0);
+        assert_eq!(fs::read(&replayed).unwrap(), fs::read(&recorded).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_replay_fails_on_divergence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.jsonl");
+        let recorder = Recorder::create(&path, "mock").unwrap();
+        recorder.request("first", &[]);
+        recorder.event(&StreamEvent::MessageStart);
+
+        let provider = ReplayProvider::load(&path).unwrap();
+        let events = drain(&mut provider.stream_turn("changed", &[], None)).await;
+        assert_eq!(events.len(), 1);
+        let StreamEvent::Error { message } = &events[0] else {
+            panic!("expected a divergence error");
+        };
+        assert!(message.contains("turn 1 sent a different prompt"));
+
+        // 录制用完后的请求同样是分歧
+        let events = drain(&mut provider.stream_turn("first", &[], None)).await;
+        assert!(
+            matches!(&events[0], StreamEvent::Error { message } if message.contains("only 1 turn"))
+        );
+
+        fs::write(
+            &path,
+            "{\"kind\":\"event\",\"event\":{\"type\":\"message_start\"}}\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            ReplayProvider::load(&path),
+            Err(ReplayError::Parse { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+//! 会话结束时整理项目笔记
+//!
+//! 开启 `[behavior] session_learning` 后，退出会话（或运行 `/save-notes`）时再发一次模型请求，
+//! 从对话中整理出最多 `session_learning_max_facts` 条跨会话有效、只属于本项目的事实。
+//! 请求中附带现有的项目记忆，避免重复；用户在清单中确认或修改后，
+//! 由 [`MemoryStore::append_learnings`](crate::tools::memory::MemoryStore::append_learnings)
+//! 写入当天的 learnings 部分。非交互运行时不整理。
+
+use crate::config::{BehaviorConfig, ConfigLoader};
+
+/// 回复中表示没有可记录事实的标记
+const NO_FACTS: &str = "NONE";
+
+/// 会话整理设置
+#[derive(Debug, Clone, Copy)]
+pub struct SessionLearning {
+    /// 退出会话时整理（`/save-notes` 不受影响）
+    pub on_exit: bool,
+    /// 每次最多整理的事实数
+    pub max_facts: usize,
+}
+
+impl SessionLearning {
+    /// 按 `[behavior] session_learning` 创建
+    pub fn from_config() -> Self {
+        let behavior: BehaviorConfig = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.behavior)
+            .unwrap_or_default();
+        Self {
+            on_exit: behavior.session_learning,
+            max_facts: behavior.session_learning_max_facts.max(1),
+        }
+    }
+}
+
+/// 发给模型的整理请求，`memory` 为现有的项目记忆文件内容
+pub fn extraction_prompt(memory: &str, max_facts: usize) -> String {
+    let mut prompt = format!(
+        "The session is ending. Do not call any tools. From this conversation, list at most {} durable facts \
+         that are specific to this project and will still be useful in future sessions: build and test commands, \
+         conventions, architecture decisions, gotchas. Skip anything temporary, about this task only, generic \
+         programming knowledge, or already in the project memory below. \
+         Write one fact per line starting with \"- \", each a short self-contained sentence. \
+         If there is nothing worth keeping, reply with {} only.",
+        max_facts, NO_FACTS
+    );
+    let memory = memory.trim();
+    prompt.push_str("\n\nCurrent project memory:\n");
+    if memory.is_empty() {
+        prompt.push_str("(empty)\n");
+    } else {
+        prompt.push_str("<project-memory>\n");
+        prompt.push_str(memory);
+        prompt.push_str("\n</project-memory>\n");
+    }
+    prompt
+}
+
+/// 从回复中取出事实（列表项），最多 `max_facts` 条
+pub fn parse_facts(reply: &str, max_facts: usize) -> Vec<String> {
+    reply
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            let fact = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .or_else(|| {
+                    let (number, rest) = line.split_once(". ")?;
+                    number.chars().all(|c| c.is_ascii_digit()).then_some(rest)
+                })?;
+            let fact = fact.trim();
+            (!fact.is_empty() && !fact.eq_ignore_ascii_case(NO_FACTS)).then(|| fact.to_string())
+        })
+        .take(max_facts)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extraction_prompt_includes_memory() {
+        let prompt = extraction_prompt("# Notes\n\n- Use pnpm, not npm\n", 3);
+        assert!(prompt.contains("at most 3 durable facts"));
+        assert!(
+            prompt.contains("<project-memory>\n# Notes\n\n- Use pnpm, not npm\n</project-memory>")
+        );
+        assert!(extraction_prompt("  \n", 5).ends_with("(empty)\n"));
+    }
+
+    #[test]
+    fn test_parse_facts() {
+        let reply = "Here is what I would keep:\n\n\
+                     - Integration tests need `DATABASE_URL` set\n\
+                     2. The `api` crate is generated; edit `api.yaml` instead\n\
+                     * \n\
+                     - Logs go to /var/log/app\n";
+        assert_eq!(
+            parse_facts(reply, 2),
+            vec![
+                "Integration tests need `DATABASE_URL` set",
+                "The `api` crate is generated; edit `api.yaml` instead"
+            ]
+        );
+        assert_eq!(parse_facts(reply, 5).len(), 3);
+        assert!(parse_facts("NONE", 5).is_empty());
+        assert!(parse_facts("- none", 5).is_empty());
+    }
+}
@@ -0,0 +1,553 @@
+//! 模型流式输出的结构化事件
+//!
+//! rig 的多轮流把助手内容、工具结果和最终回复混在一起，`StreamEventMapper`
+//! 把它们整理成与服务商无关的 `StreamEvent` 序列：每次模型请求以 `message_start`
+//! 开始、以 `message_stop` 结束，中间是文本、思考和工具调用。
+//! 交互式渲染、serve 模式和 stream-json 输出消费同一套事件。
+//!
+//! `TurnStream` 隐藏了各服务商不同的流类型，调用方只看到 `StreamEvent`。
+//! 设置了本轮的截止时间时，到期后 `TurnStream` 丢弃底层的流并以取消错误结束。
+//! 流在输出部分文本后出错（连接中断）时，`TurnStream` 保留这段文本供续写（见 `stream_recovery`）。
+//! 录制会话（`--record`）时，`TurnStream` 把发出的事件和最终回复写入录制文件（见 `replay`）。
+
+use futures::{ready, Stream, StreamExt};
+use rig::agent::{FinalResponse, MultiTurnStreamItem};
+use rig::streaming::StreamedAssistantContent;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::time::Sleep;
+
+use super::replay::Recorder;
+use super::time_box::{TurnDeadline, TIME_LIMIT_ERROR};
+use super::turn_summary::StopReason;
+
+/// 流式输出事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// 一次模型请求开始输出
+    MessageStart,
+    /// 回复文本增量
+    TextDelta { text: String },
+    /// 思考内容增量
+    ThinkingDelta { text: String },
+    /// 工具调用开始
+    ToolUseStart { id: String, name: String },
+    /// 工具调用参数（JSON 片段）
+    ToolUseDelta { id: String, partial_json: String },
+    /// 工具调用参数结束
+    ToolUseStop { id: String },
+    /// 整轮的 token 用量
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+    /// 一次模型请求结束
+    MessageStop { stop_reason: StopReason },
+    /// 流出错（包括用户取消）
+    Error { message: String },
+}
+
+impl StreamEvent {
+    /// 是否为用户取消导致的错误
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Error { message } if message.contains("PromptCancelled"))
+    }
+}
+
+/// 把 rig 的多轮流条目转换为 `StreamEvent`
+///
+/// rig 按块给出工具调用，因此每个调用依次产生 start、一个完整参数的 delta 和 stop。
+/// 工具结果出现说明上一次模型请求以 `tool_use` 结束，下一段助手内容属于新的请求。
+#[derive(Default)]
+pub struct StreamEventMapper {
+    in_message: bool,
+    tool_calls: usize,
+    final_response: Option<FinalResponse>,
+}
+
+impl StreamEventMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 转换一个流条目，返回按顺序发出的事件
+    pub fn map<R, E: Display>(
+        &mut self,
+        item: Result<MultiTurnStreamItem<R>, E>,
+    ) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        match item {
+            Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text))) => {
+                self.start(&mut events);
+                events.push(StreamEvent::TextDelta { text: text.text });
+            }
+            Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(
+                reasoning,
+            ))) => {
+                self.start(&mut events);
+                events.push(StreamEvent::ThinkingDelta {
+                    text: reasoning.reasoning.join("\n"),
+                });
+            }
+            Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall(
+                call,
+            ))) => {
+                self.start(&mut events);
+                self.tool_calls += 1;
+                events.push(StreamEvent::ToolUseStart {
+                    id: call.id.clone(),
+                    name: call.function.name,
+                });
+                events.push(StreamEvent::ToolUseDelta {
+                    id: call.id.clone(),
+                    partial_json: call.function.arguments.to_string(),
+                });
+                events.push(StreamEvent::ToolUseStop { id: call.id });
+            }
+            Ok(MultiTurnStreamItem::StreamUserItem(_)) => {
+                self.stop(&mut events, StopReason::ToolUse);
+            }
+            Ok(MultiTurnStreamItem::FinalResponse(res)) => {
+                let usage = res.usage();
+                events.push(StreamEvent::Usage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                });
+                let stop_reason = StopReason::infer(self.tool_calls, Some(usage.output_tokens));
+                self.stop(&mut events, stop_reason);
+                self.final_response = Some(res);
+            }
+            Err(err) => {
+                self.in_message = false;
+                events.push(StreamEvent::Error {
+                    message: err.to_string(),
+                });
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// 取出最终回复（流未给出时为空回复）
+    pub fn take_final_response(&mut self) -> FinalResponse {
+        self.final_response
+            .take()
+            .unwrap_or_else(FinalResponse::empty)
+    }
+
+    fn start(&mut self, events: &mut Vec<StreamEvent>) {
+        if !self.in_message {
+            self.in_message = true;
+            self.tool_calls = 0;
+            events.push(StreamEvent::MessageStart);
+        }
+    }
+
+    fn stop(&mut self, events: &mut Vec<StreamEvent>, stop_reason: StopReason) {
+        if self.in_message {
+            self.in_message = false;
+            events.push(StreamEvent::MessageStop { stop_reason });
+        }
+    }
+}
+
+/// 擦除服务商类型后的 rig 多轮流
+trait TurnSource: Send {
+    fn poll_events(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<StreamEvent>>>;
+
+    fn take_final_response(&mut self) -> FinalResponse;
+}
+
+struct MappedTurn<S> {
+    stream: S,
+    mapper: StreamEventMapper,
+}
+
+impl<S, R, E> TurnSource for MappedTurn<S>
+where
+    S: Stream<Item = Result<MultiTurnStreamItem<R>, E>> + Unpin + Send,
+    E: Display,
+{
+    fn poll_events(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<StreamEvent>>> {
+        let item = ready!(self.stream.poll_next_unpin(cx));
+        Poll::Ready(item.map(|item| self.mapper.map(item)))
+    }
+
+    fn take_final_response(&mut self) -> FinalResponse {
+        self.mapper.take_final_response()
+    }
+}
+
+/// 回放录制的事件
+struct ReplayedTurn<S> {
+    events: S,
+    final_response: Option<FinalResponse>,
+}
+
+impl<S> TurnSource for ReplayedTurn<S>
+where
+    S: Stream<Item = StreamEvent> + Unpin + Send,
+{
+    fn poll_events(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<StreamEvent>>> {
+        let event = ready!(self.events.poll_next_unpin(cx));
+        Poll::Ready(event.map(|event| vec![event]))
+    }
+
+    fn take_final_response(&mut self) -> FinalResponse {
+        self.final_response
+            .take()
+            .unwrap_or_else(FinalResponse::empty)
+    }
+}
+
+/// 一轮对话（含工具调用的多次模型请求）的事件流
+///
+/// 流结束后用 `take_final_response` 取出最终回复和用量。
+pub struct TurnStream {
+    /// 超时后为 `None`
+    source: Option<Box<dyn TurnSource>>,
+    pending: VecDeque<StreamEvent>,
+    timer: Option<(Pin<Box<Sleep>>, TurnDeadline)>,
+    /// 当前模型请求已收到的文本
+    partial: String,
+    /// 流以非取消的错误结束
+    disconnected: bool,
+    /// 本轮已发出的工具调用数
+    tool_calls: usize,
+    recorder: Option<Recorder>,
+}
+
+impl TurnStream {
+    /// 包装 rig 的多轮流（`StreamingResult`）
+    pub fn new<S, R, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<MultiTurnStreamItem<R>, E>> + Unpin + Send + 'static,
+        E: Display + 'static,
+    {
+        Self {
+            source: Some(Box::new(MappedTurn {
+                stream,
+                mapper: StreamEventMapper::new(),
+            })),
+            pending: VecDeque::new(),
+            timer: None,
+            partial: String::new(),
+            disconnected: false,
+            tool_calls: 0,
+            recorder: None,
+        }
+    }
+
+    /// 按顺序发出已有的事件，结束后 `take_final_response` 返回 `final_response`（用于回放录制）
+    pub fn replayed<S>(events: S, final_response: FinalResponse) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Unpin + Send + 'static,
+    {
+        Self {
+            source: Some(Box::new(ReplayedTurn {
+                events,
+                final_response: Some(final_response),
+            })),
+            pending: VecDeque::new(),
+            timer: None,
+            partial: String::new(),
+            disconnected: false,
+            tool_calls: 0,
+            recorder: None,
+        }
+    }
+
+    /// 把发出的事件和最终回复写入录制文件
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// 到达截止时间后丢弃底层的流（取消进行中的模型请求和工具调用），
+    /// 发出 [`TIME_LIMIT_ERROR`] 并结束
+    pub fn with_deadline(mut self, deadline: TurnDeadline) -> Self {
+        let sleep = tokio::time::sleep_until(deadline.deadline().into());
+        self.timer = Some((Box::pin(sleep), deadline));
+        self
+    }
+
+    /// 取出最终回复（流未给出时为空回复）
+    pub fn take_final_response(&mut self) -> FinalResponse {
+        let response = self
+            .source
+            .as_mut()
+            .map(|source| source.take_final_response())
+            .unwrap_or_else(FinalResponse::empty);
+        if let Some(recorder) = &self.recorder {
+            recorder.response(&response);
+        }
+        response
+    }
+
+    /// 流在输出部分文本后出错结束时，返回当前模型请求已收到的文本
+    pub fn disconnected(&self) -> Option<&str> {
+        (self.disconnected && !self.partial.is_empty()).then_some(self.partial.as_str())
+    }
+
+    /// 本轮（所有模型请求）已发出的工具调用数
+    pub fn tool_calls(&self) -> usize {
+        self.tool_calls
+    }
+
+    fn observe(&mut self, event: &StreamEvent) {
+        if let Some(recorder) = &self.recorder {
+            recorder.event(event);
+        }
+        match event {
+            StreamEvent::MessageStart | StreamEvent::MessageStop { .. } => {
+                self.partial.clear();
+                self.disconnected = false;
+            }
+            StreamEvent::TextDelta { text } => self.partial.push_str(text),
+            StreamEvent::ToolUseStart { .. } => self.tool_calls += 1,
+            StreamEvent::Error { .. } => self.disconnected = !event.is_cancelled(),
+            _ => {}
+        }
+    }
+
+    /// 截止时间已到时停止底层的流，返回超时错误
+    fn poll_timer(&mut self, cx: &mut Context<'_>) -> Option<StreamEvent> {
+        let (sleep, deadline) = self.timer.as_mut()?;
+        if sleep.as_mut().poll(cx).is_pending() {
+            return None;
+        }
+        deadline.mark_hit();
+        self.timer = None;
+        self.source = None;
+        Some(StreamEvent::Error {
+            message: TIME_LIMIT_ERROR.to_string(),
+        })
+    }
+}
+
+impl Stream for TurnStream {
+    type Item = StreamEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<StreamEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.observe(&event);
+                return Poll::Ready(Some(event));
+            }
+            if let Some(event) = self.poll_timer(cx) {
+                return Poll::Ready(Some(event));
+            }
+            let Some(source) = self.source.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match ready!(source.poll_events(cx)) {
+                Some(events) => self.pending.extend(events),
+                None => {
+                    self.timer = None;
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::completion::message::{
+        AssistantContent, Reasoning, Text, ToolResultContent, UserContent,
+    };
+    use rig::streaming::StreamedUserContent;
+    use rig::OneOrMany;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn text(s: &str) -> Result<MultiTurnStreamItem<()>, String> {
+        Ok(MultiTurnStreamItem::StreamAssistantItem(
+            StreamedAssistantContent::Text(Text {
+                text: s.to_string(),
+            }),
+        ))
+    }
+
+    fn tool_call(id: &str, name: &str) -> Result<MultiTurnStreamItem<()>, String> {
+        let AssistantContent::ToolCall(call) =
+            AssistantContent::tool_call(id, name, json!({ "file_path": "src/main.rs" }))
+        else {
+            unreachable!();
+        };
+        Ok(MultiTurnStreamItem::StreamAssistantItem(
+            StreamedAssistantContent::ToolCall(call),
+        ))
+    }
+
+    fn tool_result(id: &str) -> Result<MultiTurnStreamItem<()>, String> {
+        let UserContent::ToolResult(result) =
+            UserContent::tool_result(id, OneOrMany::one(ToolResultContent::text("fn main() {}")))
+        else {
+            unreachable!();
+        };
+        Ok(MultiTurnStreamItem::StreamUserItem(
+            StreamedUserContent::ToolResult(result),
+        ))
+    }
+
+    #[test]
+    fn test_multi_turn_transcript() {
+        let transcript = vec![
+            Ok(MultiTurnStreamItem::StreamAssistantItem(
+                StreamedAssistantContent::Reasoning(Reasoning::new("need the file")),
+            )),
+            text("Reading it."),
+            tool_call("toolu_1", "read_file"),
+            tool_result("toolu_1"),
+            text("Done"),
+            Ok(MultiTurnStreamItem::FinalResponse(FinalResponse::empty())),
+        ];
+
+        let mut mapper = StreamEventMapper::new();
+        let events: Vec<StreamEvent> = transcript
+            .into_iter()
+            .flat_map(|item| mapper.map(item))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::MessageStart,
+                StreamEvent::ThinkingDelta {
+                    text: "need the file".to_string()
+                },
+                StreamEvent::TextDelta {
+                    text: "Reading it.".to_string()
+                },
+                StreamEvent::ToolUseStart {
+                    id: "toolu_1".to_string(),
+                    name: "read_file".to_string()
+                },
+                StreamEvent::ToolUseDelta {
+                    id: "toolu_1".to_string(),
+                    partial_json: r#"{"file_path":"src/main.rs"}"#.to_string()
+                },
+                StreamEvent::ToolUseStop {
+                    id: "toolu_1".to_string()
+                },
+                StreamEvent::MessageStop {
+                    stop_reason: StopReason::ToolUse
+                },
+                StreamEvent::MessageStart,
+                StreamEvent::TextDelta {
+                    text: "Done".to_string()
+                },
+                StreamEvent::Usage {
+                    input_tokens: 0,
+                    output_tokens: 0
+                },
+                StreamEvent::MessageStop {
+                    stop_reason: StopReason::EndTurn
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_end_the_message() {
+        let mut mapper = StreamEventMapper::new();
+        assert_eq!(mapper.map(text("Hi")).len(), 2);
+
+        let events = mapper.map(Err::<MultiTurnStreamItem<()>, _>(
+            "PromptCancelled: user interrupted".to_string(),
+        ));
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_cancelled());
+
+        // 出错后的新内容属于新的请求；没有 FinalResponse 时返回空回复
+        assert_eq!(mapper.map(text("again"))[0], StreamEvent::MessageStart);
+        assert_eq!(mapper.take_final_response().response(), "");
+    }
+
+    #[tokio::test]
+    async fn test_turn_stream() {
+        let items = vec![
+            text("Hi"),
+            Ok(MultiTurnStreamItem::FinalResponse(FinalResponse::empty())),
+        ];
+        let mut stream = TurnStream::new(futures::stream::iter(items));
+
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], StreamEvent::MessageStart);
+        assert!(matches!(events[3], StreamEvent::MessageStop { .. }));
+        assert_eq!(stream.take_final_response().response(), "");
+    }
+
+    #[tokio::test]
+    async fn test_turn_stream_deadline() {
+        // 工具调用之后的结果迟迟不来，模拟一个运行很久的工具
+        let slow_tool = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            tool_result("toolu_1")
+        });
+        let items = futures::stream::iter(vec![text("Checking."), tool_call("toolu_1", "shell")])
+            .chain(slow_tool)
+            .boxed();
+        let deadline = TurnDeadline::new(Duration::from_millis(50));
+        let mut stream = TurnStream::new(items).with_deadline(deadline.clone());
+
+        let started = std::time::Instant::now();
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(deadline.was_hit());
+        assert_eq!(events.len(), 6);
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::Error {
+                message: TIME_LIMIT_ERROR.to_string()
+            })
+        );
+        assert!(events[5].is_cancelled());
+        assert_eq!(stream.take_final_response().response(), "");
+
+        // 在截止时间前结束的流不受影响
+        let deadline = TurnDeadline::new(Duration::from_secs(30));
+        let mut stream = TurnStream::new(futures::stream::iter(vec![text("Hi")]))
+            .with_deadline(deadline.clone());
+        assert_eq!(stream.next().await, Some(StreamEvent::MessageStart));
+        assert!(stream.next().await.is_some());
+        assert_eq!(stream.next().await, None);
+        assert!(!deadline.was_hit());
+    }
+
+    #[test]
+    fn test_stream_json_format() {
+        let line = serde_json::to_string(&StreamEvent::MessageStop {
+            stop_reason: StopReason::MaxTokens,
+        })
+        .unwrap();
+        assert_eq!(
+            line,
+            r#"{"type":"message_stop","stop_reason":"max_tokens"}"#
+        );
+
+        let event: StreamEvent =
+            serde_json::from_str(r#"{"type":"tool_use_start","id":"t1","name":"grep"}"#).unwrap();
+        assert_eq!(
+            event,
+            StreamEvent::ToolUseStart {
+                id: "t1".to_string(),
+                name: "grep".to_string()
+            }
+        );
+    }
+}
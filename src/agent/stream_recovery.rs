@@ -0,0 +1,355 @@
+//! 流式回复中途断开后的续写
+//!
+//! 网络不稳定时，较长的回复可能输出到一半时流出错结束。已收到的文本不丢弃：再发一次请求，
+//! 把原始提示词和部分回复放在历史末尾，要求模型从断开处接着写，两段拼接为一条助手消息。
+//! 最多续写 [`MAX_CONTINUATIONS`] 次，仍然断开时把已收到的内容作为回复并标明不完整。
+//!
+//! 续写请求的历史里只有提示词和文本，没有本轮的工具调用和结果，模型看不到已经做过的操作，
+//! 可能重新执行有副作用的工具。因此断开前本轮调用过工具时不续写，直接把已收到的部分作为
+//! 不完整的回复。
+//!
+//! 断开的请求拿不到服务商的用量数据，按发送和收到的文本估算，与其余请求的用量相加。
+
+use rig::agent::FinalResponse;
+use rig::completion::{Message, Usage};
+use std::future::Future;
+
+use super::stream_event::TurnStream;
+use crate::context::message_token_count;
+use crate::token_counter::{count_tokens, REPLY_PRIMING_TOKENS};
+
+/// 一轮回复最多续写的次数
+pub const MAX_CONTINUATIONS: usize = 2;
+
+/// 续写请求的提示词
+pub const CONTINUE_AFTER_DISCONNECT_PROMPT: &str =
+    "The connection dropped while you were responding, so your previous message is incomplete. Continue exactly where it stopped without repeating anything.";
+
+/// 一次模型请求
+#[derive(Debug, Clone)]
+pub struct TurnRequest {
+    pub prompt: String,
+    pub history: Vec<Message>,
+    /// 第几次续写，原始请求为 0
+    pub continuation: usize,
+}
+
+/// 一次模型请求的结果
+pub enum RequestOutcome {
+    Completed(FinalResponse),
+    /// 流在输出部分文本后断开
+    Disconnected {
+        partial: String,
+        /// 断开前本轮调用过工具
+        after_tool_calls: bool,
+    },
+}
+
+impl RequestOutcome {
+    /// 根据消费完的流判断请求是否中途断开（`response` 为流的最终回复）
+    pub fn from_stream(stream: &TurnStream, response: FinalResponse) -> Self {
+        match stream.disconnected() {
+            Some(partial) => Self::Disconnected {
+                partial: partial.to_string(),
+                after_tool_calls: stream.tool_calls() > 0,
+            },
+            None => Self::Completed(response),
+        }
+    }
+}
+
+/// 一轮对话的回复（断开后续写的部分已拼接）
+#[derive(Debug, Clone)]
+pub struct RecoveredReply {
+    pub text: String,
+    /// 所有请求的用量之和（断开的请求为估算值）
+    pub usage: Usage,
+    /// 续写的次数
+    pub continuations: usize,
+    /// 续写次数用完仍然断开，或断开前调用过工具而没有续写，`text` 只是部分回复
+    pub truncated: bool,
+    /// 因断开前本轮调用过工具而没有续写
+    pub after_tool_calls: bool,
+}
+
+impl From<FinalResponse> for RecoveredReply {
+    fn from(response: FinalResponse) -> Self {
+        Self {
+            text: response.response().to_string(),
+            usage: response.usage(),
+            continuations: 0,
+            truncated: false,
+            after_tool_calls: false,
+        }
+    }
+}
+
+/// 发送一轮对话，回复中途断开时续写
+///
+/// `send` 发出一次请求并消费它的流；续写请求的历史末尾追加了原始提示词和已收到的回复。
+/// 请求出错（包括用户取消）时直接返回错误，不再续写；断开前本轮调用过工具时也不续写。
+pub async fn complete_with_recovery<F, Fut, E>(
+    prompt: &str,
+    history: Vec<Message>,
+    mut send: F,
+) -> Result<RecoveredReply, E>
+where
+    F: FnMut(TurnRequest) -> Fut,
+    Fut: Future<Output = Result<RequestOutcome, E>>,
+{
+    let mut text = String::new();
+    let mut usage = Usage::default();
+    let mut request = TurnRequest {
+        prompt: prompt.to_string(),
+        history: history.clone(),
+        continuation: 0,
+    };
+    loop {
+        let estimated_input = estimate_input_tokens(&request);
+        let continuation = request.continuation;
+        match send(request).await? {
+            RequestOutcome::Completed(response) => {
+                text.push_str(response.response());
+                add_usage(&mut usage, response.usage());
+                return Ok(RecoveredReply {
+                    text,
+                    usage,
+                    continuations: continuation,
+                    truncated: false,
+                    after_tool_calls: false,
+                });
+            }
+            RequestOutcome::Disconnected {
+                partial,
+                after_tool_calls,
+            } => {
+                tracing::warn!(
+                    continuation,
+                    received = partial.len(),
+                    after_tool_calls,
+                    "response stream disconnected"
+                );
+                text.push_str(&partial);
+                add_usage(
+                    &mut usage,
+                    Usage {
+                        input_tokens: estimated_input,
+                        output_tokens: count_tokens(&partial) as u64,
+                        ..Usage::default()
+                    },
+                );
+                if after_tool_calls || continuation >= MAX_CONTINUATIONS {
+                    return Ok(RecoveredReply {
+                        text,
+                        usage,
+                        continuations: continuation,
+                        truncated: true,
+                        after_tool_calls,
+                    });
+                }
+                let mut history = history.clone();
+                history.push(Message::user(prompt));
+                history.push(Message::assistant(text.clone()));
+                request = TurnRequest {
+                    prompt: CONTINUE_AFTER_DISCONNECT_PROMPT.to_string(),
+                    history,
+                    continuation: continuation + 1,
+                };
+            }
+        }
+    }
+}
+
+/// 估算一次请求的输入 token（历史和提示词）
+fn estimate_input_tokens(request: &TurnRequest) -> u64 {
+    let history: usize = request.history.iter().map(message_token_count).sum();
+    (REPLY_PRIMING_TOKENS + history + message_token_count(&Message::user(request.prompt.as_str())))
+        as u64
+}
+
+fn add_usage(total: &mut Usage, usage: Usage) {
+    total.input_tokens += usage.input_tokens;
+    total.output_tokens += usage.output_tokens;
+    total.total_tokens += usage.input_tokens + usage.output_tokens;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use rig::agent::MultiTurnStreamItem;
+    use rig::completion::message::Text;
+    use rig::streaming::StreamedAssistantContent;
+    use std::sync::{Arc, Mutex};
+
+    /// 模拟服务商：按顺序输出 `chunks`，在第 `cut_after` 块之后断开（`None` 时正常结束）
+    fn mock_stream(chunks: &[&str], cut_after: Option<usize>) -> TurnStream {
+        let mut items: Vec<Result<MultiTurnStreamItem<()>, String>> = chunks
+            .iter()
+            .take(cut_after.unwrap_or(chunks.len()))
+            .map(|chunk| {
+                Ok(MultiTurnStreamItem::StreamAssistantItem(
+                    StreamedAssistantContent::Text(Text {
+                        text: chunk.to_string(),
+                    }),
+                ))
+            })
+            .collect();
+        items.push(match cut_after {
+            Some(_) => Err("error decoding response body: connection reset".to_string()),
+            None => Ok(MultiTurnStreamItem::FinalResponse(FinalResponse::empty())),
+        });
+        TurnStream::new(futures::stream::iter(items))
+    }
+
+    /// 消费流，返回请求结果和流中收到的文本
+    async fn drain(mut stream: TurnStream) -> (RequestOutcome, String) {
+        let mut received = String::new();
+        while let Some(event) = stream.next().await {
+            if let crate::agent::stream_event::StreamEvent::TextDelta { text } = event {
+                received.push_str(&text);
+            }
+        }
+        let response = stream.take_final_response();
+        (RequestOutcome::from_stream(&stream, response), received)
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_is_continued_and_stitched() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let displayed = Arc::new(Mutex::new(String::new()));
+        let reply = complete_with_recovery("explain", vec![Message::user("hi")], |request| {
+            let requests = requests.clone();
+            let displayed = displayed.clone();
+            async move {
+                let stream = match request.continuation {
+                    0 => mock_stream(&["The parser ", "reads tokens ", "and builds"], Some(2)),
+                    _ => mock_stream(&["and builds an AST."], None),
+                };
+                requests.lock().unwrap().push(request);
+                let (outcome, received) = drain(stream).await;
+                displayed.lock().unwrap().push_str(&received);
+                Ok::<_, std::io::Error>(outcome)
+            }
+        })
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].prompt, CONTINUE_AFTER_DISCONNECT_PROMPT);
+        assert_eq!(requests[1].history.len(), 3);
+        let appended = serde_json::to_string(&requests[1].history[1..]).unwrap();
+        assert!(appended.contains("explain"));
+        assert!(appended.contains("The parser reads tokens "));
+
+        // 模拟的最终回复不带文本（真实的最终回复是续写部分的全文），因此只拼接了断开前的部分；
+        // 用量包含断开请求的估算
+        assert_eq!(reply.text, "The parser reads tokens ");
+        assert_eq!(
+            *displayed.lock().unwrap(),
+            "The parser reads tokens and builds an AST."
+        );
+        assert_eq!(reply.continuations, 1);
+        assert!(!reply.truncated);
+        assert!(reply.usage.input_tokens > 0);
+        assert!(reply.usage.output_tokens > 0);
+        assert_eq!(
+            reply.usage.total_tokens,
+            reply.usage.input_tokens + reply.usage.output_tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_capped() {
+        let attempts = Arc::new(Mutex::new(0));
+        let reply = complete_with_recovery("explain", Vec::new(), |request| {
+            let attempts = attempts.clone();
+            async move {
+                *attempts.lock().unwrap() += 1;
+                let chunk = format!("part {} ", request.continuation);
+                let (outcome, _) = drain(mock_stream(&[&chunk, "lost"], Some(1))).await;
+                Ok::<_, std::io::Error>(outcome)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*attempts.lock().unwrap(), MAX_CONTINUATIONS + 1);
+        assert_eq!(reply.text, "part 0 part 1 part 2 ");
+        assert_eq!(reply.continuations, MAX_CONTINUATIONS);
+        assert!(reply.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_after_tool_round_is_not_continued() {
+        use rig::completion::message::{AssistantContent, ToolResultContent, UserContent};
+        use rig::streaming::StreamedUserContent;
+        use rig::OneOrMany;
+
+        let AssistantContent::ToolCall(call) = AssistantContent::tool_call(
+            "toolu_1",
+            "shell_execute",
+            serde_json::json!({ "command": "git push" }),
+        ) else {
+            unreachable!();
+        };
+        let UserContent::ToolResult(result) =
+            UserContent::tool_result("toolu_1", OneOrMany::one(ToolResultContent::text("pushed")))
+        else {
+            unreachable!();
+        };
+
+        let attempts = Arc::new(Mutex::new(0));
+        let reply = complete_with_recovery("push it", Vec::new(), |_request| {
+            let attempts = attempts.clone();
+            let items: Vec<Result<MultiTurnStreamItem<()>, String>> = vec![
+                Ok(MultiTurnStreamItem::StreamAssistantItem(
+                    StreamedAssistantContent::ToolCall(call.clone()),
+                )),
+                Ok(MultiTurnStreamItem::StreamUserItem(
+                    StreamedUserContent::ToolResult(result.clone()),
+                )),
+                Ok(MultiTurnStreamItem::StreamAssistantItem(
+                    StreamedAssistantContent::Text(Text {
+                        text: "Pushed to ".to_string(),
+                    }),
+                )),
+                Err("error decoding response body: connection reset".to_string()),
+            ];
+            async move {
+                *attempts.lock().unwrap() += 1;
+                let (outcome, _) = drain(TurnStream::new(futures::stream::iter(items))).await;
+                Ok::<_, std::io::Error>(outcome)
+            }
+        })
+        .await
+        .unwrap();
+
+        // 续写请求看不到已执行的工具调用，不能再发
+        assert_eq!(*attempts.lock().unwrap(), 1);
+        assert_eq!(reply.text, "Pushed to ");
+        assert_eq!(reply.continuations, 0);
+        assert!(reply.truncated);
+        assert!(reply.after_tool_calls);
+    }
+
+    #[tokio::test]
+    async fn test_errors_without_partial_text_are_not_continued() {
+        // 还没有收到文本就出错：没有可续写的内容
+        let (outcome, _) = drain(mock_stream(&["never sent"], Some(0))).await;
+        assert!(matches!(outcome, RequestOutcome::Completed(_)));
+
+        // 用户取消不算断开
+        let items: Vec<Result<MultiTurnStreamItem<()>, String>> = vec![
+            Ok(MultiTurnStreamItem::StreamAssistantItem(
+                StreamedAssistantContent::Text(Text {
+                    text: "Hal".to_string(),
+                }),
+            )),
+            Err("PromptCancelled: user interrupted".to_string()),
+        ];
+        let (outcome, _) = drain(TurnStream::new(futures::stream::iter(items))).await;
+        assert!(matches!(outcome, RequestOutcome::Completed(_)));
+    }
+}
@@ -0,0 +1,390 @@
+//! 结构化输出（`oxide -p <prompt> --output-schema <file>`）
+//!
+//! 自动化脚本需要固定格式的最终回答。读取 JSON Schema 后尽量让服务商约束输出：
+//! Anthropic 注册一个以该 schema 为参数的 `structured_output` 工具，要求模型用它提交最终回答；
+//! OpenAI 兼容的服务商使用 `json_schema` 输出格式。
+//!
+//! 最终回答用 `jsonschema` 校验，不符合时把校验错误发回给模型修正一次，仍不符合则报错。
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::BoxFuture;
+use rig::completion::{Message, ToolDefinition};
+use rig::tool::{ToolDyn, ToolError};
+use serde_json::{json, Value};
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+
+use super::stream_event::StreamEvent;
+
+/// Anthropic 下提交最终回答的工具名
+pub const OUTPUT_TOOL_NAME: &str = "structured_output";
+
+/// 输出格式约束
+pub struct OutputSchema {
+    schema: Value,
+    validator: jsonschema::Validator,
+}
+
+impl OutputSchema {
+    /// 读取 JSON Schema 文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read output schema {}", path.display()))?;
+        let schema: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Output schema {} is not valid JSON", path.display()))?;
+        Self::from_value(schema)
+            .with_context(|| format!("Invalid output schema {}", path.display()))
+    }
+
+    pub fn from_value(schema: Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(&schema).map_err(|e| anyhow!("{}", e))?;
+        Ok(Self { schema, validator })
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// 在提示词后附加输出要求（`use_tool` 为 true 时要求通过工具提交）
+    pub fn instructions(&self, prompt: &str, use_tool: bool) -> String {
+        let delivery = if use_tool {
+            format!(
+                "When you have the final answer, submit it by calling the `{}` tool exactly once; its input is the answer.",
+                OUTPUT_TOOL_NAME
+            )
+        } else {
+            "Reply with only the JSON document: no prose and no code fences.".to_string()
+        };
+        format!(
+            "{}\n\nYour final answer must be a JSON document that conforms to this JSON Schema:\n{}\n{}",
+            prompt,
+            serde_json::to_string_pretty(&self.schema).unwrap_or_default(),
+            delivery
+        )
+    }
+
+    /// 校验最终回答，返回解析后的 JSON 或全部校验错误
+    pub fn validate(&self, answer: &str) -> std::result::Result<Value, Vec<String>> {
+        let value: Value = serde_json::from_str(strip_code_fence(answer))
+            .map_err(|e| vec![format!("the answer is not valid JSON: {}", e)])?;
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&value)
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                let path = if path.is_empty() {
+                    "(root)".to_string()
+                } else {
+                    path
+                };
+                format!("{}: {}", path, error)
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// 校验失败后发给模型的修正请求
+fn repair_prompt(errors: &[String], use_tool: bool) -> String {
+    let delivery = if use_tool {
+        format!(
+            "Submit the corrected answer with the `{}` tool.",
+            OUTPUT_TOOL_NAME
+        )
+    } else {
+        "Reply with only the corrected JSON document.".to_string()
+    };
+    format!(
+        "Your answer does not conform to the JSON Schema:\n- {}\n\nFix these errors without changing anything else. {}",
+        errors.join("\n- "),
+        delivery
+    )
+}
+
+/// 去掉包裹回答的 Markdown 代码块
+fn strip_code_fence(answer: &str) -> &str {
+    let trimmed = answer.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// OpenAI Responses API 的 `json_schema` 输出格式（作为请求的附加参数）
+pub fn openai_text_format(schema: &Value) -> Value {
+    json!({
+        "text": {
+            "format": {
+                "type": "json_schema",
+                "name": OUTPUT_TOOL_NAME,
+                "schema": schema,
+                "strict": false,
+            }
+        }
+    })
+}
+
+/// Anthropic 下提交最终回答的工具：参数即回答，由 [`AnswerCollector`] 从事件流中取出
+pub struct StructuredOutputTool {
+    schema: Value,
+}
+
+impl StructuredOutputTool {
+    pub fn new(schema: Value) -> Self {
+        Self { schema }
+    }
+}
+
+impl ToolDyn for StructuredOutputTool {
+    fn name(&self) -> String {
+        OUTPUT_TOOL_NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> BoxFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: OUTPUT_TOOL_NAME.to_string(),
+                description: "Submit the final answer. The input must conform to the schema; call this once, after all other work is done.".to_string(),
+                parameters: self.schema.clone(),
+            }
+        })
+    }
+
+    fn call(&self, _args: String) -> BoxFuture<'_, Result<String, ToolError>> {
+        Box::pin(async {
+            Ok("Answer recorded. The task is complete; do not reply further.".to_string())
+        })
+    }
+}
+
+/// 从一轮的事件中取出最终回答：`structured_output` 工具的参数，模型没有调用它时为最后一条回复的文本
+#[derive(Debug, Default)]
+pub struct AnswerCollector {
+    text: String,
+    tool_call: Option<String>,
+    output: Option<String>,
+}
+
+impl AnswerCollector {
+    pub fn observe(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::MessageStart => self.text.clear(),
+            StreamEvent::TextDelta { text } => self.text.push_str(text),
+            StreamEvent::ToolUseStart { id, name } if name == OUTPUT_TOOL_NAME => {
+                self.tool_call = Some(id.clone());
+            }
+            StreamEvent::ToolUseDelta { id, partial_json }
+                if self.tool_call.as_ref() == Some(id) =>
+            {
+                self.output = Some(partial_json.clone());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn answer(self) -> String {
+        self.output.unwrap_or(self.text)
+    }
+}
+
+/// 取得符合 schema 的最终回答
+///
+/// `send(prompt, history)` 发送一轮对话并返回模型的最终回答；校验失败时带着上一轮的问答
+/// 和校验错误再请求一次，仍不符合则返回包含校验错误的错误。
+pub async fn complete_structured<F, Fut>(
+    schema: &OutputSchema,
+    prompt: &str,
+    use_tool: bool,
+    mut send: F,
+) -> Result<Value>
+where
+    F: FnMut(String, Vec<Message>) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let prompt = schema.instructions(prompt, use_tool);
+    let answer = send(prompt.clone(), Vec::new()).await?;
+    let errors = match schema.validate(&answer) {
+        Ok(value) => return Ok(value),
+        Err(errors) => errors,
+    };
+    tracing::warn!(
+        errors = errors.len(),
+        "structured output failed validation, asking for a repair"
+    );
+
+    let history = vec![Message::user(prompt), Message::assistant(answer)];
+    let repaired = send(repair_prompt(&errors, use_tool), history).await?;
+    match schema.validate(&repaired) {
+        Ok(value) => Ok(value),
+        Err(errors) => bail!(
+            "The final answer does not match the output schema:\n- {}",
+            errors.join("\n- ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn api_schema() -> OutputSchema {
+        OutputSchema::from_value(json!({
+            "type": "object",
+            "required": ["apis"],
+            "properties": {
+                "apis": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name", "kind"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "kind": { "enum": ["fn", "struct", "trait"] }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// 按顺序返回预设回答的模拟模型，记录收到的请求
+    fn mock_model(
+        answers: Vec<&'static str>,
+        requests: Arc<Mutex<Vec<(String, usize)>>>,
+    ) -> impl FnMut(String, Vec<Message>) -> std::future::Ready<Result<String>> {
+        let mut answers = answers.into_iter();
+        move |prompt, history| {
+            requests.lock().unwrap().push((prompt, history.len()));
+            std::future::ready(Ok(answers.next().unwrap().to_string()))
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = api_schema();
+        assert!(schema
+            .validate("```json\n{\"apis\": [{\"name\": \"parse\", \"kind\": \"fn\"}]}\n```")
+            .is_ok());
+
+        let errors = schema
+            .validate(r#"{"apis": [{"name": "parse", "kind": "module"}]}"#)
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("/apis/0/kind: "));
+
+        let errors = schema.validate("Here are the APIs: parse").unwrap_err();
+        assert!(errors[0].contains("not valid JSON"));
+
+        assert!(OutputSchema::from_value(json!({ "type": "no-such-type" })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compliant_answer() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let value = complete_structured(
+            &api_schema(),
+            "list the public APIs",
+            false,
+            mock_model(
+                vec![r#"{"apis": [{"name": "Parser", "kind": "struct"}]}"#],
+                requests.clone(),
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(value["apis"][0]["name"], "Parser");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].0.starts_with("list the public APIs\n\n"));
+        assert!(requests[0].0.contains("\"required\""));
+    }
+
+    #[tokio::test]
+    async fn test_repair_round_trip() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let value = complete_structured(
+            &api_schema(),
+            "list the public APIs",
+            true,
+            mock_model(
+                vec![
+                    r#"{"apis": [{"name": "Parser"}]}"#,
+                    r#"{"apis": [{"name": "Parser", "kind": "struct"}]}"#,
+                ],
+                requests.clone(),
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(value["apis"][0]["kind"], "struct");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        // 修正请求带着上一轮的问答和校验错误
+        assert_eq!(requests[1].1, 2);
+        assert!(requests[1].0.contains("/apis/0: "));
+        assert!(requests[1].0.contains(OUTPUT_TOOL_NAME));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_one_repair() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let error = complete_structured(
+            &api_schema(),
+            "list the public APIs",
+            false,
+            mock_model(
+                vec!["parse, render", r#"{"apis": "parse"}"#],
+                requests.clone(),
+            ),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(error.to_string().contains("/apis: "));
+    }
+
+    #[test]
+    fn test_answer_collector() {
+        let events = [
+            StreamEvent::MessageStart,
+            StreamEvent::TextDelta {
+                text: "Submitting.".to_string(),
+            },
+            StreamEvent::ToolUseStart {
+                id: "toolu_1".to_string(),
+                name: OUTPUT_TOOL_NAME.to_string(),
+            },
+            StreamEvent::ToolUseDelta {
+                id: "toolu_1".to_string(),
+                partial_json: r#"{"apis":[]}"#.to_string(),
+            },
+            StreamEvent::MessageStart,
+            StreamEvent::TextDelta {
+                text: "Done.".to_string(),
+            },
+        ];
+        let mut collector = AnswerCollector::default();
+        for event in &events {
+            collector.observe(event);
+        }
+        assert_eq!(collector.answer(), r#"{"apis":[]}"#);
+
+        // 没有调用工具时取最后一条回复的文本
+        let mut collector = AnswerCollector::default();
+        for event in &events[..2] {
+            collector.observe(event);
+        }
+        assert_eq!(collector.answer(), "Submitting.");
+    }
+}
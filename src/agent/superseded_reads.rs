@@ -0,0 +1,329 @@
+//! 发送前去掉被取代的文件读取结果
+//!
+//! 同一个文件在会话中读了五次，五份完整内容会随之后的每轮请求重复发送。构建请求时
+//! （不修改保存的会话）把较早的读取结果替换为一行说明，只保留最新的内容：
+//!
+//! - 读取整个文件：之后同一路径再次读取整个文件，或被 write_file、edit_file、multi_edit、
+//!   delete_file 修改
+//! - 读取指定行范围：只有之后以完全相同的范围再次读取时才替换
+//!
+//! 路径按工具设置解析（`[tools.root]`、工作区别名，与文件工具一致）后比较，不做其他规范化；
+//! 失败的调用和含图片的结果不参与。
+//! `[behavior] dedupe_file_reads = false` 关闭。
+
+use once_cell::sync::Lazy;
+use rig::completion::message::{AssistantContent, ToolResultContent, UserContent};
+use rig::completion::Message;
+use rig::OneOrMany;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::token_counter::count_tokens;
+use crate::tools::settings::ToolSettings;
+
+/// 参数中带 `file_path`、会修改该文件的工具
+const MODIFYING_TOOLS: &[&str] = &["write_file", "edit_file", "delete_file"];
+
+/// 工具出错时 rig 写入工具结果的前缀
+const TOOL_ERROR: &str = "ToolCallError: ";
+
+/// 解析调用参数中路径的工具设置
+static SETTINGS: Lazy<RwLock<ToolSettings>> = Lazy::new(|| RwLock::new(ToolSettings::default()));
+
+/// 设置解析路径时使用的工具设置（与构建工具集时相同）
+pub fn configure(settings: &ToolSettings) {
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = settings.clone();
+}
+
+/// 调用参数中的路径实际指向的文件
+fn resolve(path: &str) -> String {
+    SETTINGS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .resolve_path(path)
+}
+
+/// 替换掉的读取结果数和节省的 token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadSavings {
+    pub reads: usize,
+    pub tokens: usize,
+}
+
+/// 一次 read_file 调用读取的文件和行范围
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FileRead {
+    path: String,
+    offset: Option<u64>,
+    limit: Option<u64>,
+}
+
+impl FileRead {
+    fn from_args(args: &Value) -> Option<Self> {
+        Some(Self {
+            path: resolve(args.get("file_path")?.as_str()?),
+            offset: args.get("offset").and_then(Value::as_u64),
+            limit: args.get("limit").and_then(Value::as_u64),
+        })
+    }
+
+    fn is_whole_file(&self) -> bool {
+        self.offset.is_none() && self.limit.is_none()
+    }
+
+    /// 代替原结果的说明
+    fn stub(&self, modified: bool) -> String {
+        let target = match (self.offset, self.limit) {
+            (None, None) => self.path.clone(),
+            (offset, limit) => format!(
+                "{} (offset {}, limit {})",
+                self.path,
+                offset.map_or("-".to_string(), |n| n.to_string()),
+                limit.map_or("-".to_string(), |n| n.to_string())
+            ),
+        };
+        if modified {
+            format!(
+                "[earlier read of {} superseded: the file was modified later]",
+                target
+            )
+        } else {
+            format!("[earlier read of {} superseded by a later read]", target)
+        }
+    }
+}
+
+/// 工具调用涉及的文件
+enum FileAccess {
+    Read(FileRead),
+    Modify(Vec<String>),
+}
+
+impl FileAccess {
+    fn from_call(name: &str, args: &Value) -> Option<Self> {
+        match name {
+            "read_file" => FileRead::from_args(args).map(FileAccess::Read),
+            "multi_edit" => {
+                let paths = args
+                    .get("edits")?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|edit| edit.get("file_path")?.as_str().map(resolve))
+                    .collect();
+                Some(FileAccess::Modify(paths))
+            }
+            name if MODIFYING_TOOLS.contains(&name) => {
+                let path = args.get("file_path")?.as_str()?;
+                Some(FileAccess::Modify(vec![resolve(path)]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 把被取代的读取结果替换为一行说明，返回节省的量
+pub fn drop_superseded_reads(messages: &mut [Message]) -> ReadSavings {
+    let mut calls: HashMap<String, FileAccess> = HashMap::new();
+    for message in messages.iter() {
+        if let Message::Assistant { content, .. } = message {
+            for item in content.iter() {
+                if let AssistantContent::ToolCall(call) = item {
+                    if let Some(access) =
+                        FileAccess::from_call(&call.function.name, &call.function.arguments)
+                    {
+                        calls.insert(call.id.clone(), access);
+                    }
+                }
+            }
+        }
+    }
+    if calls.is_empty() {
+        return ReadSavings::default();
+    }
+
+    // 从后向前：记住之后已经出现的读取和修改
+    let mut later_reads: HashSet<FileRead> = HashSet::new();
+    let mut later_modified: HashSet<String> = HashSet::new();
+    let mut savings = ReadSavings::default();
+    for message in messages.iter_mut().rev() {
+        let Message::User { content, .. } = message else {
+            continue;
+        };
+        let mut blocks: Vec<UserContent> = content.iter().cloned().collect();
+        let mut changed = false;
+        for block in blocks.iter_mut().rev() {
+            let UserContent::ToolResult(result) = block else {
+                continue;
+            };
+            let Some(text) = result_text(&result.content) else {
+                continue;
+            };
+            if text.starts_with(TOOL_ERROR) {
+                continue;
+            }
+            match calls.get(&result.id) {
+                Some(FileAccess::Read(read)) => {
+                    let modified = read.is_whole_file() && later_modified.contains(&read.path);
+                    if later_reads.contains(read) || modified {
+                        let stub = read.stub(!later_reads.contains(read));
+                        savings.reads += 1;
+                        savings.tokens += count_tokens(&text).saturating_sub(count_tokens(&stub));
+                        result.content = OneOrMany::one(ToolResultContent::text(stub));
+                        changed = true;
+                    } else {
+                        later_reads.insert(read.clone());
+                    }
+                }
+                Some(FileAccess::Modify(paths)) => later_modified.extend(paths.iter().cloned()),
+                None => {}
+            }
+        }
+        if changed {
+            if let Ok(blocks) = OneOrMany::many(blocks) {
+                *content = blocks;
+            }
+        }
+    }
+    savings
+}
+
+/// 结果中的文本，含图片时返回 `None`
+fn result_text(content: &OneOrMany<ToolResultContent>) -> Option<String> {
+    content
+        .iter()
+        .map(|item| match item {
+            ToolResultContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|texts| texts.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(id: &str, name: &str, args: Value) -> Message {
+        Message::Assistant {
+            id: None,
+            content: OneOrMany::one(AssistantContent::tool_call(id, name, args)),
+        }
+    }
+
+    fn result(id: &str, text: &str) -> Message {
+        Message::User {
+            content: OneOrMany::one(UserContent::tool_result(
+                id,
+                OneOrMany::one(ToolResultContent::text(text)),
+            )),
+        }
+    }
+
+    fn result_of(message: &Message) -> String {
+        match message {
+            Message::User { content, .. } => match content.iter().next() {
+                Some(UserContent::ToolResult(result)) => result_text(&result.content).unwrap(),
+                other => panic!("not a tool result: {:?}", other),
+            },
+            other => panic!("not a user message: {:?}", other),
+        }
+    }
+
+    fn lib_rs(version: u32) -> String {
+        format!(
+            "{{\"content\":\"{}\"}}",
+            format!("pub fn version() -> u32 {{ {} }}\\n", version).repeat(50)
+        )
+    }
+
+    #[test]
+    fn test_older_reads_are_stubbed() {
+        let mut messages = vec![
+            Message::user("look at lib.rs"),
+            call("r1", "read_file", json!({"file_path": "src/lib.rs"})),
+            result("r1", &lib_rs(1)),
+            // 指定范围的读取：范围不同时保留
+            call(
+                "r2",
+                "read_file",
+                json!({"file_path": "src/main.rs", "offset": 1, "limit": 20}),
+            ),
+            result("r2", "{\"content\":\"fn main() {}\"}"),
+            call(
+                "r3",
+                "read_file",
+                json!({"file_path": "src/main.rs", "offset": 40}),
+            ),
+            result("r3", "{\"content\":\"// tail\"}"),
+            call(
+                "r4",
+                "read_file",
+                json!({"file_path": "src/main.rs", "offset": 1, "limit": 20}),
+            ),
+            result("r4", "{\"content\":\"fn main() {}\"}"),
+            // 读取失败不算作读取
+            call("r5", "read_file", json!({"file_path": "src/lib.rs"})),
+            result("r5", "ToolCallError: Permission denied"),
+            call("r6", "read_file", json!({"file_path": "src/lib.rs"})),
+            result("r6", &lib_rs(2)),
+            Message::assistant("lib.rs defines version()"),
+        ];
+        let original = messages.clone();
+
+        let savings = drop_superseded_reads(&mut messages);
+        assert_eq!(savings.reads, 2);
+        assert!(savings.tokens > 100);
+        assert_eq!(
+            result_of(&messages[2]),
+            "[earlier read of src/lib.rs superseded by a later read]"
+        );
+        assert_eq!(
+            result_of(&messages[4]),
+            "[earlier read of src/main.rs (offset 1, limit 20) superseded by a later read]"
+        );
+        for index in [6, 8, 10, 12] {
+            assert_eq!(result_of(&messages[index]), result_of(&original[index]));
+        }
+    }
+
+    #[test]
+    fn test_reads_before_modification_are_stubbed() {
+        let mut messages = vec![
+            call("r1", "read_file", json!({"file_path": "src/lib.rs"})),
+            result("r1", &lib_rs(1)),
+            call(
+                "r2",
+                "read_file",
+                json!({"file_path": "src/lib.rs", "limit": 10}),
+            ),
+            result("r2", &lib_rs(1)),
+            call("r3", "read_file", json!({"file_path": "README.md"})),
+            result("r3", "{\"content\":\"# oxide\"}"),
+            // 修改失败时文件没有变化
+            call(
+                "e1",
+                "write_file",
+                json!({"file_path": "README.md", "content": "x"}),
+            ),
+            result("e1", "ToolCallError: Blocked"),
+            call(
+                "e2",
+                "multi_edit",
+                json!({"edits": [{"file_path": "src/lib.rs", "patch": "@@"}]}),
+            ),
+            result("e2", "{\"success\":true}"),
+        ];
+
+        let savings = drop_superseded_reads(&mut messages);
+        assert_eq!(savings.reads, 1);
+        assert_eq!(
+            result_of(&messages[1]),
+            "[earlier read of src/lib.rs superseded: the file was modified later]"
+        );
+        // 指定范围的读取只在相同范围再次读取时替换
+        assert_eq!(result_of(&messages[3]), lib_rs(1));
+        assert_eq!(result_of(&messages[5]), "{\"content\":\"# oxide\"}");
+    }
+}
@@ -0,0 +1,176 @@
+//! 限时的对话轮次
+//!
+//! `[limits] max_turn_seconds` 或 `--max-turn-time` 为每轮对话设置截止时间。
+//! 超时后不再发起新的工具调用，进行中的模型请求和工具随流一起被丢弃；
+//! 随后再发一次模型请求，让模型总结已完成的工作和剩余步骤，作为本轮的回答（标明为限时中止）。
+//! 总结中的剩余步骤可以在用户确认一次后转为任务列表中的任务。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 超时中止本轮时流发出的错误（包含 `PromptCancelled`，按取消处理）
+pub const TIME_LIMIT_ERROR: &str = "PromptCancelled: turn time limit reached";
+
+/// 总结请求中剩余步骤列表的标题
+const REMAINING_STEPS_HEADING: &str = "Remaining steps:";
+
+/// 提供给总结请求的工具结果截断长度（字符）
+const MAX_RESULT_CHARS: usize = 200;
+
+/// 一轮对话的截止时间（克隆后共享超时状态和进度记录）
+#[derive(Debug, Clone)]
+pub struct TurnDeadline {
+    deadline: Instant,
+    limit: Duration,
+    hit: Arc<AtomicBool>,
+    /// 本轮已完成的工具调用，总结时提供给模型
+    progress: Arc<Mutex<Vec<String>>>,
+}
+
+impl TurnDeadline {
+    /// 从现在开始计时
+    pub fn new(limit: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + limit,
+            limit,
+            hit: Arc::new(AtomicBool::new(false)),
+            progress: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// 记录本轮因超时而中止
+    pub fn mark_hit(&self) {
+        self.hit.store(true, Ordering::SeqCst);
+    }
+
+    /// 本轮是否因超时而中止
+    pub fn was_hit(&self) -> bool {
+        self.hit.load(Ordering::SeqCst)
+    }
+
+    /// 记录一次完成的工具调用
+    pub fn record_tool_result(&self, tool_name: &str, args: &str, result: &str) {
+        let result: String = result.chars().take(MAX_RESULT_CHARS).collect();
+        let line = format!(
+            "{}({}) -> {}",
+            tool_name,
+            args.trim(),
+            result.replace('\n', " ").trim()
+        );
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.push(line);
+        }
+    }
+
+    /// 超时后发给模型的总结请求
+    pub fn summary_prompt(&self) -> String {
+        let mut prompt = format!(
+            "The time limit for this turn ({}s) was reached and the turn was stopped. \
+             Do not call any tools. Briefly summarize what has been done so far and what is left to do. \
+             End with a \"{}\" section listing each remaining step on its own line starting with \"- \", \
+             or \"- none\" if the task is complete.",
+            self.limit.as_secs(),
+            REMAINING_STEPS_HEADING
+        );
+        let progress = self.progress.lock().map(|p| p.clone()).unwrap_or_default();
+        if !progress.is_empty() {
+            prompt.push_str("\n\nTool calls completed in this turn:\n");
+            for line in progress {
+                prompt.push_str(&format!("- {}\n", line));
+            }
+        }
+        prompt
+    }
+}
+
+/// 从总结中取出剩余步骤（`Remaining steps:` 之后的列表项）
+pub fn parse_remaining_steps(summary: &str) -> Vec<String> {
+    let mut lines = summary.lines().map(str::trim);
+    if !lines.any(|line| {
+        line.trim_start_matches(['#', '*', ' '])
+            .to_lowercase()
+            .starts_with(&REMAINING_STEPS_HEADING.to_lowercase())
+    }) {
+        return Vec::new();
+    }
+    lines
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| {
+            let step = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .or_else(|| {
+                    let (number, rest) = line.split_once(". ")?;
+                    number.chars().all(|c| c.is_ascii_digit()).then_some(rest)
+                })?;
+            let step = step.trim();
+            (!step.is_empty() && !step.eq_ignore_ascii_case("none")).then(|| step.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_expiry() {
+        let deadline = TurnDeadline::new(Duration::from_secs(60));
+        assert!(!deadline.is_expired());
+        assert!(!deadline.was_hit());
+
+        let expired = TurnDeadline::new(Duration::ZERO);
+        assert!(expired.is_expired());
+        let shared = expired.clone();
+        shared.mark_hit();
+        assert!(expired.was_hit());
+    }
+
+    #[test]
+    fn test_summary_prompt_includes_progress() {
+        let deadline = TurnDeadline::new(Duration::from_secs(90));
+        assert!(deadline.summary_prompt().contains("(90s)"));
+        assert!(!deadline.summary_prompt().contains("Tool calls completed"));
+
+        deadline.record_tool_result(
+            "read_file",
+            "{\"file_path\":\"src/main.rs\"}\n",
+            &"x".repeat(1000),
+        );
+        let prompt = deadline.summary_prompt();
+        assert!(prompt.contains("Do not call any tools"));
+        assert!(prompt.contains("- read_file({\"file_path\":\"src/main.rs\"}) -> xxx"));
+        assert!(prompt.len() < 1000);
+    }
+
+    #[test]
+    fn test_parse_remaining_steps() {
+        let summary = "Updated the parser and added tests.\n\n\
+                       **Remaining steps:**\n\
+                       - Wire the parser into the CLI\n\
+                       2. Update the README\n\
+                       \n\
+                       Let me know if you want me to continue.";
+        assert_eq!(
+            parse_remaining_steps(summary),
+            vec!["Wire the parser into the CLI", "Update the README"]
+        );
+
+        assert!(parse_remaining_steps("Remaining steps:\n- none").is_empty());
+        assert!(parse_remaining_steps("- not under a heading").is_empty());
+    }
+}
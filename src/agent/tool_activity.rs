@@ -0,0 +1,245 @@
+//! 本次会话的工具活动记录
+//!
+//! 消费工具状态事件（见 [`super::tool_status`]），每次调用保留一条记录：工具、参数摘要、
+//! 权限结果（依据哪条规则自动放行、用户批准或拒绝）、耗时、是否成功，以及完整参数和截取后的输出。
+//! `/tools` 列出这些记录并查看单条详情；其他前端只要注册同一个消费者，显示的内容就与 CLI 一致。
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::tool_status::{PermissionOutcome, ToolStatusEvent, ToolStatusSink};
+
+/// 最多保留的记录数，超出时丢弃最早的
+const MAX_RECORDS: usize = 500;
+
+static ACTIVITY: Lazy<Arc<ToolActivity>> = Lazy::new(|| Arc::new(ToolActivity::new()));
+
+/// 调用的当前状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallStatus {
+    Running,
+    Succeeded,
+    Failed,
+    /// 权限检查没有通过，工具没有运行
+    Denied,
+}
+
+/// 一次工具调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolRecord {
+    pub call_id: u64,
+    pub tool: String,
+    pub summary: String,
+    /// 工具参数（已隐藏认证信息）
+    pub args: Value,
+    /// 没有经过权限检查（如子 agent 内部的调用）时为 `None`
+    pub permission: Option<PermissionOutcome>,
+    pub duration_ms: Option<u64>,
+    pub success: Option<bool>,
+    /// 工具输出（已截取）或错误信息
+    pub output: String,
+}
+
+impl ToolRecord {
+    fn new(call_id: u64, tool: &str) -> Self {
+        Self {
+            call_id,
+            tool: tool.to_string(),
+            summary: String::new(),
+            args: Value::Null,
+            permission: None,
+            duration_ms: None,
+            success: None,
+            output: String::new(),
+        }
+    }
+
+    pub fn status(&self) -> CallStatus {
+        match (&self.permission, self.success) {
+            (Some(PermissionOutcome::Denied { .. }), _) => CallStatus::Denied,
+            (_, None) => CallStatus::Running,
+            (_, Some(true)) => CallStatus::Succeeded,
+            (_, Some(false)) => CallStatus::Failed,
+        }
+    }
+}
+
+/// 工具活动记录（按调用开始的顺序）
+#[derive(Debug, Default)]
+pub struct ToolActivity {
+    records: Mutex<VecDeque<ToolRecord>>,
+}
+
+impl ToolActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 全部记录的副本
+    pub fn records(&self) -> Vec<ToolRecord> {
+        self.records
+            .lock()
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 更新（或新建）`call_id` 对应的记录
+    fn update(&self, call_id: u64, tool: &str, apply: impl FnOnce(&mut ToolRecord)) {
+        let Ok(mut records) = self.records.lock() else {
+            return;
+        };
+        if let Some(record) = records.iter_mut().rev().find(|r| r.call_id == call_id) {
+            apply(record);
+            return;
+        }
+        let mut record = ToolRecord::new(call_id, tool);
+        apply(&mut record);
+        records.push_back(record);
+        while records.len() > MAX_RECORDS {
+            records.pop_front();
+        }
+    }
+}
+
+impl ToolStatusSink for ToolActivity {
+    fn handle(&self, event: &ToolStatusEvent) {
+        match event {
+            ToolStatusEvent::PermissionDecided {
+                call_id,
+                tool,
+                summary,
+                args,
+                outcome,
+            } => self.update(*call_id, tool, |record| {
+                record.summary = summary.clone();
+                record.args = args.clone();
+                record.permission = Some(outcome.clone());
+            }),
+            ToolStatusEvent::ToolStarted {
+                call_id,
+                tool,
+                summary,
+                args,
+            } => self.update(*call_id, tool, |record| {
+                record.summary = summary.clone();
+                if !args.is_null() {
+                    record.args = args.clone();
+                }
+            }),
+            ToolStatusEvent::ToolCompleted {
+                call_id,
+                tool,
+                duration_ms,
+                success,
+                output,
+            } => self.update(*call_id, tool, |record| {
+                record.duration_ms = Some(*duration_ms);
+                record.success = Some(*success);
+                record.output = output.clone();
+            }),
+            ToolStatusEvent::ToolFailed {
+                call_id,
+                tool,
+                duration_ms,
+                error,
+            } => self.update(*call_id, tool, |record| {
+                record.duration_ms = Some(*duration_ms);
+                record.success = Some(false);
+                record.output = error.clone();
+            }),
+            ToolStatusEvent::ImageOutput { .. } => {}
+        }
+    }
+}
+
+/// 进程内共用的活动记录（启动交互界面时注册为事件消费者）
+pub fn global() -> Arc<ToolActivity> {
+    Arc::clone(&ACTIVITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn started(call_id: u64, tool: &str, summary: &str) -> ToolStatusEvent {
+        ToolStatusEvent::ToolStarted {
+            call_id,
+            tool: tool.to_string(),
+            summary: summary.to_string(),
+            args: json!({ "command": summary }),
+        }
+    }
+
+    #[test]
+    fn test_events_merge_into_one_record_per_call() {
+        let activity = ToolActivity::new();
+        activity.handle(&ToolStatusEvent::PermissionDecided {
+            call_id: 1,
+            tool: "shell_execute".to_string(),
+            summary: "cargo test".to_string(),
+            args: json!({ "command": "cargo test" }),
+            outcome: PermissionOutcome::AutoAllowed {
+                reason: "allow rule shell_execute(cargo test*)".to_string(),
+            },
+        });
+        activity.handle(&started(1, "shell_execute", "cargo test"));
+        activity.handle(&started(2, "read_file", "src/main.rs"));
+        assert_eq!(activity.records()[0].status(), CallStatus::Running);
+
+        activity.handle(&ToolStatusEvent::ToolCompleted {
+            call_id: 1,
+            tool: "shell_execute".to_string(),
+            duration_ms: 3200,
+            success: true,
+            output: "test result: ok".to_string(),
+        });
+        activity.handle(&ToolStatusEvent::ToolFailed {
+            call_id: 2,
+            tool: "read_file".to_string(),
+            duration_ms: 5,
+            error: "File not found".to_string(),
+        });
+
+        let records = activity.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].status(), CallStatus::Succeeded);
+        assert_eq!(records[0].duration_ms, Some(3200));
+        assert_eq!(records[0].output, "test result: ok");
+        assert!(matches!(
+            &records[0].permission,
+            Some(PermissionOutcome::AutoAllowed { reason }) if reason.contains("cargo test")
+        ));
+        assert_eq!(records[1].status(), CallStatus::Failed);
+        assert_eq!(records[1].permission, None);
+        assert_eq!(records[1].output, "File not found");
+    }
+
+    #[test]
+    fn test_denied_call_is_recorded_without_running() {
+        let activity = ToolActivity::new();
+        activity.handle(&ToolStatusEvent::PermissionDecided {
+            call_id: 7,
+            tool: "delete_file".to_string(),
+            summary: "Cargo.lock".to_string(),
+            args: json!({ "file_path": "Cargo.lock" }),
+            outcome: PermissionOutcome::Denied {
+                reason: "Delete Cargo.lock?".to_string(),
+            },
+        });
+
+        let records = activity.records();
+        assert_eq!(records[0].status(), CallStatus::Denied);
+        assert_eq!(records[0].duration_ms, None);
+        assert_eq!(records[0].args["file_path"], "Cargo.lock");
+
+        for call_id in 100..100 + MAX_RECORDS as u64 {
+            activity.handle(&started(call_id, "glob", "**/*.rs"));
+        }
+        let records = activity.records();
+        assert_eq!(records.len(), MAX_RECORDS);
+        assert_eq!(records[0].call_id, 100);
+    }
+}
@@ -0,0 +1,283 @@
+//! 工具结果的上下文预算
+//!
+//! 一次 grep 的两千行匹配、一份完整的构建日志如果原样放进对话，本轮之后的每次请求都会重复发送。
+//! 工具结果超过 `[tools] result_max_tokens` 个 token 时：完整结果保存到
+//! `.oxide/tool-results/<轮次>-<序号>.txt`，返回给模型的是开头和结尾的摘录、行数和 token 数，
+//! 以及保存路径，模型需要时用 read_file 的 offset/limit 读取具体范围。
+//!
+//! 内置的文件、搜索和命令工具都经过 [`MaybeHitlTool`](super::MaybeHitlTool)，在那里调用 [`fit_to_budget`]。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::token_counter::count_tokens;
+use crate::tools::settings::DEFAULT_RESULT_MAX_TOKENS;
+
+/// 保存完整工具结果的目录（相对于工作目录）
+pub const TOOL_RESULTS_DIR: &str = ".oxide/tool-results";
+
+/// 开头和结尾各保留的摘录行数
+const EXCERPT_LINES: usize = 20;
+
+/// 摘录中每行最多保留的字符数
+const EXCERPT_LINE_CHARS: usize = 300;
+
+static BUDGET: Lazy<RwLock<ResultBudget>> = Lazy::new(|| RwLock::new(ResultBudget::default()));
+
+/// 当前轮次（每轮对话开始时递增）
+static TURN: AtomicU64 = AtomicU64::new(0);
+
+/// 本轮已保存的工具结果数
+static SAVED_IN_TURN: AtomicU64 = AtomicU64::new(0);
+
+/// 返回给模型的工具结果：未超过预算时与工具的输出完全相同，否则为摘要
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ToolResultOutput<O> {
+    Full(O),
+    Summarized(String),
+}
+
+/// 工具结果的 token 预算
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultBudget {
+    /// 超过该 token 数时只放摘要，0 表示不限制
+    pub max_tokens: usize,
+    /// 保存完整结果的目录
+    pub dir: PathBuf,
+}
+
+impl Default for ResultBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESULT_MAX_TOKENS)
+    }
+}
+
+impl ResultBudget {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            dir: PathBuf::from(TOOL_RESULTS_DIR),
+        }
+    }
+
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// 超过预算时返回 token 数
+    pub fn exceeded_by(&self, text: &str) -> Option<usize> {
+        // 每个 token 至少一个字节，较短的结果不必计算
+        if self.max_tokens == 0 || text.len() <= self.max_tokens {
+            return None;
+        }
+        let tokens = count_tokens(text);
+        (tokens > self.max_tokens).then_some(tokens)
+    }
+
+    /// 结果超过预算时保存完整内容，返回代替它的摘要
+    ///
+    /// 未超过预算时返回 `Ok(None)`，结果原样发送。
+    pub fn summarize(&self, result: &Value, turn: u64, n: u64) -> io::Result<Option<String>> {
+        let serialized = serde_json::to_string(result).map_err(io::Error::other)?;
+        let Some(tokens) = self.exceeded_by(&serialized) else {
+            return Ok(None);
+        };
+        let text = render(result);
+        let path = save_result(&self.dir, turn, n, &text)?;
+        Ok(Some(summary(&path, &text, tokens)))
+    }
+}
+
+/// 设置全局预算（启动时按 `[tools]` 配置调用）
+pub fn configure(budget: ResultBudget) {
+    *BUDGET.write().unwrap_or_else(|e| e.into_inner()) = budget;
+}
+
+/// 开始新一轮对话，之后保存的结果文件以新的轮次编号
+pub fn begin_turn() {
+    TURN.fetch_add(1, Ordering::Relaxed);
+    SAVED_IN_TURN.store(0, Ordering::Relaxed);
+}
+
+/// 按全局预算处理工具输出，`result` 为它序列化后的 JSON
+///
+/// 保存失败时记录警告并原样返回输出，不影响工具调用本身。
+pub fn fit_to_budget<O>(tool: &str, output: O, result: &Value) -> ToolResultOutput<O> {
+    let budget = BUDGET.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let turn = TURN.load(Ordering::Relaxed);
+    let n = SAVED_IN_TURN.load(Ordering::Relaxed) + 1;
+    match budget.summarize(result, turn, n) {
+        Ok(Some(summary)) => {
+            SAVED_IN_TURN.fetch_add(1, Ordering::Relaxed);
+            ToolResultOutput::Summarized(summary)
+        }
+        Ok(None) => ToolResultOutput::Full(output),
+        Err(e) => {
+            tracing::warn!("failed to save {} result: {}", tool, e);
+            ToolResultOutput::Full(output)
+        }
+    }
+}
+
+/// 把工具结果转换为便于按行读取的文本
+///
+/// 字符串原样保留；对象逐个字段输出，多行的字符串字段（如命令输出）不转义换行，
+/// 数组和嵌套对象格式化为多行 JSON。
+pub fn render(result: &Value) -> String {
+    let Value::Object(fields) = result else {
+        return match result {
+            Value::String(text) => text.clone(),
+            other => serde_json::to_string_pretty(other).unwrap_or_default(),
+        };
+    };
+
+    let mut text = String::new();
+    for (key, value) in fields {
+        match value {
+            Value::String(s) if s.contains('\n') => {
+                text.push_str(&format!("{}:\n{}", key, s));
+                if !s.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            Value::String(s) => text.push_str(&format!("{}: {}\n", key, s)),
+            Value::Array(_) | Value::Object(_) => text.push_str(&format!(
+                "{}: {}\n",
+                key,
+                serde_json::to_string_pretty(value).unwrap_or_default()
+            )),
+            other => text.push_str(&format!("{}: {}\n", key, other)),
+        }
+    }
+    text
+}
+
+/// 保存到 `dir/<turn>-<n>.txt`，文件已存在（如之前的会话留下的）时递增序号
+fn save_result(dir: &Path, turn: u64, n: u64, text: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let mut n = n;
+    let mut path = dir.join(format!("{}-{}.txt", turn, n));
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.txt", turn, n));
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// 代替完整结果返回给模型的摘要：一行说明，加开头和结尾的摘录
+fn summary(path: &Path, text: &str, tokens: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let head = lines.len().min(EXCERPT_LINES);
+    let tail = (lines.len() - head).min(EXCERPT_LINES);
+    let omitted = lines.len() - head - tail;
+
+    let mut message = format!(
+        "[tool result too large for the conversation: {} lines (~{} tokens), full result saved to {}; use read_file with offset/limit to read specific ranges]\n",
+        lines.len(),
+        tokens,
+        path.display()
+    );
+    for line in &lines[..head] {
+        message.push_str(&excerpt_line(line));
+    }
+    if omitted > 0 {
+        message.push_str(&format!("[… {} lines omitted …]\n", omitted));
+    }
+    for line in &lines[lines.len() - tail..] {
+        message.push_str(&excerpt_line(line));
+    }
+    message
+}
+
+fn excerpt_line(line: &str) -> String {
+    match line.char_indices().nth(EXCERPT_LINE_CHARS) {
+        Some((index, _)) => format!("{}…\n", &line[..index]),
+        None => format!("{}\n", line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn build_log(lines: usize) -> String {
+        (1..=lines)
+            .map(|i| format!("   Compiling crate-{} v0.1.{} (/work/crate-{})\n", i, i, i))
+            .collect()
+    }
+
+    #[test]
+    fn test_small_results_are_sent_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let budget = ResultBudget::new(1000).with_dir(temp_dir.path());
+        let result = json!({"stdout": build_log(5), "success": true});
+        assert_eq!(budget.summarize(&result, 1, 1).unwrap(), None);
+
+        // 0 表示不限制
+        let budget = ResultBudget::new(0).with_dir(temp_dir.path());
+        let result = json!({"stdout": build_log(5000), "success": true});
+        assert_eq!(budget.summarize(&result, 1, 1).unwrap(), None);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        // 未超过预算时序列化结果与工具输出完全相同
+        let output: ToolResultOutput<Value> = ToolResultOutput::Full(result.clone());
+        assert_eq!(
+            serde_json::to_string(&output).unwrap(),
+            serde_json::to_string(&result).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_large_result_is_saved_and_summarized() {
+        let temp_dir = TempDir::new().unwrap();
+        let budget = ResultBudget::new(1000).with_dir(temp_dir.path());
+        let log = build_log(2000);
+        let result = json!({
+            "command": "cargo build",
+            "exit_code": 0,
+            "stderr": log,
+            "stdout": "",
+            "success": true,
+        });
+
+        let summary = budget.summarize(&result, 3, 1).unwrap().unwrap();
+        let path = temp_dir.path().join("3-1.txt");
+        assert!(summary.starts_with(&format!(
+            "[tool result too large for the conversation: 2005 lines (~{} tokens), full result saved to {};",
+            count_tokens(&serde_json::to_string(&result).unwrap()),
+            path.display()
+        )));
+        assert!(summary.contains("   Compiling crate-1 v0.1.1 (/work/crate-1)\n"));
+        assert!(summary.contains("[… 1965 lines omitted …]\n"));
+        assert!(summary.ends_with("success: true\n"));
+        assert!(!summary.contains("crate-1000 "));
+        assert!(summary.len() < 5000);
+
+        // 保存的文件包含完整结果，命令输出逐行保留
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, render(&result));
+        assert_eq!(
+            saved,
+            format!(
+                "command: cargo build\nexit_code: 0\nstderr:\n{}stdout: \nsuccess: true\n",
+                log
+            )
+        );
+
+        // 同名文件已存在时不覆盖
+        budget.summarize(&result, 3, 1).unwrap().unwrap();
+        assert!(temp_dir.path().join("3-2.txt").exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+    }
+}
@@ -0,0 +1,354 @@
+//! 工具执行状态事件
+//!
+//! Agent 的工具调用循环在工具开始、完成、失败时发出 `ToolStatusEvent`，
+//! 由注册的 `ToolStatusSink` 消费：交互式 CLI 渲染为实时状态行，
+//! `oxide -p --stream-json` 使用 `StreamJsonSink` 按行向 stderr 输出 JSON 事件。
+//! 模型的流式输出事件（`StreamEvent`）也通过同一组消费者广播，
+//! stream-json 输出中两类事件按发生顺序交错。需要确认的工具在开始前先发出
+//! `PermissionDecided`，与之后的事件使用同一个调用 ID；`/tools` 的活动记录
+//! （见 `super::tool_activity`）也由这些事件生成。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use super::stream_event::StreamEvent;
+use crate::tools::image::ImageInfo;
+use crate::tools::{redactor, ToolFailure};
+
+/// 参数摘要的最大字符数
+const MAX_SUMMARY_CHARS: usize = 60;
+
+/// 完成事件中工具输出的最大字符数
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+/// 用于生成参数摘要的字段（按优先级）
+const SUMMARY_KEYS: &[&str] = &[
+    "command",
+    "file_path",
+    "dir_path",
+    "pattern",
+    "query",
+    "root_path",
+    "url",
+];
+
+/// 工具调用的权限结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum PermissionOutcome {
+    /// 没有询问用户（只读操作、allow 规则、会话中记住的规则等），`reason` 说明依据
+    AutoAllowed { reason: String },
+    /// 用户批准
+    Approved,
+    /// 用户拒绝或被 deny 规则拦截
+    Denied { reason: String },
+}
+
+/// 工具执行状态事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolStatusEvent {
+    /// 权限检查有了结果（被拒绝的调用之后没有其他事件）
+    PermissionDecided {
+        call_id: u64,
+        tool: String,
+        summary: String,
+        /// 工具参数（已隐藏认证信息）
+        #[serde(default, skip_serializing_if = "Value::is_null")]
+        args: Value,
+        outcome: PermissionOutcome,
+    },
+    /// 工具开始执行
+    ToolStarted {
+        call_id: u64,
+        tool: String,
+        summary: String,
+        /// 工具参数（已隐藏认证信息）
+        #[serde(default, skip_serializing_if = "Value::is_null")]
+        args: Value,
+    },
+    /// 工具执行完成（`success` 为 false 表示工具返回了失败结果，如命令退出码非零）
+    ToolCompleted {
+        call_id: u64,
+        tool: String,
+        duration_ms: u64,
+        success: bool,
+        /// 工具输出（脱敏并截取前 2000 个字符）
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        output: String,
+    },
+    /// 工具执行出错
+    ToolFailed {
+        call_id: u64,
+        tool: String,
+        duration_ms: u64,
+        error: String,
+    },
+    /// 工具输出了图片（`read_file` 读到图片、MCP 工具返回图片），在完成事件之后发出
+    ImageOutput {
+        call_id: u64,
+        tool: String,
+        image: ImageInfo,
+    },
+}
+
+/// 工具状态事件消费者
+pub trait ToolStatusSink: Send + Sync {
+    fn handle(&self, event: &ToolStatusEvent);
+
+    /// 模型流式输出事件，默认忽略
+    fn handle_stream(&self, _event: &StreamEvent) {}
+}
+
+/// 全局事件消费者列表
+static SINKS: Lazy<RwLock<Vec<Arc<dyn ToolStatusSink>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 调用 ID 计数器
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 注册事件消费者
+pub fn register_sink(sink: Arc<dyn ToolStatusSink>) {
+    if let Ok(mut sinks) = SINKS.write() {
+        sinks.push(sink);
+    }
+}
+
+/// 分配新的调用 ID
+pub fn next_call_id() -> u64 {
+    NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 向所有消费者广播事件
+pub fn emit(event: ToolStatusEvent) {
+    if let Ok(sinks) = SINKS.read() {
+        for sink in sinks.iter() {
+            sink.handle(&event);
+        }
+    }
+}
+
+/// 向所有消费者广播模型流式输出事件
+pub fn emit_stream(event: &StreamEvent) {
+    if let Ok(sinks) = SINKS.read() {
+        for sink in sinks.iter() {
+            sink.handle_stream(event);
+        }
+    }
+}
+
+/// 工具开始执行，返回本次调用 ID
+pub fn emit_started(tool: &str, args: &Value) -> u64 {
+    let call_id = next_call_id();
+    emit_started_as(call_id, tool, args);
+    call_id
+}
+
+/// 使用已分配的调用 ID（权限检查时分配）发出开始事件
+pub fn emit_started_as(call_id: u64, tool: &str, args: &Value) {
+    emit(ToolStatusEvent::ToolStarted {
+        call_id,
+        tool: tool.to_string(),
+        summary: summarize_args(args),
+        args: args.clone(),
+    });
+}
+
+/// 权限检查有了结果
+pub fn emit_permission(call_id: u64, tool: &str, args: &Value, outcome: PermissionOutcome) {
+    emit(ToolStatusEvent::PermissionDecided {
+        call_id,
+        tool: tool.to_string(),
+        summary: summarize_args(args),
+        args: args.clone(),
+        outcome,
+    });
+}
+
+/// 工具执行完成
+pub fn emit_completed(call_id: u64, tool: &str, duration: Duration, output: &Value) {
+    let text = match output {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    emit(ToolStatusEvent::ToolCompleted {
+        call_id,
+        tool: tool.to_string(),
+        duration_ms: duration.as_millis() as u64,
+        success: output_succeeded(output),
+        output: redactor::redact_if_enabled(&truncate_chars(&text, MAX_OUTPUT_CHARS)).text,
+    });
+    if let Some(image) = output
+        .get("image")
+        .and_then(|image| ImageInfo::deserialize(image).ok())
+    {
+        emit_image(call_id, tool, image);
+    }
+}
+
+/// 工具输出了图片
+pub fn emit_image(call_id: u64, tool: &str, image: ImageInfo) {
+    emit(ToolStatusEvent::ImageOutput {
+        call_id,
+        tool: tool.to_string(),
+        image,
+    });
+}
+
+/// 工具执行出错（结构化错误只显示其中的 `message`）
+pub fn emit_failed(call_id: u64, tool: &str, duration: Duration, error: &str) {
+    emit(ToolStatusEvent::ToolFailed {
+        call_id,
+        tool: tool.to_string(),
+        duration_ms: duration.as_millis() as u64,
+        error: first_line(&ToolFailure::display_message(error)),
+    });
+}
+
+/// 生成参数摘要（如 bash 命令或文件路径）
+pub fn summarize_args(args: &Value) -> String {
+    let summary = SUMMARY_KEYS
+        .iter()
+        .find_map(|key| args.get(*key).and_then(Value::as_str))
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    truncate_chars(&first_line(&summary), MAX_SUMMARY_CHARS)
+}
+
+/// 根据工具输出判断是否成功（`success: false` 或非零退出码视为失败）
+fn output_succeeded(output: &Value) -> bool {
+    let success = output.get("success").and_then(Value::as_bool).unwrap_or(true);
+    let exit_ok = output
+        .get("exit_code")
+        .and_then(Value::as_i64)
+        .is_none_or(|code| code == 0);
+    success && exit_ok
+}
+
+fn first_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max).collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// 以 stream-json 格式（每行一个 JSON 对象）输出事件
+pub struct StreamJsonSink<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> StreamJsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// 输出一行 JSON
+    fn write_line<T: Serialize>(&self, event: &T) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{}", line);
+            let _ = writer.flush();
+        }
+    }
+
+    /// 取回底层 writer
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W: Write + Send> ToolStatusSink for StreamJsonSink<W> {
+    fn handle(&self, event: &ToolStatusEvent) {
+        self.write_line(event);
+    }
+
+    fn handle_stream(&self, event: &StreamEvent) {
+        self.write_line(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summarize_args() {
+        assert_eq!(
+            summarize_args(&json!({ "command": "cargo test\ncargo build" })),
+            "cargo test"
+        );
+        assert_eq!(
+            summarize_args(&json!({ "file_path": "src/main.rs", "content": "fn main() {}" })),
+            "src/main.rs"
+        );
+        assert_eq!(summarize_args(&json!({ "unknown": 1 })), "");
+
+        let long = "x".repeat(100);
+        let summary = summarize_args(&json!({ "query": long }));
+        assert_eq!(summary.chars().count(), MAX_SUMMARY_CHARS + 3);
+    }
+
+    #[test]
+    fn test_output_success_detection() {
+        assert!(output_succeeded(&json!({ "content": "ok" })));
+        assert!(!output_succeeded(&json!({ "success": false })));
+        assert!(!output_succeeded(&json!({ "success": true, "exit_code": 101 })));
+    }
+
+    #[test]
+    fn test_stream_json_lines() {
+        let sink = StreamJsonSink::new(Vec::new());
+        sink.handle(&ToolStatusEvent::ToolStarted {
+            call_id: 1,
+            tool: "shell_execute".to_string(),
+            summary: "cargo test".to_string(),
+            args: Value::Null,
+        });
+        sink.handle(&ToolStatusEvent::ToolFailed {
+            call_id: 1,
+            tool: "shell_execute".to_string(),
+            duration_ms: 1200,
+            error: first_line("permission denied\nstack trace"),
+        });
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let events: Vec<ToolStatusEvent> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert!(output.lines().next().unwrap().contains(r#""type":"tool_started""#));
+        assert_eq!(
+            events[1],
+            ToolStatusEvent::ToolFailed {
+                call_id: 1,
+                tool: "shell_execute".to_string(),
+                duration_ms: 1200,
+                error: "permission denied".to_string(),
+            }
+        );
+    }
+}
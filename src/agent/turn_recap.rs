@@ -0,0 +1,443 @@
+//! 复杂一轮结束后的活动回顾
+//!
+//! 工具调用超过 `[render] turn_recap` 次或调用了子 agent 的一轮，在回答之后附上一段回顾：
+//! 读了几个文件、改了哪些文件、运行了哪些命令（去重，带退出码）、调用了哪些子 agent（类型和耗时）、
+//! 访问了哪些域名，以及本轮的耗时和 token 用量。回顾不属于模型的回答：终端中显示为暗色的页脚，
+//! 同时写入会话文件和 `oxide serve` 的事件流，供之后分析。
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::turn_summary::compact_tokens;
+
+/// 终端中命令的最大显示字符数
+const MAX_COMMAND_CHARS: usize = 60;
+
+/// 工具出错时 rig 写入工具结果的前缀
+const TOOL_ERROR: &str = "ToolCallError: ";
+
+/// 读取文件的工具
+const READ_TOOLS: &[&str] = &["read_file"];
+
+/// 修改文件的工具（`apply_patch` 和 `multi_edit` 另行处理）
+const WRITE_TOOLS: &[&str] = &[
+    "write_file",
+    "edit_file",
+    "search_replace",
+    "notebook_edit",
+    "delete_file",
+];
+
+/// 运行过的一条命令（相同命令合并，保留最后一次的退出码）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandRun {
+    pub command: String,
+    /// 命令没有运行完（被拒绝、超时）时为 `None`
+    pub exit_code: Option<i32>,
+    pub runs: usize,
+}
+
+/// 调用过的一个子 agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubagentRun {
+    pub agent_type: String,
+    pub duration_ms: u64,
+}
+
+/// 一轮的活动回顾
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnRecap {
+    pub tool_calls: usize,
+    /// 读取过的不同文件数
+    pub files_read: usize,
+    pub files_modified: Vec<String>,
+    pub commands: Vec<CommandRun>,
+    pub subagents: Vec<SubagentRun>,
+    pub web_domains: Vec<String>,
+    pub elapsed_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TurnRecap {
+    /// 工具调用超过 `threshold` 次或调用了子 agent 时值得显示（`threshold` 为 0 时关闭）
+    pub fn is_notable(&self, threshold: usize) -> bool {
+        threshold > 0 && (self.tool_calls > threshold || !self.subagents.is_empty())
+    }
+
+    /// 终端中显示的各行（第一行为总览），只包含本轮实际发生的活动
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![t!(
+            "cli.turn_recap.header",
+            tool_calls = self.tool_calls,
+            elapsed = format!("{:.1}", self.elapsed_ms as f64 / 1000.0),
+            output = compact_tokens(self.output_tokens),
+            input = compact_tokens(self.input_tokens)
+        )];
+        if self.files_read > 0 {
+            lines.push(t!("cli.turn_recap.files_read", count = self.files_read));
+        }
+        if !self.files_modified.is_empty() {
+            lines.push(t!(
+                "cli.turn_recap.files_modified",
+                files = self.files_modified.join(", ")
+            ));
+        }
+        if !self.commands.is_empty() {
+            let commands: Vec<String> = self.commands.iter().map(describe_command).collect();
+            lines.push(t!(
+                "cli.turn_recap.commands",
+                commands = commands.join(", ")
+            ));
+        }
+        if !self.subagents.is_empty() {
+            let subagents: Vec<String> = self
+                .subagents
+                .iter()
+                .map(|run| {
+                    format!(
+                        "{} ({:.1}s)",
+                        run.agent_type,
+                        run.duration_ms as f64 / 1000.0
+                    )
+                })
+                .collect();
+            lines.push(t!(
+                "cli.turn_recap.subagents",
+                subagents = subagents.join(", ")
+            ));
+        }
+        if !self.web_domains.is_empty() {
+            lines.push(t!(
+                "cli.turn_recap.web",
+                domains = self.web_domains.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+fn describe_command(run: &CommandRun) -> String {
+    let first_line = run.command.lines().next().unwrap_or_default();
+    let mut command: String = first_line.chars().take(MAX_COMMAND_CHARS).collect();
+    if command.len() < run.command.len() {
+        command.push_str("...");
+    }
+    if run.runs > 1 {
+        command.push_str(&format!(" ×{}", run.runs));
+    }
+    match run.exit_code {
+        Some(code) => format!("{} (exit {})", command, code),
+        None => command,
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecapState {
+    tool_calls: usize,
+    files_read: BTreeSet<String>,
+    files_modified: Vec<String>,
+    commands: Vec<CommandRun>,
+    subagents: Vec<SubagentRun>,
+    web_domains: Vec<String>,
+    /// 进行中的子 agent 调用：调用 ID → 开始时间
+    subagent_starts: HashMap<String, Instant>,
+}
+
+impl RecapState {
+    fn modified(&mut self, path: &str) {
+        if !path.is_empty() && !self.files_modified.iter().any(|p| p == path) {
+            self.files_modified.push(path.to_string());
+        }
+    }
+}
+
+/// 收集一轮中的工具调用（克隆出的追踪器共享同一份）
+#[derive(Debug, Clone, Default)]
+pub struct RecapTracker {
+    state: Arc<Mutex<RecapState>>,
+}
+
+impl RecapTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 工具开始执行
+    pub fn record_call(&self, tool: &str, call_id: Option<&str>) {
+        self.record_call_at(tool, call_id, Instant::now());
+    }
+
+    pub fn record_call_at(&self, tool: &str, call_id: Option<&str>, at: Instant) {
+        if tool != "task" {
+            return;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            state
+                .subagent_starts
+                .insert(call_id.unwrap_or_default().to_string(), at);
+        }
+    }
+
+    /// 工具执行完成（`result` 为返回给模型的结果）
+    pub fn record_result(&self, tool: &str, call_id: Option<&str>, args: &str, result: &str) {
+        self.record_result_at(tool, call_id, args, result, Instant::now());
+    }
+
+    pub fn record_result_at(
+        &self,
+        tool: &str,
+        call_id: Option<&str>,
+        args: &str,
+        result: &str,
+        at: Instant,
+    ) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.tool_calls += 1;
+        let args: Value = serde_json::from_str(args).unwrap_or(Value::Null);
+        let failed = result.trim_start().starts_with(TOOL_ERROR);
+        let output: Value = serde_json::from_str(result).unwrap_or(Value::Null);
+        let field = |value: &Value, name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        match tool {
+            _ if READ_TOOLS.contains(&tool) && !failed => {
+                state.files_read.insert(field(&args, "file_path"));
+            }
+            _ if WRITE_TOOLS.contains(&tool) && !failed => {
+                let path = field(&args, "file_path");
+                let path = if path.is_empty() {
+                    field(&args, "notebook_path")
+                } else {
+                    path
+                };
+                state.modified(&path);
+            }
+            "multi_edit" if !failed => {
+                for edit in args["edits"].as_array().into_iter().flatten() {
+                    state.modified(&field(edit, "file_path"));
+                }
+            }
+            "apply_patch" if !failed => {
+                for path in patched_files(&output, &field(&args, "patch")) {
+                    state.modified(&path);
+                }
+            }
+            "shell_execute" => {
+                let command = field(&args, "command");
+                let exit_code = output
+                    .get("exit_code")
+                    .and_then(Value::as_i64)
+                    .map(|code| code as i32);
+                match state.commands.iter_mut().find(|run| run.command == command) {
+                    Some(run) => {
+                        run.exit_code = exit_code;
+                        run.runs += 1;
+                    }
+                    None => state.commands.push(CommandRun {
+                        command,
+                        exit_code,
+                        runs: 1,
+                    }),
+                }
+            }
+            "task" => {
+                let started = state
+                    .subagent_starts
+                    .remove(call_id.unwrap_or_default())
+                    .unwrap_or(at);
+                let agent_type = match field(&args, "agent_type") {
+                    agent_type if agent_type.is_empty() => "main".to_string(),
+                    agent_type => agent_type,
+                };
+                state.subagents.push(SubagentRun {
+                    agent_type,
+                    duration_ms: at.saturating_duration_since(started).as_millis() as u64,
+                });
+            }
+            "http_request" => {
+                let host = Url::parse(&field(&args, "url"))
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string));
+                if let Some(host) = host {
+                    if !state.web_domains.contains(&host) {
+                        state.web_domains.push(host);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 本轮的回顾
+    pub fn finish(&self, elapsed: Duration, input_tokens: u64, output_tokens: u64) -> TurnRecap {
+        let Ok(state) = self.state.lock() else {
+            return TurnRecap::default();
+        };
+        TurnRecap {
+            tool_calls: state.tool_calls,
+            files_read: state.files_read.iter().filter(|p| !p.is_empty()).count(),
+            files_modified: state.files_modified.clone(),
+            commands: state.commands.clone(),
+            subagents: state.subagents.clone(),
+            web_domains: state.web_domains.clone(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            input_tokens,
+            output_tokens,
+        }
+    }
+}
+
+/// `apply_patch` 改动的文件：优先取结果中的 `files`，结果被替换为摘要时从补丁头中解析
+fn patched_files(output: &Value, patch: &str) -> Vec<String> {
+    let from_output: Vec<String> = output["files"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|file| file.get("path").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+    if !from_output.is_empty() {
+        return from_output;
+    }
+    let mut files = Vec::new();
+    let mut old_path = None;
+    for line in patch.lines() {
+        if let Some(path) = line.strip_prefix("--- ") {
+            old_path = Some(path.trim().trim_start_matches("a/").to_string());
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.trim();
+            let path = if path == "/dev/null" {
+                old_path.take().unwrap_or_default()
+            } else {
+                path.trim_start_matches("b/").to_string()
+            };
+            files.push(path);
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_recap_from_tool_events() {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let tracker = RecapTracker::new();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let result = |tool: &str, args: Value, result: Value| {
+            tracker.record_result_at(tool, None, &args.to_string(), &result.to_string(), at(0));
+        };
+
+        result("read_file", json!({ "file_path": "src/a.rs" }), json!({}));
+        result("read_file", json!({ "file_path": "src/a.rs" }), json!({}));
+        result("read_file", json!({ "file_path": "src/b.rs" }), json!({}));
+        result(
+            "edit_file",
+            json!({ "file_path": "src/a.rs" }),
+            json!({ "success": true }),
+        );
+        tracker.record_result_at(
+            "write_file",
+            None,
+            &json!({ "file_path": "src/denied.rs" }).to_string(),
+            "ToolCallError: {\"code\":\"PermissionDenied\"}",
+            at(0),
+        );
+        result(
+            "apply_patch",
+            json!({ "patch": "--- a/src/a.rs\n+++ b/src/a.rs\n--- a/old.rs\n+++ /dev/null\n" }),
+            json!("Applied patch to 2 files"),
+        );
+        result(
+            "shell_execute",
+            json!({ "command": "cargo test" }),
+            json!({ "exit_code": 101 }),
+        );
+        result(
+            "shell_execute",
+            json!({ "command": "cargo test" }),
+            json!({ "exit_code": 0 }),
+        );
+        result(
+            "http_request",
+            json!({ "url": "https://docs.rs/serde" }),
+            json!({}),
+        );
+        tracker.record_call_at("task", Some("t1"), at(10));
+        tracker.record_result_at(
+            "task",
+            Some("t1"),
+            &json!({ "agent_type": "explore" }).to_string(),
+            "{}",
+            at(42),
+        );
+
+        let recap = tracker.finish(Duration::from_millis(61_500), 12_400, 1_830);
+        assert_eq!(recap.tool_calls, 10);
+        assert_eq!(recap.files_read, 2);
+        assert_eq!(recap.files_modified, ["src/a.rs", "old.rs"]);
+        assert_eq!(
+            recap.commands,
+            [CommandRun {
+                command: "cargo test".to_string(),
+                exit_code: Some(0),
+                runs: 2,
+            }]
+        );
+        assert_eq!(
+            recap.subagents,
+            [SubagentRun {
+                agent_type: "explore".to_string(),
+                duration_ms: 32_000,
+            }]
+        );
+        assert_eq!(recap.web_domains, ["docs.rs"]);
+        assert!(recap.is_notable(5));
+        assert!(!recap.is_notable(0));
+
+        assert_eq!(
+            recap.lines(),
+            [
+                "Turn recap: 10 tool calls · 61.5s · 1.8k out / 12.4k in tokens",
+                "read 2 files",
+                "modified src/a.rs, old.rs",
+                "ran cargo test ×2 (exit 0)",
+                "subagents: explore (32.0s)",
+                "fetched docs.rs",
+            ]
+        );
+
+        let value = serde_json::to_value(&recap).unwrap();
+        assert_eq!(value["files_modified"], json!(["src/a.rs", "old.rs"]));
+        assert_eq!(value["commands"][0]["exit_code"], 0);
+        assert_eq!(value["subagents"][0]["duration_ms"], 32_000);
+        assert_eq!(serde_json::from_value::<TurnRecap>(value).unwrap(), recap);
+    }
+
+    #[test]
+    fn test_short_turn_not_notable() {
+        let tracker = RecapTracker::new();
+        for _ in 0..5 {
+            tracker.record_result("glob", None, "{}", "[]");
+        }
+        let recap = tracker.finish(Duration::from_secs(3), 100, 10);
+        assert!(!recap.is_notable(5));
+        assert!(recap.is_notable(4));
+    }
+}
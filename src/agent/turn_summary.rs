@@ -0,0 +1,125 @@
+//! 每轮结束后的摘要：耗时、工具调用次数、token 用量和停止原因
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Agent 单次模型请求的输出 token 上限
+pub const MAX_OUTPUT_TOKENS: u64 = 4096;
+
+/// 模型停止输出的原因
+///
+/// rig 的流式结果不带服务商返回的 stop_reason，这里按最后一次请求推断：
+/// 有工具调用为 `tool_use`（一轮结束时仍是 `tool_use` 说明达到了工具轮数上限），
+/// 输出 token 达到上限为 `max_tokens`，其余为 `end_turn`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    EndTurn,
+    ToolUse,
+    MaxTokens,
+}
+
+impl StopReason {
+    /// 根据一次模型请求的工具调用次数和输出 token 推断
+    pub fn infer(tool_calls: usize, output_tokens: Option<u64>) -> Self {
+        if tool_calls > 0 {
+            Self::ToolUse
+        } else if output_tokens.is_some_and(|tokens| tokens >= MAX_OUTPUT_TOKENS) {
+            Self::MaxTokens
+        } else {
+            Self::EndTurn
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::EndTurn => "end_turn",
+            Self::ToolUse => "tool_use",
+            Self::MaxTokens => "max_tokens",
+        }
+    }
+}
+
+/// 一轮对话的摘要
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TurnSummary {
+    #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+    pub tool_calls: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub stop_reason: StopReason,
+}
+
+impl TurnSummary {
+    /// 单行摘要，如 `⏱ 14.2s · 3 tool calls · 1.8k out / 12.4k in tokens · stop: end_turn`
+    pub fn line(&self) -> String {
+        t!(
+            "cli.turn_summary.line",
+            elapsed = format!("{:.1}", self.elapsed.as_secs_f64()),
+            tool_calls = self.tool_calls,
+            output = compact_tokens(self.output_tokens),
+            input = compact_tokens(self.input_tokens),
+            stop = self.stop_reason.as_str()
+        )
+    }
+
+    /// 回复是否因达到输出上限被截断
+    pub fn truncated(&self) -> bool {
+        self.stop_reason == StopReason::MaxTokens
+    }
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    elapsed: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(elapsed.as_millis() as u64)
+}
+
+/// 紧凑的 token 数：`950`、`1.8k`、`12.4k`、`1.2M`
+pub(crate) fn compact_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_reason_inference() {
+        assert_eq!(StopReason::infer(2, Some(120)), StopReason::ToolUse);
+        assert_eq!(
+            StopReason::infer(0, Some(MAX_OUTPUT_TOKENS)),
+            StopReason::MaxTokens
+        );
+        assert_eq!(StopReason::infer(0, Some(300)), StopReason::EndTurn);
+        assert_eq!(StopReason::infer(0, None), StopReason::EndTurn);
+    }
+
+    #[test]
+    fn test_summary_line_and_json() {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let summary = TurnSummary {
+            elapsed: Duration::from_millis(14_230),
+            tool_calls: 3,
+            input_tokens: 12_400,
+            output_tokens: 1_830,
+            stop_reason: StopReason::EndTurn,
+        };
+        assert_eq!(
+            summary.line(),
+            "⏱ 14.2s · 3 tool calls · 1.8k out / 12.4k in tokens · stop: end_turn"
+        );
+        assert!(!summary.truncated());
+
+        let value = serde_json::to_value(summary).unwrap();
+        assert_eq!(value["elapsed_ms"], 14_230);
+        assert_eq!(value["tool_calls"], 3);
+        assert_eq!(value["stop_reason"], "end_turn");
+    }
+}
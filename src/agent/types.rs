@@ -61,12 +61,12 @@ impl AgentType {
     #[allow(dead_code)]
     pub fn description(&self) -> &'static str {
         match self {
-            AgentType::Main => "主对话 Agent，具有所有工具访问权限",
-            AgentType::Explore => "代码库探索 Agent，用于快速分析代码结构和搜索文件",
-            AgentType::Plan => "架构规划 Agent，用于设计实现方案和规划任务",
-            AgentType::CodeReviewer => "代码审查 Agent，用于检查代码质量和安全性",
-            AgentType::FrontendDeveloper => "前端开发 Agent，专注于 UI/UX 实现",
-            AgentType::General => "通用 Agent，用于一般性任务",
+            AgentType::Main => t!("agents.main"),
+            AgentType::Explore => t!("agents.explore"),
+            AgentType::Plan => t!("agents.plan"),
+            AgentType::CodeReviewer => t!("agents.code_reviewer"),
+            AgentType::FrontendDeveloper => t!("agents.frontend_developer"),
+            AgentType::General => t!("agents.general"),
         }
     }
 }
@@ -119,14 +119,22 @@ impl AgentCapability {
         Self {
             agent_type: AgentType::Main,
             name: "Main Agent".to_string(),
-            description: "主对话 Agent，具有所有工具访问权限".to_string(),
+            description: t!("agents.main").to_string(),
             tools: vec![
                 "read_file".to_string(),
                 "write_file".to_string(),
                 "edit_file".to_string(),
+                "apply_patch".to_string(),
                 "delete_file".to_string(),
                 "shell_execute".to_string(),
+                "run_tests".to_string(),
+                "diagnostics".to_string(),
+                "http_request".to_string(),
+                "memory".to_string(),
                 "scan_codebase".to_string(),
+                "outline".to_string(),
+                "diff".to_string(),
+                "deps".to_string(),
                 "create_directory".to_string(),
                 "grep_search".to_string(),
                 "search_replace".to_string(),
@@ -212,11 +220,12 @@ Please provide clear and concise responses and be careful when modifying files o
         Self {
             agent_type: AgentType::Explore,
             name: "Explore Agent".to_string(),
-            description: "代码库探索 Agent，用于快速分析代码结构和搜索文件".to_string(),
+            description: t!("agents.explore").to_string(),
             tools: vec![
                 "read_file".to_string(),
                 "grep_search".to_string(),
                 "scan_codebase".to_string(),
+                "outline".to_string(),
             ],
             system_prompt: r#"
 You are an Explore Agent specialized in codebase exploration and analysis.
@@ -237,11 +246,12 @@ Use Glob for file pattern matching and Grep for content searching.
         Self {
             agent_type: AgentType::Plan,
             name: "Plan Agent".to_string(),
-            description: "架构规划 Agent，用于设计实现方案和规划任务".to_string(),
+            description: t!("agents.plan").to_string(),
             tools: vec![
                 "read_file".to_string(),
                 "grep_search".to_string(),
                 "scan_codebase".to_string(),
+                "outline".to_string(),
                 "todo_write".to_string(),
             ],
             system_prompt: r#"
@@ -263,11 +273,13 @@ When planning, be thorough but focus on practical, implementable solutions.
         Self {
             agent_type: AgentType::CodeReviewer,
             name: "Code Reviewer Agent".to_string(),
-            description: "代码审查 Agent，用于检查代码质量和安全性".to_string(),
+            description: t!("agents.code_reviewer").to_string(),
             tools: vec![
                 "read_file".to_string(),
                 "grep_search".to_string(),
                 "scan_codebase".to_string(),
+                "outline".to_string(),
+                "diff".to_string(),
             ],
             system_prompt: r#"
 You are a Code Reviewer Agent specialized in code quality analysis and security review.
@@ -288,7 +300,7 @@ Focus on high-priority issues that truly matter. Be constructive and specific in
         Self {
             agent_type: AgentType::FrontendDeveloper,
             name: "Frontend Developer Agent".to_string(),
-            description: "前端开发 Agent，专注于 UI/UX 实现".to_string(),
+            description: t!("agents.frontend_developer").to_string(),
             tools: vec![
                 "read_file".to_string(),
                 "write_file".to_string(),
@@ -104,7 +104,7 @@ impl WorkflowExecutor {
         self.orchestrator.start().await?;
 
         if self.verbose {
-            println!("🚀 启动 PAOR 工作流...\n");
+            println!("{}\n", t!("workflow.starting"));
         }
 
         // 执行循环
@@ -120,7 +120,7 @@ impl WorkflowExecutor {
                 state.phase,
                 state.iteration,
                 state.max_iterations,
-                format!("执行阶段: {}", state.phase),
+                t!("workflow.progress_phase", phase = state.phase),
             );
 
             if let Some(ref callback) = self.progress_callback {
@@ -129,8 +129,13 @@ impl WorkflowExecutor {
 
             if self.verbose {
                 println!(
-                    "🔄 迭代 {}/{} | 阶段: {}",
-                    state.iteration, state.max_iterations, state.phase
+                    "{}",
+                    t!(
+                        "workflow.iteration",
+                        iteration = state.iteration,
+                        max = state.max_iterations,
+                        phase = state.phase
+                    )
                 );
             }
 
@@ -145,7 +150,7 @@ impl WorkflowExecutor {
             // 防止无限循环
             if iteration >= 100 {
                 if self.verbose {
-                    println!("⚠️  达到最大迭代次数限制");
+                    println!("{}", t!("workflow.max_iterations"));
                 }
                 break;
             }
@@ -161,7 +166,7 @@ impl WorkflowExecutor {
         let final_response = self.orchestrator.get_final_response().await;
 
         if self.verbose {
-            println!("\n✅ 工作流执行完成\n");
+            println!("\n{}\n", t!("workflow.completed"));
             println!("{}", summary);
         }
 
@@ -218,12 +223,11 @@ impl WorkflowResult {
         } else if self.success {
             self.summary.clone()
         } else {
-            format!(
-                "工作流执行失败: {}",
-                self.failure_reason
-                    .as_ref()
-                    .unwrap_or(&"未知原因".to_string())
-            )
+            let reason = self
+                .failure_reason
+                .as_deref()
+                .unwrap_or(t!("workflow.unknown_reason"));
+            t!("workflow.failed", reason = reason)
         }
     }
 }
@@ -9,6 +9,7 @@ pub mod observation;
 pub mod tool_wrapper;
 pub mod executor;
 pub mod complexity;
+pub mod output_parser;
 
 pub use orchestrator::{WorkflowOrchestrator, OrchestratorConfig};
 pub use state::{WorkflowState, WorkflowPhase};
@@ -2,6 +2,7 @@
 //!
 //! 负责收集和管理工作流执行过程中的观察数据。
 
+use super::output_parser::ParsePath;
 use super::types::Observation;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
@@ -61,6 +62,12 @@ impl ObservationCollector {
         self.add(observation);
     }
     
+    /// 添加结构化输出解析观察
+    pub fn add_output_parse(&self, source: String, path: ParsePath, errors: &[String]) {
+        let observation = Observation::output_parse(source, path, errors);
+        self.add(observation);
+    }
+    
     /// 获取所有观察数据
     pub fn get_all(&self) -> Vec<Observation> {
         self.observations.read()
@@ -244,6 +251,27 @@ mod tests {
         assert_eq!(failures[0].source, "tool2");
     }
     
+    #[test]
+    fn test_output_parse_observation() {
+        let collector = ObservationCollector::new();
+        collector.add_output_parse("plan".to_string(), ParsePath::Repaired, &[]);
+        collector.add_output_parse(
+            "reflection".to_string(),
+            ParsePath::Fallback,
+            &["progress: required field is missing".to_string()],
+        );
+
+        let parses = collector.get_by_type("output_parse");
+        assert_eq!(parses.len(), 2);
+        assert_eq!(parses[0].metadata["parse_path"], "repaired");
+        assert!(parses[0].success);
+
+        let failures = collector.get_failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].source, "reflection");
+        assert!(failures[0].error.as_deref().unwrap().contains("progress"));
+    }
+    
     #[test]
     fn test_observation_summary() {
         let collector = ObservationCollector::new();
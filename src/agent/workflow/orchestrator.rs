@@ -3,6 +3,7 @@
 //! 实现 Plan-Act-Observe-Reflect (PAOR) 循环的核心逻辑。
 
 use super::observation::ObservationCollector;
+use super::output_parser::{self, ParsePath, Parsed};
 use super::state::{WorkflowPhase, WorkflowState};
 use super::types::{ExecutionType, ObservationAnalysis, Plan, Reflection, Task, TaskId, TaskStatus};
 use crate::agent::builder::AgentEnum;
@@ -168,7 +169,7 @@ impl WorkflowOrchestrator {
     /// 异步执行计划阶段
     async fn execute_planning_phase_async(&self, agent: &AgentEnum) -> Result<()> {
         if self.config.verbose {
-            println!("📋 进入规划阶段...");
+            println!("{}", t!("workflow.planning"));
         }
 
         let (user_request, reflections_summary) = {
@@ -225,11 +226,23 @@ execution_type 可选值：
         let response = self.call_llm(agent, &planning_prompt).await?;
 
         // 解析计划
-        let plan = match Plan::from_llm_response(&response) {
+        let plan = match self
+            .parse_with_repair(
+                agent,
+                "plan",
+                &planning_prompt,
+                &response,
+                output_parser::parse_plan,
+            )
+            .await
+        {
             Ok(p) => p,
-            Err(e) => {
+            Err(errors) => {
                 if self.config.verbose {
-                    println!("⚠️  计划解析失败: {}，使用默认计划", e);
+                    println!(
+                        "{}",
+                        t!("workflow.plan_parse_failed", error = errors.join("; "))
+                    );
                 }
                 // 使用默认计划
                 self.generate_default_plan(&user_request)
@@ -262,7 +275,7 @@ execution_type 可选值：
     /// 异步执行执行阶段
     async fn execute_acting_phase_async(&self, agent: &AgentEnum) -> Result<()> {
         if self.config.verbose {
-            println!("🎬 进入执行阶段...");
+            println!("{}", t!("workflow.acting"));
         }
 
         // 获取可执行的任务（依赖已满足的 Pending 任务）
@@ -270,7 +283,7 @@ execution_type 可选值：
 
         if executable_tasks.is_empty() {
             if self.config.verbose {
-                println!("  没有可执行的任务");
+                println!("{}", t!("workflow.no_tasks"));
             }
             // 转换到 Observing 阶段
             let mut state = self.state.write().await;
@@ -281,7 +294,14 @@ execution_type 可选值：
         // 执行每个任务
         for task in executable_tasks {
             if self.config.verbose {
-                println!("  执行任务: {} - {}", task.id, task.description);
+                println!(
+                    "{}",
+                    t!(
+                        "workflow.running_task",
+                        id = task.id,
+                        description = task.description
+                    )
+                );
             }
 
             // 标记任务开始
@@ -341,7 +361,7 @@ execution_type 可选值：
     /// 执行观察阶段
     async fn execute_observing_phase(&self) -> Result<()> {
         if self.config.verbose {
-            println!("👁️  进入观察阶段...");
+            println!("{}", t!("workflow.observing"));
         }
 
         // 收集本轮迭代的观察数据
@@ -388,7 +408,7 @@ execution_type 可选值：
     /// 返回值表示是否应该继续循环
     async fn execute_reflecting_phase_async(&self, agent: &AgentEnum) -> Result<bool> {
         if self.config.verbose {
-            println!("🤔 进入反思阶段...");
+            println!("{}", t!("workflow.reflecting"));
         }
 
         let (user_request, plan_summary, obs_summary, iteration) = {
@@ -475,7 +495,16 @@ execution_type 可选值：
         let response = self.call_llm(agent, &reflection_prompt).await?;
 
         // 解析反思结果
-        let reflection = self.parse_reflection_response(&response);
+        let reflection = self
+            .parse_with_repair(
+                agent,
+                "reflection",
+                &reflection_prompt,
+                &response,
+                output_parser::parse_reflection,
+            )
+            .await
+            .unwrap_or_else(|_| Self::default_reflection());
 
         // 保存反思
         {
@@ -525,6 +554,63 @@ execution_type 可选值：
         }
     }
 
+    /// 解析 LLM 的结构化输出
+    ///
+    /// 解析或校验失败时，带着错误信息让模型修正一次；仍然失败则返回错误，
+    /// 由调用方回退到默认值。走过的路径记录在观察数据中。
+    async fn parse_with_repair<T>(
+        &self,
+        agent: &AgentEnum,
+        source: &str,
+        prompt: &str,
+        response: &str,
+        parse: fn(&str) -> Result<Parsed<T>, Vec<String>>,
+    ) -> Result<T, Vec<String>> {
+        let errors = match parse(response) {
+            Ok(parsed) => {
+                self.observation_collector
+                    .add_output_parse(source.to_string(), parsed.path, &[]);
+                return Ok(parsed.value);
+            }
+            Err(errors) => errors,
+        };
+
+        if self.config.verbose {
+            println!(
+                "{}",
+                t!(
+                    "workflow.output_repairing",
+                    source = source,
+                    errors = errors.join("; ")
+                )
+            );
+        }
+        let repair_prompt = output_parser::repair_prompt(prompt, response, &errors);
+        let result = match self.call_llm(agent, &repair_prompt).await {
+            Ok(repaired) => parse(&repaired),
+            Err(e) => Err(vec![e.to_string()]),
+        };
+
+        match result {
+            Ok(parsed) => {
+                self.observation_collector.add_output_parse(
+                    source.to_string(),
+                    ParsePath::ModelRepaired,
+                    &errors,
+                );
+                Ok(parsed.value)
+            }
+            Err(errors) => {
+                self.observation_collector.add_output_parse(
+                    source.to_string(),
+                    ParsePath::Fallback,
+                    &errors,
+                );
+                Err(errors)
+            }
+        }
+    }
+
     /// 获取可执行的任务
     async fn get_executable_tasks(&self) -> Vec<Task> {
         let plan = self.current_plan.read().await;
@@ -612,110 +698,27 @@ execution_type 可选值：
     fn generate_default_plan(&self, user_request: &str) -> Plan {
         let task = Task::new(
             "task_1".to_string(),
-            format!("分析并完成请求: {}", user_request),
+            t!("workflow.default_task", request = user_request),
         )
         .with_execution_type(ExecutionType::DirectLLM);
 
         Plan::new(
             format!("plan_{}", uuid::Uuid::new_v4()),
-            "自动生成的默认计划".to_string(),
+            t!("workflow.default_plan").to_string(),
             vec![task],
         )
     }
 
-    /// 解析反思响应
-    fn parse_reflection_response(&self, response: &str) -> Reflection {
-        // 尝试从响应中提取 JSON
-        if let Some(json_str) = Self::extract_json_from_response(response) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                let goal_achieved = json
-                    .get("goal_achieved")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-
-                let progress = json
-                    .get("progress")
-                    .and_then(|v| v.as_f64())
-                    .unwrap_or(0.5) as f32;
-
-                let content = json
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("反思完成")
-                    .to_string();
-
-                let next_action = json
-                    .get("next_action")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                let requires_intervention = json
-                    .get("requires_user_intervention")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-
-                let issues: Vec<String> = json
-                    .get("issues")
-                    .and_then(|v| v.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                let mut reflection = Reflection::new(goal_achieved, progress, content, next_action);
-                reflection.requires_user_intervention = requires_intervention;
-                reflection.issues = issues;
-
-                return reflection;
-            }
-        }
-
-        // 默认反思
+    /// 回退用的默认反思
+    fn default_reflection() -> Reflection {
         Reflection::new(
             false,
             0.5,
-            "无法解析反思结果，继续执行".to_string(),
-            Some("继续下一轮迭代".to_string()),
+            t!("workflow.reflection_unparsed").to_string(),
+            Some(t!("workflow.reflection_next").to_string()),
         )
     }
 
-    /// 从响应中提取 JSON
-    fn extract_json_from_response(response: &str) -> Option<String> {
-        // 尝试从 markdown 代码块中提取
-        if let Some(start) = response.find("```json") {
-            let content_start = start + 7;
-            if let Some(end) = response[content_start..].find("```") {
-                return Some(response[content_start..content_start + end].trim().to_string());
-            }
-        }
-
-        // 尝试找到 JSON 对象
-        if let Some(start) = response.find('{') {
-            let mut depth = 0;
-            let mut end = start;
-            for (i, c) in response[start..].char_indices() {
-                match c {
-                    '{' => depth += 1,
-                    '}' => {
-                        depth -= 1;
-                        if depth == 0 {
-                            end = start + i + 1;
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            if depth == 0 && end > start {
-                return Some(response[start..end].to_string());
-            }
-        }
-
-        None
-    }
-
     /// 获取当前状态
     pub async fn get_state(&self) -> Result<WorkflowState> {
         Ok(self.state.read().await.clone())
@@ -743,35 +746,72 @@ execution_type 可选值：
         let reflections = self.get_reflections().await?;
 
         let mut output = String::new();
-        output.push_str("# 工作流摘要\n\n");
-        output.push_str(&format!("**状态**: {}\n", state.phase));
+        output.push_str(&format!("{}\n\n", t!("workflow.summary.title")));
         output.push_str(&format!(
-            "**迭代次数**: {}/{}\n",
-            state.iteration, state.max_iterations
+            "{}\n",
+            t!("workflow.summary.status", phase = state.phase)
+        ));
+        output.push_str(&format!(
+            "{}\n",
+            t!(
+                "workflow.summary.iterations",
+                iteration = state.iteration,
+                max = state.max_iterations
+            )
+        ));
+        output.push_str(&format!(
+            "{}\n\n",
+            t!("workflow.summary.elapsed", ms = state.elapsed_ms())
         ));
-        output.push_str(&format!("**耗时**: {}ms\n\n", state.elapsed_ms()));
 
-        output.push_str("## 观察数据\n");
-        output.push_str(&format!("- 总计: {}\n", summary.total_observations));
-        output.push_str(&format!("- 成功: {}\n", summary.successful));
-        output.push_str(&format!("- 失败: {}\n", summary.failed));
-        output.push_str(&format!("- 工具执行: {}\n", summary.tool_executions));
-        output.push_str(&format!("- 子Agent调用: {}\n\n", summary.subagent_calls));
+        output.push_str(&format!("{}\n", t!("workflow.summary.observations")));
+        output.push_str(&format!(
+            "{}\n",
+            t!("workflow.summary.total", count = summary.total_observations)
+        ));
+        output.push_str(&format!(
+            "{}\n",
+            t!("workflow.summary.successful", count = summary.successful)
+        ));
+        output.push_str(&format!(
+            "{}\n",
+            t!("workflow.summary.failed", count = summary.failed)
+        ));
+        output.push_str(&format!(
+            "{}\n",
+            t!(
+                "workflow.summary.tool_executions",
+                count = summary.tool_executions
+            )
+        ));
+        output.push_str(&format!(
+            "{}\n\n",
+            t!(
+                "workflow.summary.subagent_calls",
+                count = summary.subagent_calls
+            )
+        ));
 
         if !reflections.is_empty() {
-            output.push_str("## 反思历史\n");
+            output.push_str(&format!("{}\n", t!("workflow.summary.reflections")));
             for (i, reflection) in reflections.iter().enumerate() {
                 output.push_str(&format!(
-                    "{}. 进度: {:.0}% - {}\n",
-                    i + 1,
-                    reflection.progress * 100.0,
-                    reflection.content
+                    "{}\n",
+                    t!(
+                        "workflow.summary.reflection",
+                        index = i + 1,
+                        progress = format!("{:.0}", reflection.progress * 100.0),
+                        content = reflection.content
+                    )
                 ));
             }
         }
 
         if let Some(reason) = &state.failure_reason {
-            output.push_str(&format!("\n**失败原因**: {}\n", reason));
+            output.push_str(&format!(
+                "\n{}\n",
+                t!("workflow.summary.failure_reason", reason = reason)
+            ));
         }
 
         Ok(output)
@@ -804,48 +844,4 @@ mod tests {
         let state = orchestrator.get_state().await.unwrap();
         assert_eq!(state.phase, WorkflowPhase::Planning);
     }
-
-    #[test]
-    fn test_extract_json_from_response() {
-        let response = r#"
-好的，这是我的分析：
-
-```json
-{
-    "goal_achieved": true,
-    "progress": 1.0
-}
-```
-
-完成了。
-"#;
-
-        let json = WorkflowOrchestrator::extract_json_from_response(response);
-        assert!(json.is_some());
-        let json_str = json.unwrap();
-        assert!(json_str.contains("goal_achieved"));
-    }
-
-    #[test]
-    fn test_parse_reflection_response() {
-        let subagent_manager = Arc::new(SubagentManager::new());
-        let orchestrator =
-            WorkflowOrchestrator::new("Test".to_string(), subagent_manager, None);
-
-        let response = r#"```json
-{
-    "goal_achieved": true,
-    "progress": 1.0,
-    "content": "任务完成",
-    "next_action": null,
-    "requires_user_intervention": false,
-    "issues": []
-}
-```"#;
-
-        let reflection = orchestrator.parse_reflection_response(response);
-        assert!(reflection.goal_achieved);
-        assert_eq!(reflection.progress, 1.0);
-        assert_eq!(reflection.content, "任务完成");
-    }
 }
@@ -0,0 +1,660 @@
+//! LLM 结构化输出解析
+//!
+//! 从模型回复中提取计划和反思的 JSON：剥离代码块和前后的说明文字，
+//! 修复常见的格式问题（尾随逗号、中文引号、注释、Python 字面量、字符串中的换行），
+//! 再按显式的结构校验，错误信息指向具体字段，供修正请求和日志使用。
+
+use super::types::{Plan, Reflection};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// execution_type 的合法取值（含别名）
+const EXECUTION_TYPES: &[&str] = &["llm", "tool_call", "tool", "subagent", "delegate"];
+
+/// agent_type 的合法取值
+const AGENT_TYPES: &[&str] = &["Explore", "Plan", "CodeReviewer", "FrontendDeveloper"];
+
+/// 结构化输出的解析路径，记录在观察数据中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsePath {
+    /// 原样解析成功
+    Direct,
+    /// 本地修复格式后解析成功
+    Repaired,
+    /// 模型按校验错误修正后解析成功
+    ModelRepaired,
+    /// 修正后仍然失败，使用默认值
+    Fallback,
+}
+
+impl ParsePath {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ParsePath::Direct => "direct",
+            ParsePath::Repaired => "repaired",
+            ParsePath::ModelRepaired => "model_repaired",
+            ParsePath::Fallback => "fallback",
+        }
+    }
+}
+
+/// 解析结果及其路径
+#[derive(Debug)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub path: ParsePath,
+}
+
+/// 解析并校验计划，失败时返回全部错误
+pub fn parse_plan(response: &str) -> Result<Parsed<Plan>, Vec<String>> {
+    let (json, path) = parse_object(response).map_err(|e| vec![e])?;
+    let errors = validate_plan(&json);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Parsed {
+        value: Plan::from_json(&json),
+        path,
+    })
+}
+
+/// 解析并校验反思，失败时返回全部错误
+pub fn parse_reflection(response: &str) -> Result<Parsed<Reflection>, Vec<String>> {
+    let (json, path) = parse_object(response).map_err(|e| vec![e])?;
+    let errors = validate_reflection(&json);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Parsed {
+        value: Reflection::from_json(&json),
+        path,
+    })
+}
+
+/// 构建修正请求：原始要求、上一次的输出和需要修正的问题
+///
+/// 调用 LLM 时不带历史，所以要把原始提示词一并发送。
+pub fn repair_prompt(prompt: &str, response: &str, errors: &[String]) -> String {
+    let errors: Vec<String> = errors.iter().map(|e| format!("- {}", e)).collect();
+    format!(
+        "{}\n\n## 上一次的输出\n{}\n\n## 需要修正的问题\n{}\n\n请修正以上问题，只输出符合格式要求的 JSON，不要附加其他说明。",
+        prompt,
+        response,
+        errors.join("\n")
+    )
+}
+
+/// 从回复中找出第一个能解析为 JSON 对象的片段
+///
+/// 先原样解析，失败再修复格式后解析；都失败时报告最长片段的解析错误。
+fn parse_object(response: &str) -> Result<(Value, ParsePath), String> {
+    let mut error: Option<(usize, serde_json::Error)> = None;
+    for candidate in candidates(response) {
+        match serde_json::from_str::<Value>(candidate) {
+            Ok(value) if value.is_object() => return Ok((value, ParsePath::Direct)),
+            Ok(_) => {}
+            Err(e) => {
+                if let Ok(value) = serde_json::from_str::<Value>(&repair(candidate)) {
+                    if value.is_object() {
+                        return Ok((value, ParsePath::Repaired));
+                    }
+                }
+                if error.as_ref().is_none_or(|(len, _)| candidate.len() > *len) {
+                    error = Some((candidate.len(), e));
+                }
+            }
+        }
+    }
+    Err(match error {
+        Some((_, e)) => t!("workflow.output_invalid_json", error = e),
+        None => t!("workflow.output_no_json").to_string(),
+    })
+}
+
+/// 可能是 JSON 对象的片段：标注为 json 的代码块优先，其次其他代码块，最后是正文中的 `{...}`
+fn candidates(response: &str) -> Vec<&str> {
+    let mut blocks = fenced_blocks(response);
+    blocks.sort_by_key(|(lang, _)| !lang.eq_ignore_ascii_case("json"));
+
+    let mut candidates: Vec<&str> = blocks
+        .into_iter()
+        .map(|(_, body)| body.trim())
+        .filter(|body| body.starts_with('{'))
+        .collect();
+    candidates.extend(object_spans(response));
+    candidates
+}
+
+/// 按行找出 ``` 或 ~~~ 代码块，返回（语言标识，内容）；缺少结束标记时取到末尾
+fn fenced_blocks(response: &str) -> Vec<(&str, &str)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, &str, usize)> = None;
+    let mut offset = 0;
+    for line in response.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        match open {
+            None => {
+                for fence in ["```", "~~~"] {
+                    if let Some(lang) = trimmed.strip_prefix(fence) {
+                        let fence_char = fence.chars().next().unwrap_or('`');
+                        let lang = lang.trim_start_matches(fence_char).trim();
+                        open = Some((fence, lang, offset));
+                        break;
+                    }
+                }
+            }
+            Some((fence, lang, start)) if trimmed.starts_with(fence) => {
+                blocks.push((lang, &response[start..line_start]));
+                open = None;
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some((_, lang, start)) = open {
+        blocks.push((lang, &response[start..]));
+    }
+    blocks
+}
+
+/// 按括号配对找出顶层的 `{...}` 片段（忽略字符串中的括号）
+///
+/// 不会从已找到的片段内部再开始，避免把计划里的单个任务当成整个回复；
+/// 最后一个片段没有闭合时（输出被截断）也返回，以便报告准确的解析错误。
+fn object_spans(text: &str) -> Vec<&str> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' if start.is_some() => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = start.take() {
+                        spans.push(&text[start..=i]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = start {
+        spans.push(&text[start..]);
+    }
+    spans
+}
+
+/// 修复模型常见的 JSON 格式问题
+///
+/// 字符串之外：去掉尾随逗号和 `//` 注释，中文引号改为英文引号，
+/// True/False/None 改为 true/false/null；字符串之内：转义换行和制表符。
+fn repair(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    // 当前所在字符串的结束引号
+    let mut string_end: Option<char> = None;
+    let mut escaped = false;
+    while let Some(c) = chars.next() {
+        if let Some(end) = string_end {
+            if escaped {
+                escaped = false;
+                out.push(c);
+                continue;
+            }
+            match c {
+                '\\' => {
+                    escaped = true;
+                    out.push(c);
+                }
+                c if c == end => {
+                    string_end = None;
+                    out.push('"');
+                }
+                // 中文引号括起的字符串里出现的英文引号
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                string_end = Some('"');
+                out.push('"');
+            }
+            '“' | '”' => {
+                string_end = Some('”');
+                out.push('"');
+            }
+            ',' => {
+                let next = chars.clone().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                // 行注释，跳到行尾
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                out.push_str(match word.as_str() {
+                    "True" => "true",
+                    "False" => "false",
+                    "None" => "null",
+                    _ => &word,
+                });
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 校验计划结构
+fn validate_plan(json: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(description) = optional(json, "description") {
+        expect_str(description, "description", &mut errors);
+    }
+    let Some(tasks) = required(json, "", "tasks", &mut errors) else {
+        return errors;
+    };
+    let Some(tasks) = tasks.as_array() else {
+        errors.push(wrong_type("tasks", "array", tasks));
+        return errors;
+    };
+    if tasks.is_empty() {
+        errors.push(t!("workflow.schema.no_tasks").to_string());
+    }
+
+    // 依赖可以引用排在后面的任务，先收集全部 id
+    let ids: HashSet<&str> = tasks
+        .iter()
+        .filter_map(|task| task.get("id")?.as_str())
+        .collect();
+    let mut seen = HashSet::new();
+    for (i, task) in tasks.iter().enumerate() {
+        let path = format!("tasks[{}]", i);
+        if !task.is_object() {
+            errors.push(wrong_type(&path, "object", task));
+            continue;
+        }
+
+        let id = required(task, &path, "id", &mut errors)
+            .and_then(|v| expect_str(v, &format!("{}.id", path), &mut errors));
+        if let Some(id) = id {
+            if !seen.insert(id) {
+                errors.push(t!(
+                    "workflow.schema.duplicate_id",
+                    path = format!("{}.id", path),
+                    id = id
+                ));
+            }
+        }
+        if let Some(description) = required(task, &path, "description", &mut errors) {
+            expect_str(description, &format!("{}.description", path), &mut errors);
+        }
+
+        let execution_type = optional(task, "execution_type")
+            .and_then(|v| expect_str(v, &format!("{}.execution_type", path), &mut errors));
+        match execution_type {
+            Some("tool_call" | "tool") => {
+                if let Some(tool) = required(task, &path, "tool_name", &mut errors) {
+                    expect_str(tool, &format!("{}.tool_name", path), &mut errors);
+                }
+            }
+            Some("subagent" | "delegate") => {
+                let agent_path = format!("{}.agent_type", path);
+                let agent_type = optional(task, "agent_type")
+                    .and_then(|v| expect_str(v, &agent_path, &mut errors));
+                if let Some(agent_type) = agent_type {
+                    expect_one_of(agent_type, AGENT_TYPES, &agent_path, &mut errors);
+                }
+            }
+            Some(other) => {
+                let type_path = format!("{}.execution_type", path);
+                expect_one_of(other, EXECUTION_TYPES, &type_path, &mut errors);
+            }
+            None => {}
+        }
+
+        let Some(dependencies) = optional(task, "dependencies") else {
+            continue;
+        };
+        let deps_path = format!("{}.dependencies", path);
+        let Some(dependencies) = dependencies.as_array() else {
+            errors.push(wrong_type(&deps_path, "array", dependencies));
+            continue;
+        };
+        for (j, dependency) in dependencies.iter().enumerate() {
+            let dep_path = format!("{}[{}]", deps_path, j);
+            match expect_str(dependency, &dep_path, &mut errors) {
+                Some(dep) if Some(dep) == id => {
+                    errors.push(t!("workflow.schema.self_dependency", path = dep_path));
+                }
+                Some(dep) if !ids.contains(dep) => {
+                    errors.push(t!(
+                        "workflow.schema.unknown_dependency",
+                        path = dep_path,
+                        id = dep
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    errors
+}
+
+/// 校验反思结构
+fn validate_reflection(json: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(goal) = required(json, "", "goal_achieved", &mut errors) {
+        expect_bool(goal, "goal_achieved", &mut errors);
+    }
+    if let Some(progress) = required(json, "", "progress", &mut errors) {
+        match progress.as_f64() {
+            Some(value) if !(0.0..=1.0).contains(&value) => {
+                errors.push(t!(
+                    "workflow.schema.out_of_range",
+                    path = "progress",
+                    value = value
+                ));
+            }
+            Some(_) => {}
+            None => errors.push(wrong_type("progress", "number", progress)),
+        }
+    }
+    if let Some(content) = required(json, "", "content", &mut errors) {
+        expect_str(content, "content", &mut errors);
+    }
+    if let Some(next_action) = optional(json, "next_action") {
+        expect_str(next_action, "next_action", &mut errors);
+    }
+    if let Some(intervention) = optional(json, "requires_user_intervention") {
+        expect_bool(intervention, "requires_user_intervention", &mut errors);
+    }
+    if let Some(issues) = optional(json, "issues") {
+        match issues.as_array() {
+            Some(issues) => {
+                for (i, issue) in issues.iter().enumerate() {
+                    expect_str(issue, &format!("issues[{}]", i), &mut errors);
+                }
+            }
+            None => errors.push(wrong_type("issues", "array", issues)),
+        }
+    }
+    errors
+}
+
+/// 必填字段（null 视为缺失）
+fn required<'a>(
+    object: &'a Value,
+    path: &str,
+    key: &str,
+    errors: &mut Vec<String>,
+) -> Option<&'a Value> {
+    let value = optional(object, key);
+    if value.is_none() {
+        let path = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        errors.push(t!("workflow.schema.missing", path = path));
+    }
+    value
+}
+
+/// 可选字段（null 视为缺失）
+fn optional<'a>(object: &'a Value, key: &str) -> Option<&'a Value> {
+    object.get(key).filter(|v| !v.is_null())
+}
+
+fn expect_str<'a>(value: &'a Value, path: &str, errors: &mut Vec<String>) -> Option<&'a str> {
+    let s = value.as_str();
+    if s.is_none() {
+        errors.push(wrong_type(path, "string", value));
+    }
+    s
+}
+
+fn expect_bool(value: &Value, path: &str, errors: &mut Vec<String>) {
+    if !value.is_boolean() {
+        errors.push(wrong_type(path, "boolean", value));
+    }
+}
+
+fn expect_one_of(value: &str, allowed: &[&str], path: &str, errors: &mut Vec<String>) {
+    if !allowed.contains(&value) {
+        errors.push(t!(
+            "workflow.schema.bad_value",
+            path = path,
+            value = value,
+            allowed = allowed.join(", ")
+        ));
+    }
+}
+
+fn wrong_type(path: &str, expected: &str, actual: &Value) -> String {
+    let actual = match actual {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    t!(
+        "workflow.schema.wrong_type",
+        path = path,
+        expected = expected,
+        actual = actual
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::ExecutionType;
+    use super::*;
+    use crate::agent::types::AgentType;
+
+    /// 期望的结果：解析路径，或错误信息中应出现的字段路径
+    enum Expect {
+        Ok(ParsePath),
+        Err(&'static str),
+    }
+
+    /// 模仿真实模型回复的计划样本
+    const PLAN_CORPUS: &[(&str, &str, Expect)] = &[
+        (
+            "plain",
+            r#"{"description": "d", "tasks": [{"id": "t1", "description": "read"}]}"#,
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "fenced with prose",
+            "好的，计划如下：\n\n```json\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"read\"}]}\n```\n\n这样可以逐步完成。",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "fence without language",
+            "```\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"read\"}]}\n```",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "tilde fence",
+            "~~~json\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"read\"}]}\n~~~",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "shell block before uppercase json block",
+            "Run this first:\n```bash\ncargo build\n```\nPlan:\n```JSON\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"build\"}]}\n```",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "unterminated fence",
+            "```json\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"read\"}]}\n",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "braces in leading prose",
+            "I'll fill in the {placeholders} below.\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"use {x}\"}]}",
+            Expect::Ok(ParsePath::Direct),
+        ),
+        (
+            "trailing commas",
+            "```json\n{\n  \"tasks\": [\n    {\"id\": \"t1\", \"description\": \"read\",},\n  ],\n}\n```",
+            Expect::Ok(ParsePath::Repaired),
+        ),
+        (
+            "smart quotes",
+            "{“tasks”: [{“id”: “t1”, “description”: “read”}]}",
+            Expect::Ok(ParsePath::Repaired),
+        ),
+        (
+            "line comments",
+            "{\n  // 先读文件\n  \"tasks\": [{\"id\": \"t1\", \"description\": \"read\"}] // done\n}",
+            Expect::Ok(ParsePath::Repaired),
+        ),
+        (
+            "raw newline in string",
+            "{\"tasks\": [{\"id\": \"t1\", \"description\": \"line one\nline two\"}]}",
+            Expect::Ok(ParsePath::Repaired),
+        ),
+        (
+            "truncated",
+            "```json\n{\"tasks\": [{\"id\": \"t1\", \"description\": \"read\"},\n    {\"id\": \"t2\", \"descr",
+            Expect::Err(""),
+        ),
+        ("no json", "I could not come up with a plan.", Expect::Err("")),
+        (
+            "missing tasks",
+            r#"{"description": "d", "steps": []}"#,
+            Expect::Err("tasks"),
+        ),
+        (
+            "unknown execution_type",
+            r#"{"tasks": [{"id": "t1", "description": "run", "execution_type": "shell"}]}"#,
+            Expect::Err("tasks[0].execution_type"),
+        ),
+        (
+            "tool_call without tool_name",
+            r#"{"tasks": [{"id": "t1", "description": "read", "execution_type": "tool_call"}]}"#,
+            Expect::Err("tasks[0].tool_name"),
+        ),
+        (
+            "unknown agent_type",
+            r#"{"tasks": [{"id": "t1", "description": "look", "execution_type": "subagent", "agent_type": "Researcher"}]}"#,
+            Expect::Err("tasks[0].agent_type"),
+        ),
+        (
+            "dependency on missing task",
+            r#"{"tasks": [{"id": "t1", "description": "a"}, {"id": "t2", "description": "b", "dependencies": ["t0"]}]}"#,
+            Expect::Err("tasks[1].dependencies[0]"),
+        ),
+        (
+            "duplicate id",
+            r#"{"tasks": [{"id": "t1", "description": "a"}, {"id": "t1", "description": "b"}]}"#,
+            Expect::Err("tasks[1].id"),
+        ),
+    ];
+
+    #[test]
+    fn test_plan_corpus() {
+        for (name, response, expect) in PLAN_CORPUS {
+            match (parse_plan(response), expect) {
+                (Ok(parsed), Expect::Ok(path)) => assert_eq!(parsed.path, *path, "{}", name),
+                (Err(errors), Expect::Err(field)) => {
+                    assert!(
+                        errors.iter().any(|e| e.contains(field)),
+                        "{}: {:?}",
+                        name,
+                        errors
+                    );
+                }
+                (Ok(parsed), Expect::Err(_)) => panic!("{}: unexpected plan {:?}", name, parsed),
+                (Err(errors), Expect::Ok(_)) => panic!("{}: {:?}", name, errors),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_plan_keeps_content() {
+        let response = "```json\n{\n  \"description\": \"他说“先看测试”\",\n  \"tasks\": [\n    {\"id\": \"explore\", \"description\": \"探索\", \"execution_type\": \"subagent\", \"agent_type\": \"Explore\"},\n    {\"id\": \"fix\", \"description\": \"修复\", \"execution_type\": \"tool_call\", \"tool_name\": \"edit_file\", \"dependencies\": [\"explore\"],},\n  ]\n}\n```";
+        let parsed = parse_plan(response).unwrap();
+        assert_eq!(parsed.path, ParsePath::Repaired);
+        let plan = parsed.value;
+        // 英文引号字符串中的中文引号保持原样
+        assert_eq!(plan.description, "他说“先看测试”");
+        assert_eq!(
+            plan.tasks[0].execution_type,
+            ExecutionType::SubagentDelegation(AgentType::Explore)
+        );
+        assert_eq!(plan.tasks[1].dependencies, vec!["explore".to_string()]);
+    }
+
+    #[test]
+    fn test_reflection_corpus() {
+        let parsed = parse_reflection(
+            "```json\n{\n    \"goal_achieved\": true,\n    \"progress\": 1.0,\n    \"content\": \"任务完成\",\n    \"next_action\": null,\n    \"requires_user_intervention\": false,\n    \"issues\": []\n}\n```",
+        )
+        .unwrap();
+        assert_eq!(parsed.path, ParsePath::Direct);
+        assert!(parsed.value.goal_achieved);
+        assert_eq!(parsed.value.content, "任务完成");
+
+        let errors = parse_reflection(r#"{"goal_achieved": false}"#).unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("progress")));
+        assert!(errors.iter().any(|e| e.contains("content")));
+
+        let parsed = parse_reflection(
+            r#"Assessment: {"goal_achieved": False, "progress": 0.4, "content": "half way", "next_action": None, "issues": ["tests fail",]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.path, ParsePath::Repaired);
+        assert!(!parsed.value.goal_achieved);
+        assert_eq!(parsed.value.next_action, None);
+        assert_eq!(parsed.value.issues, vec!["tests fail".to_string()]);
+
+        let errors =
+            parse_reflection(r#"{"goal_achieved": "yes", "progress": 40, "content": "half way"}"#)
+                .unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("goal_achieved")));
+        assert!(errors.iter().any(|e| e.contains("progress")));
+    }
+
+    #[test]
+    fn test_repair_prompt_lists_errors() {
+        let prompt = repair_prompt("请输出计划", "{\"tasks\": []}", &["tasks: a".to_string()]);
+        assert!(prompt.starts_with("请输出计划"));
+        assert!(prompt.contains("{\"tasks\": []}"));
+        assert!(prompt.contains("- tasks: a"));
+    }
+}
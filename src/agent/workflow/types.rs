@@ -2,6 +2,7 @@
 //!
 //! 定义任务、计划、观察和反思等核心数据结构。
 
+use super::output_parser::{self, ParsePath};
 use crate::agent::types::AgentType;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -259,26 +260,29 @@ impl Plan {
     ///   ]
     /// }
     /// ```
+    ///
+    /// 格式不对时会先尝试修复，再按上面的结构校验，错误信息指向具体字段。
     pub fn from_llm_response(response: &str) -> Result<Self, String> {
-        // 尝试提取 JSON 块
-        let json_str = Self::extract_json(response)?;
-
-        // 解析 JSON
-        let json: serde_json::Value = serde_json::from_str(&json_str)
-            .map_err(|e| format!("JSON 解析失败: {}", e))?;
+        output_parser::parse_plan(response)
+            .map(|parsed| parsed.value)
+            .map_err(|errors| errors.join("; "))
+    }
 
+    /// 从已校验的 JSON 构建计划
+    pub(crate) fn from_json(json: &serde_json::Value) -> Self {
         // 提取计划描述
         let description = json
             .get("description")
             .and_then(|v| v.as_str())
-            .unwrap_or("自动生成的计划")
+            .unwrap_or(t!("workflow.untitled_plan"))
             .to_string();
 
         // 提取任务列表
         let tasks_json = json
             .get("tasks")
             .and_then(|v| v.as_array())
-            .ok_or("缺少 tasks 字段")?;
+            .map(Vec::as_slice)
+            .unwrap_or_default();
 
         let mut tasks = Vec::new();
         for (idx, task_json) in tasks_json.iter().enumerate() {
@@ -291,7 +295,7 @@ impl Plan {
             let task_desc = task_json
                 .get("description")
                 .and_then(|v| v.as_str())
-                .unwrap_or("未命名任务")
+                .unwrap_or(t!("workflow.untitled_task"))
                 .to_string();
 
             let mut task = Task::new(task_id, task_desc);
@@ -338,64 +342,7 @@ impl Plan {
         }
 
         let plan_id = format!("plan_{}", uuid::Uuid::new_v4());
-        Ok(Plan::new(plan_id, description, tasks))
-    }
-
-    /// 从响应中提取 JSON 块
-    fn extract_json(response: &str) -> Result<String, String> {
-        // 尝试直接解析
-        if response.trim().starts_with('{') {
-            return Ok(response.trim().to_string());
-        }
-
-        // 尝试从 markdown 代码块中提取
-        if let Some(start) = response.find("```json") {
-            let content_start = start + 7;
-            if let Some(end) = response[content_start..].find("```") {
-                return Ok(response[content_start..content_start + end].trim().to_string());
-            }
-        }
-
-        // 尝试从普通代码块中提取
-        if let Some(start) = response.find("```") {
-            let content_start = start + 3;
-            // 跳过可能的语言标识符
-            let content_start = response[content_start..]
-                .find('\n')
-                .map(|i| content_start + i + 1)
-                .unwrap_or(content_start);
-            if let Some(end) = response[content_start..].find("```") {
-                let json_str = response[content_start..content_start + end].trim();
-                if json_str.starts_with('{') {
-                    return Ok(json_str.to_string());
-                }
-            }
-        }
-
-        // 尝试找到 JSON 对象
-        if let Some(start) = response.find('{') {
-            // 找到匹配的结束括号
-            let mut depth = 0;
-            let mut end = start;
-            for (i, c) in response[start..].char_indices() {
-                match c {
-                    '{' => depth += 1,
-                    '}' => {
-                        depth -= 1;
-                        if depth == 0 {
-                            end = start + i + 1;
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            if depth == 0 && end > start {
-                return Ok(response[start..end].to_string());
-            }
-        }
-
-        Err("无法从响应中提取 JSON".to_string())
+        Plan::new(plan_id, description, tasks)
     }
     
     /// 获取待执行的任务
@@ -501,6 +448,30 @@ impl Observation {
             metadata: HashMap::new(),
         }
     }
+
+    /// 创建结构化输出解析观察（计划、反思）
+    ///
+    /// 记录解析路径；修正或回退时附带校验错误。
+    pub fn output_parse(source: String, path: ParsePath, errors: &[String]) -> Self {
+        let mut input_map = HashMap::new();
+        input_map.insert("parse_path".to_string(), serde_json::json!(path.as_str()));
+
+        let mut metadata = HashMap::new();
+        metadata.insert("parse_path".to_string(), path.as_str().to_string());
+
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            observation_type: "output_parse".to_string(),
+            source,
+            input: input_map,
+            output: None,
+            success: path != ParsePath::Fallback,
+            error: (!errors.is_empty()).then(|| errors.join("; ")),
+            execution_time_ms: None,
+            timestamp: SystemTime::now(),
+            metadata,
+        }
+    }
 }
 
 /// 反思结果
@@ -562,6 +533,50 @@ impl Reflection {
         self.requires_user_intervention = true;
         self
     }
+
+    /// 从已校验的 JSON 构建反思
+    pub(crate) fn from_json(json: &serde_json::Value) -> Self {
+        let goal_achieved = json
+            .get("goal_achieved")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let progress = json
+            .get("progress")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.5) as f32;
+
+        let content = json
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or(t!("workflow.reflection_done"))
+            .to_string();
+
+        let next_action = json
+            .get("next_action")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        let requires_intervention = json
+            .get("requires_user_intervention")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let issues: Vec<String> = json
+            .get("issues")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut reflection = Reflection::new(goal_achieved, progress, content, next_action);
+        reflection.requires_user_intervention = requires_intervention;
+        reflection.issues = issues;
+        reflection
+    }
 }
 
 #[cfg(test)]
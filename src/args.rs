@@ -0,0 +1,480 @@
+//! 命令行参数
+//!
+//! `oxide completions <shell>` 输出补全脚本（`oxide completions zsh | source /dev/stdin`）。
+//! 脚本在补全时回调 `COMPLETE=<shell> oxide`，因此 `--resume` 可以补全当前目录下的会话 ID。
+
+use crate::agent::PromptMode;
+use crate::config::UpdateChannel;
+use crate::usage::{GroupBy, DEFAULT_RETENTION_DAYS};
+use chrono::NaiveDate;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::Shells;
+use std::path::{Path, PathBuf};
+
+pub use crate::context::SESSIONS_DIR;
+
+/// `oxide serve` 的默认端口
+pub const DEFAULT_SERVE_PORT: u16 = 7878;
+
+/// 补全回调使用的环境变量
+const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Debug, Parser)]
+#[command(name = "oxide", version, about = "AI coding assistant in your terminal")]
+pub struct Args {
+    /// 恢复指定会话
+    #[arg(long, value_name = "SESSION_ID", add = ArgValueCandidates::new(session_candidates))]
+    pub resume: Option<String>,
+
+    /// 用会话模板开始新会话（`~/.oxide/templates/` 或 `.oxide/templates/` 中的名称）
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["resume", "print"])]
+    pub template: Option<String>,
+
+    /// 启动时的对话模式（normal、fast、plan），优先于项目配置中 `/mode --save` 保存的默认模式
+    #[arg(long, value_name = "MODE")]
+    pub mode: Option<PromptMode>,
+
+    /// 额外的工作区根目录（可重复），工具可以访问其中的文件
+    #[arg(long = "add-dir", value_name = "PATH")]
+    pub add_dir: Vec<PathBuf>,
+
+    /// 每轮对话的最长时间（秒），覆盖 `[limits] max_turn_seconds`；超时后模型总结进度（0 表示不限制）
+    #[arg(long = "max-turn-time", value_name = "SECONDS")]
+    pub max_turn_time: Option<u64>,
+
+    /// 禁止工具联网（等同于 `[network] tools = "none"`），对模型服务商的请求不受影响
+    #[arg(long = "offline-tools")]
+    pub offline_tools: bool,
+
+    /// 非交互运行：发送一条消息，把最终回答输出到 stdout 后退出
+    #[arg(short = 'p', long = "print", value_name = "PROMPT")]
+    pub print: Option<String>,
+
+    /// 最终回答须符合的 JSON Schema 文件，校验通过的 JSON 原样输出（需要 `-p`）
+    #[arg(long = "output-schema", value_name = "FILE", requires = "print")]
+    pub output_schema: Option<PathBuf>,
+
+    /// 把工具进度和模型流式事件按行以 JSON 写到 stderr（需要 `-p`）
+    #[arg(long = "stream-json", requires = "print")]
+    pub stream_json: bool,
+
+    /// 把本次运行的模型请求、回复和工具调用（脱敏后）录制到文件，用于复现问题
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// 回放录制文件：按顺序返回录制的回复，不访问服务商；请求与录制不一致时报错
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// 回放时直接使用录制的工具结果，不执行工具（需要 `--replay`）
+    #[arg(long = "replay-tools", requires = "replay")]
+    pub replay_tools: bool,
+
+    /// 打印启动各阶段耗时
+    #[arg(long, hide = true)]
+    pub profile_startup: bool,
+
+    /// 不输出颜色（也可以设置 `NO_COLOR`）
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// MCP 相关命令
+    Mcp {
+        #[command(subcommand)]
+        command: McpCommand,
+    },
+    /// 检查 API 密钥、网络、配置文件等常见问题
+    Doctor,
+    /// 为编辑器插件提供本机 WebSocket API
+    Serve {
+        /// 监听端口（0 表示由系统分配）
+        #[arg(long, default_value_t = DEFAULT_SERVE_PORT)]
+        port: u16,
+    },
+    /// 统计 API 用量与费用
+    #[command(args_conflicts_with_subcommands = true)]
+    Usage {
+        #[command(subcommand)]
+        command: Option<UsageCommand>,
+
+        /// 分组方式
+        #[arg(long, value_enum, default_value_t = UsageGroupBy::Day)]
+        by: UsageGroupBy,
+
+        /// 只统计该日期（UTC）及之后的记录
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        since: Option<NaiveDate>,
+
+        /// 输出 JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// 导出或导入权限规则（`[permissions.rules]`），供团队共享
+    Permissions {
+        #[command(subcommand)]
+        command: PermissionsCommand,
+    },
+    /// 升级项目 `.oxide/` 目录中的数据格式（启动时也会自动执行）
+    Migrate {
+        /// 只列出将要进行的改动，不修改任何文件
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 更新到发布渠道上的最新版本
+    Update {
+        /// 发布渠道（默认使用 `[update] channel` 配置）
+        #[arg(long, value_enum)]
+        channel: Option<ReleaseChannel>,
+
+        /// 只检查是否有新版本，不安装
+        #[arg(long)]
+        check: bool,
+    },
+    /// 输出 shell 补全脚本
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum McpCommand {
+    /// 通过 stdio 提供 oxide 的工具
+    Serve {
+        /// 同时提供写文件与执行命令的工具
+        #[arg(long)]
+        allow_write: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PermissionsCommand {
+    /// 把生效的规则输出为独立的 TOML（`oxide permissions export > team-permissions.toml`）
+    Export,
+    /// 校验规则文件并合并到项目配置中
+    Import {
+        /// `oxide permissions export` 输出的文件
+        file: PathBuf,
+
+        /// 不询问，直接用更严格的导入规则替换已有规则
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum UsageCommand {
+    /// 删除过期的用量记录
+    Compact {
+        /// 保留最近多少天的记录
+        #[arg(long, default_value_t = DEFAULT_RETENTION_DAYS)]
+        keep_days: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UsageGroupBy {
+    Day,
+    Project,
+    Model,
+    Session,
+}
+
+impl From<UsageGroupBy> for GroupBy {
+    fn from(group_by: UsageGroupBy) -> Self {
+        match group_by {
+            UsageGroupBy::Day => GroupBy::Day,
+            UsageGroupBy::Project => GroupBy::Project,
+            UsageGroupBy::Model => GroupBy::Model,
+            UsageGroupBy::Session => GroupBy::Session,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReleaseChannel {
+    Stable,
+    Nightly,
+}
+
+impl From<ReleaseChannel> for UpdateChannel {
+    fn from(channel: ReleaseChannel) -> Self {
+        match channel {
+            ReleaseChannel::Stable => UpdateChannel::Stable,
+            ReleaseChannel::Nightly => UpdateChannel::Nightly,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl CompletionShell {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Powershell => "powershell",
+        }
+    }
+}
+
+/// 帮助文本按界面语言显示的 clap 命令
+pub fn command() -> clap::Command {
+    Args::command()
+        .about(t!("args.about"))
+        .mut_arg("resume", |arg| arg.help(t!("args.resume")))
+        .mut_subcommand("doctor", |doctor| doctor.about(t!("args.doctor")))
+        .mut_subcommand("serve", |serve| {
+            serve
+                .about(t!("args.server"))
+                .mut_arg("port", |arg| arg.help(t!("args.port")))
+        })
+        .mut_subcommand("usage", |usage| {
+            usage
+                .about(t!("args.usage"))
+                .mut_arg("by", |arg| arg.help(t!("args.by")))
+                .mut_arg("since", |arg| arg.help(t!("args.since")))
+                .mut_arg("json", |arg| arg.help(t!("args.json")))
+                .mut_subcommand("compact", |compact| {
+                    compact
+                        .about(t!("args.usage_compact"))
+                        .mut_arg("keep_days", |arg| arg.help(t!("args.keep_days")))
+                })
+        })
+        .mut_subcommand("permissions", |permissions| {
+            permissions
+                .about(t!("args.permissions"))
+                .mut_subcommand("export", |export| {
+                    export.about(t!("args.permissions_export"))
+                })
+                .mut_subcommand("import", |import| {
+                    import
+                        .about(t!("args.permissions_import"))
+                        .mut_arg("file", |arg| arg.help(t!("args.permissions_file")))
+                        .mut_arg("yes", |arg| arg.help(t!("args.yes")))
+                })
+        })
+        .mut_subcommand("update", |update| {
+            update
+                .about(t!("args.update"))
+                .mut_arg("channel", |arg| arg.help(t!("args.channel")))
+                .mut_arg("check", |arg| arg.help(t!("args.check")))
+        })
+        .mut_subcommand("mcp", |mcp| {
+            mcp.about(t!("args.mcp")).mut_subcommand("serve", |serve| {
+                serve
+                    .about(t!("args.serve"))
+                    .mut_arg("allow_write", |arg| arg.help(t!("args.allow_write")))
+            })
+        })
+}
+
+/// 解析命令行参数，出错或请求帮助时退出进程
+pub fn parse() -> Args {
+    let matches = command().get_matches();
+    Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+/// 处理补全回调（`COMPLETE=<shell> oxide -- ...`），非补全调用时直接返回
+pub fn handle_completion_request() {
+    clap_complete::CompleteEnv::with_factory(command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// 输出补全脚本
+pub fn write_completions(shell: CompletionShell, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+    let bin = command().get_name().to_string();
+    let completer = Shells::builtins()
+        .completer(shell.name())
+        .expect("built-in shell completer");
+    completer.write_registration(COMPLETE_VAR, &bin, &bin, &bin, out)
+}
+
+/// `--resume` 的候选值：按最近更新排序的会话 ID
+fn session_candidates() -> Vec<CompletionCandidate> {
+    list_session_ids(Path::new(SESSIONS_DIR))
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// 列出会话目录中的会话 ID（最近修改的在前）
+fn list_session_ids(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut sessions: Vec<(std::time::SystemTime, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let extension = path.extension().and_then(|s| s.to_str())?;
+            if extension != "jsonl" && extension != "json" {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, id))
+        })
+        .collect();
+
+    sessions.sort_by(|a, b| b.0.cmp(&a.0));
+    let mut ids: Vec<String> = Vec::with_capacity(sessions.len());
+    for (_, id) in sessions {
+        // 迁移前后的旧版 .json 与 .jsonl 可能同时存在
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_subcommands() {
+        let args = Args::try_parse_from(["oxide", "mcp", "serve", "--allow-write"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Mcp { command: McpCommand::Serve { allow_write: true } })
+        ));
+
+        let args = Args::try_parse_from(["oxide", "doctor"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Doctor)));
+
+        let args = Args::try_parse_from(["oxide", "usage", "--by", "model", "--since", "2024-01-01", "--json"])
+            .unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Usage { command: None, by: UsageGroupBy::Model, since: Some(_), json: true })
+        ));
+        let args = Args::try_parse_from(["oxide", "usage", "compact", "--keep-days", "30"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Usage { command: Some(UsageCommand::Compact { keep_days: 30 }), .. })
+        ));
+        assert!(Args::try_parse_from(["oxide", "usage", "--since", "last-week"]).is_err());
+
+        let args = Args::try_parse_from(["oxide", "serve"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Serve { port: DEFAULT_SERVE_PORT })));
+        let args = Args::try_parse_from(["oxide", "serve", "--port", "0"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Serve { port: 0 })));
+
+        let args = Args::try_parse_from(["oxide", "update", "--channel", "nightly", "--check"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Update { channel: Some(ReleaseChannel::Nightly), check: true })
+        ));
+
+        let args = Args::try_parse_from(["oxide", "permissions", "import", "team.toml", "--yes"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Permissions { command: PermissionsCommand::Import { yes: true, .. } })
+        ));
+        assert!(Args::try_parse_from(["oxide", "permissions", "import"]).is_err());
+
+        let args = Args::try_parse_from(["oxide", "migrate", "--dry-run"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Migrate { dry_run: true })
+        ));
+
+        let args = Args::try_parse_from(["oxide", "--resume", "brave-fox"]).unwrap();
+        assert_eq!(args.resume.as_deref(), Some("brave-fox"));
+        assert!(args.command.is_none());
+
+        let args = Args::try_parse_from(["oxide", "--template", "debugging"]).unwrap();
+        assert_eq!(args.template.as_deref(), Some("debugging"));
+        assert!(Args::try_parse_from([
+            "oxide",
+            "--template",
+            "debugging",
+            "--resume",
+            "brave-fox"
+        ])
+        .is_err());
+
+        let args = Args::try_parse_from(["oxide", "--mode", "plan"]).unwrap();
+        assert_eq!(args.mode, Some(PromptMode::Plan));
+        assert_eq!(Args::try_parse_from(["oxide"]).unwrap().mode, None);
+        assert!(Args::try_parse_from(["oxide", "--mode", "auto"]).is_err());
+
+        let args = Args::try_parse_from([
+            "oxide",
+            "-p",
+            "list the public APIs",
+            "--output-schema",
+            "api.schema.json",
+        ])
+        .unwrap();
+        assert_eq!(args.print.as_deref(), Some("list the public APIs"));
+        assert_eq!(args.output_schema, Some(PathBuf::from("api.schema.json")));
+        assert!(Args::try_parse_from(["oxide", "--output-schema", "api.schema.json"]).is_err());
+        let args = Args::try_parse_from(["oxide", "-p", "hi", "--stream-json"]).unwrap();
+        assert!(args.stream_json);
+        assert!(Args::try_parse_from(["oxide", "--stream-json"]).is_err());
+    }
+
+    #[test]
+    fn test_localized_command_keeps_arguments() {
+        let matches = command()
+            .try_get_matches_from(["oxide", "mcp", "serve", "--allow-write"])
+            .unwrap();
+        let args = Args::from_arg_matches(&matches).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Mcp { command: McpCommand::Serve { allow_write: true } })
+        ));
+    }
+
+    #[test]
+    fn test_write_completions() {
+        for shell in [
+            CompletionShell::Bash,
+            CompletionShell::Zsh,
+            CompletionShell::Fish,
+            CompletionShell::Powershell,
+        ] {
+            let mut out = Vec::new();
+            write_completions(shell, &mut out).unwrap();
+            let script = String::from_utf8(out).unwrap();
+            assert!(script.contains("oxide"), "{:?}", shell);
+            assert!(script.contains(COMPLETE_VAR), "{:?}", shell);
+        }
+    }
+
+    #[test]
+    fn test_list_session_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("old-owl.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join("old-owl.jsonl"), "").unwrap();
+        fs::write(temp_dir.path().join("brave-fox.jsonl"), "").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "").unwrap();
+
+        let mut ids = list_session_ids(temp_dir.path());
+        ids.sort();
+        assert_eq!(ids, vec!["brave-fox", "old-owl"]);
+
+        assert!(list_session_ids(&temp_dir.path().join("missing")).is_empty());
+    }
+}
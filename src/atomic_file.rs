@@ -0,0 +1,249 @@
+//! 元数据文件的原子写入和损坏恢复
+//!
+//! `.oxide` 下的任务、检查点、更新检查状态等小文件，以及配置文件的改写，都通过 [`write_atomic`]：
+//! 先写同目录下的临时文件并 fsync，再重命名覆盖目标（Unix 上再 fsync 所在目录），
+//! 中途崩溃只会留下旧文件或新文件，不会留下写了一半的文件。
+//!
+//! 读取这些文件用 [`read_json_or_recover`]：内容不是完整的 JSON 时把文件改名为
+//! `<文件名>.corrupt-<时间>` 并警告一次，调用方按文件不存在处理、重新生成默认值，不会因此无法启动。
+
+use chrono::Local;
+use colored::*;
+use serde::de::DeserializeOwned;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 临时文件序号，同一进程内并发写入同一文件时临时文件名也不冲突
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 原子写入文件（保留原文件的权限）
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temp_path = dir.join(format!(
+        ".{}.oxide-tmp-{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = write_and_rename(path, &temp_path, contents);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_and_rename(path: &Path, temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    // 保留原文件权限
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp_path, metadata.permissions())?;
+    }
+    drop(file);
+
+    fs::rename(temp_path, path)?;
+
+    // 确保重命名本身落盘
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// 把损坏的文件改名为 `<文件名>.corrupt-<时间>`，返回新路径
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut backup =
+        path.with_file_name(format!("{}.corrupt-{}", file_name.to_string_lossy(), stamp));
+    let mut n = 2;
+    while backup.exists() {
+        backup = path.with_file_name(format!(
+            "{}.corrupt-{}-{}",
+            file_name.to_string_lossy(),
+            stamp,
+            n
+        ));
+        n += 1;
+    }
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+/// 读取 JSON 元数据文件
+///
+/// 文件不存在时返回 `None`；内容不是完整的 JSON（如写入中途崩溃留下的半截文件）时移到一旁、
+/// 警告一次并同样返回 `None`。JSON 完整但结构不符（如同目录下的其他文件）时返回
+/// `InvalidData` 错误，文件保持不动。
+pub fn read_json_or_recover<T: DeserializeOwned>(path: &Path) -> io::Result<Option<T>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_slice(&content) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_syntax() || e.is_eof() => {
+            recover(path, &e);
+            Ok(None)
+        }
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// 移走无法解析的文件并警告
+fn recover(path: &Path, error: &serde_json::Error) {
+    tracing::warn!("corrupt metadata file {}: {}", path.display(), error);
+    match quarantine(path) {
+        Ok(backup) => eprintln!(
+            "{} {}",
+            "⚠️".yellow(),
+            t!(
+                "metadata.recovered",
+                path = path.display(),
+                backup = backup.display()
+            )
+        ),
+        Err(e) => eprintln!(
+            "{} {}",
+            "⚠️".yellow(),
+            t!("metadata.unreadable", path = path.display(), error = e)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+
+        // 没有残留临时文件
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("run.sh");
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        write_atomic(&path, b"#!/bin/sh\necho hi\n").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_failed_rename_cleans_up_temp_file() {
+        let temp_dir = TempDir::new().unwrap();
+        // 目标是非空目录：临时文件写入成功，重命名失败
+        let target = temp_dir.path().join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("keep.txt"), "original").unwrap();
+
+        assert!(write_atomic(&target, b"replacement").is_err());
+        assert_eq!(
+            fs::read_to_string(target.join("keep.txt")).unwrap(),
+            "original"
+        );
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        writer: usize,
+        payload: String,
+    }
+
+    #[test]
+    fn test_parallel_writers_never_interleave() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = Arc::new(temp_dir.path().join("state.json"));
+
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let path = Arc::clone(&path);
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        let state = State {
+                            writer,
+                            payload: writer.to_string().repeat(4096),
+                        };
+                        write_atomic(&path, &serde_json::to_vec(&state).unwrap()).unwrap();
+                        // 任何时刻读到的都是某一个写入者的完整内容
+                        let read: State = read_json_or_recover(&path).unwrap().unwrap();
+                        assert_eq!(read.payload, read.writer.to_string().repeat(4096));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_truncated_file_is_moved_aside() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.json");
+        assert_eq!(read_json_or_recover::<State>(&path).unwrap(), None);
+
+        fs::write(&path, r#"{"writer": 1, "payl"#).unwrap();
+        assert_eq!(read_json_or_recover::<State>(&path).unwrap(), None);
+        assert!(!path.exists());
+        let backups: Vec<String> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("state.json.corrupt-"));
+
+        // 结构不符的文件不是损坏，保持不动
+        fs::write(&path, r#"{"id": "other"}"#).unwrap();
+        let err = read_json_or_recover::<State>(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(path.exists());
+
+        // 之后正常写入和读取
+        let state = State {
+            writer: 2,
+            payload: "ok".into(),
+        };
+        write_atomic(&path, &serde_json::to_vec(&state).unwrap()).unwrap();
+        assert_eq!(read_json_or_recover(&path).unwrap(), Some(state));
+    }
+}
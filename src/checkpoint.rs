@@ -0,0 +1,503 @@
+//! 对话检查点
+//!
+//! `/checkpoint [name]` 记录当前的消息数和工作区状态，`/restore <name>` 把对话截断回
+//! 该位置并恢复文件内容。检查点保存在 `.oxide/checkpoints/<name>.json`：
+//! - 在 git 仓库中用 `git stash create` 生成记录工作区改动的提交（没有改动时为 HEAD），
+//!   并用 `refs/oxide/checkpoints/<name>` 引用它，避免被 gc 清理；
+//! - 本次会话写过、但不受 git 跟踪的文件（不在 git 仓库中时为写过的所有文件）直接保存内容。
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::atomic_file::{read_json_or_recover, write_atomic};
+
+/// 检查点提交的引用前缀
+const REF_PREFIX: &str = "refs/oxide/checkpoints/";
+
+/// 一个检查点
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub session_id: String,
+    /// 创建时的消息数，恢复时删除之后的消息
+    pub message_count: usize,
+    /// 记录工作区状态的 git 提交
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// 直接保存内容的文件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileSnapshot>,
+}
+
+/// 检查点中直接保存的文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    /// base64 编码的内容，创建检查点时文件不存在为 None
+    pub contents: Option<String>,
+}
+
+impl FileSnapshot {
+    fn capture(path: &Path) -> Result<Self> {
+        let contents = match fs::read(path) {
+            Ok(bytes) => Some(STANDARD.encode(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            contents,
+        })
+    }
+
+    fn decode(&self) -> Result<Option<Vec<u8>>> {
+        self.contents
+            .as_ref()
+            .map(|contents| STANDARD.decode(contents))
+            .transpose()
+            .with_context(|| format!("Corrupt snapshot of {}", self.path.display()))
+    }
+}
+
+/// 恢复时文件的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// 内容恢复为检查点时的版本
+    Modified,
+    /// 检查点之后删除的文件被重新创建
+    Recreated,
+    /// 检查点之后创建的文件被删除
+    Removed,
+}
+
+/// 恢复计划：会改动的文件和会被覆盖的未跟踪文件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestorePlan {
+    pub changes: Vec<(ChangeKind, PathBuf)>,
+    /// 检查点中有、当前不受 git 跟踪的文件，恢复会覆盖它们
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// 检查点存储（`<workdir>/.oxide/checkpoints`）
+pub struct CheckpointStore {
+    workdir: PathBuf,
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(workdir: impl Into<PathBuf>) -> Self {
+        let workdir = workdir.into();
+        let dir = workdir.join(".oxide").join("checkpoints");
+        Self { workdir, dir }
+    }
+
+    fn path_of(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// 创建检查点，`touched` 为本次会话写过的文件（绝对路径）
+    pub fn create(
+        &self,
+        name: Option<&str>,
+        session_id: &str,
+        message_count: usize,
+        touched: &[PathBuf],
+    ) -> Result<Checkpoint> {
+        let name = match name {
+            Some(name) => {
+                validate_name(name)?;
+                name.to_string()
+            }
+            None => Local::now().format("%Y%m%d-%H%M%S").to_string(),
+        };
+        let path = self.path_of(&name);
+        if path.exists() {
+            bail!("Checkpoint '{}' already exists", name);
+        }
+
+        let mut git_commit = None;
+        let mut tracked = BTreeSet::new();
+        if let Some(root) = git_root(&self.workdir) {
+            // 还没有提交的仓库无法生成 stash 提交，按非 git 目录处理
+            if let Ok(head) = git(&root, &["rev-parse", "--verify", "HEAD"]) {
+                let stash = git(
+                    &root,
+                    &["stash", "create", &format!("oxide checkpoint {}", name)],
+                )?;
+                let commit = if stash.is_empty() { head } else { stash };
+                git(
+                    &root,
+                    &["update-ref", &format!("{}{}", REF_PREFIX, name), &commit],
+                )?;
+                git_commit = Some(commit);
+                tracked = git_paths(&root, &["ls-files", "-z"])?;
+            }
+        }
+
+        let files = touched
+            .iter()
+            .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+            .filter(|path| !tracked.contains(path))
+            .collect::<BTreeSet<_>>()
+            .iter()
+            .map(|path| FileSnapshot::capture(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let checkpoint = Checkpoint {
+            name,
+            created_at: Utc::now(),
+            session_id: session_id.to_string(),
+            message_count,
+            git_commit,
+            files,
+        };
+        fs::create_dir_all(&self.dir)?;
+        write_atomic(&path, serde_json::to_string_pretty(&checkpoint)?.as_bytes())?;
+        Ok(checkpoint)
+    }
+
+    /// 读取检查点（损坏的检查点文件移到一旁，按不存在处理）
+    pub fn load(&self, name: &str) -> Result<Checkpoint> {
+        validate_name(name)?;
+        let path = self.path_of(name);
+        match read_json_or_recover(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            Some(checkpoint) => Ok(checkpoint),
+            None => bail!("Checkpoint '{}' not found", name),
+        }
+    }
+
+    /// 所有检查点，按创建时间排序
+    pub fn list(&self) -> Result<Vec<Checkpoint>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut checkpoints = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Ok(Some(checkpoint)) = read_json_or_recover::<Checkpoint>(&path) {
+                    checkpoints.push(checkpoint);
+                }
+            }
+        }
+        checkpoints.sort_by_key(|checkpoint| checkpoint.created_at);
+        Ok(checkpoints)
+    }
+
+    /// 删除检查点和它引用的 git 提交
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let checkpoint = self.load(name)?;
+        if checkpoint.git_commit.is_some() {
+            if let Some(root) = git_root(&self.workdir) {
+                let _ = git(
+                    &root,
+                    &["update-ref", "-d", &format!("{}{}", REF_PREFIX, name)],
+                );
+            }
+        }
+        fs::remove_file(self.path_of(name))?;
+        Ok(())
+    }
+
+    /// 计算恢复到检查点会改动的文件
+    pub fn plan_restore(&self, checkpoint: &Checkpoint) -> Result<RestorePlan> {
+        let mut plan = RestorePlan::default();
+
+        if let Some(commit) = &checkpoint.git_commit {
+            let root = git_root(&self.workdir).with_context(|| {
+                format!(
+                    "Checkpoint '{}' was taken in a git repository, but {} is no longer one",
+                    checkpoint.name,
+                    self.workdir.display()
+                )
+            })?;
+            let untracked =
+                git_paths(&root, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+            let saved = git_paths(&root, &["ls-tree", "-r", "--name-only", "-z", commit])?;
+            plan.conflicts = untracked.intersection(&saved).cloned().collect();
+
+            // `git diff <commit>` 比较的是检查点与当前工作区中受跟踪的文件
+            let diff = git(
+                &root,
+                &["diff", "--name-status", "--no-renames", "-z", commit],
+            )?;
+            let mut fields = diff.split('\0').filter(|field| !field.is_empty());
+            while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+                let path = root.join(path);
+                let kind = if plan.conflicts.contains(&path) {
+                    ChangeKind::Modified
+                } else {
+                    match status {
+                        "A" => ChangeKind::Removed,
+                        "D" => ChangeKind::Recreated,
+                        _ => ChangeKind::Modified,
+                    }
+                };
+                plan.changes.push((kind, path));
+            }
+        }
+
+        for file in &checkpoint.files {
+            let current = match fs::read(&file.path) {
+                Ok(bytes) => Some(bytes),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            let kind = match (current, file.decode()?) {
+                (Some(current), Some(saved)) if current != saved => ChangeKind::Modified,
+                (Some(_), None) => ChangeKind::Removed,
+                (None, Some(_)) => ChangeKind::Recreated,
+                _ => continue,
+            };
+            plan.changes.push((kind, file.path.clone()));
+        }
+
+        plan.changes.sort_by(|a, b| a.1.cmp(&b.1));
+        plan.changes.dedup_by(|a, b| a.1 == b.1);
+        Ok(plan)
+    }
+
+    /// 把文件恢复到检查点时的内容
+    ///
+    /// `plan` 中有会被覆盖的未跟踪文件且 `force` 为 false 时拒绝恢复。
+    /// git 部分只恢复工作区，暂存区保持不变。
+    pub fn restore(&self, checkpoint: &Checkpoint, plan: &RestorePlan, force: bool) -> Result<()> {
+        if !plan.conflicts.is_empty() && !force {
+            bail!(
+                "Restoring '{}' would overwrite untracked files: {}",
+                checkpoint.name,
+                plan.conflicts
+                    .iter()
+                    .map(|path| self.display_path(path))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        if let Some(commit) = &checkpoint.git_commit {
+            let root = git_root(&self.workdir).context("Not in a git repository")?;
+            git(
+                &root,
+                &["restore", "--source", commit, "--worktree", "--", ":/"],
+            )?;
+        }
+        for file in &checkpoint.files {
+            match file.decode()? {
+                Some(contents) => {
+                    if let Some(parent) = file.path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    write_atomic(&file.path, &contents)?;
+                }
+                None => match fs::remove_file(&file.path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// 相对工作目录显示路径
+    pub fn display_path(&self, path: &Path) -> String {
+        let workdir = fs::canonicalize(&self.workdir).unwrap_or_else(|_| self.workdir.clone());
+        path.strip_prefix(&workdir)
+            .or_else(|_| path.strip_prefix(&self.workdir))
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// 检查点名称只能包含字母、数字、`.`、`_` 和 `-`
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        bail!(
+            "Invalid checkpoint name '{}': use letters, digits, '.', '_' or '-'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// 运行 git，返回去掉末尾换行的标准输出
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(["-c", "user.name=oxide", "-c", "user.email=oxide@localhost"])
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+/// 运行输出以 NUL 分隔路径的 git 命令，返回绝对路径
+fn git_paths(root: &Path, args: &[&str]) -> Result<BTreeSet<PathBuf>> {
+    Ok(git(root, args)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| root.join(path))
+        .collect())
+}
+
+/// 工作目录所在 git 仓库的根目录
+fn git_root(workdir: &Path) -> Option<PathBuf> {
+    git(workdir, &["rev-parse", "--show-toplevel"])
+        .ok()
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        git(&root, &["init", "-q"]).unwrap();
+        fs::write(root.join("a.txt"), "v1\n").unwrap();
+        fs::write(root.join("c.txt"), "keep\n").unwrap();
+        git(&root, &["add", "."]).unwrap();
+        git(&root, &["commit", "-q", "-m", "init"]).unwrap();
+        (dir, root)
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    #[test]
+    fn test_create_and_restore_in_git_repo() {
+        let (_dir, root) = init_repo();
+        let store = CheckpointStore::new(&root);
+
+        fs::write(root.join("a.txt"), "v2\n").unwrap();
+        fs::write(root.join("draft.md"), "draft\n").unwrap();
+        let checkpoint = store
+            .create(Some("before-refactor"), "s1", 4, &[root.join("draft.md")])
+            .unwrap();
+        assert_eq!(checkpoint.message_count, 4);
+        assert!(checkpoint.git_commit.is_some());
+        assert_eq!(checkpoint.files.len(), 1);
+        // 创建检查点不改动工作区
+        assert_eq!(read(&root.join("a.txt")).as_deref(), Some("v2\n"));
+
+        fs::write(root.join("a.txt"), "v3\n").unwrap();
+        fs::remove_file(root.join("c.txt")).unwrap();
+        fs::write(root.join("b.txt"), "new\n").unwrap();
+        git(&root, &["add", "b.txt"]).unwrap();
+        fs::write(root.join("draft.md"), "rewritten\n").unwrap();
+
+        let checkpoint = store.load("before-refactor").unwrap();
+        let plan = store.plan_restore(&checkpoint).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![
+                (ChangeKind::Modified, root.join("a.txt")),
+                (ChangeKind::Removed, root.join("b.txt")),
+                (ChangeKind::Recreated, root.join("c.txt")),
+                (ChangeKind::Modified, root.join("draft.md")),
+            ]
+        );
+        assert!(plan.conflicts.is_empty());
+
+        store.restore(&checkpoint, &plan, false).unwrap();
+        assert_eq!(read(&root.join("a.txt")).as_deref(), Some("v2\n"));
+        assert_eq!(read(&root.join("b.txt")), None);
+        assert_eq!(read(&root.join("c.txt")).as_deref(), Some("keep\n"));
+        assert_eq!(read(&root.join("draft.md")).as_deref(), Some("draft\n"));
+        assert!(store.plan_restore(&checkpoint).unwrap().changes.is_empty());
+    }
+
+    #[test]
+    fn test_restore_refuses_to_overwrite_untracked_files() {
+        let (_dir, root) = init_repo();
+        let store = CheckpointStore::new(&root);
+        let checkpoint = store.create(Some("clean"), "s1", 0, &[]).unwrap();
+
+        // c.txt 不再受跟踪，本地内容会被检查点中的版本覆盖
+        git(&root, &["rm", "-q", "--cached", "c.txt"]).unwrap();
+        fs::write(root.join("c.txt"), "local notes\n").unwrap();
+
+        let plan = store.plan_restore(&checkpoint).unwrap();
+        assert_eq!(plan.conflicts, vec![root.join("c.txt")]);
+        assert_eq!(
+            plan.changes,
+            vec![(ChangeKind::Modified, root.join("c.txt"))]
+        );
+
+        let error = store.restore(&checkpoint, &plan, false).unwrap_err();
+        assert!(error.to_string().contains("c.txt"));
+        assert_eq!(read(&root.join("c.txt")).as_deref(), Some("local notes\n"));
+
+        store.restore(&checkpoint, &plan, true).unwrap();
+        assert_eq!(read(&root.join("c.txt")).as_deref(), Some("keep\n"));
+    }
+
+    #[test]
+    fn test_list_and_delete_without_git() {
+        let dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let store = CheckpointStore::new(&root);
+        let notes = root.join("notes.txt");
+        fs::write(&notes, "one\n").unwrap();
+
+        let first = store
+            .create(Some("first"), "s1", 2, std::slice::from_ref(&notes))
+            .unwrap();
+        assert_eq!(first.git_commit, None);
+        assert!(store.create(Some("first"), "s1", 2, &[]).is_err());
+        assert!(store.create(Some("../escape"), "s1", 2, &[]).is_err());
+        store.create(Some("second"), "s1", 3, &[]).unwrap();
+
+        fs::write(&notes, "two\n").unwrap();
+        let plan = store.plan_restore(&first).unwrap();
+        assert_eq!(plan.changes, vec![(ChangeKind::Modified, notes.clone())]);
+        store.restore(&first, &plan, false).unwrap();
+        assert_eq!(read(&notes).as_deref(), Some("one\n"));
+
+        let names: Vec<String> = store.list().unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["first", "second"]);
+        store.delete("first").unwrap();
+        assert!(store.load("first").is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        // 写了一半的检查点文件移到一旁，之后可以用同名重新创建
+        let path = store.path_of("second");
+        fs::write(&path, r#"{"name": "second", "sess"#).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert!(!path.exists());
+        assert!(store.load("second").is_err());
+        store.create(Some("second"), "s1", 4, &[]).unwrap();
+        assert_eq!(store.load("second").unwrap().name, "second");
+    }
+}
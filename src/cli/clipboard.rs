@@ -0,0 +1,205 @@
+//! 从剪贴板粘贴图片
+//!
+//! 在输入框按 Ctrl+V，或终端粘贴了空内容（剪贴板中只有图片）时，读取剪贴板中的图片，
+//! 保存为 `.oxide/pastes/<时间>.png`，在输入框插入 `@<路径>` 并在终端显示预览。
+//!
+//! 提交时输入中指向图片的 `@` 引用（见 [`parse_image_references`](super::file_resolver::parse_image_references)）
+//! 不作为文本读取：当前模型支持图片（`ModelInfo::supports_vision`）时作为图片内容随本轮请求发送，
+//! 否则提示模型看不到图片，只发送路径。图片内容不写入对话历史，之后的请求中只有路径。
+//!
+//! 读取系统剪贴板需要 `clipboard` feature（arboard），未启用时 Ctrl+V 不做处理。
+
+use base64::Engine;
+use rig::completion::message::{ImageMediaType, UserContent};
+use rig::completion::Message;
+use rig::OneOrMany;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::large_paste::save_stamped;
+use crate::tools::image::ImageInfo;
+
+/// 读取剪贴板图片的宿主命令（Ctrl+V 和空的粘贴触发，输入框内容保留）
+pub const PASTE_IMAGE_COMMAND: &str = "__oxide_paste_image__";
+
+/// 可以读取图片的剪贴板
+pub trait ImageClipboard: Send + Sync {
+    /// 剪贴板中的图片（PNG 编码），没有图片时返回 `None`
+    fn image_png(&mut self) -> Option<Vec<u8>>;
+}
+
+/// 系统剪贴板
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard;
+
+#[cfg(feature = "clipboard")]
+impl ImageClipboard for SystemClipboard {
+    fn image_png(&mut self) -> Option<Vec<u8>> {
+        let image = arboard::Clipboard::new().ok()?.get_image().ok()?;
+        let pixels = image::RgbaImage::from_raw(
+            image.width as u32,
+            image.height as u32,
+            image.bytes.into_owned(),
+        )?;
+        let mut png = Vec::new();
+        pixels
+            .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .ok()?;
+        Some(png)
+    }
+}
+
+/// 当前构建可用的系统剪贴板，未启用 `clipboard` feature 时为 `None`
+pub fn system_clipboard() -> Option<Box<dyn ImageClipboard>> {
+    #[cfg(feature = "clipboard")]
+    return Some(Box::new(SystemClipboard));
+    #[cfg(not(feature = "clipboard"))]
+    None
+}
+
+/// 保存剪贴板中的图片，剪贴板中没有图片时返回 `Ok(None)`
+pub fn paste_image(
+    clipboard: &mut dyn ImageClipboard,
+    dir: &Path,
+) -> io::Result<Option<ImageInfo>> {
+    let Some(png) = clipboard.image_png() else {
+        return Ok(None);
+    };
+    let path = save_stamped(dir, "png", &png)?;
+    let path = path.to_string_lossy();
+    Ok(Some(ImageInfo::sniff(&path, &png).unwrap_or_else(|| {
+        ImageInfo {
+            path: path.to_string(),
+            mime_type: "image/png".to_string(),
+            width: None,
+            height: None,
+            size_bytes: png.len() as u64,
+        }
+    })))
+}
+
+/// 插入输入框的引用，如 `@.oxide/pastes/20261018-101500.png `
+pub fn reference(image: &ImageInfo) -> String {
+    if image.path.contains(char::is_whitespace) {
+        format!("@\"{}\" ", image.path)
+    } else {
+        format!("@{} ", image.path)
+    }
+}
+
+/// 输入中引用的图片如何随请求发送
+#[derive(Debug)]
+pub enum ImageAttachment {
+    /// 作为图片内容随本轮请求发送，放在用户输入之前
+    Content(Message),
+    /// 当前模型不支持图片，只发送路径
+    PathOnly,
+}
+
+/// 按当前模型能否接收图片决定怎样发送引用的图片（`images` 不为空）
+pub fn attach_images(images: &[ImageInfo], supports_vision: bool) -> io::Result<ImageAttachment> {
+    if !supports_vision {
+        return Ok(ImageAttachment::PathOnly);
+    }
+    let mut content = Vec::new();
+    for image in images {
+        let bytes = fs::read(&image.path)?;
+        // 图片前注明路径，模型能把图片和输入中的引用对应起来
+        content.push(UserContent::text(format!("[image {}]", image.path)));
+        content.push(UserContent::image_base64(
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+            media_type(&image.mime_type),
+            None,
+        ));
+    }
+    let content = OneOrMany::many(content)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "no images to attach"))?;
+    Ok(ImageAttachment::Content(Message::User { content }))
+}
+
+fn media_type(mime_type: &str) -> Option<ImageMediaType> {
+    match mime_type {
+        "image/png" => Some(ImageMediaType::PNG),
+        "image/jpeg" => Some(ImageMediaType::JPEG),
+        "image/gif" => Some(ImageMediaType::GIF),
+        "image/webp" => Some(ImageMediaType::WEBP),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// 固定内容的剪贴板
+    struct StubClipboard(Option<Vec<u8>>);
+
+    impl ImageClipboard for StubClipboard {
+        fn image_png(&mut self) -> Option<Vec<u8>> {
+            self.0.clone()
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_paste_saves_image_and_inserts_reference() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("pastes");
+
+        let mut empty = StubClipboard(None);
+        assert_eq!(paste_image(&mut empty, &dir).unwrap(), None);
+        assert!(!dir.exists());
+
+        let mut clipboard = StubClipboard(Some(png(1280, 720)));
+        let first = paste_image(&mut clipboard, &dir).unwrap().unwrap();
+        let second = paste_image(&mut clipboard, &dir).unwrap().unwrap();
+        assert_ne!(first.path, second.path);
+        assert!(first.path.ends_with(".png"));
+        assert_eq!(fs::read(&first.path).unwrap(), png(1280, 720));
+        assert_eq!(first.mime_type, "image/png");
+        assert_eq!((first.width, first.height), (Some(1280), Some(720)));
+        assert_eq!(reference(&first), format!("@{} ", first.path));
+
+        let spaced = ImageInfo {
+            path: "My Shots/error.png".to_string(),
+            ..first
+        };
+        assert_eq!(reference(&spaced), "@\"My Shots/error.png\" ");
+    }
+
+    #[test]
+    fn test_images_attached_only_for_vision_models() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut clipboard = StubClipboard(Some(png(64, 32)));
+        let image = paste_image(&mut clipboard, temp_dir.path())
+            .unwrap()
+            .unwrap();
+
+        let attachment = attach_images(std::slice::from_ref(&image), false).unwrap();
+        assert!(matches!(attachment, ImageAttachment::PathOnly));
+
+        let ImageAttachment::Content(Message::User { content, .. }) =
+            attach_images(std::slice::from_ref(&image), true).unwrap()
+        else {
+            panic!("expected image content");
+        };
+        let blocks: Vec<UserContent> = content.iter().cloned().collect();
+        assert_eq!(blocks.len(), 2);
+        assert!(
+            matches!(&blocks[0], UserContent::Text(text) if text.text == format!("[image {}]", image.path))
+        );
+        let UserContent::Image(attached) = &blocks[1] else {
+            panic!("expected an image block");
+        };
+        assert!(matches!(attached.media_type, Some(ImageMediaType::PNG)));
+    }
+}
@@ -1,20 +1,105 @@
 use crate::agent::{AgentType, NewAgentType, SubagentManager};
 use crate::agent::workflow::{WorkflowExecutor, WorkflowResult};
-use crate::context::SerializableMessage;
+use crate::checkpoint::{ChangeKind, CheckpointStore};
+use crate::config::{ConfigLoader, ModeConfig, OutputConfig, OutputLanguage, OutputStyle};
+use crate::context::{
+    InterruptedKind, RewindOutcome, SessionMismatch, TurnRecovery, SHELL_OUTPUT_TAG,
+};
+use crate::error::OxideError;
 use crate::hooks::SessionIdHook;
 use crate::skill::{SkillExecutor, SkillManager};
-use crate::token_counter::{count_messages_tokens, TokenUsage};
-use super::file_resolver::parse_file_references;
+use crate::task::manager::get_task_manager;
+use crate::telemetry;
+use crate::agent::context_usage::{Component, ContextBreakdown, Row};
+use crate::agent::loop_guard;
+use crate::agent::reminder::with_reminders;
+use crate::agent::stream_recovery::{
+    complete_with_recovery, RecoveredReply, RequestOutcome, MAX_CONTINUATIONS,
+};
+use crate::agent::session_learning::{extraction_prompt, parse_facts};
+use crate::agent::stream_event::StreamEvent;
+use crate::agent::time_box::{parse_remaining_steps, TurnDeadline};
+use crate::agent::turn_summary::{StopReason, TurnSummary};
+use crate::tools::memory::{new_facts, with_project_memory, MemoryStore};
+use crate::tools::direct_shell::{self, DirectInput};
+use crate::tools::network;
+use crate::tools::sandbox::Sandbox;
+use crate::tools::settings::ToolSettings;
+use crate::tools::verbose_output::{self, verbose_tools};
+use crate::model_info::ModelInfo;
+use crate::permissions::{self, PermissionRules, RuleAction};
+use crate::token_counter::TokenUsage;
+use super::export::render_markdown;
+use super::find::{self, FindQuery};
+use super::help::{self, CommandHelp};
+use super::output;
+use super::palette;
+use super::large_paste::{self, PASTES_DIR};
+use super::templates::{SessionTemplate, TemplateLoader};
+use super::tool_panel;
+use super::clipboard::{attach_images, ImageAttachment};
+use super::file_resolver::{parse_file_references, parse_image_references};
 use anyhow::Result;
 use colored::*;
-use rig::completion::Message;
-use rig::streaming::StreamingPrompt;
-use std::io::{stdout, Write};
+use futures::StreamExt;
+use rig::completion::{Message, Usage};
+use std::io::{stdout, IsTerminal, Write};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{Instrument, Span};
 
-use super::render::stream_with_animation;
+use super::render::{
+    print_advisory, print_error_hint, print_markdown, print_turn_recap, print_turn_summary,
+    stream_with_animation,
+};
 use super::OxideCli;
 
+/// `/retry continue` 发送给模型的提示词
+const CONTINUE_PROMPT: &str =
+    "Your previous response was cut off at the output limit. Continue exactly where it stopped without repeating anything.";
+
+/// 一轮对话的最终回复
+struct TurnReply {
+    response: RecoveredReply,
+    /// 超时中止时为本轮的时限，此时回复是模型对进度的总结
+    time_limit: Option<Duration>,
+}
+
+impl TurnReply {
+    /// 写入对话历史的回复（限时中止或不完整的回复带有标记）
+    fn text(&self) -> String {
+        match self.time_limit {
+            Some(limit) => format!(
+                "{}\n\n{}",
+                t!("cli.time_box.marker", seconds = limit.as_secs()),
+                self.response.text
+            ),
+            None if self.response.truncated => format!(
+                "{}\n\n{}",
+                self.response.text,
+                t!("cli.stream_recovery.marker")
+            ),
+            None => self.response.text.clone(),
+        }
+    }
+
+    fn usage(&self) -> Usage {
+        self.response.usage
+    }
+
+    /// 记录在本轮 span 上的结果
+    fn outcome(&self) -> &'static str {
+        if self.time_limit.is_some() {
+            "time_boxed"
+        } else if self.response.truncated {
+            "truncated"
+        } else {
+            "completed"
+        }
+    }
+}
+
 impl OxideCli {
     pub async fn handle_command(&mut self, input: &str) -> Result<bool> {
         match input {
@@ -24,6 +109,25 @@ impl OxideCli {
             "/clear" => {
                 self.clear_context()?;
             }
+            _ if input == "/new" || input.starts_with("/new ") => {
+                let name = input.strip_prefix("/new").unwrap_or("").trim();
+                self.new_session_command(name);
+            }
+            "/templates" | "/templates list" => {
+                self.list_templates();
+            }
+            _ if input.starts_with("/templates ") => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/templates")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/templates [list]")
+                );
+            }
             "/config" | "/config show" => {
                 self.show_config()?;
             }
@@ -37,32 +141,198 @@ impl OxideCli {
                 self.validate_config()?;
             }
             _ if input.starts_with("/config ") => {
-                println!("{} Unknown /config subcommand", "❌".red());
-                println!("{} Usage: /config [show|edit|reload|validate]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/config")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!(
+                        "cli.usage_hint",
+                        usage = "/config [show|edit|reload|validate]"
+                    )
+                );
             }
             "/toggle-tools" => {
-                println!("{}", "🔧 当前仅支持 CLI 模式，工具默认启用".bright_yellow());
+                println!("{}", t!("cli.tools_always_on").bright_yellow());
                 println!();
             }
             "/help" => {
                 self.show_help()?;
             }
+            _ if input.starts_with("/help ") => {
+                self.show_command_help(input.strip_prefix("/help ").unwrap_or("").trim());
+            }
+            "/refresh-files" => {
+                self.refresh_file_index();
+            }
+            _ if input == "/add-dir" || input.starts_with("/add-dir ") => {
+                let arg = input.strip_prefix("/add-dir").unwrap_or("").trim();
+                self.add_dir_command(arg);
+            }
+            _ if input == "/permissions" || input.starts_with("/permissions ") => {
+                let arg = input.strip_prefix("/permissions").unwrap_or("").trim();
+                self.permissions_command(arg);
+            }
+            "/save-notes" => {
+                self.save_session_notes().await;
+            }
+            _ if input == "/expand" || input.starts_with("/expand ") => {
+                let arg = input.strip_prefix("/expand").unwrap_or("").trim();
+                self.expand_command(arg);
+            }
+            _ if input == "/paste" || input.starts_with("/paste ") => {
+                let arg = input.strip_prefix("/paste").unwrap_or("").trim();
+                self.paste_command(arg).await?;
+            }
             "/history" => {
                 self.show_history()?;
             }
+            "/history clear" => {
+                self.confirm_history_clear()?;
+            }
+            _ if input == "/retry" || input.starts_with("/retry ") => {
+                if input.strip_prefix("/retry").unwrap_or("").trim() == "continue" {
+                    self.handle_with_simple_chat(CONTINUE_PROMPT, Vec::new()).await?;
+                } else {
+                    println!(
+                        "{} {}",
+                        "💡".bright_blue(),
+                        t!("cli.usage_hint", usage = "/retry continue")
+                    );
+                }
+            }
+            _ if input.starts_with("/pin ") => {
+                let arg = input.strip_prefix("/pin ").unwrap_or("").trim();
+                self.pin_message(arg, true);
+            }
+            _ if input.starts_with("/unpin ") => {
+                let arg = input.strip_prefix("/unpin ").unwrap_or("").trim();
+                self.pin_message(arg, false);
+            }
+            _ if input == "/rewind" || input.starts_with("/rewind ") => {
+                let arg = input.strip_prefix("/rewind").unwrap_or("").trim();
+                self.rewind_messages(arg);
+            }
+            _ if input == "/checkpoint" || input.starts_with("/checkpoint ") => {
+                let arg = input.strip_prefix("/checkpoint").unwrap_or("").trim();
+                self.checkpoint_command(arg);
+            }
+            _ if input == "/restore" || input.starts_with("/restore ") => {
+                let arg = input.strip_prefix("/restore").unwrap_or("").trim();
+                self.restore_checkpoint(arg);
+            }
+            _ if input == "/mode" || input.starts_with("/mode ") => {
+                let arg = input.strip_prefix("/mode").unwrap_or("").trim();
+                self.mode_command(arg);
+            }
+            _ if input == "/statusbar" || input.starts_with("/statusbar ") => {
+                let arg = input.strip_prefix("/statusbar").unwrap_or("").trim();
+                self.statusbar_command(arg);
+            }
+            _ if input == "/output-style" || input.starts_with("/output-style ") => {
+                let args = input.strip_prefix("/output-style").unwrap_or("");
+                self.output_style_command(args);
+            }
+            "/undo" => {
+                self.undo_last_write();
+            }
+            "/status" => {
+                self.show_status();
+            }
+            "/context" => {
+                self.show_context_breakdown().await;
+            }
+            "/update" => {
+                if let Err(e) = crate::update::run(None, true).await {
+                    println!("{} {:#}", t!("common.error").red(), e);
+                }
+                println!();
+            }
+            "/cost" => {
+                match crate::usage::session_cost_summary(self.session.context.session_id()) {
+                    Ok(summary) => println!("{}", summary),
+                    Err(e) => println!("{} {:#}", t!("common.error").red(), e),
+                }
+                println!();
+            }
+            _ if input == "/usage" || input.starts_with("/usage ") => {
+                let args = input.strip_prefix("/usage").unwrap_or("");
+                let result = crate::usage::UsageQuery::parse(args)
+                    .map_err(anyhow::Error::msg)
+                    .and_then(|query| crate::usage::run_report(&query));
+                if let Err(e) = result {
+                    println!("{} {:#}", t!("common.error").red(), e);
+                }
+                println!();
+            }
+            "/doctor" => {
+                let checks = crate::doctor::run_checks().await;
+                crate::doctor::print_report(&checks);
+            }
+            "/mcp" | "/mcp list" => {
+                self.list_mcp_servers().await;
+            }
+            _ if input.starts_with("/mcp enable ") => {
+                let name = input.strip_prefix("/mcp enable ").unwrap_or("").trim();
+                self.enable_mcp_server(name).await;
+            }
+            _ if input.starts_with("/mcp disable ") => {
+                let name = input.strip_prefix("/mcp disable ").unwrap_or("").trim();
+                self.disable_mcp_server(name).await;
+            }
+            _ if input.starts_with("/mcp ") => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/mcp")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!(
+                        "cli.usage_hint",
+                        usage = "/mcp [list|enable <name>|disable <name>]"
+                    )
+                );
+            }
             _ if input.starts_with("/load ") => {
                 let session_id = input.strip_prefix("/load ").unwrap_or("").trim();
                 self.load_session(session_id)?;
+                self.recover_interrupted_turn().await?;
             }
             _ if input.starts_with("/sessions") => {
-                self.list_sessions()?;
+                let tag = input.strip_prefix("/sessions").unwrap_or("").trim();
+                self.list_sessions(tag.trim_start_matches('#'))?;
+            }
+            _ if input == "/export" || input.starts_with("/export ") => {
+                let args = input.strip_prefix("/export").unwrap_or("");
+                self.export_session(args)?;
+            }
+            _ if input == "/find" || input.starts_with("/find ") => {
+                let args = input.strip_prefix("/find").unwrap_or("");
+                self.find_messages(args);
+            }
+            _ if input == "/tools" || input.starts_with("/tools ") => {
+                let arg = input.strip_prefix("/tools").unwrap_or("").trim();
+                self.tools_command(arg);
+            }
+            _ if input == "/cache" || input.starts_with("/cache ") => {
+                let arg = input.strip_prefix("/cache").unwrap_or("").trim();
+                self.cache_command(arg);
             }
             _ if input.starts_with("/delete ") => {
                 let session_id = input.strip_prefix("/delete ").unwrap_or("").trim();
                 if !session_id.is_empty() {
                     self.delete_session(session_id)?;
                 } else {
-                    println!("{} Usage: /delete <session_id>", "❌".red());
+                    println!(
+                        "{} {}",
+                        "❌".red(),
+                        t!("cli.usage_hint", usage = "/delete <session_id>")
+                    );
                 }
             }
             "/agent" | "/agent list" => {
@@ -72,11 +342,23 @@ impl OxideCli {
                 self.show_agent_capabilities()?;
             }
             _ if input.starts_with("/agent ") => {
-                println!("{} Unknown /agent subcommand", "❌".red());
-                println!("{} Usage: /agent [list|capabilities]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/agent")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/agent [list|capabilities]")
+                );
             }
             "/tasks" | "/tasks list" => {
-                self.list_tasks()?;
+                self.list_tasks("")?;
+            }
+            _ if input.starts_with("/tasks list ") => {
+                let args = input.strip_prefix("/tasks list ").unwrap_or("");
+                self.list_tasks(args)?;
             }
             _ if input.starts_with("/tasks show ") => {
                 let task_id = input.strip_prefix("/tasks show ").unwrap_or("").trim();
@@ -87,8 +369,16 @@ impl OxideCli {
                 self.cancel_task(task_id)?;
             }
             _ if input.starts_with("/tasks ") => {
-                println!("{} Unknown /tasks subcommand", "❌".red());
-                println!("{} Usage: /tasks [list|show <id>|cancel <id>]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/tasks")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/tasks [list [--status <s>] [--owner <o>] [--limit <n>] [--offset <n>] [--all] [text]|show <id>|cancel <id>]")
+                );
             }
             "/skills" | "/skills list" => {
                 self.list_skills()?;
@@ -98,102 +388,659 @@ impl OxideCli {
                 self.show_skill(skill_name)?;
             }
             _ if input.starts_with("/skills ") => {
-                println!("{} Unknown /skills subcommand", "❌".red());
-                println!("{} Usage: /skills [list|show <name>]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/skills")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/skills [list|show <name>]")
+                );
             }
             "/workflow" | "/workflow status" => {
                 self.show_workflow_status()?;
             }
             _ if input.starts_with("/workflow on") || input.starts_with("/workflow enable") => {
-                println!("{}", "🔄 工作流自动模式已启用".bright_green());
-                println!("{}", "💡 复杂任务将自动使用 PAOR 工作流".bright_cyan());
-                println!("{}", "   使用 #workflow 标记强制启用".dimmed());
-                println!("{}", "   使用 #simple 标记强制使用简单对话".dimmed());
+                println!("{}", t!("cli.workflow.enabled").bright_green());
+                println!("{}", t!("cli.workflow.enabled_hint").bright_cyan());
+                println!("{}", t!("cli.workflow.force_workflow_hint").dimmed());
+                println!("{}", t!("cli.workflow.force_simple_hint").dimmed());
                 println!();
             }
             _ if input.starts_with("/workflow off") || input.starts_with("/workflow disable") => {
-                println!("{}", "⚠️  工作流自动模式已禁用".bright_yellow());
-                println!("{}", "💡 所有任务将使用简单对话模式".bright_cyan());
-                println!("{}", "   使用 #workflow 标记可手动启用工作流".dimmed());
+                println!("{}", t!("cli.workflow.disabled").bright_yellow());
+                println!("{}", t!("cli.workflow.disabled_hint").bright_cyan());
+                println!("{}", t!("cli.workflow.manual_hint").dimmed());
                 println!();
             }
             _ if input.starts_with("/workflow ") => {
-                println!("{} Unknown /workflow subcommand", "❌".red());
-                println!("{} Usage: /workflow [status|on|off]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/workflow")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/workflow [status|on|off]")
+                );
             }
             _ if input.starts_with("/skills show ") => {
                 let skill_name = input.strip_prefix("/skills show ").unwrap_or("").trim();
                 self.show_skill(skill_name)?;
             }
             _ if input.starts_with("/skills ") => {
-                println!("{} Unknown /skills subcommand", "❌".red());
-                println!("{} Usage: /skills [list|show <name>]", "💡".bright_blue());
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/skills")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/skills [list|show <name>]")
+                );
+            }
+            _ if input.starts_with('!') => {
+                self.run_direct_command(input).await;
             }
             _ if input.starts_with('/') => {
-                // 尝试作为 skill 执行
+                // 尝试作为 skill 执行（技能在后台加载）
+                self.wait_for_warmup().await;
                 if self.try_execute_skill(input).await? {
                     // 成功执行了 skill，跳过后续处理
                     return Ok(true);
                 }
 
-                println!("{} Unknown command: {}", "❌".red(), input);
-                println!("{} Type /help for available commands", "💡".bright_blue());
+                self.report_unknown_command(input);
             }
             _ => {
-                // 检查是否处于 Plan 模式
-                let force_workflow = self.is_plan_mode();
-
-                // 评估任务复杂度
-                let use_workflow = force_workflow || self.complexity_evaluator.should_use_workflow(input);
-
-                if use_workflow {
-                    // 使用 PAOR 工作流处理复杂任务
-                    self.handle_with_workflow(input).await?;
-                } else {
-                    // 使用简单对话模式
-                    self.handle_with_simple_chat(input).await?;
-                }
+                // 剥离开头的 #标签，标签作为消息元数据保存
+                let tagged = self.tags.parse(input);
+                let (input, tags) = (tagged.text, tagged.tags);
+                let input = self.shorten_large_paste(input, &tags);
+                self.send_chat(&input, tags).await?;
             }
         }
         println!(); 
         Ok(true)
     }
 
+    /// 按模式和任务复杂度选择处理方式，发送一条对话消息
+    async fn send_chat(&mut self, input: &str, tags: Vec<String>) -> Result<()> {
+        // 检查是否处于 Plan 模式
+        let force_workflow = self.is_plan_mode();
+
+        // 评估任务复杂度
+        let use_workflow = force_workflow || self.complexity_evaluator.should_use_workflow(input);
+
+        if use_workflow {
+            // 使用 PAOR 工作流处理复杂任务
+            self.handle_with_workflow(input, tags).await
+        } else {
+            // 使用简单对话模式
+            self.handle_with_simple_chat(input, tags).await
+        }
+    }
+
+    /// 输入超过粘贴阈值时把完整内容保存为文件，返回代替它发送的摘录；否则原样返回
+    fn shorten_large_paste(&mut self, input: &str, tags: &[String]) -> String {
+        let Some(size) = self.paste_limits.exceeded_by(input) else {
+            return input.to_string();
+        };
+        match large_paste::save_paste(Path::new(PASTES_DIR), input) {
+            Ok(path) => {
+                println!(
+                    "{} {}",
+                    "📋".bright_blue(),
+                    t!(
+                        "cli.paste.shortened",
+                        lines = large_paste::group_thousands(size.lines),
+                        path = path.display()
+                    )
+                    .dimmed()
+                );
+                self.last_paste = Some((input.to_string(), tags.to_vec()));
+                large_paste::marker(&path, input, size)
+            }
+            Err(e) => {
+                println!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    t!("cli.paste.save_failed", error = e)
+                );
+                input.to_string()
+            }
+        }
+    }
+
+    /// `/paste raw`：原样发送上一次只发送了摘录的输入
+    async fn paste_command(&mut self, arg: &str) -> Result<()> {
+        if arg != "raw" {
+            println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!("cli.usage_hint", usage = "/paste raw")
+            );
+            return Ok(());
+        }
+        match self.last_paste.take() {
+            Some((input, tags)) => self.send_chat(&input, tags).await,
+            None => {
+                println!("{}", t!("cli.paste.nothing").dimmed());
+                Ok(())
+            }
+        }
+    }
+
+    /// 提示未知命令，并推荐拼写相近的命令
+    fn report_unknown_command(&self, input: &str) {
+        let command = input.split_whitespace().next().unwrap_or(input);
+        println!(
+            "{} {}",
+            "❌".red(),
+            t!("cli.unknown_command", command = command)
+        );
+
+        let entries = super::build_command_entries();
+        let suggestions =
+            super::suggest::suggest_commands(command, entries.iter().map(|(name, _)| name.as_str()));
+        match suggestions.as_slice() {
+            [] => println!("{} {}", "💡".bright_blue(), t!("cli.type_help")),
+            [only] => println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!("cli.did_you_mean", command = only.bright_green())
+            ),
+            many => println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!(
+                    "cli.did_you_mean_many",
+                    commands = many.join(", ").bright_green()
+                )
+            ),
+        }
+    }
+
     fn clear_context(&mut self) -> Result<()> {
-        self.context_manager.clear();
+        self.session.context.clear()?;
         self.reset_session_tokens();
+        self.advisories.reset();
         println!(
             "{} Context cleared. Current session: {}",
             "✅".bright_green(),
-            self.context_manager.session_id().bright_cyan()
+            self.session.context.session_id().bright_cyan()
         );
         println!();
         Ok(())
     }
 
+    /// `/new [template]`：保存当前会话，开始一个新会话，指定模板时应用模板
+    fn new_session_command(&mut self, name: &str) {
+        let template = if name.is_empty() {
+            None
+        } else {
+            match TemplateLoader::new().find(name) {
+                Ok(template) => Some(template),
+                Err(e) => {
+                    println!("{} {:#}", "❌".red(), e);
+                    println!("{} {}", "💡".bright_blue(), t!("cli.templates.list_hint"));
+                    println!();
+                    return;
+                }
+            }
+        };
+
+        if let Err(e) = self.session.context.save() {
+            println!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!("cli.save_context_failed", error = e)
+            );
+        }
+        let session_id = self.session.context.start_new_session();
+        self.reset_session_tokens();
+        self.advisories.reset();
+        self.pending_template = None;
+        println!(
+            "{} {}",
+            "✅".bright_green(),
+            t!(
+                "cli.templates.new_session",
+                session = session_id.bright_cyan()
+            )
+        );
+
+        match template {
+            Some(template) => self.apply_template(&template),
+            None => {
+                // 上一个模板追加的系统提示词不带到新会话
+                if let Some(builder) = self.agent_builder.as_mut() {
+                    builder.set_prompt_addition(None);
+                }
+                self.rebuild_agent();
+            }
+        }
+        println!();
+    }
+
+    /// 应用会话模板：模式、回复风格和系统提示词补充立即生效，引用的文件和正文放进下一条消息
+    pub(super) fn apply_template(&mut self, template: &SessionTemplate) {
+        if let Some(builder) = self.agent_builder.as_mut() {
+            builder.set_prompt_addition(template.system_part());
+        }
+        if let Some(style) = template.output_style {
+            self.set_output(OutputConfig {
+                style,
+                ..self.output
+            });
+        }
+        match template.mode {
+            Some(mode) if mode != self.prompt_label.prompt_mode() => {
+                self.switch_mode(super::PromptLabel::from_prompt_mode(mode));
+            }
+            _ => self.rebuild_agent(),
+        }
+
+        let (start, errors) = template.start(&self.roots);
+        for (file, e) in errors {
+            println!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!("cli.file.reference_failed", path = file, error = e)
+            );
+        }
+        if !start.files.is_empty() {
+            println!("{}", t!("cli.referenced_files").bright_cyan());
+            for file in &start.files {
+                println!("  {}", file.display_info());
+            }
+        }
+        println!(
+            "{} {}",
+            "📋".bright_blue(),
+            t!(
+                "cli.templates.applied",
+                name = template.name.bright_white(),
+                mode = self.prompt_label.prompt_mode().as_str(),
+                style = self.output.style.as_str()
+            )
+        );
+        if !start.files.is_empty() || start.body.is_some() {
+            println!("{}", t!("cli.templates.first_message_hint").dimmed());
+            self.pending_template = Some(start);
+        }
+    }
+
+    /// 应用模板后的第一条消息带上模板引用的文件和正文
+    fn with_pending_template(&mut self, message: String) -> String {
+        match self.pending_template.take() {
+            Some(start) => start.first_message(&message),
+            None => message,
+        }
+    }
+
+    /// `/templates`：列出可用的会话模板及其来源
+    fn list_templates(&self) {
+        let (templates, errors) = TemplateLoader::new().load_all();
+        if templates.is_empty() {
+            println!("{}", t!("cli.templates.empty").bright_yellow());
+        } else {
+            println!("{}", t!("cli.templates.title").bright_cyan());
+            let width = templates.keys().map(|name| name.len()).max().unwrap_or(0);
+            for template in templates.values() {
+                println!(
+                    "  {}  {}",
+                    format!("{:<width$}", template.name, width = width).bright_white(),
+                    template.description
+                );
+                println!(
+                    "  {}  {}",
+                    " ".repeat(width),
+                    format!("{} · {}", template.source, template.path.display()).dimmed()
+                );
+            }
+        }
+        for (path, e) in errors {
+            println!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!(
+                    "cli.templates.invalid",
+                    path = path.display(),
+                    error = format!("{:#}", e)
+                )
+            );
+        }
+        println!("{} {}", "💡".bright_blue(), t!("cli.templates.new_hint"));
+        println!();
+    }
+
+    /// 重建 @ 文件补全索引
+    fn refresh_file_index(&self) {
+        let started = std::time::Instant::now();
+        self.file_index.refresh();
+        println!(
+            "{} Indexed {} files in {}ms",
+            "✅".bright_green(),
+            self.file_index.len(),
+            started.elapsed().as_millis()
+        );
+    }
+
+    /// `/add-dir [path]`：登记额外的工作区根目录，不带参数时列出所有根目录
+    fn add_dir_command(&mut self, arg: &str) {
+        if !arg.is_empty() {
+            let path = crate::tools::sensitive_paths::expand_home(arg);
+            match self.roots.add(Path::new(&path)) {
+                Ok(root) => {
+                    self.file_index.add_root(&root.alias, &root.path);
+                    // 系统提示词中的根目录列表在构建时确定
+                    self.rebuild_agent();
+                    println!(
+                        "{} {}",
+                        "✅".bright_green(),
+                        t!(
+                            "cli.workspace.added",
+                            alias = root.alias.bright_cyan(),
+                            path = root.path.display()
+                        )
+                    );
+                }
+                Err(e) => {
+                    println!(
+                        "{} {}",
+                        "❌".red(),
+                        t!("cli.workspace.add_failed", error = e)
+                    );
+                    println!();
+                    return;
+                }
+            }
+        }
+        println!("{}", t!("cli.workspace.roots"));
+        for root in self.roots.list() {
+            println!("  {}  {}", root.alias.bright_cyan(), root.path.display());
+        }
+        println!();
+    }
+
+    /// `/permissions [export [file]|import <file>]`：查看、导出或导入权限规则
+    fn permissions_command(&mut self, arg: &str) {
+        let usage = "/permissions [export [file]|import <file>]";
+        let (action, path) = match arg.split_once(char::is_whitespace) {
+            Some((action, path)) => (action, path.trim()),
+            None => (arg, ""),
+        };
+        let path = crate::tools::sensitive_paths::expand_home(path);
+        match (action, path.as_str()) {
+            ("", _) => {
+                let session = self._hitl.gatekeeper.session_rules();
+                match PermissionRules::load() {
+                    Ok(rules) if rules.is_empty() && session.list().is_empty() => {
+                        println!("{}", t!("permissions.empty").dimmed());
+                    }
+                    Ok(rules) => {
+                        for action in RuleAction::ALL {
+                            for rule in rules.list(action) {
+                                println!("  {:<5}  {}", action.as_str().bright_cyan(), rule);
+                            }
+                        }
+                        // 确认时记住的规则只在本次会话中生效
+                        for rule in session.list() {
+                            println!(
+                                "  {:<5}  {} {}",
+                                RuleAction::Allow.as_str().bright_cyan(),
+                                rule,
+                                t!("permissions.session_only").dimmed()
+                            );
+                        }
+                    }
+                    Err(e) => println!("{} {:#}", "❌".red(), e),
+                }
+            }
+            ("export", "") => match permissions::export_rules() {
+                Ok(content) => print!("{}", content),
+                Err(e) => println!("{} {:#}", "❌".red(), e),
+            },
+            ("export", path) => {
+                let result = permissions::export_rules()
+                    .and_then(|content| std::fs::write(path, content).map_err(anyhow::Error::from));
+                match result {
+                    Ok(()) => println!(
+                        "{} {}",
+                        "✅".bright_green(),
+                        t!("permissions.exported", path = path)
+                    ),
+                    Err(e) => println!("{} {:#}", "❌".red(), e),
+                }
+            }
+            ("import", path) if !path.is_empty() => {
+                match permissions::import_rules(Path::new(path), permissions::confirm_conflicts) {
+                    Ok(outcome) => {
+                        permissions::print_import_outcome(&outcome);
+                        match PermissionRules::load() {
+                            Ok(rules) => self._hitl.gatekeeper.set_rules(rules),
+                            Err(e) => println!("{} {:#}", "❌".red(), e),
+                        }
+                    }
+                    Err(e) => println!("{} {:#}", "❌".red(), e),
+                }
+            }
+            _ => println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!("cli.usage_hint", usage = usage)
+            ),
+        }
+        println!();
+    }
+
+    /// 列出 MCP 服务器
+    /// 撤销最近一次工具写入
+    /// `!<command>` / `!!`：不经过模型直接执行命令，输出加入上下文
+    async fn run_direct_command(&mut self, input: &str) {
+        let Some(parsed) = direct_shell::parse(input) else {
+            return;
+        };
+        if !std::io::stdin().is_terminal() || !stdout().is_terminal() {
+            println!("{} {}", "❌".red(), t!("cli.direct_shell.not_interactive"));
+            return;
+        }
+        let command = match parsed {
+            DirectInput::Repeat => match &self.last_direct_command {
+                Some(command) => command.clone(),
+                None => {
+                    println!("{}", t!("cli.direct_shell.no_previous").dimmed());
+                    return;
+                }
+            },
+            DirectInput::Command("") => {
+                println!("{}", t!("cli.direct_shell.usage").dimmed());
+                return;
+            }
+            DirectInput::Command(command) => command.to_string(),
+        };
+        let settings = match ToolSettings::load() {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.direct_shell.failed", error = e)
+                );
+                return;
+            }
+        };
+
+        println!("{} {}", "$".bright_green(), command.bright_white());
+        let output = match direct_shell::run(&command, &settings).await {
+            Ok(output) => output,
+            Err(crate::tools::FileToolError::PermissionDenied(reason)) => {
+                println!(
+                    "{} {}",
+                    "🚫".red(),
+                    t!("cli.direct_shell.blocked", reason = reason)
+                );
+                return;
+            }
+            Err(e) => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.direct_shell.failed", error = e.message())
+                );
+                return;
+            }
+        };
+        self.last_direct_command = Some(command);
+
+        if !output.stdout.trim().is_empty() {
+            println!("{}", output.stdout.trim_end());
+        }
+        if !output.stderr.trim().is_empty() {
+            println!("{}", output.stderr.trim_end().bright_red());
+        }
+        let status = match output.exit_code {
+            Some(code) => t!("cli.direct_shell.exit_code", code = code),
+            None => t!("cli.direct_shell.no_exit_code").to_string(),
+        };
+        if output.success {
+            println!("  └─ {}", status.dimmed());
+        } else {
+            println!("  └─ {}", status.red());
+        }
+
+        self.session.context.add_message_with_tags(
+            Message::user(direct_shell::context_message(&output)),
+            vec![SHELL_OUTPUT_TAG.to_string()],
+        );
+        if let Err(e) = self.session.context.save() {
+            println!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!("cli.save_context_failed", error = e)
+            );
+        } else {
+            println!("{}", t!("cli.direct_shell.added").dimmed());
+        }
+        println!();
+    }
+
+    fn undo_last_write(&self) {
+        match crate::tools::safe_write::undo_last() {
+            Ok(Some(entry)) => {
+                let action = if entry.previous.is_some() { "Restored" } else { "Removed" };
+                println!(
+                    "{} {} {}",
+                    "↩️".bright_green(),
+                    action,
+                    entry.path.display().to_string().bright_cyan()
+                );
+            }
+            Ok(None) => println!("{} Nothing to undo", "💡".bright_blue()),
+            Err(e) => println!("{} Failed to undo: {}", "❌".red(), e),
+        }
+        println!();
+    }
+
+    async fn list_mcp_servers(&self) {
+        let servers = self.mcp.status().await;
+        if servers.is_empty() {
+            println!("{}", "🔌 No MCP servers configured".bright_yellow());
+            println!("{} Add servers under [mcp_servers.<name>] in config.toml", "💡".bright_blue());
+            println!();
+            return;
+        }
+
+        println!("{}", "🔌 MCP Servers:".bright_cyan());
+        for server in servers {
+            let state = if !server.enabled {
+                "disabled".dimmed()
+            } else if server.running {
+                "running".bright_green()
+            } else {
+                "stopped".bright_yellow()
+            };
+            println!(
+                "  {} [{}] - {} tools",
+                server.name.bright_white(),
+                state,
+                server.tools.len()
+            );
+            for tool in &server.tools {
+                println!("    {}", crate::mcp::namespaced_tool_name(&server.name, tool).dimmed());
+            }
+            if let Some(error) = &server.last_error {
+                println!("    {} {}", "⚠️".yellow(), error.red());
+            }
+        }
+        println!();
+    }
+
+    /// 启用 MCP 服务器
+    async fn enable_mcp_server(&self, name: &str) {
+        let Some(server) = self.mcp.get(name) else {
+            println!("{} MCP server not found: {}", "❌".red(), name);
+            return;
+        };
+
+        let had_tools = !server.tools().is_empty();
+        match server.enable().await {
+            Ok(()) => {
+                println!("{} MCP server '{}' enabled", "✅".bright_green(), name);
+                if !had_tools && !server.tools().is_empty() {
+                    // 工具列表在 Agent 创建时确定
+                    println!(
+                        "{} Its tools will be available after restarting oxide",
+                        "💡".bright_blue()
+                    );
+                }
+            }
+            Err(e) => println!("{} Failed to start MCP server '{}': {}", "❌".red(), name, e),
+        }
+    }
+
+    /// 禁用 MCP 服务器
+    async fn disable_mcp_server(&self, name: &str) {
+        match self.mcp.get(name) {
+            Some(server) => {
+                server.disable().await;
+                println!("{} MCP server '{}' disabled", "✅".bright_green(), name);
+            }
+            None => println!("{} MCP server not found: {}", "❌".red(), name),
+        }
+    }
+
     /// 使用 PAOR 工作流处理复杂任务
-    async fn handle_with_workflow(&mut self, input: &str) -> Result<()> {
+    async fn handle_with_workflow(&mut self, input: &str, tags: Vec<String>) -> Result<()> {
+        self.wait_for_warmup().await;
         println!();
 
         // 根据模式显示不同的提示
         if self.is_plan_mode() {
-            println!("{}", "📋 Plan 模式 - 使用 PAOR 工作流引擎".bright_cyan());
+            println!("{}", t!("cli.workflow.plan_mode").bright_cyan());
         } else {
-            println!("{}", "🤖 检测到复杂任务，启用 PAOR 工作流引擎".bright_cyan());
+            println!("{}", t!("cli.workflow.complex_detected").bright_cyan());
         }
         println!();
 
         // 处理文件引用
-        let (parsed_input, file_refs) = parse_file_references(input);
+        let (parsed_input, file_refs) = parse_file_references(input, &self.roots);
 
         // 显示文件引用信息
         if !file_refs.is_empty() {
-            println!("{}", "📎 已引用文件:".bright_cyan());
+            println!("{}", t!("cli.referenced_files").bright_cyan());
             for ref_info in &file_refs {
                 println!("  {}", ref_info.display_info());
             }
             println!();
         }
+        palette::record_files(file_refs.iter().map(|r| r.file_path.as_path()));
+        let images = self.image_attachment(input);
 
         // 构建完整的用户请求（包含文件内容）
         let full_request = if !file_refs.is_empty() {
@@ -210,18 +1057,21 @@ impl OxideCli {
         } else {
             input.to_string()
         };
+        let full_request = self.with_pending_template(full_request);
+
+        // 标签的提示词片段和系统提醒只随本轮请求发送，不写入对话历史
+        let reminders = self.reminders.begin_turn(self.is_plan_mode());
+        verbose_tools().begin_turn();
+        let outgoing_request =
+            with_reminders(&reminders, &self.tags.apply_prompts(&tags, &full_request));
 
         // 添加用户消息到上下文
-        self.context_manager.add_message(Message::user(&full_request));
+        self.session
+            .context
+            .add_message_with_tags(Message::user(&full_request), tags);
 
         // 计算 token 预估
-        let messages = self.context_manager.get_messages();
-        let input_tokens = count_messages_tokens(
-            &messages.iter().map(|m| {
-                let serializable = SerializableMessage::from(m);
-                (serializable.role, serializable.content)
-            }).collect::<Vec<_>>()
-        );
+        let input_tokens = self.session.context.estimated_input_tokens();
 
         let estimated_output = (input_tokens as f64 * 1.5).ceil() as usize;
         let usage = TokenUsage::new(input_tokens, estimated_output);
@@ -230,33 +1080,39 @@ impl OxideCli {
         println!(
             "{} {} | {} {} | {} {}",
             "📊".bright_blue(),
-            format!("输入: {} tokens", usage.input_tokens).bright_white(),
-            "预估输出".bright_yellow(),
+            t!("cli.usage.input", tokens = usage.input_tokens).bright_white(),
+            t!("cli.usage.estimated_output").bright_yellow(),
             format!("~{} tokens", usage.output_tokens).bright_yellow(),
-            "成本".bright_green(),
-            format!("${:.6}", usage.estimated_cost()).bright_green()
+            t!("cli.usage.cost").bright_green(),
+            format!("${:.6}", usage.estimated_cost(&self.model_name)).bright_green()
         );
         println!();
 
         // 显示工作流阶段说明
-        println!("{}", "📋 PAOR 工作流阶段:".bright_yellow());
-        println!("  {} Planning  - 分析任务，制定执行计划", "1.".dimmed());
-        println!("  {} Acting    - 执行计划中的任务", "2.".dimmed());
-        println!("  {} Observing - 收集和分析执行结果", "3.".dimmed());
-        println!("  {} Reflecting - 评估进展，决定下一步", "4.".dimmed());
+        println!("{}", t!("cli.workflow.phases").bright_yellow());
+        println!("  {} {}", "1.".dimmed(), t!("cli.workflow.phase_planning"));
+        println!("  {} {}", "2.".dimmed(), t!("cli.workflow.phase_acting"));
+        println!("  {} {}", "3.".dimmed(), t!("cli.workflow.phase_observing"));
+        println!(
+            "  {} {}",
+            "4.".dimmed(),
+            t!("cli.workflow.phase_reflecting")
+        );
         println!();
 
         // 创建工作流执行器
         let executor = WorkflowExecutor::new(
-            full_request.clone(),
+            with_project_memory(&outgoing_request),
             Arc::clone(&self.subagent_manager),
         ).with_verbose(true);
 
         // 执行工作流
-        self.spinner.start("PAOR 工作流执行中...");
+        self.spinner.start(t!("cli.workflow.running"));
         stdout().flush().unwrap();
 
-        let result: Result<WorkflowResult, anyhow::Error> = executor.execute(&self.agent).await;
+        let result: Result<WorkflowResult, anyhow::Error> =
+            executor.execute(&self.session.agent).await;
+        self.reminders.end_turn();
 
         self.spinner.stop();
         println!();
@@ -266,146 +1122,191 @@ impl OxideCli {
                 // 在 Plan 模式下保存计划到文件
                 if self.is_plan_mode() {
                     if let Err(e) = self.save_plan_to_file(&workflow_result) {
-                        println!("{} 保存计划文件失败: {}", "⚠️".yellow(), e);
+                        println!(
+                            "{} {}",
+                            "⚠️".yellow(),
+                            t!("cli.workflow.save_plan_failed", error = e)
+                        );
                     }
                 }
 
                 // 显示工作流结果
                 if workflow_result.success {
-                    println!("{}", "✅ 工作流执行成功".bright_green());
+                    println!("{}", t!("cli.workflow.succeeded").bright_green());
                 } else {
-                    println!("{}", "⚠️  工作流执行未完成".bright_yellow());
+                    println!("{}", t!("cli.workflow.incomplete").bright_yellow());
                     if let Some(ref reason) = workflow_result.failure_reason {
-                        println!("  原因: {}", reason.bright_red());
+                        println!(
+                            "{}",
+                            t!("cli.workflow.reason", reason = reason.bright_red())
+                        );
                     }
                 }
 
                 println!();
-                println!("{}", "📊 执行统计:".bright_cyan());
-                println!("  迭代次数: {}", workflow_result.iterations.to_string().bright_white());
-                println!("  最终状态: {}", format!("{}", workflow_result.phase).bright_white());
+                println!("{}", t!("cli.workflow.stats").bright_cyan());
+                println!(
+                    "{}",
+                    t!(
+                        "cli.workflow.iterations",
+                        count = workflow_result.iterations.to_string().bright_white()
+                    )
+                );
+                println!(
+                    "{}",
+                    t!(
+                        "cli.workflow.final_phase",
+                        phase = workflow_result.phase.to_string().bright_white()
+                    )
+                );
                 println!();
 
                 // 获取响应内容
                 let response_content = workflow_result.display_response();
 
                 // 添加助手响应到上下文
-                self.context_manager
+                self.session
+                    .context
                     .add_message(Message::assistant(&response_content));
 
                 // 保存上下文
-                if let Err(e) = self.context_manager.save() {
-                    println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                if let Err(e) = self.session.context.save() {
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!("cli.save_context_failed", error = e)
+                    );
                 }
 
                 // 显示响应摘要
-                println!("{}", "📝 工作流摘要:".bright_cyan());
+                println!("{}", t!("cli.workflow.summary").bright_cyan());
                 println!();
 
                 // 限制显示长度
                 let display_content = if response_content.len() > 2000 {
-                    format!("{}...\n\n(响应已截断，完整内容已保存到上下文)", &response_content[..2000])
+                    format!(
+                        "{}...\n\n{}",
+                        &response_content[..2000],
+                        t!("cli.workflow.truncated")
+                    )
                 } else {
                     response_content
                 };
                 println!("{}", display_content);
             }
             Err(e) => {
-                println!("{} 工作流执行失败: {}", "❌".red(), e);
-                println!(
-                    "{} 请检查 API 配置和网络连接",
-                    "💡".bright_blue()
-                );
+                telemetry::turn_failed(&e);
+                println!("{} {}", "❌".red(), t!("cli.workflow.failed", error = e));
+                println!("{} {}", "💡".bright_blue(), t!("cli.workflow.check_api"));
 
                 // 回退到简单对话模式
                 println!();
-                println!("{}", "🔄 回退到简单对话模式...".bright_yellow());
-                return self.handle_with_simple_chat_internal(&full_request).await;
+                println!("{}", t!("cli.workflow.fallback").bright_yellow());
+                return self.handle_with_simple_chat_internal(&outgoing_request).await;
             }
         }
 
+        self.show_advisories();
+
         Ok(())
     }
 
     /// 内部简单对话处理（用于回退）
     async fn handle_with_simple_chat_internal(&mut self, input: &str) -> Result<()> {
-        self.spinner.start("Thinking...");
+        let prompt = with_project_memory(input);
+        self.spinner.start(t!("cli.thinking"));
         stdout().flush().unwrap();
 
-        let hook = SessionIdHook::new(self.context_manager.session_id().to_string());
+        let turn = self.turn_span();
+        let hook = SessionIdHook::new(self.session.context.session_id().to_string())
+            .with_turn_span(turn.clone())
+            .with_reminders(self.reminders.clone())
+            .with_advisories(self.advisories.clone());
+        let started = Instant::now();
 
-        let response_result: Result<rig::agent::FinalResponse, std::io::Error> = match &self.agent {
-            AgentType::OpenAI(agent) => {
-                let mut stream = agent
-                    .stream_prompt(input)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                self.spinner.stop();
-                stream_with_animation(&mut stream).await
-            }
-            AgentType::Anthropic(agent) => {
-                let mut stream = agent
-                    .stream_prompt(input)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                self.spinner.stop();
-                stream_with_animation(&mut stream).await
-            }
-        };
+        let response_result = self
+            .run_turn(&prompt, images, &hook)
+            .instrument(turn.clone())
+            .await;
+        self.reminders.end_turn();
 
         println!();
 
         match response_result {
-            Ok(resp) => {
-                let response_content = resp.response();
-                self.context_manager
+            Ok(reply) => {
+                turn.record("outcome", reply.outcome());
+                let response_content = reply.text();
+                self.session
+                    .context
                     .add_message(Message::assistant(response_content));
 
-                if let Err(e) = self.context_manager.save() {
-                    println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                if let Err(e) = self.session.context.save() {
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!("cli.save_context_failed", error = e)
+                    );
                 }
 
-                self.add_session_tokens(resp.usage().total_tokens as u64);
-                self.show_token_usage_animated(resp.usage().total_tokens as u64).await;
+                crate::usage::record_turn(
+                    self.session.context.session_id(),
+                    &self.model_name,
+                    &reply.usage(),
+                );
+                self.add_session_tokens(reply.usage().total_tokens as u64);
+                self.show_token_usage_animated(reply.usage().total_tokens as u64).await;
+                self.show_turn_summary(started, &hook, &reply.usage());
+                self.offer_remaining_steps(&reply);
             }
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::Interrupted
                     && e.to_string().contains("prompt_cancelled")
                 {
-                    println!("{} 操作已取消", "🚫".red());
+                    turn.record("outcome", "cancelled");
+                    turn.in_scope(|| telemetry::cancelled("prompt cancelled"));
+                    println!("{} {}", "🚫".red(), t!("cli.cancelled"));
                     // 将取消信息保存到对话历史，让 Agent 知道操作被用户拒绝
-                    self.context_manager
-                        .add_message(Message::assistant("[操作已被用户取消]"));
-                    if let Err(e) = self.context_manager.save() {
-                        println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                    self.session
+                        .context
+                        .add_message(Message::assistant(t!("cli.cancelled_history")));
+                    if let Err(e) = self.session.context.save() {
+                        println!(
+                            "{} {}",
+                            "⚠️".yellow(),
+                            t!("cli.save_context_failed", error = e)
+                        );
                     }
                 } else {
-                    println!("{} Failed to get AI response: {}", "❌".red(), e);
+                    turn.record("outcome", "error");
+                    turn.in_scope(|| telemetry::turn_failed(&e));
+                    println!("{} {}", "❌".red(), t!("cli.response_failed", error = e));
+                    print_error_hint(&OxideError::from(e));
                 }
             }
         }
 
+        self.show_advisories();
+
         Ok(())
     }
 
     /// 使用简单对话模式处理任务
-    async fn handle_with_simple_chat(&mut self, input: &str) -> Result<()> {
+    async fn handle_with_simple_chat(&mut self, input: &str, tags: Vec<String>) -> Result<()> {
+        self.wait_for_warmup().await;
         // 处理文件引用
-        let (parsed_input, file_refs) = parse_file_references(input);
+        let (parsed_input, file_refs) = parse_file_references(input, &self.roots);
 
         // 显示文件引用信息
         if !file_refs.is_empty() {
             println!();
-            println!("{}", "📎 已引用文件:".bright_cyan());
+            println!("{}", t!("cli.referenced_files").bright_cyan());
             for ref_info in &file_refs {
                 println!("  {}", ref_info.display_info());
             }
             println!();
         }
+        palette::record_files(file_refs.iter().map(|r| r.file_path.as_path()));
+        let images = self.image_attachment(input);
 
         // 构建完整的用户消息（包含文件内容）
         let enhanced_input = if !file_refs.is_empty() {
@@ -426,18 +1327,21 @@ impl OxideCli {
         } else {
             input.to_string()
         };
+        let enhanced_input = self.with_pending_template(enhanced_input);
+
+        // 标签的提示词片段和系统提醒只随本轮请求发送，不写入对话历史
+        let reminders = self.reminders.begin_turn(self.is_plan_mode());
+        verbose_tools().begin_turn();
+        let outgoing_input =
+            with_reminders(&reminders, &self.tags.apply_prompts(&tags, &enhanced_input));
 
         // Add user message to context
-        self.context_manager.add_message(Message::user(&enhanced_input));
+        self.session
+            .context
+            .add_message_with_tags(Message::user(&enhanced_input), tags);
 
         // 计算 token 预估
-        let messages = self.context_manager.get_messages();
-        let input_tokens = count_messages_tokens(
-            &messages.iter().map(|m| {
-                let serializable = SerializableMessage::from(m);
-                (serializable.role, serializable.content)
-            }).collect::<Vec<_>>()
-        );
+        let input_tokens = self.session.context.estimated_input_tokens();
 
         // 预估输出 tokens（通常是输入的 1.5-2 倍，这里保守估计）
         let estimated_output = (input_tokens as f64 * 0.5).ceil() as usize;
@@ -449,87 +1353,98 @@ impl OxideCli {
         println!(
             "{} {} | {} {} | {} {}",
             "📊".bright_blue(),
-            format!("输入: {} tokens", usage.input_tokens).bright_white(),
-            "预估输出".bright_yellow(),
+            t!("cli.usage.input", tokens = usage.input_tokens).bright_white(),
+            t!("cli.usage.estimated_output").bright_yellow(),
             format!("~{} tokens", usage.output_tokens).bright_yellow(),
-            "成本".bright_green(),
-            format!("${:.6}", usage.estimated_cost()).bright_green()
+            t!("cli.usage.cost").bright_green(),
+            format!("${:.6}", usage.estimated_cost(&self.model_name)).bright_green()
         );
         println!();
 
+        // 项目记忆只随本轮请求发送，不写入对话历史
+        let prompt = with_project_memory(&outgoing_input);
+
         // Start spinner
-        self.spinner.start("Thinking...");
+        self.spinner.start(t!("cli.thinking"));
         stdout().flush().unwrap();
 
         // Create session hook
-        let hook = SessionIdHook::new(self.context_manager.session_id().to_string());
-
-        let response_result: Result<rig::agent::FinalResponse, std::io::Error> = match &self.agent {
-            AgentType::OpenAI(agent) => {
-                let mut stream = agent
-                    .stream_prompt(&enhanced_input)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                // Stop spinner before response starts
-                self.spinner.stop();
-                stream_with_animation(&mut stream).await
-            }
-            AgentType::Anthropic(agent) => {
-                let mut stream = agent
-                    .stream_prompt(&enhanced_input)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                self.spinner.stop();
-                stream_with_animation(&mut stream).await
-            }
-        };
+        let turn = self.turn_span();
+        let hook = SessionIdHook::new(self.session.context.session_id().to_string())
+            .with_turn_span(turn.clone())
+            .with_reminders(self.reminders.clone())
+            .with_advisories(self.advisories.clone());
+        let started = Instant::now();
+
+        let response_result = self
+            .run_turn(&prompt, images, &hook)
+            .instrument(turn.clone())
+            .await;
+        self.reminders.end_turn();
 
         println!();
 
         match response_result {
-            Ok(resp) => {
+            Ok(reply) => {
+                turn.record("outcome", reply.outcome());
                 // Get response content and add to context
-                let response_content = resp.response();
-                self.context_manager
+                let response_content = reply.text();
+                self.session
+                    .context
                     .add_message(Message::assistant(response_content));
 
                 // Auto-save context
-                if let Err(e) = self.context_manager.save() {
-                    println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                if let Err(e) = self.session.context.save() {
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!("cli.save_context_failed", error = e)
+                    );
                 }
 
                 // We can't easily get token usage from the stream response in rig currently without more complex handling,
                 // or if stream_to_stdout returns it.
                 // rig 0.28 stream_to_stdout returns Result<StreamingResponse> which has a usage method?
                 // Let's assume it works.
-                self.add_session_tokens(resp.usage().total_tokens as u64);
-                self.show_token_usage_animated(resp.usage().total_tokens as u64).await;
+                crate::usage::record_turn(
+                    self.session.context.session_id(),
+                    &self.model_name,
+                    &reply.usage(),
+                );
+                self.add_session_tokens(reply.usage().total_tokens as u64);
+                self.show_token_usage_animated(reply.usage().total_tokens as u64).await;
+                self.show_turn_summary(started, &hook, &reply.usage());
+                self.offer_remaining_steps(&reply);
             }
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::Interrupted
                     && e.to_string().contains("prompt_cancelled")
                 {
-                    println!("{} 操作已取消", "🚫".red());
+                    turn.record("outcome", "cancelled");
+                    turn.in_scope(|| telemetry::cancelled("prompt cancelled"));
+                    println!("{} {}", "🚫".red(), t!("cli.cancelled"));
                     // 将取消信息保存到对话历史，让 Agent 知道操作被用户拒绝
-                    self.context_manager
-                        .add_message(Message::assistant("[操作已被用户取消]"));
-                    if let Err(e) = self.context_manager.save() {
-                        println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                    self.session
+                        .context
+                        .add_message(Message::assistant(t!("cli.cancelled_history")));
+                    if let Err(e) = self.session.context.save() {
+                        println!(
+                            "{} {}",
+                            "⚠️".yellow(),
+                            t!("cli.save_context_failed", error = e)
+                        );
                     }
-                } else {
-                    println!("{} Failed to get AI response: {}", "❌".red(), e);
-                    println!(
-                        "{} Please check your API key and network connection",
-                        "💡".bright_blue()
-                    );
+                } else {
+                    turn.record("outcome", "error");
+                    turn.in_scope(|| telemetry::turn_failed(&e));
+                    println!("{} {}", "❌".red(), t!("cli.response_failed", error = e));
+                    print_error_hint(&OxideError::from(e));
                 }
             }
         }
 
+        self.show_advisories();
+
         Ok(())
     }
 
@@ -541,10 +1456,99 @@ impl OxideCli {
             "Auth Token:".bright_white(),
             "*".repeat(self.api_key.len().min(8))
         );
+        println!("  {}", t!("cli.config.tools").bright_white());
+        match ToolSettings::load() {
+            Ok(settings) => {
+                for (key, value) in settings.entries() {
+                    let value = match value {
+                        Some(value) => value.normal(),
+                        None => t!("cli.config.tool_default").dimmed(),
+                    };
+                    println!("    {:<30} {}", key, value);
+                }
+            }
+            Err(e) => println!("    {} {}", "⚠".bright_yellow(), e.to_string().bright_yellow()),
+        }
         println!();
         Ok(())
     }
 
+    fn show_status(&self) {
+        println!("{}", t!("cli.status.title").bright_cyan());
+        println!(
+            "  {} {}",
+            t!("cli.status.version").bright_white(),
+            env!("CARGO_PKG_VERSION")
+        );
+        println!(
+            "  {} {}",
+            t!("cli.status.model").bright_white(),
+            self.model_name
+        );
+        println!(
+            "  {} {}",
+            t!("cli.status.session").bright_white(),
+            self.session.context.session_id()
+        );
+        if let Some(env) = self.session.context.session_environment() {
+            println!(
+                "  {} {}",
+                t!("cli.status.session_created").bright_white(),
+                t!(
+                    "cli.status.session_created_value",
+                    model = env.model_label(),
+                    version = env.oxide_version,
+                    mode = env.mode
+                )
+            );
+            let location = match env.git_label() {
+                Some(git) => format!("{} ({})", env.cwd, git),
+                None => env.cwd.clone(),
+            };
+            println!(
+                "  {} {}",
+                t!("cli.status.session_dir").bright_white(),
+                location
+            );
+        }
+        println!(
+            "  {} {}",
+            t!("cli.status.output").bright_white(),
+            t!(
+                "cli.status.output_value",
+                style = self.output.style.as_str(),
+                language = self.output.language.as_str()
+            )
+        );
+        let sandbox = Sandbox::from_config();
+        println!(
+            "  {} {}",
+            t!("cli.status.sandbox").bright_white(),
+            sandbox.describe()
+        );
+        for note in sandbox.fallback_notes() {
+            println!("    {} {}", "⚠".bright_yellow(), note.bright_yellow());
+        }
+        println!(
+            "  {} {}",
+            t!("cli.status.network").bright_white(),
+            network::current().describe()
+        );
+        match telemetry::active_log_file() {
+            Some(path) => println!(
+                "  {} {}",
+                t!("cli.status.log_file").bright_white(),
+                path.display().to_string().bright_cyan()
+            ),
+            None => println!(
+                "  {} {}",
+                t!("cli.status.log_file").bright_white(),
+                t!("cli.status.log_disabled").dimmed()
+            ),
+        }
+        println!();
+    }
+
     fn edit_config(&self) -> Result<()> {
         // 查找配置文件
         let config_paths = vec![
@@ -726,76 +1730,111 @@ impl OxideCli {
     }
 
     fn show_help(&self) -> Result<()> {
-        println!("{}", "📚 Oxide CLI - Help & Commands".bright_cyan().bold());
+        println!("{}", t!("cli.help.title").bright_cyan().bold());
         println!();
 
         // 斜杠命令列表
-        println!("{}", "═══ Slash Commands ═══".bright_black());
+        println!("{}", t!("cli.help.slash_commands").bright_black());
         println!();
-        println!("  {} - Exit the application", "/quit or /exit".bright_green());
-        println!("  {} - Clear all messages in current session", "/clear".bright_green());
-        println!(
-            "  {} - Show or edit configuration",
-            "/config [show|edit|reload|validate]".bright_green()
-        );
-        println!(
-            "  {} - Show conversation history",
-            "/history".bright_green()
-        );
-        println!(
-            "  {} - Load specific session",
-            "/load <session_id>".bright_green()
-        );
-        println!("  {} - List all sessions", "/sessions".bright_green());
-        println!(
-            "  {} - Delete a specific session",
-            "/delete <session_id>".bright_green()
-        );
-        println!("  {} - List Agent types or show capabilities", "/agent [list|capabilities]".bright_green());
-        println!("  {} - Manage background tasks", "/tasks [list|show <id>|cancel <id>]".bright_green());
-        println!("  {} - Manage and use skills", "/skills [list|show <name>]".bright_green());
-        println!("  {} - Show this help message", "/help".bright_green());
+        for command in help::builtin_commands() {
+            println!(
+                "  {} - {}",
+                command.usage().bright_green(),
+                command.description
+            );
+        }
+        println!();
+
+        // 自定义命令和技能
+        let mut skills = SkillManager::new()
+            .map(|manager| manager.list_skills())
+            .unwrap_or_default();
+        skills.sort_by(|a, b| a.name.cmp(&b.name));
+        let (custom, builtin): (Vec<_>, Vec<_>) =
+            skills.iter().partition(|skill| help::is_custom_command(skill));
+        for (title, skills) in [
+            (t!("cli.help.custom_commands"), custom),
+            (t!("cli.help.skills"), builtin),
+        ] {
+            if skills.is_empty() {
+                continue;
+            }
+            println!("{}", title.bright_black());
+            println!();
+            for skill in skills {
+                println!(
+                    "  {} - {}",
+                    CommandHelp::from_skill(skill).usage().bright_green(),
+                    skill.description
+                );
+            }
+            println!();
+        }
+
+        // 别名
+        println!("{}", t!("cli.help.aliases").bright_black());
+        println!();
+        for (alias, command) in help::ALIASES {
+            println!(
+                "  {} - {}",
+                alias.bright_green(),
+                t!("cli.help.alias_of", command = command)
+            );
+        }
         println!();
 
         // Agent 类型列表
-        println!("{}", "═══ Available Agents ═══".bright_black());
+        println!("{}", t!("cli.help.agents").bright_black());
         println!();
         let manager = SubagentManager::new();
         let capabilities = manager.list_capabilities();
 
         for cap in &capabilities {
-            let current_marker = if matches!(&self.agent, AgentType::Anthropic(_) if cap.agent_type == NewAgentType::Main) {
-                " (current)".bright_green()
+            let current_marker = if matches!(&self.session.agent, AgentType::Anthropic(_) if cap.agent_type == NewAgentType::Main)
+            {
+                t!("cli.help.current").bright_green()
             } else {
                 "".normal()
             };
 
-            println!("  {}{} - {}", cap.name.bright_white(), current_marker, cap.description.bright_black());
-            println!("    {}", format!("Tools: {}", cap.tools.join(", ")).dimmed());
+            println!(
+                "  {}{} - {}",
+                cap.name.bright_white(),
+                current_marker,
+                cap.description.bright_black()
+            );
+            println!(
+                "    {}",
+                t!("cli.help.agent_tools", tools = cap.tools.join(", ")).dimmed()
+            );
             if cap.read_only {
-                println!("    {} {}", "🔒".bright_red(), "Read-only".bright_red());
+                println!(
+                    "    {} {}",
+                    "🔒".bright_red(),
+                    t!("cli.help.read_only").bright_red()
+                );
             }
             println!();
         }
 
         // 可用工具列表
-        println!("{}", "═══ Available Tools ═══".bright_black());
+        println!("{}", t!("cli.help.tools").bright_black());
         println!();
         let tools = vec![
-            ("read", "Read file contents"),
-            ("write", "Write or create files"),
-            ("edit", "Edit specific parts of a file"),
-            ("delete", "Delete files or directories"),
-            ("shell_execute", "Execute shell commands"),
-            ("grep", "Search for patterns in files"),
-            ("scan", "Scan directory structure"),
-            ("mkdir", "Create directories"),
-            ("glob", "Match files using patterns"),
-            ("multi_edit", "Edit multiple files at once"),
-            ("notebook_edit", "Edit Jupyter notebooks"),
-            ("ask_user_question", "Ask the user questions"),
-            ("task", "Spawn background tasks"),
-            ("task_output", "Get background task output"),
+            ("read", t!("cli.help.tool.read")),
+            ("write", t!("cli.help.tool.write")),
+            ("edit", t!("cli.help.tool.edit")),
+            ("delete", t!("cli.help.tool.delete")),
+            ("shell_execute", t!("cli.help.tool.shell_execute")),
+            ("grep", t!("cli.help.tool.grep")),
+            ("scan", t!("cli.help.tool.scan")),
+            ("mkdir", t!("cli.help.tool.mkdir")),
+            ("glob", t!("cli.help.tool.glob")),
+            ("multi_edit", t!("cli.help.tool.multi_edit")),
+            ("notebook_edit", t!("cli.help.tool.notebook_edit")),
+            ("ask_user_question", t!("cli.help.tool.ask_user_question")),
+            ("task", t!("cli.help.tool.task")),
+            ("task_output", t!("cli.help.tool.task_output")),
         ];
 
         for (tool, description) in tools {
@@ -804,62 +1843,335 @@ impl OxideCli {
         println!();
 
         // 使用示例
-        println!("{}", "═══ Usage Examples ═══".bright_black());
+        println!("{}", t!("cli.help.examples").bright_black());
         println!();
-        println!("  {}", "Basic Chat:".bright_yellow());
-        println!("    {}", "Hello, how are you?".dimmed());
+        println!("  {}", t!("cli.help.example_chat").bright_yellow());
+        println!("    {}", t!("cli.help.example_chat_text").dimmed());
         println!();
-        println!("  {}", "File References:".bright_yellow());
-        println!("    {}", "@src/main.rs 请帮我重构这个文件".dimmed());
-        println!("    {}", "@Cargo.toml @README.md 比较这两个文件".dimmed());
+        println!("  {}", t!("cli.help.example_files").bright_yellow());
+        println!("    {}", t!("cli.help.example_refactor").dimmed());
+        println!("    {}", t!("cli.help.example_compare").dimmed());
         println!();
-        println!("  {}", "Session Management:".bright_yellow());
+        println!("  {}", t!("cli.help.example_sessions").bright_yellow());
         println!("    {}", "/sessions".dimmed());
         println!("    {}", "/load abc123".dimmed());
         println!();
-        println!("  {}", "Agent Commands:".bright_yellow());
+        println!("  {}", t!("cli.help.example_agents").bright_yellow());
         println!("    {}", "/agent list".dimmed());
         println!("    {}", "/agent capabilities".dimmed());
         println!();
-        println!("  {}", "Configuration:".bright_yellow());
+        println!("  {}", t!("cli.help.example_config").bright_yellow());
         println!("    {}", "/config show".dimmed());
         println!("    {}", "/config validate".dimmed());
         println!();
 
         // 提示
-        println!("{}", "═══ Tips ═══".bright_black());
+        println!("{}", t!("cli.help.tips").bright_black());
         println!();
+        println!("{}", t!("cli.help.tip_chat").bright_white());
+        println!("{}", t!("cli.help.tip_files").bright_blue());
+        println!("{}", t!("cli.help.tip_commands").bright_blue());
+        println!("{}", t!("cli.help.detail_hint").bright_blue());
+        println!("{}", t!("cli.help.tip_file_completion").bright_blue());
+        println!("{}", t!("cli.help.tip_agents").bright_blue());
+        println!("{}", t!("cli.help.tip_tools").bright_blue());
+        println!();
+
+        Ok(())
+    }
+
+    /// `/help <命令>`：显示单个命令的详情页，找不到时推荐拼写相近的命令
+    fn show_command_help(&self, name: &str) {
+        let mut commands = help::builtin_commands();
+        if let Ok(manager) = SkillManager::new() {
+            commands.extend(manager.list_skills().iter().map(CommandHelp::from_skill));
+        }
+        match help::find_command(name, &commands) {
+            Some(command) => print_markdown(&command.detail_markdown()),
+            None => self.report_unknown_command(&help::normalize_name(name)),
+        }
+    }
+
+    /// 确认后清空输入编辑器历史（对话历史不受影响）
+    fn confirm_history_clear(&mut self) -> Result<()> {
+        let confirmed = inquire::Confirm::new(t!("cli.history.clear_confirm"))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if confirmed {
+            self.pending_history_clear = true;
+        } else {
+            println!("{}", t!("cli.history.clear_cancelled").dimmed());
+        }
+        Ok(())
+    }
+
+    /// 解析 `/history` 中显示的消息序号（从 1 开始），返回从 0 开始的下标
+    fn parse_message_number(&self, arg: &str) -> Option<usize> {
+        let count = self.session.context.get_messages().len();
+        match arg.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Some(n - 1),
+            _ => {
+                println!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    t!("cli.pin.invalid", n = arg, count = count)
+                );
+                None
+            }
+        }
+    }
+
+    fn pin_message(&mut self, arg: &str, pinned: bool) {
+        let Some(index) = self.parse_message_number(arg) else {
+            return;
+        };
+        if let Err(e) = self.session.context.set_pinned(index, pinned) {
+            println!("{} {}", "❌".red(), t!("cli.pin.failed", error = e));
+            return;
+        }
+        let message = if pinned {
+            t!("cli.pin.pinned", n = index + 1)
+        } else {
+            t!("cli.pin.unpinned", n = index + 1)
+        };
+        println!("{} {}", "📌".bright_blue(), message);
+    }
+
+    /// 删除第 n 条及之后的消息（默认从最后一条用户消息开始），丢弃已固定的消息前需要确认
+    fn rewind_messages(&mut self, arg: &str) {
+        let from = if arg.is_empty() {
+            let last_user = self
+                .session
+                .context
+                .transcript()
+                .iter()
+                .rposition(|message| message.role == "user");
+            match last_user {
+                Some(index) => index,
+                None => {
+                    println!("{}", t!("cli.pin.nothing_to_rewind").dimmed());
+                    return;
+                }
+            }
+        } else {
+            match self.parse_message_number(arg) {
+                Some(index) => index,
+                None => return,
+            }
+        };
+
+        let outcome = match self.session.context.rewind(from, false) {
+            Ok(RewindOutcome::PinnedMessages(pinned)) => {
+                let numbers = pinned
+                    .iter()
+                    .map(|index| format!("#{}", index + 1))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let confirmed =
+                    inquire::Confirm::new(&t!("cli.pin.rewind_confirm", messages = numbers))
+                        .with_default(false)
+                        .prompt()
+                        .unwrap_or(false);
+                if !confirmed {
+                    println!("{}", t!("cli.pin.rewind_cancelled").dimmed());
+                    return;
+                }
+                self.session.context.rewind(from, true)
+            }
+            outcome => outcome,
+        };
+        match outcome {
+            Ok(RewindOutcome::Rewound(count)) => println!(
+                "{} {}",
+                "⏪".bright_blue(),
+                t!("cli.pin.rewound", count = count)
+            ),
+            Ok(RewindOutcome::PinnedMessages(_)) => {}
+            Err(e) => println!("{} {}", "❌".red(), t!("cli.pin.failed", error = e)),
+        }
+    }
+
+    fn checkpoint_store() -> Result<CheckpointStore> {
+        Ok(CheckpointStore::new(std::env::current_dir()?))
+    }
+
+    fn checkpoint_command(&mut self, arg: &str) {
+        let result = match arg.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["list"] => self.list_checkpoints(),
+            ["delete", name] => Self::checkpoint_store().and_then(|store| {
+                store.delete(name)?;
+                println!(
+                    "{} {}",
+                    "🗑".bright_blue(),
+                    t!("cli.checkpoint.deleted", name = name)
+                );
+                Ok(())
+            }),
+            [] => self.create_checkpoint(None),
+            [name] if *name != "delete" => self.create_checkpoint(Some(name)),
+            _ => {
+                println!("{}", t!("cli.checkpoint.usage").dimmed());
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            println!("{} {}", "❌".red(), t!("cli.checkpoint.failed", error = e));
+        }
+    }
+
+    fn create_checkpoint(&self, name: Option<&str>) -> Result<()> {
+        let checkpoint = Self::checkpoint_store()?.create(
+            name,
+            self.session.context.session_id(),
+            self.session.context.get_messages().len(),
+            &crate::tools::safe_write::touched_files(),
+        )?;
         println!(
-            "{}",
-            "💡 You can type any message to chat with the AI!".bright_white()
-        );
-        println!(
-            "{}",
-            "📎 Use @file_path to reference files in your messages".bright_blue()
-        );
-        println!(
-            "{}",
-            "⌨️  Press Tab after typing '/' to see available commands".bright_blue()
-        );
-        println!(
-            "{}",
-            "⌨️  Press Tab after typing '@' to see available files".bright_blue()
+            "{} {}",
+            "📍".bright_blue(),
+            t!(
+                "cli.checkpoint.created",
+                name = checkpoint.name,
+                count = checkpoint.message_count
+            )
         );
+        Ok(())
+    }
+
+    fn list_checkpoints(&self) -> Result<()> {
+        let checkpoints = Self::checkpoint_store()?.list()?;
+        if checkpoints.is_empty() {
+            println!("{}", t!("cli.checkpoint.none").dimmed());
+            return Ok(());
+        }
+        println!("{}", t!("cli.checkpoint.list_title").bright_cyan());
+        for checkpoint in checkpoints {
+            let mut line = t!(
+                "cli.checkpoint.list_item",
+                name = checkpoint.name.bright_white(),
+                time = checkpoint
+                    .created_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M"),
+                count = checkpoint.message_count
+            );
+            if checkpoint.session_id != self.session.context.session_id() {
+                line = format!("{} {}", line, t!("cli.checkpoint.other_session").dimmed());
+            }
+            println!("  {}", line);
+        }
+        Ok(())
+    }
+
+    fn restore_checkpoint(&mut self, arg: &str) {
+        let mut force = false;
+        let mut name = None;
+        for part in arg.split_whitespace() {
+            match part {
+                "--force" => force = true,
+                _ if name.is_none() => name = Some(part),
+                _ => name = Some(""),
+            }
+        }
+        let Some(name) = name.filter(|name| !name.is_empty()) else {
+            println!("{}", t!("cli.checkpoint.usage_restore").dimmed());
+            return;
+        };
+        if let Err(e) = self.try_restore_checkpoint(name, force) {
+            println!("{} {}", "❌".red(), t!("cli.checkpoint.failed", error = e));
+        }
+    }
+
+    fn try_restore_checkpoint(&mut self, name: &str, force: bool) -> Result<()> {
+        let store = Self::checkpoint_store()?;
+        let checkpoint = store.load(name)?;
+        let plan = store.plan_restore(&checkpoint)?;
+        let same_session = checkpoint.session_id == self.session.context.session_id();
+        let removed = if same_session {
+            self.session
+                .context
+                .get_messages()
+                .len()
+                .saturating_sub(checkpoint.message_count)
+        } else {
+            0
+        };
+
         println!(
-            "{}",
-            "🤖 主 Agent 为单实例，不支持手动切换；子 Agent 仅供内部调用".bright_blue()
+            "{} {}",
+            "⏪".bright_blue(),
+            t!("cli.checkpoint.restore_summary", name = checkpoint.name)
         );
+        if same_session {
+            println!(
+                "  {}",
+                t!("cli.checkpoint.restore_messages", count = removed)
+            );
+        } else {
+            println!(
+                "  {}",
+                t!(
+                    "cli.checkpoint.restore_other_session",
+                    session = checkpoint.session_id
+                )
+                .bright_yellow()
+            );
+        }
+        if plan.changes.is_empty() {
+            println!("  {}", t!("cli.checkpoint.restore_no_files").dimmed());
+        }
+        for (kind, path) in &plan.changes {
+            let path = store.display_path(path);
+            match kind {
+                ChangeKind::Modified => println!("  {} {}", "~".bright_yellow(), path),
+                ChangeKind::Recreated => println!("  {} {}", "+".bright_green(), path),
+                ChangeKind::Removed => println!("  {} {}", "-".bright_red(), path),
+            }
+        }
+        if !plan.conflicts.is_empty() {
+            let header = if force {
+                t!("cli.checkpoint.conflicts_forced")
+            } else {
+                t!("cli.checkpoint.conflicts")
+            };
+            println!("{} {}", "⚠️".bright_yellow(), header);
+            for path in &plan.conflicts {
+                println!("  {}", store.display_path(path).bright_yellow());
+            }
+            if !force {
+                return Ok(());
+            }
+        }
+        if plan.changes.is_empty() && removed == 0 {
+            return Ok(());
+        }
+
+        let confirmed = inquire::Confirm::new(t!("cli.checkpoint.restore_confirm"))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if !confirmed {
+            println!("{}", t!("cli.checkpoint.restore_cancelled").dimmed());
+            return Ok(());
+        }
+
+        store.restore(&checkpoint, &plan, force)?;
+        if same_session {
+            self.session
+                .context
+                .rewind(checkpoint.message_count, true)?;
+        }
         println!(
-            "{}",
-            "🔧 Tools are automatically available to the AI agent".bright_blue()
+            "{} {}",
+            "✅".bright_green(),
+            t!("cli.checkpoint.restored", name = checkpoint.name)
         );
-        println!();
-
         Ok(())
     }
 
     fn show_history(&self) -> Result<()> {
-        let messages = self.context_manager.get_messages();
+        let messages = self.session.context.transcript();
         if messages.is_empty() {
             println!(
                 "{} No conversation history in current session",
@@ -867,18 +2179,17 @@ impl OxideCli {
             );
             println!(
                 "  Current session: {}",
-                self.context_manager.session_id().bright_white()
+                self.session.context.session_id().bright_white()
             );
         } else {
             println!(
                 "{} Conversation History (Session: {})",
                 "📝".bright_blue(),
-                self.context_manager.session_id().bright_white()
+                self.session.context.session_id().bright_white()
             );
             println!();
 
-            for (i, message) in messages.iter().enumerate() {
-                let serializable = SerializableMessage::from(message);
+            for (i, serializable) in messages.into_iter().enumerate() {
                 let role_color = match serializable.role.as_str() {
                     "user" => "👤 User".bright_cyan(),
                     "assistant" => "🤖 Assistant".bright_green(),
@@ -886,7 +2197,16 @@ impl OxideCli {
                     _ => "❓ Unknown".bright_yellow(),
                 };
 
-                println!("{}. {}", (i + 1).to_string().bright_white(), role_color);
+                if serializable.pinned {
+                    println!(
+                        "{}. {} {}",
+                        (i + 1).to_string().bright_white(),
+                        role_color,
+                        "📌".bright_blue()
+                    );
+                } else {
+                    println!("{}. {}", (i + 1).to_string().bright_white(), role_color);
+                }
 
                 // Display content
                 let content = if serializable.content.chars().count() > 200 {
@@ -910,9 +2230,13 @@ impl OxideCli {
         Ok(())
     }
 
-    fn list_sessions(&self) -> Result<()> {
-        match self.context_manager.list_sessions() {
-            Ok(sessions) => {
+    /// 列出会话，`tag` 非空时只列出带该标签的会话
+    fn list_sessions(&self, tag: &str) -> Result<()> {
+        match self.session.context.list_sessions() {
+            Ok(mut sessions) => {
+                if !tag.is_empty() {
+                    sessions.retain(|session| session.tags.iter().any(|name| name == tag));
+                }
                 if sessions.is_empty() {
                     println!("{} No saved sessions found", "📁".bright_blue());
                 } else {
@@ -920,11 +2244,12 @@ impl OxideCli {
                     println!();
 
                     for (i, session) in sessions.iter().enumerate() {
-                        let current_marker = if session.session_id == self.context_manager.session_id() {
-                            " (current)".bright_green()
-                        } else {
-                            "".normal()
-                        };
+                        let current_marker =
+                            if session.session_id == self.session.context.session_id() {
+                                " (current)".bright_green()
+                            } else {
+                                "".normal()
+                            };
 
                         println!(
                             "{}. {} - {} messages{}",
@@ -934,6 +2259,20 @@ impl OxideCli {
                             current_marker
                         );
                         println!("   Last updated: {}", session.last_updated.dimmed());
+                        if let Some(env) = &session.environment {
+                            let mut details = vec![
+                                session.latest_model().unwrap_or_else(|| env.model_label()),
+                                format!("oxide {}", env.oxide_version),
+                                env.cwd.clone(),
+                            ];
+                            details.extend(env.git_label());
+                            println!("   {}", details.join(" · ").dimmed());
+                        }
+                        if !session.tags.is_empty() {
+                            let tags: Vec<String> =
+                                session.tags.iter().map(|name| format!("#{}", name)).collect();
+                            println!("   Tags: {}", tags.join(" ").bright_magenta());
+                        }
                     }
 
                     println!();
@@ -943,9 +2282,248 @@ impl OxideCli {
                     );
                 }
             }
-            Err(e) => {
-                println!("{} Failed to list sessions: {}", "❌".red(), e);
+            Err(e) => {
+                println!("{} Failed to list sessions: {}", "❌".red(), e);
+            }
+        }
+        println!();
+        Ok(())
+    }
+
+    /// 导出当前会话为 Markdown（`/export [#tag ...] [path]`）
+    fn export_session(&self, args: &str) -> Result<()> {
+        let mut tags = Vec::new();
+        let mut path = None;
+        for arg in args.split_whitespace() {
+            match arg.strip_prefix('#') {
+                Some(tag) => tags.push(tag.to_string()),
+                None => path = Some(std::path::PathBuf::from(arg)),
+            }
+        }
+        let path = path.unwrap_or_else(|| {
+            std::path::PathBuf::from(format!("{}.md", self.session.context.session_id()))
+        });
+
+        let (markdown, count) = render_markdown(
+            self.session.context.session_id(),
+            &self.session.context.transcript(),
+            &tags,
+        );
+        if count == 0 {
+            println!("{} {}", "⚠️".yellow(), t!("cli.export.empty"));
+            return Ok(());
+        }
+        match crate::atomic_file::write_atomic(&path, markdown.as_bytes()) {
+            Ok(()) => println!(
+                "{} {}",
+                "✅".bright_green(),
+                t!("cli.export.done", count = count, path = path.display())
+            ),
+            Err(e) => println!("{} {}", "❌".red(), t!("cli.export.failed", error = e)),
+        }
+        Ok(())
+    }
+
+    /// 在当前会话中搜索，按页显示结果（序号可直接用于 `/pin`、`/rewind`）
+    /// `/tools [序号]`：本次会话的工具调用列表或单个调用的详情
+    fn tools_command(&self, arg: &str) {
+        let records = crate::agent::tool_activity::global().records();
+        let lines = if arg.is_empty() {
+            tool_panel::list_lines(&records)
+        } else {
+            let record = arg
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| Some((index, records.get(index.checked_sub(1)?)?)));
+            match record {
+                Some((index, record)) => tool_panel::detail_lines(index, record),
+                None => vec![format!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.tool_panel.not_found", index = arg)
+                )],
+            }
+        };
+        for line in lines {
+            println!("{}", line);
+        }
+        println!();
+    }
+
+    fn cache_command(&self, arg: &str) {
+        let cache = crate::tools::web_cache::current();
+        let dir = cache
+            .dir
+            .as_ref()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        match arg.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] | ["stats"] => match cache.stats() {
+                Ok(stats) => {
+                    println!(
+                        "{}",
+                        t!(
+                            "cli.cache.stats",
+                            entries = stats.entries,
+                            expired = stats.expired,
+                            size = format!("{:.1} MB", stats.bytes as f64 / 1024.0 / 1024.0),
+                            dir = dir
+                        )
+                    );
+                    if cache.enabled() {
+                        println!(
+                            "  {}",
+                            t!(
+                                "cli.cache.limits",
+                                ttl = cache.ttl.as_secs() / 3600,
+                                max = cache.max_bytes / 1024 / 1024
+                            )
+                            .dimmed()
+                        );
+                    } else {
+                        println!("  {}", t!("cli.cache.disabled").yellow());
+                    }
+                }
+                Err(e) => println!("{} {}", "❌".red(), t!("cli.cache.failed", error = e)),
+            },
+            ["clear"] | ["clear", "web"] => match cache.clear() {
+                Ok(count) => println!("{} {}", "✓".green(), t!("cli.cache.cleared", count = count)),
+                Err(e) => println!("{} {}", "❌".red(), t!("cli.cache.failed", error = e)),
+            },
+            _ => println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!("cli.usage_hint", usage = "/cache [stats|clear [web]]")
+            ),
+        }
+        println!();
+    }
+
+    fn find_messages(&self, args: &str) {
+        let query = match FindQuery::parse(args) {
+            Ok(query) => query,
+            Err(e) => {
+                println!("{} {}", "❌".red(), e);
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!(
+                        "cli.usage_hint",
+                        usage = "/find [--regex] [--tool <name>] [--page <n>] <text>"
+                    )
+                );
+                return;
+            }
+        };
+
+        let matches = find::search(&self.session.context.transcript(), &query);
+        if matches.is_empty() {
+            println!("{}", t!("cli.find.none").dimmed());
+            return;
+        }
+        let pages = matches.len().div_ceil(find::PAGE_SIZE);
+        let page = query.page.min(pages);
+        println!(
+            "{} {}",
+            "🔎".bright_blue(),
+            t!(
+                "cli.find.found",
+                count = matches.len(),
+                page = page,
+                pages = pages
+            )
+        );
+        println!();
+        let start = (page - 1) * find::PAGE_SIZE;
+        for found in matches.iter().skip(start).take(find::PAGE_SIZE) {
+            let kind = found.kind.to_string();
+            let kind = match found.kind {
+                find::MatchKind::User => kind.bright_cyan(),
+                find::MatchKind::Assistant => kind.bright_green(),
+                _ => kind.bright_yellow(),
+            };
+            println!("{} {}", format!("#{}", found.number).bright_white(), kind);
+            println!(
+                "   {}",
+                found.highlighted(|text| text.black().on_yellow().to_string())
+            );
+        }
+        println!();
+        if page < pages {
+            println!(
+                "{}",
+                t!("cli.find.more", usage = find::page_command(args, page + 1)).dimmed()
+            );
+        }
+        println!("{}", t!("cli.find.hint").dimmed());
+    }
+
+    /// 加载的会话在另一个目录中创建，或最近使用的是另一个模型时提醒
+    pub(super) fn warn_session_mismatches(&self) {
+        for mismatch in self.session.context.environment_mismatches() {
+            let message = match mismatch {
+                SessionMismatch::WorkingDir { session, current } => t!(
+                    "cli.session_mismatch.working_dir",
+                    session = session,
+                    current = current
+                ),
+                SessionMismatch::Model { session, current } => t!(
+                    "cli.session_mismatch.model",
+                    session = session,
+                    current = current
+                ),
+            };
+            println!("{} {}", "⚠️".yellow(), message.bright_yellow());
+        }
+    }
+
+    /// 会话停在一轮中间（上次运行崩溃或被强制退出）时询问如何处理
+    ///
+    /// 非交互环境下保留并注明中断。
+    pub(super) async fn recover_interrupted_turn(&mut self) -> Result<()> {
+        let Some(turn) = self.session.context.interrupted_turn().cloned() else {
+            return Ok(());
+        };
+        let interactive = std::io::stdin().is_terminal() && stdout().is_terminal();
+        let recovery = if interactive {
+            let detail = match &turn.kind {
+                InterruptedKind::UnansweredPrompt => t!("cli.recovery.unanswered"),
+                InterruptedKind::PendingToolCalls(_) => t!("cli.recovery.pending_tools"),
+                InterruptedKind::UnansweredToolResults => t!("cli.recovery.tool_results"),
+            };
+            println!("{} {}", "⚠️".yellow(), detail.bright_yellow());
+            let preview: String = turn
+                .prompt
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(80)
+                .collect();
+            println!("  {} {}", "›".dimmed(), preview.dimmed());
+
+            let resend = t!("cli.recovery.resend");
+            let discard = t!("cli.recovery.drop");
+            let options = vec![resend, discard, t!("cli.recovery.keep")];
+            match inquire::Select::new(t!("cli.recovery.question"), options).prompt() {
+                Ok(choice) if choice == resend => TurnRecovery::Resend,
+                Ok(choice) if choice == discard => TurnRecovery::Drop,
+                _ => TurnRecovery::Keep,
+            }
+        } else {
+            TurnRecovery::Keep
+        };
+
+        match self.session.context.recover_interrupted_turn(recovery) {
+            Ok(Some(prompt)) => {
+                println!("{} {}", "↻".bright_blue(), t!("cli.recovery.resending"));
+                Box::pin(self.handle_command(&prompt)).await?;
+            }
+            Ok(None) if recovery == TurnRecovery::Drop => {
+                println!("{}", t!("cli.recovery.dropped").dimmed())
             }
+            Ok(None) => println!("{}", t!("cli.recovery.kept").dimmed()),
+            Err(e) => println!("{} {}", "❌".red(), t!("cli.recovery.failed", error = e)),
         }
         println!();
         Ok(())
@@ -953,8 +2531,8 @@ impl OxideCli {
 
     fn load_session(&mut self, session_id: &str) -> Result<()> {
         // Save current session
-        if !self.context_manager.get_messages().is_empty() {
-            if let Err(e) = self.context_manager.save() {
+        if !self.session.context.get_messages().is_empty() {
+            if let Err(e) = self.session.context.save() {
                 println!(
                     "{} Warning: Failed to save current session: {}",
                     "⚠️".yellow(),
@@ -964,10 +2542,11 @@ impl OxideCli {
         }
 
         // Switch
-        self.context_manager.switch_session(session_id.to_string());
+        self.session.context.switch_session(session_id.to_string());
         self.reset_session_tokens();
+        self.advisories.reset();
 
-        match self.context_manager.load() {
+        match self.session.context.load() {
             Ok(true) => {
                 println!(
                     "{} Successfully loaded session: {}",
@@ -976,12 +2555,14 @@ impl OxideCli {
                 );
                 println!(
                     "   Messages loaded: {}",
-                    self.context_manager
+                    self.session
+                        .context
                         .get_messages()
                         .len()
                         .to_string()
                         .bright_yellow()
                     );
+                self.warn_session_mismatches();
             }
             Ok(false) => {
                 println!(
@@ -1004,7 +2585,7 @@ impl OxideCli {
     }
 
     fn delete_session(&mut self, session_id: &str) -> Result<()> {
-        if session_id == self.context_manager.session_id() {
+        if session_id == self.session.context.session_id() {
             println!("{} Cannot delete current active session", "❌".red());
             println!("   Switch to another session first using '/load <session_id>'",);
             println!();
@@ -1047,12 +2628,13 @@ impl OxideCli {
         let manager = SubagentManager::new();
         let capabilities = manager.list_capabilities();
 
-        println!("{}", "🤖 Available Agent Types:".bright_cyan());
+        println!("{}", t!("cli.agents.title").bright_cyan());
         println!();
 
         for cap in &capabilities {
-            let current_marker = if matches!(&self.agent, AgentType::Anthropic(_) if cap.agent_type == NewAgentType::Main) {
-                " (current)".bright_green()
+            let current_marker = if matches!(&self.session.agent, AgentType::Anthropic(_) if cap.agent_type == NewAgentType::Main)
+            {
+                t!("cli.help.current").bright_green()
             } else {
                 "".normal()
             };
@@ -1061,18 +2643,23 @@ impl OxideCli {
             println!("    {}", cap.description.bright_black());
             println!(
                 "    {} {}",
-                "Tools:".bright_yellow(),
+                t!("cli.agents.tools").bright_yellow(),
                 cap.tools.join(", ").dimmed()
             );
             if cap.read_only {
-                println!("    {} {}", "🔒".bright_red(), "Read-only access".bright_red());
+                println!(
+                    "    {} {}",
+                    "🔒".bright_red(),
+                    t!("cli.agents.read_only").bright_red()
+                );
             }
             println!();
         }
 
         println!(
-            "{} 使用 '/agent capabilities' 查看能力",
-            "💡".bright_blue()
+            "{} {}",
+            "💡".bright_blue(),
+            t!("cli.agents.capabilities_hint")
         );
         println!();
         Ok(())
@@ -1082,37 +2669,56 @@ impl OxideCli {
         let manager = SubagentManager::new();
         let capabilities = manager.list_capabilities();
 
-        println!("{}", "🔧 Agent Capabilities:".bright_cyan());
+        println!("{}", t!("cli.agents.capabilities_title").bright_cyan());
         println!();
 
         for cap in &capabilities {
             println!("  {} ({})", cap.name.bright_white(), cap.agent_type.display_name().dimmed());
             println!("    {}", cap.description.bright_black());
             println!();
-            println!("    {}", "Tools:".bright_yellow());
+            println!("    {}", t!("cli.agents.tools").bright_yellow());
             for tool in &cap.tools {
                 println!("      • {}", tool.bright_white());
             }
             if cap.read_only {
-                println!("    {} {}", "🔒".bright_red(), "Read-only access".bright_red());
+                println!(
+                    "    {} {}",
+                    "🔒".bright_red(),
+                    t!("cli.agents.read_only").bright_red()
+                );
             } else {
-                println!("    {} {}", "✏️".bright_green(), "Read/Write access".bright_green());
+                println!(
+                    "    {} {}",
+                    "✏️".bright_green(),
+                    t!("cli.agents.read_write").bright_green()
+                );
             }
             println!();
         }
 
-        println!(
-            "{} Use '/agent list' to see available agents",
-            "💡".bright_blue()
-        );
+        println!("{} {}", "💡".bright_blue(), t!("cli.agents.list_hint"));
         println!();
         Ok(())
     }
 
-    fn list_tasks(&self) -> Result<()> {
-        use crate::task::TaskManager;
+    fn list_tasks(&self, args: &str) -> Result<()> {
+        use crate::task::manager::load_tasks_config;
+        use crate::task::{TaskFilter, TaskManager};
         use std::path::PathBuf;
 
+        let filter = match TaskFilter::from_args(args) {
+            Ok(filter) => filter,
+            Err(e) => {
+                println!("{} {}", "❌".red(), e);
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/tasks list [--status <s>[,<s>...]] [--owner <o>] [--limit <n>] [--offset <n>] [--all] [text]")
+                );
+                return Ok(());
+            }
+        };
+
         let tasks_dir = PathBuf::from(".oxide/tasks");
 
         if !tasks_dir.exists() {
@@ -1125,11 +2731,18 @@ impl OxideCli {
             return Ok(());
         }
 
-        let manager = TaskManager::new(tasks_dir)?;
-        let tasks = manager.list_tasks()?;
+        let manager = TaskManager::new(tasks_dir)?.with_config(load_tasks_config());
+        let page = manager.query_tasks(&filter)?;
 
-        if tasks.is_empty() {
+        if page.tasks.is_empty() {
             println!("{}", "📋 No tasks found".bright_yellow());
+            if page.hidden_completed > 0 {
+                println!(
+                    "{} {} older completed task(s) hidden, use --all to include them",
+                    "💡".bright_blue(),
+                    page.hidden_completed
+                );
+            }
             println!();
             return Ok(());
         }
@@ -1137,13 +2750,15 @@ impl OxideCli {
         println!("{}", "📋 Background Tasks:".bright_cyan());
         println!();
 
-        for task in tasks {
+        let shown = page.tasks.len();
+        for task in page.tasks {
             let status_icon = match task.status {
                 crate::task::TaskStatus::Pending => "⏳".bright_yellow(),
                 crate::task::TaskStatus::InProgress => "🔄".bright_blue(),
                 crate::task::TaskStatus::Completed => "✅".bright_green(),
                 crate::task::TaskStatus::Failed => "❌".bright_red(),
                 crate::task::TaskStatus::Deleted => "🗑️".dimmed(),
+                crate::task::TaskStatus::Cancelled => "⏹️".dimmed(),
             };
 
             println!("  {} {} ({})", status_icon, task.name.bright_white(), task.id.dimmed());
@@ -1157,12 +2772,32 @@ impl OxideCli {
             );
 
             if let Some(duration) = task.duration() {
-                println!("    {} {}", "Duration:".bright_yellow(), format!("{:?}", duration).bright_white());
+                println!(
+                    "    {} {}",
+                    "Duration:".bright_yellow(),
+                    format!("{:?}", duration).bright_white()
+                );
             }
 
             println!();
         }
 
+        if shown < page.total {
+            println!(
+                "{} Showing {}-{} of {} task(s), use --offset to see more",
+                "💡".bright_blue(),
+                filter.offset + 1,
+                filter.offset + shown,
+                page.total
+            );
+        }
+        if page.hidden_completed > 0 {
+            println!(
+                "{} {} older completed task(s) hidden, use --all to include them",
+                "💡".bright_blue(),
+                page.hidden_completed
+            );
+        }
         println!(
             "{} Use '/tasks show <id>' to view task details",
             "💡".bright_blue()
@@ -1194,6 +2829,7 @@ impl OxideCli {
             TaskStatus::Completed => "✅".bright_green(),
             TaskStatus::Failed => "❌".bright_red(),
             TaskStatus::Deleted => "🗑️".dimmed(),
+            TaskStatus::Cancelled => "⏹️".dimmed(),
         };
 
         println!("{}", "📋 Task Details:".bright_cyan());
@@ -1254,6 +2890,15 @@ impl OxideCli {
         if let Ok(Some(output)) = manager.get_task_output(&task_id_string) {
             println!("{}", "📄 Task Output:".bright_cyan());
             println!();
+            if task.output_discarded_bytes > 0 {
+                println!(
+                    "{} Earlier output was discarded after exceeding the size limit ({} bytes, {} rotations)",
+                    "⚠️".yellow(),
+                    task.output_discarded_bytes,
+                    task.output_rotations
+                );
+                println!();
+            }
             println!("{}", output.dimmed());
             println!();
         }
@@ -1321,6 +2966,13 @@ impl OxideCli {
                     task_id
                 );
             }
+            crate::task::TaskStatus::Cancelled => {
+                println!(
+                    "{} Task '{}' has already been cancelled",
+                    "ℹ️".bright_blue(),
+                    task_id
+                );
+            }
         }
 
         println!();
@@ -1348,7 +3000,12 @@ impl OxideCli {
                 crate::skill::SkillSource::Local => "📁".bright_yellow(),
             };
 
-            println!("  {} {} - {}", source_icon, format!("/{}", skill.name).bright_white(), skill.description.bright_black());
+            println!(
+                "  {} {} - {}",
+                source_icon,
+                format!("/{}", skill.name).bright_white(),
+                skill.description.bright_black()
+            );
 
             // 显示参数
             if !skill.args.is_empty() {
@@ -1498,16 +3155,12 @@ impl OxideCli {
         println!();
 
         // 将渲染后的提示词添加到上下文，作为用户消息
-        self.context_manager.add_message(Message::user(&rendered_prompt));
+        self.session
+            .context
+            .add_message(Message::user(&rendered_prompt));
 
         // 计算 token 预估
-        let messages = self.context_manager.get_messages();
-        let input_tokens = count_messages_tokens(
-            &messages.iter().map(|m| {
-                let serializable = SerializableMessage::from(m);
-                (serializable.role, serializable.content)
-            }).collect::<Vec<_>>()
-        );
+        let input_tokens = self.session.context.estimated_input_tokens();
 
         let estimated_output = (input_tokens as f64 * 0.5).ceil() as usize;
         let usage = TokenUsage::new(input_tokens, estimated_output);
@@ -1516,79 +3169,187 @@ impl OxideCli {
         println!(
             "{} {} | {} {} | {} {}",
             "📊".bright_blue(),
-            format!("输入: {} tokens", usage.input_tokens).bright_white(),
-            "预估输出".bright_yellow(),
+            t!("cli.usage.input", tokens = usage.input_tokens).bright_white(),
+            t!("cli.usage.estimated_output").bright_yellow(),
             format!("~{} tokens", usage.output_tokens).bright_yellow(),
-            "成本".bright_green(),
-            format!("${:.6}", usage.estimated_cost()).bright_green()
+            t!("cli.usage.cost").bright_green(),
+            format!("${:.6}", usage.estimated_cost(&self.model_name)).bright_green()
         );
         println!();
 
+        // 系统提醒只随本轮请求发送
+        let reminders = self.reminders.begin_turn(self.is_plan_mode());
+        verbose_tools().begin_turn();
+        let outgoing_prompt = with_reminders(&reminders, &rendered_prompt);
+
         // 执行 AI 处理
-        self.spinner.start("Thinking...");
+        self.spinner.start(t!("cli.thinking"));
         stdout().flush().unwrap();
 
-        let hook = SessionIdHook::new(self.context_manager.session_id().to_string());
+        let turn = self.turn_span();
+        let hook = SessionIdHook::new(self.session.context.session_id().to_string())
+            .with_turn_span(turn.clone())
+            .with_reminders(self.reminders.clone())
+            .with_advisories(self.advisories.clone());
+        let started = Instant::now();
 
-        let response_result: Result<rig::agent::FinalResponse, std::io::Error> = match &self.agent {
-            AgentType::OpenAI(agent) => {
-                let mut stream = agent
-                    .stream_prompt(&rendered_prompt)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                self.spinner.stop();
-                super::render::stream_with_animation(&mut stream).await
-            }
-            AgentType::Anthropic(agent) => {
-                let mut stream = agent
-                    .stream_prompt(&rendered_prompt)
-                    .with_hook(hook.clone())
-                    .multi_turn(20)
-                    .with_history(self.context_manager.get_messages().to_vec())
-                    .await;
-                self.spinner.stop();
-                super::render::stream_with_animation(&mut stream).await
-            }
-        };
+        let response_result = self
+            .run_turn(&outgoing_prompt, None, &hook)
+            .instrument(turn.clone())
+            .await;
+        self.reminders.end_turn();
 
         println!();
 
         match response_result {
-            Ok(resp) => {
-                let response_content = resp.response();
-                self.context_manager
+            Ok(reply) => {
+                turn.record("outcome", reply.outcome());
+                let response_content = reply.text();
+                self.session
+                    .context
                     .add_message(Message::assistant(response_content));
 
-                if let Err(e) = self.context_manager.save() {
-                    println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                if let Err(e) = self.session.context.save() {
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!("cli.save_context_failed", error = e)
+                    );
                 }
 
-                self.add_session_tokens(resp.usage().total_tokens as u64);
-                self.show_token_usage_animated(resp.usage().total_tokens as u64).await;
+                crate::usage::record_turn(
+                    self.session.context.session_id(),
+                    &self.model_name,
+                    &reply.usage(),
+                );
+                self.add_session_tokens(reply.usage().total_tokens as u64);
+                self.show_token_usage_animated(reply.usage().total_tokens as u64).await;
+                self.show_turn_summary(started, &hook, &reply.usage());
+                self.offer_remaining_steps(&reply);
             }
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::Interrupted
                     && e.to_string().contains("prompt_cancelled")
                 {
-                    println!("{} 操作已取消", "🚫".red());
+                    turn.record("outcome", "cancelled");
+                    turn.in_scope(|| telemetry::cancelled("prompt cancelled"));
+                    println!("{} {}", "🚫".red(), t!("cli.cancelled"));
                     // 将取消信息保存到对话历史，让 Agent 知道操作被用户拒绝
-                    self.context_manager
-                        .add_message(Message::assistant("[操作已被用户取消]"));
-                    if let Err(e) = self.context_manager.save() {
-                        println!("{} Failed to save context: {}", "⚠️".yellow(), e);
+                    self.session
+                        .context
+                        .add_message(Message::assistant(t!("cli.cancelled_history")));
+                    if let Err(e) = self.session.context.save() {
+                        println!(
+                            "{} {}",
+                            "⚠️".yellow(),
+                            t!("cli.save_context_failed", error = e)
+                        );
                     }
                 } else {
-                    println!("{} Failed to get AI response: {}", "❌".red(), e);
+                    turn.record("outcome", "error");
+                    turn.in_scope(|| telemetry::turn_failed(&e));
+                    println!("{} {}", "❌".red(), t!("cli.response_failed", error = e));
+                    print_error_hint(&OxideError::from(e));
                 }
             }
         }
 
+        self.show_advisories();
+
         println!();
         Ok(true)
     }
 
+    /// 按组成部分显示下一轮请求的 token 占用
+    async fn show_context_breakdown(&self) {
+        let Some(builder) = self.agent_builder.as_ref() else {
+            println!("{} {}", "❌".red(), t!("cli.context_usage.unavailable"));
+            println!();
+            return;
+        };
+        let (messages, savings) = self.session.context.request_messages();
+        let preview = builder.preview_main(&messages).await;
+        let breakdown = ContextBreakdown::from_preview(&preview);
+        let model = ModelInfo::lookup(&self.model_name);
+        let window = model.context_window;
+
+        println!(
+            "{}",
+            t!(
+                "cli.context_usage.title",
+                total = breakdown.total(),
+                window = window,
+                percent = format!("{:.1}", breakdown.total() as f64 * 100.0 / window as f64)
+            )
+            .bright_cyan()
+        );
+        if model.is_unknown() {
+            println!(
+                "  {}",
+                t!("cli.context_usage.unknown_model", model = &self.model_name).bright_black()
+            );
+        }
+        let labels: Vec<String> = breakdown
+            .rows()
+            .iter()
+            .map(Self::context_row_label)
+            .collect();
+        let width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (row, label) in breakdown.rows().iter().zip(&labels) {
+            let percent = breakdown.percent(row.tokens);
+            println!(
+                "  {}{}  {:>8}  {:>5.1}%  {}",
+                label.bright_white(),
+                " ".repeat(width - label.chars().count()),
+                row.tokens,
+                percent,
+                Self::create_progress_bar(percent, 20)
+            );
+        }
+        if savings.reads > 0 {
+            println!(
+                "  {}",
+                t!(
+                    "cli.context_usage.superseded_reads",
+                    count = savings.reads,
+                    tokens = savings.tokens
+                )
+                .bright_black()
+            );
+        }
+        for suggestion in breakdown.suggestions(window) {
+            println!(
+                "  {} {}",
+                "💡".bright_blue(),
+                suggestion.text().bright_yellow()
+            );
+        }
+        println!();
+    }
+
+    fn context_row_label(row: &Row) -> String {
+        let count = row.items;
+        match row.component {
+            Component::SystemPrompt => t!(
+                "cli.context_usage.system_prompt",
+                name = row.name.as_deref().unwrap_or_default()
+            ),
+            Component::ToolDefinitions => {
+                t!("cli.context_usage.tool_definitions_row", count = count)
+            }
+            Component::UserText => t!("cli.context_usage.user_text", count = count),
+            Component::AssistantText => t!("cli.context_usage.assistant_text", count = count),
+            Component::ToolCalls => t!("cli.context_usage.tool_calls", count = count),
+            Component::ToolResults => t!("cli.context_usage.tool_results_row", count = count),
+            Component::Attachments => t!("cli.context_usage.attachments", count = count),
+            Component::Formatting => t!("cli.context_usage.formatting").to_string(),
+        }
+    }
+
     /// 创建进度条字符串
     fn create_progress_bar(percentage: f64, width: usize) -> String {
         let filled = (percentage / 100.0 * width as f64).round() as usize;
@@ -1601,27 +3362,382 @@ impl OxideCli {
     }
 
     fn show_workflow_status(&self) -> Result<()> {
-        println!("{}", "🤖 PAOR 工作流状态".bright_cyan());
+        println!("{}", t!("cli.workflow.status_title").bright_cyan());
         println!();
-        println!("  {} {}", "模式:".bright_white(), "自动检测".bright_green());
-        println!("  {} {}", "评估器:".bright_white(), "已启用".bright_green());
-        println!("  {} {}", "策略:".bright_white(), "复杂任务自动使用工作流".bright_white());
+        println!(
+            "  {} {}",
+            t!("cli.workflow.status_mode").bright_white(),
+            t!("cli.workflow.status_mode_value").bright_green()
+        );
+        println!(
+            "  {} {}",
+            t!("cli.workflow.status_evaluator").bright_white(),
+            t!("cli.workflow.status_enabled").bright_green()
+        );
+        println!(
+            "  {} {}",
+            t!("cli.workflow.status_strategy").bright_white(),
+            t!("cli.workflow.status_strategy_value").bright_white()
+        );
         println!();
-        println!("{}", "📋 使用建议:".bright_yellow());
-        println!("  • 复杂任务（>50字）自动启用工作流");
-        println!("  • 使用 #workflow 标记强制启用工作流");
-        println!("  • 使用 #simple 标记强制使用简单对话");
+        println!("{}", t!("cli.workflow.status_suggestions").bright_yellow());
+        println!("{}", t!("cli.workflow.status_tip_complex"));
+        println!("{}", t!("cli.workflow.status_tip_workflow"));
+        println!("{}", t!("cli.workflow.status_tip_simple"));
         println!();
 
         Ok(())
     }
 
+    /// 本轮结束后显示摘要行（`[render] turn_summary = false` 时不显示），
+    /// 复杂的一轮再显示活动回顾并写入会话文件（`[render] turn_recap`）
+    fn show_turn_summary(
+        &mut self,
+        started: Instant,
+        hook: &SessionIdHook,
+        usage: &rig::completion::Usage,
+    ) {
+        if self.turn_summary {
+            let stats = hook.turn_stats();
+            print_turn_summary(&TurnSummary {
+                elapsed: started.elapsed(),
+                tool_calls: stats.tool_calls,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                stop_reason: stats.stop_reason.unwrap_or(StopReason::EndTurn),
+            });
+        }
+        let recap = hook.turn_recap(started.elapsed(), usage);
+        if recap.is_notable(self.turn_recap) {
+            print_turn_recap(&recap);
+            if let Err(e) = self.session.context.record_turn_recap(&recap) {
+                println!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    t!("cli.save_context_failed", error = e)
+                );
+            }
+        }
+    }
+
+    /// 输入中引用的图片：显示预览，当前模型支持图片时返回随本轮请求发送的图片内容，
+    /// 否则提示模型看不到图片、只发送路径
+    fn image_attachment(&self, input: &str) -> Option<Message> {
+        let images = parse_image_references(input, &self.roots);
+        if images.is_empty() {
+            return None;
+        }
+        for image in &images {
+            println!("{}", self.image_preview.render(image));
+        }
+        let supports_vision = ModelInfo::lookup(&self.model_name).supports_vision;
+        let attachment = match attach_images(&images, supports_vision) {
+            Ok(ImageAttachment::Content(message)) => Some(message),
+            Ok(ImageAttachment::PathOnly) => {
+                println!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    t!("cli.image.no_vision", model = self.model_name).yellow()
+                );
+                None
+            }
+            Err(e) => {
+                println!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    t!("cli.image.attach_failed", error = e).yellow()
+                );
+                None
+            }
+        };
+        println!();
+        attachment
+    }
+
+    /// 发送一轮对话并流式显示回复
+    ///
+    /// 设置了每轮时限时，超时后停止本轮并再请求一次，让模型总结进度和剩余步骤。
+    /// 因重复的工具调用被结束时，以说明作为回复。
+    /// 回复中途断开时自动续写，续写次数用完或断开前调用过工具时以已收到的部分作为回复。
+    async fn run_turn(
+        &mut self,
+        prompt: &str,
+        images: Option<Message>,
+        hook: &SessionIdHook,
+    ) -> Result<TurnReply, std::io::Error> {
+        let deadline = self.turn_time_limit.map(TurnDeadline::new);
+        let turn_hook = match &deadline {
+            Some(deadline) => hook.clone().with_deadline(deadline.clone()),
+            None => hook.clone(),
+        };
+        self.spinner.stop();
+        let session = &self.session;
+        let pacing = self.stream_pacing;
+        // 引用的图片只随本轮请求发送，放在用户输入之前
+        let mut history = self.session.context.prompt_history();
+        history.extend(images);
+        let result = complete_with_recovery(prompt, history, |request| {
+            let hook = turn_hook.clone();
+            let deadline = deadline.clone();
+            async move {
+                if request.continuation > 0 {
+                    println!();
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!(
+                            "cli.stream_recovery.continuing",
+                            attempt = request.continuation,
+                            max = MAX_CONTINUATIONS
+                        )
+                        .yellow()
+                    );
+                }
+                let mut stream = session
+                    .request(&request.prompt, request.history, hook)
+                    .await;
+                if let Some(deadline) = deadline {
+                    stream = stream.with_deadline(deadline);
+                }
+                let response = stream_with_animation(&mut stream, pacing).await?;
+                Ok::<_, std::io::Error>(RequestOutcome::from_stream(&stream, response))
+            }
+        })
+        .await;
+        if let Ok(reply) = &result {
+            if reply.truncated {
+                let message = if reply.after_tool_calls {
+                    t!("cli.stream_recovery.not_continued").to_string()
+                } else {
+                    t!("cli.stream_recovery.gave_up", max = MAX_CONTINUATIONS)
+                };
+                println!();
+                println!("{} {}", "⚠️".yellow(), message.yellow());
+            }
+        }
+
+        // 重复的相同工具调用触发两次熔断后本轮被结束，回复换成说明
+        if result.is_err() && loop_guard::should_stop(&hook.session_id) {
+            let text = t!("cli.loop_guard.stopped");
+            println!();
+            println!("{} {}", "🔁".yellow(), text.yellow());
+            return Ok(TurnReply {
+                response: RecoveredReply {
+                    text: text.to_string(),
+                    usage: Usage::default(),
+                    continuations: 0,
+                    truncated: false,
+                    after_tool_calls: false,
+                },
+                time_limit: None,
+            });
+        }
+
+        match deadline {
+            Some(deadline) if result.is_err() && deadline.was_hit() => {
+                println!();
+                println!(
+                    "{} {}",
+                    "⏱".yellow(),
+                    t!("cli.time_box.reached", seconds = deadline.limit().as_secs()).yellow()
+                );
+                // 总结请求不允许调用工具：已到期的截止时间会取消任何工具调用
+                let summary_hook = hook
+                    .clone()
+                    .with_deadline(TurnDeadline::new(Duration::ZERO));
+                let mut stream = self
+                    .session
+                    .request(
+                        &deadline.summary_prompt(),
+                        self.session.context.prompt_history(),
+                        summary_hook,
+                    )
+                    .await;
+                let response = stream_with_animation(&mut stream, self.stream_pacing).await?;
+                Ok(TurnReply {
+                    response: response.into(),
+                    time_limit: Some(deadline.limit()),
+                })
+            }
+            _ => result.map(|response| TurnReply {
+                response,
+                time_limit: None,
+            }),
+        }
+    }
+
+    /// 限时中止的回复中列出了剩余步骤时，确认后加入任务列表
+    fn offer_remaining_steps(&self, reply: &TurnReply) {
+        if reply.time_limit.is_none() {
+            return;
+        }
+        let steps = parse_remaining_steps(&reply.response.text);
+        if steps.is_empty() || !std::io::stdin().is_terminal() {
+            return;
+        }
+        let confirmed = inquire::Confirm::new(&t!("cli.time_box.add_tasks", count = steps.len()))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if !confirmed {
+            return;
+        }
+        let manager = get_task_manager();
+        let description = t!("cli.time_box.task_description").to_string();
+        for step in steps {
+            match manager.create_task_simple(step, description.clone(), None, None) {
+                Ok(task) => println!(
+                    "  {} {}",
+                    "✓".green(),
+                    t!(
+                        "cli.time_box.task_added",
+                        id = task.id,
+                        subject = task.subject
+                    )
+                ),
+                Err(e) => println!(
+                    "  {} {}",
+                    "❌".red(),
+                    t!("cli.time_box.task_failed", error = e)
+                ),
+            }
+        }
+        println!();
+    }
+
+    /// 整理本次会话中值得记住的项目事实，用户在清单中确认或修改后写入项目记忆
+    ///
+    /// 由 `/save-notes` 和退出会话（`[behavior] session_learning = true`）触发。
+    pub(super) async fn save_session_notes(&mut self) {
+        // 整理请求单独发送，整个对话都作为历史
+        let history = self.session.context.get_messages().to_vec();
+        if history.is_empty() {
+            println!("{}", t!("cli.session_learning.empty_session").dimmed());
+            return;
+        }
+        let store = MemoryStore::project();
+        let memory = match store.content() {
+            Ok(memory) => memory,
+            Err(e) => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.session_learning.failed", error = e)
+                );
+                return;
+            }
+        };
+        let max_facts = self.session_learning.max_facts;
+
+        self.spinner.start(t!("cli.session_learning.extracting"));
+        // 整理请求不允许调用工具：已到期的截止时间会取消任何工具调用
+        let hook = SessionIdHook::new(self.session.context.session_id().to_string())
+            .with_deadline(TurnDeadline::new(Duration::ZERO));
+        let mut stream = self
+            .session
+            .request(&extraction_prompt(&memory, max_facts), history, hook)
+            .await;
+        let mut reply = String::new();
+        let mut error = None;
+        while let Some(event) = stream.next().await {
+            match event {
+                StreamEvent::TextDelta { text } => reply.push_str(&text),
+                StreamEvent::Error { message } => error = Some(message),
+                _ => {}
+            }
+        }
+        self.spinner.stop();
+        if let Some(message) = error {
+            println!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!("cli.session_learning.failed", error = message).yellow()
+            );
+            return;
+        }
+        let usage = stream.take_final_response().usage();
+        crate::usage::record_turn(self.session.context.session_id(), &self.model_name, &usage);
+        self.add_session_tokens(usage.total_tokens as u64);
+
+        let facts = new_facts(&memory, &parse_facts(&reply, max_facts));
+        if facts.is_empty() {
+            println!("{}", t!("cli.session_learning.nothing_new").dimmed());
+            return;
+        }
+        let Ok(selected) = inquire::MultiSelect::new(&t!("cli.session_learning.choose"), facts)
+            .with_all_selected_by_default()
+            .prompt()
+        else {
+            return;
+        };
+        if selected.is_empty() {
+            return;
+        }
+        let edit = inquire::Confirm::new(&t!("cli.session_learning.edit"))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        let approved: Vec<String> = if edit {
+            // 清空内容即不保存该条
+            selected
+                .iter()
+                .filter_map(|fact| {
+                    inquire::Text::new(&t!("cli.session_learning.edit_fact"))
+                        .with_initial_value(fact)
+                        .prompt()
+                        .ok()
+                })
+                .filter(|fact| !fact.trim().is_empty())
+                .collect()
+        } else {
+            selected
+        };
+
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        match store.append_learnings(&date, &approved) {
+            Ok(added) if added.is_empty() => {
+                println!("{}", t!("cli.session_learning.nothing_new").dimmed());
+            }
+            Ok(added) => println!(
+                "{} {}",
+                "✅".bright_green(),
+                t!(
+                    "cli.session_learning.saved",
+                    count = added.len(),
+                    path = store.path().display()
+                )
+            ),
+            Err(e) => println!(
+                "{} {}",
+                "❌".red(),
+                t!("cli.session_learning.failed", error = e)
+            ),
+        }
+    }
+
+    /// 显示本轮新触发的会话质量提示
+    fn show_advisories(&self) {
+        let window = ModelInfo::lookup(&self.model_name).context_window.max(1);
+        let percent = self.session.context.estimated_input_tokens() * 100 / window;
+        for advisory in self.advisories.end_turn(percent) {
+            print_advisory(&advisory);
+        }
+    }
+
     /// 显示带动画的 token 统计（数字滚动 + 进度条）
     async fn show_token_usage_animated(&self, total_tokens: u64) {
-        let max_display = 200000; // 假设 200k tokens 为满进度条
+        // 上下文窗口为满进度条
+        let max_display = ModelInfo::lookup(&self.model_name).context_window.max(1);
         let percentage = (total_tokens as f64 / max_display as f64 * 100.0).min(100.0);
         let progress_bar = Self::create_progress_bar(percentage, 20);
 
+        // 纯文本输出不做数字滚动
+        if output::mode().is_plain() {
+            let label = t!("cli.usage.total_tokens");
+            println!("{} {} {} tokens", progress_bar, label, total_tokens);
+            return;
+        }
+
         // 数字滚动动画
         let steps = if total_tokens < 100 { 5 } else { 15 };
         let delay_ms = if total_tokens < 100 { 30 } else { 20 };
@@ -1631,7 +3747,7 @@ impl OxideCli {
             print!(
                 "\r{} {} {} tokens",
                 progress_bar.clone(),
-                "Total tokens used:".bright_white(),
+                t!("cli.usage.total_tokens").bright_white(),
                 current.to_string().bright_green()
             );
             stdout().flush().unwrap();
@@ -1644,10 +3760,204 @@ impl OxideCli {
     }
 
     /// 检查是否处于 Plan 模式
+    /// 本轮用户输入的根 span
+    fn turn_span(&self) -> Span {
+        telemetry::turn_span(
+            self.session.context.session_id(),
+            &self.model_name,
+            self.prompt_label.as_str(),
+        )
+    }
+
     pub(crate) fn is_plan_mode(&self) -> bool {
         self.prompt_label == super::PromptLabel::Plan
     }
 
+    /// `/statusbar [on|off]`：开关底部状态栏，不带参数时显示当前状态
+    fn statusbar_command(&mut self, arg: &str) {
+        match arg {
+            "" => {}
+            "on" => self.status_bar.set_enabled(true),
+            "off" => self.status_bar.set_enabled(false),
+            _ => {
+                println!(
+                    "{} {}",
+                    "❌".red(),
+                    t!("cli.unknown_subcommand", command = "/statusbar")
+                );
+                println!(
+                    "{} {}",
+                    "💡".bright_blue(),
+                    t!("cli.usage_hint", usage = "/statusbar [on|off]")
+                );
+                return;
+            }
+        }
+        let state = if self.status_bar.is_enabled() {
+            "on"
+        } else {
+            "off"
+        };
+        println!("{}", t!("cli.statusbar.current", state = state));
+        println!();
+    }
+
+    /// `/expand all`：完整显示上一轮折叠的工具输出
+    fn expand_command(&self, arg: &str) {
+        if arg != "all" {
+            println!(
+                "{} {}",
+                "💡".bright_blue(),
+                t!("cli.usage_hint", usage = "/expand all")
+            );
+            return;
+        }
+        let collapsed = verbose_tools().collapsed();
+        if collapsed.is_empty() {
+            println!("{}", t!("cli.verbose_tools.nothing_collapsed").dimmed());
+            println!();
+            return;
+        }
+        for output in collapsed {
+            println!("{} {}", "●".bright_green(), output.label);
+            print!("{}", verbose_output::render(&output.text));
+            println!();
+        }
+    }
+
+    /// `/output-style [style] [language] [--save]`：切换回复风格，不带参数时显示当前设置
+    fn output_style_command(&mut self, args: &str) {
+        let usage = "/output-style [concise|detailed|explanatory] [auto|en|zh] [--save]";
+        let mut output = self.output;
+        let mut save = false;
+        let mut changed = false;
+        for arg in args.split_whitespace() {
+            if arg == "--save" {
+                save = true;
+            } else if let Ok(style) = arg.parse::<OutputStyle>() {
+                output.style = style;
+                changed = true;
+            } else if let Ok(language) = arg.parse::<OutputLanguage>() {
+                output.language = language;
+                changed = true;
+            } else {
+                println!("{} {}", "❌".red(), t!("cli.output_style.invalid", value = arg));
+                println!("{} {}", "💡".bright_blue(), t!("cli.usage_hint", usage = usage));
+                return;
+            }
+        }
+
+        if changed {
+            self.set_output(output);
+        }
+        println!(
+            "{}",
+            t!(
+                "cli.output_style.current",
+                style = output.style.as_str(),
+                language = output.language.as_str()
+            )
+        );
+        if save {
+            let loader = ConfigLoader::new();
+            match loader.save_global_section("output", &output) {
+                Ok(()) => println!(
+                    "{}",
+                    t!(
+                        "cli.output_style.saved",
+                        path = loader.global_config_path().display()
+                    )
+                    .bright_green()
+                ),
+                Err(e) => println!("{} {:#}", t!("common.error").red(), e),
+            }
+        }
+        println!();
+    }
+
+    /// `/mode [normal|fast|plan] [--save]`：切换对话模式，不带参数时列出所有模式及其影响
+    ///
+    /// `--save` 把（切换后的）当前模式写入项目配置的 `[mode]`，之后在该项目启动时默认使用。
+    fn mode_command(&mut self, args: &str) {
+        let usage = "/mode [normal|fast|plan] [--save]";
+        let mut mode = None;
+        let mut save = false;
+        for arg in args.split_whitespace() {
+            if arg == "--save" {
+                save = true;
+                continue;
+            }
+            match arg.parse::<crate::agent::PromptMode>() {
+                Ok(parsed) => mode = Some(parsed),
+                Err(e) => {
+                    println!("{} {}", "❌".red(), e);
+                    println!(
+                        "{} {}",
+                        "💡".bright_blue(),
+                        t!("cli.usage_hint", usage = usage)
+                    );
+                    return;
+                }
+            }
+        }
+
+        match mode {
+            Some(mode) => self.switch_mode(super::PromptLabel::from_prompt_mode(mode)),
+            None if !save => {
+                self.list_modes(usage);
+                return;
+            }
+            None => {}
+        }
+        if save {
+            let current = self.prompt_label.prompt_mode();
+            let config = ModeConfig {
+                default: Some(current.as_str().to_string()),
+            };
+            let loader = ConfigLoader::new();
+            match loader.save_project_section("mode", &config) {
+                Ok(()) => println!(
+                    "{}",
+                    t!(
+                        "cli.mode.saved",
+                        mode = current.as_str(),
+                        path = loader.project_config_path().display()
+                    )
+                    .bright_green()
+                ),
+                Err(e) => println!("{} {:#}", t!("common.error").red(), e),
+            }
+            println!();
+        }
+    }
+
+    /// 列出所有模式和一行说明，标出当前模式
+    fn list_modes(&self, usage: &str) {
+        let current = self.prompt_label.prompt_mode();
+        println!("{}", t!("cli.mode.current", mode = current.as_str()));
+        println!();
+        for mode in crate::agent::PromptMode::ALL {
+            let marker = if mode == current {
+                "●".bright_green()
+            } else {
+                " ".normal()
+            };
+            println!(
+                "  {} {} {}",
+                marker,
+                format!("{:<8}", mode.as_str()).bright_cyan(),
+                mode.description().dimmed()
+            );
+        }
+        println!();
+        println!(
+            "{} {}",
+            "💡".bright_blue(),
+            t!("cli.usage_hint", usage = usage)
+        );
+        println!();
+    }
+
     /// 保存计划到文件
     fn save_plan_to_file(&self, workflow_result: &WorkflowResult) -> Result<()> {
         use std::fs;
@@ -1665,30 +3975,56 @@ impl OxideCli {
 
         // 构建计划内容
         let mut content = String::new();
-        content.push_str(&format!("# 工作流计划\n\n"));
-        content.push_str(&format!("> 生成时间: {}\n", Local::now().format("%Y-%m-%d %H:%M:%S")));
-        content.push_str(&format!("> 会话 ID: {}\n", self.context_manager.session_id()));
-        content.push_str(&format!("> 状态: {}\n\n", if workflow_result.success { "✅ 成功" } else { "⚠️ 未完成" }));
+        let status = if workflow_result.success {
+            t!("plan_file.succeeded")
+        } else {
+            t!("plan_file.incomplete")
+        };
+        content.push_str(&format!("{}\n\n", t!("plan_file.title")));
+        content.push_str(&format!(
+            "{}\n",
+            t!(
+                "plan_file.generated_at",
+                time = Local::now().format("%Y-%m-%d %H:%M:%S")
+            )
+        ));
+        content.push_str(&format!(
+            "{}\n",
+            t!(
+                "plan_file.session_id",
+                session = self.session.context.session_id()
+            )
+        ));
+        content.push_str(&format!("{}\n\n", t!("plan_file.status", status = status)));
 
         content.push_str("---\n\n");
 
-        content.push_str("## 📊 执行统计\n\n");
-        content.push_str(&format!("- **迭代次数**: {}\n", workflow_result.iterations));
-        content.push_str(&format!("- **最终阶段**: {}\n", workflow_result.phase));
+        content.push_str(&format!("{}\n\n", t!("plan_file.stats")));
+        content.push_str(&format!(
+            "{}\n",
+            t!("plan_file.iterations", count = workflow_result.iterations)
+        ));
+        content.push_str(&format!(
+            "{}\n",
+            t!("plan_file.final_phase", phase = workflow_result.phase)
+        ));
         if let Some(ref reason) = workflow_result.failure_reason {
-            content.push_str(&format!("- **失败原因**: {}\n", reason));
+            content.push_str(&format!(
+                "{}\n",
+                t!("plan_file.failure_reason", reason = reason)
+            ));
         }
         content.push_str("\n");
 
         content.push_str("---\n\n");
 
-        content.push_str("## 📋 工作流摘要\n\n");
+        content.push_str(&format!("{}\n\n", t!("plan_file.summary")));
         content.push_str(&workflow_result.summary);
         content.push_str("\n\n");
 
         if let Some(ref response) = workflow_result.final_response {
             content.push_str("---\n\n");
-            content.push_str("## 📝 最终响应\n\n");
+            content.push_str(&format!("{}\n\n", t!("plan_file.final_response")));
             content.push_str(response);
             content.push_str("\n");
         }
@@ -1697,7 +4033,14 @@ impl OxideCli {
         fs::write(&filepath, content)?;
 
         println!();
-        println!("{} 计划已保存到: {}", "💾".bright_blue(), filepath.display().to_string().bright_cyan());
+        println!(
+            "{} {}",
+            "💾".bright_blue(),
+            t!(
+                "plan_file.saved",
+                path = filepath.display().to_string().bright_cyan()
+            )
+        );
         println!();
 
         Ok(())
@@ -0,0 +1,670 @@
+//! 输入行下方的补全菜单
+//!
+//! 单列列表，每页最多 `page_size` 项；列表放不下时最后一行显示当前位置（如 `3/214`）。
+//! 上下键逐项移动（首尾循环），PageUp/PageDown 翻页，Home/End 跳到第一项/最后一项。
+//! 候选中与输入匹配的字符高亮，目录使用单独的颜色。
+//!
+//! 每行末尾清除到行尾，菜单末尾清除到屏幕底部，列表变短时不会在提示符下方留下旧行。
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use nu_ansi_term::{Color, Style};
+use reedline::menu_functions::{
+    can_partially_complete, completer_input, floor_char_boundary, replace_in_buffer,
+};
+use reedline::{
+    Completer, EditCommand, Editor, KeyCode, KeyModifiers, Keybindings, Menu, MenuEvent, Painter,
+    ReedlineEvent, Suggestion,
+};
+use std::io::{self, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+
+/// 菜单名称，触发符和快捷键通过它打开菜单
+pub const COMPLETION_MENU: &str = "oxide_completion";
+
+/// 默认每页显示的项数
+const DEFAULT_PAGE_SIZE: usize = 8;
+
+/// 候选与描述之间的空格数
+const DESCRIPTION_GAP: usize = 2;
+
+/// 补全菜单的颜色
+pub struct CompletionMenuStyle {
+    /// 未选中项
+    pub text: Style,
+    /// 选中项
+    pub selected: Style,
+    /// 描述
+    pub description: Style,
+    /// 匹配的字符
+    pub matched: Style,
+    /// 选中项中匹配的字符
+    pub selected_matched: Style,
+    /// 目录（未选中时）
+    pub directory: Style,
+    /// 位置指示（`3/214`）
+    pub indicator: Style,
+}
+
+impl Default for CompletionMenuStyle {
+    fn default() -> Self {
+        Self {
+            text: Style::new().fg(Color::Fixed(252)),
+            selected: Style::new().on(Color::Cyan).fg(Color::Black).bold(),
+            description: Style::new().fg(Color::Fixed(248)).italic(),
+            matched: Style::new().fg(Color::Green).underline(),
+            selected_matched: Style::new()
+                .on(Color::Cyan)
+                .fg(Color::Black)
+                .underline()
+                .bold(),
+            directory: Style::new().fg(Color::Blue).bold(),
+            indicator: Style::new().fg(Color::Fixed(244)),
+        }
+    }
+}
+
+/// 在输入行下方显示补全候选的菜单
+pub struct CompletionMenu {
+    name: String,
+    style: CompletionMenuStyle,
+    active: bool,
+    /// 尚未处理的菜单事件（绘制前在 `update_working_details` 中处理）
+    event: Option<MenuEvent>,
+    values: Vec<Suggestion>,
+    /// 选中项的下标
+    selected: usize,
+    /// 第一行显示的项的下标
+    skip: usize,
+    page_size: usize,
+    /// 正在补全的输入（用于高亮匹配的字符）
+    query: String,
+    /// 终端宽度，超出的部分截断，避免折行打乱行数
+    width: usize,
+    /// 提示符下方可用的行数
+    available_lines: usize,
+}
+
+impl CompletionMenu {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            style: CompletionMenuStyle::default(),
+            active: false,
+            event: None,
+            values: Vec::new(),
+            selected: 0,
+            skip: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+            query: String::new(),
+            width: usize::MAX,
+            available_lines: usize::MAX,
+        }
+    }
+
+    /// 每页显示的项数
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn with_style(mut self, style: CompletionMenuStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// 可显示候选的行数（列表放不下时留一行给位置指示）
+    fn visible_rows(&self) -> usize {
+        let rows = self.page_size.min(self.available_lines);
+        if self.values.len() > rows {
+            rows.saturating_sub(1).max(1)
+        } else {
+            self.values.len()
+        }
+    }
+
+    fn shows_indicator(&self) -> bool {
+        self.values.len() > self.visible_rows()
+    }
+
+    fn reset_position(&mut self) {
+        self.selected = 0;
+        self.skip = 0;
+    }
+
+    /// 处理移动选中项的事件
+    fn navigate(&mut self, event: &MenuEvent) {
+        let Some(last) = self.values.len().checked_sub(1) else {
+            return;
+        };
+        let page = self.visible_rows().max(1);
+        match event {
+            MenuEvent::NextElement | MenuEvent::MoveDown => {
+                self.selected = if self.selected >= last {
+                    0
+                } else {
+                    self.selected + 1
+                };
+            }
+            MenuEvent::PreviousElement | MenuEvent::MoveUp => {
+                self.selected = if self.selected == 0 {
+                    last
+                } else {
+                    self.selected - 1
+                };
+            }
+            // 翻页时视图和选中项一起移动，选中项在页内的位置不变
+            MenuEvent::NextPage => {
+                self.selected = (self.selected + page).min(last);
+                self.skip += page;
+            }
+            MenuEvent::PreviousPage => {
+                self.selected = self.selected.saturating_sub(page);
+                self.skip = self.skip.saturating_sub(page);
+            }
+            // 单列列表没有左右方向，Home/End 借用这两个事件（见 `add_keybindings`）
+            MenuEvent::MoveLeft => self.selected = 0,
+            MenuEvent::MoveRight => self.selected = last,
+            _ => {}
+        }
+    }
+
+    /// 调整第一行，使选中项可见
+    fn scroll_into_view(&mut self) {
+        let rows = self.visible_rows().max(1);
+        let max_skip = self.values.len().saturating_sub(rows);
+        if self.selected < self.skip {
+            self.skip = self.selected;
+        } else if self.selected >= self.skip + rows {
+            self.skip = self.selected + 1 - rows;
+        }
+        self.skip = self.skip.min(max_skip);
+    }
+
+    /// 把菜单写入 `out`
+    ///
+    /// 每行末尾清除到行尾，最后清除光标以下的内容，覆盖上次绘制留下的旧行。
+    fn render(
+        &self,
+        out: &mut impl Write,
+        available_lines: usize,
+        use_ansi_coloring: bool,
+    ) -> io::Result<()> {
+        let mut rows = self.visible_rows().min(available_lines);
+        if self.shows_indicator() && rows + 1 > available_lines {
+            rows = rows.saturating_sub(1);
+        }
+        let value_width = self
+            .values
+            .iter()
+            .skip(self.skip)
+            .take(rows)
+            .map(|suggestion| suggestion.value.width())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = Vec::with_capacity(rows + 1);
+        for (index, suggestion) in self.values.iter().enumerate().skip(self.skip).take(rows) {
+            lines.push(self.row(suggestion, index == self.selected, value_width));
+        }
+        if self.shows_indicator() && rows > 0 {
+            let indicator = format!(" {}/{}", self.selected + 1, self.values.len());
+            lines.push(vec![(indicator, self.style.indicator)]);
+        }
+
+        for (i, segments) in lines.iter().enumerate() {
+            if i > 0 {
+                queue!(out, Print("\r\n"))?;
+            }
+            queue!(
+                out,
+                Print(paint(&truncate(segments, self.width), use_ansi_coloring)),
+                Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        queue!(out, Clear(ClearType::FromCursorDown))
+    }
+
+    /// 一行的文字和样式：候选（补齐到 `value_width`）和描述
+    fn row(
+        &self,
+        suggestion: &Suggestion,
+        selected: bool,
+        value_width: usize,
+    ) -> Vec<(String, Style)> {
+        let (text, matched, description) = if selected {
+            let style = self.style.selected;
+            (style, self.style.selected_matched, style)
+        } else if is_directory(suggestion) {
+            (
+                self.style.directory,
+                self.style.matched,
+                self.style.description,
+            )
+        } else {
+            (self.style.text, self.style.matched, self.style.description)
+        };
+
+        let mut segments = vec![(" ".to_string(), text)];
+        let indices = match_indices(&suggestion.value, &self.query);
+        for (i, ch) in suggestion.value.chars().enumerate() {
+            let style = if indices.contains(&i) { matched } else { text };
+            push_segment(&mut segments, ch, style);
+        }
+        let padding = value_width.saturating_sub(suggestion.value.width());
+        if let Some(desc) = suggestion.description.as_deref().filter(|d| !d.is_empty()) {
+            let gap = " ".repeat(padding + DESCRIPTION_GAP);
+            segments.push((gap, text));
+            segments.push((desc.to_string(), description));
+        } else {
+            segments.push((" ".repeat(padding), text));
+        }
+        segments.push((" ".to_string(), text));
+        segments
+    }
+}
+
+impl Menu for CompletionMenu {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 提示符本身不带指示符，菜单打开时也不替换
+    fn indicator(&self) -> &str {
+        ""
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn menu_event(&mut self, event: MenuEvent) {
+        match &event {
+            MenuEvent::Activate(_) => self.active = true,
+            MenuEvent::Deactivate => self.active = false,
+            _ => {}
+        }
+        self.event = Some(event);
+    }
+
+    fn can_quick_complete(&self) -> bool {
+        true
+    }
+
+    fn can_partially_complete(
+        &mut self,
+        values_updated: bool,
+        editor: &mut Editor,
+        completer: &mut dyn Completer,
+    ) -> bool {
+        if !values_updated {
+            self.update_values(editor, completer);
+        }
+        if can_partially_complete(self.get_values(), editor) {
+            // 替换后需要重新计算候选的替换范围
+            self.update_values(editor, completer);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update_values(&mut self, editor: &mut Editor, completer: &mut dyn Completer) {
+        let insertion_point = editor.line_buffer().insertion_point();
+        let (input, pos) = completer_input(editor.get_buffer(), insertion_point, None, false);
+        self.values = completer.complete(&input, pos);
+
+        let pos = floor_char_boundary(&input, pos);
+        let token = &input[super::token_start(&input, pos)..pos];
+        self.query = token
+            .strip_prefix(['@', '#', '/'])
+            .unwrap_or(token)
+            .to_string();
+        self.reset_position();
+    }
+
+    fn update_working_details(
+        &mut self,
+        editor: &mut Editor,
+        completer: &mut dyn Completer,
+        painter: &Painter,
+    ) {
+        self.width = painter.screen_width() as usize;
+        self.available_lines = (painter.remaining_lines().saturating_sub(1) as usize).max(1);
+
+        if let Some(event) = self.event.take() {
+            match event {
+                MenuEvent::Activate(updated) | MenuEvent::Edit(updated) => {
+                    self.active = true;
+                    self.reset_position();
+                    if !updated {
+                        self.update_values(editor, completer);
+                    }
+                }
+                MenuEvent::Deactivate => self.active = false,
+                event => self.navigate(&event),
+            }
+        }
+        self.scroll_into_view();
+    }
+
+    fn replace_in_buffer(&self, editor: &mut Editor) {
+        replace_in_buffer(self.values.get(self.selected).cloned(), editor);
+    }
+
+    fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
+        (self.visible_rows() + usize::from(self.shows_indicator())) as u16
+    }
+
+    fn menu_string(&self, available_lines: u16, use_ansi_coloring: bool) -> String {
+        let mut out = Vec::new();
+        // 写入内存不会失败
+        let _ = self.render(&mut out, available_lines as usize, use_ansi_coloring);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn min_rows(&self) -> u16 {
+        self.menu_required_lines(0)
+    }
+
+    fn get_values(&self) -> &[Suggestion] {
+        &self.values
+    }
+}
+
+/// 注册菜单的翻页和首尾快捷键
+///
+/// 菜单打开时 PageUp/PageDown 翻页、Home/End 跳到第一项/最后一项，
+/// 菜单关闭时 Home/End 仍然移动到行首/行尾。左右方向键只移动光标。
+pub fn add_keybindings(keybindings: &mut Keybindings) {
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::PageUp,
+        ReedlineEvent::MenuPagePrevious,
+    );
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::PageDown,
+        ReedlineEvent::MenuPageNext,
+    );
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Home,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::MenuLeft,
+            ReedlineEvent::Edit(vec![EditCommand::MoveToLineStart { select: false }]),
+        ]),
+    );
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::End,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::MenuRight,
+            ReedlineEvent::HistoryHintComplete,
+            ReedlineEvent::Edit(vec![EditCommand::MoveToLineEnd { select: false }]),
+        ]),
+    );
+    keybindings.add_binding(KeyModifiers::NONE, KeyCode::Left, ReedlineEvent::Left);
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Right,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::HistoryHintComplete,
+            ReedlineEvent::Right,
+        ]),
+    );
+}
+
+/// 目录候选：文件补全把目录的描述写成以 `/` 结尾（见 `cli.completion.directory`）
+fn is_directory(suggestion: &Suggestion) -> bool {
+    suggestion
+        .description
+        .as_deref()
+        .is_some_and(|description| description.ends_with('/'))
+}
+
+/// `value` 中与 `query` 匹配的字符下标（按字符计）
+///
+/// 优先取不区分大小写的连续子串，否则按文件索引同样的模糊匹配取字符。
+fn match_indices(value: &str, query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let lower = value.to_lowercase();
+    if lower.len() == value.len() {
+        if let Some(start) = lower.find(&query.to_lowercase()) {
+            let first = value[..start].chars().count();
+            return (first..first + query.chars().count()).collect();
+        }
+    }
+    SkimMatcherV2::default()
+        .fuzzy_indices(value, query)
+        .map(|(_, indices)| indices)
+        .unwrap_or_default()
+}
+
+/// 追加一个字符，与前一段样式相同时合并
+fn push_segment(segments: &mut Vec<(String, Style)>, ch: char, style: Style) {
+    match segments.last_mut() {
+        Some((text, last)) if *last == style => text.push(ch),
+        _ => segments.push((ch.to_string(), style)),
+    }
+}
+
+/// 按显示宽度截断一行
+fn truncate(segments: &[(String, Style)], width: usize) -> Vec<(String, Style)> {
+    let mut remaining = width;
+    let mut result = Vec::with_capacity(segments.len());
+    for (text, style) in segments {
+        if text.width() <= remaining {
+            remaining -= text.width();
+            result.push((text.clone(), *style));
+            continue;
+        }
+        let mut part = String::new();
+        for ch in text.chars() {
+            let w = ch.width().unwrap_or(0);
+            if w > remaining {
+                break;
+            }
+            remaining -= w;
+            part.push(ch);
+        }
+        result.push((part, *style));
+        break;
+    }
+    result
+}
+
+fn paint(segments: &[(String, Style)], use_ansi_coloring: bool) -> String {
+    segments
+        .iter()
+        .map(|(text, style)| {
+            if use_ansi_coloring {
+                style.paint(text).to_string()
+            } else {
+                text.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reedline::Span;
+
+    const CLEAR_LINE: &str = "\x1b[K";
+    const CLEAR_DOWN: &str = "\x1b[J";
+
+    fn suggestion(value: &str, description: &str) -> Suggestion {
+        Suggestion {
+            value: value.to_string(),
+            description: Some(description.to_string()),
+            style: None,
+            extra: None,
+            span: Span::new(0, 0),
+            append_whitespace: false,
+        }
+    }
+
+    /// 30 项：前 3 项是目录
+    fn menu() -> CompletionMenu {
+        let mut menu = CompletionMenu::new(COMPLETION_MENU);
+        menu.active = true;
+        menu.width = 60;
+        menu.values = (0..30)
+            .map(|i| {
+                if i < 3 {
+                    suggestion(&format!("@src/dir_{:02}", i), "dir/")
+                } else {
+                    suggestion(&format!("@src/file_{:02}.rs", i), "1.2 KB")
+                }
+            })
+            .collect();
+        menu
+    }
+
+    fn send(menu: &mut CompletionMenu, event: MenuEvent) {
+        menu.navigate(&event);
+        menu.scroll_into_view();
+    }
+
+    fn render(menu: &CompletionMenu, available_lines: usize, color: bool) -> String {
+        let mut out = Vec::new();
+        menu.render(&mut out, available_lines, color).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// 去掉清除序列后的各行文字
+    fn lines(output: &str) -> Vec<String> {
+        output
+            .trim_end_matches(CLEAR_DOWN)
+            .split("\r\n")
+            .map(|line| line.trim_end_matches(CLEAR_LINE).trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_first_page_with_indicator() {
+        let menu = menu();
+        let output = render(&menu, 20, false);
+        let lines = lines(&output);
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].starts_with(" @src/dir_00"));
+        assert!(lines[6].starts_with(" @src/file_06.rs"));
+        assert_eq!(lines[7], " 1/30");
+        assert_eq!(output.matches(CLEAR_LINE).count(), 8);
+        assert!(output.ends_with(CLEAR_DOWN));
+        assert_eq!(menu.menu_required_lines(80), 8);
+    }
+
+    #[test]
+    fn test_paging_and_jumps() {
+        let mut menu = menu();
+        send(&mut menu, MenuEvent::MoveDown);
+        send(&mut menu, MenuEvent::MoveDown);
+        assert_eq!(lines(&render(&menu, 20, false))[7], " 3/30");
+
+        send(&mut menu, MenuEvent::NextPage);
+        let page = lines(&render(&menu, 20, false));
+        assert_eq!(menu.selected, 9);
+        assert!(page[0].starts_with(" @src/file_07.rs"));
+        assert!(page[2].starts_with(" @src/file_09.rs"));
+        assert_eq!(page[7], " 10/30");
+
+        send(&mut menu, MenuEvent::MoveRight);
+        let last = lines(&render(&menu, 20, false));
+        assert!(last[6].starts_with(" @src/file_29.rs"));
+        assert_eq!(last[7], " 30/30");
+
+        // 最后一页继续翻页停在最后一项
+        send(&mut menu, MenuEvent::NextPage);
+        assert_eq!(menu.selected, 29);
+        assert_eq!(menu.skip, 23);
+
+        send(&mut menu, MenuEvent::PreviousPage);
+        assert_eq!(menu.selected, 22);
+        assert_eq!(lines(&render(&menu, 20, false))[7], " 23/30");
+
+        send(&mut menu, MenuEvent::MoveLeft);
+        let first = lines(&render(&menu, 20, false));
+        assert!(first[0].starts_with(" @src/dir_00"));
+        assert_eq!(first[7], " 1/30");
+
+        // 上下键首尾循环
+        send(&mut menu, MenuEvent::MoveUp);
+        assert_eq!(menu.selected, 29);
+        send(&mut menu, MenuEvent::MoveDown);
+        assert_eq!(menu.selected, 0);
+    }
+
+    #[test]
+    fn test_shrinking_list_clears_stale_lines() {
+        let mut menu = menu();
+        send(&mut menu, MenuEvent::NextPage);
+        render(&menu, 20, false);
+
+        menu.values.truncate(2);
+        menu.reset_position();
+        menu.scroll_into_view();
+        let output = render(&menu, 20, false);
+        assert_eq!(lines(&output).len(), 2);
+        assert!(!output.contains("/2"));
+        assert!(output.ends_with(CLEAR_DOWN));
+        assert_eq!(menu.menu_required_lines(80), 2);
+
+        menu.values.clear();
+        assert_eq!(render(&menu, 20, false), CLEAR_DOWN);
+        assert_eq!(menu.menu_required_lines(80), 0);
+    }
+
+    #[test]
+    fn test_fits_available_lines_and_width() {
+        let mut menu = menu();
+        menu.available_lines = 4;
+        let output = lines(&render(&menu, 4, false));
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[3], " 1/30");
+
+        menu.width = 10;
+        let narrow = lines(&render(&menu, 4, false));
+        assert!(narrow.iter().all(|line| line.width() <= 10));
+        assert_eq!(narrow[0], " @src/dir_");
+    }
+
+    #[test]
+    fn test_highlights_matches_and_directories() {
+        let mut menu = menu();
+        menu.query = "FILE_1".to_string();
+        send(&mut menu, MenuEvent::NextPage);
+        for _ in 0..3 {
+            send(&mut menu, MenuEvent::MoveDown);
+        }
+        let output = render(&menu, 20, true);
+        let style = CompletionMenuStyle::default();
+
+        // 未选中项和选中项中的匹配部分
+        assert!(output.contains(&style.matched.paint("file_1").to_string()));
+        assert!(output.contains(&style.selected_matched.paint("file_1").to_string()));
+        assert!(output.contains(&style.text.paint("1.rs").to_string()));
+
+        send(&mut menu, MenuEvent::MoveLeft);
+        let output = render(&menu, 20, true);
+        // 选中的目录用选中样式，其余目录用目录颜色
+        assert!(output.contains(&style.selected.paint(" @src/dir_00").to_string()));
+        assert!(output.contains(&style.directory.paint(" @src/dir_01").to_string()));
+    }
+
+    #[test]
+    fn test_match_indices() {
+        assert_eq!(match_indices("@src/main.rs", "MAIN"), vec![5, 6, 7, 8]);
+        assert_eq!(match_indices("@src/cli/mod.rs", "scm"), vec![1, 5, 9]);
+        assert!(match_indices("@src/main.rs", "").is_empty());
+        assert!(match_indices("@src/main.rs", "xyz").is_empty());
+    }
+}
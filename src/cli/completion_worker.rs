@@ -0,0 +1,364 @@
+//! 后台补全工作线程
+//!
+//! 按键只负责把当前输入快照（行内容 + 光标位置）投递给工作线程，不等待结果；
+//! 工作线程对防抖窗口内的连续请求只计算最新的一个，算完后通知界面重绘。
+//! 每次请求都会递增代数（generation），只有代数仍为最新且快照与
+//! 当前输入一致的结果才会被界面使用，过期结果直接丢弃。
+//!
+//! reedline 在等待按键时无法从外部唤醒，[`EditorWaker`] 通过一次尺寸不变的
+//! 终端 resize 事件让它回到事件循环，[`CompletionWakeMode`] 再把这个事件换成
+//! 一次空编辑，菜单随之重新取候选。
+
+use reedline::{EditMode, PromptEditMode, ReedlineEvent, ReedlineRawEvent, Suggestion};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 补全数据源（在工作线程中调用，允许较慢）
+pub trait CompletionSource: Send + 'static {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion>;
+}
+
+/// 补全请求（输入快照）
+#[derive(Debug, Clone)]
+struct CompletionRequest {
+    generation: u64,
+    line: String,
+    pos: usize,
+}
+
+/// 补全结果
+#[derive(Debug, Clone)]
+struct CompletionResult {
+    generation: u64,
+    line: String,
+    pos: usize,
+    suggestions: Vec<Suggestion>,
+}
+
+/// 后台补全工作线程句柄
+pub struct CompletionWorker {
+    generation: Arc<AtomicU64>,
+    sender: mpsc::Sender<CompletionRequest>,
+    latest: Arc<Mutex<Option<CompletionResult>>>,
+    /// 最近一次投递的快照，相同输入不重复投递
+    requested: Option<(String, usize)>,
+}
+
+impl CompletionWorker {
+    /// 启动工作线程
+    ///
+    /// `debounce` 为防抖窗口：收到请求后若窗口内又有新请求，只计算最新的一个。
+    /// 结果发布后调用 `on_ready`，由界面重新调用 [`CompletionWorker::complete`] 取结果。
+    pub fn spawn<S, F>(mut source: S, debounce: Duration, on_ready: F) -> Self
+    where
+        S: CompletionSource,
+        F: Fn() + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<CompletionRequest>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let latest = Arc::new(Mutex::new(None));
+
+        let worker_generation = Arc::clone(&generation);
+        let worker_latest = Arc::clone(&latest);
+        std::thread::spawn(move || {
+            while let Ok(mut request) = receiver.recv() {
+                // 防抖：合并窗口内的连续请求
+                loop {
+                    match receiver.recv_timeout(debounce) {
+                        Ok(newer) => request = newer,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                // 计算前后都检查代数，过期请求不计算、过期结果不发布
+                if request.generation != worker_generation.load(Ordering::Acquire) {
+                    continue;
+                }
+                let suggestions = source.complete(&request.line, request.pos);
+                if request.generation != worker_generation.load(Ordering::Acquire) {
+                    continue;
+                }
+
+                if let Ok(mut latest) = worker_latest.lock() {
+                    *latest = Some(CompletionResult {
+                        generation: request.generation,
+                        line: request.line,
+                        pos: request.pos,
+                        suggestions,
+                    });
+                }
+                on_ready();
+            }
+        });
+
+        Self {
+            generation,
+            sender,
+            latest,
+            requested: None,
+        }
+    }
+
+    /// 取当前输入的补全结果，不阻塞
+    ///
+    /// 已有与快照一致的最新结果时直接返回；否则投递请求（同一快照只投递一次）
+    /// 并返回空列表，结果算完后通过 `on_ready` 通知界面再来取。
+    pub fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        if let Some(suggestions) = self.ready(line, pos) {
+            return suggestions;
+        }
+        let snapshot = (line.to_string(), pos);
+        if self.requested.as_ref() != Some(&snapshot) {
+            self.request(line, pos);
+            self.requested = Some(snapshot);
+        }
+        Vec::new()
+    }
+
+    /// 投递补全请求，返回本次请求的代数
+    fn request(&self, line: &str, pos: usize) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        let _ = self.sender.send(CompletionRequest {
+            generation,
+            line: line.to_string(),
+            pos,
+        });
+        generation
+    }
+
+    /// 与当前输入快照一致的最新结果（过期结果返回 None）
+    fn ready(&self, line: &str, pos: usize) -> Option<Vec<Suggestion>> {
+        let latest = self.latest.lock().ok()?;
+        latest
+            .as_ref()
+            .filter(|result| {
+                result.generation == self.generation.load(Ordering::Acquire)
+                    && result.line == line
+                    && result.pos == pos
+            })
+            .map(|result| result.suggestions.clone())
+    }
+}
+
+/// 补全结果就绪后唤醒正在等待按键的 reedline
+#[derive(Default)]
+pub struct EditorWaker {
+    pending: AtomicBool,
+}
+
+impl EditorWaker {
+    /// 标记有新结果，并让终端事件循环收到一次 resize 事件
+    pub fn wake(&self) {
+        if !self.pending.swap(true, Ordering::AcqRel) {
+            raise_resize_event();
+        }
+    }
+
+    /// 取走唤醒标记
+    fn take(&self) -> bool {
+        self.pending.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// 向自身发送 SIGWINCH，crossterm 会把它转换成 resize 事件
+#[cfg(unix)]
+fn raise_resize_event() {
+    unsafe {
+        libc::raise(libc::SIGWINCH);
+    }
+}
+
+/// 向控制台输入缓冲区写入一条窗口尺寸事件
+#[cfg(windows)]
+fn raise_resize_event() {
+    use windows_sys::Win32::System::Console::{
+        GetStdHandle, WriteConsoleInputW, COORD, INPUT_RECORD, INPUT_RECORD_0, STD_INPUT_HANDLE,
+        WINDOW_BUFFER_SIZE_EVENT, WINDOW_BUFFER_SIZE_RECORD,
+    };
+
+    let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+    let record = INPUT_RECORD {
+        EventType: WINDOW_BUFFER_SIZE_EVENT as u16,
+        Event: INPUT_RECORD_0 {
+            WindowBufferSizeEvent: WINDOW_BUFFER_SIZE_RECORD {
+                dwSize: COORD {
+                    X: width as i16,
+                    Y: height as i16,
+                },
+            },
+        },
+    };
+    let mut written = 0;
+    unsafe {
+        WriteConsoleInputW(GetStdHandle(STD_INPUT_HANDLE), &record, 1, &mut written);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn raise_resize_event() {}
+
+/// 把补全唤醒产生的 resize 事件换成一次空编辑
+///
+/// reedline 处理编辑事件时会让打开的菜单重新取候选；尺寸确实变化的 resize
+/// 事件原样传递。
+pub struct CompletionWakeMode {
+    inner: Box<dyn EditMode>,
+    waker: Arc<EditorWaker>,
+    size: Option<(u16, u16)>,
+}
+
+impl CompletionWakeMode {
+    pub fn new(inner: Box<dyn EditMode>, waker: Arc<EditorWaker>) -> Self {
+        Self {
+            inner,
+            waker,
+            size: crossterm::terminal::size().ok(),
+        }
+    }
+
+    fn translate(&mut self, event: ReedlineEvent) -> ReedlineEvent {
+        match event {
+            ReedlineEvent::Resize(width, height) => {
+                let resized = self.size.replace((width, height)) != Some((width, height));
+                if self.waker.take() && !resized {
+                    ReedlineEvent::Edit(Vec::new())
+                } else {
+                    ReedlineEvent::Resize(width, height)
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+impl EditMode for CompletionWakeMode {
+    fn parse_event(&mut self, event: ReedlineRawEvent) -> ReedlineEvent {
+        let event = self.inner.parse_event(event);
+        self.translate(event)
+    }
+
+    fn edit_mode(&self) -> PromptEditMode {
+        self.inner.edit_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reedline::Span;
+    use std::sync::atomic::AtomicUsize;
+
+    /// 计算很慢的假补全源，记录调用次数
+    struct SlowCompleter {
+        delay: Duration,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CompletionSource for SlowCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(self.delay);
+            vec![Suggestion {
+                value: line[..pos].to_string(),
+                description: None,
+                style: None,
+                extra: None,
+                span: Span::new(0, pos),
+                append_whitespace: false,
+            }]
+        }
+    }
+
+    fn slow_worker(
+        delay: Duration,
+        debounce: Duration,
+    ) -> (CompletionWorker, Arc<AtomicUsize>, mpsc::Receiver<()>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let source = SlowCompleter {
+            delay,
+            calls: Arc::clone(&calls),
+        };
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let worker = CompletionWorker::spawn(source, debounce, move || {
+            let _ = ready_tx.send(());
+        });
+        (worker, calls, ready_rx)
+    }
+
+    #[test]
+    fn test_debounce_coalesces_rapid_requests() {
+        let (mut worker, calls, ready) =
+            slow_worker(Duration::from_millis(10), Duration::from_millis(80));
+
+        for line in ["@m", "@ma", "@mai", "@main"] {
+            assert!(worker.complete(line, line.len()).is_empty());
+        }
+
+        ready
+            .recv_timeout(Duration::from_secs(2))
+            .expect("latest request should complete");
+        assert_eq!(worker.complete("@main", 5)[0].value, "@main");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_stale_result_is_discarded() {
+        let (mut worker, calls, ready) =
+            slow_worker(Duration::from_millis(150), Duration::from_millis(1));
+
+        worker.complete("@ma", 3);
+        // 等工作线程开始计算第一个请求后再输入
+        std::thread::sleep(Duration::from_millis(50));
+        worker.complete("@main", 5);
+
+        ready
+            .recv_timeout(Duration::from_secs(2))
+            .expect("second request should complete");
+        assert!(ready.recv_timeout(Duration::from_millis(300)).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(worker.complete("@main", 5)[0].value, "@main");
+        assert!(worker.ready("@ma", 3).is_none());
+    }
+
+    #[test]
+    fn test_result_requires_matching_snapshot() {
+        let (mut worker, calls, ready) = slow_worker(Duration::ZERO, Duration::from_millis(1));
+
+        worker.complete("@src", 4);
+        ready.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(!worker.complete("@src", 4).is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert!(worker.complete("@src/", 5).is_empty());
+        assert!(worker.ready("@src", 4).is_none());
+    }
+
+    #[test]
+    fn test_wake_replaces_unchanged_resize_once() {
+        let waker = Arc::new(EditorWaker::default());
+        let mut mode = CompletionWakeMode {
+            inner: Box::new(reedline::Emacs::default()),
+            waker: Arc::clone(&waker),
+            size: Some((80, 24)),
+        };
+
+        waker.pending.store(true, Ordering::Release);
+        assert_eq!(
+            mode.translate(ReedlineEvent::Resize(80, 24)),
+            ReedlineEvent::Edit(Vec::new())
+        );
+        assert_eq!(
+            mode.translate(ReedlineEvent::Resize(80, 24)),
+            ReedlineEvent::Resize(80, 24)
+        );
+
+        waker.pending.store(true, Ordering::Release);
+        assert_eq!(
+            mode.translate(ReedlineEvent::Resize(100, 30)),
+            ReedlineEvent::Resize(100, 30)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! 会话导出（`/export`）
+
+use crate::context::SerializableMessage;
+
+/// 把会话渲染为 Markdown
+///
+/// 指定标签时只导出带这些标签的用户消息及其后的助手回复。
+pub fn render_markdown(
+    session_id: &str,
+    messages: &[SerializableMessage],
+    tags: &[String],
+) -> (String, usize) {
+    let mut output = format!("# {}\n", session_id);
+    let mut count = 0;
+    let mut include = tags.is_empty();
+    for message in messages {
+        if message.role == "user" && !tags.is_empty() {
+            include = message.tags.iter().any(|tag| tags.contains(tag));
+        }
+        if !include {
+            continue;
+        }
+
+        let heading = if message.role == "user" {
+            "User"
+        } else {
+            "Assistant"
+        };
+        output.push_str(&format!("\n## {}", heading));
+        if message.pinned {
+            output.push_str(" 📌");
+        }
+        for tag in &message.tags {
+            output.push_str(&format!(" `#{}`", tag));
+        }
+        output.push_str(&format!("\n\n{}\n", message.content.trim_end()));
+        count += 1;
+    }
+    (output, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str, tags: &[&str]) -> SerializableMessage {
+        SerializableMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            blocks: Vec::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_render_markdown_marks_pinned_messages() {
+        let mut pinned = message("user", "always use tabs", &["style"]);
+        pinned.pinned = true;
+        let messages = vec![pinned, message("assistant", "noted", &[])];
+
+        let (output, _) = render_markdown("s1", &messages, &[]);
+        assert!(output.contains("## User 📌 `#style`\n\nalways use tabs\n"));
+        assert!(output.contains("## Assistant\n\nnoted\n"));
+    }
+
+    #[test]
+    fn test_render_markdown_filters_by_tag() {
+        let messages = vec![
+            message("user", "login fails", &["bug"]),
+            message("assistant", "fixed the session check", &[]),
+            message("user", "add dark mode", &["feature"]),
+            message("assistant", "added a theme toggle", &[]),
+        ];
+
+        let (all, count) = render_markdown("s1", &messages, &[]);
+        assert_eq!(count, 4);
+        assert!(all.starts_with("# s1\n"));
+
+        let (bugs, count) = render_markdown("s1", &messages, &["bug".to_string()]);
+        assert_eq!(count, 2);
+        assert!(bugs.contains("## User `#bug`\n\nlogin fails\n"));
+        assert!(bugs.contains("fixed the session check"));
+        assert!(!bugs.contains("dark mode"));
+        assert!(!bugs.contains("theme toggle"));
+    }
+}
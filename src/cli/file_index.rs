@@ -0,0 +1,445 @@
+//! @ 文件补全索引
+//!
+//! 会话内只遍历一次项目目录（在后台线程中使用 ignore-aware 的 walker），
+//! 之后通过 `notify` 文件系统监听增量更新，或由 `/refresh-files` 手动重建。
+//! 补全查询只在内存中做模糊匹配，不再每次按键都遍历目录树。
+//! 额外的工作区根目录（`--add-dir` / `/add-dir`）各自建立索引，补全项写成 `alias:相对路径`。
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// 始终忽略的目录（即使没有写进 .gitignore）
+const IGNORED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".pytest_cache",
+    "vendor",
+    ".cache",
+];
+
+/// 索引中的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    /// 相对于索引根目录的路径（统一使用 `/` 分隔），额外根目录中的文件带 `alias:` 前缀
+    pub relative_path: String,
+    /// 文件大小（字节）
+    pub size_bytes: u64,
+}
+
+/// 额外工作区根目录的索引
+struct ExtraRoot {
+    alias: String,
+    index: Arc<FileIndex>,
+    /// 文件系统监听（持有以保持监听有效）
+    _watcher: Option<RecommendedWatcher>,
+}
+
+/// 文件索引
+pub struct FileIndex {
+    root: PathBuf,
+    files: RwLock<Vec<IndexedFile>>,
+    gitignore: RwLock<Gitignore>,
+    ready: AtomicBool,
+    matcher: SkimMatcherV2,
+    extra: RwLock<Vec<ExtraRoot>>,
+}
+
+impl std::fmt::Debug for FileIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileIndex")
+            .field("root", &self.root)
+            .field("ready", &self.is_ready())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl FileIndex {
+    /// 创建空索引（不会立即遍历）
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let gitignore = build_gitignore(&root);
+        Self {
+            root,
+            files: RwLock::new(Vec::new()),
+            gitignore: RwLock::new(gitignore),
+            ready: AtomicBool::new(false),
+            matcher: SkimMatcherV2::default(),
+            extra: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 创建索引并在后台线程中完成首次遍历
+    pub fn spawn(root: impl Into<PathBuf>) -> Arc<Self> {
+        let index = Arc::new(Self::new(root));
+        let background = Arc::clone(&index);
+        std::thread::spawn(move || background.refresh());
+        index
+    }
+
+    /// 索引根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 首次遍历是否完成
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// 加入额外的工作区根目录，在后台遍历并监听其文件
+    pub fn add_root(&self, alias: impl Into<String>, root: impl Into<PathBuf>) {
+        let index = Self::spawn(root);
+        let watcher = index.watch().ok();
+        if let Ok(mut extra) = self.extra.write() {
+            extra.push(ExtraRoot {
+                alias: alias.into(),
+                index,
+                _watcher: watcher,
+            });
+        }
+    }
+
+    /// 已索引的文件数量（含额外根目录）
+    pub fn len(&self) -> usize {
+        let own = self.files.read().map(|files| files.len()).unwrap_or(0);
+        let extra: usize = self
+            .extra_indexes()
+            .iter()
+            .map(|(_, index)| index.len())
+            .sum();
+        own + extra
+    }
+
+    /// 重新遍历目录树（含额外根目录），重建索引
+    pub fn refresh(&self) {
+        for (_, index) in self.extra_indexes() {
+            index.refresh();
+        }
+        self.refresh_own();
+    }
+
+    fn refresh_own(&self) {
+        let gitignore = build_gitignore(&self.root);
+        let mut files: Vec<IndexedFile> = WalkBuilder::new(&self.root)
+            .hidden(true)
+            .git_ignore(true)
+            .require_git(false)
+            .filter_entry(|entry| !is_ignored_dir_name(&entry.file_name().to_string_lossy()))
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map_or(false, |ft| ft.is_file()))
+            .filter_map(|entry| self.to_indexed(entry.path()))
+            .collect();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        if let Ok(mut guard) = self.gitignore.write() {
+            *guard = gitignore;
+        }
+        if let Ok(mut guard) = self.files.write() {
+            *guard = files;
+        }
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// 模糊匹配文件路径，按得分从高到低返回最多 `limit` 个结果
+    ///
+    /// 文件名命中的得分高于只在目录部分命中的结果。
+    pub fn search(&self, query: &str, limit: usize) -> Vec<IndexedFile> {
+        let mut scored = self.score(query, "", limit);
+        for (alias, index) in self.extra_indexes() {
+            scored.extend(index.score(query, &format!("{}:", alias), limit));
+        }
+
+        if !query.is_empty() {
+            scored.sort_by(|a, b| {
+                b.0.cmp(&a.0)
+                    .then(a.1.relative_path.len().cmp(&b.1.relative_path.len()))
+                    .then(a.1.relative_path.cmp(&b.1.relative_path))
+            });
+        }
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, file)| file)
+            .collect()
+    }
+
+    /// 本索引中匹配的文件及得分（空查询时按路径顺序取前 `limit` 个），路径加上 `prefix`
+    fn score(&self, query: &str, prefix: &str, limit: usize) -> Vec<(i64, IndexedFile)> {
+        let files = match self.files.read() {
+            Ok(files) => files,
+            Err(_) => return Vec::new(),
+        };
+        let with_prefix = |file: &IndexedFile| IndexedFile {
+            relative_path: format!("{}{}", prefix, file.relative_path),
+            size_bytes: file.size_bytes,
+        };
+
+        if query.is_empty() {
+            return files
+                .iter()
+                .take(limit)
+                .map(|file| (0, with_prefix(file)))
+                .collect();
+        }
+
+        files
+            .iter()
+            .filter_map(|file| {
+                let name = file
+                    .relative_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&file.relative_path);
+                let name_score = self.matcher.fuzzy_match(name, query).map(|s| s * 2);
+                let path_score = self.matcher.fuzzy_match(&file.relative_path, query);
+                name_score
+                    .max(path_score)
+                    .map(|score| (score, with_prefix(file)))
+            })
+            .collect()
+    }
+
+    fn extra_indexes(&self) -> Vec<(String, Arc<FileIndex>)> {
+        self.extra
+            .read()
+            .map(|extra| {
+                extra
+                    .iter()
+                    .map(|root| (root.alias.clone(), Arc::clone(&root.index)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 根据文件系统事件增量更新索引
+    pub fn apply_event(&self, event: &notify::Event) {
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in &event.paths {
+                    if path.is_file() {
+                        self.insert(path);
+                    } else if !path.exists() {
+                        self.remove(path);
+                    }
+                }
+            }
+            EventKind::Remove(_) => {
+                for path in &event.paths {
+                    self.remove(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 启动文件系统监听，返回的 watcher 需要由调用方持有
+    pub fn watch(self: &Arc<Self>) -> notify::Result<RecommendedWatcher> {
+        let index = Arc::downgrade(self);
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if let (Ok(event), Some(index)) = (result, index.upgrade()) {
+                index.apply_event(&event);
+            }
+        })?;
+        watcher.watch(&self.root, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    }
+
+    fn insert(&self, path: &Path) {
+        let Some(file) = self.to_indexed(path) else {
+            return;
+        };
+        if self.is_ignored(path) {
+            return;
+        }
+        if let Ok(mut files) = self.files.write() {
+            match files.binary_search_by(|f| f.relative_path.cmp(&file.relative_path)) {
+                Ok(pos) => files[pos] = file,
+                Err(pos) => files.insert(pos, file),
+            }
+        }
+    }
+
+    fn remove(&self, path: &Path) {
+        let Some(relative) = self.relative_path(path) else {
+            return;
+        };
+        if let Ok(mut files) = self.files.write() {
+            // 删除目录时同时移除其下的所有文件
+            let dir_prefix = format!("{}/", relative);
+            files.retain(|f| f.relative_path != relative && !f.relative_path.starts_with(&dir_prefix));
+        }
+    }
+
+    /// 新增文件是否应被忽略（隐藏文件、内置忽略目录或 .gitignore）
+    fn is_ignored(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative_path(path) else {
+            return true;
+        };
+        if relative
+            .split('/')
+            .any(|part| part.starts_with('.') || is_ignored_dir_name(part))
+        {
+            return true;
+        }
+        self.gitignore
+            .read()
+            .map(|gitignore| {
+                gitignore
+                    .matched_path_or_any_parents(&relative, false)
+                    .is_ignore()
+            })
+            .unwrap_or(false)
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn to_indexed(&self, path: &Path) -> Option<IndexedFile> {
+        let relative_path = self.relative_path(path)?;
+        let size_bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        Some(IndexedFile {
+            relative_path,
+            size_bytes,
+        })
+    }
+}
+
+fn is_ignored_dir_name(name: &str) -> bool {
+    IGNORED_DIRS.contains(&name)
+}
+
+/// 读取根目录的 .gitignore，用于判断监听到的新文件是否应加入索引
+fn build_gitignore(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    builder.add(root.join(".gitignore"));
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+    use std::fs::{self, File};
+    use tempfile::TempDir;
+
+    fn paths(index: &FileIndex) -> Vec<String> {
+        index
+            .search("", usize::MAX)
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect()
+    }
+
+    #[test]
+    fn test_index_excludes_ignored_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+
+        fs::create_dir_all(base.join("src")).unwrap();
+        fs::create_dir_all(base.join("target/debug")).unwrap();
+        fs::create_dir_all(base.join(".git")).unwrap();
+        fs::create_dir_all(base.join("generated")).unwrap();
+        fs::write(base.join(".gitignore"), "generated/\n*.log\n").unwrap();
+
+        File::create(base.join("src/main.rs")).unwrap();
+        File::create(base.join("Cargo.toml")).unwrap();
+        File::create(base.join("target/debug/oxide")).unwrap();
+        File::create(base.join(".git/config")).unwrap();
+        File::create(base.join("generated/out.rs")).unwrap();
+        File::create(base.join("debug.log")).unwrap();
+
+        let index = FileIndex::new(base);
+        index.refresh();
+
+        assert!(index.is_ready());
+        assert_eq!(paths(&index), vec!["Cargo.toml", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_watcher_event_adds_new_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::write(base.join(".gitignore"), "*.log\n").unwrap();
+        File::create(base.join("lib.rs")).unwrap();
+
+        let index = FileIndex::new(base);
+        index.refresh();
+        assert_eq!(paths(&index), vec!["lib.rs"]);
+
+        let created = base.join("main.rs");
+        fs::write(&created, "fn main() {}").unwrap();
+        let ignored = base.join("trace.log");
+        File::create(&ignored).unwrap();
+
+        index.apply_event(
+            &notify::Event::new(EventKind::Create(CreateKind::File))
+                .add_path(created.clone())
+                .add_path(ignored),
+        );
+        assert_eq!(paths(&index), vec!["lib.rs", "main.rs"]);
+        assert_eq!(index.search("main", 10)[0].size_bytes, 12);
+
+        fs::remove_file(&created).unwrap();
+        index.apply_event(&notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(created));
+        assert_eq!(paths(&index), vec!["lib.rs"]);
+    }
+
+    #[test]
+    fn test_search_ranks_file_name_matches_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join("src/cli")).unwrap();
+        fs::create_dir_all(base.join("src/mod_utils")).unwrap();
+        File::create(base.join("src/cli/mod.rs")).unwrap();
+        File::create(base.join("src/mod_utils/helpers.rs")).unwrap();
+        File::create(base.join("README.md")).unwrap();
+
+        let index = FileIndex::new(base);
+        index.refresh();
+
+        let results = index.search("mod", 10);
+        assert_eq!(results[0].relative_path, "src/cli/mod.rs");
+        assert!(results.iter().all(|f| f.relative_path != "README.md"));
+    }
+
+    #[test]
+    fn test_search_covers_extra_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let (web, api) = (temp_dir.path().join("web"), temp_dir.path().join("api"));
+        fs::create_dir_all(web.join("src")).unwrap();
+        fs::create_dir_all(api.join("src")).unwrap();
+        File::create(web.join("src/routes.ts")).unwrap();
+        File::create(api.join("src/routes.rs")).unwrap();
+
+        let index = FileIndex::new(&web);
+        index.add_root("api", &api);
+        index.refresh();
+
+        assert_eq!(index.len(), 2);
+        let mut results: Vec<String> = index
+            .search("routes", 10)
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect();
+        results.sort();
+        assert_eq!(results, vec!["api:src/routes.rs", "src/routes.ts"]);
+        assert_eq!(paths(&index), vec!["src/routes.ts", "api:src/routes.rs"]);
+    }
+}
@@ -1,7 +1,9 @@
+use crate::tools::image::ImageInfo;
+use crate::tools::WorkspaceRoots;
 use anyhow::{Context, Result};
 use colored::*;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// 文件引用信息
 #[derive(Debug, Clone)]
@@ -22,7 +24,7 @@ impl FileReference {
     /// 创建文件引用
     pub fn new(raw_reference: String, file_path: PathBuf, content: String) -> Result<Self> {
         let size_bytes = fs::metadata(&file_path)
-            .with_context(|| format!("无法读取文件元数据: {}", file_path.display()))?
+            .with_context(|| t!("cli.file.metadata_failed", path = file_path.display()))?
             .len();
 
         let line_count = content.lines().count();
@@ -39,12 +41,15 @@ impl FileReference {
     /// 显示文件引用信息
     pub fn display_info(&self) -> String {
         format!(
-            "{} {} {} ({} bytes, {} lines)",
+            "{} {} {} {}",
             "📎".bright_cyan(),
             self.raw_reference.bright_white(),
             self.file_path.display().to_string().dimmed(),
-            self.size_bytes.to_string().dimmed(),
-            self.line_count.to_string().dimmed()
+            t!(
+                "cli.file.reference_info",
+                size = self.size_bytes.to_string().dimmed(),
+                lines = self.line_count.to_string().dimmed()
+            )
         )
     }
 }
@@ -53,31 +58,39 @@ impl FileReference {
 ///
 /// # 参数
 /// - `input`: 用户输入的文本
+/// - `roots`: 工作区根目录，用于解析 `@alias:相对路径`
 ///
 /// # 返回
 /// - (解析后的文本, 文件引用列表)
-pub fn parse_file_references(input: &str) -> (String, Vec<FileReference>) {
+pub fn parse_file_references(input: &str, roots: &WorkspaceRoots) -> (String, Vec<FileReference>) {
+    // 拖放/粘贴进来的路径先转换成 @ 引用
+    let input = &normalize_pasted_paths(input);
     let mut references = Vec::new();
     let mut parsed_input = String::from(input);
 
-    // 匹配 @路径/文件名 或 @相对路径/文件
+    // 匹配 @路径/文件名、@相对路径/文件，或带空格的 @"路径"
     // 规则：@ 后面必须跟路径分隔符 (/ 或 \) 或文件名
-    let re = regex::Regex::new(r"@([^\s@]+)").unwrap();
+    let re = regex::Regex::new(r#"@(?:"([^"]+)"|([^\s@"]+))"#).unwrap();
 
     for cap in re.captures_iter(input) {
         let full_match = cap.get(0).unwrap().as_str();
-        let path_str = cap.get(1).unwrap().as_str();
+        let path_str = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
 
         // 检查是否是有效的文件路径（包含路径分隔符，或者是看起来像文件名的字符串）
-        if is_valid_file_reference(path_str) {
-            match resolve_and_read_file(path_str) {
+        // 图片引用保留在文本中，由 parse_image_references 处理
+        if is_valid_file_reference(path_str) && resolve_image(path_str, roots).is_none() {
+            match resolve_and_read_file(path_str, roots) {
                 Ok(file_ref) => {
                     references.push(file_ref);
                     // 从输入中移除 @引用
                     parsed_input = parsed_input.replace(full_match, "");
                 }
                 Err(e) => {
-                    println!("{} {}", "⚠️".yellow(), format!("无法读取文件 @{}: {}", path_str, e));
+                    println!(
+                        "{} {}",
+                        "⚠️".yellow(),
+                        t!("cli.file.reference_failed", path = path_str, error = e)
+                    );
                 }
             }
         }
@@ -89,6 +102,250 @@ pub fn parse_file_references(input: &str) -> (String, Vec<FileReference>) {
     (parsed_input, references)
 }
 
+/// 输入中指向图片文件的 `@` 引用（按文件头识别 PNG、JPEG、GIF、WebP）
+///
+/// 这些引用不作为文本读取，`parse_file_references` 把它们原样留在输入中。
+pub fn parse_image_references(input: &str, roots: &WorkspaceRoots) -> Vec<ImageInfo> {
+    let input = &normalize_pasted_paths(input);
+    let re = regex::Regex::new(r#"@(?:"([^"]+)"|([^\s@"]+))"#).unwrap();
+    let mut images: Vec<ImageInfo> = Vec::new();
+    for cap in re.captures_iter(input) {
+        let path_str = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+        if let Some(image) = resolve_image(path_str, roots) {
+            if !images.iter().any(|seen| seen.path == image.path) {
+                images.push(image);
+            }
+        }
+    }
+    images
+}
+
+/// 引用的路径是图片文件时返回图片信息
+fn resolve_image(path_str: &str, roots: &WorkspaceRoots) -> Option<ImageInfo> {
+    let path = resolve_file_path(path_str, roots).ok()?;
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let bytes = fs::read(&path).ok()?;
+    ImageInfo::sniff(&path.to_string_lossy(), &bytes)
+}
+
+/// 按图片处理的 `@` 引用的扩展名
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// 拖放/粘贴路径的平台格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    /// macOS / Linux：反斜杠是转义符
+    Unix,
+    /// Windows：反斜杠是路径分隔符，`/` 同样可用
+    Windows,
+}
+
+impl PathStyle {
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Self::Windows
+        } else {
+            Self::Unix
+        }
+    }
+}
+
+/// 把输入中拖放/粘贴进来的文件路径转换为 `@` 引用
+///
+/// 终端拖放文件时路径常带引号或转义空格（`'/a/My Project/x.rs'`、`/a/My\ Project/x.rs`），
+/// 也可能是 `file://` URL。规范化后指向已存在文件的绝对路径会被替换成
+/// `@相对路径`（在当前目录之外时保留绝对路径），其余内容原样保留。
+pub fn normalize_pasted_paths(input: &str) -> String {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    normalize_pasted_paths_in(
+        input,
+        &cwd,
+        dirs::home_dir().as_deref(),
+        PathStyle::native(),
+    )
+}
+
+fn normalize_pasted_paths_in(
+    input: &str,
+    cwd: &Path,
+    home: Option<&Path>,
+    style: PathStyle,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut last = 0;
+    for (start, end) in split_path_tokens(input, style) {
+        let token = &input[start..end];
+        let raw = token.strip_prefix('@').unwrap_or(token);
+        let normalized = normalize_pasted_path(raw, style, home);
+        if !looks_like_pasted_path(raw) || !is_absolute_path(&normalized, style) {
+            continue;
+        }
+        let path = PathBuf::from(&normalized);
+        if !path.is_file() {
+            continue;
+        }
+
+        let reference = match path.strip_prefix(cwd) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => normalized,
+        };
+        output.push_str(&input[last..start]);
+        if reference.contains(char::is_whitespace) {
+            output.push_str(&format!("@\"{}\"", reference));
+        } else {
+            output.push_str(&format!("@{}", reference));
+        }
+        last = end;
+    }
+    output.push_str(&input[last..]);
+    output
+}
+
+/// 规范化单个拖放/粘贴的路径：去掉外层引号、反转义空格、转换 `file://` URL、展开 `~`
+pub fn normalize_pasted_path(raw: &str, style: PathStyle, home: Option<&Path>) -> String {
+    let mut path = raw.trim();
+    let quoted = path.len() >= 2
+        && ((path.starts_with('\'') && path.ends_with('\''))
+            || (path.starts_with('"') && path.ends_with('"')));
+    if quoted {
+        path = &path[1..path.len() - 1];
+    }
+
+    let mut path = if let Some(rest) = path.strip_prefix("file://") {
+        let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+        let decoded = percent_decode(rest);
+        // file:///C:/dir → C:/dir
+        match decoded.strip_prefix('/') {
+            Some(drive) if style == PathStyle::Windows && has_drive_prefix(drive) => {
+                drive.to_string()
+            }
+            _ => decoded,
+        }
+    } else if style == PathStyle::Unix && !quoted {
+        unescape_backslashes(path)
+    } else {
+        path.to_string()
+    };
+
+    if let Some(home) = home {
+        if path == "~" {
+            path = home.to_string_lossy().into_owned();
+        } else if let Some(rest) = path.strip_prefix("~/").or_else(|| {
+            if style == PathStyle::Windows {
+                path.strip_prefix("~\\")
+            } else {
+                None
+            }
+        }) {
+            path = home.join(rest).to_string_lossy().into_owned();
+        }
+    }
+    path
+}
+
+/// 按空白切分输入，引号内和（Unix 下）反斜杠转义的空白不切分，返回每个词的字节范围
+fn split_path_tokens(input: &str, style: PathStyle) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (idx, ch) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match start {
+            None if ch.is_whitespace() => continue,
+            None => start = Some(idx),
+            Some(_) => {}
+        }
+        let token_start = start.unwrap_or(idx);
+        match quote {
+            Some(open) if ch == open => quote = None,
+            Some(_) => {}
+            None if (ch == '\'' || ch == '"') && matches!(&input[token_start..idx], "" | "@") => {
+                quote = Some(ch)
+            }
+            None if ch == '\\' && style == PathStyle::Unix => escaped = true,
+            None if ch.is_whitespace() => {
+                tokens.push((token_start, idx));
+                start = None;
+            }
+            None => {}
+        }
+    }
+    if let Some(start) = start {
+        tokens.push((start, input.len()));
+    }
+    tokens
+}
+
+/// 只处理拖放会产生的格式，普通文本中的相对路径不做转换
+fn looks_like_pasted_path(raw: &str) -> bool {
+    raw.starts_with(['\'', '"', '/', '~'])
+        || raw.starts_with("file://")
+        || raw.starts_with("\\\\")
+        || has_drive_prefix(raw)
+        || raw.contains("\\ ")
+}
+
+fn is_absolute_path(path: &str, style: PathStyle) -> bool {
+    match style {
+        PathStyle::Unix => path.starts_with('/'),
+        PathStyle::Windows => has_drive_prefix(path) || path.starts_with("\\\\"),
+    }
+}
+
+/// `C:\` 或 `C:/` 开头的 Windows 盘符路径
+fn has_drive_prefix(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/')
+}
+
+/// `My\ Project` → `My Project`
+fn unescape_backslashes(path: &str) -> String {
+    let mut output = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            if let Some(next) = chars.next() {
+                output.push(next);
+                continue;
+            }
+        }
+        output.push(ch);
+    }
+    output
+}
+
+/// 解码 URL 中的 `%XX`
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
 /// 判断是否是有效的文件引用
 fn is_valid_file_reference(path: &str) -> bool {
     // 包含路径分隔符
@@ -111,8 +368,8 @@ fn is_valid_file_reference(path: &str) -> bool {
 }
 
 /// 解析文件路径并读取内容
-pub fn resolve_and_read_file(path_str: &str) -> Result<FileReference> {
-    let path = resolve_file_path(path_str)?;
+pub fn resolve_and_read_file(path_str: &str, roots: &WorkspaceRoots) -> Result<FileReference> {
+    let path = resolve_file_path(path_str, roots)?;
 
     // 检查文件大小
     let metadata = fs::metadata(&path)?;
@@ -122,27 +379,33 @@ pub fn resolve_and_read_file(path_str: &str) -> Result<FileReference> {
     if size_bytes > 1024 * 1024 {
         // 1MB
         println!(
-            "{} 文件较大: {} ({} bytes)",
+            "{} {}",
             "⚠️".yellow(),
-            path.display(),
-            size_bytes
+            t!("cli.file.large", path = path.display(), size = size_bytes)
         );
     }
 
     // 读取文件内容
     let content = fs::read_to_string(&path)
-        .with_context(|| format!("无法读取文件: {}", path.display()))?;
+        .with_context(|| t!("cli.file.read_failed", path = path.display()))?;
 
     // 警告：空文件
     if content.trim().is_empty() {
-        println!("{} 文件为空: {}", "⚠️".yellow(), path.display());
+        println!(
+            "{} {}",
+            "⚠️".yellow(),
+            t!("cli.file.empty", path = path.display())
+        );
     }
 
     FileReference::new(format!("@{}", path_str), path, content)
 }
 
-/// 解析文件路径（支持相对路径和绝对路径）
-pub fn resolve_file_path(path_str: &str) -> Result<PathBuf> {
+/// 解析文件路径（支持相对路径、绝对路径和 `alias:相对路径`）
+pub fn resolve_file_path(path_str: &str, roots: &WorkspaceRoots) -> Result<PathBuf> {
+    if let Some(path) = roots.resolve_alias(path_str) {
+        return Ok(path);
+    }
     let path = PathBuf::from(path_str);
 
     // 如果是绝对路径，直接使用
@@ -151,8 +414,7 @@ pub fn resolve_file_path(path_str: &str) -> Result<PathBuf> {
     }
 
     // 否则，相对于当前工作目录
-    let current_dir = std::env::current_dir()
-        .context("无法获取当前工作目录")?;
+    let current_dir = std::env::current_dir().context(t!("cli.file.cwd_failed"))?;
 
     Ok(current_dir.join(path))
 }
@@ -173,7 +435,7 @@ mod tests {
     #[test]
     fn test_parse_file_references() {
         let input = "@src/main.rs 请帮我重构这个文件";
-        let (parsed, refs) = parse_file_references(input);
+        let (parsed, refs) = parse_file_references(input, &WorkspaceRoots::default());
         // 由于文件可能不存在，我们只检查解析逻辑
         assert!(!parsed.contains("@src/main.rs"));
     }
@@ -182,24 +444,176 @@ mod tests {
     fn test_parse_multiple_file_references() {
         // 使用存在的文件进行测试
         let input = "@Cargo.toml @src/cli/mod.rs 比较这两个文件";
-        let (parsed, refs) = parse_file_references(input);
+        let (parsed, refs) = parse_file_references(input, &WorkspaceRoots::default());
         // 检查成功的文件引用被处理
         // 注意：由于文件可能不存在，refs 可能是空的
         // 这个测试主要验证解析逻辑不会崩溃
     }
 
+    #[test]
+    fn test_normalize_pasted_path_unix() {
+        let home = Path::new("/home/me");
+        let cases = [
+            // macOS Terminal / iTerm2
+            (
+                "/Users/me/My\\ Project/file.rs",
+                "/Users/me/My Project/file.rs",
+            ),
+            ("/Users/me/a\\ \\(copy\\).rs", "/Users/me/a (copy).rs"),
+            // GNOME Terminal / Konsole
+            (
+                "'/home/me/My Project/file.rs'",
+                "/home/me/My Project/file.rs",
+            ),
+            (
+                "\"/home/me/My Project/file.rs\"",
+                "/home/me/My Project/file.rs",
+            ),
+            (
+                "file:///home/me/My%20Project/file.rs",
+                "/home/me/My Project/file.rs",
+            ),
+            (
+                "'file://localhost/home/me/caf%C3%A9.md'",
+                "/home/me/café.md",
+            ),
+            ("~/notes/todo.md", "/home/me/notes/todo.md"),
+            ("  /tmp/plain.rs ", "/tmp/plain.rs"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(
+                normalize_pasted_path(raw, PathStyle::Unix, Some(home)),
+                expected,
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_pasted_path_windows() {
+        let home = Path::new(r"C:\Users\me");
+        let cases = [
+            // Explorer 拖放到 Windows Terminal / PowerShell
+            (
+                r#""C:\Users\me\My Project\file.rs""#,
+                r"C:\Users\me\My Project\file.rs",
+            ),
+            (r"C:\Users\me\file.rs", r"C:\Users\me\file.rs"),
+            (
+                "'C:/Users/me/My Project/file.rs'",
+                "C:/Users/me/My Project/file.rs",
+            ),
+            (
+                "file:///C:/Users/me/My%20Project/file.rs",
+                "C:/Users/me/My Project/file.rs",
+            ),
+            (r"\\server\share\file.rs", r"\\server\share\file.rs"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(
+                normalize_pasted_path(raw, PathStyle::Windows, Some(home)),
+                expected,
+                "{raw}"
+            );
+        }
+        let expanded = normalize_pasted_path(r"~\notes.md", PathStyle::Windows, Some(home));
+        assert!(expanded.starts_with(r"C:\Users\me") && expanded.ends_with("notes.md"));
+    }
+
+    #[test]
+    fn test_normalize_pasted_paths_to_references() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cwd = temp_dir.path().join("repo");
+        fs::create_dir_all(cwd.join("My Project")).unwrap();
+        fs::write(cwd.join("My Project/file.rs"), "fn main() {}").unwrap();
+        fs::write(cwd.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("outside.md"), "notes").unwrap();
+        let base = cwd.display().to_string();
+        let normalize = |input: &str| normalize_pasted_paths_in(input, &cwd, None, PathStyle::Unix);
+
+        assert_eq!(
+            normalize(&format!("explain '{}/My Project/file.rs' please", base)),
+            "explain @\"My Project/file.rs\" please"
+        );
+        assert_eq!(
+            normalize(&format!("explain {}/My\\ Project/file.rs", base)),
+            "explain @\"My Project/file.rs\""
+        );
+        assert_eq!(
+            normalize(&format!("@'{}/main.rs' and file://{}/main.rs", base, base)),
+            "@main.rs and @main.rs"
+        );
+        let outside = temp_dir.path().join("outside.md").display().to_string();
+        assert_eq!(
+            normalize(&format!("read {}", outside)),
+            format!("read @{}", outside)
+        );
+
+        // 不存在的路径、普通引号文本和相对路径保持不变
+        for input in [
+            format!("'{}/missing.rs'", base),
+            "say 'hello world' to main.rs".to_string(),
+            "@main.rs is fine".to_string(),
+        ] {
+            assert_eq!(normalize(&input), input);
+        }
+    }
+
+    #[test]
+    fn test_parse_quoted_file_reference() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("with space.txt");
+        fs::write(&file, "hello").unwrap();
+
+        let input = format!("@\"{}\" summarize", file.display());
+        let (parsed, refs) = parse_file_references(&input, &WorkspaceRoots::default());
+        assert_eq!(parsed, "summarize");
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].file_path, file);
+    }
+
+    #[test]
+    fn test_image_references_stay_in_input() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let shot = temp_dir.path().join("error.png");
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 2, 128, 0, 0, 1, 224]);
+        fs::write(&shot, &png).unwrap();
+        let notes = temp_dir.path().join("notes.txt");
+        fs::write(&notes, "hello").unwrap();
+
+        let input = format!("@{} @{} why?", shot.display(), notes.display());
+        let (parsed, refs) = parse_file_references(&input, &WorkspaceRoots::default());
+        assert_eq!(parsed, format!("@{} why?", shot.display()));
+        assert_eq!(refs.len(), 1);
+
+        let images = parse_image_references(&input, &WorkspaceRoots::default());
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].path, shot.to_string_lossy());
+        assert_eq!((images[0].width, images[0].height), (Some(640), Some(480)));
+    }
+
     #[test]
     fn test_resolve_file_path() {
         // 测试相对路径解析
-        let result = resolve_file_path("Cargo.toml");
+        let result = resolve_file_path("Cargo.toml", &WorkspaceRoots::default());
         assert!(result.is_ok());
 
         let path = result.unwrap();
         assert!(path.ends_with("Cargo.toml"));
+
+        // 额外工作区根目录中的文件
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let roots = WorkspaceRoots::new(&std::env::current_dir().unwrap());
+        let api = roots.add(temp_dir.path()).unwrap();
+        let path = resolve_file_path(&format!("{}:src/main.rs", api.alias), &roots).unwrap();
+        assert_eq!(path, api.path.join("src/main.rs"));
     }
 
     #[test]
     fn test_file_reference_display_info() {
+        crate::i18n::init(crate::i18n::Locale::En);
+
         // 创建一个模拟的文件引用
         let file_ref = FileReference {
             raw_reference: "@test.txt".to_string(),
@@ -0,0 +1,441 @@
+//! 会话内搜索（`/find`）
+//!
+//! 在当前会话的消息中查找文本，结果的消息序号与 `/history`、`/pin`、`/rewind` 一致。
+//! 工具调用的参数和工具结果按完整内容搜索（`/history` 中只显示摘要）。
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use regex::{Regex, RegexBuilder};
+
+use crate::context::{ContentBlock, SerializableMessage};
+
+/// 每页显示的结果数
+pub const PAGE_SIZE: usize = 10;
+
+/// 匹配位置之前保留的字符数
+const CONTEXT_BEFORE: usize = 40;
+
+/// 片段的最大字符数
+const SNIPPET_CHARS: usize = 120;
+
+/// `/find` 的参数
+#[derive(Debug)]
+pub struct FindQuery {
+    /// 搜索模式（不区分大小写），只按工具过滤时为 `None`
+    pattern: Option<Regex>,
+    /// 只搜索该工具的结果
+    tool: Option<String>,
+    /// 页码（从 1 开始）
+    pub page: usize,
+}
+
+impl FindQuery {
+    /// 解析 `[--regex] [--tool <name>] [--page <n>] <query>`
+    pub fn parse(args: &str) -> Result<Self> {
+        let mut regex = false;
+        let mut tool = None;
+        let mut page = 1;
+        let mut words = Vec::new();
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "--regex" => regex = true,
+                "--tool" => match tokens.next() {
+                    Some(name) => tool = Some(name.to_lowercase()),
+                    None => bail!("--tool requires a tool name"),
+                },
+                "--page" => match tokens.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0 => page = n,
+                    _ => bail!("--page requires a page number"),
+                },
+                word => words.push(word),
+            }
+        }
+
+        let query = words.join(" ");
+        let pattern = if query.is_empty() {
+            if tool.is_none() {
+                bail!("missing search text");
+            }
+            None
+        } else {
+            let source = if regex { query } else { regex::escape(&query) };
+            let pattern = RegexBuilder::new(&source)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| anyhow::anyhow!("invalid regex: {}", e))?;
+            Some(pattern)
+        };
+        Ok(Self {
+            pattern,
+            tool,
+            page,
+        })
+    }
+}
+
+/// 翻到第 `page` 页的 `/find` 命令（替换原有的 `--page`）
+pub fn page_command(args: &str, page: usize) -> String {
+    let mut command = String::from("/find");
+    let mut tokens = args.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "--page" {
+            tokens.next();
+            continue;
+        }
+        command.push(' ');
+        command.push_str(token);
+    }
+    format!("{} --page {}", command, page)
+}
+
+/// 匹配所在内容的类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchKind {
+    User,
+    Assistant,
+    /// 工具调用的参数
+    ToolCall(String),
+    /// 工具结果
+    ToolResult(String),
+}
+
+impl std::fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchKind::User => write!(f, "user"),
+            MatchKind::Assistant => write!(f, "assistant"),
+            MatchKind::ToolCall(name) => write!(f, "tool call {}", name),
+            MatchKind::ToolResult(name) => write!(f, "tool result {}", name),
+        }
+    }
+}
+
+/// 一条搜索结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindMatch {
+    /// 消息序号（从 1 开始，与 `/history` 一致）
+    pub number: usize,
+    pub kind: MatchKind,
+    /// 匹配位置附近的单行片段
+    pub snippet: String,
+    /// 片段中匹配文本的字节范围
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl FindMatch {
+    /// 用 `mark` 包裹片段中的匹配文本
+    pub fn highlighted(&self, mark: impl Fn(&str) -> String) -> String {
+        let mut output = String::new();
+        let mut last = 0;
+        for range in &self.highlights {
+            output.push_str(&self.snippet[last..range.start]);
+            output.push_str(&mark(&self.snippet[range.clone()]));
+            last = range.end;
+        }
+        output.push_str(&self.snippet[last..]);
+        output
+    }
+}
+
+/// 在消息中搜索，每个匹配的文本块或工具调用/结果产生一条结果
+pub fn search(messages: &[SerializableMessage], query: &FindQuery) -> Vec<FindMatch> {
+    // 工具结果只记录调用 ID，工具名从对应的工具调用中查找
+    let tool_names: HashMap<&str, &str> = messages
+        .iter()
+        .flat_map(|message| &message.blocks)
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, .. } => Some((id.as_str(), name.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    let mut matches = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let role = if message.role == "assistant" {
+            MatchKind::Assistant
+        } else {
+            MatchKind::User
+        };
+        let segments: Vec<(MatchKind, String)> = if message.blocks.is_empty() {
+            vec![(role, message.content.clone())]
+        } else {
+            message
+                .blocks
+                .iter()
+                .filter_map(|block| segment(block, &role, &tool_names))
+                .collect()
+        };
+
+        for (kind, text) in segments {
+            if let Some(tool) = &query.tool {
+                match &kind {
+                    MatchKind::ToolResult(name) if name.to_lowercase() == *tool => {}
+                    _ => continue,
+                }
+            }
+            let ranges: Vec<Range<usize>> = match &query.pattern {
+                Some(pattern) => pattern
+                    .find_iter(&text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| m.range())
+                    .collect(),
+                // 只按工具过滤：从开头截取片段，不高亮
+                None => vec![Range { start: 0, end: 0 }],
+            };
+            if ranges.is_empty() {
+                continue;
+            }
+            let (snippet, highlights) = snippet(&text, &ranges);
+            matches.push(FindMatch {
+                number: index + 1,
+                kind,
+                snippet,
+                highlights,
+            });
+        }
+    }
+    matches
+}
+
+/// 内容块中可搜索的文本（图片、推理内容等不参与搜索）
+fn segment(
+    block: &ContentBlock,
+    role: &MatchKind,
+    tool_names: &HashMap<&str, &str>,
+) -> Option<(MatchKind, String)> {
+    match block {
+        ContentBlock::Text { text } => Some((role.clone(), text.clone())),
+        ContentBlock::ToolUse { name, input, .. } => {
+            Some((MatchKind::ToolCall(name.clone()), input.to_string()))
+        }
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            ..
+        } => {
+            let name = tool_names
+                .get(tool_use_id.as_str())
+                .copied()
+                .unwrap_or("unknown");
+            let text = content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some((MatchKind::ToolResult(name.to_string()), text))
+        }
+        _ => None,
+    }
+}
+
+/// 截取第一个匹配附近的文本，换行替换为空格，返回片段和其中的匹配范围
+fn snippet(text: &str, ranges: &[Range<usize>]) -> (String, Vec<Range<usize>>) {
+    let first = &ranges[0];
+    let start = text[..first.start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_BEFORE - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let end = text[start..]
+        .char_indices()
+        .nth(SNIPPET_CHARS)
+        .map(|(i, _)| start + i)
+        .unwrap_or(text.len())
+        .max(first.end);
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let mut snippet = prefix.to_string();
+    // 替换的字符都是单字节，匹配范围不受影响
+    snippet.extend(text[start..end].chars().map(|c| match c {
+        '\n' | '\r' | '\t' => ' ',
+        c => c,
+    }));
+    if end < text.len() {
+        snippet.push('…');
+    }
+
+    let highlights = ranges
+        .iter()
+        .filter(|range| range.start < range.end && range.start < end && range.end > start)
+        .map(|range| {
+            let from = range.start.max(start) - start + prefix.len();
+            let to = range.end.min(end) - start + prefix.len();
+            from..to
+        })
+        .collect();
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(role: &str, content: &str) -> SerializableMessage {
+        SerializableMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            blocks: Vec::new(),
+            tags: Vec::new(),
+            pinned: false,
+        }
+    }
+
+    fn blocks(role: &str, blocks: Vec<ContentBlock>) -> SerializableMessage {
+        SerializableMessage {
+            blocks,
+            ..text(role, "")
+        }
+    }
+
+    fn tool_call(id: &str, name: &str, input: serde_json::Value) -> ContentBlock {
+        ContentBlock::ToolUse {
+            id: id.to_string(),
+            call_id: None,
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    fn tool_result(id: &str, output: &str) -> ContentBlock {
+        ContentBlock::ToolResult {
+            tool_use_id: id.to_string(),
+            call_id: None,
+            content: vec![ContentBlock::Text {
+                text: output.to_string(),
+            }],
+            is_error: false,
+        }
+    }
+
+    fn conversation() -> Vec<SerializableMessage> {
+        vec![
+            text("user", "Where is the Tool trait defined?"),
+            blocks(
+                "assistant",
+                vec![
+                    ContentBlock::Text {
+                        text: "Let me search.".to_string(),
+                    },
+                    tool_call("t1", "grep_search", json!({ "pattern": "trait Tool" })),
+                ],
+            ),
+            blocks(
+                "user",
+                vec![tool_result("t1", "src/tools/mod.rs:12: pub trait Tool {")],
+            ),
+            blocks(
+                "assistant",
+                vec![tool_call(
+                    "t2",
+                    "shell_execute",
+                    json!({ "command": "cargo test" }),
+                )],
+            ),
+            blocks(
+                "user",
+                vec![tool_result("t2", "running 3 tests\ntest result: ok")],
+            ),
+            text("assistant", "The TOOL trait lives in src/tools/mod.rs."),
+        ]
+    }
+
+    fn numbers(matches: &[FindMatch]) -> Vec<usize> {
+        matches.iter().map(|m| m.number).collect()
+    }
+
+    #[test]
+    fn test_substring_search_is_case_insensitive() {
+        let query = FindQuery::parse("tool trait").unwrap();
+        let matches = search(&conversation(), &query);
+        assert_eq!(numbers(&matches), vec![1, 6]);
+        assert_eq!(matches[1].kind, MatchKind::Assistant);
+        assert_eq!(
+            matches[1].highlighted(|s| format!("[{}]", s)),
+            "The [TOOL trait] lives in src/tools/mod.rs."
+        );
+
+        // 工具调用参数和工具结果按完整内容搜索
+        let query = FindQuery::parse("trait tool").unwrap();
+        let matches = search(&conversation(), &query);
+        assert_eq!(numbers(&matches), vec![2, 3]);
+        assert_eq!(
+            matches[0].kind,
+            MatchKind::ToolCall("grep_search".to_string())
+        );
+        assert_eq!(
+            matches[1].kind,
+            MatchKind::ToolResult("grep_search".to_string())
+        );
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let query = FindQuery::parse("--regex running \\d+ tests").unwrap();
+        let matches = search(&conversation(), &query);
+        assert_eq!(numbers(&matches), vec![5]);
+        // 换行在片段中替换为空格
+        assert_eq!(
+            matches[0].highlighted(|s| format!("[{}]", s)),
+            "[running 3 tests] test result: ok"
+        );
+
+        assert!(FindQuery::parse("--regex (unclosed").is_err());
+        // 不加 --regex 时按字面搜索
+        let query = FindQuery::parse("(unclosed").unwrap();
+        assert!(search(&conversation(), &query).is_empty());
+    }
+
+    #[test]
+    fn test_tool_filter() {
+        let query = FindQuery::parse("--tool shell_execute test").unwrap();
+        let matches = search(&conversation(), &query);
+        // 只有工具结果，不包括工具调用参数和普通消息
+        assert_eq!(numbers(&matches), vec![5]);
+        assert_eq!(
+            matches[0].kind,
+            MatchKind::ToolResult("shell_execute".to_string())
+        );
+        assert_eq!(matches[0].highlights.len(), 2);
+
+        // 只按工具过滤时列出该工具的全部结果
+        let query = FindQuery::parse("--tool GREP_SEARCH").unwrap();
+        let matches = search(&conversation(), &query);
+        assert_eq!(numbers(&matches), vec![3]);
+        assert!(matches[0].highlights.is_empty());
+
+        assert!(FindQuery::parse("").is_err());
+        assert!(FindQuery::parse("--tool").is_err());
+    }
+
+    #[test]
+    fn test_snippet_is_trimmed_around_first_match() {
+        let long = format!("{}needle{}", "a".repeat(100), "b".repeat(200));
+        let messages = vec![text("user", &long)];
+        let query = FindQuery::parse("NEEDLE").unwrap();
+        let matches = search(&messages, &query);
+
+        let snippet = matches[0].highlighted(|s| format!("[{}]", s));
+        assert!(snippet.starts_with(&format!("…{}[needle]b", "a".repeat(CONTEXT_BEFORE))));
+        assert!(snippet.ends_with("b…"));
+        assert_eq!(matches[0].snippet.chars().count(), SNIPPET_CHARS + 2);
+    }
+
+    #[test]
+    fn test_page_argument() {
+        assert_eq!(FindQuery::parse("foo --page 3").unwrap().page, 3);
+        assert_eq!(FindQuery::parse("foo").unwrap().page, 1);
+        assert!(FindQuery::parse("foo --page 0").is_err());
+        assert_eq!(
+            page_command(" --tool shell_execute --page 2 cargo", 3),
+            "/find --tool shell_execute cargo --page 3"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! 斜杠命令的帮助
+//!
+//! 内置命令的说明、用法、选项和示例集中在 [`builtin_commands`]，补全列表和 `/help` 都从这里取。
+//! `/help` 按内置命令、自定义命令、技能和别名分组列出全部命令；`/help <命令>` 显示单个命令的详情页
+//! （Markdown，示例以代码块显示）；找不到命令时推荐拼写相近的命令。
+
+use crate::skill::{Skill, SkillSource};
+
+/// 命令别名：（别名，对应的命令）
+pub const ALIASES: &[(&str, &str)] = &[("/exit", "/quit")];
+
+/// 一个命令的帮助信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandHelp {
+    /// 命令名，如 `/restore`
+    pub name: String,
+    pub description: String,
+    usage: Option<String>,
+    /// 选项及说明，如 `--force`
+    flags: Vec<(String, String)>,
+    /// 位置参数及说明（技能的参数）
+    arguments: Vec<(String, String)>,
+    examples: Vec<String>,
+}
+
+impl CommandHelp {
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            usage: None,
+            flags: Vec::new(),
+            arguments: Vec::new(),
+            examples: Vec::new(),
+        }
+    }
+
+    pub fn with_usage(mut self, usage: impl Into<String>) -> Self {
+        self.usage = Some(usage.into());
+        self
+    }
+
+    pub fn with_flag(mut self, flag: impl Into<String>, description: impl Into<String>) -> Self {
+        self.flags.push((flag.into(), description.into()));
+        self
+    }
+
+    pub fn with_argument(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.arguments.push((name.into(), description.into()));
+        self
+    }
+
+    pub fn with_examples(mut self, examples: &[&str]) -> Self {
+        self.examples = examples.iter().map(|e| e.to_string()).collect();
+        self
+    }
+
+    /// 用法，未单独设置时就是命令名
+    pub fn usage(&self) -> &str {
+        self.usage.as_deref().unwrap_or(&self.name)
+    }
+
+    /// 示例，默认没有
+    pub fn examples(&self) -> &[String] {
+        &self.examples
+    }
+
+    /// 技能的帮助：参数按模板中的顺序列出，可选参数加方括号
+    pub fn from_skill(skill: &Skill) -> Self {
+        let mut usage = format!("/{}", skill.name);
+        let mut help = Self::new(format!("/{}", skill.name), skill.description.clone());
+        for arg in &skill.args {
+            if arg.required {
+                usage.push_str(&format!(" <{}>", arg.name));
+            } else {
+                usage.push_str(&format!(" [{}]", arg.name));
+            }
+            let description = match &arg.default {
+                Some(default) => t!(
+                    "cli.help.detail.default",
+                    description = arg.description,
+                    default = default
+                ),
+                None => arg.description.clone(),
+            };
+            help = help.with_argument(&arg.name, description);
+        }
+        help.with_usage(usage)
+    }
+
+    /// 详情页的 Markdown
+    pub fn detail_markdown(&self) -> String {
+        let mut page = format!("# {}\n\n{}\n\n", self.name, self.description);
+        page.push_str(&format!(
+            "**{}** `{}`\n\n",
+            t!("cli.help.detail.usage"),
+            self.usage()
+        ));
+        for (title, items) in [
+            (t!("cli.help.detail.arguments"), &self.arguments),
+            (t!("cli.help.detail.flags"), &self.flags),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            page.push_str(&format!("**{}**\n\n", title));
+            for (name, description) in items {
+                page.push_str(&format!("* `{}` {}\n", name, description));
+            }
+            page.push('\n');
+        }
+        if !self.examples.is_empty() {
+            page.push_str(&format!("**{}**\n\n```\n", t!("cli.help.detail.examples")));
+            for example in &self.examples {
+                page.push_str(example);
+                page.push('\n');
+            }
+            page.push_str("```\n\n");
+        }
+        let aliases: Vec<String> = ALIASES
+            .iter()
+            .filter(|(_, command)| *command == self.name)
+            .map(|(alias, _)| format!("`{}`", alias))
+            .collect();
+        if !aliases.is_empty() {
+            page.push_str(&format!(
+                "**{}** {}\n",
+                t!("cli.help.detail.aliases"),
+                aliases.join(", ")
+            ));
+        }
+        page
+    }
+}
+
+/// 全部内置命令，按 `/help` 中的显示顺序
+pub fn builtin_commands() -> Vec<CommandHelp> {
+    vec![
+        CommandHelp::new("/quit", t!("commands.quit")),
+        CommandHelp::new("/clear", t!("commands.clear")),
+        CommandHelp::new("/new", t!("commands.new"))
+            .with_usage("/new [template]")
+            .with_examples(&["/new", "/new debugging"]),
+        CommandHelp::new("/config", t!("commands.config"))
+            .with_usage("/config [show|edit|reload|validate]")
+            .with_examples(&["/config show", "/config validate"]),
+        CommandHelp::new("/history", t!("commands.history"))
+            .with_usage("/history [clear]")
+            .with_examples(&["/history", "/history clear"]),
+        CommandHelp::new("/pin", t!("commands.pin"))
+            .with_usage("/pin <n>")
+            .with_examples(&["/pin 3"]),
+        CommandHelp::new("/unpin", t!("commands.unpin"))
+            .with_usage("/unpin <n>")
+            .with_examples(&["/unpin 3"]),
+        CommandHelp::new("/rewind", t!("commands.rewind"))
+            .with_usage("/rewind [n]")
+            .with_examples(&["/rewind", "/rewind 2"]),
+        CommandHelp::new("/checkpoint", t!("commands.checkpoint"))
+            .with_usage("/checkpoint [name|list|delete <name>]")
+            .with_examples(&[
+                "/checkpoint before-refactor",
+                "/checkpoint list",
+                "/checkpoint delete before-refactor",
+            ]),
+        CommandHelp::new("/restore", t!("commands.restore"))
+            .with_usage("/restore <name> [--force]")
+            .with_flag("--force", t!("cli.help.flag.restore_force"))
+            .with_examples(&[
+                "/restore before-refactor",
+                "/restore before-refactor --force",
+            ]),
+        CommandHelp::new("/retry", t!("commands.retry")).with_usage("/retry continue"),
+        CommandHelp::new("/mode", t!("commands.mode"))
+            .with_usage("/mode [normal|fast|plan] [--save]")
+            .with_examples(&["/mode", "/mode plan", "/mode fast --save"]),
+        CommandHelp::new("/statusbar", t!("commands.statusbar"))
+            .with_usage("/statusbar [on|off]")
+            .with_examples(&["/statusbar off"]),
+        CommandHelp::new("/toggle-tools", t!("commands.toggle_tools")),
+        CommandHelp::new("/output-style", t!("commands.output_style"))
+            .with_usage("/output-style [concise|detailed|explanatory] [auto|en|zh] [--save]")
+            .with_flag("--save", t!("cli.help.flag.output_style_save"))
+            .with_examples(&[
+                "/output-style",
+                "/output-style concise",
+                "/output-style explanatory zh --save",
+            ]),
+        CommandHelp::new("/load", t!("commands.load"))
+            .with_usage("/load <session_id>")
+            .with_examples(&["/load abc123"]),
+        CommandHelp::new("/sessions", t!("commands.sessions"))
+            .with_usage("/sessions [#tag]")
+            .with_examples(&["/sessions", "/sessions #bugfix"]),
+        CommandHelp::new("/delete", t!("commands.delete"))
+            .with_usage("/delete <session_id>")
+            .with_examples(&["/delete abc123"]),
+        CommandHelp::new("/templates", t!("commands.templates")).with_usage("/templates [list]"),
+        CommandHelp::new("/agent", t!("commands.agent"))
+            .with_usage("/agent [list|capabilities]")
+            .with_examples(&["/agent list", "/agent capabilities"]),
+        CommandHelp::new("/tasks", t!("commands.tasks"))
+            .with_usage("/tasks [list [filters]|show <id>|cancel <id>]")
+            .with_examples(&["/tasks", "/tasks show 1a2b", "/tasks cancel 1a2b"]),
+        CommandHelp::new("/skills", t!("commands.skills"))
+            .with_usage("/skills [list|show <name>]")
+            .with_examples(&["/skills list", "/skills show commit"]),
+        CommandHelp::new("/refresh-files", t!("commands.refresh_files")),
+        CommandHelp::new("/add-dir", t!("commands.add_dir"))
+            .with_usage("/add-dir [path]")
+            .with_examples(&["/add-dir", "/add-dir ../shared"]),
+        CommandHelp::new("/permissions", t!("commands.permissions"))
+            .with_usage("/permissions [export [file]|import <file>]")
+            .with_examples(&[
+                "/permissions",
+                "/permissions export permissions.toml",
+                "/permissions import permissions.toml",
+            ]),
+        CommandHelp::new("/save-notes", t!("commands.save_notes")),
+        CommandHelp::new("/paste", t!("commands.paste")).with_usage("/paste raw"),
+        CommandHelp::new("/expand", t!("commands.expand")).with_usage("/expand all"),
+        CommandHelp::new("/undo", t!("commands.undo")),
+        CommandHelp::new("!", t!("commands.direct_shell"))
+            .with_usage("!<command> or !!")
+            .with_examples(&["!git status", "!!"]),
+        CommandHelp::new("/status", t!("commands.status")),
+        CommandHelp::new("/context", t!("commands.context")),
+        CommandHelp::new("/doctor", t!("commands.doctor")),
+        CommandHelp::new("/update", t!("commands.update")),
+        CommandHelp::new("/usage", t!("commands.usage"))
+            .with_usage("/usage [--by day|project|model|session]")
+            .with_flag("--by", t!("cli.help.flag.usage_by"))
+            .with_examples(&["/usage", "/usage --by model"]),
+        CommandHelp::new("/cost", t!("commands.cost")),
+        CommandHelp::new("/find", t!("commands.find"))
+            .with_usage("/find [--regex] [--tool <name>] <text>")
+            .with_flag("--regex", t!("cli.help.flag.find_regex"))
+            .with_flag("--tool", t!("cli.help.flag.find_tool"))
+            .with_examples(&["/find timeout", "/find --tool shell_execute cargo test"]),
+        CommandHelp::new("/tools", t!("commands.tools"))
+            .with_usage("/tools [number]")
+            .with_examples(&["/tools", "/tools 3"]),
+        CommandHelp::new("/cache", t!("commands.cache"))
+            .with_usage("/cache [stats|clear [web]]")
+            .with_examples(&["/cache stats", "/cache clear web"]),
+        CommandHelp::new("/export", t!("commands.export"))
+            .with_usage("/export [#tag ...] [path]")
+            .with_examples(&["/export", "/export #bugfix notes.md"]),
+        CommandHelp::new("/mcp", t!("commands.mcp"))
+            .with_usage("/mcp [list|enable <name>|disable <name>]")
+            .with_examples(&["/mcp list", "/mcp disable github"]),
+        CommandHelp::new("/workflow", t!("commands.workflow"))
+            .with_usage("/workflow [status|on|off]")
+            .with_examples(&["/workflow status", "/workflow off"]),
+        CommandHelp::new("/help", t!("commands.help"))
+            .with_usage("/help [command]")
+            .with_examples(&["/help", "/help restore"]),
+    ]
+}
+
+/// 技能中用户自己定义的（全局或项目目录下的）算作自定义命令
+pub fn is_custom_command(skill: &Skill) -> bool {
+    skill.source != SkillSource::BuiltIn
+}
+
+/// 规范化 `/help` 的参数：补上开头的 `/`，并把别名换成对应的命令
+pub fn normalize_name(name: &str) -> String {
+    let name = if name.starts_with('/') || name.starts_with('!') {
+        name.to_string()
+    } else {
+        format!("/{}", name)
+    };
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, command)| command.to_string())
+        .unwrap_or(name)
+}
+
+/// 在内置命令和技能中查找命令
+pub fn find_command(name: &str, commands: &[CommandHelp]) -> Option<CommandHelp> {
+    let name = normalize_name(name);
+    commands.iter().find(|c| c.name == name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::suggest::suggest_commands;
+
+    #[test]
+    fn test_detail_page_has_usage_flags_and_examples() {
+        let commands = builtin_commands();
+        let restore = find_command("restore", &commands).unwrap();
+        let page = restore.detail_markdown();
+        assert!(page.starts_with("# /restore\n"));
+        assert!(page.contains("`/restore <name> [--force]`"));
+        assert!(page.contains("* `--force` "));
+        assert!(page.contains("```\n/restore before-refactor\n"));
+
+        // 没有单独设置用法时用命令名，别名指向同一页
+        let quit = find_command("/exit", &commands).unwrap();
+        assert_eq!(quit.usage(), "/quit");
+        assert!(quit.examples().is_empty());
+        assert!(quit.detail_markdown().contains("`/exit`"));
+        assert!(!quit.detail_markdown().contains("```"));
+    }
+
+    #[test]
+    fn test_unknown_command_gets_suggestions() {
+        let commands = builtin_commands();
+        assert_eq!(find_command("chekpoint", &commands), None);
+        let names = commands.iter().map(|c| c.name.as_str());
+        assert_eq!(
+            suggest_commands(&normalize_name("chekpoint"), names),
+            vec!["/checkpoint"]
+        );
+    }
+}
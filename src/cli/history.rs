@@ -0,0 +1,226 @@
+//! 输入历史（上下方向键翻阅的编辑器历史，与对话历史无关）
+//!
+//! 历史写入 `~/.oxide/history.txt`，写入前过滤：空行、与上一条重复的输入、
+//! 匹配 `[editor] history_exclude` 的输入，以及包含明显密钥的斜杠命令。
+//! 超过 `history_size` 条时淘汰最早的记录。
+
+use std::path::PathBuf;
+
+use reedline::{
+    FileBackedHistory, History, HistoryItem, HistoryItemId, HistorySessionId, ReedlineError,
+    ReedlineErrorVariants, SearchFilter, SearchQuery,
+};
+use regex::Regex;
+
+use crate::config::{ConfigLoader, EditorConfig};
+use crate::tools::redactor;
+
+/// 输入历史文件（`~/.oxide/history.txt`）
+pub fn history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".oxide").join("history.txt"))
+}
+
+/// 决定一条输入是否写入历史
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    exclude: Vec<Regex>,
+}
+
+impl HistoryFilter {
+    /// 编译排除规则，无效的正则记录警告后忽略
+    pub fn new(patterns: &[String]) -> Self {
+        let exclude = patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    tracing::warn!(
+                        "ignoring invalid history_exclude pattern {:?}: {}",
+                        pattern,
+                        e
+                    );
+                    None
+                }
+            })
+            .collect();
+        Self { exclude }
+    }
+
+    pub fn should_store(&self, line: &str, previous: Option<&str>) -> bool {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || previous.map(str::trim) == Some(trimmed) {
+            return false;
+        }
+        if self.exclude.iter().any(|regex| regex.is_match(line)) {
+            return false;
+        }
+        // 斜杠命令常带令牌参数（如 /config set api_key=...），命中密钥规则时不保存
+        if trimmed.starts_with('/') && !redactor::redact(trimmed).redactions.is_empty() {
+            return false;
+        }
+        true
+    }
+}
+
+/// 带过滤规则的文件历史
+pub struct FilteredHistory {
+    inner: FileBackedHistory,
+    filter: HistoryFilter,
+}
+
+impl FilteredHistory {
+    pub fn new(inner: FileBackedHistory, filter: HistoryFilter) -> Self {
+        Self { inner, filter }
+    }
+
+    /// 按 `[editor]` 配置打开 `~/.oxide/history.txt`
+    pub fn open_default() -> Option<Self> {
+        let config: EditorConfig = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.editor)
+            .unwrap_or_default();
+        let path = history_path()?;
+        let inner = FileBackedHistory::with_file(config.history_size, path)
+            .map_err(|e| tracing::warn!("failed to open input history: {}", e))
+            .ok()?;
+        Some(Self::new(
+            inner,
+            HistoryFilter::new(&config.history_exclude),
+        ))
+    }
+
+    fn last_entry(&self) -> Option<String> {
+        self.inner
+            .search(SearchQuery::last_with_search(SearchFilter::anything(
+                self.inner.session(),
+            )))
+            .ok()?
+            .pop()
+            .map(|item| item.command_line)
+    }
+}
+
+impl History for FilteredHistory {
+    fn save(&mut self, h: HistoryItem) -> reedline::Result<HistoryItem> {
+        let previous = self.last_entry();
+        if h.id.is_none()
+            && !self
+                .filter
+                .should_store(&h.command_line, previous.as_deref())
+        {
+            return Ok(h);
+        }
+        self.inner.save(h)
+    }
+
+    fn load(&self, id: HistoryItemId) -> reedline::Result<HistoryItem> {
+        self.inner.load(id)
+    }
+
+    fn count(&self, query: SearchQuery) -> reedline::Result<i64> {
+        self.inner.count(query)
+    }
+
+    fn search(&self, query: SearchQuery) -> reedline::Result<Vec<HistoryItem>> {
+        self.inner.search(query)
+    }
+
+    fn update(
+        &mut self,
+        id: HistoryItemId,
+        updater: &dyn Fn(HistoryItem) -> HistoryItem,
+    ) -> reedline::Result<()> {
+        self.inner.update(id, updater)
+    }
+
+    /// 清空内存中的历史并删除历史文件（文件不存在不算错误）
+    fn clear(&mut self) -> reedline::Result<()> {
+        match self.inner.clear() {
+            Err(ReedlineError(ReedlineErrorVariants::IOError(e)))
+                if e.kind() == std::io::ErrorKind::NotFound =>
+            {
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn delete(&mut self, h: HistoryItemId) -> reedline::Result<()> {
+        self.inner.delete(h)
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        self.inner.sync()
+    }
+
+    fn session(&self) -> Option<HistorySessionId> {
+        self.inner.session()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reedline::SearchDirection;
+
+    fn entries(history: &FilteredHistory) -> Vec<String> {
+        history
+            .search(SearchQuery::everything(SearchDirection::Forward, None))
+            .unwrap()
+            .into_iter()
+            .map(|item| item.command_line)
+            .collect()
+    }
+
+    fn save(history: &mut FilteredHistory, line: &str) {
+        history.save(HistoryItem::from_command_line(line)).unwrap();
+    }
+
+    #[test]
+    fn test_eviction_drops_oldest_first() {
+        let mut history =
+            FilteredHistory::new(FileBackedHistory::new(3).unwrap(), HistoryFilter::default());
+        for line in ["first", "second", "second", "third", "fourth"] {
+            save(&mut history, line);
+        }
+        assert_eq!(entries(&history), vec!["second", "third", "fourth"]);
+    }
+
+    #[test]
+    fn test_exclusion_filter() {
+        let filter = HistoryFilter::new(&[
+            r"ghp_[A-Za-z0-9]{10,}".to_string(),
+            "^export ".to_string(),
+            "(unclosed".to_string(),
+        ]);
+        let mut history = FilteredHistory::new(FileBackedHistory::new(100).unwrap(), filter);
+        for line in [
+            "explain src/main.rs",
+            "use ghp_abcdefghij1234567890 to push",
+            "export OPENAI_KEY=1",
+            "   ",
+            "explain src/main.rs",
+            "/config set api_key=\"sk-ant-REDACTED\"",
+            "/sessions",
+            "explain src/main.rs",
+        ] {
+            save(&mut history, line);
+        }
+        assert_eq!(
+            entries(&history),
+            vec!["explain src/main.rs", "/sessions", "explain src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_clear_without_file() {
+        let mut history = FilteredHistory::new(
+            FileBackedHistory::new(10).unwrap(),
+            HistoryFilter::default(),
+        );
+        save(&mut history, "hello");
+        history.clear().unwrap();
+        assert!(entries(&history).is_empty());
+    }
+}
@@ -0,0 +1,449 @@
+//! 终端图片预览
+//!
+//! 按环境变量检测终端支持的图片协议（kitty graphics protocol、iTerm2 inline images、sixel），
+//! 支持时把图片等比缩小到 `[render] image_max_cols` × `image_max_rows` 个字符格内联显示；
+//! 终端不支持、关闭预览、解码失败或未启用 `images` feature 时显示一行占位：格式、尺寸和文件路径。
+
+use colored::*;
+use std::io::{self, IsTerminal};
+
+use crate::config::{ImagePreviewMode, RenderConfig};
+use crate::tools::image::ImageInfo;
+
+/// 终端复用器：图片转义序列需要 passthrough 才能到达外层终端，直接输出会显示成乱码
+const MULTIPLEXER_VARS: &[&str] = &["TMUX", "STY"];
+
+/// 支持 sixel 的终端（按 `TERM`）
+const SIXEL_TERMS: &[&str] = &["foot", "foot-extra", "mlterm", "yaft-256color", "contour"];
+
+/// 终端图片协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+}
+
+impl ImageProtocol {
+    /// 按环境变量检测当前终端支持的协议（`env` 读取环境变量，测试时可以替换）
+    pub fn detect(env: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let var = |name: &str| env(name).filter(|value| !value.is_empty());
+        if MULTIPLEXER_VARS.iter().any(|name| var(name).is_some()) {
+            return None;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || program == "ghostty"
+        {
+            Some(Self::Kitty)
+        } else if program == "iTerm.app"
+            || program == "WezTerm"
+            || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+        {
+            Some(Self::Iterm2)
+        } else if term.contains("sixel") || SIXEL_TERMS.contains(&term.as_str()) {
+            Some(Self::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// 图片预览设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImagePreview {
+    /// 使用的协议，`None` 表示只显示占位行
+    #[cfg_attr(not(feature = "images"), allow(dead_code))]
+    protocol: Option<ImageProtocol>,
+    max_cols: u16,
+    max_rows: u16,
+}
+
+impl Default for ImagePreview {
+    fn default() -> Self {
+        Self::from_config(&RenderConfig {
+            images: ImagePreviewMode::Off,
+            ..RenderConfig::default()
+        })
+    }
+}
+
+impl ImagePreview {
+    /// 按 `[render]` 配置创建；`auto` 时检测当前终端，stdout 不是终端时只显示占位行
+    pub fn from_config(config: &RenderConfig) -> Self {
+        let protocol = match config.images {
+            ImagePreviewMode::Auto if io::stdout().is_terminal() => {
+                ImageProtocol::detect(|name| std::env::var(name).ok())
+            }
+            ImagePreviewMode::Auto | ImagePreviewMode::Off => None,
+            ImagePreviewMode::Kitty => Some(ImageProtocol::Kitty),
+            ImagePreviewMode::Iterm2 => Some(ImageProtocol::Iterm2),
+            ImagePreviewMode::Sixel => Some(ImageProtocol::Sixel),
+        };
+        Self {
+            protocol,
+            max_cols: config.image_max_cols.max(1),
+            max_rows: config.image_max_rows.max(1),
+        }
+    }
+
+    /// 读取合并后的 `[render]` 配置创建
+    pub fn load() -> Self {
+        Self::from_config(&RenderConfig::load())
+    }
+
+    /// 生成预览（终端转义序列），无法预览时返回占位行
+    pub fn render(&self, image: &ImageInfo) -> String {
+        #[cfg(feature = "images")]
+        if let Some(protocol) = self.protocol {
+            if let Ok(preview) = encode::preview(image, protocol, self.max_cols, self.max_rows) {
+                return preview;
+            }
+        }
+        placeholder(image)
+    }
+}
+
+/// 占位行：格式、尺寸和文件路径
+pub fn placeholder(image: &ImageInfo) -> String {
+    format!(
+        "  {}",
+        t!(
+            "cli.image.placeholder",
+            description = image.describe(),
+            path = image.path
+        )
+        .dimmed()
+    )
+}
+
+#[cfg(feature = "images")]
+mod encode {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use image::imageops::FilterType;
+    use image::{DynamicImage, ImageFormat, ImageReader, ImageResult, RgbImage};
+    use std::io::Cursor;
+
+    use super::ImageProtocol;
+    use crate::tools::image::ImageInfo;
+
+    /// 终端未报告像素尺寸时假定的字符格大小（宽、高，像素）
+    const DEFAULT_CELL: (u32, u32) = (8, 16);
+
+    /// kitty 协议单段传输的 base64 字节数上限
+    const KITTY_CHUNK: usize = 4096;
+
+    /// 解码、缩放并编码为对应协议的转义序列
+    pub fn preview(
+        image: &ImageInfo,
+        protocol: ImageProtocol,
+        max_cols: u16,
+        max_rows: u16,
+    ) -> ImageResult<String> {
+        let decoded = ImageReader::open(&image.path)?
+            .with_guessed_format()?
+            .decode()?;
+        let (cols, rows, width, height) = fit(
+            decoded.width(),
+            decoded.height(),
+            max_cols,
+            max_rows,
+            cell_size(),
+        );
+        let scaled = decoded.resize_exact(width, height, FilterType::Triangle);
+        Ok(match protocol {
+            ImageProtocol::Kitty => kitty(&png(&scaled)?, cols, rows),
+            ImageProtocol::Iterm2 => iterm2(&png(&scaled)?, cols, rows),
+            ImageProtocol::Sixel => sixel(&scaled.to_rgb8()),
+        })
+    }
+
+    /// 字符格的像素大小
+    fn cell_size() -> (u32, u32) {
+        match crossterm::terminal::window_size() {
+            Ok(size) if size.columns > 0 && size.rows > 0 && size.width > 0 && size.height > 0 => (
+                (size.width / size.columns).max(1) as u32,
+                (size.height / size.rows).max(1) as u32,
+            ),
+            _ => DEFAULT_CELL,
+        }
+    }
+
+    /// 等比缩小到字符格上限以内（不放大），返回（列数，行数，像素宽，像素高）
+    fn fit(
+        width: u32,
+        height: u32,
+        max_cols: u16,
+        max_rows: u16,
+        cell: (u32, u32),
+    ) -> (u32, u32, u32, u32) {
+        let max_width = max_cols as f64 * cell.0 as f64;
+        let max_height = max_rows as f64 * cell.1 as f64;
+        let scale = (max_width / width.max(1) as f64)
+            .min(max_height / height.max(1) as f64)
+            .min(1.0);
+        let width = ((width as f64 * scale).round() as u32).max(1);
+        let height = ((height as f64 * scale).round() as u32).max(1);
+        (
+            width.div_ceil(cell.0),
+            height.div_ceil(cell.1),
+            width,
+            height,
+        )
+    }
+
+    fn png(image: &DynamicImage) -> ImageResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+        Ok(bytes)
+    }
+
+    /// kitty graphics protocol：PNG 数据按段传输，最后一段 `m=0`
+    fn kitty(png: &[u8], cols: u32, rows: u32) -> String {
+        let data = STANDARD.encode(png);
+        let chunks: Vec<&str> = data
+            .as_bytes()
+            .chunks(KITTY_CHUNK)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect();
+        let mut out = String::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(index + 1 < chunks.len());
+            if index == 0 {
+                out.push_str(&format!(
+                    "\x1b_Ga=T,f=100,c={},r={},m={};{}\x1b\\",
+                    cols, rows, more, chunk
+                ));
+            } else {
+                out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+            }
+        }
+        out
+    }
+
+    /// iTerm2 inline images
+    fn iterm2(png: &[u8], cols: u32, rows: u32) -> String {
+        format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            png.len(),
+            cols,
+            rows,
+            STANDARD.encode(png)
+        )
+    }
+
+    /// 量化到 6×6×6 色立方体的调色板索引
+    fn palette_index(pixel: &image::Rgb<u8>) -> usize {
+        let level = |c: u8| (c as usize * 5 + 127) / 255;
+        level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])
+    }
+
+    /// 追加一段重复的 sixel 字符（超过 3 个时用 `!` 压缩）
+    fn push_run(out: &mut String, sixel: char, count: usize) {
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, sixel));
+        } else {
+            out.extend(std::iter::repeat_n(sixel, count));
+        }
+    }
+
+    /// sixel：每 6 行像素为一段，段内每种颜色一遍
+    fn sixel(image: &RgbImage) -> String {
+        let (width, height) = image.dimensions();
+        let indexes: Vec<usize> = image.pixels().map(palette_index).collect();
+        let at = |x: u32, y: u32| indexes[(y * width + x) as usize];
+
+        let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+        let mut used = [false; 216];
+        for index in &indexes {
+            used[*index] = true;
+        }
+        for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+            let (r, g, b) = (index / 36, index / 6 % 6, index % 6);
+            out.push_str(&format!("#{};2;{};{};{}", index, r * 20, g * 20, b * 20));
+        }
+
+        for top in (0..height).step_by(6) {
+            let bottom = (top + 6).min(height);
+            let mut colors: Vec<usize> = (top..bottom)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| at(x, y))
+                .collect();
+            colors.sort_unstable();
+            colors.dedup();
+
+            for (pass, color) in colors.iter().enumerate() {
+                if pass > 0 {
+                    out.push('$');
+                }
+                out.push_str(&format!("#{}", color));
+                let mut run: Option<(char, usize)> = None;
+                for x in 0..width {
+                    let bits = (top..bottom)
+                        .filter(|y| at(x, *y) == *color)
+                        .fold(0u8, |bits, y| bits | 1 << (y - top));
+                    let sixel = (63 + bits) as char;
+                    match run.as_mut() {
+                        Some((current, count)) if *current == sixel => *count += 1,
+                        _ => {
+                            if let Some((current, count)) = run {
+                                push_run(&mut out, current, count);
+                            }
+                            run = Some((sixel, 1));
+                        }
+                    }
+                }
+                if let Some((current, count)) = run {
+                    push_run(&mut out, current, count);
+                }
+            }
+            out.push('-');
+        }
+        out.push_str("\x1b\\");
+        out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use image::Rgb;
+
+        #[test]
+        fn test_fit_keeps_aspect_ratio() {
+            // 1600×900 缩小到 60×20 格（每格 8×16 像素，即 480×320）：宽度先到上限
+            assert_eq!(fit(1600, 900, 60, 20, (8, 16)), (60, 17, 480, 270));
+            // 竖图：高度先到上限
+            assert_eq!(fit(900, 1600, 60, 20, (8, 16)), (23, 20, 180, 320));
+            // 小图不放大
+            assert_eq!(fit(16, 16, 60, 20, (8, 16)), (2, 1, 16, 16));
+        }
+
+        #[test]
+        fn test_encodings() {
+            let mut image = RgbImage::new(3, 7);
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+                *pixel = if (x + y) % 2 == 0 {
+                    Rgb([255, 0, 0])
+                } else {
+                    Rgb([0, 0, 0])
+                };
+            }
+
+            let encoded = sixel(&image);
+            assert!(encoded.starts_with("\x1bP0;1;0q\"1;1;3;7"));
+            assert!(encoded.ends_with("-\x1b\\"));
+            // 红色和黑色两种颜色，两段
+            assert!(encoded.contains("#180;2;100;0;0"));
+            assert!(encoded.contains("#0;2;0;0;0"));
+            assert_eq!(encoded.matches('-').count(), 2);
+
+            let data = png(&DynamicImage::ImageRgb8(image)).unwrap();
+            assert!(kitty(&data, 1, 1).starts_with("\x1b_Ga=T,f=100,c=1,r=1,m=0;"));
+            assert!(iterm2(&data, 1, 1)
+                .starts_with(&format!("\x1b]1337;File=inline=1;size={};", data.len())));
+
+            let large = kitty(&vec![0; KITTY_CHUNK * 2], 4, 2);
+            assert!(large.starts_with("\x1b_Ga=T,f=100,c=4,r=2,m=1;"));
+            assert!(large.contains("\x1b_Gm=0;"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_detect_protocol() {
+        let detect = |vars: &[(&str, &str)]| ImageProtocol::detect(env(vars));
+        assert_eq!(
+            detect(&[("TERM", "xterm-kitty")]),
+            Some(ImageProtocol::Kitty)
+        );
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("KITTY_WINDOW_ID", "1")]),
+            Some(ImageProtocol::Kitty)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "ghostty")]),
+            Some(ImageProtocol::Kitty)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app")]),
+            Some(ImageProtocol::Iterm2)
+        );
+        assert_eq!(
+            detect(&[("LC_TERMINAL", "iTerm2")]),
+            Some(ImageProtocol::Iterm2)
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "WezTerm")]),
+            Some(ImageProtocol::Iterm2)
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), Some(ImageProtocol::Sixel));
+        assert_eq!(
+            detect(&[("TERM", "xterm-sixel")]),
+            Some(ImageProtocol::Sixel)
+        );
+
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("KITTY_WINDOW_ID", "")]),
+            None
+        );
+        assert_eq!(detect(&[]), None);
+        // tmux / screen 中不输出图片
+        assert_eq!(
+            detect(&[
+                ("TERM", "xterm-kitty"),
+                ("TMUX", "/tmp/tmux-1000/default,1,0")
+            ]),
+            None
+        );
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app"), ("STY", "1234.pts-0")]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_config_overrides_detection() {
+        let config = |images| RenderConfig {
+            images,
+            ..RenderConfig::default()
+        };
+        assert_eq!(
+            ImagePreview::from_config(&config(ImagePreviewMode::Off)).protocol,
+            None
+        );
+        assert_eq!(
+            ImagePreview::from_config(&config(ImagePreviewMode::Sixel)).protocol,
+            Some(ImageProtocol::Sixel)
+        );
+
+        crate::i18n::init(crate::i18n::Locale::En);
+        let image = ImageInfo {
+            path: "/tmp/chart.png".to_string(),
+            mime_type: "image/png".to_string(),
+            width: Some(640),
+            height: Some(480),
+            size_bytes: 1024,
+        };
+        let rendered = ImagePreview::from_config(&config(ImagePreviewMode::Off)).render(&image);
+        assert!(rendered.contains("🖼  image/png 640×480, 1024 bytes · /tmp/chart.png"));
+    }
+}
@@ -0,0 +1,332 @@
+//! 大段粘贴的处理
+//!
+//! 提交的输入超过 `[editor] paste_max_lines` 行或 `paste_max_tokens` 个 token 时不原样发送：
+//! 完整内容保存到 `.oxide/pastes/<时间>.txt`，发给模型的消息中换成一行标记加开头和结尾的摘录，
+//! 模型需要时用 read_file 的 offset/limit 读取具体范围。`/paste raw` 原样发送上一次被截取的内容。
+//!
+//! 在输入框中粘贴大段内容时只显示占位符（见 [`PasteEditMode`]），提交时再展开，
+//! 终端和输入历史中都不会出现整段内容。
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use reedline::{EditCommand, EditMode, PromptEditMode, ReedlineEvent, ReedlineRawEvent};
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::clipboard::PASTE_IMAGE_COMMAND;
+use crate::config::{ConfigLoader, EditorConfig};
+use crate::token_counter::count_tokens;
+
+/// 保存粘贴内容的目录（相对于工作目录）
+pub const PASTES_DIR: &str = ".oxide/pastes";
+
+/// 开头和结尾各保留的摘录行数
+const EXCERPT_LINES: usize = 20;
+
+/// 摘录中每行最多保留的字符数
+const EXCERPT_LINE_CHARS: usize = 300;
+
+/// 输入框中的占位符，如 `[pasted content #1: 31,204 lines]`
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[pasted content #(\d+): [\d,]+ lines\]").unwrap());
+
+/// 粘贴内容的大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasteSize {
+    pub lines: usize,
+    /// 只按行数判断时为 `None`（没有计算）
+    pub tokens: Option<usize>,
+}
+
+/// 大段粘贴的阈值，0 表示不按该项限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasteLimits {
+    pub max_lines: usize,
+    pub max_tokens: usize,
+}
+
+impl PasteLimits {
+    /// 按 `[editor]` 配置创建
+    pub fn from_config() -> Self {
+        let config: EditorConfig = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.editor)
+            .unwrap_or_default();
+        Self {
+            max_lines: config.paste_max_lines,
+            max_tokens: config.paste_max_tokens,
+        }
+    }
+
+    /// 超过阈值时返回内容大小
+    pub fn exceeded_by(&self, text: &str) -> Option<PasteSize> {
+        let lines = text.lines().count();
+        if self.max_lines > 0 && lines > self.max_lines {
+            return Some(PasteSize {
+                lines,
+                tokens: None,
+            });
+        }
+        // 每个 token 至少一个字节，较短的输入不必计算
+        if self.max_tokens == 0 || text.len() <= self.max_tokens {
+            return None;
+        }
+        let tokens = count_tokens(text);
+        (tokens > self.max_tokens).then_some(PasteSize {
+            lines,
+            tokens: Some(tokens),
+        })
+    }
+}
+
+/// 把完整内容保存到 `dir` 下以当前时间命名的文件
+pub fn save_paste(dir: &Path, text: &str) -> io::Result<PathBuf> {
+    save_stamped(dir, "txt", text.as_bytes())
+}
+
+/// 保存到 `dir/<时间>.<extension>`，同一秒内已有同名文件时加序号
+pub fn save_stamped(dir: &Path, extension: &str, bytes: &[u8]) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut path = dir.join(format!("{}.{}", stamp, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", stamp, n, extension));
+        n += 1;
+    }
+    fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+/// 代替完整内容发给模型的消息：一行标记，加开头和结尾的摘录
+pub fn marker(path: &Path, text: &str, size: PasteSize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let head = lines.len().min(EXCERPT_LINES);
+    let tail = (lines.len() - head).min(EXCERPT_LINES);
+    let omitted = lines.len() - head - tail;
+
+    let mut message = format!(
+        "[pasted content: {} lines{}, saved to {}; use read_file with offset/limit to read specific ranges]\n",
+        group_thousands(size.lines),
+        size.tokens
+            .map(|tokens| format!(" (~{} tokens)", group_thousands(tokens)))
+            .unwrap_or_default(),
+        path.display()
+    );
+    for line in &lines[..head] {
+        message.push_str(&excerpt_line(line));
+    }
+    if omitted > 0 {
+        message.push_str(&format!(
+            "[… {} lines omitted …]\n",
+            group_thousands(omitted)
+        ));
+    }
+    for line in &lines[lines.len() - tail..] {
+        message.push_str(&excerpt_line(line));
+    }
+    message
+}
+
+fn excerpt_line(line: &str) -> String {
+    match line.char_indices().nth(EXCERPT_LINE_CHARS) {
+        Some((index, _)) => format!("{}…\n", &line[..index]),
+        None => format!("{}\n", line),
+    }
+}
+
+/// 千位分隔，如 `31,204`
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let first = match digits.len() % 3 {
+        0 => 3,
+        r => r,
+    };
+    let (mut grouped, mut rest) = (digits[..first].to_string(), &digits[first..]);
+    while !rest.is_empty() {
+        let (group, tail) = rest.split_at(3);
+        grouped.push(',');
+        grouped.push_str(group);
+        rest = tail;
+    }
+    grouped
+}
+
+/// 输入框中以占位符显示、等待提交时展开的粘贴内容
+#[derive(Debug, Default)]
+pub struct PendingPastes {
+    pastes: Mutex<Vec<String>>,
+}
+
+impl PendingPastes {
+    fn pastes(&self) -> MutexGuard<'_, Vec<String>> {
+        self.pastes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记下粘贴内容，返回输入框中显示的占位符
+    pub fn hold(&self, text: String) -> String {
+        let lines = text.lines().count();
+        let mut pastes = self.pastes();
+        pastes.push(text);
+        format!(
+            "[pasted content #{}: {} lines]",
+            pastes.len(),
+            group_thousands(lines)
+        )
+    }
+
+    /// 放弃输入框中的粘贴内容（如按 Ctrl+C 清空输入时）
+    pub fn clear(&self) {
+        self.pastes().clear();
+    }
+
+    /// 把提交的输入中的占位符换回粘贴内容，并清空记录（无法识别的占位符原样保留）
+    pub fn expand(&self, line: &str) -> String {
+        let pastes = std::mem::take(&mut *self.pastes());
+        PLACEHOLDER
+            .replace_all(line, |caps: &regex::Captures| {
+                caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| pastes.get(n.checked_sub(1)?))
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+/// 在输入框中把大段粘贴显示为占位符的编辑模式
+///
+/// 终端的 bracketed paste 整段送达，内层编辑模式把它转换为一次 `InsertString`；
+/// 超过阈值时改为插入占位符，内容交给 [`PendingPastes`]。粘贴的内容为空时
+/// （剪贴板中只有图片，终端没有可粘贴的文本）改为读取剪贴板中的图片，见 [`super::clipboard`]。
+pub struct PasteEditMode {
+    inner: Box<dyn EditMode>,
+    limits: PasteLimits,
+    pending: Arc<PendingPastes>,
+}
+
+impl PasteEditMode {
+    pub fn new(inner: Box<dyn EditMode>, limits: PasteLimits, pending: Arc<PendingPastes>) -> Self {
+        Self {
+            inner,
+            limits,
+            pending,
+        }
+    }
+}
+
+impl EditMode for PasteEditMode {
+    fn parse_event(&mut self, event: ReedlineRawEvent) -> ReedlineEvent {
+        match self.inner.parse_event(event) {
+            ReedlineEvent::Edit(commands) => match commands.as_slice() {
+                [EditCommand::InsertString(text)] if text.is_empty() => {
+                    ReedlineEvent::ExecuteHostCommand(PASTE_IMAGE_COMMAND.to_string())
+                }
+                [EditCommand::InsertString(text)] if self.limits.exceeded_by(text).is_some() => {
+                    let placeholder = self.pending.hold(text.clone());
+                    ReedlineEvent::Edit(vec![EditCommand::InsertString(placeholder)])
+                }
+                _ => ReedlineEvent::Edit(commands),
+            },
+            other => other,
+        }
+    }
+
+    fn edit_mode(&self) -> PromptEditMode {
+        self.inner.edit_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(lines: usize) -> String {
+        (1..=lines).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = PasteLimits {
+            max_lines: 100,
+            max_tokens: 50,
+        };
+        assert_eq!(limits.exceeded_by("short question"), None);
+        assert_eq!(
+            limits.exceeded_by(&log(101)),
+            Some(PasteSize {
+                lines: 101,
+                tokens: None
+            })
+        );
+        // 行数不多但 token 很多（如压缩成一行的 JSON）
+        let size = limits.exceeded_by(&"word ".repeat(200)).unwrap();
+        assert_eq!(size.lines, 1);
+        assert!(size.tokens.unwrap() > 50);
+
+        let unlimited = PasteLimits {
+            max_lines: 0,
+            max_tokens: 0,
+        };
+        assert_eq!(unlimited.exceeded_by(&log(5000)), None);
+    }
+
+    #[test]
+    fn test_marker_keeps_head_and_tail() {
+        let text = log(31_204);
+        let size = PasteSize {
+            lines: 31_204,
+            tokens: None,
+        };
+        let message = marker(Path::new(".oxide/pastes/20261018-101500.txt"), &text, size);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(
+            lines[0],
+            "[pasted content: 31,204 lines, saved to .oxide/pastes/20261018-101500.txt; \
+             use read_file with offset/limit to read specific ranges]"
+        );
+        assert_eq!(lines[1], "line 1");
+        assert_eq!(lines[20], "line 20");
+        assert_eq!(lines[21], "[… 31,164 lines omitted …]");
+        assert_eq!(lines[22], "line 31185");
+        assert_eq!(lines.last(), Some(&"line 31204"));
+        assert_eq!(lines.len(), 1 + 20 + 1 + 20);
+
+        // 行数少于两段摘录时不省略，过长的行截断
+        let long = format!("{}\nend\n", "x".repeat(1000));
+        let message = marker(
+            Path::new("p.txt"),
+            &long,
+            PasteSize {
+                lines: 2,
+                tokens: Some(12_345),
+            },
+        );
+        assert!(message.starts_with("[pasted content: 2 lines (~12,345 tokens), saved to p.txt;"));
+        assert!(!message.contains("omitted"));
+        assert!(message.contains(&format!("{}…\nend\n", "x".repeat(300))));
+    }
+
+    #[test]
+    fn test_placeholder_round_trip() {
+        let pending = PendingPastes::default();
+        let placeholder = pending.hold(log(1500));
+        assert_eq!(placeholder, "[pasted content #1: 1,500 lines]");
+
+        let line = format!(
+            "why does this fail? {} [pasted content #9: 3 lines]",
+            placeholder
+        );
+        let expanded = pending.expand(&line);
+        assert!(expanded.starts_with("why does this fail? line 1\n"));
+        assert!(expanded.ends_with("line 1500\n [pasted content #9: 3 lines]"));
+        // 提交后记录清空
+        assert_eq!(pending.expand(&placeholder), placeholder);
+    }
+}
@@ -1,24 +1,61 @@
+pub mod clipboard;
 pub mod command;
+pub mod completion_menu;
+pub mod completion_worker;
+pub mod export;
+pub mod file_index;
 pub mod file_resolver;
+pub mod find;
+pub mod help;
+pub mod history;
+pub mod image;
+pub mod large_paste;
+pub mod output;
+pub mod palette;
 pub mod render;
+pub mod statusbar;
+pub mod stream_pacer;
+pub mod suggest;
+pub mod tags;
+pub mod templates;
+pub mod tool_panel;
+pub mod tool_status;
+pub mod turn_keys;
 
 use anyhow::Result;
 use colored::*;
-use nu_ansi_term::{Color, Style};
 use inquire::Select;
 use reedline::{
-    default_emacs_keybindings, Completer, DescriptionMode, EditCommand, Emacs, IdeMenu, KeyCode,
-    KeyModifiers, MenuBuilder, Prompt, PromptEditMode, Reedline, ReedlineEvent, ReedlineMenu,
-    Signal, Span, Suggestion,
+    default_emacs_keybindings, Completer, EditCommand, Emacs, KeyCode, KeyModifiers, Prompt,
+    PromptEditMode, Reedline, ReedlineEvent, ReedlineMenu, Signal, Span, Suggestion,
 };
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::context::ContextManager;
+use crate::mcp::McpManager;
+use crate::model_info::ModelInfo;
+use crate::startup::{StartupProfile, Warmup};
+use crate::tools::WorkspaceRoots;
+use clipboard::{ImageClipboard, PASTE_IMAGE_COMMAND};
+use completion_menu::{CompletionMenu, COMPLETION_MENU};
+use completion_worker::{CompletionSource, CompletionWakeMode, CompletionWorker, EditorWaker};
+use file_index::FileIndex;
+use history::FilteredHistory;
+use image::ImagePreview;
+use large_paste::{PasteEditMode, PasteLimits, PendingPastes, PASTES_DIR};
+use output::PlainWriter;
+use palette::{Palette, PaletteAction, PaletteEntry, UsageStats, PALETTE_COMMAND};
+use stream_pacer::StreamPacing;
+use tags::TagSet;
+use templates::{SessionTemplate, TemplateStart};
+use statusbar::StatusBar;
+use tool_status::ToolStatusDisplay;
+use turn_keys::TurnKeys;
 
 const PROMPT_CYCLE_COMMAND: &str = "__oxide_prompt_cycle__";
 
@@ -41,47 +78,21 @@ impl CommandInfo {
 
 fn build_commands() -> HashMap<String, CommandInfo> {
     let mut commands = HashMap::new();
-    commands.insert("/quit".to_string(), CommandInfo::new("/quit", "退出程序"));
-    commands.insert("/exit".to_string(), CommandInfo::new("/exit", "退出程序"));
-    commands.insert("/clear".to_string(), CommandInfo::new("/clear", "清除屏幕"));
-    commands.insert("/config".to_string(), CommandInfo::new("/config", "显示当前配置"));
-    commands.insert("/help".to_string(), CommandInfo::new("/help", "显示帮助信息"));
-    commands.insert(
-        "/toggle-tools".to_string(),
-        CommandInfo::new("/toggle-tools", "切换工具显示"),
-    );
-    commands.insert(
-        "/history".to_string(),
-        CommandInfo::new("/history", "显示对话历史"),
-    );
-    commands.insert(
-        "/load".to_string(),
-        CommandInfo::new("/load <session_id>", "加载指定会话"),
-    );
-    commands.insert(
-        "/sessions".to_string(),
-        CommandInfo::new("/sessions", "列出所有会话"),
-    );
-    commands.insert(
-        "/delete".to_string(),
-        CommandInfo::new("/delete <session_id>", "删除指定会话"),
-    );
-    commands.insert(
-        "/agent".to_string(),
-        CommandInfo::new("/agent [list|capabilities]", "查看 Agent 类型与能力"),
-    );
-    commands.insert(
-        "/tasks".to_string(),
-        CommandInfo::new("/tasks [list|show <id>]", "管理后台任务"),
-    );
-    commands.insert(
-        "/skills".to_string(),
-        CommandInfo::new("/skills [list|show <name>]", "管理技能"),
-    );
-    commands.insert(
-        "/workflow".to_string(),
-        CommandInfo::new("/workflow [status|on|off]", "PAOR 工作流设置"),
-    );
+    for command in help::builtin_commands() {
+        // `!` 不是斜杠命令，不参与补全
+        if !command.name.starts_with('/') {
+            continue;
+        }
+        commands.insert(
+            command.name.clone(),
+            CommandInfo::new(command.usage(), &command.description),
+        );
+    }
+    for (alias, command) in help::ALIASES {
+        if let Some(description) = commands.get(*command).map(|info| info.description.clone()) {
+            commands.insert(alias.to_string(), CommandInfo::new(alias, &description));
+        }
+    }
     commands
 }
 
@@ -103,19 +114,13 @@ fn build_command_entries() -> Vec<(String, String)> {
 #[allow(dead_code)]
 fn build_context_entries() -> Vec<(String, String)> {
     vec![
-        ("@file".to_string(), "引用文件".to_string()),
-        ("@codebase".to_string(), "搜索代码库".to_string()),
-        ("@web".to_string(), "搜索网页".to_string()),
-        ("@docs".to_string(), "搜索文档".to_string()),
-    ]
-}
-
-fn build_tag_entries() -> Vec<(String, String)> {
-    vec![
-        ("#bug".to_string(), "问题修复".to_string()),
-        ("#feature".to_string(), "新功能".to_string()),
-        ("#refactor".to_string(), "重构".to_string()),
-        ("#docs".to_string(), "文档".to_string()),
+        ("@file".to_string(), t!("cli.context.file").to_string()),
+        (
+            "@codebase".to_string(),
+            t!("cli.context.codebase").to_string(),
+        ),
+        ("@web".to_string(), t!("cli.context.web").to_string()),
+        ("@docs".to_string(), t!("cli.context.docs").to_string()),
     ]
 }
 
@@ -145,9 +150,40 @@ fn is_line_start(line: &str, start: usize) -> bool {
     line[..start].trim().is_empty()
 }
 
-struct OxideCompleter;
+/// @ 文件补全最多返回的结果数
+const MAX_FILE_SUGGESTIONS: usize = 50;
+
+struct OxideCompleter {
+    /// 会话级文件索引
+    file_index: Arc<FileIndex>,
+    /// `#` 标签补全项
+    tag_entries: Vec<(String, String)>,
+}
 
 impl OxideCompleter {
+    fn new(file_index: Arc<FileIndex>, tags: &TagSet) -> Self {
+        Self {
+            file_index,
+            tag_entries: tags.entries(),
+        }
+    }
+
+    /// 基于文件索引的模糊补全（保持索引给出的排序）
+    fn build_indexed_suggestions(&self, query: &str, span: Span) -> Vec<Suggestion> {
+        self.file_index
+            .search(query, MAX_FILE_SUGGESTIONS)
+            .into_iter()
+            .map(|file| Suggestion {
+                value: format!("@{}", file.relative_path),
+                description: Some(format_file_size(file.size_bytes)),
+                style: None,
+                extra: None,
+                span,
+                append_whitespace: false,
+            })
+            .collect()
+    }
+
     fn match_entries(
         &self,
         entries: &[(String, String)],
@@ -303,90 +339,51 @@ impl OxideCompleter {
                     };
 
                     let description = if file_type.as_ref().map_or(false, |ft| ft.is_dir()) {
-                        "目录/".to_string()
+                        t!("cli.completion.directory").to_string()
                     } else if file_type.as_ref().map_or(false, |ft| ft.is_file()) {
                         if let Ok(metadata) = entry.metadata() {
                             format_file_size(metadata.len())
                         } else {
-                            "文件".to_string()
+                            t!("cli.completion.file").to_string()
                         }
                     } else {
-                        "其他".to_string()
+                        t!("cli.completion.other").to_string()
                     };
 
                     entries.push((display_path, description));
                 }
             }
         } else {
-            // 不包含目录：递归扫描当前目录下的所有文件
+            // 不包含目录且输入为空：只显示当前目录的直接子项
+            // （非空输入由文件索引处理，见 build_indexed_suggestions）
             let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
 
-            if path_str.is_empty() {
-                // 输入为空：只显示当前目录的直接子项
-                if let Ok(read_dir) = fs::read_dir(&current_dir) {
-                    for entry in read_dir.filter_map(|e| e.ok()) {
-                        let file_name = entry.file_name();
-                        let name = file_name.to_string_lossy().to_string();
-
-                        if name.starts_with('.') {
-                            continue;
-                        }
-
-                        let file_type = entry.file_type();
-                        let display_path = format!("@{}", name);
-
-                        let description = if file_type.as_ref().map_or(false, |ft| ft.is_dir()) {
-                            "目录/".to_string()
-                        } else if file_type.as_ref().map_or(false, |ft| ft.is_file()) {
-                            if let Ok(metadata) = entry.metadata() {
-                                format_file_size(metadata.len())
-                            } else {
-                                "文件".to_string()
-                            }
-                        } else {
-                            "其他".to_string()
-                        };
-
-                        entries.push((display_path, description));
-                    }
-                }
-            } else {
-                // 输入不为空：递归扫描所有文件进行模糊匹配
-                let all_files = Self::list_files_recursive(&current_dir);
-
-                for file_path in all_files {
-                    let file_name = file_path.file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_default();
-
-                    if file_name.starts_with('.') {
-                        continue;
-                    }
+            if let Ok(read_dir) = fs::read_dir(&current_dir) {
+                for entry in read_dir.filter_map(|e| e.ok()) {
+                    let file_name = entry.file_name();
+                    let name = file_name.to_string_lossy().to_string();
 
-                    // 模糊匹配文件名
-                    if !file_name.to_lowercase().contains(&path_str.to_lowercase()) {
+                    if name.starts_with('.') {
                         continue;
                     }
 
-                    // 获取相对路径
-                    let relative_path = file_path.strip_prefix(&current_dir)
-                        .unwrap_or(&file_path);
-                    let display_path = format!("@{}", relative_path.display());
+                    let file_type = entry.file_type();
+                    let display_path = format!("@{}", name);
 
-                    // 获取文件大小
-                    let description = if let Ok(metadata) = fs::metadata(&file_path) {
-                        format_file_size(metadata.len())
+                    let description = if file_type.as_ref().map_or(false, |ft| ft.is_dir()) {
+                        t!("cli.completion.directory").to_string()
+                    } else if file_type.as_ref().map_or(false, |ft| ft.is_file()) {
+                        if let Ok(metadata) = entry.metadata() {
+                            format_file_size(metadata.len())
+                        } else {
+                            t!("cli.completion.file").to_string()
+                        }
                     } else {
-                        "文件".to_string()
+                        t!("cli.completion.other").to_string()
                     };
 
                     entries.push((display_path, description));
                 }
-
-                // 限制结果数量，避免太多
-                if entries.len() > 50 {
-                    entries.truncate(50);
-                }
             }
         }
 
@@ -418,8 +415,9 @@ fn format_file_size(bytes: u64) -> String {
     }
 }
 
-impl Completer for OxideCompleter {
-    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+impl OxideCompleter {
+    /// 计算补全项（在补全工作线程中调用）
+    fn suggestions(&self, line: &str, pos: usize) -> Vec<Suggestion> {
         let start = token_start(line, pos);
         let token = &line[start..pos];
         let end = token_end(line, pos);
@@ -435,13 +433,16 @@ impl Completer for OxideCompleter {
                 '@' => {
                     // 动态生成文件路径补全
                     let path_str = &token[1..]; // 移除 @ 符号
+                    if !path_str.is_empty() && !path_str.contains('/') && !path_str.contains('\\') {
+                        return self.build_indexed_suggestions(path_str, span);
+                    }
                     if let Ok(file_entries) = self.build_file_entries(path_str) {
                         return self.match_entries(&file_entries, token, span);
                     }
                 }
                 '#' => {
                     if is_line_start(line, start) {
-                        return self.match_entries(&build_tag_entries(), token, span);
+                        return self.match_entries(&self.tag_entries, token, span);
                     }
                 }
                 _ => {}
@@ -452,26 +453,121 @@ impl Completer for OxideCompleter {
     }
 }
 
+impl CompletionSource for OxideCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        self.suggestions(line, pos)
+    }
+}
+
+/// 补全工作线程的防抖窗口
+const COMPLETION_DEBOUNCE: Duration = Duration::from_millis(80);
+
+/// 交给 reedline 的补全器：只投递请求，计算在后台线程完成，结果就绪后唤醒编辑器重绘
+struct DebouncedCompleter {
+    worker: CompletionWorker,
+}
+
+impl DebouncedCompleter {
+    fn new(source: OxideCompleter, waker: Arc<EditorWaker>) -> Self {
+        Self {
+            worker: CompletionWorker::spawn(source, COMPLETION_DEBOUNCE, move || waker.wake()),
+        }
+    }
+}
+
+impl Completer for DebouncedCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        self.worker.complete(line, pos)
+    }
+}
+
+/// 输入区至少保留的宽度，不够时先去掉右侧信息
+const MIN_INPUT_WIDTH: usize = 20;
+
+/// 提示符右侧的信息
+#[derive(Clone, Debug, PartialEq)]
+struct PromptInfo {
+    /// 模型简称
+    model: String,
+    /// 上下文已用百分比
+    context_percent: usize,
+}
+
+impl PromptInfo {
+    fn new(model: &str, context_tokens: usize) -> Self {
+        let window = ModelInfo::lookup(model).context_window.max(1);
+        Self {
+            model: short_model_name(model),
+            context_percent: (context_tokens * 100 / window).min(100),
+        }
+    }
+}
+
+/// 模型简称：去掉 provider 前缀、`claude-` 和日期后缀
+fn short_model_name(model: &str) -> String {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    let name = name.strip_prefix("claude-").unwrap_or(name);
+    match name.rsplit_once('-') {
+        Some((rest, date)) if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) => {
+            rest.to_string()
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// 按终端宽度决定左右两侧的内容：先缩短、再去掉右侧，最后缩短左侧
+fn prompt_layout(label: &str, info: Option<&PromptInfo>, width: usize) -> (String, String) {
+    let left = format!("{}> ", label);
+    let left_width = left.chars().count();
+    if let Some(info) = info {
+        let full = format!("{} · {}%", info.model, info.context_percent);
+        let short = format!("{}%", info.context_percent);
+        for right in [full, short] {
+            if left_width + right.chars().count() + MIN_INPUT_WIDTH <= width {
+                return (left, right);
+            }
+        }
+    }
+    if left_width + MIN_INPUT_WIDTH <= width {
+        (left, String::new())
+    } else {
+        ("> ".to_string(), String::new())
+    }
+}
+
 /// 自定义 Prompt
 #[derive(Clone)]
 struct OxidePrompt {
-    /// 左侧提示符标签
-    label: PromptLabel,
+    left: String,
+    right: String,
 }
 
 impl OxidePrompt {
-    fn new(label: PromptLabel) -> Self {
-        Self { label }
+    /// `info` 为 None 时不显示右侧信息
+    fn new(label: PromptLabel, info: Option<&PromptInfo>) -> Self {
+        let width = crossterm::terminal::size()
+            .map(|(width, _)| width as usize)
+            .unwrap_or(80);
+        let (left, right) = prompt_layout(label.as_str(), info, width);
+        Self { left, right }
+    }
+
+    /// 提交后重绘的精简形式（`ui.transient_prompt`）
+    fn transient() -> Self {
+        Self {
+            left: "> ".to_string(),
+            right: String::new(),
+        }
     }
 }
 
 impl Prompt for OxidePrompt {
     fn render_prompt_left(&self) -> Cow<'_, str> {
-        Cow::Owned(format!("{}> ", self.label.as_str()))
+        Cow::Borrowed(&self.left)
     }
 
     fn render_prompt_right(&self) -> Cow<'_, str> {
-        Cow::Borrowed("")
+        Cow::Borrowed(&self.right)
     }
 
     fn render_prompt_indicator(&self, _prompt_mode: PromptEditMode) -> Cow<'_, str> {
@@ -507,11 +603,25 @@ impl PromptLabel {
         }
     }
 
+    /// Tab 切换到的下一个模式，顺序与 `/mode` 列表一致
     fn next(self) -> Self {
+        Self::from_prompt_mode(self.prompt_mode().next())
+    }
+
+    /// 对应的系统提示词模式
+    fn prompt_mode(self) -> PromptMode {
         match self {
-            PromptLabel::Oxide => PromptLabel::Fast,
-            PromptLabel::Fast => PromptLabel::Plan,
-            PromptLabel::Plan => PromptLabel::Oxide,
+            PromptLabel::Oxide => PromptMode::Normal,
+            PromptLabel::Fast => PromptMode::Fast,
+            PromptLabel::Plan => PromptMode::Plan,
+        }
+    }
+
+    fn from_prompt_mode(mode: PromptMode) -> Self {
+        match mode {
+            PromptMode::Normal => PromptLabel::Oxide,
+            PromptMode::Fast => PromptLabel::Fast,
+            PromptMode::Plan => PromptLabel::Plan,
         }
     }
 }
@@ -541,9 +651,9 @@ impl TriggerType {
     #[allow(dead_code)]
     fn prompt(&self) -> &'static str {
         match self {
-            TriggerType::Command => "选择命令",
-            TriggerType::Context => "选择上下文",
-            TriggerType::Tag => "选择标签",
+            TriggerType::Command => t!("cli.select.command"),
+            TriggerType::Context => t!("cli.select.context"),
+            TriggerType::Tag => t!("cli.select.tag"),
         }
     }
 }
@@ -559,18 +669,20 @@ pub const LOGO: &str = r#"
 (_______)|/     \|\_______/(______/ (_______/
 "#;
 
-use crate::agent::HitlIntegration;
-use crate::agent::AgentType;
+use crate::agent::{AdvisoryTracker, AgentBuilder, HitlIntegration, PromptMode, ReminderTracker};
 use crate::agent::SubagentManager;
 use crate::agent::workflow::ComplexityEvaluator;
+use crate::agent::session_learning::SessionLearning;
 use crate::cli::render::Spinner;
 use crate::config::secret::Secret;
+use crate::config::{OutputConfig, OutputStyle, RenderConfig};
+use crate::session::OxideSession;
 
 pub struct OxideCli {
     pub api_key: Secret<String>,
     pub model_name: String,
-    pub agent: AgentType,
-    pub context_manager: ContextManager,
+    /// Main Agent 和对话历史，请求都经由会话发送
+    pub session: OxideSession,
     pub _hitl: Arc<HitlIntegration>,
     prompt_label: PromptLabel,
     spinner: Spinner,
@@ -579,6 +691,64 @@ pub struct OxideCli {
     subagent_manager: Arc<SubagentManager>,
     /// 复杂度评估器
     complexity_evaluator: ComplexityEvaluator,
+    /// @ 文件补全索引
+    file_index: Arc<FileIndex>,
+    /// 流式输出节奏
+    stream_pacing: StreamPacing,
+    /// 提交后把提示符重绘为精简形式
+    transient_prompt: bool,
+    /// 可用的消息标签
+    tags: TagSet,
+    /// 系统提醒（Plan 模式、违规的工具调用、长时间空闲）
+    reminders: ReminderTracker,
+    /// 会话质量提示（上下文将满、会话过长、工具反复失败）
+    advisories: AdvisoryTracker,
+    /// `/history clear` 已确认，等待输入循环清空编辑器历史
+    pending_history_clear: bool,
+    /// 上一条 `!` 直接执行的命令，供 `!!` 重新执行
+    last_direct_command: Option<String>,
+    /// MCP 服务器
+    mcp: Arc<McpManager>,
+    /// 切换模式时用于按新提示词重建 Main Agent
+    agent_builder: Option<AgentBuilder>,
+    /// 工作区根目录（与工具设置共享）
+    roots: WorkspaceRoots,
+    /// 回复风格与语言
+    output: OutputConfig,
+    /// 工具状态行
+    tool_status: Arc<ToolStatusDisplay>,
+    /// 底部状态栏
+    status_bar: StatusBar,
+    /// 处理请求期间的按键（Ctrl+O 切换详细工具输出）
+    turn_keys: TurnKeys,
+    /// 大段粘贴的阈值（`[editor] paste_max_lines` / `paste_max_tokens`）
+    paste_limits: PasteLimits,
+    /// 输入框中以占位符显示的粘贴内容
+    pending_pastes: Arc<PendingPastes>,
+    /// 读取粘贴图片的剪贴板（未启用 `clipboard` feature 时为 `None`）
+    clipboard: Option<Box<dyn ImageClipboard>>,
+    /// 粘贴和引用的图片在终端中的预览
+    image_preview: ImagePreview,
+    /// 上一次只发送了摘录的输入及其标签，`/paste raw` 原样发送
+    last_paste: Option<(String, Vec<String>)>,
+    /// 每轮结束后显示摘要行
+    turn_summary: bool,
+    /// 工具调用超过该次数（或调用了子 agent）时显示活动回顾，0 关闭
+    turn_recap: usize,
+    /// 每轮对话的最长时间（`[limits] max_turn_seconds` / `--max-turn-time`）
+    turn_time_limit: Option<Duration>,
+    /// 会话结束时整理项目事实（`[behavior] session_learning`）
+    session_learning: SessionLearning,
+    /// 文件系统监听（持有以保持监听有效）
+    _file_watcher: Option<notify::RecommendedWatcher>,
+    /// 后台启动工作（MCP 服务器、技能），第一条消息发送前等待
+    warmup: Option<Warmup>,
+    /// 启动阶段计时（`--profile-startup`）
+    startup_profile: StartupProfile,
+    /// `--template` 指定的模板，进入输入循环前应用
+    startup_template: Option<SessionTemplate>,
+    /// 已应用的模板中等待放进第一条消息的文件和正文
+    pending_template: Option<TemplateStart>,
 }
 
 // 手动实现 Debug，防止 api_key 泄露
@@ -587,8 +757,8 @@ impl std::fmt::Debug for OxideCli {
         f.debug_struct("OxideCli")
             .field("api_key", &self.api_key) // Secret 的 Debug 实现会输出 "***"
             .field("model_name", &self.model_name)
-            .field("agent", &self.agent)
-            .field("context_manager", &self.context_manager)
+            .field("agent", &self.session.agent)
+            .field("context_manager", &self.session.context)
             .finish()
     }
 }
@@ -597,21 +767,263 @@ impl OxideCli {
     pub fn new(
         api_key: Secret<String>,
         model_name: String,
-        agent: AgentType,
-        context_manager: ContextManager,
+        session: OxideSession,
         hitl: Arc<HitlIntegration>,
     ) -> Self {
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let file_index = FileIndex::spawn(current_dir);
+        let file_watcher = file_index.watch().ok();
+
+        // 工具执行期间显示实时状态行
+        let output = OutputConfig::load();
+        let render = RenderConfig::load();
+        let image_preview = ImagePreview::load();
+        let tool_status = ToolStatusDisplay::new().with_image_preview(image_preview);
+        let tool_status = if output::mode().is_plain() {
+            Arc::new(tool_status.with_plain_output(PlainWriter::stdout()))
+        } else {
+            Arc::new(tool_status)
+        };
+        tool_status.set_compact(output.style == OutputStyle::Concise);
+        crate::agent::tool_status::register_sink(tool_status.clone());
+        crate::agent::tool_status::register_sink(crate::agent::tool_activity::global());
+        crate::agent::interaction::register(tool_status.clone());
+
+        let total_tokens = Arc::new(AtomicU64::new(0));
+        let status_bar = StatusBar::new(model_name.clone(), Arc::clone(&total_tokens), false);
+        crate::agent::interaction::register(status_bar.region());
+        let turn_keys = {
+            let notice = status_bar.notice();
+            let tool_status = Arc::clone(&tool_status);
+            TurnKeys::new(Box::new(move |verbose| {
+                let text = if verbose {
+                    t!("cli.verbose_tools.on")
+                } else {
+                    t!("cli.verbose_tools.off")
+                };
+                if !notice.show(&text) {
+                    tool_status.notice(&text);
+                }
+            }))
+        };
+
         Self {
             api_key,
             model_name,
-            agent,
-            context_manager,
+            session,
             _hitl: hitl,
             prompt_label: PromptLabel::Oxide,
             spinner: Spinner::new(),
-            total_tokens: Arc::new(AtomicU64::new(0)),
+            total_tokens,
             subagent_manager: Arc::new(SubagentManager::new()),
             complexity_evaluator: ComplexityEvaluator::new(),
+            file_index,
+            stream_pacing: StreamPacing::default(),
+            transient_prompt: false,
+            tags: TagSet::load(),
+            reminders: ReminderTracker::from_config(),
+            advisories: AdvisoryTracker::from_config(),
+            pending_history_clear: false,
+            last_direct_command: None,
+            mcp: Arc::new(McpManager::default()),
+            agent_builder: None,
+            roots: WorkspaceRoots::default(),
+            output,
+            tool_status,
+            status_bar,
+            turn_keys,
+            paste_limits: PasteLimits::from_config(),
+            pending_pastes: Arc::new(PendingPastes::default()),
+            clipboard: clipboard::system_clipboard(),
+            image_preview,
+            last_paste: None,
+            turn_summary: render.turn_summary,
+            turn_recap: render.turn_recap,
+            session_learning: SessionLearning::from_config(),
+            turn_time_limit: None,
+            _file_watcher: file_watcher,
+            warmup: None,
+            startup_profile: StartupProfile::default(),
+            startup_template: None,
+            pending_template: None,
+        }
+    }
+
+    /// 设置流式输出节奏
+    pub fn with_stream_pacing(mut self, stream_pacing: StreamPacing) -> Self {
+        self.stream_pacing = stream_pacing;
+        self
+    }
+
+    /// 提交后把提示符重绘为精简形式，保持滚动历史紧凑
+    pub fn with_transient_prompt(mut self, transient_prompt: bool) -> Self {
+        self.transient_prompt = transient_prompt;
+        self
+    }
+
+    /// 处理请求期间在终端底部显示状态栏
+    pub fn with_status_bar(mut self, enabled: bool) -> Self {
+        self.status_bar.set_enabled(enabled);
+        self
+    }
+
+    /// 设置 MCP 服务器
+    pub fn with_mcp(mut self, mcp: Arc<McpManager>) -> Self {
+        self.mcp = mcp;
+        self
+    }
+
+    /// 设置 Agent 构建器，切换模式时重建 Main Agent 以更新系统提示词
+    pub fn with_agent_builder(mut self, builder: AgentBuilder) -> Self {
+        self.agent_builder = Some(builder);
+        self
+    }
+
+    /// 设置工作区根目录，额外的根目录加入 @ 文件补全
+    pub fn with_workspace_roots(mut self, roots: WorkspaceRoots) -> Self {
+        for root in roots.extra() {
+            self.file_index.add_root(root.alias, root.path);
+        }
+        self.roots = roots;
+        self
+    }
+
+    /// 设置每轮对话的最长时间，超时后模型总结进度和剩余步骤
+    pub fn with_turn_time_limit(mut self, limit: Option<Duration>) -> Self {
+        self.turn_time_limit = limit;
+        self
+    }
+
+    /// 设置启动时的对话模式（Main Agent 须已按同一模式构建）
+    pub fn with_mode(mut self, mode: PromptMode) -> Self {
+        self.prompt_label = PromptLabel::from_prompt_mode(mode);
+        self
+    }
+
+    /// 用会话模板开始本次会话（`--template`）
+    pub fn with_template(mut self, template: Option<SessionTemplate>) -> Self {
+        self.startup_template = template;
+        self
+    }
+
+    /// 设置后台启动工作，完成后重建 Main Agent 以加入 MCP 工具
+    pub fn with_warmup(mut self, warmup: Warmup, profile: StartupProfile) -> Self {
+        self.warmup = Some(warmup);
+        self.startup_profile = profile;
+        self
+    }
+
+    /// 后台启动工作已完成时收尾，不等待
+    async fn poll_warmup(&mut self) {
+        if self.warmup.as_ref().is_some_and(Warmup::is_ready) {
+            self.wait_for_warmup().await;
+        }
+    }
+
+    /// 等待后台启动工作完成（只在尚未完成时显示提示）
+    async fn wait_for_warmup(&mut self) {
+        let Some(warmup) = self.warmup.take() else {
+            return;
+        };
+        if !warmup.is_ready() {
+            println!("{}", t!("cli.startup.initializing").dimmed());
+        }
+        for warning in warmup.wait().await {
+            eprintln!("{}", warning.yellow());
+        }
+        if let Some(report) = self.startup_profile.take_background() {
+            eprintln!("{}", report);
+        }
+        if !self.mcp.is_empty() {
+            self.rebuild_agent();
+        }
+    }
+
+    /// 切换对话模式，新的系统提示词从下一轮对话开始生效
+    fn switch_mode(&mut self, label: PromptLabel) {
+        let old_label = self.prompt_label;
+        self.prompt_label = label;
+        if let Some(builder) = self.agent_builder.as_mut() {
+            builder.set_mode(label.prompt_mode());
+        }
+        self.rebuild_agent();
+        self.show_mode_switch_hint(old_label, label);
+    }
+
+    /// 读取剪贴板中的图片，保存后显示预览并在输入框插入 `@` 引用（输入框内容保留）
+    fn paste_clipboard_image(&mut self, rl: &mut Reedline) {
+        let Some(source) = self.clipboard.as_mut() else {
+            return;
+        };
+        match clipboard::paste_image(source.as_mut(), Path::new(PASTES_DIR)) {
+            Ok(Some(image)) => {
+                println!();
+                println!("{}", self.image_preview.render(&image));
+                rl.run_edit_commands(&[EditCommand::InsertString(clipboard::reference(&image))]);
+            }
+            Ok(None) => {}
+            Err(e) => println!(
+                "\n{} {}",
+                "⚠️".yellow(),
+                t!("cli.image.paste_failed", error = e)
+            ),
+        }
+    }
+
+    /// 打开命令面板（Ctrl+K）
+    ///
+    /// 选中不需要参数的命令时返回该命令直接执行；其余的插入到输入框后返回 `None`，
+    /// 按 Esc 关闭时输入框不变。
+    fn open_palette(&self, rl: &mut Reedline) -> Option<String> {
+        let skills = crate::skill::SkillManager::new()
+            .map(|manager| manager.list_skills())
+            .unwrap_or_default();
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let stats = UsageStats::load();
+        let entries = palette::entries(&help::builtin_commands(), &skills, &stats, &cwd);
+        let palette = Palette::new(entries, stats);
+
+        println!();
+        let scorer =
+            |query: &str, _: &PaletteEntry, _: &str, index: usize| palette.sort_score(query, index);
+        let choice = Select::new(t!("cli.palette.title"), palette.entries().to_vec())
+            .with_help_message(t!("cli.palette.help"))
+            .with_page_size(12)
+            .with_scorer(&scorer)
+            .prompt_skippable()
+            .ok()
+            .flatten()?;
+        palette::record_use([&choice.key]);
+        match choice.action {
+            PaletteAction::Run(command) => Some(command),
+            PaletteAction::Insert(text) => {
+                rl.run_edit_commands(&[EditCommand::InsertString(text)]);
+                None
+            }
+        }
+    }
+
+    /// 切换回复风格，新的风格说明从下一轮对话开始生效
+    fn set_output(&mut self, output: OutputConfig) {
+        self.output = output;
+        self.tool_status.set_compact(output.style == OutputStyle::Concise);
+        if let Some(builder) = self.agent_builder.as_mut() {
+            builder.set_output(output);
+        }
+        self.rebuild_agent();
+    }
+
+    /// 按构建器的当前设置重建 Main Agent（系统提示词在构建时确定）
+    fn rebuild_agent(&mut self) {
+        let Some(builder) = self.agent_builder.as_ref() else {
+            return;
+        };
+        match builder.build_main() {
+            Ok(agent) => self.session.agent = agent,
+            Err(e) => {
+                use colored::Colorize;
+                println!("{}", t!("cli.mode.rebuild_failed", error = e).yellow());
+            }
         }
     }
 
@@ -619,13 +1031,18 @@ impl OxideCli {
         println!("{}", LOGO);
         self.show_welcome()?;
         self.show_tips()?;
+        self.warn_session_mismatches();
+        self.recover_interrupted_turn().await?;
+        if let Some(template) = self.startup_template.take() {
+            self.apply_template(&template);
+        }
 
         let result = self.run_input_loop().await;
 
         match result {
-            Ok(_) => println!("\n{}", "👋 Goodbye!".bright_cyan()),
+            Ok(_) => println!("\n{}", t!("cli.goodbye").bright_cyan()),
             Err(e) => {
-                println!("\n{} {}", "❌ Error:".red(), e);
+                println!("\n{} {}", t!("cli.error").red(), e);
                 return Err(e);
             }
         }
@@ -656,7 +1073,7 @@ impl OxideCli {
         // 按命令名称排序
         command_items.sort();
 
-        let selection = Select::new("选择命令 (输入过滤)", command_items).prompt()?;
+        let selection = Select::new(t!("cli.select.command_filter"), command_items).prompt()?;
 
         // 提取命令名称（去除描述部分）
         let selected = selection
@@ -672,14 +1089,12 @@ impl OxideCli {
     #[allow(dead_code)]
     fn show_context_selector(&self) -> Result<String> {
         // TODO: 实现文件/上下文选择
-        let context_items = vec![
-            "@file - 引用文件",
-            "@codebase - 搜索代码库",
-            "@web - 搜索网页",
-            "@docs - 搜索文档",
-        ];
+        let context_items: Vec<String> = build_context_entries()
+            .into_iter()
+            .map(|(name, description)| format!("{} - {}", name, description))
+            .collect();
 
-        let selection = Select::new("选择上下文 (输入过滤)", context_items).prompt()?;
+        let selection = Select::new(t!("cli.select.context_filter"), context_items).prompt()?;
 
         let selected = selection
             .split(" - ")
@@ -694,14 +1109,14 @@ impl OxideCli {
     #[allow(dead_code)]
     fn show_tag_selector(&self) -> Result<String> {
         // TODO: 实现标签选择
-        let tag_items = vec![
-            "#bug - 问题修复",
-            "#feature - 新功能",
-            "#refactor - 重构",
-            "#docs - 文档",
-        ];
+        let tag_items: Vec<String> = self
+            .tags
+            .entries()
+            .into_iter()
+            .map(|(name, description)| format!("{} - {}", name, description))
+            .collect();
 
-        let selection = Select::new("选择标签 (输入过滤)", tag_items).prompt()?;
+        let selection = Select::new(t!("cli.select.tag_filter"), tag_items).prompt()?;
 
         let selected = selection
             .split(" - ")
@@ -729,7 +1144,7 @@ impl OxideCli {
             KeyCode::Char('/'),
             ReedlineEvent::Multiple(vec![
                 ReedlineEvent::Edit(vec![EditCommand::InsertChar('/')]),
-                ReedlineEvent::Menu("oxide_completion".to_string()),
+                ReedlineEvent::Menu(COMPLETION_MENU.to_string()),
             ]),
         );
         keybindings.add_binding(
@@ -737,7 +1152,7 @@ impl OxideCli {
             KeyCode::Char('@'),
             ReedlineEvent::Multiple(vec![
                 ReedlineEvent::Edit(vec![EditCommand::InsertChar('@')]),
-                ReedlineEvent::Menu("oxide_completion".to_string()),
+                ReedlineEvent::Menu(COMPLETION_MENU.to_string()),
             ]),
         );
         keybindings.add_binding(
@@ -745,7 +1160,7 @@ impl OxideCli {
             KeyCode::Char('#'),
             ReedlineEvent::Multiple(vec![
                 ReedlineEvent::Edit(vec![EditCommand::InsertChar('#')]),
-                ReedlineEvent::Menu("oxide_completion".to_string()),
+                ReedlineEvent::Menu(COMPLETION_MENU.to_string()),
             ]),
         );
         keybindings.add_binding(
@@ -763,52 +1178,97 @@ impl OxideCli {
             KeyCode::Tab,
             ReedlineEvent::ExecuteHostCommand(PROMPT_CYCLE_COMMAND.to_string()),
         );
-
-        let edit_mode = Box::new(Emacs::new(keybindings));
-        let completion_menu = IdeMenu::default()
-            .with_name("oxide_completion")
-            .with_description_mode(DescriptionMode::PreferRight)
-            .with_max_completion_height(8)
-            .with_max_description_height(6)
-            .with_max_description_width(48)
-            .with_correct_cursor_pos(true)
-            .with_selected_text_style(Style::new().on(Color::Cyan).fg(Color::Black).bold()) // 选中项：青色背景+黑字 (实心高亮)
-            .with_text_style(Style::new().fg(Color::Fixed(252)))                             // 未选中项：接近纯白
-            .with_description_text_style(Style::new().fg(Color::Fixed(248)).italic())       // 描述：更浅的灰色+斜体
-            .with_match_text_style(Style::new().fg(Color::Green).underline())               // 匹配字：绿色下划线
-            .with_selected_match_text_style(Style::new().on(Color::Cyan).fg(Color::Black).underline().bold()); // 选中匹配：青底黑字+下划线
+        keybindings.add_binding(
+            KeyModifiers::CONTROL,
+            KeyCode::Char('v'),
+            ReedlineEvent::ExecuteHostCommand(PASTE_IMAGE_COMMAND.to_string()),
+        );
+        keybindings.add_binding(
+            KeyModifiers::CONTROL,
+            KeyCode::Char('k'),
+            ReedlineEvent::ExecuteHostCommand(PALETTE_COMMAND.to_string()),
+        );
+        completion_menu::add_keybindings(&mut keybindings);
+
+        let completion_waker = Arc::new(EditorWaker::default());
+        let edit_mode = Box::new(CompletionWakeMode::new(
+            Box::new(PasteEditMode::new(
+                Box::new(Emacs::new(keybindings)),
+                self.paste_limits,
+                Arc::clone(&self.pending_pastes),
+            )),
+            Arc::clone(&completion_waker),
+        ));
+        let completion_menu = CompletionMenu::new(COMPLETION_MENU).with_page_size(8);
 
         let mut rl = Reedline::create()
             .with_edit_mode(edit_mode)
-            .with_completer(Box::new(OxideCompleter))
+            .with_completer(Box::new(DebouncedCompleter::new(
+                OxideCompleter::new(Arc::clone(&self.file_index), &self.tags),
+                completion_waker,
+            )))
             .with_menu(ReedlineMenu::EngineCompleter(Box::new(completion_menu)));
+        if self.transient_prompt {
+            rl = rl.with_transient_prompt(Box::new(OxidePrompt::transient()));
+        }
+        if let Some(history) = FilteredHistory::open_default() {
+            rl = rl.with_history(Box::new(history));
+        }
 
         let mut last_ctrl_c: Option<Instant> = None;
 
         let mut skip_separator = false;
 
+        if let Some(report) = self.startup_profile.prompt_ready() {
+            eprintln!("{}", report);
+        }
+
         loop {
+            self.poll_warmup().await;
+
             // 每次循环重新创建 prompt 以获取最新的显示信息
-            let prompt = OxidePrompt::new(self.prompt_label);
+            let info = PromptInfo::new(
+                &self.model_name,
+                self.session.context.estimated_input_tokens(),
+            );
+            let prompt = OxidePrompt::new(self.prompt_label, Some(&info));
 
             if skip_separator {
                 skip_separator = false;
             } else {
                 self.print_separator()?;
             }
+            // 处理请求期间输入的文本放回输入框
+            let type_ahead = self.turn_keys.take_type_ahead();
+            if !type_ahead.is_empty() {
+                rl.run_edit_commands(&[EditCommand::InsertString(type_ahead)]);
+            }
             let readline = rl.read_line(&prompt);
             let final_input = match readline {
                 Ok(Signal::Success(line)) => {
                     if line == PROMPT_CYCLE_COMMAND {
-                        let old_label = self.prompt_label;
-                        self.prompt_label = self.prompt_label.next();
-
-                        // 显示模式切换提示
-                        self.show_mode_switch_hint(old_label, self.prompt_label);
+                        self.switch_mode(self.prompt_label.next());
 
                         skip_separator = true;
                         continue;
                     }
+                    if line == PASTE_IMAGE_COMMAND {
+                        self.paste_clipboard_image(&mut rl);
+                        skip_separator = true;
+                        continue;
+                    }
+                    let line = if line == PALETTE_COMMAND {
+                        // 直接执行选中的命令，输入框中的内容（包括粘贴占位符）留到之后提交
+                        match self.open_palette(&mut rl) {
+                            Some(command) => command,
+                            None => {
+                                skip_separator = true;
+                                continue;
+                            }
+                        }
+                    } else {
+                        self.pending_pastes.expand(&line)
+                    };
                     let input = line.trim().to_string();
                     if input.is_empty() {
                         continue;
@@ -817,6 +1277,7 @@ impl OxideCli {
                     input
                 }
                 Ok(Signal::CtrlC) => {
+                    self.pending_pastes.clear();
                     let now = Instant::now();
                     let should_exit = last_ctrl_c
                         .map(|prev| now.duration_since(prev) <= Duration::from_secs(1))
@@ -836,11 +1297,38 @@ impl OxideCli {
             };
 
             self.print_separator()?;
+            palette::record_command(&final_input);
 
-            let should_continue = self.handle_command(&final_input).await?;
-            if !should_continue {
+            // 斜杠命令可能打开编辑器或交互菜单，状态栏只在对话请求期间显示
+            let is_chat = !final_input.starts_with('/') && !final_input.starts_with('!');
+            if is_chat {
+                self.status_bar.start(self.session.context.session_id());
+                self.turn_keys.start();
+            }
+            let result = self.handle_command(&final_input).await;
+            if is_chat {
+                self.turn_keys.stop();
+                self.status_bar.stop().await;
+            }
+            if !result? {
                 break;
             }
+
+            // 编辑器历史由 Reedline 持有，只能在这里清空
+            if std::mem::take(&mut self.pending_history_clear) {
+                match rl.history_mut().clear() {
+                    Ok(()) => println!("{} {}", "✅".bright_green(), t!("cli.history.cleared")),
+                    Err(e) => println!(
+                        "{} {}",
+                        "❌".red(),
+                        t!("cli.history.clear_failed", error = e)
+                    ),
+                }
+            }
+        }
+
+        if self.session_learning.on_exit && std::io::stdin().is_terminal() {
+            self.save_session_notes().await;
         }
 
         Ok(())
@@ -858,7 +1346,7 @@ impl OxideCli {
 
     #[allow(dead_code)]
     pub fn session_id(&self) -> &str {
-        self.context_manager.session_id()
+        self.session.context.session_id()
     }
 
     fn reset_session_tokens(&self) {
@@ -876,21 +1364,21 @@ impl OxideCli {
         match new_label {
             PromptLabel::Plan => {
                 println!();
-                println!("{}", "📋 已切换到 Plan 模式".bright_cyan().bold());
-                println!("{}", "   所有任务将使用 PAOR 工作流处理".bright_white());
+                println!("{}", t!("cli.mode.plan").bright_cyan().bold());
+                println!("{}", t!("cli.mode.plan_hint").bright_white());
                 println!("{}", "   Planning → Acting → Observing → Reflecting".dimmed());
                 println!();
             }
             PromptLabel::Fast => {
                 println!();
-                println!("{}", "⚡ 已切换到 Fast 模式".bright_yellow().bold());
-                println!("{}", "   使用快速响应模式".bright_white());
+                println!("{}", t!("cli.mode.fast").bright_yellow().bold());
+                println!("{}", t!("cli.mode.fast_hint").bright_white());
                 println!();
             }
             PromptLabel::Oxide => {
                 println!();
-                println!("{}", "🦀 已切换到 Oxide 模式".bright_green().bold());
-                println!("{}", "   使用标准对话模式".bright_white());
+                println!("{}", t!("cli.mode.oxide").bright_green().bold());
+                println!("{}", t!("cli.mode.oxide_hint").bright_white());
                 println!();
             }
         }
@@ -904,6 +1392,35 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_short_model_name() {
+        assert_eq!(short_model_name("claude-sonnet-4-20250514"), "sonnet-4");
+        assert_eq!(short_model_name("anthropic/claude-opus-4-1"), "opus-4-1");
+        assert_eq!(short_model_name("gpt-4o"), "gpt-4o");
+        assert_eq!(short_model_name("gpt-4o-2024-08-06"), "gpt-4o-2024-08-06");
+    }
+
+    #[test]
+    fn test_prompt_info_percent() {
+        let info = PromptInfo::new("claude-sonnet-4-20250514", 50_000);
+        assert_eq!(info, PromptInfo { model: "sonnet-4".to_string(), context_percent: 25 });
+        assert_eq!(PromptInfo::new("gpt-4o", 1_000_000).context_percent, 100);
+    }
+
+    #[test]
+    fn test_prompt_layout_degrades_with_width() {
+        let info = PromptInfo { model: "sonnet-4".to_string(), context_percent: 12 };
+        let layout = |width| prompt_layout("oxide", Some(&info), width);
+
+        assert_eq!(layout(120), ("oxide> ".to_string(), "sonnet-4 · 12%".to_string()));
+        // 放不下完整信息时只保留百分比，再窄时去掉右侧
+        assert_eq!(layout(30), ("oxide> ".to_string(), "12%".to_string()));
+        assert_eq!(layout(27), ("oxide> ".to_string(), String::new()));
+        // 最后才缩短左侧
+        assert_eq!(layout(20), ("> ".to_string(), String::new()));
+        assert_eq!(prompt_layout("plan", None, 120), ("plan> ".to_string(), String::new()));
+    }
+
     #[test]
     fn test_list_files_recursive() {
         // 创建临时目录结构
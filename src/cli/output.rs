@@ -0,0 +1,142 @@
+//! 输出模式
+//!
+//! stdout 不是终端（`oxide | tee log.txt`）时使用纯文本模式：不输出颜色、spinner、
+//! 工具状态行、状态栏和动画，回复原样逐行写出，工具调用结束时输出一行
+//! `[tool] shell_execute: cargo test … ok (3.2s)`。`NO_COLOR` 环境变量和 `--no-color`
+//! 只关闭颜色。需要用户回答的提问见 `agent::interaction::interact`。
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// 输出模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// 终端，带颜色
+    Rich,
+    /// 终端，不带颜色
+    Monochrome,
+    /// 不是终端：逐行纯文本，不含任何转义序列
+    Plain,
+}
+
+impl OutputMode {
+    pub fn detect(stdout_is_terminal: bool, no_color: bool) -> Self {
+        match (stdout_is_terminal, no_color) {
+            (false, _) => OutputMode::Plain,
+            (true, true) => OutputMode::Monochrome,
+            (true, false) => OutputMode::Rich,
+        }
+    }
+
+    pub fn colors(self) -> bool {
+        self == OutputMode::Rich
+    }
+
+    pub fn is_plain(self) -> bool {
+        self == OutputMode::Plain
+    }
+}
+
+static MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// 按 stdout、`NO_COLOR` 和 `--no-color` 确定输出模式，启动时调用一次
+///
+/// 纯文本模式下同时隐藏工具的显示，只保留 `[tool] …` 状态行（见 `VerboseTools::hide`）。
+pub fn init(no_color: bool) -> OutputMode {
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let mode = *MODE.get_or_init(|| OutputMode::detect(io::stdout().is_terminal(), no_color));
+    if !mode.colors() {
+        colored::control::set_override(false);
+    }
+    if mode.is_plain() {
+        crate::tools::verbose_output::verbose_tools().hide();
+    }
+    mode
+}
+
+/// 当前输出模式（未调用 [`init`] 时只按 stdout 判断）
+pub fn mode() -> OutputMode {
+    *MODE.get_or_init(|| OutputMode::detect(io::stdout().is_terminal(), false))
+}
+
+struct PlainState {
+    out: Box<dyn Write + Send>,
+    /// 当前行还没有换行
+    line_open: bool,
+}
+
+/// 纯文本模式的输出：回复文本和工具状态行共用，状态行总是从新的一行开始
+pub struct PlainWriter {
+    state: Mutex<PlainState>,
+}
+
+impl PlainWriter {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            state: Mutex::new(PlainState {
+                out,
+                line_open: false,
+            }),
+        }
+    }
+
+    /// 写到 stdout 的共享实例
+    pub fn stdout() -> Arc<Self> {
+        static STDOUT: OnceLock<Arc<PlainWriter>> = OnceLock::new();
+        Arc::clone(STDOUT.get_or_init(|| Arc::new(Self::new(Box::new(io::stdout())))))
+    }
+
+    /// 原样写出一段文本
+    pub fn text(&self, text: &str) -> io::Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let mut state = self.lock();
+        state.out.write_all(text.as_bytes())?;
+        state.out.flush()?;
+        state.line_open = !text.ends_with('\n');
+        Ok(())
+    }
+
+    /// 写出完整的一行
+    pub fn line(&self, line: &str) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.line_open {
+            state.out.write_all(b"\n")?;
+        }
+        writeln!(state.out, "{}", line)?;
+        state.out.flush()?;
+        state.line_open = false;
+        Ok(())
+    }
+
+    /// 结束未换行的当前行
+    pub fn end_line(&self) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.line_open {
+            state.out.write_all(b"\n")?;
+            state.out.flush()?;
+            state.line_open = false;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PlainState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(OutputMode::detect(true, false), OutputMode::Rich);
+        assert_eq!(OutputMode::detect(true, true), OutputMode::Monochrome);
+        assert_eq!(OutputMode::detect(false, false), OutputMode::Plain);
+        assert_eq!(OutputMode::detect(false, true), OutputMode::Plain);
+        assert!(!OutputMode::Monochrome.colors());
+        assert!(!OutputMode::Monochrome.is_plain());
+    }
+}
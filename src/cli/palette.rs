@@ -0,0 +1,460 @@
+//! 命令面板（Ctrl+K）
+//!
+//! 在输入框按 Ctrl+K 打开可模糊搜索的列表：斜杠命令、自定义命令、技能、模式切换，以及最近在
+//! `@` 引用中用过的当前目录下的文件。输入文字过滤，Enter 对不需要参数的命令直接执行，
+//! 其余的插入到输入框（命令后留一个空格等待参数，文件插入为 `@` 引用）；Esc 关闭，
+//! 输入框的内容和光标位置保持不变。
+//!
+//! 排序时优先使用次数多的项：每次从面板选择、输入斜杠命令或 `@` 引用文件时计数，
+//! 记录在 `~/.oxide/usage-stats.json`。
+
+use chrono::{DateTime, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::help::{self, CommandHelp};
+use crate::agent::PromptMode;
+use crate::atomic_file::{read_json_or_recover, write_atomic};
+use crate::skill::Skill;
+
+/// 打开命令面板的宿主命令（Ctrl+K 触发，输入框内容保留）
+pub const PALETTE_COMMAND: &str = "__oxide_palette__";
+
+/// 面板中列出的最近使用的文件数
+const RECENT_FILES: usize = 20;
+
+/// 使用次数的加分权重（按次数的对数，常用项排在前面但不会压过明显更好的匹配）
+const FREQUENCY_WEIGHT: f64 = 16.0;
+
+/// 面板中一项的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Command,
+    CustomCommand,
+    Skill,
+    Mode,
+    File,
+}
+
+impl EntryKind {
+    fn label(self) -> &'static str {
+        match self {
+            EntryKind::Command => t!("cli.palette.kind_command"),
+            EntryKind::CustomCommand => t!("cli.palette.kind_custom"),
+            EntryKind::Skill => t!("cli.palette.kind_skill"),
+            EntryKind::Mode => t!("cli.palette.kind_mode"),
+            EntryKind::File => t!("cli.palette.kind_file"),
+        }
+    }
+}
+
+/// 选中一项后的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteAction {
+    /// 作为输入直接执行
+    Run(String),
+    /// 插入到输入框的光标处
+    Insert(String),
+}
+
+/// 面板中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub kind: EntryKind,
+    /// 显示和匹配的文本，如 `/checkpoint`、`/mode plan`、`@src/main.rs`
+    pub label: String,
+    pub description: String,
+    /// 使用次数的记录键（文件为绝对路径，其余与 `label` 相同）
+    pub key: String,
+    pub action: PaletteAction,
+}
+
+impl PaletteEntry {
+    fn new(kind: EntryKind, label: String, description: String, action: PaletteAction) -> Self {
+        Self {
+            kind,
+            key: label.clone(),
+            label,
+            description,
+            action,
+        }
+    }
+}
+
+impl fmt::Display for PaletteEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} · {}",
+            self.label,
+            self.description,
+            self.kind.label()
+        )
+    }
+}
+
+/// 内置命令、自定义命令、技能、模式切换和最近使用的文件
+pub fn entries(
+    commands: &[CommandHelp],
+    skills: &[Skill],
+    stats: &UsageStats,
+    cwd: &Path,
+) -> Vec<PaletteEntry> {
+    let mut entries: Vec<PaletteEntry> = commands
+        .iter()
+        .filter(|command| command.name.starts_with('/'))
+        .map(|command| {
+            let (description, action) = if command.usage() == command.name {
+                (
+                    command.description.clone(),
+                    PaletteAction::Run(command.name.clone()),
+                )
+            } else {
+                (
+                    format!("{} ({})", command.description, command.usage()),
+                    PaletteAction::Insert(format!("{} ", command.name)),
+                )
+            };
+            PaletteEntry::new(
+                EntryKind::Command,
+                command.name.clone(),
+                description,
+                action,
+            )
+        })
+        .collect();
+
+    for skill in skills {
+        let kind = if help::is_custom_command(skill) {
+            EntryKind::CustomCommand
+        } else {
+            EntryKind::Skill
+        };
+        let name = format!("/{}", skill.name);
+        let action = if skill.args.is_empty() {
+            PaletteAction::Run(name.clone())
+        } else {
+            PaletteAction::Insert(format!("{} ", name))
+        };
+        entries.push(PaletteEntry::new(
+            kind,
+            name,
+            skill.description.clone(),
+            action,
+        ));
+    }
+
+    for mode in PromptMode::ALL {
+        let command = format!("/mode {}", mode.as_str());
+        entries.push(PaletteEntry::new(
+            EntryKind::Mode,
+            command.clone(),
+            mode.description().to_string(),
+            PaletteAction::Run(command),
+        ));
+    }
+
+    for path in stats.recent_files(cwd, RECENT_FILES) {
+        let relative = path
+            .strip_prefix(cwd)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        let label = format!("@{}", relative);
+        entries.push(PaletteEntry {
+            kind: EntryKind::File,
+            description: t!("cli.palette.recent_file").to_string(),
+            key: file_key(&path),
+            action: PaletteAction::Insert(format!("{} ", label)),
+            label,
+        });
+    }
+    entries
+}
+
+/// 带排序的面板项
+pub struct Palette {
+    entries: Vec<PaletteEntry>,
+    stats: UsageStats,
+    matcher: SkimMatcherV2,
+}
+
+impl Palette {
+    /// 按使用次数排好初始顺序（次数相同时保持原顺序）
+    pub fn new(mut entries: Vec<PaletteEntry>, stats: UsageStats) -> Self {
+        entries.sort_by_key(|entry| std::cmp::Reverse(stats.count(&entry.key)));
+        Self {
+            entries,
+            stats,
+            matcher: SkimMatcherV2::default(),
+        }
+    }
+
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+
+    /// 匹配分数加使用次数的加分，不匹配时返回 `None`（查询为空时全部匹配）
+    pub fn score(&self, query: &str, entry: &PaletteEntry) -> Option<i64> {
+        let query = query.trim();
+        let matched = if query.is_empty() {
+            0
+        } else {
+            // 名称匹配优先于描述匹配
+            let label = self.matcher.fuzzy_match(&entry.label, query).map(|s| s * 2);
+            let description = self.matcher.fuzzy_match(&entry.description, query);
+            label.max(description)?
+        };
+        let count = self.stats.count(&entry.key) as f64;
+        Some(matched + (count.ln_1p() * FREQUENCY_WEIGHT) as i64)
+    }
+
+    /// 第 `index` 项的排序分数：分数相同时靠前的项排在前面（inquire 的排序不稳定）
+    pub fn sort_score(&self, query: &str, index: usize) -> Option<i64> {
+        let len = self.entries.len() as i64;
+        let score = self.score(query, self.entries.get(index)?)?;
+        Some(score * len + (len - index as i64))
+    }
+
+    /// 按查询过滤并排序
+    pub fn filter(&self, query: &str) -> Vec<&PaletteEntry> {
+        let mut scored: Vec<(i64, &PaletteEntry)> = (0..self.entries.len())
+            .filter_map(|index| Some((self.sort_score(query, index)?, &self.entries[index])))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+/// 一项的使用记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+/// 命令和文件的使用次数（`~/.oxide/usage-stats.json`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageStats {
+    #[serde(default)]
+    entries: BTreeMap<String, Usage>,
+}
+
+impl UsageStats {
+    /// 默认位置 `~/.oxide/usage-stats.json`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".oxide").join("usage-stats.json"))
+    }
+
+    /// 读取默认位置的记录，文件不存在或无法读取时为空
+    pub fn load() -> Self {
+        Self::default_path()
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        match read_json_or_recover(path) {
+            Ok(stats) => stats.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("failed to read {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, &content)
+    }
+
+    pub fn record(&mut self, key: &str, now: DateTime<Utc>) {
+        let usage = self.entries.entry(key.to_string()).or_insert(Usage {
+            count: 0,
+            last_used: now,
+        });
+        usage.count += 1;
+        usage.last_used = now;
+    }
+
+    pub fn count(&self, key: &str) -> u64 {
+        self.entries.get(key).map_or(0, |usage| usage.count)
+    }
+
+    /// `dir` 下仍然存在的、最近引用过的文件，最近使用的在前
+    pub fn recent_files(&self, dir: &Path, limit: usize) -> Vec<PathBuf> {
+        let mut files: Vec<(&DateTime<Utc>, PathBuf)> = self
+            .entries
+            .iter()
+            .filter_map(|(key, usage)| {
+                let path = PathBuf::from(key.strip_prefix('@')?);
+                (path.starts_with(dir) && path.is_file()).then_some((&usage.last_used, path))
+            })
+            .collect();
+        files.sort_by(|a, b| b.0.cmp(a.0));
+        files
+            .into_iter()
+            .take(limit)
+            .map(|(_, path)| path)
+            .collect()
+    }
+}
+
+/// 文件的记录键：`@` 加绝对路径
+fn file_key(path: &Path) -> String {
+    format!("@{}", path.display())
+}
+
+/// 记录一次使用并写回默认位置（失败时只记录警告）
+pub fn record_use<I, S>(keys: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let Some(path) = UsageStats::default_path() else {
+        return;
+    };
+    let mut stats = UsageStats::load_from(&path);
+    let now = Utc::now();
+    let mut changed = false;
+    for key in keys {
+        stats.record(key.as_ref(), now);
+        changed = true;
+    }
+    if changed {
+        if let Err(e) = stats.save_to(&path) {
+            tracing::warn!("failed to save {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// 记录输入的斜杠命令（只记命令名，不含参数）
+pub fn record_command(input: &str) {
+    if let Some(command) = input
+        .split_whitespace()
+        .next()
+        .filter(|c| c.starts_with('/'))
+    {
+        record_use([command]);
+    }
+}
+
+/// 记录 `@` 引用的文件（绝对路径）
+pub fn record_files<'a>(paths: impl IntoIterator<Item = &'a Path>) {
+    record_use(paths.into_iter().map(file_key));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn commands() -> Vec<CommandHelp> {
+        vec![
+            CommandHelp::new("/checkpoint", "Create or list checkpoints")
+                .with_usage("/checkpoint [name|list|delete <name>]"),
+            CommandHelp::new("/clear", "Clear the conversation"),
+            CommandHelp::new("/context", "Show context usage"),
+            CommandHelp::new("!", "Run a shell command"),
+        ]
+    }
+
+    fn labels<'a>(entries: &[&'a PaletteEntry]) -> Vec<&'a str> {
+        entries.iter().map(|entry| entry.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_and_actions() {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let temp_dir = TempDir::new().unwrap();
+        let palette = Palette::new(
+            entries(&commands(), &[], &UsageStats::default(), temp_dir.path()),
+            UsageStats::default(),
+        );
+
+        // 查询为空时全部列出，保持原顺序；`!` 不是斜杠命令
+        let all = palette.filter("");
+        assert_eq!(labels(&all)[..3], ["/checkpoint", "/clear", "/context"]);
+        assert!(labels(&all).contains(&"/mode plan"));
+        assert!(!labels(&all).contains(&"!"));
+
+        let matches = palette.filter("ctx");
+        assert_eq!(labels(&matches)[0], "/context");
+        assert!(!labels(&matches).contains(&"/clear"));
+        // 描述也参与匹配
+        assert_eq!(labels(&palette.filter("conversation")), ["/clear"]);
+
+        // 不需要参数的直接执行，需要参数的插入输入框并留出空格
+        let by_label = |label: &str| {
+            palette
+                .entries()
+                .iter()
+                .find(|entry| entry.label == label)
+                .unwrap()
+                .action
+                .clone()
+        };
+        assert_eq!(by_label("/clear"), PaletteAction::Run("/clear".to_string()));
+        assert_eq!(
+            by_label("/checkpoint"),
+            PaletteAction::Insert("/checkpoint ".to_string())
+        );
+        assert_eq!(
+            by_label("/mode fast"),
+            PaletteAction::Run("/mode fast".to_string())
+        );
+    }
+
+    #[test]
+    fn test_frequent_entries_rank_first() {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let temp_dir = TempDir::new().unwrap();
+        let cwd = temp_dir.path();
+        std::fs::create_dir_all(cwd.join("src")).unwrap();
+        for name in ["main.rs", "lib.rs"] {
+            std::fs::write(cwd.join("src").join(name), "").unwrap();
+        }
+
+        let now = Utc::now();
+        let mut stats = UsageStats::default();
+        for _ in 0..5 {
+            stats.record("/context", now);
+        }
+        stats.record(&file_key(&cwd.join("src/lib.rs")), now - Duration::hours(1));
+        stats.record(&file_key(&cwd.join("src/main.rs")), now);
+        // 已删除的文件和其他目录下的文件不列出
+        stats.record(&file_key(&cwd.join("gone.rs")), now);
+        stats.record("@/elsewhere/notes.md", now);
+
+        // 保存后读回
+        let path = cwd.join("usage-stats.json");
+        stats.save_to(&path).unwrap();
+        let stats = UsageStats::load_from(&path);
+        assert_eq!(stats.count("/context"), 5);
+
+        let palette = Palette::new(entries(&commands(), &[], &stats, cwd), stats.clone());
+        assert_eq!(labels(&palette.filter(""))[0], "/context");
+        // 同样匹配 "c" 时常用的排在前面
+        assert_eq!(labels(&palette.filter("c"))[0], "/context");
+
+        let files: Vec<&PaletteEntry> = palette
+            .entries()
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::File)
+            .collect();
+        assert_eq!(labels(&files), ["@src/main.rs", "@src/lib.rs"]);
+        assert_eq!(
+            files[0].action,
+            PaletteAction::Insert("@src/main.rs ".to_string())
+        );
+    }
+}
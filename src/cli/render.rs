@@ -1,23 +1,35 @@
 use anyhow::Result;
 use colored::*;
 use futures::StreamExt;
-use rig::agent::{FinalResponse, MultiTurnStreamItem, StreamingResult};
-use rig::streaming::StreamedAssistantContent;
+use rig::agent::FinalResponse;
 use std::io::{stdout, Write};
 use std::sync::OnceLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use termimad::MadSkin;
 use tokio::sync::oneshot;
-use tokio::time::interval;
-
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::agent::interaction;
+use crate::agent::stream_event::{StreamEvent, TurnStream};
+use crate::agent::tool_status;
+use crate::agent::advisory::Advisory;
+use crate::agent::turn_recap::TurnRecap;
+use crate::agent::turn_summary::TurnSummary;
+use crate::error::OxideError;
+
+use super::output::{self, PlainWriter};
+use super::stream_pacer::{take_chars, StreamPacer, StreamPacing};
 use super::OxideCli;
 
 /// 全局 Markdown 渲染器（线程安全）
 static MAD_SKIN: OnceLock<MadSkin> = OnceLock::new();
 
-/// 获取配置好的 MadSkin
+/// 获取配置好的 MadSkin（不输出颜色时不带任何样式）
 fn get_mad_skin() -> &'static MadSkin {
     MAD_SKIN.get_or_init(|| {
+        if !output::mode().colors() {
+            return MadSkin::no_style();
+        }
         let mut skin = MadSkin::default();
 
         // 自定义样式
@@ -34,6 +46,11 @@ fn get_mad_skin() -> &'static MadSkin {
     })
 }
 
+/// 以 Markdown 渲染并打印一段完整的文本（如命令详情页）
+pub fn print_markdown(text: &str) {
+    print!("{}", get_mad_skin().term_text(text));
+}
+
 const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub struct Spinner {
@@ -47,7 +64,11 @@ impl Spinner {
         }
     }
 
+    /// 开始转动（纯文本输出模式下不显示）
     pub fn start(&mut self, message: &str) {
+        if output::mode().is_plain() {
+            return;
+        }
         let (shutdown_tx, mut shutdown_rx) = tokio::sync::oneshot::channel();
         self.shutdown_tx = Some(shutdown_tx);
 
@@ -69,9 +90,11 @@ impl Spinner {
                     }
                     _ = ticker.tick() => {
                         let spinner = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
-                        print!("\r{} {}", spinner.yellow(), message.dimmed());
-                        use std::io::Write;
-                        std::io::stdout().flush().unwrap();
+                        interaction::draw(|| {
+                            print!("\r{} {}", spinner.yellow(), message.dimmed());
+                            use std::io::Write;
+                            std::io::stdout().flush().unwrap();
+                        });
                         frame += 1;
                     }
                 }
@@ -167,16 +190,54 @@ impl MarkdownStreamRenderer {
     }
 }
 
+/// 打印本轮摘要行，回复被截断时提示用 `/retry continue` 继续
+pub fn print_turn_summary(summary: &TurnSummary) {
+    println!("{}", summary.line().dimmed());
+    if summary.truncated() {
+        println!("{} {}", "⚠️".yellow(), t!("cli.turn_summary.truncated").yellow());
+    }
+}
+
+/// 打印本轮的活动回顾（暗色页脚，第一行为总览，其余缩进）
+pub fn print_turn_recap(recap: &TurnRecap) {
+    for (i, line) in recap.lines().iter().enumerate() {
+        let indent = if i == 0 { "" } else { "  " };
+        println!("{}{}", indent, line.dimmed());
+    }
+}
+
+/// 打印一条会话质量提示
+pub fn print_advisory(advisory: &Advisory) {
+    println!("{} {}", "💡".bright_blue(), advisory.text().yellow());
+}
+
+/// 打印模型请求错误：错误信息、操作提示和服务商请求 ID
+pub fn print_error(error: &OxideError) {
+    eprintln!("{} {}", "Error:".red(), error);
+    print_error_hint(error);
+}
+
+/// 打印错误的操作提示和服务商请求 ID（错误信息已由调用方输出）
+pub fn print_error_hint(error: &OxideError) {
+    if let Some(hint) = error.user_hint() {
+        eprintln!("{} {}", "💡".bright_blue(), hint.yellow());
+    }
+    if let Some(id) = &error.request_id {
+        eprintln!("{}", t!("error.request_id", id = id).dimmed());
+    }
+}
+
 /// 自定义流式输出函数，替代 rig 的 stream_to_stdout
 /// 去掉 "Response:" 前缀，并在 "● oxide:" 后添加动画效果
-/// 支持实时 Markdown 渲染
-pub async fn stream_with_animation<R>(
-    stream: &mut StreamingResult<R>,
-) -> Result<FinalResponse, std::io::Error>
-where
-    R: Send + 'static,
-{
-    let mut final_res = FinalResponse::empty();
+/// 支持实时 Markdown 渲染，输出节奏由 `StreamPacer` 自适应控制
+pub async fn stream_with_animation(
+    stream: &mut TurnStream,
+    pacing: StreamPacing,
+) -> Result<FinalResponse, std::io::Error> {
+    if output::mode().is_plain() {
+        return stream_plain(stream, &PlainWriter::stdout()).await;
+    }
+
     let (stop_spinner_tx, mut stop_spinner_rx) = oneshot::channel();
     let mut stop_spinner_tx = Some(stop_spinner_tx);
 
@@ -197,8 +258,10 @@ where
                 }
                 _ = ticker.tick() => {
                     let spinner = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
-                    print!("\r{} {}", spinner.blue(), "oxide:".dimmed());
-                    stdout().flush().unwrap();
+                    interaction::draw(|| {
+                        print!("\r{} {}", spinner.blue(), "oxide:".dimmed());
+                        stdout().flush().unwrap();
+                    });
                     frame += 1;
                 }
             }
@@ -210,11 +273,38 @@ where
     let mut renderer = MarkdownStreamRenderer::new();
     let skin = get_mad_skin();
 
-    while let Some(content) = stream.next().await {
-        match content {
-            Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(
-                text,
-            ))) => {
+    // 已收到但尚未显示的文本
+    let mut pending = String::new();
+    let mut pacer = StreamPacer::new(pacing);
+    let mut ticker = interval(pacing.tick);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        let event = tokio::select! {
+            event = stream.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = ticker.tick(), if pacer.backlog() > 0 => {
+                // 工具提问期间暂停输出积压的文本
+                interaction::draw(|| {
+                    let chunk = take_chars(&mut pending, pacer.next_chunk(Instant::now()));
+                    renderer.process_text(&chunk, skin);
+                });
+                continue;
+            }
+        };
+
+        tool_status::emit_stream(&event);
+
+        // 工具调用、思考内容、取消等事件之前先输出积压的文本，保证显示顺序且不丢内容
+        if !matches!(event, StreamEvent::TextDelta { .. }) && pacer.backlog() > 0 {
+            let chunk = take_chars(&mut pending, pacer.flush());
+            renderer.process_text(&chunk, skin);
+        }
+
+        match event {
+            StreamEvent::TextDelta { text } => {
                 if first_content {
                     // 收到第一个文本块，停止 spinner
                     if let Some(tx) = stop_spinner_tx.take() {
@@ -227,12 +317,16 @@ where
                     first_content = false;
                 }
 
-                // 使用 Markdown 渲染器处理文本
-                renderer.process_text(&text.text, skin);
+                pending.push_str(&text);
+                pacer.on_received(text.chars().count(), Instant::now());
+
+                // 不做动画时收到即输出
+                if !pacer.is_animating() {
+                    let chunk = take_chars(&mut pending, pacer.next_chunk(Instant::now()));
+                    renderer.process_text(&chunk, skin);
+                }
             }
-            Ok(MultiTurnStreamItem::StreamAssistantItem(
-                StreamedAssistantContent::Reasoning(r),
-            )) => {
+            StreamEvent::ThinkingDelta { text } => {
                 if first_content {
                     // 收到第一个内容块，停止 spinner
                     if let Some(tx) = stop_spinner_tx.take() {
@@ -243,34 +337,34 @@ where
                     }
                     first_content = false;
                 }
-                let reasoning = r.reasoning.join("\n");
                 // Reasoning 内容直接输出（通常不含 markdown）
-                print!("{}", reasoning.dimmed());
+                print!("{}", text.dimmed());
                 stdout().flush().unwrap();
             }
-            Ok(MultiTurnStreamItem::FinalResponse(res)) => {
-                final_res = res;
-            }
-            Err(err) => {
-                let err_msg = err.to_string();
-                if err_msg.contains("PromptCancelled") {
-                    if let Some(tx) = stop_spinner_tx.take() {
-                        let _ = tx.send(());
-                    }
-                    if let Some(handle) = spinner_handle.take() {
-                        let _ = handle.await;
-                    }
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Interrupted,
-                        "prompt_cancelled",
-                    ));
+            event if event.is_cancelled() => {
+                if let Some(tx) = stop_spinner_tx.take() {
+                    let _ = tx.send(());
+                }
+                if let Some(handle) = spinner_handle.take() {
+                    let _ = handle.await;
                 }
-                eprintln!("Error: {}", err);
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "prompt_cancelled",
+                ));
+            }
+            StreamEvent::Error { message } => {
+                print_error(&OxideError::classify(message));
             }
             _ => {}
         }
     }
 
+    // 流结束后立即输出剩余内容，不依赖节拍循环
+    pacer.finish();
+    let chunk = take_chars(&mut pending, pacer.next_chunk(Instant::now()));
+    renderer.process_text(&chunk, skin);
+
     // 完成渲染
     renderer.finish(skin);
 
@@ -284,19 +378,56 @@ where
         }
     }
 
-    Ok(final_res)
+    Ok(stream.take_final_response())
+}
+
+/// 纯文本输出模式：不显示 spinner、不做动画和 Markdown 渲染，收到的文本原样写出
+async fn stream_plain(
+    stream: &mut TurnStream,
+    out: &PlainWriter,
+) -> Result<FinalResponse, std::io::Error> {
+    let mut first_content = true;
+    while let Some(event) = stream.next().await {
+        tool_status::emit_stream(&event);
+        match event {
+            StreamEvent::TextDelta { text } | StreamEvent::ThinkingDelta { text } => {
+                if first_content {
+                    out.text("● oxide: ")?;
+                    first_content = false;
+                }
+                out.text(&text)?;
+            }
+            event if event.is_cancelled() => {
+                out.end_line()?;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "prompt_cancelled",
+                ));
+            }
+            StreamEvent::Error { message } => {
+                out.end_line()?;
+                print_error(&OxideError::classify(message));
+            }
+            _ => {}
+        }
+    }
+    out.end_line()?;
+    Ok(stream.take_final_response())
 }
 
 impl OxideCli {
     pub fn show_welcome(&self) -> Result<()> {
-        println!("{}", "✨ Welcome to Oxide CLI v0.1.0!".bright_green());
+        println!(
+            "{}",
+            t!("cli.welcome.title", version = "0.1.0").bright_green()
+        );
         println!(
             "{} {} | {} {} | {} {}",
-            "Session:".dimmed(),
-            self.context_manager.session_id(),
-            "cwd:".dimmed(),
+            t!("cli.welcome.session").dimmed(),
+            self.session.context.session_id(),
+            t!("cli.welcome.cwd").dimmed(),
             std::env::current_dir().unwrap().display(),
-            "model:".dimmed(),
+            t!("cli.welcome.model").dimmed(),
             self.model_name
         );
         println!();
@@ -304,20 +435,172 @@ impl OxideCli {
     }
 
     pub fn show_tips(&self) -> Result<()> {
-        println!("{}", "Tips for getting started:".bright_white());
+        println!("{}", t!("cli.tips.title").bright_white());
         println!();
-        println!(
-            "{} Ask questions, edit files, or run commands.",
-            "1.".bright_white()
-        );
-        println!("{} Be specific for the best results.", "2.".bright_white());
-        println!("{} Type /help for more information.", "3.".bright_white());
+        println!("{} {}", "1.".bright_white(), t!("cli.tips.ask"));
+        println!("{} {}", "2.".bright_white(), t!("cli.tips.specific"));
+        println!("{} {}", "3.".bright_white(), t!("cli.tips.help"));
         println!();
-        println!(
-            "{}",
-            "ctrl+c twice within 1s to exit, /help for commands, Tab for completion".dimmed()
-        );
+        println!("{}", t!("cli.tips.keys").dimmed());
         println!();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::hitl_integration::MaybeHitlTool;
+    use crate::agent::tool_status::{ToolStatusEvent, ToolStatusSink};
+    use crate::agent::turn_summary::StopReason;
+    use crate::cli::tool_status::ToolStatusDisplay;
+    use crate::tools::read_file::{ReadFileArgs, WrappedReadFileTool};
+    use crate::tools::verbose_output::verbose_tools;
+    use crate::tools::ToolSettings;
+    use regex::Regex;
+    use rig::agent::MultiTurnStreamItem;
+    use rig::completion::message::Text;
+    use rig::streaming::StreamedAssistantContent;
+    use rig::tool::Tool;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// 收集写出的内容
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn model_request(chunks: &[&str]) -> TurnStream {
+        let done = MultiTurnStreamItem::FinalResponse(FinalResponse::empty());
+        let items: Vec<Result<MultiTurnStreamItem<()>, String>> = chunks
+            .iter()
+            .map(|chunk| {
+                Ok(MultiTurnStreamItem::StreamAssistantItem(
+                    StreamedAssistantContent::Text(Text {
+                        text: chunk.to_string(),
+                    }),
+                ))
+            })
+            .chain([Ok(done)])
+            .collect();
+        TurnStream::new(futures::stream::iter(items))
+    }
+
+    /// 只转发指定文件上的工具调用事件（其他测试也在并行调用工具）
+    struct FileCalls {
+        files: [&'static str; 2],
+        calls: Mutex<Vec<u64>>,
+        display: ToolStatusDisplay,
+    }
+
+    impl ToolStatusSink for FileCalls {
+        fn handle(&self, event: &ToolStatusEvent) {
+            let call_id = match event {
+                ToolStatusEvent::ToolStarted {
+                    call_id, summary, ..
+                } => {
+                    if self.files.contains(&summary.as_str()) {
+                        self.calls.lock().unwrap().push(*call_id);
+                    }
+                    *call_id
+                }
+                ToolStatusEvent::ToolCompleted { call_id, .. }
+                | ToolStatusEvent::ToolFailed { call_id, .. } => *call_id,
+                _ => return,
+            };
+            if self.calls.lock().unwrap().contains(&call_id) {
+                self.display.handle(event);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plain_turn_has_no_escape_sequences() {
+        let project = TempDir::new().unwrap();
+        std::fs::write(project.path().join("plain_turn.txt"), "remember the milk\n").unwrap();
+        let settings = ToolSettings {
+            root: Some(project.path().to_path_buf()),
+            ..Default::default()
+        };
+        let captured = Captured::default();
+        let out = Arc::new(PlainWriter::new(Box::new(captured.clone())));
+        tool_status::register_sink(Arc::new(FileCalls {
+            files: ["plain_turn.txt", "plain_turn_missing.txt"],
+            calls: Mutex::default(),
+            display: ToolStatusDisplay::new().with_plain_output(Arc::clone(&out)),
+        }));
+
+        // 工具自己的显示（标题、摘要行）在纯文本模式下隐藏
+        let decorations = Captured::default();
+        verbose_tools().redirect(Box::new(decorations.clone()));
+        let read_file = WrappedReadFileTool::new(&settings);
+        let args = ReadFileArgs {
+            file_path: "plain_turn.txt".to_string(),
+            offset: None,
+            limit: None,
+        };
+        read_file.call(args).await.unwrap();
+        assert!(String::from_utf8_lossy(&decorations.0.lock().unwrap()).contains("Read("));
+        verbose_tools().hide();
+        let shown = decorations.0.lock().unwrap().len();
+
+        let read_file = MaybeHitlTool::new(read_file, None);
+        stream_plain(&mut model_request(&["I'll read ", "the **notes**."]), &out)
+            .await
+            .unwrap();
+        for file in ["plain_turn.txt", "plain_turn_missing.txt"] {
+            let _ = read_file.call(json!({ "file_path": file })).await;
+        }
+        stream_plain(
+            &mut model_request(&["It says:\n", "```\nremember the milk\n```\n"]),
+            &out,
+        )
+        .await
+        .unwrap();
+        let summary = TurnSummary {
+            elapsed: Duration::from_millis(5300),
+            tool_calls: 2,
+            input_tokens: 1200,
+            output_tokens: 80,
+            stop_reason: StopReason::EndTurn,
+        };
+        out.line(&summary.line()).unwrap();
+
+        assert_eq!(
+            decorations.0.lock().unwrap().len(),
+            shown,
+            "tool output in plain mode"
+        );
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains('\x1b'), "escape sequence in {:?}", output);
+        assert!(!output.contains('\r'));
+        let output = Regex::new(r"\(\d+\.\ds\)")
+            .unwrap()
+            .replace_all(&output, "(…s)");
+        let (output, error) = output.split_once("): ").unwrap();
+        let (error, rest) = error.split_once('\n').unwrap();
+        assert!(error.starts_with("File not found"), "{}", error);
+        assert_eq!(
+            format!("{}): …\n{}", output, rest),
+            format!(
+                "● oxide: I'll read the **notes**.\n\
+                 [tool] read_file: plain_turn.txt … ok (…s)\n\
+                 [tool] read_file: plain_turn_missing.txt … failed (…s): …\n\
+                 ● oxide: It says:\n```\nremember the milk\n```\n\
+                 {}\n",
+                summary.line()
+            )
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! 底部状态栏
+//!
+//! 处理请求期间用 DECSTBM 把终端最后一行划出滚动区域，在这一行显示会话 ID、模型、
+//! 累计 token 和转动的指示符。流式输出、Spinner 和工具状态行（MultiProgress）
+//! 都在上方的滚动区域内绘制，状态栏只在保存/恢复光标之间写最后一行，不移动它们的光标。
+//! 等待输入时不占用底部行，避免与 reedline 重绘提示符冲突；工具提问期间暂时恢复完整的
+//! 滚动区域（见 [`ScrollRegion`]），提问结束后重新保留。
+//! 处理请求期间的提示（如 Ctrl+O 切换详细输出）通过 [`StatusNotice`] 在状态栏上短暂显示。
+
+use colored::*;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::agent::interaction::{self, Pausable};
+
+/// 刷新间隔
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 终端行数少于该值时不显示状态栏
+const MIN_ROWS: u16 = 3;
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// 提示在状态栏上显示的时长
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// 恢复完整滚动区域
+const RESET_SCROLL_REGION: &str = "\x1b[r";
+
+/// 生成状态栏的终端控制序列
+#[derive(Debug, Default)]
+struct Painter {
+    /// 已保留底部行时的终端行数
+    rows: Option<u16>,
+    /// 提问期间暂时释放的终端行数
+    suspended: Option<u16>,
+}
+
+impl Painter {
+    /// 保留最后一行：先换行留出空行，再把滚动区域设为第 1 行到倒数第 2 行
+    ///
+    /// 设置滚动区域会把光标移到左上角，所以前后保存/恢复光标。
+    fn reserve(&mut self, rows: u16) -> String {
+        if rows < MIN_ROWS {
+            return String::new();
+        }
+        self.rows = Some(rows);
+        format!("\n\x1b[1A\x1b7\x1b[1;{}r\x1b8", rows - 1)
+    }
+
+    /// 终端大小变化后按新的行数重新设置滚动区域
+    fn resize(&mut self, rows: u16) -> String {
+        match self.rows {
+            Some(current) if current != rows && rows >= MIN_ROWS => {
+                self.rows = Some(rows);
+                format!("\x1b7\x1b[1;{}r\x1b8", rows - 1)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// 重绘最后一行
+    fn draw(&self, line: &str) -> String {
+        match self.rows {
+            Some(rows) => format!("\x1b7\x1b[{};1H\x1b[2K{}\x1b8", rows, line),
+            None => String::new(),
+        }
+    }
+
+    /// 暂时恢复完整滚动区域并清除状态行，`resume` 时按原来的行数重新保留
+    fn suspend(&mut self) -> String {
+        let rows = self.rows;
+        let sequence = self.release();
+        self.suspended = rows;
+        sequence
+    }
+
+    fn resume(&mut self) -> String {
+        match self.suspended.take() {
+            Some(rows) => self.reserve(rows),
+            None => String::new(),
+        }
+    }
+
+    /// 恢复完整滚动区域并清除状态行
+    fn release(&mut self) -> String {
+        self.suspended = None;
+        match self.rows.take() {
+            Some(rows) => format!("\x1b7{}\x1b[{};1H\x1b[2K\x1b8", RESET_SCROLL_REGION, rows),
+            None => String::new(),
+        }
+    }
+}
+
+/// 状态栏显示的内容
+#[derive(Debug, Clone)]
+struct StatusInfo {
+    session_id: String,
+    model: String,
+    tokens: Arc<AtomicU64>,
+    notice: Arc<StatusNotice>,
+}
+
+impl StatusInfo {
+    fn line(&self, frame: usize, width: u16) -> String {
+        let spinner = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
+        let notice = self.notice.current(Instant::now());
+        let text = match &notice {
+            Some(notice) => format!(" {}", notice),
+            None => format!(
+                " {} │ {} │ {} tokens",
+                self.session_id,
+                self.model,
+                self.tokens.load(Ordering::Relaxed)
+            ),
+        };
+        // 留出指示符和行尾各一列，避免写满最后一列触发换行
+        let text: String = text
+            .chars()
+            .take((width as usize).saturating_sub(3))
+            .collect();
+        match notice {
+            Some(_) => format!("{}{}", spinner.yellow(), text.bright_yellow()),
+            None => format!("{}{}", spinner.yellow(), text.dimmed()),
+        }
+    }
+}
+
+/// 在状态栏上短暂显示的提示
+#[derive(Debug, Default)]
+pub struct StatusNotice {
+    /// 状态栏是否正在显示
+    visible: AtomicBool,
+    text: Mutex<Option<(String, Instant)>>,
+}
+
+impl StatusNotice {
+    /// 在状态栏上显示 `text`，状态栏未显示时返回 false（由调用方另行输出）
+    pub fn show(&self, text: &str) -> bool {
+        if !self.visible.load(Ordering::Relaxed) {
+            return false;
+        }
+        if let Ok(mut current) = self.text.lock() {
+            *current = Some((text.to_string(), Instant::now()));
+        }
+        true
+    }
+
+    fn current(&self, now: Instant) -> Option<String> {
+        let current = self.text.lock().ok()?;
+        let (text, shown_at) = current.as_ref()?;
+        (now.duration_since(*shown_at) < NOTICE_DURATION).then(|| text.clone())
+    }
+
+    fn set_visible(&self, visible: bool) {
+        self.visible.store(visible, Ordering::Relaxed);
+        if !visible {
+            if let Ok(mut current) = self.text.lock() {
+                *current = None;
+            }
+        }
+    }
+}
+
+/// 状态栏保留的滚动区域
+///
+/// 注册到 `interaction` 后，工具提问期间恢复完整的滚动区域，提问可以使用整个终端。
+#[derive(Debug, Default)]
+pub struct ScrollRegion {
+    painter: Mutex<Painter>,
+}
+
+impl ScrollRegion {
+    fn painter(&self) -> MutexGuard<'_, Painter> {
+        self.painter.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Pausable for ScrollRegion {
+    fn pause(&self) {
+        let sequence = self.painter().suspend();
+        write_sequence(&mut io::stdout(), &sequence);
+    }
+
+    fn resume(&self) {
+        let sequence = self.painter().resume();
+        write_sequence(&mut io::stdout(), &sequence);
+    }
+}
+
+/// 刷新任务：保留底部行，定时重绘，收到停止信号后恢复终端
+async fn run<W, S>(
+    mut out: W,
+    info: StatusInfo,
+    size: S,
+    region: Arc<ScrollRegion>,
+    mut shutdown: oneshot::Receiver<()>,
+) where
+    W: Write,
+    S: Fn() -> Option<(u16, u16)>,
+{
+    let Some((mut width, rows)) = size() else {
+        return;
+    };
+    let reserve = region.painter().reserve(rows);
+    write_sequence(&mut out, &reserve);
+    info.notice.set_visible(!reserve.is_empty());
+
+    let mut ticker = interval(TICK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut frame = 0;
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = ticker.tick() => {
+                // 工具提问期间不重绘，避免光标跳到底部打断提问
+                interaction::draw(|| {
+                    let mut painter = region.painter();
+                    let mut sequence = String::new();
+                    if let Some((new_width, new_rows)) = size() {
+                        width = new_width;
+                        sequence.push_str(&painter.resize(new_rows));
+                    }
+                    sequence.push_str(&painter.draw(&info.line(frame, width)));
+                    write_sequence(&mut out, &sequence);
+                });
+                frame += 1;
+            }
+        }
+    }
+
+    info.notice.set_visible(false);
+    let release = region.painter().release();
+    write_sequence(&mut out, &release);
+}
+
+fn write_sequence<W: Write>(out: &mut W, sequence: &str) {
+    if sequence.is_empty() {
+        return;
+    }
+    let _ = out.write_all(sequence.as_bytes());
+    let _ = out.flush();
+}
+
+fn terminal_size() -> Option<(u16, u16)> {
+    crossterm::terminal::size().ok()
+}
+
+/// 底部状态栏
+pub struct StatusBar {
+    enabled: bool,
+    model: String,
+    tokens: Arc<AtomicU64>,
+    region: Arc<ScrollRegion>,
+    notice: Arc<StatusNotice>,
+    running: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+}
+
+impl StatusBar {
+    pub fn new(model: String, tokens: Arc<AtomicU64>, enabled: bool) -> Self {
+        Self {
+            enabled,
+            model,
+            tokens,
+            region: Arc::new(ScrollRegion::default()),
+            notice: Arc::new(StatusNotice::default()),
+            running: None,
+        }
+    }
+
+    /// 保留的滚动区域，需要注册到 `interaction` 以便提问期间暂停
+    pub fn region(&self) -> Arc<ScrollRegion> {
+        Arc::clone(&self.region)
+    }
+
+    /// 状态栏上的提示，可在其他线程中显示
+    pub fn notice(&self) -> Arc<StatusNotice> {
+        Arc::clone(&self.notice)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// 开始显示（未启用或输出不是终端时不做任何事）
+    pub fn start(&mut self, session_id: &str) {
+        if !self.enabled || self.running.is_some() || !io::stdout().is_terminal() {
+            return;
+        }
+        let info = StatusInfo {
+            session_id: session_id.to_string(),
+            model: self.model.clone(),
+            tokens: Arc::clone(&self.tokens),
+            notice: Arc::clone(&self.notice),
+        };
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let handle = tokio::spawn(run(
+            io::stdout(),
+            info,
+            terminal_size,
+            Arc::clone(&self.region),
+            shutdown_rx,
+        ));
+        self.running = Some((shutdown_tx, handle));
+    }
+
+    /// 停止刷新，等恢复终端的序列写出后返回
+    pub async fn stop(&mut self) {
+        if let Some((shutdown_tx, handle)) = self.running.take() {
+            let _ = shutdown_tx.send(());
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for StatusBar {
+    /// 未正常停止（如出错提前返回）时也要恢复滚动区域，避免影响退出后的 shell
+    fn drop(&mut self) {
+        if let Some((_, handle)) = self.running.take() {
+            handle.abort();
+            write_sequence(&mut io::stdout(), RESET_SCROLL_REGION);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录写入内容的输出
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Capture {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn info() -> StatusInfo {
+        StatusInfo {
+            session_id: "session-1".to_string(),
+            model: "claude-sonnet".to_string(),
+            tokens: Arc::new(AtomicU64::new(1234)),
+            notice: Arc::new(StatusNotice::default()),
+        }
+    }
+
+    #[test]
+    fn test_line_fits_width() {
+        colored::control::set_override(false);
+        let line = info().line(0, 80);
+        assert_eq!(line, "⠋ session-1 │ claude-sonnet │ 1234 tokens");
+        assert_eq!(info().line(1, 12).chars().count(), 10);
+    }
+
+    #[test]
+    fn test_notice_replaces_line_briefly() {
+        colored::control::set_override(false);
+        let info = info();
+        // 状态栏未显示时由调用方输出
+        assert!(!info.notice.show("verbose tool output on"));
+
+        info.notice.set_visible(true);
+        assert!(info.notice.show("verbose tool output on"));
+        assert_eq!(info.line(0, 80), "⠋ verbose tool output on");
+        let later = Instant::now() + NOTICE_DURATION;
+        assert_eq!(info.notice.current(later), None);
+
+        info.notice.set_visible(false);
+        assert_eq!(
+            info.line(0, 80),
+            "⠋ session-1 │ claude-sonnet │ 1234 tokens"
+        );
+    }
+
+    #[test]
+    fn test_painter_resize_and_release() {
+        let mut painter = Painter::default();
+        assert_eq!(painter.draw("x"), "");
+        assert_eq!(painter.reserve(2), "");
+        assert_eq!(painter.release(), "");
+
+        assert_eq!(painter.reserve(24), "\n\x1b[1A\x1b7\x1b[1;23r\x1b8");
+        assert_eq!(painter.resize(24), "");
+        assert_eq!(painter.resize(30), "\x1b7\x1b[1;29r\x1b8");
+        assert_eq!(painter.draw("x"), "\x1b7\x1b[30;1H\x1b[2Kx\x1b8");
+        assert_eq!(painter.release(), "\x1b7\x1b[r\x1b[30;1H\x1b[2K\x1b8");
+        assert_eq!(painter.release(), "");
+    }
+
+    #[test]
+    fn test_painter_suspend_and_resume() {
+        let mut painter = Painter::default();
+        assert_eq!(painter.suspend(), "");
+        assert_eq!(painter.resume(), "");
+
+        painter.reserve(24);
+        assert_eq!(painter.suspend(), "\x1b7\x1b[r\x1b[24;1H\x1b[2K\x1b8");
+        assert_eq!(painter.draw("x"), "");
+        assert_eq!(painter.resume(), "\n\x1b[1A\x1b7\x1b[1;23r\x1b8");
+        assert_eq!(painter.draw("x"), "\x1b7\x1b[24;1H\x1b[2Kx\x1b8");
+
+        // 提问期间状态栏停止时不再重新保留
+        painter.suspend();
+        assert_eq!(painter.release(), "");
+        assert_eq!(painter.resume(), "");
+    }
+
+    #[tokio::test]
+    async fn test_stop_restores_terminal() {
+        let capture = Capture::default();
+        let sizes = Arc::new(Mutex::new((80, 24)));
+        let size = {
+            let sizes = Arc::clone(&sizes);
+            move || Some(*sizes.lock().unwrap())
+        };
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let region = Arc::new(ScrollRegion::default());
+        let task = tokio::spawn(run(capture.clone(), info(), size, region, shutdown_rx));
+
+        tokio::time::sleep(TICK_INTERVAL * 2).await;
+        *sizes.lock().unwrap() = (100, 40);
+        tokio::time::sleep(TICK_INTERVAL * 2).await;
+        shutdown_tx.send(()).unwrap();
+        task.await.unwrap();
+
+        let output = capture.text();
+        assert!(output.starts_with("\n\x1b[1A\x1b7\x1b[1;23r\x1b8"));
+        assert!(output.contains("\x1b[1;39r"));
+        assert!(output.contains("\x1b[40;1H\x1b[2K"));
+        // 每次保存光标都有对应的恢复，最后一个序列恢复完整滚动区域并清除状态行
+        assert_eq!(
+            output.matches("\x1b7").count(),
+            output.matches("\x1b8").count()
+        );
+        assert!(output.ends_with("\x1b7\x1b[r\x1b[40;1H\x1b[2K\x1b8"));
+    }
+}
@@ -0,0 +1,292 @@
+//! 流式输出节奏控制
+//!
+//! 网络流到达的文本先进入缓冲区，再按节拍输出到终端。每次输出的字符数
+//! 根据积压量自适应：积压的文本必须在最后一次到达后 `catch_up` 时间内显示完，
+//! 因此快模型不会被动画拖慢，慢模型也不会一顿一顿地输出。
+//! 消息结束时立即输出全部剩余内容；超大消息或关闭动画时不做动画。
+
+use std::time::{Duration, Instant};
+
+/// 默认节拍间隔
+const DEFAULT_TICK: Duration = Duration::from_millis(16);
+
+/// 默认追赶时间：显示最多落后网络流这么久
+const DEFAULT_CATCH_UP: Duration = Duration::from_millis(300);
+
+/// 超过该字符数的消息不做动画
+const DEFAULT_MAX_ANIMATED_CHARS: usize = 20_000;
+
+/// 流式输出节奏配置
+#[derive(Debug, Clone, Copy)]
+pub struct StreamPacing {
+    /// 是否启用动画（`stream_animation = false` 时直接输出）
+    pub enabled: bool,
+    /// 每个节拍至少输出的字符数
+    pub min_chars_per_tick: usize,
+    /// 节拍间隔
+    pub tick: Duration,
+    /// 追赶时间
+    pub catch_up: Duration,
+    /// 超过该字符数的消息不做动画
+    pub max_animated_chars: usize,
+}
+
+impl Default for StreamPacing {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_chars_per_tick: 1,
+            tick: DEFAULT_TICK,
+            catch_up: DEFAULT_CATCH_UP,
+            max_animated_chars: DEFAULT_MAX_ANIMATED_CHARS,
+        }
+    }
+}
+
+impl StreamPacing {
+    /// 根据配置创建
+    pub fn new(enabled: bool, min_chars_per_tick: usize) -> Self {
+        Self {
+            enabled,
+            min_chars_per_tick: min_chars_per_tick.max(1),
+            ..Self::default()
+        }
+    }
+}
+
+/// 单条消息的节奏状态
+#[derive(Debug)]
+pub struct StreamPacer {
+    pacing: StreamPacing,
+    received: usize,
+    displayed: usize,
+    /// 当前积压需要显示完的时间点
+    deadline: Option<Instant>,
+    last_tick: Option<Instant>,
+    finished: bool,
+}
+
+impl StreamPacer {
+    pub fn new(pacing: StreamPacing) -> Self {
+        Self {
+            pacing,
+            received: 0,
+            displayed: 0,
+            deadline: None,
+            last_tick: None,
+            finished: false,
+        }
+    }
+
+    /// 记录从网络收到的字符数
+    pub fn on_received(&mut self, chars: usize, now: Instant) {
+        if chars == 0 {
+            return;
+        }
+        self.received += chars;
+        self.deadline = Some(now + self.pacing.catch_up);
+        if self.last_tick.is_none() {
+            self.last_tick = Some(now);
+        }
+    }
+
+    /// 标记消息结束，之后的节拍会一次性输出全部剩余内容
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// 尚未显示的字符数
+    pub fn backlog(&self) -> usize {
+        self.received - self.displayed
+    }
+
+    /// 是否逐步输出（否则收到即显示）
+    pub fn is_animating(&self) -> bool {
+        self.pacing.enabled && !self.finished && self.received <= self.pacing.max_animated_chars
+    }
+
+    /// 计算本次节拍应输出的字符数，并记为已显示
+    pub fn next_chunk(&mut self, now: Instant) -> usize {
+        let backlog = self.backlog();
+        if backlog == 0 {
+            self.last_tick = Some(now);
+            return 0;
+        }
+
+        let chunk = if !self.is_animating() {
+            backlog
+        } else {
+            let elapsed = self
+                .last_tick
+                .map(|last| now.saturating_duration_since(last))
+                .unwrap_or(self.pacing.tick);
+            let remaining = self
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(now))
+                .unwrap_or_default();
+
+            if remaining <= elapsed {
+                // 已到（或即将到）截止时间，包括节拍循环卡顿的情况
+                backlog
+            } else {
+                // 线性消化积压，恰好在截止时间显示完
+                let share = backlog as f64 * elapsed.as_secs_f64() / remaining.as_secs_f64();
+                (share.ceil() as usize)
+                    .max(self.pacing.min_chars_per_tick)
+                    .min(backlog)
+            }
+        };
+
+        self.displayed += chunk;
+        self.last_tick = Some(now);
+        chunk
+    }
+
+    /// 立即输出全部积压（工具调用等事件之前），之后到达的文本照常做动画
+    pub fn flush(&mut self) -> usize {
+        let backlog = self.backlog();
+        self.displayed = self.received;
+        backlog
+    }
+}
+
+/// 从缓冲区头部取出 `chars` 个字符
+pub fn take_chars(buffer: &mut String, chars: usize) -> String {
+    let end = buffer
+        .char_indices()
+        .nth(chars)
+        .map(|(idx, _)| idx)
+        .unwrap_or(buffer.len());
+    buffer.drain(..end).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(16);
+
+    /// 按节拍运行直到积压清空，返回耗时
+    fn drain(pacer: &mut StreamPacer, start: Instant) -> Duration {
+        let mut now = start;
+        while pacer.backlog() > 0 {
+            now += TICK;
+            pacer.next_chunk(now);
+            assert!(now - start < Duration::from_secs(5), "pacer never drained");
+        }
+        now - start
+    }
+
+    #[test]
+    fn test_burst_catches_up_within_window() {
+        let mut pacer = StreamPacer::new(StreamPacing::default());
+        let start = Instant::now();
+        pacer.on_received(3000, start);
+
+        // 第一个节拍不会一次性输出全部
+        let first = pacer.next_chunk(start + TICK);
+        assert!(first > 0 && first < 3000);
+
+        let elapsed = drain(&mut pacer, start + TICK) + TICK;
+        assert!(elapsed <= DEFAULT_CATCH_UP + TICK, "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_steady_stream_lag_is_bounded() {
+        let mut pacer = StreamPacer::new(StreamPacing::default());
+        let start = Instant::now();
+        let mut arrivals: Vec<(Instant, usize)> = Vec::new();
+
+        // 每 50ms 到达 40 个字符，持续 2 秒
+        for step in 0..125 {
+            let now = start + TICK * step;
+            if (now - start).as_millis() as usize / 50 >= arrivals.len() {
+                pacer.on_received(40, now);
+                arrivals.push((now, 40));
+            }
+            pacer.next_chunk(now);
+
+            // 积压不超过最近 catch_up 窗口内到达的字符数
+            let recent: usize = arrivals
+                .iter()
+                .filter(|(at, _)| now.saturating_duration_since(*at) <= DEFAULT_CATCH_UP)
+                .map(|(_, n)| n)
+                .sum();
+            assert!(pacer.backlog() <= recent, "backlog {} > recent {}", pacer.backlog(), recent);
+        }
+    }
+
+    #[test]
+    fn test_finish_flushes_immediately() {
+        let mut pacer = StreamPacer::new(StreamPacing::default());
+        let start = Instant::now();
+        pacer.on_received(500, start);
+        pacer.next_chunk(start + TICK);
+        pacer.finish();
+
+        let backlog = pacer.backlog();
+        assert!(backlog > 0);
+        assert_eq!(pacer.next_chunk(start + TICK * 2), backlog);
+        assert_eq!(pacer.backlog(), 0);
+    }
+
+    #[test]
+    fn test_flush_keeps_animating_later_text() {
+        let mut pacer = StreamPacer::new(StreamPacing::default());
+        let start = Instant::now();
+        pacer.on_received(500, start);
+        pacer.next_chunk(start + TICK);
+
+        let backlog = pacer.backlog();
+        assert_eq!(pacer.flush(), backlog);
+        assert_eq!(pacer.backlog(), 0);
+
+        pacer.on_received(500, start + TICK * 2);
+        assert!(pacer.is_animating());
+        assert!(pacer.next_chunk(start + TICK * 3) < 500);
+    }
+
+    #[test]
+    fn test_large_message_and_disabled_skip_animation() {
+        let mut pacer = StreamPacer::new(StreamPacing {
+            max_animated_chars: 100,
+            ..StreamPacing::default()
+        });
+        let start = Instant::now();
+        pacer.on_received(150, start);
+        assert!(!pacer.is_animating());
+        assert_eq!(pacer.next_chunk(start + TICK), 150);
+
+        let mut pacer = StreamPacer::new(StreamPacing::new(false, 8));
+        pacer.on_received(10, start);
+        assert_eq!(pacer.next_chunk(start + TICK), 10);
+    }
+
+    #[test]
+    fn test_stalled_tick_loop_flushes_on_next_tick() {
+        let mut pacer = StreamPacer::new(StreamPacing::default());
+        let start = Instant::now();
+        pacer.on_received(1000, start);
+        pacer.next_chunk(start + TICK);
+
+        // 节拍循环卡住 1 秒后恢复，应立即追上
+        let backlog = pacer.backlog();
+        assert_eq!(pacer.next_chunk(start + Duration::from_secs(1)), backlog);
+    }
+
+    #[test]
+    fn test_min_chars_per_tick() {
+        let mut pacer = StreamPacer::new(StreamPacing::new(true, 8));
+        let start = Instant::now();
+        pacer.on_received(20, start);
+        assert!(pacer.next_chunk(start + TICK) >= 8);
+    }
+
+    #[test]
+    fn test_take_chars_respects_char_boundaries() {
+        let mut buffer = "你好, world".to_string();
+        assert_eq!(take_chars(&mut buffer, 2), "你好");
+        assert_eq!(take_chars(&mut buffer, 100), ", world");
+        assert!(buffer.is_empty());
+    }
+}
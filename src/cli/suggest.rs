@@ -0,0 +1,111 @@
+//! 未知命令的纠错建议
+//!
+//! 前缀匹配优先，其次按编辑距离（相邻字符交换计为一次）筛选拼写相近的命令。
+
+/// 最多返回的建议数
+const MAX_SUGGESTIONS: usize = 3;
+
+/// 为输入的命令（只取第一个词）推荐相近的已注册命令
+pub fn suggest_commands<'a, I>(input: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let command = input.split_whitespace().next().unwrap_or("").to_lowercase();
+    let name = command.trim_start_matches('/');
+    if name.is_empty() {
+        return Vec::new();
+    }
+    // 允许的编辑距离随长度增加，短命令只容忍一处错误
+    let max_distance = (name.chars().count() / 3).clamp(1, 3);
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let candidate_name = candidate.trim_start_matches('/').to_lowercase();
+            if name.chars().count() >= 2 && candidate_name.starts_with(name) {
+                return Some((0, candidate));
+            }
+            let distance = edit_distance(name, &candidate_name);
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// 按字符计算的编辑距离（相邻字符交换计为一次编辑）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut dist = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dist[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (dist[i - 1][j - 1] + cost)
+                .min(dist[i - 1][j] + 1)
+                .min(dist[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(dist[i - 2][j - 2] + 1);
+            }
+            dist[i][j] = d;
+        }
+    }
+    dist[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMANDS: &[&str] = &[
+        "/help", "/history", "/clear", "/config", "/quit", "/exit", "/sessions", "/load", "/mcp",
+        "/tasks", "/skills", "/undo", "/workflow",
+    ];
+
+    fn suggest(input: &str) -> Vec<String> {
+        suggest_commands(input, COMMANDS.iter().copied())
+    }
+
+    #[test]
+    fn test_typos() {
+        assert_eq!(suggest("/helpp"), vec!["/help"]);
+        assert_eq!(suggest("/hlep"), vec!["/help"]);
+        assert_eq!(suggest("/claer"), vec!["/clear"]);
+        assert_eq!(suggest("/sesions"), vec!["/sessions"]);
+        assert_eq!(suggest("/workflwo status"), vec!["/workflow"]);
+        assert_eq!(suggest("/CONFIG"), vec!["/config"]);
+    }
+
+    #[test]
+    fn test_prefix_matches_first() {
+        assert_eq!(suggest("/hi"), vec!["/history"]);
+        assert_eq!(suggest("/sk"), vec!["/skills"]);
+    }
+
+    #[test]
+    fn test_no_suggestion_for_unrelated_input() {
+        assert!(suggest("/deploy").is_empty());
+        assert!(suggest("/").is_empty());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("hlep", "help"), 1);
+        assert_eq!(edit_distance("配置", "配置"), 0);
+    }
+}
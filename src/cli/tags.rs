@@ -0,0 +1,207 @@
+//! 消息标签（`#bug`、`#feature` 等）
+//!
+//! 标签由内置列表和 `[tags]` 配置合并而来。提交的消息以已知标签开头时，
+//! 标签会从正文中剥离、作为元数据保存在会话记录里，标签的提示词片段只随本轮请求发送。
+
+use std::collections::BTreeMap;
+
+use crate::config::{ConfigLoader, TagConfig};
+
+/// 一个可用的标签
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// 标签名（不含 `#`）
+    pub name: String,
+    pub description: String,
+    /// 发送时插入到消息开头的提示词片段
+    pub prompt: Option<String>,
+}
+
+/// 从消息开头解析出的标签
+#[derive(Debug, PartialEq)]
+pub struct TaggedInput<'a> {
+    /// 已识别的标签（按出现顺序，去重）
+    pub tags: Vec<String>,
+    /// 剥离标签后的正文
+    pub text: &'a str,
+}
+
+/// 当前可用的标签集合（按名称排序）
+#[derive(Debug, Clone)]
+pub struct TagSet {
+    tags: BTreeMap<String, Tag>,
+}
+
+impl TagSet {
+    /// 内置标签 + `[tags]` 配置（同名标签以配置为准）
+    pub fn load() -> Self {
+        let configured = ConfigLoader::new()
+            .load_toml_layers()
+            .map(|config| config.tags)
+            .unwrap_or_default();
+        Self::from_config(configured)
+    }
+
+    /// 在内置标签上叠加配置的标签
+    pub fn from_config(configured: BTreeMap<String, TagConfig>) -> Self {
+        let mut tags = builtin_tags();
+        for (name, config) in configured {
+            let name = name.trim_start_matches('#').to_string();
+            tags.insert(
+                name.clone(),
+                Tag {
+                    name,
+                    description: config.description,
+                    prompt: config.prompt,
+                },
+            );
+        }
+        Self { tags }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        self.tags.get(name.trim_start_matches('#'))
+    }
+
+    /// 补全菜单条目（`#name`, 说明）
+    pub fn entries(&self) -> Vec<(String, String)> {
+        self.tags
+            .values()
+            .map(|tag| (format!("#{}", tag.name), tag.description.clone()))
+            .collect()
+    }
+
+    /// 解析消息开头连续的已知标签
+    ///
+    /// 遇到第一个非标签或未知标签即停止，之后的内容原样保留。
+    /// 消息只有标签没有正文时不做剥离，按普通文本处理。
+    pub fn parse<'a>(&self, input: &'a str) -> TaggedInput<'a> {
+        let mut tags = Vec::new();
+        let mut rest = input.trim_start();
+        while let Some(name) = rest.strip_prefix('#') {
+            let end = name.find(char::is_whitespace).unwrap_or(name.len());
+            let name = &name[..end];
+            if !self.tags.contains_key(name) {
+                break;
+            }
+            if !tags.iter().any(|tag| tag == name) {
+                tags.push(name.to_string());
+            }
+            rest = rest[1 + end..].trim_start();
+        }
+
+        if tags.is_empty() || rest.is_empty() {
+            return TaggedInput {
+                tags: Vec::new(),
+                text: input,
+            };
+        }
+        TaggedInput { tags, text: rest }
+    }
+
+    /// 在发送给模型的消息前插入标签的提示词片段
+    pub fn apply_prompts(&self, tags: &[String], text: &str) -> String {
+        let snippets: Vec<&str> = tags
+            .iter()
+            .filter_map(|name| self.get(name)?.prompt.as_deref())
+            .collect();
+        if snippets.is_empty() {
+            return text.to_string();
+        }
+        format!("{}\n\n{}", snippets.join("\n"), text)
+    }
+}
+
+fn builtin_tags() -> BTreeMap<String, Tag> {
+    [
+        (
+            "bug",
+            t!("cli.tag.bug"),
+            "This is a bug report. Focus on reproducing the problem and writing a regression test before fixing it.",
+        ),
+        (
+            "feature",
+            t!("cli.tag.feature"),
+            "This is a feature request. Follow the existing project conventions and add tests for the new behavior.",
+        ),
+        (
+            "refactor",
+            t!("cli.tag.refactor"),
+            "This is a refactoring. Preserve existing behavior and keep unrelated code untouched.",
+        ),
+        (
+            "docs",
+            t!("cli.tag.docs"),
+            "This is a documentation task. Keep it accurate, concise and consistent with the existing docs.",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, description, prompt)| {
+        (
+            name.to_string(),
+            Tag {
+                name: name.to_string(),
+                description: description.to_string(),
+                prompt: Some(prompt.to_string()),
+            },
+        )
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_set() -> TagSet {
+        let mut configured = BTreeMap::new();
+        configured.insert(
+            "security".to_string(),
+            TagConfig {
+                description: "Security review".to_string(),
+                prompt: None,
+            },
+        );
+        TagSet::from_config(configured)
+    }
+
+    #[test]
+    fn test_parse_multiple_leading_tags() {
+        let tags = tag_set();
+        let parsed = tags.parse("#bug  #security #bug login fails with #refactor");
+        assert_eq!(parsed.tags, vec!["bug", "security"]);
+        assert_eq!(parsed.text, "login fails with #refactor");
+
+        let prompt = tags.apply_prompts(&parsed.tags, parsed.text);
+        assert!(prompt.starts_with("This is a bug report."));
+        assert!(prompt.ends_with("\n\nlogin fails with #refactor"));
+    }
+
+    #[test]
+    fn test_unknown_tags_pass_through() {
+        let tags = tag_set();
+        for input in [
+            "#unknown fix this",
+            "#123 is broken",
+            "#bug",
+            "fix #bug later",
+        ] {
+            let parsed = tags.parse(input);
+            assert!(parsed.tags.is_empty(), "{input}");
+            assert_eq!(parsed.text, input);
+            assert_eq!(tags.apply_prompts(&parsed.tags, parsed.text), input);
+        }
+
+        // 已知标签后的未知标签保留在正文中
+        let parsed = tags.parse("#docs #unknown update README");
+        assert_eq!(parsed.tags, vec!["docs"]);
+        assert_eq!(parsed.text, "#unknown update README");
+    }
+
+    #[test]
+    fn test_entries_include_configured_tags() {
+        let entries = tag_set().entries();
+        assert!(entries.contains(&("#security".to_string(), "Security review".to_string())));
+        assert!(entries.iter().any(|(name, _)| name == "#bug"));
+    }
+}
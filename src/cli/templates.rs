@@ -0,0 +1,442 @@
+//! 会话模板
+//!
+//! 模板是 `~/.oxide/templates/*.md`（全局）和 `.oxide/templates/*.md`（项目，同名时优先）中
+//! 带 front matter 的 Markdown 文件，描述一类反复开始的会话：
+//!
+//! ```markdown
+//! ---
+//! name: debugging
+//! description: Reproduce and fix a failing test
+//! mode: normal              # normal / fast / plan
+//! output_style: concise     # concise / detailed / explanatory
+//! files: [docs/ARCHITECTURE.md, Makefile]
+//! system: Run tests with `cargo test -p core`.
+//! body: message             # 正文作为第一条消息的开头（message，默认）或追加到系统提示词（system）
+//! ---
+//! Start by reproducing the failure before changing any code.
+//! ```
+//!
+//! `--template <name>` 和 `/new <name>` 开始新会话并应用模板：切换模式和回复风格，
+//! `system`（以及 `body: system` 时的正文）作为系统提示词的一部分，
+//! 引用的文件像 @ 引用一样读取，和 `body: message` 时的正文一起放在第一条消息的开头。
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::file_resolver::{resolve_and_read_file, FileReference};
+use crate::agent::{PromptMode, PromptPart};
+use crate::config::OutputStyle;
+use crate::tools::WorkspaceRoots;
+
+/// 项目模板目录（相对于工作目录）
+pub const TEMPLATES_DIR: &str = ".oxide/templates";
+
+/// 模板正文放在哪里
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyPlacement {
+    /// 第一条用户消息的开头
+    #[default]
+    Message,
+    /// 系统提示词末尾
+    System,
+}
+
+/// 模板来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// `~/.oxide/templates/`
+    Global,
+    /// `.oxide/templates/`
+    Project,
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::Global => write!(f, "global"),
+            TemplateSource::Project => write!(f, "project"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFrontMatter {
+    name: String,
+    #[serde(default)]
+    description: String,
+    mode: Option<String>,
+    output_style: Option<String>,
+    #[serde(default)]
+    files: Vec<String>,
+    system: Option<String>,
+    #[serde(default)]
+    body: BodyPlacement,
+}
+
+/// 一个会话模板
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionTemplate {
+    pub name: String,
+    pub description: String,
+    pub mode: Option<PromptMode>,
+    pub output_style: Option<OutputStyle>,
+    /// 预先引用的文件（相对工作目录、绝对路径或 `alias:相对路径`）
+    pub files: Vec<String>,
+    /// 追加到系统提示词的内容
+    pub system: Option<String>,
+    pub body: String,
+    pub body_placement: BodyPlacement,
+    pub source: TemplateSource,
+    pub path: PathBuf,
+}
+
+impl SessionTemplate {
+    /// 解析并校验模板文件的内容，错误信息指出具体的字段
+    pub fn parse(content: &str, path: &Path, source: TemplateSource) -> Result<Self> {
+        let (front_matter, body) = split_front_matter(content)
+            .ok_or_else(|| anyhow!("missing front matter (expected ---\\n...\\n---)"))?;
+        let front_matter: TemplateFrontMatter =
+            serde_yaml::from_str(front_matter).context("invalid front matter")?;
+
+        let name = front_matter.name.trim().to_string();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("invalid name: must be a single word");
+        }
+        let mode = front_matter
+            .mode
+            .as_deref()
+            .map(str::parse::<PromptMode>)
+            .transpose()
+            .map_err(|e| anyhow!("invalid mode: {}", e))?;
+        let output_style = front_matter
+            .output_style
+            .as_deref()
+            .map(str::parse::<OutputStyle>)
+            .transpose()
+            .map_err(|e| anyhow!("invalid output_style: {}", e))?;
+        if front_matter.files.iter().any(|file| file.trim().is_empty()) {
+            bail!("invalid files: entries must not be empty");
+        }
+        let body = body.trim().to_string();
+        if front_matter.body == BodyPlacement::System && body.is_empty() {
+            bail!("invalid body: body = system but the template has no body");
+        }
+
+        Ok(Self {
+            name,
+            description: front_matter.description.trim().to_string(),
+            mode,
+            output_style,
+            files: front_matter.files,
+            system: front_matter
+                .system
+                .map(|system| system.trim().to_string())
+                .filter(|system| !system.is_empty()),
+            body,
+            body_placement: front_matter.body,
+            source,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// 追加到系统提示词的部分：`system` 和放在系统提示词中的正文
+    pub fn system_part(&self) -> Option<PromptPart> {
+        let mut sections: Vec<&str> = self.system.iter().map(String::as_str).collect();
+        if self.body_placement == BodyPlacement::System {
+            sections.push(&self.body);
+        }
+        (!sections.is_empty())
+            .then(|| PromptPart::new(format!("template: {}", self.name), sections.join("\n\n")))
+    }
+
+    /// 读取引用的文件，准备放进第一条消息的内容；读取失败的文件返回在错误列表中
+    pub fn start(&self, roots: &WorkspaceRoots) -> (TemplateStart, Vec<(String, anyhow::Error)>) {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        for file in &self.files {
+            match resolve_and_read_file(file, roots) {
+                Ok(reference) => files.push(reference),
+                Err(e) => errors.push((file.clone(), e)),
+            }
+        }
+        let body = match self.body_placement {
+            BodyPlacement::Message if !self.body.is_empty() => Some(self.body.clone()),
+            _ => None,
+        };
+        (TemplateStart { files, body }, errors)
+    }
+}
+
+/// 应用模板后等待放进第一条消息的内容
+#[derive(Debug, Clone)]
+pub struct TemplateStart {
+    pub files: Vec<FileReference>,
+    pub body: Option<String>,
+}
+
+impl TemplateStart {
+    /// 第一条用户消息：引用文件的内容、模板正文，最后是用户的输入
+    pub fn first_message(&self, input: &str) -> String {
+        let mut message = String::new();
+        for file in &self.files {
+            message.push_str(&format!(
+                "```file_path=\"{}\"\n{}\n```\n\n",
+                file.file_path.display(),
+                file.content
+            ));
+        }
+        if let Some(body) = &self.body {
+            message.push_str(body);
+            message.push_str("\n\n");
+        }
+        message.push_str(input);
+        message
+    }
+}
+
+/// 无效的模板文件及原因
+pub type InvalidTemplates = Vec<(PathBuf, anyhow::Error)>;
+
+/// 模板加载器
+pub struct TemplateLoader {
+    global_dir: Option<PathBuf>,
+    project_dir: PathBuf,
+}
+
+impl TemplateLoader {
+    pub fn new() -> Self {
+        Self {
+            global_dir: dirs::home_dir().map(|home| home.join(".oxide").join("templates")),
+            project_dir: PathBuf::from(TEMPLATES_DIR),
+        }
+    }
+
+    /// 使用指定的目录（测试用）
+    pub fn with_dirs(global_dir: Option<PathBuf>, project_dir: PathBuf) -> Self {
+        Self {
+            global_dir,
+            project_dir,
+        }
+    }
+
+    /// 加载所有模板（按名称排序，项目模板覆盖同名的全局模板），以及无效的文件和原因
+    pub fn load_all(&self) -> (BTreeMap<String, SessionTemplate>, InvalidTemplates) {
+        let mut templates = BTreeMap::new();
+        let mut errors = Vec::new();
+        let dirs = [
+            (self.global_dir.as_deref(), TemplateSource::Global),
+            (Some(self.project_dir.as_path()), TemplateSource::Project),
+        ];
+        for (dir, source) in dirs {
+            let Some(Ok(entries)) = dir.map(fs::read_dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+                .collect();
+            paths.sort();
+            for path in paths {
+                let parsed = fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| SessionTemplate::parse(&content, &path, source));
+                match parsed {
+                    Ok(template) => {
+                        templates.insert(template.name.clone(), template);
+                    }
+                    Err(e) => errors.push((path, e)),
+                }
+            }
+        }
+        (templates, errors)
+    }
+
+    /// 按名称查找模板
+    pub fn find(&self, name: &str) -> Result<SessionTemplate> {
+        let (mut templates, errors) = self.load_all();
+        if let Some(template) = templates.remove(name) {
+            return Ok(template);
+        }
+        // 文件名相同但无效时给出原因
+        let invalid = errors
+            .into_iter()
+            .find(|(path, _)| path.file_stem().is_some_and(|stem| stem == name));
+        match invalid {
+            Some((path, e)) => Err(e.context(format!("invalid template {}", path.display()))),
+            None => bail!("template not found: {}", name),
+        }
+    }
+}
+
+impl Default for TemplateLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 分离 front matter 和正文：文件须以 `---` 行开头，下一个 `---` 行结束 front matter
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::PromptBuilder;
+    use crate::config::OutputConfig;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    #[test]
+    fn test_loader_validates_and_prefers_project_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        let global = temp_dir.path().join("global");
+        let project = temp_dir.path().join("project");
+        write(
+            &global,
+            "review.md",
+            "---\nname: review\ndescription: Global review\n---\nReview the diff.\n",
+        );
+        write(
+            &global,
+            "debugging.md",
+            "---\nname: debugging\ndescription: Global\n---\nbody\n",
+        );
+        write(
+            &project,
+            "debugging.md",
+            "---\nname: debugging\ndescription: Project\nmode: fast\n---\nbody\n",
+        );
+        write(
+            &project,
+            "broken.md",
+            "---\nname: broken\nmode: turbo\n---\n",
+        );
+        write(&project, "typo.md", "---\nname: typo\nfile: [a.md]\n---\n");
+        write(&project, "plain.md", "no front matter\n");
+        write(
+            &project,
+            "system.md",
+            "---\nname: system\nbody: system\n---\n\n",
+        );
+        write(&project, "notes.txt", "ignored");
+
+        let loader = TemplateLoader::with_dirs(Some(global), project.clone());
+        let (templates, errors) = loader.load_all();
+        assert_eq!(
+            templates.keys().collect::<Vec<_>>(),
+            vec!["debugging", "review"]
+        );
+        assert_eq!(templates["debugging"].source, TemplateSource::Project);
+        assert_eq!(templates["debugging"].mode, Some(PromptMode::Fast));
+        assert_eq!(templates["review"].source, TemplateSource::Global);
+
+        let errors: BTreeMap<String, String> = errors
+            .into_iter()
+            .map(|(path, e)| {
+                let file = path.file_name().unwrap().to_string_lossy().into_owned();
+                (file, format!("{:#}", e))
+            })
+            .collect();
+        assert_eq!(errors.len(), 4);
+        assert!(errors["broken.md"].contains("invalid mode: unknown mode: turbo"));
+        assert!(errors["typo.md"].contains("unknown field `file`"));
+        assert!(errors["plain.md"].contains("missing front matter"));
+        assert!(errors["system.md"].contains("invalid body"));
+
+        assert!(loader
+            .find("broken")
+            .unwrap_err()
+            .to_string()
+            .contains("broken.md"));
+        assert!(loader
+            .find("missing")
+            .unwrap_err()
+            .to_string()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn test_applied_template_shapes_first_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes = temp_dir.path().join("ARCHITECTURE.md");
+        fs::write(&notes, "# Architecture\nThe core crate owns parsing.\n").unwrap();
+        let content = format!(
+            "---\nname: debugging\nmode: plan\noutput_style: concise\nfiles: [\"{}\"]\nsystem: Run tests with `cargo test -p core`.\n---\nReproduce the failure first.\n",
+            notes.display()
+        );
+        let template =
+            SessionTemplate::parse(&content, Path::new("debugging.md"), TemplateSource::Project)
+                .unwrap();
+
+        // 系统提示词：模板指定的模式和回复风格，末尾追加模板的补充
+        let output = OutputConfig {
+            style: template.output_style.unwrap(),
+            ..OutputConfig::default()
+        };
+        let parts = PromptBuilder::new(template.mode.unwrap())
+            .with_output(output)
+            .with_prompts_dir(None)
+            .with_addition(template.system_part())
+            .parts();
+        assert!(parts.iter().any(|part| part.name == "mode: plan"));
+        let last = parts.last().unwrap();
+        assert_eq!(last.name, "template: debugging");
+        assert_eq!(last.text, "Run tests with `cargo test -p core`.");
+
+        // 第一条消息：引用文件的内容、模板正文、用户输入
+        let (start, errors) = template.start(&WorkspaceRoots::default());
+        assert!(errors.is_empty());
+        assert_eq!(
+            start.first_message("Why does parse_header panic?"),
+            format!(
+                "```file_path=\"{}\"\n# Architecture\nThe core crate owns parsing.\n\n```\n\nReproduce the failure first.\n\nWhy does parse_header panic?",
+                notes.display()
+            )
+        );
+
+        // body: system 时正文进入系统提示词，第一条消息只带文件
+        let content = content.replace("---\nReproduce", "body: system\n---\nReproduce");
+        let template =
+            SessionTemplate::parse(&content, Path::new("debugging.md"), TemplateSource::Project)
+                .unwrap();
+        assert_eq!(
+            template.system_part().unwrap().text,
+            "Run tests with `cargo test -p core`.\n\nReproduce the failure first."
+        );
+        let (start, _) = template.start(&WorkspaceRoots::default());
+        assert!(start.body.is_none());
+        assert!(start.first_message("go").ends_with("```\n\ngo"));
+
+        // 找不到的文件单独报告，不影响其他部分
+        let content = content.replace(&notes.display().to_string(), "missing.md");
+        let template =
+            SessionTemplate::parse(&content, Path::new("debugging.md"), TemplateSource::Project)
+                .unwrap();
+        let (start, errors) = template.start(&WorkspaceRoots::default());
+        assert!(start.files.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "missing.md");
+    }
+}
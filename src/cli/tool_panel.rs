@@ -0,0 +1,195 @@
+//! 工具调用面板（`/tools`）
+//!
+//! 列出本次会话的工具调用（数据来自 [`crate::agent::tool_activity`]），每行显示序号、状态、
+//! 工具和参数摘要、权限结果和耗时；`/tools <序号>` 显示该调用的完整参数和截取后的结果。
+
+use colored::*;
+
+use crate::agent::tool_activity::{CallStatus, ToolRecord};
+use crate::agent::tool_status::PermissionOutcome;
+
+/// 列表中最多显示的调用数（最近的）
+pub const LIST_LIMIT: usize = 30;
+
+/// 列表中权限依据的最大字符数
+const REASON_CHARS: usize = 48;
+
+fn status_icon(status: CallStatus) -> ColoredString {
+    match status {
+        CallStatus::Running => "…".bright_blue(),
+        CallStatus::Succeeded => "✓".green(),
+        CallStatus::Failed => "✗".red(),
+        CallStatus::Denied => "⊘".yellow(),
+    }
+}
+
+fn status_text(status: CallStatus) -> &'static str {
+    match status {
+        CallStatus::Running => t!("cli.tool_panel.running"),
+        CallStatus::Succeeded => t!("cli.tool_panel.succeeded"),
+        CallStatus::Failed => t!("cli.tool_panel.failed"),
+        CallStatus::Denied => t!("cli.tool_panel.not_run"),
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max).collect();
+    format!("{}…", truncated)
+}
+
+/// 权限结果的说明，`max_reason` 限制依据的长度
+fn describe_permission(permission: Option<&PermissionOutcome>, max_reason: usize) -> String {
+    match permission {
+        None => t!("cli.tool_panel.unchecked").to_string(),
+        Some(PermissionOutcome::AutoAllowed { reason }) => t!(
+            "cli.tool_panel.auto_allowed",
+            reason = truncate(reason, max_reason)
+        ),
+        Some(PermissionOutcome::Approved) => t!("cli.tool_panel.approved").to_string(),
+        Some(PermissionOutcome::Denied { reason }) => {
+            t!(
+                "cli.tool_panel.denied",
+                reason = truncate(reason, max_reason)
+            )
+        }
+    }
+}
+
+fn format_duration(duration_ms: Option<u64>) -> String {
+    match duration_ms {
+        Some(ms) => format!("{:.1}s", ms as f64 / 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+/// 列表的各行（序号从 1 开始，与 `/tools <序号>` 一致）
+pub fn list_lines(records: &[ToolRecord]) -> Vec<String> {
+    if records.is_empty() {
+        return vec![t!("cli.tool_panel.empty").dimmed().to_string()];
+    }
+    let mut lines = vec![t!("cli.tool_panel.title", count = records.len())
+        .bright_cyan()
+        .to_string()];
+    let skipped = records.len().saturating_sub(LIST_LIMIT);
+    if skipped > 0 {
+        lines.push(format!(
+            "  {}",
+            t!("cli.tool_panel.older", count = skipped).dimmed()
+        ));
+    }
+    for (index, record) in records.iter().enumerate().skip(skipped) {
+        let permission = describe_permission(record.permission.as_ref(), REASON_CHARS);
+        let permission = match &record.permission {
+            Some(PermissionOutcome::Denied { .. }) => permission.yellow(),
+            Some(PermissionOutcome::Approved) => permission.green(),
+            _ => permission.dimmed(),
+        };
+        lines.push(format!(
+            "  {:>3} {} {} {}  {}  {}",
+            index + 1,
+            status_icon(record.status()),
+            record.tool.bright_white(),
+            record.summary,
+            permission,
+            format_duration(record.duration_ms).dimmed()
+        ));
+    }
+    lines.push(t!("cli.tool_panel.hint").dimmed().to_string());
+    lines
+}
+
+/// 单个调用的详情
+pub fn detail_lines(index: usize, record: &ToolRecord) -> Vec<String> {
+    let mut lines = vec![
+        format!("#{} {}", index, record.tool)
+            .bright_cyan()
+            .to_string(),
+        format!(
+            "  {} {}",
+            t!("cli.tool_panel.permission").bright_white(),
+            describe_permission(record.permission.as_ref(), usize::MAX)
+        ),
+        format!(
+            "  {} {} ({})",
+            t!("cli.tool_panel.status").bright_white(),
+            status_text(record.status()),
+            format_duration(record.duration_ms)
+        ),
+        t!("cli.tool_panel.arguments").bright_white().to_string(),
+    ];
+    let args = serde_json::to_string_pretty(&record.args).unwrap_or_default();
+    lines.extend(args.lines().map(|line| format!("  {}", line)));
+    if !record.output.is_empty() {
+        lines.push(t!("cli.tool_panel.result").bright_white().to_string());
+        lines.extend(record.output.lines().map(|line| format!("  {}", line)));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tool_activity::ToolActivity;
+    use crate::agent::tool_status::{ToolStatusEvent, ToolStatusSink};
+    use serde_json::json;
+
+    #[test]
+    fn test_render_seeded_events() {
+        colored::control::set_override(false);
+        let activity = ToolActivity::new();
+        let events = [
+            ToolStatusEvent::PermissionDecided {
+                call_id: 1,
+                tool: "shell_execute".to_string(),
+                summary: "cargo test".to_string(),
+                args: json!({ "command": "cargo test" }),
+                outcome: PermissionOutcome::AutoAllowed {
+                    reason: "shell_execute(cargo test*)".to_string(),
+                },
+            },
+            ToolStatusEvent::ToolStarted {
+                call_id: 1,
+                tool: "shell_execute".to_string(),
+                summary: "cargo test".to_string(),
+                args: json!({ "command": "cargo test" }),
+            },
+            ToolStatusEvent::ToolCompleted {
+                call_id: 1,
+                tool: "shell_execute".to_string(),
+                duration_ms: 3200,
+                success: true,
+                output: "running 3 tests\ntest result: ok".to_string(),
+            },
+            ToolStatusEvent::PermissionDecided {
+                call_id: 2,
+                tool: "delete_file".to_string(),
+                summary: "Cargo.lock".to_string(),
+                args: json!({ "file_path": "Cargo.lock" }),
+                outcome: PermissionOutcome::Denied {
+                    reason: "Delete Cargo.lock".to_string(),
+                },
+            },
+        ];
+        for event in &events {
+            activity.handle(event);
+        }
+        let records = activity.records();
+
+        let lines = list_lines(&records);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("    1 ✓ shell_execute cargo test"));
+        assert!(lines[1].contains("shell_execute(cargo test*)"));
+        assert!(lines[1].ends_with("3.2s"));
+        assert!(lines[2].starts_with("    2 ⊘ delete_file Cargo.lock"));
+        assert!(lines[2].contains("Delete Cargo.lock"));
+
+        let detail = detail_lines(1, &records[0]);
+        assert_eq!(detail[0], "#1 shell_execute");
+        assert!(detail.contains(&"    \"command\": \"cargo test\"".to_string()));
+        assert_eq!(detail.last().unwrap(), "  test result: ok");
+        assert_eq!(list_lines(&[]).len(), 1);
+    }
+}
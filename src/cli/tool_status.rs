@@ -0,0 +1,326 @@
+//! 工具执行状态显示
+//!
+//! 消费 Agent 发出的 `ToolStatusEvent`，在当前回合下方为每个正在执行的工具
+//! 显示一行实时状态（耗时持续刷新）。多个工具同时执行时各占一行。
+//! 简洁模式（`[output] style = "concise"`）下成功的调用不保留记录，错误只显示第一行。
+//! 工具输出的图片在状态行下方显示预览（见 `super::image`）。
+//! 纯文本输出模式（见 `super::output`）下不显示状态行，每个调用结束时输出一行
+//! `[tool] shell_execute: cargo test … ok (3.2s)`。
+
+use colored::*;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::agent::interaction::Pausable;
+use crate::agent::tool_status::{ToolStatusEvent, ToolStatusSink};
+use crate::tools::image::ImageInfo;
+
+use super::image::{self, ImagePreview};
+use super::output::PlainWriter;
+
+/// 状态行刷新间隔
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 超过该耗时的成功调用保留一行耗时记录，更快的直接清除
+const KEEP_LINE_AFTER: Duration = Duration::from_secs(1);
+
+/// 简洁模式下错误信息的最大长度（字符）
+const COMPACT_ERROR_CHARS: usize = 80;
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏", "●"];
+
+/// 正在执行的工具
+struct ActiveTool {
+    /// 纯文本模式下没有状态行
+    bar: Option<ProgressBar>,
+    tool: String,
+    summary: String,
+}
+
+impl ActiveTool {
+    fn label(&self) -> String {
+        if self.summary.is_empty() {
+            self.tool.clone()
+        } else {
+            format!("{}({})", self.tool, self.summary)
+        }
+    }
+
+    /// 纯文本模式下的结果行
+    fn plain_line(&self, duration: Duration, error: Option<&str>) -> String {
+        let target = if self.summary.is_empty() {
+            self.tool.clone()
+        } else {
+            format!("{}: {}", self.tool, self.summary)
+        };
+        let elapsed = duration.as_secs_f64();
+        match error {
+            None => format!("[tool] {} … ok ({:.1}s)", target, elapsed),
+            Some(error) => format!(
+                "[tool] {} … failed ({:.1}s): {}",
+                target,
+                elapsed,
+                error.lines().next().unwrap_or("")
+            ),
+        }
+    }
+}
+
+/// 工具状态显示
+pub struct ToolStatusDisplay {
+    progress: MultiProgress,
+    /// 提问结束后恢复显示用的输出目标
+    draw_target: fn() -> ProgressDrawTarget,
+    active: Mutex<HashMap<u64, ActiveTool>>,
+    compact: AtomicBool,
+    image_preview: ImagePreview,
+    /// 纯文本模式的输出
+    plain: Option<Arc<PlainWriter>>,
+}
+
+impl Default for ToolStatusDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolStatusDisplay {
+    pub fn new() -> Self {
+        Self::with_draw_target(ProgressDrawTarget::stderr)
+    }
+
+    fn with_draw_target(draw_target: fn() -> ProgressDrawTarget) -> Self {
+        Self {
+            progress: MultiProgress::with_draw_target(draw_target()),
+            draw_target,
+            active: Mutex::new(HashMap::new()),
+            compact: AtomicBool::new(false),
+            image_preview: ImagePreview::default(),
+            plain: None,
+        }
+    }
+
+    /// 纯文本模式：不显示状态行，结果逐行写到 `out`
+    pub fn with_plain_output(mut self, out: Arc<PlainWriter>) -> Self {
+        self.draw_target = ProgressDrawTarget::hidden;
+        self.progress.set_draw_target(ProgressDrawTarget::hidden());
+        self.plain = Some(out);
+        self
+    }
+
+    /// 设置工具输出图片的预览方式
+    pub fn with_image_preview(mut self, image_preview: ImagePreview) -> Self {
+        self.image_preview = image_preview;
+        self
+    }
+
+    /// 设置简洁模式
+    pub fn set_compact(&self, compact: bool) {
+        self.compact.store(compact, Ordering::Relaxed);
+    }
+
+    fn is_compact(&self) -> bool {
+        self.compact.load(Ordering::Relaxed)
+    }
+
+    /// 失败行显示的错误信息
+    fn error_text(&self, error: &str) -> String {
+        if !self.is_compact() {
+            return error.to_string();
+        }
+        let first_line = error.lines().next().unwrap_or("");
+        match first_line.char_indices().nth(COMPACT_ERROR_CHARS) {
+            Some((index, _)) => format!("{}…", &first_line[..index]),
+            None => first_line.to_string(),
+        }
+    }
+
+    /// 正在执行的工具数量
+    pub fn active_count(&self) -> usize {
+        self.active.lock().map(|active| active.len()).unwrap_or(0)
+    }
+
+    fn start(&self, call_id: u64, tool: &str, summary: &str) {
+        let mut active_tool = ActiveTool {
+            bar: None,
+            tool: tool.to_string(),
+            summary: summary.to_string(),
+        };
+
+        if self.plain.is_none() {
+            let style = ProgressStyle::with_template("  {spinner:.blue} {msg} {elapsed:.dim}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner())
+                .tick_strings(SPINNER_FRAMES);
+            let bar = self.progress.add(ProgressBar::new_spinner().with_style(style));
+            bar.set_message(active_tool.label());
+            bar.enable_steady_tick(TICK_INTERVAL);
+            active_tool.bar = Some(bar);
+        }
+
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(call_id, active_tool);
+        }
+    }
+
+    fn finish(&self, call_id: u64, duration: Duration, error: Option<String>) {
+        let Some(tool) = self.active.lock().ok().and_then(|mut a| a.remove(&call_id)) else {
+            return;
+        };
+        if let Some(out) = &self.plain {
+            let _ = out.line(&tool.plain_line(duration, error.as_deref()));
+            return;
+        }
+        let Some(bar) = &tool.bar else {
+            return;
+        };
+
+        let elapsed = format!("{:.1}s", duration.as_secs_f64());
+        let line = match error {
+            None if duration < KEEP_LINE_AFTER || self.is_compact() => {
+                bar.finish_and_clear();
+                self.progress.remove(bar);
+                return;
+            }
+            None => format!("{} {} {}", "✓".green(), tool.label(), elapsed.dimmed()),
+            Some(error) => format!(
+                "{} {} {} {}",
+                "✗".red(),
+                tool.label(),
+                elapsed.dimmed(),
+                self.error_text(&error).red()
+            ),
+        };
+
+        bar.set_style(
+            ProgressStyle::with_template("  {msg}").unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        bar.finish_with_message(line);
+    }
+
+    /// 在状态行下方显示图片预览（纯文本模式下只显示占位行）
+    fn show_image(&self, image: &ImageInfo) {
+        if let Some(out) = &self.plain {
+            let _ = out.line(&image::placeholder(image));
+            return;
+        }
+        let preview = self.image_preview.render(image);
+        self.progress.suspend(|| println!("{}", preview));
+    }
+
+    /// 在状态行上方输出一行提示，不打乱正在刷新的状态行
+    pub fn notice(&self, text: &str) {
+        if let Some(out) = &self.plain {
+            let _ = out.line(text);
+            return;
+        }
+        let line = format!("  {}", text.bright_yellow());
+        self.progress.suspend(|| println!("{}", line));
+    }
+}
+
+impl Pausable for ToolStatusDisplay {
+    /// 提问期间清掉状态行并停止绘制，工具仍在后台计时
+    fn pause(&self) {
+        let _ = self.progress.clear();
+        self.progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    fn resume(&self) {
+        self.progress.set_draw_target((self.draw_target)());
+    }
+}
+
+impl ToolStatusSink for ToolStatusDisplay {
+    fn handle(&self, event: &ToolStatusEvent) {
+        match event {
+            ToolStatusEvent::ToolStarted {
+                call_id,
+                tool,
+                summary,
+                ..
+            } => self.start(*call_id, tool, summary),
+            ToolStatusEvent::ToolCompleted {
+                call_id,
+                duration_ms,
+                success,
+                ..
+            } => {
+                let error = (!success).then(|| "failed".to_string());
+                self.finish(*call_id, Duration::from_millis(*duration_ms), error);
+            }
+            ToolStatusEvent::ToolFailed {
+                call_id,
+                duration_ms,
+                error,
+                ..
+            } => self.finish(
+                *call_id,
+                Duration::from_millis(*duration_ms),
+                Some(error.clone()),
+            ),
+            ToolStatusEvent::ImageOutput { image, .. } => self.show_image(image),
+            // 权限确认已在提问时显示
+            ToolStatusEvent::PermissionDecided { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_concurrent_tools() {
+        let display = ToolStatusDisplay::with_draw_target(ProgressDrawTarget::hidden);
+
+        for call_id in [1, 2] {
+            display.handle(&ToolStatusEvent::ToolStarted {
+                call_id,
+                tool: "shell_execute".to_string(),
+                summary: "cargo test".to_string(),
+                args: serde_json::Value::Null,
+            });
+        }
+        assert_eq!(display.active_count(), 2);
+
+        display.handle(&ToolStatusEvent::ToolCompleted {
+            call_id: 1,
+            tool: "shell_execute".to_string(),
+            duration_ms: 2500,
+            success: true,
+            output: String::new(),
+        });
+        assert_eq!(display.active_count(), 1);
+
+        display.handle(&ToolStatusEvent::ToolFailed {
+            call_id: 2,
+            tool: "shell_execute".to_string(),
+            duration_ms: 10,
+            error: "permission denied".to_string(),
+        });
+        assert_eq!(display.active_count(), 0);
+
+        // 未知调用 ID 被忽略
+        display.handle(&ToolStatusEvent::ToolFailed {
+            call_id: 99,
+            tool: "read_file".to_string(),
+            duration_ms: 0,
+            error: "not found".to_string(),
+        });
+        assert_eq!(display.active_count(), 0);
+    }
+
+    #[test]
+    fn test_compact_mode_shortens_errors() {
+        let display = ToolStatusDisplay::with_draw_target(ProgressDrawTarget::hidden);
+        let error = format!("{}\nsecond line", "x".repeat(100));
+        assert_eq!(display.error_text(&error), error);
+
+        display.set_compact(true);
+        assert_eq!(display.error_text(&error), format!("{}…", "x".repeat(80)));
+        assert_eq!(display.error_text("not found\ndetails"), "not found");
+    }
+}
@@ -0,0 +1,291 @@
+//! 处理请求期间的按键
+//!
+//! reedline 只在等待输入时读取按键。处理请求期间由后台线程关闭终端的规范模式和回显
+//! （保留 ISIG 和输出处理，Ctrl+C 和换行照常），逐字节读取 stdin：Ctrl+O 切换详细工具输出
+//! （见 [`verbose_output`](crate::tools::verbose_output)），其余按键作为预输入，
+//! 在下一次提示时放回输入框。工具提问期间恢复原来的终端设置并停止读取，提问照常从 stdin 读取回答。
+
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use crate::agent::interaction::{self, Pausable};
+use crate::tools::verbose_output::{verbose_tools, VerboseTools};
+
+/// Ctrl+O
+const TOGGLE_VERBOSE: u8 = 0x0F;
+
+/// 等待按键的超时（毫秒），决定停止监听的最长延迟
+#[cfg(unix)]
+const POLL_TIMEOUT_MS: i32 = 100;
+
+/// 切换详细输出后的回调，参数为切换后的状态
+pub type ToggleCallback = Box<dyn Fn(bool) + Send + Sync>;
+
+/// 监听状态，在后台线程和提问暂停之间共享
+struct Listener {
+    tools: &'static VerboseTools,
+    on_toggle: ToggleCallback,
+    /// 处于 start 和 stop 之间
+    running: AtomicBool,
+    /// 进入监听前的终端设置
+    #[cfg(unix)]
+    saved: Mutex<Option<libc::termios>>,
+    /// 尚未放回输入框的按键
+    type_ahead: Mutex<Vec<u8>>,
+}
+
+impl Listener {
+    fn new(tools: &'static VerboseTools, on_toggle: ToggleCallback) -> Self {
+        Self {
+            tools,
+            on_toggle,
+            running: AtomicBool::new(false),
+            #[cfg(unix)]
+            saved: Mutex::new(None),
+            type_ahead: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn type_ahead(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.type_ahead.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 处理读到的按键
+    fn feed(&self, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == TOGGLE_VERBOSE {
+                let verbose = self.tools.toggle();
+                (self.on_toggle)(verbose);
+            } else {
+                self.type_ahead().push(byte);
+            }
+        }
+    }
+
+    fn take_type_ahead(&self) -> String {
+        type_ahead_text(&std::mem::take(&mut *self.type_ahead()))
+    }
+}
+
+/// 把预输入的字节还原为输入框中的文本
+///
+/// 回显已关闭，退格在这里生效；方向键等转义序列和其他控制字符丢弃，换行换成空格
+/// （不替用户提交）。
+fn type_ahead_text(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut result = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // CSI（ESC [ ... 终止字符）、SS3（ESC O 加一个字符）或 ESC 加一个字符
+                if chars.next_if_eq(&'[').is_some() {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                } else {
+                    chars.next_if_eq(&'O');
+                    chars.next();
+                }
+            }
+            '\x7f' | '\x08' => {
+                result.pop();
+            }
+            '\r' | '\n' | '\t' => result.push(' '),
+            c if c.is_control() => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(unix)]
+impl Listener {
+    fn saved(&self) -> MutexGuard<'_, Option<libc::termios>> {
+        self.saved.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 关闭规范模式和回显，保留信号和输出处理
+    fn enter_cbreak(&self) {
+        let Some(mut termios) = *self.saved() else {
+            return;
+        };
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::IEXTEN);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        // SAFETY: termios 来自 tcgetattr，fd 0 在进程内始终有效
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+        }
+    }
+
+    fn restore_terminal(&self) {
+        if let Some(termios) = *self.saved() {
+            // SAFETY: 同上
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+
+    /// 等待按键；返回 true 表示 stdin 有可读数据
+    fn poll(timeout_ms: i32) -> bool {
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: fds 在调用期间有效
+        let ready = unsafe { libc::poll(&mut fds, 1, timeout_ms) };
+        ready > 0 && fds.revents & libc::POLLIN != 0
+    }
+
+    fn run(&self) {
+        let mut buf = [0u8; 64];
+        while self.running.load(Ordering::Relaxed) {
+            if !Self::poll(POLL_TIMEOUT_MS) {
+                continue;
+            }
+            // 提问开始后不再读取，按键留给提问
+            let mut read = 0;
+            interaction::draw(|| {
+                if Self::poll(0) {
+                    // SAFETY: buf 在调用期间有效
+                    let n = unsafe {
+                        libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len())
+                    };
+                    read = n.max(0) as usize;
+                }
+            });
+            if read > 0 {
+                self.feed(&buf[..read]);
+            } else if interaction::is_prompting() {
+                std::thread::sleep(std::time::Duration::from_millis(POLL_TIMEOUT_MS as u64));
+            }
+        }
+    }
+}
+
+impl Pausable for Listener {
+    fn pause(&self) {
+        #[cfg(unix)]
+        if self.running.load(Ordering::Relaxed) {
+            self.restore_terminal();
+        }
+    }
+
+    fn resume(&self) {
+        #[cfg(unix)]
+        if self.running.load(Ordering::Relaxed) {
+            self.enter_cbreak();
+        }
+    }
+}
+
+/// 处理请求期间的按键监听
+pub struct TurnKeys {
+    listener: Arc<Listener>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TurnKeys {
+    /// 创建监听，Ctrl+O 切换后调用 `on_toggle` 显示提示
+    ///
+    /// 监听注册到 `interaction`，工具提问期间暂停。
+    pub fn new(on_toggle: ToggleCallback) -> Self {
+        let listener = Arc::new(Listener::new(verbose_tools(), on_toggle));
+        interaction::register(listener.clone());
+        Self {
+            listener,
+            thread: None,
+        }
+    }
+
+    /// 开始监听（stdin 不是终端时不做任何事）
+    pub fn start(&mut self) {
+        if self.thread.is_some() || !io::stdin().is_terminal() {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+            // SAFETY: tcgetattr 成功时写满 termios
+            let saved = unsafe {
+                (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                    .then(|| termios.assume_init())
+            };
+            let Some(saved) = saved else {
+                return;
+            };
+            *self.listener.saved() = Some(saved);
+            self.listener.running.store(true, Ordering::Relaxed);
+            self.listener.enter_cbreak();
+            let listener = Arc::clone(&self.listener);
+            self.thread = Some(std::thread::spawn(move || listener.run()));
+        }
+    }
+
+    /// 停止监听并恢复终端设置（最多等待一个轮询周期）
+    pub fn stop(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        self.listener.running.store(false, Ordering::Relaxed);
+        let _ = thread.join();
+        #[cfg(unix)]
+        self.listener.restore_terminal();
+    }
+
+    /// 取出处理请求期间输入的文本，放回下一次提示的输入框
+    pub fn take_type_ahead(&self) -> String {
+        self.listener.take_type_ahead()
+    }
+}
+
+impl Drop for TurnKeys {
+    /// 出错提前返回时也要恢复终端，避免影响退出后的 shell
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctrl_o_toggles_and_keeps_type_ahead() {
+        let tools: &'static VerboseTools = Box::leak(Box::new(VerboseTools::new(false)));
+        let notices = Arc::new(Mutex::new(Vec::new()));
+        let listener = {
+            let notices = Arc::clone(&notices);
+            Listener::new(
+                tools,
+                Box::new(move |verbose| notices.lock().unwrap().push(verbose)),
+            )
+        };
+
+        listener.feed(b"fix\x0f");
+        assert!(tools.is_verbose());
+        listener.feed(b" it\x0f\x0f");
+        assert!(tools.is_verbose());
+        listener.feed(&[TOGGLE_VERBOSE]);
+        assert!(!tools.is_verbose());
+        assert_eq!(*notices.lock().unwrap(), vec![true, false, true, false]);
+
+        assert_eq!(listener.take_type_ahead(), "fix it");
+        assert_eq!(listener.take_type_ahead(), "");
+    }
+
+    #[test]
+    fn test_type_ahead_text() {
+        assert_eq!(type_ahead_text(b"abd\x7fc"), "abc");
+        assert_eq!(type_ahead_text(b"up\x1b[A\x1bOBdown"), "updown");
+        assert_eq!(type_ahead_text(b"one\rtwo\x03"), "one two");
+        assert_eq!(type_ahead_text("café".as_bytes()), "café");
+    }
+}
@@ -13,7 +13,10 @@ use std::env;
 
 mod loader;
 pub mod secret;
-pub use loader::ConfigLoader;
+pub use loader::{
+    BashToolConfig, BehaviorConfig, ConfigLoader, EditorConfig, FormatOnWriteConfig, ImagePreviewMode, LimitsConfig, McpServerConfig, MemoryConfig, ModeConfig, ModelConfig, ModelPricingConfig, NetworkConfig, NetworkMode, OutputConfig, OutputLanguage, OutputStyle, PermissionRulesConfig, PermissionsConfig, RenderConfig, SandboxConfig, SandboxMode, SensitivePathMode, SensitivePathsConfig, TagConfig, TasksConfig, TomlConfig, ToolsConfig, UiConfig, UpdateChannel, UpdateConfig,
+};
+pub(crate) use loader::upsert_section;
 pub use secret::Secret;
 
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
@@ -29,8 +32,9 @@ pub struct Config {
     pub model: Option<String>,
     #[allow(dead_code)]
     pub max_tokens: u32,
-    #[allow(dead_code)]
     pub stream_chars_per_tick: usize,
+    pub stream_animation: bool,
+    pub debug: bool,
 }
 
 // 手动实现 Debug，防止 auth_token 泄露
@@ -42,6 +46,8 @@ impl std::fmt::Debug for Config {
             .field("model", &self.model)
             .field("max_tokens", &self.max_tokens)
             .field("stream_chars_per_tick", &self.stream_chars_per_tick)
+            .field("stream_animation", &self.stream_animation)
+            .field("debug", &self.debug)
             .finish()
     }
 }
@@ -58,6 +64,8 @@ impl Config {
             model: loaded.model,
             max_tokens: loaded.max_tokens,
             stream_chars_per_tick: loaded.stream_chars_per_tick,
+            stream_animation: loaded.stream_animation,
+            debug: loaded.debug,
         })
     }
 
@@ -100,12 +108,24 @@ impl Config {
             .filter(|v| *v > 0)
             .unwrap_or(DEFAULT_STREAM_CHARS_PER_TICK);
 
+        let stream_animation = env::var("STREAM_ANIMATION")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+            .unwrap_or(true);
+
+        let debug = env::var("OXIDE_DEBUG")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+            .unwrap_or(false);
+
         Ok(Config {
             base_url,
             auth_token,
             model,
             max_tokens,
             stream_chars_per_tick,
+            stream_animation,
+            debug,
         })
     }
 
@@ -131,6 +151,8 @@ mod tests {
             model: Some(DEFAULT_MODEL.to_string()),
             max_tokens: DEFAULT_MAX_TOKENS,
             stream_chars_per_tick: DEFAULT_STREAM_CHARS_PER_TICK,
+            stream_animation: true,
+            debug: false,
         };
         assert!(config.validate().is_ok());
     }
@@ -143,6 +165,8 @@ mod tests {
             model: Some(DEFAULT_MODEL.to_string()),
             max_tokens: DEFAULT_MAX_TOKENS,
             stream_chars_per_tick: DEFAULT_STREAM_CHARS_PER_TICK,
+            stream_animation: true,
+            debug: false,
         };
         assert!(config.validate().is_err());
     }
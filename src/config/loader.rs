@@ -8,10 +8,12 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::atomic_file::write_atomic;
 use crate::config::secret::Secret;
 
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
@@ -37,6 +39,73 @@ fn project_config_dir() -> PathBuf {
     PathBuf::from(".oxide")
 }
 
+/// 替换 TOML 文本中 `[section]` 段的内容，不存在时追加到末尾
+///
+/// 只改动这一段，其余内容（包括注释）原样保留。
+pub(crate) fn upsert_section(content: &str, section: &str, body: &str) -> String {
+    let header = format!("[{}]", section);
+    let mut output = String::new();
+    let mut lines = content.lines().peekable();
+    let mut replaced = false;
+    while let Some(line) = lines.next() {
+        if replaced || line.trim() != header {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+
+        let mut old_body = Vec::new();
+        while let Some(next) = lines.next_if(|next| !next.trim_start().starts_with('[')) {
+            old_body.push(next);
+        }
+        // 段末尾的空行和注释属于下一段，保留
+        let keep_from = old_body
+            .iter()
+            .rposition(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            })
+            .map_or(0, |index| index + 1);
+
+        output.push_str(&header);
+        output.push('\n');
+        output.push_str(body);
+        if keep_from == old_body.len() && lines.peek().is_some() {
+            output.push('\n');
+        }
+        for line in &old_body[keep_from..] {
+            output.push_str(line);
+            output.push('\n');
+        }
+        replaced = true;
+    }
+
+    if !replaced {
+        if !output.is_empty() && !output.ends_with("\n\n") {
+            output.push('\n');
+        }
+        output.push_str(&header);
+        output.push('\n');
+        output.push_str(body);
+    }
+    output
+}
+
+/// 把一个配置段写入 `path`（替换已有的同名段，其余内容保持不变）
+fn save_section<T: Serialize>(path: &Path, section: &str, value: &T) -> Result<()> {
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("无法读取配置文件: {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let body = toml::to_string(value).context("序列化配置失败")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("无法创建配置目录: {}", dir.display()))?;
+    }
+    write_atomic(path, upsert_section(&content, section, &body).as_bytes())
+        .with_context(|| format!("无法写入配置文件: {}", path.display()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TomlConfig {
     #[serde(default)]
@@ -50,6 +119,72 @@ pub struct TomlConfig {
 
     #[serde(default)]
     pub features: Option<FeaturesConfig>,
+
+    #[serde(default)]
+    pub permissions: Option<PermissionsConfig>,
+
+    #[serde(default)]
+    pub behavior: Option<BehaviorConfig>,
+
+    /// 写入后格式化（`[format_on_write]`）
+    #[serde(default)]
+    pub format_on_write: Option<FormatOnWriteConfig>,
+
+    /// 项目记忆（`[memory]`）
+    #[serde(default)]
+    pub memory: Option<MemoryConfig>,
+
+    /// 后台任务（`[tasks]`）
+    #[serde(default)]
+    pub tasks: Option<TasksConfig>,
+
+    /// 命令执行工具（`[tools]`）
+    #[serde(default)]
+    pub tools: Option<ToolsConfig>,
+
+    /// 界面（`[ui]`）
+    #[serde(default)]
+    pub ui: Option<UiConfig>,
+
+    /// 输入编辑器（`[editor]`）
+    #[serde(default)]
+    pub editor: Option<EditorConfig>,
+
+    /// 自动更新（`[update]`）
+    #[serde(default)]
+    pub update: Option<UpdateConfig>,
+
+    /// 回复风格与语言（`[output]`）
+    #[serde(default)]
+    pub output: Option<OutputConfig>,
+
+    /// 回复渲染（`[render]`）
+    #[serde(default)]
+    pub render: Option<RenderConfig>,
+
+    /// 每轮对话的限制（`[limits]`）
+    #[serde(default)]
+    pub limits: Option<LimitsConfig>,
+
+    /// 启动时的对话模式（`[mode]`）
+    #[serde(default)]
+    pub mode: Option<ModeConfig>,
+
+    /// 工具的网络访问（`[network]`）
+    #[serde(default)]
+    pub network: Option<NetworkConfig>,
+
+    /// MCP 服务器（`[mcp_servers.<name>]`）
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+
+    /// 消息标签（`[tags.<name>]`）
+    #[serde(default)]
+    pub tags: BTreeMap<String, TagConfig>,
+
+    /// 模型信息（`[models."<pattern>"]`）
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,80 +195,975 @@ pub struct DefaultConfig {
     #[serde(default)]
     pub model: Option<String>,
 
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: u32,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+
+    /// 是否启用流式输出动画
+    #[serde(default = "default_stream_animation")]
+    pub stream_animation: bool,
+}
+
+impl Default for DefaultConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_base_url(),
+            model: None,
+            max_tokens: default_max_tokens(),
+            temperature: default_temperature(),
+            stream_animation: default_stream_animation(),
+        }
+    }
+}
+
+fn default_stream_animation() -> bool {
+    true
+}
+
+fn default_base_url() -> String {
+    DEFAULT_BASE_URL.to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    DEFAULT_MAX_TOKENS
+}
+
+fn default_temperature() -> f32 {
+    0.7
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfigs {
+    #[serde(default)]
+    pub explore: Option<AgentConfig>,
+
+    #[serde(default)]
+    pub plan: Option<AgentConfig>,
+
+    #[serde(default)]
+    pub code_reviewer: Option<AgentConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfig {
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub mode: String,
+
+    #[serde(default)]
+    pub custom_theme: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeaturesConfig {
+    #[serde(default)]
+    pub enable_mcp: bool,
+
+    #[serde(default)]
+    pub enable_multimodal: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            enable_mcp: false,
+            enable_multimodal: false,
+        }
+    }
+}
+
+/// 运行行为配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviorConfig {
+    /// 调试模式：把 trace 写入 `.oxide/traces/`
+    #[serde(default)]
+    pub debug: bool,
+
+    /// 文件日志级别（`"error"`、`"warn"`、`"info"`、`"debug"`、`"trace"`），
+    /// 写入 `~/.oxide/logs/`；未设置或为 `"off"` 时不写日志
+    #[serde(default)]
+    pub log_level: Option<String>,
+
+    /// 最多保留的日志文件数（每天一个）
+    #[serde(default = "default_behavior_log_max_files")]
+    pub log_max_files: usize,
+
+    /// 距离上一轮超过该分钟数时提醒模型重新读取文件（0 表示关闭）
+    #[serde(default = "default_behavior_idle_reminder_minutes")]
+    pub idle_reminder_minutes: u64,
+
+    /// 上下文将满、会话过长、工具反复失败时在终端显示提示
+    #[serde(default = "default_behavior_advisories")]
+    pub advisories: bool,
+
+    /// 会话结束时整理项目事实，确认后写入项目记忆
+    #[serde(default)]
+    pub session_learning: bool,
+
+    /// 每次最多整理的事实数
+    #[serde(default = "default_behavior_session_learning_max_facts")]
+    pub session_learning_max_facts: usize,
+
+    /// 发送请求时把被之后的读取或修改取代的文件读取结果替换为一行说明（不修改保存的会话）
+    #[serde(default = "default_behavior_dedupe_file_reads")]
+    pub dedupe_file_reads: bool,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            log_level: None,
+            log_max_files: default_behavior_log_max_files(),
+            idle_reminder_minutes: default_behavior_idle_reminder_minutes(),
+            advisories: default_behavior_advisories(),
+            session_learning: false,
+            session_learning_max_facts: default_behavior_session_learning_max_facts(),
+            dedupe_file_reads: default_behavior_dedupe_file_reads(),
+        }
+    }
+}
+
+impl BehaviorConfig {
+    /// 读取合并后的 `[behavior]` 配置
+    pub fn load() -> Self {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.behavior)
+            .unwrap_or_default()
+    }
+}
+
+fn default_behavior_log_max_files() -> usize {
+    7
+}
+
+fn default_behavior_idle_reminder_minutes() -> u64 {
+    30
+}
+
+fn default_behavior_session_learning_max_facts() -> usize {
+    5
+}
+
+fn default_behavior_advisories() -> bool {
+    true
+}
+
+fn default_behavior_dedupe_file_reads() -> bool {
+    true
+}
+
+/// 权限配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PermissionsConfig {
+    /// 敏感文件访问策略
+    #[serde(default)]
+    pub sensitive_paths: Option<SensitivePathsConfig>,
+
+    /// 是否对返回给模型的工具结果进行密钥脱敏（默认启用）
+    #[serde(default)]
+    pub redact_secrets: Option<bool>,
+
+    /// 是否允许 http_request 访问 localhost 和内网地址（默认禁止）
+    #[serde(default)]
+    pub allow_private_network: Option<bool>,
+
+    /// 是否允许直接覆盖 agent 本次会话没有读过、带有未提交修改的文件（默认写入前确认）
+    #[serde(default)]
+    pub allow_overwrite_uncommitted: Option<bool>,
+
+    /// 工具调用的权限规则（`[permissions.rules]`）
+    #[serde(default)]
+    pub rules: Option<PermissionRulesConfig>,
+}
+
+/// 工具调用的权限规则（`[permissions.rules]`），语法见 `crate::permissions`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionRulesConfig {
+    /// 直接执行的操作
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// 总是询问的操作
+    #[serde(default)]
+    pub ask: Vec<String>,
+
+    /// 拒绝执行的操作
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// MCP 服务器配置（`[mcp_servers.<name>]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// 启动命令
+    pub command: String,
+
+    /// 命令参数
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// 额外的环境变量
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// 是否在启动时连接（默认启用）
+    #[serde(default = "default_mcp_enabled")]
+    pub enabled: bool,
+}
+
+fn default_mcp_enabled() -> bool {
+    true
+}
+
+/// 消息标签配置（`[tags.<name>]`）
+///
+/// 以 `#<name>` 开头的消息会带上该标签，`prompt` 会在发送给模型前插入到消息开头。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagConfig {
+    /// 补全菜单中显示的说明
+    pub description: String,
+
+    /// 发送时插入的提示词片段
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+/// 模型信息配置（`[models."<pattern>"]`）
+///
+/// 模式中的 `*` 匹配任意字符，未设置的字段沿用内置信息（见 `model_info`）。
+///
+/// ```toml
+/// [models."my-custom-model"]
+/// context_window = 32768
+/// max_output = 4096
+/// supports_vision = false
+/// pricing = { input = 0.5, output = 1.5 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelConfig {
+    /// 上下文窗口（token）
+    #[serde(default)]
+    pub context_window: Option<usize>,
+
+    /// 单次回复的输出上限（token）
+    #[serde(default)]
+    pub max_output: Option<u32>,
+
+    #[serde(default)]
+    pub supports_vision: Option<bool>,
+
+    #[serde(default)]
+    pub supports_tools: Option<bool>,
+
+    /// 价格（美元 / 百万 token）
+    #[serde(default)]
+    pub pricing: Option<ModelPricingConfig>,
+}
+
+/// 模型价格配置（美元 / 百万 token）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricingConfig {
+    pub input: f64,
+    pub output: f64,
+    /// 命中提示缓存的输入（默认与 `input` 相同）
+    #[serde(default)]
+    pub cached_input: Option<f64>,
+}
+
+/// 写入后格式化配置（`[format_on_write]`）
+///
+/// ```toml
+/// [format_on_write]
+/// "**/*.rs" = "rustfmt --edition 2021 {path}"
+/// "**/*.{ts,tsx}" = "prettier --write {path}"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatOnWriteConfig {
+    /// 单次格式化的超时时间（秒）
+    #[serde(default = "default_format_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// 没有规则匹配时使用内置预设（存在 Cargo.toml 时用 rustfmt 等）
+    #[serde(default)]
+    pub auto_detect: bool,
+
+    /// 文件 glob → 格式化命令，`{path}` 替换为写入的文件路径
+    #[serde(flatten)]
+    pub rules: BTreeMap<String, String>,
+}
+
+impl Default for FormatOnWriteConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_format_timeout_secs(),
+            auto_detect: false,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_format_timeout_secs() -> u64 {
+    10
+}
+
+/// 项目记忆配置（`[memory]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// `.oxide/memory.md` 中 "Agent notes" 部分的字节上限，超出时删除最早的笔记
+    #[serde(default = "default_memory_max_notes_bytes")]
+    pub max_notes_bytes: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            max_notes_bytes: default_memory_max_notes_bytes(),
+        }
+    }
+}
+
+fn default_memory_max_notes_bytes() -> usize {
+    4096
+}
+
+/// 后台任务配置（`[tasks]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TasksConfig {
+    /// 单个输出文件的字节上限，超出时轮转为 `<id>.output.1.txt`
+    #[serde(default = "default_tasks_max_output_bytes")]
+    pub max_output_bytes: u64,
+
+    /// 最多保留的轮转文件数，更早的输出被丢弃
+    #[serde(default = "default_tasks_max_output_rotations")]
+    pub max_output_rotations: u32,
+
+    /// 已完成任务在列表中的保留天数，更早完成的任务默认不显示（0 表示全部显示）
+    #[serde(default = "default_tasks_completed_retention_days")]
+    pub completed_retention_days: u64,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            max_output_bytes: default_tasks_max_output_bytes(),
+            max_output_rotations: default_tasks_max_output_rotations(),
+            completed_retention_days: default_tasks_completed_retention_days(),
+        }
+    }
+}
+
+fn default_tasks_max_output_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_tasks_max_output_rotations() -> u32 {
+    3
+}
+
+fn default_tasks_completed_retention_days() -> u64 {
+    7
+}
+
+/// 工具配置（`[tools]`）
+///
+/// 项目配置按键覆盖全局配置，见 [`ToolsConfig::merge`]。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    /// shell_execute 和后台任务使用的 shell：`"bash"`、`"powershell"`、`"cmd"`
+    /// 或 shell 可执行文件的路径；未设置时按平台自动选择
+    #[serde(default)]
+    pub shell: Option<String>,
+
+    /// 工具结果超过该 token 数时保存到 `.oxide/tool-results/`，对话中只放摘要（默认 10000，0 不限制）
+    #[serde(default)]
+    pub result_max_tokens: Option<usize>,
+
+    /// 一轮中同一工具调用（相同参数）超过该次数时不再执行（默认 3，0 不限制）
+    #[serde(default)]
+    pub max_identical_calls: Option<usize>,
+
+    /// shell_execute 工具（`[tools.bash]`）
+    #[serde(default)]
+    pub bash: Option<BashToolConfig>,
+
+    /// grep_search 工具（`[tools.grep]`）
+    #[serde(default)]
+    pub grep: Option<GrepToolConfig>,
+
+    /// read_file 工具（`[tools.read]`）
+    #[serde(default)]
+    pub read: Option<ReadToolConfig>,
+
+    /// 工具的根目录（`[tools.root]`）
+    #[serde(default)]
+    pub root: Option<RootToolConfig>,
+
+    /// 联网工具（`[tools.web]`）
+    #[serde(default)]
+    pub web: Option<WebToolConfig>,
+}
+
+impl ToolsConfig {
+    /// 逐项合并，`overlay` 中设置了的键覆盖当前值
+    pub fn merge(self, overlay: ToolsConfig) -> ToolsConfig {
+        ToolsConfig {
+            shell: overlay.shell.or(self.shell),
+            result_max_tokens: overlay.result_max_tokens.or(self.result_max_tokens),
+            max_identical_calls: overlay.max_identical_calls.or(self.max_identical_calls),
+            bash: merge_tables(self.bash, overlay.bash, |base, overlay| BashToolConfig {
+                shell: overlay.shell.or(base.shell),
+                default_timeout_ms: overlay.default_timeout_ms.or(base.default_timeout_ms),
+                sandbox: overlay.sandbox.or(base.sandbox),
+            }),
+            grep: merge_tables(self.grep, overlay.grep, |base, overlay| GrepToolConfig {
+                head_limit: overlay.head_limit.or(base.head_limit),
+                hidden: overlay.hidden.or(base.hidden),
+            }),
+            read: merge_tables(self.read, overlay.read, |base, overlay| ReadToolConfig {
+                max_lines: overlay.max_lines.or(base.max_lines),
+            }),
+            root: merge_tables(self.root, overlay.root, |base, overlay| RootToolConfig {
+                path: overlay.path.or(base.path),
+            }),
+            web: merge_tables(self.web, overlay.web, |base, overlay| WebToolConfig {
+                cache_ttl_hours: overlay.cache_ttl_hours.or(base.cache_ttl_hours),
+                cache_max_mb: overlay.cache_max_mb.or(base.cache_max_mb),
+            }),
+        }
+    }
+
+    /// 命令使用的 shell：`[tools.bash] shell` 优先于 `[tools] shell`
+    pub fn shell(&self) -> Option<&str> {
+        self.bash
+            .as_ref()
+            .and_then(|bash| bash.shell.as_deref())
+            .or(self.shell.as_deref())
+    }
+}
+
+/// 合并两个可选的子表，都存在时用 `merge` 逐项合并
+fn merge_tables<T>(
+    base: Option<T>,
+    overlay: Option<T>,
+    merge: impl FnOnce(T, T) -> T,
+) -> Option<T> {
+    match (base, overlay) {
+        (Some(base), Some(overlay)) => Some(merge(base, overlay)),
+        (base, overlay) => overlay.or(base),
+    }
+}
+
+/// shell_execute 工具配置（`[tools.bash]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BashToolConfig {
+    /// 覆盖 `[tools] shell`
+    #[serde(default)]
+    pub shell: Option<String>,
+
+    /// 命令超时（毫秒），未设置时不限制
+    #[serde(default)]
+    pub default_timeout_ms: Option<u64>,
+
+    /// 系统沙箱（`[tools.bash.sandbox]`）
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+}
+
+/// grep_search 工具配置（`[tools.grep]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GrepToolConfig {
+    /// 未指定 `max_results` 时返回的最大匹配数（默认 100）
+    #[serde(default)]
+    pub head_limit: Option<usize>,
+
+    /// 是否搜索隐藏文件（默认搜索）
+    #[serde(default)]
+    pub hidden: Option<bool>,
+}
+
+/// read_file 工具配置（`[tools.read]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReadToolConfig {
+    /// 单次读取返回的最大行数，未设置时不限制
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+}
+
+/// 工具根目录配置（`[tools.root]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RootToolConfig {
+    /// 相对路径的基准目录（相对于启动目录），如 monorepo 中的某个包
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+/// 联网工具配置（`[tools.web]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WebToolConfig {
+    /// 缓存条目的有效期（小时，默认 24），0 表示不使用缓存
+    #[serde(default)]
+    pub cache_ttl_hours: Option<u64>,
+
+    /// 缓存的总大小上限（MB，默认 50）
+    #[serde(default)]
+    pub cache_max_mb: Option<u64>,
+}
+
+/// 沙箱模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SandboxMode {
+    /// 不使用沙箱
+    #[default]
+    Off,
+    /// 只能写入工作目录和 `.oxide/`
+    WorkspaceWrite,
+    /// 不能写入任何文件
+    ReadOnly,
+}
+
+impl SandboxMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SandboxMode::Off => "off",
+            SandboxMode::WorkspaceWrite => "workspace-write",
+            SandboxMode::ReadOnly => "read-only",
+        }
+    }
+}
+
+/// 命令沙箱配置（`[tools.bash.sandbox]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub mode: SandboxMode,
+
+    /// 允许访问网络；为 `false` 时命令在独立的网络命名空间中运行
+    #[serde(default = "default_sandbox_network")]
+    pub network: bool,
+
+    /// 每条命令的 CPU 时间上限（秒，0 表示不限制）
+    #[serde(default = "default_sandbox_cpu_seconds")]
+    pub cpu_seconds: u64,
+
+    /// 每条命令的内存（地址空间）上限（MB，0 表示不限制）
+    #[serde(default = "default_sandbox_memory_mb")]
+    pub memory_mb: u64,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            mode: SandboxMode::default(),
+            network: default_sandbox_network(),
+            cpu_seconds: default_sandbox_cpu_seconds(),
+            memory_mb: default_sandbox_memory_mb(),
+        }
+    }
+}
+
+fn default_sandbox_network() -> bool {
+    true
+}
+
+fn default_sandbox_cpu_seconds() -> u64 {
+    300
+}
+
+fn default_sandbox_memory_mb() -> u64 {
+    4096
+}
+
+/// 工具的网络访问模式（`[network] tools`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkMode {
+    /// 工具不能联网
+    None,
+    /// 只能访问 `allowed_domains` 中的域名
+    Allowlist,
+    /// 不限制
+    #[default]
+    All,
+}
+
+impl NetworkMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkMode::None => "none",
+            NetworkMode::Allowlist => "allowlist",
+            NetworkMode::All => "all",
+        }
+    }
+}
+
+/// 工具的网络访问配置（`[network]`），不影响对模型服务商的请求
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub tools: NetworkMode,
+
+    /// `allowlist` 模式下允许访问的域名（包含其子域名），`host:port` 只允许该端口
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+/// 界面配置（`[ui]`）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiConfig {
+    /// 界面语言：`"en"` 或 `"zh"`；未设置时按 `LANG` 等环境变量确定，默认英文
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// 提交后把提示符重绘为单个字符，保持滚动历史紧凑
+    #[serde(default)]
+    pub transient_prompt: bool,
+
+    /// 处理请求期间在终端底部显示状态栏
+    #[serde(default)]
+    pub status_bar: bool,
+}
+
+/// 输入编辑器配置（`[editor]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorConfig {
+    /// 输入历史最多保留的条数，超出时淘汰最早的记录
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+
+    /// 匹配任一正则的输入不写入历史（例如粘贴的令牌）
+    #[serde(default)]
+    pub history_exclude: Vec<String>,
+
+    /// 输入超过该行数时保存为文件，只发送开头和结尾的摘录；0 表示不按行数限制
+    #[serde(default = "default_paste_max_lines")]
+    pub paste_max_lines: usize,
+
+    /// 输入超过该 token 数时同样处理；0 表示不按 token 数限制
+    #[serde(default = "default_paste_max_tokens")]
+    pub paste_max_tokens: usize,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            history_size: default_history_size(),
+            history_exclude: Vec::new(),
+            paste_max_lines: default_paste_max_lines(),
+            paste_max_tokens: default_paste_max_tokens(),
+        }
+    }
+}
+
+fn default_history_size() -> usize {
+    10_000
+}
+
+fn default_paste_max_lines() -> usize {
+    1_000
+}
+
+fn default_paste_max_tokens() -> usize {
+    20_000
+}
+
+/// 更新渠道
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// 正式版本
+    #[default]
+    Stable,
+    /// 包含预发布版本（每日构建）
+    Nightly,
+}
+
+/// 自动更新配置（`[update]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    #[serde(default)]
+    pub channel: UpdateChannel,
+
+    /// 启动时检查新版本（每天最多一次）
+    #[serde(default = "default_update_check_on_startup")]
+    pub check_on_startup: bool,
+
+    /// 发布版本的 GitHub 仓库（`owner/name`）
+    #[serde(default = "default_update_repository")]
+    pub repository: String,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::default(),
+            check_on_startup: default_update_check_on_startup(),
+            repository: default_update_repository(),
+        }
+    }
+}
+
+fn default_update_check_on_startup() -> bool {
+    true
+}
+
+fn default_update_repository() -> String {
+    "zzfn/oxide".to_string()
+}
+
+/// 回复风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStyle {
+    /// 简洁：先给结论，少解释
+    #[default]
+    Concise,
+    /// 详细：完整说明发现、改动和后续步骤
+    Detailed,
+    /// 讲解：说明思路和相关概念
+    Explanatory,
+}
+
+impl OutputStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputStyle::Concise => "concise",
+            OutputStyle::Detailed => "detailed",
+            OutputStyle::Explanatory => "explanatory",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "concise" => Ok(OutputStyle::Concise),
+            "detailed" => Ok(OutputStyle::Detailed),
+            "explanatory" => Ok(OutputStyle::Explanatory),
+            other => Err(format!("unknown output style: {}", other)),
+        }
+    }
+}
+
+/// 回复语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputLanguage {
+    /// 跟随用户使用的语言
+    #[default]
+    Auto,
+    En,
+    Zh,
+}
+
+impl OutputLanguage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputLanguage::Auto => "auto",
+            OutputLanguage::En => "en",
+            OutputLanguage::Zh => "zh",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputLanguage {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(OutputLanguage::Auto),
+            "en" => Ok(OutputLanguage::En),
+            "zh" => Ok(OutputLanguage::Zh),
+            other => Err(format!("unknown output language: {}", other)),
+        }
+    }
+}
+
+/// 回复风格配置（`[output]`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OutputConfig {
+    #[serde(default)]
+    pub style: OutputStyle,
+
+    #[serde(default)]
+    pub language: OutputLanguage,
+}
+
+impl OutputConfig {
+    /// 读取合并后的 `[output]` 配置
+    pub fn load() -> Self {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.output)
+            .unwrap_or_default()
+    }
+}
+
+/// 终端图片预览方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImagePreviewMode {
+    /// 按终端检测支持的图片协议
+    #[default]
+    Auto,
+    /// 只显示占位行
+    Off,
+    /// kitty graphics protocol
+    Kitty,
+    /// iTerm2 inline images
+    Iterm2,
+    Sixel,
+}
+
+/// 回复渲染配置（`[render]`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderConfig {
+    /// 每轮结束后显示耗时、工具调用次数、token 用量和停止原因
+    #[serde(default = "default_turn_summary")]
+    pub turn_summary: bool,
+
+    /// 工具调用超过该次数或调用了子 agent 时，在回答后附上本轮的活动回顾（0 关闭）
+    #[serde(default = "default_turn_recap")]
+    pub turn_recap: usize,
+
+    /// 工具输出图片时在终端显示预览
+    #[serde(default)]
+    pub images: ImagePreviewMode,
+
+    /// 图片预览最多占用的列数
+    #[serde(default = "default_image_max_cols")]
+    pub image_max_cols: u16,
+
+    /// 图片预览最多占用的行数
+    #[serde(default = "default_image_max_rows")]
+    pub image_max_rows: u16,
 
-    #[serde(default = "default_temperature")]
-    pub temperature: f32,
+    /// 在工具的摘要行下方显示完整输出（处理请求期间可按 Ctrl+O 切换）
+    #[serde(default)]
+    pub verbose_tools: bool,
 }
 
-impl Default for DefaultConfig {
+impl Default for RenderConfig {
     fn default() -> Self {
         Self {
-            base_url: default_base_url(),
-            model: None,
-            max_tokens: default_max_tokens(),
-            temperature: default_temperature(),
+            turn_summary: default_turn_summary(),
+            turn_recap: default_turn_recap(),
+            images: ImagePreviewMode::default(),
+            image_max_cols: default_image_max_cols(),
+            image_max_rows: default_image_max_rows(),
+            verbose_tools: false,
         }
     }
 }
 
-fn default_base_url() -> String {
-    DEFAULT_BASE_URL.to_string()
+impl RenderConfig {
+    /// 读取合并后的 `[render]` 配置
+    pub fn load() -> Self {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.render)
+            .unwrap_or_default()
+    }
 }
 
-fn default_max_tokens() -> u32 {
-    DEFAULT_MAX_TOKENS
+/// 每轮对话的限制（`[limits]`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// 每轮对话的最长时间（秒），超时后模型总结进度和剩余步骤；未设置或为 0 时不限制
+    #[serde(default)]
+    pub max_turn_seconds: Option<u64>,
 }
 
-fn default_temperature() -> f32 {
-    0.7
+impl LimitsConfig {
+    /// 读取合并后的 `[limits]` 配置
+    pub fn load() -> Self {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.limits)
+            .unwrap_or_default()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentConfigs {
-    #[serde(default)]
-    pub explore: Option<AgentConfig>,
+/// 启动时的对话模式（`[mode]`，`/mode <模式> --save` 写入项目配置）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModeConfig {
+    /// 模式名（`normal`、`fast` 或 `plan`），命令行 `--mode` 优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
 
-    #[serde(default)]
-    pub plan: Option<AgentConfig>,
+impl ModeConfig {
+    /// 读取合并后的 `[mode]` 配置
+    pub fn load() -> Self {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.mode)
+            .unwrap_or_default()
+    }
+}
 
-    #[serde(default)]
-    pub code_reviewer: Option<AgentConfig>,
+fn default_turn_summary() -> bool {
+    true
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentConfig {
-    pub model: Option<String>,
-    pub max_tokens: Option<u32>,
-    pub temperature: Option<f32>,
+fn default_turn_recap() -> usize {
+    5
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ThemeConfig {
-    #[serde(default)]
-    pub mode: String,
+fn default_image_max_cols() -> u16 {
+    60
+}
 
-    #[serde(default)]
-    pub custom_theme: Option<String>,
+fn default_image_max_rows() -> u16 {
+    20
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FeaturesConfig {
+/// 敏感文件访问模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitivePathMode {
+    /// 直接拒绝访问
+    #[default]
+    Deny,
+    /// 访问前需要用户确认
+    Confirm,
+}
+
+/// 敏感文件配置（`[permissions.sensitive_paths]`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SensitivePathsConfig {
     #[serde(default)]
-    pub enable_mcp: bool,
+    pub mode: SensitivePathMode,
 
+    /// 追加的敏感文件模式（glob 语法，支持 `~/` 前缀）
     #[serde(default)]
-    pub enable_multimodal: bool,
-}
+    pub patterns: Vec<String>,
 
-impl Default for FeaturesConfig {
-    fn default() -> Self {
-        Self {
-            enable_mcp: false,
-            enable_multimodal: false,
-        }
-    }
+    /// 即使命中敏感模式也允许访问的文件（如 `.env.example`）
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// 为 true 时不使用内置默认模式，仅使用 `patterns`
+    #[serde(default)]
+    pub replace_defaults: bool,
 }
 
 impl Default for TomlConfig {
@@ -143,6 +1173,23 @@ impl Default for TomlConfig {
             agent: None,
             theme: None,
             features: None,
+            permissions: None,
+            behavior: None,
+            format_on_write: None,
+            memory: None,
+            tasks: None,
+            tools: None,
+            ui: None,
+            editor: None,
+            update: None,
+            output: None,
+            render: None,
+            limits: None,
+            mode: None,
+            network: None,
+            mcp_servers: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            models: BTreeMap::new(),
         }
     }
 }
@@ -163,8 +1210,28 @@ impl ConfigLoader {
         }
     }
 
+    /// 全局配置文件路径
+    pub fn global_config_path(&self) -> &Path {
+        &self.global_config_path
+    }
+
+    /// 项目配置文件路径
+    pub fn project_config_path(&self) -> &Path {
+        &self.project_config_path
+    }
+
+    /// 把一个配置段写入全局配置文件（替换已有的同名段，其余内容保持不变）
+    pub fn save_global_section<T: Serialize>(&self, section: &str, value: &T) -> Result<()> {
+        save_section(&self.global_config_path, section, value)
+    }
+
+    /// 把一个配置段写入项目配置文件（`.oxide/config.toml`）
+    pub fn save_project_section<T: Serialize>(&self, section: &str, value: &T) -> Result<()> {
+        save_section(&self.project_config_path, section, value)
+    }
+
     /// 加载 TOML 配置文件
-    fn load_toml(&self, path: &Path) -> Result<TomlConfig> {
+    pub fn load_toml(&self, path: &Path) -> Result<TomlConfig> {
         let content = fs::read_to_string(path)
             .with_context(|| format!("无法读取配置文件: {}", path.display()))?;
 
@@ -211,13 +1278,71 @@ impl ConfigLoader {
             base.features = overlay.features;
         }
 
+        // 合并 permissions 配置
+        if overlay.permissions.is_some() {
+            base.permissions = overlay.permissions;
+        }
+
+        // 合并 behavior 配置
+        if overlay.behavior.is_some() {
+            base.behavior = overlay.behavior;
+        }
+
+        // 合并写入后格式化配置
+        if overlay.format_on_write.is_some() {
+            base.format_on_write = overlay.format_on_write;
+        }
+
+        // 合并项目记忆配置
+        if overlay.memory.is_some() {
+            base.memory = overlay.memory;
+        }
+
+        // 合并后台任务配置
+        if overlay.tasks.is_some() {
+            base.tasks = overlay.tasks;
+        }
+
+        // 合并工具配置（按键覆盖）
+        base.tools = merge_tables(base.tools, overlay.tools, ToolsConfig::merge);
+
+        // 合并界面配置
+        if overlay.ui.is_some() {
+            base.ui = overlay.ui;
+        }
+        if overlay.editor.is_some() {
+            base.editor = overlay.editor;
+        }
+        if overlay.update.is_some() {
+            base.update = overlay.update;
+        }
+        if overlay.output.is_some() {
+            base.output = overlay.output;
+        }
+        if overlay.render.is_some() {
+            base.render = overlay.render;
+        }
+        if overlay.limits.is_some() {
+            base.limits = overlay.limits;
+        }
+        if overlay.mode.is_some() {
+            base.mode = overlay.mode;
+        }
+        if overlay.network.is_some() {
+            base.network = overlay.network;
+        }
+
+        // 合并 MCP 服务器（同名服务器以项目配置为准）
+        base.mcp_servers.extend(overlay.mcp_servers);
+        base.tags.extend(overlay.tags);
+        base.models.extend(overlay.models);
+
         base
     }
 
-    /// 加载完整配置
-    pub fn load(&self) -> Result<LoadedConfig> {
+    /// 加载并合并全局与项目 TOML 配置（不读取环境变量）
+    pub fn load_toml_layers(&self) -> Result<TomlConfig> {
         let mut config = TomlConfig::default();
-        let mut project_instructions = None;
 
         // 1. 加载全局配置
         if self.global_config_path.exists() {
@@ -231,6 +1356,14 @@ impl ConfigLoader {
             config = Self::merge_configs(config, project);
         }
 
+        Ok(config)
+    }
+
+    /// 加载完整配置
+    pub fn load(&self) -> Result<LoadedConfig> {
+        let config = self.load_toml_layers()?;
+        let mut project_instructions = None;
+
         // 3. 加载项目指令（系统提示词）
         if self.project_instructions_path.exists() {
             project_instructions = Some(self.read_instructions(&self.project_instructions_path)?);
@@ -268,6 +1401,16 @@ impl ConfigLoader {
             .filter(|v| *v > 0)
             .unwrap_or(DEFAULT_STREAM_CHARS_PER_TICK);
 
+        let stream_animation = env::var("STREAM_ANIMATION")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+            .unwrap_or(config.default.stream_animation);
+
+        let debug = env::var("OXIDE_DEBUG")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+            .unwrap_or_else(|| config.behavior.as_ref().is_some_and(|b| b.debug));
+
         Ok(LoadedConfig {
             base_url,
             auth_token,
@@ -275,10 +1418,13 @@ impl ConfigLoader {
             max_tokens,
             temperature,
             stream_chars_per_tick,
+            stream_animation,
+            debug,
             project_instructions,
             agent_configs: config.agent,
             theme_config: config.theme,
             features_config: config.features.unwrap_or_default(),
+            permissions_config: config.permissions.unwrap_or_default(),
         })
     }
 }
@@ -299,6 +1445,8 @@ pub struct LoadedConfig {
     #[allow(dead_code)]
     pub temperature: f32,
     pub stream_chars_per_tick: usize,
+    pub stream_animation: bool,
+    pub debug: bool,
     #[allow(dead_code)]
     pub project_instructions: Option<String>,
     #[allow(dead_code)]
@@ -307,6 +1455,8 @@ pub struct LoadedConfig {
     pub theme_config: Option<ThemeConfig>,
     #[allow(dead_code)]
     pub features_config: FeaturesConfig,
+    #[allow(dead_code)]
+    pub permissions_config: PermissionsConfig,
 }
 
 // 手动实现 Debug，防止 auth_token 泄露
@@ -319,10 +1469,13 @@ impl std::fmt::Debug for LoadedConfig {
             .field("max_tokens", &self.max_tokens)
             .field("temperature", &self.temperature)
             .field("stream_chars_per_tick", &self.stream_chars_per_tick)
+            .field("stream_animation", &self.stream_animation)
+            .field("debug", &self.debug)
             .field("project_instructions", &self.project_instructions)
             .field("agent_configs", &self.agent_configs)
             .field("theme_config", &self.theme_config)
             .field("features_config", &self.features_config)
+            .field("permissions_config", &self.permissions_config)
             .finish()
     }
 }
@@ -355,6 +1508,50 @@ mod tests {
         assert_eq!(merged.default.temperature, 0.5);
     }
 
+    #[test]
+    fn test_merge_tools_by_key() {
+        let global: TomlConfig = toml::from_str(
+            r#"
+[tools]
+shell = "bash"
+[tools.bash]
+default_timeout_ms = 60000
+[tools.bash.sandbox]
+mode = "read-only"
+[tools.grep]
+head_limit = 200
+hidden = false
+"#,
+        )
+        .unwrap();
+        let project: TomlConfig = toml::from_str(
+            r#"
+[tools.bash]
+shell = "fish"
+[tools.grep]
+head_limit = 50
+[tools.root]
+path = "packages/web"
+"#,
+        )
+        .unwrap();
+
+        let tools = ConfigLoader::merge_configs(global, project).tools.unwrap();
+        assert_eq!(tools.shell(), Some("fish"));
+        assert_eq!(tools.shell.as_deref(), Some("bash"));
+        let bash = tools.bash.unwrap();
+        assert_eq!(bash.default_timeout_ms, Some(60000));
+        assert_eq!(bash.sandbox.unwrap().mode, SandboxMode::ReadOnly);
+        let grep = tools.grep.unwrap();
+        assert_eq!(grep.head_limit, Some(50));
+        assert_eq!(grep.hidden, Some(false));
+        assert_eq!(tools.read, None);
+        assert_eq!(
+            tools.root.unwrap().path,
+            Some(PathBuf::from("packages/web"))
+        );
+    }
+
     #[test]
     fn test_load_toml() {
         let temp_dir = TempDir::new().unwrap();
@@ -379,6 +1576,428 @@ temperature = 0.5
         assert_eq!(config.default.temperature, 0.5);
     }
 
+    #[test]
+    fn test_load_toml_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let content = r#"
+[permissions]
+redact_secrets = false
+allow_private_network = true
+allow_overwrite_uncommitted = true
+
+[permissions.sensitive_paths]
+mode = "confirm"
+patterns = ["*.secret"]
+allow = [".env.example"]
+"#;
+
+        fs::write(&config_file, content).unwrap();
+
+        let loader = ConfigLoader::new();
+        let config = loader.load_toml(&config_file).unwrap();
+        let permissions = config.permissions.unwrap();
+        let sensitive = permissions.sensitive_paths.unwrap();
+
+        assert_eq!(permissions.redact_secrets, Some(false));
+        assert_eq!(permissions.allow_private_network, Some(true));
+        assert_eq!(permissions.allow_overwrite_uncommitted, Some(true));
+        assert_eq!(sensitive.mode, SensitivePathMode::Confirm);
+        assert_eq!(sensitive.patterns, vec!["*.secret".to_string()]);
+        assert_eq!(sensitive.allow, vec![".env.example".to_string()]);
+        assert!(!sensitive.replace_defaults);
+    }
+
+    #[test]
+    fn test_load_toml_mcp_servers() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let content = r#"
+[mcp_servers.sqlite]
+command = "uvx"
+args = ["mcp-server-sqlite", "--db-path", "test.db"]
+
+[mcp_servers.browser]
+command = "npx"
+env = { HEADLESS = "1" }
+enabled = false
+"#;
+
+        fs::write(&config_file, content).unwrap();
+
+        let loader = ConfigLoader::new();
+        let config = loader.load_toml(&config_file).unwrap();
+
+        let sqlite = &config.mcp_servers["sqlite"];
+        assert_eq!(sqlite.command, "uvx");
+        assert_eq!(sqlite.args.len(), 3);
+        assert!(sqlite.enabled);
+
+        let browser = &config.mcp_servers["browser"];
+        assert_eq!(browser.env.get("HEADLESS"), Some(&"1".to_string()));
+        assert!(!browser.enabled);
+
+        // 同名服务器以项目配置为准
+        let mut overlay = TomlConfig::default();
+        overlay.mcp_servers.insert(
+            "sqlite".to_string(),
+            McpServerConfig {
+                command: "sqlite-mcp".to_string(),
+                args: Vec::new(),
+                env: BTreeMap::new(),
+                enabled: false,
+            },
+        );
+        let merged = ConfigLoader::merge_configs(config, overlay);
+        assert_eq!(merged.mcp_servers.len(), 2);
+        assert_eq!(merged.mcp_servers["sqlite"].command, "sqlite-mcp");
+    }
+
+    #[test]
+    fn test_load_toml_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let content = r#"
+[tags.security]
+description = "Security review"
+prompt = "Focus on injection, auth and secrets handling."
+
+[tags.wip]
+description = "Work in progress"
+"#;
+
+        fs::write(&config_file, content).unwrap();
+
+        let loader = ConfigLoader::new();
+        let config = loader.load_toml(&config_file).unwrap();
+
+        let security = &config.tags["security"];
+        assert_eq!(security.description, "Security review");
+        assert!(security.prompt.as_deref().unwrap().starts_with("Focus on"));
+        assert_eq!(config.tags["wip"].prompt, None);
+
+        // 同名标签以项目配置为准
+        let mut overlay = TomlConfig::default();
+        overlay.tags.insert(
+            "wip".to_string(),
+            TagConfig {
+                description: "Draft".to_string(),
+                prompt: Some("Keep it short.".to_string()),
+            },
+        );
+        let merged = ConfigLoader::merge_configs(config, overlay);
+        assert_eq!(merged.tags.len(), 2);
+        assert_eq!(merged.tags["wip"].description, "Draft");
+    }
+
+    #[test]
+    fn test_load_toml_models() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let content = r#"
+[models."my-custom-model"]
+context_window = 32768
+pricing = { input = 0.5, output = 1.5 }
+
+[models."claude-sonnet-4*"]
+context_window = 1000000
+"#;
+
+        fs::write(&config_file, content).unwrap();
+
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let custom = &config.models["my-custom-model"];
+        assert_eq!(custom.context_window, Some(32768));
+        assert_eq!(custom.supports_vision, None);
+        assert_eq!(
+            custom.pricing,
+            Some(ModelPricingConfig {
+                input: 0.5,
+                output: 1.5,
+                cached_input: None
+            })
+        );
+        assert_eq!(
+            config.models["claude-sonnet-4*"].context_window,
+            Some(1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_load_toml_format_on_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let content = r#"
+[format_on_write]
+timeout_secs = 5
+"**/*.rs" = "rustfmt --edition 2021 {path}"
+"**/*.{ts,tsx}" = "prettier --write {path}"
+"#;
+
+        fs::write(&config_file, content).unwrap();
+
+        let loader = ConfigLoader::new();
+        let config = loader.load_toml(&config_file).unwrap();
+        let format = config.format_on_write.unwrap();
+
+        assert_eq!(format.timeout_secs, 5);
+        assert!(!format.auto_detect);
+        assert_eq!(format.rules.len(), 2);
+        assert_eq!(format.rules["**/*.rs"], "rustfmt --edition 2021 {path}");
+    }
+
+    #[test]
+    fn test_load_toml_memory() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[memory]\nmax_notes_bytes = 1024\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.memory.unwrap().max_notes_bytes, 1024);
+
+        fs::write(&config_file, "[memory]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.memory.unwrap(), MemoryConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_tasks() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[tasks]\nmax_output_bytes = 1048576\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let tasks = config.tasks.unwrap();
+        assert_eq!(tasks.max_output_bytes, 1_048_576);
+        assert_eq!(tasks.max_output_rotations, 3);
+        assert_eq!(tasks.completed_retention_days, 7);
+
+        fs::write(&config_file, "[tasks]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.tasks.unwrap(), TasksConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[limits]\nmax_turn_seconds = 300\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.limits.unwrap().max_turn_seconds, Some(300));
+
+        fs::write(&config_file, "[limits]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.limits.unwrap(), LimitsConfig::default());
+    }
+
+    #[test]
+    fn test_save_project_section_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let loader = ConfigLoader {
+            global_config_path: temp_dir.path().join("global/config.toml"),
+            project_config_path: temp_dir.path().join("project/.oxide/config.toml"),
+            project_instructions_path: temp_dir.path().join("project/.oxide/CONFIG.md"),
+        };
+        fs::create_dir_all(temp_dir.path().join("project/.oxide")).unwrap();
+        fs::write(&loader.project_config_path, "[ui]\ntheme = \"dark\"\n").unwrap();
+
+        let mode = ModeConfig {
+            default: Some("plan".to_string()),
+        };
+        loader.save_project_section("mode", &mode).unwrap();
+        let config = loader.load_toml(&loader.project_config_path).unwrap();
+        assert_eq!(config.mode, Some(mode));
+        assert!(config.ui.is_some());
+        assert!(!loader.global_config_path.exists());
+
+        // 再次保存替换原有的段
+        let mode = ModeConfig {
+            default: Some("fast".to_string()),
+        };
+        loader.save_project_section("mode", &mode).unwrap();
+        let content = fs::read_to_string(&loader.project_config_path).unwrap();
+        assert_eq!(content.matches("[mode]").count(), 1);
+        let config = loader.load_toml(&loader.project_config_path).unwrap();
+        assert_eq!(config.mode, Some(mode));
+    }
+
+    #[test]
+    fn test_load_toml_tools() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[tools]\nshell = \"powershell\"\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.tools.unwrap().shell.as_deref(), Some("powershell"));
+
+        fs::write(&config_file, "[tools]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.tools.unwrap(), ToolsConfig::default());
+
+        fs::write(
+            &config_file,
+            "[tools.bash.sandbox]\nmode = \"workspace-write\"\nnetwork = false\n",
+        )
+        .unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let sandbox = config.tools.unwrap().bash.unwrap().sandbox.unwrap();
+        assert_eq!(sandbox.mode, SandboxMode::WorkspaceWrite);
+        assert!(!sandbox.network);
+        assert_eq!(sandbox.cpu_seconds, 300);
+        assert_eq!(sandbox.memory_mb, 4096);
+
+        fs::write(&config_file, "[tools.bash]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(
+            config.tools.unwrap().bash.unwrap(),
+            BashToolConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_load_toml_behavior() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[behavior]\nlog_level = \"debug\"\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let behavior = config.behavior.unwrap();
+        assert_eq!(behavior.log_level.as_deref(), Some("debug"));
+        assert_eq!(behavior.log_max_files, 7);
+        assert_eq!(behavior.idle_reminder_minutes, 30);
+        assert!(behavior.advisories);
+        assert!(!behavior.session_learning);
+        assert_eq!(behavior.session_learning_max_facts, 5);
+        assert!(behavior.dedupe_file_reads);
+        assert!(!behavior.debug);
+    }
+
+    #[test]
+    fn test_load_toml_ui() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[ui]\nlanguage = \"zh\"\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.ui.unwrap().language.as_deref(), Some("zh"));
+
+        fs::write(&config_file, "[ui]\ntransient_prompt = true\nstatus_bar = true\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let ui = config.ui.unwrap();
+        assert!(ui.transient_prompt);
+        assert!(ui.status_bar);
+
+        fs::write(&config_file, "[ui]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.ui.unwrap(), UiConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_editor() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(
+            &config_file,
+            "[editor]\nhistory_size = 500\nhistory_exclude = [\"^export \", \"sk-[a-z0-9]+\"]\n",
+        )
+        .unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let editor = config.editor.unwrap();
+        assert_eq!(editor.history_size, 500);
+        assert_eq!(editor.history_exclude.len(), 2);
+
+        fs::write(&config_file, "[editor]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.editor.unwrap(), EditorConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[update]\nchannel = \"nightly\"\ncheck_on_startup = false\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let update = config.update.unwrap();
+        assert_eq!(update.channel, UpdateChannel::Nightly);
+        assert!(!update.check_on_startup);
+        assert_eq!(update.repository, "zzfn/oxide");
+
+        fs::write(&config_file, "[update]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.update.unwrap(), UpdateConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(&config_file, "[output]\nstyle = \"explanatory\"\nlanguage = \"zh\"\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let output = config.output.unwrap();
+        assert_eq!(output.style, OutputStyle::Explanatory);
+        assert_eq!(output.language, OutputLanguage::Zh);
+
+        fs::write(&config_file, "[output]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert_eq!(config.output.unwrap(), OutputConfig::default());
+    }
+
+    #[test]
+    fn test_load_toml_render() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(
+            &config_file,
+            "[render]\nturn_summary = false\nturn_recap = 0\nimages = \"sixel\"\nimage_max_rows = 10\n",
+        )
+        .unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        let render = config.render.unwrap();
+        assert!(!render.turn_summary);
+        assert_eq!(render.turn_recap, 0);
+        assert_eq!(render.images, ImagePreviewMode::Sixel);
+        assert_eq!(render.image_max_rows, 10);
+        assert_eq!(render.image_max_cols, 60);
+
+        fs::write(&config_file, "[render]\n").unwrap();
+        let config = ConfigLoader::new().load_toml(&config_file).unwrap();
+        assert!(config.render.unwrap().turn_summary);
+        assert!(RenderConfig::default().turn_summary);
+        assert_eq!(RenderConfig::default().turn_recap, 5);
+        assert_eq!(RenderConfig::default().images, ImagePreviewMode::Auto);
+    }
+
+    #[test]
+    fn test_upsert_section() {
+        let body = "style = \"detailed\"\nlanguage = \"zh\"\n";
+
+        assert_eq!(upsert_section("", "output", body), format!("[output]\n{}", body));
+
+        let appended = upsert_section("# my config\n[ui]\ntheme = \"dark\"\n", "output", body);
+        assert_eq!(
+            appended,
+            format!("# my config\n[ui]\ntheme = \"dark\"\n\n[output]\n{}", body)
+        );
+
+        let original = "[output]\nstyle = \"concise\"\n\n# editor settings\n[editor]\nhistory_size = 10\n";
+        let replaced = upsert_section(original, "output", body);
+        assert_eq!(
+            replaced,
+            format!("[output]\n{}\n# editor settings\n[editor]\nhistory_size = 10\n", body)
+        );
+        assert_eq!(upsert_section(&replaced, "output", body), replaced);
+    }
+
     #[test]
     fn test_global_config_dir() {
         let dir = global_config_dir();
@@ -1,16 +1,98 @@
-use anyhow::{Context, Result};
+use crate::agent::superseded_reads::{drop_superseded_reads, ReadSavings};
+use crate::agent::turn_recap::TurnRecap;
+use crate::atomic_file::write_atomic;
+use crate::file_lock::{FileLock, FileLockError, DEFAULT_LOCK_TIMEOUT};
+use crate::token_counter::{count_message_tokens, REPLY_PRIMING_TOKENS};
+use anyhow::{anyhow, bail, Context, Result};
+use names::Generator;
+use rig::completion::message::{AssistantContent, ToolResultContent, UserContent};
 use rig::completion::Message;
+use rig::OneOrMany;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
 /// 对话上下文管理器
+///
+/// 会话以 JSONL 格式保存在 `<storage_dir>/<session_id>.jsonl`：
+/// 第一行是元数据，之后每条消息一行，`add_message` 时立即追加。
+/// 清空/压缩会写入新文件后原子重命名。
 #[derive(Debug, Clone)]
 pub struct ContextManager {
     storage_dir: PathBuf,
     session_id: String,
     messages: Vec<Message>,
+    /// 每条消息的 token 数缓存（与 `messages` 一一对应）
+    message_tokens: Vec<usize>,
+    /// 每条消息的标签（与 `messages` 一一对应）
+    message_tags: Vec<Vec<String>>,
+    /// 每条消息是否已固定（与 `messages` 一一对应）
+    message_pinned: Vec<bool>,
     max_messages: usize,
+    /// 尚未成功写入磁盘的消息（追加失败时保留，下次 `save` 重试）
+    pending: Vec<SerializableMessage>,
+    /// `load` 时发现的中断的一轮，会话有任何修改后清除
+    interrupted: Option<InterruptedTurn>,
+    /// 当前进程的运行环境，写入新会话的文件头
+    environment: Option<SessionEnvironment>,
+    /// 会话文件中已有的文件头（加载、创建或首次追加时读取）
+    header: Option<SessionHeader>,
+    /// 发送前去掉被取代的文件读取结果（`[behavior] dedupe_file_reads`）
+    dedupe_file_reads: bool,
+}
+
+/// 会话存储目录（相对项目根目录）
+pub const SESSIONS_DIR: &str = ".oxide/sessions";
+
+/// 会话文件格式版本，格式不兼容时递增
+///
+/// 没有版本号的文件是版本 1，版本 3 增加了每轮的活动回顾记录。版本更高的文件由更新的 oxide 写入，
+/// 拒绝加载，避免把无法识别的记录当作损坏行丢弃。
+pub const SESSION_FORMAT_VERSION: u32 = 3;
+
+/// `!` 直接执行命令的输出消息带有此标签，它们不需要模型回复
+pub const SHELL_OUTPUT_TAG: &str = "shell";
+
+/// 中断时工具调用收到的错误结果
+const INTERRUPTED_TOOL_RESULT: &str =
+    "ToolCallError: interrupted: oxide exited before the tool finished";
+
+/// 保留中断的一轮时追加的助手消息
+const INTERRUPTED_NOTE: &str = "[Response interrupted: oxide exited before this turn finished]";
+
+/// 崩溃或强制退出导致没有完成的一轮
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterruptedTurn {
+    pub kind: InterruptedKind,
+    /// 开始这一轮的用户消息（从 0 开始）
+    pub prompt_index: usize,
+    /// 该用户消息的文本
+    pub prompt: String,
+}
+
+/// 会话停在哪一步
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterruptedKind {
+    /// 最后一条是用户消息，没有助手回复
+    UnansweredPrompt,
+    /// 最后一条助手消息中的工具调用没有结果
+    PendingToolCalls(Vec<String>),
+    /// 工具结果已保存，但模型没有继续回复
+    UnansweredToolResults,
+}
+
+/// 中断的一轮如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnRecovery {
+    /// 删除这一轮，由调用方重新发送用户消息
+    Resend,
+    /// 删除这一轮
+    Drop,
+    /// 保留这一轮：补上工具调用的错误结果，并追加一条说明中断的助手消息
+    Keep,
 }
 
 /// 会话元数据
@@ -20,61 +102,533 @@ pub struct SessionMetadata {
     pub created_at: String,
     pub last_updated: String,
     pub message_count: usize,
+    /// 会话中出现过的消息标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 会话创建时的运行环境（旧版会话没有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<SessionEnvironment>,
+    /// 会话中途更换服务商或模型的记录
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_changes: Vec<ModelChange>,
+}
+
+impl SessionMetadata {
+    /// 会话最近使用的 `服务商/模型`
+    pub fn latest_model(&self) -> Option<String> {
+        latest_model(self.environment.as_ref(), &self.model_changes)
+    }
+}
+
+/// 会话运行环境，创建会话时写入文件头
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionEnvironment {
+    pub oxide_version: String,
+    pub provider: String,
+    pub model: String,
+    /// 工作目录
+    pub cwd: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    /// 运行方式：`repl`、`serve` 或 `embedded`
+    pub mode: String,
+}
+
+impl SessionEnvironment {
+    /// 记录当前环境，git 提交和分支取自 `cwd` 所在的仓库
+    pub fn capture(cwd: &Path, provider: &str, model: &str, mode: &str) -> Self {
+        let (git_commit, git_branch) = git_head(cwd);
+        Self {
+            oxide_version: env!("CARGO_PKG_VERSION").to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            cwd: cwd.display().to_string(),
+            git_commit,
+            git_branch,
+            mode: mode.to_string(),
+        }
+    }
+
+    /// `服务商/模型`
+    pub fn model_label(&self) -> String {
+        format!("{}/{}", self.provider, self.model)
+    }
+
+    /// `分支@短提交`，不在 git 仓库中时为 None
+    pub fn git_label(&self) -> Option<String> {
+        let commit = self.git_commit.as_deref().map(|c| &c[..c.len().min(7)]);
+        match (self.git_branch.as_deref(), commit) {
+            (Some(branch), Some(commit)) => Some(format!("{}@{}", branch, commit)),
+            (None, Some(commit)) => Some(commit.to_string()),
+            (Some(branch), None) => Some(branch.to_string()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// 会话中途更换服务商或模型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelChange {
+    pub timestamp: String,
+    pub provider: String,
+    pub model: String,
+}
+
+/// 一轮的活动回顾（见 `crate::agent::turn_recap`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub recap: TurnRecap,
 }
 
-/// 持久化的会话数据
+/// 加载的会话与当前环境不一致的地方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionMismatch {
+    /// 会话在另一个目录中创建
+    WorkingDir { session: String, current: String },
+    /// 会话最近使用的是另一个模型（`服务商/模型`）
+    Model { session: String, current: String },
+}
+
+/// 旧版（整文件 JSON）会话数据，仅用于迁移
 #[derive(Debug, Serialize, Deserialize)]
 struct SessionData {
     pub metadata: SessionMetadata,
     pub messages: Vec<SerializableMessage>,
 }
 
+/// JSONL 会话文件中的一行
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionRecord {
+    /// 文件头
+    Meta {
+        session_id: String,
+        created_at: String,
+        #[serde(default = "legacy_format_version")]
+        version: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        environment: Option<SessionEnvironment>,
+    },
+    /// 更换了服务商或模型
+    Model(ModelChange),
+    /// 一轮的活动回顾
+    Turn(TurnRecord),
+    /// 一条消息
+    Message {
+        timestamp: String,
+        #[serde(flatten)]
+        message: SerializableMessage,
+    },
+}
+
+/// 没有版本号的文件头
+fn legacy_format_version() -> u32 {
+    1
+}
+
+/// 会话文件头及之后的模型变更（重写文件时保留）
+#[derive(Debug, Clone, Default)]
+struct SessionHeader {
+    created_at: Option<String>,
+    environment: Option<SessionEnvironment>,
+    model_changes: Vec<ModelChange>,
+    /// 活动回顾（重写文件时保留）
+    turns: Vec<TurnRecord>,
+}
+
+impl SessionHeader {
+    fn latest_model(&self) -> Option<String> {
+        latest_model(self.environment.as_ref(), &self.model_changes)
+    }
+}
+
+/// 从 JSONL 会话文件中回放出的内容
+struct SessionReplay {
+    header: SessionHeader,
+    last_updated: Option<String>,
+    messages: Vec<SerializableMessage>,
+    /// 无法解析的行数（例如崩溃时写了一半的最后一行）
+    corrupt_lines: usize,
+}
+
 /// 可序列化的消息类型
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SerializableMessage {
     pub role: String,
+    /// 消息文本，用于显示、导出和搜索（工具调用和结果只保留摘要）
     pub content: String,
+    /// 完整的内容块，只有含文本以外内容的消息才保存，恢复会话时据此还原发给模型的消息
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<ContentBlock>,
+    /// 消息标签（`#bug` 等，不含 `#`）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 已固定（`/pin`）：超出消息上限时不会被丢弃
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+/// 消息中的内容块
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    /// 助手发起的工具调用
+    ToolUse {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        name: String,
+        input: Value,
+    },
+    /// 工具结果，`content` 中只有文本和图片块
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        content: Vec<ContentBlock>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+    /// 图片，`source` 为 rig 的图片结构
+    Image {
+        source: Value,
+    },
+    /// 推理内容（含签名，工具循环中需要原样发回），`source` 为 rig 的推理结构
+    Reasoning {
+        source: Value,
+    },
+    /// 音频、视频、文档等其他附件，`source` 为 rig 的用户内容结构
+    Attachment {
+        source: Value,
+    },
+}
+
+/// 工具出错时 rig 写入工具结果的前缀
+const TOOL_ERROR: &str = "ToolCallError: ";
+
+/// 工具结果摘要中错误信息的最大字符数
+const TOOL_ERROR_SUMMARY_CHARS: usize = 80;
+
+impl ContentBlock {
+    /// 显示用的文本：工具调用和结果只保留摘要，避免在 `/history` 和导出中输出大段原始内容
+    fn summary(&self) -> String {
+        match self {
+            ContentBlock::Text { text } => text.clone(),
+            ContentBlock::ToolUse { name, .. } => format!("[tool call: {}]", name),
+            ContentBlock::ToolResult {
+                content, is_error, ..
+            } => {
+                let text = blocks_text(content);
+                if *is_error {
+                    let error = text.trim_start_matches(TOOL_ERROR);
+                    let first_line = error.lines().next().unwrap_or_default();
+                    let mut summary: String =
+                        first_line.chars().take(TOOL_ERROR_SUMMARY_CHARS).collect();
+                    if summary.len() < first_line.len() {
+                        summary.push('…');
+                    }
+                    format!("[tool error: {}]", summary)
+                } else {
+                    format!("[tool result: {} lines]", text.lines().count())
+                }
+            }
+            ContentBlock::Image { .. } => "[image]".to_string(),
+            ContentBlock::Reasoning { .. } => "[reasoning]".to_string(),
+            ContentBlock::Attachment { .. } => "[attachment]".to_string(),
+        }
+    }
+
+    /// 发给模型的文本，用于估算 token 数（图片不按 base64 数据计算）
+    fn model_text(&self) -> String {
+        match self {
+            ContentBlock::ToolUse { name, input, .. } => format!("{} {}", name, input),
+            ContentBlock::ToolResult { content, .. } => blocks_text(content),
+            ContentBlock::Reasoning { source } => source.to_string(),
+            block => block.summary(),
+        }
+    }
+
+    fn from_tool_result_content(content: &ToolResultContent) -> Self {
+        match content {
+            ToolResultContent::Text(text) => ContentBlock::Text {
+                text: text.text.clone(),
+            },
+            ToolResultContent::Image(image) => ContentBlock::Image {
+                source: serde_json::to_value(image).unwrap_or_default(),
+            },
+        }
+    }
+
+    fn to_tool_result_content(&self) -> Option<ToolResultContent> {
+        match self {
+            ContentBlock::Text { text } => Some(ToolResultContent::text(text.clone())),
+            ContentBlock::Image { source } => serde_json::from_value(source.clone())
+                .ok()
+                .map(ToolResultContent::Image),
+            _ => None,
+        }
+    }
+
+    fn from_user_content(content: &UserContent) -> Self {
+        match content {
+            UserContent::Text(text) => ContentBlock::Text {
+                text: text.text.clone(),
+            },
+            UserContent::ToolResult(result) => {
+                let content: Vec<ContentBlock> = result
+                    .content
+                    .iter()
+                    .map(ContentBlock::from_tool_result_content)
+                    .collect();
+                ContentBlock::ToolResult {
+                    tool_use_id: result.id.clone(),
+                    call_id: result.call_id.clone(),
+                    is_error: blocks_text(&content).starts_with(TOOL_ERROR),
+                    content,
+                }
+            }
+            UserContent::Image(image) => ContentBlock::Image {
+                source: serde_json::to_value(image).unwrap_or_default(),
+            },
+            other => ContentBlock::Attachment {
+                source: serde_json::to_value(other).unwrap_or_default(),
+            },
+        }
+    }
+
+    fn to_user_content(&self) -> Option<UserContent> {
+        match self {
+            ContentBlock::Text { text } => Some(UserContent::text(text.clone())),
+            ContentBlock::ToolResult {
+                tool_use_id,
+                call_id,
+                content,
+                ..
+            } => {
+                let content = content
+                    .iter()
+                    .map(ContentBlock::to_tool_result_content)
+                    .collect::<Option<Vec<_>>>()?;
+                let mut result =
+                    UserContent::tool_result(tool_use_id.clone(), OneOrMany::many(content).ok()?);
+                if let UserContent::ToolResult(result) = &mut result {
+                    result.call_id = call_id.clone();
+                }
+                Some(result)
+            }
+            ContentBlock::Image { source } => serde_json::from_value(source.clone())
+                .ok()
+                .map(UserContent::Image),
+            ContentBlock::Attachment { source } => serde_json::from_value(source.clone()).ok(),
+            ContentBlock::ToolUse { .. } | ContentBlock::Reasoning { .. } => None,
+        }
+    }
+
+    fn from_assistant_content(content: &AssistantContent) -> Self {
+        match content {
+            AssistantContent::Text(text) => ContentBlock::Text {
+                text: text.text.clone(),
+            },
+            AssistantContent::ToolCall(call) => ContentBlock::ToolUse {
+                id: call.id.clone(),
+                call_id: call.call_id.clone(),
+                name: call.function.name.clone(),
+                input: call.function.arguments.clone(),
+            },
+            AssistantContent::Reasoning(reasoning) => ContentBlock::Reasoning {
+                source: serde_json::to_value(reasoning).unwrap_or_default(),
+            },
+            AssistantContent::Image(image) => ContentBlock::Image {
+                source: serde_json::to_value(image).unwrap_or_default(),
+            },
+        }
+    }
+
+    fn to_assistant_content(&self) -> Option<AssistantContent> {
+        match self {
+            ContentBlock::Text { text } => Some(AssistantContent::text(text.clone())),
+            ContentBlock::ToolUse {
+                id,
+                call_id,
+                name,
+                input,
+            } => {
+                let mut content =
+                    AssistantContent::tool_call(id.clone(), name.clone(), input.clone());
+                if let AssistantContent::ToolCall(call) = &mut content {
+                    call.call_id = call_id.clone();
+                }
+                Some(content)
+            }
+            ContentBlock::Reasoning { source } => serde_json::from_value(source.clone())
+                .ok()
+                .map(AssistantContent::Reasoning),
+            ContentBlock::Image { source } => serde_json::from_value(source.clone())
+                .ok()
+                .map(AssistantContent::Image),
+            ContentBlock::ToolResult { .. } | ContentBlock::Attachment { .. } => None,
+        }
+    }
+}
+
+/// 内容块中的文本（忽略图片）
+fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl SerializableMessage {
+    fn from_blocks(role: &str, blocks: Vec<ContentBlock>) -> Self {
+        let content = blocks
+            .iter()
+            .map(ContentBlock::summary)
+            .collect::<Vec<_>>()
+            .join(" ");
+        // 纯文本消息只保存 content，与旧版会话文件一致
+        let text_only = blocks
+            .iter()
+            .all(|block| matches!(block, ContentBlock::Text { .. }));
+        Self {
+            role: role.to_string(),
+            content,
+            blocks: if text_only { Vec::new() } else { blocks },
+            tags: Vec::new(),
+            pinned: false,
+        }
+    }
+
+    /// 估算 token 数（工具调用参数和工具结果按完整内容计算）
+    pub fn token_count(&self) -> usize {
+        if self.blocks.is_empty() {
+            return count_message_tokens(&self.role, &self.content);
+        }
+        let text = self
+            .blocks
+            .iter()
+            .map(ContentBlock::model_text)
+            .collect::<Vec<_>>()
+            .join(" ");
+        count_message_tokens(&self.role, &text)
+    }
+}
+
+/// 消息中工具调用与工具结果的 ID（磁盘格式与内存中的 rig 消息共用）
+trait ToolCallIds {
+    /// 消息中工具调用的 ID
+    fn tool_use_ids(&self) -> impl Iterator<Item = &str>;
+
+    /// 消息中工具结果对应的调用 ID
+    fn tool_result_ids(&self) -> impl Iterator<Item = &str>;
+}
+
+impl ToolCallIds for SerializableMessage {
+    fn tool_use_ids(&self) -> impl Iterator<Item = &str> {
+        self.blocks.iter().filter_map(|block| match block {
+            ContentBlock::ToolUse { id, .. } => Some(id.as_str()),
+            _ => None,
+        })
+    }
+
+    fn tool_result_ids(&self) -> impl Iterator<Item = &str> {
+        self.blocks.iter().filter_map(|block| match block {
+            ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl ToolCallIds for Message {
+    fn tool_use_ids(&self) -> impl Iterator<Item = &str> {
+        let content = match self {
+            Message::Assistant { content, .. } => Some(content.iter()),
+            Message::User { .. } => None,
+        };
+        content.into_iter().flatten().filter_map(|item| match item {
+            AssistantContent::ToolCall(call) => Some(call.id.as_str()),
+            _ => None,
+        })
+    }
+
+    fn tool_result_ids(&self) -> impl Iterator<Item = &str> {
+        let content = match self {
+            Message::User { content } => Some(content.iter()),
+            Message::Assistant { .. } => None,
+        };
+        content.into_iter().flatten().filter_map(|item| match item {
+            UserContent::ToolResult(result) => Some(result.id.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// `rewind` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewindOutcome {
+    /// 已删除的消息数
+    Rewound(usize),
+    /// 要删除的消息中有已固定的消息（序号从 0 开始），需要用户确认后再次调用
+    PinnedMessages(Vec<usize>),
 }
 
 impl From<&Message> for SerializableMessage {
     fn from(msg: &Message) -> Self {
         match msg {
-            Message::User { content, .. } => Self {
-                role: "user".to_string(),
-                content: content
+            Message::User { content, .. } => Self::from_blocks(
+                "user",
+                content
                     .iter()
-                    .map(|c| match c {
-                        rig::completion::message::UserContent::Text(text) => text.text.clone(),
-                        _ => "[non-text content]".to_string(),
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" "),
-            },
-            Message::Assistant { content, .. } => Self {
-                role: "assistant".to_string(),
-                content: content
+                    .map(ContentBlock::from_user_content)
+                    .collect(),
+            ),
+            Message::Assistant { content, .. } => Self::from_blocks(
+                "assistant",
+                content
                     .iter()
-                    .map(|c| match c {
-                        rig::completion::message::AssistantContent::Text(text) => text.text.clone(),
-                        rig::completion::message::AssistantContent::ToolCall(_) => {
-                            "[tool call]".to_string()
-                        }
-                        rig::completion::message::AssistantContent::Reasoning(_) => {
-                            "[reasoning]".to_string()
-                        }
-                        rig::completion::message::AssistantContent::Image(_) => {
-                            "[image]".to_string()
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" "),
-            },
+                    .map(ContentBlock::from_assistant_content)
+                    .collect(),
+            ),
         }
     }
 }
 
 impl From<SerializableMessage> for Message {
     fn from(msg: SerializableMessage) -> Self {
+        if !msg.blocks.is_empty() {
+            let restored = match msg.role.as_str() {
+                "assistant" => msg
+                    .blocks
+                    .iter()
+                    .map(ContentBlock::to_assistant_content)
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|content| OneOrMany::many(content).ok())
+                    .map(|content| Message::Assistant { id: None, content }),
+                _ => msg
+                    .blocks
+                    .iter()
+                    .map(ContentBlock::to_user_content)
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|content| OneOrMany::many(content).ok())
+                    .map(|content| Message::User { content }),
+            };
+            // 有内容块无法还原时（例如 rig 的结构有变化）退回到文本
+            if let Some(message) = restored {
+                return message;
+            }
+        }
         match msg.role.as_str() {
             "user" => Message::user(msg.content),
             "assistant" => Message::assistant(msg.content),
@@ -95,78 +649,364 @@ impl ContextManager {
             storage_dir,
             session_id,
             messages: Vec::new(),
+            message_tokens: Vec::new(),
+            message_tags: Vec::new(),
+            message_pinned: Vec::new(),
             max_messages: 100,
+            pending: Vec::new(),
+            interrupted: None,
+            environment: None,
+            header: None,
+            dedupe_file_reads: true,
         })
     }
 
+    /// 设置发送前是否去掉被取代的文件读取结果
+    pub fn with_dedupe_file_reads(mut self, enabled: bool) -> Self {
+        self.dedupe_file_reads = enabled;
+        self
+    }
+
+    /// 设置当前运行环境：新会话写入文件头，继续旧会话时模型不同会追加变更记录
+    pub fn with_environment(mut self, environment: SessionEnvironment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// 生成一个未被占用的会话 ID（`<形容词>-<名词>-<4 位随机后缀>`）
+    ///
+    /// 单靠形容词-名词组合容易重复，重复时两段对话会被写进同一个会话文件。
+    pub fn generate_session_id(storage_dir: &Path) -> String {
+        let mut generator = Generator::default();
+        loop {
+            let name = generator.next().unwrap_or_else(|| "session".to_string());
+            let suffix = uuid::Uuid::new_v4().simple().to_string();
+            let session_id = format!("{}-{}", name, &suffix[..4]);
+            let taken = ["jsonl", "json"]
+                .iter()
+                .any(|ext| storage_dir.join(format!("{}.{}", session_id, ext)).exists());
+            if !taken {
+                return session_id;
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub fn with_max_messages(mut self, max_messages: usize) -> Self {
         self.max_messages = max_messages;
         self
     }
 
+    /// 添加消息并立即追加到会话文件
+    ///
+    /// 写入失败不会丢失消息，会在下次 `save` 时重试。
     pub fn add_message(&mut self, message: Message) {
+        self.add_message_with_tags(message, Vec::new());
+    }
+
+    /// 添加带标签的消息（标签只保存在会话记录中，不发送给模型）
+    pub fn add_message_with_tags(&mut self, message: Message, tags: Vec<String>) {
+        let mut serializable = SerializableMessage::from(&message);
+        serializable.tags = tags.clone();
+        self.interrupted = None;
+        self.message_tokens.push(serializable.token_count());
+        self.pending.push(serializable);
         self.messages.push(message);
-        if self.messages.len() > self.max_messages {
-            self.messages.remove(0);
+        self.message_tags.push(tags);
+        self.message_pinned.push(false);
+        self.trim_to_max();
+        let _ = self.flush_pending();
+    }
+
+    /// 超出消息上限时从最早的未固定消息开始丢弃，已固定的消息原样保留
+    fn trim_to_max(&mut self) {
+        if self.message_pinned.len() != self.messages.len() {
+            self.message_pinned = vec![false; self.messages.len()];
+        }
+        let mut keep = keep_mask(&self.message_pinned, self.max_messages);
+        if keep.iter().all(|&kept| kept) {
+            return;
+        }
+        drop_orphaned_tool_results(&self.messages, &mut keep);
+        retain_by_mask(&mut self.messages, &keep);
+        retain_by_mask(&mut self.message_tokens, &keep);
+        retain_by_mask(&mut self.message_tags, &keep);
+        retain_by_mask(&mut self.message_pinned, &keep);
+    }
+
+    /// 消息是否已固定（`index` 从 0 开始）
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.message_pinned.get(index).copied().unwrap_or(false)
+    }
+
+    /// 固定或取消固定一条消息（`index` 从 0 开始），并重写会话文件持久化
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> Result<()> {
+        if index >= self.messages.len() {
+            anyhow::bail!("Message index out of range: {}", index);
         }
+        if self.message_pinned.len() != self.messages.len() {
+            self.message_pinned = vec![false; self.messages.len()];
+        }
+        self.message_pinned[index] = pinned;
+        self.compact()
+    }
+
+    /// 删除从 `from`（从 0 开始）起的所有消息
+    ///
+    /// 其中有已固定的消息且 `allow_pinned` 为 false 时不做任何修改，
+    /// 返回这些消息的序号，由调用方确认后再次调用。
+    pub fn rewind(&mut self, from: usize, allow_pinned: bool) -> Result<RewindOutcome> {
+        if from >= self.messages.len() {
+            return Ok(RewindOutcome::Rewound(0));
+        }
+        let pinned: Vec<usize> = (from..self.messages.len())
+            .filter(|&i| self.is_pinned(i))
+            .collect();
+        if !pinned.is_empty() && !allow_pinned {
+            return Ok(RewindOutcome::PinnedMessages(pinned));
+        }
+        let removed = self.messages.len() - from;
+        self.interrupted = None;
+        self.messages.truncate(from);
+        self.message_tokens.truncate(from);
+        self.message_tags.truncate(from);
+        self.message_pinned.truncate(from);
+        self.compact()?;
+        Ok(RewindOutcome::Rewound(removed))
+    }
+
+    /// 当前会话的可序列化记录（带标签），用于导出和重写会话文件
+    pub fn transcript(&self) -> Vec<SerializableMessage> {
+        // 通过 get_messages_mut 修改过消息时标签无法对应，只能丢弃
+        let tags_valid = self.message_tags.len() == self.messages.len();
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let mut serializable = SerializableMessage::from(message);
+                if tags_valid {
+                    serializable.tags = self.message_tags[i].clone();
+                }
+                serializable.pinned = self.is_pinned(i);
+                serializable
+            })
+            .collect()
     }
 
     pub fn get_messages(&self) -> &[Message] {
         &self.messages
     }
 
+    /// 直接修改内存中的消息（回退等场景），修改后需调用 `compact` 持久化
     #[allow(dead_code)]
     pub fn get_messages_mut(&mut self) -> &mut Vec<Message> {
         &mut self.messages
     }
 
-    pub fn clear(&mut self) {
+    /// 发送给模型的历史记录
+    ///
+    /// rig 的 `with_history` 需要拥有所有权的 `Vec<Message>`，这是每轮唯一的一次拷贝。
+    /// 最后一条消息是当前提示词（由 `stream_prompt` 单独发送），因此不包含在内。
+    /// 被取代的文件读取结果在拷贝中替换为一行说明（见 [`drop_superseded_reads`]）。
+    pub fn prompt_history(&self) -> Vec<Message> {
+        let len = self.messages.len().saturating_sub(1);
+        self.for_request(&self.messages[..len]).0
+    }
+
+    /// 按发送给模型的形式整理的全部消息，以及去掉被取代的读取结果节省的量（`/context` 使用）
+    pub fn request_messages(&self) -> (Vec<Message>, ReadSavings) {
+        self.for_request(&self.messages)
+    }
+
+    /// 拷贝消息并做发送前的优化，保存的会话不受影响
+    fn for_request(&self, messages: &[Message]) -> (Vec<Message>, ReadSavings) {
+        let mut messages = messages.to_vec();
+        let savings = if self.dedupe_file_reads {
+            drop_superseded_reads(&mut messages)
+        } else {
+            ReadSavings::default()
+        };
+        (messages, savings)
+    }
+
+    /// 当前上下文的输入 token 预估（使用缓存，不重新序列化消息）
+    pub fn estimated_input_tokens(&self) -> usize {
+        if self.message_tokens.len() != self.messages.len() {
+            // 通过 get_messages_mut 修改过消息，缓存已失效
+            return REPLY_PRIMING_TOKENS + self.messages.iter().map(message_token_count).sum::<usize>();
+        }
+        REPLY_PRIMING_TOKENS + self.message_tokens.iter().sum::<usize>()
+    }
+
+    /// 清空当前会话（同时重写会话文件）
+    pub fn clear(&mut self) -> Result<()> {
+        self.interrupted = None;
         self.messages.clear();
+        self.message_tokens.clear();
+        self.message_tags.clear();
+        self.message_pinned.clear();
+        self.pending.clear();
+        if self.get_session_file_path().exists() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// 确保所有消息都已写入磁盘
+    ///
+    /// 消息在 `add_message` 时已经追加，这里只重试之前写入失败的部分。
+    pub fn save(&mut self) -> Result<()> {
+        self.flush_pending()
     }
 
-    pub fn save(&self) -> Result<()> {
+    /// 用内存中的消息重写会话文件（写临时文件后原子重命名）
+    pub fn compact(&mut self) -> Result<()> {
+        let _lock = self.lock_session()?;
         let file_path = self.get_session_file_path();
-        let now = chrono::Utc::now().to_rfc3339();
-        let metadata = SessionMetadata {
-            session_id: self.session_id.clone(),
-            created_at: now.clone(),
-            last_updated: now,
-            message_count: self.messages.len(),
+        // 文件版本更高时 read_session_file 报错，不会覆盖
+        let header = if file_path.exists() {
+            read_session_file(&file_path)?.header
+        } else {
+            self.new_header()
         };
-        let serializable_messages: Vec<SerializableMessage> = self
-            .messages
+        let messages = self.transcript();
+        write_session_file(&file_path, &self.session_id, &header, &messages)?;
+        self.header = Some(header);
+        self.message_tokens = messages
             .iter()
-            .map(SerializableMessage::from)
+            .map(SerializableMessage::token_count)
             .collect();
-        let session_data = SessionData {
-            metadata,
-            messages: serializable_messages,
-        };
-        let json_data = serde_json::to_string_pretty(&session_data)
-            .context("Failed to serialize session data")?;
-        fs::write(&file_path, json_data)
-            .with_context(|| format!("Failed to write session file: {:?}", file_path))?;
+        self.message_pinned = messages.iter().map(|m| m.pinned).collect();
+        self.message_tags = messages.into_iter().map(|m| m.tags).collect();
+        self.pending.clear();
         Ok(())
     }
 
     pub fn load(&mut self) -> Result<bool> {
+        self.migrate_legacy_session()?;
+
         let file_path = self.get_session_file_path();
         if !file_path.exists() {
             return Ok(false);
         }
-        let json_data = fs::read_to_string(&file_path)
-            .with_context(|| format!("Failed to read session file: {:?}", file_path))?;
-        let session_data: SessionData =
-            serde_json::from_str(&json_data).context("Failed to deserialize session data")?;
-        self.messages = session_data
-            .messages
-            .into_iter()
-            .map(Message::from)
+        let replay = read_session_file(&file_path)?;
+        let corrupt_lines = replay.corrupt_lines;
+
+        // 旧版本重写文件中途崩溃时留下的临时文件（持锁时不会有其他实例正在写）
+        let tmp_path = file_path.with_extension("jsonl.tmp");
+        if tmp_path.exists() {
+            let _lock = self.lock_session()?;
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        let mut messages = replay.messages;
+        let pinned: Vec<bool> = messages.iter().map(|m| m.pinned).collect();
+        let mut keep = keep_mask(&pinned, self.max_messages);
+        drop_orphaned_tool_results(&messages, &mut keep);
+        retain_by_mask(&mut messages, &keep);
+        self.message_tokens = messages
+            .iter()
+            .map(SerializableMessage::token_count)
             .collect();
+        self.message_tags = messages.iter().map(|m| m.tags.clone()).collect();
+        self.message_pinned = messages.iter().map(|m| m.pinned).collect();
+        self.interrupted = detect_interrupted_turn(&messages);
+        self.messages = messages.into_iter().map(Message::from).collect();
+        self.pending.clear();
+        self.header = Some(replay.header);
+
+        // 存在损坏行（例如写入中途崩溃）时重写文件，避免后续追加接在半行后面
+        if corrupt_lines > 0 {
+            self.compact()?;
+        }
         Ok(true)
     }
 
+    /// 会话创建时的运行环境（旧版会话或尚未写入的新会话为 None）
+    pub fn session_environment(&self) -> Option<&SessionEnvironment> {
+        self.header.as_ref()?.environment.as_ref()
+    }
+
+    /// 加载的会话与当前环境的差异：创建时的工作目录、最近使用的模型
+    pub fn environment_mismatches(&self) -> Vec<SessionMismatch> {
+        let mut mismatches = Vec::new();
+        let (Some(current), Some(header)) = (&self.environment, &self.header) else {
+            return mismatches;
+        };
+        if let Some(created) = &header.environment {
+            if created.cwd != current.cwd {
+                mismatches.push(SessionMismatch::WorkingDir {
+                    session: created.cwd.clone(),
+                    current: current.cwd.clone(),
+                });
+            }
+        }
+        if let Some(model) = header.latest_model() {
+            if model != current.model_label() {
+                mismatches.push(SessionMismatch::Model {
+                    session: model,
+                    current: current.model_label(),
+                });
+            }
+        }
+        mismatches
+    }
+
+    /// `load` 时发现的中断的一轮（上次运行在模型回复完成前退出）
+    pub fn interrupted_turn(&self) -> Option<&InterruptedTurn> {
+        self.interrupted.as_ref()
+    }
+
+    /// 处理中断的一轮，`Resend` 时返回需要重新发送的用户消息
+    pub fn recover_interrupted_turn(&mut self, recovery: TurnRecovery) -> Result<Option<String>> {
+        let Some(turn) = self.interrupted.take() else {
+            return Ok(None);
+        };
+        match recovery {
+            TurnRecovery::Resend | TurnRecovery::Drop => {
+                self.rewind(turn.prompt_index, true)?;
+                Ok((recovery == TurnRecovery::Resend).then_some(turn.prompt))
+            }
+            TurnRecovery::Keep => {
+                if let InterruptedKind::PendingToolCalls(ids) = &turn.kind {
+                    let call_ids = self
+                        .messages
+                        .last()
+                        .map(SerializableMessage::from)
+                        .map(|message| message.blocks)
+                        .unwrap_or_default();
+                    let results: Vec<UserContent> = ids
+                        .iter()
+                        .map(|id| {
+                            let call_id = call_ids.iter().find_map(|block| match block {
+                                ContentBlock::ToolUse {
+                                    id: use_id,
+                                    call_id,
+                                    ..
+                                } if use_id == id => call_id.clone(),
+                                _ => None,
+                            });
+                            let mut result = UserContent::tool_result(
+                                id.clone(),
+                                OneOrMany::one(ToolResultContent::text(INTERRUPTED_TOOL_RESULT)),
+                            );
+                            if let UserContent::ToolResult(result) = &mut result {
+                                result.call_id = call_id;
+                            }
+                            result
+                        })
+                        .collect();
+                    if let Ok(content) = OneOrMany::many(results) {
+                        self.add_message(Message::User { content });
+                    }
+                }
+                self.add_message(Message::assistant(INTERRUPTED_NOTE));
+                self.save()?;
+                Ok(None)
+            }
+        }
+    }
+
     pub fn list_sessions(&self) -> Result<Vec<SessionMetadata>> {
         let mut sessions = Vec::new();
         if !self.storage_dir.exists() {
@@ -175,12 +1015,33 @@ impl ContextManager {
         for entry in fs::read_dir(&self.storage_dir)? {
             let entry = entry?;
             let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                if let Ok(json_data) = fs::read_to_string(&path) {
-                    if let Ok(session_data) = serde_json::from_str::<SessionData>(&json_data) {
-                        sessions.push(session_data.metadata);
+            let Some(session_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            match path.extension().and_then(|s| s.to_str()) {
+                Some("jsonl") => {
+                    if let Ok(replay) = read_session_file(&path) {
+                        let created_at = replay.header.created_at.unwrap_or_default();
+                        sessions.push(SessionMetadata {
+                            session_id: session_id.to_string(),
+                            last_updated: replay.last_updated.unwrap_or_else(|| created_at.clone()),
+                            created_at,
+                            message_count: replay.messages.len(),
+                            tags: collect_tags(&replay.messages),
+                            environment: replay.header.environment,
+                            model_changes: replay.header.model_changes,
+                        });
+                    }
+                }
+                // 尚未迁移的旧版会话
+                Some("json") if !path.with_extension("jsonl").exists() => {
+                    if let Ok(json_data) = fs::read_to_string(&path) {
+                        if let Ok(session_data) = serde_json::from_str::<SessionData>(&json_data) {
+                            sessions.push(session_data.metadata);
+                        }
                     }
                 }
+                _ => {}
             }
         }
         sessions.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
@@ -188,26 +1049,1183 @@ impl ContextManager {
     }
 
     pub fn delete_session(&self) -> Result<bool> {
-        let file_path = self.get_session_file_path();
-        if file_path.exists() {
-            fs::remove_file(&file_path)
-                .with_context(|| format!("Failed to delete session file: {:?}", file_path))?;
-            Ok(true)
-        } else {
-            Ok(false)
+        let _lock = self.lock_session()?;
+        let mut deleted = false;
+        for file_path in [self.get_session_file_path(), self.get_legacy_session_file_path()] {
+            if file_path.exists() {
+                fs::remove_file(&file_path)
+                    .with_context(|| format!("Failed to delete session file: {:?}", file_path))?;
+                deleted = true;
+            }
         }
+        Ok(deleted)
     }
 
     fn get_session_file_path(&self) -> PathBuf {
+        self.storage_dir.join(format!("{}.jsonl", self.session_id))
+    }
+
+    fn get_legacy_session_file_path(&self) -> PathBuf {
         self.storage_dir.join(format!("{}.json", self.session_id))
     }
 
+    /// 获取会话文件锁，被其他实例占用时给出明确提示
+    fn lock_session(&self) -> Result<FileLock> {
+        FileLock::acquire(&self.get_session_file_path(), DEFAULT_LOCK_TIMEOUT).map_err(|e| match e {
+            FileLockError::Timeout(_) => anyhow!(
+                "Session '{}' is open in another oxide instance",
+                self.session_id
+            ),
+            e => e.into(),
+        })
+    }
+
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
 
     pub fn switch_session(&mut self, new_session_id: String) {
         self.session_id = new_session_id;
+        self.interrupted = None;
+        self.header = None;
         self.messages.clear();
+        self.message_tokens.clear();
+        self.message_tags.clear();
+        self.message_pinned.clear();
+        self.pending.clear();
+    }
+
+    /// 切换到一个新生成 ID 的空会话，返回新的会话 ID
+    pub fn start_new_session(&mut self) -> String {
+        let session_id = Self::generate_session_id(&self.storage_dir);
+        self.switch_session(session_id.clone());
+        session_id
+    }
+
+    /// 追加尚未写入的消息
+    fn flush_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        // 持锁期间判断文件是否新建，避免两个实例都写入 Meta 头
+        let _lock = self.lock_session()?;
+        let file_path = self.get_session_file_path();
+        let is_new = !file_path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)
+            .with_context(|| format!("Failed to open session file: {:?}", file_path))?;
+
+        // 追加到未加载过的已有文件前先读取文件头（版本更高时报错，消息留待重试）
+        let mut header = match self.header.take() {
+            Some(header) if !is_new => header,
+            _ if is_new => self.new_header(),
+            _ => read_session_file(&file_path)?.header,
+        };
+
+        // 每条记录一次性写入完整的一行，崩溃时最多留下一行残缺数据
+        let mut buffer = String::new();
+        let now = chrono::Utc::now().to_rfc3339();
+        if is_new {
+            push_meta(&mut buffer, &self.session_id, &header)?;
+        } else if let Some(current) = &self.environment {
+            if header.latest_model() != Some(current.model_label()) {
+                let change = ModelChange {
+                    timestamp: now.clone(),
+                    provider: current.provider.clone(),
+                    model: current.model.clone(),
+                };
+                push_record(&mut buffer, &SessionRecord::Model(change.clone()))?;
+                header.model_changes.push(change);
+            }
+        }
+        for message in &self.pending {
+            push_record(
+                &mut buffer,
+                &SessionRecord::Message {
+                    timestamp: now.clone(),
+                    message: message.clone(),
+                },
+            )?;
+        }
+        file.write_all(buffer.as_bytes())
+            .with_context(|| format!("Failed to append to session file: {:?}", file_path))?;
+        file.flush()?;
+
+        self.header = Some(header);
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// 在会话文件中追加一轮的活动回顾（先写入尚未保存的消息）
+    pub fn record_turn_recap(&mut self, recap: &TurnRecap) -> Result<()> {
+        self.flush_pending()?;
+        let _lock = self.lock_session()?;
+        let file_path = self.get_session_file_path();
+        if !file_path.exists() {
+            return Ok(());
+        }
+        let mut header = match self.header.take() {
+            Some(header) => header,
+            None => read_session_file(&file_path)?.header,
+        };
+        let turn = TurnRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            recap: recap.clone(),
+        };
+        let mut buffer = String::new();
+        push_record(&mut buffer, &SessionRecord::Turn(turn.clone()))?;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&file_path)
+            .with_context(|| format!("Failed to open session file: {:?}", file_path))?;
+        file.write_all(buffer.as_bytes())
+            .with_context(|| format!("Failed to append to session file: {:?}", file_path))?;
+        header.turns.push(turn);
+        self.header = Some(header);
+        Ok(())
+    }
+
+    /// 新会话的文件头
+    fn new_header(&self) -> SessionHeader {
+        SessionHeader {
+            created_at: Some(chrono::Utc::now().to_rfc3339()),
+            environment: self.environment.clone(),
+            model_changes: Vec::new(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// 将旧版 `<id>.json` 会话转换为 JSONL，原文件保留为 `<id>.json.bak`
+    pub(crate) fn migrate_legacy_session(&self) -> Result<()> {
+        let legacy_path = self.get_legacy_session_file_path();
+        let file_path = self.get_session_file_path();
+        if !legacy_path.exists() || file_path.exists() {
+            return Ok(());
+        }
+
+        let _lock = self.lock_session()?;
+        if !legacy_path.exists() || file_path.exists() {
+            return Ok(());
+        }
+
+        let json_data = fs::read_to_string(&legacy_path)
+            .with_context(|| format!("Failed to read session file: {:?}", legacy_path))?;
+        let session_data: SessionData =
+            serde_json::from_str(&json_data).context("Failed to deserialize session data")?;
+
+        let header = SessionHeader {
+            created_at: Some(session_data.metadata.created_at),
+            ..SessionHeader::default()
+        };
+        write_session_file(
+            &file_path,
+            &self.session_id,
+            &header,
+            &session_data.messages,
+        )?;
+
+        let backup_path = self.storage_dir.join(format!("{}.json.bak", self.session_id));
+        fs::rename(&legacy_path, &backup_path)
+            .with_context(|| format!("Failed to back up legacy session file: {:?}", legacy_path))?;
+        Ok(())
+    }
+}
+
+/// 检查会话是否停在一轮的中间：最后一条是没有回复的用户消息或工具结果，
+/// 或者是工具调用没有结果的助手消息。`!` 命令的输出不需要回复，不算中断。
+fn detect_interrupted_turn(messages: &[SerializableMessage]) -> Option<InterruptedTurn> {
+    let last = messages.last()?;
+    let kind = if last.role == "assistant" {
+        let pending: Vec<String> = last.tool_use_ids().map(str::to_string).collect();
+        if pending.is_empty() {
+            return None;
+        }
+        InterruptedKind::PendingToolCalls(pending)
+    } else if last.tags.iter().any(|tag| tag == SHELL_OUTPUT_TAG) {
+        return None;
+    } else if last.tool_result_ids().next().is_some() {
+        InterruptedKind::UnansweredToolResults
+    } else {
+        InterruptedKind::UnansweredPrompt
+    };
+
+    let prompt_index = messages.iter().rposition(|message| {
+        message.role == "user"
+            && message.tool_result_ids().next().is_none()
+            && !message.tags.iter().any(|tag| tag == SHELL_OUTPUT_TAG)
+    })?;
+    let prompt = &messages[prompt_index];
+    let text = if prompt.blocks.is_empty() {
+        prompt.content.clone()
+    } else {
+        blocks_text(&prompt.blocks)
+    };
+    Some(InterruptedTurn {
+        kind,
+        prompt_index,
+        prompt: text,
+    })
+}
+
+/// 汇总会话中出现过的标签（去重并排序）
+fn collect_tags(messages: &[SerializableMessage]) -> Vec<String> {
+    let mut tags: Vec<String> = messages.iter().flat_map(|m| m.tags.iter().cloned()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// 计算保留哪些消息：已固定的全部保留，其余从最新往前保留到总数不超过 `max`
+///
+/// 已固定的消息本身超过上限时全部保留，不再保留未固定的消息。
+fn keep_mask(pinned: &[bool], max: usize) -> Vec<bool> {
+    let mut budget = max.saturating_sub(pinned.iter().filter(|&&p| p).count());
+    let mut keep = vec![false; pinned.len()];
+    for (i, &is_pinned) in pinned.iter().enumerate().rev() {
+        if is_pinned {
+            keep[i] = true;
+        } else if budget > 0 {
+            keep[i] = true;
+            budget -= 1;
+        }
+    }
+    keep
+}
+
+/// 不再保留对应工具调用的工具结果消息（模型 API 拒绝没有对应调用的工具结果）
+fn drop_orphaned_tool_results(messages: &[impl ToolCallIds], keep: &mut [bool]) {
+    let mut calls = HashSet::new();
+    for (message, kept) in messages.iter().zip(keep.iter_mut()) {
+        if !*kept {
+            continue;
+        }
+        if message.tool_result_ids().any(|id| !calls.contains(id)) {
+            *kept = false;
+            continue;
+        }
+        calls.extend(message.tool_use_ids());
+    }
+}
+
+/// 按掩码保留元素（长度不一致时说明缓存已失效，保持原样）
+fn retain_by_mask<T>(items: &mut Vec<T>, keep: &[bool]) {
+    if items.len() != keep.len() {
+        return;
+    }
+    let mut flags = keep.iter();
+    items.retain(|_| *flags.next().unwrap_or(&true));
+}
+
+/// 计算单条消息的 token 数
+pub(crate) fn message_token_count(message: &Message) -> usize {
+    SerializableMessage::from(message).token_count()
+}
+
+/// 序列化一条记录并追加到缓冲区（带换行）
+fn push_record(buffer: &mut String, record: &SessionRecord) -> Result<()> {
+    buffer.push_str(&serde_json::to_string(record).context("Failed to serialize session record")?);
+    buffer.push('\n');
+    Ok(())
+}
+
+/// 写入文件头（以当前格式版本）
+fn push_meta(buffer: &mut String, session_id: &str, header: &SessionHeader) -> Result<()> {
+    push_record(
+        buffer,
+        &SessionRecord::Meta {
+            session_id: session_id.to_string(),
+            created_at: header
+                .created_at
+                .clone()
+                .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+            version: SESSION_FORMAT_VERSION,
+            environment: header.environment.clone(),
+        },
+    )
+}
+
+/// 会话最近使用的 `服务商/模型`：最后一次变更，没有变更时取创建时的模型
+fn latest_model(
+    environment: Option<&SessionEnvironment>,
+    model_changes: &[ModelChange],
+) -> Option<String> {
+    match model_changes.last() {
+        Some(change) => Some(format!("{}/{}", change.provider, change.model)),
+        None => environment.map(SessionEnvironment::model_label),
+    }
+}
+
+/// 当前提交和分支（分离 HEAD 时没有分支）
+fn git_head(cwd: &Path) -> (Option<String>, Option<String>) {
+    let Ok(repo) = git2::Repository::discover(cwd) else {
+        return (None, None);
+    };
+    let Ok(head) = repo.head() else {
+        return (None, None);
+    };
+    let commit = head
+        .peel_to_commit()
+        .ok()
+        .map(|commit| commit.id().to_string());
+    let branch = if head.is_branch() {
+        head.shorthand().map(str::to_string)
+    } else {
+        None
+    };
+    (commit, branch)
+}
+
+/// 原子写入完整的会话文件
+///
+/// 模型变更和活动回顾记录写在文件头之后。
+fn write_session_file(
+    file_path: &Path,
+    session_id: &str,
+    header: &SessionHeader,
+    messages: &[SerializableMessage],
+) -> Result<()> {
+    let mut buffer = String::new();
+    push_meta(&mut buffer, session_id, header)?;
+    for change in &header.model_changes {
+        push_record(&mut buffer, &SessionRecord::Model(change.clone()))?;
+    }
+    for turn in &header.turns {
+        push_record(&mut buffer, &SessionRecord::Turn(turn.clone()))?;
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    for message in messages {
+        push_record(
+            &mut buffer,
+            &SessionRecord::Message {
+                timestamp: now.clone(),
+                message: message.clone(),
+            },
+        )?;
+    }
+
+    write_atomic(file_path, buffer.as_bytes())
+        .with_context(|| format!("Failed to replace session file: {:?}", file_path))
+}
+
+/// 逐行回放 JSONL 会话文件，跳过无法解析的行
+fn read_session_file(file_path: &Path) -> Result<SessionReplay> {
+    let file = fs::File::open(file_path)
+        .with_context(|| format!("Failed to read session file: {:?}", file_path))?;
+
+    let mut replay = SessionReplay {
+        header: SessionHeader::default(),
+        last_updated: None,
+        messages: Vec::new(),
+        corrupt_lines: 0,
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => {
+                replay.corrupt_lines += 1;
+                continue;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<SessionRecord>(&line) {
+            Ok(SessionRecord::Meta {
+                created_at,
+                version,
+                environment,
+                ..
+            }) => {
+                if version > SESSION_FORMAT_VERSION {
+                    bail!(
+                        "Session file {:?} was written by a newer version of oxide (session format v{}, this version supports up to v{}); upgrade oxide to open it",
+                        file_path,
+                        version,
+                        SESSION_FORMAT_VERSION
+                    );
+                }
+                replay.header.created_at = Some(created_at);
+                replay.header.environment = environment;
+            }
+            Ok(SessionRecord::Model(change)) => replay.header.model_changes.push(change),
+            Ok(SessionRecord::Turn(turn)) => replay.header.turns.push(turn),
+            Ok(SessionRecord::Message { timestamp, message }) => {
+                replay.last_updated = Some(timestamp);
+                replay.messages.push(message);
+            }
+            Err(_) => replay.corrupt_lines += 1,
+        }
+    }
+
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::completion::message::{Image, ImageMediaType, Reasoning};
+    use tempfile::TempDir;
+
+    fn session_file(dir: &TempDir, id: &str) -> PathBuf {
+        dir.path().join(format!("{}.jsonl", id))
+    }
+
+    #[test]
+    fn test_add_message_appends_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "s1".to_string()).unwrap();
+
+        manager.add_message(Message::user("hello"));
+        manager.add_message(Message::assistant("hi there"));
+
+        let content = fs::read_to_string(session_file(&temp_dir, "s1")).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("\"type\":\"meta\""));
+        assert!(lines[2].contains("hi there"));
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "s1".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.get_messages().len(), 2);
+    }
+
+    #[test]
+    fn test_turn_recap_kept_on_compact() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "r".to_string()).unwrap();
+        manager.add_message(Message::user("fix the tests"));
+        manager.add_message(Message::assistant("done"));
+        let recap = TurnRecap {
+            tool_calls: 7,
+            files_modified: vec!["src/lib.rs".to_string()],
+            ..TurnRecap::default()
+        };
+        manager.record_turn_recap(&recap).unwrap();
+
+        let content = fs::read_to_string(session_file(&temp_dir, "r")).unwrap();
+        let last = content.lines().last().unwrap();
+        assert!(last.contains("\"type\":\"turn\""));
+
+        manager.compact().unwrap();
+        let replay = read_session_file(&session_file(&temp_dir, "r")).unwrap();
+        assert_eq!(replay.corrupt_lines, 0);
+        assert_eq!(replay.messages.len(), 2);
+        assert_eq!(replay.header.turns.len(), 1);
+        assert_eq!(replay.header.turns[0].recap, recap);
+    }
+
+    #[test]
+    fn test_load_recovers_from_truncated_last_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "crash".to_string()).unwrap();
+        manager.add_message(Message::user("first"));
+        manager.add_message(Message::assistant("second"));
+        manager.add_message(Message::user("third"));
+
+        // 模拟写入中途崩溃：截断最后一行
+        let path = session_file(&temp_dir, "crash");
+        let content = fs::read_to_string(&path).unwrap();
+        let truncated = &content[..content.len() - 10];
+        fs::write(&path, truncated).unwrap();
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "crash".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.get_messages().len(), 2);
+
+        // 修复后继续追加不会与残缺行粘连
+        reloaded.add_message(Message::assistant("fourth"));
+        let mut again = ContextManager::new(temp_dir.path(), "crash".to_string()).unwrap();
+        assert!(again.load().unwrap());
+        let contents: Vec<String> = again
+            .get_messages()
+            .iter()
+            .map(|m| SerializableMessage::from(m).content)
+            .collect();
+        assert_eq!(contents, vec!["first", "second", "fourth"]);
+    }
+
+    #[test]
+    fn test_legacy_session_migrated_with_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = SessionData {
+            metadata: SessionMetadata {
+                session_id: "old".to_string(),
+                created_at: "2024-01-01T00:00:00+00:00".to_string(),
+                last_updated: "2024-01-01T00:00:00+00:00".to_string(),
+                message_count: 2,
+                tags: Vec::new(),
+                environment: None,
+                model_changes: Vec::new(),
+            },
+            messages: vec![
+                SerializableMessage {
+                    role: "user".to_string(),
+                    content: "question".to_string(),
+                    blocks: Vec::new(),
+                    tags: Vec::new(),
+                    pinned: false,
+                },
+                SerializableMessage {
+                    role: "assistant".to_string(),
+                    content: "answer".to_string(),
+                    blocks: Vec::new(),
+                    tags: Vec::new(),
+                    pinned: false,
+                },
+            ],
+        };
+        fs::write(
+            temp_dir.path().join("old.json"),
+            serde_json::to_string_pretty(&legacy).unwrap(),
+        )
+        .unwrap();
+
+        let mut manager = ContextManager::new(temp_dir.path(), "old".to_string()).unwrap();
+        assert!(manager.load().unwrap());
+        assert_eq!(manager.get_messages().len(), 2);
+        assert!(session_file(&temp_dir, "old").exists());
+        assert!(temp_dir.path().join("old.json.bak").exists());
+        assert!(!temp_dir.path().join("old.json").exists());
+
+        let sessions = manager.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].created_at, "2024-01-01T00:00:00+00:00");
+        assert_eq!(sessions[0].message_count, 2);
+    }
+
+    #[test]
+    fn test_token_cache_and_prompt_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "t".to_string())
+            .unwrap()
+            .with_max_messages(2);
+        manager.add_message(Message::user("dropped"));
+        manager.add_message(Message::assistant("kept answer"));
+        manager.add_message(Message::user("current prompt"));
+
+        let expected = crate::token_counter::count_messages_tokens(&[
+            ("assistant".to_string(), "kept answer".to_string()),
+            ("user".to_string(), "current prompt".to_string()),
+        ]);
+        assert_eq!(manager.estimated_input_tokens(), expected);
+
+        let history = manager.prompt_history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(SerializableMessage::from(&history[0]).content, "kept answer");
+    }
+
+    #[test]
+    fn test_superseded_reads_leave_session_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "reads".to_string()).unwrap();
+        let content = "fn main() {}\n".repeat(200);
+        for id in ["r1", "r2"] {
+            manager.add_message(Message::Assistant {
+                id: None,
+                content: OneOrMany::one(AssistantContent::tool_call(
+                    id,
+                    "read_file",
+                    serde_json::json!({ "file_path": "src/main.rs" }),
+                )),
+            });
+            manager.add_message(tool_result(id, &content));
+        }
+        manager.add_message(Message::user("current prompt"));
+        let stored = fs::read_to_string(session_file(&temp_dir, "reads")).unwrap();
+
+        let history = manager.prompt_history();
+        assert_eq!(
+            SerializableMessage::from(&history[1]).blocks,
+            vec![ContentBlock::ToolResult {
+                tool_use_id: "r1".to_string(),
+                call_id: None,
+                content: vec![ContentBlock::Text {
+                    text: "[earlier read of src/main.rs superseded by a later read]".to_string(),
+                }],
+                is_error: false,
+            }]
+        );
+        let latest = SerializableMessage::from(&history[3]);
+        assert_eq!(latest.blocks[0].model_text(), content);
+        let (_, savings) = manager.request_messages();
+        assert_eq!(savings.reads, 1);
+
+        // 内存中和文件中保存的消息不变
+        let kept = SerializableMessage::from(&manager.get_messages()[1]);
+        assert_eq!(kept.blocks[0].model_text(), content);
+        let file = fs::read_to_string(session_file(&temp_dir, "reads")).unwrap();
+        assert_eq!(file, stored);
+
+        let manager = manager.with_dedupe_file_reads(false);
+        let (_, savings) = manager.request_messages();
+        assert_eq!(savings, ReadSavings::default());
+    }
+
+    #[test]
+    fn test_tags_survive_reload_and_compact() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "tagged".to_string()).unwrap();
+        manager.add_message_with_tags(Message::user("login fails"), vec!["bug".to_string()]);
+        manager.add_message(Message::assistant("found it"));
+        manager.compact().unwrap();
+
+        let content = fs::read_to_string(session_file(&temp_dir, "tagged")).unwrap();
+        assert!(content.contains("\"tags\":[\"bug\"]"));
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "tagged".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        let transcript = reloaded.transcript();
+        assert_eq!(transcript[0].tags, vec!["bug"]);
+        assert!(transcript[1].tags.is_empty());
+
+        let sessions = reloaded.list_sessions().unwrap();
+        assert_eq!(sessions[0].tags, vec!["bug"]);
+    }
+
+    #[test]
+    fn test_pinned_messages_survive_trimming_and_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "pins".to_string())
+            .unwrap()
+            .with_max_messages(5);
+        manager.add_message(Message::user("use snake_case"));
+        manager.add_message(Message::assistant("ok"));
+        manager.add_message(Message::user("target is wasm32"));
+        manager.set_pinned(0, true).unwrap();
+        manager.set_pinned(2, true).unwrap();
+        for i in 0..20 {
+            manager.add_message(Message::user(format!("question {}", i)));
+            manager.add_message(Message::assistant(format!("answer {}", i)));
+        }
+
+        let contents = |manager: &ContextManager| -> Vec<String> {
+            manager
+                .transcript()
+                .into_iter()
+                .map(|m| m.content)
+                .collect()
+        };
+        let expected = vec![
+            "use snake_case",
+            "target is wasm32",
+            "answer 18",
+            "question 19",
+            "answer 19",
+        ];
+        assert_eq!(contents(&manager), expected);
+        assert!(manager.is_pinned(0) && manager.is_pinned(1));
+        assert!(!manager.is_pinned(2));
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "pins".to_string())
+            .unwrap()
+            .with_max_messages(5);
+        assert!(reloaded.load().unwrap());
+        assert_eq!(contents(&reloaded), expected);
+        assert!(reloaded.transcript()[1].pinned);
+    }
+
+    #[test]
+    fn test_rewind_across_pin_requires_confirmation() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "rw".to_string()).unwrap();
+        manager.add_message(Message::user("first"));
+        manager.add_message(Message::assistant("reply"));
+        manager.add_message(Message::user("keep this"));
+        manager.add_message(Message::assistant("sure"));
+        manager.set_pinned(2, true).unwrap();
+
+        assert_eq!(
+            manager.rewind(1, false).unwrap(),
+            RewindOutcome::PinnedMessages(vec![2])
+        );
+        assert_eq!(manager.get_messages().len(), 4);
+
+        assert_eq!(manager.rewind(3, false).unwrap(), RewindOutcome::Rewound(1));
+        assert_eq!(manager.rewind(1, true).unwrap(), RewindOutcome::Rewound(2));
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "rw".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.get_messages().len(), 1);
+    }
+
+    #[test]
+    fn test_clear_rewrites_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "c".to_string()).unwrap();
+        manager.add_message(Message::user("to be cleared"));
+        manager.clear().unwrap();
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "c".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert!(reloaded.get_messages().is_empty());
+        assert!(!temp_dir.path().join("c.jsonl.tmp").exists());
+    }
+
+    #[test]
+    fn test_concurrent_writers_do_not_corrupt_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let dir = temp_dir.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let mut manager = ContextManager::new(&dir, "shared".to_string()).unwrap();
+                    for i in 0..100 {
+                        manager.add_message(Message::user(format!("writer {} message {}", writer, i)));
+                    }
+                    manager.save().unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let content = fs::read_to_string(session_file(&temp_dir, "shared")).unwrap();
+        let records: Vec<SessionRecord> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("every line is a complete record"))
+            .collect();
+        let metas = records
+            .iter()
+            .filter(|r| matches!(r, SessionRecord::Meta { .. }))
+            .count();
+        assert_eq!(metas, 1);
+        assert_eq!(records.len(), 201);
+    }
+
+    fn tool_use(id: &str, path: &str) -> Message {
+        Message::Assistant {
+            id: None,
+            content: OneOrMany::many(vec![
+                AssistantContent::Reasoning(Reasoning::new("need to look at the file")),
+                AssistantContent::text("Let me read it."),
+                AssistantContent::tool_call(id, "read_file", serde_json::json!({ "path": path })),
+            ])
+            .unwrap(),
+        }
+    }
+
+    fn tool_result(id: &str, output: &str) -> Message {
+        Message::User {
+            content: OneOrMany::one(UserContent::tool_result(
+                id,
+                OneOrMany::one(ToolResultContent::text(output)),
+            )),
+        }
+    }
+
+    fn png() -> Image {
+        match UserContent::image_base64("iVBORw0KGgo=", Some(ImageMediaType::PNG), None) {
+            UserContent::Image(image) => image,
+            _ => unreachable!(),
+        }
+    }
+
+    /// 随机交错文本、工具调用、工具结果（含出错结果和图片）和图片消息
+    fn random_conversation(seed: u64) -> Vec<Message> {
+        let mut state = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let mut next = move |n: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % n
+        };
+        let mut messages = Vec::new();
+        for turn in 0..1 + next(6) {
+            let id = format!("toolu_{}_{}", seed, turn);
+            match next(4) {
+                0 => {
+                    messages.push(Message::user(format!("question {}", turn)));
+                    messages.push(Message::assistant(format!("answer {}", turn)));
+                }
+                1 => {
+                    messages.push(tool_use(&id, &format!("src/{}.rs", turn)));
+                    messages.push(tool_result(&id, "fn main() {}\n// {\"json\": true}\n"));
+                }
+                2 => {
+                    messages.push(tool_use(&id, "missing.rs"));
+                    messages.push(tool_result(
+                        &id,
+                        "ToolCallError: ToolCallError: File not found: missing.rs",
+                    ));
+                }
+                _ => {
+                    messages.push(Message::User {
+                        content: OneOrMany::many(vec![
+                            UserContent::text("what is in this screenshot?"),
+                            UserContent::Image(png()),
+                        ])
+                        .unwrap(),
+                    });
+                    messages.push(tool_use(&id, "shot.png"));
+                    messages.push(Message::User {
+                        content: OneOrMany::one(UserContent::tool_result(
+                            &id,
+                            OneOrMany::many(vec![
+                                ToolResultContent::text("Read image shot.png"),
+                                ToolResultContent::Image(png()),
+                            ])
+                            .unwrap(),
+                        )),
+                    });
+                }
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn test_content_blocks_round_trip() {
+        for seed in 0..64 {
+            let conversation = random_conversation(seed);
+            for message in &conversation {
+                let serializable = SerializableMessage::from(message);
+                let json = serde_json::to_string(&serializable).unwrap();
+                let parsed: SerializableMessage = serde_json::from_str(&json).unwrap();
+                assert_eq!(parsed.blocks, serializable.blocks, "seed {}", seed);
+                let restored = Message::from(parsed);
+                assert_eq!(
+                    serde_json::to_value(&restored).unwrap(),
+                    serde_json::to_value(message).unwrap(),
+                    "seed {}",
+                    seed
+                );
+            }
+
+            // 经过会话文件保存和加载后同样不丢失内容
+            let temp_dir = TempDir::new().unwrap();
+            let session_id = format!("s{}", seed);
+            let mut manager = ContextManager::new(temp_dir.path(), session_id.clone()).unwrap();
+            for message in &conversation {
+                manager.add_message(message.clone());
+            }
+            let mut reloaded = ContextManager::new(temp_dir.path(), session_id).unwrap();
+            assert!(reloaded.load().unwrap());
+            assert_eq!(
+                serde_json::to_value(reloaded.get_messages()).unwrap(),
+                serde_json::to_value(&conversation).unwrap(),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_tool_blocks_are_summarized_for_display() {
+        let call = SerializableMessage::from(&tool_use("toolu_1", "src/main.rs"));
+        assert_eq!(
+            call.content,
+            "[reasoning] Let me read it. [tool call: read_file]"
+        );
+
+        let output = "fn main() {}\n".repeat(20);
+        let result = SerializableMessage::from(&tool_result("toolu_1", &output));
+        assert_eq!(result.content, "[tool result: 20 lines]");
+        assert!(result.token_count() > count_message_tokens("user", &result.content));
+
+        let error = SerializableMessage::from(&tool_result(
+            "toolu_1",
+            "ToolCallError: ToolCallError: old_string not found in src/main.rs",
+        ));
+        assert_eq!(
+            error.content,
+            "[tool error: old_string not found in src/main.rs]"
+        );
+        assert!(matches!(
+            error.blocks.as_slice(),
+            [ContentBlock::ToolResult { is_error: true, .. }]
+        ));
+
+        // 纯文本消息不保存内容块，会话文件格式与之前相同
+        let text = serde_json::to_value(SerializableMessage::from(&Message::user("hi"))).unwrap();
+        assert!(text.get("blocks").is_none());
+    }
+
+    #[test]
+    fn test_trim_drops_orphaned_tool_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "t".to_string())
+            .unwrap()
+            .with_max_messages(3);
+        manager.add_message(Message::user("read main.rs"));
+        manager.add_message(tool_use("toolu_1", "src/main.rs"));
+        manager.add_message(tool_result("toolu_1", "fn main() {}"));
+        manager.add_message(Message::assistant("It is empty."));
+
+        // 上限 3 条时只丢弃最早的用户消息，工具调用和结果仍成对保留
+        assert_eq!(manager.get_messages().len(), 3);
+
+        // 再加一条后工具调用被丢弃，它的结果也随之丢弃
+        manager.add_message(Message::user("thanks"));
+        let contents: Vec<String> = manager
+            .transcript()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["It is empty.", "thanks"]);
+    }
+
+    /// 写入一轮完整的工具调用对话，返回会话文件的各行（第一行是元数据）
+    fn tool_turn_lines(dir: &TempDir, id: &str) -> Vec<String> {
+        let mut manager = ContextManager::new(dir.path(), id.to_string()).unwrap();
+        manager.add_message(Message::assistant("Hello"));
+        manager.add_message(Message::user("read main.rs"));
+        manager.add_message(tool_use("toolu_1", "src/main.rs"));
+        manager.add_message(tool_result("toolu_1", "fn main() {}"));
+        manager.add_message(Message::assistant("It is empty."));
+        fs::read_to_string(session_file(dir, id))
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// 模拟在第 `keep` 行之后崩溃的会话
+    fn load_truncated(dir: &TempDir, id: &str, lines: &[String], keep: usize) -> ContextManager {
+        fs::write(session_file(dir, id), lines[..keep].join("\n") + "\n").unwrap();
+        let mut manager = ContextManager::new(dir.path(), id.to_string()).unwrap();
+        assert!(manager.load().unwrap());
+        manager
+    }
+
+    #[test]
+    fn test_load_detects_interrupted_turns() {
+        let temp_dir = TempDir::new().unwrap();
+        let lines = tool_turn_lines(&temp_dir, "i");
+        assert_eq!(lines.len(), 6);
+
+        let expected = [
+            (2, None),
+            (3, Some(InterruptedKind::UnansweredPrompt)),
+            (
+                4,
+                Some(InterruptedKind::PendingToolCalls(vec![
+                    "toolu_1".to_string()
+                ])),
+            ),
+            (5, Some(InterruptedKind::UnansweredToolResults)),
+            (6, None),
+        ];
+        for (keep, kind) in expected {
+            let manager = load_truncated(&temp_dir, "i", &lines, keep);
+            let turn = manager.interrupted_turn();
+            assert_eq!(turn.map(|turn| turn.kind.clone()), kind, "{} lines", keep);
+            if let Some(turn) = turn {
+                assert_eq!(turn.prompt_index, 1);
+                assert_eq!(turn.prompt, "read main.rs");
+            }
+        }
+
+        // `!` 命令的输出不需要回复
+        let mut manager = load_truncated(&temp_dir, "i", &lines, 6);
+        manager.add_message_with_tags(
+            Message::user("I ran this command in my shell"),
+            vec![SHELL_OUTPUT_TAG.to_string()],
+        );
+        let mut reloaded = ContextManager::new(temp_dir.path(), "i".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.interrupted_turn(), None);
+    }
+
+    #[test]
+    fn test_recover_interrupted_turn() {
+        let temp_dir = TempDir::new().unwrap();
+        let lines = tool_turn_lines(&temp_dir, "r");
+
+        let mut manager = load_truncated(&temp_dir, "r", &lines, 4);
+        let prompt = manager
+            .recover_interrupted_turn(TurnRecovery::Resend)
+            .unwrap();
+        assert_eq!(prompt.as_deref(), Some("read main.rs"));
+        assert_eq!(manager.get_messages().len(), 1);
+
+        let mut manager = load_truncated(&temp_dir, "r", &lines, 5);
+        assert_eq!(
+            manager
+                .recover_interrupted_turn(TurnRecovery::Drop)
+                .unwrap(),
+            None
+        );
+        let mut reloaded = ContextManager::new(temp_dir.path(), "r".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.get_messages().len(), 1);
+        assert_eq!(reloaded.interrupted_turn(), None);
+
+        // 保留时补上工具调用的错误结果，重新加载后不再视为中断；重写中途留下的临时文件被清理
+        fs::write(temp_dir.path().join("r.jsonl.tmp"), "partial").unwrap();
+        let mut manager = load_truncated(&temp_dir, "r", &lines, 4);
+        assert!(!temp_dir.path().join("r.jsonl.tmp").exists());
+        manager
+            .recover_interrupted_turn(TurnRecovery::Keep)
+            .unwrap();
+        let mut reloaded = ContextManager::new(temp_dir.path(), "r".to_string()).unwrap();
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.interrupted_turn(), None);
+        let transcript = reloaded.transcript();
+        assert_eq!(transcript.len(), 5);
+        assert!(transcript[3].tool_result_ids().eq(["toolu_1"]));
+        assert_eq!(
+            transcript[3].content,
+            "[tool error: interrupted: oxide exited before the tool finished]"
+        );
+        assert_eq!(transcript[4].content, INTERRUPTED_NOTE);
+    }
+
+    fn environment(cwd: &str, model: &str) -> SessionEnvironment {
+        SessionEnvironment {
+            oxide_version: "0.1.0".to_string(),
+            provider: "anthropic".to_string(),
+            model: model.to_string(),
+            cwd: cwd.to_string(),
+            git_commit: Some("0123456789abcdef".to_string()),
+            git_branch: Some("main".to_string()),
+            mode: "repl".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_environment_header_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let env = environment("/work/app", "claude-a");
+        let mut manager = ContextManager::new(temp_dir.path(), "env".to_string())
+            .unwrap()
+            .with_environment(env.clone());
+        manager.add_message(Message::user("hello"));
+        manager.save().unwrap();
+
+        let content = fs::read_to_string(session_file(&temp_dir, "env")).unwrap();
+        let header: Value = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(header["type"], "meta");
+        assert_eq!(header["version"], SESSION_FORMAT_VERSION);
+        assert_eq!(header["environment"]["model"], "claude-a");
+        assert_eq!(header["environment"]["git_branch"], "main");
+
+        let mut reloaded = ContextManager::new(temp_dir.path(), "env".to_string())
+            .unwrap()
+            .with_environment(env.clone());
+        assert!(reloaded.load().unwrap());
+        assert_eq!(reloaded.session_environment(), Some(&env));
+        assert!(reloaded.environment_mismatches().is_empty());
+        assert_eq!(env.git_label().as_deref(), Some("main@0123456"));
+
+        let sessions = reloaded.list_sessions().unwrap();
+        assert_eq!(sessions[0].environment, Some(env));
+        assert_eq!(
+            sessions[0].latest_model().as_deref(),
+            Some("anthropic/claude-a")
+        );
+    }
+
+    #[test]
+    fn test_model_change_and_mismatch_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ContextManager::new(temp_dir.path(), "moved".to_string())
+            .unwrap()
+            .with_environment(environment("/work/app", "claude-a"));
+        manager.add_message(Message::user("hello"));
+        manager.save().unwrap();
+
+        // 在另一个目录用另一个模型继续
+        let mut resumed = ContextManager::new(temp_dir.path(), "moved".to_string())
+            .unwrap()
+            .with_environment(environment("/work/other", "claude-b"));
+        assert!(resumed.load().unwrap());
+        assert_eq!(
+            resumed.environment_mismatches(),
+            vec![
+                SessionMismatch::WorkingDir {
+                    session: "/work/app".to_string(),
+                    current: "/work/other".to_string(),
+                },
+                SessionMismatch::Model {
+                    session: "anthropic/claude-a".to_string(),
+                    current: "anthropic/claude-b".to_string(),
+                },
+            ]
+        );
+
+        // 发送消息时记录模型变更，只记录一次，重写文件后保留
+        resumed.add_message(Message::user("again"));
+        resumed.add_message(Message::assistant("ok"));
+        resumed.save().unwrap();
+        resumed.compact().unwrap();
+        let sessions = resumed.list_sessions().unwrap();
+        assert_eq!(sessions[0].model_changes.len(), 1);
+        assert_eq!(sessions[0].model_changes[0].model, "claude-b");
+        assert_eq!(
+            sessions[0].latest_model().as_deref(),
+            Some("anthropic/claude-b")
+        );
+        assert_eq!(sessions[0].message_count, 3);
+
+        // 之后只剩目录不同
+        let mut reloaded = ContextManager::new(temp_dir.path(), "moved".to_string())
+            .unwrap()
+            .with_environment(environment("/work/other", "claude-b"));
+        assert!(reloaded.load().unwrap());
+        assert!(matches!(
+            reloaded.environment_mismatches().as_slice(),
+            [SessionMismatch::WorkingDir { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_newer_session_format_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let content = concat!(
+            r#"{"type":"meta","session_id":"future","created_at":"2030-01-01T00:00:00+00:00","version":99}"#,
+            "\n",
+            r#"{"type":"hologram","timestamp":"2030-01-01T00:00:00+00:00"}"#,
+            "\n",
+        );
+        let path = session_file(&temp_dir, "future");
+        fs::write(&path, content).unwrap();
+
+        let mut manager = ContextManager::new(temp_dir.path(), "future".to_string()).unwrap();
+        let error = manager.load().unwrap_err().to_string();
+        assert!(error.contains("newer version of oxide"), "{}", error);
+        assert!(error.contains("v99"), "{}", error);
+
+        // 不加载直接追加也不会写入
+        manager.add_message(Message::user("hello"));
+        assert!(manager.save().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_capture_records_git_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let env = SessionEnvironment::capture(temp_dir.path(), "openai", "gpt", "serve");
+        assert_eq!(env.oxide_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(env.git_commit, None);
+        assert_eq!(env.git_label(), None);
+
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let env = SessionEnvironment::capture(temp_dir.path(), "openai", "gpt", "serve");
+        assert_eq!(env.git_commit, Some(commit.to_string()));
+        assert!(env.git_branch.is_some());
+        assert_eq!(env.model_label(), "openai/gpt");
+    }
+
+    #[test]
+    fn test_generate_session_id_is_unique() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = ContextManager::generate_session_id(temp_dir.path());
+        let second = ContextManager::generate_session_id(temp_dir.path());
+        assert_ne!(first, second);
+        assert_eq!(first.split('-').count(), 3);
     }
 }
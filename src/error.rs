@@ -0,0 +1,330 @@
+//! 统一错误类型
+//!
+//! 模型请求、工具和配置的错误按类别区分：调用方据此判断能否重试，终端和 serve 客户端
+//! 显示对应的操作提示（如"检查 ANTHROPIC_API_KEY"）。rig 的请求错误只以文本形式到达
+//! （流中的 `StreamingError`），`OxideError::classify` 从其中的 HTTP 状态码、服务商
+//! 返回的错误类型和网络错误信息推断类别，并提取请求 ID 和建议的等待时间。
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::tools::FileToolError;
+
+/// 错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// API 密钥缺失、无效或无权限（401/403）
+    Auth,
+    /// 触发限流（429）
+    RateLimit,
+    /// 连接失败、超时、DNS 错误
+    Network,
+    /// 服务商返回的其他错误（过载、5xx、请求被拒绝）
+    Provider,
+    /// 工具执行失败
+    Tool,
+    /// 配置错误
+    Config,
+    /// 本地文件读写失败
+    Io,
+}
+
+impl ErrorCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::RateLimit => "rate_limit",
+            Self::Network => "network",
+            Self::Provider => "provider",
+            Self::Tool => "tool",
+            Self::Config => "config",
+            Self::Io => "io",
+        }
+    }
+}
+
+/// 带类别的错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message}")]
+pub struct OxideError {
+    pub category: ErrorCategory,
+    pub message: String,
+    /// HTTP 状态码（服务商错误）
+    pub status: Option<u16>,
+    /// 服务商返回的请求 ID，反馈问题时提供给服务商
+    pub request_id: Option<String>,
+    /// 服务商建议的等待时间（限流）
+    pub retry_after: Option<Duration>,
+}
+
+/// 错误文本中的 HTTP 状态码，如 `status 429`、`HTTP 401`、`(529)`、`503 Service Unavailable`
+static STATUS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:status(?:\s*code)?[\s:=]*|http(?:/\d(?:\.\d)?)?\s+|\()([1-5]\d\d)\b|\b([45]\d\d)\s+(?:unauthorized|forbidden|too many requests|internal server error|bad gateway|service unavailable|gateway timeout|bad request|not found)",
+    )
+    .unwrap()
+});
+
+/// 请求 ID：Anthropic 错误体中的 `"request_id"`，或响应头 `request-id` / `x-request-id`
+static REQUEST_ID_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:x-)?request[-_]id"?\s*[:=]\s*"?([A-Za-z0-9_\-]{6,})"#).unwrap()
+});
+
+/// 建议的等待时间（秒）
+static RETRY_AFTER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)retry[-_ ]after"?\s*[:=]?\s*"?(\d+(?:\.\d+)?)\s*s?"#).unwrap());
+
+/// 服务商错误类型到类别的映射（Anthropic / OpenAI 的错误体）
+const PROVIDER_ERROR_TYPES: &[(&str, ErrorCategory, Option<u16>)] = &[
+    ("authentication_error", ErrorCategory::Auth, Some(401)),
+    ("invalid_api_key", ErrorCategory::Auth, Some(401)),
+    ("permission_error", ErrorCategory::Auth, Some(403)),
+    ("rate_limit_error", ErrorCategory::RateLimit, Some(429)),
+    ("rate_limit_exceeded", ErrorCategory::RateLimit, Some(429)),
+    ("overloaded_error", ErrorCategory::Provider, Some(529)),
+    ("api_error", ErrorCategory::Provider, Some(500)),
+];
+
+/// 网络错误的特征文本（reqwest / hyper / 系统错误）
+const NETWORK_PATTERNS: &[&str] = &[
+    "error sending request",
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "tcp connect error",
+    "dns error",
+    "failed to lookup address",
+    "timed out",
+    "operation timed out",
+    "network is unreachable",
+];
+
+impl OxideError {
+    pub fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        Self {
+            category,
+            message: message.into(),
+            status: None,
+            request_id: None,
+            retry_after: None,
+        }
+    }
+
+    /// 由 HTTP 响应创建（`body` 为响应体）
+    pub fn from_http(status: u16, body: &str) -> Self {
+        let mut error = Self::classify(body);
+        error.status = Some(status);
+        error.category = match status {
+            401 | 403 => ErrorCategory::Auth,
+            429 => ErrorCategory::RateLimit,
+            _ => ErrorCategory::Provider,
+        };
+        error
+    }
+
+    /// 从错误文本推断类别（模型请求失败时 rig 只提供文本）
+    pub fn classify(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let lower = message.to_lowercase();
+
+        let status = STATUS_RE.captures(&message).and_then(|caps| {
+            caps.get(1)
+                .or_else(|| caps.get(2))
+                .and_then(|m| m.as_str().parse::<u16>().ok())
+        });
+        let provider_type = PROVIDER_ERROR_TYPES
+            .iter()
+            .find(|(name, _, _)| lower.contains(name));
+
+        let (category, status) = match (status, provider_type) {
+            (Some(401 | 403), _) => (ErrorCategory::Auth, status),
+            (Some(429), _) => (ErrorCategory::RateLimit, status),
+            (Some(_), Some((_, category, _))) => (*category, status),
+            (Some(_), None) => (ErrorCategory::Provider, status),
+            (None, Some((_, category, implied))) => (*category, *implied),
+            (None, None) if NETWORK_PATTERNS.iter().any(|p| lower.contains(p)) => {
+                (ErrorCategory::Network, None)
+            }
+            (None, None) => (ErrorCategory::Provider, None),
+        };
+
+        let request_id = REQUEST_ID_RE
+            .captures(&message)
+            .map(|caps| caps[1].to_string());
+        let retry_after = RETRY_AFTER_RE
+            .captures(&message)
+            .and_then(|caps| caps[1].parse::<f64>().ok())
+            .map(Duration::from_secs_f64);
+
+        Self {
+            category,
+            message,
+            status,
+            request_id,
+            retry_after,
+        }
+    }
+
+    /// 稍后重试是否可能成功：限流、网络错误、服务商过载或 5xx
+    pub fn is_retryable(&self) -> bool {
+        match self.category {
+            ErrorCategory::RateLimit | ErrorCategory::Network => true,
+            ErrorCategory::Provider => self.status.is_some_and(|status| status >= 500),
+            _ => false,
+        }
+    }
+
+    /// 显示给用户的一行操作提示，没有可给的建议时为 None
+    pub fn user_hint(&self) -> Option<String> {
+        match self.category {
+            ErrorCategory::Auth => Some(t!("error.hint.auth").to_string()),
+            ErrorCategory::RateLimit => Some(match self.retry_after {
+                Some(wait) => t!(
+                    "error.hint.rate_limit_wait",
+                    seconds = wait.as_secs().max(1)
+                ),
+                None => t!("error.hint.rate_limit").to_string(),
+            }),
+            ErrorCategory::Network => Some(t!("error.hint.network").to_string()),
+            ErrorCategory::Provider if self.is_retryable() => {
+                Some(t!("error.hint.overloaded").to_string())
+            }
+            ErrorCategory::Config => Some(t!("error.hint.config").to_string()),
+            ErrorCategory::Provider | ErrorCategory::Tool | ErrorCategory::Io => None,
+        }
+    }
+}
+
+impl From<FileToolError> for OxideError {
+    fn from(error: FileToolError) -> Self {
+        Self::new(ErrorCategory::Tool, error.message())
+    }
+}
+
+impl From<std::io::Error> for OxideError {
+    fn from(error: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        let category = match error.kind() {
+            ErrorKind::TimedOut
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected => ErrorCategory::Network,
+            _ => ErrorCategory::Io,
+        };
+        Self::new(category, error.to_string())
+    }
+}
+
+/// 其余错误：优先取错误链中已知的类型，否则按文本推断
+impl From<anyhow::Error> for OxideError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<OxideError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        for cause in error.chain() {
+            if cause.is::<FileToolError>() {
+                return Self::new(ErrorCategory::Tool, format!("{:#}", error));
+            }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                let category = Self::from(std::io::Error::from(io.kind())).category;
+                return Self::new(category, format!("{:#}", error));
+            }
+        }
+        Self::classify(format!("{:#}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(error: &OxideError) -> String {
+        crate::i18n::init(crate::i18n::Locale::En);
+        error.user_hint().unwrap_or_default()
+    }
+
+    #[test]
+    fn test_classify_http_failures() {
+        // Anthropic 401
+        let auth = OxideError::classify(
+            r#"CompletionError: ProviderError: {"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"},"request_id":"req_011CTXk2pXz"}"#,
+        );
+        assert_eq!(auth.category, ErrorCategory::Auth);
+        assert_eq!(auth.status, Some(401));
+        assert_eq!(auth.request_id.as_deref(), Some("req_011CTXk2pXz"));
+        assert!(!auth.is_retryable());
+        assert!(hint(&auth).contains("ANTHROPIC_API_KEY"));
+
+        // OpenAI 兼容接口的 429，带 Retry-After
+        let limited = OxideError::classify(
+            "HttpError: status 429 Too Many Requests: {\"error\":{\"code\":\"rate_limit_exceeded\"}} retry-after: 20",
+        );
+        assert_eq!(limited.category, ErrorCategory::RateLimit);
+        assert_eq!(limited.status, Some(429));
+        assert_eq!(limited.retry_after, Some(Duration::from_secs(20)));
+        assert!(limited.is_retryable());
+        assert!(hint(&limited).contains("20s"));
+
+        // 过载和 5xx 可以重试，400 不行
+        let overloaded =
+            OxideError::classify(r#"{"type":"error","error":{"type":"overloaded_error"}}"#);
+        assert_eq!(
+            (overloaded.category, overloaded.status),
+            (ErrorCategory::Provider, Some(529))
+        );
+        assert!(overloaded.is_retryable());
+        assert!(OxideError::classify("HTTP 503 Service Unavailable").is_retryable());
+        let bad_request = OxideError::classify(
+            r#"status 400: {"error":{"type":"invalid_request_error","message":"max_tokens too large"}}"#,
+        );
+        assert_eq!(bad_request.category, ErrorCategory::Provider);
+        assert!(!bad_request.is_retryable());
+        assert_eq!(bad_request.user_hint(), None);
+
+        let forbidden = OxideError::from_http(403, r#"{"error":{"type":"permission_error"}}"#);
+        assert_eq!(forbidden.category, ErrorCategory::Auth);
+    }
+
+    #[test]
+    fn test_classify_network_failures() {
+        let refused = OxideError::classify(
+            "HttpError: error sending request for url (https://api.anthropic.com/v1/messages): tcp connect error: Connection refused (os error 111)",
+        );
+        assert_eq!(refused.category, ErrorCategory::Network);
+        assert!(refused.is_retryable());
+        assert!(!hint(&refused).is_empty());
+
+        let timeout = OxideError::from(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "deadline elapsed",
+        ));
+        assert_eq!(timeout.category, ErrorCategory::Network);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let tool = OxideError::from(FileToolError::FileNotFound("a.rs".to_string()));
+        assert_eq!(tool.category, ErrorCategory::Tool);
+        assert!(!tool.is_retryable());
+
+        let io: anyhow::Error = std::io::Error::new(std::io::ErrorKind::NotFound, "gone").into();
+        let io = OxideError::from(io.context("reading session"));
+        assert_eq!(io.category, ErrorCategory::Io);
+        assert_eq!(io.message, "reading session: gone");
+
+        let original = OxideError::new(ErrorCategory::Config, "bad base_url");
+        let round_trip = OxideError::from(anyhow::Error::new(original.clone()));
+        assert_eq!(round_trip, original);
+        assert!(hint(&round_trip).contains("oxide doctor"));
+
+        // 无法识别的错误归为服务商错误，不重试
+        let unknown = OxideError::from(anyhow::anyhow!("something odd happened"));
+        assert_eq!(unknown.category, ErrorCategory::Provider);
+        assert!(!unknown.is_retryable());
+    }
+}
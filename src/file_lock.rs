@@ -0,0 +1,98 @@
+//! 文件锁
+//!
+//! 多个 oxide 实例可能同时写同一个会话或任务文件。写入前先获取旁路锁文件
+//! `<file>.lock` 上的排他锁（建议锁，进程退出时由系统释放），锁住旁路文件而不是
+//! 目标文件本身，因为目标文件可能被原子重命名替换。
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// 默认的等待时间
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Error)]
+pub enum FileLockError {
+    #[error("Timed out waiting for lock: {}", .0.display())]
+    Timeout(PathBuf),
+
+    #[error("Failed to lock {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// 持有期间独占目标文件，drop 时释放
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// 获取 `path` 对应的锁，在 `timeout` 内拿不到时返回 `FileLockError::Timeout`
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self, FileLockError> {
+        let lock_path = lock_path(path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|source| FileLockError::Io {
+                path: lock_path.clone(),
+                source,
+            })?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => return Err(FileLockError::Timeout(lock_path)),
+                Err(TryLockError::Error(source)) => {
+                    return Err(FileLockError::Io {
+                        path: lock_path,
+                        source,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// 旁路锁文件路径：`<file>.lock`
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.jsonl");
+
+        let guard = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT).unwrap();
+        assert!(temp_dir.path().join("session.jsonl.lock").exists());
+
+        let err = FileLock::acquire(&path, Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(err, FileLockError::Timeout(_)));
+
+        drop(guard);
+        FileLock::acquire(&path, Duration::from_millis(50)).unwrap();
+    }
+}
@@ -1,39 +1,149 @@
 use rig::agent::{CancelSignal, StreamingPromptHook};
 use rig::completion::CompletionModel;
-use rig::completion::Message;
+use rig::completion::{GetTokenUsage, Message, Usage};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::Span;
+
+use crate::agent::loop_guard;
+use crate::agent::replay;
+use crate::agent::time_box::TurnDeadline;
+use crate::agent::turn_recap::{RecapTracker, TurnRecap};
+use crate::agent::turn_summary::StopReason;
+use crate::agent::{AdvisoryTracker, ReminderTracker};
+use crate::telemetry;
+
+/// 进行中的模型请求
+struct ProviderRequest {
+    span: Span,
+    start: Instant,
+    tool_calls: usize,
+}
+
+/// 本轮所有模型请求的累计统计
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TurnStats {
+    pub tool_calls: usize,
+    /// 最后一次模型请求的停止原因
+    pub stop_reason: Option<StopReason>,
+}
 
 /// Session-aware hook that logs tool calls and completions with session context
 #[derive(Clone)]
 pub struct SessionIdHook {
-    #[allow(dead_code)]
     pub session_id: String,
+    /// 本轮的根 span，模型请求 span 挂在其下
+    turn: Span,
+    request: Arc<Mutex<Option<ProviderRequest>>>,
+    stats: Arc<Mutex<TurnStats>>,
+    /// 本轮的工具活动，用于回答后的回顾
+    recap: RecapTracker,
+    /// 检查本轮的工具结果，违反规则时在结果上附加提醒
+    reminders: Option<ReminderTracker>,
+    /// 追踪反复失败的工具调用，在本轮结束时提示用户
+    advisories: Option<AdvisoryTracker>,
+    /// 本轮的截止时间，到期后不再发起新的工具调用
+    deadline: Option<TurnDeadline>,
 }
 
 impl SessionIdHook {
     pub fn new(session_id: String) -> Self {
-        Self { session_id }
+        Self {
+            session_id,
+            turn: Span::none(),
+            request: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(TurnStats::default())),
+            recap: RecapTracker::new(),
+            reminders: None,
+            advisories: None,
+            deadline: None,
+        }
+    }
+
+    /// 本轮到目前为止的统计（克隆出的 hook 共享同一份）
+    pub fn turn_stats(&self) -> TurnStats {
+        self.stats.lock().map(|stats| *stats).unwrap_or_default()
+    }
+
+    /// 本轮的活动回顾（`elapsed` 和 token 用量由调用方提供）
+    pub fn turn_recap(&self, elapsed: Duration, usage: &Usage) -> TurnRecap {
+        self.recap
+            .finish(elapsed, usage.input_tokens, usage.output_tokens)
+    }
+
+    /// 设置本轮的根 span
+    pub fn with_turn_span(mut self, turn: Span) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    /// 设置系统提醒追踪器
+    pub fn with_reminders(mut self, reminders: ReminderTracker) -> Self {
+        self.reminders = Some(reminders);
+        self
+    }
+
+    /// 设置会话质量提示追踪器
+    pub fn with_advisories(mut self, advisories: AdvisoryTracker) -> Self {
+        self.advisories = Some(advisories);
+        self
+    }
+
+    /// 设置本轮的截止时间
+    pub fn with_deadline(mut self, deadline: TurnDeadline) -> Self {
+        self.deadline = Some(deadline);
+        self
     }
 }
 
 impl<M: CompletionModel> StreamingPromptHook<M> for SessionIdHook {
     async fn on_tool_call(
         &self,
-        _tool_name: &str,
-        _tool_call_id: Option<String>,
+        tool_name: &str,
+        tool_call_id: Option<String>,
         _args: &str,
-        _cancel_sig: CancelSignal,
+        cancel_sig: CancelSignal,
     ) {
+        self.recap.record_call(tool_name, tool_call_id.as_deref());
+        loop_guard::activate(&self.session_id);
+        if let Ok(mut request) = self.request.lock() {
+            if let Some(request) = request.as_mut() {
+                request.tool_calls += 1;
+            }
+        }
+        if let Some(deadline) = self.deadline.as_ref().filter(|d| d.is_expired()) {
+            deadline.mark_hit();
+            self.turn
+                .in_scope(|| telemetry::cancelled("turn time limit reached"));
+            cancel_sig.cancel();
+        }
     }
 
     async fn on_tool_result(
         &self,
-        _tool_name: &str,
-        _tool_call_id: Option<String>,
-        _args: &str,
+        tool_name: &str,
+        tool_call_id: Option<String>,
+        args: &str,
         result: &str,
         cancel_sig: CancelSignal,
     ) {
-        if result.contains("Operation cancelled by user") {
+        self.recap
+            .record_result(tool_name, tool_call_id.as_deref(), args, result);
+        if let Some(deadline) = &self.deadline {
+            deadline.record_tool_result(tool_name, args, result);
+        }
+        if let Some(advisories) = &self.advisories {
+            advisories.record_tool_result(tool_name, result);
+        }
+        if let Some(recorder) = replay::recorder() {
+            recorder.tool_result(tool_name, args, result);
+        }
+        if loop_guard::should_stop(&self.session_id) {
+            self.turn
+                .in_scope(|| telemetry::cancelled("repeated identical tool calls"));
+            cancel_sig.cancel();
+        } else if result.contains("Operation cancelled by user") {
+            self.turn.in_scope(|| telemetry::cancelled("tool call cancelled by user"));
             cancel_sig.cancel();
         }
     }
@@ -44,6 +154,19 @@ impl<M: CompletionModel> StreamingPromptHook<M> for SessionIdHook {
         _history: &[Message],
         _cancel_sig: CancelSignal,
     ) {
+        // 本次请求中的工具结果由本会话的追踪器检查（rig 的 hook 不能修改工具结果，
+        // 提醒由 MaybeHitlTool 附加）
+        if let Some(reminders) = &self.reminders {
+            reminders.activate();
+        }
+        loop_guard::activate(&self.session_id);
+        if let Ok(mut request) = self.request.lock() {
+            *request = Some(ProviderRequest {
+                span: telemetry::provider_request_span(&self.turn),
+                start: Instant::now(),
+                tool_calls: 0,
+            });
+        }
     }
 
     async fn on_text_delta(
@@ -66,8 +189,27 @@ impl<M: CompletionModel> StreamingPromptHook<M> for SessionIdHook {
     async fn on_stream_completion_response_finish(
         &self,
         _prompt: &Message,
-        _response: &M::StreamingResponse,
+        response: &M::StreamingResponse,
         _cancel_sig: CancelSignal,
     ) {
+        let Some(request) = self.request.lock().ok().and_then(|mut r| r.take()) else {
+            return;
+        };
+        let (input_tokens, output_tokens) = match response.token_usage() {
+            Some(usage) => (Some(usage.input_tokens), Some(usage.output_tokens)),
+            None => (None, None),
+        };
+        let stop_reason = StopReason::infer(request.tool_calls, output_tokens);
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.tool_calls += request.tool_calls;
+            stats.stop_reason = Some(stop_reason);
+        }
+        telemetry::record_provider_response(
+            &request.span,
+            input_tokens,
+            output_tokens,
+            request.start.elapsed(),
+            stop_reason.as_str(),
+        );
     }
 }
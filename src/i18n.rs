@@ -0,0 +1,276 @@
+//! 界面语言
+//!
+//! 面向用户的 CLI 文本放在 `locales/en.toml` 和 `locales/zh.toml` 中，编译时嵌入。
+//! 代码中用 [`t!`](crate::t) 按键取文本，键为 TOML 表的点分路径（如 `"cli.help.title"`），
+//! 文本中的 `{name}` 由 `t!("key", name = value)` 替换。
+//!
+//! 语言按 `[ui] language` 配置、`LC_ALL` / `LC_MESSAGES` / `LANG` 环境变量的顺序确定，
+//! 都没有时使用英文。当前语言缺少某个键时回退到英文并输出调试日志。
+
+use crate::config::ConfigLoader;
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Locale {
+    /// 解析语言标识，支持 `en`、`zh`、`zh-CN`、`zh_CN.UTF-8` 等形式
+    pub fn parse(value: &str) -> Option<Self> {
+        let language = value
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "zh" | "chinese" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// 按配置值和环境变量确定语言
+    ///
+    /// `env` 按 `LC_ALL`、`LC_MESSAGES`、`LANG` 的顺序查询，取第一个非空值。
+    pub fn detect(setting: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(locale) = setting.and_then(Self::parse) {
+            return locale;
+        }
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env(var).filter(|value| !value.is_empty()))
+            .next()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+}
+
+static LOCALE: OnceCell<Locale> = OnceCell::new();
+
+/// 设置本次运行的界面语言，只有第一次调用生效
+pub fn init(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+/// 读取 `[ui] language` 配置和环境变量确定界面语言
+pub fn init_from_config() {
+    let setting = ConfigLoader::new()
+        .load_toml_layers()
+        .ok()
+        .and_then(|config| config.ui)
+        .and_then(|ui| ui.language);
+    init(Locale::detect(setting.as_deref(), |var| {
+        std::env::var(var).ok()
+    }));
+}
+
+/// 当前界面语言（未初始化时按环境变量确定）
+pub fn locale() -> Locale {
+    *LOCALE.get_or_init(|| Locale::detect(None, |var| std::env::var(var).ok()))
+}
+
+static EN: Lazy<HashMap<String, String>> =
+    Lazy::new(|| load_table(include_str!("../locales/en.toml")));
+static ZH: Lazy<HashMap<String, String>> =
+    Lazy::new(|| load_table(include_str!("../locales/zh.toml")));
+
+fn table(locale: Locale) -> &'static HashMap<String, String> {
+    match locale {
+        Locale::En => &EN,
+        Locale::Zh => &ZH,
+    }
+}
+
+/// 把嵌套的 TOML 表展开为点分键
+fn load_table(source: &str) -> HashMap<String, String> {
+    fn flatten(prefix: &str, value: &toml::Value, out: &mut HashMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    flatten(&key, value, out);
+                }
+            }
+            toml::Value::String(text) => {
+                out.insert(prefix.to_string(), text.clone());
+            }
+            _ => {}
+        }
+    }
+
+    let value: toml::Value = source.parse().expect("embedded locale table is valid TOML");
+    let mut out = HashMap::new();
+    flatten("", &value, &mut out);
+    out
+}
+
+/// 按当前语言取文本，缺失时回退到英文，英文也没有时返回键本身
+pub fn translate(key: &'static str) -> &'static str {
+    translate_in(locale(), key)
+}
+
+fn translate_in(locale: Locale, key: &'static str) -> &'static str {
+    if let Some(text) = table(locale).get(key) {
+        return text;
+    }
+    if locale != Locale::En {
+        tracing::debug!(key, ?locale, "missing translation, falling back to English");
+    }
+    match EN.get(key) {
+        Some(text) => text,
+        None => {
+            tracing::debug!(key, "unknown translation key");
+            key
+        }
+    }
+}
+
+/// 替换文本中的 `{name}` 占位符
+pub fn format(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    out
+}
+
+/// 按键取当前语言的文本
+///
+/// `t!("key")` 返回 `&'static str`；`t!("key", name = value, ...)` 替换 `{name}`
+/// 占位符后返回 `String`。
+#[macro_export]
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::translate($key)
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::translate($key),
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("zh"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("zh-CN"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("zh_TW.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("C"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_detect_locale() {
+        // 配置优先于环境变量
+        assert_eq!(
+            Locale::detect(Some("en"), env(&[("LANG", "zh_CN.UTF-8")])),
+            Locale::En
+        );
+        assert_eq!(
+            Locale::detect(None, env(&[("LANG", "zh_CN.UTF-8")])),
+            Locale::Zh
+        );
+        // LC_ALL 优先于 LANG，空值被忽略
+        assert_eq!(
+            Locale::detect(None, env(&[("LC_ALL", "en_US"), ("LANG", "zh_CN")])),
+            Locale::En
+        );
+        assert_eq!(
+            Locale::detect(None, env(&[("LC_ALL", ""), ("LANG", "zh_CN")])),
+            Locale::Zh
+        );
+        // 无法识别的配置值按环境变量处理，都没有时为英文
+        assert_eq!(
+            Locale::detect(Some("klingon"), env(&[("LANG", "zh")])),
+            Locale::Zh
+        );
+        assert_eq!(Locale::detect(None, env(&[])), Locale::En);
+        assert_eq!(Locale::detect(None, env(&[("LANG", "de_DE")])), Locale::En);
+    }
+
+    #[test]
+    fn test_translate_falls_back_to_english() {
+        assert_eq!(
+            translate_in(Locale::En, "cli.help.title"),
+            EN["cli.help.title"]
+        );
+        assert_eq!(
+            translate_in(Locale::Zh, "cli.help.title"),
+            ZH["cli.help.title"]
+        );
+        assert_eq!(translate_in(Locale::Zh, "no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_format_placeholders() {
+        assert_eq!(
+            format("{count} of {total}", &[("count", &3), ("total", &"10")]),
+            "3 of 10"
+        );
+        assert_eq!(format("no placeholders", &[("x", &1)]), "no placeholders");
+    }
+
+    /// 两种语言的键和占位符必须一致，英文表中不能有中文
+    #[test]
+    fn test_locale_tables_match() {
+        let en: BTreeSet<_> = EN.keys().collect();
+        let zh: BTreeSet<_> = ZH.keys().collect();
+        assert_eq!(
+            en.difference(&zh).collect::<Vec<_>>(),
+            Vec::<&&String>::new(),
+            "keys missing from zh.toml"
+        );
+        assert_eq!(
+            zh.difference(&en).collect::<Vec<_>>(),
+            Vec::<&&String>::new(),
+            "keys missing from en.toml"
+        );
+
+        let placeholders = |text: &str| -> BTreeSet<String> {
+            text.split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}'))
+                .map(|(name, _)| name.to_string())
+                .collect()
+        };
+        for (key, text) in EN.iter() {
+            assert_eq!(
+                placeholders(text),
+                placeholders(&ZH[key]),
+                "placeholders differ for {}",
+                key
+            );
+            assert!(
+                !text.chars().any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c)),
+                "en.toml contains Chinese text for {}",
+                key
+            );
+        }
+    }
+}
@@ -1,7 +1,23 @@
+#[macro_use]
+pub mod i18n;
 pub mod agent;
+pub mod atomic_file;
+pub mod checkpoint;
 pub mod config;
 pub mod context;
+pub mod error;
+pub mod file_lock;
+pub mod hooks;
+pub mod mcp;
+pub mod migrate;
+pub mod model_info;
+pub mod permissions;
+pub mod serve;
+pub mod session;
+pub mod shell;
 pub mod skill;
 pub mod tools;
 pub mod task;
+pub mod telemetry;
 pub mod token_counter;
+pub mod usage;
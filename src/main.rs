@@ -1,27 +1,111 @@
+#[macro_use]
+mod i18n;
 mod agent;
+mod args;
+mod atomic_file;
+mod checkpoint;
 mod config;
 mod context;
+mod doctor;
+mod error;
+mod file_lock;
 mod hooks;
+mod mcp;
+mod migrate;
+mod model_info;
+mod permissions;
+mod print_mode;
+mod serve;
+// 嵌入用的构建器只在库中使用
+#[allow(dead_code)]
+mod session;
+mod shell;
 mod skill;
+mod startup;
 mod tools;
 mod task;
+mod telemetry;
 mod token_counter;
+mod update;
+mod usage;
 
 #[cfg(feature = "cli")]
 mod cli;
 
 
 use anyhow::{Context, Result};
+use args::{Command, McpCommand, PermissionsCommand, UsageCommand};
 use config::Config;
-use crate::agent::AgentBuilder;
+use crate::agent::{AgentBuilder, PromptMode};
 use crate::cli::OxideCli;
-use crate::context::ContextManager;
-use crate::agent::HitlIntegration;
-use crate::skill::SkillManager;
+use crate::cli::stream_pacer::StreamPacing;
+use crate::context::{ContextManager, SessionEnvironment};
+use crate::config::{ConfigLoader, LimitsConfig, ModeConfig};
+use crate::mcp::McpManager;
+use crate::agent::replay::{self, Recorder, ReplayProvider};
+use crate::agent::structured_output::OutputSchema;
+use crate::agent::{HitlIntegration, PermissionPolicy};
+use crate::startup::{StartupProfile, Warmup};
+use crate::tools::network::NetworkPolicy;
+use crate::tools::{ToolSettings, WorkspaceRoots};
 use std::sync::Arc;
-use names::Generator;
+use std::time::{Duration, Instant};
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let started = Instant::now();
+    i18n::init_from_config();
+    update::cleanup_previous_update();
+    args::handle_completion_request();
+    let args = args::parse();
+    #[cfg(feature = "cli")]
+    cli::output::init(args.no_color);
+    let profile = StartupProfile::new(started, args.profile_startup);
+
+    // Subcommands that don't need API credentials
+    match args.command {
+        Some(Command::Mcp { command: McpCommand::Serve { allow_write } }) => {
+            return mcp::serve_stdio(allow_write).await;
+        }
+        Some(Command::Completions { shell }) => {
+            args::write_completions(shell, &mut std::io::stdout())?;
+            return Ok(());
+        }
+        Some(Command::Usage { command, by, since, json }) => {
+            return match command {
+                Some(UsageCommand::Compact { keep_days }) => usage::run_compact(keep_days),
+                None => usage::run_report(&usage::UsageQuery { group_by: by.into(), since, json }),
+            };
+        }
+        Some(Command::Permissions { command }) => {
+            return match command {
+                PermissionsCommand::Export => {
+                    print!("{}", permissions::export_rules()?);
+                    Ok(())
+                }
+                PermissionsCommand::Import { file, yes } => permissions::run_import(&file, yes),
+            };
+        }
+        Some(Command::Migrate { dry_run }) => {
+            return migrate::run_command(dry_run);
+        }
+        Some(Command::Update { channel, check }) => {
+            return update::run(channel.map(Into::into), check).await;
+        }
+        Some(Command::Doctor) => {
+            let checks = doctor::run_checks().await;
+            doctor::print_report(&checks);
+            if doctor::has_failures(&checks) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Serve { .. }) | None => {}
+    }
+
+    // 升级 .oxide/ 中的旧数据；数据比当前版本新时拒绝启动
+    migrate::run_startup()?;
+
     // Load config
     let config = Config::load().context("Failed to load configuration")?;
 
@@ -31,47 +115,203 @@ async fn main() -> Result<()> {
         eprintln!("Tip: Or create .env file in project root");
         std::process::exit(1);
     }
+    profile.phase("config");
 
     // Initialize HITL
-    let hitl = Arc::new(HitlIntegration::new()?);
+    let mut hitl = HitlIntegration::new()?;
+    if args.print.is_some() {
+        // 非交互运行时不在终端提问，需要确认的操作一律拒绝
+        hitl = hitl.with_prompter(Arc::new(PermissionPolicy::RejectAll));
+        // stdout 只留给回答，工具调用的进度用 --stream-json 查看
+        tools::verbose_output::verbose_tools().hide();
+    }
+    let hitl = Arc::new(hitl);
+    let output_schema = args
+        .output_schema
+        .as_deref()
+        .map(OutputSchema::load)
+        .transpose()?;
+
+    // Start MCP servers
+    let mcp_servers = ConfigLoader::new()
+        .load_toml_layers()
+        .map(|toml| toml.mcp_servers)
+        .unwrap_or_default();
+    let mcp = Arc::new(McpManager::from_config(&mcp_servers));
+
+    if let Some(Command::Serve { port }) = args.command {
+        for (name, error) in mcp.start_all().await {
+            eprintln!("Warning: MCP server '{}' failed to start: {}", name, error);
+        }
+        let options = serve::ServeOptions {
+            base_url: config.base_url.clone(),
+            auth_token: config.auth_token.clone(),
+            model: config.model.clone(),
+            sessions_dir: std::path::PathBuf::from(args::SESSIONS_DIR),
+            mcp: Some(mcp.clone()),
+        };
+        let result = tokio::select! {
+            result = serve::run(options, port) => result,
+            _ = tokio::signal::ctrl_c() => Ok(()),
+        };
+        mcp.shutdown().await;
+        return result;
+    }
+
+    // MCP 服务器和技能在后台启动，REPL 在第一条消息前等待
+    let warmup = Warmup::spawn(mcp.clone(), profile.clone());
 
     // Create Agent using AgentBuilder
-    let builder = AgentBuilder::new(
+    // MCP 工具在预热完成后重建 Agent 时加入
+    let mut tool_settings = ToolSettings::load().context("Invalid [tools] configuration")?;
+    if args.offline_tools {
+        tool_settings.network = NetworkPolicy::offline();
+    }
+    let workspace = match &tool_settings.root {
+        Some(root) => root.clone(),
+        None => std::env::current_dir().context("Failed to read the current directory")?,
+    };
+    tool_settings.roots = WorkspaceRoots::new(&workspace);
+    for dir in &args.add_dir {
+        tool_settings
+            .roots
+            .add(dir)
+            .with_context(|| format!("Invalid --add-dir {}", dir.display()))?;
+    }
+    #[cfg(feature = "cli")]
+    let workspace_roots = tool_settings.roots.clone();
+    // 对话模式：--mode 优先，其次是项目配置中保存的默认模式
+    let mode = match args.mode {
+        Some(mode) => mode,
+        None => match ModeConfig::load().default.as_deref().map(str::parse::<PromptMode>) {
+            Some(Ok(mode)) => mode,
+            Some(Err(e)) => {
+                eprintln!("Warning: Invalid [mode] default: {}", e);
+                PromptMode::default()
+            }
+            None => PromptMode::default(),
+        },
+    };
+    let mut builder = AgentBuilder::new(
         config.base_url.clone(),
         config.auth_token.clone(),
         config.model.clone(),
-    ).with_hitl(hitl.clone());
+    )
+    .with_hitl(hitl.clone())
+    .with_mcp(mcp.clone())
+    .with_tool_settings(tool_settings)
+    .with_mode(mode);
+    if let Some(schema) = &output_schema {
+        builder = builder.with_output_schema(schema.schema().clone());
+    }
+
+    if let Some(prompt) = &args.print {
+        // MCP 工具要在服务器启动后构建 Agent 时才能加入
+        for warning in warmup.wait().await {
+            eprintln!("{}", warning);
+        }
+        let agent = builder.build_main().context("Failed to create agent")?;
+        start_replay_mode(&args, &builder, agent.provider())?;
+        if args.stream_json {
+            crate::agent::tool_status::register_sink(Arc::new(
+                crate::agent::tool_status::StreamJsonSink::new(std::io::stderr()),
+            ));
+        }
+        let model = config
+            .model
+            .clone()
+            .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
+        let result = match print_mode::PrintRun::new(agent, model) {
+            Ok(run) => run.run(prompt, output_schema.as_ref()).await,
+            Err(e) => Err(e),
+        };
+        mcp.shutdown().await;
+        return result;
+    }
     
     let agent = builder.build_main().context("Failed to create agent")?;
+    start_replay_mode(&args, &builder, agent.provider())?;
+    profile.phase("agent");
 
     #[cfg(feature = "cli")]
     {
         // Generate session ID
-        let session_id = {
-            let mut generator = Generator::default();
-            generator.next().unwrap_or_else(|| "unknown-session".to_string())
-        };
+        let storage_dir = std::path::PathBuf::from(args::SESSIONS_DIR);
+        let session_id = args
+            .resume
+            .clone()
+            .unwrap_or_else(|| ContextManager::generate_session_id(&storage_dir));
 
-        // Create ContextManager
-        let storage_dir = std::path::PathBuf::from(".oxide/sessions");
-        let context_manager = ContextManager::new(storage_dir, session_id)?;
+        // Tracing (OTLP or debug trace file) and file logging
+        let behavior = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|toml| toml.behavior)
+            .unwrap_or_default();
+        let log_settings = telemetry::LogSettings::from_config(&behavior).unwrap_or_else(|e| {
+            eprintln!("Warning: {:#}", e);
+            None
+        });
+        let _telemetry = telemetry::init(config.debug, &session_id, log_settings.as_ref())
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to initialize tracing: {:#}", e);
+                None
+            });
+        profile.phase("telemetry");
 
-        // Initialize SkillManager
-        let skill_manager = SkillManager::new()?;
-        skill_manager.init()?;
+        // Create ContextManager
+        let model = config.model.unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let environment = SessionEnvironment::capture(&cwd, agent.provider(), &model, "repl");
+        let mut context_manager = ContextManager::new(storage_dir, session_id)?
+            .with_environment(environment)
+            .with_dedupe_file_reads(behavior.dedupe_file_reads);
+        if let Some(session_id) = &args.resume {
+            if !context_manager.load()? {
+                eprintln!("Warning: Session '{}' not found, starting a new session", session_id);
+            }
+        }
+        profile.phase("session");
 
         // Initialize and run CLI
-        let mut cli = OxideCli::new(
-            config.auth_token,
-            config.model.unwrap_or_else(|| "claude-sonnet-4-20250514".to_string()),
-            agent,
-            context_manager,
-            hitl,
-        );
+        telemetry::session_started(context_manager.session_id(), &model);
+        if let Some(notice) = update::startup_check(&update::load_config()) {
+            println!("{}", notice);
+        }
+        let stream_pacing = StreamPacing::new(config.stream_animation, config.stream_chars_per_tick);
+        let ui = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|toml| toml.ui)
+            .unwrap_or_default();
+        let max_turn_seconds = args
+            .max_turn_time
+            .or(LimitsConfig::load().max_turn_seconds)
+            .filter(|secs| *secs > 0);
+        let template = args
+            .template
+            .as_deref()
+            .map(|name| cli::templates::TemplateLoader::new().find(name))
+            .transpose()?;
+        let session = session::OxideSession::from_parts(agent, context_manager, model.clone());
+        let mut cli = OxideCli::new(config.auth_token, model, session, hitl)
+        .with_stream_pacing(stream_pacing)
+        .with_transient_prompt(ui.transient_prompt)
+        .with_status_bar(ui.status_bar)
+        .with_mcp(mcp.clone())
+        .with_agent_builder(builder)
+        .with_mode(mode)
+        .with_workspace_roots(workspace_roots)
+        .with_turn_time_limit(max_turn_seconds.map(Duration::from_secs))
+        .with_template(template)
+        .with_warmup(warmup, profile.clone());
+        profile.phase("repl setup");
 
         cli.run().await?;
     }
 
+    mcp.shutdown().await;
+
     #[cfg(not(feature = "cli"))]
     {
         println!("Please run with --features cli");
@@ -79,3 +319,20 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// 按 `--record` / `--replay` 开始录制或进入回放模式
+fn start_replay_mode(args: &args::Args, builder: &AgentBuilder, provider: &str) -> Result<()> {
+    if let Some(path) = &args.record {
+        let recorder = Recorder::create(path, provider)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        replay::start_recording(recorder);
+    }
+    if let Some(path) = &args.replay {
+        let mut replay_provider = ReplayProvider::load(path)?;
+        if !args.replay_tools {
+            replay_provider = replay_provider.with_tools(builder.main_tools());
+        }
+        replay::start_replay(replay_provider);
+    }
+    Ok(())
+}
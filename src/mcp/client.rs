@@ -0,0 +1,330 @@
+//! MCP stdio 客户端
+//!
+//! 通过子进程的 stdin/stdout 以换行分隔的 JSON-RPC 2.0 消息与 MCP 服务器通信。
+//! 请求按顺序发送，读取响应时跳过服务器发来的通知。
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use crate::config::McpServerConfig;
+use crate::tools::image::ImageInfo;
+
+/// 客户端支持的协议版本
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// 默认请求超时
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// JSON-RPC: 方法不存在
+const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Error)]
+pub enum McpError {
+    #[error("Failed to start MCP server '{0}': {1}")]
+    Spawn(String, #[source] std::io::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JSON from MCP server: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("MCP server closed the connection")]
+    Closed,
+    #[error("MCP request '{0}' timed out")]
+    Timeout(String),
+    #[error("MCP error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("MCP tool returned an error: {0}")]
+    ToolFailed(String),
+    #[error("MCP server '{0}' is disabled")]
+    Disabled(String),
+}
+
+impl McpError {
+    /// 是否为连接层面的错误（服务器崩溃、退出或消息流已错位），此时连接不可再用
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, Self::Io(_) | Self::Json(_) | Self::Closed | Self::Timeout(_))
+    }
+}
+
+/// `tools/call` 的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct McpToolOutput {
+    /// 返回给模型的文本
+    pub text: String,
+    /// 结果中的图片（已写入临时目录）
+    pub images: Vec<ImageInfo>,
+}
+
+/// MCP 服务器提供的工具
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", default = "empty_schema")]
+    pub input_schema: Value,
+}
+
+fn empty_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// 与单个 MCP 服务器的连接
+pub struct McpClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    timeout: Duration,
+}
+
+impl McpClient {
+    /// 启动服务器进程并完成握手
+    pub async fn connect(
+        name: &str,
+        config: &McpServerConfig,
+        timeout: Duration,
+    ) -> Result<Self, McpError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| McpError::Spawn(name.to_string(), e))?;
+
+        let stdin = child.stdin.take().ok_or(McpError::Closed)?;
+        let stdout = child.stdout.take().ok_or(McpError::Closed)?;
+
+        let mut client = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 1,
+            timeout,
+        };
+        client.initialize().await?;
+        Ok(client)
+    }
+
+    async fn initialize(&mut self) -> Result<(), McpError> {
+        self.request(
+            "initialize",
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": {
+                    "name": "oxide",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+        )
+        .await?;
+        self.notify("notifications/initialized", json!({})).await
+    }
+
+    /// 列出服务器的全部工具（处理分页）
+    pub async fn list_tools(&mut self) -> Result<Vec<McpToolInfo>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+
+            if let Some(page) = result.get("tools") {
+                tools.extend(serde_json::from_value::<Vec<McpToolInfo>>(page.clone())?);
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// 调用工具，返回转换为文本的结果
+    pub async fn call_tool(
+        &mut self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<McpToolOutput, McpError> {
+        let result = self
+            .request("tools/call", json!({ "name": name, "arguments": arguments }))
+            .await?;
+
+        let output = parse_tool_output(&result);
+        if result.get("isError").and_then(Value::as_bool).unwrap_or(false) {
+            return Err(McpError::ToolFailed(output.text));
+        }
+        Ok(output)
+    }
+
+    /// 服务器进程是否仍在运行
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// 关闭服务器进程
+    pub async fn shutdown(mut self) {
+        let _ = self.child.start_kill();
+        let _ = self.child.wait().await;
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> Result<(), McpError> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+
+        match tokio::time::timeout(self.timeout, self.read_response(id)).await {
+            Ok(result) => result,
+            Err(_) => Err(McpError::Timeout(method.to_string())),
+        }
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), McpError> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn read_response(&mut self, id: u64) -> Result<Value, McpError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line).await? == 0 {
+                return Err(McpError::Closed);
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let message: Value = serde_json::from_str(trimmed)?;
+
+            // 服务器发起的请求：客户端不支持任何方法
+            if let (Some(request_id), Some(_)) = (message.get("id"), message.get("method")) {
+                let reply = json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id,
+                    "error": { "code": METHOD_NOT_FOUND, "message": "Method not found" },
+                });
+                self.send(&reply).await?;
+                continue;
+            }
+
+            // 通知或其它请求的响应
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+
+            if let Some(error) = message.get("error") {
+                return Err(McpError::Rpc {
+                    code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                    message: error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error")
+                        .to_string(),
+                });
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+}
+
+/// 将 `tools/call` 结果的 content 列表转换为文本
+///
+/// 文本块直接拼接；图片写入临时目录，文本中只保留格式、尺寸和路径；
+/// 其它类型（资源等）和无法解码的图片保留为 JSON。
+fn parse_tool_output(result: &Value) -> McpToolOutput {
+    let Some(items) = result.get("content").and_then(Value::as_array) else {
+        return McpToolOutput {
+            text: result.to_string(),
+            images: Vec::new(),
+        };
+    };
+
+    let mut images = Vec::new();
+    let text = items
+        .iter()
+        .map(|item| match item.get("type").and_then(Value::as_str) {
+            Some("text") => item
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            Some("image") => match save_image(item) {
+                Some(image) => {
+                    let line = format!("[image: {}, saved to {}]", image.describe(), image.path);
+                    images.push(image);
+                    line
+                }
+                None => item.to_string(),
+            },
+            _ => item.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    McpToolOutput { text, images }
+}
+
+/// 解码图片块（base64 的 `data` 和 `mimeType`）并写入临时目录
+fn save_image(item: &Value) -> Option<ImageInfo> {
+    let data = item.get("data").and_then(Value::as_str)?;
+    let mime_type = item.get("mimeType").and_then(Value::as_str).unwrap_or("image/png");
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data).ok()?;
+    ImageInfo::save(&bytes, mime_type).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_output() {
+        let result = json!({
+            "content": [
+                { "type": "text", "text": "first" },
+                { "type": "image", "data": "aGk=", "mimeType": "image/png" },
+                { "type": "text", "text": "second" },
+            ]
+        });
+        let output = parse_tool_output(&result);
+        let lines: Vec<&str> = output.text.lines().collect();
+        assert_eq!(lines[0], "first");
+        assert!(lines[1].contains("image/png"));
+        assert!(!lines[1].contains("aGk="));
+        assert_eq!(lines[2], "second");
+        assert_eq!(output.images.len(), 1);
+        assert_eq!(std::fs::read(&output.images[0].path).unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_tool_info_defaults_schema() {
+        let info: McpToolInfo = serde_json::from_value(json!({ "name": "ping" })).unwrap();
+        assert_eq!(info.description, None);
+        assert_eq!(info.input_schema["type"], "object");
+    }
+}
@@ -0,0 +1,247 @@
+//! MCP 服务器生命周期管理
+//!
+//! 启动时连接所有启用的服务器并获取工具列表；调用工具前发现进程已退出会自动重启，
+//! 调用中连接断开则丢弃连接，下次调用时重启。禁用的服务器会被关闭，其工具调用直接返回错误。
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::client::{McpClient, McpError, McpToolInfo, McpToolOutput, DEFAULT_REQUEST_TIMEOUT};
+use super::tool::McpTool;
+use crate::agent::HitlIntegration;
+use crate::config::McpServerConfig;
+
+/// 单个 MCP 服务器
+pub struct McpServer {
+    name: String,
+    config: McpServerConfig,
+    timeout: Duration,
+    client: Mutex<Option<McpClient>>,
+    tools: RwLock<Vec<McpToolInfo>>,
+    enabled: AtomicBool,
+    last_error: RwLock<Option<String>>,
+}
+
+/// 服务器状态（用于 `/mcp list`）
+#[derive(Debug, Clone)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub tools: Vec<String>,
+    pub last_error: Option<String>,
+}
+
+impl McpServer {
+    pub fn new(name: impl Into<String>, config: McpServerConfig) -> Self {
+        let enabled = config.enabled;
+        Self {
+            name: name.into(),
+            config,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            client: Mutex::new(None),
+            tools: RwLock::new(Vec::new()),
+            enabled: AtomicBool::new(enabled),
+            last_error: RwLock::new(None),
+        }
+    }
+
+    /// 设置请求超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// 已发现的工具
+    pub fn tools(&self) -> Vec<McpToolInfo> {
+        self.tools.read().map(|tools| tools.clone()).unwrap_or_default()
+    }
+
+    /// 启动服务器（已在运行时不重复启动）并刷新工具列表
+    pub async fn start(&self) -> Result<(), McpError> {
+        let mut guard = self.client.lock().await;
+        let result = self.ensure_connected(&mut guard).await;
+        let result = match result {
+            Ok(client) => client.list_tools().await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(tools) => {
+                if let Ok(mut guard) = self.tools.write() {
+                    *guard = tools;
+                }
+                self.set_last_error(None);
+                Ok(())
+            }
+            Err(e) => {
+                if e.is_connection_error() {
+                    *guard = None;
+                }
+                self.set_last_error(Some(e.to_string()));
+                Err(e)
+            }
+        }
+    }
+
+    /// 关闭服务器进程
+    pub async fn stop(&self) {
+        if let Some(client) = self.client.lock().await.take() {
+            client.shutdown().await;
+        }
+    }
+
+    /// 启用服务器并启动
+    pub async fn enable(&self) -> Result<(), McpError> {
+        self.enabled.store(true, Ordering::Release);
+        self.start().await
+    }
+
+    /// 禁用服务器并关闭进程
+    pub async fn disable(&self) {
+        self.enabled.store(false, Ordering::Release);
+        self.stop().await;
+    }
+
+    /// 调用工具
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<McpToolOutput, McpError> {
+        if !self.is_enabled() {
+            return Err(McpError::Disabled(self.name.clone()));
+        }
+
+        let mut guard = self.client.lock().await;
+        let client = self.ensure_connected(&mut guard).await?;
+        let result = client.call_tool(tool, arguments).await;
+
+        // 连接已不可用：丢弃连接，下次调用时重启
+        if let Err(e) = &result {
+            if e.is_connection_error() {
+                *guard = None;
+                self.set_last_error(Some(e.to_string()));
+            }
+        }
+        result
+    }
+
+    /// 服务器状态
+    pub async fn status(&self) -> McpServerStatus {
+        let running = match self.client.lock().await.as_mut() {
+            Some(client) => client.is_running(),
+            None => false,
+        };
+        McpServerStatus {
+            name: self.name.clone(),
+            enabled: self.is_enabled(),
+            running,
+            tools: self.tools().into_iter().map(|t| t.name).collect(),
+            last_error: self.last_error.read().ok().and_then(|e| e.clone()),
+        }
+    }
+
+    /// 确保连接可用：未启动或进程已退出时（重新）启动
+    async fn ensure_connected<'a>(
+        &self,
+        guard: &'a mut Option<McpClient>,
+    ) -> Result<&'a mut McpClient, McpError> {
+        let alive = guard.as_mut().is_some_and(|client| client.is_running());
+        if !alive {
+            *guard = Some(McpClient::connect(&self.name, &self.config, self.timeout).await?);
+        }
+        guard.as_mut().ok_or(McpError::Closed)
+    }
+
+    fn set_last_error(&self, error: Option<String>) {
+        if let Ok(mut guard) = self.last_error.write() {
+            *guard = error;
+        }
+    }
+}
+
+/// MCP 服务器管理器
+#[derive(Default)]
+pub struct McpManager {
+    servers: Vec<Arc<McpServer>>,
+}
+
+impl McpManager {
+    /// 根据 `[mcp_servers]` 配置创建（不会立即启动）
+    pub fn from_config(servers: &BTreeMap<String, McpServerConfig>) -> Self {
+        Self {
+            servers: servers
+                .iter()
+                .map(|(name, config)| Arc::new(McpServer::new(name.clone(), config.clone())))
+                .collect(),
+        }
+    }
+
+    /// 使用已创建的服务器
+    pub fn with_servers(servers: Vec<McpServer>) -> Self {
+        Self {
+            servers: servers.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// 并发启动所有启用的服务器，返回启动失败的服务器及原因
+    pub async fn start_all(&self) -> Vec<(String, McpError)> {
+        let starts = self
+            .servers
+            .iter()
+            .filter(|server| server.is_enabled())
+            .map(|server| async move { (server.name.clone(), server.start().await) });
+
+        futures::future::join_all(starts)
+            .await
+            .into_iter()
+            .filter_map(|(name, result)| result.err().map(|e| (name, e)))
+            .collect()
+    }
+
+    /// 关闭所有服务器
+    pub async fn shutdown(&self) {
+        futures::future::join_all(self.servers.iter().map(|server| server.stop())).await;
+    }
+
+    /// 按名称查找服务器
+    pub fn get(&self, name: &str) -> Option<Arc<McpServer>> {
+        self.servers.iter().find(|s| s.name == name).cloned()
+    }
+
+    /// 所有服务器的状态
+    pub async fn status(&self) -> Vec<McpServerStatus> {
+        let mut status = Vec::with_capacity(self.servers.len());
+        for server in &self.servers {
+            status.push(server.status().await);
+        }
+        status
+    }
+
+    /// 把所有已发现的 MCP 工具包装为 Agent 工具
+    pub fn agent_tools(&self, hitl: Option<Arc<HitlIntegration>>) -> Vec<McpTool> {
+        self.servers
+            .iter()
+            .flat_map(|server| {
+                let hitl = hitl.clone();
+                server
+                    .tools()
+                    .into_iter()
+                    .map(move |info| McpTool::new(Arc::clone(server), info, hitl.clone()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,15 @@
+//! MCP（Model Context Protocol）支持
+//!
+//! - 客户端：从 `[mcp_servers.<name>]` 配置启动外部 MCP 服务器（stdio 传输），
+//!   将其工具以 `mcp__<server>__<tool>` 的名字注册给 Agent
+//! - 服务器：`oxide mcp serve` 通过 stdio 暴露 oxide 自带的工具
+
+pub mod client;
+pub mod manager;
+pub mod server;
+pub mod tool;
+
+pub use client::{McpClient, McpError, McpToolInfo, McpToolOutput};
+pub use manager::{McpManager, McpServer, McpServerStatus};
+pub use server::{serve_stdio, serve_tools, McpToolServer};
+pub use tool::{namespaced_tool_name, McpTool, MCP_TOOL_PREFIX};
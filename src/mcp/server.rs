@@ -0,0 +1,336 @@
+//! MCP 服务器（`oxide mcp serve`）
+//!
+//! 通过 stdio 把 oxide 自带的文件/搜索/命令工具暴露给其它 Agent 或编辑器。
+//! 默认只提供只读工具，`--allow-write` 时提供完整工具集。每次调用都先经过
+//! HITL Gatekeeper 评估：stdio 被协议占用无法交互确认，因此需要确认的操作
+//! 只在 `--allow-write`（调用方已被授权修改）时执行，被拒绝的操作始终拒绝。
+
+use anyhow::Result;
+use rig::tool::ToolDyn;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use super::client::PROTOCOL_VERSION;
+use crate::agent::{HitlConfig, HitlDecision, HitlGatekeeper, OperationContext, ToolCallRequest};
+use crate::tools::apply_patch::ApplyPatchTool;
+use crate::tools::create_directory::CreateDirectoryTool;
+use crate::tools::delete_file::DeleteFileTool;
+use crate::tools::deps::DepsTool;
+use crate::tools::diff::DiffTool;
+use crate::tools::diagnostics::DiagnosticsTool;
+use crate::tools::edit_file::EditFileTool;
+use crate::tools::glob::GlobTool;
+use crate::tools::grep_search::GrepSearchTool;
+use crate::tools::http_request::HttpRequestTool;
+use crate::tools::network;
+use crate::tools::outline::OutlineTool;
+use crate::tools::read_file::ReadFileTool;
+use crate::tools::run_tests::RunTestsTool;
+use crate::tools::scan_codebase::ScanCodebaseTool;
+use crate::tools::search_replace::SearchReplaceTool;
+use crate::tools::settings::ToolSettings;
+use crate::tools::shell_execute::ShellExecuteTool;
+use crate::tools::tool_args::LenientArgs;
+use crate::tools::write_file::WriteFileTool;
+
+/// JSON-RPC 错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// 服务器暴露的工具
+///
+/// 使用不带终端输出的内部工具实现，避免污染 stdout 上的协议消息；
+/// 参数按工具的 schema 解析（见 [`LenientArgs`]）。
+pub fn serve_tools(allow_write: bool, settings: &ToolSettings) -> Vec<Box<dyn ToolDyn>> {
+    let mut tools: Vec<Box<dyn ToolDyn>> = vec![
+        Box::new(LenientArgs::new(ReadFileTool::new(settings))),
+        Box::new(LenientArgs::new(GrepSearchTool::new(settings))),
+        Box::new(LenientArgs::new(GlobTool::new(settings))),
+        Box::new(LenientArgs::new(ScanCodebaseTool)),
+        Box::new(LenientArgs::new(OutlineTool::new(settings))),
+        Box::new(LenientArgs::new(DiffTool::new(settings))),
+        Box::new(LenientArgs::new(DepsTool)),
+    ];
+    if allow_write {
+        tools.push(Box::new(LenientArgs::new(WriteFileTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(EditFileTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(ApplyPatchTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(SearchReplaceTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(DeleteFileTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(CreateDirectoryTool::new(
+            settings,
+        ))));
+        tools.push(Box::new(LenientArgs::new(ShellExecuteTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(RunTestsTool::new(settings))));
+        tools.push(Box::new(LenientArgs::new(DiagnosticsTool::new(settings))));
+        if settings.network.tools_enabled() {
+            tools.push(Box::new(LenientArgs::new(HttpRequestTool)));
+        }
+    }
+    tools
+}
+
+/// MCP 工具服务器
+pub struct McpToolServer {
+    tools: Vec<Box<dyn ToolDyn>>,
+    gatekeeper: HitlGatekeeper,
+    allow_write: bool,
+}
+
+impl McpToolServer {
+    pub fn new(tools: Vec<Box<dyn ToolDyn>>, allow_write: bool) -> Result<Self> {
+        Ok(Self {
+            tools,
+            gatekeeper: HitlGatekeeper::new(HitlConfig::default())?,
+            allow_write,
+        })
+    }
+
+    /// 逐行读取请求并写回响应，直到输入结束
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(message).await,
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+
+            if let Some(response) = response {
+                let mut out = serde_json::to_string(&response)?;
+                out.push('\n');
+                writer.write_all(out.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 处理单条消息，通知返回 None
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id, INVALID_REQUEST, "missing method"));
+        };
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools().await),
+            "tools/call" => self.call_tool(&params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let protocol_version = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or(PROTOCOL_VERSION);
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "oxide", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    async fn list_tools(&self) -> Value {
+        let mut tools = Vec::with_capacity(self.tools.len());
+        for tool in &self.tools {
+            let definition = tool.definition(String::new()).await;
+            tools.push(json!({
+                "name": definition.name,
+                "description": definition.description,
+                "inputSchema": definition.parameters,
+            }));
+        }
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {}", name)))?;
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        if let Err(reason) = self.check_permission(name, &arguments).await {
+            return Ok(tool_result(&reason, true));
+        }
+
+        Ok(match tool.call(arguments.to_string()).await {
+            Ok(output) => tool_result(&output, false),
+            Err(e) => tool_result(&e.to_string(), true),
+        })
+    }
+
+    /// 通过 Gatekeeper 评估调用（无法交互确认）
+    async fn check_permission(&self, name: &str, arguments: &Value) -> Result<(), String> {
+        let request = ToolCallRequest {
+            tool_name: name.to_string(),
+            args: arguments.clone(),
+            context: OperationContext {
+                recent_operations: Vec::new(),
+                current_task: None,
+                has_git: std::path::Path::new(".git").exists(),
+                git_branch: None,
+            },
+        };
+
+        let decision = self
+            .gatekeeper
+            .evaluate_tool_call(request)
+            .await
+            .map_err(|e| format!("Permission check failed: {}", e))?;
+
+        match decision {
+            HitlDecision::ExecuteDirectly { .. } => Ok(()),
+            HitlDecision::RequireConfirmation { .. } | HitlDecision::RequireChoice { .. }
+                if self.allow_write =>
+            {
+                Ok(())
+            }
+            HitlDecision::RequireConfirmation { reason, .. } => Err(format!(
+                "Permission denied: {} (restart the server with --allow-write to permit)",
+                reason
+            )),
+            HitlDecision::RequireChoice { question, .. } => Err(format!(
+                "Permission denied: {} (restart the server with --allow-write to permit)",
+                question
+            )),
+            HitlDecision::Reject { reason, suggestion } => Err(match suggestion {
+                Some(suggestion) => format!("Permission denied: {}. {}", reason, suggestion),
+                None => format!("Permission denied: {}", reason),
+            }),
+        }
+    }
+}
+
+fn tool_result(text: &str, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// 在 stdio 上运行 MCP 服务器（`oxide mcp serve [--allow-write]`）
+pub async fn serve_stdio(allow_write: bool) -> Result<()> {
+    let settings = ToolSettings::load()?;
+    network::configure(settings.network.clone());
+    let server = McpToolServer::new(serve_tools(allow_write, &settings), allow_write)?;
+    server
+        .serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// 模拟 MCP 客户端：发送一组请求，返回所有响应
+    async fn drive(server: &McpToolServer, requests: &[Value]) -> Vec<Value> {
+        let input: String = requests.iter().map(|r| format!("{}\n", r)).collect();
+        let mut output = Vec::new();
+        server
+            .serve(BufReader::new(input.as_bytes()), &mut output)
+            .await
+            .unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_initialize_list_and_call() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("notes.txt");
+        fs::write(&file, "hello from oxide").unwrap();
+
+        let server =
+            McpToolServer::new(serve_tools(false, &ToolSettings::default()), false).unwrap();
+        let responses = drive(
+            &server,
+            &[
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                    "protocolVersion": PROTOCOL_VERSION, "capabilities": {},
+                    "clientInfo": { "name": "test", "version": "0" } } }),
+                json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+                json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+                json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {
+                    "name": "read_file", "arguments": { "file_path": file.to_string_lossy() } } }),
+                json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {
+                    "name": "write_file", "arguments": { "file_path": "x", "content": "y" } } }),
+                json!({ "jsonrpc": "2.0", "id": 5, "method": "unknown" }),
+            ],
+        )
+        .await;
+
+        // 通知没有响应
+        assert_eq!(responses.len(), 5);
+        assert_eq!(responses[0]["result"]["serverInfo"]["name"], "oxide");
+
+        let names: Vec<&str> = responses[1]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["read_file", "grep_search", "glob", "scan_codebase", "outline", "deps"]);
+        assert_eq!(responses[1]["result"]["tools"][0]["inputSchema"]["type"], "object");
+
+        assert_eq!(responses[2]["result"]["isError"], false);
+        let text = responses[2]["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("hello from oxide"));
+
+        // 只读模式下没有写工具
+        assert_eq!(responses[3]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[4]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_allow_write_still_rejects_dangerous_commands() {
+        let server = McpToolServer::new(serve_tools(true, &ToolSettings::default()), true).unwrap();
+        let responses = drive(
+            &server,
+            &[json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {
+                "name": "shell_execute", "arguments": { "command": "rm -rf /" } } })],
+        )
+        .await;
+
+        assert_eq!(responses[0]["result"]["isError"], true);
+        let text = responses[0]["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("Permission denied"));
+    }
+}
@@ -0,0 +1,179 @@
+//! 将 MCP 工具包装为 rig 工具
+//!
+//! 工具名使用 `mcp__<server>__<tool>` 命名空间，参数的 JSON Schema 原样传给模型。
+//! 调用前经过 HITL 权限确认，并发出工具状态事件和 tracing span；
+//! 结果与内置工具一样在返回给模型之前脱敏。
+
+use futures::future::BoxFuture;
+use rig::completion::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
+
+use super::client::McpToolInfo;
+use super::manager::McpServer;
+use crate::agent::hitl_integration::redact_result;
+use crate::agent::tool_status;
+use crate::agent::{HitlIntegration, HitlResult, OperationContext, ToolCallRequest};
+use crate::telemetry;
+use crate::tools::FileToolError;
+
+/// MCP 工具名前缀
+pub const MCP_TOOL_PREFIX: &str = "mcp__";
+
+/// 生成命名空间工具名（非法字符替换为 `_`）
+pub fn namespaced_tool_name(server: &str, tool: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect()
+    };
+    format!("{}{}__{}", MCP_TOOL_PREFIX, sanitize(server), sanitize(tool))
+}
+
+/// 单个 MCP 工具
+pub struct McpTool {
+    server: Arc<McpServer>,
+    info: McpToolInfo,
+    name: String,
+    hitl: Option<Arc<HitlIntegration>>,
+}
+
+impl McpTool {
+    pub fn new(server: Arc<McpServer>, info: McpToolInfo, hitl: Option<Arc<HitlIntegration>>) -> Self {
+        let name = namespaced_tool_name(server.name(), &info.name);
+        Self {
+            server,
+            info,
+            name,
+            hitl,
+        }
+    }
+
+    /// 参数 Schema（确保顶层为 object）
+    fn parameters(&self) -> Value {
+        match &self.info.input_schema {
+            Value::Object(schema) if schema.get("type").and_then(Value::as_str) == Some("object") => {
+                self.info.input_schema.clone()
+            }
+            Value::Object(schema) => {
+                let mut schema = schema.clone();
+                schema.insert("type".to_string(), json!("object"));
+                Value::Object(schema)
+            }
+            _ => json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn confirm(&self, call_id: u64, args: &Value) -> Result<(), ToolError> {
+        let Some(hitl) = &self.hitl else {
+            return Ok(());
+        };
+
+        let request = ToolCallRequest {
+            tool_name: self.name.clone(),
+            args: args.clone(),
+            context: OperationContext {
+                recent_operations: Vec::new(),
+                current_task: None,
+                has_git: std::path::Path::new(".git").exists(),
+                git_branch: None,
+            },
+        };
+
+        let evaluated = hitl
+            .evaluate_with_outcome(request)
+            .await
+            .map(|(result, outcome)| {
+                tool_status::emit_permission(call_id, &self.name, args, outcome);
+                result
+            });
+        match evaluated {
+            Ok(HitlResult::Approved) => Ok(()),
+            Ok(_) => Err(ToolError::ToolCallError(Box::new(FileToolError::Cancelled))),
+            Err(e) => Err(ToolError::ToolCallError(e.to_string().into())),
+        }
+    }
+
+    async fn run(&self, args: String) -> Result<String, ToolError> {
+        let args: Value = if args.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&args)?
+        };
+
+        let call_id = tool_status::next_call_id();
+        self.confirm(call_id, &args).await?;
+
+        let span = telemetry::tool_span(&self.name, &args);
+        tool_status::emit_started_as(call_id, &self.name, &args);
+        let start = Instant::now();
+        let result = self
+            .server
+            .call_tool(&self.info.name, args)
+            .instrument(span.clone())
+            .await;
+
+        match &result {
+            Ok(output) => {
+                let text = Value::String(output.text.clone());
+                tool_status::emit_completed(call_id, &self.name, start.elapsed(), &text);
+                for image in &output.images {
+                    tool_status::emit_image(call_id, &self.name, image.clone());
+                }
+            }
+            Err(e) => tool_status::emit_failed(call_id, &self.name, start.elapsed(), &e.to_string()),
+        }
+        telemetry::record_tool_result(&span, start.elapsed(), result.is_ok());
+
+        result
+            .map(|output| {
+                let mut text = Value::String(output.text);
+                redact_result((), &mut text);
+                match text {
+                    Value::String(text) => text,
+                    masked => masked.to_string(),
+                }
+            })
+            .map_err(|e| ToolError::ToolCallError(Box::new(e)))
+    }
+}
+
+impl ToolDyn for McpTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn definition(&self, _prompt: String) -> BoxFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: self.name.clone(),
+                description: self
+                    .info
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| {
+                        format!("MCP tool '{}' from server '{}'", self.info.name, self.server.name())
+                    }),
+                parameters: self.parameters(),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> BoxFuture<'_, Result<String, ToolError>> {
+        Box::pin(self.run(args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaced_tool_name() {
+        assert_eq!(namespaced_tool_name("sqlite", "read_query"), "mcp__sqlite__read_query");
+        assert_eq!(namespaced_tool_name("my server", "a.b"), "mcp__my_server__a_b");
+    }
+}
@@ -0,0 +1,528 @@
+//! 项目 `.oxide/` 目录的格式版本与迁移（`oxide migrate`）
+//!
+//! 目录中的 `schema_version` 文件和 `.oxide/config.toml` 顶层的 `schema_version` 记录数据的格式版本。
+//! 启动时按版本顺序执行尚未执行的迁移（[`MIGRATIONS`]），每个迁移完成后更新版本号，
+//! 所以每个迁移只执行一次；迁移本身也是幂等的，中途失败后重新执行不会出错。
+//! 被改写的文件先复制到 `.oxide/backups/<时间>-v<旧版本>/`。
+//!
+//! 版本号比当前程序支持的更新时（数据由更新的 oxide 写入）拒绝启动，不读写任何数据。
+
+use crate::atomic_file::write_atomic;
+use crate::config::ConfigLoader;
+use crate::context::ContextManager;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 当前程序支持的格式版本（等于最后一个迁移的版本）
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 记录格式版本的文件和配置键
+const VERSION_KEY: &str = "schema_version";
+
+/// 备份目录（相对于 `.oxide/`）
+const BACKUPS_DIR: &str = "backups";
+
+/// 按版本顺序排列的迁移
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "convert legacy single-file sessions to JSONL",
+    run: migrate_legacy_sessions,
+}];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error(
+        "{} was written by a newer oxide (schema version {found}, this oxide supports up to \
+         {supported}); upgrade oxide, or move the directory aside to start fresh",
+        .path.display()
+    )]
+    Newer {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+    #[error("invalid schema version in {}: '{value}'", .path.display())]
+    InvalidVersion { path: PathBuf, value: String },
+}
+
+/// 一个迁移：把数据从 `version - 1` 升级到 `version`
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub run: fn(&mut MigrationContext) -> Result<()>,
+}
+
+/// 迁移执行时的目录、备份位置和改动记录
+pub struct MigrationContext {
+    dir: PathBuf,
+    backup_dir: PathBuf,
+    dry_run: bool,
+    changes: Vec<String>,
+}
+
+impl MigrationContext {
+    /// `.oxide/` 目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 记录一项改动，返回是否应当执行（`--dry-run` 时只记录）
+    pub fn change(&mut self, description: impl Into<String>) -> bool {
+        self.changes.push(description.into());
+        !self.dry_run
+    }
+
+    /// 改写文件前把它复制到备份目录（保留相对于 `.oxide/` 的路径）
+    pub fn backup(&self, path: &Path) -> Result<()> {
+        let relative = path.strip_prefix(&self.dir).unwrap_or(path);
+        let target = self.backup_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::copy(path, &target).with_context(|| format!("Failed to back up {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// 已执行（或 `--dry-run` 时将要执行）的迁移
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+/// 一次迁移的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    pub applied: Vec<AppliedMigration>,
+    /// 有文件被备份时为备份目录
+    pub backup_dir: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    /// 是否有改动（包括只更新版本号）
+    pub fn is_empty(&self) -> bool {
+        self.from == self.to
+    }
+
+    fn change_count(&self) -> usize {
+        self.applied.iter().map(|m| m.changes.len()).sum()
+    }
+}
+
+/// 对一个 `.oxide/` 目录执行迁移
+pub struct Migrator {
+    dir: PathBuf,
+    migrations: &'static [Migration],
+}
+
+impl Migrator {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            migrations: MIGRATIONS,
+        }
+    }
+
+    /// 当前项目的 `.oxide/`
+    pub fn for_project() -> Self {
+        let config_path = ConfigLoader::new().project_config_path().to_path_buf();
+        Self::new(config_path.parent().unwrap_or(Path::new(".oxide")))
+    }
+
+    /// 使用指定的迁移列表（测试用）
+    pub fn with_migrations(mut self, migrations: &'static [Migration]) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    fn latest(&self) -> u32 {
+        self.migrations.last().map_or(0, |m| m.version)
+    }
+
+    fn marker_path(&self) -> PathBuf {
+        self.dir.join(VERSION_KEY)
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.dir.join("config.toml")
+    }
+
+    /// 目录当前的格式版本（没有记录时为 0），比程序支持的更新时报错
+    pub fn current_version(&self) -> Result<u32, MigrateError> {
+        let marker_path = self.marker_path();
+        let marker = match fs::read_to_string(&marker_path) {
+            Ok(text) => match text.trim().parse::<u32>() {
+                Ok(version) => Some(version),
+                Err(_) => {
+                    return Err(MigrateError::InvalidVersion {
+                        path: marker_path,
+                        value: text.trim().to_string(),
+                    })
+                }
+            },
+            Err(_) => None,
+        };
+        // 配置文件解析失败由加载配置时报告
+        let config_path = self.config_path();
+        let config = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+            .and_then(|value| value.get(VERSION_KEY)?.as_integer())
+            .map(|version| u32::try_from(version).unwrap_or(u32::MAX));
+
+        for (path, version) in [(marker_path, marker), (config_path, config)] {
+            if let Some(found) = version.filter(|&found| found > self.latest()) {
+                return Err(MigrateError::Newer {
+                    path,
+                    found,
+                    supported: self.latest(),
+                });
+            }
+        }
+        Ok(marker.max(config).unwrap_or(0))
+    }
+
+    /// 执行尚未执行的迁移；`dry_run` 时只列出改动，不修改任何文件
+    pub fn run(&self, dry_run: bool) -> Result<MigrationReport> {
+        let latest = self.latest();
+        if !self.dir.is_dir() {
+            // 新项目，没有需要迁移的数据
+            return Ok(MigrationReport {
+                from: latest,
+                to: latest,
+                applied: Vec::new(),
+                backup_dir: None,
+            });
+        }
+
+        let from = self.current_version()?;
+        let backup_dir = self.dir.join(BACKUPS_DIR).join(format!(
+            "{}-v{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            from
+        ));
+        let mut applied = Vec::new();
+        for migration in self.migrations.iter().filter(|m| m.version > from) {
+            let mut context = MigrationContext {
+                dir: self.dir.clone(),
+                backup_dir: backup_dir.clone(),
+                dry_run,
+                changes: Vec::new(),
+            };
+            (migration.run)(&mut context).with_context(|| {
+                format!(
+                    "Migration to schema version {} ({}) failed",
+                    migration.version, migration.description
+                )
+            })?;
+            if !dry_run {
+                self.write_marker(migration.version)?;
+            }
+            applied.push(AppliedMigration {
+                version: migration.version,
+                description: migration.description,
+                changes: context.changes,
+            });
+        }
+
+        if from < latest && !dry_run {
+            self.stamp_config(latest, &backup_dir)?;
+        }
+        Ok(MigrationReport {
+            from,
+            to: latest,
+            applied,
+            backup_dir: backup_dir.is_dir().then_some(backup_dir),
+        })
+    }
+
+    fn write_marker(&self, version: u32) -> Result<()> {
+        let path = self.marker_path();
+        write_atomic(&path, format!("{}\n", version).as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// 在项目配置中写入 `schema_version`（没有配置文件时不创建）
+    fn stamp_config(&self, version: u32, backup_dir: &Path) -> Result<()> {
+        let path = self.config_path();
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(());
+        };
+        let stamped = set_config_version(&content, version);
+        if stamped == content {
+            return Ok(());
+        }
+        let backup = backup_dir.join("config.toml");
+        fs::create_dir_all(backup_dir)
+            .with_context(|| format!("Failed to create {}", backup_dir.display()))?;
+        fs::copy(&path, &backup)
+            .with_context(|| format!("Failed to back up {}", path.display()))?;
+        write_atomic(&path, stamped.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// 替换或在开头插入顶层的 `schema_version = N`，其余内容原样保留
+fn set_config_version(content: &str, version: u32) -> String {
+    let line = format!("{} = {}", VERSION_KEY, version);
+    let mut output = String::new();
+    let mut replaced = false;
+    let mut in_table = false;
+    for current in content.lines() {
+        let trimmed = current.trim_start();
+        in_table |= trimmed.starts_with('[');
+        let is_version = !in_table
+            && trimmed
+                .strip_prefix(VERSION_KEY)
+                .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if is_version && !replaced {
+            output.push_str(&line);
+            replaced = true;
+        } else {
+            output.push_str(current);
+        }
+        output.push('\n');
+    }
+    if replaced {
+        output
+    } else {
+        format!("{}\n{}", line, content)
+    }
+}
+
+/// 版本 1：旧版 `sessions/<id>.json` 转换为 JSONL（原文件同时保留为 `<id>.json.bak`）
+fn migrate_legacy_sessions(context: &mut MigrationContext) -> Result<()> {
+    let sessions_dir = context.dir().join("sessions");
+    let Ok(entries) = fs::read_dir(&sessions_dir) else {
+        return Ok(());
+    };
+    let mut legacy: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter(|path| !path.with_extension("jsonl").exists())
+        .collect();
+    legacy.sort();
+
+    for path in legacy {
+        let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let description = format!("sessions/{id}.json -> sessions/{id}.jsonl", id = session_id);
+        if context.change(description) {
+            context.backup(&path)?;
+            ContextManager::new(&sessions_dir, session_id.to_string())?.migrate_legacy_session()?;
+        }
+    }
+    Ok(())
+}
+
+/// `oxide migrate [--dry-run]`
+pub fn run_command(dry_run: bool) -> Result<()> {
+    let report = Migrator::for_project().run(dry_run)?;
+    print_report(&report, dry_run);
+    Ok(())
+}
+
+/// 启动时迁移项目的 `.oxide/`：数据比程序新时报错，迁移失败只警告（下次启动时重试）
+pub fn run_startup() -> Result<()> {
+    match Migrator::for_project().run(false) {
+        Ok(report) if !report.is_empty() => {
+            eprintln!(
+                "{}",
+                t!(
+                    "migrate.applied",
+                    from = report.from,
+                    to = report.to,
+                    changes = report.change_count()
+                )
+            );
+            if let Some(dir) = &report.backup_dir {
+                eprintln!("{}", t!("migrate.backup", path = dir.display()));
+            }
+        }
+        Ok(_) => {}
+        Err(e) if e.downcast_ref::<MigrateError>().is_some() => return Err(e),
+        Err(e) => eprintln!("{}", t!("migrate.failed", error = format!("{:#}", e))),
+    }
+    Ok(())
+}
+
+fn print_report(report: &MigrationReport, dry_run: bool) {
+    if report.is_empty() {
+        println!("{}", t!("migrate.up_to_date", version = report.to));
+        return;
+    }
+    let summary = if dry_run {
+        t!("migrate.would_apply", from = report.from, to = report.to)
+    } else {
+        t!(
+            "migrate.applied",
+            from = report.from,
+            to = report.to,
+            changes = report.change_count()
+        )
+    };
+    println!("{}", summary);
+    for migration in &report.applied {
+        println!("  v{}: {}", migration.version, migration.description);
+        for change in &migration.changes {
+            println!("    - {}", change);
+        }
+    }
+    if let Some(dir) = &report.backup_dir {
+        println!("{}", t!("migrate.backup", path = dir.display()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn count_run(context: &mut MigrationContext) -> Result<()> {
+        let path = context.dir().join("runs.log");
+        if context.change("append to runs.log") {
+            let mut runs = fs::read_to_string(&path).unwrap_or_default();
+            runs.push_str("run\n");
+            fs::write(&path, runs)?;
+        }
+        Ok(())
+    }
+
+    const TWO: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "first",
+            run: count_run,
+        },
+        Migration {
+            version: 2,
+            description: "second",
+            run: count_run,
+        },
+    ];
+
+    const THREE: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "first",
+            run: count_run,
+        },
+        Migration {
+            version: 2,
+            description: "second",
+            run: count_run,
+        },
+        Migration {
+            version: 3,
+            description: "third",
+            run: count_run,
+        },
+    ];
+
+    #[test]
+    fn test_migrations_apply_once() {
+        assert_eq!(MIGRATIONS.last().unwrap().version, SCHEMA_VERSION);
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("config.toml"),
+            "# project\n[mode]\ndefault = \"plan\"\n",
+        )
+        .unwrap();
+        let runs = || fs::read_to_string(dir.join("runs.log")).unwrap_or_default();
+
+        let dry = Migrator::new(dir).with_migrations(TWO).run(true).unwrap();
+        assert_eq!((dry.from, dry.to, dry.applied.len()), (0, 2, 2));
+        assert_eq!(runs(), "");
+        assert!(!dir.join(VERSION_KEY).exists());
+
+        let report = Migrator::new(dir).with_migrations(TWO).run(false).unwrap();
+        assert_eq!(report.applied.len(), 2);
+        assert_eq!(runs(), "run\nrun\n");
+        assert_eq!(fs::read_to_string(dir.join(VERSION_KEY)).unwrap(), "2\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml")).unwrap(),
+            "schema_version = 2\n# project\n[mode]\ndefault = \"plan\"\n"
+        );
+
+        let again = Migrator::new(dir).with_migrations(TWO).run(false).unwrap();
+        assert!(again.is_empty());
+        assert_eq!(runs(), "run\nrun\n");
+
+        let newer = Migrator::new(dir)
+            .with_migrations(THREE)
+            .run(false)
+            .unwrap();
+        assert_eq!(newer.applied.len(), 1);
+        assert_eq!(newer.applied[0].version, 3);
+        assert_eq!(runs(), "run\nrun\nrun\n");
+        assert!(fs::read_to_string(dir.join("config.toml"))
+            .unwrap()
+            .starts_with("schema_version = 3\n# project\n"));
+
+        // 数据比程序新：拒绝，不修改任何文件
+        let err = Migrator::new(dir)
+            .with_migrations(TWO)
+            .run(false)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MigrateError>(),
+            Some(MigrateError::Newer {
+                found: 3,
+                supported: 2,
+                ..
+            })
+        ));
+        assert_eq!(runs(), "run\nrun\nrun\n");
+    }
+
+    #[test]
+    fn test_legacy_sessions_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let sessions = dir.join("sessions");
+        fs::create_dir_all(&sessions).unwrap();
+        let legacy = r#"{
+  "metadata": {
+    "session_id": "old",
+    "created_at": "2024-01-01T00:00:00+00:00",
+    "last_updated": "2024-01-01T00:00:00+00:00",
+    "message_count": 1
+  },
+  "messages": [{ "role": "user", "content": "question" }]
+}"#;
+        fs::write(sessions.join("old.json"), legacy).unwrap();
+        fs::write(sessions.join("new.jsonl"), "{}\n").unwrap();
+
+        let dry = Migrator::new(dir).run(true).unwrap();
+        assert_eq!(
+            dry.applied[0].changes,
+            ["sessions/old.json -> sessions/old.jsonl"]
+        );
+        assert!(sessions.join("old.json").exists());
+        assert!(!sessions.join("old.jsonl").exists());
+
+        let report = Migrator::new(dir).run(false).unwrap();
+        assert_eq!(report.applied[0].changes.len(), 1);
+        assert!(sessions.join("old.jsonl").exists());
+        assert!(sessions.join("old.json.bak").exists());
+        assert_eq!(
+            fs::read_to_string(sessions.join("new.jsonl")).unwrap(),
+            "{}\n"
+        );
+        let backup = report.backup_dir.unwrap().join("sessions/old.json");
+        assert_eq!(fs::read_to_string(backup).unwrap(), legacy);
+
+        let mut manager = ContextManager::new(&sessions, "old".to_string()).unwrap();
+        assert!(manager.load().unwrap());
+        assert_eq!(manager.get_messages().len(), 1);
+    }
+}
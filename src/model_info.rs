@@ -0,0 +1,513 @@
+//! 模型信息注册表
+//!
+//! 按模型名查询上下文窗口、输出上限、能力和价格。内置常见的 Anthropic / OpenAI / DeepSeek
+//! 模型，`[models."<pattern>"]` 配置可以覆盖内置信息或补充自定义模型。上下文占用提示、
+//! `/context`、提示符中的上下文百分比和费用统计都从这里查询。
+//!
+//! 模式中的 `*` 匹配任意字符，没有 `*` 的模式只匹配完全相同的模型名；查询时忽略大小写和
+//! 服务商前缀（`anthropic/claude-sonnet-4` 按 `claude-sonnet-4` 查询）。多个条目匹配时
+//! 精确匹配优先于通配符，同类匹配中配置优先于内置，通配符之间字面部分更长的优先。
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::config::{ConfigLoader, ModelConfig};
+use crate::token_counter::ModelPricing;
+
+/// 未知模型的上下文窗口
+const DEFAULT_CONTEXT_WINDOW: usize = 200_000;
+
+/// 未知模型的输出上限
+const DEFAULT_MAX_OUTPUT: u32 = 4096;
+
+/// 未知模型按默认模型（Claude Sonnet）计价
+pub(crate) const DEFAULT_PRICING: ModelPricing = ModelPricing::new(3.0, 15.0, 0.3);
+
+/// 内置模型信息
+struct BuiltIn {
+    pattern: &'static str,
+    context_window: usize,
+    max_output: u32,
+    supports_vision: bool,
+    supports_tools: bool,
+    pricing: ModelPricing,
+}
+
+const fn builtin(
+    pattern: &'static str,
+    context_window: usize,
+    max_output: u32,
+    supports_vision: bool,
+    pricing: ModelPricing,
+) -> BuiltIn {
+    BuiltIn {
+        pattern,
+        context_window,
+        max_output,
+        supports_vision,
+        supports_tools: true,
+        pricing,
+    }
+}
+
+/// 内置模型表（价格为美元 / 百万 token：输入、输出、命中缓存的输入）
+const BUILTIN_MODELS: &[BuiltIn] = &[
+    builtin(
+        "claude-opus-4-5*",
+        200_000,
+        64_000,
+        true,
+        ModelPricing::new(5.0, 25.0, 0.5),
+    ),
+    builtin(
+        "claude-opus-4*",
+        200_000,
+        32_000,
+        true,
+        ModelPricing::new(15.0, 75.0, 1.5),
+    ),
+    builtin(
+        "claude-3-opus*",
+        200_000,
+        4096,
+        true,
+        ModelPricing::new(15.0, 75.0, 1.5),
+    ),
+    builtin(
+        "claude-sonnet-4*",
+        200_000,
+        64_000,
+        true,
+        ModelPricing::new(3.0, 15.0, 0.3),
+    ),
+    builtin(
+        "claude-3-7-sonnet*",
+        200_000,
+        64_000,
+        true,
+        ModelPricing::new(3.0, 15.0, 0.3),
+    ),
+    builtin(
+        "claude-3-5-sonnet*",
+        200_000,
+        8192,
+        true,
+        ModelPricing::new(3.0, 15.0, 0.3),
+    ),
+    builtin(
+        "claude-haiku-4-5*",
+        200_000,
+        64_000,
+        true,
+        ModelPricing::new(1.0, 5.0, 0.1),
+    ),
+    builtin(
+        "claude-3-5-haiku*",
+        200_000,
+        8192,
+        false,
+        ModelPricing::new(0.8, 4.0, 0.08),
+    ),
+    builtin(
+        "gpt-5-mini*",
+        400_000,
+        128_000,
+        true,
+        ModelPricing::new(0.25, 2.0, 0.025),
+    ),
+    builtin(
+        "gpt-5*",
+        400_000,
+        128_000,
+        true,
+        ModelPricing::new(1.25, 10.0, 0.125),
+    ),
+    builtin(
+        "gpt-4.1-mini*",
+        1_047_576,
+        32_768,
+        true,
+        ModelPricing::new(0.4, 1.6, 0.1),
+    ),
+    builtin(
+        "gpt-4.1*",
+        1_047_576,
+        32_768,
+        true,
+        ModelPricing::new(2.0, 8.0, 0.5),
+    ),
+    builtin(
+        "gpt-4o-mini*",
+        128_000,
+        16_384,
+        true,
+        ModelPricing::new(0.15, 0.6, 0.075),
+    ),
+    builtin(
+        "gpt-4o*",
+        128_000,
+        16_384,
+        true,
+        ModelPricing::new(2.5, 10.0, 1.25),
+    ),
+    builtin(
+        "o4-mini*",
+        200_000,
+        100_000,
+        true,
+        ModelPricing::new(1.1, 4.4, 0.275),
+    ),
+    builtin(
+        "o3*",
+        200_000,
+        100_000,
+        true,
+        ModelPricing::new(2.0, 8.0, 0.5),
+    ),
+    builtin(
+        "deepseek-chat*",
+        128_000,
+        8192,
+        false,
+        ModelPricing::new(0.28, 0.42, 0.028),
+    ),
+    builtin(
+        "deepseek-reasoner*",
+        128_000,
+        64_000,
+        false,
+        ModelPricing::new(0.28, 0.42, 0.028),
+    ),
+];
+
+/// 模型信息的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelSource {
+    /// `[models."<pattern>"]` 配置（未设置的字段沿用内置信息）
+    Config,
+    /// 内置模型表
+    BuiltIn,
+    /// 没有匹配的条目，使用默认值
+    Unknown,
+}
+
+/// 一个模型的上下文窗口、输出上限、能力和价格
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelInfo {
+    /// 匹配到的模式，未知模型为 `None`
+    pub pattern: Option<String>,
+    pub source: ModelSource,
+    /// 上下文窗口（token）
+    pub context_window: usize,
+    /// 单次回复的输出上限（token）
+    pub max_output: u32,
+    /// 能否接收图片
+    pub supports_vision: bool,
+    /// 能否调用工具
+    pub supports_tools: bool,
+    pub pricing: ModelPricing,
+}
+
+impl ModelInfo {
+    /// 按合并后的 `[models]` 配置和内置模型表查询
+    pub fn lookup(model: &str) -> Self {
+        ModelRegistry::global().lookup(model)
+    }
+
+    /// 没有匹配的条目，各项为默认值
+    pub fn is_unknown(&self) -> bool {
+        self.source == ModelSource::Unknown
+    }
+
+    fn unknown() -> Self {
+        Self {
+            pattern: None,
+            source: ModelSource::Unknown,
+            context_window: DEFAULT_CONTEXT_WINDOW,
+            max_output: DEFAULT_MAX_OUTPUT,
+            supports_vision: false,
+            supports_tools: true,
+            pricing: DEFAULT_PRICING,
+        }
+    }
+
+    fn from_builtin(entry: &BuiltIn) -> Self {
+        Self {
+            pattern: Some(entry.pattern.to_string()),
+            source: ModelSource::BuiltIn,
+            context_window: entry.context_window,
+            max_output: entry.max_output,
+            supports_vision: entry.supports_vision,
+            supports_tools: entry.supports_tools,
+            pricing: entry.pricing,
+        }
+    }
+
+    /// 在 `base` 上应用配置中设置了的字段
+    fn with_config(base: Self, pattern: &str, config: &ModelConfig) -> Self {
+        Self {
+            pattern: Some(pattern.to_string()),
+            source: ModelSource::Config,
+            context_window: config.context_window.unwrap_or(base.context_window),
+            max_output: config.max_output.unwrap_or(base.max_output),
+            supports_vision: config.supports_vision.unwrap_or(base.supports_vision),
+            supports_tools: config.supports_tools.unwrap_or(base.supports_tools),
+            pricing: config
+                .pricing
+                .map(|pricing| {
+                    ModelPricing::new(
+                        pricing.input,
+                        pricing.output,
+                        pricing.cached_input.unwrap_or(pricing.input),
+                    )
+                })
+                .unwrap_or(base.pricing),
+        }
+    }
+}
+
+/// 内置模型表加上 `[models]` 配置
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry {
+    overrides: BTreeMap<String, ModelConfig>,
+}
+
+impl ModelRegistry {
+    pub fn new(overrides: BTreeMap<String, ModelConfig>) -> Self {
+        let overrides = overrides
+            .into_iter()
+            .map(|(pattern, config)| (pattern.to_lowercase(), config))
+            .collect();
+        Self { overrides }
+    }
+
+    /// 读取合并后的 `[models]` 配置
+    pub fn load() -> Self {
+        let overrides = ConfigLoader::new()
+            .load_toml_layers()
+            .map(|config| config.models)
+            .unwrap_or_default();
+        Self::new(overrides)
+    }
+
+    /// 进程内共享的注册表（首次使用时读取配置）
+    pub fn global() -> &'static Self {
+        static REGISTRY: OnceLock<ModelRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(Self::load)
+    }
+
+    pub fn lookup(&self, model: &str) -> ModelInfo {
+        let name = normalize(model);
+        let builtin = BUILTIN_MODELS
+            .iter()
+            .filter_map(|entry| Some((rank(entry.pattern, &name)?, entry)))
+            .max_by_key(|(rank, _)| *rank);
+        let configured = self
+            .overrides
+            .iter()
+            .filter_map(|(pattern, config)| Some((rank(pattern, &name)?, pattern, config)))
+            .max_by_key(|(rank, _, _)| *rank);
+
+        let base = builtin
+            .map(|(_, entry)| ModelInfo::from_builtin(entry))
+            .unwrap_or_else(ModelInfo::unknown);
+        match (configured, builtin) {
+            // 同类匹配中配置优先：配置是精确匹配，或内置条目也只是通配符匹配
+            (Some((config_rank, pattern, config)), Some((builtin_rank, _)))
+                if config_rank.exact || !builtin_rank.exact =>
+            {
+                ModelInfo::with_config(base, pattern, config)
+            }
+            (Some((_, pattern, config)), None) => ModelInfo::with_config(base, pattern, config),
+            _ => base,
+        }
+    }
+}
+
+/// 模式匹配的优先级：先比较是否精确匹配，再比较字面部分的长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Rank {
+    exact: bool,
+    literal_len: usize,
+}
+
+/// 小写并去掉服务商前缀
+fn normalize(model: &str) -> String {
+    let model = model.trim().to_lowercase();
+    match model.rsplit_once('/') {
+        Some((_, name)) => name.to_string(),
+        None => model,
+    }
+}
+
+/// 模式与模型名匹配时返回优先级
+fn rank(pattern: &str, name: &str) -> Option<Rank> {
+    if !pattern.contains('*') {
+        return (pattern == name).then_some(Rank {
+            exact: true,
+            literal_len: pattern.len(),
+        });
+    }
+    wildcard_match(pattern, name).then(|| Rank {
+        exact: false,
+        literal_len: pattern.chars().filter(|c| *c != '*').count(),
+    })
+}
+
+/// `*` 匹配任意字符（包括空）
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || name.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &name[first.len()..];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelPricingConfig;
+
+    fn registry(entries: &[(&str, ModelConfig)]) -> ModelRegistry {
+        ModelRegistry::new(
+            entries
+                .iter()
+                .map(|(pattern, config)| (pattern.to_string(), config.clone()))
+                .collect(),
+        )
+    }
+
+    fn window(size: usize) -> ModelConfig {
+        ModelConfig {
+            context_window: Some(size),
+            ..ModelConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        let registry = ModelRegistry::default();
+        let cases = [
+            ("claude-sonnet-4-20250514", "claude-sonnet-4*", 200_000),
+            ("anthropic/Claude-Opus-4-1", "claude-opus-4*", 200_000),
+            ("claude-opus-4-5-20251101", "claude-opus-4-5*", 200_000),
+            ("gpt-4o-mini-2024-07-18", "gpt-4o-mini*", 128_000),
+            ("gpt-4o", "gpt-4o*", 128_000),
+            ("gpt-4.1-nano", "gpt-4.1*", 1_047_576),
+            ("openai/gpt-5", "gpt-5*", 400_000),
+            ("deepseek-chat", "deepseek-chat*", 128_000),
+        ];
+        for (model, pattern, context_window) in cases {
+            let info = registry.lookup(model);
+            assert_eq!(info.source, ModelSource::BuiltIn, "{}", model);
+            assert_eq!(info.pattern.as_deref(), Some(pattern), "{}", model);
+            assert_eq!(info.context_window, context_window, "{}", model);
+        }
+        assert!(!registry.lookup("deepseek-chat").supports_vision);
+        assert!(registry.lookup("claude-sonnet-4-5").supports_vision);
+    }
+
+    #[test]
+    fn test_unknown_model_is_flagged() {
+        let info = ModelRegistry::default().lookup("some-local-model");
+        assert!(info.is_unknown());
+        assert_eq!(info.pattern, None);
+        assert_eq!(info.context_window, DEFAULT_CONTEXT_WINDOW);
+        assert_eq!(info.pricing, DEFAULT_PRICING);
+    }
+
+    #[test]
+    fn test_lookup_precedence() {
+        let registry = registry(&[
+            ("claude-sonnet-4-20250514", window(1)),
+            ("claude-*", window(2)),
+            ("gpt-4o", window(3)),
+            ("my-*", window(4)),
+            ("my-custom-*", window(5)),
+            ("my-custom-model", window(6)),
+        ]);
+        // (模型, 期望的来源, 期望的窗口)
+        let cases = [
+            // 精确匹配 > 通配符
+            ("my-custom-model", ModelSource::Config, 6),
+            // 通配符之间字面部分更长的优先
+            ("my-custom-other", ModelSource::Config, 5),
+            ("my-other", ModelSource::Config, 4),
+            // 配置的精确匹配 > 内置通配符
+            ("claude-sonnet-4-20250514", ModelSource::Config, 1),
+            ("gpt-4o", ModelSource::Config, 3),
+            // 同为通配符匹配时配置 > 内置（即使内置模式更具体）
+            ("claude-haiku-4-5", ModelSource::Config, 2),
+            // 配置的精确模式不匹配其他名字，回到内置
+            ("gpt-4o-mini", ModelSource::BuiltIn, 128_000),
+            (
+                "mistral-large",
+                ModelSource::Unknown,
+                DEFAULT_CONTEXT_WINDOW,
+            ),
+        ];
+        for (model, source, context_window) in cases {
+            let info = registry.lookup(model);
+            assert_eq!(info.source, source, "{}", model);
+            assert_eq!(info.context_window, context_window, "{}", model);
+        }
+    }
+
+    #[test]
+    fn test_config_overrides_only_set_fields() {
+        let registry = registry(&[
+            (
+                "claude-sonnet-4*",
+                ModelConfig {
+                    context_window: Some(1_000_000),
+                    ..ModelConfig::default()
+                },
+            ),
+            (
+                "My-Custom-Model",
+                ModelConfig {
+                    supports_vision: Some(true),
+                    pricing: Some(ModelPricingConfig {
+                        input: 1.0,
+                        output: 2.0,
+                        cached_input: None,
+                    }),
+                    ..ModelConfig::default()
+                },
+            ),
+        ]);
+
+        let sonnet = registry.lookup("claude-sonnet-4-5");
+        assert_eq!(sonnet.context_window, 1_000_000);
+        assert_eq!(sonnet.pricing, ModelPricing::new(3.0, 15.0, 0.3));
+        assert_eq!(sonnet.max_output, 64_000);
+
+        // 自定义模型未设置的字段使用默认值，模式不区分大小写
+        let custom = registry.lookup("my-custom-model");
+        assert_eq!(custom.source, ModelSource::Config);
+        assert!(custom.supports_vision);
+        assert_eq!(custom.pricing, ModelPricing::new(1.0, 2.0, 1.0));
+        assert_eq!(custom.context_window, DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("gpt-4o*", "gpt-4o"));
+        assert!(wildcard_match("*sonnet*", "claude-3-7-sonnet-latest"));
+        assert!(wildcard_match(
+            "claude-*-latest",
+            "claude-3-7-sonnet-latest"
+        ));
+        assert!(!wildcard_match("claude-*-latest", "claude-3-7-sonnet"));
+        assert!(!wildcard_match("o3*", "gpt-4o3"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+}
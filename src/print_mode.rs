@@ -0,0 +1,93 @@
+//! 非交互运行（`oxide -p <prompt>`）
+//!
+//! 发送一条消息后退出：工具照常执行，需要确认的操作一律拒绝，不在终端提问。
+//! 回复写到 stdout，警告和错误写到 stderr，出错时以非零状态退出，方便在脚本中使用。
+//! 指定 `--output-schema` 时只输出校验通过的 JSON（见 `structured_output`）；
+//! 指定 `--stream-json` 时工具进度和流式事件按行以 JSON 写到 stderr（见 `StreamJsonSink`）。
+//! 请求通过不保存的 [`OxideSession`] 发送。
+
+use anyhow::Result;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rig::completion::Message;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::agent::stream_event::StreamEvent;
+use crate::agent::structured_output::{complete_structured, AnswerCollector, OutputSchema};
+use crate::agent::tool_status;
+use crate::agent::AgentType;
+use crate::args::SESSIONS_DIR;
+use crate::context::ContextManager;
+use crate::error::OxideError;
+use crate::session::OxideSession;
+
+/// 一次非交互运行
+pub struct PrintRun {
+    /// 用于日志和用量统计，对话不保存为会话
+    session: Mutex<OxideSession>,
+    provider: &'static str,
+}
+
+impl PrintRun {
+    pub fn new(agent: AgentType, model: String) -> Result<Self> {
+        let storage_dir = Path::new(SESSIONS_DIR);
+        let session_id = ContextManager::generate_session_id(storage_dir);
+        let context = ContextManager::new(storage_dir, session_id)?;
+        let provider = agent.provider();
+        let session = OxideSession::from_parts(agent, context, model)
+            .ephemeral()
+            .with_warnings(Arc::new(|message: &str| eprintln!("{}", message)));
+        Ok(Self {
+            session: Mutex::new(session),
+            provider,
+        })
+    }
+
+    /// 发送 `prompt`，把最终回答（或校验通过的 JSON）写到 stdout
+    pub async fn run(&self, prompt: &str, schema: Option<&OutputSchema>) -> Result<()> {
+        let Some(schema) = schema else {
+            let mut session = self.session.lock().await;
+            drain(session.send(prompt), true).await?;
+            return Ok(());
+        };
+        let use_tool = self.provider == "anthropic";
+        let value = complete_structured(schema, prompt, use_tool, |prompt, history| {
+            self.send_with_history(prompt, history)
+        })
+        .await?;
+        println!("{}", serde_json::to_string(&value)?);
+        Ok(())
+    }
+
+    /// 以 `history` 为历史发送一轮并返回最终回答
+    async fn send_with_history(&self, prompt: String, history: Vec<Message>) -> Result<String> {
+        let mut session = self.session.lock().await;
+        drain(session.send_with_history(&prompt, history), false).await
+    }
+}
+
+/// 读完一轮的事件流并返回最终回答，`echo` 为 true 时边收边把回复写到 stdout
+async fn drain(mut events: BoxStream<'_, StreamEvent>, echo: bool) -> Result<String> {
+    let mut collector = AnswerCollector::default();
+    let mut stdout = std::io::stdout();
+    while let Some(event) = events.next().await {
+        collector.observe(&event);
+        tool_status::emit_stream(&event);
+        match event {
+            StreamEvent::TextDelta { text } if echo => {
+                stdout.write_all(text.as_bytes())?;
+                stdout.flush()?;
+            }
+            // 处理建议已由会话写到 stderr
+            StreamEvent::Error { message } => return Err(OxideError::classify(message).into()),
+            _ => {}
+        }
+    }
+    if echo {
+        writeln!(stdout)?;
+    }
+    Ok(collector.answer())
+}
@@ -0,0 +1,407 @@
+//! 编辑器集成服务器（`oxide serve`）
+//!
+//! 在 127.0.0.1 上提供 WebSocket 接口，每个文本帧是一条 JSON-RPC 2.0 消息。
+//! 连接时需要携带启动时打印的令牌：`Authorization: Bearer <token>` 请求头
+//! 或 `?token=<token>` 查询参数。
+//!
+//! 方法：
+//! - `session/start`：新建会话，传入 `session_id` 时继续已保存的会话
+//! - `session/list`：列出已保存的会话
+//! - `session/send`：发送用户消息，立即返回；输出以 `session/event` 通知推送
+//! - `session/cancel`：取消进行中的一轮
+//! - `permission/respond`：回答 `permission_request` 事件
+//!
+//! 服务器只负责传输：会话使用与 CLI 相同的 Agent、会话存储和 HITL 权限检查，
+//! 需要确认的操作交给客户端决定。会话属于打开它的连接，断开时取消进行中的一轮。
+
+mod protocol;
+mod session;
+
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::agent::{AgentBuilder, HitlIntegration};
+use crate::config::secret::Secret;
+use crate::config::BehaviorConfig;
+use crate::context::{ContextManager, SessionEnvironment, TurnRecovery};
+use crate::mcp::McpManager;
+use crate::tools::ToolSettings;
+use protocol::{
+    error_response, parse_params, success_response, EventSink, PermissionResponse, INTERNAL_ERROR,
+    INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use session::{ServeSession, SessionPrompter};
+
+/// 创建会话所需的配置
+#[derive(Clone)]
+pub struct ServeOptions {
+    pub base_url: String,
+    pub auth_token: Secret<String>,
+    pub model: Option<String>,
+    pub sessions_dir: PathBuf,
+    pub mcp: Option<Arc<McpManager>>,
+}
+
+/// WebSocket 服务器
+pub struct Server {
+    options: ServeOptions,
+    token: String,
+}
+
+impl Server {
+    /// 使用随机令牌创建服务器
+    pub fn new(options: ServeOptions) -> Self {
+        Self {
+            options,
+            token: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+
+    /// 指定连接令牌
+    #[allow(dead_code)]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = token.into();
+        self
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// 只监听本机回环地址，`port` 为 0 时由系统分配
+    pub async fn bind(port: u16) -> Result<TcpListener> {
+        TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to listen on 127.0.0.1:{}", port))
+    }
+
+    /// 接受连接直到进程退出
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = Arc::clone(&self);
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream).await {
+                    tracing::debug!("serve connection closed: {:#}", e);
+                }
+            });
+        }
+    }
+
+    // 握手回调的错误类型由 tungstenite 决定
+    #[allow(clippy::result_large_err)]
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let token = self.token.clone();
+        let socket =
+            tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+                if is_authorized(request, &token) {
+                    Ok(response)
+                } else {
+                    let mut error =
+                        ErrorResponse::new(Some("invalid or missing token".to_string()));
+                    *error.status_mut() = StatusCode::UNAUTHORIZED;
+                    Err(error)
+                }
+            })
+            .await?;
+        let (mut sink, mut source) = socket.split();
+
+        // 响应和事件都经由 outbox 写出，轮次在后台任务中推送事件
+        let (outbox, mut outgoing) = mpsc::unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            while let Some(message) = outgoing.recv().await {
+                if sink
+                    .send(WsMessage::text(message.to_string()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let connection = Connection::new(self.options.clone(), outbox.clone());
+        while let Some(frame) = source.next().await {
+            let text = match frame? {
+                WsMessage::Text(text) => text,
+                WsMessage::Close(_) => break,
+                _ => continue,
+            };
+            let response = match serde_json::from_str::<Value>(&text) {
+                Ok(message) => connection.handle_message(message).await,
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+            if let Some(response) = response {
+                let _ = outbox.send(response);
+            }
+        }
+
+        // 客户端已断开，后续事件无处可发
+        connection.close();
+        writer.abort();
+        Ok(())
+    }
+}
+
+/// 检查请求头或查询参数中的令牌
+fn is_authorized(request: &Request, token: &str) -> bool {
+    let bearer = request
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer == Some(token) {
+        return true;
+    }
+    request
+        .uri()
+        .query()
+        .map(|query| {
+            query
+                .split('&')
+                .any(|pair| pair.strip_prefix("token=") == Some(token))
+        })
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StartParams {
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SendParams {
+    session_id: String,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionParams {
+    session_id: String,
+}
+
+/// 一个 WebSocket 连接及其打开的会话
+struct Connection {
+    options: ServeOptions,
+    outbox: mpsc::UnboundedSender<Value>,
+    sessions: Mutex<HashMap<String, Arc<ServeSession>>>,
+}
+
+impl Connection {
+    fn new(options: ServeOptions, outbox: mpsc::UnboundedSender<Value>) -> Self {
+        Self {
+            options,
+            outbox,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 处理单条消息，通知返回 None
+    async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id, INVALID_REQUEST, "missing method"));
+        };
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match method {
+            "session/start" => self.start_session(&params),
+            "session/list" => self.list_sessions(),
+            "session/send" => self.send(&params),
+            "session/cancel" => self.cancel(&params),
+            "permission/respond" => self.respond(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => success_response(id, result),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn start_session(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let params: StartParams = parse_params(params)?;
+        let dir = &self.options.sessions_dir;
+        let session_id = params
+            .session_id
+            .clone()
+            .unwrap_or_else(|| ContextManager::generate_session_id(dir));
+
+        let mut context = ContextManager::new(dir, session_id.clone())
+            .map_err(internal)?
+            .with_dedupe_file_reads(BehaviorConfig::load().dedupe_file_reads);
+        let resumed = match &params.session_id {
+            Some(_) => context.load().map_err(internal)?,
+            None => false,
+        };
+        // 上次停在一轮中间时保留并注明中断，客户端可以再发消息继续
+        context
+            .recover_interrupted_turn(TurnRecovery::Keep)
+            .map_err(internal)?;
+
+        let events = EventSink::new(session_id.clone(), self.outbox.clone());
+        let prompter = Arc::new(SessionPrompter::new(events.clone()));
+        let hitl = HitlIntegration::new()
+            .map_err(internal)?
+            .with_prompter(prompter.clone());
+        let tool_settings = ToolSettings::load().map_err(internal)?;
+        let mut builder = AgentBuilder::new(
+            self.options.base_url.clone(),
+            self.options.auth_token.clone(),
+            self.options.model.clone(),
+        )
+        .with_hitl(Arc::new(hitl))
+        .with_tool_settings(tool_settings);
+        if let Some(mcp) = &self.options.mcp {
+            builder = builder.with_mcp(mcp.clone());
+        }
+        let agent = builder.build_main().map_err(internal)?;
+        let model = self
+            .options
+            .model
+            .clone()
+            .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let context = context.with_environment(SessionEnvironment::capture(
+            &cwd,
+            agent.provider(),
+            &model,
+            "serve",
+        ));
+
+        let session = Arc::new(ServeSession::new(context, agent, model, prompter, events));
+        if let Ok(mut sessions) = self.sessions.lock() {
+            if let Some(previous) = sessions.insert(session_id.clone(), session) {
+                previous.cancel();
+            }
+        }
+        Ok(json!({ "session_id": session_id, "resumed": resumed }))
+    }
+
+    fn list_sessions(&self) -> Result<Value, (i64, String)> {
+        let context =
+            ContextManager::new(&self.options.sessions_dir, String::new()).map_err(internal)?;
+        let sessions = context.list_sessions().map_err(internal)?;
+        Ok(json!({ "sessions": sessions }))
+    }
+
+    fn send(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let params: SendParams = parse_params(params)?;
+        let session = self.session(&params.session_id)?;
+        // `!` 直接执行命令只在交互式终端中可用，不能由客户端远程触发
+        if crate::tools::direct_shell::parse(&params.message).is_some() {
+            return Err((
+                INVALID_PARAMS,
+                "Direct shell commands (!) are only available in the interactive terminal"
+                    .to_string(),
+            ));
+        }
+        if !session.send(params.message) {
+            return Err((
+                INVALID_REQUEST,
+                "A turn is already running in this session".to_string(),
+            ));
+        }
+        Ok(json!({ "accepted": true }))
+    }
+
+    fn cancel(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let params: SessionParams = parse_params(params)?;
+        let cancelled = self.session(&params.session_id)?.cancel();
+        Ok(json!({ "cancelled": cancelled }))
+    }
+
+    fn respond(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let response: PermissionResponse = parse_params(params)?;
+        let session = self.session(&response.session_id)?;
+        let request_id = response.request_id;
+        if !session
+            .prompter()
+            .respond(request_id, response.into_result())
+        {
+            return Err((
+                INVALID_PARAMS,
+                format!("No pending permission request: {}", request_id),
+            ));
+        }
+        Ok(json!({}))
+    }
+
+    fn session(&self, session_id: &str) -> Result<Arc<ServeSession>, (i64, String)> {
+        self.sessions
+            .lock()
+            .ok()
+            .and_then(|sessions| sessions.get(session_id).cloned())
+            .ok_or_else(|| {
+                (
+                    INVALID_PARAMS,
+                    format!("Session not started: {}", session_id),
+                )
+            })
+    }
+
+    /// 连接断开：取消所有进行中的轮次
+    fn close(&self) {
+        if let Ok(sessions) = self.sessions.lock() {
+            for session in sessions.values() {
+                session.cancel();
+            }
+        }
+    }
+}
+
+fn internal(error: anyhow::Error) -> (i64, String) {
+    (INTERNAL_ERROR, format!("{:#}", error))
+}
+
+/// 运行 `oxide serve --port <port>`
+pub async fn run(options: ServeOptions, port: u16) -> Result<()> {
+    let listener = Server::bind(port).await?;
+    let addr = listener.local_addr()?;
+    let server = Arc::new(Server::new(options));
+    println!("{}", t!("serve.listening", addr = format!("ws://{}", addr)));
+    println!("{}", t!("serve.token", token = server.token()));
+    server.serve(listener).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, authorization: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(value) = authorization {
+            builder = builder.header("Authorization", value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(
+            &request("/", Some("Bearer secret")),
+            "secret"
+        ));
+        assert!(is_authorized(
+            &request("/?a=1&token=secret", None),
+            "secret"
+        ));
+        assert!(!is_authorized(&request("/", None), "secret"));
+        assert!(!is_authorized(
+            &request("/?token=other", Some("Bearer other")),
+            "secret"
+        ));
+        assert!(!is_authorized(&request("/?token=secrets", None), "secret"));
+    }
+}
@@ -0,0 +1,254 @@
+//! `oxide serve` 的 JSON-RPC 消息
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::agent::turn_recap::TurnRecap;
+use crate::agent::turn_summary::TurnSummary;
+use crate::agent::{HitlResult, PermissionRequest};
+use crate::error::{ErrorCategory, OxideError};
+
+/// JSON-RPC 错误码
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// 推送给客户端的会话事件（`session/event` 通知的 `event` 字段）
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// 模型输出的一段文本
+    TextDelta { text: String },
+    /// 开始执行工具
+    ToolStarted {
+        tool: String,
+        call_id: Option<String>,
+        /// 工具参数（已隐藏认证信息）
+        args: Value,
+    },
+    /// 工具执行结束
+    ToolResult {
+        tool: String,
+        call_id: Option<String>,
+        result: String,
+    },
+    /// 等待 `permission/respond`
+    PermissionRequest {
+        request_id: u64,
+        #[serde(flatten)]
+        request: PermissionRequest,
+    },
+    /// 本轮的 token 用量
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+        total_tokens: u64,
+    },
+    /// 本轮摘要：耗时、工具调用次数、token 用量和停止原因
+    TurnSummary {
+        #[serde(flatten)]
+        summary: TurnSummary,
+    },
+    /// 复杂一轮的活动回顾（见 `crate::agent::turn_recap`）
+    TurnRecap {
+        #[serde(flatten)]
+        recap: TurnRecap,
+    },
+    /// 本轮结束，`text` 为完整回复
+    TurnCompleted { text: String },
+    /// 本轮被取消
+    TurnCancelled,
+    /// 本轮出错，`retryable` 表示稍后重发同一条消息可能成功
+    TurnFailed {
+        error: String,
+        category: ErrorCategory,
+        retryable: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+}
+
+impl Event {
+    pub fn turn_failed(error: &OxideError) -> Self {
+        Event::TurnFailed {
+            error: error.message.clone(),
+            category: error.category,
+            retryable: error.is_retryable(),
+            hint: error.user_hint(),
+            request_id: error.request_id.clone(),
+        }
+    }
+}
+
+/// 某个会话的事件出口
+#[derive(Debug, Clone)]
+pub struct EventSink {
+    session_id: String,
+    outbox: mpsc::UnboundedSender<Value>,
+}
+
+impl EventSink {
+    pub fn new(session_id: String, outbox: mpsc::UnboundedSender<Value>) -> Self {
+        Self { session_id, outbox }
+    }
+
+    /// 发送事件，连接已关闭时丢弃
+    pub fn send(&self, event: Event) {
+        let _ = self.outbox.send(notification(&self.session_id, &event));
+    }
+}
+
+/// `session/event` 通知
+pub fn notification(session_id: &str, event: &Event) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "session/event",
+        "params": { "session_id": session_id, "event": event },
+    })
+}
+
+pub fn success_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// 客户端对权限请求的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Approve,
+    Deny,
+}
+
+/// `permission/respond` 的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct PermissionResponse {
+    pub session_id: String,
+    pub request_id: u64,
+    pub decision: Decision,
+    /// 拒绝时给 Agent 的建议
+    #[serde(default)]
+    pub feedback: Option<String>,
+}
+
+impl PermissionResponse {
+    pub fn into_result(self) -> HitlResult {
+        match (self.decision, self.feedback) {
+            (Decision::Approve, _) => HitlResult::Approved,
+            (Decision::Deny, Some(feedback)) if !feedback.trim().is_empty() => {
+                HitlResult::Suggested(feedback)
+            }
+            (Decision::Deny, _) => HitlResult::Rejected,
+        }
+    }
+}
+
+/// 解析方法参数
+pub fn parse_params<T: for<'de> Deserialize<'de>>(params: &Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params.clone()).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_notification_shape() {
+        let event = Event::ToolStarted {
+            tool: "read_file".to_string(),
+            call_id: Some("call_1".to_string()),
+            args: json!({ "file_path": "src/main.rs" }),
+        };
+        let message = notification("s1", &event);
+        assert_eq!(message["method"], "session/event");
+        assert_eq!(message["params"]["session_id"], "s1");
+        assert_eq!(message["params"]["event"]["type"], "tool_started");
+        assert_eq!(
+            message["params"]["event"]["args"]["file_path"],
+            "src/main.rs"
+        );
+
+        let message = notification("s1", &Event::TurnCancelled);
+        assert_eq!(
+            message["params"]["event"],
+            json!({ "type": "turn_cancelled" })
+        );
+    }
+
+    #[test]
+    fn test_turn_summary_is_flattened() {
+        let event = Event::TurnSummary {
+            summary: TurnSummary {
+                elapsed: std::time::Duration::from_millis(1_500),
+                tool_calls: 2,
+                input_tokens: 900,
+                output_tokens: 4096,
+                stop_reason: crate::agent::turn_summary::StopReason::MaxTokens,
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "turn_summary");
+        assert_eq!(value["elapsed_ms"], 1_500);
+        assert_eq!(value["tool_calls"], 2);
+        assert_eq!(value["stop_reason"], "max_tokens");
+    }
+
+    #[test]
+    fn test_permission_request_is_flattened() {
+        let event = Event::PermissionRequest {
+            request_id: 3,
+            request: PermissionRequest {
+                tool: "shell_execute".to_string(),
+                args: json!({ "command": "rm -rf target" }),
+                reason: "deletes files".to_string(),
+                warning_level: None,
+                options: Vec::new(),
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "permission_request");
+        assert_eq!(value["request_id"], 3);
+        assert_eq!(value["tool"], "shell_execute");
+        assert!(value.get("options").is_none());
+    }
+
+    #[test]
+    fn test_permission_response_into_result() {
+        let parse = |value: Value| {
+            parse_params::<PermissionResponse>(&value)
+                .unwrap()
+                .into_result()
+        };
+        assert_eq!(
+            parse(json!({ "session_id": "s", "request_id": 1, "decision": "approve" })),
+            HitlResult::Approved
+        );
+        assert_eq!(
+            parse(json!({ "session_id": "s", "request_id": 1, "decision": "deny" })),
+            HitlResult::Rejected
+        );
+        assert_eq!(
+            parse(
+                json!({ "session_id": "s", "request_id": 1, "decision": "deny", "feedback": "use cargo check" })
+            ),
+            HitlResult::Suggested("use cargo check".to_string())
+        );
+
+        let err =
+            parse_params::<PermissionResponse>(&json!({ "session_id": "s", "decision": "maybe" }))
+                .unwrap_err();
+        assert_eq!(err.0, INVALID_PARAMS);
+    }
+}
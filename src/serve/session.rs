@@ -0,0 +1,413 @@
+//! `oxide serve` 中的单个会话
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use rig::agent::{CancelSignal, FinalResponse, StreamingPromptHook};
+use rig::completion::{CompletionModel, Message};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tracing::Instrument;
+
+use super::protocol::{Event, EventSink};
+use crate::agent::loop_guard;
+use crate::agent::reminder::with_reminders;
+use crate::agent::stream_event::{StreamEvent, TurnStream};
+use crate::agent::turn_summary::{StopReason, TurnSummary};
+use crate::agent::{
+    AgentType, HitlResult, PermissionPrompter, PermissionRequest, ReminderTracker,
+};
+use crate::config::RenderConfig;
+use crate::context::ContextManager;
+use crate::error::OxideError;
+use crate::hooks::SessionIdHook;
+use crate::telemetry;
+use crate::tools::memory::with_project_memory;
+use crate::tools::redactor::redact_tool_args;
+
+/// 把权限确认转发给客户端，等待 `permission/respond`
+pub struct SessionPrompter {
+    events: EventSink,
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<HitlResult>>>,
+}
+
+impl SessionPrompter {
+    pub fn new(events: EventSink) -> Self {
+        Self {
+            events,
+            next_id: AtomicU64::new(0),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 回答权限请求，请求不存在时返回 false
+    pub fn respond(&self, request_id: u64, result: HitlResult) -> bool {
+        let sender = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut p| p.remove(&request_id));
+        match sender {
+            Some(sender) => sender.send(result).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 拒绝所有未回答的请求（取消或断开连接时）
+    pub fn reject_all(&self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+    }
+}
+
+impl PermissionPrompter for SessionPrompter {
+    fn prompt(&self, request: PermissionRequest) -> BoxFuture<'_, HitlResult> {
+        Box::pin(async move {
+            let request_id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+            let (tx, rx) = oneshot::channel();
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.insert(request_id, tx);
+            }
+            self.events.send(Event::PermissionRequest {
+                request_id,
+                request,
+            });
+            // 发送端被丢弃（取消、断开）视为拒绝
+            rx.await.unwrap_or(HitlResult::Rejected)
+        })
+    }
+}
+
+/// 在 `SessionIdHook` 之外把工具调用推送给客户端
+#[derive(Clone)]
+struct EventHook {
+    inner: SessionIdHook,
+    events: EventSink,
+}
+
+impl<M: CompletionModel> StreamingPromptHook<M> for EventHook {
+    async fn on_tool_call(
+        &self,
+        tool_name: &str,
+        tool_call_id: Option<String>,
+        args: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        let parsed =
+            serde_json::from_str::<Value>(args).unwrap_or_else(|_| Value::String(args.to_string()));
+        self.events.send(Event::ToolStarted {
+            tool: tool_name.to_string(),
+            call_id: tool_call_id.clone(),
+            args: redact_tool_args(&parsed),
+        });
+        <SessionIdHook as StreamingPromptHook<M>>::on_tool_call(
+            &self.inner,
+            tool_name,
+            tool_call_id,
+            args,
+            cancel_sig,
+        )
+        .await;
+    }
+
+    async fn on_tool_result(
+        &self,
+        tool_name: &str,
+        tool_call_id: Option<String>,
+        args: &str,
+        result: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        self.events.send(Event::ToolResult {
+            tool: tool_name.to_string(),
+            call_id: tool_call_id.clone(),
+            result: result.to_string(),
+        });
+        <SessionIdHook as StreamingPromptHook<M>>::on_tool_result(
+            &self.inner,
+            tool_name,
+            tool_call_id,
+            args,
+            result,
+            cancel_sig,
+        )
+        .await;
+    }
+
+    async fn on_completion_call(
+        &self,
+        prompt: &Message,
+        history: &[Message],
+        cancel_sig: CancelSignal,
+    ) {
+        <SessionIdHook as StreamingPromptHook<M>>::on_completion_call(
+            &self.inner,
+            prompt,
+            history,
+            cancel_sig,
+        )
+        .await;
+    }
+
+    async fn on_text_delta(
+        &self,
+        text_delta: &str,
+        aggregated_text: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        <SessionIdHook as StreamingPromptHook<M>>::on_text_delta(
+            &self.inner,
+            text_delta,
+            aggregated_text,
+            cancel_sig,
+        )
+        .await;
+    }
+
+    async fn on_tool_call_delta(
+        &self,
+        tool_call_id: &str,
+        tool_name: Option<&str>,
+        tool_call_delta: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        <SessionIdHook as StreamingPromptHook<M>>::on_tool_call_delta(
+            &self.inner,
+            tool_call_id,
+            tool_name,
+            tool_call_delta,
+            cancel_sig,
+        )
+        .await;
+    }
+
+    async fn on_stream_completion_response_finish(
+        &self,
+        prompt: &Message,
+        response: &M::StreamingResponse,
+        cancel_sig: CancelSignal,
+    ) {
+        <SessionIdHook as StreamingPromptHook<M>>::on_stream_completion_response_finish(
+            &self.inner,
+            prompt,
+            response,
+            cancel_sig,
+        )
+        .await;
+    }
+}
+
+/// 本轮的结果
+enum TurnOutcome {
+    Completed(FinalResponse),
+    Cancelled,
+    Failed(OxideError),
+}
+
+/// 进行中的一轮：编号与取消信号
+struct RunningTurn {
+    id: u64,
+    cancel: oneshot::Sender<()>,
+}
+
+/// 一个连接上打开的会话
+///
+/// 使用与 CLI 相同的 Agent、会话存储和 HITL 权限检查，同一时间只运行一轮。
+pub struct ServeSession {
+    id: String,
+    model: String,
+    agent: AgentType,
+    context: tokio::sync::Mutex<ContextManager>,
+    prompter: Arc<SessionPrompter>,
+    events: EventSink,
+    next_turn: AtomicU64,
+    running: Mutex<Option<RunningTurn>>,
+    reminders: ReminderTracker,
+    /// 工具调用超过该次数（或调用了子 agent）时发送活动回顾（`[render] turn_recap`）
+    turn_recap: usize,
+}
+
+impl ServeSession {
+    pub fn new(
+        context: ContextManager,
+        agent: AgentType,
+        model: String,
+        prompter: Arc<SessionPrompter>,
+        events: EventSink,
+    ) -> Self {
+        Self {
+            id: context.session_id().to_string(),
+            model,
+            agent,
+            context: tokio::sync::Mutex::new(context),
+            prompter,
+            events,
+            next_turn: AtomicU64::new(0),
+            running: Mutex::new(None),
+            reminders: ReminderTracker::from_config(),
+            turn_recap: RenderConfig::load().turn_recap,
+        }
+    }
+
+    pub fn prompter(&self) -> &SessionPrompter {
+        &self.prompter
+    }
+
+    /// 在后台开始新的一轮，已有一轮在运行时返回 false
+    pub fn send(self: &Arc<Self>, message: String) -> bool {
+        let turn_id = self.next_turn.fetch_add(1, Ordering::SeqCst) + 1;
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        {
+            let Ok(mut running) = self.running.lock() else {
+                return false;
+            };
+            if running.is_some() {
+                return false;
+            }
+            *running = Some(RunningTurn {
+                id: turn_id,
+                cancel: cancel_tx,
+            });
+        }
+
+        let session = Arc::clone(self);
+        tokio::spawn(async move {
+            session.run_turn(message, cancel_rx).await;
+            if let Ok(mut running) = session.running.lock() {
+                if running.as_ref().is_some_and(|turn| turn.id == turn_id) {
+                    *running = None;
+                }
+            }
+        });
+        true
+    }
+
+    /// 取消进行中的一轮，没有时返回 false
+    pub fn cancel(&self) -> bool {
+        let turn = self
+            .running
+            .lock()
+            .ok()
+            .and_then(|mut running| running.take());
+        self.prompter.reject_all();
+        match turn {
+            Some(turn) => {
+                let _ = turn.cancel.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn run_turn(&self, message: String, cancel: oneshot::Receiver<()>) {
+        let mut context = self.context.lock().await;
+        context.add_message(Message::user(&message));
+
+        // 项目记忆和系统提醒只随本轮请求发送，不写入对话历史
+        let reminders = self.reminders.begin_turn(false);
+        let prompt = with_project_memory(&with_reminders(&reminders, &message));
+        let history = context.prompt_history();
+        let turn = telemetry::turn_span(&self.id, &self.model, "serve");
+        let session_hook = SessionIdHook::new(self.id.clone())
+            .with_turn_span(turn.clone())
+            .with_reminders(self.reminders.clone());
+        let hook = EventHook {
+            inner: session_hook.clone(),
+            events: self.events.clone(),
+        };
+        let started = Instant::now();
+
+        let outcome = tokio::select! {
+            outcome = self.stream_turn(&prompt, hook, history).instrument(turn.clone()) => outcome,
+            _ = cancel => TurnOutcome::Cancelled,
+        };
+        self.reminders.end_turn();
+
+        match outcome {
+            TurnOutcome::Completed(response) => {
+                let text = response.response().to_string();
+                context.add_message(Message::assistant(&text));
+                let usage = response.usage();
+                crate::usage::record_turn(&self.id, &self.model, &usage);
+                self.events.send(Event::Usage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                    total_tokens: usage.total_tokens,
+                });
+                let stats = session_hook.turn_stats();
+                self.events.send(Event::TurnSummary {
+                    summary: TurnSummary {
+                        elapsed: started.elapsed(),
+                        tool_calls: stats.tool_calls,
+                        input_tokens: usage.input_tokens,
+                        output_tokens: usage.output_tokens,
+                        stop_reason: stats.stop_reason.unwrap_or(StopReason::EndTurn),
+                    },
+                });
+                let recap = session_hook.turn_recap(started.elapsed(), &usage);
+                if recap.is_notable(self.turn_recap) {
+                    if let Err(e) = context.record_turn_recap(&recap) {
+                        tracing::warn!(session.id = %self.id, "failed to save turn recap: {:#}", e);
+                    }
+                    self.events.send(Event::TurnRecap { recap });
+                }
+                turn.record("outcome", "completed");
+                self.events.send(Event::TurnCompleted { text });
+            }
+            // 重复的相同工具调用触发两次熔断后本轮被结束，以说明作为回复
+            TurnOutcome::Cancelled if loop_guard::should_stop(&self.id) => {
+                let text = t!("cli.loop_guard.stopped").to_string();
+                context.add_message(Message::assistant(&text));
+                turn.record("outcome", "cancelled");
+                self.events.send(Event::TurnCompleted { text });
+            }
+            TurnOutcome::Cancelled => {
+                // 与 CLI 一样把取消写入历史，让 Agent 知道操作被拒绝
+                context.add_message(Message::assistant(t!("cli.cancelled_history")));
+                turn.record("outcome", "cancelled");
+                turn.in_scope(|| telemetry::cancelled("prompt cancelled"));
+                self.events.send(Event::TurnCancelled);
+            }
+            TurnOutcome::Failed(error) => {
+                turn.record("outcome", "error");
+                turn.in_scope(|| telemetry::turn_failed(&error));
+                self.events.send(Event::turn_failed(&error));
+            }
+        }
+
+        if let Err(e) = context.save() {
+            tracing::warn!(session.id = %self.id, "failed to save session: {:#}", e);
+        }
+    }
+
+    async fn stream_turn(
+        &self,
+        prompt: &str,
+        hook: EventHook,
+        history: Vec<Message>,
+    ) -> TurnOutcome {
+        loop_guard::begin_turn(&self.id);
+        let mut stream = self.agent.stream_turn(prompt, history, hook).await;
+        forward_stream(&mut stream, &self.events).await
+    }
+}
+
+/// 把流中的文本转成协议事件，返回最终回复
+async fn forward_stream(stream: &mut TurnStream, events: &EventSink) -> TurnOutcome {
+    while let Some(event) = stream.next().await {
+        match event {
+            StreamEvent::TextDelta { text } => events.send(Event::TextDelta { text }),
+            event if event.is_cancelled() => return TurnOutcome::Cancelled,
+            StreamEvent::Error { message } => {
+                return TurnOutcome::Failed(OxideError::classify(message));
+            }
+            _ => {}
+        }
+    }
+    TurnOutcome::Completed(stream.take_final_response())
+}
@@ -0,0 +1,383 @@
+//! 嵌入用的会话入口
+//!
+//! `OxideSession` 把 Agent、会话存储、系统提醒和用量统计组合在一起，
+//! 供其他 Rust 程序直接驱动 oxide 的 Agent 循环，不需要启动终端界面。
+//! 需要确认的操作交给 `PermissionPrompter` 决定（默认全部拒绝），不会在终端提问。
+//! REPL 和 `oxide -p` 也通过它向模型发送请求（见 [`OxideSession::from_parts`]）。
+//!
+//! ```ignore
+//! let mut session = OxideSession::builder(Config::load()?)
+//!     .with_working_dir("/path/to/repo")
+//!     .with_agent_type(NewAgentType::CodeReviewer)
+//!     .build()?;
+//! let mut events = session.send("Review the staged changes");
+//! while let Some(event) = events.next().await {
+//!     // StreamEvent::TextDelta / ToolUseStart / Usage ...
+//! }
+//! ```
+
+use anyhow::{bail, Result};
+use futures::stream::{self, BoxStream};
+use futures::{future, StreamExt};
+use rig::agent::FinalResponse;
+use rig::completion::Message;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::agent::loop_guard;
+use crate::agent::reminder::with_reminders;
+use crate::agent::stream_event::{StreamEvent, TurnStream};
+use crate::agent::{
+    AgentBuilder, AgentType, HitlIntegration, NewAgentType, PermissionPolicy, PermissionPrompter,
+    ReminderTracker,
+};
+use crate::config::{BehaviorConfig, Config};
+use crate::context::{ContextManager, SessionEnvironment, TurnRecovery, SESSIONS_DIR};
+use crate::error::OxideError;
+use crate::hooks::SessionIdHook;
+use crate::mcp::McpManager;
+use crate::tools::memory::with_project_memory;
+use crate::tools::ToolSettings;
+use crate::usage::{self, UsageTotals};
+
+/// 未配置模型时使用的模型
+const DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
+
+/// 会话产生的警告（保存失败、出错时的处理建议）的去向
+pub type WarningSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// `OxideSession` 的构建器
+pub struct OxideSessionBuilder {
+    config: Config,
+    working_dir: PathBuf,
+    agent_type: NewAgentType,
+    tool_settings: Option<ToolSettings>,
+    permissions: Arc<dyn PermissionPrompter>,
+    session_id: Option<String>,
+    mcp: Option<Arc<McpManager>>,
+    warnings: Option<WarningSink>,
+}
+
+impl OxideSessionBuilder {
+    /// 使用 `config` 中的服务地址、密钥和模型（服务商由地址决定）
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            working_dir: PathBuf::from("."),
+            agent_type: NewAgentType::Main,
+            tool_settings: None,
+            permissions: Arc::new(PermissionPolicy::default()),
+            session_id: None,
+            mcp: None,
+            warnings: None,
+        }
+    }
+
+    /// 工作目录：工具相对路径的基准、命令的工作目录，会话保存在其下的 `.oxide/sessions`
+    pub fn with_working_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.working_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// 选择 Agent 类型（决定系统提示词和可用工具）
+    ///
+    /// 只有 Main Agent 的工具经过权限检查，其他类型与子 Agent 一样直接执行工具。
+    pub fn with_agent_type(mut self, agent_type: NewAgentType) -> Self {
+        self.agent_type = agent_type;
+        self
+    }
+
+    /// 设置工具默认设置（未设置根目录时使用工作目录）
+    pub fn with_tool_settings(mut self, settings: ToolSettings) -> Self {
+        self.tool_settings = Some(settings);
+        self
+    }
+
+    /// 由 `prompter` 决定需要确认的操作
+    pub fn with_permissions(mut self, prompter: Arc<dyn PermissionPrompter>) -> Self {
+        self.permissions = prompter;
+        self
+    }
+
+    /// 继续已有的会话
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// 设置 MCP 服务器（其工具只注册给 Main Agent）
+    pub fn with_mcp(mut self, mcp: Arc<McpManager>) -> Self {
+        self.mcp = Some(mcp);
+        self
+    }
+
+    /// 警告交给 `sink`（默认写入日志）
+    pub fn with_warnings(mut self, sink: WarningSink) -> Self {
+        self.warnings = Some(sink);
+        self
+    }
+
+    pub fn build(self) -> Result<OxideSession> {
+        self.config.validate()?;
+
+        let cwd =
+            std::fs::canonicalize(&self.working_dir).unwrap_or_else(|_| self.working_dir.clone());
+        // 文件工具和命令都以工作目录为基准，与宿主进程的当前目录无关
+        let mut tool_settings = self.tool_settings.unwrap_or_default();
+        if tool_settings.root.is_none() {
+            tool_settings.root = Some(cwd.clone());
+        }
+        let hitl = Arc::new(HitlIntegration::new()?.with_prompter(self.permissions));
+        let mut builder = AgentBuilder::new(
+            self.config.base_url.clone(),
+            self.config.auth_token.clone(),
+            self.config.model.clone(),
+        )
+        .with_hitl(hitl)
+        .with_tool_settings(tool_settings);
+        if let Some(mcp) = self.mcp {
+            builder = builder.with_mcp(mcp);
+        }
+        let agent = builder.build_with_type(self.agent_type)?;
+        let model = self
+            .config
+            .model
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        let environment = SessionEnvironment::capture(&cwd, agent.provider(), &model, "embedded");
+
+        let storage_dir = self.working_dir.join(SESSIONS_DIR);
+        let dedupe_file_reads = BehaviorConfig::load().dedupe_file_reads;
+        let context = match self.session_id {
+            Some(session_id) => {
+                let mut context = ContextManager::new(&storage_dir, session_id.clone())?
+                    .with_environment(environment)
+                    .with_dedupe_file_reads(dedupe_file_reads);
+                if !context.load()? {
+                    bail!("Session '{}' not found", session_id);
+                }
+                // 上次停在一轮中间时保留并注明中断
+                context.recover_interrupted_turn(TurnRecovery::Keep)?;
+                context
+            }
+            None => {
+                let session_id = ContextManager::generate_session_id(&storage_dir);
+                ContextManager::new(&storage_dir, session_id)?
+                    .with_environment(environment)
+                    .with_dedupe_file_reads(dedupe_file_reads)
+            }
+        };
+
+        let session = OxideSession::from_parts(agent, context, model);
+        Ok(match self.warnings {
+            Some(sink) => session.with_warnings(sink),
+            None => session,
+        })
+    }
+}
+
+/// 一个可以嵌入其他程序的对话会话
+pub struct OxideSession {
+    pub(crate) agent: AgentType,
+    pub(crate) context: ContextManager,
+    model: String,
+    reminders: ReminderTracker,
+    usage: UsageTotals,
+    /// 每轮结束后保存会话（`oxide -p` 不保存）
+    persist: bool,
+    warnings: WarningSink,
+}
+
+/// `send` 的进度
+enum SendState<'a> {
+    /// 本轮的输入，以及代替会话历史的历史（见 [`OxideSession::send_with_history`]）
+    Start(&'a mut OxideSession, String, Option<Vec<Message>>),
+    Streaming {
+        session: &'a mut OxideSession,
+        stream: TurnStream,
+        failed: bool,
+        injected: bool,
+    },
+    Done,
+}
+
+impl OxideSession {
+    pub fn builder(config: Config) -> OxideSessionBuilder {
+        OxideSessionBuilder::new(config)
+    }
+
+    /// 用已构建的 Agent 和会话历史创建
+    ///
+    /// REPL 和 `oxide -p` 按命令行参数自行构建 Agent（HITL、MCP、模式、录制回放），
+    /// 再通过会话发送请求。
+    pub(crate) fn from_parts(agent: AgentType, context: ContextManager, model: String) -> Self {
+        Self {
+            agent,
+            context,
+            model,
+            reminders: ReminderTracker::from_config(),
+            usage: UsageTotals::default(),
+            persist: true,
+            warnings: Arc::new(|message: &str| tracing::warn!("{}", message)),
+        }
+    }
+
+    /// 每轮结束后不保存会话
+    pub(crate) fn ephemeral(mut self) -> Self {
+        self.persist = false;
+        self
+    }
+
+    /// 警告交给 `sink`
+    pub(crate) fn with_warnings(mut self, sink: WarningSink) -> Self {
+        self.warnings = sink;
+        self
+    }
+
+    pub fn session_id(&self) -> &str {
+        self.context.session_id()
+    }
+
+    /// 发送一条消息，返回本轮的事件流
+    ///
+    /// 流结束时回复已写入历史并保存；中途丢弃流会中断本轮，
+    /// 下次加载会话时按中断的一轮处理。
+    pub fn send<'a>(&'a mut self, text: &str) -> BoxStream<'a, StreamEvent> {
+        self.send_turn(text, None)
+    }
+
+    /// 以 `history` 代替会话历史发送一轮，事件流与 [`OxideSession::send`] 相同
+    ///
+    /// 本轮的输入和回复不写入会话历史，只统计用量；用于调用方自行组织历史的请求
+    /// （如 `--output-schema` 校验失败后带着上一轮回答的修正请求）。
+    pub fn send_with_history<'a>(
+        &'a mut self,
+        text: &str,
+        history: Vec<Message>,
+    ) -> BoxStream<'a, StreamEvent> {
+        self.send_turn(text, Some(history))
+    }
+
+    fn send_turn<'a>(
+        &'a mut self,
+        text: &str,
+        history: Option<Vec<Message>>,
+    ) -> BoxStream<'a, StreamEvent> {
+        stream::unfold(
+            SendState::Start(self, text.to_string(), history),
+            |state| async move {
+                match state {
+                    SendState::Start(session, text, history) => {
+                        let injected = history.is_some();
+                        let stream = session.begin_turn(&text, history).await;
+                        Some((
+                            None,
+                            SendState::Streaming {
+                                session,
+                                stream,
+                                failed: false,
+                                injected,
+                            },
+                        ))
+                    }
+                    SendState::Streaming {
+                        session,
+                        mut stream,
+                        failed,
+                        injected,
+                    } => match stream.next().await {
+                        Some(event) => {
+                            if let StreamEvent::Error { message } = &event {
+                                session.warn_hint(message);
+                            }
+                            let failed = failed || matches!(event, StreamEvent::Error { .. });
+                            Some((
+                                Some(event),
+                                SendState::Streaming {
+                                    session,
+                                    stream,
+                                    failed,
+                                    injected,
+                                },
+                            ))
+                        }
+                        None => {
+                            let response = (!failed).then(|| stream.take_final_response());
+                            session.finish_turn(response, injected);
+                            Some((None, SendState::Done))
+                        }
+                    },
+                    SendState::Done => None,
+                }
+            },
+        )
+        .filter_map(future::ready)
+        .boxed()
+    }
+
+    /// 会话中的全部消息
+    pub fn history(&self) -> &[Message] {
+        self.context.get_messages()
+    }
+
+    /// 本会话（本进程中）已完成各轮的用量合计
+    pub fn usage(&self) -> &UsageTotals {
+        &self.usage
+    }
+
+    /// 用指定的历史向模型发送一次请求，不经过会话历史、系统提醒和用量统计
+    ///
+    /// REPL 自行管理每轮的历史和提醒，续写中断的回复、限时后的总结等请求都从这里发出。
+    pub(crate) async fn request(
+        &self,
+        prompt: &str,
+        history: Vec<Message>,
+        hook: SessionIdHook,
+    ) -> TurnStream {
+        loop_guard::begin_turn(self.context.session_id());
+        self.agent.stream_turn(prompt, history, hook).await
+    }
+
+    async fn begin_turn(&mut self, text: &str, history: Option<Vec<Message>>) -> TurnStream {
+        let history = match history {
+            Some(history) => history,
+            None => {
+                self.context.add_message(Message::user(text));
+                self.context.prompt_history()
+            }
+        };
+
+        // 项目记忆和系统提醒只随本轮请求发送，不写入对话历史
+        let reminders = self.reminders.begin_turn(false);
+        let prompt = with_project_memory(&with_reminders(&reminders, text));
+        let hook = SessionIdHook::new(self.context.session_id().to_string())
+            .with_reminders(self.reminders.clone());
+        self.request(&prompt, history, hook).await
+    }
+
+    /// 出错时不写入回复，历史停在本轮的用户消息；`injected` 的一轮只统计用量
+    fn finish_turn(&mut self, response: Option<FinalResponse>, injected: bool) {
+        self.reminders.end_turn();
+        if let Some(response) = response {
+            if !injected {
+                self.context
+                    .add_message(Message::assistant(response.response()));
+            }
+            let record =
+                usage::record_turn(self.context.session_id(), &self.model, &response.usage());
+            self.usage.add(&record);
+        }
+        if !self.persist || injected {
+            return;
+        }
+        if let Err(e) = self.context.save() {
+            (self.warnings)(&format!("Failed to save session: {:#}", e));
+        }
+    }
+
+    /// 出错时把处理建议交给警告去向
+    fn warn_hint(&self, message: &str) {
+        if let Some(hint) = OxideError::classify(message).user_hint() {
+            (self.warnings)(&format!("Tip: {}", hint));
+        }
+    }
+}
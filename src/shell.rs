@@ -0,0 +1,637 @@
+//! Shell 选择与进程树管理
+//!
+//! shell_execute 和后台任务通过这里构造命令。Unix 上使用 `sh -c`；Windows 上优先使用
+//! Git Bash，找不到时使用 PowerShell。`[tools] shell` 可以指定 `"bash"`、`"powershell"`、
+//! `"cmd"` 或 shell 可执行文件的路径（例如 WSL 的 `C:\Windows\System32\bash.exe`）。
+//!
+//! 后台进程和 shell_execute 的前台命令都放入 [`ProcessTree`]：Unix 上为独立的进程组，
+//! Windows 上为 Job Object，停止任务或命令超时时连同它派生的所有子进程一起结束。
+
+use crate::config::ConfigLoader;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// Shell 的命令行约定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    /// sh / bash 及兼容 shell（`-c <script>`）
+    Posix,
+    /// Windows PowerShell 或 PowerShell 7（`-Command <script>`）
+    PowerShell,
+    /// cmd.exe（`/C <script>`）
+    Cmd,
+}
+
+impl ShellKind {
+    /// 写给模型看的 shell 说明，用于工具描述
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ShellKind::Posix => "a POSIX shell (sh/bash)",
+            ShellKind::PowerShell => "PowerShell",
+            ShellKind::Cmd => "cmd.exe",
+        }
+    }
+}
+
+/// 执行命令使用的 shell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    kind: ShellKind,
+    program: PathBuf,
+}
+
+impl Shell {
+    pub fn new(kind: ShellKind, program: impl Into<PathBuf>) -> Self {
+        Self {
+            kind,
+            program: program.into(),
+        }
+    }
+
+    /// 按 `[tools.bash] shell` 或 `[tools] shell` 配置选择 shell，未配置时按平台自动选择
+    pub fn from_config() -> Self {
+        let tools = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.tools)
+            .unwrap_or_default();
+        Self::detect(tools.shell())
+    }
+
+    /// 根据配置值和当前平台选择 shell
+    pub fn detect(setting: Option<&str>) -> Self {
+        Self::detect_with(setting, cfg!(windows), &find_program)
+    }
+
+    /// [`Shell::detect`] 的实现，`find` 用于查找可执行文件，便于测试
+    fn detect_with(
+        setting: Option<&str>,
+        windows: bool,
+        find: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Self {
+        let setting = setting.map(str::trim).filter(|s| !s.is_empty());
+        match setting.map(str::to_lowercase).as_deref() {
+            Some("sh") => Self::new(ShellKind::Posix, "sh"),
+            Some("bash") if windows => {
+                let program = find("git-bash")
+                    .or_else(|| find("bash"))
+                    .unwrap_or_else(|| "bash".into());
+                Self::new(ShellKind::Posix, program)
+            }
+            Some("bash") => Self::new(ShellKind::Posix, "bash"),
+            Some("powershell") | Some("pwsh") => Self::powershell(windows, find),
+            Some("cmd") => Self::new(ShellKind::Cmd, "cmd"),
+            Some(_) => {
+                let path = setting.unwrap_or_default();
+                Self::new(kind_of(path), path)
+            }
+            None if windows => match find("git-bash") {
+                Some(bash) => Self::new(ShellKind::Posix, bash),
+                None => Self::powershell(windows, find),
+            },
+            None => Self::new(ShellKind::Posix, "sh"),
+        }
+    }
+
+    /// PowerShell 7（pwsh）优先，Windows 上回退到系统自带的 powershell.exe
+    fn powershell(windows: bool, find: &dyn Fn(&str) -> Option<PathBuf>) -> Self {
+        let program = match find("pwsh") {
+            Some(pwsh) => pwsh,
+            None if windows => "powershell".into(),
+            None => "pwsh".into(),
+        };
+        Self::new(ShellKind::PowerShell, program)
+    }
+
+    pub fn kind(&self) -> ShellKind {
+        self.kind
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /// 执行 `script` 的命令行参数（不含程序名）
+    ///
+    /// cmd.exe 不按 MSVC 规则解析引号，最后一个参数已用 `/S` 的规则加好引号，
+    /// 需要原样传给命令行（见 [`Shell::command`]）。
+    pub fn args(&self, script: &str) -> Vec<String> {
+        match self.kind {
+            ShellKind::Posix => vec!["-c".to_string(), script.to_string()],
+            ShellKind::PowerShell => vec![
+                "-NoLogo".to_string(),
+                "-NoProfile".to_string(),
+                "-NonInteractive".to_string(),
+                "-Command".to_string(),
+                // 输出统一为 UTF-8，与捕获端的解码方式一致
+                format!(
+                    "[Console]::OutputEncoding = [System.Text.Encoding]::UTF8; {}",
+                    script
+                ),
+            ],
+            ShellKind::Cmd => vec![
+                "/D".to_string(),
+                "/S".to_string(),
+                "/C".to_string(),
+                format!("\"{}\"", script),
+            ],
+        }
+    }
+
+    /// 构造执行 `script` 的命令
+    pub fn command(&self, script: &str) -> Command {
+        let mut cmd = Command::new(&self.program);
+        let args = self.args(script);
+        #[cfg(windows)]
+        if self.kind == ShellKind::Cmd {
+            use std::os::windows::process::CommandExt;
+            if let Some((line, rest)) = args.split_last() {
+                cmd.args(rest).raw_arg(line);
+                return cmd;
+            }
+        }
+        cmd.args(&args);
+        cmd
+    }
+
+    /// 把命令输出中的路径改写为 Read/Edit 等工具可以直接使用的形式
+    ///
+    /// 只在 Windows 上生效：Git Bash 的 `/c/Users/...` 改写为 `C:/Users/...`，
+    /// `C:\Users\...`、`.\src\main.rs` 中的反斜杠改写为 `/`。
+    pub fn normalize_output(&self, text: &str) -> String {
+        if cfg!(windows) {
+            normalize_paths(text, self.kind)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// 根据可执行文件名推断 shell 的命令行约定
+///
+/// 按 `/` 和 `\` 拆分路径，在任何平台上都能识别 Windows 风格的配置值。
+fn kind_of(path: &str) -> ShellKind {
+    let name = path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(path)
+        .to_lowercase();
+    match name.strip_suffix(".exe").unwrap_or(&name) {
+        "pwsh" | "powershell" => ShellKind::PowerShell,
+        "cmd" => ShellKind::Cmd,
+        _ => ShellKind::Posix,
+    }
+}
+
+/// 查找可执行文件
+///
+/// `"git-bash"` 表示 Git for Windows 自带的 bash：先查常见安装位置，再根据 PATH 中的
+/// git.exe 推断。不会自动选中 `System32\bash.exe`（WSL），WSL 看到的文件系统路径
+/// 与 Windows 不同，需要在 `[tools] shell` 中显式指定。
+fn find_program(name: &str) -> Option<PathBuf> {
+    if name == "git-bash" {
+        let installs = ["ProgramFiles", "ProgramFiles(x86)", "ProgramW6432"]
+            .into_iter()
+            .filter_map(|var| env::var_os(var).map(|dir| PathBuf::from(dir).join("Git")))
+            .chain(
+                env::var_os("LOCALAPPDATA")
+                    .map(|dir| PathBuf::from(dir).join("Programs").join("Git")),
+            )
+            // <Git>\cmd\git.exe 或 <Git>\bin\git.exe
+            .chain(
+                find_in_path("git").and_then(|git| git.parent()?.parent().map(Path::to_path_buf)),
+            );
+        return installs
+            .map(|git| git.join("bin").join("bash.exe"))
+            .find(|bash| bash.is_file());
+    }
+    find_in_path(name)
+}
+
+/// 在 PATH 中查找可执行文件（Windows 上补全 `.exe`）
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// MSYS 风格的盘符路径：`/c/Users/...`
+static MSYS_DRIVE_PATH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)(^|[\s'"=(\[])/([a-zA-Z])/"#).unwrap());
+
+/// 带反斜杠的绝对路径或 `.\`、`..\` 开头的相对路径
+static BACKSLASH_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?m)(?:\b[a-zA-Z]:\\|(?:^|[\s'"=(\[])\.{1,2}\\)[^\s'"<>|*?]*"#).unwrap()
+});
+
+/// 改写输出中的 Windows 路径分隔符（见 [`Shell::normalize_output`]）
+fn normalize_paths(text: &str, kind: ShellKind) -> String {
+    let text = if kind == ShellKind::Posix {
+        MSYS_DRIVE_PATH.replace_all(text, |caps: &Captures| {
+            format!("{}{}:/", &caps[1], caps[2].to_uppercase())
+        })
+    } else {
+        text.into()
+    };
+    BACKSLASH_PATH
+        .replace_all(&text, |caps: &Captures| caps[0].replace('\\', "/"))
+        .into_owned()
+}
+
+/// 一个子进程及其派生的所有进程
+///
+/// Unix 上为以子进程为组长的进程组，Windows 上为包含子进程的 Job Object
+/// （关闭句柄时结束其中所有进程，oxide 退出时不会留下孤儿进程）。
+pub struct ProcessTree {
+    pid: u32,
+    #[cfg(windows)]
+    job: windows::Job,
+}
+
+impl ProcessTree {
+    /// 让命令在独立的进程组中启动，之后用 [`ProcessTree::attach`] 接管
+    pub fn configure(cmd: &mut Command) -> &mut Command {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0)
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(windows::CREATE_NEW_PROCESS_GROUP)
+        }
+    }
+
+    /// 接管由 [`ProcessTree::configure`] 配置的命令启动的子进程
+    ///
+    /// Windows 上子进程在启动后才加入 Job Object，此前派生的进程不受管理；
+    /// shell 启动时还没有执行用户命令，实际上不会遗漏。
+    pub fn attach(child: &Child) -> std::io::Result<Self> {
+        #[cfg(windows)]
+        use std::os::windows::io::AsRawHandle;
+        Ok(Self {
+            pid: child.id(),
+            #[cfg(windows)]
+            job: windows::Job::assign(child.as_raw_handle())?,
+        })
+    }
+
+    /// 与 [`ProcessTree::attach`] 相同，用于 tokio 启动的子进程
+    pub fn attach_async(child: &tokio::process::Child) -> std::io::Result<Self> {
+        let exited = || std::io::Error::other("the child process has already been reaped");
+        Ok(Self {
+            pid: child.id().ok_or_else(exited)?,
+            #[cfg(windows)]
+            job: windows::Job::assign(child.raw_handle().ok_or_else(exited)?)?,
+        })
+    }
+
+    /// 子进程（进程组组长）的 PID
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// 请求所有进程退出：Unix 上发送 SIGTERM，Windows 上发送 CTRL_BREAK
+    pub fn terminate(&self) {
+        #[cfg(unix)]
+        signal_group(self.pid, libc::SIGTERM);
+        #[cfg(windows)]
+        windows::ctrl_break(self.pid);
+    }
+
+    /// 强制结束所有进程：Unix 上发送 SIGKILL，Windows 上结束 Job Object
+    pub fn kill(&self) {
+        #[cfg(unix)]
+        signal_group(self.pid, libc::SIGKILL);
+        #[cfg(windows)]
+        self.job.terminate();
+    }
+
+    /// 是否还有存活的进程
+    ///
+    /// Unix 上已退出但未回收的组长仍计为存活，调用方应先 `try_wait` 回收。
+    pub fn is_alive(&self) -> bool {
+        #[cfg(unix)]
+        {
+            // SAFETY: 信号 0 只检查进程组是否存在，不会实际发送信号
+            let ret = unsafe { libc::kill(-(self.pid as libc::pid_t), 0) };
+            ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+        }
+        #[cfg(windows)]
+        {
+            self.job.active_processes() > 0
+        }
+    }
+}
+
+/// 向整个进程组发送信号
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: libc::c_int) {
+    // 0 和 1 会把信号发给调用方自己的进程组或所有进程
+    if pgid <= 1 {
+        return;
+    }
+    // SAFETY: pgid 为本会话启动的进程组组长 PID
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), signal);
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::mem;
+    use std::os::windows::io::RawHandle;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicAccountingInformation,
+        JobObjectExtendedLimitInformation, QueryInformationJobObject, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    pub const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+    /// 被强制结束的进程的退出码
+    const KILLED_EXIT_CODE: u32 = 1;
+
+    /// Job Object 句柄，drop 时关闭（并结束其中的所有进程）
+    pub struct Job(HANDLE);
+
+    // SAFETY: Job Object 句柄可以在线程间传递和共享，相关 API 都是线程安全的
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        /// 创建 Job Object 并把子进程（`process` 为其句柄）加入其中
+        pub fn assign(process: RawHandle) -> io::Result<Self> {
+            // SAFETY: 参数均为空指针（默认安全属性、匿名）
+            let handle = unsafe { CreateJobObjectW(ptr::null(), ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Job(handle);
+
+            // SAFETY: 全零是 JOBOBJECT_EXTENDED_LIMIT_INFORMATION 的合法值
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { mem::zeroed() };
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            // SAFETY: 传入的结构体和长度与信息类别一致
+            let ok = unsafe {
+                SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &limits as *const _ as *const _,
+                    mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // SAFETY: 调用方持有子进程，句柄在调用期间有效
+            let ok = unsafe { AssignProcessToJobObject(job.0, process as HANDLE) };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        /// 结束 Job Object 中的所有进程
+        pub fn terminate(&self) {
+            // SAFETY: 句柄在 Job 存活期间有效
+            unsafe {
+                TerminateJobObject(self.0, KILLED_EXIT_CODE);
+            }
+        }
+
+        /// Job Object 中仍在运行的进程数
+        pub fn active_processes(&self) -> u32 {
+            // SAFETY: 全零是 JOBOBJECT_BASIC_ACCOUNTING_INFORMATION 的合法值
+            let mut info: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION = unsafe { mem::zeroed() };
+            // SAFETY: 传入的结构体和长度与信息类别一致
+            let ok = unsafe {
+                QueryInformationJobObject(
+                    self.0,
+                    JobObjectBasicAccountingInformation,
+                    &mut info as *mut _ as *mut _,
+                    mem::size_of::<JOBOBJECT_BASIC_ACCOUNTING_INFORMATION>() as u32,
+                    ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                0
+            } else {
+                info.ActiveProcesses
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: 句柄只在这里关闭一次
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    /// 向以 `pid` 为组长的进程组发送 CTRL_BREAK（控制台程序的优雅退出请求）
+    pub fn ctrl_break(pid: u32) {
+        // SAFETY: 只向 CREATE_NEW_PROCESS_GROUP 创建的进程组发送控制台事件
+        unsafe {
+            GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(names: &'static [&'static str]) -> impl Fn(&str) -> Option<PathBuf> {
+        move |name| {
+            names
+                .contains(&name)
+                .then(|| PathBuf::from(format!(r"C:\Tools\{}.exe", name)))
+        }
+    }
+
+    #[test]
+    fn test_detect_unix_defaults_to_sh() {
+        let shell = Shell::detect_with(None, false, &found(&["bash", "pwsh"]));
+        assert_eq!(shell, Shell::new(ShellKind::Posix, "sh"));
+        assert_eq!(shell.args("echo hi"), vec!["-c", "echo hi"]);
+    }
+
+    #[test]
+    fn test_detect_windows_prefers_git_bash() {
+        let shell = Shell::detect_with(None, true, &found(&["git-bash", "pwsh"]));
+        assert_eq!(shell.kind(), ShellKind::Posix);
+        assert_eq!(shell.program(), Path::new(r"C:\Tools\git-bash.exe"));
+
+        // 没有 Git Bash 时使用 PowerShell，pwsh 优先
+        let shell = Shell::detect_with(None, true, &found(&["pwsh"]));
+        assert_eq!(shell.kind(), ShellKind::PowerShell);
+        assert_eq!(shell.program(), Path::new(r"C:\Tools\pwsh.exe"));
+
+        let shell = Shell::detect_with(None, true, &found(&[]));
+        assert_eq!(shell, Shell::new(ShellKind::PowerShell, "powershell"));
+    }
+
+    #[test]
+    fn test_detect_configured_shell() {
+        let none = found(&[]);
+        assert_eq!(
+            Shell::detect_with(Some("cmd"), true, &none),
+            Shell::new(ShellKind::Cmd, "cmd")
+        );
+        assert_eq!(
+            Shell::detect_with(Some("PowerShell"), false, &none),
+            Shell::new(ShellKind::PowerShell, "pwsh")
+        );
+        assert_eq!(
+            Shell::detect_with(Some("bash"), true, &none),
+            Shell::new(ShellKind::Posix, "bash")
+        );
+        assert_eq!(
+            Shell::detect_with(Some("bash"), true, &found(&["bash"])).program(),
+            Path::new(r"C:\Tools\bash.exe")
+        );
+
+        // 路径按文件名推断命令行约定
+        let wsl = Shell::detect_with(Some(r"C:\Windows\System32\bash.exe"), true, &none);
+        assert_eq!(wsl.kind(), ShellKind::Posix);
+        assert_eq!(wsl.program(), Path::new(r"C:\Windows\System32\bash.exe"));
+        let pwsh = Shell::detect_with(Some("/usr/local/bin/pwsh"), false, &none);
+        assert_eq!(pwsh.kind(), ShellKind::PowerShell);
+        assert_eq!(
+            Shell::detect_with(Some(r"C:\Windows\System32\CMD.EXE"), true, &none).kind(),
+            ShellKind::Cmd
+        );
+
+        // 空字符串等同于未配置
+        assert_eq!(
+            Shell::detect_with(Some("  "), false, &none),
+            Shell::new(ShellKind::Posix, "sh")
+        );
+    }
+
+    #[test]
+    fn test_command_assembly() {
+        let powershell = Shell::new(ShellKind::PowerShell, "powershell");
+        let args = powershell.args("Get-ChildItem");
+        assert_eq!(
+            args[..4],
+            ["-NoLogo", "-NoProfile", "-NonInteractive", "-Command"]
+        );
+        assert!(args[4].ends_with("; Get-ChildItem"));
+
+        let cmd = Shell::new(ShellKind::Cmd, "cmd");
+        assert_eq!(
+            cmd.args(r#"dir "C:\Program Files""#),
+            vec!["/D", "/S", "/C", r#""dir "C:\Program Files"""#]
+        );
+
+        let command = Shell::new(ShellKind::Posix, "sh").command("echo hi");
+        assert_eq!(command.get_program(), "sh");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["-c", "echo hi"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_paths() {
+        // Git Bash 输出
+        assert_eq!(
+            normalize_paths(
+                "/c/Users/dev/project/src/main.rs:12: error",
+                ShellKind::Posix
+            ),
+            "C:/Users/dev/project/src/main.rs:12: error"
+        );
+        assert_eq!(
+            normalize_paths("cd '/d/work' && ls", ShellKind::Posix),
+            "cd 'D:/work' && ls"
+        );
+        // 普通的 Unix 路径和 URL 不受影响
+        assert_eq!(
+            normalize_paths("see https://example.com/a/b and /usr/bin", ShellKind::Posix),
+            "see https://example.com/a/b and /usr/bin"
+        );
+
+        // PowerShell / cmd 输出
+        assert_eq!(
+            normalize_paths(
+                r"    Directory: C:\Users\dev\project",
+                ShellKind::PowerShell
+            ),
+            "    Directory: C:/Users/dev/project"
+        );
+        assert_eq!(
+            normalize_paths(r"modified: .\src\tools\mod.rs", ShellKind::Cmd),
+            "modified: ./src/tools/mod.rs"
+        );
+        // 转义序列等非路径的反斜杠保持不变
+        assert_eq!(
+            normalize_paths(r#"println!("a\nb")"#, ShellKind::PowerShell),
+            r#"println!("a\nb")"#
+        );
+    }
+
+    /// 派生进程的结束由 TaskManager::stop_task 的测试覆盖
+    #[cfg(unix)]
+    #[test]
+    fn test_process_tree_kill() {
+        use std::time::{Duration, Instant};
+
+        let mut cmd = Shell::new(ShellKind::Posix, "sh").command("exec sleep 30");
+        let mut child = ProcessTree::configure(&mut cmd).spawn().unwrap();
+        let tree = ProcessTree::attach(&child).unwrap();
+        assert_eq!(tree.id(), child.id());
+        assert!(tree.is_alive());
+
+        tree.kill();
+        child.wait().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while tree.is_alive() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!tree.is_alive());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_process_tree_kills_descendants() {
+        use std::time::{Duration, Instant};
+
+        // cmd 派生的 ping 也在 Job Object 中
+        let mut cmd = Shell::new(ShellKind::Cmd, "cmd").command("ping -n 30 127.0.0.1 >NUL");
+        let mut child = ProcessTree::configure(&mut cmd).spawn().unwrap();
+        let tree = ProcessTree::attach(&child).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while tree.job.active_processes() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(tree.job.active_processes() >= 2);
+
+        tree.kill();
+        child.wait().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while tree.is_alive() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!tree.is_alive());
+    }
+}
@@ -0,0 +1,209 @@
+//! 启动阶段计时与后台预热
+//!
+//! 提示符出现之前只做必需的工作（配置、Agent、会话）；MCP 服务器和技能在后台启动，
+//! 第一条消息发送前等待它们完成（[`Warmup`]）。`oxide --profile-startup` 在提示符
+//! 出现时向 stderr 打印各阶段耗时，后台阶段在完成后补充打印。
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::mcp::McpManager;
+use crate::skill::SkillManager;
+
+/// 提示符出现时打印的总耗时标签（启动计时测试按此解析）
+pub const TIME_TO_PROMPT: &str = "time to prompt";
+
+/// 阶段名的对齐宽度
+const NAME_WIDTH: usize = 18;
+
+#[derive(Debug)]
+struct ProfileState {
+    started: Instant,
+    last: Instant,
+    phases: Vec<(String, Duration)>,
+}
+
+/// 启动阶段计时（未开启时所有方法都是空操作）
+#[derive(Debug, Clone, Default)]
+pub struct StartupProfile {
+    state: Option<Arc<Mutex<ProfileState>>>,
+}
+
+impl StartupProfile {
+    /// `started` 为进程开始的时间
+    pub fn new(started: Instant, enabled: bool) -> Self {
+        let state = enabled.then(|| {
+            Arc::new(Mutex::new(ProfileState {
+                started,
+                last: started,
+                phases: Vec::new(),
+            }))
+        });
+        Self { state }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// 结束一个顺序阶段：记录从上一个阶段结束到现在的耗时
+    pub fn phase(&self, name: &str) {
+        self.with_state(|state| {
+            let now = Instant::now();
+            let elapsed = now - state.last;
+            state.last = now;
+            state.phases.push((name.to_string(), elapsed));
+        });
+    }
+
+    /// 记录一个单独计时的阶段（后台任务）
+    pub fn record(&self, name: &str, elapsed: Duration) {
+        self.with_state(|state| state.phases.push((name.to_string(), elapsed)));
+    }
+
+    /// 提示符出现：返回已记录的阶段和总耗时，并清空已记录的阶段
+    pub fn prompt_ready(&self) -> Option<String> {
+        let mut report = None;
+        self.with_state(|state| {
+            let total = state.started.elapsed();
+            let mut phases = std::mem::take(&mut state.phases);
+            phases.push((TIME_TO_PROMPT.to_string(), total));
+            report = Some(format_phases(&phases));
+        });
+        report
+    }
+
+    /// 后台阶段完成后补充打印的内容（没有新阶段时为 `None`）
+    pub fn take_background(&self) -> Option<String> {
+        let mut report = None;
+        self.with_state(|state| {
+            let phases = std::mem::take(&mut state.phases);
+            if !phases.is_empty() {
+                report = Some(format_phases(&phases));
+            }
+        });
+        report
+    }
+
+    fn with_state(&self, f: impl FnOnce(&mut ProfileState)) {
+        if let Some(state) = &self.state {
+            if let Ok(mut state) = state.lock() {
+                f(&mut state);
+            }
+        }
+    }
+}
+
+fn format_phases(phases: &[(String, Duration)]) -> String {
+    phases
+        .iter()
+        .map(|(name, elapsed)| {
+            format!(
+                "startup  {:<width$} {:>8.1} ms",
+                name,
+                elapsed.as_secs_f64() * 1000.0,
+                width = NAME_WIDTH
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 在后台进行的启动工作，第一条消息发送前等待
+pub struct Warmup {
+    handle: tokio::task::JoinHandle<Vec<String>>,
+}
+
+impl Warmup {
+    /// 并发启动 MCP 服务器并加载技能，结果为需要提示用户的警告
+    pub fn spawn(mcp: Arc<McpManager>, profile: StartupProfile) -> Self {
+        let handle = tokio::spawn(async move {
+            let skills_profile = profile.clone();
+            let skills = tokio::task::spawn_blocking(move || {
+                let started = Instant::now();
+                let result = SkillManager::new().and_then(|manager| manager.init());
+                skills_profile.record("skills (bg)", started.elapsed());
+                result
+            });
+            let started = Instant::now();
+            let mut warnings: Vec<String> = mcp
+                .start_all()
+                .await
+                .into_iter()
+                .map(|(name, error)| {
+                    format!("Warning: MCP server '{}' failed to start: {}", name, error)
+                })
+                .collect();
+            if !mcp.is_empty() {
+                profile.record("mcp servers (bg)", started.elapsed());
+            }
+            match skills.await {
+                Ok(Err(e)) => warnings.push(format!("Warning: Failed to load skills: {:#}", e)),
+                Err(e) => warnings.push(format!("Warning: Failed to load skills: {}", e)),
+                Ok(Ok(())) => {}
+            }
+            warnings
+        });
+        Self { handle }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// 等待完成，返回警告
+    pub async fn wait(self) -> Vec<String> {
+        match self.handle.await {
+            Ok(warnings) => warnings,
+            Err(e) => vec![format!("Warning: Startup task failed: {}", e)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_profile_reports_nothing() {
+        let profile = StartupProfile::new(Instant::now(), false);
+        profile.phase("config");
+        assert!(!profile.is_enabled());
+        assert_eq!(profile.prompt_ready(), None);
+        assert_eq!(profile.take_background(), None);
+    }
+
+    #[test]
+    fn test_report_lists_phases_then_total() {
+        let started = Instant::now() - Duration::from_millis(50);
+        let profile = StartupProfile::new(started, true);
+        profile.phase("config");
+        profile.record("skills (bg)", Duration::from_micros(2500));
+
+        let report = profile.prompt_ready().unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("startup  config "));
+        assert_eq!(
+            lines[1],
+            format!("startup  {:<18}      2.5 ms", "skills (bg)")
+        );
+        assert!(lines[2].starts_with(&format!("startup  {}", TIME_TO_PROMPT)));
+        let total: f64 = lines[2]
+            .split_whitespace()
+            .rev()
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(total >= 50.0);
+
+        // 已打印的阶段不再重复，后台阶段单独补充
+        assert_eq!(profile.take_background(), None);
+        profile.record("mcp servers (bg)", Duration::from_millis(120));
+        assert_eq!(
+            profile.take_background().unwrap(),
+            format!("startup  {:<18}    120.0 ms", "mcp servers (bg)")
+        );
+    }
+}
@@ -3,17 +3,31 @@
 //! 管理后台任务的创建、执行和追踪。
 
 use crate::agent::types::AgentType;
+use crate::atomic_file::{read_json_or_recover, write_atomic};
+use crate::config::{ConfigLoader, TasksConfig};
+use crate::file_lock::{FileLock, FileLockError, DEFAULT_LOCK_TIMEOUT};
+use crate::shell::ProcessTree;
+use crate::task::output::{read_output, remove_output, OutputWriter};
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+/// 停止后台进程时默认的优雅退出等待时间
+pub const DEFAULT_STOP_GRACE: Duration = Duration::from_secs(5);
+
+/// 检查后台进程状态的轮询间隔
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 /// 任务 ID 类型
 pub type TaskId = String;
 
@@ -35,6 +49,39 @@ pub enum TaskStatus {
 
     /// 已删除
     Deleted,
+
+    /// 已被用户取消
+    Cancelled,
+}
+
+impl std::str::FromStr for TaskStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pending" => Ok(TaskStatus::Pending),
+            "in_progress" => Ok(TaskStatus::InProgress),
+            "completed" => Ok(TaskStatus::Completed),
+            "failed" => Ok(TaskStatus::Failed),
+            "deleted" => Ok(TaskStatus::Deleted),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            other => Err(format!("Invalid status: '{}'", other)),
+        }
+    }
+}
+
+/// 后台进程的终止方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// 进程组在等待时间内自行退出
+    Graceful,
+
+    /// 超过等待时间后被强制结束
+    Forced,
+
+    /// 进程不在当前会话中运行（已退出，或由其他 oxide 实例启动）
+    NotRunning,
 }
 
 /// 任务信息
@@ -93,6 +140,18 @@ pub struct Task {
 
     /// 错误信息
     pub error: Option<String>,
+
+    /// 后台进程的进程组 ID（仅进程类任务）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_group: Option<u32>,
+
+    /// 输出文件因超过大小上限而轮转的次数
+    #[serde(default)]
+    pub output_rotations: u32,
+
+    /// 轮转时被丢弃的最早输出的字节数（大于 0 表示输出不完整）
+    #[serde(default)]
+    pub output_discarded_bytes: u64,
 }
 
 impl Task {
@@ -119,6 +178,9 @@ impl Task {
             completed_at: None,
             output_file: None,
             error: None,
+            process_group: None,
+            output_rotations: 0,
+            output_discarded_bytes: 0,
         }
     }
 
@@ -151,6 +213,9 @@ impl Task {
             completed_at: None,
             output_file: None,
             error: None,
+            process_group: None,
+            output_rotations: 0,
+            output_discarded_bytes: 0,
         }
     }
 
@@ -225,6 +290,146 @@ impl Task {
     }
 }
 
+/// 任务查询条件（TaskList 工具与 `/tasks list` 共用）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskFilter {
+    /// 只保留这些状态；为空时保留除 Deleted 以外的所有任务
+    pub statuses: Vec<TaskStatus>,
+
+    /// 只保留该所有者的任务
+    pub owner: Option<String>,
+
+    /// 在标题和描述中搜索（不区分大小写）
+    pub query: Option<String>,
+
+    /// 包括超过保留期的已完成任务
+    pub include_all: bool,
+
+    /// 最多返回的任务数（None 表示不限）
+    pub limit: Option<usize>,
+
+    /// 跳过的任务数
+    pub offset: usize,
+}
+
+impl TaskFilter {
+    /// 解析 `/tasks list` 的参数：
+    /// `--status <s>[,<s>...]`（可重复）、`--owner <name>`、`--limit <n>`、`--offset <n>`、`--all`，
+    /// 其余文本作为搜索词
+    pub fn from_args(args: &str) -> std::result::Result<Self, String> {
+        let mut filter = TaskFilter::default();
+        let mut query = Vec::new();
+        let mut tokens = args.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            let mut value = |flag: &str| {
+                tokens
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", flag))
+            };
+            match token {
+                "--status" => {
+                    for status in value(token)?.split(',').filter(|s| !s.is_empty()) {
+                        filter.statuses.push(status.parse()?);
+                    }
+                }
+                "--owner" => filter.owner = Some(value(token)?.to_string()),
+                "--limit" => {
+                    let limit = value(token)?;
+                    filter.limit = Some(
+                        limit
+                            .parse()
+                            .map_err(|_| format!("Invalid limit: '{}'", limit))?,
+                    );
+                }
+                "--offset" => {
+                    let offset = value(token)?;
+                    filter.offset = offset
+                        .parse()
+                        .map_err(|_| format!("Invalid offset: '{}'", offset))?;
+                }
+                "--all" => filter.include_all = true,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+                word => query.push(word),
+            }
+        }
+
+        if !query.is_empty() {
+            filter.query = Some(query.join(" "));
+        }
+        Ok(filter)
+    }
+
+    /// 任务是否满足状态、所有者和搜索条件
+    fn matches(&self, task: &Task) -> bool {
+        let status_ok = if self.statuses.is_empty() {
+            task.status != TaskStatus::Deleted
+        } else {
+            self.statuses.contains(&task.status)
+        };
+        let owner_ok = self
+            .owner
+            .as_ref()
+            .is_none_or(|owner| task.owner.as_deref() == Some(owner.as_str()));
+        let query_ok = self.query.as_ref().is_none_or(|query| {
+            let query = query.to_lowercase();
+            task.subject.to_lowercase().contains(&query)
+                || task.description.to_lowercase().contains(&query)
+        });
+        status_ok && owner_ok && query_ok
+    }
+}
+
+/// 一页查询结果
+#[derive(Debug, Clone)]
+pub struct TaskPage {
+    /// 本页的任务，按最后更新时间倒序
+    pub tasks: Vec<Task>,
+
+    /// 满足条件的任务总数（分页之前）
+    pub total: usize,
+
+    /// 因超过保留期而隐藏的已完成任务数
+    pub hidden_completed: usize,
+}
+
+/// 当前会话启动的后台进程
+struct ProcessHandle {
+    child: Child,
+
+    /// 子进程及其派生的进程（Unix 上为进程组，Windows 上为 Job Object）
+    tree: ProcessTree,
+
+    /// 任务输出，stdout/stderr 捕获线程与停止标记共用
+    output: Arc<OutputSink>,
+
+    /// stdout/stderr 捕获线程，管道关闭（输出读完）时结束
+    captures: Vec<std::thread::JoinHandle<()>>,
+}
+
+/// 后台进程输出的写入端：超过大小上限时轮转，并把轮转情况记录到任务元数据
+struct OutputSink {
+    writer: Mutex<OutputWriter>,
+    task_id: TaskId,
+    tasks: Arc<Mutex<HashMap<TaskId, Task>>>,
+    storage_dir: PathBuf,
+}
+
+impl OutputSink {
+    fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write(data, |rotations, discarded_bytes| {
+            let mut tasks = self.tasks.lock().unwrap();
+            if let Some(task) = tasks.get_mut(&self.task_id) {
+                task.output_rotations = rotations;
+                task.output_discarded_bytes = discarded_bytes;
+                task.updated_at = Utc::now();
+                let _ = save_task_to(&self.storage_dir, task);
+            }
+        })
+    }
+}
+
 /// 任务管理器
 pub struct TaskManager {
     /// 任务存储
@@ -233,16 +438,45 @@ pub struct TaskManager {
     /// 活跃的异步任务句柄
     active_handles: Arc<Mutex<HashMap<TaskId, JoinHandle<()>>>>,
 
+    /// 当前会话启动、尚未结束的后台进程
+    processes: Arc<Mutex<HashMap<TaskId, ProcessHandle>>>,
+
     /// 存储目录
     storage_dir: PathBuf,
+
+    /// 后台任务设置（`[tasks]`）：输出上限、轮转数量和已完成任务的保留期
+    config: TasksConfig,
+}
+
+/// 把任务写入存储目录（见 [`TaskManager::save_task`]）
+fn save_task_to(storage_dir: &Path, task: &Task) -> Result<()> {
+    let path = storage_dir.join(format!("{}.json", task.id));
+    let json = serde_json::to_string_pretty(task).context(t!("task.serialize_failed"))?;
+    let _lock = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT).map_err(|e| match e {
+        FileLockError::Timeout(_) => anyhow!(t!("task.locked", id = task.id)),
+        e => e.into(),
+    })?;
+    write_atomic(&path, json.as_bytes()).context(t!("task.write_failed", path = path.display()))?;
+    Ok(())
 }
 
 /// 全局任务管理器单例
 static TASK_MANAGER: Lazy<TaskManager> = Lazy::new(|| {
     let storage_dir = PathBuf::from(".oxide/tasks");
-    TaskManager::new(storage_dir).expect("无法初始化任务管理器")
+    TaskManager::new(storage_dir)
+        .expect(t!("task.init_failed"))
+        .with_config(load_tasks_config())
 });
 
+/// 读取配置文件中的 `[tasks]` 设置，未配置时使用默认值
+pub fn load_tasks_config() -> TasksConfig {
+    ConfigLoader::new()
+        .load_toml_layers()
+        .ok()
+        .and_then(|config| config.tasks)
+        .unwrap_or_default()
+}
+
 /// 获取全局任务管理器
 pub fn get_task_manager() -> &'static TaskManager {
     &TASK_MANAGER
@@ -253,15 +487,23 @@ impl TaskManager {
     pub fn new(storage_dir: PathBuf) -> Result<Self> {
         // 确保存储目录存在
         fs::create_dir_all(&storage_dir)
-            .context(format!("无法创建任务存储目录: {}", storage_dir.display()))?;
+            .context(t!("task.create_dir_failed", path = storage_dir.display()))?;
 
         Ok(Self {
             tasks: Arc::new(Mutex::new(HashMap::new())),
             active_handles: Arc::new(Mutex::new(HashMap::new())),
+            processes: Arc::new(Mutex::new(HashMap::new())),
             storage_dir,
+            config: TasksConfig::default(),
         })
     }
 
+    /// 使用指定的 `[tasks]` 设置
+    pub fn with_config(mut self, config: TasksConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// 创建任务存储目录路径
     fn task_storage_path(&self, task_id: &TaskId) -> PathBuf {
         self.storage_dir.join(format!("{}.json", task_id))
@@ -273,27 +515,16 @@ impl TaskManager {
     }
 
     /// 保存任务到磁盘
+    ///
+    /// 持有任务文件锁并原子替换，多个 oxide 实例同时更新同一任务时不会写出残缺的 JSON。
     fn save_task(&self, task: &Task) -> Result<()> {
-        let path = self.task_storage_path(&task.id);
-        let json = serde_json::to_string_pretty(task)
-            .context("序列化任务失败")?;
-        fs::write(&path, json)
-            .context(format!("无法写入任务文件: {}", path.display()))?;
-        Ok(())
+        save_task_to(&self.storage_dir, task)
     }
 
-    /// 从磁盘加载任务
+    /// 从磁盘加载任务（损坏的任务文件移到一旁，按任务不存在处理）
     fn load_task(&self, task_id: &TaskId) -> Result<Option<Task>> {
         let path = self.task_storage_path(task_id);
-        if !path.exists() {
-            return Ok(None);
-        }
-
-        let content = fs::read_to_string(&path)
-            .context(format!("无法读取任务文件: {}", path.display()))?;
-        let task: Task = serde_json::from_str(&content)
-            .context(format!("解析任务文件失败: {}", path.display()))?;
-        Ok(Some(task))
+        read_json_or_recover(&path).context(t!("task.read_failed", path = path.display()))
     }
 
     /// 创建新任务（简化版，用于任务管理工具）
@@ -369,18 +600,17 @@ impl TaskManager {
         let mut tasks = Vec::new();
 
         if self.storage_dir.exists() {
-            for entry in fs::read_dir(&self.storage_dir)
-                .context(format!("无法读取存储目录: {}", self.storage_dir.display()))?
-            {
+            for entry in fs::read_dir(&self.storage_dir).context(t!(
+                "task.read_dir_failed",
+                path = self.storage_dir.display()
+            ))? {
                 let entry = entry?;
                 let path = entry.path();
 
-                // 只处理 .json 文件
+                // 只处理 .json 文件；同目录下 task 工具的元数据不是 Task，跳过
                 if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        if let Ok(task) = serde_json::from_str::<Task>(&content) {
-                            tasks.push(task);
-                        }
+                    if let Ok(Some(task)) = read_json_or_recover::<Task>(&path) {
+                        tasks.push(task);
                     }
                 }
             }
@@ -392,6 +622,51 @@ impl TaskManager {
         Ok(tasks)
     }
 
+    /// 按条件查询任务，结果按最后更新时间倒序分页返回
+    ///
+    /// 除非 `include_all`，完成时间早于 `completed_retention_days` 的已完成任务不计入结果。
+    pub fn query_tasks(&self, filter: &TaskFilter) -> Result<TaskPage> {
+        let retention_cutoff = (!filter.include_all && self.config.completed_retention_days > 0)
+            .then(|| {
+                Utc::now() - chrono::Duration::days(self.config.completed_retention_days as i64)
+            });
+
+        let mut hidden_completed = 0;
+        let mut tasks: Vec<Task> = self
+            .list_tasks()?
+            .into_iter()
+            .filter(|task| filter.matches(task))
+            .filter(|task| {
+                let expired = task.status == TaskStatus::Completed
+                    && retention_cutoff.is_some_and(|cutoff| {
+                        task.completed_at.unwrap_or(task.updated_at) < cutoff
+                    });
+                if expired {
+                    hidden_completed += 1;
+                }
+                !expired
+            })
+            .collect();
+
+        tasks.sort_by(|a, b| {
+            b.updated_at
+                .cmp(&a.updated_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        let total = tasks.len();
+        let tasks = tasks
+            .into_iter()
+            .skip(filter.offset)
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(TaskPage {
+            tasks,
+            total,
+            hidden_completed,
+        })
+    }
+
     /// 更新任务状态
     pub fn update_task_status(&self, task_id: &TaskId, status: TaskStatus) -> Result<()> {
         let mut tasks = self.tasks.lock().unwrap();
@@ -406,7 +681,10 @@ impl TaskManager {
                         task.started_at = Some(Utc::now());
                     }
                 }
-                TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Deleted => {
+                TaskStatus::Completed
+                | TaskStatus::Failed
+                | TaskStatus::Deleted
+                | TaskStatus::Cancelled => {
                     if task.completed_at.is_none() {
                         task.completed_at = Some(Utc::now());
                     }
@@ -443,11 +721,11 @@ impl TaskManager {
     pub fn add_blocks(&self, task_id: &TaskId, blocked_task_id: &TaskId) -> Result<()> {
         // 检查循环依赖
         if self.would_create_cycle(blocked_task_id, task_id)? {
-            return Err(anyhow!(
-                "添加依赖关系会导致循环依赖: {} -> {}",
-                task_id,
-                blocked_task_id
-            ));
+            return Err(anyhow!(t!(
+                "task.cycle",
+                from = task_id,
+                to = blocked_task_id
+            )));
         }
 
         // 更新 task_id 的 blocks 列表
@@ -544,13 +822,22 @@ impl TaskManager {
     }
 
     /// 获取任务输出
+    ///
+    /// 依次拼接保留下来的轮转文件和当前输出文件；被丢弃的部分记录在
+    /// `Task::output_discarded_bytes` 中。
     pub fn get_task_output(&self, task_id: &TaskId) -> Result<Option<String>> {
         let output_path = self.task_output_path(task_id);
 
         if output_path.exists() {
-            let content = fs::read_to_string(&output_path)
-                .context(format!("无法读取任务输出: {}", output_path.display()))?;
-            Ok(Some(content))
+            let chunk = read_output(&output_path, 0, || {
+                self.get_task(task_id)
+                    .ok()
+                    .flatten()
+                    .map(|task| task.output_discarded_bytes)
+                    .unwrap_or(0)
+            })
+            .context(t!("task.read_output_failed", path = output_path.display()))?;
+            Ok(Some(String::from_utf8_lossy(&chunk.data).into_owned()))
         } else {
             Ok(None)
         }
@@ -573,6 +860,198 @@ impl TaskManager {
         }
     }
 
+    /// 在后台启动 shell 命令，并创建对应的进行中任务
+    ///
+    /// `cmd` 由调用方按 `command` 构造好（shell、工作目录、沙箱，见 shell_execute 的
+    /// `run_in_background`），这里把它放入 [`ProcessTree`]（Unix 上为新的进程组，
+    /// Windows 上为 Job Object），停止任务时可以连同它派生的子进程一起结束；stdout/stderr 追加写入任务输出文件，
+    /// 超过 `max_output_bytes` 时按 [`OutputWriter`] 的规则轮转。
+    /// 进程自行退出后任务按退出码标记为 Completed 或 Failed。
+    pub fn spawn_process(
+        &self,
+        subject: String,
+        command: &str,
+        mut cmd: std::process::Command,
+    ) -> Result<Task> {
+        let mut task = Task::new(subject, command.to_string(), None);
+        let output_path = self.task_output_path(&task.id);
+        let writer = OutputWriter::create(&output_path, &self.config).context(t!(
+            "task.create_output_failed",
+            path = output_path.display()
+        ))?;
+        let output = Arc::new(OutputSink {
+            writer: Mutex::new(writer),
+            task_id: task.id.clone(),
+            tasks: self.tasks.clone(),
+            storage_dir: self.storage_dir.clone(),
+        });
+
+        let mut child = ProcessTree::configure(&mut cmd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(t!("task.spawn_failed", command = command))?;
+        let mut captures = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            captures.push(spawn_capture(stdout, output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            captures.push(spawn_capture(stderr, output.clone()));
+        }
+
+        let tree = match ProcessTree::attach(&child) {
+            Ok(tree) => tree,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow!(t!("task.process_tree_failed", error = e)));
+            }
+        };
+        task.process_group = Some(tree.id());
+        task.output_file = Some(output_path);
+        task.mark_in_progress();
+        self.save_task(&task)?;
+        self.tasks.lock().unwrap().insert(task.id.clone(), task.clone());
+        self.processes
+            .lock()
+            .unwrap()
+            .insert(
+                task.id.clone(),
+                ProcessHandle {
+                    child,
+                    tree,
+                    output,
+                    captures,
+                },
+            );
+
+        self.spawn_exit_watcher(task.id.clone());
+        Ok(task)
+    }
+
+    /// 等待进程自行退出并更新任务状态；进程被 `stop_task` 接管后结束
+    fn spawn_exit_watcher(&self, task_id: TaskId) {
+        let processes = self.processes.clone();
+        let tasks = self.tasks.clone();
+        let storage_dir = self.storage_dir.clone();
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(PROCESS_POLL_INTERVAL);
+            let (status, handle) = {
+                let mut processes = processes.lock().unwrap();
+                let Some(handle) = processes.get_mut(&task_id) else {
+                    return;
+                };
+                match handle.child.try_wait() {
+                    Ok(None) => continue,
+                    Ok(Some(status)) => (status, processes.remove(&task_id)),
+                    Err(_) => {
+                        processes.remove(&task_id);
+                        return;
+                    }
+                }
+            };
+            // 输出全部写入后才标记结束，读取已结束任务的输出时不会缺少末尾
+            for capture in handle.into_iter().flat_map(|handle| handle.captures) {
+                let _ = capture.join();
+            }
+
+            let mut tasks = tasks.lock().unwrap();
+            if let Some(task) = tasks.get_mut(&task_id) {
+                if status.success() {
+                    task.status = TaskStatus::Completed;
+                } else {
+                    task.status = TaskStatus::Failed;
+                    task.error = Some(t!("task.exited", status = status));
+                }
+                task.completed_at = Some(Utc::now());
+                task.updated_at = Utc::now();
+                let _ = save_task_to(&storage_dir, task);
+            }
+            return;
+        });
+    }
+
+    /// 停止后台进程任务
+    ///
+    /// 先向整个进程组发送 SIGTERM（Windows 上为 CTRL_BREAK），最多等待 `grace`；
+    /// 仍未退出时发送 SIGKILL（Windows 上结束 Job Object）。停止后任务标记为 Cancelled，
+    /// 并在输出文件末尾追加一行说明。任务不存在或不是进程任务时返回 `None`。
+    pub async fn stop_task(&self, task_id: &TaskId, grace: Duration) -> Result<Option<Termination>> {
+        let Some(task) = self.get_task(task_id)? else {
+            return Ok(None);
+        };
+        if task.process_group.is_none() {
+            return Ok(None);
+        }
+
+        // 只处理本会话启动的进程：持久化的进程组 ID 在重启后可能已被其他进程复用
+        let Some(mut handle) = self.processes.lock().unwrap().remove(task_id) else {
+            return Ok(Some(Termination::NotRunning));
+        };
+        if handle.tree.id() <= 1 {
+            return Err(anyhow!(t!(
+                "task.invalid_process_group",
+                id = task_id,
+                pgid = handle.tree.id()
+            )));
+        }
+
+        if let Ok(Some(status)) = handle.child.try_wait() {
+            // 进程已自行退出，按正常结束处理
+            self.update_task(task_id, |task| {
+                task.status = if status.success() {
+                    TaskStatus::Completed
+                } else {
+                    TaskStatus::Failed
+                };
+                task.completed_at = Some(Utc::now());
+            })?;
+            return Ok(Some(Termination::NotRunning));
+        }
+
+        handle.tree.terminate();
+        let deadline = Instant::now() + grace;
+        let termination = loop {
+            if !group_alive(&mut handle) {
+                break Termination::Graceful;
+            }
+            if Instant::now() >= deadline {
+                handle.tree.kill();
+                // SIGKILL 之后组长很快退出，回收它以免留下僵尸进程
+                let reap_deadline = Instant::now() + Duration::from_secs(1);
+                while matches!(handle.child.try_wait(), Ok(None)) && Instant::now() < reap_deadline {
+                    tokio::time::sleep(PROCESS_POLL_INTERVAL).await;
+                }
+                break Termination::Forced;
+            }
+            tokio::time::sleep(PROCESS_POLL_INTERVAL).await;
+        };
+
+        let how = match termination {
+            Termination::Graceful => "graceful",
+            _ => "forced",
+        };
+        let marker = format!(
+            "[oxide] Task stopped by user ({} termination) at {}\n",
+            how,
+            Utc::now().to_rfc3339()
+        );
+        handle
+            .output
+            .write(marker.as_bytes())
+            .context(t!("task.write_output_failed"))?;
+
+        self.update_task(task_id, |task| {
+            task.status = TaskStatus::Cancelled;
+            task.completed_at = Some(Utc::now());
+            task.error = Some("stopped by user".to_string());
+        })?;
+
+        Ok(Some(termination))
+    }
+
     /// 清理已完成的任务
     #[allow(dead_code)]
     pub fn cleanup_completed_tasks(&self, older_than: chrono::Duration) -> Result<usize> {
@@ -590,11 +1069,9 @@ impl TaskManager {
                             fs::remove_file(task_path)?;
                         }
 
-                        // 删除输出文件
+                        // 删除输出文件（包括轮转文件）
                         if let Some(output_path) = task.output_file {
-                            if output_path.exists() {
-                                fs::remove_file(output_path)?;
-                            }
+                            remove_output(&output_path)?;
                         }
 
                         // 从内存中移除
@@ -611,6 +1088,38 @@ impl TaskManager {
     }
 }
 
+/// 捕获线程单次写入的最大长度，避免没有换行的输出占用过多内存
+const CAPTURE_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// 把子进程的一路输出逐行追加到任务输出文件
+fn spawn_capture(
+    stream: impl Read + Send + 'static,
+    output: Arc<OutputSink>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match (&mut reader).take(CAPTURE_CHUNK_BYTES).read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if output.write(&line).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// 进程组中是否还有存活的进程
+fn group_alive(handle: &mut ProcessHandle) -> bool {
+    // 先回收组长，避免僵尸进程让进程组看起来仍然存活
+    let _ = handle.child.try_wait();
+    handle.tree.is_alive()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -746,6 +1255,37 @@ mod tests {
         assert_eq!(task.agent_type, AgentType::Plan);
     }
 
+    #[test]
+    fn test_truncated_task_file_is_moved_aside() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_path = temp_dir.path().to_path_buf();
+        let manager1 = TaskManager::new(storage_path.clone()).unwrap();
+        let create = |name: &str| {
+            manager1
+                .create_task(name.to_string(), String::new(), String::new(), AgentType::Plan)
+                .unwrap()
+        };
+        let kept = create("保留");
+        let broken = create("截断");
+
+        // 写入中途崩溃留下的半截文件
+        let path = storage_path.join(format!("{}.json", broken));
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &content[..content.len() / 2]).unwrap();
+
+        let manager2 = TaskManager::new(storage_path.clone()).unwrap();
+        assert!(manager2.get_task(&broken).unwrap().is_none());
+        assert!(!path.exists());
+        let tasks = manager2.list_tasks().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, kept);
+        let names: Vec<String> = fs::read_dir(&storage_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert!(names.iter().any(|name| name.contains(".corrupt-")));
+    }
+
     #[test]
     fn test_task_dependencies() {
         let temp_dir = TempDir::new().unwrap();
@@ -835,4 +1375,405 @@ mod tests {
         assert_eq!(available.len(), 1);
         assert_eq!(available[0].id, task2.id);
     }
+
+    /// 写入 50 个任务：状态按 pending/in_progress/completed/failed 轮换，
+    /// 所有者按 alice/bob/无 轮换，每 5 个任务的标题含 "auth"，
+    /// 第 i 个任务在 i 分钟前更新，i >= 40 的已完成任务在 30 天前完成
+    fn seed_tasks(manager: &TaskManager) -> Vec<Task> {
+        let statuses = [
+            TaskStatus::Pending,
+            TaskStatus::InProgress,
+            TaskStatus::Completed,
+            TaskStatus::Failed,
+        ];
+        let owners = [Some("alice"), Some("bob"), None];
+        let now = Utc::now();
+
+        (0..50)
+            .map(|i| {
+                let subject = if i % 5 == 0 {
+                    format!("Fix auth bug {}", i)
+                } else {
+                    format!("Task {}", i)
+                };
+                let mut task = Task::new(subject, format!("Description {}", i), None);
+                task.status = statuses[i % statuses.len()];
+                task.owner = owners[i % owners.len()].map(String::from);
+                task.updated_at = now - chrono::Duration::minutes(i as i64);
+                if task.status == TaskStatus::Completed {
+                    task.completed_at = Some(if i >= 40 {
+                        now - chrono::Duration::days(30)
+                    } else {
+                        task.updated_at
+                    });
+                }
+                manager.save_task(&task).unwrap();
+                task
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_query_tasks_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let seeded = seed_tasks(&manager);
+
+        let mut deleted = Task::new("Deleted task".to_string(), String::new(), None);
+        deleted.status = TaskStatus::Deleted;
+        manager.save_task(&deleted).unwrap();
+
+        // 默认：排除已删除任务和 2 个过期的已完成任务（i = 42, 46）
+        let page = manager.query_tasks(&TaskFilter::default()).unwrap();
+        assert_eq!(page.total, 48);
+        assert_eq!(page.hidden_completed, 2);
+        assert!(page
+            .tasks
+            .windows(2)
+            .all(|w| w[0].updated_at >= w[1].updated_at));
+        assert_eq!(page.tasks[0].id, seeded[0].id);
+
+        // --all 包括过期的已完成任务
+        let filter = TaskFilter {
+            include_all: true,
+            ..TaskFilter::default()
+        };
+        assert_eq!(manager.query_tasks(&filter).unwrap().total, 50);
+
+        // 单个状态
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::InProgress],
+            ..TaskFilter::default()
+        };
+        let page = manager.query_tasks(&filter).unwrap();
+        assert_eq!(page.total, 13);
+        assert!(page
+            .tasks
+            .iter()
+            .all(|t| t.status == TaskStatus::InProgress));
+
+        // 多个状态
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::Pending, TaskStatus::Failed],
+            ..TaskFilter::default()
+        };
+        assert_eq!(manager.query_tasks(&filter).unwrap().total, 25);
+
+        // 已删除任务需要显式请求
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::Deleted],
+            ..TaskFilter::default()
+        };
+        let page = manager.query_tasks(&filter).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.tasks[0].id, deleted.id);
+
+        // 所有者
+        let filter = TaskFilter {
+            owner: Some("alice".to_string()),
+            ..TaskFilter::default()
+        };
+        let page = manager.query_tasks(&filter).unwrap();
+        assert_eq!(page.total, 16); // 17 个任务中 i = 42 已过期
+        assert!(page
+            .tasks
+            .iter()
+            .all(|t| t.owner.as_deref() == Some("alice")));
+
+        // 关键字（不区分大小写，匹配标题或描述）
+        let filter = TaskFilter {
+            query: Some("AUTH".to_string()),
+            ..TaskFilter::default()
+        };
+        assert_eq!(manager.query_tasks(&filter).unwrap().total, 10);
+        let filter = TaskFilter {
+            query: Some("description 7".to_string()),
+            ..TaskFilter::default()
+        };
+        assert_eq!(manager.query_tasks(&filter).unwrap().total, 1);
+
+        // 组合条件
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::Pending],
+            owner: Some("alice".to_string()),
+            query: Some("auth".to_string()),
+            ..TaskFilter::default()
+        };
+        let ids: Vec<_> = manager
+            .query_tasks(&filter)
+            .unwrap()
+            .tasks
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![seeded[0].id.clone()]);
+    }
+
+    #[test]
+    fn test_query_tasks_pagination() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf()).unwrap();
+        seed_tasks(&manager);
+
+        let page_of = |offset, limit| {
+            manager
+                .query_tasks(&TaskFilter {
+                    include_all: true,
+                    limit: Some(limit),
+                    offset,
+                    ..TaskFilter::default()
+                })
+                .unwrap()
+        };
+
+        let all = page_of(0, 50);
+        assert_eq!(all.tasks.len(), 50);
+
+        // 相邻页首尾相接，不重复不遗漏
+        let first = page_of(0, 20);
+        let second = page_of(20, 20);
+        let last = page_of(40, 20);
+        assert_eq!(
+            (first.tasks.len(), second.tasks.len(), last.tasks.len()),
+            (20, 20, 10)
+        );
+        let paged: Vec<_> = [first, second, last]
+            .into_iter()
+            .flat_map(|page| page.tasks)
+            .map(|t| t.id)
+            .collect();
+        let expected: Vec<_> = all.tasks.iter().map(|t| t.id.clone()).collect();
+        assert_eq!(paged, expected);
+
+        // 边界
+        let page = page_of(49, 20);
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.total, 50);
+        assert!(page_of(50, 20).tasks.is_empty());
+        assert!(page_of(100, 20).tasks.is_empty());
+        assert!(page_of(0, 0).tasks.is_empty());
+    }
+
+    #[test]
+    fn test_query_tasks_retention_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .with_config(TasksConfig {
+                completed_retention_days: 0,
+                ..TasksConfig::default()
+            });
+        seed_tasks(&manager);
+
+        let page = manager.query_tasks(&TaskFilter::default()).unwrap();
+        assert_eq!(page.total, 50);
+        assert_eq!(page.hidden_completed, 0);
+    }
+
+    #[test]
+    fn test_task_filter_from_args() {
+        assert_eq!(TaskFilter::from_args("").unwrap(), TaskFilter::default());
+
+        let filter = TaskFilter::from_args(
+            "--status pending,in_progress --owner alice --limit 10 --offset 5 --all fix auth",
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            TaskFilter {
+                statuses: vec![TaskStatus::Pending, TaskStatus::InProgress],
+                owner: Some("alice".to_string()),
+                query: Some("fix auth".to_string()),
+                include_all: true,
+                limit: Some(10),
+                offset: 5,
+            }
+        );
+
+        let filter = TaskFilter::from_args("--status failed --status completed").unwrap();
+        assert_eq!(
+            filter.statuses,
+            vec![TaskStatus::Failed, TaskStatus::Completed]
+        );
+
+        assert!(TaskFilter::from_args("--status done").is_err());
+        assert!(TaskFilter::from_args("--limit").is_err());
+        assert!(TaskFilter::from_args("--limit many").is_err());
+        assert!(TaskFilter::from_args("--verbose").is_err());
+    }
+
+    /// 轮询读取任务输出，直到包含 `needle`
+    #[cfg(unix)]
+    async fn wait_for_output(manager: &TaskManager, task_id: &TaskId, needle: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let output = manager.get_task_output(task_id).unwrap().unwrap_or_default();
+            if output.contains(needle) || Instant::now() >= deadline {
+                return output;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// 用配置的 shell 在后台启动命令
+    #[cfg(unix)]
+    fn spawn(manager: &TaskManager, subject: &str, command: &str) -> Task {
+        manager
+            .spawn_process(
+                subject.to_string(),
+                command,
+                crate::shell::Shell::from_config().command(command),
+            )
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_task_graceful() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let task = spawn(
+            &manager,
+            "dev server",
+            "trap 'echo cleaning up; exit 0' TERM; echo started; while true; do sleep 0.1; done",
+        );
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert!(task.process_group.is_some());
+        wait_for_output(&manager, &task.id, "started").await;
+
+        let termination = manager.stop_task(&task.id, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(termination, Some(Termination::Graceful));
+
+        let stopped = manager.get_task(&task.id).unwrap().unwrap();
+        assert_eq!(stopped.status, TaskStatus::Cancelled);
+        assert_eq!(stopped.error.as_deref(), Some("stopped by user"));
+
+        let output = manager.get_task_output(&task.id).unwrap().unwrap();
+        assert!(output.contains("cleaning up"));
+        assert!(output
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("[oxide] Task stopped by user (graceful termination)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_task_escalates_to_sigkill() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let pid_file = temp_dir.path().join("worker.pid");
+
+        // 组长捕获 SIGTERM 后继续运行；派生的 worker 忽略 SIGTERM，只能被 SIGKILL 结束
+        let command = format!(
+            "sh -c 'trap \"\" TERM; sleep 30' & echo $! > '{}'; \
+             trap 'echo ignoring TERM' TERM; echo started; while true; do sleep 0.1; done",
+            pid_file.display()
+        );
+        let task = spawn(&manager, "stubborn", &command);
+        wait_for_output(&manager, &task.id, "started").await;
+        let worker_pid: i32 = fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+
+        let started = Instant::now();
+        let termination = manager.stop_task(&task.id, Duration::from_millis(300)).await.unwrap();
+        assert_eq!(termination, Some(Termination::Forced));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let output = wait_for_output(&manager, &task.id, "[oxide]").await;
+        assert!(output.contains("ignoring TERM"));
+        assert!(output.contains("[oxide] Task stopped by user (forced termination)"));
+        assert_eq!(
+            manager.get_task(&task.id).unwrap().unwrap().status,
+            TaskStatus::Cancelled
+        );
+
+        // worker 不应成为孤儿进程继续运行（可能短暂残留为等待回收的僵尸进程）
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let stat = fs::read_to_string(format!("/proc/{}/stat", worker_pid));
+            let alive = match &stat {
+                Ok(stat) => !stat.rsplit(')').next().unwrap_or("").trim_start().starts_with('Z'),
+                Err(_) => unsafe { libc::kill(worker_pid, 0) == 0 },
+            };
+            if !alive {
+                break;
+            }
+            assert!(Instant::now() < deadline, "worker {} survived the stop", worker_pid);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_task_after_process_exited() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let task = spawn(&manager, "quick", "echo done");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.get_task(&task.id).unwrap().unwrap().status == TaskStatus::InProgress {
+            assert!(Instant::now() < deadline);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(
+            manager.get_task(&task.id).unwrap().unwrap().status,
+            TaskStatus::Completed
+        );
+
+        let termination = manager.stop_task(&task.id, DEFAULT_STOP_GRACE).await.unwrap();
+        assert_eq!(termination, Some(Termination::NotRunning));
+        assert_eq!(
+            manager.get_task(&task.id).unwrap().unwrap().status,
+            TaskStatus::Completed
+        );
+
+        // 普通任务没有进程可停止
+        let plain = manager
+            .create_task_simple("plain".to_string(), "desc".to_string(), None, None)
+            .unwrap();
+        assert_eq!(manager.stop_task(&plain.id, DEFAULT_STOP_GRACE).await.unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_process_rotates_large_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TaskManager::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .with_config(TasksConfig {
+                max_output_bytes: 1024,
+                max_output_rotations: 2,
+                ..TasksConfig::default()
+            });
+
+        let task = spawn(
+            &manager,
+            "chatty",
+            "i=0; while [ $i -lt 500 ]; do echo \"line $i\"; i=$((i+1)); done",
+        );
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.get_task(&task.id).unwrap().unwrap().status == TaskStatus::InProgress {
+            assert!(Instant::now() < deadline);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let task = manager.get_task(&task.id).unwrap().unwrap();
+        assert!(task.output_rotations >= 2);
+        assert!(task.output_discarded_bytes > 0);
+
+        // 磁盘上的元数据同样记录了丢弃情况
+        let reloaded = TaskManager::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .get_task(&task.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reloaded.output_discarded_bytes, task.output_discarded_bytes);
+
+        let output = manager.get_task_output(&task.id).unwrap().unwrap();
+        assert!(output.len() <= 3 * 1024);
+        assert!(output.ends_with("line 499\n"));
+        assert!(output.starts_with("line "));
+        assert!(!output.contains("line 0\n"));
+    }
 }
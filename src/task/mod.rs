@@ -3,5 +3,6 @@
 //! 提供后台任务的创建、执行和追踪功能。
 
 pub mod manager;
+pub mod output;
 
-pub use manager::{TaskManager, TaskStatus};
+pub use manager::{TaskFilter, TaskManager, TaskStatus};
@@ -0,0 +1,316 @@
+//! 任务输出文件
+//!
+//! 后台进程的输出写入 `<id>.output.txt`，超过上限时轮转为 `<id>.output.1.txt`、
+//! `<id>.output.2.txt`……（数字越大越旧），最多保留 `max_output_rotations` 个旧文件，
+//! 更早的输出被丢弃。
+//!
+//! 读取时把保留下来的文件按时间顺序视为一条连续的字节流。偏移量从任务开始输出时
+//! 计起，被丢弃部分的长度记录在任务元数据的 `output_discarded_bytes` 中，因此轮转
+//! 前后记录的偏移量仍然有效。轮转和读取都持有输出文件的 [`FileLock`]，读取方
+//! 不会看到轮转到一半的文件组。
+
+use crate::config::TasksConfig;
+use crate::file_lock::{FileLock, DEFAULT_LOCK_TIMEOUT};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// 第 `n` 个轮转文件的路径，`n == 0` 为当前文件
+///
+/// `<id>.output.txt` 的第 2 个轮转文件为 `<id>.output.2.txt`。
+pub fn rotated_path(output: &Path, n: u32) -> PathBuf {
+    if n == 0 {
+        return output.to_path_buf();
+    }
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = name.strip_suffix(".txt").unwrap_or(&name);
+    output.with_file_name(format!("{}.{}.txt", stem, n))
+}
+
+/// 带大小上限和轮转的输出写入器
+pub struct OutputWriter {
+    path: PathBuf,
+    /// 轮转重命名期间为 `None`（Windows 上不能重命名已打开的文件）
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+    max_rotations: u32,
+    rotations: u32,
+    discarded_bytes: u64,
+}
+
+impl OutputWriter {
+    /// 创建（或清空）输出文件
+    pub fn create(path: &Path, limits: &TasksConfig) -> io::Result<Self> {
+        let file = open_output(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Some(file),
+            size: 0,
+            max_bytes: limits.max_output_bytes.max(1),
+            max_rotations: limits.max_output_rotations,
+            rotations: 0,
+            discarded_bytes: 0,
+        })
+    }
+
+    /// 追加一段输出
+    ///
+    /// 写入后会超过上限时先轮转；单段输出本身超过上限时仍完整写入新文件。
+    /// 发生轮转时调用 `on_rotate(累计轮转次数, 累计丢弃字节数)`，调用期间持有文件锁。
+    pub fn write(&mut self, data: &[u8], on_rotate: impl FnOnce(u32, u64)) -> io::Result<()> {
+        if self.size > 0 && self.size + data.len() as u64 > self.max_bytes {
+            let _lock = lock(&self.path)?;
+            self.rotate()?;
+            on_rotate(self.rotations, self.discarded_bytes);
+        }
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.file.insert(open_output(&self.path)?),
+        };
+        file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    /// 轮转输出文件，丢弃超出保留数量的最旧文件
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_rotations == 0 {
+            self.discarded_bytes += self.size;
+            if let Some(file) = self.file.as_mut() {
+                file.set_len(0)?;
+                file.seek(SeekFrom::Start(0))?;
+            }
+        } else {
+            self.file = None;
+            let oldest = rotated_path(&self.path, self.max_rotations);
+            if let Ok(meta) = fs::metadata(&oldest) {
+                self.discarded_bytes += meta.len();
+                fs::remove_file(&oldest)?;
+            }
+            for n in (1..self.max_rotations).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = Some(open_output(&self.path)?);
+        }
+        self.size = 0;
+        self.rotations += 1;
+        Ok(())
+    }
+}
+
+/// 从某个偏移量开始读到的输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    /// `data` 第一个字节的偏移量
+    pub start: u64,
+
+    /// 读到的内容
+    pub data: Vec<u8>,
+
+    /// 已被丢弃的字节数，即保留下来的最早字节的偏移量
+    pub discarded_bytes: u64,
+}
+
+impl OutputChunk {
+    /// 请求的起点已被丢弃时，被跳过的字节数
+    pub fn skipped_from(&self, from: u64) -> u64 {
+        self.start.saturating_sub(from)
+    }
+}
+
+/// 从偏移量 `from` 开始读取保留下来的输出（依次为最旧的轮转文件到当前文件）
+///
+/// `discarded_bytes` 在持有文件锁时调用，应返回任务元数据中记录的丢弃字节数。
+/// `from` 落在已丢弃的部分时从最早保留的字节开始；`from` 超过输出末尾
+/// （输出文件被重建）时从头开始。输出文件不存在时返回空内容。
+pub fn read_output(
+    path: &Path,
+    from: u64,
+    discarded_bytes: impl FnOnce() -> u64,
+) -> io::Result<OutputChunk> {
+    let _lock = lock(path)?;
+    let discarded_bytes = discarded_bytes();
+
+    let mut files = vec![path.to_path_buf()];
+    let mut n = 1;
+    loop {
+        let rotated = rotated_path(path, n);
+        if !rotated.exists() {
+            break;
+        }
+        files.push(rotated);
+        n += 1;
+    }
+    files.reverse();
+
+    let mut data = Vec::new();
+    for file in &files {
+        match File::open(file) {
+            Ok(mut file) => {
+                file.read_to_end(&mut data)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let end = discarded_bytes + data.len() as u64;
+    let start = if from > end {
+        discarded_bytes
+    } else {
+        from.max(discarded_bytes)
+    };
+    data.drain(..(start - discarded_bytes) as usize);
+
+    Ok(OutputChunk {
+        start,
+        data,
+        discarded_bytes,
+    })
+}
+
+/// 删除输出文件及其所有轮转文件
+pub fn remove_output(path: &Path) -> io::Result<()> {
+    let mut n = 1;
+    while rotated_path(path, n).exists() {
+        fs::remove_file(rotated_path(path, n))?;
+        n += 1;
+    }
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// 创建（或清空）输出文件
+fn open_output(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+}
+
+fn lock(path: &Path) -> io::Result<FileLock> {
+    FileLock::acquire(path, DEFAULT_LOCK_TIMEOUT).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn limits(max_output_bytes: u64, max_output_rotations: u32) -> TasksConfig {
+        TasksConfig {
+            max_output_bytes,
+            max_output_rotations,
+            ..TasksConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_rotated_path() {
+        let path = Path::new("/tmp/tasks/abc.output.txt");
+        assert_eq!(rotated_path(path, 0), path);
+        assert_eq!(
+            rotated_path(path, 2),
+            PathBuf::from("/tmp/tasks/abc.output.2.txt")
+        );
+    }
+
+    #[test]
+    fn test_rotation_keeps_offsets_continuous() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("gen.output.txt");
+        let mut writer = OutputWriter::create(&path, &limits(64, 2)).unwrap();
+        let mut discarded = 0;
+        let mut rotations = 0;
+
+        // 生成器写入 100 行（约 1 KB），远超上限
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            let line = format!("line {:03}\n", i);
+            expected.extend_from_slice(line.as_bytes());
+            writer
+                .write(line.as_bytes(), |r, d| {
+                    rotations = r;
+                    discarded = d;
+                })
+                .unwrap();
+        }
+
+        assert!(rotations > 2);
+        assert!(fs::metadata(&path).unwrap().len() <= 64);
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        // 保留下来的内容恰好是完整输出去掉被丢弃的前缀
+        let chunk = read_output(&path, 0, || discarded).unwrap();
+        assert_eq!(chunk.start, discarded);
+        assert_eq!(chunk.skipped_from(0), discarded);
+        assert_eq!(chunk.data, expected[discarded as usize..]);
+
+        // 从中间的偏移量读取跨越轮转文件
+        let from = expected.len() as u64 - 100;
+        let chunk = read_output(&path, from, || discarded).unwrap();
+        assert_eq!(chunk.start, from);
+        assert_eq!(chunk.data, expected[from as usize..]);
+
+        // 偏移量在末尾时没有新内容
+        let chunk = read_output(&path, expected.len() as u64, || discarded).unwrap();
+        assert!(chunk.data.is_empty());
+    }
+
+    #[test]
+    fn test_offset_survives_later_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("gen.output.txt");
+        let mut writer = OutputWriter::create(&path, &limits(32, 1)).unwrap();
+        let mut discarded = 0;
+
+        writer.write(b"first line\n", |_, _| {}).unwrap();
+        let chunk = read_output(&path, 0, || discarded).unwrap();
+        assert_eq!(chunk.data, b"first line\n");
+        let offset = chunk.start + chunk.data.len() as u64;
+
+        for i in 0..3 {
+            writer
+                .write(format!("more output {}\n", i).as_bytes(), |_, d| discarded = d)
+                .unwrap();
+        }
+
+        // 上次读取的位置仍然指向紧接着的内容
+        let chunk = read_output(&path, offset, || discarded).unwrap();
+        assert_eq!(chunk.start, offset);
+        assert!(chunk.data.starts_with(b"more output 0\n"));
+    }
+
+    #[test]
+    fn test_zero_rotations_truncates_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("gen.output.txt");
+        let mut writer = OutputWriter::create(&path, &limits(16, 0)).unwrap();
+        let mut discarded = 0;
+
+        writer.write(b"0123456789\n", |_, d| discarded = d).unwrap();
+        writer.write(b"abcdefghij\n", |_, d| discarded = d).unwrap();
+
+        assert_eq!(discarded, 11);
+        assert!(!rotated_path(&path, 1).exists());
+        let chunk = read_output(&path, 0, || discarded).unwrap();
+        assert_eq!(chunk.start, 11);
+        assert_eq!(chunk.data, b"abcdefghij\n");
+
+        remove_output(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,612 @@
+//! 链路追踪
+//!
+//! 每轮用户输入对应一个根 span（`agent_turn`），其下是每次模型请求（`provider_request`）
+//! 和每次工具调用（`tool_call`），权限确认与取消记录为事件。
+//!
+//! 导出方式：
+//! - 设置了 `OTEL_EXPORTER_OTLP_ENDPOINT`：通过 OTLP/HTTP 导出（Langfuse 等兼容 OTLP 的后端均可，
+//!   认证头通过 `OTEL_EXPORTER_OTLP_HEADERS` 设置）
+//! - 否则在 `behavior.debug` 开启时写入 `.oxide/traces/<session_id>.jsonl`
+//! - 都未配置时不安装 subscriber，span 为空操作
+//!
+//! 与导出方式无关，设置了 `behavior.log_level` 时另外把日志事件以 JSON 行写入
+//! `~/.oxide/logs/oxide.<日期>.log`，按天轮转，最多保留 `behavior.log_max_files` 个文件。
+//! 写入前按工具结果的规则对密钥脱敏。不向终端输出任何日志，避免打乱界面。
+
+use crate::config::BehaviorConfig;
+use crate::tools::redactor;
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+/// 调试 trace 文件目录
+pub const TRACES_DIR: &str = ".oxide/traces";
+
+/// 日志文件名前缀和后缀，完整文件名为 `oxide.<YYYY-MM-DD>.log`
+const LOG_FILE_PREFIX: &str = "oxide";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// 当前写入日志的目录，未启用文件日志时为空
+static LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// OTLP 导出端点环境变量
+const OTLP_ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// 工具参数在 span 中保留的最大字符数
+const MAX_ARGS_CHARS: usize = 256;
+
+/// 持有导出器和日志写入线程，drop 时刷新尚未导出的 span 和尚未写入的日志
+#[derive(Default)]
+pub struct TelemetryGuard {
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+    _log_worker: Option<WorkerGuard>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = &self.provider {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// 文件日志设置（`behavior.log_level`）
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSettings {
+    /// 写入的最低级别
+    pub level: LevelFilter,
+
+    /// 日志目录
+    pub dir: PathBuf,
+
+    /// 最多保留的日志文件数
+    pub max_files: usize,
+}
+
+impl LogSettings {
+    /// 按 `[behavior]` 配置生成日志设置，未设置 `log_level` 或为 `"off"` 时返回 None
+    pub fn from_config(behavior: &BehaviorConfig) -> Result<Option<Self>> {
+        let Some(level) = behavior.log_level.as_deref() else {
+            return Ok(None);
+        };
+        let level: LevelFilter = level
+            .parse()
+            .with_context(|| format!("Invalid behavior.log_level: '{}'", level))?;
+        if level == LevelFilter::OFF {
+            return Ok(None);
+        }
+        let Some(dir) = default_log_dir() else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            level,
+            dir,
+            max_files: behavior.log_max_files.max(1),
+        }))
+    }
+}
+
+/// 默认日志目录 `~/.oxide/logs`
+pub fn default_log_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".oxide").join("logs"))
+}
+
+/// 当前正在写入的日志文件，未启用文件日志时返回 None
+pub fn active_log_file() -> Option<PathBuf> {
+    LOG_DIR
+        .get()
+        .map(|dir| log_file_path(dir, Utc::now().date_naive()))
+}
+
+/// 某一天的日志文件路径（轮转按 UTC 日期进行）
+fn log_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!(
+        "{}.{}.{}",
+        LOG_FILE_PREFIX,
+        date.format("%Y-%m-%d"),
+        LOG_FILE_SUFFIX
+    ))
+}
+
+/// 安装全局 subscriber，未配置任何导出方式且未启用文件日志时返回 None
+pub fn init(
+    debug: bool,
+    session_id: &str,
+    log: Option<&LogSettings>,
+) -> Result<Option<TelemetryGuard>> {
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+    let mut guard = TelemetryGuard::default();
+
+    if std::env::var_os(OTLP_ENDPOINT_VAR).is_some() {
+        let (layer, provider) = otlp_layer()?;
+        layers.push(layer);
+        guard.provider = Some(provider);
+    } else if debug {
+        layers.push(trace_file_layer(Path::new(TRACES_DIR), session_id)?);
+    }
+
+    if let Some(log) = log {
+        let (writer, worker) = log_writer(log)?;
+        layers.push(log_layer(writer, log.level).boxed());
+        guard._log_worker = Some(worker);
+    }
+
+    if layers.is_empty() {
+        return Ok(None);
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .context("Failed to install tracing subscriber")?;
+
+    if let Some(log) = log {
+        let _ = LOG_DIR.set(log.dir.clone());
+    }
+    Ok(Some(guard))
+}
+
+/// 只保留 oxide 自己的 span，避免依赖库的内部 span 淹没 trace
+fn oxide_targets() -> Targets {
+    Targets::new().with_target("oxide", LevelFilter::TRACE)
+}
+
+fn otlp_layer() -> Result<(BoxedLayer, opentelemetry_sdk::trace::SdkTracerProvider)> {
+    use opentelemetry::trace::TracerProvider as _;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .context("Failed to create OTLP exporter")?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name("oxide")
+                .build(),
+        )
+        .build();
+
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("oxide"))
+        .with_filter(oxide_targets())
+        .boxed();
+
+    Ok((layer, provider))
+}
+
+fn trace_file_layer(dir: &Path, session_id: &str) -> Result<BoxedLayer> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create trace directory: {}", dir.display()))?;
+    let path = dir.join(format!("{}.jsonl", session_id));
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open trace file: {}", path.display()))?;
+
+    Ok(file_layer(file).boxed())
+}
+
+/// 按天轮转的非阻塞日志写入器
+fn log_writer(
+    log: &LogSettings,
+) -> Result<(tracing_appender::non_blocking::NonBlocking, WorkerGuard)> {
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(log.max_files)
+        .build(&log.dir)
+        .with_context(|| format!("Failed to open log directory: {}", log.dir.display()))?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// JSON 行日志：只写事件（附带所在 span 的字段），不写 span 的开始和结束
+fn log_layer<S, W>(writer: W, level: LevelFilter) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    W: Write + Clone + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_ansi(false)
+        .with_writer(move || RedactingWriter(writer.clone()))
+        .with_filter(Targets::new().with_target("oxide", level))
+}
+
+/// 写入前对密钥脱敏的写入器
+///
+/// fmt layer 把每个事件格式化成一整行后一次写入，因此按次脱敏不会把密钥拆开。
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let redacted = redactor::redact(&String::from_utf8_lossy(buf));
+        self.0.write_all(redacted.text.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// JSONL 输出：每个 span 关闭时写一行（含耗时和字段），事件各写一行
+fn file_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    W: std::io::Write + Send + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .with_writer(Mutex::new(writer))
+        .with_filter(oxide_targets())
+}
+
+/// 一轮用户输入的根 span
+pub fn turn_span(session_id: &str, model: &str, mode: &str) -> Span {
+    tracing::info_span!(
+        "agent_turn",
+        session.id = session_id,
+        model = model,
+        mode = mode,
+        outcome = Empty,
+    )
+}
+
+/// 单次模型请求
+pub fn provider_request_span(parent: &Span) -> Span {
+    tracing::info_span!(
+        parent: parent,
+        "provider_request",
+        tokens.input = Empty,
+        tokens.output = Empty,
+        latency_ms = Empty,
+        stop_reason = Empty,
+    )
+}
+
+/// 记录模型请求结果
+pub fn record_provider_response(
+    span: &Span,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    latency: Duration,
+    stop_reason: &str,
+) {
+    if let Some(tokens) = input_tokens {
+        span.record("tokens.input", tokens);
+    }
+    if let Some(tokens) = output_tokens {
+        span.record("tokens.output", tokens);
+    }
+    span.record("latency_ms", latency.as_millis() as u64);
+    span.record("stop_reason", stop_reason);
+}
+
+/// 单次工具调用（父 span 为当前 span，即所在的 `agent_turn`）
+pub fn tool_span(tool_name: &str, args: &Value) -> Span {
+    tracing::info_span!(
+        "tool_call",
+        tool.name = tool_name,
+        tool.args = %truncate_args(args),
+        duration_ms = Empty,
+        success = Empty,
+    )
+}
+
+/// 记录工具调用结果
+pub fn record_tool_result(span: &Span, duration: Duration, success: bool) {
+    span.record("duration_ms", duration.as_millis() as u64);
+    span.record("success", success);
+}
+
+/// 权限确认事件
+pub fn permission_prompt(tool_name: &str, decision: &str, reason: &str) {
+    tracing::info!(
+        name: "permission_prompt",
+        decision = decision,
+        tool.name = tool_name,
+        reason = reason,
+    );
+}
+
+/// 取消事件
+pub fn cancelled(reason: &str) {
+    tracing::info!(name: "cancelled", reason = reason);
+}
+
+/// 一轮对话失败（模型请求出错等）
+pub fn turn_failed(error: &dyn Display) {
+    tracing::error!(name: "turn_failed", error = %error);
+}
+
+/// 会话开始，记录版本、模型和会话 ID
+pub fn session_started(session_id: &str, model: &str) {
+    tracing::info!(
+        name: "session_start",
+        version = env!("CARGO_PKG_VERSION"),
+        os = std::env::consts::OS,
+        model = model,
+        session.id = session_id,
+    );
+}
+
+/// 工具参数序列化、脱敏后截断，避免密钥和大段文件内容进入 trace
+///
+/// span 属性会发送给 OTLP 导出器，不经过日志文件的脱敏写入器，因此在这里脱敏；
+/// 先脱敏再截断，截断不会把密钥切成识别不出的片段。
+fn truncate_args(args: &Value) -> String {
+    let text = redactor::redact(&args.to_string()).text;
+    match text.char_indices().nth(MAX_ARGS_CHARS) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tracing::span::{Attributes, Id};
+    use tracing_subscriber::layer::Context as LayerContext;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// (名称, 父 span 名称)
+    type Captured = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+    /// 记录 span 名称及其父 span 名称，以及 span 的所有字段
+    #[derive(Clone, Default)]
+    struct CaptureLayer {
+        spans: Captured,
+        events: Captured,
+        fields: Arc<Mutex<Vec<String>>>,
+    }
+
+    /// 把字段写成 `name=value`
+    struct FieldVisitor<'a>(&'a mut Vec<String>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S> Layer<S> for CaptureLayer
+    where
+        S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+            let parent = ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name().to_string());
+            self.spans
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), parent));
+            attrs.record(&mut FieldVisitor(&mut self.fields.lock().unwrap()));
+        }
+
+        fn on_record(
+            &self,
+            _id: &Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: LayerContext<'_, S>,
+        ) {
+            values.record(&mut FieldVisitor(&mut self.fields.lock().unwrap()));
+        }
+
+        fn on_event(&self, event: &tracing::Event<'_>, ctx: LayerContext<'_, S>) {
+            let parent = ctx.event_span(event).map(|span| span.name().to_string());
+            self.events
+                .lock()
+                .unwrap()
+                .push((event.metadata().name().to_string(), parent));
+        }
+    }
+
+    fn simulate_turn() {
+        let turn = turn_span("brave-fox", "claude-sonnet-4", "oxide");
+        let request = provider_request_span(&turn);
+        record_provider_response(&request, Some(120), Some(30), Duration::from_millis(5), "tool_use");
+        drop(request);
+
+        turn.in_scope(|| {
+            let tool = tool_span("read_file", &json!({ "file_path": "src/main.rs" }));
+            tool.in_scope(|| permission_prompt("read_file", "approved", "read"));
+            record_tool_result(&tool, Duration::from_millis(1), true);
+            cancelled("user");
+        });
+    }
+
+    #[test]
+    fn test_span_structure() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, simulate_turn);
+
+        let spans = capture.spans.lock().unwrap().clone();
+        assert_eq!(
+            spans,
+            vec![
+                ("agent_turn".to_string(), None),
+                ("provider_request".to_string(), Some("agent_turn".to_string())),
+                ("tool_call".to_string(), Some("agent_turn".to_string())),
+            ]
+        );
+
+        let events = capture.events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            vec![
+                ("permission_prompt".to_string(), Some("tool_call".to_string())),
+                ("cancelled".to_string(), Some("agent_turn".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_layer_writes_jsonl() {
+        #[derive(Clone, Default)]
+        struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = SharedBuf::default();
+        let subscriber = tracing_subscriber::registry().with(file_layer(buf.clone()));
+        tracing::subscriber::with_default(subscriber, simulate_turn);
+
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let tool = lines
+            .iter()
+            .find(|line| {
+                line["span"]["name"] == "tool_call" && line["fields"]["message"] == "close"
+            })
+            .expect("tool_call span closed");
+        assert_eq!(tool["span"]["tool.name"], "read_file");
+        assert_eq!(tool["span"]["success"], true);
+        assert_eq!(tool["spans"][0]["name"], "agent_turn");
+
+        let request = lines
+            .iter()
+            .find(|line| {
+                line["span"]["name"] == "provider_request" && line["fields"]["message"] == "close"
+            })
+            .expect("provider_request span closed");
+        assert_eq!(request["span"]["tokens.input"], 120);
+        assert_eq!(request["span"]["stop_reason"], "tool_use");
+    }
+
+    #[test]
+    fn test_log_settings_from_config() {
+        let mut behavior = BehaviorConfig::default();
+        assert_eq!(LogSettings::from_config(&behavior).unwrap(), None);
+
+        behavior.log_level = Some("off".to_string());
+        assert_eq!(LogSettings::from_config(&behavior).unwrap(), None);
+
+        behavior.log_level = Some("verbose".to_string());
+        assert!(LogSettings::from_config(&behavior).is_err());
+
+        behavior.log_level = Some("debug".to_string());
+        behavior.log_max_files = 0;
+        if let Some(settings) = LogSettings::from_config(&behavior).unwrap() {
+            assert_eq!(settings.level, LevelFilter::DEBUG);
+            assert_eq!(settings.max_files, 1);
+            assert!(settings.dir.ends_with(".oxide/logs"));
+        }
+    }
+
+    #[test]
+    fn test_log_file_path() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        assert_eq!(
+            log_file_path(Path::new("/home/me/.oxide/logs"), date),
+            PathBuf::from("/home/me/.oxide/logs/oxide.2026-03-09.log")
+        );
+    }
+
+    #[test]
+    fn test_log_layer_writes_redacted_json() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let settings = LogSettings {
+            level: LevelFilter::INFO,
+            dir: temp_dir.path().to_path_buf(),
+            max_files: 2,
+        };
+        let (writer, worker) = log_writer(&settings).unwrap();
+        let subscriber = tracing_subscriber::registry().with(log_layer(writer, settings.level));
+        tracing::subscriber::with_default(subscriber, || {
+            session_started("brave-fox", "claude-sonnet-4");
+            let turn = turn_span("brave-fox", "claude-sonnet-4", "oxide");
+            turn.in_scope(|| {
+                turn_failed(&"401 for key sk-ant-REDACTED");
+                tracing::debug!("below the configured level");
+            });
+        });
+        drop(worker);
+
+        let path = log_file_path(temp_dir.path(), Utc::now().date_naive());
+        let output = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        assert_eq!(lines[0]["fields"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(lines[0]["fields"]["session.id"], "brave-fox");
+
+        assert_eq!(lines[1]["level"], "ERROR");
+        assert_eq!(lines[1]["span"]["session.id"], "brave-fox");
+        let error = lines[1]["fields"]["error"].as_str().unwrap();
+        assert!(error.contains("[REDACTED:anthropic_api_key]"), "{}", error);
+        assert!(!output.contains("abcdefghijklmnopqrstuvwxyz"));
+    }
+
+    #[test]
+    fn test_tool_span_redacts_args() {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let args = json!({
+                "url": "https://api.example.com",
+                "headers": { "x-api-key": "sk-ant-REDACTED" },
+            });
+            let tool = tool_span("http_request", &args);
+            record_tool_result(&tool, Duration::from_millis(1), true);
+        });
+
+        let fields = capture.fields.lock().unwrap().join("\n");
+        assert!(fields.contains("tool.args="), "{}", fields);
+        assert!(fields.contains("[REDACTED:anthropic_api_key]"));
+        assert!(!fields.contains("abcdefghijklmnopqrstuvwxyz"), "{}", fields);
+    }
+
+    #[test]
+    fn test_truncate_args() {
+        let short = json!({ "path": "a.rs" });
+        assert_eq!(truncate_args(&short), short.to_string());
+
+        let long = json!({ "content": "字".repeat(1000) });
+        let truncated = truncate_args(&long);
+        assert_eq!(truncated.chars().count(), MAX_ARGS_CHARS + 1);
+        assert!(truncated.ends_with('…'));
+    }
+}
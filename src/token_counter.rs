@@ -1,5 +1,7 @@
 use std::sync::OnceLock;
 
+use crate::model_info::ModelInfo;
+
 /// Token 分词器缓存
 static CL100K_BASE: OnceLock<tiktoken_rs::CoreBPE> = OnceLock::new();
 
@@ -23,6 +25,35 @@ pub fn count_tokens(text: &str) -> usize {
     bpe.encode_with_special_tokens(text).len()
 }
 
+/// 模型价格（美元 / 百万 token）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// 命中提示缓存的输入
+    pub cached_input: f64,
+}
+
+impl ModelPricing {
+    pub(crate) const fn new(input: f64, output: f64, cached_input: f64) -> Self {
+        Self { input, output, cached_input }
+    }
+
+    /// 计算费用（美元），`cached_tokens` 是 `input_tokens` 中命中缓存的部分
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64, cached_tokens: u64) -> f64 {
+        let uncached = input_tokens.saturating_sub(cached_tokens);
+        (uncached as f64 * self.input
+            + cached_tokens as f64 * self.cached_input
+            + output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// 查询模型价格（见 [`ModelInfo`]），`/cost`、`oxide usage` 和 token 预估共用
+pub fn pricing_for(model: &str) -> ModelPricing {
+    ModelInfo::lookup(model).pricing
+}
+
 /// Token 使用统计
 #[derive(Debug, Clone, Default)]
 pub struct TokenUsage {
@@ -45,40 +76,40 @@ impl TokenUsage {
         }
     }
 
-    /// 计算预估成本（美元）
-    ///
-    /// 基于 GPT-4 定价（输入 $0.03/1K tokens, 输出 $0.06/1K tokens）
-    pub fn estimated_cost(&self) -> f64 {
-        const INPUT_COST_PER_1K: f64 = 0.03;
-        const OUTPUT_COST_PER_1K: f64 = 0.06;
-
-        let input_cost = (self.input_tokens as f64 / 1000.0) * INPUT_COST_PER_1K;
-        let output_cost = (self.output_tokens as f64 / 1000.0) * OUTPUT_COST_PER_1K;
-        input_cost + output_cost
+    /// 按模型价格计算预估成本（美元）
+    pub fn estimated_cost(&self, model: &str) -> f64 {
+        pricing_for(model).cost(self.input_tokens as u64, self.output_tokens as u64, 0)
     }
 }
 
-/// 计算消息列表的 token 数量
-pub fn count_messages_tokens(messages: &[(String, String)]) -> usize {
-    let bpe = get_cl100k_base();
+/// 每个回复的固定开销（prime tokens）
+///
+/// 参考: https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
+pub const REPLY_PRIMING_TOKENS: usize = 3;
 
-    // 每条消息的开销（格式化 tokens）
-    // 参考: https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
-    let mut total = 3; // 每个回复的 primtokens
+/// 计算单条消息的 token 数量（含格式化开销）
+pub fn count_message_tokens(role: &str, content: &str) -> usize {
+    let bpe = get_cl100k_base();
 
-    for (_role, content) in messages {
-        // 每条消息: `<|start|>{role}<|message|>\n{content}<|end|>`
-        total += bpe.encode_with_special_tokens(_role).len();
-        total += bpe.encode_with_special_tokens(content).len();
-        total += 4; // <|start|>, <|message|>, \n, <|end|>
-    }
+    // 每条消息: `<|start|>{role}<|message|>\n{content}<|end|>`
+    bpe.encode_with_special_tokens(role).len()
+        + bpe.encode_with_special_tokens(content).len()
+        + 4 // <|start|>, <|message|>, \n, <|end|>
+}
 
-    total
+/// 计算消息列表的 token 数量
+pub fn count_messages_tokens(messages: &[(String, String)]) -> usize {
+    REPLY_PRIMING_TOKENS
+        + messages
+            .iter()
+            .map(|(role, content)| count_message_tokens(role, content))
+            .sum::<usize>()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_info::DEFAULT_PRICING;
 
     #[test]
     fn test_count_tokens() {
@@ -100,11 +131,27 @@ mod tests {
         assert_eq!(usage.output_tokens, 500);
         assert_eq!(usage.total_tokens, 1500);
 
-        let cost = usage.estimated_cost();
+        let cost = usage.estimated_cost("claude-sonnet-4-20250514");
         assert!(cost > 0.0);
         println!("Estimated cost: ${:.6}", cost);
     }
 
+    #[test]
+    fn test_pricing_for() {
+        let sonnet = pricing_for("claude-sonnet-4-20250514");
+        assert_eq!(sonnet, ModelPricing::new(3.0, 15.0, 0.3));
+        assert_eq!(pricing_for("anthropic/Claude-Opus-4-1"), ModelPricing::new(15.0, 75.0, 1.5));
+        assert_eq!(pricing_for("gpt-4o-mini-2024-07-18"), ModelPricing::new(0.15, 0.6, 0.075));
+        assert_eq!(pricing_for("gpt-4o"), ModelPricing::new(2.5, 10.0, 1.25));
+        assert_eq!(pricing_for("some-local-model"), DEFAULT_PRICING);
+
+        // 1M 输入（其中 40 万命中缓存）+ 10 万输出
+        let cost = sonnet.cost(1_000_000, 100_000, 400_000);
+        assert!((cost - (0.6 * 3.0 + 0.4 * 0.3 + 0.1 * 15.0)).abs() < 1e-9);
+        // 缓存数异常大于输入时不出现负数
+        assert!(sonnet.cost(10, 0, 20) >= 0.0);
+    }
+
     #[test]
     fn test_count_messages() {
         let messages = vec![
@@ -114,5 +161,11 @@ mod tests {
         let count = count_messages_tokens(&messages);
         assert!(count > 0);
         println!("Messages use {} tokens", count);
+
+        let per_message: usize = messages
+            .iter()
+            .map(|(role, content)| count_message_tokens(role, content))
+            .sum();
+        assert_eq!(count, REPLY_PRIMING_TOKENS + per_message);
     }
 }
@@ -0,0 +1,1021 @@
+//! 多文件补丁
+//!
+//! 应用一个可能涉及多个文件的 unified diff（如 `git diff` 的输出）：支持 `---`/`+++`
+//! 文件头、新建文件（`--- /dev/null`）和删除文件（`+++ /dev/null`）。先在内存中对所有
+//! 文件试应用全部 hunk，任何一个不匹配都不改动文件；全部通过后经撤销日志批量写入，
+//! 写入中途失败会恢复已写入的文件。行号不准的 hunk 在附近搜索，并允许忽略最多两行
+//! 上下文（fuzz），这类 hunk 会在结果中列出。`partial` 为 true 时改为只应用能匹配的
+//! hunk（见 [`super::partial_patch`]）。
+
+use super::format_on_write::{format_after_write, print_result};
+use super::line_endings::{to_lf, LineEndingInfo};
+use super::partial_patch::{
+    apply_partial, AskUser, HunkReport, HunkResolver, HunkStatus, SkipFailing,
+};
+use super::safe_write::{apply_all_with_backup, FileChange};
+use super::sensitive_paths::{get_sensitive_guard, SensitiveAccess};
+use super::settings::ToolSettings;
+use super::FileToolError;
+use crate::agent::interaction::interact;
+use colored::*;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 匹配 hunk 时最多忽略的首尾上下文行数
+const MAX_FUZZ: usize = 2;
+
+#[derive(Deserialize, Serialize)]
+pub struct ApplyPatchArgs {
+    pub patch: String,
+    /// 只应用能匹配的 hunk，其余的跳过（交互时由用户处理）
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// 补丁对单个文件的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAction {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl FileAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// 单个文件的改动统计
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PatchedFile {
+    pub path: String,
+    pub action: FileAction,
+    pub hunks: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// 没有在原位置精确匹配的 hunk
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyHunk {
+    pub path: String,
+    /// 文件内的 hunk 序号（从 1 开始）
+    pub hunk: usize,
+    /// 实际位置相对 hunk 头行号的偏移
+    pub offset: isize,
+    /// 忽略的上下文行数
+    pub fuzz: usize,
+}
+
+/// 部分应用时单个 hunk 的结果
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartialHunk {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: HunkReport,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApplyPatchOutput {
+    pub files: Vec<PatchedFile>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fuzzy_hunks: Vec<FuzzyHunk>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<PartialHunk>,
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Hunk {
+    /// hunk 头中旧文件的起始行号（新建文件为 0）
+    pub(super) old_start: usize,
+    pub(super) lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// 取 `lines[front..len - back]` 的旧内容和新内容
+    pub(super) fn sides(&self, front: usize, back: usize) -> (Vec<&str>, Vec<&str>) {
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        for line in &self.lines[front..self.lines.len() - back] {
+            match line {
+                HunkLine::Context(text) => {
+                    old.push(text.as_str());
+                    new.push(text.as_str());
+                }
+                HunkLine::Remove(text) => old.push(text.as_str()),
+                HunkLine::Add(text) => new.push(text.as_str()),
+            }
+        }
+        (old, new)
+    }
+
+    /// 开头和结尾的上下文行数
+    fn context_edges(&self) -> (usize, usize) {
+        let is_context = |line: &&HunkLine| matches!(line, HunkLine::Context(_));
+        let leading = self.lines.iter().take_while(is_context).count();
+        let trailing = self.lines.iter().rev().take_while(is_context).count();
+        (leading, trailing)
+    }
+}
+
+/// 补丁中一个文件的部分
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FilePatch {
+    /// `---` 中的路径（`/dev/null` 为 None）
+    old_path: Option<String>,
+    /// `+++` 中的路径（`/dev/null` 为 None）
+    new_path: Option<String>,
+    pub(super) hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn line_counts(&self) -> (usize, usize) {
+        let lines = self.hunks.iter().flat_map(|hunk| &hunk.lines);
+        let added = lines
+            .clone()
+            .filter(|l| matches!(l, HunkLine::Add(_)))
+            .count();
+        let removed = lines.filter(|l| matches!(l, HunkLine::Remove(_))).count();
+        (added, removed)
+    }
+}
+
+/// 解析 `---`/`+++` 中的路径：去掉时间戳和 git 的 `a/`、`b/` 前缀
+fn header_path(raw: &str, prefix: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// 从 `@@ -12,5 +12,7 @@` 中取旧文件的起始行号
+fn hunk_old_start(header: &str) -> Option<usize> {
+    let old = header.strip_prefix("@@")?.split_whitespace().next()?;
+    let old = old.strip_prefix('-')?;
+    old.split(',').next()?.parse().ok()
+}
+
+/// 解析多文件 unified diff
+///
+/// hunk 的行数以内容为准（不信任 hunk 头中的计数）；`diff --git`、`index` 等
+/// 文件头之外的行被忽略。
+pub(super) fn parse_patch(patch: &str) -> Result<Vec<FilePatch>, FileToolError> {
+    let patch = to_lf(patch);
+    let lines: Vec<&str> = patch.lines().collect();
+    let is_file_header = |i: usize| {
+        lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "))
+    };
+
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if is_file_header(i) {
+            files.push(FilePatch {
+                old_path: header_path(&lines[i][4..], "a/"),
+                new_path: header_path(&lines[i + 1][4..], "b/"),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if !lines[i].starts_with("@@") {
+            i += 1;
+            continue;
+        }
+
+        let header = lines[i];
+        let file = files.last_mut().ok_or_else(|| {
+            FileToolError::InvalidInput(format!(
+                "Hunk '{}' appears before any ---/+++ file header",
+                header
+            ))
+        })?;
+        let old_start = hunk_old_start(header).ok_or_else(|| {
+            FileToolError::InvalidInput(format!("Invalid hunk header: '{}'", header))
+        })?;
+        i += 1;
+
+        let mut hunk_lines = Vec::new();
+        // 结尾的空行通常是文件之间的分隔，而不是上下文
+        let mut bare_empty_tail = 0;
+        while i < lines.len() && !lines[i].starts_with("@@") && !is_file_header(i) {
+            let line = lines[i];
+            let parsed = match line.chars().next() {
+                Some(' ') => HunkLine::Context(line[1..].to_string()),
+                Some('-') => HunkLine::Remove(line[1..].to_string()),
+                Some('+') => HunkLine::Add(line[1..].to_string()),
+                // `\ No newline at end of file`：末尾换行沿用原文件
+                Some('\\') => {
+                    i += 1;
+                    continue;
+                }
+                None => HunkLine::Context(String::new()),
+                _ => break,
+            };
+            bare_empty_tail = if line.is_empty() {
+                bare_empty_tail + 1
+            } else {
+                0
+            };
+            hunk_lines.push(parsed);
+            i += 1;
+        }
+        hunk_lines.truncate(hunk_lines.len() - bare_empty_tail);
+        file.hunks.push(Hunk {
+            old_start,
+            lines: hunk_lines,
+        });
+    }
+
+    if files.is_empty() {
+        return Err(FileToolError::InvalidInput(
+            "No ---/+++ file headers found in patch".to_string(),
+        ));
+    }
+    Ok(files)
+}
+
+/// 从 `expected` 开始向两侧搜索 `old` 出现的位置（不早于 `min`）
+fn find_hunk(lines: &[String], old: &[&str], expected: isize, min: usize) -> Option<usize> {
+    let max_start = lines.len().checked_sub(old.len())?;
+    if min > max_start {
+        return None;
+    }
+    let expected = expected.clamp(min as isize, max_start as isize) as usize;
+    if old.is_empty() {
+        return Some(expected);
+    }
+    let matches = |start: usize| {
+        lines[start..start + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(line, old)| line == old)
+    };
+    (0..=max_start - min).find_map(|distance| {
+        let after = expected + distance;
+        if after <= max_start && matches(after) {
+            return Some(after);
+        }
+        let before = expected
+            .checked_sub(distance)
+            .filter(|start| *start >= min)?;
+        matches(before).then_some(before)
+    })
+}
+
+/// 不精确匹配的 hunk：（序号、偏移、fuzz）
+type InexactHunk = (usize, isize, usize);
+
+/// hunk 在文件中匹配到的位置
+pub(super) struct HunkMatch<'a> {
+    /// 忽略的上下文行数
+    pub(super) fuzz: usize,
+    /// 开头忽略的上下文行数
+    pub(super) front: usize,
+    /// 匹配的起始行（从 0 开始）
+    pub(super) start: usize,
+    /// 被替换的行数
+    pub(super) old_len: usize,
+    pub(super) new: Vec<&'a str>,
+}
+
+/// 在 `expected` 附近查找 hunk（不早于 `min`），需要时忽略最多 [`MAX_FUZZ`] 行首尾上下文
+pub(super) fn locate_hunk<'a>(
+    lines: &[String],
+    hunk: &'a Hunk,
+    expected: isize,
+    min: usize,
+) -> Option<HunkMatch<'a>> {
+    let (leading, trailing) = hunk.context_edges();
+    let context_only = leading == hunk.lines.len();
+    // fuzz 为 n 时首尾各最多忽略 n 行上下文
+    let trim = |fuzz: usize| {
+        let back = if context_only { 0 } else { fuzz.min(trailing) };
+        (fuzz.min(leading), back)
+    };
+
+    (0..=MAX_FUZZ).find_map(|fuzz| {
+        let (front, back) = trim(fuzz);
+        if fuzz > 0 && (front, back) == trim(fuzz - 1) {
+            return None;
+        }
+        let (old, new) = hunk.sides(front, back);
+        if fuzz > 0 && old.is_empty() {
+            return None;
+        }
+        let start = find_hunk(lines, &old, expected + front as isize, min)?;
+        Some(HunkMatch {
+            fuzz,
+            front,
+            start,
+            old_len: old.len(),
+            new,
+        })
+    })
+}
+
+/// 在内存中依次应用一个文件的全部 hunk
+///
+/// 返回新内容的行和不精确匹配的 hunk；失败时返回每个不匹配 hunk 的说明。
+fn apply_hunks(
+    original: &[&str],
+    hunks: &[Hunk],
+) -> Result<(Vec<String>, Vec<InexactHunk>), Vec<String>> {
+    let mut lines: Vec<String> = original.iter().map(|line| line.to_string()).collect();
+    let mut inexact = Vec::new();
+    let mut failures = Vec::new();
+    // 之前的 hunk 造成的行数变化，加上上一个 hunk 的偏移
+    let mut shift: isize = 0;
+    let mut min = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let expected = hunk.old_start.saturating_sub(1) as isize + shift;
+        let Some(HunkMatch {
+            fuzz,
+            front,
+            start,
+            old_len,
+            new,
+        }) = locate_hunk(&lines, hunk, expected, min)
+        else {
+            failures.push(format!(
+                "hunk {} (@@ -{}) does not match the current file content",
+                index + 1,
+                hunk.old_start
+            ));
+            continue;
+        };
+        let offset = start as isize - (expected + front as isize);
+        if offset != 0 || fuzz > 0 {
+            inexact.push((index + 1, offset, fuzz));
+        }
+        let new_len = new.len();
+        lines.splice(start..start + old_len, new.into_iter().map(str::to_string));
+        shift += new_len as isize - old_len as isize + offset;
+        min = start + new_len;
+    }
+
+    if failures.is_empty() {
+        Ok((lines, inexact))
+    } else {
+        Err(failures)
+    }
+}
+
+/// 把补丁中的路径解析到工作目录下，拒绝工作目录之外的路径
+fn resolve_path(root: &Path, raw: &str) -> Result<PathBuf, FileToolError> {
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(raw).components() {
+        match component {
+            Component::Prefix(prefix) => resolved = PathBuf::from(prefix.as_os_str()),
+            Component::RootDir => resolved.push(Component::RootDir),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => resolved.push(part),
+        }
+    }
+    let outside =
+        || FileToolError::PermissionDenied(format!("{} (outside the working directory)", raw));
+    if !resolved.starts_with(root) {
+        return Err(outside());
+    }
+    // 符号链接可能指向工作目录之外
+    if let (Ok(real), Ok(real_root)) = (resolved.canonicalize(), root.canonicalize()) {
+        if !real.starts_with(real_root) {
+            return Err(outside());
+        }
+    }
+    if let SensitiveAccess::Denied { pattern } = get_sensitive_guard().check(&resolved) {
+        return Err(FileToolError::PermissionDenied(format!(
+            "{} (matches sensitive path pattern '{}'; configure [permissions.sensitive_paths] to change this)",
+            raw, pattern
+        )));
+    }
+    Ok(resolved)
+}
+
+/// 试应用的结果：待写入的改动、统计、不精确的 hunk 和部分应用时每个 hunk 的结果
+struct PreparedPatch {
+    changes: Vec<FileChange>,
+    files: Vec<PatchedFile>,
+    fuzzy_hunks: Vec<FuzzyHunk>,
+    hunks: Vec<PartialHunk>,
+}
+
+/// 试应用所有文件的补丁（不写文件），收集全部错误
+///
+/// 传入 `resolver` 时部分应用：不匹配的 hunk 交给它处理，不算作错误。
+fn prepare(
+    root: &Path,
+    file_patches: &[FilePatch],
+    mut resolver: Option<&mut dyn HunkResolver>,
+) -> Result<PreparedPatch, FileToolError> {
+    let mut prepared = PreparedPatch {
+        changes: Vec::new(),
+        files: Vec::new(),
+        fuzzy_hunks: Vec::new(),
+        hunks: Vec::new(),
+    };
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+
+    for file_patch in file_patches {
+        let (display, action) = match (&file_patch.old_path, &file_patch.new_path) {
+            (None, Some(new)) => (new, FileAction::Created),
+            (Some(old), None) => (old, FileAction::Deleted),
+            (Some(old), Some(new)) if old == new => (new, FileAction::Modified),
+            (Some(old), Some(new)) => {
+                errors.push(format!("{} -> {}: renames are not supported", old, new));
+                continue;
+            }
+            (None, None) => {
+                errors.push("file header with /dev/null on both sides".to_string());
+                continue;
+            }
+        };
+        let path = match resolve_path(root, display) {
+            Ok(path) => path,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+        if !seen.insert(path.clone()) {
+            errors.push(format!("{}: appears more than once in the patch", display));
+            continue;
+        }
+
+        let original = match action {
+            FileAction::Created if path.exists() => {
+                errors.push(format!("{}: file already exists", display));
+                continue;
+            }
+            FileAction::Created => String::new(),
+            _ if !path.exists() => {
+                errors.push(format!("{}: file not found", display));
+                continue;
+            }
+            _ if !path.is_file() => {
+                errors.push(format!("{}: not a file", display));
+                continue;
+            }
+            _ => match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    errors.push(format!("{}: {}", display, e));
+                    continue;
+                }
+            },
+        };
+
+        // 在 LF 文本上匹配，写回时恢复原有行尾和末尾换行
+        let line_endings = LineEndingInfo::detect(&original);
+        let normalized = line_endings.normalize(&original);
+        let original_lines: Vec<&str> = normalized.lines().collect();
+        let (lines, inexact, (lines_added, lines_removed)) = match resolver.as_deref_mut() {
+            Some(resolver) => {
+                let result = apply_partial(display, &original_lines, &file_patch.hunks, resolver);
+                let applied = result
+                    .reports
+                    .iter()
+                    .any(|r| r.status != HunkStatus::Skipped);
+                prepared
+                    .hunks
+                    .extend(result.reports.into_iter().map(|report| PartialHunk {
+                        path: display.clone(),
+                        report,
+                    }));
+                // 没有 hunk 能应用的文件保持不变
+                if !applied {
+                    continue;
+                }
+                (
+                    result.lines,
+                    Vec::new(),
+                    (result.lines_added, result.lines_removed),
+                )
+            }
+            None => match apply_hunks(&original_lines, &file_patch.hunks) {
+                Ok((lines, inexact)) => (lines, inexact, file_patch.line_counts()),
+                Err(failures) => {
+                    errors.extend(failures.into_iter().map(|f| format!("{}: {}", display, f)));
+                    continue;
+                }
+            },
+        };
+
+        let contents = match action {
+            FileAction::Deleted if !lines.is_empty() => {
+                errors.push(format!(
+                    "{}: patch deletes the file but {} lines would remain",
+                    display,
+                    lines.len()
+                ));
+                continue;
+            }
+            FileAction::Deleted => None,
+            _ if lines.is_empty() => Some(Vec::new()),
+            _ => Some(
+                line_endings
+                    .restore(&format!("{}\n", lines.join("\n")))
+                    .into_bytes(),
+            ),
+        };
+
+        prepared.changes.push(FileChange { path, contents });
+        prepared.files.push(PatchedFile {
+            path: display.clone(),
+            action,
+            hunks: file_patch.hunks.len(),
+            lines_added,
+            lines_removed,
+        });
+        prepared
+            .fuzzy_hunks
+            .extend(inexact.into_iter().map(|(hunk, offset, fuzz)| FuzzyHunk {
+                path: display.clone(),
+                hunk,
+                offset,
+                fuzz,
+            }));
+    }
+
+    if errors.is_empty() {
+        Ok(prepared)
+    } else {
+        Err(FileToolError::InvalidInput(format!(
+            "Patch not applied, no files were changed:\n- {}\n\nRe-read the affected files and regenerate the failing hunks.",
+            errors.join("\n- ")
+        )))
+    }
+}
+
+/// 在 `root` 下应用补丁，传入 `resolver` 时部分应用
+fn apply_in(
+    root: &Path,
+    patch: &str,
+    resolver: Option<&mut dyn HunkResolver>,
+) -> Result<ApplyPatchOutput, FileToolError> {
+    let file_patches = parse_patch(patch)?;
+    let prepared = prepare(root, &file_patches, resolver)?;
+    let skipped: Vec<String> = prepared
+        .hunks
+        .iter()
+        .filter(|h| h.report.status == HunkStatus::Skipped)
+        .map(|h| {
+            format!(
+                "{} hunk {} (@@ -{})",
+                h.path, h.report.hunk, h.report.old_start
+            )
+        })
+        .collect();
+    if prepared.changes.is_empty() && !skipped.is_empty() {
+        return Err(FileToolError::InvalidInput(format!(
+            "Patch not applied, no hunk matches the current files: {}\n\nRe-read the affected files and regenerate the patch.",
+            skipped.join(", ")
+        )));
+    }
+
+    for change in &prepared.changes {
+        if let (Some(_), Some(parent)) = (&change.contents, change.path.parent()) {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    apply_all_with_backup(&prepared.changes).map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => {
+            FileToolError::PermissionDenied(format!("{} (no files were changed)", e))
+        }
+        _ => FileToolError::Io(e),
+    })?;
+
+    let count = |action| prepared.files.iter().filter(|f| f.action == action).count();
+    let (added, removed) = prepared
+        .files
+        .iter()
+        .fold((0, 0), |(added, removed), file| {
+            (added + file.lines_added, removed + file.lines_removed)
+        });
+    let mut message = format!(
+        "Applied patch to {} files ({} created, {} modified, {} deleted): +{} lines, -{} lines",
+        prepared.files.len(),
+        count(FileAction::Created),
+        count(FileAction::Modified),
+        count(FileAction::Deleted),
+        added,
+        removed
+    );
+    if !prepared.fuzzy_hunks.is_empty() {
+        message.push_str(&format!(
+            "; {} hunks applied at an offset or with fuzz, check them",
+            prepared.fuzzy_hunks.len()
+        ));
+    }
+    let retargeted = prepared
+        .hunks
+        .iter()
+        .filter(|h| h.report.status == HunkStatus::Retargeted)
+        .count();
+    if retargeted > 0 {
+        message.push_str(&format!(
+            "; {} hunks retargeted by the user, check them",
+            retargeted
+        ));
+    }
+    if !skipped.is_empty() {
+        message.push_str(&format!(
+            "; skipped {}, regenerate only these hunks",
+            skipped.join(", ")
+        ));
+    }
+
+    Ok(ApplyPatchOutput {
+        files: prepared.files,
+        fuzzy_hunks: prepared.fuzzy_hunks,
+        hunks: prepared.hunks,
+        success: true,
+        message,
+    })
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct ApplyPatchTool {
+    settings: ToolSettings,
+}
+
+impl ApplyPatchTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// 补丁中相对路径的基准目录（`[tools.root] path`，未设置时为当前目录）
+    fn root(&self) -> std::io::Result<PathBuf> {
+        match &self.settings.root {
+            Some(root) => Ok(root.clone()),
+            None => std::env::current_dir(),
+        }
+    }
+}
+
+impl Tool for ApplyPatchTool {
+    const NAME: &'static str = "apply_patch";
+
+    type Error = FileToolError;
+    type Args = ApplyPatchArgs;
+    type Output = ApplyPatchOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "apply_patch".to_string(),
+            description: "Apply a unified diff that changes several files at once (e.g. the output of `git diff` or a patch from a pull request). Each file starts with `--- a/path` and `+++ b/path` headers followed by `@@` hunks; use `--- /dev/null` to create a file and `+++ /dev/null` to delete one. Paths are relative to the working directory. Every hunk is checked against the current files first: if any hunk does not apply, no file is changed and the failing hunks are reported. Hunks whose line numbers are off are located nearby, ignoring up to 2 context lines if needed; such hunks are listed in fuzzy_hunks and should be double-checked. Set partial=true to apply the hunks that match and skip the rest instead (in an interactive session the user decides what to do with each failing hunk); every hunk is then listed in hunks as applied, skipped or retargeted. For a change to a single file prefer edit_file or search_replace.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "patch": {
+                        "type": "string",
+                        "description": "The multi-file unified diff to apply."
+                    },
+                    "partial": {
+                        "type": "boolean",
+                        "description": "When true, apply the hunks that match and skip the ones that don't instead of changing nothing. Default: false."
+                    }
+                },
+                "required": ["patch"]
+            })
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = self.root()?;
+        let mut skip = SkipFailing;
+        let resolver = args.partial.then_some(&mut skip as &mut dyn HunkResolver);
+        apply_in(&root, &args.patch, resolver)
+    }
+}
+
+/// 部分应用时是否有 hunk 会被跳过（需要询问用户）
+fn has_failing_hunks(root: &Path, patch: &str) -> bool {
+    parse_patch(patch)
+        .and_then(|files| prepare(root, &files, Some(&mut SkipFailing)))
+        .is_ok_and(|prepared| {
+            prepared
+                .hunks
+                .iter()
+                .any(|h| h.report.status == HunkStatus::Skipped)
+        })
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedApplyPatchTool {
+    inner: ApplyPatchTool,
+}
+
+impl WrappedApplyPatchTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: ApplyPatchTool::new(settings),
+        }
+    }
+}
+
+impl Tool for WrappedApplyPatchTool {
+    const NAME: &'static str = "apply_patch";
+
+    type Error = FileToolError;
+    type Args = <ApplyPatchTool as Tool>::Args;
+    type Output = <ApplyPatchTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        tool_println!("{} ApplyPatch", "●".bright_green());
+
+        let root = self.inner.root()?;
+        let result = if args.partial && has_failing_hunks(&root, &args.patch) {
+            let (root, patch) = (root.clone(), args.patch.clone());
+            let resolved = interact(move || apply_in(&root, &patch, Some(&mut AskUser))).await;
+            match resolved {
+                Ok(result) => result,
+                // 没有终端时与非交互一样跳过
+                Err(_) => self.inner.call(args).await,
+            }
+        } else {
+            self.inner.call(args).await
+        };
+        match &result {
+            Ok(output) => {
+                for file in &output.files {
+                    tool_println!(
+                        "  └─ {} {} (+{} lines, -{} lines)",
+                        file.action.as_str().dimmed(),
+                        file.path,
+                        file.lines_added.to_string().green(),
+                        file.lines_removed.to_string().red()
+                    );
+                    if file.action != FileAction::Deleted {
+                        if let Some(format) = format_after_write(&root.join(&file.path)).await {
+                            print_result(&format);
+                        }
+                    }
+                }
+                for hunk in &output.fuzzy_hunks {
+                    tool_println!(
+                        "  └─ {}",
+                        format!(
+                            "{} hunk {}: offset {}, fuzz {}",
+                            hunk.path, hunk.hunk, hunk.offset, hunk.fuzz
+                        )
+                        .yellow()
+                    );
+                }
+                for hunk in &output.hunks {
+                    let status = match (hunk.report.status, hunk.report.line) {
+                        (HunkStatus::Applied, _) => continue,
+                        (HunkStatus::Retargeted, Some(line)) => {
+                            format!("retargeted to line {}", line)
+                        }
+                        _ => "skipped".to_string(),
+                    };
+                    tool_println!(
+                        "  └─ {}",
+                        format!("{} hunk {}: {}", hunk.path, hunk.report.hunk, status).yellow()
+                    );
+                }
+            }
+            Err(e) => {
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn read(dir: &TempDir, path: &str) -> String {
+        fs::read_to_string(dir.path().join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_multi_file_patch() {
+        let patch = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                     index 83db48f..bf269f4 100644\n\
+                     --- a/src/lib.rs\t2024-01-01 00:00:00\n\
+                     +++ b/src/lib.rs\n\
+                     @@ -1,2 +1,2 @@\n\
+                     \x20mod a;\n\
+                     -mod b;\n\
+                     +mod c;\n\
+                     \n\
+                     --- /dev/null\n\
+                     +++ b/src/c.rs\n\
+                     @@ -0,0 +1 @@\n\
+                     +pub fn c() {}\n\
+                     \\ No newline at end of file\n";
+        let files = parse_patch(patch).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].old_path.as_deref(), Some("src/lib.rs"));
+        assert_eq!(files[0].new_path.as_deref(), Some("src/lib.rs"));
+        // 文件之间的空行不算上下文
+        assert_eq!(
+            files[0].hunks[0].lines,
+            vec![
+                HunkLine::Context("mod a;".to_string()),
+                HunkLine::Remove("mod b;".to_string()),
+                HunkLine::Add("mod c;".to_string()),
+            ]
+        );
+        assert_eq!(files[1].old_path, None);
+        assert_eq!(files[1].hunks[0].old_start, 0);
+        assert_eq!(files[1].line_counts(), (1, 0));
+
+        assert!(parse_patch("just some text").is_err());
+        assert!(parse_patch("@@ -1 +1 @@\n-a\n+b\n").is_err());
+    }
+
+    #[test]
+    fn test_create_modify_delete_in_one_patch() {
+        let dir = workspace(&[
+            ("src/lib.rs", "mod a;\nmod b;\n\nfn main() {}\n"),
+            ("src/b.rs", "pub fn b() {}\n"),
+            ("win.txt", "one\r\ntwo\r\n"),
+        ]);
+        let patch = "--- a/src/lib.rs\n\
+                     +++ b/src/lib.rs\n\
+                     @@ -1,3 +1,3 @@\n\
+                     \x20mod a;\n\
+                     -mod b;\n\
+                     +mod c;\n\
+                     \x20\n\
+                     --- a/src/b.rs\n\
+                     +++ /dev/null\n\
+                     @@ -1 +0,0 @@\n\
+                     -pub fn b() {}\n\
+                     --- /dev/null\n\
+                     +++ b/src/nested/c.rs\n\
+                     @@ -0,0 +1,2 @@\n\
+                     +pub fn c() {}\n\
+                     +pub fn d() {}\n\
+                     --- a/win.txt\n\
+                     +++ b/win.txt\n\
+                     @@ -2 +2 @@\n\
+                     -two\n\
+                     +three\n";
+
+        let output = apply_in(dir.path(), patch, None).unwrap();
+        assert_eq!(read(&dir, "src/lib.rs"), "mod a;\nmod c;\n\nfn main() {}\n");
+        assert!(!dir.path().join("src/b.rs").exists());
+        assert_eq!(
+            read(&dir, "src/nested/c.rs"),
+            "pub fn c() {}\npub fn d() {}\n"
+        );
+        assert_eq!(read(&dir, "win.txt"), "one\r\nthree\r\n");
+
+        let actions: Vec<_> = output
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.action))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("src/lib.rs", FileAction::Modified),
+                ("src/b.rs", FileAction::Deleted),
+                ("src/nested/c.rs", FileAction::Created),
+                ("win.txt", FileAction::Modified),
+            ]
+        );
+        assert_eq!(
+            (output.files[2].lines_added, output.files[2].lines_removed),
+            (2, 0)
+        );
+        assert!(output.fuzzy_hunks.is_empty());
+        assert!(output.message.contains("1 created, 2 modified, 1 deleted"));
+    }
+
+    #[test]
+    fn test_failing_hunk_changes_nothing() {
+        let dir = workspace(&[
+            ("a.txt", "alpha\nbeta\n"),
+            ("b.txt", "gamma\ndelta\n"),
+            ("gone.txt", "bye\n"),
+        ]);
+        let patch = "--- a/a.txt\n\
+                     +++ b/a.txt\n\
+                     @@ -1,2 +1,2 @@\n\
+                     \x20alpha\n\
+                     -beta\n\
+                     +BETA\n\
+                     --- /dev/null\n\
+                     +++ b/new.txt\n\
+                     @@ -0,0 +1 @@\n\
+                     +hello\n\
+                     --- a/gone.txt\n\
+                     +++ /dev/null\n\
+                     @@ -1 +0,0 @@\n\
+                     -bye\n\
+                     --- a/b.txt\n\
+                     +++ b/b.txt\n\
+                     @@ -1,2 +1,2 @@\n\
+                     \x20gamma\n\
+                     -epsilon\n\
+                     +EPSILON\n";
+
+        let error = apply_in(dir.path(), patch, None).unwrap_err().to_string();
+        assert!(error.contains("b.txt: hunk 1"), "{}", error);
+        assert!(error.contains("no files were changed"));
+        assert_eq!(read(&dir, "a.txt"), "alpha\nbeta\n");
+        assert_eq!(read(&dir, "gone.txt"), "bye\n");
+        assert!(!dir.path().join("new.txt").exists());
+
+        // 部分应用时只跳过不匹配的 hunk，b.txt 保持不变
+        let output = apply_in(dir.path(), patch, Some(&mut SkipFailing)).unwrap();
+        let changed: Vec<&str> = output.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(changed, ["a.txt", "new.txt", "gone.txt"]);
+        assert_eq!(read(&dir, "a.txt"), "alpha\nBETA\n");
+        assert_eq!(read(&dir, "b.txt"), "gamma\ndelta\n");
+        assert!(!dir.path().join("gone.txt").exists());
+        assert_eq!(output.hunks.len(), 4);
+        assert_eq!(output.hunks[3].report.status, HunkStatus::Skipped);
+        assert!(output.message.contains("skipped b.txt hunk 1 (@@ -1)"));
+    }
+
+    #[test]
+    fn test_offset_and_fuzz_are_reported() {
+        let dir = workspace(&[(
+            "main.rs",
+            "// header\n// added later\nfn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n",
+        )]);
+        // 行号偏了一行，最后一行上下文也已改变
+        let patch = "--- a/main.rs\n\
+                     +++ b/main.rs\n\
+                     @@ -2,4 +2,4 @@\n\
+                     \x20fn main() {\n\
+                     -    let x = 1;\n\
+                     +    let x = 2;\n\
+                     \x20    println!(\"{}\", x);\n\
+                     \x20}  // end\n";
+
+        let output = apply_in(dir.path(), patch, None).unwrap();
+        assert!(read(&dir, "main.rs").contains("let x = 2;"));
+        assert_eq!(
+            output.fuzzy_hunks,
+            vec![FuzzyHunk {
+                path: "main.rs".to_string(),
+                hunk: 1,
+                offset: 1,
+                fuzz: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_paths_outside_workspace_rejected() {
+        let dir = workspace(&[("a.txt", "a\n")]);
+        for path in ["../escape.txt", "/etc/passwd", "src/../../escape.txt"] {
+            let patch = format!("--- /dev/null\n+++ {}\n@@ -0,0 +1 @@\n+x\n", path);
+            let error = apply_in(dir.path(), &patch, None).unwrap_err().to_string();
+            assert!(error.contains("outside the working directory"), "{}", error);
+        }
+        assert!(resolve_path(dir.path(), "./src/../a.txt").is_ok());
+    }
+}
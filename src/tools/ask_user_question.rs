@@ -1,16 +1,24 @@
 //! AskUserQuestion 工具
 //!
-//! 向用户提问并收集答案。
+//! 向用户提问并收集答案。选项列表直接用 crossterm 绘制：↑↓ 移动，空格切换（多选）或选中（单选），
+//! `a` / `n` 全选 / 全不选，Enter 确认，Esc 跳过。描述按终端宽度折行。
+//! 终端交互前后的状态（raw mode、状态栏的滚动区域）由 `interaction::interact` 暂停和恢复。
 
 #![allow(dead_code)]
 
 use super::FileToolError;
+use crate::agent::interaction;
 use colored::*;
-use inquire::{InquireError, MultiSelect, Select};
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// 问题选项
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +45,10 @@ pub struct Question {
     /// 是否允许多选
     #[serde(default)]
     pub multi_select: bool,
+
+    /// 是否提供"其他…"选项，让用户输入自己的答案（答案中为输入的文本）
+    #[serde(default)]
+    pub allow_other: bool,
 }
 
 /// AskUserQuestion 工具输入参数
@@ -86,29 +98,281 @@ pub fn ask_question_interactive(question: &Question) -> Result<Answer, FileToolE
     AskUserQuestionTool::ask_question(question)
 }
 
-impl AskUserQuestionTool {
-    fn ask_question_free_text(question: &Question) -> Result<Answer, FileToolError> {
-        print!("{} ", question.question.bright_green());
-        io::stdout().flush().map_err(|e| FileToolError::Io(e))?;
+/// 选项列表中的按键操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChoiceKey {
+    Up,
+    Down,
+    /// 空格：多选时切换当前项，单选时选中当前项
+    Toggle,
+    /// `a`：全选（多选）
+    All,
+    /// `n`：全不选（多选）
+    None,
+    Confirm,
+    Cancel,
+}
 
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .map_err(|e| FileToolError::Io(e))?;
-
-        let input = input.trim().to_string();
-        if input.is_empty() {
-            return Ok(Answer {
-                question_header: question.header.clone(),
-                selected: serde_json::json!(null),
-                has_answer: false,
-            });
+impl ChoiceKey {
+    fn from_event(key: &KeyEvent) -> Option<Self> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return matches!(key.code, KeyCode::Char('c') | KeyCode::Char('d'))
+                .then_some(ChoiceKey::Cancel);
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => Some(ChoiceKey::Up),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => Some(ChoiceKey::Down),
+            KeyCode::Char(' ') => Some(ChoiceKey::Toggle),
+            KeyCode::Char('a') => Some(ChoiceKey::All),
+            KeyCode::Char('n') => Some(ChoiceKey::None),
+            KeyCode::Enter => Some(ChoiceKey::Confirm),
+            KeyCode::Esc => Some(ChoiceKey::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// 选项列表的状态，`allow_other` 时最后一项是"其他…"
+#[derive(Debug)]
+struct ChoiceState {
+    multi: bool,
+    cursor: usize,
+    checked: Vec<bool>,
+    /// 选项数（不含"其他…"）
+    options: usize,
+}
+
+impl ChoiceState {
+    fn new(question: &Question) -> Self {
+        let items = question.options.len() + usize::from(question.allow_other);
+        Self {
+            multi: question.multi_select,
+            cursor: 0,
+            checked: vec![false; items],
+            options: question.options.len(),
+        }
+    }
+
+    /// 处理一次按键，结束时返回选中的序号（`Some(vec![])` 表示未选择任何项，`None` 表示继续）
+    fn apply(&mut self, key: ChoiceKey) -> Option<Option<Vec<usize>>> {
+        let items = self.checked.len();
+        match key {
+            ChoiceKey::Up => self.cursor = (self.cursor + items - 1) % items,
+            ChoiceKey::Down => self.cursor = (self.cursor + 1) % items,
+            ChoiceKey::Toggle if self.multi => self.checked[self.cursor] ^= true,
+            ChoiceKey::Toggle => return Some(Some(vec![self.cursor])),
+            // "其他…"需要输入文本，全选时不包括它
+            ChoiceKey::All if self.multi => self.checked[..self.options].fill(true),
+            ChoiceKey::None if self.multi => self.checked.fill(false),
+            ChoiceKey::All | ChoiceKey::None => {}
+            ChoiceKey::Confirm if self.multi => {
+                return Some(Some((0..items).filter(|&i| self.checked[i]).collect()))
+            }
+            ChoiceKey::Confirm => return Some(Some(vec![self.cursor])),
+            ChoiceKey::Cancel => return Some(None),
+        }
+        None
+    }
+
+    /// 渲染选项列表，每行不超过 `width` 列；`descriptions` 为 false 时只显示标签
+    fn render(&self, question: &Question, width: usize, descriptions: bool) -> Vec<String> {
+        let other = t!("ask.other");
+        let labels = question
+            .options
+            .iter()
+            .map(|option| (option.label.as_str(), option.description.as_str()))
+            .chain(question.allow_other.then_some((other, "")));
+
+        let mut lines = Vec::new();
+        for (i, (label, description)) in labels.enumerate() {
+            let pointer = if i == self.cursor { "❯" } else { " " };
+            let mark = match (self.multi, self.checked[i]) {
+                (false, _) => "",
+                (true, true) => "◉ ",
+                (true, false) => "◯ ",
+            };
+            let prefix = format!("{} {}", pointer, mark);
+            let indent = display_width(&prefix);
+            for (n, line) in wrap(label, width.saturating_sub(indent))
+                .into_iter()
+                .enumerate()
+            {
+                let line = if i == self.cursor {
+                    line.bright_cyan().bold().to_string()
+                } else {
+                    line
+                };
+                if n == 0 {
+                    lines.push(format!("{}{}", prefix.bright_cyan(), line));
+                } else {
+                    lines.push(format!("{}{}", " ".repeat(indent), line));
+                }
+            }
+            if descriptions && !description.is_empty() {
+                let indent = indent + 2;
+                for line in wrap(description, width.saturating_sub(indent)) {
+                    lines.push(format!("{}{}", " ".repeat(indent), line.dimmed()));
+                }
+            }
+        }
+        let keys = if self.multi {
+            t!("ask.multi_keys")
+        } else {
+            t!("ask.single_keys")
+        };
+        lines.extend(
+            wrap(keys, width)
+                .into_iter()
+                .map(|line| line.bright_black().to_string()),
+        );
+        lines
+    }
+}
+
+/// 文本的显示宽度（中日韩字符占两列）
+fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// 按显示宽度折行：优先在空白处断开，单个词超过一行时在字符处断开
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let word_width = display_width(word);
+            let space = usize::from(!line.is_empty());
+            if line_width + space + word_width <= width {
+                if space == 1 {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                line_width += space + word_width;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            for ch in word.chars() {
+                let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+                if line_width + ch_width > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                }
+                line.push(ch);
+                line_width += ch_width;
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// 由选中的标签构造答案：单选为字符串，多选为数组，没有选择时为 null
+fn build_answer(question: &Question, labels: Vec<String>) -> Answer {
+    let has_answer = !labels.is_empty();
+    let selected = if !has_answer {
+        serde_json::json!(null)
+    } else if question.multi_select {
+        serde_json::json!(labels)
+    } else {
+        serde_json::json!(labels[0])
+    };
+    Answer {
+        question_header: question.header.clone(),
+        selected,
+        has_answer,
+    }
+}
+
+/// 解析手动输入的答案：序号从 1 开始，多选时用逗号分隔
+///
+/// 允许"其他…"时，不是序号的输入作为用户自己填写的答案。
+fn parse_manual_input(question: &Question, input: &str) -> Vec<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Vec::new();
+    }
+    let option = |token: &str| {
+        token
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&i| i >= 1 && i <= question.options.len())
+            .map(|i| question.options[i - 1].label.clone())
+    };
+
+    if !question.multi_select {
+        return match option(input) {
+            Some(label) => vec![label],
+            None if question.allow_other => vec![input.to_string()],
+            None => Vec::new(),
+        };
+    }
+
+    let mut labels = Vec::new();
+    let mut other = Vec::new();
+    for token in input.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        match option(token) {
+            Some(label) if !labels.contains(&label) => labels.push(label),
+            Some(_) => {}
+            None => other.push(token),
+        }
+    }
+    if question.allow_other && !other.is_empty() {
+        labels.push(other.join(", "));
+    }
+    labels
+}
+
+/// 选项列表显示期间开启 raw mode，结束时恢复原来的状态
+struct RawMode {
+    was_raw: bool,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let was_raw = terminal::is_raw_mode_enabled()?;
+        if !was_raw {
+            terminal::enable_raw_mode()?;
         }
+        Ok(Self { was_raw })
+    }
+}
 
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if !self.was_raw {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+impl AskUserQuestionTool {
+    fn read_line() -> Result<String, FileToolError> {
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        Ok(input.trim().to_string())
+    }
+
+    fn ask_question_free_text(question: &Question) -> Result<Answer, FileToolError> {
+        print!("{} ", question.question.bright_green());
+        let input = Self::read_line()?;
         Ok(Answer {
             question_header: question.header.clone(),
-            selected: serde_json::json!(input),
-            has_answer: true,
+            has_answer: !input.is_empty(),
+            selected: if input.is_empty() {
+                serde_json::json!(null)
+            } else {
+                serde_json::json!(input)
+            },
         })
     }
 
@@ -117,139 +381,125 @@ impl AskUserQuestionTool {
             return Self::ask_question_free_text(question);
         }
 
-        let prompt = if question.multi_select {
-            format!(
-                "{} (多个选项用逗号分隔, 例如: 1,3): ",
-                "选择".bright_green()
-            )
+        println!("{}", question.question.bright_green());
+        for (i, option) in question.options.iter().enumerate() {
+            println!(
+                "  {}. {} - {}",
+                i + 1,
+                option.label,
+                option.description.dimmed()
+            );
+        }
+        let hint = if question.multi_select {
+            t!("ask.multi_hint")
         } else {
-            format!("{} (输入数字): ", "选择".bright_green())
+            t!("ask.single_hint")
         };
+        print!("{} {}", t!("ask.choose").bright_green(), hint);
+        let input = Self::read_line()?;
+        Ok(build_answer(question, parse_manual_input(question, &input)))
+    }
 
-        print!("{}", prompt);
-        io::stdout().flush().map_err(|e| FileToolError::Io(e))?;
-
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .map_err(|e| FileToolError::Io(e))?;
-
-        let input = input.trim();
-
-        if input.is_empty() {
-            return Ok(Answer {
-                question_header: question.header.clone(),
-                selected: serde_json::json!(null),
-                has_answer: false,
-            });
-        }
-
-        if question.multi_select {
-            let selected_indices: Vec<usize> = input
-                .split(',')
-                .map(|s| s.trim().parse::<usize>().unwrap_or(0))
-                .filter(|&i| i >= 1 && i <= question.options.len())
-                .map(|i| i - 1)
-                .collect();
-
-            let selected_labels: Vec<String> = selected_indices
-                .iter()
-                .filter_map(|&i| question.options.get(i).map(|o| o.label.clone()))
-                .collect();
-
-            Ok(Answer {
-                question_header: question.header.clone(),
-                selected: serde_json::json!(selected_labels),
-                has_answer: !selected_labels.is_empty(),
-            })
-        } else {
-            match input.trim().parse::<usize>() {
-                Ok(choice) if choice >= 1 && choice <= question.options.len() => {
-                    let index = choice - 1;
-                    let selected_label = &question.options[index].label;
-
-                    Ok(Answer {
-                        question_header: question.header.clone(),
-                        selected: serde_json::json!(selected_label),
-                        has_answer: true,
-                    })
+    /// 显示选项列表直到用户确认或取消，返回选中的序号
+    fn select_options(question: &Question) -> io::Result<Option<Vec<usize>>> {
+        let mut state = ChoiceState::new(question);
+        let mut stdout = io::stdout();
+        let _raw = RawMode::enable()?;
+        execute!(stdout, cursor::Hide)?;
+
+        let mut drawn = 0usize;
+        let result = loop {
+            let (width, rows) = terminal::size().unwrap_or((80, 24));
+            // 留一列避免写满最后一列时终端自动换行，行数超出屏幕时不显示描述
+            let width = (width as usize).saturating_sub(1).max(20);
+            let mut lines = state.render(question, width, true);
+            if lines.len() + 1 >= rows as usize {
+                lines = state.render(question, width, false);
+            }
+            if drawn > 1 {
+                queue!(stdout, cursor::MoveUp((drawn - 1) as u16))?;
+            }
+            queue!(
+                stdout,
+                cursor::MoveToColumn(0),
+                Clear(ClearType::FromCursorDown),
+                Print(lines.join("\r\n"))
+            )?;
+            stdout.flush()?;
+            drawn = lines.len();
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(done) = ChoiceKey::from_event(&key).and_then(|key| state.apply(key)) {
+                    break done;
                 }
-                _ => Ok(Answer {
-                    question_header: question.header.clone(),
-                    selected: serde_json::json!(null),
-                    has_answer: false,
-                }),
             }
+        };
+
+        // 清掉选项列表，只保留一行结果
+        if drawn > 1 {
+            queue!(stdout, cursor::MoveUp((drawn - 1) as u16))?;
         }
+        queue!(
+            stdout,
+            cursor::MoveToColumn(0),
+            Clear(ClearType::FromCursorDown),
+            cursor::Show
+        )?;
+        stdout.flush()?;
+        Ok(result)
     }
 
     /// 显示单个问题并收集答案 (CLI 模式)
-    #[allow(dead_code)]
     fn ask_question_cli(question: &Question) -> Result<Answer, FileToolError> {
         println!();
         if !question.header.is_empty() {
-             println!("{} {}", "◆".bright_cyan(), question.header.bright_cyan().bold());
+            println!(
+                "{} {}",
+                "◆".bright_cyan(),
+                question.header.bright_cyan().bold()
+            );
         }
 
         if question.options.is_empty() {
             return Self::ask_question_free_text(question);
         }
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return Self::ask_question_manual_input(question);
+        }
 
-        let display_items: Vec<String> = question
-            .options
-            .iter()
-            .map(|opt| format!("{} - {}", opt.label, opt.description))
-            .collect();
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        for line in wrap(&question.question, width.saturating_sub(1)) {
+            println!("{}", line.bright_green());
+        }
 
-        if question.multi_select {
-            let selection = MultiSelect::new(&question.question, display_items)
-                .with_help_message("↑↓移动，Space选择，Enter确认")
-                .prompt();
-
-            match selection {
-                Ok(items) => {
-                    let selected_labels: Vec<String> = items
-                        .iter()
-                        .filter_map(|item| item.split(" - ").next().map(|s| s.to_string()))
-                        .collect();
-
-                    Ok(Answer {
-                        question_header: question.header.clone(),
-                        selected: serde_json::json!(selected_labels),
-                        has_answer: !selected_labels.is_empty(),
-                    })
-                }
-                Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
-                    Ok(Answer {
-                        question_header: question.header.clone(),
-                        selected: serde_json::json!(null),
-                        has_answer: false,
-                    })
-                }
-                Err(_) => Self::ask_question_manual_input(question),
+        let selected = match Self::select_options(question) {
+            Ok(Some(selected)) => selected,
+            Ok(None) => {
+                println!("{}", t!("ask.skipped").bright_black());
+                return Ok(build_answer(question, Vec::new()));
             }
-        } else {
-            let selection =
-                Select::new(&question.question, display_items)
-                .with_help_message("↑↓移动，Enter确认，可输入筛选")
-                .prompt();
-
-            match selection {
-                Ok(item) => Ok(Answer {
-                    question_header: question.header.clone(),
-                    selected: serde_json::json!(item.split(" - ").next().unwrap_or("").to_string()),
-                    has_answer: true,
-                }),
-                Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
-                    Ok(Answer {
-                        question_header: question.header.clone(),
-                        selected: serde_json::json!(null),
-                        has_answer: false,
-                    })
-                }
-                Err(_) => Self::ask_question_manual_input(question),
+            Err(_) => return Self::ask_question_manual_input(question),
+        };
+
+        let mut labels: Vec<String> = selected
+            .iter()
+            .filter_map(|&i| question.options.get(i).map(|o| o.label.clone()))
+            .collect();
+        if selected.contains(&question.options.len()) {
+            print!("{} ", t!("ask.other_prompt").bright_green());
+            let text = Self::read_line()?;
+            if !text.is_empty() {
+                labels.push(text);
             }
         }
+
+        if !labels.is_empty() {
+            println!("{} {}", "✔".bright_green(), labels.join(", "));
+        }
+        Ok(build_answer(question, labels))
     }
 
     /// 显示单个问题并收集答案 (自动选择模式)
@@ -257,6 +507,59 @@ impl AskUserQuestionTool {
         // 默认使用 CLI 模式
         Self::ask_question_cli(question)
     }
+
+    /// 依次显示所有问题并收集答案
+    ///
+    /// 直接读取终端，调用方需要通过 `interaction::interact` 排队。
+    pub fn ask_all(args: &AskUserQuestionArgs) -> AskUserQuestionOutput {
+        let total = args.questions.len();
+        let mut answers = HashMap::new();
+        let mut answered = 0usize;
+
+        println!();
+        println!("{}", t!("ask.title").bright_yellow().bold());
+        println!("{}", t!("ask.subtitle").bright_black().dimmed());
+
+        for question in &args.questions {
+            match Self::ask_question(question) {
+                Ok(answer) => {
+                    if answer.has_answer {
+                        answered += 1;
+                        answers.insert(question.header.clone(), answer.selected);
+                    } else {
+                        // 用户没有回答,插入 null
+                        answers.insert(question.header.clone(), serde_json::json!(null));
+                    }
+                }
+                Err(e) => {
+                    // 出错时插入 null
+                    eprintln!(
+                        "{}",
+                        t!("ask.answer_failed", header = question.header, error = e).red()
+                    );
+                    answers.insert(question.header.clone(), serde_json::json!(null));
+                }
+            }
+        }
+
+        println!();
+        println!("{}", "═".repeat(80).bright_black());
+
+        let success = answered > 0;
+        let message = if success {
+            format!("收集了 {}/{} 个问题的答案", answered, total)
+        } else {
+            "未收到任何有效答案".to_string()
+        };
+
+        AskUserQuestionOutput {
+            answers,
+            success,
+            message,
+            total_questions: total,
+            answered_questions: answered,
+        }
+    }
 }
 
 impl Tool for AskUserQuestionTool {
@@ -308,6 +611,10 @@ impl Tool for AskUserQuestionTool {
                                 "multi_select": {
                                     "type": "boolean",
                                     "description": "Whether to allow multiple selections (default: false)"
+                                },
+                                "allow_other": {
+                                    "type": "boolean",
+                                    "description": "Whether to add an 'Other…' option where the user can type their own answer; the typed text is returned in place of a label (default: false)"
                                 }
                             },
                             "required": ["question", "header", "options", "multi_select"]
@@ -320,58 +627,7 @@ impl Tool for AskUserQuestionTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let total = args.questions.len();
-        let mut answers = HashMap::new();
-        let mut answered = 0usize;
-
-        println!();
-        println!("{}", "╡ 需要用户输入 ╞".bright_yellow().bold());
-        println!(
-            "{}",
-            "请回答以下问题以继续执行:"
-                .bright_black()
-                .dimmed()
-        );
-
-        for question in &args.questions {
-            match Self::ask_question(question) {
-                Ok(answer) => {
-                    if answer.has_answer {
-                        answered += 1;
-                        answers.insert(question.header.clone(), answer.selected);
-                    } else {
-                        // 用户没有回答,插入 null
-                        answers.insert(question.header.clone(), serde_json::json!(null));
-                    }
-                }
-                Err(e) => {
-                    // 出错时插入 null
-                    eprintln!(
-                        "{}",
-                        format!("错误: 无法获取问题 '{}' 的答案: {}", question.header, e).red()
-                    );
-                    answers.insert(question.header.clone(), serde_json::json!(null));
-                }
-            }
-        }
-
-        println!();
-        println!("{}", "═".repeat(80).bright_black());
-
-        let success = answered > 0;
-        let message = if success {
-            format!("收集了 {}/{} 个问题的答案", answered, total)
-        } else {
-            "未收到任何有效答案".to_string()
-        };
-
-        Ok(AskUserQuestionOutput {
-            answers,
-            success,
-            message,
-            total_questions: total,
-            answered_questions: answered,
-        })
+        Ok(interaction::interact(move || Self::ask_all(&args)).await?)
     }
 }
 
@@ -448,6 +704,7 @@ mod tests {
                 },
             ],
             multi_select: false,
+            allow_other: false,
         };
 
         let json = serde_json::to_string(&question).unwrap();
@@ -516,4 +773,144 @@ mod tests {
         assert!(answer.has_answer);
         assert_eq!(answer.selected, serde_json::json!("选项1"));
     }
+
+    fn question(multi_select: bool, allow_other: bool) -> Question {
+        Question {
+            question: "Which cache?".to_string(),
+            header: "Cache".to_string(),
+            options: vec![
+                QuestionOption {
+                    label: "Redis".to_string(),
+                    description: "Shared across instances".to_string(),
+                },
+                QuestionOption {
+                    label: "Memory".to_string(),
+                    description: "Per process".to_string(),
+                },
+                QuestionOption {
+                    label: "None".to_string(),
+                    description: String::new(),
+                },
+            ],
+            multi_select,
+            allow_other,
+        }
+    }
+
+    #[test]
+    fn test_allow_other_defaults_to_false() {
+        let json = r#"{"question": "q", "header": "h", "options": [], "multi_select": true}"#;
+        let question: Question = serde_json::from_str(json).unwrap();
+        assert!(!question.allow_other);
+    }
+
+    #[test]
+    fn test_parse_manual_input() {
+        let single = question(false, false);
+        assert_eq!(parse_manual_input(&single, " 2 "), vec!["Memory"]);
+        assert!(parse_manual_input(&single, "4").is_empty());
+        assert!(parse_manual_input(&single, "sqlite").is_empty());
+        assert!(parse_manual_input(&single, "").is_empty());
+
+        let multi = question(true, false);
+        assert_eq!(parse_manual_input(&multi, "3, 1,1"), vec!["None", "Redis"]);
+        assert!(parse_manual_input(&multi, "0,9").is_empty());
+
+        // 允许"其他…"时，不是序号的输入作为自己填写的答案
+        let single_other = question(false, true);
+        assert_eq!(parse_manual_input(&single_other, "sqlite"), vec!["sqlite"]);
+        let multi_other = question(true, true);
+        assert_eq!(
+            parse_manual_input(&multi_other, "1, sqlite, disk"),
+            vec!["Redis", "sqlite, disk"]
+        );
+    }
+
+    #[test]
+    fn test_multi_select_keys() {
+        let mut state = ChoiceState::new(&question(true, true));
+        assert_eq!(state.apply(ChoiceKey::Toggle), None);
+        assert_eq!(state.apply(ChoiceKey::Up), None);
+        assert_eq!(state.cursor, 3);
+        assert_eq!(state.apply(ChoiceKey::Toggle), None);
+        assert_eq!(state.apply(ChoiceKey::Confirm), Some(Some(vec![0, 3])));
+
+        // 全选不包括"其他…"
+        assert_eq!(state.apply(ChoiceKey::None), None);
+        assert_eq!(state.apply(ChoiceKey::All), None);
+        assert_eq!(state.apply(ChoiceKey::Confirm), Some(Some(vec![0, 1, 2])));
+        assert_eq!(state.apply(ChoiceKey::None), None);
+        assert_eq!(state.apply(ChoiceKey::Confirm), Some(Some(vec![])));
+        assert_eq!(state.apply(ChoiceKey::Cancel), Some(None));
+    }
+
+    #[test]
+    fn test_single_select_keys() {
+        let mut state = ChoiceState::new(&question(false, false));
+        assert_eq!(state.apply(ChoiceKey::All), None);
+        assert_eq!(state.apply(ChoiceKey::Down), None);
+        assert_eq!(state.apply(ChoiceKey::Confirm), Some(Some(vec![1])));
+        assert_eq!(state.apply(ChoiceKey::Down), None);
+        assert_eq!(state.apply(ChoiceKey::Down), None);
+        assert_eq!(state.cursor, 0);
+        assert_eq!(state.apply(ChoiceKey::Toggle), Some(Some(vec![0])));
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(
+            ChoiceKey::from_event(&key(KeyCode::Char('a'))),
+            Some(ChoiceKey::All)
+        );
+        assert_eq!(ChoiceKey::from_event(&key(KeyCode::Char('x'))), None);
+        assert_eq!(
+            ChoiceKey::from_event(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(ChoiceKey::Cancel)
+        );
+    }
+
+    #[test]
+    fn test_wrap_by_display_width() {
+        assert_eq!(wrap("alpha beta gamma", 11), vec!["alpha beta", "gamma"]);
+        assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        // 中文每个字占两列
+        assert_eq!(wrap("缓存所有请求", 5), vec!["缓存", "所有", "请求"]);
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_render_wraps_long_descriptions() {
+        colored::control::set_override(false);
+        crate::i18n::init(crate::i18n::Locale::En);
+        let mut question = question(true, true);
+        question.options[0].description = "Shared across every instance of the service".to_string();
+        let state = ChoiceState::new(&question);
+
+        let lines = state.render(&question, 24, true);
+        assert!(lines.iter().all(|line| display_width(line) <= 24));
+        assert_eq!(lines[0], "❯ ◯ Redis");
+        assert_eq!(lines[1], "      Shared across");
+        assert!(lines.contains(&"  ◯ Other…".to_string()));
+
+        let compact = state.render(&question, 24, false);
+        assert_eq!(
+            &compact[..4],
+            &["❯ ◯ Redis", "  ◯ Memory", "  ◯ None", "  ◯ Other…"]
+        );
+    }
+
+    #[test]
+    fn test_answer_payload_shape() {
+        let single = build_answer(&question(false, true), vec!["sqlite".to_string()]);
+        assert_eq!(single.selected, serde_json::json!("sqlite"));
+        assert!(single.has_answer);
+
+        let multi = build_answer(
+            &question(true, false),
+            vec!["Redis".to_string(), "None".to_string()],
+        );
+        assert_eq!(multi.selected, serde_json::json!(["Redis", "None"]));
+
+        let skipped = build_answer(&question(true, false), Vec::new());
+        assert_eq!(skipped.selected, serde_json::json!(null));
+        assert!(!skipped.has_answer);
+    }
 }
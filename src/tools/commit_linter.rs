@@ -208,7 +208,7 @@ impl CommitLinter {
         let lines: Vec<&str> = message.lines().collect();
 
         if lines.is_empty() {
-            return ValidationResult::invalid(vec!["提交消息为空".to_string()]);
+            return ValidationResult::invalid(vec![t!("git.commit.empty").to_string()]);
         }
 
         let first_line = lines[0];
@@ -216,9 +216,9 @@ impl CommitLinter {
         // 检查第一行长度(建议不超过 50 个字符)
         if first_line.len() > 50 {
             let mut result = ValidationResult::invalid(vec![]);
-            result.warnings.push(format!(
-                "第一行超过 50 个字符 (当前 {} 个)",
-                first_line.len()
+            result.warnings.push(t!(
+                "git.commit.first_line_too_long",
+                length = first_line.len()
             ));
             result.valid = true; // 仍然是有效的,只是有警告
             return self.validate_with_result(first_line, result);
@@ -227,12 +227,13 @@ impl CommitLinter {
         // 使用正则表达式验证格式
         if !self.pattern.is_match(first_line) {
             return ValidationResult::invalid(vec![
-                "提交消息格式无效".to_string(),
-                "期望格式: <type>[optional scope]: <description>".to_string(),
-                format!(
-                    "示例: feat: add new feature, fix(api): resolve bug",
+                t!("git.commit.invalid_format").to_string(),
+                t!("git.commit.expected_format").to_string(),
+                t!("git.commit.example").to_string(),
+                t!(
+                    "git.commit.supported_types",
+                    types = Self::supported_types()
                 ),
-                format!("支持的类型: {}", Self::supported_types()),
             ]);
         }
 
@@ -251,7 +252,9 @@ impl CommitLinter {
         if let Some(type_match) = caps.name("type") {
             let type_str = type_match.as_str();
             if CommitType::from_str(type_str).is_none() {
-                result.errors.push(format!("未知的提交类型: {}", type_str));
+                result
+                    .errors
+                    .push(t!("git.commit.unknown_type", kind = type_str));
                 result.valid = false;
                 return result;
             }
@@ -271,17 +274,18 @@ impl CommitLinter {
         if let Some(desc_match) = caps.name("desc") {
             let desc = desc_match.as_str();
             if desc.len() < 3 {
-                result = result.with_warning("描述太短,建议至少 3 个字符".to_string());
+                result = result.with_warning(t!("git.commit.description_too_short").to_string());
             }
             // 检查描述是否以大写字母开头(建议)
             if let Some(first_char) = desc.chars().next() {
                 if first_char.is_uppercase() {
-                    result = result.with_warning("描述建议以小写字母开头".to_string());
+                    result =
+                        result.with_warning(t!("git.commit.description_lowercase").to_string());
                 }
             }
             // 检查描述是否以句号结尾(不推荐)
             if desc.ends_with('.') {
-                result = result.with_warning("描述不应以句号结尾".to_string());
+                result = result.with_warning(t!("git.commit.description_period").to_string());
             }
         }
 
@@ -311,29 +315,29 @@ impl CommitLinter {
             println!(
                 "{} {}",
                 "✓".bright_green(),
-                "提交消息格式正确".bright_green()
+                t!("git.commit.valid").bright_green()
             );
 
             if let Some(commit_type) = &result.commit_type {
+                let description = CommitType::from_str(commit_type)
+                    .map(|t| t.description())
+                    .unwrap_or(t!("git.commit.unknown"));
                 println!(
-                    "  类型: {} - {}",
-                    commit_type.bright_cyan(),
-                    CommitType::from_str(commit_type)
-                        .map(|t| t.description())
-                        .unwrap_or("未知")
-                        .bright_black()
+                    "{}",
+                    t!(
+                        "git.commit.type",
+                        kind = commit_type.bright_cyan(),
+                        description = description.bright_black()
+                    )
                 );
             }
 
             if let Some(scope) = &result.scope {
-                println!("  作用域: {}", scope.bright_yellow());
+                println!("{}", t!("git.commit.scope", scope = scope.bright_yellow()));
             }
 
             if result.breaking {
-                println!(
-                    "  {}",
-                    "⚠️ 破坏性变更".bright_yellow().bold()
-                );
+                println!("  {}", t!("git.commit.breaking").bright_yellow().bold());
             }
 
             for warning in &result.warnings {
@@ -347,7 +351,7 @@ impl CommitLinter {
             println!(
                 "{} {}",
                 "✗".bright_red(),
-                "提交消息格式无效".bright_red()
+                t!("git.commit.invalid_format").bright_red()
             );
 
             for error in &result.errors {
@@ -1,3 +1,4 @@
+use super::settings::ToolSettings;
 use super::FileToolError;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
@@ -18,8 +19,18 @@ pub struct CreateDirectoryOutput {
     pub created_parents: bool,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct CreateDirectoryTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct CreateDirectoryTool {
+    settings: ToolSettings,
+}
+
+impl CreateDirectoryTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
 
 impl Tool for CreateDirectoryTool {
     const NAME: &'static str = "create_directory";
@@ -46,7 +57,7 @@ impl Tool for CreateDirectoryTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let dir_path = &args.dir_path;
+        let dir_path = &self.settings.resolve_path(&args.dir_path);
         let path = Path::new(dir_path);
 
         // Check if directory already exists
@@ -100,9 +111,9 @@ pub struct WrappedCreateDirectoryTool {
 }
 
 impl WrappedCreateDirectoryTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: CreateDirectoryTool,
+            inner: CreateDirectoryTool::new(settings),
         }
     }
 }
@@ -119,24 +130,24 @@ impl Tool for WrappedCreateDirectoryTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "CreateDir", args.dir_path);
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "CreateDir", args.dir_path);
 
         let result = self.inner.call(args).await;
 
         match &result {
             Ok(output) => {
                 if output.created_parents {
-                    println!("  └─ {} (with parents)", "Directory created".dimmed());
+                    tool_println!("  └─ {} (with parents)", "Directory created".dimmed());
                 } else {
-                    println!("  └─ {}", "Directory created".dimmed());
+                    tool_println!("  └─ {}", "Directory created".dimmed());
                 }
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
@@ -1,3 +1,4 @@
+use super::settings::ToolSettings;
 use super::FileToolError;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
@@ -17,8 +18,18 @@ pub struct DeleteFileOutput {
     pub message: String,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct DeleteFileTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct DeleteFileTool {
+    settings: ToolSettings,
+}
+
+impl DeleteFileTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
 
 impl Tool for DeleteFileTool {
     const NAME: &'static str = "delete_file";
@@ -45,7 +56,7 @@ impl Tool for DeleteFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let file_path = &args.file_path;
+        let file_path = &self.settings.resolve_path(&args.file_path);
         let path = Path::new(file_path);
 
         // Check if file exists
@@ -81,9 +92,9 @@ pub struct WrappedDeleteFileTool {
 }
 
 impl WrappedDeleteFileTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: DeleteFileTool,
+            inner: DeleteFileTool::new(settings),
         }
     }
 }
@@ -100,20 +111,20 @@ impl Tool for WrappedDeleteFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Delete", args.file_path);
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "Delete", args.file_path);
 
         let result = self.inner.call(args).await;
 
         match &result {
             Ok(_output) => {
-                println!("  └─ {}", "File deleted".dimmed());
+                tool_println!("  └─ {}", "File deleted".dimmed());
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
@@ -0,0 +1,1248 @@
+//! 依赖检查工具
+//!
+//! 解析项目的清单和锁文件（Cargo.toml / Cargo.lock、package.json / package-lock.json、
+//! pyproject.toml / requirements.txt / poetry.lock / uv.lock），列出声明的版本要求、
+//! 实际锁定的版本、启用的 features，以及直接/间接依赖。Cargo 和 npm 的 workspace
+//! 会汇总所有成员。`check_latest` 时查询 crates.io / npm / PyPI 的最新版本，
+//! 查询结果在会话内缓存，网络不可用时只在 `note` 中说明而不报错；
+//! 网络访问策略为 `none` 时不查询（见 [`super::network`]）。
+
+use super::network::{self, ToolHttpClient};
+use super::FileToolError;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 单次注册表请求超时
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 整体查询的时间上限
+const REGISTRY_TOTAL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 并发请求数
+const REGISTRY_CONCURRENCY: usize = 8;
+
+/// 摘要中列出的可升级依赖数
+const SUMMARY_OUTDATED: usize = 10;
+
+/// 包管理生态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Python,
+}
+
+impl Ecosystem {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+            Self::Python => "python",
+        }
+    }
+}
+
+/// 依赖类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepKind {
+    Normal,
+    Build,
+    Dev,
+    Peer,
+    Optional,
+}
+
+/// 一个依赖
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub kind: DepKind,
+    /// 是否由清单直接声明
+    pub direct: bool,
+    /// 声明的版本要求（`path:`/`git:` 表示本地或 git 依赖；成员间不同时以 ", " 连接）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub declared: Option<String>,
+    /// 锁文件中的版本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+    /// 启用的 features / extras
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// 声明该依赖的 workspace 成员
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<String>,
+    /// 注册表上的最新版本（`check_latest` 时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    /// 最新版本是否比锁定版本新
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outdated: Option<bool>,
+}
+
+/// 一个生态下的依赖
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectDeps {
+    pub ecosystem: Ecosystem,
+    pub manifest: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockfile: Option<String>,
+    /// workspace 成员（相对路径）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+    pub direct_count: usize,
+    pub transitive_count: usize,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DepsArgs {
+    /// 项目目录（默认当前目录）
+    #[serde(default)]
+    pub path: Option<String>,
+    /// 只返回该名称的依赖（包括间接依赖）
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 查询注册表上的最新版本
+    #[serde(default)]
+    pub check_latest: bool,
+    /// 返回全部间接依赖
+    #[serde(default)]
+    pub include_transitive: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DepsOutput {
+    pub projects: Vec<ProjectDeps>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// 清单中声明的一条依赖
+#[derive(Debug, Clone)]
+struct Declared {
+    name: String,
+    kind: DepKind,
+    req: Option<String>,
+    features: Vec<String>,
+    member: String,
+}
+
+/// 锁文件中的一个包
+#[derive(Debug, Clone)]
+struct Locked {
+    name: String,
+    version: String,
+    /// 是否是项目自身（workspace 成员、link 包）
+    local: bool,
+}
+
+fn parse_error(path: &Path, e: impl std::fmt::Display) -> FileToolError {
+    FileToolError::InvalidInput(format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value, FileToolError> {
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|e| parse_error(path, e))
+}
+
+fn read_json(path: &Path) -> Result<Value, FileToolError> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| parse_error(path, e))
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let display = relative.to_string_lossy().replace('\\', "/");
+    if display.is_empty() {
+        ".".to_string()
+    } else {
+        display
+    }
+}
+
+/// 展开 workspace 成员模式（支持 `crates/*` 这类 glob）
+fn expand_members(root: &Path, patterns: &[String], manifest: &str) -> Vec<PathBuf> {
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let full = root.join(pattern).to_string_lossy().replace('\\', "/");
+        let Ok(paths) = glob::glob(&full) else {
+            continue;
+        };
+        for path in paths.flatten() {
+            if path.join(manifest).is_file() && !members.contains(&path) {
+                members.push(path);
+            }
+        }
+    }
+    members.sort();
+    members
+}
+
+/// 识别并解析目录下的所有项目
+pub fn inspect(root: &Path) -> Result<Vec<ProjectDeps>, FileToolError> {
+    let mut projects = Vec::new();
+    if let Some(project) = inspect_cargo(root)? {
+        projects.push(project);
+    }
+    if let Some(project) = inspect_npm(root)? {
+        projects.push(project);
+    }
+    if let Some(project) = inspect_python(root)? {
+        projects.push(project);
+    }
+    Ok(projects)
+}
+
+// ---------------------------------------------------------------------------
+// Cargo
+// ---------------------------------------------------------------------------
+
+fn inspect_cargo(root: &Path) -> Result<Option<ProjectDeps>, FileToolError> {
+    let manifest_path = root.join("Cargo.toml");
+    if !manifest_path.is_file() {
+        return Ok(None);
+    }
+    let manifest = read_toml(&manifest_path)?;
+
+    let workspace = manifest.get("workspace");
+    let workspace_deps = workspace
+        .and_then(|w| w.get("dependencies"))
+        .and_then(toml::Value::as_table);
+    let member_patterns: Vec<String> = workspace
+        .and_then(|w| w.get("members"))
+        .and_then(toml::Value::as_array)
+        .map(|members| members.iter().filter_map(|m| m.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    let mut declared = Vec::new();
+    let mut members = Vec::new();
+    let mut member_names = BTreeSet::new();
+    if let Some(name) = cargo_package_name(&manifest) {
+        member_names.insert(name);
+        cargo_declared(&manifest, ".", workspace_deps, &mut declared);
+    }
+    for member in expand_members(root, &member_patterns, "Cargo.toml") {
+        if member == root {
+            continue;
+        }
+        let member_path = member.join("Cargo.toml");
+        let member_manifest = read_toml(&member_path)?;
+        let label = relative(root, &member);
+        if let Some(name) = cargo_package_name(&member_manifest) {
+            member_names.insert(name);
+        }
+        cargo_declared(&member_manifest, &label, workspace_deps, &mut declared);
+        members.push(label);
+    }
+
+    let lock_path = root.join("Cargo.lock");
+    let locked = if lock_path.is_file() {
+        Some(parse_cargo_lock(&read_toml(&lock_path)?, &member_names))
+    } else {
+        None
+    };
+
+    Ok(Some(build_project(
+        Ecosystem::Cargo,
+        "Cargo.toml",
+        locked.as_ref().map(|_| "Cargo.lock"),
+        members,
+        declared,
+        locked.unwrap_or_default(),
+    )))
+}
+
+fn cargo_package_name(manifest: &toml::Value) -> Option<String> {
+    manifest
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(toml::Value::as_str)
+        .map(String::from)
+}
+
+/// 收集一个 Cargo 清单中的依赖（包括 `[target.'cfg(..)'.dependencies]`）
+fn cargo_declared(
+    manifest: &toml::Value,
+    member: &str,
+    workspace_deps: Option<&toml::value::Table>,
+    out: &mut Vec<Declared>,
+) {
+    let sections = [
+        ("dependencies", DepKind::Normal),
+        ("build-dependencies", DepKind::Build),
+        ("dev-dependencies", DepKind::Dev),
+    ];
+    let mut tables = Vec::new();
+    for (section, kind) in sections {
+        if let Some(table) = manifest.get(section).and_then(toml::Value::as_table) {
+            tables.push((table, kind));
+        }
+        let targets = manifest.get("target").and_then(toml::Value::as_table);
+        for target in targets.into_iter().flat_map(|t| t.values()) {
+            if let Some(table) = target.get(section).and_then(toml::Value::as_table) {
+                tables.push((table, kind));
+            }
+        }
+    }
+
+    for (table, kind) in tables {
+        for (key, spec) in table {
+            let mut dep = cargo_spec(key, spec, kind, member);
+            let inherits = spec.get("workspace").and_then(toml::Value::as_bool) == Some(true);
+            if let Some(base) = workspace_deps.and_then(|deps| deps.get(key)).filter(|_| inherits) {
+                let base = cargo_spec(key, base, kind, member);
+                dep.name = base.name;
+                dep.req = base.req;
+                dep.features = base.features.into_iter().chain(dep.features).collect();
+            }
+            out.push(dep);
+        }
+    }
+}
+
+/// 解析一条依赖声明：`"1.0"` 或 `{ version, path, git, package, features, optional }`
+fn cargo_spec(key: &str, spec: &toml::Value, kind: DepKind, member: &str) -> Declared {
+    let field = |name: &str| spec.get(name).and_then(toml::Value::as_str);
+    let req = match spec {
+        toml::Value::String(version) => Some(version.clone()),
+        _ => field("version")
+            .map(String::from)
+            .or_else(|| field("path").map(|path| format!("path:{}", path)))
+            .or_else(|| field("git").map(|git| format!("git:{}", git))),
+    };
+    let features = spec
+        .get("features")
+        .and_then(toml::Value::as_array)
+        .map(|f| f.iter().filter_map(|f| f.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let optional = spec.get("optional").and_then(toml::Value::as_bool) == Some(true);
+
+    Declared {
+        name: field("package").unwrap_or(key).to_string(),
+        kind: if optional && kind == DepKind::Normal {
+            DepKind::Optional
+        } else {
+            kind
+        },
+        req,
+        features,
+        member: member.to_string(),
+    }
+}
+
+fn parse_cargo_lock(lock: &toml::Value, member_names: &BTreeSet<String>) -> Vec<Locked> {
+    lock.get("package")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            let version = package.get("version")?.as_str()?;
+            Some(Locked {
+                name: name.to_string(),
+                version: version.to_string(),
+                // 没有 source 的是本地路径包
+                local: package.get("source").is_none() && member_names.contains(name),
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// npm
+// ---------------------------------------------------------------------------
+
+fn inspect_npm(root: &Path) -> Result<Option<ProjectDeps>, FileToolError> {
+    let manifest_path = root.join("package.json");
+    if !manifest_path.is_file() {
+        return Ok(None);
+    }
+    let manifest = read_json(&manifest_path)?;
+
+    let mut declared = Vec::new();
+    npm_declared(&manifest, ".", &mut declared);
+
+    // "workspaces": [...] 或 { "packages": [...] }
+    let patterns: Vec<String> = manifest["workspaces"]
+        .as_array()
+        .or_else(|| manifest["workspaces"]["packages"].as_array())
+        .map(|w| w.iter().filter_map(|p| p.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let mut members = Vec::new();
+    for member in expand_members(root, &patterns, "package.json") {
+        let member_manifest = read_json(&member.join("package.json"))?;
+        let label = relative(root, &member);
+        npm_declared(&member_manifest, &label, &mut declared);
+        members.push(label);
+    }
+
+    let lock_path = root.join("package-lock.json");
+    let locked = if lock_path.is_file() {
+        Some(parse_npm_lock(&read_json(&lock_path)?))
+    } else {
+        None
+    };
+
+    Ok(Some(build_project(
+        Ecosystem::Npm,
+        "package.json",
+        locked.as_ref().map(|_| "package-lock.json"),
+        members,
+        declared,
+        locked.unwrap_or_default(),
+    )))
+}
+
+fn npm_declared(manifest: &Value, member: &str, out: &mut Vec<Declared>) {
+    let sections = [
+        ("dependencies", DepKind::Normal),
+        ("devDependencies", DepKind::Dev),
+        ("peerDependencies", DepKind::Peer),
+        ("optionalDependencies", DepKind::Optional),
+    ];
+    for (section, kind) in sections {
+        for (name, req) in manifest[section].as_object().into_iter().flatten() {
+            out.push(Declared {
+                name: name.clone(),
+                kind,
+                req: req.as_str().map(String::from),
+                features: Vec::new(),
+                member: member.to_string(),
+            });
+        }
+    }
+}
+
+/// 解析 package-lock.json（v2/v3 的 `packages`，v1 的 `dependencies`）
+fn parse_npm_lock(lock: &Value) -> Vec<Locked> {
+    let mut locked = Vec::new();
+    if let Some(packages) = lock["packages"].as_object() {
+        for (key, package) in packages {
+            let Some(index) = key.rfind("node_modules/") else {
+                continue;
+            };
+            let name = &key[index + "node_modules/".len()..];
+            let link = package["link"].as_bool() == Some(true);
+            let version = package["version"].as_str().unwrap_or_default();
+            if version.is_empty() && !link {
+                continue;
+            }
+            locked.push(Locked {
+                name: name.to_string(),
+                version: version.to_string(),
+                local: link,
+            });
+        }
+    } else if let Some(dependencies) = lock["dependencies"].as_object() {
+        fn walk(deps: &serde_json::Map<String, Value>, out: &mut Vec<Locked>) {
+            for (name, package) in deps {
+                if let Some(version) = package["version"].as_str() {
+                    out.push(Locked {
+                        name: name.clone(),
+                        version: version.to_string(),
+                        local: version.starts_with("file:"),
+                    });
+                }
+                if let Some(nested) = package["dependencies"].as_object() {
+                    walk(nested, out);
+                }
+            }
+        }
+        walk(dependencies, &mut locked);
+    }
+    locked
+}
+
+// ---------------------------------------------------------------------------
+// Python
+// ---------------------------------------------------------------------------
+
+fn inspect_python(root: &Path) -> Result<Option<ProjectDeps>, FileToolError> {
+    let pyproject_path = root.join("pyproject.toml");
+    let requirements_path = root.join("requirements.txt");
+
+    let mut declared = Vec::new();
+    let mut project_name = None;
+    let manifest = if pyproject_path.is_file() {
+        let pyproject = read_toml(&pyproject_path)?;
+        project_name = pyproject
+            .get("project")
+            .and_then(|p| p.get("name"))
+            .or_else(|| pyproject.get("tool")?.get("poetry")?.get("name"))
+            .and_then(toml::Value::as_str)
+            .map(normalize_python_name);
+        pyproject_declared(&pyproject, &mut declared);
+        "pyproject.toml"
+    } else if requirements_path.is_file() {
+        requirements_declared(&fs::read_to_string(&requirements_path)?, &mut declared);
+        "requirements.txt"
+    } else {
+        return Ok(None);
+    };
+
+    let mut lockfile = None;
+    let mut locked = Vec::new();
+    for name in ["poetry.lock", "uv.lock"] {
+        let path = root.join(name);
+        if path.is_file() {
+            locked = parse_python_lock(&read_toml(&path)?, project_name.as_deref());
+            lockfile = Some(name);
+            break;
+        }
+    }
+    // requirements.txt 中 `==` 固定的版本视为已锁定
+    if lockfile.is_none() {
+        for dep in &declared {
+            if let Some(version) = dep.req.as_deref().and_then(|r| r.strip_prefix("==")) {
+                locked.push(Locked {
+                    name: dep.name.clone(),
+                    version: version.trim().to_string(),
+                    local: false,
+                });
+            }
+        }
+    }
+
+    Ok(Some(build_project(
+        Ecosystem::Python,
+        manifest,
+        lockfile,
+        Vec::new(),
+        declared,
+        locked,
+    )))
+}
+
+/// PEP 503 名称规范化：小写，`-_.` 统一为 `-`
+fn normalize_python_name(name: &str) -> String {
+    static SEPARATORS: Lazy<Regex> = Lazy::new(|| Regex::new(r"[-_.]+").unwrap());
+    SEPARATORS.replace_all(&name.to_lowercase(), "-").to_string()
+}
+
+/// 解析 PEP 508 依赖字符串，如 `fastapi[standard]>=0.110; python_version < '3.11'`
+fn parse_pep508(spec: &str, kind: DepKind) -> Option<Declared> {
+    static PEP508: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\s*([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[([^\]]*)\])?\s*([^;]*)").unwrap()
+    });
+    let caps = PEP508.captures(spec)?;
+    let req = caps[3].trim().trim_start_matches('(').trim_end_matches(')').trim();
+    Some(Declared {
+        name: normalize_python_name(&caps[1]),
+        kind,
+        req: (!req.is_empty()).then(|| req.replace(' ', "")),
+        features: caps
+            .get(2)
+            .map(|extras| {
+                extras
+                    .as_str()
+                    .split(',')
+                    .map(|e| e.trim().to_string())
+                    .filter(|e| !e.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        member: ".".to_string(),
+    })
+}
+
+fn pyproject_declared(pyproject: &toml::Value, out: &mut Vec<Declared>) {
+    let project = pyproject.get("project");
+    let strings = |value: Option<&toml::Value>| -> Vec<String> {
+        value
+            .and_then(toml::Value::as_array)
+            .map(|a| a.iter().filter_map(|s| s.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+
+    for spec in strings(project.and_then(|p| p.get("dependencies"))) {
+        out.extend(parse_pep508(&spec, DepKind::Normal));
+    }
+    let optional = project
+        .and_then(|p| p.get("optional-dependencies"))
+        .and_then(toml::Value::as_table);
+    for group in optional.into_iter().flat_map(|t| t.values()) {
+        for spec in strings(Some(group)) {
+            out.extend(parse_pep508(&spec, DepKind::Optional));
+        }
+    }
+    let groups = pyproject.get("dependency-groups").and_then(toml::Value::as_table);
+    for group in groups.into_iter().flat_map(|t| t.values()) {
+        for spec in strings(Some(group)) {
+            out.extend(parse_pep508(&spec, DepKind::Dev));
+        }
+    }
+
+    // Poetry
+    let Some(poetry) = pyproject.get("tool").and_then(|t| t.get("poetry")) else {
+        return;
+    };
+    let mut tables = vec![(poetry.get("dependencies"), DepKind::Normal)];
+    tables.push((poetry.get("dev-dependencies"), DepKind::Dev));
+    let poetry_groups = poetry.get("group").and_then(toml::Value::as_table);
+    for group in poetry_groups.into_iter().flat_map(|t| t.values()) {
+        tables.push((group.get("dependencies"), DepKind::Dev));
+    }
+    for (table, kind) in tables {
+        for (name, spec) in table.and_then(toml::Value::as_table).into_iter().flatten() {
+            if name == "python" {
+                continue;
+            }
+            let field = |key: &str| spec.get(key).and_then(toml::Value::as_str);
+            let req = match spec {
+                toml::Value::String(version) => Some(version.clone()),
+                _ => field("version")
+                    .map(String::from)
+                    .or_else(|| field("path").map(|p| format!("path:{}", p)))
+                    .or_else(|| field("git").map(|g| format!("git:{}", g))),
+            };
+            let optional = spec.get("optional").and_then(toml::Value::as_bool) == Some(true);
+            out.push(Declared {
+                name: normalize_python_name(name),
+                kind: if optional { DepKind::Optional } else { kind },
+                req: req.filter(|r| r != "*"),
+                features: strings(spec.get("extras")),
+                member: ".".to_string(),
+            });
+        }
+    }
+}
+
+fn requirements_declared(content: &str, out: &mut Vec<Declared>) {
+    for line in content.lines() {
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        // 跳过注释、`-r`/`-e` 和索引等选项
+        if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+            continue;
+        }
+        out.extend(parse_pep508(line, DepKind::Normal));
+    }
+}
+
+/// poetry.lock 和 uv.lock 都是 `[[package]] name/version`
+fn parse_python_lock(lock: &toml::Value, project_name: Option<&str>) -> Vec<Locked> {
+    lock.get("package")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|package| {
+            let name = normalize_python_name(package.get("name")?.as_str()?);
+            let version = package.get("version")?.as_str()?.to_string();
+            Some(Locked {
+                local: Some(name.as_str()) == project_name,
+                name,
+                version,
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// 汇总
+// ---------------------------------------------------------------------------
+
+/// 合并各成员的声明，按锁文件补充版本，其余锁定包记为间接依赖
+fn build_project(
+    ecosystem: Ecosystem,
+    manifest: &str,
+    lockfile: Option<&str>,
+    members: Vec<String>,
+    declared: Vec<Declared>,
+    locked: Vec<Locked>,
+) -> ProjectDeps {
+    let mut direct: BTreeMap<String, Dependency> = BTreeMap::new();
+    let mut requirements: HashMap<String, Vec<String>> = HashMap::new();
+    for dep in declared {
+        let entry = direct.entry(dep.name.clone()).or_insert_with(|| Dependency {
+            name: dep.name.clone(),
+            kind: dep.kind,
+            direct: true,
+            declared: None,
+            resolved: None,
+            features: Vec::new(),
+            used_by: Vec::new(),
+            latest: None,
+            outdated: None,
+        });
+        // 同时作为普通依赖和 dev 依赖时按普通依赖计
+        entry.kind = entry.kind.min(dep.kind);
+        for feature in dep.features {
+            if !entry.features.contains(&feature) {
+                entry.features.push(feature);
+            }
+        }
+        if !members.is_empty() && !entry.used_by.contains(&dep.member) {
+            entry.used_by.push(dep.member.clone());
+        }
+        if let Some(req) = dep.req {
+            let reqs = requirements.entry(dep.name).or_default();
+            if !reqs.contains(&req) {
+                reqs.push(req);
+            }
+        }
+    }
+
+    for (name, dep) in direct.iter_mut() {
+        let reqs = requirements.remove(name).unwrap_or_default();
+        dep.resolved = resolve_version(&locked, name, &reqs);
+        dep.declared = (!reqs.is_empty()).then(|| reqs.join(", "));
+        dep.features.sort();
+    }
+
+    let mut transitive: Vec<Dependency> = locked
+        .iter()
+        .filter(|package| !package.local && !direct.contains_key(&package.name))
+        .map(|package| Dependency {
+            name: package.name.clone(),
+            kind: DepKind::Normal,
+            direct: false,
+            declared: None,
+            resolved: Some(package.version.clone()),
+            features: Vec::new(),
+            used_by: Vec::new(),
+            latest: None,
+            outdated: None,
+        })
+        .collect();
+    transitive.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.resolved.cmp(&b.resolved)));
+    transitive.dedup_by(|a, b| a.name == b.name && a.resolved == b.resolved);
+
+    let mut dependencies: Vec<Dependency> = direct.into_values().collect();
+    dependencies.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+    let direct_count = dependencies.len();
+    let transitive_count = transitive.len();
+    dependencies.extend(transitive);
+
+    ProjectDeps {
+        ecosystem,
+        manifest: manifest.to_string(),
+        lockfile: lockfile.map(String::from),
+        members,
+        direct_count,
+        transitive_count,
+        dependencies,
+    }
+}
+
+/// 在锁定的同名包中为每个版本要求选出满足的最高版本
+///
+/// 同名包被锁定为多个版本（如 thiserror 1.x 和 2.x）时，按声明顺序以 ", " 连接。
+fn resolve_version(locked: &[Locked], name: &str, reqs: &[String]) -> Option<String> {
+    let candidates: Vec<&Locked> = locked.iter().filter(|p| p.name == name).collect();
+    if candidates.len() <= 1 {
+        return candidates.first().map(|p| p.version.clone()).filter(|v| !v.is_empty());
+    }
+    let mut versions: Vec<semver::Version> = candidates
+        .iter()
+        .filter_map(|p| semver::Version::parse(&p.version).ok())
+        .collect();
+    versions.sort();
+
+    let mut resolved: Vec<String> = Vec::new();
+    for req in reqs.iter().filter_map(|req| semver::VersionReq::parse(req).ok()) {
+        if let Some(version) = versions.iter().rev().find(|v| req.matches(v)) {
+            let version = version.to_string();
+            if !resolved.contains(&version) {
+                resolved.push(version);
+            }
+        }
+    }
+    if resolved.is_empty() {
+        return versions.last().map(|v| v.to_string());
+    }
+    Some(resolved.join(", "))
+}
+
+/// 按名称筛选依赖，未指定名称且不要求间接依赖时只保留直接依赖
+fn filter_dependencies(project: &mut ProjectDeps, name: Option<&str>, include_transitive: bool) {
+    match name {
+        Some(name) => {
+            let wanted = match project.ecosystem {
+                Ecosystem::Python => normalize_python_name(name),
+                _ => name.to_string(),
+            };
+            project.dependencies.retain(|dep| dep.name == wanted);
+        }
+        None if !include_transitive => project.dependencies.retain(|dep| dep.direct),
+        None => {}
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 注册表查询
+// ---------------------------------------------------------------------------
+
+/// (生态, 包名) -> 最新版本，None 表示注册表上不存在
+type LatestCache = HashMap<(Ecosystem, String), Option<String>>;
+
+/// 会话内的最新版本缓存
+static LATEST_CACHE: Lazy<Mutex<LatestCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static HTTP_CLIENT: Lazy<Option<ToolHttpClient>> = Lazy::new(|| {
+    ToolHttpClient::build(|builder| {
+        builder
+            .timeout(REGISTRY_TIMEOUT)
+            .connect_timeout(REGISTRY_TIMEOUT)
+            // crates.io 要求请求带 User-Agent
+            .user_agent(concat!("oxide/", env!("CARGO_PKG_VERSION"), " (dependency inspector)"))
+    })
+    .ok()
+});
+
+fn registry_url(ecosystem: Ecosystem, name: &str) -> String {
+    match ecosystem {
+        Ecosystem::Cargo => format!("https://crates.io/api/v1/crates/{}", name),
+        Ecosystem::Npm => format!("https://registry.npmjs.org/{}/latest", name.replace('/', "%2f")),
+        Ecosystem::Python => format!("https://pypi.org/pypi/{}/json", name),
+    }
+}
+
+fn latest_from_response(ecosystem: Ecosystem, body: &Value) -> Option<String> {
+    let version = match ecosystem {
+        Ecosystem::Cargo => body["crate"]["max_stable_version"]
+            .as_str()
+            .or_else(|| body["crate"]["max_version"].as_str()),
+        Ecosystem::Npm => body["version"].as_str(),
+        Ecosystem::Python => body["info"]["version"].as_str(),
+    };
+    version.map(String::from)
+}
+
+/// 查询最新版本；网络错误返回 Err，不写入缓存
+async fn latest_version(ecosystem: Ecosystem, name: &str) -> Result<Option<String>, String> {
+    let key = (ecosystem, name.to_string());
+    if let Some(cached) = LATEST_CACHE.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return Ok(cached);
+    }
+
+    let client = HTTP_CLIENT.as_ref().ok_or("HTTP client unavailable")?;
+    let url = reqwest::Url::parse(&registry_url(ecosystem, name)).map_err(|e| e.to_string())?;
+    let response = client
+        .request(reqwest::Method::GET, url)
+        .map_err(|failure| failure.message)?
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let latest = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        None
+    } else if response.status().is_success() {
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        latest_from_response(ecosystem, &body)
+    } else {
+        return Err(format!("{} returned {}", registry_url(ecosystem, name), response.status()));
+    };
+
+    if let Ok(mut cache) = LATEST_CACHE.lock() {
+        cache.insert(key, latest.clone());
+    }
+    Ok(latest)
+}
+
+/// `latest` 是否比 `current` 新；无法按 semver 解析时只要不同就算
+fn is_newer(latest: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<semver::Version> {
+        let version = version.trim().trim_start_matches('v');
+        semver::Version::parse(version).ok().or_else(|| {
+            // PyPI 常见的 "2.32" 形式补齐为三段
+            let padded = match version.matches('.').count() {
+                0 => format!("{}.0.0", version),
+                1 => format!("{}.0", version),
+                _ => return None,
+            };
+            semver::Version::parse(&padded).ok()
+        })
+    }
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => latest != current,
+    }
+}
+
+/// 为直接依赖补充最新版本，返回查询失败的说明
+async fn annotate_latest(projects: &mut [ProjectDeps]) -> Option<String> {
+    let mut targets: BTreeSet<(Ecosystem, String)> = BTreeSet::new();
+    for project in projects.iter() {
+        for dep in &project.dependencies {
+            let local = dep
+                .declared
+                .as_deref()
+                .is_some_and(|d| d.starts_with("path:") || d.starts_with("git:"));
+            if dep.direct && !local {
+                targets.insert((project.ecosystem, dep.name.clone()));
+            }
+        }
+    }
+    if targets.is_empty() {
+        return None;
+    }
+
+    let total = targets.len();
+    let lookups = stream::iter(targets)
+        .map(|(ecosystem, name)| async move {
+            let result = latest_version(ecosystem, &name).await;
+            ((ecosystem, name), result)
+        })
+        .buffer_unordered(REGISTRY_CONCURRENCY)
+        .collect::<Vec<_>>();
+    let results = tokio::time::timeout(REGISTRY_TOTAL_TIMEOUT, lookups)
+        .await
+        .unwrap_or_default();
+
+    let mut latest = HashMap::new();
+    let mut first_error = None;
+    let mut failed = total - results.len();
+    for (key, result) in results {
+        match result {
+            Ok(version) => {
+                latest.insert(key, version);
+            }
+            Err(e) => {
+                failed += 1;
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    for project in projects.iter_mut() {
+        for dep in project.dependencies.iter_mut().filter(|d| d.direct) {
+            let Some(Some(version)) = latest.get(&(project.ecosystem, dep.name.clone())) else {
+                continue;
+            };
+            dep.outdated = dep.resolved.as_deref().map(|current| is_newer(version, current));
+            dep.latest = Some(version.clone());
+        }
+    }
+
+    (failed > 0).then(|| {
+        format!(
+            "Could not query the registry for {} of {} packages ({}); latest versions are incomplete",
+            failed,
+            total,
+            first_error.unwrap_or_else(|| "timed out".to_string())
+        )
+    })
+}
+
+/// 生成简短的文字摘要
+fn summarize(projects: &[ProjectDeps]) -> String {
+    if projects.is_empty() {
+        return "No Cargo.toml, package.json, pyproject.toml or requirements.txt found".to_string();
+    }
+    let mut lines = Vec::new();
+    for project in projects {
+        let mut line = format!(
+            "{} ({}{}): {} direct, {} transitive",
+            project.ecosystem.name(),
+            project.manifest,
+            project
+                .lockfile
+                .as_ref()
+                .map(|lock| format!(" + {}", lock))
+                .unwrap_or_else(|| ", no lockfile".to_string()),
+            project.direct_count,
+            project.transitive_count
+        );
+        if !project.members.is_empty() {
+            line.push_str(&format!(", {} workspace members", project.members.len()));
+        }
+        let outdated: Vec<String> = project
+            .dependencies
+            .iter()
+            .filter(|dep| dep.outdated == Some(true))
+            .map(|dep| {
+                format!(
+                    "{} {} -> {}",
+                    dep.name,
+                    dep.resolved.as_deref().unwrap_or("?"),
+                    dep.latest.as_deref().unwrap_or("?")
+                )
+            })
+            .collect();
+        if !outdated.is_empty() {
+            line.push_str(&format!("; {} outdated: ", outdated.len()));
+            line.push_str(&outdated.iter().take(SUMMARY_OUTDATED).cloned().collect::<Vec<_>>().join(", "));
+            if outdated.len() > SUMMARY_OUTDATED {
+                line.push_str(", ...");
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DepsTool;
+
+impl Tool for DepsTool {
+    const NAME: &'static str = "deps";
+
+    type Error = FileToolError;
+    type Args = DepsArgs;
+    type Output = DepsOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "deps".to_string(),
+            description: "Inspect the project's dependencies from Cargo.toml/Cargo.lock, package.json/package-lock.json and pyproject.toml/requirements.txt/poetry.lock/uv.lock. Returns declared version requirements, resolved (locked) versions, enabled features and direct vs. transitive classification, aggregated across Cargo and npm workspace members. With check_latest, looks up the newest published version on crates.io/npm/PyPI and marks outdated dependencies.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Project directory (default: current directory)"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Only report this dependency (direct or transitive), e.g. 'serde'"
+                    },
+                    "check_latest": {
+                        "type": "boolean",
+                        "description": "Query the package registry for the latest version of each direct dependency (default false)"
+                    },
+                    "include_transitive": {
+                        "type": "boolean",
+                        "description": "Also list every transitive dependency from the lockfile (default false; counts are always reported)"
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = PathBuf::from(args.path.as_deref().unwrap_or("."));
+        if !root.is_dir() {
+            return Err(FileToolError::FileNotFound(root.display().to_string()));
+        }
+
+        let mut projects = inspect(&root)?;
+        let name = args.name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        for project in projects.iter_mut() {
+            filter_dependencies(project, name, args.include_transitive);
+        }
+
+        let note = if args.check_latest && !network::current().tools_enabled() {
+            Some("Latest versions were not checked: network access from tools is disabled by the network policy".to_string())
+        } else if args.check_latest {
+            annotate_latest(&mut projects).await
+        } else {
+            None
+        };
+
+        Ok(DepsOutput {
+            summary: summarize(&projects),
+            projects,
+            note,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedDepsTool {
+    inner: DepsTool,
+}
+
+impl WrappedDepsTool {
+    pub fn new() -> Self {
+        Self { inner: DepsTool }
+    }
+}
+
+impl Default for WrappedDepsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tool for WrappedDepsTool {
+    const NAME: &'static str = "deps";
+
+    type Error = FileToolError;
+    type Args = <DepsTool as Tool>::Args;
+    type Output = <DepsTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        let mut target = args.path.clone().unwrap_or_else(|| ".".to_string());
+        if let Some(name) = &args.name {
+            target.push_str(&format!(", name={}", name));
+        }
+        if args.check_latest {
+            target.push_str(", check_latest");
+        }
+        tool_println!("{} {}({})", "●".bright_blue(), "Deps".bright_blue(), target.bright_white());
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => {
+                for line in output.summary.lines() {
+                    tool_println!("  └─ {}", line.dimmed());
+                }
+                if let Some(note) = &output.note {
+                    tool_println!("  {} {}", "⚠".bright_yellow(), note.bright_yellow());
+                }
+            }
+            Err(e) => {
+                tool_println!("  └─ {} {}", t!("common.error").red(), e.message().red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/deps")
+            .join(name)
+    }
+
+    fn find<'a>(project: &'a ProjectDeps, name: &str) -> &'a Dependency {
+        project
+            .dependencies
+            .iter()
+            .find(|dep| dep.name == name)
+            .unwrap_or_else(|| panic!("dependency {} not found", name))
+    }
+
+    #[test]
+    fn test_cargo_workspace() {
+        let project = inspect_cargo(&fixture("cargo_workspace")).unwrap().unwrap();
+        assert_eq!(project.lockfile.as_deref(), Some("Cargo.lock"));
+        assert_eq!(project.members, vec!["crates/app", "crates/core"]);
+        assert_eq!((project.direct_count, project.transitive_count), (9, 3));
+
+        // workspace 继承：版本来自 [workspace.dependencies]，features 合并
+        let serde = find(&project, "serde");
+        assert_eq!(serde.declared.as_deref(), Some("1.0"));
+        assert_eq!(serde.resolved.as_deref(), Some("1.0.210"));
+        assert_eq!(serde.features, vec!["derive", "rc"]);
+        assert_eq!(serde.used_by, vec!["crates/app", "crates/core"]);
+
+        // 两个成员要求不同主版本时分别解析
+        let thiserror = find(&project, "thiserror");
+        assert_eq!(thiserror.declared.as_deref(), Some("2.0, 1"));
+        assert_eq!(thiserror.resolved.as_deref(), Some("2.0.3, 1.0.64"));
+
+        // package 重命名、可选依赖、target 依赖、路径依赖
+        assert_eq!(find(&project, "serde_json").resolved.as_deref(), Some("1.0.128"));
+        assert_eq!(find(&project, "regex").kind, DepKind::Optional);
+        assert_eq!(find(&project, "regex").resolved, None);
+        assert_eq!(find(&project, "libc").resolved.as_deref(), Some("0.2.158"));
+        assert_eq!(find(&project, "demo-core").declared.as_deref(), Some("path:../core"));
+        assert_eq!(find(&project, "cc").kind, DepKind::Build);
+        assert_eq!(find(&project, "tempfile").kind, DepKind::Dev);
+
+        let transitive: Vec<&str> = project
+            .dependencies
+            .iter()
+            .filter(|dep| !dep.direct)
+            .map(|dep| dep.name.as_str())
+            .collect();
+        assert_eq!(transitive, vec!["itoa", "serde_derive", "shlex"]);
+    }
+
+    #[test]
+    fn test_npm_workspace() {
+        let project = inspect_npm(&fixture("npm")).unwrap().unwrap();
+        assert_eq!(project.members, vec!["packages/ui"]);
+        assert_eq!((project.direct_count, project.transitive_count), (5, 4));
+
+        let react = find(&project, "react");
+        assert_eq!(react.declared.as_deref(), Some("^18.2.0, ^18.0.0"));
+        assert_eq!(react.resolved.as_deref(), Some("18.3.1"));
+        assert_eq!(react.used_by, vec![".", "packages/ui"]);
+
+        assert_eq!(find(&project, "@types/react").kind, DepKind::Dev);
+        assert_eq!(find(&project, "react-dom").kind, DepKind::Peer);
+        assert_eq!(find(&project, "react-dom").resolved, None);
+        // workspace 自身的 link 条目不算依赖
+        assert!(project.dependencies.iter().all(|dep| dep.name != "@web/ui"));
+    }
+
+    #[test]
+    fn test_python_pyproject() {
+        let project = inspect_python(&fixture("python")).unwrap().unwrap();
+        assert_eq!(project.lockfile.as_deref(), Some("poetry.lock"));
+        assert_eq!((project.direct_count, project.transitive_count), (6, 2));
+
+        let fastapi = find(&project, "fastapi");
+        assert_eq!(fastapi.declared.as_deref(), Some(">=0.110"));
+        assert_eq!(fastapi.resolved.as_deref(), Some("0.111.0"));
+        assert_eq!(fastapi.features, vec!["standard"]);
+
+        // 名称规范化、带括号的版本要求、环境标记
+        assert_eq!(find(&project, "requests").declared.as_deref(), Some(">=2.31,<3"));
+        assert_eq!(find(&project, "pydantic-settings").resolved.as_deref(), Some("2.3.4"));
+        assert_eq!(find(&project, "tomli").declared.as_deref(), Some(">=2.0"));
+        assert_eq!(find(&project, "ruff").kind, DepKind::Optional);
+        assert_eq!(find(&project, "ruff").declared, None);
+    }
+
+    #[test]
+    fn test_requirements_txt() {
+        let project = inspect_python(&fixture("requirements")).unwrap().unwrap();
+        assert_eq!(project.manifest, "requirements.txt");
+        assert_eq!(project.lockfile, None);
+        assert_eq!(project.direct_count, 2);
+        assert_eq!(find(&project, "flask").resolved.as_deref(), Some("3.0.3"));
+        assert_eq!(find(&project, "gunicorn").declared.as_deref(), Some(">=22.0"));
+        assert_eq!(find(&project, "gunicorn").resolved, None);
+    }
+
+    #[test]
+    fn test_filter_by_name() {
+        let mut project = inspect_cargo(&fixture("cargo_workspace")).unwrap().unwrap();
+        filter_dependencies(&mut project, Some("serde_derive"), false);
+        assert_eq!(project.dependencies.len(), 1);
+        assert!(!project.dependencies[0].direct);
+
+        let mut project = inspect_python(&fixture("python")).unwrap().unwrap();
+        filter_dependencies(&mut project, Some("Pydantic_Settings"), false);
+        assert_eq!(project.dependencies.len(), 1);
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.0.215", "1.0.210"));
+        assert!(!is_newer("1.0.210", "1.0.210"));
+        assert!(is_newer("2.32", "2.31.0"));
+        assert!(!is_newer("0.9.0", "1.0.0"));
+        assert!(is_newer("2024.1b1", "2023.12"));
+    }
+
+    #[test]
+    fn test_latest_from_response() {
+        let crates = serde_json::json!({"crate": {"max_version": "2.0.0-rc.1", "max_stable_version": "1.0.210"}});
+        assert_eq!(latest_from_response(Ecosystem::Cargo, &crates).as_deref(), Some("1.0.210"));
+        let npm = serde_json::json!({"name": "react", "version": "18.3.1"});
+        assert_eq!(latest_from_response(Ecosystem::Npm, &npm).as_deref(), Some("18.3.1"));
+        let pypi = serde_json::json!({"info": {"version": "2.32.3"}});
+        assert_eq!(latest_from_response(Ecosystem::Python, &pypi).as_deref(), Some("2.32.3"));
+
+        assert_eq!(
+            registry_url(Ecosystem::Npm, "@types/react"),
+            "https://registry.npmjs.org/@types%2freact/latest"
+        );
+    }
+}
@@ -0,0 +1,748 @@
+//! 诊断工具
+//!
+//! 编辑后快速确认代码是否还能编译：识别项目使用的检查器（cargo check、tsc、eslint、
+//! ruff）或使用指定的 `checker`，以机器可读格式运行并解析为统一的
+//! `{file, line, column, severity, message, code}` 列表，按文件分组返回。
+//! 检查器自身崩溃（退出码非零却没有任何诊断）和超时与“代码有错误”分开报告。
+
+use super::safe_write::touched_files;
+use super::settings::ToolSettings;
+use super::FileToolError;
+use colored::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// 检查超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 默认返回的诊断条数上限
+const DEFAULT_MAX_DIAGNOSTICS: usize = 50;
+
+/// 检查器失败时保留的输出行数
+const OUTPUT_TAIL_LINES: usize = 40;
+
+/// `path` 取该值时只保留本次会话改动过的文件
+const SESSION_FILTER: &str = "session";
+
+/// 检查器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Checker {
+    Cargo,
+    Tsc,
+    Eslint,
+    Ruff,
+}
+
+impl Checker {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Tsc => "tsc",
+            Self::Eslint => "eslint",
+            Self::Ruff => "ruff",
+        }
+    }
+
+    /// 根据项目文件识别检查器
+    pub fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").exists() {
+            return Some(Self::Cargo);
+        }
+        if root.join("tsconfig.json").exists() {
+            return Some(Self::Tsc);
+        }
+        let eslint_markers = [
+            "eslint.config.js",
+            "eslint.config.mjs",
+            "eslint.config.cjs",
+            ".eslintrc",
+            ".eslintrc.js",
+            ".eslintrc.cjs",
+            ".eslintrc.json",
+            ".eslintrc.yml",
+        ];
+        if eslint_markers.iter().any(|marker| root.join(marker).exists()) {
+            return Some(Self::Eslint);
+        }
+        let python_markers = ["ruff.toml", ".ruff.toml", "pyproject.toml", "setup.py", "setup.cfg"];
+        if python_markers.iter().any(|marker| root.join(marker).exists()) {
+            return Some(Self::Ruff);
+        }
+        None
+    }
+
+    fn command(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Cargo => (
+                "cargo",
+                &["check", "--all-targets", "--keep-going", "--message-format=json"],
+            ),
+            Self::Tsc => ("npx", &["tsc", "--noEmit", "--pretty", "false"]),
+            Self::Eslint => ("npx", &["eslint", "-f", "json", "."]),
+            Self::Ruff => ("ruff", &["check", "--output-format", "json", "."]),
+        }
+    }
+
+    /// 解析检查器输出，无法识别时返回 None
+    fn parse(self, stdout: &str) -> Option<Vec<Diagnostic>> {
+        match self {
+            Self::Cargo => parse_cargo(stdout),
+            Self::Tsc => Some(parse_tsc(stdout)),
+            Self::Eslint => parse_eslint(stdout),
+            Self::Ruff => parse_ruff(stdout),
+        }
+    }
+}
+
+/// 诊断级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// 一条诊断
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// 已按文件分组，输出中不再重复
+    #[serde(skip_serializing)]
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// 检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// 没有错误和警告
+    Clean,
+    /// 只有警告
+    Warnings,
+    /// 有错误
+    Errors,
+    /// 检查器无法运行或崩溃，没有产生诊断
+    CheckerFailed,
+    /// 检查超时
+    TimedOut,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiagnosticsArgs {
+    /// 检查器（不指定时自动识别）
+    #[serde(default)]
+    pub checker: Option<Checker>,
+    /// 只保留该路径下的诊断；取 "session" 时只保留本次会话改动过的文件
+    #[serde(default)]
+    pub path: Option<String>,
+    /// 返回的诊断条数上限
+    #[serde(default)]
+    pub max_diagnostics: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiagnosticsOutput {
+    pub checker: Checker,
+    pub command: String,
+    pub status: CheckStatus,
+    /// 过滤后的错误总数
+    pub errors: usize,
+    /// 过滤后的警告总数
+    pub warnings: usize,
+    /// 被 `path` 过滤掉的诊断数
+    pub filtered_out: usize,
+    /// 因条数上限未返回的诊断数
+    pub truncated: usize,
+    /// 按文件分组的诊断（错误优先）
+    pub files: BTreeMap<String, Vec<Diagnostic>>,
+    pub duration_ms: u64,
+    /// 检查器失败或超时时附带输出末尾
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tail: Option<String>,
+}
+
+/// 诊断的文件过滤
+enum PathFilter {
+    All,
+    Files(Vec<PathBuf>),
+    Prefix(PathBuf),
+}
+
+impl PathFilter {
+    fn new(path: Option<&str>, root: &Path, settings: &ToolSettings) -> Self {
+        match path.map(str::trim).filter(|p| !p.is_empty()) {
+            None => Self::All,
+            Some(SESSION_FILTER) => Self::Files(touched_files()),
+            Some(path) => Self::Prefix(absolute(root, &settings.resolve_path(path))),
+        }
+    }
+
+    fn matches(&self, root: &Path, file: &str) -> bool {
+        let file = absolute(root, file);
+        match self {
+            Self::All => true,
+            Self::Files(files) => files.contains(&file),
+            Self::Prefix(prefix) => file.starts_with(prefix),
+        }
+    }
+}
+
+/// 在 `[tools.root]`（未设置时为当前目录）运行检查器
+#[derive(Default, Deserialize, Serialize)]
+pub struct DiagnosticsTool {
+    settings: ToolSettings,
+}
+
+impl DiagnosticsTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
+
+impl Tool for DiagnosticsTool {
+    const NAME: &'static str = "diagnostics";
+
+    type Error = FileToolError;
+    type Args = DiagnosticsArgs;
+    type Output = DiagnosticsOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "diagnostics".to_string(),
+            description: "Check whether the project still compiles/lints cleanly and return compiler or linter diagnostics grouped by file: line, column, severity, message and code. The checker (cargo check, tsc, eslint, ruff) is detected from project files unless `checker` is given. Use it after edits instead of running the build through shell_execute. status is `errors`/`warnings`/`clean` for real results, `checker_failed` when the checker itself could not run, and `timed_out` on timeout.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "checker": {
+                        "type": "string",
+                        "enum": ["cargo", "tsc", "eslint", "ruff"],
+                        "description": "Checker to run. Detected automatically when omitted."
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Only report diagnostics for files under this path. Use \"session\" to only report files modified in this session."
+                    },
+                    "max_diagnostics": {
+                        "type": "integer",
+                        "description": "Maximum number of diagnostics to return, errors first (default 50). Totals are always reported."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = self.settings.working_dir();
+        let checker = args.checker.or_else(|| Checker::detect(&root)).ok_or_else(|| {
+            FileToolError::InvalidInput(
+                "Could not detect a checker for this project; pass `checker` explicitly".to_string(),
+            )
+        })?;
+        let filter = PathFilter::new(args.path.as_deref(), &root, &self.settings);
+        let limit = args.max_diagnostics.unwrap_or(DEFAULT_MAX_DIAGNOSTICS);
+
+        let (program, program_args) = checker.command();
+        let command = format!("{} {}", program, program_args.join(" "));
+        let failed = |status, tail: String, started: Instant| DiagnosticsOutput {
+            checker,
+            command: command.clone(),
+            status,
+            errors: 0,
+            warnings: 0,
+            filtered_out: 0,
+            truncated: 0,
+            files: BTreeMap::new(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            output_tail: Some(tail),
+        };
+
+        let started = Instant::now();
+        let child = match Command::new(program)
+            .args(program_args)
+            .current_dir(&root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                let tail = format!("failed to start `{}`: {}", program, e);
+                return Ok(failed(CheckStatus::CheckerFailed, tail, started));
+            }
+        };
+        let output = match tokio::time::timeout(CHECK_TIMEOUT, child.wait_with_output()).await {
+            Ok(output) => output?,
+            Err(_) => {
+                let tail = format!("checker timed out after {}s", CHECK_TIMEOUT.as_secs());
+                return Ok(failed(CheckStatus::TimedOut, tail, started));
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let diagnostics = checker.parse(&stdout).unwrap_or_default();
+
+        // 退出码非零却没有任何诊断：检查器自身出错（配置错误、依赖缺失等）
+        if diagnostics.is_empty() && !output.status.success() {
+            let tail = tail(&format!("{}\n{}", stdout, stderr));
+            return Ok(failed(CheckStatus::CheckerFailed, tail, started));
+        }
+
+        let mut output = summarize(checker, diagnostics, &root, &filter, limit);
+        output.duration_ms = started.elapsed().as_millis() as u64;
+        Ok(output)
+    }
+}
+
+/// 过滤、排序、截断并按文件分组
+fn summarize(
+    checker: Checker,
+    diagnostics: Vec<Diagnostic>,
+    root: &Path,
+    filter: &PathFilter,
+    limit: usize,
+) -> DiagnosticsOutput {
+    let total = diagnostics.len();
+    let matched: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .filter(|d| filter.matches(root, &d.file))
+        .collect();
+    let filtered_out = total - matched.len();
+
+    // cargo 对 lib 和测试目标会重复报告同一条诊断
+    let mut seen = HashSet::new();
+    let mut kept: Vec<Diagnostic> = matched
+        .into_iter()
+        .filter(|d| seen.insert((d.file.clone(), d.line, d.column, d.message.clone())))
+        .map(|d| Diagnostic {
+            file: display_path(root, &d.file),
+            ..d
+        })
+        .collect();
+    kept.sort_by(|a, b| {
+        (a.severity, &a.file, a.line, a.column).cmp(&(b.severity, &b.file, b.line, b.column))
+    });
+
+    let errors = kept.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = kept.iter().filter(|d| d.severity == Severity::Warning).count();
+    let truncated = kept.len().saturating_sub(limit);
+    let status = if errors > 0 {
+        CheckStatus::Errors
+    } else if warnings > 0 {
+        CheckStatus::Warnings
+    } else {
+        CheckStatus::Clean
+    };
+
+    let mut files: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+    for diagnostic in kept.into_iter().take(limit) {
+        files.entry(diagnostic.file.clone()).or_default().push(diagnostic);
+    }
+
+    let (program, args) = checker.command();
+    DiagnosticsOutput {
+        checker,
+        command: format!("{} {}", program, args.join(" ")),
+        status,
+        errors,
+        warnings,
+        filtered_out,
+        truncated,
+        files,
+        duration_ms: 0,
+        output_tail: None,
+    }
+}
+
+/// 解析 `cargo check --message-format=json`（每行一个 JSON 消息）
+fn parse_cargo(output: &str) -> Option<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let mut seen_message = false;
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        seen_message = true;
+        if event["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &event["message"];
+        // "aborting due to..."、"For more information..." 等汇总信息没有位置
+        let Some(span) = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true))
+        else {
+            continue;
+        };
+        let level = message["level"].as_str().unwrap_or_default();
+        let severity = if level.starts_with("error") {
+            Severity::Error
+        } else if level == "warning" {
+            Severity::Warning
+        } else {
+            Severity::Note
+        };
+        let mut text = message["message"].as_str().unwrap_or_default().to_string();
+        if let Some(label) = span["label"].as_str().filter(|label| !label.is_empty()) {
+            text = format!("{}: {}", text, label);
+        }
+        diagnostics.push(Diagnostic {
+            file: span["file_name"].as_str().unwrap_or_default().to_string(),
+            line: count(&span["line_start"]),
+            column: count(&span["column_start"]),
+            severity,
+            message: text,
+            code: message["code"]["code"].as_str().map(String::from),
+        });
+    }
+    seen_message.then_some(diagnostics)
+}
+
+/// 解析 `tsc --pretty false` 输出，缩进的续行属于上一条诊断
+fn parse_tsc(output: &str) -> Vec<Diagnostic> {
+    static LOCATED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(.+)\((\d+),(\d+)\): (error|warning|message) (TS\d+): (.*)$").unwrap()
+    });
+    // 配置等全局错误没有文件位置
+    static GLOBAL: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(error|warning|message) (TS\d+): (.*)$").unwrap());
+
+    let severity = |level: &str| match level {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => Severity::Note,
+    };
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = LOCATED.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: caps[1].to_string(),
+                line: caps[2].parse().unwrap_or(0),
+                column: caps[3].parse().unwrap_or(0),
+                severity: severity(&caps[4]),
+                message: caps[6].to_string(),
+                code: Some(caps[5].to_string()),
+            });
+        } else if let Some(caps) = GLOBAL.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: String::new(),
+                line: 0,
+                column: 0,
+                severity: severity(&caps[1]),
+                message: caps[3].to_string(),
+                code: Some(caps[2].to_string()),
+            });
+        } else if line.starts_with(' ') && !line.trim().is_empty() {
+            if let Some(last) = diagnostics.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            }
+        }
+    }
+    diagnostics
+}
+
+/// 解析 `eslint -f json`
+fn parse_eslint(output: &str) -> Option<Vec<Diagnostic>> {
+    let start = output.find('[')?;
+    let results: Vec<Value> = serde_json::from_str(output[start..].trim()).ok()?;
+
+    let mut diagnostics = Vec::new();
+    for result in &results {
+        let file = result["filePath"].as_str().unwrap_or_default();
+        for message in result["messages"].as_array().into_iter().flatten() {
+            diagnostics.push(Diagnostic {
+                file: file.to_string(),
+                line: count(&message["line"]),
+                column: count(&message["column"]),
+                severity: match message["severity"].as_u64() {
+                    Some(2) => Severity::Error,
+                    Some(1) => Severity::Warning,
+                    _ => Severity::Note,
+                },
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+                code: message["ruleId"].as_str().map(String::from),
+            });
+        }
+    }
+    Some(diagnostics)
+}
+
+/// 解析 `ruff check --output-format json`，没有规则码的是语法错误
+fn parse_ruff(output: &str) -> Option<Vec<Diagnostic>> {
+    let start = output.find('[')?;
+    let results: Vec<Value> = serde_json::from_str(output[start..].trim()).ok()?;
+
+    Some(
+        results
+            .iter()
+            .map(|result| {
+                let code = result["code"].as_str().map(String::from);
+                Diagnostic {
+                    file: result["filename"].as_str().unwrap_or_default().to_string(),
+                    line: count(&result["location"]["row"]),
+                    column: count(&result["location"]["column"]),
+                    severity: if code.is_some() {
+                        Severity::Warning
+                    } else {
+                        Severity::Error
+                    },
+                    message: result["message"].as_str().unwrap_or_default().to_string(),
+                    code,
+                }
+            })
+            .collect(),
+    )
+}
+
+fn count(value: &Value) -> usize {
+    value.as_u64().unwrap_or(0) as usize
+}
+
+fn absolute(root: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+    std::path::absolute(&joined).unwrap_or(joined)
+}
+
+/// 项目内的文件显示为相对路径
+fn display_path(root: &Path, file: &str) -> String {
+    if file.is_empty() {
+        return "<project>".to_string();
+    }
+    match Path::new(file).strip_prefix(root) {
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => file.to_string(),
+    }
+}
+
+/// 输出最后若干行
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim().lines().collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedDiagnosticsTool {
+    inner: DiagnosticsTool,
+}
+
+impl WrappedDiagnosticsTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: DiagnosticsTool::new(settings),
+        }
+    }
+}
+
+impl Tool for WrappedDiagnosticsTool {
+    const NAME: &'static str = "diagnostics";
+
+    type Error = FileToolError;
+    type Args = <DiagnosticsTool as Tool>::Args;
+    type Output = <DiagnosticsTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        let target = match (&args.checker, &args.path) {
+            (Some(checker), Some(path)) => format!("{} {}", checker.name(), path),
+            (Some(checker), None) => checker.name().to_string(),
+            (None, Some(path)) => path.clone(),
+            (None, None) => String::new(),
+        };
+        tool_println!("{} Check({})", "●".bright_green(), target);
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => match output.status {
+                CheckStatus::Clean => tool_println!("  └─ {}", "No problems".dimmed()),
+                CheckStatus::Warnings | CheckStatus::Errors => {
+                    let counts = format!("{} errors, {} warnings", output.errors, output.warnings);
+                    if output.errors > 0 {
+                        tool_println!("  └─ {}", counts.red());
+                    } else {
+                        tool_println!("  └─ {}", counts.yellow());
+                    }
+                    for (file, diagnostics) in output.files.iter().take(5) {
+                        tool_println!("     {} ({})", file, diagnostics.len());
+                    }
+                }
+                CheckStatus::CheckerFailed => {
+                    tool_println!("  └─ {}", format!("Checker failed ({})", output.command).red());
+                }
+                CheckStatus::TimedOut => {
+                    tool_println!("  └─ {}", format!("Timed out ({})", output.command).red());
+                }
+            },
+            Err(e) => {
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const CARGO_JSON: &str = include_str!("../../tests/fixtures/diagnostics/cargo.jsonl");
+    const TSC_TEXT: &str = include_str!("../../tests/fixtures/diagnostics/tsc.txt");
+    const ESLINT_JSON: &str = include_str!("../../tests/fixtures/diagnostics/eslint.json");
+    const RUFF_JSON: &str = include_str!("../../tests/fixtures/diagnostics/ruff.json");
+
+    fn brief(diagnostics: &[Diagnostic]) -> Vec<(&str, usize, usize, Severity, Option<&str>)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.file.as_str(), d.line, d.column, d.severity, d.code.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_cargo() {
+        let diagnostics = parse_cargo(CARGO_JSON).unwrap();
+        // lib 和测试目标各报告一次 unused 警告；汇总信息被跳过
+        assert_eq!(
+            brief(&diagnostics),
+            vec![
+                ("src/lib.rs", 2, 9, Severity::Warning, Some("unused_variables")),
+                ("src/lib.rs", 15, 38, Severity::Error, Some("E0425")),
+                ("src/lib.rs", 2, 9, Severity::Warning, Some("unused_variables")),
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "cannot find value `missing` in this scope: not found in this scope"
+        );
+
+        assert!(parse_cargo("error: could not find `Cargo.toml`").is_none());
+    }
+
+    #[test]
+    fn test_parse_tsc() {
+        let diagnostics = parse_tsc(TSC_TEXT);
+        assert_eq!(
+            brief(&diagnostics),
+            vec![
+                ("src/api/client.ts", 14, 7, Severity::Error, Some("TS2322")),
+                ("src/api/client.ts", 22, 18, Severity::Error, Some("TS2339")),
+                ("src/components/Button.tsx", 8, 4, Severity::Error, Some("TS2741")),
+            ]
+        );
+        assert!(diagnostics[2].message.ends_with("on type 'IntrinsicAttributes & ButtonProps'"));
+
+        let global = parse_tsc("error TS5023: Unknown compiler option 'strictest'.");
+        assert_eq!(brief(&global), vec![("", 0, 0, Severity::Error, Some("TS5023"))]);
+    }
+
+    #[test]
+    fn test_parse_eslint() {
+        let diagnostics = parse_eslint(ESLINT_JSON).unwrap();
+        assert_eq!(
+            brief(&diagnostics),
+            vec![
+                ("/work/web/src/App.jsx", 1, 10, Severity::Error, Some("no-unused-vars")),
+                ("/work/web/src/App.jsx", 12, 15, Severity::Warning, Some("eqeqeq")),
+                ("/work/web/src/broken.js", 4, 22, Severity::Error, None),
+            ]
+        );
+        assert_eq!(diagnostics[2].message, "Parsing error: Unexpected token )");
+
+        assert!(parse_eslint("Oops! Something went wrong!").is_none());
+    }
+
+    #[test]
+    fn test_parse_ruff() {
+        let diagnostics = parse_ruff(RUFF_JSON).unwrap();
+        assert_eq!(
+            brief(&diagnostics),
+            vec![
+                ("/work/app/app/main.py", 1, 8, Severity::Warning, Some("F401")),
+                ("/work/app/app/main.py", 9, 16, Severity::Warning, Some("E711")),
+                ("/work/app/app/models.py", 4, 25, Severity::Error, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_groups_and_truncates() {
+        let root = Path::new("/work/web");
+        let diagnostics = parse_eslint(ESLINT_JSON).unwrap();
+
+        let output = summarize(Checker::Eslint, diagnostics.clone(), root, &PathFilter::All, 2);
+        assert_eq!(output.status, CheckStatus::Errors);
+        assert_eq!((output.errors, output.warnings), (2, 1));
+        assert_eq!((output.filtered_out, output.truncated), (0, 1));
+        // 错误优先，路径相对于项目根目录
+        let files: Vec<&String> = output.files.keys().collect();
+        assert_eq!(files, vec!["src/App.jsx", "src/broken.js"]);
+        assert_eq!(output.files["src/App.jsx"][0].code.as_deref(), Some("no-unused-vars"));
+
+        let filter = PathFilter::Files(vec![PathBuf::from("/work/web/src/App.jsx")]);
+        let output = summarize(Checker::Eslint, diagnostics.clone(), root, &filter, 50);
+        assert_eq!((output.errors, output.warnings, output.filtered_out), (1, 1, 1));
+
+        let filter = PathFilter::Prefix(PathBuf::from("/work/web/lib"));
+        let output = summarize(Checker::Eslint, diagnostics, root, &filter, 50);
+        assert_eq!(output.status, CheckStatus::Clean);
+        assert_eq!(output.filtered_out, 3);
+    }
+
+    #[test]
+    fn test_summarize_dedupes_cargo_targets() {
+        let diagnostics = parse_cargo(CARGO_JSON).unwrap();
+        let output = summarize(Checker::Cargo, diagnostics, Path::new("/work/calc"), &PathFilter::All, 50);
+        assert_eq!((output.errors, output.warnings), (1, 1));
+        assert_eq!(output.files["src/lib.rs"].len(), 2);
+    }
+
+    #[test]
+    fn test_detect_checker() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(Checker::detect(temp_dir.path()), None);
+
+        fs::write(temp_dir.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(Checker::detect(temp_dir.path()), Some(Checker::Ruff));
+
+        fs::write(temp_dir.path().join("eslint.config.js"), "").unwrap();
+        assert_eq!(Checker::detect(temp_dir.path()), Some(Checker::Eslint));
+
+        fs::write(temp_dir.path().join("tsconfig.json"), "{}").unwrap();
+        assert_eq!(Checker::detect(temp_dir.path()), Some(Checker::Tsc));
+
+        fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Checker::detect(temp_dir.path()), Some(Checker::Cargo));
+    }
+}
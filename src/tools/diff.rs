@@ -0,0 +1,584 @@
+//! 文件对比
+//!
+//! 比较两个文件，或比较文件与某个 git 提交中的版本（`git show <ref>:<path>`），返回
+//! unified diff 和增删统计，避免模型把两个文件完整读入后自行比较。`unified_diff`
+//! 是共用的 diff 格式化，供其他需要展示差异的地方复用。
+
+use super::sensitive_paths::{get_sensitive_guard, SensitiveAccess};
+use super::settings::ToolSettings;
+use super::FileToolError;
+use colored::*;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 默认上下文行数
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// 上下文行数上限
+const MAX_CONTEXT_LINES: usize = 50;
+
+/// 返回的 diff 最多字符数
+const MAX_DIFF_CHARS: usize = 20_000;
+
+/// `git show` 的超时
+const GIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 格式化后的 unified diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedDiff {
+    /// diff 文本（内容相同时为空）
+    pub text: String,
+    pub hunks: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// 生成带 `---`/`+++` 文件头的 unified diff
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_label: &str,
+    new_label: &str,
+    context_lines: usize,
+) -> UnifiedDiff {
+    let diff = TextDiff::from_lines(old, new);
+    let (mut lines_added, mut lines_removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => lines_added += 1,
+            ChangeTag::Delete => lines_removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let hunks = diff.grouped_ops(context_lines).len();
+    let text = if hunks == 0 {
+        String::new()
+    } else {
+        diff.unified_diff()
+            .context_radius(context_lines)
+            .missing_newline_hint(true)
+            .header(old_label, new_label)
+            .to_string()
+    };
+    UnifiedDiff {
+        text,
+        hunks,
+        lines_added,
+        lines_removed,
+    }
+}
+
+/// 按行截断过长的 diff，返回是否截断
+fn truncate_diff(text: &mut String) -> bool {
+    if text.len() <= MAX_DIFF_CHARS {
+        return false;
+    }
+    let mut cut = MAX_DIFF_CHARS;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let cut = text[..cut].rfind('\n').map(|i| i + 1).unwrap_or(cut);
+    let omitted = text[cut..].lines().count();
+    text.truncate(cut);
+    text.push_str(&format!(
+        "... diff truncated: {} more lines omitted; narrow the comparison or reduce context_lines\n",
+        omitted
+    ));
+    true
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiffArgs {
+    /// 旧文件（与 path_b 一起使用）
+    #[serde(default)]
+    pub path_a: Option<String>,
+    /// 新文件（与 path_a 一起使用）
+    #[serde(default)]
+    pub path_b: Option<String>,
+    /// 与 git_ref 中的版本比较的文件
+    #[serde(default)]
+    pub path: Option<String>,
+    /// git 提交、分支或标签（如 HEAD、main、v1.2.0）
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    #[serde(default)]
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffOutput {
+    pub old: String,
+    pub new: String,
+    pub identical: bool,
+    pub diff: String,
+    pub hunks: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// 拒绝敏感文件
+fn check_sensitive(path: &str) -> Result<(), FileToolError> {
+    match get_sensitive_guard().check(Path::new(path)) {
+        SensitiveAccess::Denied { pattern } | SensitiveAccess::NeedsConfirmation { pattern } => {
+            Err(FileToolError::PermissionDenied(format!(
+                "{} (matches sensitive path pattern '{}')",
+                path, pattern
+            )))
+        }
+        SensitiveAccess::Allowed => Ok(()),
+    }
+}
+
+fn decode(path: &str, bytes: Vec<u8>) -> Result<String, FileToolError> {
+    String::from_utf8(bytes).map_err(|_| {
+        FileToolError::InvalidInput(format!("{} is a binary file and cannot be diffed", path))
+    })
+}
+
+/// 读取工作区中的文件（不存在时为 None）
+async fn read_worktree(path: &str) -> Result<Option<String>, FileToolError> {
+    let file = Path::new(path);
+    if !file.exists() {
+        return Ok(None);
+    }
+    if !file.is_file() {
+        return Err(FileToolError::NotAFile(path.to_string()));
+    }
+    decode(path, tokio::fs::read(file).await?).map(Some)
+}
+
+async fn read_required(path: &str) -> Result<String, FileToolError> {
+    read_worktree(path)
+        .await?
+        .ok_or_else(|| FileToolError::FileNotFound(path.to_string()))
+}
+
+/// 读取文件在 `git_ref` 中的版本（该版本中不存在时为 None）
+async fn read_at_ref(path: &str, git_ref: &str) -> Result<Option<String>, FileToolError> {
+    if git_ref.starts_with('-') || git_ref.contains(':') {
+        return Err(FileToolError::InvalidInput(format!(
+            "Invalid git_ref: '{}'",
+            git_ref
+        )));
+    }
+    // 在文件所在目录执行，`./` 让路径相对该目录解析
+    let file = Path::new(path);
+    let (dir, spec) = match (file.parent(), file.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() && parent.is_dir() => {
+            (parent, format!("{}:./{}", git_ref, name.to_string_lossy()))
+        }
+        _ => (Path::new("."), format!("{}:./{}", git_ref, path)),
+    };
+
+    let child = Command::new("git")
+        .args(["show", "--no-textconv", &spec])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let output = tokio::time::timeout(GIT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("git show timed out after {}s", GIT_TIMEOUT.as_secs()),
+            )
+        })??;
+
+    if output.status.success() {
+        return decode(path, output.stdout).map(Some);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // 文件在该版本中不存在：按全部新增处理
+    if stderr.contains("does not exist in") || stderr.contains("exists on disk, but not in") {
+        return Ok(None);
+    }
+    Err(FileToolError::InvalidInput(format!(
+        "git show {} failed: {}",
+        spec,
+        stderr.trim()
+    )))
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct DiffTool {
+    settings: ToolSettings,
+}
+
+impl DiffTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// 解析路径，拒绝工作区根目录之外的文件和敏感文件
+    fn resolve(&self, path: &str) -> Result<String, FileToolError> {
+        let file = self.settings.resolve_path(path);
+        self.settings.roots.check(Path::new(&file))?;
+        check_sensitive(&file)?;
+        Ok(file)
+    }
+}
+
+impl Tool for DiffTool {
+    const NAME: &'static str = "diff";
+
+    type Error = FileToolError;
+    type Args = DiffArgs;
+    type Output = DiffOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "diff".to_string(),
+            description: "Compare two text files and return a unified diff with added/removed line counts, instead of reading both files and comparing them yourself. Pass path_a (old) and path_b (new) to compare two files, or path and git_ref to compare a file against its version in a git commit, branch or tag (a file missing at that ref counts as entirely added, a file deleted since shows as entirely removed). Large diffs are truncated.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path_a": {
+                        "type": "string",
+                        "description": "The old file, compared with path_b."
+                    },
+                    "path_b": {
+                        "type": "string",
+                        "description": "The new file, compared with path_a."
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "The file to compare against its version at git_ref."
+                    },
+                    "git_ref": {
+                        "type": "string",
+                        "description": "A git commit, branch or tag, e.g. 'HEAD', 'main', 'HEAD~3', 'v1.2.0'."
+                    },
+                    "context_lines": {
+                        "type": "integer",
+                        "description": "Unchanged lines shown around each change (default 3)."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let context_lines = args
+            .context_lines
+            .unwrap_or(DEFAULT_CONTEXT_LINES)
+            .min(MAX_CONTEXT_LINES);
+
+        let (old_label, new_label, old, new, note) =
+            match (&args.path_a, &args.path_b, &args.path, &args.git_ref) {
+                (Some(path_a), Some(path_b), None, None) => {
+                    let (file_a, file_b) = (self.resolve(path_a)?, self.resolve(path_b)?);
+                    let old = read_required(&file_a).await?;
+                    let new = read_required(&file_b).await?;
+                    (path_a.clone(), path_b.clone(), old, new, None)
+                }
+                (None, None, Some(path), Some(git_ref)) => {
+                    let file = self.resolve(path)?;
+                    let old = read_at_ref(&file, git_ref).await?;
+                    let new = read_worktree(&file).await?;
+                    let note = match (&old, &new) {
+                        (None, None) => {
+                            return Err(FileToolError::FileNotFound(format!(
+                                "{} (not in the working tree nor at {})",
+                                path, git_ref
+                            )))
+                        }
+                        (None, Some(_)) => Some(format!(
+                            "{} does not exist at {}; shown as added",
+                            path, git_ref
+                        )),
+                        (Some(_), None) => Some(format!(
+                            "{} was deleted since {}; shown as removed",
+                            path, git_ref
+                        )),
+                        _ => None,
+                    };
+                    (
+                        format!("{}@{}", path, git_ref),
+                        path.clone(),
+                        old.unwrap_or_default(),
+                        new.unwrap_or_default(),
+                        note,
+                    )
+                }
+                _ => {
+                    return Err(FileToolError::InvalidInput(
+                        "Pass either path_a and path_b, or path and git_ref".to_string(),
+                    ))
+                }
+            };
+
+        let diff = unified_diff(&old, &new, &old_label, &new_label, context_lines);
+        let mut text = diff.text;
+        let truncated = truncate_diff(&mut text);
+
+        Ok(DiffOutput {
+            old: old_label,
+            new: new_label,
+            identical: diff.hunks == 0,
+            diff: text,
+            hunks: diff.hunks,
+            lines_added: diff.lines_added,
+            lines_removed: diff.lines_removed,
+            truncated,
+            note,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedDiffTool {
+    inner: DiffTool,
+}
+
+impl WrappedDiffTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: DiffTool::new(settings),
+        }
+    }
+}
+
+impl Tool for WrappedDiffTool {
+    const NAME: &'static str = "diff";
+
+    type Error = FileToolError;
+    type Args = <DiffTool as Tool>::Args;
+    type Output = <DiffTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let target = match (&args.path_a, &args.path_b, &args.path, &args.git_ref) {
+            (Some(a), Some(b), _, _) => format!("{} → {}", a, b),
+            (_, _, Some(path), Some(git_ref)) => format!("{}@{}", path, git_ref),
+            _ => String::new(),
+        };
+        tool_println!();
+        tool_println!("{} Diff({})", "●".bright_green(), target);
+
+        let result = self.inner.call(args).await;
+        match &result {
+            Ok(output) if output.identical => {
+                tool_println!("  └─ {}", "no differences".dimmed());
+            }
+            Ok(output) => {
+                tool_println!(
+                    "  └─ {} hunks (+{} lines, -{} lines){}",
+                    output.hunks.to_string().dimmed(),
+                    output.lines_added.to_string().green(),
+                    output.lines_removed.to_string().red(),
+                    if output.truncated { ", truncated" } else { "" }
+                );
+            }
+            Err(e) => {
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ErrorCode, WorkspaceRoots};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn args(path_a: Option<&str>, path_b: Option<&str>) -> DiffArgs {
+        DiffArgs {
+            path_a: path_a.map(str::to_string),
+            path_b: path_b.map(str::to_string),
+            path: None,
+            git_ref: None,
+            context_lines: None,
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=Oxide",
+                "-c",
+                "user.email=oxide@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[tokio::test]
+    async fn test_diff_two_files() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("old.toml");
+        let new = dir.path().join("new.toml");
+        fs::write(&old, "[server]\nport = 8080\nhost = \"localhost\"\n").unwrap();
+        fs::write(
+            &new,
+            "[server]\nport = 9090\nhost = \"localhost\"\nworkers = 4\n",
+        )
+        .unwrap();
+
+        let output = DiffTool::default()
+            .call(args(old.to_str(), new.to_str()))
+            .await
+            .unwrap();
+        assert!(!output.identical);
+        assert_eq!(
+            (output.hunks, output.lines_added, output.lines_removed),
+            (1, 2, 1)
+        );
+        assert!(output.diff.starts_with(&format!(
+            "--- {}\n+++ {}\n",
+            old.display(),
+            new.display()
+        )));
+        assert!(output.diff.contains("-port = 8080\n+port = 9090\n"));
+        assert!(output.diff.contains("+workers = 4\n"));
+        assert!(!output.truncated);
+
+        let missing = DiffTool::default()
+            .call(args(old.to_str(), Some("/nonexistent/new.toml")))
+            .await;
+        assert!(matches!(missing, Err(FileToolError::FileNotFound(_))));
+        assert!(DiffTool::default()
+            .call(args(old.to_str(), None))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_identical_files_have_empty_diff() {
+        let dir = TempDir::new().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "same\n").unwrap();
+        fs::write(&b, "same\n").unwrap();
+
+        let output = DiffTool::default()
+            .call(args(a.to_str(), b.to_str()))
+            .await
+            .unwrap();
+        assert!(output.identical);
+        assert_eq!(output.diff, "");
+        assert_eq!(
+            (output.hunks, output.lines_added, output.lines_removed),
+            (0, 0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relative_paths_resolve_against_root() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("b.txt"), "two\n").unwrap();
+        let settings = ToolSettings {
+            root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let output = DiffTool::new(&settings)
+            .call(args(Some("a.txt"), Some("b.txt")))
+            .await
+            .unwrap();
+        assert_eq!(
+            (output.old.as_str(), output.new.as_str()),
+            ("a.txt", "b.txt")
+        );
+        assert_eq!((output.lines_added, output.lines_removed), (1, 1));
+
+        // 工作区根目录之外的文件不读取
+        let outside = TempDir::new().unwrap();
+        let other = outside.path().join("c.txt");
+        fs::write(&other, "three\n").unwrap();
+        let settings = ToolSettings {
+            roots: WorkspaceRoots::new(dir.path()),
+            ..settings
+        };
+        let error = DiffTool::new(&settings)
+            .call(args(Some("a.txt"), other.to_str()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_diff_against_git_ref() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        fs::write(root.join("gone.txt"), "bye\n").unwrap();
+        git(root, &["init", "-q"]);
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "init"]);
+
+        fs::write(root.join("src/lib.rs"), "pub fn a() {}\npub fn b() {}\n").unwrap();
+        fs::write(root.join("src/new.rs"), "pub fn c() {}\n").unwrap();
+        fs::remove_file(root.join("gone.txt")).unwrap();
+
+        let at_head = |path: &Path| DiffArgs {
+            path_a: None,
+            path_b: None,
+            path: Some(path.to_string_lossy().to_string()),
+            git_ref: Some("HEAD".to_string()),
+            context_lines: Some(0),
+        };
+
+        let modified = DiffTool::default()
+            .call(at_head(&root.join("src/lib.rs")))
+            .await
+            .unwrap();
+        assert_eq!((modified.lines_added, modified.lines_removed), (1, 0));
+        assert!(modified.diff.contains("+pub fn b() {}\n"));
+        assert!(modified.old.ends_with("lib.rs@HEAD"));
+        assert_eq!(modified.note, None);
+
+        let added = DiffTool::default()
+            .call(at_head(&root.join("src/new.rs")))
+            .await
+            .unwrap();
+        assert_eq!((added.lines_added, added.lines_removed), (1, 0));
+        assert!(added.note.unwrap().contains("does not exist at HEAD"));
+
+        let removed = DiffTool::default()
+            .call(at_head(&root.join("gone.txt")))
+            .await
+            .unwrap();
+        assert_eq!((removed.lines_added, removed.lines_removed), (0, 1));
+
+        let mut bad_ref = at_head(&root.join("src/lib.rs"));
+        bad_ref.git_ref = Some("no-such-branch".to_string());
+        assert!(DiffTool::default().call(bad_ref).await.is_err());
+    }
+
+    #[test]
+    fn test_truncate_diff() {
+        let mut text: String = (0..5000).map(|i| format!("+line {}\n", i)).collect();
+        assert!(truncate_diff(&mut text));
+        assert!(text.len() < MAX_DIFF_CHARS + 200);
+        assert!(text.ends_with("reduce context_lines\n"));
+        assert!(text.lines().rev().nth(1).unwrap().starts_with("+line "));
+    }
+}
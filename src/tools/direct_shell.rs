@@ -0,0 +1,221 @@
+//! `!` 前缀：用户直接执行 shell 命令
+//!
+//! 交互模式下以 `!` 开头的输入不发给模型，而是交给 shell_execute 执行（超时、沙箱、
+//! 脱敏和 trace 与模型调用时一致）。输出显示在终端，同时作为一条用户消息加入上下文，
+//! 模型在下一轮可以看到。`!!` 重新执行上一条直接命令。危险命令和命中 `[permissions.rules]`
+//! deny 规则的命令与模型调用时一样被拒绝；allow / ask 规则不适用，命令本来就是用户输入的。
+
+use rig::tool::Tool;
+use std::time::Instant;
+use tracing::Instrument;
+
+use super::redactor::redact_if_enabled;
+use super::settings::ToolSettings;
+use super::shell_execute::{ShellExecuteArgs, ShellExecuteOutput, ShellExecuteTool};
+use super::FileToolError;
+use crate::agent::hitl_gatekeeper::is_dangerous_command;
+use crate::permissions::{PermissionRules, RuleAction};
+use crate::telemetry;
+
+/// 加入上下文的 stdout / stderr 各自保留的最大字符数（保留末尾）
+pub const MAX_CONTEXT_CHARS: usize = 8_000;
+
+/// 解析后的 `!` 输入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectInput<'a> {
+    /// `!<command>`，命令为空表示只输入了 `!`
+    Command(&'a str),
+    /// `!!`
+    Repeat,
+}
+
+/// 解析 `!` 前缀，不是直接命令时返回 None
+pub fn parse(input: &str) -> Option<DirectInput<'_>> {
+    let rest = input.trim().strip_prefix('!')?;
+    if rest == "!" {
+        return Some(DirectInput::Repeat);
+    }
+    Some(DirectInput::Command(rest.trim()))
+}
+
+/// 危险命令和命中 `[permissions.rules]` deny 规则的命令返回 `PermissionDenied`
+///
+/// 规则有语法错误时同样拒绝，无法确认命令没有被禁止。
+pub fn check_allowed(command: &str) -> Result<(), FileToolError> {
+    let rules =
+        PermissionRules::load().map_err(|e| FileToolError::PermissionDenied(format!("{:#}", e)))?;
+    check_rules(command, &rules)
+}
+
+fn check_rules(command: &str, rules: &PermissionRules) -> Result<(), FileToolError> {
+    if is_dangerous_command(command) {
+        return Err(FileToolError::PermissionDenied(format!(
+            "'{}' matches the dangerous command list",
+            command
+        )));
+    }
+    let args = serde_json::json!({ "command": command });
+    if let Some((RuleAction::Deny, rule)) = rules.decide(ShellExecuteTool::NAME, &args) {
+        return Err(FileToolError::PermissionDenied(format!(
+            "'{}' is denied by the permission rule {}",
+            command, rule
+        )));
+    }
+    Ok(())
+}
+
+/// 通过 shell_execute 执行命令
+pub async fn run(
+    command: &str,
+    settings: &ToolSettings,
+) -> Result<ShellExecuteOutput, FileToolError> {
+    check_allowed(command)?;
+
+    let args = ShellExecuteArgs {
+        command: command.to_string(),
+        cwd: None,
+        run_in_background: false,
+    };
+    let span = telemetry::tool_span(
+        ShellExecuteTool::NAME,
+        &serde_json::json!({ "command": command, "direct": true }),
+    );
+    let start = Instant::now();
+    let result = ShellExecuteTool::new(settings)
+        .call(args)
+        .instrument(span.clone())
+        .await;
+    telemetry::record_tool_result(
+        &span,
+        start.elapsed(),
+        result.as_ref().is_ok_and(|output| output.success),
+    );
+    result
+}
+
+/// 加入上下文的用户消息：命令、退出码和（脱敏、截断后的）输出
+///
+/// 这条消息不是工具结果，不经过 `MaybeHitlTool`，在这里脱敏。
+pub fn context_message(output: &ShellExecuteOutput) -> String {
+    let status = match output.exit_code {
+        Some(code) => format!("exit code {}", code),
+        None => "no exit code".to_string(),
+    };
+    let mut message = format!(
+        "I ran this command in my shell ({}):\n$ {}\n",
+        status, output.command
+    );
+    for (tag, text) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        if !text.trim().is_empty() {
+            message.push_str(&format!(
+                "\n<{tag}>\n{}\n</{tag}>\n",
+                keep_tail(&redact_if_enabled(text).text, MAX_CONTEXT_CHARS)
+            ));
+        }
+    }
+    if output.stdout.trim().is_empty() && output.stderr.trim().is_empty() {
+        message.push_str("\n(no output)\n");
+    }
+    message.trim_end().to_string()
+}
+
+/// 超过 `max_chars` 时从某一行开头截取末尾部分，并注明省略的行数
+fn keep_tail(text: &str, max_chars: usize) -> String {
+    let text = text.trim_end();
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    let mut cut = text
+        .char_indices()
+        .nth(total - max_chars)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    if let Some(newline) = text[cut..].find('\n') {
+        cut += newline + 1;
+    }
+    format!(
+        "[... {} earlier lines omitted]\n{}",
+        text[..cut].lines().count(),
+        &text[cut..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(stdout: &str, stderr: &str, exit_code: Option<i32>) -> ShellExecuteOutput {
+        ShellExecuteOutput {
+            command: "cargo test".to_string(),
+            cwd: "/work".to_string(),
+            success: exit_code == Some(0),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            exit_code,
+            task_id: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("!cargo test"),
+            Some(DirectInput::Command("cargo test"))
+        );
+        assert_eq!(parse("  ! ls -la  "), Some(DirectInput::Command("ls -la")));
+        assert_eq!(parse("!!"), Some(DirectInput::Repeat));
+        assert_eq!(parse("!"), Some(DirectInput::Command("")));
+        // `!!` 后面还有内容时按普通命令处理
+        assert_eq!(parse("!!x"), Some(DirectInput::Command("!x")));
+        assert_eq!(parse("run cargo test!"), None);
+        assert_eq!(parse("/help"), None);
+    }
+
+    #[test]
+    fn test_context_message() {
+        let message = context_message(&output("ok\n", "", Some(0)));
+        assert_eq!(
+            message,
+            "I ran this command in my shell (exit code 0):\n$ cargo test\n\n<stdout>\nok\n</stdout>"
+        );
+
+        let message = context_message(&output("", "", None));
+        assert!(message.contains("(no exit code)"));
+        assert!(message.ends_with("(no output)"));
+
+        let long: String = (1..=5000).map(|i| format!("line {}\n", i)).collect();
+        let message = context_message(&output("", &long, Some(101)));
+        assert!(message.contains("<stderr>\n[... "));
+        assert!(message.contains("earlier lines omitted]\nline "));
+        assert!(message.ends_with("line 5000\n</stderr>"));
+        assert!(!message.contains("line 1\n"));
+        assert!(message.chars().count() < MAX_CONTEXT_CHARS + 200);
+    }
+
+    #[tokio::test]
+    async fn test_dangerous_command_is_blocked() {
+        let error = run("rm -rf ./oxide-direct-shell-test", &ToolSettings::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)));
+        assert!(check_rules("cargo test", &PermissionRules::default()).is_ok());
+    }
+
+    #[test]
+    fn test_deny_rule_blocks_command() {
+        let rules = PermissionRules::from_config(&crate::config::PermissionRulesConfig {
+            allow: vec!["shell_execute".to_string()],
+            deny: vec!["shell_execute(git push*)".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let error = check_rules("git push --force origin main", &rules).unwrap_err();
+        let FileToolError::PermissionDenied(reason) = error else {
+            panic!("expected PermissionDenied");
+        };
+        assert!(reason.contains("shell_execute(git push*)"), "{}", reason);
+        assert!(check_rules("git status", &rules).is_ok());
+    }
+}
@@ -0,0 +1,527 @@
+//! 同一替换的多处编辑合并确认
+//!
+//! “把这个函数改名”通常是先搜索，再对十几个文件分别调用 edit_file，每次都要确认一遍。
+//! 一次编辑如果只是把某段文本（通常是一个标识符）替换为另一段，就按（旧文本, 新文本）分组：
+//! 短时间内到达的同组编辑合并为一次确认，列出每个文件的 hunk 数和增删行数，可以展开查看 diff，
+//! 然后全部批准、挑选文件或全部拒绝。选择全部批准后，本轮中之后同一替换的编辑不再确认。
+
+use colored::*;
+use once_cell::sync::Lazy;
+use similar::{DiffOp, TextDiff};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use super::ask_user_question::{ask_question_interactive, Question, QuestionOption};
+use super::diff::unified_diff;
+use crate::agent::interaction;
+
+/// 第一次编辑到达后等待同组编辑的时间
+pub const BATCH_WINDOW: Duration = Duration::from_millis(300);
+
+static BATCHER: Lazy<EditBatcher> = Lazy::new(|| EditBatcher::new(BATCH_WINDOW));
+
+/// 一次编辑所做的替换（分组键）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Replacement {
+    pub old: String,
+    pub new: String,
+}
+
+/// 编辑只是把同一段文本替换为另一段时返回这个替换
+///
+/// 修改的每一行都要能由 `old` → `new` 的替换得到；增删整行的编辑不参与分组。
+pub fn replacement_key(original: &str, modified: &str) -> Option<Replacement> {
+    let diff = TextDiff::from_lines(original, modified);
+    let (old_lines, new_lines) = (diff.old_slices(), diff.new_slices());
+    let mut pairs = Vec::new();
+    for op in diff.ops() {
+        match *op {
+            DiffOp::Equal { .. } => {}
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } if old_len == new_len => {
+                for i in 0..old_len {
+                    pairs.push((old_lines[old_index + i], new_lines[new_index + i]));
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let (first_old, first_new) = pairs.first()?;
+    line_replacements(first_old, first_new)
+        .into_iter()
+        .find(|replacement| {
+            pairs
+                .iter()
+                .all(|(old, new)| old.replace(&replacement.old, &replacement.new) == *new)
+        })
+}
+
+/// 一行中可能的替换：先试第一处不同所在的标识符，再试去掉相同首尾后的整段（扩展到完整的标识符）
+fn line_replacements(old: &str, new: &str) -> Vec<Replacement> {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((i, _), _)| i)
+        .unwrap_or(old.len().min(new.len()));
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let start = old[..prefix]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(prefix);
+    let token_end = |line: &str, end: usize| {
+        end + line[end..]
+            .chars()
+            .take_while(|c| is_ident(*c))
+            .map(char::len_utf8)
+            .sum::<usize>()
+    };
+    let candidates = [
+        (token_end(old, prefix), token_end(new, prefix)),
+        (
+            token_end(old, old.len() - suffix),
+            token_end(new, new.len() - suffix),
+        ),
+    ];
+    candidates
+        .into_iter()
+        .map(|(old_end, new_end)| Replacement {
+            old: old[start..old_end].to_string(),
+            new: new[start..new_end].to_string(),
+        })
+        .filter(|replacement| !replacement.old.trim().is_empty())
+        .collect()
+}
+
+/// 等待确认的一次编辑
+#[derive(Debug, Clone)]
+pub struct PendingEdit {
+    pub file_path: String,
+    pub original: String,
+    pub modified: String,
+    pub hunks: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl PendingEdit {
+    pub fn new(file_path: &str, original: &str, modified: &str) -> Self {
+        let diff = unified_diff(original, modified, file_path, file_path, 3);
+        Self {
+            file_path: file_path.to_string(),
+            original: original.to_string(),
+            modified: modified.to_string(),
+            hunks: diff.hunks,
+            lines_added: diff.lines_added,
+            lines_removed: diff.lines_removed,
+        }
+    }
+}
+
+/// 用户对一组编辑的决定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchDecision {
+    /// 全部批准，本轮之后同一替换的编辑也不再确认
+    ApproveAll,
+    /// 只批准这些文件的编辑
+    Pick(Vec<String>),
+    /// 全部拒绝
+    Reject,
+}
+
+/// 一次编辑的确认结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchVerdict {
+    Approved,
+    /// 用户只挑选了同组的其他文件
+    Skipped,
+    Rejected,
+}
+
+impl BatchDecision {
+    fn verdict(&self, file_path: &str) -> BatchVerdict {
+        match self {
+            BatchDecision::ApproveAll => BatchVerdict::Approved,
+            BatchDecision::Pick(files) if files.iter().any(|f| f == file_path) => {
+                BatchVerdict::Approved
+            }
+            BatchDecision::Pick(_) => BatchVerdict::Skipped,
+            BatchDecision::Reject => BatchVerdict::Rejected,
+        }
+    }
+}
+
+struct Member {
+    edit: PendingEdit,
+    /// 组内第一次编辑负责提问，没有回复通道
+    reply: Option<oneshot::Sender<BatchVerdict>>,
+}
+
+/// 按替换分组等待确认的编辑
+pub struct EditBatcher {
+    window: Duration,
+    groups: Mutex<HashMap<Replacement, Vec<Member>>>,
+    /// 本轮已全部批准的替换
+    approved: Mutex<HashSet<Replacement>>,
+}
+
+impl EditBatcher {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            groups: Mutex::new(HashMap::new()),
+            approved: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 开始新一轮，之前全部批准的替换重新需要确认
+    pub fn reset(&self) {
+        self.approved
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// 确认一次编辑
+    ///
+    /// 组内第一次编辑等待 `window` 收集同组的编辑，然后调用一次 `decide`，
+    /// 其余编辑等它的结果。
+    pub async fn confirm<F, Fut>(
+        &self,
+        replacement: Replacement,
+        edit: PendingEdit,
+        decide: F,
+    ) -> BatchVerdict
+    where
+        F: FnOnce(Replacement, Vec<PendingEdit>) -> Fut,
+        Fut: Future<Output = BatchDecision>,
+    {
+        if self
+            .approved
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&replacement)
+        {
+            return BatchVerdict::Approved;
+        }
+
+        let follower = {
+            let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+            match groups.get_mut(&replacement) {
+                Some(members) => {
+                    let (reply, receiver) = oneshot::channel();
+                    members.push(Member {
+                        edit,
+                        reply: Some(reply),
+                    });
+                    Some(receiver)
+                }
+                None => {
+                    groups.insert(replacement.clone(), vec![Member { edit, reply: None }]);
+                    None
+                }
+            }
+        };
+        if let Some(receiver) = follower {
+            return receiver.await.unwrap_or(BatchVerdict::Rejected);
+        }
+
+        tokio::time::sleep(self.window).await;
+        let members = self
+            .groups
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&replacement)
+            .unwrap_or_default();
+        let edits = members.iter().map(|member| member.edit.clone()).collect();
+        let decision = decide(replacement.clone(), edits).await;
+        if decision == BatchDecision::ApproveAll {
+            self.approved
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(replacement);
+        }
+
+        let mut own = BatchVerdict::Rejected;
+        for member in members {
+            let verdict = decision.verdict(&member.edit.file_path);
+            match member.reply {
+                Some(reply) => {
+                    let _ = reply.send(verdict);
+                }
+                None => own = verdict,
+            }
+        }
+        own
+    }
+}
+
+/// 开始新一轮对话
+pub fn begin_turn() {
+    BATCHER.reset();
+}
+
+/// 在终端中确认一次替换编辑（与同组编辑合并）
+pub async fn confirm_edit(replacement: Replacement, edit: PendingEdit) -> BatchVerdict {
+    BATCHER
+        .confirm(replacement, edit, |replacement, edits| async move {
+            interaction::interact(move || ask(&replacement, &edits))
+                .await
+                .unwrap_or(BatchDecision::Reject)
+        })
+        .await
+}
+
+/// 显示合并后的预览并询问
+fn ask(replacement: &Replacement, edits: &[PendingEdit]) -> BatchDecision {
+    let approve_all = t!("edit.batch.approve_all");
+    let pick = t!("edit.batch.pick");
+    let show = t!("edit.batch.show_diffs");
+    let reject = t!("edit.batch.reject");
+
+    println!();
+    println!(
+        "{}",
+        t!(
+            "edit.batch.title",
+            old = replacement.old,
+            new = replacement.new,
+            files = edits.len()
+        )
+        .bright_cyan()
+        .bold()
+    );
+    if edits.len() == 1 {
+        println!();
+        super::edit_file::render_colored_diff(&edits[0].original, &edits[0].modified);
+    }
+    loop {
+        for edit in edits {
+            println!(
+                "  {} {}",
+                edit.file_path,
+                t!(
+                    "edit.batch.file_stats",
+                    hunks = edit.hunks,
+                    added = edit.lines_added,
+                    removed = edit.lines_removed
+                )
+                .dimmed()
+            );
+        }
+        println!();
+
+        let mut options = vec![QuestionOption {
+            label: approve_all.to_string(),
+            description: t!("edit.batch.approve_all_description").to_string(),
+        }];
+        if edits.len() > 1 {
+            options.extend([
+                QuestionOption {
+                    label: pick.to_string(),
+                    description: t!("edit.batch.pick_description").to_string(),
+                },
+                QuestionOption {
+                    label: show.to_string(),
+                    description: t!("edit.batch.show_diffs_description").to_string(),
+                },
+            ]);
+        }
+        options.push(QuestionOption {
+            label: reject.to_string(),
+            description: t!("edit.batch.reject_description").to_string(),
+        });
+        let question = Question {
+            question: t!("edit.batch.question").to_string(),
+            header: String::new(),
+            options,
+            multi_select: false,
+            allow_other: false,
+        };
+        let Ok(answer) = ask_question_interactive(&question) else {
+            return BatchDecision::Reject;
+        };
+        match answer.selected.as_str() {
+            Some(label) if label == approve_all => return BatchDecision::ApproveAll,
+            Some(label) if label == pick => return pick_files(edits),
+            Some(label) if label == show => {
+                for edit in edits {
+                    println!();
+                    println!("{}", edit.file_path.bold());
+                    super::edit_file::render_colored_diff(&edit.original, &edit.modified);
+                }
+            }
+            _ => return BatchDecision::Reject,
+        }
+    }
+}
+
+/// 多选要应用的文件
+fn pick_files(edits: &[PendingEdit]) -> BatchDecision {
+    let question = Question {
+        question: t!("edit.batch.pick_question").to_string(),
+        header: String::new(),
+        options: edits
+            .iter()
+            .map(|edit| QuestionOption {
+                label: edit.file_path.clone(),
+                description: t!(
+                    "edit.batch.file_stats",
+                    hunks = edit.hunks,
+                    added = edit.lines_added,
+                    removed = edit.lines_removed
+                ),
+            })
+            .collect(),
+        multi_select: true,
+        allow_other: false,
+    };
+    let files = match ask_question_interactive(&question).map(|answer| answer.selected) {
+        Ok(serde_json::Value::Array(labels)) => labels
+            .iter()
+            .filter_map(|label| label.as_str().map(str::to_string))
+            .collect(),
+        Ok(serde_json::Value::String(label)) => vec![label],
+        _ => Vec::new(),
+    };
+    if files.is_empty() {
+        BatchDecision::Reject
+    } else {
+        BatchDecision::Pick(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_replacement_key() {
+        let key = replacement_key(
+            "fn load_config() {}\nlet c = load_config();\n",
+            "fn read_config() {}\nlet c = read_config();\n",
+        );
+        assert_eq!(
+            key,
+            Some(Replacement {
+                old: "load_config".to_string(),
+                new: "read_config".to_string()
+            })
+        );
+
+        // 同一行出现两次
+        let key = replacement_key("foo(foo)\n", "bar(bar)\n").unwrap();
+        assert_eq!((key.old.as_str(), key.new.as_str()), ("foo", "bar"));
+
+        // 不同的改动、增删整行不分组
+        assert_eq!(
+            replacement_key("a = foo\nb = 1\n", "a = bar\nb = 2\n"),
+            None
+        );
+        assert_eq!(replacement_key("a\n", "a\nb\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_partial_approval_applies_chosen_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let batcher = EditBatcher::new(Duration::from_millis(50));
+        let paths: Vec<String> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| temp_dir.path().join(name).to_string_lossy().to_string())
+            .collect();
+        for path in &paths {
+            std::fs::write(path, "old_name();\n").unwrap();
+        }
+
+        // 模拟 agent 同时发出的三次编辑，用户只挑选 a 和 c
+        let picked = vec![paths[0].clone(), paths[2].clone()];
+        let edit = |path: &String| {
+            let batcher = &batcher;
+            let picked = picked.clone();
+            let path = path.clone();
+            async move {
+                let original = std::fs::read_to_string(&path).unwrap();
+                let modified = original.replace("old_name", "new_name");
+                let key = replacement_key(&original, &modified).unwrap();
+                let pending = PendingEdit::new(&path, &original, &modified);
+                let verdict = batcher
+                    .confirm(key, pending, |_, edits| async move {
+                        assert_eq!(edits.len(), 3);
+                        BatchDecision::Pick(picked)
+                    })
+                    .await;
+                if verdict == BatchVerdict::Approved {
+                    std::fs::write(&path, modified).unwrap();
+                }
+                verdict
+            }
+        };
+        let verdicts = tokio::join!(edit(&paths[0]), edit(&paths[1]), edit(&paths[2]));
+        assert_eq!(
+            verdicts,
+            (
+                BatchVerdict::Approved,
+                BatchVerdict::Skipped,
+                BatchVerdict::Approved
+            )
+        );
+        let contents: Vec<String> = paths
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        assert_eq!(
+            contents,
+            ["new_name();\n", "old_name();\n", "new_name();\n"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_approve_all_covers_later_edits_until_reset() {
+        let batcher = EditBatcher::new(Duration::from_millis(10));
+        let key = Replacement {
+            old: "x".to_string(),
+            new: "y".to_string(),
+        };
+        let edit = PendingEdit::new("a.rs", "x\n", "y\n");
+        let verdict = batcher
+            .confirm(key.clone(), edit.clone(), |_, _| async {
+                BatchDecision::ApproveAll
+            })
+            .await;
+        assert_eq!(verdict, BatchVerdict::Approved);
+
+        // 之后同一替换的编辑不再询问
+        let verdict = batcher
+            .confirm(key.clone(), edit.clone(), |_, _| async {
+                panic!("should not ask again")
+            })
+            .await;
+        assert_eq!(verdict, BatchVerdict::Approved);
+
+        batcher.reset();
+        let verdict = batcher
+            .confirm(key, edit, |_, _| async { BatchDecision::Reject })
+            .await;
+        assert_eq!(verdict, BatchVerdict::Rejected);
+    }
+}
@@ -1,9 +1,23 @@
-use super::FileToolError;
+use super::apply_patch::parse_patch;
+use super::edit_batch::{self, replacement_key, BatchVerdict, PendingEdit};
+use super::format_on_write::{format_after_write, print_result, FormatResult};
+use super::line_endings::{to_lf, LineEndingInfo};
+use super::partial_patch::{
+    any_skipped, apply_partial, summarize, AskUser, HunkReport, HunkResolver, HunkStatus,
+    SkipFailing,
+};
+use super::safe_write::write_with_backup;
+use super::settings::ToolSettings;
+use super::uncommitted::get_overwrite_guard;
+use super::verbose_output::verbose_tools;
+use super::{with_note, ErrorCode, FileToolError, ToolFailure};
+use crate::agent::interaction::interact;
 use colored::*;
 use diffy::{apply, Patch};
 use super::ask_user_question::{ask_question_interactive, Question, QuestionOption};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use similar::{TextDiff};
 use std::borrow::Cow;
 use std::env;
@@ -20,7 +34,7 @@ fn preview_enabled() -> bool {
 }
 
 /// 渲染带颜色的 diff
-fn render_colored_diff(original: &str, modified: &str) {
+pub(super) fn render_colored_diff(original: &str, modified: &str) {
     let diff = TextDiff::from_lines(original, modified);
 
     for ops in diff.grouped_ops(3) {
@@ -56,19 +70,20 @@ fn request_confirmation(
     );
 
     let default_question = Question {
-        question: format!("确认应用此修改？{}", stats),
+        question: t!("edit.confirm.question", stats = stats),
         header: "".to_string(),
         options: vec![
             QuestionOption {
-                label: "是".to_string(),
-                description: "应用当前修改".to_string(),
+                label: t!("common.yes").to_string(),
+                description: t!("edit.confirm.apply").to_string(),
             },
             QuestionOption {
-                label: "否".to_string(),
-                description: "取消本次修改".to_string(),
+                label: t!("common.no").to_string(),
+                description: t!("edit.confirm.cancel").to_string(),
             },
         ],
         multi_select: false,
+        allow_other: false,
     };
 
     let question = confirmation.cloned().unwrap_or(default_question);
@@ -76,7 +91,7 @@ fn request_confirmation(
         .options
         .first()
         .map(|opt| opt.label.clone())
-        .unwrap_or_else(|| "是".to_string());
+        .unwrap_or_else(|| t!("common.yes").to_string());
 
     let answer = ask_question_interactive(&question)?;
     match answer.selected {
@@ -88,42 +103,29 @@ fn request_confirmation(
     }
 }
 
+/// 合并确认时用户没有挑选这个文件（不按取消处理，本轮继续）
+fn not_selected(file_path: &str) -> FileToolError {
+    ToolFailure::new(
+        ErrorCode::Blocked,
+        format!("The user did not select '{}' for this change", file_path),
+    )
+    .with_suggestion("the user applied the same change to other files only; do not retry this file")
+    .into()
+}
+
 fn build_parse_error<E: std::fmt::Display>(e: E, patch_str: &str) -> FileToolError {
     // 提取 patch 的前几行用于诊断
     let preview_lines: Vec<&str> = patch_str.lines().take(20).collect();
-    let patch_preview = preview_lines.join("\n");
-
-    let error_msg = format!(
-        "Failed to parse patch: {}\n\n\
-         ═══════════════════════════════════════════════════════════\n\
-         🔍 Patch 解析失败 - 诊断信息:\n\
-         ═══════════════════════════════════════════════════════════\n\
-         \n\
-         常见原因:\n\
-         1. ❌ Hunk header 格式错误\n\
-            正确格式: @@ -line_count,count +line_count,count @@\n\
-         2. ❌ 缺少足够的上下文行（推荐 3 行）\n\
-         3. ❌ 行号不准确（文件内容可能已改变）\n\
-         4. ❌ 缩进不匹配（空格/制表符）\n\
-         5. ❌ 缺少 ---/+++ 文件头\n\
-         \n\
-         📋 Patch 内容预览（前 20 行）:\n\
-         ─────────────────────────────────────────────────────────────\n\
-         {}\n\
-         ─────────────────────────────────────────────────────────────\n\
-         \n\
-         💡 建议:\n\
-         - 检查 hunk header 中的行号是否准确\n\
-         - 确保包含足够的上下文行\n\
-         - 使用 Read 工具确认当前文件内容\n\
-         - 或考虑使用 search-replace 格式代替 unified diff",
-        e, patch_preview
-    );
 
-    FileToolError::Io(std::io::Error::new(
-        std::io::ErrorKind::InvalidData,
-        error_msg,
-    ))
+    ToolFailure::new(
+        ErrorCode::InvalidArgs,
+        format!("Failed to parse patch: {}", e),
+    )
+    .with_details(json!({ "patch_preview": preview_lines.join("\n") }))
+    .with_suggestion(
+        "check the ---/+++ headers and the `@@ -start,count +start,count @@` hunk headers, or use search_replace instead",
+    )
+    .into()
 }
 
 fn parse_range(range: &str) -> Option<(usize, usize)> {
@@ -232,6 +234,9 @@ pub struct EditFileArgs {
     pub patch: String,
     #[serde(default)]
     pub confirmation: Option<Question>,
+    /// 只应用能匹配的 hunk，其余的跳过（交互时由用户处理）
+    #[serde(default)]
+    pub partial: bool,
 }
 
 #[derive(Serialize, Debug)]
@@ -243,10 +248,15 @@ pub struct EditFileOutput {
     pub message: String,
     /// 预览内容（如果生成了的话）
     pub preview: Option<String>,
+    /// 部分应用时每个 hunk 的处理结果
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<HunkReport>,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct EditFileTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct EditFileTool {
+    settings: ToolSettings,
+}
 
 impl Tool for EditFileTool {
     const NAME: &'static str = "edit_file";
@@ -340,7 +350,13 @@ impl Tool for EditFileTool {
 2. 复制文件中的确切内容作为上下文（包括缩进）
 3. 小修改（< 10 行）使用 edit_file
 4. 大修改（≥ 10 行）考虑使用 write_file
-5. 如果 patch 应用失败，检查错误提示中的诊断信息
+5. 如果 patch 应用失败，按错误中的 suggestion 处理
+6. 多个 hunk 中只有个别不匹配时，设置 partial=true 只应用匹配的 hunk，
+   结果的 hunks 列出每个 hunk 的处理结果，之后只需重新生成被跳过的 hunk
+
+错误码：NotFound（文件不存在）、InvalidArgs（补丁格式错误或路径是目录）、
+Stale（补丁上下文与文件内容不一致，重新 Read 后再生成）、PermissionDenied、
+Blocked（用户拒绝了修改）
 "#.trim().to_string(),
             parameters: serde_json::json!({
                 "type": "object",
@@ -389,6 +405,10 @@ impl Tool for EditFileTool {
                             }
                         },
                         "required": ["question", "header", "options", "multi_select"]
+                    },
+                    "partial": {
+                        "type": "boolean",
+                        "description": "When true, apply the hunks that match and skip the ones that don't instead of failing the whole patch (in an interactive session the user decides what to do with each failing hunk). The result lists every hunk as applied, skipped or retargeted. Default: false."
                     }
                 },
                 "required": ["file_path", "patch"]
@@ -396,22 +416,53 @@ impl Tool for EditFileTool {
         }
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let (_current_content, patched_content, lines_added, lines_removed) =
-            Self::apply_patch_internal(&args.file_path, &args.patch)?;
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        args.file_path = self.settings.resolve_path(&args.file_path);
+        let (current_content, patched_content, lines_added, lines_removed, hunks) = if args.partial
+        {
+            let applied =
+                Self::apply_partial_internal(&args.file_path, &args.patch, &mut SkipFailing)?;
+            if applied.4.iter().all(|h| h.status == HunkStatus::Skipped) {
+                return Err(Self::nothing_applied(&args.file_path, applied.4));
+            }
+            applied
+        } else {
+            let (current_content, patched_content, lines_added, lines_removed) =
+                Self::apply_patch_internal(&args.file_path, &args.patch)?;
+            (
+                current_content,
+                patched_content,
+                lines_added,
+                lines_removed,
+                Vec::new(),
+            )
+        };
+        let note = LineEndingInfo::detect(&current_content).mixed_note();
+        let message = if hunks.is_empty() {
+            format!(
+                "Successfully applied patch to '{}': +{} lines, -{} lines",
+                args.file_path, lines_added, lines_removed
+            )
+        } else {
+            format!(
+                "Applied patch to '{}' ({}): +{} lines, -{} lines",
+                args.file_path,
+                summarize(&hunks),
+                lines_added,
+                lines_removed
+            )
+        };
 
         // Write the modified content back to the file
-        match fs::write(&args.file_path, &patched_content) {
+        match write_with_backup(Path::new(&args.file_path), patched_content.as_bytes()) {
             Ok(()) => Ok(EditFileOutput {
                 file_path: args.file_path.clone(),
                 lines_added,
                 lines_removed,
                 success: true,
-                message: format!(
-                    "Successfully applied patch to '{}': +{} lines, -{} lines",
-                    args.file_path, lines_added, lines_removed
-                ),
+                message: with_note(message, note),
                 preview: None,
+                hunks,
             }),
             Err(e) => match e.kind() {
                 std::io::ErrorKind::PermissionDenied => {
@@ -424,6 +475,12 @@ impl Tool for EditFileTool {
 }
 
 impl EditFileTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
     /// 内部方法：应用补丁并返回所有中间结果
     /// 返回 (原始内容, 修改后内容, 新增行数, 删除行数)
     fn apply_patch_internal(
@@ -445,11 +502,16 @@ impl EditFileTool {
         // Read the current file content
         let current_content = fs::read_to_string(file_path)?;
 
+        // 在 LF 文本上应用补丁，写回时恢复原有行尾和末尾换行
+        let line_endings = LineEndingInfo::detect(&current_content);
+        let normalized_content = line_endings.normalize(&current_content);
+
         // Ensure patch_str ends with a newline
-        let patch_str_normalized = if !patch_str.ends_with('\n') {
-            Cow::Owned(format!("{}\n", patch_str))
+        let patch_lf = to_lf(patch_str);
+        let patch_str_normalized = if !patch_lf.ends_with('\n') {
+            Cow::Owned(format!("{}\n", patch_lf))
         } else {
-            Cow::Borrowed(patch_str)
+            Cow::Borrowed(patch_lf.as_str())
         };
 
         // Parse the patch using diffy (with repair for bad hunk counts)
@@ -458,39 +520,19 @@ impl EditFileTool {
             .map_err(|e| build_parse_error(e, patch_str_used.as_ref()))?;
 
         // Apply the patch using diffy::apply
-        let patched_content = apply(&current_content, &patch).map_err(|e| {
-            // 计算文件行数用于诊断
-            let file_lines: Vec<&str> = current_content.lines().collect();
-            let total_lines = file_lines.len();
-
-            let error_msg = format!(
-                "Failed to apply patch: {}\n\n\
-                 ═══════════════════════════════════════════════════════════\n\
-                 ❌ Patch 应用失败 - 诊断信息:\n\
-                 ═══════════════════════════════════════════════════════════\n\
-                 \n\
-                 文件信息:\n\
-                 - 文件: {}\n\
-                 - 总行数: {}\n\
-                 \n\
-                 常见原因:\n\
-                 1. ❌ Hunk header 中的行号超出文件范围\n\
-                 2. ❌ 上下文内容与文件实际内容不匹配\n\
-                 3. ❌ 文件内容在生成 patch 后已被修改\n\
-                 4. ❌ 缩进或空格不匹配\n\
-                 \n\
-                 💡 建议:\n\
-                 - 使用 Read 工具重新读取文件，确认当前内容\n\
-                 - 检查 patch 中的上下文行是否与文件完全一致\n\
-                 - 确认 hunk header 的行号在有效范围内 (1-{})\n\
-                 - 如果文件最近被修改过，需要重新生成 patch",
-                e, file_path, total_lines, total_lines
-            );
-
-            FileToolError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                error_msg,
-            ))
+        let patched_content = apply(&normalized_content, &patch).map_err(|e| {
+            // 文件行数用于诊断 hunk header 是否越界
+            let total_lines = normalized_content.lines().count();
+            FileToolError::from(
+                ToolFailure::new(
+                    ErrorCode::Stale,
+                    format!("Failed to apply patch to '{}': {}", file_path, e),
+                )
+                .with_details(json!({ "file": file_path, "total_lines": total_lines }))
+                .with_suggestion(
+                    "the patch context does not match the file; call read_file again and regenerate the patch, or retry with partial=true to apply the hunks that still match",
+                ),
+            )
         })?;
 
         // Calculate statistics
@@ -506,9 +548,77 @@ impl EditFileTool {
             }
         }
 
+        let patched_content = line_endings.restore(&patched_content);
+
         Ok((current_content, patched_content, lines_added, lines_removed))
     }
 
+    /// 内部方法：部分应用补丁，不匹配的 hunk 交给 `resolver` 处理
+    /// 返回 (原始内容, 修改后内容, 新增行数, 删除行数, 每个 hunk 的结果)
+    fn apply_partial_internal(
+        file_path: &str,
+        patch_str: &str,
+        resolver: &mut dyn HunkResolver,
+    ) -> Result<(String, String, usize, usize, Vec<HunkReport>), FileToolError> {
+        let path = Path::new(file_path);
+        if !path.exists() {
+            return Err(FileToolError::FileNotFound(file_path.to_string()));
+        }
+        if !path.is_file() {
+            return Err(FileToolError::NotAFile(file_path.to_string()));
+        }
+        let current_content = fs::read_to_string(file_path)?;
+
+        // 与 diffy 一样接受没有 ---/+++ 文件头的补丁
+        let patch_lf = to_lf(patch_str);
+        let patch_str = if patch_lf.lines().any(|line| line.starts_with("+++ ")) {
+            patch_lf
+        } else {
+            format!("--- a/{0}\n+++ b/{0}\n{1}", file_path, patch_lf)
+        };
+        let files = parse_patch(&patch_str)?;
+        let [file_patch] = files.as_slice() else {
+            return Err(FileToolError::InvalidInput(format!(
+                "Patch for '{}' contains {} file sections; edit_file expects exactly one (use apply_patch for several files)",
+                file_path,
+                files.len()
+            )));
+        };
+
+        let line_endings = LineEndingInfo::detect(&current_content);
+        let normalized_content = line_endings.normalize(&current_content);
+        let original_lines: Vec<&str> = normalized_content.lines().collect();
+        let result = apply_partial(file_path, &original_lines, &file_patch.hunks, resolver);
+
+        let patched_content = if result.lines.is_empty() {
+            String::new()
+        } else {
+            line_endings.restore(&format!("{}\n", result.lines.join("\n")))
+        };
+        Ok((
+            current_content,
+            patched_content,
+            result.lines_added,
+            result.lines_removed,
+            result.reports,
+        ))
+    }
+
+    /// 所有 hunk 都被跳过时返回给模型的错误
+    fn nothing_applied(file_path: &str, hunks: Vec<HunkReport>) -> FileToolError {
+        ToolFailure::new(
+            ErrorCode::Stale,
+            format!(
+                "None of the {} hunks match '{}'; the file was not changed",
+                hunks.len(),
+                file_path
+            ),
+        )
+        .with_details(json!({ "file": file_path, "hunks": hunks }))
+        .with_suggestion("call read_file again and regenerate the patch")
+        .into()
+    }
+
     /// 预览补丁（不实际应用）
     /// 返回 (原始内容, 修改后内容, 新增行数, 删除行数, 补丁字符串)
     pub async fn preview_patch(&self, args: &EditFileArgs) -> Result<(String, String, usize, usize, String), FileToolError> {
@@ -524,6 +634,48 @@ impl EditFileTool {
 
         Ok((current_content, patched_content, lines_added, lines_removed, preview))
     }
+
+    /// 预览部分应用的补丁（不实际应用）
+    ///
+    /// 先跳过不匹配的 hunk 试应用一次；有被跳过的 hunk 且有终端时，重新应用并逐个询问用户。
+    /// 返回 (原始内容, 修改后内容, 新增行数, 删除行数, 补丁字符串, 每个 hunk 的结果)
+    pub async fn preview_partial(
+        &self,
+        args: &EditFileArgs,
+    ) -> Result<(String, String, usize, usize, String, Vec<HunkReport>), FileToolError> {
+        let dry_run = Self::apply_partial_internal(&args.file_path, &args.patch, &mut SkipFailing)?;
+        let (current_content, patched_content, lines_added, lines_removed, hunks) =
+            if any_skipped(&dry_run.4) {
+                let (file_path, patch) = (args.file_path.clone(), args.patch.clone());
+                match interact(move || {
+                    Self::apply_partial_internal(&file_path, &patch, &mut AskUser)
+                })
+                .await
+                {
+                    Ok(resolved) => resolved?,
+                    Err(_) => dry_run,
+                }
+            } else {
+                dry_run
+            };
+        if hunks.iter().all(|h| h.status == HunkStatus::Skipped) {
+            return Err(Self::nothing_applied(&args.file_path, hunks));
+        }
+
+        let preview = if args.patch.ends_with('\n') {
+            args.patch.clone()
+        } else {
+            format!("{}\n", args.patch)
+        };
+        Ok((
+            current_content,
+            patched_content,
+            lines_added,
+            lines_removed,
+            preview,
+            hunks,
+        ))
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -532,9 +684,9 @@ pub struct WrappedEditFileTool {
 }
 
 impl WrappedEditFileTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: EditFileTool,
+            inner: EditFileTool::new(settings),
         }
     }
 }
@@ -550,29 +702,94 @@ impl Tool for WrappedEditFileTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Edit", args.file_path);
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "Edit", args.file_path);
+        args.file_path = self.inner.settings.resolve_path(&args.file_path);
+
+        // 没读过的文件有未提交的修改时先让用户确认（关闭预览时同样检查）
+        if let Err(e) = get_overwrite_guard().confirm(&args.file_path) {
+            tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            tool_println!();
+            return Err(e);
+        }
 
         // 检查是否启用预览
         if preview_enabled() {
-            // 生成预览
-            match self.inner.preview_patch(&args).await {
-                Ok((current_content, patched_content, lines_added, lines_removed, preview)) => {
-                    // 显示预览
-                    println!();
-                    println!("{}", "📋 即将应用以下修改:".bright_cyan().bold());
-                    println!();
-                    render_colored_diff(&current_content, &patched_content);
-                    println!();
-
-                    // 请求用户确认
-                    match request_confirmation(lines_added, lines_removed, args.confirmation.as_ref()) {
+            // 生成预览（部分应用时先处理不匹配的 hunk）
+            let previewed = if args.partial {
+                self.inner.preview_partial(&args).await
+            } else {
+                self.inner.preview_patch(&args).await.map(
+                    |(current_content, patched_content, lines_added, lines_removed, preview)| {
+                        (
+                            current_content,
+                            patched_content,
+                            lines_added,
+                            lines_removed,
+                            preview,
+                            Vec::new(),
+                        )
+                    },
+                )
+            };
+            match previewed {
+                Ok((
+                    current_content,
+                    patched_content,
+                    lines_added,
+                    lines_removed,
+                    preview,
+                    hunks,
+                )) => {
+                    // 只是替换同一段文本的编辑与同组的其他文件合并确认
+                    let replacement = replacement_key(&current_content, &patched_content)
+                        .filter(|_| args.confirmation.is_none() && hunks.is_empty());
+                    let approval = match replacement {
+                        Some(replacement) => {
+                            let pending = PendingEdit::new(
+                                &args.file_path,
+                                &current_content,
+                                &patched_content,
+                            );
+                            match edit_batch::confirm_edit(replacement, pending).await {
+                                BatchVerdict::Approved => Ok(true),
+                                BatchVerdict::Rejected => Ok(false),
+                                BatchVerdict::Skipped => {
+                                    tool_println!(
+                                        "  └─ {}",
+                                        t!("edit.batch.skipped").bright_yellow()
+                                    );
+                                    tool_println!();
+                                    return Err(not_selected(&args.file_path));
+                                }
+                            }
+                        }
+                        None => {
+                            // 显示预览
+                            if !verbose_tools().is_hidden() {
+                                println!();
+                                println!("{}", t!("edit.preview_title").bright_cyan().bold());
+                                println!();
+                                render_colored_diff(&current_content, &patched_content);
+                                println!();
+                            }
+
+                            // 请求用户确认
+                            request_confirmation(
+                                lines_added,
+                                lines_removed,
+                                args.confirmation.as_ref(),
+                            )
+                        }
+                    };
+
+                    match approval {
                         Ok(true) => {
                             // 用户确认，应用修改
-                            if let Err(e) = fs::write(&args.file_path, &patched_content) {
-                                println!("  └─ {}", format!("Error: {}", e).red());
-                                println!();
+                            if let Err(e) = write_with_backup(Path::new(&args.file_path), patched_content.as_bytes()) {
+                                tool_println!("  └─ {}", format!("Error: {}", e).red());
+                                tool_println!();
                                 return match e.kind() {
                                     std::io::ErrorKind::PermissionDenied => {
                                         Err(FileToolError::PermissionDenied(args.file_path.clone()))
@@ -581,63 +798,112 @@ impl Tool for WrappedEditFileTool {
                                 };
                             }
 
-                            println!(
+                            tool_println!(
                                 "  └─ {} (+{} lines, -{} lines)",
                                 format!("Patched '{}'", args.file_path).dimmed(),
                                 lines_added.to_string().green(),
                                 lines_removed.to_string().red()
                             );
-                            println!();
-
+                            if !hunks.is_empty() {
+                                tool_println!("  └─ {}", summarize(&hunks).yellow());
+                            }
+                            get_overwrite_guard().record_seen(Path::new(&args.file_path));
+                            let format = format_after_write(Path::new(&args.file_path)).await;
+                            if let Some(format) = &format {
+                                print_result(format);
+                                // 确认的是格式化前的 diff，格式化另有改动时补充显示
+                                if let FormatResult::Formatted { before, after, .. } = format {
+                                    if before != after && !verbose_tools().is_hidden() {
+                                        println!();
+                                        render_colored_diff(before, after);
+                                    }
+                                }
+                            }
+                            tool_println!();
+
+                            let applied = if hunks.is_empty() {
+                                t!(
+                                    "edit.applied",
+                                    path = args.file_path,
+                                    added = lines_added,
+                                    removed = lines_removed
+                                )
+                            } else {
+                                t!(
+                                    "edit.applied_hunks",
+                                    path = args.file_path,
+                                    hunks = summarize(&hunks),
+                                    added = lines_added,
+                                    removed = lines_removed
+                                )
+                            };
+                            let message = with_note(
+                                applied,
+                                LineEndingInfo::detect(&current_content).mixed_note(),
+                            );
                             Ok(EditFileOutput {
                                 file_path: args.file_path.clone(),
                                 lines_added,
                                 lines_removed,
                                 success: true,
-                                message: format!(
-                                    "已应用修改到 '{}': +{} 行, -{} 行",
-                                    args.file_path, lines_added, lines_removed
-                                ),
+                                message: with_note(message, format.and_then(|f| f.note())),
                                 preview: Some(preview),
+                                hunks,
                             })
                         }
                         Ok(false) => {
                             // 用户取消
-                            println!("  └─ {}", "修改已取消".bright_yellow());
-                            println!();
+                            tool_println!("  └─ {}", t!("edit.cancelled").bright_yellow());
+                            tool_println!();
                             Err(FileToolError::Cancelled)
                         }
                         Err(e) => {
-                            println!("  └─ {}", format!("读取输入错误: {}", e).red());
-                            println!();
+                            tool_println!("  └─ {}", t!("edit.input_error", error = e).red());
+                            tool_println!();
                             Err(e)
                         }
                     }
                 }
                 Err(e) => {
-                    println!("  └─ {}", format!("预览失败: {}", e).red());
-                    println!();
+                    tool_println!("  └─ {}", t!("edit.preview_failed", error = e).red());
+                    tool_println!();
                     Err(e)
                 }
             }
         } else {
             // 不启用预览，直接应用
-            let result = self.inner.call(args).await;
+            let mut result = self.inner.call(args).await;
+            let format = match &mut result {
+                Ok(output) => {
+                    let format = format_after_write(Path::new(&output.file_path)).await;
+                    let note = format.as_ref().and_then(|f| f.note());
+                    output.message = with_note(std::mem::take(&mut output.message), note);
+                    format
+                }
+                Err(_) => None,
+            };
 
             match &result {
                 Ok(output) => {
-                    println!(
+                    tool_println!(
                         "  └─ {} (+{} lines, -{} lines)",
                         format!("Patched '{}'", output.file_path).dimmed(),
                         output.lines_added.to_string().green(),
                         output.lines_removed.to_string().red()
                     );
+                    if !output.hunks.is_empty() {
+                        tool_println!("  └─ {}", summarize(&output.hunks).yellow());
+                    }
+                    get_overwrite_guard().record_seen(Path::new(&output.file_path));
+                    if let Some(format) = &format {
+                        print_result(format);
+                    }
                 }
                 Err(e) => {
-                    println!("  └─ {}", format!("Error: {}", e).red());
+                    tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
                 }
             }
-            println!();
+            tool_println!();
             result
         }
     }
@@ -657,7 +923,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_preview_patch() {
-        let tool = EditFileTool;
+        let tool = EditFileTool::default();
 
         // 创建临时测试文件
         let temp_file = NamedTempFile::new().unwrap();
@@ -673,6 +939,7 @@ mod tests {
  line 3
 ".to_string(),
             confirmation: None,
+            partial: false,
         };
 
         let result = tool.preview_patch(&args).await;
@@ -697,7 +964,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_preview_patch_repairs_hunk_counts() {
-        let tool = EditFileTool;
+        let tool = EditFileTool::default();
 
         let temp_file = NamedTempFile::new().unwrap();
         let test_path = temp_file.path().to_path_buf();
@@ -713,6 +980,7 @@ mod tests {
  line 3
 ".to_string(),
             confirmation: None,
+            partial: false,
         };
 
         let result = tool.preview_patch(&args).await;
@@ -750,7 +1018,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_preview_patch_file_not_found() {
-        let tool = EditFileTool;
+        let tool = EditFileTool::default();
 
         let args = EditFileArgs {
             file_path: "/nonexistent/file.rs".to_string(),
@@ -759,6 +1027,7 @@ mod tests {
 +new
 ".to_string(),
             confirmation: None,
+            partial: false,
         };
 
         let result = tool.preview_patch(&args).await;
@@ -774,7 +1043,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_preview_patch_invalid_patch() {
-        let tool = EditFileTool;
+        let tool = EditFileTool::default();
 
         // 创建临时文件
         let temp_file = NamedTempFile::new().unwrap();
@@ -791,6 +1060,7 @@ mod tests {
 +line 11 modified
 ".to_string(),
             confirmation: None,
+            partial: false,
         };
 
         let result = tool.preview_patch(&args).await;
@@ -807,4 +1077,206 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_stale_patch_failure() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let test_path = temp_file.path().to_str().unwrap().to_string();
+        fs::write(&test_path, "alpha\nbeta\ngamma\n").unwrap();
+
+        let error = EditFileTool::default()
+            .call(EditFileArgs {
+                file_path: test_path.clone(),
+                patch: "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n alpha\n-delta\n+DELTA\n gamma\n"
+                    .to_string(),
+                confirmation: None,
+                partial: false,
+            })
+            .await
+            .unwrap_err();
+
+        let failure = error.failure();
+        assert_eq!(failure.code, ErrorCode::Stale);
+        assert!(failure.retryable);
+        assert_eq!(failure.details.unwrap()["total_lines"], 3);
+        assert!(failure.suggestion.unwrap().contains("read_file"));
+        assert_eq!(
+            fs::read_to_string(&test_path).unwrap(),
+            "alpha\nbeta\ngamma\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_partial_skips_stale_hunk() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let test_path = temp_file.path().to_str().unwrap().to_string();
+        fs::write(&test_path, "a\nb\nc\nd\ne\nf\ng\nh\ni\n").unwrap();
+
+        // 第二个 hunk 的上下文在文件中不存在
+        let patch = "@@ -1,2 +1,2 @@\n-a\n+A\n b\n\
+                     @@ -4,2 +4,2 @@\n x\n-y\n+Y\n\
+                     @@ -8,2 +8,2 @@\n h\n-i\n+I\n";
+        let output = EditFileTool::default()
+            .call(EditFileArgs {
+                file_path: test_path.clone(),
+                patch: patch.to_string(),
+                confirmation: None,
+                partial: true,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&test_path).unwrap(),
+            "A\nb\nc\nd\ne\nf\ng\nh\nI\n"
+        );
+        assert_eq!((output.lines_added, output.lines_removed), (2, 2));
+        let statuses: Vec<HunkStatus> = output.hunks.iter().map(|h| h.status).collect();
+        assert_eq!(statuses[1], HunkStatus::Skipped);
+        assert!(output
+            .message
+            .contains("2 of 3 hunks applied; skipped hunk 2 (@@ -4)"));
+
+        // 没有 hunk 能应用时按 Stale 失败，文件不变
+        let failure = EditFileTool::default()
+            .call(EditFileArgs {
+                file_path: test_path.clone(),
+                patch: "@@ -4,2 +4,2 @@\n x\n-y\n+Y\n".to_string(),
+                confirmation: None,
+                partial: true,
+            })
+            .await
+            .unwrap_err()
+            .failure();
+        assert_eq!(failure.code, ErrorCode::Stale);
+        assert_eq!(failure.details.unwrap()["hunks"][0]["status"], "skipped");
+    }
+
+    /// 写入内容到临时文件，调用 EditFileTool 并返回写回后的内容和输出消息
+    async fn edit(content: &str, patch: &str) -> (String, String) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let test_path = temp_file.path().to_str().unwrap().to_string();
+        fs::write(&test_path, content).unwrap();
+
+        let output = EditFileTool::default()
+            .call(EditFileArgs {
+                file_path: test_path.clone(),
+                patch: patch.to_string(),
+                confirmation: None,
+                partial: false,
+            })
+            .await
+            .unwrap();
+        (fs::read_to_string(&test_path).unwrap(), output.message)
+    }
+
+    #[tokio::test]
+    async fn test_crlf_file_with_lf_patch() {
+        let (content, message) = edit(
+            "line 1\r\nline 2\r\nline 3\r\n",
+            "@@ -1,3 +1,3 @@\n line 1\n-line 2\n+line 2 modified\n line 3\n",
+        )
+        .await;
+        assert_eq!(content, "line 1\r\nline 2 modified\r\nline 3\r\n");
+        assert!(!message.contains("mixed"));
+    }
+
+    #[tokio::test]
+    async fn test_lf_file_with_crlf_patch() {
+        let (content, _) = edit(
+            "line 1\nline 2\nline 3\n",
+            "@@ -1,3 +1,3 @@\r\n line 1\r\n-line 2\r\n+line 2 modified\r\n line 3\r\n",
+        )
+        .await;
+        assert_eq!(content, "line 1\nline 2 modified\nline 3\n");
+    }
+
+    #[tokio::test]
+    async fn test_file_without_trailing_newline() {
+        let (content, _) = edit(
+            "line 1\r\nline 2",
+            "@@ -1,2 +1,2 @@\n line 1\n-line 2\n+line 2 modified\n",
+        )
+        .await;
+        assert_eq!(content, "line 1\r\nline 2 modified");
+    }
+
+    #[tokio::test]
+    async fn test_mixed_line_endings_reported() {
+        let (content, message) = edit(
+            "line 1\r\nline 2\r\nline 3\n",
+            "@@ -1,3 +1,3 @@\n-line 1\n+line 1 modified\n line 2\n line 3\n",
+        )
+        .await;
+        assert_eq!(content, "line 1 modified\r\nline 2\r\nline 3\r\n");
+        assert!(message.contains("mixed line endings (2 CRLF, 1 LF)"), "{}", message);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_write_keeps_original() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let test_path = temp_dir.path().join("locked.txt");
+        let original = "line 1\r\nline 2\r\n";
+        fs::write(&test_path, original).unwrap();
+
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        let result = EditFileTool::default()
+            .call(EditFileArgs {
+                file_path: test_path.to_str().unwrap().to_string(),
+                patch: "@@ -1,2 +1,2 @@\n-line 1\n+line 1 modified\n line 2\n".to_string(),
+                confirmation: None,
+                partial: false,
+            })
+            .await;
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+
+        // root 不受目录权限限制，此时无法模拟失败
+        if result.is_ok() {
+            return;
+        }
+        assert_eq!(fs::read(&test_path).unwrap(), original.as_bytes());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relative_paths_resolve_under_tools_root() {
+        use crate::tools::read_file::{ReadFileArgs, ReadFileTool};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().join("pkg");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        let settings = ToolSettings {
+            root: Some(root.clone()),
+            ..ToolSettings::default()
+        };
+
+        // 读取和编辑同一个相对路径，都落在根目录下
+        let read = ReadFileTool::new(&settings)
+            .call(ReadFileArgs {
+                file_path: "src/lib.rs".to_string(),
+                offset: None,
+                limit: None,
+            })
+            .await
+            .unwrap();
+        assert!(read.content.contains("fn a()"));
+        let output = EditFileTool::new(&settings)
+            .call(EditFileArgs {
+                file_path: "src/lib.rs".to_string(),
+                patch: "@@ -1 +1 @@\n-fn a() {}\n+fn b() {}\n".to_string(),
+                confirmation: None,
+                partial: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(Path::new(&output.file_path), root.join("src/lib.rs"));
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            "fn b() {}\n"
+        );
+    }
 }
@@ -0,0 +1,321 @@
+//! 文本编码处理
+//!
+//! 读取时按 BOM 和内容识别文件编码（BOM 优先，其次是合法的 UTF-8，最后由 chardetng 猜测），
+//! 写入时默认沿用已有文件的编码和 BOM，避免旧的 Latin-1 / UTF-16 文件被悄悄转成 UTF-8。
+//! 内容无法用目标编码表示时报错，不做有损替换。
+
+use encoding_rs::Encoding;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// `write_file` 的 `encoding` 参数可选的值
+pub const ENCODING_LABELS: &[&str] = &["utf-8", "utf-16le", "utf-16be", "latin-1"];
+
+/// 文本编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1：每个字节对应 U+0000..U+00FF 中的一个字符
+    Latin1,
+    /// chardetng 识别出的其他编码（如 windows-1252、Shift_JIS）
+    Legacy(&'static Encoding),
+}
+
+impl TextEncoding {
+    /// 解析 `encoding` 参数（不区分大小写）
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-16le" | "utf16le" | "utf-16" => Some(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Self::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Utf8 => "utf-8".to_string(),
+            Self::Utf16Le => "utf-16le".to_string(),
+            Self::Utf16Be => "utf-16be".to_string(),
+            Self::Latin1 => "latin-1".to_string(),
+            Self::Legacy(encoding) => encoding.name().to_ascii_lowercase(),
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => UTF8_BOM,
+            Self::Utf16Le => UTF16LE_BOM,
+            Self::Utf16Be => UTF16BE_BOM,
+            Self::Latin1 | Self::Legacy(_) => &[],
+        }
+    }
+
+    /// 新文件是否写 BOM（没有 BOM 的 UTF-16 大多数工具无法识别）
+    fn bom_by_default(self) -> bool {
+        matches!(self, Self::Utf16Le | Self::Utf16Be)
+    }
+}
+
+/// 文件的编码信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileEncoding {
+    pub encoding: TextEncoding,
+    /// 是否带 BOM
+    pub bom: bool,
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: TextEncoding::Utf8,
+            bom: false,
+        }
+    }
+}
+
+impl FileEncoding {
+    /// 以 `encoding` 写入新文件时的编码信息
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            encoding,
+            bom: encoding.bom_by_default(),
+        }
+    }
+
+    /// 识别文件编码，看起来是二进制文件时返回 `None`
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+        ] {
+            if bytes.starts_with(encoding.bom()) {
+                return Some(Self {
+                    encoding,
+                    bom: true,
+                });
+            }
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return Some(Self::default());
+        }
+        // 没有 BOM 的 UTF-16 以外，文本文件不含 NUL
+        if bytes.contains(&0) {
+            return None;
+        }
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        let encoding = detector.guess(None, false);
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|_| Self {
+                encoding: TextEncoding::Legacy(encoding),
+                bom: false,
+            })
+    }
+
+    /// 按编码解码文件内容（去掉 BOM），内容与编码不符时返回 `None`
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        let bytes = if self.bom {
+            bytes.strip_prefix(self.encoding.bom())?
+        } else {
+            bytes
+        };
+        match self.encoding {
+            TextEncoding::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            TextEncoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            TextEncoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            TextEncoding::Latin1 => Some(bytes.iter().map(|&b| b as char).collect()),
+            TextEncoding::Legacy(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|text| text.into_owned()),
+        }
+    }
+
+    /// 把文本编码为文件内容（带 BOM 时写在开头）
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, Unrepresentable> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.bom {
+            bytes.extend_from_slice(self.encoding.bom());
+        }
+        match self.encoding {
+            TextEncoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            TextEncoding::Utf16Le => {
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            }
+            TextEncoding::Utf16Be => {
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            }
+            TextEncoding::Latin1 => {
+                for (index, c) in text.char_indices() {
+                    let byte = u8::try_from(u32::from(c))
+                        .map_err(|_| Unrepresentable::at(text, index, self.encoding))?;
+                    bytes.push(byte);
+                }
+            }
+            TextEncoding::Legacy(encoding) => {
+                let (encoded, _, had_errors) = encoding.encode(text);
+                if had_errors {
+                    let index = text
+                        .char_indices()
+                        .find(|(index, c)| {
+                            let c = &text[*index..*index + c.len_utf8()];
+                            encoding.encode(c).2
+                        })
+                        .map_or(0, |(index, _)| index);
+                    return Err(Unrepresentable::at(text, index, self.encoding));
+                }
+                bytes.extend_from_slice(&encoded);
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// 在工具输出中显示的编码，如 `utf-16le with BOM`
+    pub fn describe(&self) -> String {
+        if self.bom {
+            format!("{} with BOM", self.encoding.label())
+        } else {
+            self.encoding.label()
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let units = pairs.map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+/// 内容中有目标编码无法表示的字符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unrepresentable {
+    pub character: char,
+    /// 行号和列号（从 1 开始，列按字符计）
+    pub line: usize,
+    pub column: usize,
+    pub encoding: TextEncoding,
+}
+
+impl Unrepresentable {
+    fn at(text: &str, index: usize, encoding: TextEncoding) -> Self {
+        let before = &text[..index];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            character: text[index..].chars().next().unwrap_or_default(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            encoding,
+        }
+    }
+}
+
+impl std::fmt::Display for Unrepresentable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' (U+{:04X}) at line {}, column {} cannot be represented in {}",
+            self.character,
+            u32::from(self.character),
+            self.line,
+            self.column,
+            self.encoding.label()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Grüße aus Köln, café crème\r\nZeile 2\r\n";
+
+    const UTF8: &[u8] = include_bytes!("../../tests/fixtures/encodings/utf8.txt");
+    const UTF8_BOM_FILE: &[u8] = include_bytes!("../../tests/fixtures/encodings/utf8_bom.txt");
+    const UTF16LE: &[u8] = include_bytes!("../../tests/fixtures/encodings/utf16le_bom.txt");
+    const UTF16BE: &[u8] = include_bytes!("../../tests/fixtures/encodings/utf16be_bom.txt");
+    const WINDOWS_1252: &[u8] = include_bytes!("../../tests/fixtures/encodings/windows1252.txt");
+
+    #[test]
+    fn test_detect_and_round_trip_fixtures() {
+        let cases = [
+            (UTF8, "utf-8"),
+            (UTF8_BOM_FILE, "utf-8 with BOM"),
+            (UTF16LE, "utf-16le with BOM"),
+            (UTF16BE, "utf-16be with BOM"),
+            (WINDOWS_1252, "windows-1252"),
+        ];
+        for (bytes, expected) in cases {
+            let encoding = FileEncoding::detect(bytes).unwrap();
+            assert_eq!(encoding.describe(), expected);
+            let text = encoding.decode(bytes).unwrap();
+            assert_eq!(text, TEXT, "{}", expected);
+            // 原样写回时字节不变（包括 BOM）
+            assert_eq!(encoding.encode(&text).unwrap(), bytes, "{}", expected);
+        }
+    }
+
+    #[test]
+    fn test_explicit_encodings() {
+        assert_eq!(
+            TextEncoding::from_label("UTF-16"),
+            Some(TextEncoding::Utf16Le)
+        );
+        assert_eq!(
+            TextEncoding::from_label("latin1"),
+            Some(TextEncoding::Latin1)
+        );
+        assert_eq!(TextEncoding::from_label("ebcdic"), None);
+        for label in ENCODING_LABELS {
+            let encoding = TextEncoding::from_label(label).unwrap();
+            assert_eq!(encoding.label(), *label);
+        }
+
+        let latin1 = FileEncoding::new(TextEncoding::Latin1);
+        let bytes = latin1.encode("café").unwrap();
+        assert_eq!(bytes, b"caf\xE9");
+        assert_eq!(latin1.decode(&bytes).unwrap(), "café");
+
+        // 新的 UTF-16 文件带 BOM
+        let utf16 = FileEncoding::new(TextEncoding::Utf16Le);
+        assert_eq!(utf16.encode("a").unwrap(), vec![0xFF, 0xFE, b'a', 0]);
+        assert!(!FileEncoding::new(TextEncoding::Utf8).bom);
+    }
+
+    #[test]
+    fn test_unrepresentable_content_is_rejected() {
+        let err = FileEncoding::new(TextEncoding::Latin1)
+            .encode("price:\n  10 €")
+            .unwrap_err();
+        assert_eq!((err.character, err.line, err.column), ('€', 2, 6));
+        assert!(err.to_string().contains("cannot be represented in latin-1"));
+
+        let windows_1252 = FileEncoding::detect(WINDOWS_1252).unwrap();
+        assert!(windows_1252.encode("10 €").is_ok());
+        let err = windows_1252.encode("ok\n日本").unwrap_err();
+        assert_eq!((err.character, err.line, err.column), ('日', 2, 1));
+    }
+
+    #[test]
+    fn test_binary_is_not_text() {
+        assert_eq!(
+            FileEncoding::detect(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"),
+            None
+        );
+        assert_eq!(FileEncoding::detect(b""), Some(FileEncoding::default()));
+        // BOM 之后不是合法的 UTF-16
+        let encoding = FileEncoding::detect(&[0xFF, 0xFE, 0x00]).unwrap();
+        assert_eq!(encoding.decode(&[0xFF, 0xFE, 0x00]), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! 结构化的工具错误
+//!
+//! 工具失败时返回给模型的不是一段自由文本，而是一个 JSON 对象：
+//!
+//! ```json
+//! {"code":"Stale","message":"...","details":{...},"retryable":true,"suggestion":"..."}
+//! ```
+//!
+//! `code` 取自固定的 [`ErrorCode`]，`suggestion` 用一句话告诉模型下一步该做什么，
+//! 避免模型误读错误后原样重试。[`FileToolError`](super::FileToolError) 显示为这个对象，
+//! rig 把它作为出错的工具结果交给模型。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// 工具结果前的 rig 错误前缀（可能出现多次）
+const TOOL_ERROR_PREFIX: &str = "ToolCallError: ";
+
+/// 工具失败的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// 文件、目录、任务或主机不存在
+    NotFound,
+    /// 文件系统或权限配置（敏感文件、内网地址等）拒绝访问
+    PermissionDenied,
+    /// 要修改的内容匹配到多处，无法确定目标
+    Ambiguous,
+    /// 文件内容与调用时假设的不一致（已被修改）
+    Stale,
+    /// 超时
+    Timeout,
+    /// 输入或结果超过大小上限
+    TooLarge,
+    /// 参数无效
+    InvalidArgs,
+    /// 被用户拒绝，或被运行环境阻止（沙箱、网络不可达等）
+    Blocked,
+}
+
+impl ErrorCode {
+    /// 同样的调用稍后（或按建议调整后）重试可能成功
+    fn default_retryable(self) -> bool {
+        matches!(self, ErrorCode::Stale | ErrorCode::Timeout)
+    }
+}
+
+/// 返回给模型的工具错误
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolFailure {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    pub retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// 违反规则时附加的 `<system-reminder>` 块（见 `agent::reminder::attach`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_reminder: Option<String>,
+}
+
+impl ToolFailure {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            retryable: code.default_retryable(),
+            suggestion: None,
+            system_reminder: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// 下一步该做什么（一句话）
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn with_system_reminder(mut self, reminder: impl Into<String>) -> Self {
+        self.system_reminder = Some(reminder.into());
+        self
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// 从工具结果文本中解析（忽略 rig 加的 `ToolCallError: ` 前缀）
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut text = text.trim_start();
+        while let Some(rest) = text.strip_prefix(TOOL_ERROR_PREFIX) {
+            text = rest;
+        }
+        serde_json::from_str(text).ok()
+    }
+
+    /// 终端中显示的错误：结构化错误取 `message`，其他文本原样返回
+    pub fn display_message(text: &str) -> String {
+        Self::parse(text)
+            .map(|failure| failure.message)
+            .unwrap_or_else(|| text.to_string())
+    }
+}
+
+impl fmt::Display for ToolFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialized_shape() {
+        let failure = ToolFailure::new(ErrorCode::Stale, "patch context does not match")
+            .with_details(json!({"file": "src/main.rs"}))
+            .with_suggestion("call read_file again");
+        let value: Value = serde_json::from_str(&failure.to_string()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "code": "Stale",
+                "message": "patch context does not match",
+                "details": {"file": "src/main.rs"},
+                "retryable": true,
+                "suggestion": "call read_file again",
+            })
+        );
+
+        let bare = ToolFailure::new(ErrorCode::InvalidArgs, "bad").to_string();
+        assert_eq!(
+            bare,
+            r#"{"code":"InvalidArgs","message":"bad","retryable":false}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_tool_result() {
+        let failure = ToolFailure::new(ErrorCode::NotFound, "File not found: a.rs");
+        let text = format!("ToolCallError: ToolCallError: {}", failure);
+        assert_eq!(ToolFailure::parse(&text), Some(failure));
+        assert_eq!(ToolFailure::display_message(&text), "File not found: a.rs");
+
+        assert_eq!(ToolFailure::parse("ToolCallError: plain text"), None);
+        assert_eq!(ToolFailure::display_message("plain text"), "plain text");
+    }
+}
@@ -0,0 +1,365 @@
+//! 写入后格式化
+//!
+//! `[format_on_write]` 把文件 glob 映射到格式化命令，文件工具写入成功后对匹配的文件
+//! 执行一次。格式化失败或超时只作为警告附加到工具结果，不影响已完成的写入。
+//! 开启 `auto_detect` 时，没有显式规则匹配的文件按项目类型使用内置预设。
+
+use crate::config::{ConfigLoader, FormatOnWriteConfig};
+use colored::*;
+use glob::{MatchOptions, Pattern};
+use once_cell::sync::Lazy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 命令中替换为文件路径的占位符
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// 警告中保留的错误输出行数
+const MAX_ERROR_LINES: usize = 5;
+
+/// 内置预设：(项目标记文件, glob, 命令)
+const PRESETS: &[(&str, &str, &str)] = &[
+    ("Cargo.toml", "**/*.rs", "rustfmt --edition 2021 {path}"),
+    ("go.mod", "**/*.go", "gofmt -w {path}"),
+    (
+        "node_modules/.bin/prettier",
+        "**/*.{js,jsx,ts,tsx,json,css,scss,md}",
+        "node_modules/.bin/prettier --write {path}",
+    ),
+];
+
+/// 一条 glob → 命令规则
+#[derive(Debug, Clone)]
+struct FormatRule {
+    /// 展开花括号后的模式
+    patterns: Vec<Pattern>,
+    command: String,
+}
+
+impl FormatRule {
+    fn new(glob: &str, command: &str) -> Option<Self> {
+        let patterns: Vec<Pattern> = expand_braces(glob)
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok())
+            .collect();
+        (!patterns.is_empty()).then(|| Self {
+            patterns,
+            command: command.to_string(),
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        self.patterns.iter().any(|p| p.matches_with(path, options))
+    }
+}
+
+/// 格式化结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatResult {
+    /// 格式化成功，`before`/`after` 为格式化前后的文件内容
+    Formatted {
+        command: String,
+        before: String,
+        after: String,
+    },
+    /// 格式化失败或超时，文件保持写入时的内容
+    Failed { command: String, error: String },
+}
+
+impl FormatResult {
+    /// 格式化是否改动了文件
+    pub fn changed(&self) -> bool {
+        matches!(self, Self::Formatted { before, after, .. } if before != after)
+    }
+
+    /// 附加到工具结果中的说明
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Self::Formatted { command, .. } if self.changed() => Some(format!(
+                "file was reformatted by `{}`; re-read it before further edits",
+                command
+            )),
+            Self::Formatted { .. } => None,
+            Self::Failed { command, error } => {
+                Some(format!("warning: formatter `{}` failed: {}", command, error))
+            }
+        }
+    }
+}
+
+/// 写入后格式化的规则集
+#[derive(Debug, Clone)]
+pub struct FileFormatter {
+    rules: Vec<FormatRule>,
+    presets: Vec<FormatRule>,
+    timeout: Duration,
+    root: PathBuf,
+}
+
+impl FileFormatter {
+    /// 根据配置构建，`root` 为项目根目录（用于预设检测和计算相对路径）
+    pub fn from_config(config: &FormatOnWriteConfig, root: &Path) -> Self {
+        let rules = config
+            .rules
+            .iter()
+            .filter_map(|(glob, command)| FormatRule::new(glob, command))
+            .collect();
+        let presets = if config.auto_detect {
+            PRESETS
+                .iter()
+                .filter(|(marker, _, _)| root.join(marker).exists())
+                .filter_map(|(_, glob, command)| FormatRule::new(glob, command))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            rules,
+            presets,
+            timeout: Duration::from_secs(config.timeout_secs.max(1)),
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// 文件对应的格式化命令（显式规则优先于预设）
+    fn command_for(&self, path: &Path) -> Option<&str> {
+        let relative = self.relative_path(path);
+        self.rules
+            .iter()
+            .chain(&self.presets)
+            .find(|rule| rule.matches(&relative))
+            .map(|rule| rule.command.as_str())
+    }
+
+    /// 相对项目根目录的路径（统一使用 `/` 分隔）
+    fn relative_path(&self, path: &Path) -> String {
+        let relative = if path.is_absolute() {
+            let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            path.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(path)
+        } else {
+            path.strip_prefix(".").unwrap_or(path).to_path_buf()
+        };
+        relative.to_string_lossy().replace('\\', "/")
+    }
+
+    /// 格式化刚写入的文件，没有匹配的规则时返回 None
+    pub async fn format(&self, path: &Path) -> Option<FormatResult> {
+        let command = self.command_for(path)?.to_string();
+        let before = fs::read_to_string(path).ok()?;
+
+        let result = match self.run(&command, path).await {
+            Ok(()) => match fs::read_to_string(path) {
+                Ok(after) => FormatResult::Formatted {
+                    command,
+                    before,
+                    after,
+                },
+                Err(e) => FormatResult::Failed {
+                    command,
+                    error: e.to_string(),
+                },
+            },
+            Err(error) => FormatResult::Failed { command, error },
+        };
+        Some(result)
+    }
+
+    async fn run(&self, command: &str, path: &Path) -> Result<(), String> {
+        let path = path.to_string_lossy();
+        let argv: Vec<String> = shlex::split(command)
+            .filter(|argv| !argv.is_empty())
+            .ok_or_else(|| "invalid command".to_string())?
+            .into_iter()
+            .map(|arg| arg.replace(PATH_PLACEHOLDER, &path))
+            .collect();
+
+        let child = Command::new(&argv[0])
+            .args(&argv[1..])
+            .current_dir(&self.root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| format!("timed out after {}s", self.timeout.as_secs()))?
+            .map_err(|e| e.to_string())?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let details: Vec<&str> = stderr
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(MAX_ERROR_LINES)
+            .collect();
+        if details.is_empty() {
+            Err(format!("exited with {}", output.status))
+        } else {
+            Err(details.join("\n"))
+        }
+    }
+}
+
+/// 全局格式化规则（从全局/项目配置加载，未配置时为 None）
+static FORMATTER: Lazy<Option<FileFormatter>> = Lazy::new(|| {
+    let config = ConfigLoader::new().load_toml_layers().ok()?.format_on_write?;
+    let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    Some(FileFormatter::from_config(&config, &root))
+});
+
+/// 按配置格式化刚写入的文件，未启用或没有匹配的规则时返回 None
+pub async fn format_after_write(path: &Path) -> Option<FormatResult> {
+    FORMATTER.as_ref()?.format(path).await
+}
+
+/// 在终端显示格式化结果（未改动文件时不显示）
+pub fn print_result(result: &FormatResult) {
+    match result {
+        FormatResult::Formatted { command, .. } if result.changed() => {
+            tool_println!("  └─ {}", format!("Formatted with `{}`", command).dimmed());
+        }
+        FormatResult::Formatted { .. } => {}
+        FormatResult::Failed { command, error } => {
+            let first_line = error.lines().next().unwrap_or_default();
+            tool_println!(
+                "  └─ {}",
+                format!("Warning: formatter `{}` failed: {}", command, first_line).yellow()
+            );
+        }
+    }
+}
+
+/// 展开 glob 中的花括号（`*.{ts,tsx}` → `*.ts`、`*.tsx`），不支持嵌套
+fn expand_braces(glob: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (glob.find('{'), glob.find('}')) else {
+        return vec![glob.to_string()];
+    };
+    if close < open {
+        return vec![glob.to_string()];
+    }
+    let (prefix, suffix) = (&glob[..open], &glob[close + 1..]);
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", prefix, alt, suffix)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn config(rules: &[(&str, &str)], auto_detect: bool) -> FormatOnWriteConfig {
+        FormatOnWriteConfig {
+            timeout_secs: 1,
+            auto_detect,
+            rules: rules
+                .iter()
+                .map(|(glob, command)| (glob.to_string(), command.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("**/*.{ts,tsx}"), vec!["**/*.ts", "**/*.tsx"]);
+        assert_eq!(expand_braces("**/*.rs"), vec!["**/*.rs"]);
+        assert_eq!(
+            expand_braces("{src,lib}/*.{a,b}"),
+            vec!["src/*.a", "src/*.b", "lib/*.a", "lib/*.b"]
+        );
+    }
+
+    #[test]
+    fn test_command_for_explicit_rules_win() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+
+        let formatter = FileFormatter::from_config(
+            &config(&[("**/*.{ts,tsx}", "prettier --write {path}")], true),
+            temp_dir.path(),
+        );
+        assert_eq!(
+            formatter.command_for(Path::new("web/app.tsx")),
+            Some("prettier --write {path}")
+        );
+        assert_eq!(
+            formatter.command_for(&temp_dir.path().join("src/main.rs")),
+            Some("rustfmt --edition 2021 {path}")
+        );
+        assert_eq!(formatter.command_for(Path::new("README.txt")), None);
+
+        let formatter = FileFormatter::from_config(
+            &config(&[("src/*.rs", "custom-fmt {path}")], true),
+            temp_dir.path(),
+        );
+        assert_eq!(formatter.command_for(Path::new("./src/lib.rs")), Some("custom-fmt {path}"));
+        assert_eq!(
+            formatter.command_for(Path::new("src/nested/mod.rs")),
+            Some("rustfmt --edition 2021 {path}")
+        );
+    }
+
+    #[test]
+    fn test_presets_require_auto_detect() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+
+        let formatter = FileFormatter::from_config(&config(&[], false), temp_dir.path());
+        assert_eq!(formatter.command_for(Path::new("src/main.rs")), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_format_reports_changes_and_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "hello\n").unwrap();
+
+        let formatter = FileFormatter::from_config(
+            &config(&[("*.txt", "sed -i s/hello/world/ {path}")], false),
+            temp_dir.path(),
+        );
+        let result = formatter.format(&file).await.unwrap();
+        assert!(result.changed());
+        assert!(matches!(&result, FormatResult::Formatted { after, .. } if after == "world\n"));
+        assert!(result.note().unwrap().contains("reformatted"));
+
+        let formatter = FileFormatter::from_config(
+            &config(&[("*.txt", "sh -c 'echo broken >&2; exit 1' {path}")], false),
+            temp_dir.path(),
+        );
+        let result = formatter.format(&file).await.unwrap();
+        assert_eq!(
+            result,
+            FormatResult::Failed {
+                command: "sh -c 'echo broken >&2; exit 1' {path}".to_string(),
+                error: "broken".to_string(),
+            }
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "world\n");
+
+        let formatter = FileFormatter::from_config(
+            &config(&[("*.txt", "sleep 5")], false),
+            temp_dir.path(),
+        );
+        let result = formatter.format(&file).await.unwrap();
+        assert!(result.note().unwrap().contains("timed out"));
+    }
+}
@@ -186,21 +186,24 @@ impl GitGuard {
     pub fn warn_if_pushing_to_main(&self) {
         if let Some(branch) = self.current_branch() {
             if Self::is_main_branch(&branch) {
-                println!();
-                println!(
+                tool_println!();
+                tool_println!(
                     "{} {}",
                     "⚠️ ".bright_yellow(),
-                    "警告: 即将操作在主分支上".bright_yellow().bold()
+                    t!("git.main_branch_warning").bright_yellow().bold()
                 );
-                println!(
-                    "  当前分支: {}",
-                    branch.bright_white()
+                tool_println!(
+                    "{}",
+                    t!("git.current_branch", branch = branch.bright_white())
                 );
-                println!(
-                    "  建议先创建功能分支: {}",
-                    "git checkout -b feat/your-feature".bright_cyan()
+                tool_println!(
+                    "{}",
+                    t!(
+                        "git.create_feature_branch",
+                        command = "git checkout -b feat/your-feature".bright_cyan()
+                    )
                 );
-                println!();
+                tool_println!();
             }
         }
     }
@@ -212,7 +215,7 @@ impl GitGuard {
                 println!(
                     "{} {}",
                     "✓".bright_green(),
-                    "Git 状态良好".bright_green()
+                    t!("git.status_ok").bright_green()
                 );
             }
             GitSafety::UncommittedChanges => {
@@ -220,7 +223,7 @@ impl GitGuard {
                 println!(
                     "{} {}",
                     "⚠️ ".bright_yellow(),
-                    "警告: 有未提交的更改".bright_yellow().bold()
+                    t!("git.uncommitted_changes").bright_yellow().bold()
                 );
 
                 let files = self.uncommitted_files();
@@ -235,10 +238,7 @@ impl GitGuard {
                         println!("  {} {}", status_symbol, path.dimmed());
                     }
                 } else {
-                    println!(
-                        "  {} 个文件有更改 (使用 'git status' 查看详情)",
-                        files.len()
-                    );
+                    println!("{}", t!("git.changed_files", count = files.len()));
                 }
                 println!();
             }
@@ -247,9 +247,9 @@ impl GitGuard {
                 println!(
                     "{} {}",
                     "⚠️ ".bright_yellow(),
-                    "注意: 当前在主分支".bright_yellow().bold()
+                    t!("git.on_main_branch").bright_yellow().bold()
                 );
-                println!("  分支: {}", branch_name.bright_white());
+                println!("{}", t!("git.branch", branch = branch_name.bright_white()));
                 println!();
             }
             GitSafety::BehindRemote { local, remote } => {
@@ -257,13 +257,13 @@ impl GitGuard {
                 println!(
                     "{} {}",
                     "⚠️ ".bright_yellow(),
-                    "警告: 本地分支落后于远程".bright_yellow().bold()
+                    t!("git.behind_remote").bright_yellow().bold()
                 );
-                println!("  本地: {}", local.bright_white());
-                println!("  远程: {}", remote.bright_cyan());
+                println!("{}", t!("git.local", commit = local.bright_white()));
+                println!("{}", t!("git.remote", commit = remote.bright_cyan()));
                 println!(
-                    "  建议: {}",
-                    "git pull".bright_cyan()
+                    "{}",
+                    t!("git.suggestion", command = "git pull".bright_cyan())
                 );
                 println!();
             }
@@ -271,14 +271,14 @@ impl GitGuard {
                 println!(
                     "{} {}",
                     "ℹ️ ".bright_blue(),
-                    "不在 Git 仓库中".bright_black()
+                    t!("git.not_in_repository").bright_black()
                 );
             }
             GitSafety::CannotCheck { error } => {
                 println!(
                     "{} {}",
                     "✗".bright_red(),
-                    format!("无法检查 Git 状态: {}", error).bright_red()
+                    t!("git.check_failed", error = error).bright_red()
                 );
             }
         }
@@ -287,13 +287,13 @@ impl GitGuard {
     /// 格式化状态符号
     fn format_status(status: Status) -> colored::ColoredString {
         match status {
-            Status::WT_NEW => "新建".bright_green(),
-            Status::WT_MODIFIED => "修改".bright_yellow(),
-            Status::WT_DELETED => "删除".bright_red(),
-            Status::INDEX_NEW => "已暂存".bright_green(),
-            Status::INDEX_MODIFIED => "已暂存修改".bright_yellow(),
-            Status::INDEX_DELETED => "已暂存删除".bright_red(),
-            _ => "其他".bright_black(),
+            Status::WT_NEW => t!("git.status.new").bright_green(),
+            Status::WT_MODIFIED => t!("git.status.modified").bright_yellow(),
+            Status::WT_DELETED => t!("git.status.deleted").bright_red(),
+            Status::INDEX_NEW => t!("git.status.staged").bright_green(),
+            Status::INDEX_MODIFIED => t!("git.status.staged_modified").bright_yellow(),
+            Status::INDEX_DELETED => t!("git.status.staged_deleted").bright_red(),
+            _ => t!("git.status.other").bright_black(),
         }
     }
 }
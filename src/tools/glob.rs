@@ -2,11 +2,15 @@
 //!
 //! 提供文件模式匹配功能，支持通配符模式搜索文件。
 
-use super::FileToolError;
+use super::sensitive_paths::get_sensitive_guard;
+use super::settings::ToolSettings;
+use super::workspace::ALL_ROOTS;
+use super::{ErrorCode, FileToolError, ToolFailure};
+use crate::config::SensitivePathMode;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Glob 工具输入
 #[derive(Debug, Deserialize, Serialize)]
@@ -17,6 +21,10 @@ pub struct GlobInput {
     /// 搜索路径（可选，默认当前目录）
     #[serde(rename = "path")]
     pub search_path: Option<String>,
+
+    /// 在哪个工作区根目录下搜索：别名或 "all"（可选，默认当前目录）
+    #[serde(default)]
+    pub root: Option<String>,
 }
 
 /// Glob 工具输出
@@ -28,6 +36,9 @@ pub struct GlobOutput {
     /// 匹配的文件数量
     pub count: usize,
 
+    /// 因匹配敏感文件模式而隐藏的文件数量
+    pub hidden_sensitive: usize,
+
     /// 是否成功
     pub success: bool,
 
@@ -36,8 +47,80 @@ pub struct GlobOutput {
 }
 
 /// Glob 工具
-#[derive(Deserialize, Serialize)]
-pub struct GlobTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct GlobTool {
+    settings: ToolSettings,
+}
+
+impl GlobTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// 要搜索的目录：指定 `root` 时为所选根目录（下的 `path`），否则为 `path` 或当前目录
+    fn search_bases(&self, input: &GlobInput) -> Result<Vec<String>, FileToolError> {
+        let Some(root) = input.root.as_deref() else {
+            let base = match &input.search_path {
+                Some(path) => self.settings.resolve_path(path),
+                None => ".".to_string(),
+            };
+            return Ok(vec![base]);
+        };
+        Ok(self
+            .settings
+            .roots
+            .select(root)?
+            .into_iter()
+            .map(|root| match &input.search_path {
+                Some(path) => root.path.join(path).to_string_lossy().to_string(),
+                None => root.path.to_string_lossy().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// 在 `base` 下匹配 `pattern`，只返回文件
+fn glob_files(pattern: &str, base: &str) -> Result<Vec<PathBuf>, FileToolError> {
+    if !Path::new(base).is_dir() {
+        return Err(ToolFailure::new(
+            ErrorCode::NotFound,
+            format!("搜索路径不存在或不是目录: {}", base),
+        )
+        .with_suggestion(
+            "omit search_path to search the working directory, or glob its parent first",
+        )
+        .into());
+    }
+
+    // 构建完整的模式路径
+    let full_pattern = if base == "." {
+        pattern.to_string()
+    } else {
+        // 确保路径分隔符正确
+        let base_normalized = base.replace('\\', "/");
+        format!("{}/{}", base_normalized, pattern)
+    };
+
+    // 使用 glob crate 进行模式匹配
+    let matches = glob::glob(&full_pattern).map_err(|e| {
+        FileToolError::from(
+            ToolFailure::new(
+                ErrorCode::InvalidArgs,
+                format!("无效的 glob 模式 '{}': {}", pattern, e),
+            )
+            .with_suggestion("use `*` within a path segment and `**` only as a whole segment"),
+        )
+    })?;
+
+    // 收集所有匹配的文件路径
+    Ok(matches
+        .filter_map(|entry| entry.ok())
+        // 过滤掉目录
+        .filter(|path| path.is_file())
+        .collect())
+}
 
 impl Tool for GlobTool {
     const NAME: &'static str = "glob";
@@ -49,7 +132,9 @@ impl Tool for GlobTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "glob".to_string(),
-            description: "使用模式匹配搜索文件。支持通配符模式，例如 **/*.rs 或 src/**/*.toml".to_string(),
+            description: "使用模式匹配搜索文件。支持通配符模式，例如 **/*.rs 或 src/**/*.toml。\
+                错误码：NotFound（search_path 不存在）、InvalidArgs（模式无效）"
+                .to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -60,6 +145,13 @@ impl Tool for GlobTool {
                     "path": {
                         "type": "string",
                         "description": "可选的搜索路径（默认当前目录）"
+                    },
+                    "root": {
+                        "type": "string",
+                        "description": format!(
+                            "可选的工作区根目录别名，\"{}\" 搜索所有根目录；指定时 path 相对于该根目录",
+                            ALL_ROOTS
+                        )
                     }
                 },
                 "required": ["pattern"]
@@ -69,34 +161,24 @@ impl Tool for GlobTool {
 
     async fn call(&self, input: Self::Args) -> Result<Self::Output, Self::Error> {
         let pattern = &input.pattern;
-        let base = input.search_path.unwrap_or_else(|| ".".to_string());
-
-        // 构建完整的模式路径
-        let full_pattern = if base == "." {
-            pattern.clone()
-        } else {
-            // 确保路径分隔符正确
-            let base_normalized = base.replace('\\', "/");
-            format!("{}/{}", base_normalized, pattern)
-        };
-
-        // 使用 glob crate 进行模式匹配
-        let matches = match glob::glob(&full_pattern) {
-            Ok(m) => m,
-            Err(e) => {
-                return Err(FileToolError::InvalidInput(format!(
-                    "无效的 glob 模式 '{}': {}",
-                    pattern, e
-                )))
-            }
-        };
+        let mut paths = Vec::new();
+        for base in self.search_bases(&input)? {
+            self.settings.roots.check(Path::new(&base))?;
+            paths.extend(glob_files(pattern, &base)?);
+        }
 
-        // 收集所有匹配的文件路径
-        let mut paths: Vec<PathBuf> = matches
-            .filter_map(|entry| entry.ok())
-            // 过滤掉目录
-            .filter(|path| path.is_file())
-            .collect();
+        // 拒绝模式下隐藏敏感文件（确认模式下保留，由 read_file 询问用户）
+        let guard = get_sensitive_guard();
+        let mut hidden_sensitive = 0;
+        if guard.mode() == SensitivePathMode::Deny {
+            paths.retain(|path| {
+                let sensitive = guard.is_sensitive(path);
+                if sensitive {
+                    hidden_sensitive += 1;
+                }
+                !sensitive
+            });
+        }
 
         // 按路径排序以便结果稳定
         paths.sort();
@@ -107,11 +189,17 @@ impl Tool for GlobTool {
             .map(|p| p.to_string_lossy().to_string())
             .collect();
 
+        let mut message = format!("找到 {} 个匹配 '{}' 的文件", count, pattern);
+        if hidden_sensitive > 0 {
+            message.push_str(&format!("（已隐藏 {} 个敏感文件）", hidden_sensitive));
+        }
+
         Ok(GlobOutput {
             paths: path_strs,
             count,
+            hidden_sensitive,
             success: true,
-            message: format!("找到 {} 个匹配 '{}' 的文件", count, pattern),
+            message,
         })
     }
 }
@@ -123,9 +211,9 @@ pub struct WrappedGlobTool {
 }
 
 impl WrappedGlobTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: GlobTool,
+            inner: GlobTool::new(settings),
         }
     }
 }
@@ -143,10 +231,13 @@ impl Tool for WrappedGlobTool {
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let pattern = args.pattern.clone();
-        let path = args.search_path.clone().unwrap_or_else(|| ".".to_string());
+        let mut path = args.search_path.clone().unwrap_or_else(|| ".".to_string());
+        if let Some(root) = &args.root {
+            path = format!("{}:{}", root, path);
+        }
 
-        println!();
-        println!(
+        tool_println!();
+        tool_println!(
             "{} {}(pattern={}, path={})",
             "●".bright_blue(),
             "Glob".bright_blue(),
@@ -158,23 +249,41 @@ impl Tool for WrappedGlobTool {
 
         match &result {
             Ok(output) => {
-                println!(
-                    "  └─ {} 匹配文件",
-                    format!("{}", output.count).bright_green()
+                tool_println!(
+                    "  └─ {}",
+                    t!(
+                        "tools.glob.matched",
+                        count = output.count.to_string().bright_green()
+                    )
                 );
                 // 显示前几个匹配的文件
                 for (_i, path) in output.paths.iter().take(5).enumerate() {
-                    println!("     {}", path.dimmed());
+                    let path = self.inner.settings.roots.display(Path::new(path));
+                    tool_println!("     {}", path.dimmed());
                 }
                 if output.count > 5 {
-                    println!("     ... 还有 {} 个文件", output.count - 5);
+                    tool_println!("     {}", t!("tools.glob.more", count = output.count - 5));
+                }
+                if output.hidden_sensitive > 0 {
+                    tool_println!(
+                        "  {} {}",
+                        "🔒".bright_yellow(),
+                        t!(
+                            "tools.glob.hidden_sensitive",
+                            count = output.hidden_sensitive
+                        )
+                        .bright_yellow()
+                    );
                 }
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!(
+                    "  └─ {}",
+                    format!("{} {}", t!("common.error"), e.message()).red()
+                );
             }
         }
-        println!();
+        tool_println!();
 
         result
     }
@@ -198,7 +307,7 @@ mod tests {
         File::create(base.join("test1.txt")).unwrap();
         File::create(base.join("test2.txt")).unwrap();
 
-        let tool = GlobTool;
+        let tool = GlobTool::default();
 
         // 测试简单模式 - 使用绝对路径模式
         let base_str = base.to_string_lossy();
@@ -206,6 +315,7 @@ mod tests {
             .call(GlobInput {
                 pattern: format!("{}/*.txt", base_str),
                 search_path: None,
+                root: None,
             })
             .await
             .unwrap();
@@ -228,7 +338,7 @@ mod tests {
         std::fs::create_dir_all(base.join("src/subdir")).unwrap();
         File::create(base.join("src/subdir/helper.rs")).unwrap();
 
-        let tool = GlobTool;
+        let tool = GlobTool::default();
 
         // 测试递归模式 - 使用绝对路径模式
         let base_str = base.to_string_lossy();
@@ -236,6 +346,7 @@ mod tests {
             .call(GlobInput {
                 pattern: format!("{}/**/*.rs", base_str),
                 search_path: None,
+                root: None,
             })
             .await
             .unwrap();
@@ -259,7 +370,7 @@ mod tests {
         std::fs::create_dir_all(base.join("tests")).unwrap();
         File::create(base.join("tests/test.rs")).unwrap();
 
-        let tool = GlobTool;
+        let tool = GlobTool::default();
 
         // 测试指定路径 - 使用绝对路径
         let base_str = base.to_string_lossy();
@@ -267,6 +378,7 @@ mod tests {
             .call(GlobInput {
                 pattern: format!("{}/*.rs", base_str),
                 search_path: Some("src".to_string()),
+                root: None,
             })
             .await
             .unwrap();
@@ -284,13 +396,14 @@ mod tests {
 
         std::env::set_current_dir(base).unwrap();
 
-        let tool = GlobTool;
+        let tool = GlobTool::default();
 
         // 测试没有匹配的情况
         let result = tool
             .call(GlobInput {
                 pattern: "*.nonexistent".to_string(),
                 search_path: None,
+                root: None,
             })
             .await
             .unwrap();
@@ -299,4 +412,61 @@ mod tests {
         assert!(result.success);
         assert!(result.paths.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_glob_tool_failure_codes() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().to_string_lossy().to_string();
+
+        let error = GlobTool::default()
+            .call(GlobInput {
+                pattern: "a***b".to_string(),
+                search_path: Some(base.clone()),
+                root: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::InvalidArgs);
+
+        let error = GlobTool::default()
+            .call(GlobInput {
+                pattern: "*.rs".to_string(),
+                search_path: Some(format!("{}/missing", base)),
+                root: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_glob_tool_workspace_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let (web, api) = (temp_dir.path().join("web"), temp_dir.path().join("api"));
+        std::fs::create_dir_all(web.join("src")).unwrap();
+        std::fs::create_dir_all(api.join("src")).unwrap();
+        File::create(web.join("src/app.ts")).unwrap();
+        File::create(api.join("src/main.rs")).unwrap();
+
+        let settings = ToolSettings {
+            roots: crate::tools::WorkspaceRoots::new(&web),
+            ..ToolSettings::default()
+        };
+        settings.roots.add(&api).unwrap();
+        let tool = GlobTool::new(&settings);
+        let glob = |root: &str| {
+            tool.call(GlobInput {
+                pattern: "**/*.*".to_string(),
+                search_path: Some("src".to_string()),
+                root: Some(root.to_string()),
+            })
+        };
+
+        let output = glob("api").await.unwrap();
+        assert_eq!(output.count, 1);
+        assert!(output.paths[0].ends_with("main.rs"));
+        assert_eq!(glob("all").await.unwrap().count, 2);
+        let error = glob("docs").await.unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::InvalidArgs);
+    }
 }
@@ -1,43 +1,120 @@
-use super::FileToolError;
+use super::sensitive_paths::get_sensitive_guard;
+use super::settings::ToolSettings;
+use super::verbose_output::verbose_tools;
+use super::workspace::ALL_ROOTS;
+use super::{ErrorCode, FileToolError, ToolFailure};
 use colored::*;
-use grep_regex::RegexMatcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
 use grep_searcher::{
+    BinaryDetection,
+    MmapChoice,
     Searcher,
     SearcherBuilder,
     Sink,
     SinkMatch,
 };
-use ignore::WalkBuilder;
+use ignore::{WalkBuilder, WalkState};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 多行模式下单个文件的大小上限（字节），超过的文件跳过，避免整个读入内存
+pub const MULTILINE_MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// 多行匹配返回的最大行数，超出部分省略
+pub const MULTILINE_MAX_MATCH_LINES: usize = 20;
 
 #[derive(Deserialize, Serialize)]
 pub struct GrepSearchArgs {
     pub root_path: String,
     pub query: String,
     pub max_results: Option<usize>,
+    /// 把 query 当作普通字符串而不是正则
+    #[serde(default)]
+    pub fixed_strings: bool,
+    /// 允许匹配跨越多行
+    #[serde(default)]
+    pub multiline: bool,
+    /// 在哪个工作区根目录下搜索：别名或 "all"，指定时 root_path 相对于该根目录
+    #[serde(default)]
+    pub root: Option<String>,
+}
+
+/// 匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// query 按字面匹配（ripgrep `-F`）
+    pub fixed_strings: bool,
+    /// 匹配可以跨行（ripgrep `-U`），`^` / `$` 匹配每一行的开头和结尾
+    pub multiline: bool,
+    /// 多行模式下跳过大于该字节数的文件
+    pub multiline_max_file_bytes: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            fixed_strings: false,
+            multiline: false,
+            multiline_max_file_bytes: MULTILINE_MAX_FILE_BYTES,
+        }
+    }
+}
+
+impl SearchOptions {
+    fn matcher(&self, query: &str) -> Result<RegexMatcher, FileToolError> {
+        let mut builder = RegexMatcherBuilder::new();
+        builder.fixed_strings(self.fixed_strings);
+        if self.multiline {
+            builder.multi_line(true);
+        } else {
+            // 单行模式下禁止匹配换行，`\n` 直接报错而不是静默地匹配不到
+            builder.line_terminator(Some(b'\n'));
+        }
+        builder.build(query).map_err(|e| {
+            let (kind, suggestion) = match (self.fixed_strings, self.multiline) {
+                (true, _) => ("pattern", "set multiline: true to match across lines"),
+                (false, true) => (
+                    "regex",
+                    "escape regex metacharacters, or set fixed_strings: true to search for literal text",
+                ),
+                (false, false) => (
+                    "regex",
+                    "escape regex metacharacters or set fixed_strings: true for literal text; set multiline: true to match across lines",
+                ),
+            };
+            ToolFailure::new(ErrorCode::InvalidArgs, format!("Invalid {}: {}", kind, e))
+                .with_suggestion(suggestion)
+                .into()
+        })
+    }
 }
 
 #[derive(Serialize, Clone, Debug)]
 pub struct SearchMatch {
     pub file_path: String,
     pub line_number: usize,
+    /// 匹配所在的行，多行匹配时包含所有行（超过 `MULTILINE_MAX_MATCH_LINES` 时截断）
     pub line_content: String,
     pub match_start: usize,
     pub match_end: usize,
 }
 
 // 用于收集单个文件搜索结果的 sink
-struct FileCollector {
+struct FileCollector<'a> {
     matches: Vec<(usize, String)>,
+    /// 所有线程共享的匹配计数，达到上限后提前终止
+    found: &'a AtomicUsize,
     max_results: usize,
 }
 
-impl Sink for FileCollector {
+impl Sink for FileCollector<'_> {
     type Error = std::io::Error;
 
     fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch) -> Result<bool, Self::Error> {
-        if self.matches.len() >= self.max_results {
+        if self.found.fetch_add(1, Ordering::Relaxed) >= self.max_results {
             return Ok(false);
         }
 
@@ -56,12 +133,250 @@ pub struct GrepSearchOutput {
     pub matches: Vec<SearchMatch>,
     pub total_matches: usize,
     pub files_searched: usize,
+    /// 因匹配敏感文件模式而跳过的文件数
+    pub skipped_sensitive: usize,
+    /// 多行模式下因超过大小上限而跳过的文件数
+    #[serde(skip_serializing_if = "is_zero")]
+    pub skipped_large: usize,
     pub success: bool,
     pub message: String,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct GrepSearchTool;
+/// 搜索过程中各线程共享的状态
+#[derive(Default)]
+struct SearchState {
+    matches: Mutex<Vec<SearchMatch>>,
+    found: AtomicUsize,
+    files_searched: AtomicUsize,
+    skipped_sensitive: AtomicUsize,
+    skipped_large: AtomicUsize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// 多行匹配超过 `MULTILINE_MAX_MATCH_LINES` 行时只保留开头，并注明省略的行数
+fn cap_match_lines(content: &str) -> String {
+    let content = content.trim_end_matches(['\r', '\n']);
+    let total = content.lines().count();
+    if total <= MULTILINE_MAX_MATCH_LINES {
+        return content.to_string();
+    }
+    let kept: Vec<&str> = content.lines().take(MULTILINE_MAX_MATCH_LINES).collect();
+    format!(
+        "{}\n[... {} more lines]",
+        kept.join("\n"),
+        total - MULTILINE_MAX_MATCH_LINES
+    )
+}
+
+/// 在 `root_path` 下搜索匹配 `query` 的行
+///
+/// 使用 `ignore` 的并行遍历器和 ripgrep 的 `grep-searcher`：
+/// 在安全时使用内存映射，遇到 NUL 字节视为二进制文件并跳过，
+/// 匹配总数达到 `max_results` 后所有线程提前退出。
+/// 结果按 (路径, 行号) 排序以保证输出稳定；但提前退出时哪些文件已被搜索取决于线程调度，
+/// 截断后返回哪些匹配在多次运行间可能不同，`message` 中会注明。
+///
+/// `threads` 为 0 时自动选择线程数；`hidden` 为 false 时跳过隐藏文件和目录。
+/// 多行模式需要把整个文件放进内存，大于 `options.multiline_max_file_bytes` 的文件会被跳过。
+pub fn search_files(
+    root_path: &str,
+    query: &str,
+    options: &SearchOptions,
+    max_results: usize,
+    threads: usize,
+    hidden: bool,
+) -> Result<GrepSearchOutput, FileToolError> {
+    search_paths(
+        &[root_path.to_string()],
+        query,
+        options,
+        max_results,
+        threads,
+        hidden,
+    )
+}
+
+/// 在多个目录下搜索，匹配合并后按 (路径, 行号) 排序，参数同 [`search_files`]
+pub fn search_paths(
+    root_paths: &[String],
+    query: &str,
+    options: &SearchOptions,
+    max_results: usize,
+    threads: usize,
+    hidden: bool,
+) -> Result<GrepSearchOutput, FileToolError> {
+    // 使用 ripgrep 的 RegexMatcher
+    let matcher = options.matcher(query)?;
+
+    let state = SearchState::default();
+    let guard = get_sensitive_guard();
+
+    // 使用 ignore crate 并行遍历文件
+    let mut walker = WalkBuilder::new(root_paths.first().map_or(".", String::as_str));
+    for root_path in root_paths.iter().skip(1) {
+        walker.add(root_path);
+    }
+    walker
+        .hidden(!hidden)
+        .git_ignore(true)
+        .threads(threads)
+        .build_parallel()
+        .run(|| {
+            let matcher = &matcher;
+            let state = &state;
+            // SAFETY: 只在搜索期间读取映射，文件被并发截断时最坏情况是读到错误内容，
+            // `auto` 也会在不适合的平台/文件上回退为普通读取。
+            let mmap = unsafe { MmapChoice::auto() };
+            let mut searcher = SearcherBuilder::new()
+                .memory_map(mmap)
+                .binary_detection(BinaryDetection::quit(b'\x00'))
+                .line_number(true)
+                .multi_line(options.multiline)
+                .build();
+
+            Box::new(move |result| {
+                if state.found.load(Ordering::Relaxed) >= max_results {
+                    return WalkState::Quit;
+                }
+
+                let entry = match result {
+                    Ok(entry) => entry,
+                    Err(_) => return WalkState::Continue,
+                };
+
+                if !entry.file_type().map_or(false, |ft| ft.is_file()) {
+                    return WalkState::Continue;
+                }
+
+                // 敏感文件不参与搜索（确认模式下也跳过，避免批量泄露）
+                if guard.is_sensitive(entry.path()) {
+                    state.skipped_sensitive.fetch_add(1, Ordering::Relaxed);
+                    return WalkState::Continue;
+                }
+
+                if options.multiline
+                    && entry
+                        .metadata()
+                        .is_ok_and(|meta| meta.len() > options.multiline_max_file_bytes)
+                {
+                    state.skipped_large.fetch_add(1, Ordering::Relaxed);
+                    return WalkState::Continue;
+                }
+
+                state.files_searched.fetch_add(1, Ordering::Relaxed);
+
+                // 为每个文件创建 collector
+                let mut collector = FileCollector {
+                    matches: Vec::new(),
+                    found: &state.found,
+                    max_results,
+                };
+
+                if searcher
+                    .search_path(matcher, entry.path(), &mut collector)
+                    .is_ok()
+                    && !collector.matches.is_empty()
+                {
+                    let file_path = entry.path().to_string_lossy().to_string();
+
+                    // 将结果转换为 SearchMatch
+                    let file_matches: Vec<SearchMatch> = collector
+                        .matches
+                        .into_iter()
+                        .map(|(line_number, line_content)| {
+                            let line_content = if options.multiline {
+                                cap_match_lines(&line_content)
+                            } else {
+                                line_content
+                            };
+                            let content_len = line_content.len();
+                            SearchMatch {
+                                file_path: file_path.clone(),
+                                line_number,
+                                line_content,
+                                match_start: 0,
+                                match_end: content_len,
+                            }
+                        })
+                        .collect();
+
+                    state.matches.lock().unwrap().extend(file_matches);
+                }
+
+                if state.found.load(Ordering::Relaxed) >= max_results {
+                    WalkState::Quit
+                } else {
+                    WalkState::Continue
+                }
+            })
+        });
+
+    let mut all_matches = state.matches.into_inner().unwrap();
+    all_matches.sort_by(|a, b| {
+        a.file_path
+            .cmp(&b.file_path)
+            .then(a.line_number.cmp(&b.line_number))
+    });
+    all_matches.truncate(max_results);
+    let hit_limit = state.found.into_inner() >= max_results;
+
+    let files_searched = state.files_searched.into_inner();
+    let skipped_sensitive = state.skipped_sensitive.into_inner();
+    let skipped_large = state.skipped_large.into_inner();
+
+    let mut message = format!(
+        "Found {} match{} in {} files",
+        all_matches.len(),
+        if all_matches.len() == 1 { "" } else { "es" },
+        files_searched
+    );
+    if skipped_sensitive > 0 {
+        message.push_str(&format!(
+            " ({} sensitive file(s) skipped)",
+            skipped_sensitive
+        ));
+    }
+    if skipped_large > 0 {
+        message.push_str(&format!(
+            " ({} file(s) over {} bytes skipped in multiline mode)",
+            skipped_large, options.multiline_max_file_bytes
+        ));
+    }
+    if hit_limit {
+        message.push_str(&format!(
+            " (stopped at the limit of {} matches; more may exist and which ones are returned can vary between runs, narrow the query or path to see the rest)",
+            max_results
+        ));
+    }
+
+    Ok(GrepSearchOutput {
+        root_path: root_paths.join(", "),
+        query: query.to_string(),
+        total_matches: all_matches.len(),
+        matches: all_matches,
+        files_searched,
+        skipped_sensitive,
+        skipped_large,
+        success: true,
+        message,
+    })
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct GrepSearchTool {
+    settings: ToolSettings,
+}
+
+impl GrepSearchTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
 
 impl Tool for GrepSearchTool {
     const NAME: &'static str = "grep_search";
@@ -73,13 +388,44 @@ impl Tool for GrepSearchTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "grep_search".to_string(),
-            description: "Search for text patterns in files using regex. Respects .gitignore automatically.".to_string(),
+            description: concat!(
+                "Search for text patterns in files using regex. Respects .gitignore automatically.\n",
+                "Use fixed_strings for exact code snippets containing regex metacharacters ",
+                "instead of escaping them, e.g. {\"query\": \"foo(bar)?.baz[0]\", \"fixed_strings\": true}.\n",
+                "Use multiline for patterns that span lines, e.g. a signature split across lines: ",
+                "{\"query\": \"fn parse\\\\(\\\\s*input: &str\", \"multiline\": true}. ",
+                "In multiline mode `.` does not match newlines (use \\s or [\\s\\S]), long matches are ",
+                "truncated and large files are skipped.\n",
+                "Error codes: NotFound (root_path does not exist), InvalidArgs (the query is not a valid regex)."
+            )
+            .to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "root_path": {"type": "string", "description": "Root directory to search"},
-                    "query": {"type": "string", "description": "Regex pattern to search for"},
-                    "max_results": {"type": "integer", "description": "Max matches (default: 100)", "default": 100}
+                    "root": {
+                        "type": "string",
+                        "description": format!(
+                            "Workspace root alias to search in, or \"{}\" for every root; root_path is then relative to it",
+                            ALL_ROOTS
+                        )
+                    },
+                    "query": {"type": "string", "description": "Regex pattern to search for (a literal string with fixed_strings)"},
+                    "fixed_strings": {
+                        "type": "boolean",
+                        "description": "Treat query as a literal string, not a regex",
+                        "default": false
+                    },
+                    "multiline": {
+                        "type": "boolean",
+                        "description": "Allow matches to span multiple lines",
+                        "default": false
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "description": format!("Max matches (default: {}). Once reached the search stops early, so which matches are returned can vary between runs", self.settings.grep_head_limit),
+                        "default": self.settings.grep_head_limit
+                    }
                 },
                 "required": ["root_path", "query"]
             }),
@@ -87,78 +433,43 @@ impl Tool for GrepSearchTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let max_results = args.max_results.unwrap_or(100);
-
-        // 使用 ripgrep 的 RegexMatcher
-        let matcher = RegexMatcher::new(&args.query)
-            .map_err(|e| FileToolError::InvalidInput(format!("Invalid regex: {}", e)))?;
-
-        let mut all_matches = Vec::new();
-        let mut files_searched = 0;
-
-        // 使用 ignore crate 遍历文件
-        for result in WalkBuilder::new(&args.root_path)
-            .hidden(false)
-            .git_ignore(true)
-            .build()
-        {
-            if all_matches.len() >= max_results {
-                break;
-            }
-
-            let entry = match result {
-                Ok(entry) => entry,
-                Err(_) => continue,
-            };
-
-            if entry.file_type().map_or(false, |ft| ft.is_file()) {
-                files_searched += 1;
-
-                // 为每个文件创建 collector
-                let remaining = max_results - all_matches.len();
-                let mut collector = FileCollector {
-                    matches: Vec::new(),
-                    max_results: remaining,
-                };
-
-                // 使用 grep-searcher 搜索单个文件
-                let mut searcher = SearcherBuilder::new().build();
-                if searcher.search_path(&matcher, entry.path(), &mut collector).is_ok() {
-                    // 将结果转换为 SearchMatch
-                    for (line_num, line_content) in collector.matches {
-                        let content_len = line_content.len();
-                        all_matches.push(SearchMatch {
-                            file_path: entry.path().to_string_lossy().to_string(),
-                            line_number: line_num,
-                            line_content,
-                            match_start: 0,
-                            match_end: content_len,
-                        });
-
-                        if all_matches.len() >= max_results {
-                            break;
-                        }
-                    }
-                }
-            }
+        let max_results = args.max_results.unwrap_or(self.settings.grep_head_limit);
+        let root_paths = match args.root.as_deref() {
+            Some(root) => self
+                .settings
+                .roots
+                .select(root)?
+                .into_iter()
+                .map(|root| root.path.join(&args.root_path).display().to_string())
+                .collect(),
+            None => vec![self.settings.resolve_path(&args.root_path)],
+        };
+        let hidden = self.settings.grep_hidden;
+        if let Some(missing) = root_paths.iter().find(|path| !Path::new(path).exists()) {
+            return Err(ToolFailure::new(
+                ErrorCode::NotFound,
+                format!("Search path not found: {}", missing),
+            )
+            .with_suggestion(
+                "check root_path with glob; relative paths start at the working directory",
+            )
+            .into());
         }
+        for root_path in &root_paths {
+            self.settings.roots.check(Path::new(root_path))?;
+        }
+        let options = SearchOptions {
+            fixed_strings: args.fixed_strings,
+            multiline: args.multiline,
+            ..SearchOptions::default()
+        };
 
-        let message = format!(
-            "Found {} match{} in {} files",
-            all_matches.len(),
-            if all_matches.len() == 1 { "" } else { "es" },
-            files_searched
-        );
-
-        Ok(GrepSearchOutput {
-            root_path: args.root_path,
-            query: args.query,
-            total_matches: all_matches.len(),
-            matches: all_matches,
-            files_searched,
-            success: true,
-            message,
+        // 遍历和搜索是阻塞操作，放到阻塞线程池中执行
+        tokio::task::spawn_blocking(move || {
+            search_paths(&root_paths, &args.query, &options, max_results, 0, hidden)
         })
+        .await
+        .map_err(|e| FileToolError::InvalidInput(format!("Search task failed: {}", e)))?
     }
 }
 
@@ -169,9 +480,9 @@ pub struct WrappedGrepSearchTool {
 }
 
 impl WrappedGrepSearchTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: GrepSearchTool,
+            inner: GrepSearchTool::new(settings),
         }
     }
 }
@@ -187,7 +498,8 @@ impl Tool for WrappedGrepSearchTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!("{} {}({})", "●".bright_green(), "Search", args.query);
+        tool_println!("{} {}({})", "●".bright_green(), "Search", args.query);
+        let label = format!("Search({})", args.query);
 
         let result = self.inner.call(args).await;
 
@@ -200,18 +512,277 @@ impl Tool for WrappedGrepSearchTool {
                     } else {
                         preview.clone()
                     };
-                    println!(
+                    tool_println!(
                         "  └─ {} ... +{} matches",
                         preview.dimmed(),
                         output.total_matches
                     );
                 } else {
-                    println!("  └─ {}", "No matches found".dimmed());
+                    tool_println!("  └─ {}", "No matches found".dimmed());
+                }
+                let full_output: String = output
+                    .matches
+                    .iter()
+                    .map(|m| format!("{}:{}: {}\n", m.file_path, m.line_number, m.line_content))
+                    .collect();
+                verbose_tools().show(&label, &full_output);
+                if output.skipped_sensitive > 0 {
+                    tool_println!(
+                        "  {} {}",
+                        "🔒".bright_yellow(),
+                        format!("{} sensitive file(s) skipped", output.skipped_sensitive)
+                            .bright_yellow()
+                    );
                 }
             }
-            Err(e) => println!("  └─ {}", format!("Error: {}", e).red()),
+            Err(e) => tool_println!("  └─ {}", format!("Error: {}", e.message()).red()),
         }
 
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_tree(base: &std::path::Path) {
+        for dir in ["a", "b", "c"] {
+            fs::create_dir_all(base.join(dir)).unwrap();
+            for i in 0..5 {
+                fs::write(
+                    base.join(dir).join(format!("file{}.txt", i)),
+                    "first line\nneedle here\nmiddle\nanother needle\n",
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_results_sorted_by_path_and_line() {
+        let temp_dir = TempDir::new().unwrap();
+        create_tree(temp_dir.path());
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let output =
+            search_files(&root, "needle", &SearchOptions::default(), 100, 0, true).unwrap();
+        assert_eq!(output.total_matches, 30);
+        assert_eq!(output.files_searched, 15);
+
+        let keys: Vec<(String, usize)> = output
+            .matches
+            .iter()
+            .map(|m| (m.file_path.clone(), m.line_number))
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        assert_eq!(output.matches[0].line_number, 2);
+        assert_eq!(output.matches[1].line_number, 4);
+    }
+
+    #[test]
+    fn test_search_is_deterministic_across_thread_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        create_tree(temp_dir.path());
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let options = SearchOptions::default();
+        let single = search_files(&root, "needle", &options, 100, 1, true).unwrap();
+        let parallel = search_files(&root, "needle", &options, 100, 4, true).unwrap();
+        let paths = |o: &GrepSearchOutput| {
+            o.matches
+                .iter()
+                .map(|m| (m.file_path.clone(), m.line_number))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&single), paths(&parallel));
+    }
+
+    #[test]
+    fn test_search_respects_max_results() {
+        let temp_dir = TempDir::new().unwrap();
+        create_tree(temp_dir.path());
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let output = search_files(&root, "needle", &SearchOptions::default(), 3, 0, true).unwrap();
+        assert_eq!(output.total_matches, 3);
+        assert_eq!(output.matches.len(), 3);
+        assert!(output.message.contains("stopped at the limit of 3 matches"));
+
+        let output =
+            search_files(&root, "needle", &SearchOptions::default(), 100, 0, true).unwrap();
+        assert!(!output.message.contains("stopped at the limit"));
+    }
+
+    #[test]
+    fn test_search_skips_binary_files() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("text.txt"), "needle\n").unwrap();
+        fs::write(
+            temp_dir.path().join("blob.bin"),
+            b"needle\x00\x01\x02needle\n",
+        )
+        .unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let output =
+            search_files(&root, "needle", &SearchOptions::default(), 100, 0, true).unwrap();
+        assert!(output
+            .matches
+            .iter()
+            .all(|m| m.file_path.ends_with("text.txt")));
+    }
+
+    #[tokio::test]
+    async fn test_tool_honors_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        create_tree(temp_dir.path());
+        fs::create_dir_all(temp_dir.path().join("a/.cache")).unwrap();
+        fs::write(temp_dir.path().join("a/.cache/hit.txt"), "needle\n").unwrap();
+        let args = |max_results| GrepSearchArgs {
+            root_path: "a".to_string(),
+            query: "needle".to_string(),
+            max_results,
+            fixed_strings: false,
+            multiline: false,
+            root: None,
+        };
+
+        let settings = ToolSettings {
+            root: Some(temp_dir.path().to_path_buf()),
+            grep_head_limit: 4,
+            grep_hidden: false,
+            ..ToolSettings::default()
+        };
+        let tool = GrepSearchTool::new(&settings);
+        let output = tool.call(args(None)).await.unwrap();
+        assert_eq!(output.total_matches, 4);
+        let output = tool.call(args(Some(100))).await.unwrap();
+        assert_eq!(output.total_matches, 10);
+        assert!(output
+            .matches
+            .iter()
+            .all(|m| !m.file_path.contains(".cache")));
+
+        let settings = ToolSettings {
+            grep_hidden: true,
+            ..settings
+        };
+        let output = GrepSearchTool::new(&settings)
+            .call(args(Some(100)))
+            .await
+            .unwrap();
+        assert_eq!(output.total_matches, 11);
+
+        let error = GrepSearchTool::new(&settings)
+            .call(GrepSearchArgs {
+                root_path: "missing".to_string(),
+                ..args(None)
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let result = search_files(".", "(unclosed", &SearchOptions::default(), 10, 0, true);
+        let failure = result.unwrap_err().failure();
+        assert_eq!(failure.code, ErrorCode::InvalidArgs);
+        assert!(failure.suggestion.unwrap().contains("fixed_strings"));
+    }
+
+    #[test]
+    fn test_fixed_strings_match_metacharacters_literally() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "let x = foo(bar)?.baz[0];\nlet y = foobar.baz0;\n",
+        )
+        .unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let query = "foo(bar)?.baz[0]";
+
+        let options = SearchOptions {
+            fixed_strings: true,
+            ..SearchOptions::default()
+        };
+        let output = search_files(&root, query, &options, 100, 0, true).unwrap();
+        assert_eq!(output.total_matches, 1);
+        assert_eq!(output.matches[0].line_number, 1);
+
+        // 作为正则时 `(bar)?` 和 `[0]` 会匹配到第二行
+        let output = search_files(&root, query, &SearchOptions::default(), 100, 0, true).unwrap();
+        assert_eq!(output.total_matches, 1);
+        assert_eq!(output.matches[0].line_number, 2);
+    }
+
+    #[test]
+    fn test_multiline_matches_across_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "// parser\npub fn parse(\n    input: &str,\n) -> Result<Ast> {\n",
+        )
+        .unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let query = r"fn parse\(\s*input: &str";
+
+        let options = SearchOptions {
+            multiline: true,
+            ..SearchOptions::default()
+        };
+        let output = search_files(&root, query, &options, 100, 0, true).unwrap();
+        assert_eq!(output.total_matches, 1);
+        assert_eq!(output.matches[0].line_number, 2);
+        assert_eq!(
+            output.matches[0].line_content,
+            "pub fn parse(\n    input: &str,"
+        );
+
+        // 单行模式下 `\s` 不匹配换行
+        let output = search_files(&root, query, &SearchOptions::default(), 100, 0, true).unwrap();
+        assert_eq!(output.total_matches, 0);
+        let result = search_files(&root, r"parse\(\n", &SearchOptions::default(), 100, 0, true);
+        let failure = result.unwrap_err().failure();
+        assert_eq!(failure.code, ErrorCode::InvalidArgs);
+        assert!(failure.suggestion.unwrap().contains("multiline"));
+    }
+
+    #[test]
+    fn test_multiline_skips_large_files_and_caps_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let body: String = (0..50).map(|i| format!("item {}\n", i)).collect();
+        fs::write(
+            temp_dir.path().join("small.txt"),
+            format!("begin\n{}", body),
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("large.txt"),
+            format!("begin\n{}{}", body, "x".repeat(4096)),
+        )
+        .unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let options = SearchOptions {
+            multiline: true,
+            multiline_max_file_bytes: 1024,
+            ..SearchOptions::default()
+        };
+        let output = search_files(&root, r"begin\n(item \d+\n)+", &options, 100, 0, true).unwrap();
+        assert_eq!(output.skipped_large, 1);
+        assert_eq!(output.files_searched, 1);
+        assert_eq!(output.total_matches, 1);
+        assert!(output.matches[0].file_path.ends_with("small.txt"));
+        assert!(output.message.contains("multiline mode"));
+
+        let content = &output.matches[0].line_content;
+        assert_eq!(content.lines().count(), MULTILINE_MAX_MATCH_LINES + 1);
+        assert!(content.ends_with("[... 31 more lines]"));
+    }
+}
@@ -0,0 +1,880 @@
+//! HTTP 请求工具
+//!
+//! 用于调试 API：发送任意方法的 HTTP 请求，返回状态码、响应头和响应体
+//! （JSON 自动格式化）。重定向次数和读取的响应大小都有上限。
+//! 访问 localhost 和内网地址需要在配置中开启 `[permissions] allow_private_network`，
+//! 每一跳还要符合 `[network]` 的网络访问策略（见 [`super::network`]）。
+//!
+//! 发往公网、不带请求头和请求体的 GET 请求成功后写入磁盘缓存（见 [`super::web_cache`]），
+//! 有效期内再次请求同一 URL 时直接返回缓存并注明缓存的年龄，`force: true` 时绕过缓存。
+//! 本机和内网的服务（通常是正在调试的服务）不缓存。
+
+use super::network::{self, ToolHttpClient};
+use super::redactor::{mask_header_value, redact_url_credentials};
+use super::web_cache;
+use super::{ErrorCode, FileToolError, ToolFailure};
+use colored::*;
+use once_cell::sync::Lazy;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION,
+};
+use reqwest::{Method, StatusCode, Url};
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crate::config::ConfigLoader;
+
+/// 默认超时（毫秒）
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// 超时上限（毫秒）
+const MAX_TIMEOUT_MS: u64 = 120_000;
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// 最多读取的响应字节数
+const MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+
+/// 返回给模型的响应体最大字符数
+const MAX_BODY_CHARS: usize = 20_000;
+
+/// 允许的请求方法
+const ALLOWED_METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HttpRequestArgs {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 字符串原样发送，对象/数组按 JSON 发送
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 不读取缓存，重新请求
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// 响应体格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    Json,
+    Text,
+    Binary,
+    Empty,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpRequestOutput {
+    pub method: String,
+    /// 最终请求的 URL（跟随重定向后）
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    /// 状态码小于 400
+    pub success: bool,
+    /// 响应头（名称小写，多个同名头以 ", " 连接）
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+    pub body_format: BodyFormat,
+    /// 读取的响应字节数
+    pub size_bytes: usize,
+    /// 响应体是否被截断
+    pub truncated: bool,
+    /// 依次跟随的重定向地址
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<String>,
+    pub duration_ms: u64,
+    /// 来自缓存时的说明，如 `served from cache (age 3h)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached: Option<String>,
+}
+
+/// 是否允许访问 localhost 和内网地址（`[permissions] allow_private_network`，默认禁止）
+pub fn private_network_allowed() -> bool {
+    static ALLOWED: Lazy<bool> = Lazy::new(|| {
+        ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.permissions)
+            .and_then(|permissions| permissions.allow_private_network)
+            .unwrap_or(false)
+    });
+    *ALLOWED
+}
+
+/// 权限确认时展示的操作描述，如 `POST api.example.com`
+pub fn operation_descriptor(args: &Value) -> String {
+    let method = args
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or("GET")
+        .to_uppercase();
+    let url = args.get("url").and_then(Value::as_str).unwrap_or_default();
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        })
+        .unwrap_or_else(|| redact_url_credentials(url));
+    format!("{} {}", method, host)
+}
+
+/// loopback、私有网段、链路本地、CGNAT 等非公网地址
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || octets[0] == 0
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_private_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// URL 的主机是 localhost 或内网 IP（不解析域名）
+fn is_local_host(url: &Url) -> bool {
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    match host.parse::<IpAddr>() {
+        Ok(ip) => is_private_ip(ip),
+        Err(_) => host.is_empty() || host == "localhost" || host.ends_with(".localhost"),
+    }
+}
+
+/// 解析目标地址；不允许访问内网时拒绝任何解析到内网的主机
+async fn resolve_destination(
+    url: &Url,
+    allow_private: bool,
+) -> Result<Vec<SocketAddr>, FileToolError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| FileToolError::InvalidInput(format!("URL has no host: {}", url)))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addrs: Vec<SocketAddr> = match bare_host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((bare_host, port))
+            .await
+            .map_err(|e| {
+                FileToolError::from(
+                    ToolFailure::new(
+                        ErrorCode::NotFound,
+                        format!("Failed to resolve {}: {}", host, e),
+                    )
+                    .with_suggestion("check the host name in the URL"),
+                )
+            })?
+            .collect(),
+    };
+
+    let local_name = bare_host.eq_ignore_ascii_case("localhost")
+        || bare_host.to_ascii_lowercase().ends_with(".localhost");
+    if !allow_private && (local_name || addrs.iter().any(|addr| is_private_ip(addr.ip()))) {
+        return Err(ToolFailure::new(
+            ErrorCode::PermissionDenied,
+            format!(
+                "{} resolves to a local or private network address; set `allow_private_network = true` under [permissions] in .oxide/config.toml to allow it",
+                host
+            ),
+        )
+        .with_details(serde_json::json!({ "host": host }))
+        .with_suggestion("do not retry; ask the user to enable allow_private_network if this request is intended")
+        .into());
+    }
+    Ok(addrs)
+}
+
+/// 重定向后的请求方法：303 以及 301/302 的 POST 改为 GET 且不再发送请求体
+fn redirect_method(status: StatusCode, method: &Method) -> Method {
+    let to_get = status == StatusCode::SEE_OTHER
+        || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+            && *method == Method::POST);
+    if to_get && *method != Method::HEAD {
+        Method::GET
+    } else {
+        method.clone()
+    }
+}
+
+fn build_headers(headers: &BTreeMap<String, String>) -> Result<HeaderMap, FileToolError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| FileToolError::InvalidInput(format!("Invalid header name: {}", name)))?;
+        let header_value = HeaderValue::from_str(value).map_err(|_| {
+            FileToolError::InvalidInput(format!("Invalid value for header {}", name))
+        })?;
+        map.append(header_name, header_value);
+    }
+    Ok(map)
+}
+
+/// 读取响应体，超过上限时停止
+async fn read_capped(response: &mut reqwest::Response) -> Result<(Vec<u8>, bool), FileToolError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+        let remaining = MAX_RESPONSE_BYTES - bytes.len();
+        if chunk.len() > remaining {
+            bytes.extend_from_slice(&chunk[..remaining]);
+            return Ok((bytes, true));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((bytes, false))
+}
+
+fn request_error(e: reqwest::Error) -> FileToolError {
+    let message = format!("HTTP request failed: {}", e);
+    let failure = if e.is_timeout() {
+        ToolFailure::new(ErrorCode::Timeout, message).with_suggestion(format!(
+            "retry with a larger timeout_ms (max {})",
+            MAX_TIMEOUT_MS
+        ))
+    } else {
+        // 连接被拒绝、TLS 失败等：请求没有到达服务端
+        ToolFailure::new(ErrorCode::Blocked, message)
+            .with_retryable(true)
+            .with_suggestion(
+                "the server could not be reached; check the URL and port, or retry later",
+            )
+    };
+    failure.into()
+}
+
+/// 把响应体格式化为文本：JSON 缩进，非 UTF-8 内容只给出大小
+fn format_body(bytes: &[u8], content_type: Option<&str>) -> (String, BodyFormat) {
+    if bytes.is_empty() {
+        return (String::new(), BodyFormat::Empty);
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return (
+            format!(
+                "<{} bytes of binary data ({})>",
+                bytes.len(),
+                content_type.unwrap_or("unknown type")
+            ),
+            BodyFormat::Binary,
+        );
+    };
+    let looks_json = content_type.is_some_and(|ct| ct.contains("json"))
+        || matches!(text.trim_start().chars().next(), Some('{') | Some('['));
+    if looks_json {
+        if let Ok(value) = serde_json::from_str::<Value>(text) {
+            if let Ok(pretty) = serde_json::to_string_pretty(&value) {
+                return (pretty, BodyFormat::Json);
+            }
+        }
+    }
+    (text.to_string(), BodyFormat::Text)
+}
+
+fn truncate_chars(text: String, max: usize) -> (String, bool) {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => (text[..index].to_string(), true),
+        None => (text, false),
+    }
+}
+
+/// 发送请求并跟随重定向
+pub async fn execute(
+    args: HttpRequestArgs,
+    allow_private: bool,
+) -> Result<HttpRequestOutput, FileToolError> {
+    let method_name = args.method.trim().to_uppercase();
+    if !ALLOWED_METHODS.contains(&method_name.as_str()) {
+        return Err(FileToolError::InvalidInput(format!(
+            "Unsupported method '{}'; use one of {}",
+            args.method,
+            ALLOWED_METHODS.join(", ")
+        )));
+    }
+    let mut method = Method::from_bytes(method_name.as_bytes())
+        .map_err(|_| FileToolError::InvalidInput(format!("Invalid method: {}", args.method)))?;
+
+    let mut url = Url::parse(args.url.trim()).map_err(|e| {
+        FileToolError::InvalidInput(format!(
+            "Invalid URL '{}': {}",
+            redact_url_credentials(&args.url),
+            e
+        ))
+    })?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FileToolError::InvalidInput(format!(
+            "Only http and https URLs are supported, got '{}'",
+            url.scheme()
+        )));
+    }
+
+    // 只缓存发往公网、不带请求头和请求体的 GET，认证信息不同的响应不会混在一起
+    let cache = web_cache::current();
+    let cache_key = (cache.enabled()
+        && method == Method::GET
+        && args.headers.is_empty()
+        && args.body.as_ref().is_none_or(Value::is_null)
+        && !is_local_host(&url))
+    .then(|| web_cache::normalize_url(&url));
+    if let (Some(key), false) = (&cache_key, args.force) {
+        if let Some(hit) = cache.get::<HttpRequestOutput>(key) {
+            // 缓存不联网，但策略收紧后同样不返回被禁止的主机的内容
+            network::check(&url)?;
+            let mut output = hit.value;
+            output.duration_ms = 0;
+            output.cached = Some(format!(
+                "served from cache (age {})",
+                web_cache::format_age(hit.age)
+            ));
+            return Ok(output);
+        }
+    }
+
+    let timeout = Duration::from_millis(
+        args.timeout_ms
+            .unwrap_or(DEFAULT_TIMEOUT_MS)
+            .clamp(1, MAX_TIMEOUT_MS),
+    );
+    let mut headers = build_headers(&args.headers)?;
+    let mut body = match args.body {
+        None | Some(Value::Null) => None,
+        Some(Value::String(text)) => Some(text),
+        Some(value) => {
+            if !headers.contains_key(CONTENT_TYPE) {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            Some(value.to_string())
+        }
+    };
+
+    let start = Instant::now();
+    let mut redirects = Vec::new();
+    let mut response = loop {
+        // 在解析 DNS 之前检查，被策略拒绝的主机不会产生任何查询
+        network::check(&url)?;
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let addrs = tokio::time::timeout(remaining, resolve_destination(&url, allow_private))
+            .await
+            .map_err(|_| timed_out(&url, timeout))??;
+
+        // 固定为检查过的地址，避免 DNS 在检查后被改指到内网
+        let client = ToolHttpClient::build(|builder| {
+            let builder = builder
+                .redirect(reqwest::redirect::Policy::none())
+                .timeout(timeout.saturating_sub(start.elapsed()));
+            match url.domain() {
+                Some(domain) => builder.resolve_to_addrs(domain, &addrs),
+                None => builder,
+            }
+        })
+        .map_err(request_error)?;
+
+        let mut request = client
+            .request(method.clone(), url.clone())?
+            .headers(headers.clone());
+        if let Some(body) = &body {
+            request = request.body(body.clone());
+        }
+        let response = request.send().await.map_err(|e| match e.is_timeout() {
+            true => timed_out(&url, timeout),
+            false => request_error(e),
+        })?;
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let Some(location) = location.filter(|_| response.status().is_redirection()) else {
+            break response;
+        };
+        if redirects.len() >= MAX_REDIRECTS {
+            return Err(FileToolError::InvalidInput(format!(
+                "Too many redirects (more than {}), last location: {}",
+                MAX_REDIRECTS,
+                redact_url_credentials(&location)
+            )));
+        }
+
+        let next = url.join(&location).map_err(|e| {
+            FileToolError::InvalidInput(format!("Invalid redirect location '{}': {}", location, e))
+        })?;
+        // 跨站重定向时不再携带认证信息
+        if next.origin() != url.origin() {
+            for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+                headers.remove(name);
+            }
+        }
+        let next_method = redirect_method(response.status(), &method);
+        if next_method != method {
+            body = None;
+            headers.remove(CONTENT_TYPE);
+        }
+        method = next_method;
+        redirects.push(redact_url_credentials(next.as_str()));
+        url = next;
+    };
+
+    let (bytes, size_capped) = tokio::time::timeout(
+        timeout.saturating_sub(start.elapsed()),
+        read_capped(&mut response),
+    )
+    .await
+    .map_err(|_| timed_out(&url, timeout))??;
+
+    let mut response_headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in response.headers() {
+        let value = mask_header_value(name.as_str(), &String::from_utf8_lossy(value.as_bytes()));
+        response_headers
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    let content_type = response_headers.get("content-type").map(String::as_str);
+    let (text, body_format) = format_body(&bytes, content_type);
+    let (text, chars_capped) = truncate_chars(text, MAX_BODY_CHARS);
+
+    let status = response.status();
+    let output = HttpRequestOutput {
+        method: method.to_string(),
+        url: redact_url_credentials(url.as_str()),
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        success: status.as_u16() < 400,
+        headers: response_headers,
+        body: text,
+        body_format,
+        size_bytes: bytes.len(),
+        truncated: size_capped || chars_capped,
+        redirects,
+        duration_ms: start.elapsed().as_millis() as u64,
+        cached: None,
+    };
+    if let Some(key) = cache_key.filter(|_| status.is_success()) {
+        // 缓存写入失败不影响本次请求
+        let _ = cache.put(&key, &output);
+    }
+    Ok(output)
+}
+
+fn timed_out(url: &Url, timeout: Duration) -> FileToolError {
+    ToolFailure::new(
+        ErrorCode::Timeout,
+        format!(
+            "HTTP request to {} timed out after {} ms",
+            redact_url_credentials(url.as_str()),
+            timeout.as_millis()
+        ),
+    )
+    .with_details(serde_json::json!({ "timeout_ms": timeout.as_millis() as u64 }))
+    .with_suggestion(format!(
+        "retry with a larger timeout_ms (max {})",
+        MAX_TIMEOUT_MS
+    ))
+    .into()
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HttpRequestTool;
+
+impl Tool for HttpRequestTool {
+    const NAME: &'static str = "http_request";
+
+    type Error = FileToolError;
+    type Args = HttpRequestArgs;
+    type Output = HttpRequestOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "http_request".to_string(),
+            description: format!(
+                "Send an HTTP request (e.g. to debug an API) and return the status, response headers and body. JSON bodies are pretty-printed; the body is truncated to {} characters and at most {} MB is read. Follows up to {} redirects. Prefer this over running curl through shell_execute. Requests to localhost or private network addresses are only allowed when enabled in the config. HTTP error statuses are returned normally with success=false. Successful GET requests to public hosts without headers or body are cached on disk; a cached result says so in `cached` (with its age), pass force=true to fetch again. Error codes: InvalidArgs (bad method, URL, header or redirect), NotFound (the host does not resolve), PermissionDenied (a private address), Blocked (the server is unreachable or the network policy does not allow the host), Timeout.",
+                MAX_BODY_CHARS,
+                MAX_RESPONSE_BYTES / 1024 / 1024,
+                MAX_REDIRECTS
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "method": {
+                        "type": "string",
+                        "enum": ALLOWED_METHODS,
+                        "description": "HTTP method (default GET)"
+                    },
+                    "url": {
+                        "type": "string",
+                        "description": "Absolute http(s) URL"
+                    },
+                    "headers": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Request headers, e.g. {\"Authorization\": \"Bearer ...\"}"
+                    },
+                    "body": {
+                        "description": "Request body. Strings are sent as-is; objects and arrays are sent as JSON with Content-Type: application/json"
+                    },
+                    "timeout_ms": {
+                        "type": "integer",
+                        "description": format!("Total timeout in milliseconds (default {}, max {})", DEFAULT_TIMEOUT_MS, MAX_TIMEOUT_MS)
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Bypass the response cache and fetch again (default false)"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        execute(args, private_network_allowed()).await
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedHttpRequestTool {
+    inner: HttpRequestTool,
+}
+
+impl WrappedHttpRequestTool {
+    pub fn new() -> Self {
+        Self {
+            inner: HttpRequestTool,
+        }
+    }
+}
+
+impl Default for WrappedHttpRequestTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tool for WrappedHttpRequestTool {
+    const NAME: &'static str = "http_request";
+
+    type Error = FileToolError;
+    type Args = <HttpRequestTool as Tool>::Args;
+    type Output = <HttpRequestTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        tool_println!(
+            "{} {}({} {})",
+            "●".bright_green(),
+            "Http".bright_green(),
+            args.method.to_uppercase(),
+            redact_url_credentials(&args.url).bright_white()
+        );
+        for (name, value) in &args.headers {
+            tool_println!(
+                "  │ {}: {}",
+                name.dimmed(),
+                mask_header_value(name, value).dimmed()
+            );
+        }
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => {
+                let status = format!("{} {}", output.status, output.status_text);
+                let status = if output.success {
+                    status.green()
+                } else {
+                    status.red()
+                };
+                let mut details = match &output.cached {
+                    Some(note) => format!("{} bytes, {}", output.size_bytes, note),
+                    None => format!("{} bytes, {} ms", output.size_bytes, output.duration_ms),
+                };
+                if !output.redirects.is_empty() {
+                    details.push_str(&format!(", {} redirect(s)", output.redirects.len()));
+                }
+                if output.truncated {
+                    details.push_str(", truncated");
+                }
+                tool_println!("  └─ {} {}", status, format!("({})", details).dimmed());
+            }
+            Err(e) => {
+                tool_println!("  └─ {} {}", t!("common.error").red(), e.message().red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn args(method: &str, url: String) -> HttpRequestArgs {
+        HttpRequestArgs {
+            method: method.to_string(),
+            url,
+            headers: BTreeMap::new(),
+            body: None,
+            timeout_ms: None,
+            force: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_response_is_pretty_printed() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/users")
+            .match_header("authorization", "Bearer token-123")
+            .match_header("content-type", "application/json")
+            .match_body(mockito::Matcher::Json(serde_json::json!({ "name": "ada" })))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_header("set-cookie", "session=abc")
+            .with_body(r#"{"id":1,"name":"ada"}"#)
+            .create_async()
+            .await;
+
+        let mut request = args("post", format!("{}/users", server.url()));
+        request
+            .headers
+            .insert("Authorization".to_string(), "Bearer token-123".to_string());
+        request.body = Some(serde_json::json!({ "name": "ada" }));
+        let output = execute(request, true).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(output.status, 201);
+        assert_eq!(output.status_text, "Created");
+        assert!(output.success);
+        assert_eq!(output.body_format, BodyFormat::Json);
+        assert_eq!(output.body, "{\n  \"id\": 1,\n  \"name\": \"ada\"\n}");
+        assert_eq!(output.headers["content-type"], "application/json");
+        assert_eq!(output.headers["set-cookie"], "[REDACTED:set-cookie]");
+        assert!(!output.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_large_body_is_capped() {
+        let mut server = mockito::Server::new_async().await;
+        let large = "x".repeat(MAX_RESPONSE_BYTES + 4096);
+        server
+            .mock("GET", "/large")
+            .with_header("content-type", "text/plain")
+            .with_body(&large)
+            .create_async()
+            .await;
+
+        let output = execute(args("GET", format!("{}/large", server.url())), true)
+            .await
+            .unwrap();
+        assert_eq!(output.size_bytes, MAX_RESPONSE_BYTES);
+        assert_eq!(output.body.chars().count(), MAX_BODY_CHARS);
+        assert_eq!(output.body_format, BodyFormat::Text);
+        assert!(output.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_and_binary_body() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .with_header("content-type", "application/octet-stream")
+            .with_body([0xff_u8, 0xfe, 0x00, 0x01])
+            .create_async()
+            .await;
+
+        let output = execute(args("GET", format!("{}/missing", server.url())), true)
+            .await
+            .unwrap();
+        assert_eq!(output.status, 404);
+        assert!(!output.success);
+        assert_eq!(output.body_format, BodyFormat::Binary);
+        assert_eq!(
+            output.body,
+            "<4 bytes of binary data (application/octet-stream)>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_followed_and_limited() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/old")
+            .with_status(303)
+            .with_header("location", "/new")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/new")
+            .with_body("moved")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/loop")
+            .with_status(302)
+            .with_header("location", "/loop")
+            .expect(MAX_REDIRECTS + 1)
+            .create_async()
+            .await;
+
+        let mut request = args("POST", format!("{}/old", server.url()));
+        request.body = Some(Value::String("payload".to_string()));
+        let output = execute(request, true).await.unwrap();
+        assert_eq!(output.method, "GET");
+        assert_eq!(output.body, "moved");
+        assert_eq!(output.redirects, vec![format!("{}/new", server.url())]);
+
+        let err = execute(args("GET", format!("{}/loop", server.url())), true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Too many redirects"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // 接受连接但从不响应
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let mut request = args("GET", format!("http://{}/slow", addr));
+        request.timeout_ms = Some(200);
+        let start = Instant::now();
+        let err = execute(request, true).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let failure = err.failure();
+        assert_eq!(failure.code, ErrorCode::Timeout);
+        assert!(failure.retryable);
+        assert!(
+            failure.message.contains("timed out after 200 ms"),
+            "{}",
+            failure.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_private_network_requires_opt_in() {
+        for url in [
+            "http://127.0.0.1:9/",
+            "http://localhost:9/",
+            "http://[::1]:9/",
+            "http://10.1.2.3/",
+        ] {
+            let err = execute(args("GET", url.to_string()), false)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.failure().code,
+                ErrorCode::PermissionDenied,
+                "{}: {}",
+                url,
+                err
+            );
+            assert!(!err.failure().retryable);
+        }
+
+        let err = execute(args("GET", "ftp://example.com/file".to_string()), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.failure().code, ErrorCode::InvalidArgs);
+        let err = execute(args("TRACE", "https://example.com".to_string()), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.failure().code, ErrorCode::InvalidArgs);
+    }
+
+    #[test]
+    fn test_is_private_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.8",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+        ] {
+            assert!(is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "93.184.216.34",
+            "1.1.1.1",
+            "2606:4700:4700::1111",
+            "100.128.0.1",
+        ] {
+            assert!(!is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+
+        // 本机和内网的服务不缓存
+        let local = |url: &str| is_local_host(&Url::parse(url).unwrap());
+        assert!(local("http://localhost:3000/api"));
+        assert!(local("http://app.localhost/"));
+        assert!(local("http://[::1]:8080/"));
+        assert!(local("http://192.168.1.20/"));
+        assert!(!local("https://docs.rs/serde"));
+    }
+
+    #[test]
+    fn test_operation_descriptor() {
+        let args = serde_json::json!({ "method": "post", "url": "https://user:pw@api.example.com/v1/users?x=1" });
+        assert_eq!(operation_descriptor(&args), "POST api.example.com");
+        let args = serde_json::json!({ "url": "http://localhost:8080/health" });
+        assert_eq!(operation_descriptor(&args), "GET localhost:8080");
+    }
+}
@@ -0,0 +1,199 @@
+//! 图片文件信息
+//!
+//! 从文件头识别 PNG、JPEG、GIF、WebP 并读取尺寸（不解码像素）。`read_file` 读到图片、
+//! MCP 工具返回图片时在输出中附带 `ImageInfo`，交互式 CLI 据此在终端显示预览。
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// 保存 MCP 工具返回图片的临时目录（位于系统临时目录下）
+const INLINE_IMAGE_DIR: &str = "oxide-images";
+
+/// 图片文件信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub path: String,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub size_bytes: u64,
+}
+
+impl ImageInfo {
+    /// 按文件头识别图片，不是支持的图片格式时返回 `None`
+    pub fn sniff(path: &str, bytes: &[u8]) -> Option<Self> {
+        let mime_type = mime_type(bytes)?;
+        let (width, height) = match dimensions(mime_type, bytes) {
+            Some((width, height)) => (Some(width), Some(height)),
+            None => (None, None),
+        };
+        Some(Self {
+            path: path.to_string(),
+            mime_type: mime_type.to_string(),
+            width,
+            height,
+            size_bytes: bytes.len() as u64,
+        })
+    }
+
+    /// 把工具返回的图片数据写入临时目录（按内容命名，重复的图片只写一次）
+    pub fn save(bytes: &[u8], mime_type: &str) -> io::Result<Self> {
+        let extension = match mime_type {
+            "image/png" => "png",
+            "image/jpeg" => "jpg",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            _ => "bin",
+        };
+        let digest = Sha256::digest(bytes);
+        let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        let dir = std::env::temp_dir().join(INLINE_IMAGE_DIR);
+        fs::create_dir_all(&dir)?;
+        let path: PathBuf = dir.join(format!("{}.{}", name, extension));
+        if !path.exists() {
+            fs::write(&path, bytes)?;
+        }
+        let path = path.to_string_lossy().to_string();
+        Ok(Self::sniff(&path, bytes).unwrap_or_else(|| Self {
+            path,
+            mime_type: mime_type.to_string(),
+            width: None,
+            height: None,
+            size_bytes: bytes.len() as u64,
+        }))
+    }
+
+    /// 简短描述，如 `image/png 1280×720, 48213 bytes`
+    pub fn describe(&self) -> String {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => format!(
+                "{} {}×{}, {} bytes",
+                self.mime_type, width, height, self.size_bytes
+            ),
+            _ => format!("{}, {} bytes", self.mime_type, self.size_bytes),
+        }
+    }
+}
+
+fn mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+/// 从文件头读取宽高
+fn dimensions(mime_type: &str, bytes: &[u8]) -> Option<(u32, u32)> {
+    match mime_type {
+        "image/png" => {
+            let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+            let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+            Some((width, height))
+        }
+        "image/gif" => Some((le_u16(bytes, 6)?, le_u16(bytes, 8)?)),
+        "image/jpeg" => jpeg_dimensions(bytes),
+        "image/webp" => match bytes.get(12..16)? {
+            b"VP8 " => Some((le_u16(bytes, 26)? & 0x3FFF, le_u16(bytes, 28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le_u24(bytes, 24)? + 1, le_u24(bytes, 27)? + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 依次跳过 JPEG 段，读取 SOF 段中的宽高
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // 填充字节
+            0xFF => at += 1,
+            // SOF0..SOF15（DHT、JPG、DAC 除外）
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((be_u16(bytes, at + 7)?, be_u16(bytes, at + 5)?));
+            }
+            _ => at += 2 + be_u16(bytes, at + 2)? as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_sniff_formats() {
+        let png = ImageInfo::sniff("chart.png", &png_header(1280, 720)).unwrap();
+        assert_eq!(png.mime_type, "image/png");
+        assert_eq!((png.width, png.height), (Some(1280), Some(720)));
+        assert_eq!(png.describe(), "image/png 1280×720, 24 bytes");
+
+        let gif = ImageInfo::sniff("a.gif", b"GIF89a\x40\x01\xf0\x00").unwrap();
+        assert_eq!((gif.width, gif.height), (Some(320), Some(240)));
+
+        // SOI、APP0（长度 16）、SOF0：高 480、宽 640
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(&[0; 14]);
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80]);
+        let jpeg = ImageInfo::sniff("photo.jpg", &jpeg).unwrap();
+        assert_eq!(jpeg.mime_type, "image/jpeg");
+        assert_eq!((jpeg.width, jpeg.height), (Some(640), Some(480)));
+
+        // 截断的文件仍识别为图片，只是没有尺寸
+        let truncated = ImageInfo::sniff("cut.png", b"\x89PNG\r\n\x1a\n").unwrap();
+        assert_eq!(truncated.width, None);
+        assert_eq!(truncated.describe(), "image/png, 8 bytes");
+
+        assert!(ImageInfo::sniff("main.rs", b"fn main() {}").is_none());
+    }
+
+    #[test]
+    fn test_save_inline_image() {
+        let bytes = png_header(2, 3);
+        let image = ImageInfo::save(&bytes, "image/png").unwrap();
+        assert!(image.path.ends_with(".png"));
+        assert_eq!(fs::read(&image.path).unwrap(), bytes);
+        assert_eq!((image.width, image.height), (Some(2), Some(3)));
+        assert_eq!(ImageInfo::save(&bytes, "image/png").unwrap(), image);
+    }
+}
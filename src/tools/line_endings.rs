@@ -0,0 +1,149 @@
+//! 行尾处理
+//!
+//! 编辑工具统一在 LF 文本上匹配和修改，写回前恢复文件原有的行尾风格（LF/CRLF）
+//! 和末尾换行状态，避免 Windows 文件被整体转换成 LF。
+
+/// 行尾风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+}
+
+/// 文件的行尾信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEndingInfo {
+    /// 主要行尾风格（CRLF 多于 LF 时为 CRLF）
+    pub dominant: LineEnding,
+    pub crlf_count: usize,
+    pub lf_count: usize,
+    /// 是否以换行结尾（空文件为 None）
+    pub final_newline: Option<bool>,
+}
+
+impl LineEndingInfo {
+    pub fn detect(content: &str) -> Self {
+        let crlf_count = content.matches("\r\n").count();
+        let lf_count = content.matches('\n').count() - crlf_count;
+        let dominant = if crlf_count > lf_count {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        let final_newline = (!content.is_empty()).then(|| content.ends_with('\n'));
+
+        Self {
+            dominant,
+            crlf_count,
+            lf_count,
+            final_newline,
+        }
+    }
+
+    /// 是否同时存在 LF 和 CRLF
+    pub fn is_mixed(&self) -> bool {
+        self.crlf_count > 0 && self.lf_count > 0
+    }
+
+    /// 转换为用于匹配的 LF 文本
+    ///
+    /// 没有末尾换行的文件会补上换行，使补丁中最后一行的上下文可以匹配。
+    pub fn normalize(&self, content: &str) -> String {
+        let mut normalized = to_lf(content);
+        if self.final_newline == Some(false) {
+            normalized.push('\n');
+        }
+        normalized
+    }
+
+    /// 把 LF 文本恢复为原有的行尾风格和末尾换行状态
+    pub fn restore(&self, normalized: &str) -> String {
+        let mut content = normalized;
+        if self.final_newline == Some(false) {
+            content = content.strip_suffix('\n').unwrap_or(content);
+        }
+        let mut restored = match self.dominant {
+            LineEnding::Lf => content.to_string(),
+            LineEnding::CrLf => content.replace('\n', "\r\n"),
+        };
+        if self.final_newline == Some(true) && !restored.is_empty() && !restored.ends_with('\n') {
+            restored.push_str(self.dominant.as_str());
+        }
+        restored
+    }
+
+    /// 混合行尾时的提示信息
+    pub fn mixed_note(&self) -> Option<String> {
+        self.is_mixed().then(|| {
+            format!(
+                "File had mixed line endings ({} CRLF, {} LF); all lines were written as {}",
+                self.crlf_count,
+                self.lf_count,
+                self.dominant.name()
+            )
+        })
+    }
+}
+
+/// 把 CRLF 转换为 LF
+pub fn to_lf(content: &str) -> String {
+    content.replace("\r\n", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let info = LineEndingInfo::detect("a\r\nb\r\nc\n");
+        assert_eq!(info.dominant, LineEnding::CrLf);
+        assert_eq!((info.crlf_count, info.lf_count), (2, 1));
+        assert!(info.is_mixed());
+        assert_eq!(info.final_newline, Some(true));
+
+        let info = LineEndingInfo::detect("a\nb");
+        assert_eq!(info.dominant, LineEnding::Lf);
+        assert_eq!(info.final_newline, Some(false));
+        assert!(info.mixed_note().is_none());
+
+        assert_eq!(LineEndingInfo::detect("").final_newline, None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for content in ["a\r\nb\r\n", "a\r\nb", "a\nb\n", "a\nb", ""] {
+            let info = LineEndingInfo::detect(content);
+            assert_eq!(info.restore(&info.normalize(content)), content);
+        }
+    }
+
+    #[test]
+    fn test_restore_keeps_original_final_newline() {
+        let info = LineEndingInfo::detect("a\r\nb");
+        assert_eq!(info.restore("a\nc\n"), "a\r\nc");
+
+        let info = LineEndingInfo::detect("a\r\n");
+        assert_eq!(info.restore("a\nb"), "a\r\nb\r\n");
+
+        // 空文件没有原有状态，按修改结果写入
+        let info = LineEndingInfo::detect("");
+        assert_eq!(info.restore("a\n"), "a\n");
+    }
+}
@@ -0,0 +1,893 @@
+//! 项目记忆工具
+//!
+//! 模型在用户要求"记住"某件事时调用，把笔记写入项目的 `.oxide/memory.md` 中
+//! `## Agent notes` 部分，每条带时间戳；文件的其他内容由用户维护，不会被改动。
+//! 笔记部分超过 `[memory] max_notes_bytes` 时删除最早的笔记。
+//! 每轮对话发送前都会重新读取该文件附加到用户消息中，新笔记在下一轮生效。
+//!
+//! 开启 `[behavior] session_learning` 时，会话结束（或 `/save-notes`）整理出的事实经用户确认后
+//! 追加到当天的 `## Session learnings (YYYY-MM-DD)` 部分，不受笔记上限约束。
+
+use super::FileToolError;
+use crate::atomic_file::write_atomic;
+use crate::config::ConfigLoader;
+use crate::file_lock::{FileLock, DEFAULT_LOCK_TIMEOUT};
+use colored::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 笔记部分的标题
+const NOTES_HEADING: &str = "## Agent notes";
+
+/// 标题下的说明
+const NOTES_COMMENT: &str =
+    "<!-- Maintained by oxide's memory tool. Oldest notes are pruned when this section exceeds [memory] max_notes_bytes. -->";
+
+/// 会话整理出的事实所在部分的标题（后接日期）
+const LEARNINGS_HEADING: &str = "## Session learnings";
+
+/// 附加到用户消息时的最大字符数
+const MAX_PROMPT_CHARS: usize = 16_000;
+
+/// 项目记忆文件路径
+pub fn project_memory_path() -> PathBuf {
+    PathBuf::from(".oxide").join("memory.md")
+}
+
+/// 一条笔记
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemoryNote {
+    /// 序号（从 1 开始，最早的在前）
+    pub id: usize,
+    /// 记录时间（`YYYY-MM-DD HH:MM`，手写的笔记可能没有）
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub timestamp: String,
+    pub text: String,
+}
+
+impl MemoryNote {
+    fn line(&self) -> String {
+        if self.timestamp.is_empty() {
+            format!("- {}", self.text)
+        } else {
+            format!("- [{}] {}", self.timestamp, self.text)
+        }
+    }
+
+    /// 在笔记部分占用的字节数（含换行）
+    fn size(&self) -> usize {
+        self.line().len() + 1
+    }
+}
+
+/// 解析后的记忆文件：笔记部分之外的内容原样保留
+#[derive(Debug, Default)]
+struct MemoryFile {
+    before: String,
+    /// 笔记部分中不是笔记的行（用户手写的说明等）
+    extra: Vec<String>,
+    notes: Vec<MemoryNote>,
+    after: String,
+}
+
+impl MemoryFile {
+    fn parse(content: &str) -> Self {
+        static NOTE: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"^[-*]\s+(?:\[(\d{4}-\d{2}-\d{2}[^\]]*)\]\s*)?(.+)$").unwrap()
+        });
+
+        let lines: Vec<&str> = content.lines().collect();
+        let Some(start) = lines
+            .iter()
+            .position(|line| line.trim().eq_ignore_ascii_case(NOTES_HEADING))
+        else {
+            return Self {
+                before: content.to_string(),
+                ..Self::default()
+            };
+        };
+        // 笔记部分到下一个一级或二级标题为止
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| line.starts_with("# ") || line.starts_with("## "))
+            .map_or(lines.len(), |offset| start + 1 + offset);
+
+        let mut file = Self {
+            before: lines[..start].join("\n"),
+            after: lines[end..].join("\n"),
+            ..Self::default()
+        };
+        for line in &lines[start + 1..end] {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed == NOTES_COMMENT {
+                continue;
+            }
+            match NOTE.captures(trimmed) {
+                Some(caps) => file.notes.push(MemoryNote {
+                    id: file.notes.len() + 1,
+                    timestamp: caps.get(1).map_or("", |m| m.as_str()).to_string(),
+                    text: caps[2].trim().to_string(),
+                }),
+                None => file.extra.push(line.to_string()),
+            }
+        }
+        file
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let before = self.before.trim_end();
+        if !before.is_empty() {
+            out.push_str(before);
+            out.push_str("\n\n");
+        }
+        out.push_str(NOTES_HEADING);
+        out.push('\n');
+        out.push_str(NOTES_COMMENT);
+        out.push('\n');
+        for line in &self.extra {
+            out.push_str(line);
+            out.push('\n');
+        }
+        if !self.notes.is_empty() {
+            out.push('\n');
+        }
+        for note in &self.notes {
+            out.push_str(&note.line());
+            out.push('\n');
+        }
+        let after = self.after.trim();
+        if !after.is_empty() {
+            out.push('\n');
+            out.push_str(after);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn renumber(&mut self) {
+        for (index, note) in self.notes.iter_mut().enumerate() {
+            note.id = index + 1;
+        }
+    }
+
+    fn notes_size(&self) -> usize {
+        self.notes.iter().map(MemoryNote::size).sum()
+    }
+}
+
+/// 项目记忆文件的读写
+pub struct MemoryStore {
+    path: PathBuf,
+    max_notes_bytes: usize,
+}
+
+impl MemoryStore {
+    pub fn new(path: impl Into<PathBuf>, max_notes_bytes: usize) -> Self {
+        Self {
+            path: path.into(),
+            max_notes_bytes,
+        }
+    }
+
+    /// 当前项目的记忆文件，上限取自配置
+    pub fn project() -> Self {
+        let config = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.memory)
+            .unwrap_or_default();
+        Self::new(project_memory_path(), config.max_notes_bytes)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn max_notes_bytes(&self) -> usize {
+        self.max_notes_bytes
+    }
+
+    fn read(&self) -> Result<MemoryFile, FileToolError> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(MemoryFile::parse(&content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MemoryFile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 在锁内读取、修改并写回
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut MemoryFile) -> Result<T, FileToolError>,
+    ) -> Result<(T, MemoryFile), FileToolError> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let _lock = FileLock::acquire(&self.path, DEFAULT_LOCK_TIMEOUT)
+            .map_err(|e| FileToolError::Io(std::io::Error::other(e.to_string())))?;
+        let mut file = self.read()?;
+        let result = change(&mut file)?;
+        file.renumber();
+        write_atomic(&self.path, file.render().as_bytes())?;
+        Ok((result, file))
+    }
+
+    pub fn list(&self) -> Result<Vec<MemoryNote>, FileToolError> {
+        Ok(self.read()?.notes)
+    }
+
+    /// 记忆文件的全部内容（文件不存在时为空）
+    pub fn content(&self) -> Result<String, FileToolError> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 把会话中整理出的事实追加到 `date` 当天的 learnings 部分，返回实际写入的事实
+    ///
+    /// 与文件中已有内容重复的事实被跳过；其余内容原样保留。
+    pub fn append_learnings(
+        &self,
+        date: &str,
+        facts: &[String],
+    ) -> Result<Vec<String>, FileToolError> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let _lock = FileLock::acquire(&self.path, DEFAULT_LOCK_TIMEOUT)
+            .map_err(|e| FileToolError::Io(std::io::Error::other(e.to_string())))?;
+        let content = self.content()?;
+        let added = new_facts(&content, facts);
+        if !added.is_empty() {
+            write_atomic(
+                &self.path,
+                merge_learnings(&content, date, &added).as_bytes(),
+            )?;
+        }
+        Ok(added)
+    }
+
+    /// 添加一条笔记，返回新笔记和因超出上限被删除的旧笔记
+    pub fn add(&self, text: &str) -> Result<(MemoryNote, Vec<MemoryNote>), FileToolError> {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return Err(FileToolError::InvalidInput(
+                "Note text is empty".to_string(),
+            ));
+        }
+        let note = MemoryNote {
+            id: 0,
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            text,
+        };
+        if note.size() > self.max_notes_bytes {
+            return Err(FileToolError::InvalidInput(format!(
+                "Note is {} bytes, larger than the {} byte limit for agent notes; shorten it",
+                note.size(),
+                self.max_notes_bytes
+            )));
+        }
+
+        let max = self.max_notes_bytes;
+        let (pruned, file) = self.update(|file| {
+            file.notes.push(note);
+            let mut pruned = Vec::new();
+            while file.notes_size() > max {
+                pruned.push(file.notes.remove(0));
+            }
+            Ok(pruned)
+        })?;
+        let added = file.notes.last().cloned().expect("note was just added");
+        Ok((added, pruned))
+    }
+
+    /// 按序号，或按唯一匹配的文本片段（不区分大小写）删除笔记
+    pub fn remove(
+        &self,
+        id: Option<usize>,
+        text: Option<&str>,
+    ) -> Result<MemoryNote, FileToolError> {
+        let (removed, _) = self.update(|file| {
+            let index = match (id, text.map(str::trim).filter(|t| !t.is_empty())) {
+                (Some(id), _) => {
+                    if id == 0 || id > file.notes.len() {
+                        return Err(FileToolError::InvalidInput(format!(
+                            "No note with id {} ({} notes)",
+                            id,
+                            file.notes.len()
+                        )));
+                    }
+                    id - 1
+                }
+                (None, Some(text)) => {
+                    let needle = text.to_lowercase();
+                    let matches: Vec<usize> = file
+                        .notes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, note)| note.text.to_lowercase().contains(&needle))
+                        .map(|(index, _)| index)
+                        .collect();
+                    match matches.as_slice() {
+                        [index] => *index,
+                        [] => {
+                            return Err(FileToolError::InvalidInput(format!(
+                                "No note matches '{}'",
+                                text
+                            )))
+                        }
+                        _ => {
+                            return Err(FileToolError::InvalidInput(format!(
+                                "'{}' matches {} notes (ids {}); pass the id instead",
+                                text,
+                                matches.len(),
+                                matches
+                                    .iter()
+                                    .map(|index| (index + 1).to_string())
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )))
+                        }
+                    }
+                }
+                (None, None) => {
+                    return Err(FileToolError::InvalidInput(
+                        "remove needs either `id` or `text`".to_string(),
+                    ))
+                }
+            };
+            Ok(file.notes.remove(index))
+        })?;
+        Ok(removed)
+    }
+}
+
+/// 比较事实时忽略大小写、标点和空白
+fn normalize_fact(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 从 `facts` 中去掉记忆文件已包含的事实和彼此重复的事实
+///
+/// 规范化后与某一行相同，或是文件中某一行的一部分，都算已包含。
+pub fn new_facts(memory: &str, facts: &[String]) -> Vec<String> {
+    let mut known: Vec<String> = memory
+        .lines()
+        .map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*', ' ']);
+            // 去掉笔记的时间戳
+            let line = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                Some((_, text)) => text,
+                None => line,
+            };
+            normalize_fact(line)
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    let mut added = Vec::new();
+    for fact in facts {
+        let fact = fact.split_whitespace().collect::<Vec<_>>().join(" ");
+        let normalized = normalize_fact(&fact);
+        if normalized.is_empty()
+            || known.iter().any(|line| {
+                line == &normalized || format!(" {} ", line).contains(&format!(" {} ", normalized))
+            })
+        {
+            continue;
+        }
+        known.push(normalized);
+        added.push(fact);
+    }
+    added
+}
+
+/// 把事实作为列表项追加到 `date` 当天的 learnings 部分（不存在时在文件末尾新建）
+fn merge_learnings(content: &str, date: &str, facts: &[String]) -> String {
+    let heading = format!("{} ({})", LEARNINGS_HEADING, date);
+    let items: Vec<String> = facts.iter().map(|fact| format!("- {}", fact)).collect();
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    match lines.iter().position(|line| line.trim() == heading) {
+        Some(start) => {
+            // 插在该部分最后一个非空行之后
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| line.starts_with("# ") || line.starts_with("## "))
+                .map_or(lines.len(), |offset| start + 1 + offset);
+            let last = lines[start..end]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map_or(start, |offset| start + offset);
+            let at = if last == start {
+                lines.insert(start + 1, String::new());
+                start + 2
+            } else {
+                last + 1
+            };
+            lines.splice(at..at, items);
+            let mut out = lines.join("\n");
+            out.push('\n');
+            out
+        }
+        None => {
+            let mut out = content.trim_end().to_string();
+            if !out.is_empty() {
+                out.push_str("\n\n");
+            }
+            out.push_str(&heading);
+            out.push_str("\n\n");
+            for item in items {
+                out.push_str(&item);
+                out.push('\n');
+            }
+            out
+        }
+    }
+}
+
+/// 把项目记忆附加到发送给模型的用户消息前
+///
+/// 每轮都重新读取文件，因此模型或用户刚写下的笔记在下一轮即可生效。
+pub fn with_project_memory(input: &str) -> String {
+    match memory_context(&project_memory_path()) {
+        Some(memory) => format!("{}\n\n{}", memory, input),
+        None => input.to_string(),
+    }
+}
+
+fn memory_context(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let content = content.trim();
+    if content.is_empty() {
+        return None;
+    }
+    let content = match content.char_indices().nth(MAX_PROMPT_CHARS) {
+        Some((index, _)) => format!("{}\n…(truncated)", &content[..index]),
+        None => content.to_string(),
+    };
+    Some(format!(
+        "<project-memory path=\"{}\">\nDurable notes about this project. Follow them unless the user says otherwise.\n\n{}\n</project-memory>",
+        path.display(),
+        content
+    ))
+}
+
+/// 操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryAction {
+    Add,
+    List,
+    Remove,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MemoryArgs {
+    pub action: MemoryAction,
+    /// add：笔记内容；remove：要删除的笔记中的文本片段
+    #[serde(default)]
+    pub text: Option<String>,
+    /// remove：笔记序号（来自 list）
+    #[serde(default)]
+    pub id: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryOutput {
+    pub action: MemoryAction,
+    pub path: String,
+    /// 操作后的全部笔记
+    pub notes: Vec<MemoryNote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added: Option<MemoryNote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<MemoryNote>,
+    /// 因超出上限被删除的最早笔记
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pruned: Vec<MemoryNote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    pub used_bytes: usize,
+    pub max_bytes: usize,
+}
+
+/// 执行一次记忆操作
+pub fn run(store: &MemoryStore, args: &MemoryArgs) -> Result<MemoryOutput, FileToolError> {
+    let mut added = None;
+    let mut removed = None;
+    let mut pruned = Vec::new();
+    match args.action {
+        MemoryAction::Add => {
+            let text = args
+                .text
+                .as_deref()
+                .ok_or_else(|| FileToolError::InvalidInput("add needs `text`".to_string()))?;
+            let (note, dropped) = store.add(text)?;
+            added = Some(note);
+            pruned = dropped;
+        }
+        MemoryAction::Remove => {
+            removed = Some(store.remove(args.id, args.text.as_deref())?);
+        }
+        MemoryAction::List => {}
+    }
+
+    let notes = store.list()?;
+    let warning = (!pruned.is_empty()).then(|| {
+        format!(
+            "Agent notes exceeded {} bytes; removed {} oldest note(s)",
+            store.max_notes_bytes(),
+            pruned.len()
+        )
+    });
+    Ok(MemoryOutput {
+        action: args.action,
+        path: store.path().display().to_string(),
+        used_bytes: notes.iter().map(MemoryNote::size).sum(),
+        max_bytes: store.max_notes_bytes(),
+        notes,
+        added,
+        removed,
+        pruned,
+        warning,
+    })
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MemoryTool;
+
+impl Tool for MemoryTool {
+    const NAME: &'static str = "memory";
+
+    type Error = FileToolError;
+    type Args = MemoryArgs;
+    type Output = MemoryOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "memory".to_string(),
+            description: "Persist short, durable facts about this project across sessions (e.g. 'the staging DB is read-only', 'run tests with --features e2e'). Notes are stored in .oxide/memory.md and shown to you at the start of every turn. Only add a note when the user asks you to remember something or states a lasting project convention; do not store secrets or transient task state. Actions: add (text), list, remove (id from list, or a unique text fragment).".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["add", "list", "remove"]
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "add: the note (one line); remove: a fragment that identifies exactly one note"
+                    },
+                    "id": {
+                        "type": "integer",
+                        "description": "remove: the note id returned by list"
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        run(&MemoryStore::project(), &args)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedMemoryTool {
+    inner: MemoryTool,
+}
+
+impl WrappedMemoryTool {
+    pub fn new() -> Self {
+        Self { inner: MemoryTool }
+    }
+}
+
+impl Default for WrappedMemoryTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tool for WrappedMemoryTool {
+    const NAME: &'static str = "memory";
+
+    type Error = FileToolError;
+    type Args = <MemoryTool as Tool>::Args;
+    type Output = <MemoryTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        let label = match args.action {
+            MemoryAction::Add => "add",
+            MemoryAction::List => "list",
+            MemoryAction::Remove => "remove",
+        };
+        tool_println!(
+            "{} {}({})",
+            "●".bright_magenta(),
+            "Memory".bright_magenta(),
+            label.bright_white()
+        );
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => {
+                if let Some(note) = &output.added {
+                    tool_println!(
+                        "  └─ {} {}",
+                        t!("tools.memory.remembered").green(),
+                        note.text
+                    );
+                } else if let Some(note) = &output.removed {
+                    tool_println!("  └─ {} {}", t!("tools.memory.removed").yellow(), note.text);
+                } else {
+                    tool_println!(
+                        "  └─ {}",
+                        t!(
+                            "tools.memory.summary",
+                            count = output.notes.len(),
+                            used = output.used_bytes,
+                            max = output.max_bytes
+                        )
+                        .dimmed()
+                    );
+                }
+                if let Some(warning) = &output.warning {
+                    tool_println!("  {} {}", "⚠".bright_yellow(), warning.bright_yellow());
+                }
+            }
+            Err(e) => {
+                tool_println!("  └─ {} {}", t!("common.error").red(), e.message().red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MemoryConfig;
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir, max: usize) -> MemoryStore {
+        MemoryStore::new(dir.path().join(".oxide/memory.md"), max)
+    }
+
+    fn args(action: MemoryAction, text: Option<&str>, id: Option<usize>) -> MemoryArgs {
+        MemoryArgs {
+            action,
+            text: text.map(String::from),
+            id,
+        }
+    }
+
+    #[test]
+    fn test_add_list_remove_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir, MemoryConfig::default().max_notes_bytes);
+
+        let output = run(
+            &store,
+            &args(MemoryAction::Add, Some("The staging DB is read-only"), None),
+        )
+        .unwrap();
+        assert_eq!(output.added.as_ref().unwrap().id, 1);
+        run(
+            &store,
+            &args(
+                MemoryAction::Add,
+                Some("Run e2e tests with\n  --features e2e"),
+                None,
+            ),
+        )
+        .unwrap();
+        run(
+            &store,
+            &args(MemoryAction::Add, Some("Deploys go through CI only"), None),
+        )
+        .unwrap();
+
+        let listed = run(&store, &args(MemoryAction::List, None, None)).unwrap();
+        let texts: Vec<&str> = listed.notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "The staging DB is read-only",
+                "Run e2e tests with --features e2e",
+                "Deploys go through CI only"
+            ]
+        );
+        assert!(listed
+            .notes
+            .iter()
+            .all(|n| n.timestamp.len() == "2026-01-01 00:00".len()));
+
+        let removed = run(&store, &args(MemoryAction::Remove, None, Some(2))).unwrap();
+        assert_eq!(
+            removed.removed.unwrap().text,
+            "Run e2e tests with --features e2e"
+        );
+        let removed = run(&store, &args(MemoryAction::Remove, Some("STAGING"), None)).unwrap();
+        assert_eq!(removed.removed.unwrap().text, "The staging DB is read-only");
+        assert_eq!(removed.notes.len(), 1);
+        assert_eq!(removed.notes[0].id, 1);
+
+        assert!(run(&store, &args(MemoryAction::Remove, None, Some(5))).is_err());
+        assert!(run(&store, &args(MemoryAction::Remove, Some("missing"), None)).is_err());
+        assert!(run(&store, &args(MemoryAction::Add, Some("   "), None)).is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_remove_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir, 4096);
+        store.add("Use pnpm, not npm").unwrap();
+        store.add("Use rustls, not openssl").unwrap();
+
+        let err = store.remove(None, Some("use")).unwrap_err();
+        assert!(err.to_string().contains("ids 1, 2"), "{}", err);
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_user_content_is_preserved() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir, 4096);
+        fs::create_dir_all(dir.path().join(".oxide")).unwrap();
+        fs::write(
+            store.path(),
+            "# Project memory\n\nWritten by hand.\n\n## Agent notes\n\n- [2026-01-02 09:30] Old note\n- hand-written bullet\n\n## Glossary\n\n- PAOR: plan/act/observe/reflect\n",
+        )
+        .unwrap();
+
+        let notes = store.list().unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].timestamp, "2026-01-02 09:30");
+        assert_eq!(notes[1].timestamp, "");
+        assert_eq!(notes[1].text, "hand-written bullet");
+
+        store.add("New note").unwrap();
+        let content = fs::read_to_string(store.path()).unwrap();
+        assert!(content.starts_with("# Project memory\n\nWritten by hand.\n\n## Agent notes\n"));
+        assert!(
+            content.ends_with("] New note\n\n## Glossary\n\n- PAOR: plan/act/observe/reflect\n")
+        );
+        assert_eq!(store.list().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_size_cap_prunes_oldest() {
+        let dir = TempDir::new().unwrap();
+        // 每条笔记为 "- [YYYY-MM-DD HH:MM] note N" + 换行 = 28 字节
+        let store = store(&dir, 28 * 3);
+        for n in 1..=3 {
+            let (_, pruned) = store.add(&format!("note {}", n)).unwrap();
+            assert!(pruned.is_empty());
+        }
+
+        let output = run(&store, &args(MemoryAction::Add, Some("note 4"), None)).unwrap();
+        assert_eq!(output.pruned.len(), 1);
+        assert_eq!(output.pruned[0].text, "note 1");
+        assert!(output.warning.unwrap().contains("removed 1 oldest"));
+        let texts: Vec<String> = output.notes.iter().map(|n| n.text.clone()).collect();
+        assert_eq!(texts, vec!["note 2", "note 3", "note 4"]);
+        assert!(output.used_bytes <= output.max_bytes);
+
+        let err = store.add(&"x".repeat(100)).unwrap_err();
+        assert!(err.to_string().contains("larger than"), "{}", err);
+    }
+
+    /// 已有记忆文件：用户手写的内容、Agent notes 和更早的 learnings
+    const FIXTURE: &str = "# Project memory\n\nUse `cargo nextest run` for tests.\n\n## Agent notes\n\n- [2026-01-02 09:30] The staging DB is read-only\n\n## Session learnings (2026-10-01)\n\n- Migrations live in db/migrations and run with `make migrate`\n";
+
+    #[test]
+    fn test_new_facts_skips_known_and_repeated_facts() {
+        let facts: Vec<String> = [
+            "use cargo nextest run for tests",
+            "The staging DB is read-only.",
+            "Migrations live in db/migrations",
+            "Release builds need the `vendored` feature",
+            "release builds need the vendored feature!",
+            "  ",
+            "The CI cache key includes\n  rust-toolchain.toml",
+        ]
+        .iter()
+        .map(|fact| fact.to_string())
+        .collect();
+        assert_eq!(
+            new_facts(FIXTURE, &facts),
+            vec![
+                "Release builds need the `vendored` feature",
+                "The CI cache key includes rust-toolchain.toml"
+            ]
+        );
+        // 只是某个词的一部分不算重复
+        assert_eq!(
+            new_facts(FIXTURE, &["DB".to_string(), "stag".to_string()]),
+            vec!["stag"]
+        );
+    }
+
+    #[test]
+    fn test_append_learnings_merges_under_dated_heading() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir, 4096);
+        fs::create_dir_all(dir.path().join(".oxide")).unwrap();
+        fs::write(store.path(), FIXTURE).unwrap();
+
+        let added = store
+            .append_learnings(
+                "2026-10-18",
+                &[
+                    "The staging DB is read-only".to_string(),
+                    "Release builds need the `vendored` feature".to_string(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(added, vec!["Release builds need the `vendored` feature"]);
+        store
+            .append_learnings("2026-10-18", &["Docs are built with mdbook".to_string()])
+            .unwrap();
+        store
+            .append_learnings("2026-10-01", &["Use rustls, not openssl".to_string()])
+            .unwrap();
+        assert!(store
+            .append_learnings("2026-10-18", &["docs are built with mdBook".to_string()])
+            .unwrap()
+            .is_empty());
+
+        let content = fs::read_to_string(store.path()).unwrap();
+        assert_eq!(
+            content,
+            FIXTURE.replace(
+                "`make migrate`\n",
+                "`make migrate`\n- Use rustls, not openssl\n\n## Session learnings (2026-10-18)\n\n- Release builds need the `vendored` feature\n- Docs are built with mdbook\n"
+            )
+        );
+        // Agent notes 不受影响
+        assert_eq!(store.list().unwrap().len(), 1);
+        store.add("New note").unwrap();
+        assert!(fs::read_to_string(store.path())
+            .unwrap()
+            .ends_with("- Docs are built with mdbook\n"));
+    }
+
+    #[test]
+    fn test_memory_context_picks_up_notes() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir, 4096);
+        assert_eq!(memory_context(store.path()), None);
+
+        store.add("The staging DB is read-only").unwrap();
+        let context = memory_context(store.path()).unwrap();
+        assert!(context.starts_with("<project-memory"));
+        assert!(context.contains("] The staging DB is read-only"));
+        assert!(context.ends_with("</project-memory>"));
+    }
+}
@@ -1,37 +1,159 @@
-use thiserror::Error;
+use std::fmt;
 
-#[derive(Debug, Error)]
+/// 工具错误
+///
+/// 显示为 [`ToolFailure`] 的 JSON（rig 把它作为出错的工具结果交给模型），
+/// 终端中显示 [`FileToolError::message`]。
+#[derive(Debug)]
 pub enum FileToolError {
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("File not found: {0}")]
+    Io(std::io::Error),
     FileNotFound(String),
-    #[error("Permission denied: {0}")]
     PermissionDenied(String),
-    #[error("Path is not a file: {0}")]
     NotAFile(String),
-    #[error("Input is invalid: {0}")]
     InvalidInput(String),
-    #[error("Operation cancelled by user")]
     #[allow(dead_code)]
     Cancelled,
+    /// 工具给出了具体错误码和建议的失败
+    Failed(Box<ToolFailure>),
 }
 
+impl FileToolError {
+    /// 返回给模型的结构化错误
+    pub fn failure(&self) -> ToolFailure {
+        match self {
+            FileToolError::Io(e) => io_failure(e),
+            FileToolError::FileNotFound(path) => {
+                ToolFailure::new(ErrorCode::NotFound, format!("File not found: {}", path))
+                    .with_suggestion(
+                        "check the path with glob; paths are relative to the working directory",
+                    )
+            }
+            FileToolError::PermissionDenied(detail) => ToolFailure::new(
+                ErrorCode::PermissionDenied,
+                format!("Permission denied: {}", detail),
+            )
+            .with_suggestion("do not retry the same path; ask the user if you need access"),
+            FileToolError::NotAFile(path) => ToolFailure::new(
+                ErrorCode::InvalidArgs,
+                format!("Path is not a file: {}", path),
+            )
+            .with_suggestion("this is a directory; use glob to list the files in it"),
+            FileToolError::InvalidInput(message) => ToolFailure::new(
+                ErrorCode::InvalidArgs,
+                format!("Input is invalid: {}", message),
+            ),
+            FileToolError::Cancelled => {
+                ToolFailure::new(ErrorCode::Blocked, "Operation cancelled by user").with_suggestion(
+                    "the user declined this call; do not repeat it, ask how to proceed",
+                )
+            }
+            FileToolError::Failed(failure) => (**failure).clone(),
+        }
+    }
+
+    /// 终端中显示的错误说明
+    pub fn message(&self) -> String {
+        self.failure().message
+    }
+}
+
+/// 在工具结果消息后追加提示（混合行尾、写入后格式化等）
+pub(crate) fn with_note(message: String, note: Option<String>) -> String {
+    match note {
+        Some(note) => format!("{} ({})", message, note),
+        None => message,
+    }
+}
+
+fn io_failure(e: &std::io::Error) -> ToolFailure {
+    use std::io::ErrorKind;
+    let message = format!("IO error: {}", e);
+    match e.kind() {
+        ErrorKind::NotFound => ToolFailure::new(ErrorCode::NotFound, message),
+        ErrorKind::PermissionDenied => ToolFailure::new(ErrorCode::PermissionDenied, message),
+        ErrorKind::TimedOut => ToolFailure::new(ErrorCode::Timeout, message),
+        ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+            ToolFailure::new(ErrorCode::InvalidArgs, message)
+        }
+        _ => ToolFailure::new(ErrorCode::Blocked, message),
+    }
+}
+
+impl fmt::Display for FileToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.failure().fmt(f)
+    }
+}
+
+impl std::error::Error for FileToolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileToolError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FileToolError {
+    fn from(error: std::io::Error) -> Self {
+        FileToolError::Io(error)
+    }
+}
+
+impl From<ToolFailure> for FileToolError {
+    fn from(failure: ToolFailure) -> Self {
+        FileToolError::Failed(Box::new(failure))
+    }
+}
+
+impl From<crate::agent::interaction::NoTerminal> for FileToolError {
+    fn from(error: crate::agent::interaction::NoTerminal) -> Self {
+        ToolFailure::new(ErrorCode::Blocked, error.to_string())
+            .with_suggestion("the user cannot answer in this session; continue without asking")
+            .into()
+    }
+}
+
+// `tool_println!` 在这里定义，需要先于其他工具模块声明
+#[macro_use]
+pub mod verbose_output;
+pub mod apply_patch;
 pub mod ask_user_question;
 pub mod commit_linter;
 pub mod create_directory;
 pub mod delete_file;
+pub mod deps;
+pub mod diagnostics;
+pub mod diff;
+pub mod direct_shell;
+pub mod edit_batch;
 pub mod edit_file;
+pub mod encoding;
+pub mod failure;
+pub mod format_on_write;
 pub mod git_guard;
 pub mod glob;
 pub mod grep_search;
+pub mod http_request;
+pub mod image;
+pub mod line_endings;
+pub mod memory;
 pub mod multiedit;
+pub mod network;
 pub mod notebook_edit;
+pub mod outline;
+pub mod partial_patch;
 pub mod plan_mode;
 pub mod read_file;
+pub mod redactor;
+pub mod run_tests;
+pub mod safe_write;
+pub mod sandbox;
 pub mod scan_codebase;
 pub mod write_file;
 pub mod search_replace;
+pub mod sensitive_paths;
+pub mod settings;
 pub mod shell_execute;
 pub mod task;
 pub mod task_output;
@@ -39,23 +161,40 @@ pub mod task_create;
 pub mod task_update;
 pub mod task_list;
 pub mod task_get;
+pub mod task_stop;
+pub mod tool_args;
+pub mod uncommitted;
+pub mod web_cache;
+pub mod workspace;
 
+pub use apply_patch::WrappedApplyPatchTool;
 pub use ask_user_question::WrappedAskUserQuestionTool;
 pub use create_directory::WrappedCreateDirectoryTool;
 pub use delete_file::WrappedDeleteFileTool;
+pub use deps::WrappedDepsTool;
+pub use diagnostics::WrappedDiagnosticsTool;
+pub use diff::WrappedDiffTool;
 pub use edit_file::WrappedEditFileTool;
+pub use failure::{ErrorCode, ToolFailure};
 pub use glob::WrappedGlobTool;
 pub use grep_search::WrappedGrepSearchTool;
+pub use http_request::WrappedHttpRequestTool;
+pub use memory::WrappedMemoryTool;
+pub use outline::WrappedOutlineTool;
 pub use plan_mode::{WrappedEnterPlanModeTool, WrappedExitPlanModeTool};
-pub use plan_mode::{AllowedPrompt, PlanModeState, is_in_plan_mode, is_plan_approved, is_operation_allowed, set_plan_content, get_plan_state};
+pub use plan_mode::{AllowedPrompt, PlanModeError, PlanModeManager, PlanModeState};
 pub use read_file::WrappedReadFileTool;
+pub use run_tests::WrappedRunTestsTool;
 pub use scan_codebase::WrappedScanCodebaseTool;
 pub use write_file::WrappedWriteFileTool;
 pub use shell_execute::WrappedShellExecuteTool;
 pub use search_replace::WrappedSearchReplaceTool;
+pub use settings::ToolSettings;
+pub use workspace::{WorkspaceRoot, WorkspaceRoots};
 
 // 任务管理工具
 pub use task_create::WrappedTaskCreateTool;
 pub use task_update::WrappedTaskUpdateTool;
 pub use task_list::WrappedTaskListTool;
 pub use task_get::WrappedTaskGetTool;
+pub use task_stop::WrappedTaskStopTool;
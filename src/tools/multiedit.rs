@@ -81,7 +81,7 @@ impl MultiEditTool {
     /// 创建新的 MultiEdit 工具
     pub fn new() -> Self {
         Self {
-            edit_tool: EditFileTool,
+            edit_tool: EditFileTool::default(),
         }
     }
 
@@ -188,6 +188,7 @@ impl Tool for MultiEditTool {
                 file_path: operation.file_path.clone(),
                 patch: operation.patch.clone(),
                 confirmation: None,
+                partial: false,
             };
 
             match self.edit_tool.call(edit_args).await {
@@ -275,24 +276,24 @@ impl Tool for WrappedMultiEditTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!(
-            "{} {} ({} 文件)",
+        tool_println!();
+        tool_println!(
+            "{} {} {}",
             "●".bright_green(),
             "MultiEdit",
-            args.edits.len()
+            t!("tools.multiedit.files", count = args.edits.len())
         );
 
         let result = self.inner.call(args).await;
 
         match &result {
             Ok(output) => {
-                println!(
+                tool_println!(
                     "  └─ {}",
-                    format!(
-                        "完成: {} 成功, {} 失败",
-                        output.successful_operations.to_string().green(),
-                        output.failed_operations.to_string().red()
+                    t!(
+                        "tools.multiedit.done",
+                        succeeded = output.successful_operations.to_string().green(),
+                        failed = output.failed_operations.to_string().red()
                     )
                     .dimmed()
                 );
@@ -300,7 +301,7 @@ impl Tool for WrappedMultiEditTool {
                 // 显示详细结果
                 for edit_result in &output.results {
                     if edit_result.success {
-                        println!(
+                        tool_println!(
                             "    ✓ {} (+{} lines, -{} lines)",
                             edit_result.file_path.bright_green(),
                             edit_result
@@ -315,19 +316,26 @@ impl Tool for WrappedMultiEditTool {
                                 .red()
                         );
                     } else {
-                        println!(
+                        tool_println!(
                             "    ✗ {} - {}",
                             edit_result.file_path.bright_red(),
-                            edit_result.error.as_ref().unwrap_or(&"未知错误".to_string()).red()
+                            edit_result
+                                .error
+                                .as_deref()
+                                .unwrap_or(t!("common.unknown_error"))
+                                .red()
                         );
                     }
                 }
             }
             Err(e) => {
-                println!("  └─ {}", format!("错误: {}", e).red());
+                tool_println!(
+                    "  └─ {}",
+                    format!("{} {}", t!("common.error"), e.message()).red()
+                );
             }
         }
-        println!();
+        tool_println!();
 
         result
     }
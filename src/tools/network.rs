@@ -0,0 +1,272 @@
+//! 工具的网络访问策略
+//!
+//! `[network] tools` 控制工具能否联网，对模型服务商的请求不受影响：
+//! - `all`（默认）：不限制
+//! - `allowlist`：只能访问 `[network] allowed_domains` 中的域名及其子域名
+//! - `none`：不注册 http_request，deps 不查询最新版本（命令行 `--offline-tools` 等同于此模式）
+//!
+//! 联网的工具都通过 [`ToolHttpClient`] 发送请求，发出请求和跟随每一次重定向前按当前策略
+//! 检查目标，被拒绝时返回 `Blocked` 并说明生效的策略。
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use reqwest::redirect::Policy;
+use reqwest::{ClientBuilder, Method, RequestBuilder, Url};
+use std::fmt;
+use std::sync::RwLock;
+
+use super::{ErrorCode, ToolFailure};
+use crate::config::{NetworkConfig, NetworkMode};
+
+/// 工具请求默认的 User-Agent
+const USER_AGENT: &str = concat!("oxide/", env!("CARGO_PKG_VERSION"));
+
+/// 客户端自动跟随的最大重定向次数
+const MAX_REDIRECTS: usize = 10;
+
+static POLICY: Lazy<RwLock<NetworkPolicy>> = Lazy::new(|| RwLock::new(NetworkPolicy::default()));
+
+/// 允许访问的域名，`port` 为空时不限端口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainRule {
+    domain: String,
+    port: Option<u16>,
+}
+
+impl DomainRule {
+    /// 解析 `example.com`、`*.example.com` 或 `example.com:8443`
+    pub fn parse(entry: &str) -> Result<Self> {
+        let entry = entry.trim().to_ascii_lowercase();
+        let (host, port) = match entry.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, Some(port)),
+                Err(_) => bail!(
+                    "Invalid network.allowed_domains entry '{}': bad port",
+                    entry
+                ),
+            },
+            None => (entry.as_str(), None),
+        };
+        let domain = host.trim_start_matches("*.").trim_end_matches('.');
+        if domain.is_empty() || domain.contains(['/', '*', ':', ' ']) {
+            bail!(
+                "Invalid network.allowed_domains entry '{}': expected a domain such as example.com or example.com:8443",
+                entry
+            );
+        }
+        Ok(Self {
+            domain: domain.to_string(),
+            port,
+        })
+    }
+
+    /// 主机是该域名或其子域名，且端口符合
+    pub fn matches(&self, host: &str, port: Option<u16>) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let domain_matches = host == self.domain
+            || host
+                .strip_suffix(self.domain.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'));
+        domain_matches && self.port.is_none_or(|allowed| port == Some(allowed))
+    }
+}
+
+impl fmt::Display for DomainRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.domain, port),
+            None => f.write_str(&self.domain),
+        }
+    }
+}
+
+/// 解析后的网络访问策略
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkPolicy {
+    pub mode: NetworkMode,
+    pub allowed_domains: Vec<DomainRule>,
+}
+
+impl NetworkPolicy {
+    /// 校验并解析 `[network]` 配置
+    pub fn from_config(config: &NetworkConfig) -> Result<Self> {
+        let allowed_domains = config
+            .allowed_domains
+            .iter()
+            .map(|entry| DomainRule::parse(entry))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            mode: config.tools,
+            allowed_domains,
+        })
+    }
+
+    /// 工具不能联网（`--offline-tools`）
+    pub fn offline() -> Self {
+        Self {
+            mode: NetworkMode::None,
+            allowed_domains: Vec::new(),
+        }
+    }
+
+    /// 是否注册联网的工具
+    pub fn tools_enabled(&self) -> bool {
+        self.mode != NetworkMode::None
+    }
+
+    /// 是否允许访问该主机和端口
+    pub fn allows(&self, host: &str, port: Option<u16>) -> bool {
+        match self.mode {
+            NetworkMode::All => true,
+            NetworkMode::None => false,
+            NetworkMode::Allowlist => self
+                .allowed_domains
+                .iter()
+                .any(|rule| rule.matches(host, port)),
+        }
+    }
+
+    /// 检查请求的目标，不允许时返回给模型的错误
+    pub fn check(&self, url: &Url) -> Result<(), ToolFailure> {
+        let host = url.host_str().unwrap_or_default();
+        if self.allows(host, url.port_or_known_default()) {
+            return Ok(());
+        }
+        let (message, suggestion) = match self.mode {
+            NetworkMode::None => (
+                format!(
+                    "Network access from tools is disabled (network.tools = \"none\" or --offline-tools); {} was not contacted",
+                    host
+                ),
+                "do not retry; continue without network access or ask the user to change [network] tools",
+            ),
+            _ => (
+                format!(
+                    "{} is not allowed by the network policy ({})",
+                    host,
+                    self.describe()
+                ),
+                "do not retry; ask the user to add the domain to [network] allowed_domains if this request is intended",
+            ),
+        };
+        Err(ToolFailure::new(ErrorCode::Blocked, message)
+            .with_details(serde_json::json!({ "host": host, "policy": self.mode.as_str() }))
+            .with_suggestion(suggestion))
+    }
+
+    /// 用于 `/status` 的说明，如 `allowlist (crates.io, api.example.com:8443)`
+    pub fn describe(&self) -> String {
+        match self.mode {
+            NetworkMode::Allowlist => {
+                let domains: Vec<String> = self
+                    .allowed_domains
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                format!("allowlist ({})", domains.join(", "))
+            }
+            mode => mode.as_str().to_string(),
+        }
+    }
+}
+
+/// 设置当前生效的策略（启动时由工具设置传入）
+pub fn configure(policy: NetworkPolicy) {
+    *POLICY.write().unwrap_or_else(|e| e.into_inner()) = policy;
+}
+
+/// 当前生效的策略
+pub fn current() -> NetworkPolicy {
+    POLICY.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 按当前策略检查请求的目标
+pub fn check(url: &Url) -> Result<(), ToolFailure> {
+    POLICY.read().unwrap_or_else(|e| e.into_inner()).check(url)
+}
+
+/// 工具共用的 HTTP 客户端
+pub struct ToolHttpClient {
+    inner: reqwest::Client,
+}
+
+impl ToolHttpClient {
+    /// 在共用设置（User-Agent、按策略检查的重定向）上由 `configure` 补充工具自己的设置
+    pub fn build(configure: impl FnOnce(ClientBuilder) -> ClientBuilder) -> reqwest::Result<Self> {
+        let builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match check(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(failure) => attempt.error(failure.message),
+                }
+            }));
+        Ok(Self {
+            inner: configure(builder).build()?,
+        })
+    }
+
+    /// 创建请求，目标不被策略允许时返回 `Blocked`
+    pub fn request(&self, method: Method, url: Url) -> Result<RequestBuilder, ToolFailure> {
+        check(&url)?;
+        Ok(self.inner.request(method, url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(entries: &[&str]) -> NetworkPolicy {
+        NetworkPolicy::from_config(&NetworkConfig {
+            tools: NetworkMode::Allowlist,
+            allowed_domains: entries.iter().map(|e| e.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_domain_matcher_subdomains_and_ports() {
+        let policy = allowlist(&["Example.com", "*.crates.io", "api.internal.dev:8443"]);
+
+        assert!(policy.allows("example.com", Some(443)));
+        assert!(policy.allows("docs.example.com.", Some(80)));
+        assert!(!policy.allows("badexample.com", Some(443)));
+        assert!(!policy.allows("example.com.evil.net", Some(443)));
+        assert!(policy.allows("crates.io", Some(443)));
+        assert!(policy.allows("static.crates.io", Some(443)));
+
+        // 带端口的条目只允许该端口
+        assert!(policy.allows("api.internal.dev", Some(8443)));
+        assert!(!policy.allows("api.internal.dev", Some(443)));
+
+        let url = Url::parse("https://api.internal.dev/v1").unwrap();
+        let failure = policy.check(&url).unwrap_err();
+        assert_eq!(failure.code, ErrorCode::Blocked);
+        assert!(failure
+            .message
+            .contains("allowlist (example.com, crates.io, api.internal.dev:8443)"));
+
+        assert!(DomainRule::parse("https://example.com/path").is_err());
+        assert!(DomainRule::parse("example.com:http").is_err());
+    }
+
+    #[test]
+    fn test_modes() {
+        let url = Url::parse("https://example.com").unwrap();
+        assert!(NetworkPolicy::default().check(&url).is_ok());
+        assert!(NetworkPolicy::default().tools_enabled());
+
+        let offline = NetworkPolicy::offline();
+        assert!(!offline.tools_enabled());
+        let failure = offline.check(&url).unwrap_err();
+        assert_eq!(failure.code, ErrorCode::Blocked);
+        assert!(failure.message.contains("--offline-tools"));
+
+        // 允许列表为空时不能访问任何域名
+        assert!(!allowlist(&[]).allows("example.com", Some(443)));
+    }
+}
@@ -427,8 +427,8 @@ impl Tool for WrappedNotebookEditTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!(
+        tool_println!();
+        tool_println!(
             "{} {} ({})",
             "●".bright_green(),
             "NotebookEdit",
@@ -439,28 +439,31 @@ impl Tool for WrappedNotebookEditTool {
 
         match &result {
             Ok(output) => {
-                println!(
+                tool_println!(
                     "  └─ {}",
-                    format!(
-                        "成功 {} 单元 {} (总计 {} 个单元)",
-                        if output.edit_mode == "delete" {
-                            "删除"
+                    t!(
+                        "tools.notebook.done",
+                        action = if output.edit_mode == "delete" {
+                            t!("tools.notebook.delete")
                         } else if output.edit_mode == "insert" {
-                            "插入"
+                            t!("tools.notebook.insert")
                         } else {
-                            "替换"
+                            t!("tools.notebook.replace")
                         },
-                        output.cell_index.to_string().bright_yellow(),
-                        output.total_cells.to_string().bright_green()
+                        index = output.cell_index.to_string().bright_yellow(),
+                        total = output.total_cells.to_string().bright_green()
                     )
                     .dimmed()
                 );
             }
             Err(e) => {
-                println!("  └─ {}", format!("错误: {}", e).red());
+                tool_println!(
+                    "  └─ {}",
+                    format!("{} {}", t!("common.error"), e.message()).red()
+                );
             }
         }
-        println!();
+        tool_println!();
 
         result
     }
@@ -0,0 +1,719 @@
+//! Outline 工具
+//!
+//! 用 tree-sitter 解析源文件，列出函数、类型、impl 块等结构及其行号范围，模型可以先看
+//! 大纲再按行号读取需要的部分，而不必读完整个文件。各语言语法通过 cargo 特性
+//! `lang-rust`、`lang-typescript`、`lang-python`、`lang-go` 启用；未启用或不支持的语言
+//! 退回基于关键字和缩进的启发式提取，结果附带说明。
+
+use super::sensitive_paths::{get_sensitive_guard, SensitiveAccess};
+use super::settings::ToolSettings;
+use super::FileToolError;
+use colored::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::iter::Peekable;
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+/// 签名行的最大字符数
+const MAX_SIGNATURE_CHARS: usize = 160;
+
+/// 支持结构化解析的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceLanguage {
+    Rust,
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Python,
+    Go,
+}
+
+impl SourceLanguage {
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "rs" => Some(Self::Rust),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "py" | "pyi" => Some(Self::Python),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::TypeScript => "typescript",
+            Self::Tsx => "tsx",
+            Self::JavaScript => "javascript",
+            Self::Python => "python",
+            Self::Go => "go",
+        }
+    }
+
+    /// 启用该语言所需的 cargo 特性
+    fn feature(self) -> &'static str {
+        match self {
+            Self::Rust => "lang-rust",
+            Self::TypeScript | Self::Tsx | Self::JavaScript => "lang-typescript",
+            Self::Python => "lang-python",
+            Self::Go => "lang-go",
+        }
+    }
+
+    /// 对应的 tree-sitter 语法，特性未启用时返回 None
+    fn grammar(self) -> Option<tree_sitter::Language> {
+        match self {
+            #[cfg(feature = "lang-rust")]
+            Self::Rust => Some(tree_sitter_rust::LANGUAGE.into()),
+            #[cfg(feature = "lang-typescript")]
+            Self::TypeScript => Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+            #[cfg(feature = "lang-typescript")]
+            Self::Tsx => Some(tree_sitter_typescript::LANGUAGE_TSX.into()),
+            #[cfg(feature = "lang-typescript")]
+            Self::JavaScript => Some(tree_sitter_javascript::LANGUAGE.into()),
+            #[cfg(feature = "lang-python")]
+            Self::Python => Some(tree_sitter_python::LANGUAGE.into()),
+            #[cfg(feature = "lang-go")]
+            Self::Go => Some(tree_sitter_go::LANGUAGE.into()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// Outline 工具参数
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OutlineArgs {
+    /// 文件路径
+    pub file_path: String,
+
+    /// 只返回名称匹配的符号（可选）
+    #[serde(default)]
+    pub symbol: Option<String>,
+}
+
+/// 大纲中的一项
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OutlineItem {
+    /// 种类：function、method、struct、class、impl 等
+    pub kind: String,
+
+    /// 名称（Rust impl 为 `Trait for Type`，Go 方法为 `Type.Method`）
+    pub name: String,
+
+    /// 定义所在的第一行
+    pub signature: String,
+
+    /// 起始行（从 1 开始，包含装饰器）
+    pub start_line: usize,
+
+    /// 结束行（包含）
+    pub end_line: usize,
+
+    /// 嵌套的成员
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<OutlineItem>,
+}
+
+/// Outline 工具输出
+#[derive(Debug, Serialize)]
+pub struct OutlineOutput {
+    pub file_path: String,
+
+    /// 识别出的语言，无法识别时为 "unknown"
+    pub language: String,
+
+    pub items: Vec<OutlineItem>,
+
+    /// 启发式提取或解析出错时的说明
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// 提取文件大纲
+pub fn outline_source(path: &Path, source: &str) -> (String, Vec<OutlineItem>, Option<String>) {
+    let Some(language) = SourceLanguage::from_path(path) else {
+        return (
+            "unknown".to_string(),
+            heuristic_outline(source),
+            Some(t!("tools.outline.unsupported_language").to_string()),
+        );
+    };
+
+    let Some(grammar) = language.grammar() else {
+        return (
+            language.name().to_string(),
+            heuristic_outline(source),
+            Some(t!(
+                "tools.outline.feature_disabled",
+                feature = language.feature()
+            )),
+        );
+    };
+
+    let mut parser = Parser::new();
+    let tree = parser
+        .set_language(&grammar)
+        .ok()
+        .and_then(|_| parser.parse(source, None));
+    let Some(tree) = tree else {
+        return (
+            language.name().to_string(),
+            heuristic_outline(source),
+            Some(t!("tools.outline.parse_failed").to_string()),
+        );
+    };
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut items = Vec::new();
+    collect_items(tree.root_node(), language, source, &lines, None, &mut items);
+
+    let note = tree
+        .root_node()
+        .has_error()
+        .then(|| t!("tools.outline.syntax_errors").to_string());
+    (language.name().to_string(), items, note)
+}
+
+/// 递归收集节点下的定义；不是定义的节点视为透明，继续向下查找
+fn collect_items(
+    node: Node,
+    language: SourceLanguage,
+    source: &str,
+    lines: &[&str],
+    parent_kind: Option<&str>,
+    out: &mut Vec<OutlineItem>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match classify(child, language, source, parent_kind) {
+            Some((kind, name)) => {
+                let mut item = OutlineItem {
+                    signature: signature_line(lines, child.start_position().row),
+                    start_line: range_start(child) + 1,
+                    end_line: child.end_position().row + 1,
+                    kind: kind.to_string(),
+                    name,
+                    children: Vec::new(),
+                };
+                collect_items(child, language, source, lines, Some(kind), &mut item.children);
+                out.push(item);
+            }
+            None => collect_items(child, language, source, lines, parent_kind, out),
+        }
+    }
+}
+
+/// 判断节点是否是需要列出的定义，返回种类和名称
+fn classify(
+    node: Node,
+    language: SourceLanguage,
+    source: &str,
+    parent_kind: Option<&str>,
+) -> Option<(&'static str, String)> {
+    let field = |name: &str| node.child_by_field_name(name).map(|n| node_text(n, source));
+    let in_type = matches!(parent_kind, Some("class" | "impl" | "trait"));
+
+    let kind = match language {
+        SourceLanguage::Rust => match node.kind() {
+            "function_item" | "function_signature_item" if in_type => "method",
+            "function_item" | "function_signature_item" => "function",
+            "struct_item" => "struct",
+            "enum_item" => "enum",
+            "union_item" => "union",
+            "trait_item" => "trait",
+            "mod_item" => "module",
+            "const_item" => "const",
+            "static_item" => "static",
+            "type_item" => "type",
+            "macro_definition" => "macro",
+            "impl_item" => {
+                let ty = field("type")?;
+                let name = match field("trait") {
+                    Some(tr) => format!("{} for {}", tr, ty),
+                    None => ty,
+                };
+                return Some(("impl", name));
+            }
+            _ => return None,
+        },
+        SourceLanguage::TypeScript | SourceLanguage::Tsx | SourceLanguage::JavaScript => {
+            match node.kind() {
+                "function_declaration" | "generator_function_declaration" => "function",
+                "class_declaration" | "abstract_class_declaration" => "class",
+                "method_definition" | "abstract_method_signature" => "method",
+                "interface_declaration" => "interface",
+                "type_alias_declaration" => "type",
+                "enum_declaration" => "enum",
+                "internal_module" | "module" => "namespace",
+                // const foo = () => {} / const foo = function () {}
+                "variable_declarator" => {
+                    let value = node.child_by_field_name("value")?;
+                    if !matches!(
+                        value.kind(),
+                        "arrow_function" | "function_expression" | "function"
+                    ) {
+                        return None;
+                    }
+                    "function"
+                }
+                _ => return None,
+            }
+        }
+        SourceLanguage::Python => match node.kind() {
+            "function_definition" if in_type => "method",
+            "function_definition" => "function",
+            "class_definition" => "class",
+            _ => return None,
+        },
+        SourceLanguage::Go => match node.kind() {
+            "function_declaration" => "function",
+            "method_declaration" => {
+                let name = field("name")?;
+                let receiver = node
+                    .child_by_field_name("receiver")
+                    .and_then(|list| list.named_child(0))
+                    .and_then(|param| param.child_by_field_name("type"))
+                    .map(|ty| node_text(ty, source).trim_start_matches('*').to_string());
+                let name = match receiver {
+                    Some(receiver) => format!("{}.{}", receiver, name),
+                    None => name,
+                };
+                return Some(("method", name));
+            }
+            "type_spec" => match node.child_by_field_name("type").map(|ty| ty.kind()) {
+                Some("struct_type") => "struct",
+                Some("interface_type") => "interface",
+                _ => "type",
+            },
+            "type_alias" => "type",
+            _ => return None,
+        },
+    };
+
+    Some((kind, field("name")?))
+}
+
+/// 定义的起始行（0 基），Python 装饰器和 `export const` 声明算在定义内
+fn range_start(node: Node) -> usize {
+    let mut start = node.start_position().row;
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        match parent.kind() {
+            "decorated_definition" | "export_statement" | "lexical_declaration"
+            | "variable_declaration" => {
+                start = parent.start_position().row;
+                current = parent;
+            }
+            _ => break,
+        }
+    }
+    start
+}
+
+fn node_text(node: Node, source: &str) -> String {
+    source[node.byte_range()].to_string()
+}
+
+fn signature_line(lines: &[&str], row: usize) -> String {
+    let line = lines.get(row).map(|l| l.trim()).unwrap_or_default();
+    if line.chars().count() > MAX_SIGNATURE_CHARS {
+        let truncated: String = line.chars().take(MAX_SIGNATURE_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        line.to_string()
+    }
+}
+
+/// 启发式识别定义行的正则：可选修饰符 + 关键字 + 名称
+static DEFINITION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(\s*)(?:export\s+)?(?:default\s+)?(?:pub(?:\([^)]*\))?\s+)?(?:(?:public|private|protected|internal|static|abstract|final|sealed|open|override|async|unsafe|data|case)\s+)*(fn|func|function|def|class|struct|enum|trait|interface|impl|module|mod|object|protocol|type)\s+(?:\([^)]*\)\s*)?([A-Za-z_$][\w.$:]*)",
+    )
+    .unwrap()
+});
+
+/// 不依赖语法的启发式提取：按关键字识别定义，按缩进推断结束行和嵌套关系
+fn heuristic_outline(source: &str) -> Vec<OutlineItem> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut flat = Vec::new();
+
+    for (row, line) in lines.iter().enumerate() {
+        let Some(caps) = DEFINITION_RE.captures(line) else {
+            continue;
+        };
+        let indent = caps[1].len();
+        let kind = match &caps[2] {
+            "fn" | "func" | "function" | "def" => "function",
+            "mod" | "module" => "module",
+            "object" => "class",
+            "protocol" => "interface",
+            other => other,
+        };
+        flat.push(OutlineItem {
+            kind: kind.to_string(),
+            name: caps[3].to_string(),
+            signature: signature_line(&lines, row),
+            start_line: row + 1,
+            end_line: heuristic_end(&lines, row, indent) + 1,
+            children: Vec::new(),
+        });
+    }
+
+    nest(&mut flat.into_iter().peekable(), usize::MAX)
+}
+
+/// 从定义行往下找第一个缩进不超过它的非空行；收尾行（`}`、`end`）算在定义内
+fn heuristic_end(lines: &[&str], row: usize, indent: usize) -> usize {
+    let mut last = row;
+    for (offset, line) in lines[row + 1..].iter().enumerate() {
+        let current = row + 1 + offset;
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if line.len() - trimmed.len() <= indent {
+            let closing = trimmed.starts_with('}')
+                || trimmed.starts_with(')')
+                || trimmed == "end"
+                || trimmed.starts_with("end ");
+            return if closing { current } else { last };
+        }
+        last = current;
+    }
+    last
+}
+
+/// 把按起始行排序的扁平列表按范围包含关系组织成树
+fn nest<I>(items: &mut Peekable<I>, parent_end: usize) -> Vec<OutlineItem>
+where
+    I: Iterator<Item = OutlineItem>,
+{
+    let mut result = Vec::new();
+    while let Some(mut item) = items.next_if(|next| next.start_line <= parent_end) {
+        item.children = nest(items, item.end_line);
+        result.push(item);
+    }
+    result
+}
+
+/// 按名称筛选符号：优先精确匹配名称或 `Parent.child` 路径，否则按不区分大小写的子串匹配
+pub fn filter_symbol(items: &[OutlineItem], symbol: &str) -> Vec<OutlineItem> {
+    fn walk(
+        items: &[OutlineItem],
+        prefix: &str,
+        pred: &dyn Fn(&str, &str) -> bool,
+        out: &mut Vec<OutlineItem>,
+    ) {
+        for item in items {
+            let path = if prefix.is_empty() {
+                item.name.clone()
+            } else {
+                format!("{}.{}", prefix, item.name)
+            };
+            if pred(&item.name, &path) {
+                out.push(item.clone());
+            }
+            walk(&item.children, &path, pred, out);
+        }
+    }
+
+    let mut exact = Vec::new();
+    walk(items, "", &|name, path| name == symbol || path == symbol, &mut exact);
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    let needle = symbol.to_lowercase();
+    let mut fuzzy = Vec::new();
+    walk(items, "", &|name, _| name.to_lowercase().contains(&needle), &mut fuzzy);
+    fuzzy
+}
+
+/// 渲染为缩进文本，每行一个符号
+pub fn render_outline(items: &[OutlineItem]) -> String {
+    fn render(items: &[OutlineItem], depth: usize, out: &mut String) {
+        for item in items {
+            out.push_str(&format!(
+                "{}{} {} [{}-{}] {}\n",
+                "  ".repeat(depth),
+                item.kind,
+                item.name,
+                item.start_line,
+                item.end_line,
+                item.signature
+            ));
+            render(&item.children, depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    render(items, 0, &mut out);
+    out
+}
+
+/// Outline 工具
+#[derive(Default, Deserialize, Serialize)]
+pub struct OutlineTool {
+    settings: ToolSettings,
+}
+
+impl OutlineTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
+
+impl Tool for OutlineTool {
+    const NAME: &'static str = "outline";
+
+    type Error = FileToolError;
+    type Args = OutlineArgs;
+    type Output = OutlineOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "outline".to_string(),
+            description: "List the structure of a source file: functions, methods, classes/structs, traits, impls and so on, each with its kind, name, signature line and line range (nested members are listed under children). Call it before reading a large file to locate symbols, then read just those lines with read_file. With symbol, only the matching symbols and their exact line ranges are returned. Rust, TypeScript/JavaScript, Python and Go are parsed; other languages are outlined heuristically, which is explained in note.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {
+                        "type": "string",
+                        "description": "Path of the source file to outline"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Optional. Only return symbols whose name matches (e.g. 'parse' or 'UserStore.get')"
+                    }
+                },
+                "required": ["file_path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let resolved = self.settings.resolve_path(&args.file_path);
+        let path = Path::new(&resolved);
+        self.settings.roots.check(path)?;
+        if let SensitiveAccess::Denied { pattern } | SensitiveAccess::NeedsConfirmation { pattern } =
+            get_sensitive_guard().check(path)
+        {
+            return Err(FileToolError::PermissionDenied(format!(
+                "{} (matches sensitive path pattern '{}')",
+                args.file_path, pattern
+            )));
+        }
+        if !path.exists() {
+            return Err(FileToolError::FileNotFound(args.file_path));
+        }
+        if !path.is_file() {
+            return Err(FileToolError::NotAFile(args.file_path));
+        }
+
+        let source = tokio::fs::read_to_string(path).await?;
+        let (language, mut items, mut note) = outline_source(path, &source);
+
+        if let Some(symbol) = args.symbol.as_deref().filter(|s| !s.trim().is_empty()) {
+            items = filter_symbol(&items, symbol.trim());
+            if items.is_empty() {
+                let message = t!("tools.outline.symbol_not_found", symbol = symbol.trim());
+                note = Some(match note {
+                    Some(existing) => format!("{}; {}", message, existing),
+                    None => message,
+                });
+            }
+        }
+
+        Ok(OutlineOutput {
+            file_path: args.file_path,
+            language,
+            items,
+            note,
+        })
+    }
+}
+
+/// 包装后的 Outline 工具（用于显示额外信息）
+#[derive(Deserialize, Serialize)]
+pub struct WrappedOutlineTool {
+    inner: OutlineTool,
+}
+
+impl WrappedOutlineTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: OutlineTool::new(settings),
+        }
+    }
+}
+
+impl Tool for WrappedOutlineTool {
+    const NAME: &'static str = "outline";
+
+    type Error = FileToolError;
+    type Args = <OutlineTool as Tool>::Args;
+    type Output = <OutlineTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        match &args.symbol {
+            Some(symbol) => tool_println!(
+                "{} {}({}, symbol={})",
+                "●".bright_blue(),
+                "Outline".bright_blue(),
+                args.file_path.bright_white(),
+                symbol.bright_white()
+            ),
+            None => tool_println!(
+                "{} {}({})",
+                "●".bright_blue(),
+                "Outline".bright_blue(),
+                args.file_path.bright_white()
+            ),
+        }
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => {
+                tool_println!(
+                    "  └─ {}",
+                    t!(
+                        "tools.outline.symbols",
+                        count = output.items.len().to_string().bright_green(),
+                        language = output.language
+                    )
+                );
+                for line in render_outline(&output.items).lines().take(8) {
+                    tool_println!("     {}", line.dimmed());
+                }
+                if let Some(note) = &output.note {
+                    tool_println!("  {} {}", "⚠".bright_yellow(), note.bright_yellow());
+                }
+            }
+            Err(e) => {
+                tool_println!("  └─ {} {}", t!("common.error").red(), e.message().red());
+            }
+        }
+        tool_println!();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ErrorCode, WorkspaceRoots};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/outline")
+            .join(name)
+    }
+
+    /// 与 `<fixture>.snap` 对比；设置 UPDATE_SNAPSHOTS=1 时重写快照
+    fn assert_snapshot(name: &str) {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let path = fixture(name);
+        let source = fs::read_to_string(&path).unwrap();
+        let (language, items, note) = outline_source(&path, &source);
+        let mut actual = format!("language: {}\n", language);
+        if let Some(note) = note {
+            actual.push_str(&format!("note: {}\n", note));
+        }
+        actual.push('\n');
+        actual.push_str(&render_outline(&items));
+
+        let snap_path = fixture(&format!("{}.snap", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&snap_path, &actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&snap_path).unwrap_or_default();
+        assert_eq!(actual, expected, "snapshot mismatch for {}", name);
+    }
+
+    #[cfg(feature = "lang-rust")]
+    #[test]
+    fn test_rust_snapshot() {
+        assert_snapshot("sample.rs");
+    }
+
+    #[cfg(feature = "lang-typescript")]
+    #[test]
+    fn test_typescript_snapshot() {
+        assert_snapshot("sample.ts");
+        assert_snapshot("sample.js");
+    }
+
+    #[cfg(feature = "lang-python")]
+    #[test]
+    fn test_python_snapshot() {
+        assert_snapshot("sample.py");
+    }
+
+    #[cfg(feature = "lang-go")]
+    #[test]
+    fn test_go_snapshot() {
+        assert_snapshot("sample.go");
+    }
+
+    #[test]
+    fn test_heuristic_snapshot() {
+        assert_snapshot("sample.rb");
+    }
+
+    #[cfg(feature = "lang-rust")]
+    #[test]
+    fn test_filter_symbol() {
+        let path = fixture("sample.rs");
+        let source = fs::read_to_string(&path).unwrap();
+        let (_, items, _) = outline_source(&path, &source);
+
+        let matches = filter_symbol(&items, "distance");
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].start_line, matches[0].end_line), (50, 52));
+
+        // 精确匹配优先于子串匹配，`Parent.child` 路径可区分同名方法
+        let matches = filter_symbol(&items, "Area for Shape.area");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].kind, "method");
+        assert_eq!((matches[0].start_line, matches[0].end_line), (28, 33));
+        assert_eq!(filter_symbol(&items, "area").len(), 2);
+        // trait Area、两个 area 方法和 impl Area for Shape
+        assert_eq!(filter_symbol(&items, "AREA").len(), 4);
+
+        assert!(filter_symbol(&items, "missing").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_outside_roots() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let settings = ToolSettings {
+            roots: WorkspaceRoots::new(workspace.path()),
+            ..ToolSettings::default()
+        };
+        let args = OutlineArgs {
+            file_path: fixture("sample.rb").to_string_lossy().to_string(),
+            symbol: None,
+        };
+        let error = OutlineTool::new(&settings).call(args).await.unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::PermissionDenied);
+    }
+}
@@ -0,0 +1,505 @@
+//! 补丁的部分应用
+//!
+//! `partial` 模式下 edit_file 和 apply_patch 应用能匹配的 hunk，不再因为一个 hunk 的上下文
+//! 对不上而整体失败。对每个不匹配的 hunk，交互时让用户选择：跳过、应用到模糊匹配找到的
+//! 位置，或者对照补丁期望的上下文和文件中的实际内容后决定是否替换；非交互时一律跳过并报告，
+//! 不做任何猜测。结果逐个列出已应用、跳过和重新定位的 hunk，模型只需跟进被跳过的部分。
+
+use super::apply_patch::{locate_hunk, Hunk, HunkLine, HunkMatch};
+use super::ask_user_question::{ask_question_interactive, Question, QuestionOption};
+use super::edit_file::render_colored_diff;
+use colored::*;
+use serde::Serialize;
+use similar::TextDiff;
+
+/// 模糊匹配的最低相似度
+const MIN_SIMILARITY: f32 = 0.6;
+
+/// 模糊匹配最多比较的行数（窗口数 × hunk 行数），超出时不查找候选位置
+const MAX_FUZZY_WORK: usize = 2_000_000;
+
+/// hunk 的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HunkStatus {
+    /// 在 hunk 头指向的位置（或附近）应用
+    Applied,
+    /// 没有应用
+    Skipped,
+    /// 上下文不匹配，经用户确认后应用到其他位置
+    Retargeted,
+}
+
+/// 单个 hunk 的报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HunkReport {
+    /// 文件内的 hunk 序号（从 1 开始）
+    pub hunk: usize,
+    /// hunk 头中旧文件的起始行号
+    pub old_start: usize,
+    pub status: HunkStatus,
+    /// 应用后所在的起始行（从 1 开始）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// 模糊匹配找到的候选位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// 起始行（从 0 开始）
+    pub start: usize,
+    /// 与补丁期望内容的相似度（0–1）
+    pub similarity: f32,
+    pub lines: Vec<String>,
+}
+
+/// 一个不匹配的 hunk
+#[derive(Debug, Clone)]
+pub struct HunkConflict<'a> {
+    /// 文件内的 hunk 序号（从 1 开始）
+    pub index: usize,
+    pub old_start: usize,
+    /// 补丁期望的原内容（上下文和删除的行）
+    pub expected: Vec<&'a str>,
+    /// hunk 头指向的位置（从 0 开始，计入了之前 hunk 的行数变化）
+    pub position: usize,
+    /// 文件在该位置的实际内容
+    pub actual: Vec<String>,
+    pub candidate: Option<Candidate>,
+}
+
+/// 对不匹配 hunk 的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Skip,
+    /// 替换模糊匹配找到的位置
+    Retarget,
+    /// 替换 hunk 头指向位置的实际内容
+    ReplaceActual,
+}
+
+/// 决定不匹配 hunk 的处理方式
+pub trait HunkResolver {
+    fn resolve(&mut self, path: &str, conflict: &HunkConflict) -> Resolution;
+}
+
+/// 非交互：跳过所有不匹配的 hunk
+pub struct SkipFailing;
+
+impl HunkResolver for SkipFailing {
+    fn resolve(&mut self, _path: &str, _conflict: &HunkConflict) -> Resolution {
+        Resolution::Skip
+    }
+}
+
+/// 部分应用的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialResult {
+    pub lines: Vec<String>,
+    pub reports: Vec<HunkReport>,
+    /// 已应用的 hunk 新增和删除的行数
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// 是否有 hunk 被跳过
+pub fn any_skipped(reports: &[HunkReport]) -> bool {
+    reports.iter().any(|r| r.status == HunkStatus::Skipped)
+}
+
+/// 给模型的摘要，如 `2 of 3 hunks applied; skipped hunk 2 (@@ -12)`
+pub fn summarize(reports: &[HunkReport]) -> String {
+    let applied = reports
+        .iter()
+        .filter(|r| r.status != HunkStatus::Skipped)
+        .count();
+    let mut summary = format!("{} of {} hunks applied", applied, reports.len());
+    for report in reports {
+        match report.status {
+            HunkStatus::Applied => continue,
+            HunkStatus::Skipped => summary.push_str(&format!(
+                "; skipped hunk {} (@@ -{})",
+                report.hunk, report.old_start
+            )),
+            HunkStatus::Retargeted => summary.push_str(&format!(
+                "; hunk {} (@@ -{}) retargeted to line {}",
+                report.hunk,
+                report.old_start,
+                report.line.unwrap_or_default()
+            )),
+        }
+    }
+    summary
+}
+
+/// 新增和删除的行数
+fn hunk_counts(hunk: &Hunk) -> (usize, usize) {
+    hunk.lines
+        .iter()
+        .fold((0, 0), |(added, removed), line| match line {
+            HunkLine::Add(_) => (added + 1, removed),
+            HunkLine::Remove(_) => (added, removed + 1),
+            HunkLine::Context(_) => (added, removed),
+        })
+}
+
+/// 把 hunk 套用到另一段内容上：上下文行保留 `region` 中对应位置的实际内容，
+/// 删除行按位置去掉，新增行原样插入
+fn retarget_lines(hunk: &Hunk, region: &[String]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut index = 0;
+    for line in &hunk.lines {
+        match line {
+            HunkLine::Context(text) => {
+                lines.push(region.get(index).unwrap_or(text).clone());
+                index += 1;
+            }
+            HunkLine::Remove(_) => index += 1,
+            HunkLine::Add(text) => lines.push(text.clone()),
+        }
+    }
+    lines
+}
+
+/// 在 `min` 之后找与 `expected` 最相似的一段（相似度不低于 [`MIN_SIMILARITY`]）
+fn fuzzy_candidate(lines: &[String], expected: &[&str], min: usize) -> Option<Candidate> {
+    let len = expected.len();
+    if len == 0 || lines.len() < min + len {
+        return None;
+    }
+    let windows = lines.len() - len - min + 1;
+    if windows.saturating_mul(len) > MAX_FUZZY_WORK {
+        return None;
+    }
+    let all: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let (start, similarity) = (min..min + windows)
+        .map(|start| {
+            let similarity = TextDiff::from_slices(expected, &all[start..start + len]).ratio();
+            (start, similarity)
+        })
+        // 相似度相同时取更靠前的位置
+        .fold(
+            None,
+            |best: Option<(usize, f32)>, (start, similarity)| match best {
+                Some((_, best_similarity)) if best_similarity >= similarity => best,
+                _ => Some((start, similarity)),
+            },
+        )?;
+    (similarity >= MIN_SIMILARITY).then(|| Candidate {
+        start,
+        similarity,
+        lines: lines[start..start + len].to_vec(),
+    })
+}
+
+/// 依次应用一个文件的 hunk，不匹配的交给 `resolver` 决定
+pub(super) fn apply_partial(
+    path: &str,
+    original: &[&str],
+    hunks: &[Hunk],
+    resolver: &mut dyn HunkResolver,
+) -> PartialResult {
+    let mut lines: Vec<String> = original.iter().map(|line| line.to_string()).collect();
+    let mut reports = Vec::new();
+    let (mut lines_added, mut lines_removed) = (0, 0);
+    // 与 apply_hunks 相同：之前的 hunk 造成的行数变化加上上一个 hunk 的偏移
+    let mut shift: isize = 0;
+    let mut min = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let expected = hunk.old_start.saturating_sub(1) as isize + shift;
+        let mut report = HunkReport {
+            hunk: index + 1,
+            old_start: hunk.old_start,
+            status: HunkStatus::Applied,
+            line: None,
+            note: None,
+        };
+
+        let (start, old_len, new): (usize, usize, Vec<String>) =
+            match locate_hunk(&lines, hunk, expected, min) {
+                Some(HunkMatch {
+                    fuzz,
+                    front,
+                    start,
+                    old_len,
+                    new,
+                }) => {
+                    let offset = start as isize - (expected + front as isize);
+                    if offset != 0 || fuzz > 0 {
+                        report.note = Some(format!("offset {}, fuzz {}", offset, fuzz));
+                    }
+                    (
+                        start,
+                        old_len,
+                        new.into_iter().map(str::to_string).collect(),
+                    )
+                }
+                None => {
+                    let (old, _) = hunk.sides(0, 0);
+                    let position = expected.clamp(min as isize, lines.len() as isize) as usize;
+                    let end = (position + old.len()).min(lines.len());
+                    let conflict = HunkConflict {
+                        index: index + 1,
+                        old_start: hunk.old_start,
+                        candidate: fuzzy_candidate(&lines, &old, min),
+                        actual: lines[position..end].to_vec(),
+                        expected: old,
+                        position,
+                    };
+                    let target = match resolver.resolve(path, &conflict) {
+                    Resolution::Skip => None,
+                    Resolution::Retarget => conflict.candidate.as_ref().map(|c| {
+                        let note = format!(
+                            "context not found; applied at the closest match ({:.0}% similar)",
+                            c.similarity * 100.0
+                        );
+                        (c.start, &c.lines, note)
+                    }),
+                    Resolution::ReplaceActual => Some((
+                        position,
+                        &conflict.actual,
+                        "context not found; replaced the lines at the hunk position after review"
+                            .to_string(),
+                    )),
+                };
+                    let Some((start, region, note)) = target else {
+                        report.status = HunkStatus::Skipped;
+                        report.note = Some("context not found in the file".to_string());
+                        reports.push(report);
+                        continue;
+                    };
+                    report.status = HunkStatus::Retargeted;
+                    report.note = Some(note);
+                    (start, region.len(), retarget_lines(hunk, region))
+                }
+            };
+
+        let new_len = new.len();
+        lines.splice(start..start + old_len, new);
+        if report.status != HunkStatus::Retargeted {
+            shift += new_len as isize - old_len as isize + (start as isize - expected);
+            min = start + new_len;
+        } else if start as isize <= expected {
+            // 重新定位的位置与补丁的行号无关，只计入行数变化；
+            // 定位到更靠后的位置时，后面的 hunk 仍按原来的行号查找
+            shift += new_len as isize - old_len as isize;
+            min = start + new_len;
+        }
+        report.line = Some(start + 1);
+        reports.push(report);
+        let (added, removed) = hunk_counts(hunk);
+        lines_added += added;
+        lines_removed += removed;
+    }
+
+    PartialResult {
+        lines,
+        reports,
+        lines_added,
+        lines_removed,
+    }
+}
+
+/// 交互：在终端中逐个询问（调用方通过 `interaction::interact` 排队）
+pub struct AskUser;
+
+impl AskUser {
+    fn choose(question: String, options: Vec<(&'static str, String)>) -> Option<&'static str> {
+        let question = Question {
+            question,
+            header: String::new(),
+            options: options
+                .iter()
+                .map(|(label, description)| QuestionOption {
+                    label: label.to_string(),
+                    description: description.clone(),
+                })
+                .collect(),
+            multi_select: false,
+            allow_other: false,
+        };
+        let answer = ask_question_interactive(&question).ok()?;
+        let selected = answer.selected.as_str()?.to_string();
+        options
+            .into_iter()
+            .map(|(label, _)| label)
+            .find(|label| *label == selected)
+    }
+}
+
+impl HunkResolver for AskUser {
+    fn resolve(&mut self, path: &str, conflict: &HunkConflict) -> Resolution {
+        let skip = t!("edit.partial.skip");
+        let retarget = t!("edit.partial.retarget");
+        let manual = t!("edit.partial.manual");
+        let replace = t!("edit.partial.replace");
+
+        println!();
+        println!(
+            "{}",
+            t!(
+                "edit.partial.conflict",
+                hunk = conflict.index,
+                line = conflict.old_start,
+                path = path
+            )
+            .bright_yellow()
+        );
+        let mut options = vec![(skip, t!("edit.partial.skip_description").to_string())];
+        if let Some(candidate) = &conflict.candidate {
+            println!(
+                "{}",
+                t!(
+                    "edit.partial.candidate",
+                    start = candidate.start + 1,
+                    end = candidate.start + candidate.lines.len(),
+                    percent = format!("{:.0}", candidate.similarity * 100.0)
+                )
+                .dimmed()
+            );
+            render_colored_diff(&joined(&conflict.expected), &joined(&candidate.lines));
+            options.push((
+                retarget,
+                t!("edit.partial.retarget_description").to_string(),
+            ));
+        }
+        options.push((manual, t!("edit.partial.manual_description").to_string()));
+
+        match Self::choose(t!("edit.partial.question").to_string(), options) {
+            Some(choice) if choice == retarget => return Resolution::Retarget,
+            Some(choice) if choice == manual => {}
+            _ => return Resolution::Skip,
+        }
+
+        // 对照补丁期望的内容（-）和文件中该位置的实际内容（+）
+        println!();
+        println!(
+            "{}",
+            t!(
+                "edit.partial.compare",
+                start = conflict.position + 1,
+                end = conflict.position + conflict.actual.len()
+            )
+            .bright_cyan()
+        );
+        render_colored_diff(&joined(&conflict.expected), &joined(&conflict.actual));
+        let options = vec![
+            (replace, t!("edit.partial.replace_description").to_string()),
+            (skip, t!("edit.partial.skip_description").to_string()),
+        ];
+        match Self::choose(t!("edit.partial.question").to_string(), options) {
+            Some(choice) if choice == replace => Resolution::ReplaceActual,
+            _ => Resolution::Skip,
+        }
+    }
+}
+
+fn joined<S: AsRef<str>>(lines: &[S]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{}\n", line.as_ref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::apply_patch::parse_patch;
+
+    const ORIGINAL: &str =
+        "fn one() {\n    1\n}\n\nfn two() {\n    let x = 2;\n    x\n}\n\nfn three() {\n    3\n}\n";
+
+    /// 三个 hunk 的补丁，第二个 hunk 的上下文（`fn two`）在文件中已被删除
+    const PATCH: &str = "--- a/src/lib.rs\n+++ b/src/lib.rs\n\
+                         @@ -1,3 +1,3 @@\n fn one() {\n-    1\n+    10\n }\n\
+                         @@ -5,4 +5,4 @@\n fn two() {\n     let x = 2;\n-    x\n+    x * 10\n }\n\
+                         @@ -10,3 +10,3 @@\n fn three() {\n-    3\n+    30\n }\n";
+
+    /// 记录收到的冲突，按预设的选择处理
+    struct Scripted {
+        choice: Resolution,
+        seen: Vec<(usize, Vec<String>, Option<usize>)>,
+    }
+
+    impl HunkResolver for Scripted {
+        fn resolve(&mut self, _path: &str, conflict: &HunkConflict) -> Resolution {
+            let expected = conflict.expected.iter().map(|l| l.to_string()).collect();
+            let candidate = conflict.candidate.as_ref().map(|c| c.start);
+            self.seen.push((conflict.index, expected, candidate));
+            self.choice
+        }
+    }
+
+    fn apply(original: &str, resolver: &mut dyn HunkResolver) -> PartialResult {
+        let files = parse_patch(PATCH).unwrap();
+        let lines: Vec<&str> = original.lines().collect();
+        apply_partial("src/lib.rs", &lines, &files[0].hunks, resolver)
+    }
+
+    #[test]
+    fn test_middle_hunk_with_deleted_context_is_skipped() {
+        // 文件中 fn two 已被删除，其后的行号整体上移
+        let current = ORIGINAL.replace("fn two() {\n    let x = 2;\n    x\n}\n\n", "");
+        let mut resolver = Scripted {
+            choice: Resolution::Skip,
+            seen: Vec::new(),
+        };
+        let result = apply(&current, &mut resolver);
+
+        assert_eq!(
+            result.lines.join("\n"),
+            "fn one() {\n    10\n}\n\nfn three() {\n    30\n}"
+        );
+        let statuses: Vec<HunkStatus> = result.reports.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                HunkStatus::Applied,
+                HunkStatus::Skipped,
+                HunkStatus::Applied
+            ]
+        );
+        assert_eq!((result.lines_added, result.lines_removed), (2, 2));
+        assert_eq!(
+            summarize(&result.reports),
+            "2 of 3 hunks applied; skipped hunk 2 (@@ -5)"
+        );
+        // 只有第二个 hunk 交给了 resolver，没有足够相似的位置可以重新定位
+        assert_eq!(resolver.seen.len(), 1);
+        assert_eq!(resolver.seen[0].0, 2);
+        assert_eq!(
+            resolver.seen[0].1,
+            vec!["fn two() {", "    let x = 2;", "    x", "}"]
+        );
+        assert_eq!(resolver.seen[0].2, None);
+
+        // 非交互时同样跳过，不做猜测
+        assert_eq!(apply(&current, &mut SkipFailing), result);
+    }
+
+    #[test]
+    fn test_retarget_to_fuzzy_match() {
+        // fn two 被移动到文件末尾，函数体也改了，模糊匹配能找到它
+        let current = ORIGINAL.replace("fn two() {\n    let x = 2;\n    x\n}\n\n", "")
+            + "\nfn two() {\n    let x = 2;\n    x + 0\n}\n";
+        let mut resolver = Scripted {
+            choice: Resolution::Retarget,
+            seen: Vec::new(),
+        };
+        let result = apply(&current, &mut resolver);
+
+        assert_eq!(resolver.seen[0].2, Some(8));
+        assert_eq!(result.reports[1].status, HunkStatus::Retargeted);
+        assert_eq!(result.reports[1].line, Some(9));
+        assert_eq!(
+            result.lines[8..],
+            ["fn two() {", "    let x = 2;", "    x * 10", "}"]
+        );
+        // 候选位置在第三个 hunk 之后，第三个 hunk 仍按原位置应用
+        assert_eq!(result.reports[2].status, HunkStatus::Applied);
+        assert!(result.lines.contains(&"    30".to_string()));
+    }
+}
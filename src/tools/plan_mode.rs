@@ -1,11 +1,14 @@
 //! 计划模式工具
 //!
 //! 实现 EnterPlanMode 和 ExitPlanMode 工具，让 Agent 可以自主进入和退出计划模式。
+//!
+//! 计划状态属于会话：[`PlanModeManager`] 由 `AgentBuilder` 持有并传给两个工具，
+//! 不同会话（REPL、`oxide serve` 的每个会话、嵌入的 `OxideSession`）互不影响。
 
 use super::FileToolError;
+use crate::agent::interaction;
 use colored::*;
 use inquire::{Confirm, Select};
-use once_cell::sync::Lazy;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -70,6 +73,20 @@ pub struct PlanModeState {
     pub approved: bool,
 }
 
+/// 计划模式状态转换错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PlanModeError {
+    /// 正在制定计划时再次进入
+    #[error("Already in plan mode. Use exit_plan_mode to exit first.")]
+    AlreadyActive,
+    /// 未进入计划模式
+    #[error("Not in plan mode. Use enter_plan_mode first.")]
+    NotActive,
+    /// 计划已批准，不能再次提交
+    #[error("The plan has already been approved. Use enter_plan_mode to plan new work.")]
+    AlreadyApproved,
+}
+
 impl Default for PlanModeState {
     fn default() -> Self {
         Self {
@@ -85,8 +102,25 @@ impl Default for PlanModeState {
 }
 
 impl PlanModeState {
-    /// 进入计划模式
-    pub fn enter(&mut self) -> String {
+    /// 正在制定计划（已进入、尚未批准）
+    pub fn is_planning(&self) -> bool {
+        self.active && !self.approved
+    }
+
+    /// 制定中的计划才能提交或批准
+    fn ensure_planning(&self) -> Result<(), PlanModeError> {
+        match (self.active, self.approved) {
+            (false, _) => Err(PlanModeError::NotActive),
+            (true, true) => Err(PlanModeError::AlreadyApproved),
+            (true, false) => Ok(()),
+        }
+    }
+
+    /// 进入计划模式（计划已批准时开始新的计划）
+    pub fn enter(&mut self) -> Result<String, PlanModeError> {
+        if self.is_planning() {
+            return Err(PlanModeError::AlreadyActive);
+        }
         let plan_id = format!("plan_{}", Local::now().format("%Y%m%d_%H%M%S"));
         let plan_file = PathBuf::from(".oxide/plans").join(format!("{}.md", plan_id));
 
@@ -98,23 +132,31 @@ impl PlanModeState {
         self.entered_at = Some(Utc::now());
         self.approved = false;
 
-        plan_id
+        Ok(plan_id)
     }
 
     /// 退出计划模式
-    pub fn exit(&mut self) {
+    pub fn exit(&mut self) -> Result<(), PlanModeError> {
+        if !self.active {
+            return Err(PlanModeError::NotActive);
+        }
         self.active = false;
         self.approved = false;
+        Ok(())
     }
 
     /// 设置计划内容
-    pub fn set_plan_content(&mut self, content: String) {
+    pub fn set_plan_content(&mut self, content: String) -> Result<(), PlanModeError> {
+        self.ensure_planning()?;
         self.plan_content = Some(content);
+        Ok(())
     }
 
     /// 添加允许的权限
-    pub fn add_allowed_prompt(&mut self, prompt: AllowedPrompt) {
+    pub fn add_allowed_prompt(&mut self, prompt: AllowedPrompt) -> Result<(), PlanModeError> {
+        self.ensure_planning()?;
         self.allowed_prompts.push(prompt);
+        Ok(())
     }
 
     /// 检查权限是否被允许
@@ -126,12 +168,17 @@ impl PlanModeState {
     }
 
     /// 批准计划
-    pub fn approve(&mut self) {
+    pub fn approve(&mut self) -> Result<(), PlanModeError> {
+        self.ensure_planning()?;
         self.approved = true;
+        Ok(())
     }
 }
 
-/// 全局计划模式状态管理器
+/// 一个会话的计划模式状态
+///
+/// 克隆得到的是同一份状态的句柄；每次状态转换在同一次加锁内完成校验和修改。
+#[derive(Debug)]
 pub struct PlanModeManager {
     state: Arc<RwLock<PlanModeState>>,
 }
@@ -155,24 +202,28 @@ impl PlanModeManager {
         self.state.read().unwrap().approved
     }
 
-    pub fn enter(&self) -> String {
+    pub fn enter(&self) -> Result<String, PlanModeError> {
         self.state.write().unwrap().enter()
     }
 
-    pub fn exit(&self) {
+    pub fn exit(&self) -> Result<(), PlanModeError> {
         self.state.write().unwrap().exit()
     }
 
-    pub fn set_plan_content(&self, content: String) {
-        self.state.write().unwrap().set_plan_content(content);
+    pub fn set_plan_content(&self, content: String) -> Result<(), PlanModeError> {
+        self.state.write().unwrap().set_plan_content(content)
     }
 
-    pub fn add_allowed_prompt(&self, prompt: AllowedPrompt) {
-        self.state.write().unwrap().add_allowed_prompt(prompt);
+    /// 提交计划需要的权限（全部添加或全部不添加）
+    pub fn add_allowed_prompts(&self, prompts: &[AllowedPrompt]) -> Result<(), PlanModeError> {
+        let mut state = self.state.write().unwrap();
+        state.ensure_planning()?;
+        state.allowed_prompts.extend_from_slice(prompts);
+        Ok(())
     }
 
-    pub fn approve(&self) {
-        self.state.write().unwrap().approve();
+    pub fn approve(&self) -> Result<(), PlanModeError> {
+        self.state.write().unwrap().approve()
     }
 
     pub fn get_allowed_prompts(&self) -> Vec<AllowedPrompt> {
@@ -198,9 +249,6 @@ impl Clone for PlanModeManager {
     }
 }
 
-// 全局单例
-static PLAN_MODE_MANAGER: Lazy<PlanModeManager> = Lazy::new(|| PlanModeManager::new());
-
 // ============================================================================
 // EnterPlanMode 工具
 // ============================================================================
@@ -227,9 +275,22 @@ pub struct EnterPlanModeOutput {
     pub message: String,
 }
 
+impl EnterPlanModeOutput {
+    fn rejected(error: PlanModeError) -> Self {
+        Self {
+            success: false,
+            plan_id: None,
+            plan_file: None,
+            message: error.to_string(),
+        }
+    }
+}
+
 /// EnterPlanMode 工具
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct EnterPlanModeTool;
+#[derive(Debug, Clone)]
+pub struct EnterPlanModeTool {
+    manager: PlanModeManager,
+}
 
 impl Tool for EnterPlanModeTool {
     const NAME: &'static str = "enter_plan_mode";
@@ -281,13 +342,8 @@ In plan mode, you'll:
 
     async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
         // 检查是否已经在计划模式中
-        if PLAN_MODE_MANAGER.is_active() {
-            return Ok(EnterPlanModeOutput {
-                success: false,
-                plan_id: None,
-                plan_file: None,
-                message: "Already in plan mode. Use exit_plan_mode to exit first.".to_string(),
-            });
+        if self.manager.get_state().is_planning() {
+            return Ok(EnterPlanModeOutput::rejected(PlanModeError::AlreadyActive));
         }
 
         // 创建计划目录
@@ -301,27 +357,43 @@ In plan mode, you'll:
             });
         }
 
-        // 进入计划模式
-        let plan_id = PLAN_MODE_MANAGER.enter();
-        let state = PLAN_MODE_MANAGER.get_state();
+        // 进入计划模式（检查之后状态可能已被同一会话的其他调用改变）
+        let plan_id = match self.manager.enter() {
+            Ok(plan_id) => plan_id,
+            Err(e) => return Ok(EnterPlanModeOutput::rejected(e)),
+        };
+        let state = self.manager.get_state();
         let plan_file = state.plan_file.map(|p| p.display().to_string());
 
         // 显示进入计划模式的提示
-        println!();
-        println!("{}", "╔══════════════════════════════════════════════════════════════╗".bright_cyan());
-        println!("{}", "║                    📋 进入计划模式                            ║".bright_cyan());
-        println!("{}", "╚══════════════════════════════════════════════════════════════╝".bright_cyan());
-        println!();
-        println!("{} {}", "计划 ID:".bright_white(), plan_id.bright_yellow());
+        tool_println!();
+        tool_println!(
+            "{}",
+            "╔══════════════════════════════════════════════════════════════╗".bright_cyan()
+        );
+        tool_println!(
+            "{}",
+            format!("║{:^62}║", t!("plan.enter.title")).bright_cyan()
+        );
+        tool_println!(
+            "{}",
+            "╚══════════════════════════════════════════════════════════════╝".bright_cyan()
+        );
+        tool_println!();
+        tool_println!(
+            "{} {}",
+            t!("plan.id").bright_white(),
+            plan_id.bright_yellow()
+        );
         if let Some(ref file) = plan_file {
-            println!("{} {}", "计划文件:".bright_white(), file.bright_cyan());
+            tool_println!("{} {}", t!("plan.file").bright_white(), file.bright_cyan());
         }
-        println!();
-        println!("{}", "在计划模式下，你可以：".bright_white());
-        println!("  {} 探索代码库，了解现有架构", "•".bright_green());
-        println!("  {} 设计实现方案", "•".bright_green());
-        println!("  {} 使用 exit_plan_mode 提交计划并请求用户批准", "•".bright_green());
-        println!();
+        tool_println!();
+        tool_println!("{}", t!("plan.enter.intro").bright_white());
+        tool_println!("  {} {}", "•".bright_green(), t!("plan.enter.explore"));
+        tool_println!("  {} {}", "•".bright_green(), t!("plan.enter.design"));
+        tool_println!("  {} {}", "•".bright_green(), t!("plan.enter.submit"));
+        tool_println!();
 
         Ok(EnterPlanModeOutput {
             success: true,
@@ -370,27 +442,55 @@ pub struct ExitPlanModeOutput {
     pub approved_prompts: Vec<AllowedPrompt>,
 }
 
+impl ExitPlanModeOutput {
+    fn rejected(error: PlanModeError) -> Self {
+        Self {
+            success: false,
+            approved: false,
+            message: error.to_string(),
+            approved_prompts: Vec::new(),
+        }
+    }
+}
+
 /// ExitPlanMode 工具
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ExitPlanModeTool;
+#[derive(Debug, Clone)]
+pub struct ExitPlanModeTool {
+    manager: PlanModeManager,
+}
 
 impl ExitPlanModeTool {
     /// 请求用户批准计划
-    fn request_approval(allowed_prompts: &[AllowedPrompt]) -> Result<(bool, String), FileToolError> {
+    fn request_approval(
+        state: &PlanModeState,
+        allowed_prompts: &[AllowedPrompt],
+    ) -> Result<(bool, String), FileToolError> {
         println!();
-        println!("{}", "╔══════════════════════════════════════════════════════════════╗".bright_yellow());
-        println!("{}", "║                    📋 计划审批请求                            ║".bright_yellow());
-        println!("{}", "╚══════════════════════════════════════════════════════════════╝".bright_yellow());
+        println!(
+            "{}",
+            "╔══════════════════════════════════════════════════════════════╗".bright_yellow()
+        );
+        println!(
+            "{}",
+            format!("║{:^62}║", t!("plan.approval.title")).bright_yellow()
+        );
+        println!(
+            "{}",
+            "╚══════════════════════════════════════════════════════════════╝".bright_yellow()
+        );
         println!();
 
         // 显示计划内容
-        let state = PLAN_MODE_MANAGER.get_state();
         if let Some(ref content) = state.plan_content {
-            println!("{}", "📝 计划内容:".bright_cyan());
+            println!("{}", t!("plan.approval.content").bright_cyan());
             println!("{}", "─".repeat(60).dimmed());
             // 限制显示长度
             let display_content = if content.len() > 2000 {
-                format!("{}...\n\n(内容已截断)", &content[..2000])
+                format!(
+                    "{}...\n\n{}",
+                    &content[..2000],
+                    t!("plan.approval.truncated")
+                )
             } else {
                 content.clone()
             };
@@ -401,7 +501,7 @@ impl ExitPlanModeTool {
 
         // 显示需要的权限
         if !allowed_prompts.is_empty() {
-            println!("{}", "🔐 需要的权限:".bright_cyan());
+            println!("{}", t!("plan.approval.permissions").bright_cyan());
             for (i, prompt) in allowed_prompts.iter().enumerate() {
                 println!(
                     "  {}. {} - {}",
@@ -414,24 +514,22 @@ impl ExitPlanModeTool {
         }
 
         // 请求用户批准
-        let options = vec![
-            "批准并执行 - Approve and execute the plan",
-            "修改计划 - Request modifications to the plan",
-            "取消 - Cancel and discard the plan",
-        ];
-
-        let selection = Select::new("请选择操作:", options)
-            .with_help_message("↑↓ 移动，Enter 确认")
+        let approve = t!("plan.approval.approve");
+        let modify = t!("plan.approval.modify");
+        let options = vec![approve, modify, t!("plan.approval.cancel")];
+
+        let selection = Select::new(t!("plan.approval.prompt"), options)
+            .with_help_message(t!("plan.approval.help"))
             .prompt();
 
         match selection {
             Ok(choice) => {
-                if choice.starts_with("批准") {
+                if choice == approve {
                     Ok((true, "Plan approved by user.".to_string()))
-                } else if choice.starts_with("修改") {
+                } else if choice == modify {
                     // 请求用户输入修改意见
                     println!();
-                    println!("{}", "请输入修改意见 (按 Enter 提交):".bright_yellow());
+                    println!("{}", t!("plan.approval.feedback").bright_yellow());
                     let mut input = String::new();
                     std::io::stdin().read_line(&mut input).map_err(|e| FileToolError::Io(e))?;
                     let feedback = input.trim().to_string();
@@ -443,7 +541,7 @@ impl ExitPlanModeTool {
             Err(_) => {
                 // 用户取消或出错，使用简单的确认
                 println!();
-                let confirm = Confirm::new("是否批准此计划?")
+                let confirm = Confirm::new(t!("plan.approval.confirm"))
                     .with_default(false)
                     .prompt();
 
@@ -464,19 +562,30 @@ impl ExitPlanModeTool {
         let plan_file = plans_dir.join(format!("{}.md", plan_id));
 
         let mut full_content = String::new();
-        full_content.push_str(&format!("# 计划: {}\n\n", plan_id));
-        full_content.push_str(&format!("> 生成时间: {}\n", Local::now().format("%Y-%m-%d %H:%M:%S")));
-        full_content.push_str(&format!("> 状态: {}\n\n", if approved { "✅ 已批准" } else { "❌ 未批准" }));
+        full_content.push_str(&format!("{}\n\n", t!("plan_file.plan_title", id = plan_id)));
+        full_content.push_str(&format!(
+            "{}\n",
+            t!(
+                "plan_file.generated_at",
+                time = Local::now().format("%Y-%m-%d %H:%M:%S")
+            )
+        ));
+        let status = if approved {
+            t!("plan_file.approved")
+        } else {
+            t!("plan_file.not_approved")
+        };
+        full_content.push_str(&format!("{}\n\n", t!("plan_file.status", status = status)));
 
         if !allowed_prompts.is_empty() {
-            full_content.push_str("## 🔐 权限列表\n\n");
+            full_content.push_str(&format!("{}\n\n", t!("plan_file.permissions")));
             for prompt in allowed_prompts {
                 full_content.push_str(&format!("- **{}**: {}\n", prompt.tool, prompt.prompt));
             }
             full_content.push_str("\n");
         }
 
-        full_content.push_str("## 📋 计划内容\n\n");
+        full_content.push_str(&format!("{}\n\n", t!("plan_file.content")));
         full_content.push_str(content);
 
         fs::write(&plan_file, full_content).map_err(|e| FileToolError::Io(e))?;
@@ -542,16 +651,6 @@ Ensure your plan is complete and unambiguous:
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        // 检查是否在计划模式中
-        if !PLAN_MODE_MANAGER.is_active() {
-            return Ok(ExitPlanModeOutput {
-                success: false,
-                approved: false,
-                message: "Not in plan mode. Use enter_plan_mode first.".to_string(),
-                approved_prompts: Vec::new(),
-            });
-        }
-
         // 转换权限参数
         let allowed_prompts: Vec<AllowedPrompt> = args
             .allowed_prompts
@@ -559,32 +658,39 @@ Ensure your plan is complete and unambiguous:
             .map(|p| AllowedPrompt::new(&p.tool, &p.prompt))
             .collect();
 
-        // 添加权限到状态
-        for prompt in &allowed_prompts {
-            PLAN_MODE_MANAGER.add_allowed_prompt(prompt.clone());
+        // 添加权限到状态（只有制定中的计划可以提交）
+        if let Err(e) = self.manager.add_allowed_prompts(&allowed_prompts) {
+            return Ok(ExitPlanModeOutput::rejected(e));
         }
 
-        // 请求用户批准
-        let (approved, message) = Self::request_approval(&allowed_prompts)?;
+        // 请求用户批准（与其他工具的提问一起排队）
+        let state = self.manager.get_state();
+        let (approved, message) =
+            interaction::interact(|| Self::request_approval(&state, &allowed_prompts)).await??;
 
         // 获取计划信息
-        let state = PLAN_MODE_MANAGER.get_state();
         let plan_id = state.plan_id.clone().unwrap_or_else(|| "unknown".to_string());
         let plan_content = state.plan_content.clone().unwrap_or_else(|| "No plan content provided.".to_string());
 
         // 保存计划到文件
         if let Err(e) = Self::save_plan(&plan_id, &plan_content, &allowed_prompts, approved) {
-            eprintln!("{} 保存计划文件失败: {}", "⚠️".yellow(), e);
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                t!("plan.save_failed", error = e.message())
+            );
         }
 
         if approved {
             // 批准计划
-            PLAN_MODE_MANAGER.approve();
+            if let Err(e) = self.manager.approve() {
+                return Ok(ExitPlanModeOutput::rejected(e));
+            }
 
-            println!();
-            println!("{}", "✅ 计划已批准！".bright_green().bold());
-            println!("{}", "现在可以开始执行计划。".bright_white());
-            println!();
+            tool_println!();
+            tool_println!("{}", t!("plan.approved").bright_green().bold());
+            tool_println!("{}", t!("plan.approved_hint").bright_white());
+            tool_println!();
 
             // 退出计划模式但保留批准状态
             // 注意：这里不调用 exit()，因为我们需要保留权限信息
@@ -597,12 +703,14 @@ Ensure your plan is complete and unambiguous:
             })
         } else {
             // 退出计划模式
-            PLAN_MODE_MANAGER.exit();
+            if let Err(e) = self.manager.exit() {
+                return Ok(ExitPlanModeOutput::rejected(e));
+            }
 
-            println!();
-            println!("{}", "❌ 计划未批准".bright_red().bold());
-            println!("{}", message.bright_white());
-            println!();
+            tool_println!();
+            tool_println!("{}", t!("plan.not_approved").bright_red().bold());
+            tool_println!("{}", message.bright_white());
+            tool_println!();
 
             Ok(ExitPlanModeOutput {
                 success: true,
@@ -619,25 +727,19 @@ Ensure your plan is complete and unambiguous:
 // ============================================================================
 
 /// EnterPlanMode 工具包装器
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone)]
 pub struct WrappedEnterPlanModeTool {
     inner: EnterPlanModeTool,
 }
 
 impl WrappedEnterPlanModeTool {
-    pub fn new() -> Self {
+    pub fn new(manager: PlanModeManager) -> Self {
         Self {
-            inner: EnterPlanModeTool,
+            inner: EnterPlanModeTool { manager },
         }
     }
 }
 
-impl Default for WrappedEnterPlanModeTool {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl Tool for WrappedEnterPlanModeTool {
     const NAME: &'static str = "enter_plan_mode";
 
@@ -655,25 +757,19 @@ impl Tool for WrappedEnterPlanModeTool {
 }
 
 /// ExitPlanMode 工具包装器
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone)]
 pub struct WrappedExitPlanModeTool {
     inner: ExitPlanModeTool,
 }
 
 impl WrappedExitPlanModeTool {
-    pub fn new() -> Self {
+    pub fn new(manager: PlanModeManager) -> Self {
         Self {
-            inner: ExitPlanModeTool,
+            inner: ExitPlanModeTool { manager },
         }
     }
 }
 
-impl Default for WrappedExitPlanModeTool {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl Tool for WrappedExitPlanModeTool {
     const NAME: &'static str = "exit_plan_mode";
 
@@ -690,35 +786,6 @@ impl Tool for WrappedExitPlanModeTool {
     }
 }
 
-// ============================================================================
-// 辅助函数
-// ============================================================================
-
-/// 检查当前是否在计划模式中
-pub fn is_in_plan_mode() -> bool {
-    PLAN_MODE_MANAGER.is_active()
-}
-
-/// 检查计划是否已被批准
-pub fn is_plan_approved() -> bool {
-    PLAN_MODE_MANAGER.is_approved()
-}
-
-/// 检查操作是否被允许
-pub fn is_operation_allowed(tool: &str, operation: &str) -> bool {
-    PLAN_MODE_MANAGER.is_allowed(tool, operation)
-}
-
-/// 设置计划内容（供 Agent 在计划模式中使用）
-pub fn set_plan_content(content: &str) {
-    PLAN_MODE_MANAGER.set_plan_content(content.to_string());
-}
-
-/// 获取当前计划状态
-pub fn get_plan_state() -> PlanModeState {
-    PLAN_MODE_MANAGER.get_state()
-}
-
 // ============================================================================
 // 测试
 // ============================================================================
@@ -755,7 +822,7 @@ mod tests {
     #[test]
     fn test_plan_mode_state_enter() {
         let mut state = PlanModeState::default();
-        let plan_id = state.enter();
+        let plan_id = state.enter().unwrap();
 
         assert!(state.active);
         assert!(plan_id.starts_with("plan_"));
@@ -767,9 +834,9 @@ mod tests {
     #[test]
     fn test_plan_mode_state_exit() {
         let mut state = PlanModeState::default();
-        state.enter();
-        state.approve();
-        state.exit();
+        state.enter().unwrap();
+        state.approve().unwrap();
+        state.exit().unwrap();
 
         assert!(!state.active);
         assert!(!state.approved);
@@ -778,11 +845,75 @@ mod tests {
     #[test]
     fn test_plan_mode_state_is_allowed() {
         let mut state = PlanModeState::default();
-        state.enter();
-        state.add_allowed_prompt(AllowedPrompt::new("Bash", "run tests"));
-        state.approve();
+        state.enter().unwrap();
+        state
+            .add_allowed_prompt(AllowedPrompt::new("Bash", "run tests"))
+            .unwrap();
+        state.approve().unwrap();
 
         assert!(state.is_allowed("Bash", "tests"));
         assert!(!state.is_allowed("Write", "tests"));
     }
+
+    #[test]
+    fn test_plan_mode_transitions_are_validated() {
+        let manager = PlanModeManager::new();
+        assert_eq!(manager.exit(), Err(PlanModeError::NotActive));
+        assert_eq!(manager.approve(), Err(PlanModeError::NotActive));
+        assert_eq!(
+            manager.set_plan_content("plan".to_string()),
+            Err(PlanModeError::NotActive)
+        );
+
+        manager.enter().unwrap();
+        assert_eq!(manager.enter(), Err(PlanModeError::AlreadyActive));
+        manager.approve().unwrap();
+        assert_eq!(manager.approve(), Err(PlanModeError::AlreadyApproved));
+        assert_eq!(
+            manager.add_allowed_prompts(&[AllowedPrompt::new("Bash", "run tests")]),
+            Err(PlanModeError::AlreadyApproved)
+        );
+
+        // 计划批准后可以开始新的计划，旧计划的权限不会保留
+        manager.enter().unwrap();
+        let state = manager.get_state();
+        assert!(state.is_planning());
+        assert!(state.allowed_prompts.is_empty());
+        manager.exit().unwrap();
+        assert!(!manager.is_active());
+    }
+
+    #[test]
+    fn test_sessions_do_not_share_plan_state() {
+        let handles: Vec<_> = (0..2)
+            .map(|session| {
+                std::thread::spawn(move || {
+                    // 每个会话持有自己的管理器，工具拿到的是它的克隆
+                    let manager = PlanModeManager::new();
+                    let tool_handle = manager.clone();
+                    let own = format!("session {}", session);
+                    let other = format!("session {}", 1 - session);
+                    for round in 0..200 {
+                        tool_handle.enter().unwrap();
+                        let prompt = AllowedPrompt::new("Bash", &own);
+                        tool_handle.add_allowed_prompts(&[prompt]).unwrap();
+                        if round % 2 == 0 {
+                            tool_handle.approve().unwrap();
+                            assert!(manager.is_allowed("Bash", &own));
+                            assert!(!manager.is_allowed("Bash", &other));
+                        }
+                        let state = manager.get_state();
+                        assert_eq!(state.allowed_prompts.len(), 1);
+                        tool_handle.exit().unwrap();
+                    }
+                    manager
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let manager = handle.join().unwrap();
+            assert!(!manager.is_active());
+        }
+    }
 }
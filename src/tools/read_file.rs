@@ -1,4 +1,11 @@
-use super::FileToolError;
+use super::ask_user_question::{ask_question_interactive, Question, QuestionOption};
+use super::encoding::FileEncoding;
+use super::image::ImageInfo;
+use super::sensitive_paths::{get_sensitive_guard, SensitiveAccess};
+use super::settings::ToolSettings;
+use super::uncommitted::get_overwrite_guard;
+use super::verbose_output::verbose_tools;
+use super::{ErrorCode, FileToolError, ToolFailure};
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -8,6 +15,32 @@ use std::path::Path;
 #[derive(Deserialize, Serialize)]
 pub struct ReadFileArgs {
     pub file_path: String,
+    /// 从第几行开始读取（从 1 开始）
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// 最多读取的行数
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// 要读取的行范围，未指定时读取整个文件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineRange {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl LineRange {
+    fn from_args(args: &ReadFileArgs) -> Self {
+        Self {
+            offset: args.offset,
+            limit: args.limit,
+        }
+    }
+
+    fn is_whole_file(&self) -> bool {
+        self.offset.is_none() && self.limit.is_none()
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -15,12 +48,211 @@ pub struct ReadFileOutput {
     pub content: String,
     pub file_path: String,
     pub size_bytes: u64,
+    /// 识别出的文件编码，如 `utf-8`、`latin-1`、`utf-16le with BOM`（图片为空）
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub encoding: String,
     pub success: bool,
     pub message: String,
+    /// 文件是图片时的格式和尺寸（此时 `content` 为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageInfo>,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct ReadFileTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct ReadFileTool {
+    settings: ToolSettings,
+}
+
+impl ReadFileTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// 读取文件内容并脱敏（不做敏感路径检查）
+    fn read(&self, file_path: &str, range: LineRange) -> Result<ReadFileOutput, FileToolError> {
+        let path = Path::new(file_path);
+
+        // Check if file exists
+        if !path.exists() {
+            return Err(FileToolError::FileNotFound(file_path.to_string()));
+        }
+
+        // Check if it's actually a file (not a directory)
+        if !path.is_file() {
+            return Err(FileToolError::NotAFile(file_path.to_string()));
+        }
+
+        // Try to read the file
+        let bytes = match fs::read(file_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                return Err(FileToolError::PermissionDenied(file_path.to_string()));
+            }
+            Err(e) => return Err(FileToolError::Io(e)),
+        };
+
+        // 图片不返回内容，只说明格式和尺寸；交互式 CLI 会在终端显示预览
+        if let Some(image) = ImageInfo::sniff(file_path, &bytes) {
+            return Ok(ReadFileOutput {
+                content: String::new(),
+                file_path: file_path.to_string(),
+                size_bytes: image.size_bytes,
+                encoding: String::new(),
+                success: true,
+                message: format!(
+                    "'{}' is an image ({}); its pixels are not included in this result",
+                    file_path,
+                    image.describe()
+                ),
+                image: Some(image),
+            });
+        }
+
+        let decoded = FileEncoding::detect(&bytes)
+            .and_then(|encoding| Some((encoding, encoding.decode(&bytes)?)));
+        match decoded {
+            Some((encoding, mut content)) => {
+                let size_bytes = bytes.len() as u64;
+                let selected = select_lines(&mut content, range).map_err(|total| {
+                    FileToolError::from(ToolFailure::new(
+                        ErrorCode::InvalidArgs,
+                        format!(
+                            "offset {} is past the end of '{}' ({} lines)",
+                            range.offset.unwrap_or(1),
+                            file_path,
+                            total
+                        ),
+                    ))
+                })?;
+                let total_lines = self
+                    .settings
+                    .read_max_lines
+                    .and_then(|max_lines| truncate_lines(&mut content, max_lines));
+
+                let mut message = format!(
+                    "Successfully read {} bytes from '{}'",
+                    size_bytes, file_path
+                );
+                if encoding != FileEncoding::default() {
+                    message.push_str(&format!(
+                        " (encoded as {}; write_file keeps this encoding)",
+                        encoding.describe()
+                    ));
+                }
+                if let Some((start, end, total)) = selected {
+                    message.push_str(&format!(" (lines {}-{} of {})", start, end, total));
+                }
+                if let (Some(total), Some(max_lines)) = (total_lines, self.settings.read_max_lines)
+                {
+                    message.push_str(&format!(
+                        " (showing the first {} of {} lines; [tools.read] max_lines limits this, pass offset to read further)",
+                        max_lines, total
+                    ));
+                }
+
+                // 读过的文件之后写入时不再确认是否覆盖未提交的修改
+                get_overwrite_guard().record_seen(Path::new(file_path));
+                Ok(ReadFileOutput {
+                    content,
+                    file_path: file_path.to_string(),
+                    size_bytes,
+                    encoding: encoding.describe(),
+                    success: true,
+                    message,
+                    image: None,
+                })
+            }
+            None => Err(ToolFailure::new(
+                ErrorCode::InvalidArgs,
+                format!("'{}' is not a text file", file_path),
+            )
+            .with_suggestion(
+                "binary file; inspect it with shell_execute (e.g. `file` or `xxd | head`)",
+            )
+            .into()),
+        }
+    }
+}
+
+/// 只保留 `range` 中的行，返回 (起始行, 结束行, 总行数)；读取整个文件时返回 `None`
+///
+/// 起始行超出文件末尾时返回总行数作为错误。
+fn select_lines(
+    content: &mut String,
+    range: LineRange,
+) -> Result<Option<(usize, usize, usize)>, usize> {
+    if range.is_whole_file() {
+        return Ok(None);
+    }
+    let total = content.lines().count();
+    let start = range.offset.unwrap_or(1).max(1);
+    if start > total.max(1) {
+        return Err(total);
+    }
+    let selected: String = content
+        .split_inclusive('\n')
+        .skip(start - 1)
+        .take(range.limit.unwrap_or(usize::MAX))
+        .collect();
+    let end = start + selected.lines().count().saturating_sub(1);
+    *content = selected;
+    Ok(Some((start, end, total)))
+}
+
+/// 只保留前 `max_lines` 行，超出时返回截断前的总行数
+fn truncate_lines(content: &mut String, max_lines: usize) -> Option<usize> {
+    let total = content.lines().count();
+    if total <= max_lines {
+        return None;
+    }
+    if let Some((index, _)) = content.match_indices('\n').nth(max_lines - 1) {
+        content.truncate(index + 1);
+    }
+    Some(total)
+}
+
+/// 构建敏感文件拒绝错误
+fn sensitive_denied_error(file_path: &str, pattern: &str) -> FileToolError {
+    ToolFailure::new(
+        ErrorCode::PermissionDenied,
+        format!(
+            "Permission denied: {} (matches sensitive path pattern '{}'; configure [permissions.sensitive_paths] to change this)",
+            file_path, pattern
+        ),
+    )
+    .with_details(serde_json::json!({ "pattern": pattern }))
+    .with_suggestion("do not try to read this file another way; ask the user for what you need")
+    .into()
+}
+
+/// 请求用户确认读取敏感文件
+fn confirm_sensitive_read(file_path: &str, pattern: &str) -> Result<bool, FileToolError> {
+    let question = Question {
+        question: t!(
+            "read.sensitive.question",
+            path = file_path,
+            pattern = pattern
+        ),
+        header: "".to_string(),
+        options: vec![
+            QuestionOption {
+                label: t!("common.yes").to_string(),
+                description: t!("read.sensitive.allow").to_string(),
+            },
+            QuestionOption {
+                label: t!("common.no").to_string(),
+                description: t!("read.sensitive.deny").to_string(),
+            },
+        ],
+        multi_select: false,
+        allow_other: false,
+    };
+
+    let answer = ask_question_interactive(&question)?;
+    Ok(answer.selected.as_str() == Some(t!("common.yes")))
+}
 
 impl Tool for ReadFileTool {
     const NAME: &'static str = "read_file";
@@ -32,13 +264,23 @@ impl Tool for ReadFileTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "read_file".to_string(),
-            description: "Read the contents of a file from the filesystem. Supports text files and returns the content as a string.".to_string(),
+            description: "Read the contents of a file from the filesystem. Supports text files in any common encoding and returns the content as a string, reporting the detected encoding. Use offset and limit to read a range of lines from a large file. For images (PNG, JPEG, GIF, WebP) only the format and dimensions are returned. Error codes: NotFound (no such file), InvalidArgs (a directory or a binary file), PermissionDenied (unreadable, or a protected sensitive file).".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "file_path": {
                         "type": "string",
                         "description": "The path to the file to read (relative or absolute). Examples: 'README.md', 'src/main.rs', '/path/to/file.txt'"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Line number to start reading from (1-based). Defaults to the first line."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Maximum number of lines to read. Defaults to the rest of the file."
                     }
                 },
                 "required": ["file_path"]
@@ -47,43 +289,17 @@ impl Tool for ReadFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let file_path = &args.file_path;
-        let path = Path::new(file_path);
+        let file_path = &self.settings.resolve_path(&args.file_path);
+        let range = LineRange::from_args(&args);
+        self.settings.roots.check(Path::new(file_path))?;
 
-        // Check if file exists
-        if !path.exists() {
-            return Err(FileToolError::FileNotFound(file_path.clone()));
-        }
-
-        // Check if it's actually a file (not a directory)
-        if !path.is_file() {
-            return Err(FileToolError::NotAFile(file_path.clone()));
-        }
-
-        // Try to read the file
-        match fs::read_to_string(file_path) {
-            Ok(content) => {
-                // Get file metadata for size
-                let metadata = fs::metadata(file_path)?;
-                let size_bytes = metadata.len();
-
-                Ok(ReadFileOutput {
-                    content,
-                    file_path: file_path.clone(),
-                    size_bytes,
-                    success: true,
-                    message: format!(
-                        "Successfully read {} bytes from '{}'",
-                        size_bytes, file_path
-                    ),
-                })
+        // 非交互场景下，需要确认的敏感文件同样拒绝
+        match get_sensitive_guard().check(Path::new(file_path)) {
+            SensitiveAccess::Allowed => self.read(file_path, range),
+            SensitiveAccess::Denied { pattern }
+            | SensitiveAccess::NeedsConfirmation { pattern } => {
+                Err(sensitive_denied_error(file_path, &pattern))
             }
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::PermissionDenied => {
-                    Err(FileToolError::PermissionDenied(file_path.clone()))
-                }
-                _ => Err(FileToolError::Io(e)),
-            },
         }
     }
 }
@@ -94,9 +310,9 @@ pub struct WrappedReadFileTool {
 }
 
 impl WrappedReadFileTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: ReadFileTool,
+            inner: ReadFileTool::new(settings),
         }
     }
 }
@@ -113,10 +329,32 @@ impl Tool for WrappedReadFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Read", args.file_path);
+        let file_path = &self.inner.settings.resolve_path(&args.file_path);
+        let range = LineRange::from_args(&args);
+        let roots = &self.inner.settings.roots;
+        tool_println!();
+        tool_println!(
+            "{} {}({})",
+            "●".bright_green(),
+            "Read",
+            roots.display(Path::new(file_path))
+        );
 
-        let result = self.inner.call(args).await;
+        let result = roots.check(Path::new(file_path)).and_then(|()| {
+            match get_sensitive_guard().check(Path::new(file_path)) {
+                SensitiveAccess::Allowed => self.inner.read(file_path, range),
+                SensitiveAccess::Denied { pattern } => {
+                    Err(sensitive_denied_error(file_path, &pattern))
+                }
+                SensitiveAccess::NeedsConfirmation { pattern } => {
+                    match confirm_sensitive_read(file_path, &pattern) {
+                        Ok(true) => self.inner.read(file_path, range),
+                        Ok(false) => Err(sensitive_denied_error(file_path, &pattern)),
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+        });
 
         match &result {
             Ok(output) => {
@@ -128,18 +366,153 @@ impl Tool for WrappedReadFileTool {
                 } else {
                     first_line.to_string()
                 };
-                println!(
+                tool_println!(
                     "  └─ {}| {} ... +{} lines",
-                    "1".dimmed(),
+                    range.offset.unwrap_or(1).to_string().dimmed(),
                     preview.dimmed(),
                     line_count
                 );
+                verbose_tools().show(
+                    &format!("Read({})", roots.display(Path::new(file_path))),
+                    &output.content,
+                );
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::workspace::WorkspaceRoots;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_honors_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+
+        let settings = ToolSettings {
+            root: Some(temp_dir.path().to_path_buf()),
+            read_max_lines: Some(2),
+            ..ToolSettings::default()
+        };
+        let tool = ReadFileTool::new(&settings);
+        let output = tool
+            .read(&settings.resolve_path("notes.txt"), LineRange::default())
+            .unwrap();
+        assert_eq!(output.content, "one\ntwo\n");
+        assert_eq!(output.size_bytes, 19);
+        assert!(output.message.contains("first 2 of 4 lines"));
+
+        let output = ReadFileTool::default()
+            .read(
+                &temp_dir.path().join("notes.txt").to_string_lossy(),
+                LineRange::default(),
+            )
+            .unwrap();
+        assert_eq!(output.content.lines().count(), 4);
+    }
+
+    #[test]
+    fn test_read_line_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("log.txt");
+        std::fs::write(&path, "l1\nl2\nl3\nl4\nl5\n").unwrap();
+        let path = path.to_string_lossy();
+        let tool = ReadFileTool::default();
+        let range = |offset, limit| LineRange { offset, limit };
+
+        let output = tool.read(&path, range(Some(2), Some(2))).unwrap();
+        assert_eq!(output.content, "l2\nl3\n");
+        assert!(output.message.contains("(lines 2-3 of 5)"));
+        let output = tool.read(&path, range(Some(4), None)).unwrap();
+        assert_eq!(output.content, "l4\nl5\n");
+        let output = tool.read(&path, range(None, Some(1))).unwrap();
+        assert_eq!(output.content, "l1\n");
+
+        let error = tool.read(&path, range(Some(6), None)).unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::InvalidArgs);
+    }
+
+    #[test]
+    fn test_read_reports_encoding() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/encodings");
+        let tool = ReadFileTool::default();
+
+        let output = tool
+            .read(
+                &fixtures.join("utf16le_bom.txt").to_string_lossy(),
+                LineRange::default(),
+            )
+            .unwrap();
+        assert_eq!(output.content, "Grüße aus Köln, café crème\r\nZeile 2\r\n");
+        assert_eq!(output.encoding, "utf-16le with BOM");
+        assert_eq!(output.size_bytes, 76);
+        assert!(output.message.contains("encoded as utf-16le with BOM"));
+
+        let output = tool
+            .read(
+                &fixtures.join("utf8.txt").to_string_lossy(),
+                LineRange::default(),
+            )
+            .unwrap();
+        assert_eq!(output.encoding, "utf-8");
+        assert!(!output.message.contains("encoded as"));
+
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("data.bin");
+        std::fs::write(&binary, b"\x00\x01\x02\xff\xfe\x00").unwrap();
+        let error = tool
+            .read(&binary.to_string_lossy(), LineRange::default())
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::InvalidArgs);
+    }
+
+    #[tokio::test]
+    async fn test_read_limited_to_workspace_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let (web, api, other) = (
+            temp_dir.path().join("web"),
+            temp_dir.path().join("api"),
+            temp_dir.path().join("other"),
+        );
+        for dir in [&web, &api, &other] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("notes.txt"), "hello\n").unwrap();
+        }
+
+        let settings = ToolSettings {
+            root: Some(web.clone()),
+            roots: WorkspaceRoots::new(&web),
+            ..ToolSettings::default()
+        };
+        settings.roots.add(&api).unwrap();
+        let tool = ReadFileTool::new(&settings);
+        let read = |file_path: String| {
+            tool.call(ReadFileArgs {
+                file_path,
+                offset: None,
+                limit: None,
+            })
+        };
+
+        assert_eq!(read("notes.txt".into()).await.unwrap().content, "hello\n");
+        let in_api = api.join("notes.txt").to_string_lossy().to_string();
+        assert_eq!(read(in_api).await.unwrap().content, "hello\n");
+        assert!(read("api:notes.txt".into()).await.is_ok());
+
+        for outside in [
+            other.join("notes.txt").to_string_lossy().to_string(),
+            "../other/notes.txt".to_string(),
+        ] {
+            let error = read(outside).await.unwrap_err();
+            assert_eq!(error.failure().code, ErrorCode::PermissionDenied);
+        }
+    }
+}
@@ -0,0 +1,827 @@
+//! 测试运行工具
+//!
+//! 识别项目的测试框架（Cargo、jest、vitest、npm、pytest）或使用指定的 `runner`，
+//! 尽量以机器可读格式运行测试（nightly 下 libtest JSON、`jest --json`、
+//! `vitest --reporter=json`、`pytest --json-report`），返回通过/失败数量、
+//! 失败用例及其错误信息和耗时。没有机器可读输出时按纯文本解析。
+
+use super::settings::ToolSettings;
+use super::FileToolError;
+use colored::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// 测试运行超时时间
+const TEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 返回的失败用例上限
+const MAX_FAILURES: usize = 20;
+
+/// 单条失败信息保留的最大字符数
+const MAX_MESSAGE_CHARS: usize = 1500;
+
+/// 无法解析结果时保留的输出行数
+const OUTPUT_TAIL_LINES: usize = 40;
+
+/// 测试框架
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestRunner {
+    Cargo,
+    Jest,
+    Vitest,
+    Npm,
+    Pytest,
+}
+
+impl TestRunner {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Jest => "jest",
+            Self::Vitest => "vitest",
+            Self::Npm => "npm",
+            Self::Pytest => "pytest",
+        }
+    }
+
+    /// 根据项目文件识别测试框架
+    pub fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").exists() {
+            return Some(Self::Cargo);
+        }
+        if let Ok(package) = fs::read_to_string(root.join("package.json")) {
+            return Some(if package.contains("\"vitest\"") {
+                Self::Vitest
+            } else if package.contains("\"jest\"") {
+                Self::Jest
+            } else {
+                Self::Npm
+            });
+        }
+        let pytest_markers = ["pytest.ini", "pyproject.toml", "setup.cfg", "tox.ini", "conftest.py"];
+        if pytest_markers.iter().any(|marker| root.join(marker).exists()) {
+            return Some(Self::Pytest);
+        }
+        None
+    }
+}
+
+/// 结果的来源格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    Text,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RunTestsArgs {
+    /// 测试框架（不指定时自动识别）
+    #[serde(default)]
+    pub runner: Option<TestRunner>,
+    /// 只运行名称匹配的测试
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+/// 失败的测试用例
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TestFailure {
+    pub name: String,
+    /// 错误信息（已截断）
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RunTestsOutput {
+    pub runner: TestRunner,
+    pub command: String,
+    pub success: bool,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// 失败用例（最多 20 个）
+    pub failures: Vec<TestFailure>,
+    pub duration_ms: u64,
+    pub format: ReportFormat,
+    /// 无法解析测试结果时（如编译失败）附带输出末尾
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tail: Option<String>,
+}
+
+/// 解析出的测试结果
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TestSummary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    failures: Vec<TestFailure>,
+}
+
+/// 机器可读结果的位置
+enum JsonReport {
+    None,
+    Stdout,
+    File(PathBuf),
+}
+
+/// 一次测试命令
+struct Invocation {
+    program: String,
+    args: Vec<String>,
+    report: JsonReport,
+}
+
+impl Invocation {
+    fn new(program: &str, args: &[&str], report: JsonReport) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            report,
+        }
+    }
+
+    fn display(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 在 `[tools.root]`（未设置时为当前目录）运行测试
+#[derive(Default, Deserialize, Serialize)]
+pub struct RunTestsTool {
+    settings: ToolSettings,
+}
+
+impl RunTestsTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
+
+impl Tool for RunTestsTool {
+    const NAME: &'static str = "run_tests";
+
+    type Error = FileToolError;
+    type Args = RunTestsArgs;
+    type Output = RunTestsOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "run_tests".to_string(),
+            description: "Run the project's tests and return a structured result: pass/fail/skip counts, failed test names with their (truncated) failure messages, and duration. The test framework (cargo, jest, vitest, npm, pytest) is detected from project files unless `runner` is given. Prefer this over shell_execute for running tests.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "runner": {
+                        "type": "string",
+                        "enum": ["cargo", "jest", "vitest", "npm", "pytest"],
+                        "description": "Test framework to use. Detected automatically when omitted."
+                    },
+                    "filter": {
+                        "type": "string",
+                        "description": "Only run tests whose name matches this filter (cargo test name filter, jest/vitest -t pattern, pytest -k expression)."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = self.settings.working_dir();
+        let runner = args.runner.or_else(|| TestRunner::detect(&root)).ok_or_else(|| {
+            FileToolError::InvalidInput(
+                "Could not detect the test framework; pass `runner` explicitly".to_string(),
+            )
+        })?;
+        let filter = args.filter.as_deref().filter(|f| !f.trim().is_empty());
+        let invocation = build_invocation(runner, filter).await;
+
+        let started = Instant::now();
+        let child = Command::new(&invocation.program)
+            .args(&invocation.args)
+            .current_dir(&root)
+            // 失败信息只需要 panic 消息，不需要 backtrace
+            .env("RUST_BACKTRACE", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let output = tokio::time::timeout(TEST_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("tests timed out after {}s", TEST_TIMEOUT.as_secs()),
+                )
+            })??;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let json_summary = match &invocation.report {
+            JsonReport::None => None,
+            JsonReport::Stdout => parse_json_report(runner, &stdout),
+            JsonReport::File(path) => {
+                let report = fs::read_to_string(path).ok();
+                let _ = fs::remove_file(path);
+                report.and_then(|report| parse_json_report(runner, &report))
+            }
+        };
+        let (summary, format) = match json_summary {
+            Some(summary) => (Some(summary), ReportFormat::Json),
+            None => (
+                parse_text_report(runner, &format!("{}\n{}", stdout, stderr)),
+                ReportFormat::Text,
+            ),
+        };
+
+        let exit_ok = output.status.success();
+        let output = match summary {
+            Some(summary) => RunTestsOutput {
+                runner,
+                command: invocation.display(),
+                success: exit_ok && summary.failed == 0,
+                passed: summary.passed,
+                failed: summary.failed,
+                skipped: summary.skipped,
+                failures: summary.failures.into_iter().take(MAX_FAILURES).collect(),
+                duration_ms,
+                format,
+                output_tail: None,
+            },
+            None => RunTestsOutput {
+                runner,
+                command: invocation.display(),
+                success: exit_ok,
+                passed: 0,
+                failed: 0,
+                skipped: 0,
+                failures: Vec::new(),
+                duration_ms,
+                format,
+                output_tail: Some(tail(&format!("{}\n{}", stdout, stderr))),
+            },
+        };
+        Ok(output)
+    }
+}
+
+/// 构造测试命令
+async fn build_invocation(runner: TestRunner, filter: Option<&str>) -> Invocation {
+    let mut invocation = match runner {
+        TestRunner::Cargo => {
+            let mut invocation = Invocation::new("cargo", &["test", "--no-fail-fast"], JsonReport::None);
+            if let Some(filter) = filter {
+                invocation.args.push(filter.to_string());
+            }
+            // libtest 的 JSON 输出仍是不稳定功能，只在 nightly 工具链上使用
+            if rustc_is_nightly().await {
+                invocation.args.extend(
+                    ["--", "-Z", "unstable-options", "--format", "json", "--report-time"]
+                        .map(String::from),
+                );
+                invocation.report = JsonReport::Stdout;
+            }
+            return invocation;
+        }
+        TestRunner::Jest => Invocation::new("npx", &["jest", "--json"], JsonReport::Stdout),
+        TestRunner::Vitest => Invocation::new(
+            "npx",
+            &["vitest", "run", "--reporter=json"],
+            JsonReport::Stdout,
+        ),
+        TestRunner::Npm => {
+            let mut invocation = Invocation::new("npm", &["test"], JsonReport::None);
+            if let Some(filter) = filter {
+                invocation.args.extend(["--".to_string(), filter.to_string()]);
+            }
+            return invocation;
+        }
+        TestRunner::Pytest => {
+            let python = if cfg!(windows) { "python" } else { "python3" };
+            let mut invocation = Invocation::new(python, &["-m", "pytest", "-rfE"], JsonReport::None);
+            if has_pytest_json_report(python).await {
+                let report = std::env::temp_dir()
+                    .join(format!("oxide-pytest-{}.json", uuid::Uuid::new_v4().simple()));
+                invocation.args.extend([
+                    "--json-report".to_string(),
+                    format!("--json-report-file={}", report.display()),
+                ]);
+                invocation.report = JsonReport::File(report);
+            }
+            if let Some(filter) = filter {
+                invocation.args.extend(["-k".to_string(), filter.to_string()]);
+            }
+            return invocation;
+        }
+    };
+    // jest / vitest
+    if let Some(filter) = filter {
+        invocation.args.extend(["-t".to_string(), filter.to_string()]);
+    }
+    invocation
+}
+
+async fn rustc_is_nightly() -> bool {
+    Command::new("rustc")
+        .arg("-V")
+        .output()
+        .await
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains("-nightly"))
+        .unwrap_or(false)
+}
+
+/// 是否安装了 pytest-json-report 插件
+async fn has_pytest_json_report(python: &str) -> bool {
+    Command::new(python)
+        .args(["-c", "import pytest_jsonreport"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn parse_json_report(runner: TestRunner, report: &str) -> Option<TestSummary> {
+    match runner {
+        TestRunner::Cargo => parse_libtest_json(report),
+        TestRunner::Jest | TestRunner::Vitest => parse_jest_json(report),
+        TestRunner::Pytest => parse_pytest_json(report),
+        TestRunner::Npm => None,
+    }
+}
+
+fn parse_text_report(runner: TestRunner, output: &str) -> Option<TestSummary> {
+    match runner {
+        TestRunner::Cargo => parse_libtest_text(output),
+        TestRunner::Pytest => parse_pytest_text(output),
+        // npm test 通常包装 jest，其它框架只能退回到输出末尾
+        TestRunner::Jest | TestRunner::Vitest | TestRunner::Npm => parse_jest_text(output),
+    }
+}
+
+/// 解析 libtest JSON（每行一个事件，可能包含多个测试二进制）
+fn parse_libtest_json(output: &str) -> Option<TestSummary> {
+    let mut summary = TestSummary::default();
+    let mut seen_suite = false;
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        match (event["type"].as_str(), event["event"].as_str()) {
+            (Some("test"), Some("failed")) => summary.failures.push(failure(
+                event["name"].as_str().unwrap_or_default(),
+                event["stdout"].as_str().unwrap_or_default(),
+            )),
+            (Some("suite"), Some("ok" | "failed")) => {
+                seen_suite = true;
+                summary.passed += count(&event["passed"]);
+                summary.failed += count(&event["failed"]);
+                summary.skipped += count(&event["ignored"]);
+            }
+            _ => {}
+        }
+    }
+    seen_suite.then_some(summary)
+}
+
+/// 解析 libtest 文本输出
+fn parse_libtest_text(output: &str) -> Option<TestSummary> {
+    static RESULT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored").unwrap()
+    });
+    static FAILED: Lazy<Regex> = Lazy::new(|| Regex::new(r"^test (\S+) \.\.\. FAILED").unwrap());
+    static BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"^---- (\S+) stdout ----$").unwrap());
+
+    let mut summary = TestSummary::default();
+    let mut seen_result = false;
+    let mut failed_names = Vec::new();
+    let mut messages: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = RESULT.captures(line) {
+            seen_result = true;
+            summary.passed += caps[1].parse::<usize>().unwrap_or(0);
+            summary.failed += caps[2].parse::<usize>().unwrap_or(0);
+            summary.skipped += caps[3].parse::<usize>().unwrap_or(0);
+        }
+        if let Some(caps) = FAILED.captures(line) {
+            failed_names.push(caps[1].to_string());
+        }
+        if let Some(caps) = BLOCK.captures(line) {
+            current = Some(caps[1].to_string());
+            continue;
+        }
+        if line == "failures:" {
+            current = None;
+        }
+        if let Some(name) = &current {
+            let message = messages.entry(name.clone()).or_default();
+            message.push_str(line);
+            message.push('\n');
+        }
+    }
+
+    summary.failures = failed_names
+        .into_iter()
+        .map(|name| {
+            let message = messages.remove(&name).unwrap_or_default();
+            failure(&name, &message)
+        })
+        .collect();
+    seen_result.then_some(summary)
+}
+
+/// 解析 jest / vitest 的 JSON 报告
+fn parse_jest_json(output: &str) -> Option<TestSummary> {
+    // 报告前可能混有其它输出
+    let start = output.find('{')?;
+    let report: Value = serde_json::from_str(output[start..].trim()).ok()?;
+    report.get("numTotalTests")?;
+
+    let mut summary = TestSummary {
+        passed: count(&report["numPassedTests"]),
+        failed: count(&report["numFailedTests"]),
+        skipped: count(&report["numPendingTests"]) + count(&report["numTodoTests"]),
+        failures: Vec::new(),
+    };
+    for suite in report["testResults"].as_array().into_iter().flatten() {
+        let assertions = suite["assertionResults"].as_array();
+        for test in assertions.into_iter().flatten() {
+            if test["status"] == "failed" {
+                let messages: Vec<&str> = test["failureMessages"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                summary.failures.push(failure(
+                    test["fullName"].as_str().unwrap_or_default(),
+                    &messages.join("\n"),
+                ));
+            }
+        }
+        // 整个测试文件无法运行（语法错误、导入失败）时没有单条用例结果
+        let message = suite["message"].as_str().unwrap_or_default();
+        if suite["status"] == "failed" && assertions.is_none_or(|a| a.is_empty()) && !message.is_empty() {
+            summary.failures.push(failure(suite["name"].as_str().unwrap_or_default(), message));
+        }
+    }
+    Some(summary)
+}
+
+/// 解析 jest 文本输出
+fn parse_jest_text(output: &str) -> Option<TestSummary> {
+    static TESTS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Tests:\s+(.*)$").unwrap());
+
+    let mut summary = TestSummary::default();
+    let mut seen_summary = false;
+    let mut current: Option<(String, String)> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = TESTS.captures(line) {
+            seen_summary = true;
+            for (n, status) in counts_by_status(&caps[1]) {
+                match status {
+                    "passed" => summary.passed += n,
+                    "failed" => summary.failed += n,
+                    "skipped" | "todo" | "pending" => summary.skipped += n,
+                    _ => {}
+                }
+            }
+        }
+        if let Some(name) = line.strip_prefix("  ● ") {
+            if let Some((name, message)) = current.take() {
+                summary.failures.push(failure(&name, &message));
+            }
+            current = Some((name.trim().to_string(), String::new()));
+            continue;
+        }
+        if line.starts_with(" PASS ") || line.starts_with(" FAIL ") || line.starts_with("Test Suites:") {
+            if let Some((name, message)) = current.take() {
+                summary.failures.push(failure(&name, &message));
+            }
+        }
+        if let Some((_, message)) = &mut current {
+            message.push_str(line.trim());
+            message.push('\n');
+        }
+    }
+    if let Some((name, message)) = current {
+        summary.failures.push(failure(&name, &message));
+    }
+    seen_summary.then_some(summary)
+}
+
+/// 解析 pytest-json-report 报告
+fn parse_pytest_json(report: &str) -> Option<TestSummary> {
+    let report: Value = serde_json::from_str(report).ok()?;
+    let totals = report.get("summary")?;
+
+    let mut summary = TestSummary {
+        passed: count(&totals["passed"]),
+        failed: count(&totals["failed"]) + count(&totals["error"]),
+        skipped: count(&totals["skipped"]),
+        failures: Vec::new(),
+    };
+    for test in report["tests"].as_array().into_iter().flatten() {
+        if test["outcome"] != "failed" && test["outcome"] != "error" {
+            continue;
+        }
+        let message = ["call", "setup", "teardown"]
+            .iter()
+            .find_map(|stage| {
+                let stage = &test[*stage];
+                stage["longrepr"].as_str().or_else(|| stage["crash"]["message"].as_str())
+            })
+            .unwrap_or_default();
+        summary
+            .failures
+            .push(failure(test["nodeid"].as_str().unwrap_or_default(), message));
+    }
+    Some(summary)
+}
+
+/// 解析 pytest 文本输出（需要 `-rfE` 输出简短失败摘要）
+fn parse_pytest_text(output: &str) -> Option<TestSummary> {
+    static FINAL: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^=+ (.+) in [\d.]+s.*=+$").unwrap());
+    static SHORT: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(?:FAILED|ERROR) (\S+)(?: - (.*))?$").unwrap());
+    static SECTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^_{3,} (.+?) _{3,}$").unwrap());
+
+    let mut summary = TestSummary::default();
+    let mut seen_summary = false;
+    let mut sections: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = FINAL.captures(line) {
+            seen_summary = true;
+            for (n, status) in counts_by_status(&caps[1]) {
+                match status {
+                    "passed" => summary.passed += n,
+                    "failed" | "error" | "errors" => summary.failed += n,
+                    "skipped" => summary.skipped += n,
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        if let Some(caps) = SECTION.captures(line) {
+            current = Some(caps[1].to_string());
+            continue;
+        }
+        if line.starts_with("====") {
+            current = None;
+        }
+        if let Some(caps) = SHORT.captures(line) {
+            let node_id = caps[1].to_string();
+            // 失败详情的标题是用例名（`::` 之后的部分）
+            let title = node_id.rsplit("::").next().unwrap_or(&node_id);
+            let message = sections
+                .remove(title)
+                .unwrap_or_else(|| caps.get(2).map_or("", |m| m.as_str()).to_string());
+            summary.failures.push(failure(&node_id, &message));
+            continue;
+        }
+        if let Some(title) = &current {
+            let section = sections.entry(title.clone()).or_default();
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    seen_summary.then_some(summary)
+}
+
+/// 解析 "2 failed, 3 passed, 1 skipped" 形式的统计
+fn counts_by_status(text: &str) -> Vec<(usize, &str)> {
+    text.split(',')
+        .filter_map(|part| {
+            let mut words = part.split_whitespace();
+            let n = words.next()?.parse().ok()?;
+            Some((n, words.next()?))
+        })
+        .collect()
+}
+
+fn count(value: &Value) -> usize {
+    value.as_u64().unwrap_or(0) as usize
+}
+
+/// 构造失败记录：去掉空行和 backtrace 提示，超长时截断
+fn failure(name: &str, message: &str) -> TestFailure {
+    let message = message
+        .lines()
+        .filter(|line| !line.starts_with("note: run with `RUST_BACKTRACE=1`"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = message.trim();
+    let message = match message.char_indices().nth(MAX_MESSAGE_CHARS) {
+        Some((index, _)) => format!("{}…", &message[..index]),
+        None => message.to_string(),
+    };
+    TestFailure {
+        name: name.to_string(),
+        message,
+    }
+}
+
+/// 输出最后若干行
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WrappedRunTestsTool {
+    inner: RunTestsTool,
+}
+
+impl WrappedRunTestsTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: RunTestsTool::new(settings),
+        }
+    }
+}
+
+impl Tool for WrappedRunTestsTool {
+    const NAME: &'static str = "run_tests";
+
+    type Error = FileToolError;
+    type Args = <RunTestsTool as Tool>::Args;
+    type Output = <RunTestsTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        let target = match (&args.runner, &args.filter) {
+            (Some(runner), Some(filter)) => format!("{} {}", runner.name(), filter),
+            (Some(runner), None) => runner.name().to_string(),
+            (None, Some(filter)) => filter.clone(),
+            (None, None) => String::new(),
+        };
+        tool_println!("{} Test({})", "●".bright_green(), target);
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) if output.output_tail.is_some() => {
+                tool_println!(
+                    "  └─ {}",
+                    format!("Could not parse test results ({})", output.command).red()
+                );
+            }
+            Ok(output) => {
+                let counts = format!(
+                    "{} passed, {} failed, {} skipped in {:.1}s",
+                    output.passed,
+                    output.failed,
+                    output.skipped,
+                    output.duration_ms as f64 / 1000.0
+                );
+                if output.success {
+                    tool_println!("  └─ {}", counts.dimmed());
+                } else {
+                    tool_println!("  └─ {}", counts.red());
+                    for failure in output.failures.iter().take(5) {
+                        tool_println!("     {} {}", "✗".red(), failure.name);
+                    }
+                }
+            }
+            Err(e) => {
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            }
+        }
+        tool_println!();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CARGO_JSON: &str = include_str!("../../tests/fixtures/test_runners/cargo.jsonl");
+    const CARGO_TEXT: &str = include_str!("../../tests/fixtures/test_runners/cargo.txt");
+    const JEST_JSON: &str = include_str!("../../tests/fixtures/test_runners/jest.json");
+    const JEST_TEXT: &str = include_str!("../../tests/fixtures/test_runners/jest.txt");
+    const VITEST_JSON: &str = include_str!("../../tests/fixtures/test_runners/vitest.json");
+    const PYTEST_JSON: &str = include_str!("../../tests/fixtures/test_runners/pytest.json");
+    const PYTEST_TEXT: &str = include_str!("../../tests/fixtures/test_runners/pytest.txt");
+
+    fn names(summary: &TestSummary) -> Vec<&str> {
+        summary.failures.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_cargo() {
+        for summary in [parse_libtest_json(CARGO_JSON).unwrap(), parse_libtest_text(CARGO_TEXT).unwrap()] {
+            assert_eq!((summary.passed, summary.failed, summary.skipped), (1, 2, 1));
+            assert_eq!(names(&summary), vec!["tests::adds_negative", "tests::panics"]);
+            assert_eq!(
+                summary.failures[0].message,
+                "thread 'tests::adds_negative' panicked at src/lib.rs:9:26:\nassertion `left == right` failed\n  left: -3\n right: -4"
+            );
+            assert!(summary.failures[1].message.ends_with("boom"));
+        }
+    }
+
+    #[test]
+    fn test_parse_jest() {
+        let summary = parse_jest_json(JEST_JSON).unwrap();
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (3, 1, 1));
+        assert_eq!(names(&summary), vec!["add handles negatives"]);
+        assert!(summary.failures[0].message.contains("Expected: -4"));
+
+        let summary = parse_jest_text(JEST_TEXT).unwrap();
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (3, 1, 1));
+        assert_eq!(names(&summary), vec!["add › handles negatives"]);
+        assert!(summary.failures[0].message.contains("Received: -3"));
+    }
+
+    #[test]
+    fn test_parse_vitest() {
+        let summary = parse_jest_json(VITEST_JSON).unwrap();
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 1, 0));
+        assert_eq!(
+            names(&summary),
+            vec!["Button calls onClick", "/work/web/src/Broken.test.ts"]
+        );
+        assert!(summary.failures[1].message.starts_with("Failed to load url"));
+    }
+
+    #[test]
+    fn test_parse_pytest() {
+        for summary in [parse_pytest_json(PYTEST_JSON).unwrap(), parse_pytest_text(PYTEST_TEXT).unwrap()] {
+            assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 2, 1));
+            assert_eq!(
+                names(&summary),
+                vec!["tests/test_math.py::test_add_negative", "tests/test_format.py::test_padding"]
+            );
+            assert!(summary.failures[0].message.contains("assert -3 == -4"));
+            assert!(summary.failures[1].message.contains("'07' == '007'"));
+        }
+    }
+
+    #[test]
+    fn test_unparseable_output() {
+        let compile_error = "error[E0425]: cannot find value `x` in this scope\nerror: could not compile `calc`";
+        assert!(parse_libtest_text(compile_error).is_none());
+        assert!(parse_libtest_json(compile_error).is_none());
+        assert!(parse_jest_json("not json").is_none());
+        assert!(parse_pytest_text("").is_none());
+    }
+
+    #[test]
+    fn test_failure_message_truncated() {
+        let failure = failure("big", &"x".repeat(MAX_MESSAGE_CHARS * 2));
+        assert_eq!(failure.message.chars().count(), MAX_MESSAGE_CHARS + 1);
+        assert!(failure.message.ends_with('…'));
+    }
+
+    #[test]
+    fn test_detect_runner() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(TestRunner::detect(temp_dir.path()), None);
+
+        fs::write(temp_dir.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(TestRunner::detect(temp_dir.path()), Some(TestRunner::Pytest));
+
+        fs::write(
+            temp_dir.path().join("package.json"),
+            r#"{"devDependencies": {"vitest": "^2.0.0"}}"#,
+        )
+        .unwrap();
+        assert_eq!(TestRunner::detect(temp_dir.path()), Some(TestRunner::Vitest));
+
+        fs::write(temp_dir.path().join("package.json"), r#"{"scripts": {"test": "mocha"}}"#).unwrap();
+        assert_eq!(TestRunner::detect(temp_dir.path()), Some(TestRunner::Npm));
+
+        fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(TestRunner::detect(temp_dir.path()), Some(TestRunner::Cargo));
+    }
+}
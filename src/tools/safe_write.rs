@@ -0,0 +1,280 @@
+//! 安全写入
+//!
+//! 文件工具的写入经由 [`write_atomic`]：先写到同目录下的临时文件并 fsync，再原子重命名覆盖目标，
+//! 保留原文件的权限；任何一步失败都不会改动原文件。覆盖前的内容记录到撤销日志，
+//! 可以通过 `/undo` 恢复。日志同时记录本次会话写过的文件，供诊断工具按文件过滤。
+//! 一次改动多个文件时（`apply_patch`）用 `apply_all`：中途失败会恢复已改动的文件。
+
+use once_cell::sync::Lazy;
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_file::write_atomic;
+
+/// 撤销日志保留的最大条目数
+const MAX_UNDO_ENTRIES: usize = 50;
+
+/// 一次写入前的文件状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoEntry {
+    pub path: PathBuf,
+    /// 写入前的内容（文件原本不存在时为 None）
+    pub previous: Option<Vec<u8>>,
+}
+
+/// 一次批量改动中单个文件的目标内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// 写入的内容（None 表示删除文件）
+    pub contents: Option<Vec<u8>>,
+}
+
+/// 撤销日志（最近的在后）
+pub struct UndoJournal {
+    entries: Mutex<VecDeque<UndoEntry>>,
+    /// 写过的文件（绝对路径），撤销和条目淘汰都不会移除
+    touched: Mutex<BTreeSet<PathBuf>>,
+}
+
+/// 文件工具共用的撤销日志
+static UNDO_JOURNAL: Lazy<UndoJournal> = Lazy::new(UndoJournal::new);
+
+impl UndoJournal {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            touched: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// 原子写入文件，成功后记录写入前的内容
+    pub fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let previous = read_previous(path)?;
+
+        write_atomic(path, contents)?;
+
+        self.record(UndoEntry {
+            path: path.to_path_buf(),
+            previous,
+        });
+        Ok(())
+    }
+
+    /// 依次写入或删除多个文件，全部成功后逐个记录到日志
+    ///
+    /// 任何一步失败时按相反顺序恢复已改动的文件，日志保持不变。
+    pub fn apply_all(&self, changes: &[FileChange]) -> io::Result<()> {
+        let mut applied: Vec<UndoEntry> = Vec::with_capacity(changes.len());
+        for change in changes {
+            let result = read_previous(&change.path).and_then(|previous| {
+                match &change.contents {
+                    Some(contents) => write_atomic(&change.path, contents)?,
+                    None => fs::remove_file(&change.path)?,
+                }
+                Ok(previous)
+            });
+            match result {
+                Ok(previous) => applied.push(UndoEntry {
+                    path: change.path.clone(),
+                    previous,
+                }),
+                Err(e) => {
+                    for entry in applied.iter().rev() {
+                        let _ = restore(entry);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        for entry in applied {
+            self.record(entry);
+        }
+        Ok(())
+    }
+
+    fn record(&self, entry: UndoEntry) {
+        if let Ok(mut touched) = self.touched.lock() {
+            touched.insert(std::path::absolute(&entry.path).unwrap_or_else(|_| entry.path.clone()));
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= MAX_UNDO_ENTRIES {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    /// 撤销最近一次写入：恢复原内容，原本不存在的文件会被删除
+    ///
+    /// 没有可撤销的写入时返回 None。恢复失败时条目保留在日志中。
+    pub fn undo_last(&self) -> io::Result<Option<UndoEntry>> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| io::Error::other("undo journal poisoned"))?;
+        let Some(entry) = entries.back().cloned() else {
+            return Ok(None);
+        };
+
+        restore(&entry)?;
+
+        entries.pop_back();
+        Ok(Some(entry))
+    }
+
+    /// 通过日志写过的文件（绝对路径，按路径排序）
+    pub fn touched_files(&self) -> Vec<PathBuf> {
+        self.touched
+            .lock()
+            .map(|touched| touched.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// 读取写入前的内容（文件不存在时为 None）
+fn read_previous(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(previous) => Ok(Some(previous)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 把文件恢复到条目记录的状态：恢复原内容，原本不存在的文件会被删除
+fn restore(entry: &UndoEntry) -> io::Result<()> {
+    match &entry.previous {
+        Some(previous) => write_atomic(&entry.path, previous),
+        None => match fs::remove_file(&entry.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+impl Default for UndoJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 原子写入文件，成功后把写入前的内容记录到全局撤销日志
+pub fn write_with_backup(path: &Path, contents: &[u8]) -> io::Result<()> {
+    UNDO_JOURNAL.write(path, contents)
+}
+
+/// 批量改动多个文件，成功后逐个记录到全局撤销日志；失败时恢复已改动的文件
+pub fn apply_all_with_backup(changes: &[FileChange]) -> io::Result<()> {
+    UNDO_JOURNAL.apply_all(changes)
+}
+
+/// 撤销全局日志中最近一次写入
+pub fn undo_last() -> io::Result<Option<UndoEntry>> {
+    UNDO_JOURNAL.undo_last()
+}
+
+/// 本次会话中文件工具写过的文件
+pub fn touched_files() -> Vec<PathBuf> {
+    UNDO_JOURNAL.touched_files()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_write_leaves_original_untouched() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("locked");
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("data.bin");
+        let original = b"line 1\r\nline 2\x00\xff".to_vec();
+        fs::write(&path, &original).unwrap();
+
+        // 目录只读：无法创建临时文件
+        let journal = UndoJournal::new();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        let result = journal.write(&path, b"replacement");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // root 不受目录权限限制，此时无法模拟失败
+        if result.is_ok() {
+            return;
+        }
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(journal.undo_last().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_restores_previous_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let existing = temp_dir.path().join("existing.txt");
+        let created = temp_dir.path().join("created.txt");
+        fs::write(&existing, "before").unwrap();
+
+        let journal = UndoJournal::new();
+        journal.write(&existing, b"after").unwrap();
+        journal.write(&created, b"new file").unwrap();
+
+        assert_eq!(journal.undo_last().unwrap().unwrap().path, created);
+        assert!(!created.exists());
+        assert_eq!(journal.undo_last().unwrap().unwrap().path, existing);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "before");
+        assert!(journal.undo_last().unwrap().is_none());
+
+        // 撤销后仍算本次会话改动过的文件
+        assert_eq!(journal.touched_files(), vec![created, existing]);
+    }
+
+    #[test]
+    fn test_apply_all_rolls_back_on_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let modified = temp_dir.path().join("modified.txt");
+        let deleted = temp_dir.path().join("deleted.txt");
+        let created = temp_dir.path().join("created.txt");
+        fs::write(&modified, "before").unwrap();
+        fs::write(&deleted, "doomed").unwrap();
+        // 目标是非空目录：写入时重命名失败
+        let blocked = temp_dir.path().join("blocked");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep.txt"), "keep").unwrap();
+
+        let change = |path: &PathBuf, contents: Option<&str>| FileChange {
+            path: path.clone(),
+            contents: contents.map(|c| c.as_bytes().to_vec()),
+        };
+        let journal = UndoJournal::new();
+        let result = journal.apply_all(&[
+            change(&modified, Some("after")),
+            change(&deleted, None),
+            change(&created, Some("new")),
+            change(&blocked, Some("replacement")),
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&modified).unwrap(), "before");
+        assert_eq!(fs::read_to_string(&deleted).unwrap(), "doomed");
+        assert!(!created.exists());
+        assert!(journal.undo_last().unwrap().is_none());
+
+        journal
+            .apply_all(&[change(&modified, Some("after")), change(&deleted, None)])
+            .unwrap();
+        assert!(!deleted.exists());
+        // 每个文件单独记录，/undo 逐个恢复
+        assert_eq!(journal.undo_last().unwrap().unwrap().path, deleted);
+        assert_eq!(fs::read_to_string(&deleted).unwrap(), "doomed");
+        assert_eq!(journal.undo_last().unwrap().unwrap().path, modified);
+        assert_eq!(fs::read_to_string(&modified).unwrap(), "before");
+    }
+}
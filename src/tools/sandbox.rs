@@ -0,0 +1,391 @@
+//! shell_execute 的系统沙箱（Linux）
+//!
+//! 权限确认之外的另一道防线。`[tools.bash.sandbox]` 开启后，命令在子进程 exec 之前
+//! 按配置进入新的网络命名空间断开网络，用 setrlimit 限制 CPU 时间和内存，
+//! 再用 Landlock 把文件写入限制在工作区根目录和 `~/.oxide/`（`read-only` 模式下不能写任何文件）。
+//! 平台或内核不支持时不加对应的限制，照常运行命令，并在 `/status` 中说明。
+
+use crate::config::{ConfigLoader, SandboxConfig, SandboxMode};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 任何模式下都允许写入的设备文件（重定向到 /dev/null 等）
+const WRITABLE_DEVICES: &[&str] = &["/dev/null", "/dev/tty"];
+
+/// shell_execute 命令的沙箱设置
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    /// 允许写入的路径（不含 [`WRITABLE_DEVICES`]）
+    writable: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig, workspace: &Path) -> Self {
+        let mut writable = Vec::new();
+        if config.mode == SandboxMode::WorkspaceWrite {
+            writable.push(workspace.to_path_buf());
+            if let Some(home) = dirs::home_dir() {
+                writable.push(home.join(".oxide"));
+            }
+        }
+        Self { config, writable }
+    }
+
+    /// 按 `[tools.bash.sandbox]` 创建，工作目录为当前目录
+    pub fn from_config() -> Self {
+        let config = ConfigLoader::new()
+            .load_toml_layers()
+            .ok()
+            .and_then(|config| config.tools)
+            .and_then(|tools| tools.bash)
+            .and_then(|bash| bash.sandbox)
+            .unwrap_or_default();
+        let workspace = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self::new(config, &workspace)
+    }
+
+    /// 额外允许写入的工作区根目录（`--add-dir` / `/add-dir`，只在 `workspace-write` 模式下生效）
+    pub fn with_extra_roots(mut self, roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        if self.config.mode == SandboxMode::WorkspaceWrite {
+            self.writable.extend(roots);
+        }
+        self
+    }
+
+    pub fn mode(&self) -> SandboxMode {
+        self.config.mode
+    }
+
+    /// 当前系统能否用 Landlock 限制文件写入
+    pub fn filesystem_supported() -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            linux::support().landlock
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
+
+    /// 当前系统能否为命令创建独立的网络命名空间
+    pub fn network_supported() -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            linux::support().network
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
+
+    /// 让 `cmd` 启动的子进程在沙箱中运行（关闭时不做任何事）
+    pub fn apply(&self, cmd: &mut Command) -> io::Result<()> {
+        if self.config.mode == SandboxMode::Off {
+            return Ok(());
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let support = linux::support();
+            let ruleset = if support.landlock {
+                let writable = self
+                    .writable
+                    .iter()
+                    .map(PathBuf::as_path)
+                    .chain(WRITABLE_DEVICES.iter().map(Path::new));
+                Some(linux::ruleset(writable).map_err(io::Error::other)?)
+            } else {
+                None
+            };
+            let network = (!self.config.network && support.network).then(linux::IdMapping::current);
+            let limits = linux::Limits {
+                cpu_seconds: self.config.cpu_seconds,
+                memory_bytes: self.config.memory_mb.saturating_mul(1024 * 1024),
+            };
+            linux::install(cmd, ruleset, network, limits);
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = cmd;
+        Ok(())
+    }
+
+    /// `/status` 中显示的沙箱设置
+    pub fn describe(&self) -> String {
+        let mode = self.config.mode.as_str();
+        if self.config.mode == SandboxMode::Off {
+            return mode.to_string();
+        }
+        let mut details = Vec::new();
+        if !self.config.network {
+            details.push(t!("tools.sandbox.network_off").to_string());
+        }
+        if self.config.cpu_seconds > 0 {
+            details.push(t!("tools.sandbox.cpu", seconds = self.config.cpu_seconds));
+        }
+        if self.config.memory_mb > 0 {
+            details.push(t!("tools.sandbox.memory", mb = self.config.memory_mb));
+        }
+        if details.is_empty() {
+            mode.to_string()
+        } else {
+            format!("{} ({})", mode, details.join(", "))
+        }
+    }
+
+    /// 平台或内核不支持、退回为不加限制时的说明
+    pub fn fallback_notes(&self) -> Vec<&'static str> {
+        let mut notes = Vec::new();
+        if self.config.mode == SandboxMode::Off {
+            return notes;
+        }
+        if !cfg!(target_os = "linux") {
+            notes.push(t!("tools.sandbox.unsupported_platform"));
+            return notes;
+        }
+        if !Self::filesystem_supported() {
+            notes.push(t!("tools.sandbox.no_landlock"));
+        }
+        if !self.config.network && !Self::network_supported() {
+            notes.push(t!("tools.sandbox.no_network_namespace"));
+        }
+        notes
+    }
+
+    /// 告诉模型命令受到的限制（关闭时为 `None`）
+    pub fn tool_hint(&self) -> Option<&'static str> {
+        match self.config.mode {
+            SandboxMode::Off => None,
+            SandboxMode::WorkspaceWrite => Some(
+                "Commands run in a sandbox: writes outside the working directory fail with permission errors.",
+            ),
+            SandboxMode::ReadOnly => {
+                Some("Commands run in a read-only sandbox: any file write fails with permission errors.")
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreated,
+        RulesetCreatedAttr, RulesetError, ABI,
+    };
+    use once_cell::sync::Lazy;
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    /// 使用的 Landlock ABI，旧内核上由 landlock crate 自动降级
+    const LANDLOCK_ABI: ABI = ABI::V3;
+
+    /// `landlock_create_ruleset` 查询 ABI 版本的标志
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+
+    const SETGROUPS: &[u8] = b"/proc/self/setgroups\0";
+    const UID_MAP: &[u8] = b"/proc/self/uid_map\0";
+    const GID_MAP: &[u8] = b"/proc/self/gid_map\0";
+
+    /// 当前系统对沙箱的支持情况
+    #[derive(Debug, Clone, Copy)]
+    pub struct Support {
+        pub landlock: bool,
+        pub network: bool,
+    }
+
+    static SUPPORT: Lazy<Support> = Lazy::new(|| Support {
+        landlock: landlock_abi().is_some(),
+        network: network_namespace_available(),
+    });
+
+    /// 首次调用时检测，之后复用结果
+    pub fn support() -> Support {
+        *SUPPORT
+    }
+
+    fn landlock_abi() -> Option<i64> {
+        let version = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<libc::c_void>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        (version > 0).then_some(version)
+    }
+
+    /// 在一个空命令的子进程中尝试进入新的网络命名空间
+    fn network_namespace_available() -> bool {
+        let mapping = IdMapping::current();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", ":"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        unsafe {
+            cmd.pre_exec(move || enter_network_namespace(&mapping));
+        }
+        cmd.status().map(|status| status.success()).unwrap_or(false)
+    }
+
+    /// 只读访问整个文件系统，完全访问 `writable` 中的路径（不存在的路径会被跳过）
+    pub fn ruleset<'a>(
+        writable: impl IntoIterator<Item = &'a Path>,
+    ) -> Result<RulesetCreated, RulesetError> {
+        Ruleset::default()
+            .handle_access(AccessFs::from_all(LANDLOCK_ABI))?
+            .create()?
+            .add_rules(path_beneath_rules(["/"], AccessFs::from_read(LANDLOCK_ABI)))?
+            .add_rules(path_beneath_rules(
+                writable,
+                AccessFs::from_all(LANDLOCK_ABI),
+            ))
+    }
+
+    /// 进入用户命名空间后保持原来的 uid/gid
+    ///
+    /// 映射内容在 fork 之前准备好，子进程里只做系统调用。
+    pub struct IdMapping {
+        uid_map: String,
+        gid_map: String,
+    }
+
+    impl IdMapping {
+        pub fn current() -> Self {
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Self {
+                uid_map: format!("{} {} 1\n", uid, uid),
+                gid_map: format!("{} {} 1\n", gid, gid),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Limits {
+        pub cpu_seconds: u64,
+        pub memory_bytes: u64,
+    }
+
+    impl Limits {
+        fn apply(&self) -> io::Result<()> {
+            if self.cpu_seconds > 0 {
+                let limit = rlimit(self.cpu_seconds);
+                if unsafe { libc::setrlimit(libc::RLIMIT_CPU, &limit) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if self.memory_bytes > 0 {
+                let limit = rlimit(self.memory_bytes);
+                if unsafe { libc::setrlimit(libc::RLIMIT_AS, &limit) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn rlimit(value: u64) -> libc::rlimit {
+        libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        }
+    }
+
+    /// 在子进程 exec 之前依次断开网络、设置资源上限、启用 Landlock
+    ///
+    /// fork 之后只能做系统调用，出错时返回不需要分配内存的错误。
+    pub fn install(
+        cmd: &mut Command,
+        mut ruleset: Option<RulesetCreated>,
+        network: Option<IdMapping>,
+        limits: Limits,
+    ) {
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(mapping) = &network {
+                    enter_network_namespace(mapping)?;
+                }
+                limits.apply()?;
+                if let Some(ruleset) = ruleset.take() {
+                    ruleset
+                        .restrict_self()
+                        .map_err(|_| io::Error::from_raw_os_error(libc::EPERM))?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    fn enter_network_namespace(mapping: &IdMapping) -> io::Result<()> {
+        if unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0 {
+            return Ok(());
+        }
+        // 普通用户需要同时创建用户命名空间，并把自己映射为原来的 uid/gid
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        write_proc(SETGROUPS, b"deny")?;
+        write_proc(UID_MAP, mapping.uid_map.as_bytes())?;
+        write_proc(GID_MAP, mapping.gid_map.as_bytes())
+    }
+
+    fn write_proc(path: &[u8], data: &[u8]) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr().cast(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = unsafe { libc::write(fd, data.as_ptr().cast(), data.len()) };
+        let error = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        if written == data.len() as isize {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_and_writable_paths() {
+        crate::i18n::init(crate::i18n::Locale::En);
+        let workspace = Path::new("/work/project");
+        let off = Sandbox::new(SandboxConfig::default(), workspace);
+        assert_eq!(off.describe(), "off");
+        assert!(off.fallback_notes().is_empty());
+        assert!(off.tool_hint().is_none());
+
+        let config = SandboxConfig {
+            mode: SandboxMode::WorkspaceWrite,
+            network: false,
+            ..SandboxConfig::default()
+        };
+        let sandbox = Sandbox::new(config, workspace);
+        assert_eq!(
+            sandbox.describe(),
+            "workspace-write (network disabled, CPU 300s, memory 4096 MB)"
+        );
+        assert_eq!(sandbox.writable[0], workspace);
+        let sandbox = sandbox.with_extra_roots([PathBuf::from("/work/api")]);
+        assert_eq!(sandbox.writable.last().unwrap(), Path::new("/work/api"));
+
+        let config = SandboxConfig {
+            mode: SandboxMode::ReadOnly,
+            cpu_seconds: 0,
+            memory_mb: 0,
+            ..SandboxConfig::default()
+        };
+        let sandbox = Sandbox::new(config, workspace);
+        assert_eq!(sandbox.describe(), "read-only");
+        let sandbox = sandbox.with_extra_roots([PathBuf::from("/work/api")]);
+        assert!(sandbox.writable.is_empty());
+    }
+}
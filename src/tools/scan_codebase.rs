@@ -173,24 +173,24 @@ impl Tool for WrappedScanCodebaseTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Scan", args.root_path);
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "Scan", args.root_path);
 
         let result = self.inner.call(args).await;
 
         match &result {
             Ok(output) => {
-                println!(
+                tool_println!(
                     "  └─ {} files, {} directories",
                     output.total_files.to_string().dimmed(),
                     output.total_directories.to_string().dimmed()
                 );
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
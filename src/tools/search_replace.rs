@@ -1,7 +1,12 @@
-use super::FileToolError;
+use super::line_endings::{to_lf, LineEndingInfo};
+use super::format_on_write::{format_after_write, print_result};
+use super::safe_write::write_with_backup;
+use super::settings::ToolSettings;
+use super::{with_note, ErrorCode, FileToolError, ToolFailure};
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::fs;
 use std::path::Path;
 
@@ -24,8 +29,18 @@ pub struct SearchReplaceOutput {
     pub replacements_count: usize,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct SearchReplaceTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct SearchReplaceTool {
+    settings: ToolSettings,
+}
+
+impl SearchReplaceTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
 
 impl Tool for SearchReplaceTool {
     const NAME: &'static str = "search_replace";
@@ -55,6 +70,8 @@ When to use:
 - When you want to replace a block of code (function, class, configuration block).
 - When you are unsure about exact line numbers.
 - When edit_file fails due to context mismatch.
+
+Error codes: NotFound (no such file), Stale (search_content is not in the file; read it again), Ambiguous (search_content matches several places), InvalidArgs, PermissionDenied.
 "#.trim().to_string(),
             parameters: serde_json::json!({
                 "type": "object",
@@ -81,7 +98,8 @@ When to use:
         }
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        args.file_path = self.settings.resolve_path(&args.file_path);
         let path = Path::new(&args.file_path);
         
         if !path.exists() {
@@ -91,36 +109,56 @@ When to use:
             return Err(FileToolError::NotAFile(args.file_path.clone()));
         }
 
-        let content = fs::read_to_string(&args.file_path)?;
-        
+        let raw_content = fs::read_to_string(&args.file_path)?;
+
+        // 在 LF 文本上匹配和替换，写回时恢复原有行尾和末尾换行
+        let line_endings = LineEndingInfo::detect(&raw_content);
+        let content = line_endings.normalize(&raw_content);
+        let search_content = to_lf(&args.search_content);
+        let replace_content = to_lf(&args.replace_content);
+
         // Strategy 1: Exact Match
-        let (matches, strategy) = if content.contains(&args.search_content) {
+        let (matches, strategy) = if content.contains(&search_content) {
             (
-                content.match_indices(&args.search_content)
+                content.match_indices(&search_content)
                     .map(|(start, matched_str)| (start, start + matched_str.len()))
                     .collect::<Vec<(usize, usize)>>(),
                 "exact"
             )
         } else {
             // Strategy 2: Robust Match (Ignore indentation/line variations)
-            match find_robust_matches(&content, &args.search_content) {
+            match find_robust_matches(&content, &search_content) {
                 Some(indices) if !indices.is_empty() => (indices, "robust"),
                 _ => (Vec::new(), "none")
             }
         };
 
         if matches.is_empty() {
-            return Err(FileToolError::InvalidInput(format!(
-                "Could not find the search content in '{}'. Tried exact match and robust match (ignoring indentation).", 
-                args.file_path
-            )));
+            return Err(ToolFailure::new(
+                ErrorCode::Stale,
+                format!(
+                    "Could not find the search content in '{}'. Tried exact match and robust match (ignoring indentation).",
+                    args.file_path
+                ),
+            )
+            .with_suggestion("call read_file again and copy search_content exactly from the current file")
+            .into());
         }
 
         if matches.len() > 1 && !args.allow_multiple {
-            return Err(FileToolError::InvalidInput(format!(
-                "Found {} occurrences of the search content in '{}'. Please provide more context to make the search unique, or set allow_multiple=true (careful!).", 
-                matches.len(), args.file_path
-            )));
+            return Err(ToolFailure::new(
+                ErrorCode::Ambiguous,
+                format!(
+                    "Found {} occurrences of the search content in '{}'.",
+                    matches.len(),
+                    args.file_path
+                ),
+            )
+            .with_details(json!({ "occurrences": matches.len(), "strategy": strategy }))
+            .with_suggestion(
+                "add surrounding lines to make search_content unique, or set allow_multiple=true to replace every occurrence",
+            )
+            .into());
         }
 
         // Apply replacements
@@ -131,15 +169,20 @@ When to use:
 
         let mut new_content = content.clone();
         for (start, end) in sorted_ranges {
-            new_content.replace_range(start..end, &args.replace_content);
+            new_content.replace_range(start..end, &replace_content);
         }
 
-        fs::write(&args.file_path, &new_content)?;
+        write_with_backup(path, line_endings.restore(&new_content).as_bytes())?;
+
+        let message = with_note(
+            format!("Successfully replaced {} occurrence(s) using {} matching.", replacements_count, strategy),
+            line_endings.mixed_note(),
+        );
 
         Ok(SearchReplaceOutput {
             file_path: args.file_path,
             success: true,
-            message: format!("Successfully replaced {} occurrence(s) using {} matching.", replacements_count, strategy),
+            message,
             replacements_count,
         })
     }
@@ -228,8 +271,10 @@ pub struct WrappedSearchReplaceTool {
 }
 
 impl WrappedSearchReplaceTool {
-    pub fn new() -> Self {
-        Self { inner: SearchReplaceTool }
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            inner: SearchReplaceTool::new(settings),
+        }
     }
 }
 
@@ -245,24 +290,87 @@ impl Tool for WrappedSearchReplaceTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "SearchReplace", args.file_path);
-        
-        let result = self.inner.call(args).await;
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "SearchReplace", args.file_path);
         
+        let mut result = self.inner.call(args).await;
+        let format = match &mut result {
+            Ok(output) => {
+                let format = format_after_write(Path::new(&output.file_path)).await;
+                let note = format.as_ref().and_then(|f| f.note());
+                output.message = with_note(std::mem::take(&mut output.message), note);
+                format
+            }
+            Err(_) => None,
+        };
+
         match &result {
             Ok(output) => {
-                 println!(
+                 tool_println!(
                     "  └─ {} (replaced {} block(s))",
                     "Success".green(),
                     output.replacements_count
                 );
+                if let Some(format) = &format {
+                    print_result(format);
+                }
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_replace_in_crlf_file() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let test_path = temp_file.path().to_str().unwrap().to_string();
+        fs::write(&test_path, "fn a() {\r\n    1\r\n}\r\n").unwrap();
+
+        SearchReplaceTool::default()
+            .call(SearchReplaceArgs {
+                file_path: test_path.clone(),
+                search_content: "fn a() {\n    1\n}".to_string(),
+                replace_content: "fn a() {\n    2\n}".to_string(),
+                allow_multiple: false,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&test_path).unwrap(), "fn a() {\r\n    2\r\n}\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_failure_codes() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let test_path = temp_file.path().to_str().unwrap().to_string();
+        fs::write(&test_path, "x = 1\nx = 1\n").unwrap();
+        let replace = |search: &str| SearchReplaceArgs {
+            file_path: test_path.clone(),
+            search_content: search.to_string(),
+            replace_content: "y = 2".to_string(),
+            allow_multiple: false,
+        };
+
+        let stale = SearchReplaceTool::default().call(replace("z = 3")).await.unwrap_err();
+        assert_eq!(stale.failure().code, ErrorCode::Stale);
+        assert!(stale.failure().retryable);
+
+        let ambiguous = SearchReplaceTool::default()
+            .call(replace("x = 1"))
+            .await
+            .unwrap_err()
+            .failure();
+        assert_eq!(ambiguous.code, ErrorCode::Ambiguous);
+        assert_eq!(ambiguous.details.unwrap()["occurrences"], 2);
+        assert!(ambiguous.suggestion.unwrap().contains("allow_multiple"));
+    }
+}
@@ -0,0 +1,273 @@
+//! 敏感文件访问控制
+//!
+//! 读取 `.env`、`*.pem`、`~/.aws/credentials` 等文件时，内容会被发送给第三方 API。
+//! 这里维护一个默认的敏感文件模式列表（可通过 `[permissions.sensitive_paths]` 配置），
+//! 供 read_file、grep_search 和 glob 在访问文件前查询。
+
+use glob::{MatchOptions, Pattern};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+
+use crate::config::{ConfigLoader, SensitivePathMode, SensitivePathsConfig};
+
+/// 内置敏感文件模式
+const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.keystore",
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    ".netrc",
+    ".pgpass",
+    "~/.aws/credentials",
+    "~/.ssh/*",
+    "~/.docker/config.json",
+    "~/.kube/config",
+];
+
+/// 内置白名单（模板文件通常不包含真实密钥）
+const DEFAULT_ALLOWED_PATTERNS: &[&str] = &[".env.example", ".env.sample", ".env.template"];
+
+/// 敏感文件检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SensitiveAccess {
+    /// 允许访问
+    Allowed,
+    /// 拒绝访问（附带命中的模式）
+    Denied { pattern: String },
+    /// 需要用户确认（附带命中的模式）
+    NeedsConfirmation { pattern: String },
+}
+
+/// 编译后的路径模式
+#[derive(Debug, Clone)]
+struct PathPattern {
+    raw: String,
+    pattern: Pattern,
+    /// 模式中包含路径分隔符时匹配完整路径，否则只匹配文件名
+    match_full_path: bool,
+}
+
+impl PathPattern {
+    fn new(raw: &str) -> Option<Self> {
+        let expanded = expand_home(raw);
+        let match_full_path = expanded.contains('/');
+        let pattern = Pattern::new(&expanded).ok()?;
+        Some(Self {
+            raw: raw.to_string(),
+            pattern,
+            match_full_path,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+
+        if self.match_full_path {
+            let full = absolute_path(path);
+            self.pattern
+                .matches_with(&full.to_string_lossy().replace('\\', "/"), options)
+        } else {
+            path.file_name()
+                .map(|name| self.pattern.matches_with(&name.to_string_lossy(), options))
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// 敏感文件守卫
+#[derive(Debug, Clone)]
+pub struct SensitivePathGuard {
+    mode: SensitivePathMode,
+    patterns: Vec<PathPattern>,
+    allow: Vec<PathPattern>,
+}
+
+impl Default for SensitivePathGuard {
+    fn default() -> Self {
+        Self::from_config(&SensitivePathsConfig::default())
+    }
+}
+
+impl SensitivePathGuard {
+    /// 根据配置创建守卫
+    pub fn from_config(config: &SensitivePathsConfig) -> Self {
+        let mut raw_patterns: Vec<&str> = Vec::new();
+        if !config.replace_defaults {
+            raw_patterns.extend(DEFAULT_SENSITIVE_PATTERNS);
+        }
+        raw_patterns.extend(config.patterns.iter().map(String::as_str));
+
+        let mut raw_allow: Vec<&str> = DEFAULT_ALLOWED_PATTERNS.to_vec();
+        raw_allow.extend(config.allow.iter().map(String::as_str));
+
+        Self {
+            mode: config.mode,
+            patterns: raw_patterns.into_iter().filter_map(PathPattern::new).collect(),
+            allow: raw_allow.into_iter().filter_map(PathPattern::new).collect(),
+        }
+    }
+
+    /// 访问模式
+    pub fn mode(&self) -> SensitivePathMode {
+        self.mode
+    }
+
+    /// 返回命中的敏感模式（白名单优先）
+    pub fn matching_pattern(&self, path: &Path) -> Option<&str> {
+        if self.allow.iter().any(|p| p.matches(path)) {
+            return None;
+        }
+        self.patterns
+            .iter()
+            .find(|p| p.matches(path))
+            .map(|p| p.raw.as_str())
+    }
+
+    /// 检查路径是否允许访问
+    pub fn check(&self, path: &Path) -> SensitiveAccess {
+        match self.matching_pattern(path) {
+            None => SensitiveAccess::Allowed,
+            Some(pattern) => match self.mode {
+                SensitivePathMode::Deny => SensitiveAccess::Denied {
+                    pattern: pattern.to_string(),
+                },
+                SensitivePathMode::Confirm => SensitiveAccess::NeedsConfirmation {
+                    pattern: pattern.to_string(),
+                },
+            },
+        }
+    }
+
+    /// 是否为敏感文件
+    pub fn is_sensitive(&self, path: &Path) -> bool {
+        self.matching_pattern(path).is_some()
+    }
+}
+
+/// 全局敏感文件守卫（从全局/项目配置加载）
+static SENSITIVE_GUARD: Lazy<SensitivePathGuard> = Lazy::new(|| {
+    ConfigLoader::new()
+        .load_toml_layers()
+        .ok()
+        .and_then(|config| config.permissions)
+        .and_then(|permissions| permissions.sensitive_paths)
+        .map(|config| SensitivePathGuard::from_config(&config))
+        .unwrap_or_default()
+});
+
+/// 获取全局敏感文件守卫
+pub fn get_sensitive_guard() -> &'static SensitivePathGuard {
+    &SENSITIVE_GUARD
+}
+
+/// 展开 `~/` 前缀
+pub(crate) fn expand_home(raw: &str) -> String {
+    if let Some(rest) = raw.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return format!("{}/{}", home.to_string_lossy().replace('\\', "/"), rest);
+        }
+    }
+    raw.to_string()
+}
+
+/// 获取绝对路径（文件不存在时基于当前目录拼接）
+fn absolute_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_patterns_match_common_files() {
+        let guard = SensitivePathGuard::default();
+        assert!(guard.is_sensitive(Path::new(".env")));
+        assert!(guard.is_sensitive(Path::new("config/.env.production")));
+        assert!(guard.is_sensitive(Path::new("certs/server.pem")));
+        assert!(guard.is_sensitive(Path::new("/home/user/.ssh/id_rsa")));
+        assert!(!guard.is_sensitive(Path::new("src/main.rs")));
+        assert!(!guard.is_sensitive(Path::new("Cargo.lock")));
+    }
+
+    #[test]
+    fn test_home_patterns() {
+        let guard = SensitivePathGuard::default();
+        if let Some(home) = dirs::home_dir() {
+            assert!(guard.is_sensitive(&home.join(".aws/credentials")));
+            assert!(guard.is_sensitive(&home.join(".ssh/config")));
+            assert!(!guard.is_sensitive(&home.join(".aws/config")));
+        }
+    }
+
+    #[test]
+    fn test_allow_list_wins() {
+        let guard = SensitivePathGuard::default();
+        assert!(!guard.is_sensitive(Path::new(".env.example")));
+
+        let config = SensitivePathsConfig {
+            allow: vec!["test.pem".to_string()],
+            ..Default::default()
+        };
+        let guard = SensitivePathGuard::from_config(&config);
+        assert!(!guard.is_sensitive(Path::new("fixtures/test.pem")));
+        assert!(guard.is_sensitive(Path::new("fixtures/prod.pem")));
+    }
+
+    #[test]
+    fn test_check_modes() {
+        let guard = SensitivePathGuard::default();
+        assert_eq!(
+            guard.check(Path::new(".env")),
+            SensitiveAccess::Denied {
+                pattern: ".env".to_string()
+            }
+        );
+        assert_eq!(guard.check(Path::new("README.md")), SensitiveAccess::Allowed);
+
+        let config = SensitivePathsConfig {
+            mode: SensitivePathMode::Confirm,
+            ..Default::default()
+        };
+        let guard = SensitivePathGuard::from_config(&config);
+        assert_eq!(
+            guard.check(Path::new("server.key")),
+            SensitiveAccess::NeedsConfirmation {
+                pattern: "*.key".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_custom_patterns_and_replace_defaults() {
+        let config = SensitivePathsConfig {
+            patterns: vec!["*.secret".to_string(), "*/private/*".to_string()],
+            replace_defaults: true,
+            ..Default::default()
+        };
+        let guard = SensitivePathGuard::from_config(&config);
+        assert!(guard.is_sensitive(Path::new("db.secret")));
+        assert!(guard.is_sensitive(Path::new("/srv/app/private/data.txt")));
+        assert!(!guard.is_sensitive(Path::new(".env")));
+    }
+}
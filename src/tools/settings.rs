@@ -0,0 +1,307 @@
+//! 工具默认设置
+//!
+//! 由合并后的 `[tools]` 配置（以及 `[network]`）解析并校验，构建工具集时传给各工具的构造函数，
+//! 取代散落在各工具中的硬编码默认值。
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::network::NetworkPolicy;
+use super::web_cache::{DEFAULT_MAX_MB, DEFAULT_TTL_HOURS};
+use super::workspace::WorkspaceRoots;
+use crate::config::{ConfigLoader, ToolsConfig};
+
+/// grep_search 默认返回的最大匹配数
+pub const DEFAULT_GREP_HEAD_LIMIT: usize = 100;
+
+/// 工具结果放进对话前的默认 token 上限
+pub const DEFAULT_RESULT_MAX_TOKENS: usize = 10_000;
+
+/// 一轮中同一工具调用（相同参数）默认允许的次数
+pub const DEFAULT_MAX_IDENTICAL_CALLS: usize = 3;
+
+/// 解析后的工具设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSettings {
+    /// 相对路径的基准目录，也是命令的工作目录（`[tools.root] path`）
+    pub root: Option<PathBuf>,
+    /// shell_execute 使用的 shell（`[tools.bash] shell`，其次 `[tools] shell`）
+    pub shell: Option<String>,
+    /// shell_execute 的命令超时（`[tools.bash] default_timeout_ms`）
+    pub bash_timeout: Option<Duration>,
+    /// grep_search 默认返回的最大匹配数（`[tools.grep] head_limit`）
+    pub grep_head_limit: usize,
+    /// grep_search 是否搜索隐藏文件（`[tools.grep] hidden`）
+    pub grep_hidden: bool,
+    /// read_file 返回的最大行数（`[tools.read] max_lines`）
+    pub read_max_lines: Option<usize>,
+    /// 工具结果超过该 token 数时只把摘要放进对话，0 表示不限制（`[tools] result_max_tokens`）
+    pub result_max_tokens: usize,
+    /// 一轮中同一调用超过该次数时不再执行，0 表示不限制（`[tools] max_identical_calls`）
+    pub max_identical_calls: usize,
+    /// 联网工具缓存条目的有效期，为 0 时不使用缓存（`[tools.web] cache_ttl_hours`）
+    pub web_cache_ttl: Duration,
+    /// 联网工具缓存的总大小上限（字节，`[tools.web] cache_max_mb`）
+    pub web_cache_max_bytes: u64,
+    /// 工具的网络访问策略（`[network]`，`--offline-tools` 时为 `none`）
+    #[serde(skip)]
+    pub network: NetworkPolicy,
+    /// 允许访问的工作区根目录（由命令行和 `/add-dir` 登记，为空时不限制）
+    #[serde(skip)]
+    pub roots: WorkspaceRoots,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            root: None,
+            shell: None,
+            bash_timeout: None,
+            grep_head_limit: DEFAULT_GREP_HEAD_LIMIT,
+            grep_hidden: true,
+            read_max_lines: None,
+            result_max_tokens: DEFAULT_RESULT_MAX_TOKENS,
+            max_identical_calls: DEFAULT_MAX_IDENTICAL_CALLS,
+            web_cache_ttl: Duration::from_secs(DEFAULT_TTL_HOURS * 3600),
+            web_cache_max_bytes: DEFAULT_MAX_MB * 1024 * 1024,
+            network: NetworkPolicy::default(),
+            roots: WorkspaceRoots::default(),
+        }
+    }
+}
+
+impl ToolSettings {
+    /// 读取全局与项目配置（项目按键覆盖全局）并解析
+    pub fn load() -> Result<Self> {
+        let config = ConfigLoader::new().load_toml_layers()?;
+        let mut settings =
+            Self::from_config(&config.tools.unwrap_or_default(), &std::env::current_dir()?)?;
+        settings.network = NetworkPolicy::from_config(&config.network.unwrap_or_default())?;
+        Ok(settings)
+    }
+
+    /// 校验并解析 `[tools]` 配置，`base` 为相对 `[tools.root] path` 的基准目录
+    pub fn from_config(config: &ToolsConfig, base: &Path) -> Result<Self> {
+        let mut settings = Self::default();
+
+        if let Some(shell) = config.shell() {
+            if shell.trim().is_empty() {
+                let key = match config.bash.as_ref().and_then(|bash| bash.shell.as_ref()) {
+                    Some(_) => "tools.bash.shell",
+                    None => "tools.shell",
+                };
+                bail!("Invalid {}: must not be empty", key);
+            }
+            settings.shell = Some(shell.to_string());
+        }
+
+        if let Some(bash) = &config.bash {
+            if let Some(ms) = bash.default_timeout_ms {
+                if ms == 0 {
+                    bail!("Invalid tools.bash.default_timeout_ms: must be greater than 0");
+                }
+                settings.bash_timeout = Some(Duration::from_millis(ms));
+            }
+        }
+
+        if let Some(grep) = &config.grep {
+            if let Some(limit) = grep.head_limit {
+                if limit == 0 {
+                    bail!("Invalid tools.grep.head_limit: must be greater than 0");
+                }
+                settings.grep_head_limit = limit;
+            }
+            if let Some(hidden) = grep.hidden {
+                settings.grep_hidden = hidden;
+            }
+        }
+
+        if let Some(max_lines) = config.read.as_ref().and_then(|read| read.max_lines) {
+            if max_lines == 0 {
+                bail!("Invalid tools.read.max_lines: must be greater than 0");
+            }
+            settings.read_max_lines = Some(max_lines);
+        }
+
+        if let Some(max_tokens) = config.result_max_tokens {
+            settings.result_max_tokens = max_tokens;
+        }
+
+        if let Some(max_calls) = config.max_identical_calls {
+            settings.max_identical_calls = max_calls;
+        }
+
+        if let Some(web) = &config.web {
+            if let Some(hours) = web.cache_ttl_hours {
+                settings.web_cache_ttl = Duration::from_secs(hours * 3600);
+            }
+            if let Some(mb) = web.cache_max_mb {
+                if mb == 0 {
+                    bail!("Invalid tools.web.cache_max_mb: must be greater than 0; set tools.web.cache_ttl_hours = 0 to disable the cache");
+                }
+                settings.web_cache_max_bytes = mb * 1024 * 1024;
+            }
+        }
+
+        if let Some(path) = config.root.as_ref().and_then(|root| root.path.as_ref()) {
+            let root = base.join(path);
+            if !root.is_dir() {
+                bail!(
+                    "Invalid tools.root.path: '{}' is not a directory",
+                    path.display()
+                );
+            }
+            settings.root = Some(root);
+        }
+
+        Ok(settings)
+    }
+
+    /// 把相对路径解析到根目录下，未设置根目录或路径为绝对路径时原样返回
+    ///
+    /// `alias:相对路径` 解析到对应的工作区根目录下。
+    pub fn resolve_path(&self, path: &str) -> String {
+        if let Some(resolved) = self.roots.resolve_alias(path) {
+            return resolved.to_string_lossy().to_string();
+        }
+        match &self.root {
+            Some(root) if Path::new(path).is_relative() => {
+                root.join(path).to_string_lossy().to_string()
+            }
+            _ => path.to_string(),
+        }
+    }
+
+    /// 命令的工作目录：`[tools.root]`，未设置时为当前目录
+    pub fn working_dir(&self) -> PathBuf {
+        match &self.root {
+            Some(root) => root.clone(),
+            None => std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// 生效的设置（配置键, 值），未设置的项为 `None`，用于 `/config show`
+    pub fn entries(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            (
+                "tools.root.path",
+                self.root.as_ref().map(|root| root.display().to_string()),
+            ),
+            ("tools.bash.shell", self.shell.clone()),
+            (
+                "tools.bash.default_timeout_ms",
+                self.bash_timeout
+                    .map(|timeout| timeout.as_millis().to_string()),
+            ),
+            (
+                "tools.grep.head_limit",
+                Some(self.grep_head_limit.to_string()),
+            ),
+            ("tools.grep.hidden", Some(self.grep_hidden.to_string())),
+            (
+                "tools.read.max_lines",
+                self.read_max_lines.map(|lines| lines.to_string()),
+            ),
+            (
+                "tools.result_max_tokens",
+                Some(self.result_max_tokens.to_string()),
+            ),
+            (
+                "tools.max_identical_calls",
+                Some(self.max_identical_calls.to_string()),
+            ),
+            (
+                "tools.web.cache_ttl_hours",
+                Some((self.web_cache_ttl.as_secs() / 3600).to_string()),
+            ),
+            (
+                "tools.web.cache_max_mb",
+                Some((self.web_cache_max_bytes / 1024 / 1024).to_string()),
+            ),
+            ("network.tools", Some(self.network.describe())),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn parse(toml: &str, base: &Path) -> Result<ToolSettings> {
+        let config: crate::config::TomlConfig = toml::from_str(toml).unwrap();
+        ToolSettings::from_config(&config.tools.unwrap_or_default(), base)
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("packages/web")).unwrap();
+
+        let settings = parse("", temp_dir.path()).unwrap();
+        assert_eq!(settings, ToolSettings::default());
+
+        let settings = parse(
+            r#"
+[tools]
+shell = "bash"
+result_max_tokens = 0
+max_identical_calls = 5
+[tools.bash]
+shell = "fish"
+default_timeout_ms = 1500
+[tools.grep]
+head_limit = 50
+hidden = false
+[tools.read]
+max_lines = 400
+[tools.root]
+path = "packages/web"
+[tools.web]
+cache_ttl_hours = 0
+"#,
+            temp_dir.path(),
+        )
+        .unwrap();
+        assert_eq!(settings.shell.as_deref(), Some("fish"));
+        assert_eq!(settings.bash_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(settings.grep_head_limit, 50);
+        assert!(!settings.grep_hidden);
+        assert_eq!(settings.read_max_lines, Some(400));
+        assert_eq!(settings.result_max_tokens, 0);
+        assert_eq!(settings.max_identical_calls, 5);
+        assert!(settings.web_cache_ttl.is_zero());
+        assert_eq!(settings.web_cache_max_bytes, DEFAULT_MAX_MB * 1024 * 1024);
+
+        let root = temp_dir.path().join("packages/web");
+        assert_eq!(settings.root.as_deref(), Some(root.as_path()));
+        assert_eq!(
+            settings.resolve_path("src/lib.rs"),
+            root.join("src/lib.rs").to_string_lossy()
+        );
+        let absolute = temp_dir.path().join("README.md");
+        let absolute = absolute.to_string_lossy();
+        assert_eq!(settings.resolve_path(&absolute), absolute);
+    }
+
+    #[test]
+    fn test_invalid_settings_name_the_key() {
+        let temp_dir = TempDir::new().unwrap();
+        for (toml, key) in [
+            ("[tools.grep]\nhead_limit = 0\n", "tools.grep.head_limit"),
+            ("[tools.read]\nmax_lines = 0\n", "tools.read.max_lines"),
+            (
+                "[tools.bash]\ndefault_timeout_ms = 0\n",
+                "tools.bash.default_timeout_ms",
+            ),
+            ("[tools.bash]\nshell = \" \"\n", "tools.bash.shell"),
+            ("[tools.root]\npath = \"missing\"\n", "tools.root.path"),
+            ("[tools.web]\ncache_max_mb = 0\n", "tools.web.cache_max_mb"),
+        ] {
+            let error = parse(toml, temp_dir.path()).unwrap_err().to_string();
+            assert!(error.contains(key), "{} should mention {}", error, key);
+        }
+    }
+}
@@ -1,27 +1,131 @@
-use super::FileToolError;
+use super::{ErrorCode, FileToolError, ToolFailure};
 use super::git_guard::GitGuard;
 use super::commit_linter::CommitLinter;
+use super::sandbox::Sandbox;
+use super::settings::ToolSettings;
+use super::verbose_output::verbose_tools;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
+use crate::shell::{ProcessTree, Shell};
+use crate::task::manager::{get_task_manager, TaskManager};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 
 #[derive(Deserialize, Serialize)]
 pub struct ShellExecuteArgs {
     pub command: String,
+    /// 命令的工作目录（相对默认工作目录，或 `alias:相对路径`），省略时为默认工作目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// 在后台运行，立即返回任务 ID（输出写入任务输出文件，用 task_stop 停止）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub run_in_background: bool,
 }
 
 #[derive(Serialize, Debug)]
 pub struct ShellExecuteOutput {
     pub command: String,
+    /// 命令实际运行的目录
+    pub cwd: String,
     pub success: bool,
     pub stdout: String,
     pub stderr: String,
     pub exit_code: Option<i32>,
+    /// 后台运行时的任务 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct ShellExecuteTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct ShellExecuteTool {
+    settings: ToolSettings,
+}
+
+impl ShellExecuteTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// 命令的工作目录：默认为 `[tools.root]`（未设置时为当前目录），`cwd` 必须在工作区根目录之内
+    fn working_dir(&self, cwd: Option<&str>) -> Result<PathBuf, FileToolError> {
+        let base = self.settings.working_dir();
+        let Some(cwd) = cwd.map(str::trim).filter(|cwd| !cwd.is_empty()) else {
+            return Ok(base);
+        };
+
+        let dir = self
+            .settings
+            .roots
+            .resolve_alias(cwd)
+            .unwrap_or_else(|| base.join(cwd));
+        self.settings.roots.check(&dir)?;
+        if !dir.is_dir() {
+            return Err(ToolFailure::new(
+                ErrorCode::NotFound,
+                format!("Working directory does not exist: {}", cwd),
+            )
+            .with_suggestion("pass an existing directory as cwd, or omit it")
+            .into());
+        }
+        Ok(dir.canonicalize().unwrap_or(dir))
+    }
+
+    /// 作为 `tasks` 中的后台任务启动命令（见 [`TaskManager::spawn_process`]），不等待它结束
+    fn spawn_background(
+        &self,
+        tasks: &TaskManager,
+        command: &str,
+        cmd: std::process::Command,
+        cwd: &Path,
+    ) -> Result<ShellExecuteOutput, FileToolError> {
+        let task = tasks
+            .spawn_process(command.to_string(), command, cmd)
+            .map_err(|e| {
+                ToolFailure::new(
+                    ErrorCode::Blocked,
+                    format!("Failed to start the background command: {:#}", e),
+                )
+                .with_suggestion("check [tools.bash] shell; the command was not run")
+            })?;
+        let output_file = task
+            .output_file
+            .as_ref()
+            .map(|path| {
+                std::env::current_dir()
+                    .map(|dir| dir.join(path))
+                    .unwrap_or_else(|_| path.clone())
+            })
+            .unwrap_or_default();
+        Ok(ShellExecuteOutput {
+            command: command.to_string(),
+            cwd: self.settings.roots.display(cwd),
+            success: true,
+            stdout: format!(
+                "Started in the background as task {}. Output is written to {} (read it with read_file); stop it with task_stop.",
+                task.id,
+                output_file.display()
+            ),
+            stderr: String::new(),
+            exit_code: None,
+            task_id: Some(task.id),
+        })
+    }
+}
+
+/// 离开作用域时结束命令的整个进程树
+///
+/// 超时返回和调用被取消（future 被丢弃）时都会生效，命令在后台派生的进程不会残留；
+/// 需要常驻的进程应使用 `run_in_background`。
+struct KillTreeOnDrop(ProcessTree);
+
+impl Drop for KillTreeOnDrop {
+    fn drop(&mut self) {
+        self.0.kill();
+    }
+}
 
 impl Tool for ShellExecuteTool {
     const NAME: &'static str = "shell_execute";
@@ -31,15 +135,47 @@ impl Tool for ShellExecuteTool {
     type Output = ShellExecuteOutput;
 
     async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let mut description = format!(
+            "Execute a shell command and return the output. Commands run in {}, so use its syntax. Use with caution as this can modify the system.",
+            Shell::detect(self.settings.shell.as_deref()).kind().describe()
+        );
+        if let Some(timeout) = self.settings.bash_timeout {
+            description.push_str(&format!(
+                " Commands are killed after {} ms, together with any processes they started.",
+                timeout.as_millis()
+            ));
+        }
+        if let Some(hint) = Sandbox::from_config().tool_hint() {
+            description.push(' ');
+            description.push_str(hint);
+        }
+        description.push_str(
+            " A non-zero exit status is not an error: check success, exit_code and stderr. \
+             Every call starts a fresh shell, so `cd` does not carry over: to run a command in another directory, \
+             set cwd instead of prefixing it with `cd dir &&`. The result includes the directory the command ran in. \
+             Processes a foreground command leaves running are ended when it returns, \
+             so for servers, watchers and other long-running commands set run_in_background: the call returns a task_id at once, \
+             the output goes to a file named in the result, and task_stop ends the command together with its child processes. \
+             Error codes: Timeout (the command was killed), Blocked (the sandbox could not be set up or the shell could not start), \
+             PermissionDenied (cwd is outside the workspace roots), NotFound (cwd does not exist).",
+        );
         ToolDefinition {
             name: "shell_execute".to_string(),
-            description: "Execute a shell command and return the output. Use with caution as this can modify the system.".to_string(),
+            description,
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "command": {
                         "type": "string",
                         "description": "The command to execute."
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Directory to run the command in, relative to the working directory (or an absolute path inside the workspace roots). Defaults to the working directory."
+                    },
+                    "run_in_background": {
+                        "type": "boolean",
+                        "description": "Start the command as a background task and return its task_id without waiting for it to finish. Default: false."
                     }
                 },
                 "required": ["command"]
@@ -49,30 +185,84 @@ impl Tool for ShellExecuteTool {
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let command = &args.command;
+        let cwd = self.working_dir(args.cwd.as_deref())?;
 
-        // Execute the command using cmd on Windows or sh on Unix
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd").args(["/C", command]).output()
-        } else {
-            Command::new("sh").args(["-c", command]).output()
+        // sh on Unix; Git Bash or PowerShell on Windows unless [tools.bash] shell overrides it
+        let shell = Shell::detect(self.settings.shell.as_deref());
+        let mut cmd = shell.command(command);
+        cmd.current_dir(&cwd);
+        // [tools.bash.sandbox] 开启时在 Landlock / setrlimit 沙箱中运行
+        let roots = self.settings.roots.extra();
+        let sandbox = Sandbox::from_config().with_extra_roots(roots.into_iter().map(|r| r.path));
+        sandbox.apply(&mut cmd).map_err(|e| {
+            ToolFailure::new(
+                ErrorCode::Blocked,
+                format!("Failed to set up the sandbox: {}", e),
+            )
+            .with_suggestion("the command was not run; ask the user to check [tools.bash.sandbox]")
+        })?;
+        if args.run_in_background {
+            return self.spawn_background(get_task_manager(), command, cmd, &cwd);
+        }
+
+        let start_failure = |e: std::io::Error| -> FileToolError {
+            ToolFailure::new(
+                ErrorCode::Blocked,
+                format!("Failed to start {}: {}", shell.kind().describe(), e),
+            )
+            .with_suggestion("check [tools.bash] shell; the command was not run")
+            .into()
+        };
+
+        // 命令在独立的进程树中运行，返回时（包括超时）连同派生的进程一起结束
+        ProcessTree::configure(&mut cmd);
+        let mut cmd = tokio::process::Command::from(cmd);
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let child = cmd.spawn().map_err(start_failure)?;
+        let _tree = KillTreeOnDrop(ProcessTree::attach_async(&child).map_err(start_failure)?);
+        let output = match self.settings.bash_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, child.wait_with_output()).await {
+                Ok(output) => output,
+                Err(_) => {
+                    return Err(ToolFailure::new(
+                        ErrorCode::Timeout,
+                        format!(
+                            "Command timed out after {} ms ([tools.bash] default_timeout_ms)",
+                            timeout.as_millis()
+                        ),
+                    )
+                    .with_details(serde_json::json!({ "timeout_ms": timeout.as_millis() as u64 }))
+                    .with_suggestion(
+                        "run a narrower or backgrounded command; do not repeat the same long-running command",
+                    )
+                    .with_retryable(false)
+                    .into());
+                }
+            },
+            None => child.wait_with_output().await,
         };
 
         match output {
             Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                let stdout = shell.normalize_output(&String::from_utf8_lossy(&output.stdout));
+                let stderr = shell.normalize_output(&String::from_utf8_lossy(&output.stderr));
                 let success = output.status.success();
                 let exit_code = output.status.code();
 
                 Ok(ShellExecuteOutput {
                     command: command.clone(),
+                    cwd: self.settings.roots.display(&cwd),
                     success,
                     stdout,
                     stderr,
                     exit_code,
+                    task_id: None,
                 })
             }
-            Err(e) => Err(FileToolError::Io(e)),
+            Err(e) => Err(start_failure(e)),
         }
     }
 }
@@ -83,9 +273,9 @@ pub struct WrappedShellExecuteTool {
 }
 
 impl WrappedShellExecuteTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: ShellExecuteTool,
+            inner: ShellExecuteTool::new(settings),
         }
     }
 }
@@ -102,8 +292,12 @@ impl Tool for WrappedShellExecuteTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Exec", args.command);
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "Exec", args.command);
+        if let Some(cwd) = &args.cwd {
+            tool_println!("  {}", format!("in {}", cwd).dimmed());
+        }
+        let label = format!("Exec({})", args.command);
 
         // Git 安全检查
         Self::check_git_safety(&args.command);
@@ -111,32 +305,49 @@ impl Tool for WrappedShellExecuteTool {
         let result = self.inner.call(args).await;
 
         match &result {
+            Ok(output) if output.task_id.is_some() => {
+                tool_println!(
+                    "  └─ {}",
+                    format!(
+                        "Running in the background as task {}",
+                        output.task_id.as_deref().unwrap_or_default()
+                    )
+                    .dimmed()
+                );
+            }
             Ok(output) => {
                 if output.success {
                     let stdout_lines = output.stdout.lines().count();
                     if stdout_lines > 0 {
-                        println!(
+                        tool_println!(
                             "  └─ {} ... +{} lines output",
                             "Command succeeded".dimmed(),
                             stdout_lines
                         );
                     } else {
-                        println!("  └─ {}", "Command succeeded".dimmed());
+                        tool_println!("  └─ {}", "Command succeeded".dimmed());
                     }
                 } else {
                     let stderr_lines = output.stderr.lines().count();
-                    println!(
+                    tool_println!(
                         "  └─ {} (exit: {})",
                         format!("Command failed, {} lines stderr", stderr_lines).red(),
                         output.exit_code.unwrap_or(-1)
                     );
                 }
+                let full_output = [output.stdout.as_str(), output.stderr.as_str()]
+                    .iter()
+                    .filter(|text| !text.trim().is_empty())
+                    .map(|text| text.trim_end_matches('\n'))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                verbose_tools().show(&label, &full_output);
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
@@ -164,21 +375,27 @@ impl WrappedShellExecuteTool {
 
             // 检查是否有 --force 标志
             if command_lower.contains("--force") || command_lower.contains("-f") {
-                println!();
-                println!(
+                tool_println!();
+                tool_println!(
                     "{} {}",
                     "🚨".bright_red(),
-                    "警告: 强制推送将会重写 Git 历史".bright_red().bold()
+                    t!("git.force_push_warning").bright_red().bold()
                 );
-                println!(
-                    "  这可能导致: {}",
-                    "其他协作者的提交丢失、分支冲突".bright_yellow()
+                tool_println!(
+                    "{}",
+                    t!(
+                        "git.force_push_consequence",
+                        consequence = t!("git.force_push_consequence_detail").bright_yellow()
+                    )
                 );
-                println!(
-                    "  如果确实需要, 请考虑使用: {}",
-                    "git push --force-with-lease".bright_cyan()
+                tool_println!(
+                    "{}",
+                    t!(
+                        "git.force_push_alternative",
+                        command = "git push --force-with-lease".bright_cyan()
+                    )
                 );
-                println!();
+                tool_println!();
             }
         } else if command_lower.contains("git commit") {
             // 验证 commit 消息
@@ -191,32 +408,38 @@ impl WrappedShellExecuteTool {
                     // 对于 commit 命令，这是正常的，不需要警告
                 }
                 super::git_guard::GitSafety::OnMainBranch { branch_name } => {
-                    println!();
-                    println!(
+                    tool_println!();
+                    tool_println!(
                         "{} {}",
                         "⚠️ ".bright_yellow(),
-                        "注意: 即将在主分支上提交".bright_yellow().bold()
+                        t!("git.commit_on_main").bright_yellow().bold()
                     );
-                    println!("  当前分支: {}", branch_name.bright_white());
-                    println!();
+                    tool_println!(
+                        "{}",
+                        t!("git.current_branch", branch = branch_name.bright_white())
+                    );
+                    tool_println!();
                 }
                 _ => {}
             }
         } else if command_lower.contains("git checkout") || command_lower.contains("git switch") {
             // 检查是否有未提交的更改
             if let super::git_guard::GitSafety::UncommittedChanges = guard.check_safety() {
-                println!();
-                println!(
+                tool_println!();
+                tool_println!(
                     "{} {}",
                     "⚠️ ".bright_yellow(),
-                    "警告: 切换分支前有未提交的更改".bright_yellow().bold()
+                    t!("git.uncommitted_before_switch").bright_yellow().bold()
                 );
-                println!(
-                    "  建议: {} 或 {}",
-                    "git stash".bright_cyan(),
-                    "git commit".bright_cyan()
+                tool_println!(
+                    "{}",
+                    t!(
+                        "git.switch_suggestion",
+                        stash = "git stash".bright_cyan(),
+                        commit = "git commit".bright_cyan()
+                    )
                 );
-                println!();
+                tool_println!();
             }
         }
     }
@@ -261,34 +484,192 @@ impl WrappedShellExecuteTool {
 
         // 显示验证结果
         if !result.valid {
-            println!();
-            println!(
+            tool_println!();
+            tool_println!(
                 "{} {}",
                 "✗".bright_red(),
-                "Commit 消息格式无效".bright_red()
+                t!("git.commit_invalid").bright_red()
             );
             for error in &result.errors {
-                println!("  {}", error.dimmed());
+                tool_println!("  {}", error.dimmed());
             }
-            println!();
+            tool_println!();
         } else if !result.warnings.is_empty() {
-            println!();
-            println!(
+            tool_println!();
+            tool_println!(
                 "{} {}",
                 "⚠️".bright_yellow(),
-                "Commit 消息格式建议".bright_yellow()
+                t!("git.commit_suggestions").bright_yellow()
             );
             for warning in &result.warnings {
-                println!("  {}", warning.dimmed());
+                tool_println!("  {}", warning.dimmed());
             }
-            println!();
+            tool_println!();
         } else {
             // 验证通过，显示简洁的成功信息
             let type_str = result.commit_type.as_deref().unwrap_or("unknown");
-            println!(
-                "  └─ {}",
-                format!("✓ Commit 格式: {}", type_str).dimmed()
-            );
+            tool_println!("  └─ {}", t!("git.commit_type", kind = type_str).dimmed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::WorkspaceRoots;
+    use std::time::Duration;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_failure() {
+        let settings = ToolSettings {
+            bash_timeout: Some(Duration::from_millis(100)),
+            ..ToolSettings::default()
+        };
+        let error = ShellExecuteTool::new(&settings)
+            .call(ShellExecuteArgs {
+                command: "sleep 5".to_string(),
+                cwd: None,
+                run_in_background: false,
+            })
+            .await
+            .unwrap_err();
+
+        let failure = error.failure();
+        assert_eq!(failure.code, ErrorCode::Timeout);
+        assert_eq!(failure.details.unwrap()["timeout_ms"], 100);
+        assert!(failure.suggestion.is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_kills_background_grandchild() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("grandchild.pid");
+        let settings = ToolSettings {
+            bash_timeout: Some(Duration::from_millis(300)),
+            ..ToolSettings::default()
+        };
+        let error = ShellExecuteTool::new(&settings)
+            .call(ShellExecuteArgs {
+                command: format!(
+                    "sleep 30 >/dev/null 2>&1 & echo $! > '{}'; sleep 5",
+                    pid_file.display()
+                ),
+                cwd: None,
+                run_in_background: false,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::Timeout);
+
+        // 已退出但未被回收的僵尸进程不算存活
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        let alive = || {
+            std::fs::read_to_string(&stat)
+                .map(|stat| !stat.contains(") Z "))
+                .unwrap_or(false)
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while alive() && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
+        assert!(!alive(), "background grandchild survived the timeout");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cwd_runs_command_in_subdirectory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let workspace = temp_dir.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("crates/core")).unwrap();
+        std::fs::write(workspace.join("crates/core/lib.rs"), "").unwrap();
+        let settings = ToolSettings {
+            root: Some(workspace.clone()),
+            roots: WorkspaceRoots::new(&workspace),
+            ..ToolSettings::default()
+        };
+        let tool = ShellExecuteTool::new(&settings);
+
+        let output = tool
+            .call(ShellExecuteArgs {
+                command: "ls".to_string(),
+                cwd: Some("crates/core".to_string()),
+                run_in_background: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "lib.rs\n");
+        let expected = workspace.join("crates/core").canonicalize().unwrap();
+        assert_eq!(output.cwd, expected.display().to_string());
+
+        // 省略时在默认工作目录运行
+        let output = tool
+            .call(ShellExecuteArgs {
+                command: "ls".to_string(),
+                cwd: None,
+                run_in_background: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "crates\n");
+        assert_eq!(output.cwd, workspace.display().to_string());
+
+        // 工作区根目录之外的目录被拒绝，命令不运行
+        let error = tool
+            .call(ShellExecuteArgs {
+                command: "touch escaped".to_string(),
+                cwd: Some("..".to_string()),
+                run_in_background: false,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::PermissionDenied);
+        assert!(!temp_dir.path().join("escaped").exists());
+
+        let error = tool
+            .call(ShellExecuteArgs {
+                command: "ls".to_string(),
+                cwd: Some("missing".to_string()),
+                run_in_background: false,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.failure().code, ErrorCode::NotFound);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_background_command_is_a_stoppable_task() {
+        use crate::task::manager::{TaskStatus, Termination};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let tasks = TaskManager::new(temp_dir.path().join("tasks")).unwrap();
+        let command = "echo started; sleep 30";
+        let output = ShellExecuteTool::default()
+            .spawn_background(
+                &tasks,
+                command,
+                Shell::detect(None).command(command),
+                temp_dir.path(),
+            )
+            .unwrap();
+        let task_id = output.task_id.unwrap();
+        assert!(output.stdout.contains(&task_id));
+        assert_eq!(
+            tasks.get_task(&task_id).unwrap().unwrap().status,
+            TaskStatus::InProgress
+        );
+
+        let termination = tasks
+            .stop_task(&task_id, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(termination, Some(Termination::Graceful));
+        assert_eq!(
+            tasks.get_task(&task_id).unwrap().unwrap().status,
+            TaskStatus::Cancelled
+        );
     }
 }
@@ -4,12 +4,14 @@
 
 #![allow(dead_code)]
 
-use super::FileToolError;
+use super::{ErrorCode, FileToolError, ToolFailure};
+use crate::atomic_file::write_atomic;
+use crate::task::manager::load_tasks_config;
+use crate::task::output::OutputWriter;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use std::io::Write;
 
 /// Task 工具输入参数
 #[derive(Deserialize)]
@@ -71,8 +73,7 @@ impl TaskTool {
         let json = serde_json::to_string_pretty(metadata)
             .map_err(|e| FileToolError::InvalidInput(format!("序列化失败: {}", e)))?;
 
-        fs::write(&meta_path, json)
-            .map_err(|e| FileToolError::Io(e))?;
+        write_atomic(&meta_path, json.as_bytes()).map_err(FileToolError::Io)?;
 
         Ok(())
     }
@@ -86,23 +87,22 @@ impl TaskTool {
         let tasks_dir = Self::get_tasks_dir();
         let output_file = tasks_dir.join(format!("{}.output.txt", task_id));
 
-        // 创建输出文件
-        let mut file = fs::File::create(&output_file)
-            .map_err(|e| FileToolError::Io(e))?;
+        // 创建输出文件，与后台进程的输出一样受 `[tasks]` 的大小上限和轮转约束
+        let mut writer = OutputWriter::create(&output_file, &load_tasks_config())
+            .map_err(FileToolError::Io)?;
 
         // 写入初始信息
-        writeln!(file, "Task ID: {}", task_id)
-            .map_err(|e| FileToolError::Io(e))?;
-        writeln!(file, "Description: {}", description)
-            .map_err(|e| FileToolError::Io(e))?;
-        writeln!(file, "Agent Type: {}", agent_type)
-            .map_err(|e| FileToolError::Io(e))?;
-        writeln!(file, "Started at: {}", chrono::Utc::now().to_rfc3339())
-            .map_err(|e| FileToolError::Io(e))?;
-        writeln!(file, "{}", "=".repeat(80))
-            .map_err(|e| FileToolError::Io(e))?;
-        writeln!(file, "Task execution in progress...")
-            .map_err(|e| FileToolError::Io(e))?;
+        let header = format!(
+            "Task ID: {}\nDescription: {}\nAgent Type: {}\nStarted at: {}\n{}\nTask execution in progress...\n",
+            task_id,
+            description,
+            agent_type,
+            chrono::Utc::now().to_rfc3339(),
+            "=".repeat(80)
+        );
+        writer
+            .write(header.as_bytes(), |_, _| {})
+            .map_err(FileToolError::Io)?;
 
         // 注意: 这里我们只是创建了一个占位符
         // 真正的后台任务执行需要在更高层级实现
@@ -133,7 +133,7 @@ impl Tool for TaskTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "task".to_string(),
-            description: "Launch a specialized agent to handle complex, multi-step tasks autonomously. Each agent type has specific capabilities and tools available to it. Use this tool when you need to delegate work to a specialized agent (e.g., Explore agent for codebase analysis, Plan agent for design, Code Reviewer for code review, Frontend Developer for UI work).".to_string(),
+            description: "Launch a specialized agent to handle complex, multi-step tasks autonomously. Each agent type has specific capabilities and tools available to it. Use this tool when you need to delegate work to a specialized agent (e.g., Explore agent for codebase analysis, Plan agent for design, Code Reviewer for code review, Frontend Developer for UI work). Error codes: InvalidArgs (unknown agent_type).".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -166,18 +166,17 @@ impl Tool for TaskTool {
         let valid_types = ["main", "explore", "plan", "code_reviewer", "frontend_developer"];
 
         if !valid_types.contains(&agent_type.as_str()) {
-            return Ok(TaskOutput {
-                task_id: String::new(),
-                name: String::new(),
-                status: "failed".to_string(),
-                output_file: None,
-                success: false,
-                message: format!(
+            return Err(ToolFailure::new(
+                ErrorCode::InvalidArgs,
+                format!(
                     "Invalid agent_type: '{}'. Valid types are: {}",
                     agent_type,
                     valid_types.join(", ")
                 ),
-            });
+            )
+            .with_details(serde_json::json!({ "valid_types": valid_types }))
+            .with_suggestion("pick one of the valid agent types")
+            .into());
         }
 
         // 生成任务 ID
@@ -301,4 +300,18 @@ mod tests {
         assert!(json.contains("test-id"));
         assert!(json.contains("completed"));
     }
+
+    #[tokio::test]
+    async fn test_invalid_agent_type() {
+        let args = TaskArgs {
+            description: "x".to_string(),
+            agent_type: Some("wizard".to_string()),
+            run_in_background: None,
+            name: None,
+        };
+
+        let failure = TaskTool.call(args).await.unwrap_err().failure();
+        assert_eq!(failure.code, ErrorCode::InvalidArgs);
+        assert_eq!(failure.details.unwrap()["valid_types"][1], "explore");
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! 获取任务的详细信息。
 
-use super::FileToolError;
+use super::{ErrorCode, FileToolError, ToolFailure};
 use crate::task::manager::{get_task_manager, TaskStatus};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -79,6 +79,7 @@ impl TaskGetTool {
             TaskStatus::Completed => "completed".to_string(),
             TaskStatus::Failed => "failed".to_string(),
             TaskStatus::Deleted => "deleted".to_string(),
+            TaskStatus::Cancelled => "cancelled".to_string(),
         }
     }
 }
@@ -106,7 +107,7 @@ impl Tool for TaskGetTool {
 Returns full task details:
 - **subject**: Task title
 - **description**: Detailed requirements and context
-- **status**: 'pending', 'in_progress', 'completed', 'failed', or 'deleted'
+- **status**: 'pending', 'in_progress', 'completed', 'failed', 'cancelled', or 'deleted'
 - **blocks**: Tasks waiting on this one to complete
 - **blockedBy**: Tasks that must complete before this one can start
 - **metadata**: Custom metadata attached to the task
@@ -116,7 +117,11 @@ Returns full task details:
 ## Tips
 
 - After fetching a task, verify its blockedBy list is empty before beginning work.
-- Use TaskList to see all tasks in summary form."#.to_string(),
+- Use TaskList to see all tasks in summary form.
+
+## Errors
+
+- **NotFound**: no task has this ID; call task_list for the current IDs."#.to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -155,11 +160,12 @@ Returns full task details:
                     message: "Task retrieved successfully".to_string(),
                 })
             }
-            Ok(None) => Ok(TaskGetOutput {
-                task: None,
-                success: false,
-                message: format!("Task '{}' not found", args.task_id),
-            }),
+            Ok(None) => Err(ToolFailure::new(
+                ErrorCode::NotFound,
+                format!("Task '{}' not found", args.task_id),
+            )
+            .with_suggestion("call task_list to see the existing task ids")
+            .into()),
             Err(e) => Ok(TaskGetOutput {
                 task: None,
                 success: false,
@@ -1,17 +1,72 @@
 //! TaskList 工具
 //!
-//! 列出任务列表中的所有任务。
+//! 按状态、所有者和关键字筛选任务列表，结果按最后更新时间倒序分页返回。
 
 use super::FileToolError;
-use crate::task::manager::{get_task_manager, Task, TaskStatus};
+use crate::task::manager::{get_task_manager, Task, TaskFilter, TaskStatus};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// 默认每页任务数
+const DEFAULT_LIMIT: usize = 50;
+
+/// 每页任务数上限
+const MAX_LIMIT: usize = 200;
+
 /// TaskList 工具输入参数
 #[derive(Deserialize)]
 pub struct TaskListArgs {
-    // 无参数，列出所有任务
+    /// 只列出这些状态的任务（单个状态或状态数组）
+    #[serde(default)]
+    pub status: Option<StatusArg>,
+
+    /// 只列出该所有者的任务
+    #[serde(default)]
+    pub owner: Option<String>,
+
+    /// 在标题和描述中搜索
+    #[serde(default)]
+    pub query: Option<String>,
+
+    /// 每页任务数（默认 50，最大 200）
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// 跳过的任务数
+    #[serde(default)]
+    pub offset: usize,
+
+    /// 包括超过保留期的已完成任务
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// `status` 参数，可以是单个状态或状态数组
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum StatusArg {
+    One(TaskStatus),
+    Many(Vec<TaskStatus>),
+}
+
+impl TaskListArgs {
+    /// 转换为任务查询条件
+    fn to_filter(&self) -> TaskFilter {
+        let statuses = match &self.status {
+            Some(StatusArg::One(status)) => vec![*status],
+            Some(StatusArg::Many(statuses)) => statuses.clone(),
+            None => Vec::new(),
+        };
+        TaskFilter {
+            statuses,
+            owner: self.owner.clone().filter(|owner| !owner.is_empty()),
+            query: self.query.clone().filter(|query| !query.trim().is_empty()),
+            include_all: self.all,
+            limit: Some(self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)),
+            offset: self.offset,
+        }
+    }
 }
 
 /// 任务摘要
@@ -39,9 +94,15 @@ pub struct TaskListOutput {
     /// 任务列表
     pub tasks: Vec<TaskSummary>,
 
-    /// 任务总数
+    /// 满足条件的任务总数（分页之前）
     pub total: usize,
 
+    /// 本页第一个任务的位置
+    pub offset: usize,
+
+    /// 是否还有下一页
+    pub has_more: bool,
+
     /// 是否成功
     pub success: bool,
 
@@ -62,6 +123,7 @@ impl TaskListTool {
             TaskStatus::Completed => "completed".to_string(),
             TaskStatus::Failed => "failed".to_string(),
             TaskStatus::Deleted => "deleted".to_string(),
+            TaskStatus::Cancelled => "cancelled".to_string(),
         }
     }
 
@@ -87,7 +149,7 @@ impl Tool for TaskListTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "task_list".to_string(),
-            description: r#"Use this tool to list all tasks in the task list.
+            description: r#"Use this tool to list tasks in the task list.
 
 ## When to Use This Tool
 
@@ -97,55 +159,129 @@ impl Tool for TaskListTool {
 - After completing a task, to check for newly unblocked work or claim the next available task
 - **Prefer working on tasks in ID order** (lowest ID first) when multiple tasks are available
 
+## Filtering
+
+All parameters are optional and combine with AND:
+- **status**: one status or an array, e.g. "in_progress" or ["pending", "in_progress"]
+- **owner**: only tasks owned by this agent
+- **query**: case-insensitive text search over subject and description
+- **limit** / **offset**: paginate (default limit 50, max 200)
+- **all**: completed tasks older than the retention period are hidden by default; set to true to include them
+
+Deleted tasks are only listed when requested via status.
+
 ## Output
 
+Tasks are sorted by last update, most recent first. `total` is the number of matching tasks before pagination and `has_more` tells whether another page exists.
+
 Returns a summary of each task:
 - **id**: Task identifier (use with TaskGet, TaskUpdate)
 - **subject**: Brief description of the task
-- **status**: 'pending', 'in_progress', 'completed', 'failed', or 'deleted'
+- **status**: 'pending', 'in_progress', 'completed', 'failed', 'cancelled', or 'deleted'
 - **owner**: Agent ID if assigned, empty if available
 - **blockedBy**: List of open task IDs that must be resolved first
 
 Use TaskGet with a specific task ID to view full details including description."#.to_string(),
             parameters: serde_json::json!({
                 "type": "object",
-                "properties": {},
+                "properties": {
+                    "status": {
+                        "oneOf": [
+                            { "type": "string", "enum": ["pending", "in_progress", "completed", "failed", "cancelled", "deleted"] },
+                            {
+                                "type": "array",
+                                "items": { "type": "string", "enum": ["pending", "in_progress", "completed", "failed", "cancelled", "deleted"] }
+                            }
+                        ],
+                        "description": "Only list tasks with this status (or any of these statuses)"
+                    },
+                    "owner": {
+                        "type": "string",
+                        "description": "Only list tasks owned by this agent"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Case-insensitive text to search for in subject and description"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of tasks to return (default: 50, max: 200)",
+                        "default": 50,
+                        "minimum": 1,
+                        "maximum": 200
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Number of matching tasks to skip",
+                        "default": 0,
+                        "minimum": 0
+                    },
+                    "all": {
+                        "type": "boolean",
+                        "description": "Include completed tasks older than the retention period",
+                        "default": false
+                    }
+                },
                 "required": []
             }),
         }
     }
 
-    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let manager = get_task_manager();
+        let filter = args.to_filter();
 
-        match manager.list_tasks() {
-            Ok(tasks) => {
+        let result = manager
+            .list_tasks()
+            .and_then(|tasks| Ok((tasks, manager.query_tasks(&filter)?)));
+
+        match result {
+            Ok((tasks, page)) => {
                 // 构建任务映射用于计算阻塞关系
-                let tasks_map: HashMap<String, Task> = tasks
-                    .iter()
-                    .cloned()
-                    .map(|t| (t.id.clone(), t))
-                    .collect();
+                let tasks_map: HashMap<String, Task> =
+                    tasks.into_iter().map(|t| (t.id.clone(), t)).collect();
 
-                // 过滤掉已删除的任务，转换为摘要
-                let summaries: Vec<TaskSummary> = tasks
+                let summaries: Vec<TaskSummary> = page
+                    .tasks
                     .iter()
-                    .filter(|t| t.status != TaskStatus::Deleted)
                     .map(|t| Self::task_to_summary(t, &tasks_map))
                     .collect();
 
-                let total = summaries.len();
+                let shown = summaries.len();
+                let has_more = filter.offset + shown < page.total;
+                let mut message = if shown == 0 {
+                    format!("Found {} task(s), none on this page", page.total)
+                } else if shown == page.total {
+                    format!("Found {} task(s)", page.total)
+                } else {
+                    format!(
+                        "Showing {}-{} of {} task(s)",
+                        filter.offset + 1,
+                        filter.offset + shown,
+                        page.total
+                    )
+                };
+                if page.hidden_completed > 0 {
+                    message.push_str(&format!(
+                        "; {} older completed task(s) hidden, use all=true to include them",
+                        page.hidden_completed
+                    ));
+                }
 
                 Ok(TaskListOutput {
                     tasks: summaries,
-                    total,
+                    total: page.total,
+                    offset: filter.offset,
+                    has_more,
                     success: true,
-                    message: format!("Found {} task(s)", total),
+                    message,
                 })
             }
             Err(e) => Ok(TaskListOutput {
                 tasks: Vec::new(),
                 total: 0,
+                offset: args.offset,
+                has_more: false,
                 success: false,
                 message: format!("Failed to list tasks: {}", e),
             }),
@@ -196,7 +332,31 @@ mod tests {
     #[test]
     fn test_task_list_args_deserialization() {
         let json = r#"{}"#;
-        let _args: TaskListArgs = serde_json::from_str(json).unwrap();
+        let args: TaskListArgs = serde_json::from_str(json).unwrap();
+        let filter = args.to_filter();
+        assert!(filter.statuses.is_empty());
+        assert_eq!(filter.limit, Some(DEFAULT_LIMIT));
+        assert!(!filter.include_all);
+
+        let args: TaskListArgs = serde_json::from_str(
+            r#"{"status": ["pending", "in_progress"], "owner": "agent-1", "query": "auth", "limit": 500, "offset": 10, "all": true}"#,
+        )
+        .unwrap();
+        let filter = args.to_filter();
+        assert_eq!(
+            filter.statuses,
+            vec![TaskStatus::Pending, TaskStatus::InProgress]
+        );
+        assert_eq!(filter.owner.as_deref(), Some("agent-1"));
+        assert_eq!(filter.query.as_deref(), Some("auth"));
+        assert_eq!(filter.limit, Some(MAX_LIMIT));
+        assert_eq!(filter.offset, 10);
+        assert!(filter.include_all);
+
+        let args: TaskListArgs = serde_json::from_str(r#"{"status": "failed"}"#).unwrap();
+        assert_eq!(args.to_filter().statuses, vec![TaskStatus::Failed]);
+
+        assert!(serde_json::from_str::<TaskListArgs>(r#"{"status": "done"}"#).is_err());
     }
 
     #[test]
@@ -1,14 +1,46 @@
 //! TaskOutput 工具
 //!
 //! 检索后台任务的输出。
+//!
+//! 除一次性读取外还支持 follow 模式：持续追踪任务输出文件，
+//! 直到出现匹配的行、任务结束或超时。
 
 #![allow(dead_code)]
 
 use super::FileToolError;
+use crate::atomic_file::read_json_or_recover;
+use crate::shell::Shell;
+use crate::task::output::{read_output, OutputChunk};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 默认等待时间（毫秒）
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// 最长等待时间（毫秒），follow 模式永远不会无限阻塞
+const MAX_TIMEOUT_MS: u64 = 600_000;
+
+/// follow 模式轮询输出文件的间隔
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 匹配行之后附带的上下文行数
+const FOLLOW_CONTEXT_AFTER: usize = 5;
+
+/// follow 模式单次返回的最多行数（保留最后的部分）
+const FOLLOW_MAX_LINES: usize = 200;
+
+/// 未指定调用方时使用的默认标识
+const DEFAULT_CALLER: &str = "default";
+
+/// follow 模式的读取位置，按 (输出文件, 调用方) 记录
+static FOLLOW_OFFSETS: Lazy<Mutex<HashMap<(PathBuf, String), u64>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 /// TaskOutput 工具输入参数
 #[derive(Deserialize)]
@@ -20,9 +52,33 @@ pub struct TaskOutputArgs {
     #[serde(default)]
     pub block: Option<bool>,
 
-    /// 超时时间(毫秒,默认: 30000)
-    #[serde(default)]
+    /// 超时时间(毫秒,默认: 30000,最大: 600000)
+    #[serde(default, alias = "timeout_ms")]
     pub timeout: Option<u64>,
+
+    /// 是否以 follow 模式追踪输出 (默认: false)
+    #[serde(default)]
+    pub follow: bool,
+
+    /// follow 模式下等待出现的正则表达式
+    #[serde(default)]
+    pub until_pattern: Option<String>,
+
+    /// 调用方标识，用于区分各自的读取位置 (默认: "default")
+    #[serde(default)]
+    pub caller: Option<String>,
+}
+
+/// follow 模式结束的原因
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowStop {
+    /// 输出中出现了匹配 `until_pattern` 的行
+    Matched,
+    /// 任务已结束，且输出已读取完毕
+    TaskFinished,
+    /// 达到等待时间上限
+    TimedOut,
 }
 
 /// TaskOutput 工具输出
@@ -45,6 +101,31 @@ pub struct TaskOutputResult {
 
     /// 消息
     pub message: String,
+
+    /// follow 模式结束的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped_by: Option<FollowStop>,
+
+    /// 匹配到的行（仅 follow 模式且匹配成功时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_line: Option<String>,
+
+    /// 因输出超过大小上限而被丢弃、无法再读取的字节数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discarded_bytes: Option<u64>,
+}
+
+/// 一次 follow 的结果
+#[derive(Debug)]
+struct FollowOutcome {
+    /// 本次读到的新输出
+    output: String,
+    stopped_by: FollowStop,
+    matched_line: Option<String>,
+    /// 本次丢弃的较早行数（超过 FOLLOW_MAX_LINES 时）
+    skipped_lines: usize,
+    /// 上次读取位置之后、已被轮转丢弃的字节数
+    skipped_bytes: u64,
 }
 
 /// TaskOutput 工具
@@ -59,35 +140,223 @@ impl TaskOutputTool {
 
     /// 读取任务元数据
     fn read_task_metadata(task_id: &str) -> Result<Option<TaskMetadata>, FileToolError> {
-        let tasks_dir = Self::get_tasks_dir();
-        let meta_path = tasks_dir.join(format!("{}.json", task_id));
-
-        if !meta_path.exists() {
-            return Ok(None);
-        }
-
-        let content = fs::read_to_string(&meta_path)
-            .map_err(|e| FileToolError::Io(e))?;
-
-        let metadata: TaskMetadata = serde_json::from_str(&content)
-            .map_err(|e| FileToolError::InvalidInput(format!("解析任务元数据失败: {}", e)))?;
+        Self::read_task_metadata_in(&Self::get_tasks_dir(), task_id)
+    }
 
-        Ok(Some(metadata))
+    /// 从指定目录读取任务元数据（损坏的元数据文件移到一旁，按任务不存在处理）
+    fn read_task_metadata_in(
+        tasks_dir: &Path,
+        task_id: &str,
+    ) -> Result<Option<TaskMetadata>, FileToolError> {
+        let meta_path = tasks_dir.join(format!("{}.json", task_id));
+        read_json_or_recover(&meta_path).map_err(FileToolError::Io)
     }
 
     /// 读取任务输出
     fn read_task_output(task_id: &str) -> Result<Option<String>, FileToolError> {
-        let tasks_dir = Self::get_tasks_dir();
+        Ok(Self::read_task_output_in(&Self::get_tasks_dir(), task_id)?
+            .map(|chunk| String::from_utf8_lossy(&chunk.data).into_owned()))
+    }
+
+    /// 从指定目录读取任务保留下来的全部输出（包括轮转文件）
+    fn read_task_output_in(
+        tasks_dir: &Path,
+        task_id: &str,
+    ) -> Result<Option<OutputChunk>, FileToolError> {
         let output_path = tasks_dir.join(format!("{}.output.txt", task_id));
 
         if !output_path.exists() {
             return Ok(None);
         }
 
-        let content = fs::read_to_string(&output_path)
-            .map_err(|e| FileToolError::Io(e))?;
+        let chunk = read_output(&output_path, 0, || Self::discarded_bytes_in(tasks_dir, task_id))?;
+        Ok(Some(chunk))
+    }
+
+    /// 任务元数据中记录的、因轮转而丢弃的输出字节数
+    fn discarded_bytes_in(tasks_dir: &Path, task_id: &str) -> u64 {
+        Self::read_task_metadata_in(tasks_dir, task_id)
+            .ok()
+            .flatten()
+            .map(|meta| meta.output_discarded_bytes)
+            .unwrap_or(0)
+    }
+
+    /// 任务输出文件路径，优先使用元数据中记录的位置
+    fn output_path_in(tasks_dir: &Path, meta: &TaskMetadata) -> PathBuf {
+        meta.output_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| tasks_dir.join(format!("{}.output.txt", meta.id)))
+    }
+
+    /// follow 模式：从上次的读取位置开始追踪输出，
+    /// 直到匹配 `pattern`、任务结束或超时
+    async fn follow_in(
+        tasks_dir: &Path,
+        task_id: &str,
+        caller: &str,
+        pattern: Option<&Regex>,
+        timeout: Duration,
+    ) -> Result<Option<FollowOutcome>, FileToolError> {
+        let Some(meta) = Self::read_task_metadata_in(tasks_dir, task_id)? else {
+            return Ok(None);
+        };
+        let output_path = Self::output_path_in(tasks_dir, &meta);
+        let key = (output_path.clone(), caller.to_string());
+        let mut offset = FOLLOW_OFFSETS.lock().unwrap().get(&key).copied().unwrap_or(0);
+
+        let deadline = Instant::now() + timeout;
+        let mut lines: Vec<String> = Vec::new();
+        let mut matched: Option<usize> = None;
+        let mut skipped_bytes = 0;
+
+        let stopped_by = loop {
+            // 先确认任务状态，再读取输出，避免漏掉结束前写入的最后几行
+            // 读取失败时视为仍在运行并在下一轮重试
+            let finished = match Self::read_task_metadata_in(tasks_dir, task_id) {
+                Ok(Some(m)) => m.status != "in_progress",
+                Ok(None) => true,
+                Err(_) => false,
+            };
+
+            // 偏移量按整个输出流计算，跨越轮转文件仍然连续；
+            // 上次读取之后被轮转丢弃的部分直接跳过并记录下来
+            let chunk = read_output(&output_path, offset, || {
+                Self::discarded_bytes_in(tasks_dir, task_id)
+            })?;
+            skipped_bytes += chunk.skipped_from(offset);
+            offset = chunk.start;
+            let chunk = chunk.data;
+
+            // 只消费完整的行；任务结束后剩余的半行也一并返回
+            for raw in chunk.split_inclusive(|b| *b == b'\n') {
+                if !raw.ends_with(b"\n") && !finished {
+                    break;
+                }
+                if matched.is_some_and(|idx| lines.len() - idx > FOLLOW_CONTEXT_AFTER) {
+                    break;
+                }
+                let line = String::from_utf8_lossy(raw)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string();
+                // 匹配行之后的上下文只展示、不推进读取位置，
+                // 以免下一次调用漏掉其中再次出现的匹配
+                if matched.is_none() {
+                    offset += raw.len() as u64;
+                    if pattern.is_some_and(|re| re.is_match(&line)) {
+                        matched = Some(lines.len());
+                    }
+                }
+                lines.push(line);
+            }
+
+            if matched.is_some() {
+                break FollowStop::Matched;
+            }
+            if finished {
+                break FollowStop::TaskFinished;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break FollowStop::TimedOut;
+            }
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL.min(deadline - now)).await;
+        };
+
+        FOLLOW_OFFSETS.lock().unwrap().insert(key, offset);
+
+        let matched_line = matched.map(|idx| lines[idx].clone());
+        let skipped_lines = lines.len().saturating_sub(FOLLOW_MAX_LINES);
+        Ok(Some(FollowOutcome {
+            output: lines[skipped_lines..].join("\n"),
+            stopped_by,
+            matched_line,
+            skipped_lines,
+            skipped_bytes,
+        }))
+    }
+}
+
+impl TaskOutputTool {
+    /// follow 模式的工具入口
+    async fn call_follow(
+        tasks_dir: &Path,
+        args: TaskOutputArgs,
+    ) -> Result<TaskOutputResult, FileToolError> {
+        let pattern = args
+            .until_pattern
+            .as_deref()
+            .map(|p| {
+                Regex::new(p)
+                    .map_err(|e| {
+                        FileToolError::InvalidInput(t!("tools.task_output.invalid_pattern", error = e))
+                    })
+            })
+            .transpose()?;
+        let timeout =
+            Duration::from_millis(args.timeout.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS));
+        let caller = args.caller.as_deref().unwrap_or(DEFAULT_CALLER);
+
+        let Some(outcome) =
+            Self::follow_in(tasks_dir, &args.task_id, caller, pattern.as_ref(), timeout).await?
+        else {
+            return Ok(TaskOutputResult {
+                task_id: args.task_id.clone(),
+                status: "not_found".to_string(),
+                output: None,
+                error: Some(format!("Task '{}' not found", args.task_id)),
+                success: false,
+                message: format!("Task '{}' does not exist", args.task_id),
+                stopped_by: None,
+                matched_line: None,
+                discarded_bytes: None,
+            });
+        };
+
+        let status = Self::read_task_metadata_in(tasks_dir, &args.task_id)?
+            .map(|m| m.status)
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut message = match outcome.stopped_by {
+            FollowStop::Matched => format!("Pattern matched in task '{}' output", args.task_id),
+            FollowStop::TaskFinished if pattern.is_none() => {
+                format!("Task '{}' finished ({})", args.task_id, status)
+            }
+            FollowStop::TaskFinished => format!(
+                "Task '{}' finished ({}) before the pattern appeared",
+                args.task_id, status
+            ),
+            FollowStop::TimedOut => format!(
+                "Timed out after {}ms waiting for task '{}'; call again to continue from here",
+                timeout.as_millis(),
+                args.task_id
+            ),
+        };
+        if outcome.skipped_bytes > 0 {
+            message.push_str(&format!(
+                " ({} bytes of output were discarded by rotation before they could be read)",
+                outcome.skipped_bytes
+            ));
+        }
+        if outcome.skipped_lines > 0 {
+            message.push_str(&format!(
+                " ({} earlier lines omitted)",
+                outcome.skipped_lines
+            ));
+        }
 
-        Ok(Some(content))
+        let shell = Shell::from_config();
+        Ok(TaskOutputResult {
+            task_id: args.task_id,
+            status,
+            output: Some(shell.normalize_output(&outcome.output)),
+            error: None,
+            success: true,
+            message,
+            stopped_by: Some(outcome.stopped_by),
+            matched_line: outcome.matched_line.map(|line| shell.normalize_output(&line)),
+            discarded_bytes: Some(outcome.skipped_bytes).filter(|bytes| *bytes > 0),
+        })
     }
 }
 
@@ -100,6 +369,8 @@ struct TaskMetadata {
     status: String,
     created_at: String,
     output_file: Option<String>,
+    #[serde(default)]
+    output_discarded_bytes: u64,
 }
 
 impl Tool for TaskOutputTool {
@@ -112,7 +383,7 @@ impl Tool for TaskOutputTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "task_output".to_string(),
-            description: "Retrieves output from a running or completed task. Takes a task_id parameter and returns the task output along with status information. Use block=true (default) to wait for task completion, or block=false for a non-blocking status check. Works with all task types: background shells, async agents, and remote sessions.".to_string(),
+            description: "Retrieves output from a running or completed task. Takes a task_id parameter and returns the task output along with status information. Use block=true (default) to wait for task completion, or block=false for a non-blocking status check. Set follow=true to tail the output from where your previous follow call stopped, returning as soon as a line matches until_pattern (with surrounding context), the task finishes, or timeout_ms elapses; stopped_by reports which one happened. Works with all task types: background shells, async agents, and remote sessions.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -125,12 +396,25 @@ impl Tool for TaskOutputTool {
                         "description": "Whether to wait for completion (default: true)",
                         "default": true
                     },
-                    "timeout": {
+                    "timeout_ms": {
                         "type": "integer",
                         "description": "Max wait time in milliseconds (default: 30000, max: 600000)",
                         "default": 30000,
                         "minimum": 0,
                         "maximum": 600000
+                    },
+                    "follow": {
+                        "type": "boolean",
+                        "description": "Tail new output since the previous follow call instead of returning the whole log (default: false)",
+                        "default": false
+                    },
+                    "until_pattern": {
+                        "type": "string",
+                        "description": "Regex to wait for in follow mode, e.g. \"Listening on|error\""
+                    },
+                    "caller": {
+                        "type": "string",
+                        "description": "Identifies the reader so separate callers keep separate read positions (default: \"default\")"
                     }
                 },
                 "required": ["task_id"]
@@ -139,13 +423,32 @@ impl Tool for TaskOutputTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.follow {
+            return Self::call_follow(&Self::get_tasks_dir(), args).await;
+        }
+
         // 读取任务元数据
         let metadata = Self::read_task_metadata(&args.task_id)?;
 
         match metadata {
             Some(meta) => {
-                // 读取任务输出
-                let output = Self::read_task_output(&args.task_id)?;
+                // 读取任务输出（包括轮转文件）
+                let chunk = Self::read_task_output_in(&Self::get_tasks_dir(), &args.task_id)?;
+                let discarded_bytes = chunk
+                    .as_ref()
+                    .map(|chunk| chunk.discarded_bytes)
+                    .filter(|bytes| *bytes > 0);
+                let output = chunk.map(|chunk| {
+                    Shell::from_config().normalize_output(&String::from_utf8_lossy(&chunk.data))
+                });
+                let note = discarded_bytes
+                    .map(|bytes| {
+                        format!(
+                            " (the first {} bytes of output exceeded the size limit and were discarded)",
+                            bytes
+                        )
+                    })
+                    .unwrap_or_default();
 
                 // 检查是否需要等待
                 let block = args.block.unwrap_or(true);
@@ -160,9 +463,12 @@ impl Tool for TaskOutputTool {
                         error: None,
                         success: true,
                         message: format!(
-                            "Task '{}' is still in progress. Use block=false for non-blocking checks.",
-                            args.task_id
+                            "Task '{}' is still in progress{}. Use block=false for non-blocking checks.",
+                            args.task_id, note
                         ),
+                        stopped_by: None,
+                        matched_line: None,
+                        discarded_bytes,
                     })
                 } else {
                     // 返回当前状态
@@ -173,9 +479,12 @@ impl Tool for TaskOutputTool {
                         error: None,
                         success: true,
                         message: format!(
-                            "Retrieved output for task '{}': {}",
-                            args.task_id, meta.status
+                            "Retrieved output for task '{}': {}{}",
+                            args.task_id, meta.status, note
                         ),
+                        stopped_by: None,
+                        matched_line: None,
+                        discarded_bytes,
                     })
                 }
             }
@@ -188,6 +497,9 @@ impl Tool for TaskOutputTool {
                     error: Some(format!("Task '{}' not found", args.task_id)),
                     success: false,
                     message: format!("Task '{}' does not exist", args.task_id),
+                    stopped_by: None,
+                    matched_line: None,
+                    discarded_bytes: None,
                 })
             }
         }
@@ -217,6 +529,9 @@ impl Default for WrappedTaskOutputTool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::atomic_file::write_atomic;
+    use crate::config::TasksConfig;
+    use crate::task::output::{rotated_path, OutputWriter};
     use std::fs;
     use tempfile::TempDir;
 
@@ -243,6 +558,9 @@ mod tests {
             error: None,
             success: true,
             message: "Output retrieved successfully".to_string(),
+            stopped_by: None,
+            matched_line: None,
+            discarded_bytes: None,
         };
 
         let json = serde_json::to_string(&result).unwrap();
@@ -276,14 +594,246 @@ mod tests {
             status: "in_progress".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             output_file: None,
+            output_discarded_bytes: 0,
         };
 
         let meta_path = tasks_dir.join("test-id.json");
         let json = serde_json::to_string_pretty(&metadata).unwrap();
         fs::write(&meta_path, json).unwrap();
+        let read = TaskOutputTool::read_task_metadata_in(&tasks_dir, "test-id")
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.status, "in_progress");
+
+        // 写了一半的元数据文件移到一旁，按任务不存在处理
+        fs::write(&meta_path, r#"{"id": "test-"#).unwrap();
+        let read = TaskOutputTool::read_task_metadata_in(&tasks_dir, "test-id").unwrap();
+        assert!(read.is_none());
+        assert!(!meta_path.exists());
+    }
+
+    /// 写入任务元数据，模拟 TaskTool 创建的后台任务
+    fn write_meta(tasks_dir: &Path, id: &str, status: &str) {
+        write_meta_discarded(tasks_dir, id, status, 0);
+    }
+
+    fn write_meta_discarded(tasks_dir: &Path, id: &str, status: &str, discarded: u64) {
+        let metadata = TaskMetadata {
+            id: id.to_string(),
+            name: "Fake Task".to_string(),
+            description: "Fake background task".to_string(),
+            agent_type: "general-purpose".to_string(),
+            status: status.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            output_file: Some(
+                tasks_dir.join(format!("{}.output.txt", id)).to_string_lossy().to_string(),
+            ),
+            output_discarded_bytes: discarded,
+        };
+        let json = serde_json::to_string_pretty(&metadata).unwrap();
+        write_atomic(&tasks_dir.join(format!("{}.json", id)), json.as_bytes()).unwrap();
+    }
 
-        // 注意: 这个测试需要 TaskOutputTool 能够使用自定义的 tasks_dir
-        // 当前的实现使用硬编码的 ".oxide/tasks"
-        // 在完整实现中,应该通过依赖注入或配置来解决这个问题
+    /// 按固定间隔逐行写入输出的假后台任务，写完后标记为 completed
+    fn spawn_fake_task(
+        tasks_dir: &Path,
+        id: &str,
+        lines: Vec<&'static str>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        write_meta(tasks_dir, id, "in_progress");
+        let tasks_dir = tasks_dir.to_path_buf();
+        let id = id.to_string();
+        tokio::spawn(async move {
+            let path = tasks_dir.join(format!("{}.output.txt", id));
+            for line in lines {
+                tokio::time::sleep(interval).await;
+                let mut file = fs::OpenOptions::new().create(true).append(true).open(&path).unwrap();
+                std::io::Write::write_all(&mut file, format!("{}\n", line).as_bytes()).unwrap();
+            }
+            write_meta(&tasks_dir, &id, "completed");
+        })
+    }
+
+    fn follow_args(task_id: &str, pattern: Option<&str>, timeout_ms: u64) -> TaskOutputArgs {
+        TaskOutputArgs {
+            task_id: task_id.to_string(),
+            block: None,
+            timeout: Some(timeout_ms),
+            follow: true,
+            until_pattern: pattern.map(str::to_string),
+            caller: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_returns_when_pattern_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let writer = spawn_fake_task(
+            temp_dir.path(),
+            "server",
+            vec!["Compiling app", "Finished dev", "Listening on 127.0.0.1:8080", "GET /health"],
+            Duration::from_millis(30),
+        );
+
+        let result = TaskOutputTool::call_follow(
+            temp_dir.path(),
+            follow_args("server", Some(r"Listening on \S+"), 5_000),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.stopped_by, Some(FollowStop::Matched));
+        assert_eq!(result.matched_line.as_deref(), Some("Listening on 127.0.0.1:8080"));
+        let output = result.output.unwrap();
+        assert!(output.starts_with("Compiling app\nFinished dev\n"));
+        assert!(output.contains("Listening on"));
+        assert_eq!(result.status, "in_progress");
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_follow_times_out_without_blocking() {
+        let temp_dir = TempDir::new().unwrap();
+        let writer = spawn_fake_task(
+            temp_dir.path(),
+            "slow",
+            vec!["tick"; 20],
+            Duration::from_millis(50),
+        );
+
+        let started = Instant::now();
+        let result = TaskOutputTool::call_follow(
+            temp_dir.path(),
+            follow_args("slow", Some("never printed"), 200),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.stopped_by, Some(FollowStop::TimedOut));
+        assert!(result.matched_line.is_none());
+        assert!(started.elapsed() < Duration::from_millis(1_000));
+        writer.abort();
+    }
+
+    #[tokio::test]
+    async fn test_follow_resumes_per_caller() {
+        let temp_dir = TempDir::new().unwrap();
+        spawn_fake_task(
+            temp_dir.path(),
+            "build",
+            vec!["step 1", "ready", "step 2", "ready again", "done"],
+            Duration::from_millis(20),
+        )
+        .await
+        .unwrap();
+
+        let first = TaskOutputTool::call_follow(temp_dir.path(), follow_args("build", Some("^ready"), 1_000))
+            .await
+            .unwrap();
+        assert_eq!(first.matched_line.as_deref(), Some("ready"));
+
+        // 同一调用方从上次停下的位置继续
+        let second = TaskOutputTool::call_follow(temp_dir.path(), follow_args("build", Some("^ready"), 1_000))
+            .await
+            .unwrap();
+        assert_eq!(second.matched_line.as_deref(), Some("ready again"));
+        assert!(!second.output.as_deref().unwrap().contains("step 1"));
+
+        let third = TaskOutputTool::call_follow(temp_dir.path(), follow_args("build", None, 1_000))
+            .await
+            .unwrap();
+        assert_eq!(third.stopped_by, Some(FollowStop::TaskFinished));
+        assert_eq!(third.status, "completed");
+
+        // 其他调用方有独立的读取位置
+        let mut args = follow_args("build", Some("^ready"), 1_000);
+        args.caller = Some("reviewer".to_string());
+        let other = TaskOutputTool::call_follow(temp_dir.path(), args).await.unwrap();
+        assert_eq!(other.matched_line.as_deref(), Some("ready"));
+    }
+
+    #[tokio::test]
+    async fn test_follow_stops_when_task_finishes() {
+        let temp_dir = TempDir::new().unwrap();
+        let writer = spawn_fake_task(
+            temp_dir.path(),
+            "job",
+            vec!["working", "all done"],
+            Duration::from_millis(20),
+        );
+
+        let result = TaskOutputTool::call_follow(temp_dir.path(), follow_args("job", Some("error"), 5_000))
+            .await
+            .unwrap();
+
+        assert_eq!(result.stopped_by, Some(FollowStop::TaskFinished));
+        assert_eq!(result.output.as_deref(), Some("working\nall done"));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_follow_rejects_invalid_pattern() {
+        let temp_dir = TempDir::new().unwrap();
+        write_meta(temp_dir.path(), "job", "in_progress");
+
+        let result =
+            TaskOutputTool::call_follow(temp_dir.path(), follow_args("job", Some("(unclosed"), 100)).await;
+        assert!(matches!(result, Err(FileToolError::InvalidInput(_))));
+    }
+
+    /// 以 TaskManager 的方式写入第 `from..to` 行，轮转时更新元数据中的丢弃字节数
+    fn emit_lines(writer: &mut OutputWriter, tasks_dir: &Path, lines: std::ops::Range<usize>) {
+        for i in lines {
+            writer
+                .write(format!("line {:03}\n", i).as_bytes(), |_, discarded| {
+                    write_meta_discarded(tasks_dir, "gen", "in_progress", discarded)
+                })
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_continues_across_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        write_meta(dir, "gen", "in_progress");
+        let limits = TasksConfig {
+            max_output_bytes: 64,
+            max_output_rotations: 1,
+            ..TasksConfig::default()
+        };
+        let mut writer = OutputWriter::create(&dir.join("gen.output.txt"), &limits).unwrap();
+
+        emit_lines(&mut writer, dir, 0..5);
+        let first = TaskOutputTool::call_follow(dir, follow_args("gen", Some("line 002"), 1_000))
+            .await
+            .unwrap();
+        assert_eq!(first.matched_line.as_deref(), Some("line 002"));
+
+        // 文件轮转后，下一次读取仍从紧接着的一行开始
+        emit_lines(&mut writer, dir, 5..12);
+        assert!(rotated_path(&dir.join("gen.output.txt"), 1).exists());
+        let second = TaskOutputTool::call_follow(dir, follow_args("gen", Some("line 010"), 1_000))
+            .await
+            .unwrap();
+        assert_eq!(second.matched_line.as_deref(), Some("line 010"));
+        assert!(second.output.as_deref().unwrap().starts_with("line 003\n"));
+        assert!(second.discarded_bytes.is_none());
+
+        // 读取位置之后的输出被丢弃时跳过并报告
+        emit_lines(&mut writer, dir, 12..40);
+        let third = TaskOutputTool::call_follow(dir, follow_args("gen", Some("line 039"), 1_000))
+            .await
+            .unwrap();
+        assert_eq!(third.matched_line.as_deref(), Some("line 039"));
+        assert!(third.discarded_bytes.unwrap() > 0);
+        assert!(!third.output.as_deref().unwrap().contains("line 011"));
+        assert!(third.message.contains("discarded by rotation"));
+
+        // 一次性读取返回保留的全部输出并说明丢弃情况
+        let chunk = TaskOutputTool::read_task_output_in(dir, "gen").unwrap().unwrap();
+        assert!(chunk.discarded_bytes > 0);
+        assert!(String::from_utf8_lossy(&chunk.data).ends_with("line 039\n"));
     }
 }
@@ -0,0 +1,191 @@
+//! TaskStop 工具
+//!
+//! 停止后台进程任务：先请求进程组优雅退出，超时后强制结束。
+
+use super::FileToolError;
+use crate::task::manager::{get_task_manager, Termination, DEFAULT_STOP_GRACE};
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 优雅退出等待时间上限（毫秒）
+const MAX_GRACE_MS: u64 = 60_000;
+
+/// TaskStop 工具输入参数
+#[derive(Deserialize)]
+pub struct TaskStopArgs {
+    /// 任务 ID
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+
+    /// 发送终止信号后等待进程自行退出的时间（毫秒，默认 5000）
+    #[serde(default)]
+    pub grace_ms: Option<u64>,
+}
+
+/// TaskStop 工具输出
+#[derive(Serialize, Debug)]
+pub struct TaskStopOutput {
+    /// 任务 ID
+    pub task_id: String,
+
+    /// 是否成功
+    pub success: bool,
+
+    /// 终止方式（graceful / forced / not_running）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub termination: Option<Termination>,
+
+    /// 消息
+    pub message: String,
+}
+
+/// TaskStop 工具
+#[derive(Deserialize, Serialize)]
+pub struct TaskStopTool;
+
+impl Tool for TaskStopTool {
+    const NAME: &'static str = "task_stop";
+
+    type Error = FileToolError;
+    type Args = TaskStopArgs;
+    type Output = TaskStopOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "task_stop".to_string(),
+            description: r#"Stop a running background process task together with every child process it spawned.
+
+The process group first receives SIGTERM (CTRL_BREAK on Windows) so it can clean up. If anything is still running after grace_ms, the whole group is killed with SIGKILL (on Windows, every process in the task's job object is terminated). The task is then marked as cancelled and a final line is appended to its output.
+
+The result's termination field reports what happened:
+- graceful: the processes exited on their own within grace_ms
+- forced: they had to be killed
+- not_running: the process had already exited or was not started by this session"#.to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "taskId": {
+                        "type": "string",
+                        "description": "The ID of the task to stop"
+                    },
+                    "grace_ms": {
+                        "type": "integer",
+                        "description": "How long to wait for a graceful exit before killing (default: 5000, max: 60000)",
+                        "default": 5000,
+                        "minimum": 0,
+                        "maximum": 60000
+                    }
+                },
+                "required": ["taskId"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let grace = args
+            .grace_ms
+            .map(|ms| Duration::from_millis(ms.min(MAX_GRACE_MS)))
+            .unwrap_or(DEFAULT_STOP_GRACE);
+
+        let (success, termination, message) =
+            match get_task_manager().stop_task(&args.task_id, grace).await {
+                Ok(Some(Termination::Graceful)) => (
+                    true,
+                    Some(Termination::Graceful),
+                    format!("Task '{}' exited gracefully after SIGTERM", args.task_id),
+                ),
+                Ok(Some(Termination::Forced)) => (
+                    true,
+                    Some(Termination::Forced),
+                    format!(
+                        "Task '{}' did not exit within {}ms and was killed",
+                        args.task_id,
+                        grace.as_millis()
+                    ),
+                ),
+                Ok(Some(Termination::NotRunning)) => (
+                    true,
+                    Some(Termination::NotRunning),
+                    format!("Task '{}' has no running process in this session", args.task_id),
+                ),
+                Ok(None) => (
+                    false,
+                    None,
+                    format!("Task '{}' not found or is not a background process", args.task_id),
+                ),
+                Err(e) => (false, None, format!("Failed to stop task: {}", e)),
+            };
+
+        Ok(TaskStopOutput {
+            task_id: args.task_id,
+            success,
+            termination,
+            message,
+        })
+    }
+}
+
+/// TaskStop 工具包装器
+#[derive(Deserialize, Serialize)]
+pub struct WrappedTaskStopTool {
+    inner: TaskStopTool,
+}
+
+impl WrappedTaskStopTool {
+    pub fn new() -> Self {
+        Self {
+            inner: TaskStopTool,
+        }
+    }
+}
+
+impl Default for WrappedTaskStopTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tool for WrappedTaskStopTool {
+    const NAME: &'static str = "task_stop";
+
+    type Error = FileToolError;
+    type Args = TaskStopArgs;
+    type Output = TaskStopOutput;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.inner.call(args).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_stop_args_deserialization() {
+        let args: TaskStopArgs =
+            serde_json::from_str(r#"{"taskId": "task-1", "grace_ms": 1500}"#).unwrap();
+        assert_eq!(args.task_id, "task-1");
+        assert_eq!(args.grace_ms, Some(1500));
+
+        let args: TaskStopArgs = serde_json::from_str(r#"{"taskId": "task-1"}"#).unwrap();
+        assert!(args.grace_ms.is_none());
+    }
+
+    #[test]
+    fn test_task_stop_output_serialization() {
+        let output = TaskStopOutput {
+            task_id: "task-1".to_string(),
+            success: true,
+            termination: Some(Termination::Forced),
+            message: "killed".to_string(),
+        };
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["termination"], "forced");
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! 更新任务列表中的任务。
 
-use super::FileToolError;
+use super::{ErrorCode, FileToolError, ToolFailure};
 use crate::task::manager::{get_task_manager, TaskStatus};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -121,7 +121,11 @@ impl Tool for TaskUpdateTool {
 
 Status progresses: `pending` → `in_progress` → `completed`
 
-ONLY mark a task as completed when you have FULLY accomplished it."#.to_string(),
+ONLY mark a task as completed when you have FULLY accomplished it.
+
+## Errors
+
+- **NotFound**: no task has this ID; call task_list for the current IDs."#.to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -178,11 +182,12 @@ ONLY mark a task as completed when you have FULLY accomplished it."#.to_string()
         let task = match manager.get_task(&args.task_id) {
             Ok(Some(t)) => t,
             Ok(None) => {
-                return Ok(TaskUpdateOutput {
-                    task_id: args.task_id.clone(),
-                    success: false,
-                    message: format!("Task '{}' not found", args.task_id),
-                });
+                return Err(ToolFailure::new(
+                    ErrorCode::NotFound,
+                    format!("Task '{}' not found", args.task_id),
+                )
+                .with_suggestion("call task_list to see the existing task ids")
+                .into());
             }
             Err(e) => {
                 return Ok(TaskUpdateOutput {
@@ -0,0 +1,485 @@
+//! 工具参数的解析
+//!
+//! 模型偶尔把数字写成字符串（`"max_results": "50"`）、把可选参数写成 `null`。rig 直接用 serde
+//! 解析参数时，各工具的报错各不相同，也不指出是哪个字段，模型往往原样重试。所有工具的参数都经过
+//! [`parse_args`]：
+//!
+//! 1. 先按严格的 serde 解析；
+//! 2. 失败时按工具的参数 schema 做一次转换：数字字符串 → 数字，`"true"`/`"false"` → 布尔值，
+//!    可选参数的 `null` → 省略。转换后解析成功时，工具结果的 `argument_coercions` 列出做过的转换；
+//! 3. 仍失败时返回 `InvalidArgs`，指出字段路径和期望的类型，并附上按 schema 生成的参数示例。
+//!
+//! 经过 HITL 的工具在 [`MaybeHitlTool`](crate::agent::MaybeHitlTool) 中解析，
+//! 其他工具（计划模式、任务工具、`oxide mcp serve` 暴露的工具）用 [`LenientArgs`] 包装。
+
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use super::{ErrorCode, FileToolError, ToolFailure};
+
+/// 一次自动转换
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coercion {
+    /// 字段路径，如 `max_results`、`edits[0].replace_all`
+    pub path: String,
+    pub from: Value,
+    /// 为 `None` 时该字段被省略
+    pub to: Option<Value>,
+}
+
+impl fmt::Display for Coercion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.to {
+            Some(to) => write!(f, "{}: {} -> {}", self.path, self.from, to),
+            None => write!(f, "{}: {} -> omitted", self.path, self.from),
+        }
+    }
+}
+
+/// 解析后的参数和做过的转换
+#[derive(Debug)]
+pub struct ParsedArgs<A> {
+    pub args: A,
+    pub coercions: Vec<Coercion>,
+}
+
+/// 工具结果：参数经过转换时附带说明
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ArgsOutput<O> {
+    Plain(O),
+    Coerced {
+        result: O,
+        argument_coercions: Vec<String>,
+    },
+}
+
+/// 把转换说明附到工具结果上，没有转换时结果不变
+pub fn annotate<O>(output: O, coercions: &[Coercion]) -> ArgsOutput<O> {
+    if coercions.is_empty() {
+        return ArgsOutput::Plain(output);
+    }
+    ArgsOutput::Coerced {
+        result: output,
+        argument_coercions: coercions.iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// 按工具的参数 schema 解析参数（`tool` 用于错误信息）
+pub fn parse_args<A: DeserializeOwned>(
+    tool: &str,
+    raw: Value,
+    schema: &Value,
+) -> Result<ParsedArgs<A>, ToolFailure> {
+    if let Ok(args) = A::deserialize(&raw) {
+        return Ok(ParsedArgs {
+            args,
+            coercions: Vec::new(),
+        });
+    }
+    let mut value = raw;
+    let mut coercions = Vec::new();
+    coerce(&mut value, schema, "", &mut coercions);
+    match A::deserialize(&value) {
+        Ok(args) => Ok(ParsedArgs { args, coercions }),
+        Err(e) => Err(invalid_args(tool, &value, schema, &e)),
+    }
+}
+
+/// 解析工具的参数，只有严格解析失败时才读取工具的 schema
+pub async fn parse_for<T: Tool>(tool: &T, raw: Value) -> Result<ParsedArgs<T::Args>, ToolFailure> {
+    if let Ok(args) = T::Args::deserialize(&raw) {
+        return Ok(ParsedArgs {
+            args,
+            coercions: Vec::new(),
+        });
+    }
+    let ToolDefinition { parameters, .. } = tool.definition(String::new()).await;
+    parse_args(T::NAME, raw, &parameters)
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_required(schema: &Value, key: &str) -> bool {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .is_some_and(|required| required.iter().any(|name| name == key))
+}
+
+/// 按 schema 转换 `value`，记录每一处转换
+fn coerce(value: &mut Value, schema: &Value, path: &str, coercions: &mut Vec<Coercion>) {
+    match value {
+        Value::Object(fields) => {
+            let properties = schema.get("properties");
+            let keys: Vec<String> = fields.keys().cloned().collect();
+            for key in keys {
+                let field_path = join_path(path, &key);
+                if fields[&key].is_null() && !is_required(schema, &key) {
+                    fields.remove(&key);
+                    coercions.push(Coercion {
+                        path: field_path,
+                        from: Value::Null,
+                        to: None,
+                    });
+                } else if let Some(field_schema) = properties.and_then(|p| p.get(&key)) {
+                    if let Some(field) = fields.get_mut(&key) {
+                        coerce(field, field_schema, &field_path, coercions);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter_mut().enumerate() {
+                    let item_path = format!("{}[{}]", path, index);
+                    coerce(item, item_schema, &item_path, coercions);
+                }
+            }
+        }
+        Value::String(text) => {
+            if let Some(coerced) = coerce_scalar(text, schema) {
+                let from = std::mem::replace(value, coerced.clone());
+                coercions.push(Coercion {
+                    path: path.to_string(),
+                    from,
+                    to: Some(coerced),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// schema 不接受字符串时，把数字字符串和 `"true"`/`"false"` 转换为对应的类型
+fn coerce_scalar(text: &str, schema: &Value) -> Option<Value> {
+    let types = schema_types(schema);
+    if types.contains(&"string") {
+        return None;
+    }
+    let text = text.trim();
+    types.iter().find_map(|kind| match *kind {
+        "integer" => text
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| text.parse::<u64>().map(Value::from))
+            .ok(),
+        "number" => text.parse::<i64>().map(Value::from).ok().or_else(|| {
+            text.parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+        }),
+        "boolean" if text.eq_ignore_ascii_case("true") => Some(Value::Bool(true)),
+        "boolean" if text.eq_ignore_ascii_case("false") => Some(Value::Bool(false)),
+        _ => None,
+    })
+}
+
+/// 字段的 schema（按 JSON Pointer 的各段查找）
+fn schema_at<'a>(schema: &'a Value, segments: &[String]) -> Option<&'a Value> {
+    segments.iter().try_fold(schema, |schema, segment| {
+        schema
+            .get("properties")
+            .and_then(|properties| properties.get(segment))
+            .or_else(|| {
+                segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|_| schema.get("items"))
+            })
+    })
+}
+
+/// JSON Pointer 的各段显示为 `edits[0].old_string`
+fn display_path(segments: &[String]) -> String {
+    segments.iter().fold(String::new(), |path, segment| {
+        if segment.parse::<usize>().is_ok() {
+            format!("{}[{}]", path, segment)
+        } else {
+            join_path(&path, segment)
+        }
+    })
+}
+
+/// 期望的类型，如 `integer`、`one of "GET", "POST"`
+fn describe_expected(schema: Option<&Value>) -> String {
+    let Some(schema) = schema else {
+        return "a valid value".to_string();
+    };
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        let options: Vec<String> = options.iter().map(ToString::to_string).collect();
+        return format!("one of {}", options.join(", "));
+    }
+    match schema_types(schema).as_slice() {
+        [] => "a valid value".to_string(),
+        types => types.join(" or "),
+    }
+}
+
+/// 示例中的占位值：默认值、第一个可选值或按类型生成的值
+fn placeholder(name: &str, schema: &Value) -> Value {
+    if let Some(default) = schema.get("default") {
+        return default.clone();
+    }
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|options| options.first())
+    {
+        return first.clone();
+    }
+    match schema_types(schema).first().copied() {
+        Some("integer") | Some("number") => {
+            schema.get("minimum").cloned().unwrap_or(Value::from(1))
+        }
+        Some("boolean") => Value::Bool(true),
+        Some("array") => Value::Array(Vec::new()),
+        Some("object") => Value::Object(Default::default()),
+        _ => Value::String(format!("<{}>", name)),
+    }
+}
+
+/// 参数示例：必填参数加上出错的参数
+fn example(schema: &Value, field: Option<&str>) -> Value {
+    let mut example = serde_json::Map::new();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Value::Object(example);
+    };
+    for (name, property) in properties {
+        if is_required(schema, name) || field == Some(name.as_str()) {
+            example.insert(name.clone(), placeholder(name, property));
+        }
+    }
+    Value::Object(example)
+}
+
+/// 参数校验的第一个错误：字段的 JSON Pointer 各段，以及原因（缺少必填字段时为 `None`）
+fn first_schema_error(value: &Value, schema: &Value) -> Option<(Vec<String>, Option<String>)> {
+    let validator = jsonschema::validator_for(schema).ok()?;
+    let error = validator.iter_errors(value).next()?;
+    let mut segments: Vec<String> = error
+        .instance_path
+        .to_string()
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    if let jsonschema::error::ValidationErrorKind::Required { property } = &error.kind {
+        segments.push(property.as_str().unwrap_or_default().to_string());
+        return Some((segments, None));
+    }
+    Some((segments, Some(error.to_string())))
+}
+
+/// 解析失败时返回给模型的错误：用 schema 校验找出字段和原因，schema 没有发现问题时用 serde 的错误
+fn invalid_args(
+    tool: &str,
+    value: &Value,
+    schema: &Value,
+    error: &serde_json::Error,
+) -> ToolFailure {
+    let (segments, problem) =
+        first_schema_error(value, schema).unwrap_or_else(|| (Vec::new(), Some(error.to_string())));
+
+    let field = display_path(&segments);
+    let expected = describe_expected(schema_at(schema, &segments));
+    let example = example(schema, segments.first().map(String::as_str));
+    let message = match (field.as_str(), problem) {
+        (_, None) => format!(
+            "Invalid arguments for {}: missing required field `{}` ({})",
+            tool, field, expected
+        ),
+        ("", Some(problem)) => format!("Invalid arguments for {}: {}", tool, problem),
+        (_, Some(problem)) => format!(
+            "Invalid arguments for {}: field `{}`: {}",
+            tool, field, problem
+        ),
+    };
+    ToolFailure::new(ErrorCode::InvalidArgs, message)
+        .with_details(serde_json::json!({
+            "field": if field.is_empty() { "(root)" } else { field.as_str() },
+            "expected": expected,
+            "example": example,
+        }))
+        .with_suggestion(format!(
+            "call {} again with arguments shaped like {}",
+            tool, example
+        ))
+}
+
+/// 在调用前按 [`parse_args`] 解析参数的工具包装（不经过 HITL 的工具使用）
+#[derive(Deserialize, Serialize)]
+pub struct LenientArgs<T> {
+    inner: T,
+}
+
+impl<T> LenientArgs<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Tool> Tool for LenientArgs<T>
+where
+    T::Error: From<FileToolError>,
+{
+    const NAME: &'static str = T::NAME;
+
+    type Error = T::Error;
+    type Args = Value;
+    type Output = ArgsOutput<T::Output>;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let parsed = parse_for(&self.inner, args)
+            .await
+            .map_err(FileToolError::from)?;
+        let output = self.inner.call(parsed.args).await?;
+        Ok(annotate(output, &parsed.coercions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::grep_search::{GrepSearchArgs, GrepSearchTool};
+    use crate::tools::http_request::{HttpRequestArgs, HttpRequestTool};
+    use crate::tools::read_file::{ReadFileArgs, ReadFileTool};
+    use crate::tools::settings::ToolSettings;
+    use serde_json::json;
+
+    async fn schema<T: Tool>(tool: &T) -> Value {
+        tool.definition(String::new()).await.parameters
+    }
+
+    fn coerced<A: DeserializeOwned>(raw: Value, schema: &Value) -> (A, Vec<String>) {
+        let parsed = parse_args::<A>("test", raw, schema).unwrap();
+        let coercions = parsed.coercions.iter().map(ToString::to_string).collect();
+        (parsed.args, coercions)
+    }
+
+    #[tokio::test]
+    async fn test_coercions_are_applied_and_reported() {
+        let settings = ToolSettings::default();
+        let grep = schema(&GrepSearchTool::new(&settings)).await;
+        let read = schema(&ReadFileTool::new(&settings)).await;
+        let http = schema(&HttpRequestTool).await;
+
+        let (args, notes) = coerced::<GrepSearchArgs>(
+            json!({ "root_path": ".", "query": "fn main", "max_results": "50", "fixed_strings": "true" }),
+            &grep,
+        );
+        assert_eq!(args.max_results, Some(50));
+        assert!(args.fixed_strings);
+        assert_eq!(
+            notes,
+            [
+                "fixed_strings: \"true\" -> true",
+                "max_results: \"50\" -> 50"
+            ]
+        );
+
+        let (args, notes) = coerced::<GrepSearchArgs>(
+            json!({ "root_path": ".", "query": "todo", "multiline": null }),
+            &grep,
+        );
+        assert!(!args.multiline);
+        assert_eq!(notes, ["multiline: null -> omitted"]);
+
+        let (args, notes) = coerced::<ReadFileArgs>(
+            json!({ "file_path": "src/main.rs", "offset": " 10 ", "limit": null }),
+            &read,
+        );
+        assert_eq!((args.offset, args.limit), (Some(10), None));
+        assert_eq!(notes, ["limit: null -> omitted", "offset: \" 10 \" -> 10"]);
+
+        let (args, notes) = coerced::<HttpRequestArgs>(
+            json!({ "url": "https://docs.rs", "timeout_ms": "5000", "force": "FALSE" }),
+            &http,
+        );
+        assert_eq!(args.timeout_ms, Some(5000));
+        assert!(!args.force);
+        assert_eq!(
+            notes,
+            ["force: \"FALSE\" -> false", "timeout_ms: \"5000\" -> 5000"]
+        );
+
+        // 严格解析成功时不做任何转换，结果原样返回
+        let (_, notes) =
+            coerced::<ReadFileArgs>(json!({ "file_path": "src/main.rs", "limit": 20 }), &read);
+        assert!(notes.is_empty());
+        assert_eq!(annotate("ok", &[]), ArgsOutput::Plain("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_args_name_the_field_and_show_an_example() {
+        let settings = ToolSettings::default();
+        let grep = schema(&GrepSearchTool::new(&settings)).await;
+        let read = schema(&ReadFileTool::new(&settings)).await;
+
+        let cases = [
+            (
+                parse_args::<GrepSearchArgs>(
+                    "grep_search",
+                    json!({ "root_path": ".", "query": "x", "max_results": "many" }),
+                    &grep,
+                )
+                .err(),
+                "max_results",
+                "integer",
+                "field `max_results`: \"many\" is not of type \"integer\"",
+            ),
+            (
+                parse_args::<GrepSearchArgs>("grep_search", json!({ "root_path": "." }), &grep)
+                    .err(),
+                "query",
+                "string",
+                "missing required field `query` (string)",
+            ),
+            (
+                parse_args::<ReadFileArgs>(
+                    "read_file",
+                    json!({ "file_path": "a.rs", "offset": "-3" }),
+                    &read,
+                )
+                .err(),
+                "offset",
+                "integer",
+                "field `offset`: -3 is less than the minimum of 1",
+            ),
+        ];
+        for (failure, field, expected, message) in cases {
+            let failure = failure.expect("arguments should be rejected");
+            assert_eq!(failure.code, ErrorCode::InvalidArgs);
+            assert!(failure.message.ends_with(message), "{}", failure.message);
+            let details = failure.details.unwrap();
+            assert_eq!(details["field"], field);
+            assert_eq!(details["expected"], expected);
+            // 示例包含必填参数和出错的参数
+            let example = &details["example"];
+            assert!(example.get(field).is_some(), "{}", example);
+            assert!(failure.suggestion.unwrap().contains(&example.to_string()));
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! 覆盖未提交修改前的确认
+//!
+//! 用户在编辑器里改了很久还没提交的文件，如果 agent 没读过就改写它，这些修改就丢了。
+//! write_file 和 edit_file 写入前检查目标文件：工作区中有 git 无法找回的修改（未暂存的修改，
+//! 或未跟踪的文件），并且本次会话中 agent 没有读取或写入过它时，显示这些修改的 diff，
+//! 由用户选择继续写入、让 agent 先重新读取，或者跳过。
+//!
+//! 只暂存、之后没有再修改的文件不询问：暂存区中有一份副本，可以用 `git restore` 找回。
+//! 权限规则的 allow、信任分数的自动批准和关闭编辑预览都不会跳过这项检查，
+//! 只有 `[permissions] allow_overwrite_uncommitted = true` 关闭它。
+
+use colored::*;
+use git2::{Repository, Status};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::ask_user_question::{ask_question_interactive, Question, QuestionOption};
+use super::diff::{unified_diff, UnifiedDiff};
+use super::{ErrorCode, FileToolError, ToolFailure};
+use crate::config::ConfigLoader;
+
+/// 确认时最多显示的 diff 行数
+const MAX_PREVIEW_LINES: usize = 40;
+
+/// 未提交修改的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UncommittedKind {
+    /// 已跟踪的文件有未暂存的修改
+    Modified,
+    /// 未跟踪的文件
+    Untracked,
+}
+
+/// 写入会覆盖的未提交修改
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncommittedEdits {
+    pub kind: UncommittedKind,
+    /// 相对暂存区（没有时相对 HEAD）的 diff，未跟踪的文件为整个文件
+    pub diff: UnifiedDiff,
+}
+
+/// 用户的选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwriteChoice {
+    /// 继续写入
+    Proceed,
+    /// 不写入，让 agent 先重新读取文件
+    ReadFirst,
+    /// 不写入
+    Skip,
+}
+
+/// 记录本次会话中 agent 读取或写入过的文件，判断写入前是否需要确认
+#[derive(Debug, Default)]
+pub struct OverwriteGuard {
+    /// 为 true 时不检查（`[permissions] allow_overwrite_uncommitted`）
+    allow: bool,
+    seen: Mutex<HashSet<PathBuf>>,
+}
+
+impl OverwriteGuard {
+    pub fn new(allow: bool) -> Self {
+        Self {
+            allow,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// agent 读取或写入了文件，之后写入它不再确认
+    pub fn record_seen(&self, path: &Path) {
+        self.seen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(canonical(path));
+    }
+
+    /// 写入前需要用户确认时返回会被覆盖的修改
+    pub fn check(&self, path: &Path) -> Option<UncommittedEdits> {
+        if self.allow {
+            return None;
+        }
+        let seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.contains(&canonical(path)) {
+            return None;
+        }
+        drop(seen);
+        uncommitted_edits(path)
+    }
+
+    /// 需要时询问用户；用户选择继续时返回 `Ok`，之后写入同一文件不再询问
+    pub fn confirm(&self, file_path: &str) -> Result<(), FileToolError> {
+        let path = Path::new(file_path);
+        let Some(edits) = self.check(path) else {
+            return Ok(());
+        };
+        match ask_overwrite(file_path, &edits)? {
+            OverwriteChoice::Proceed => {
+                self.record_seen(path);
+                Ok(())
+            }
+            OverwriteChoice::ReadFirst => Err(ToolFailure::new(
+                ErrorCode::Stale,
+                format!(
+                    "{} has uncommitted changes by the user that you have not read; the file was not modified",
+                    file_path
+                ),
+            )
+            .with_suggestion("read the file again with read_file, then make the change on top of the user's edits")
+            .into()),
+            OverwriteChoice::Skip => Err(ToolFailure::new(
+                ErrorCode::Blocked,
+                format!(
+                    "The user chose not to overwrite the uncommitted changes in {}",
+                    file_path
+                ),
+            )
+            .with_suggestion("do not modify this file; ask the user how to proceed")
+            .into()),
+        }
+    }
+}
+
+static OVERWRITE_GUARD: Lazy<OverwriteGuard> = Lazy::new(|| {
+    let allow = ConfigLoader::new()
+        .load_toml_layers()
+        .ok()
+        .and_then(|config| config.permissions)
+        .and_then(|permissions| permissions.allow_overwrite_uncommitted)
+        .unwrap_or(false);
+    OverwriteGuard::new(allow)
+});
+
+/// 全局的覆盖检查（从全局/项目配置加载）
+pub fn get_overwrite_guard() -> &'static OverwriteGuard {
+    &OVERWRITE_GUARD
+}
+
+/// 文件中 git 无法找回的修改：未暂存的修改或未跟踪的文件
+///
+/// 文件不存在、不在 git 仓库中、被忽略、没有修改或只有已暂存的修改时返回 `None`。
+pub fn uncommitted_edits(path: &Path) -> Option<UncommittedEdits> {
+    if !path.is_file() {
+        return None;
+    }
+    let path = canonical(path);
+    let repo = Repository::discover(path.parent()?).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let relative = path.strip_prefix(&workdir).ok()?;
+    let status = repo.status_file(relative).ok()?;
+
+    let kind = if status.contains(Status::WT_NEW) {
+        UncommittedKind::Untracked
+    } else if status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE | Status::WT_RENAMED) {
+        UncommittedKind::Modified
+    } else {
+        return None;
+    };
+
+    let current = String::from_utf8_lossy(&fs::read(&path).ok()?).into_owned();
+    let original = match kind {
+        UncommittedKind::Untracked => String::new(),
+        UncommittedKind::Modified => committed_content(&repo, relative).unwrap_or_default(),
+    };
+    let label = relative.to_string_lossy().replace('\\', "/");
+    Some(UncommittedEdits {
+        kind,
+        diff: unified_diff(
+            &original,
+            &current,
+            &format!("a/{}", label),
+            &format!("b/{}", label),
+            3,
+        ),
+    })
+}
+
+/// 暂存区中的版本，不在暂存区时取 HEAD 中的版本
+fn committed_content(repo: &Repository, relative: &Path) -> Option<String> {
+    let id = match repo.index().ok()?.get_path(relative, 0) {
+        Some(entry) => entry.id,
+        None => repo
+            .head()
+            .ok()?
+            .peel_to_tree()
+            .ok()?
+            .get_path(relative)
+            .ok()?
+            .id(),
+    };
+    let blob = repo.find_blob(id).ok()?;
+    Some(String::from_utf8_lossy(blob.content()).into_owned())
+}
+
+/// 显示会被覆盖的修改并询问用户
+fn ask_overwrite(
+    file_path: &str,
+    edits: &UncommittedEdits,
+) -> Result<OverwriteChoice, FileToolError> {
+    let warning = match edits.kind {
+        UncommittedKind::Modified => t!(
+            "edit.uncommitted.modified",
+            path = file_path,
+            added = edits.diff.lines_added,
+            removed = edits.diff.lines_removed
+        ),
+        UncommittedKind::Untracked => t!("edit.uncommitted.untracked", path = file_path),
+    };
+    println!();
+    println!("{} {}", "⚠️".bright_yellow(), warning.bright_yellow());
+    print_preview(&edits.diff.text);
+
+    let choices = [
+        (
+            OverwriteChoice::Proceed,
+            t!("edit.uncommitted.proceed"),
+            t!("edit.uncommitted.proceed_description"),
+        ),
+        (
+            OverwriteChoice::ReadFirst,
+            t!("edit.uncommitted.read_first"),
+            t!("edit.uncommitted.read_first_description"),
+        ),
+        (
+            OverwriteChoice::Skip,
+            t!("edit.uncommitted.skip"),
+            t!("edit.uncommitted.skip_description"),
+        ),
+    ];
+    let question = Question {
+        question: t!("edit.uncommitted.question").to_string(),
+        header: "".to_string(),
+        options: choices
+            .iter()
+            .map(|(_, label, description)| QuestionOption {
+                label: label.to_string(),
+                description: description.to_string(),
+            })
+            .collect(),
+        multi_select: false,
+        allow_other: false,
+    };
+
+    let answer = ask_question_interactive(&question)?;
+    let selected = answer.selected.as_str().unwrap_or_default();
+    Ok(choices
+        .iter()
+        .find(|(_, label, _)| *label == selected)
+        .map(|(choice, _, _)| *choice)
+        .unwrap_or(OverwriteChoice::Skip))
+}
+
+fn print_preview(diff: &str) {
+    let lines: Vec<&str> = diff.lines().collect();
+    for line in lines.iter().take(MAX_PREVIEW_LINES) {
+        let line = if line.starts_with("+++") || line.starts_with("---") {
+            line.bold()
+        } else if line.starts_with('+') {
+            line.green()
+        } else if line.starts_with('-') {
+            line.red()
+        } else if line.starts_with("@@") {
+            line.cyan()
+        } else {
+            line.dimmed()
+        };
+        println!("  {}", line);
+    }
+    if lines.len() > MAX_PREVIEW_LINES {
+        println!(
+            "  {}",
+            t!(
+                "edit.uncommitted.more_lines",
+                count = lines.len() - MAX_PREVIEW_LINES
+            )
+            .dimmed()
+        );
+    }
+    println!();
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn commit_all(repo: &Repository) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "commit",
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    fn stage(repo: &Repository, name: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn test_which_changes_need_confirmation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let repo = Repository::init(root).unwrap();
+        for name in ["clean.rs", "unstaged.rs", "staged.rs", "both.rs"] {
+            fs::write(root.join(name), "fn a() {}\n").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        commit_all(&repo);
+
+        fs::write(root.join("unstaged.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        fs::write(root.join("staged.rs"), "fn staged() {}\n").unwrap();
+        stage(&repo, "staged.rs");
+        fs::write(root.join("both.rs"), "fn staged() {}\n").unwrap();
+        stage(&repo, "both.rs");
+        fs::write(root.join("both.rs"), "fn staged() {}\nfn later() {}\n").unwrap();
+        fs::write(root.join("untracked.rs"), "fn new() {}\n").unwrap();
+        fs::write(root.join("build.log"), "ignored\n").unwrap();
+
+        let guard = OverwriteGuard::new(false);
+        let kind = |name: &str| guard.check(&root.join(name)).map(|edits| edits.kind);
+        assert_eq!(kind("clean.rs"), None);
+        assert_eq!(kind("staged.rs"), None);
+        assert_eq!(kind("build.log"), None);
+        assert_eq!(kind("missing.rs"), None);
+        assert_eq!(kind("unstaged.rs"), Some(UncommittedKind::Modified));
+        assert_eq!(kind("both.rs"), Some(UncommittedKind::Modified));
+        assert_eq!(kind("untracked.rs"), Some(UncommittedKind::Untracked));
+
+        // diff 相对暂存区：只包含暂存之后的修改
+        let edits = guard.check(&root.join("both.rs")).unwrap();
+        assert_eq!((edits.diff.lines_added, edits.diff.lines_removed), (1, 0));
+        assert!(edits.diff.text.contains("+fn later() {}"));
+        let edits = guard.check(&root.join("unstaged.rs")).unwrap();
+        assert!(edits
+            .diff
+            .text
+            .starts_with("--- a/unstaged.rs\n+++ b/unstaged.rs\n"));
+
+        // 读取过的文件不再询问
+        guard.record_seen(&root.join("unstaged.rs"));
+        assert_eq!(kind("unstaged.rs"), None);
+        assert_eq!(kind("untracked.rs"), Some(UncommittedKind::Untracked));
+
+        // allow_overwrite_uncommitted = true 时不检查
+        let guard = OverwriteGuard::new(true);
+        assert_eq!(guard.check(&root.join("untracked.rs")), None);
+    }
+
+    #[test]
+    fn test_files_outside_a_repository_are_not_checked() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "draft\n").unwrap();
+        assert_eq!(uncommitted_edits(&path), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! 工具输出的详细显示
+//!
+//! 工具结果默认折叠为一行摘要（如 `Command succeeded ... +120 lines output`）。
+//! `[render] verbose_tools = true` 时在摘要下方显示完整输出；处理请求期间按 Ctrl+O 随时切换，
+//! 只影响之后的结果，不写回配置。本轮折叠的输出保留到下一轮开始，`/expand all` 重新完整显示。
+//!
+//! `-p` 和纯文本模式（stdout 不是终端）下工具调用不在 stdout 显示（见 [`VerboseTools::hide`]）：
+//! 前者的 stdout 只留给回答，后者由 `[tool] …` 状态行代替。工具的显示都通过 `tool_println!` 输出；
+//! 提问（`interaction::interact`）期间的输出不受影响。
+
+use crate::config::RenderConfig;
+use colored::*;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// 一次折叠显示的工具输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollapsedOutput {
+    /// 工具调用的标题，如 `Exec(cargo test)`
+    pub label: String,
+    pub text: String,
+}
+
+/// 工具调用的显示（标题、摘要行、预览和提示），见 [`VerboseTools::println`]
+macro_rules! tool_println {
+    () => {
+        $crate::tools::verbose_output::verbose_tools().println(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::tools::verbose_output::verbose_tools().println(format_args!($($arg)*))
+    };
+}
+
+/// 工具输出的显示状态
+pub struct VerboseTools {
+    verbose: AtomicBool,
+    hidden: AtomicBool,
+    collapsed: Mutex<Vec<CollapsedOutput>>,
+    /// 工具调用显示的去向，默认 stdout
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+static VERBOSE_TOOLS: Lazy<VerboseTools> =
+    Lazy::new(|| VerboseTools::new(RenderConfig::load().verbose_tools));
+
+/// 当前会话的工具输出显示状态（初始值取自 `[render] verbose_tools`）
+pub fn verbose_tools() -> &'static VerboseTools {
+    &VERBOSE_TOOLS
+}
+
+impl VerboseTools {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose: AtomicBool::new(verbose),
+            hidden: AtomicBool::new(false),
+            collapsed: Mutex::new(Vec::new()),
+            out: Mutex::new(Box::new(io::stdout())),
+        }
+    }
+
+    /// 工具调用的显示写到 `out`
+    #[cfg(test)]
+    pub(crate) fn redirect(&self, out: Box<dyn Write + Send>) {
+        *self.out.lock().unwrap_or_else(|e| e.into_inner()) = out;
+    }
+
+    fn collapsed_outputs(&self) -> MutexGuard<'_, Vec<CollapsedOutput>> {
+        self.collapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_verbose(&self) -> bool {
+        self.verbose.load(Ordering::Relaxed)
+    }
+
+    /// 不再在 stdout 显示工具调用（`-p` 和纯文本模式，启动时调用）
+    pub fn hide(&self) {
+        self.hidden.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.load(Ordering::Relaxed)
+    }
+
+    /// 输出一行工具调用的显示，[`hide`](Self::hide) 之后不输出
+    pub fn println(&self, args: fmt::Arguments<'_>) {
+        self.print(format_args!("{}\n", args));
+    }
+
+    fn print(&self, args: fmt::Arguments<'_>) {
+        if self.is_hidden() {
+            return;
+        }
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = out.write_fmt(args);
+        let _ = out.flush();
+    }
+
+    /// 切换详细显示，返回切换后的状态
+    pub fn toggle(&self) -> bool {
+        !self.verbose.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    /// 新一轮开始：丢弃上一轮折叠的输出
+    pub fn begin_turn(&self) {
+        self.collapsed_outputs().clear();
+    }
+
+    /// 本轮折叠的输出，按显示顺序
+    pub fn collapsed(&self) -> Vec<CollapsedOutput> {
+        self.collapsed_outputs().clone()
+    }
+
+    /// 详细显示时返回要打印的完整输出，否则记下折叠的输出并返回 `None`
+    pub fn expand_or_collapse(&self, label: &str, text: &str) -> Option<String> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        if self.is_verbose() {
+            return Some(render(text));
+        }
+        self.collapsed_outputs().push(CollapsedOutput {
+            label: label.to_string(),
+            text: text.to_string(),
+        });
+        None
+    }
+
+    /// 在工具的摘要行下方显示完整输出（详细显示关闭时只记录）
+    pub fn show(&self, label: &str, text: &str) {
+        if let Some(expanded) = self.expand_or_collapse(label, text) {
+            self.print(format_args!("{}", expanded));
+        }
+    }
+}
+
+/// 完整输出的显示形式：每行缩进并加竖线
+pub fn render(text: &str) -> String {
+    text.trim_end_matches('\n')
+        .lines()
+        .map(|line| format!("  {} {}\n", "│".dimmed(), line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_switches_later_results() {
+        colored::control::set_override(false);
+        let tools = VerboseTools::new(false);
+        assert_eq!(tools.expand_or_collapse("Exec(ls)", "a\nb\n"), None);
+
+        assert!(tools.toggle());
+        assert_eq!(
+            tools.expand_or_collapse("Exec(cat x)", "full\noutput\n"),
+            Some("  │ full\n  │ output\n".to_string())
+        );
+        assert!(!tools.toggle());
+        assert_eq!(tools.expand_or_collapse("Read(y)", "c"), None);
+        assert_eq!(tools.expand_or_collapse("Exec(true)", " \n"), None);
+
+        // 只有折叠显示的输出被记录，供 /expand all 重新显示
+        let labels: Vec<String> = tools.collapsed().into_iter().map(|c| c.label).collect();
+        assert_eq!(labels, vec!["Exec(ls)", "Read(y)"]);
+        tools.begin_turn();
+        assert!(tools.collapsed().is_empty());
+        assert!(!tools.is_verbose());
+    }
+}
@@ -0,0 +1,370 @@
+//! 联网工具的磁盘缓存
+//!
+//! 同一会话（以及之后的会话）中重复获取同一个页面时直接返回缓存的结果，不再联网。
+//! 缓存位于 `~/.oxide/cache/web/`，每条一个 JSON 文件，文件名是规范化 URL 的 SHA-256：
+//! scheme 和主机小写、去掉默认端口和片段、查询参数按名称排序，
+//! 所以 `?b=2&a=1` 与 `?a=1&b=2` 命中同一条。
+//!
+//! 每条记录写入时的有效期（`[tools.web] cache_ttl_hours`，默认 24 小时，0 表示不使用缓存），
+//! 过期的条目不再返回；缓存总大小超过 `[tools.web] cache_max_mb` 时按最近访问时间淘汰最久未用的条目。
+//! 写入都通过 [`write_atomic`]，并发的进程不会读到写了一半的文件。
+
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::atomic_file::write_atomic;
+
+/// 缓存条目默认的有效期（小时）
+pub const DEFAULT_TTL_HOURS: u64 = 24;
+
+/// 缓存默认的总大小上限（MB）
+pub const DEFAULT_MAX_MB: u64 = 50;
+
+static CACHE: Lazy<RwLock<WebCache>> = Lazy::new(|| RwLock::new(WebCache::default()));
+
+/// 磁盘上的一条缓存
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<T> {
+    /// 规范化后的 URL
+    key: String,
+    /// 获取时间（Unix 秒）
+    fetched_at: u64,
+    /// 最近一次命中的时间（Unix 秒），用于淘汰
+    accessed_at: u64,
+    /// 写入时的有效期（秒）
+    ttl_secs: u64,
+    value: T,
+}
+
+/// 命中的缓存
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHit<T> {
+    pub value: T,
+    /// 距离获取时的时长
+    pub age: Duration,
+}
+
+/// 缓存目录的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub expired: usize,
+    pub bytes: u64,
+}
+
+/// 联网工具的磁盘缓存
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebCache {
+    /// 缓存目录，没有主目录时为 `None`（不使用缓存）
+    pub dir: Option<PathBuf>,
+    /// 新条目的有效期，为 0 时不使用缓存
+    pub ttl: Duration,
+    /// 缓存文件的总大小上限（字节）
+    pub max_bytes: u64,
+}
+
+impl Default for WebCache {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_secs(DEFAULT_TTL_HOURS * 3600),
+            DEFAULT_MAX_MB * 1024 * 1024,
+        )
+    }
+}
+
+impl WebCache {
+    pub fn new(ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            dir: default_dir(),
+            ttl,
+            max_bytes,
+        }
+    }
+
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// 是否使用缓存
+    pub fn enabled(&self) -> bool {
+        self.dir.is_some() && !self.ttl.is_zero()
+    }
+
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        let digest = Sha256::digest(key.as_bytes());
+        Some(self.dir.as_ref()?.join(format!("{:x}.json", digest)))
+    }
+
+    /// 读取未过期的缓存，命中时更新访问时间
+    pub fn get<T: Serialize + DeserializeOwned>(&self, key: &str) -> Option<CacheHit<T>> {
+        self.get_at(key, unix_now())
+    }
+
+    fn get_at<T: Serialize + DeserializeOwned>(&self, key: &str, now: u64) -> Option<CacheHit<T>> {
+        if !self.enabled() {
+            return None;
+        }
+        let path = self.entry_path(key)?;
+        let mut entry: Entry<T> = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+        // 文件名只是摘要，再核对一次原始键
+        if entry.key != key {
+            return None;
+        }
+        let age = now.saturating_sub(entry.fetched_at);
+        if age >= entry.ttl_secs {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        entry.accessed_at = now;
+        if let Ok(bytes) = serde_json::to_vec(&entry) {
+            let _ = write_atomic(&path, &bytes);
+        }
+        Some(CacheHit {
+            value: entry.value,
+            age: Duration::from_secs(age),
+        })
+    }
+
+    /// 写入缓存，超过总大小上限时淘汰最久未访问的条目
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) -> io::Result<()> {
+        self.put_at(key, value, unix_now())
+    }
+
+    fn put_at<T: Serialize>(&self, key: &str, value: &T, now: u64) -> io::Result<()> {
+        let (Some(dir), Some(path)) = (self.dir.as_ref(), self.entry_path(key)) else {
+            return Ok(());
+        };
+        if self.ttl.is_zero() {
+            return Ok(());
+        }
+        let entry = Entry {
+            key: key.to_string(),
+            fetched_at: now,
+            accessed_at: now,
+            ttl_secs: self.ttl.as_secs().max(1),
+            value,
+        };
+        let bytes = serde_json::to_vec(&entry).map_err(io::Error::other)?;
+        fs::create_dir_all(dir)?;
+        write_atomic(&path, &bytes)?;
+        self.evict(dir, now)
+    }
+
+    /// 删除过期的条目，总大小仍超过上限时按访问时间从旧到新删除
+    fn evict(&self, dir: &Path, now: u64) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for (path, size, meta) in scan(dir)? {
+            match meta {
+                Some(meta) if now.saturating_sub(meta.fetched_at) < meta.ttl_secs => {
+                    total += size;
+                    entries.push((meta.accessed_at, path, size));
+                }
+                _ => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        entries.sort();
+        for (_, path, size) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    /// 条目数、过期条目数和总大小
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        let Some(dir) = &self.dir else {
+            return Ok(stats);
+        };
+        let now = unix_now();
+        for (_, size, meta) in scan(dir)? {
+            stats.entries += 1;
+            stats.bytes += size;
+            if meta.is_none_or(|meta| now.saturating_sub(meta.fetched_at) >= meta.ttl_secs) {
+                stats.expired += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// 删除全部条目，返回删除的条目数
+    pub fn clear(&self) -> io::Result<usize> {
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        let mut removed = 0;
+        for (path, _, _) in scan(dir)? {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// 淘汰时只需要的字段
+#[derive(Deserialize)]
+struct EntryMeta {
+    fetched_at: u64,
+    accessed_at: u64,
+    ttl_secs: u64,
+}
+
+/// 缓存目录中的条目：路径、大小，以及能解析时的元数据
+fn scan(dir: &Path) -> io::Result<Vec<(PathBuf, u64, Option<EntryMeta>)>> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for item in read_dir {
+        let path = item?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Ok(bytes) = fs::read(&path) else {
+            continue;
+        };
+        let meta = serde_json::from_slice(&bytes).ok();
+        entries.push((path, bytes.len() as u64, meta));
+    }
+    Ok(entries)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn default_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".oxide").join("cache").join("web"))
+}
+
+/// 缓存键：scheme 和主机小写、去掉默认端口和片段、查询参数按名称（同名时按值）排序
+pub fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        pairs.sort();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// 缓存的年龄，如 `45s`、`12m`、`3h`、`2d`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// 设置当前生效的缓存（启动时由工具设置传入）
+pub fn configure(cache: WebCache) {
+    *CACHE.write().unwrap_or_else(|e| e.into_inner()) = cache;
+}
+
+/// 当前生效的缓存
+pub fn current() -> WebCache {
+    CACHE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache(dir: &TempDir, ttl_secs: u64, max_bytes: u64) -> WebCache {
+        WebCache::new(Duration::from_secs(ttl_secs), max_bytes).with_dir(dir.path())
+    }
+
+    #[test]
+    fn test_normalize_url_sorts_query_and_drops_fragment() {
+        let key = |url: &str| normalize_url(&Url::parse(url).unwrap());
+        assert_eq!(
+            key("HTTPS://Docs.RS:443/serde?b=2&a=1#section"),
+            "https://docs.rs/serde?a=1&b=2"
+        );
+        assert_eq!(
+            key("https://docs.rs/serde?a=1&b=2"),
+            key("https://docs.rs/serde?b=2&a=1")
+        );
+        assert_eq!(key("https://docs.rs/serde?"), "https://docs.rs/serde");
+        assert_ne!(
+            key("https://docs.rs/serde?a=1"),
+            key("https://docs.rs/serde?a=2")
+        );
+        assert_eq!(format_age(Duration::from_secs(3 * 3600 + 59)), "3h");
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 3600, u64::MAX);
+        cache
+            .put_at("https://a.test/", &"page".to_string(), 1000)
+            .unwrap();
+
+        let hit: CacheHit<String> = cache.get_at("https://a.test/", 1000 + 3599).unwrap();
+        assert_eq!(hit.value, "page");
+        assert_eq!(hit.age, Duration::from_secs(3599));
+        assert!(cache
+            .get_at::<String>("https://a.test/", 1000 + 3600)
+            .is_none());
+        assert!(cache.get_at::<String>("https://b.test/", 1000).is_none());
+        // 过期的条目在读取时删除
+        assert_eq!(cache.stats().unwrap().entries, 0);
+    }
+
+    #[test]
+    fn test_least_recently_used_entries_are_evicted_first() {
+        let dir = TempDir::new().unwrap();
+        let body = "x".repeat(1000);
+        let probe = cache(&dir, 3600, u64::MAX);
+        probe.put_at("probe", &body, 0).unwrap();
+        let entry_size = probe.stats().unwrap().bytes;
+        probe.clear().unwrap();
+
+        // 只容得下三条
+        let cache = cache(&dir, 3600, entry_size * 3 + entry_size / 2);
+        cache.put_at("a", &body, 100).unwrap();
+        cache.put_at("b", &body, 200).unwrap();
+        cache.put_at("c", &body, 300).unwrap();
+        // 访问 a 之后 b 是最久未用的
+        assert!(cache.get_at::<String>("a", 400).is_some());
+        cache.put_at("d", &body, 500).unwrap();
+
+        assert!(cache.get_at::<String>("b", 600).is_none());
+        for key in ["a", "c", "d"] {
+            assert!(
+                cache.get_at::<String>(key, 600).is_some(),
+                "{} was evicted",
+                key
+            );
+        }
+        assert_eq!(cache.stats().unwrap().entries, 3);
+    }
+}
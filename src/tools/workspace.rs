@@ -0,0 +1,313 @@
+//! 工作区根目录
+//!
+//! 一个会话可以同时操作多个目录（例如并排的前端和后端仓库）：主目录之外，
+//! `--add-dir <path>` 和 `/add-dir` 登记额外的根目录。读取工具只允许访问这些根目录下的文件，
+//! shell_execute 的沙箱允许写入所有根目录，文件补全和系统提示词也会列出它们。
+//!
+//! 每个根目录有一个简短的别名（目录名），有多个根目录时显示给用户的路径写成
+//! `alias:相对路径`，工具参数中的路径也可以使用这种写法。
+//! 未登记任何根目录时（测试、`oxide mcp serve`）不限制访问。
+
+use super::{ErrorCode, FileToolError, ToolFailure};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// glob / grep_search 的 `root` 参数中表示搜索所有根目录的值
+pub const ALL_ROOTS: &str = "all";
+
+/// 一个工作区根目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// 简短别名，默认为目录名
+    pub alias: String,
+    /// 规范化后的绝对路径
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WorkspaceError {
+    #[error("'{0}' is not a directory")]
+    NotADirectory(String),
+    #[error("'{0}' is already inside workspace root '{1}'")]
+    AlreadyAdded(String, String),
+}
+
+/// 会话的工作区根目录（克隆后共享同一份列表，`/add-dir` 对已构建的工具同样生效）
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceRoots {
+    roots: Arc<RwLock<Vec<WorkspaceRoot>>>,
+}
+
+impl PartialEq for WorkspaceRoots {
+    fn eq(&self, other: &Self) -> bool {
+        self.list() == other.list()
+    }
+}
+
+impl WorkspaceRoots {
+    /// 以 `primary` 为主目录
+    pub fn new(primary: &Path) -> Self {
+        let path = normalize(primary);
+        let roots = Self::default();
+        roots.write().push(WorkspaceRoot {
+            alias: alias_for(&path, &[]),
+            path,
+        });
+        roots
+    }
+
+    /// 登记额外的根目录，已在某个根目录之下时报错
+    pub fn add(&self, path: &Path) -> Result<WorkspaceRoot, WorkspaceError> {
+        if !path.is_dir() {
+            return Err(WorkspaceError::NotADirectory(path.display().to_string()));
+        }
+        let path = normalize(path);
+        let mut roots = self.write();
+        if let Some(existing) = roots.iter().find(|root| path.starts_with(&root.path)) {
+            return Err(WorkspaceError::AlreadyAdded(
+                path.display().to_string(),
+                existing.alias.clone(),
+            ));
+        }
+        let root = WorkspaceRoot {
+            alias: alias_for(&path, &roots),
+            path,
+        };
+        roots.push(root.clone());
+        Ok(root)
+    }
+
+    /// 所有根目录，第一个为主目录
+    pub fn list(&self) -> Vec<WorkspaceRoot> {
+        self.read().clone()
+    }
+
+    /// 主目录之外的根目录
+    pub fn extra(&self) -> Vec<WorkspaceRoot> {
+        self.read().iter().skip(1).cloned().collect()
+    }
+
+    pub fn is_multi(&self) -> bool {
+        self.read().len() > 1
+    }
+
+    /// 按别名查找
+    pub fn get(&self, alias: &str) -> Option<WorkspaceRoot> {
+        self.read().iter().find(|root| root.alias == alias).cloned()
+    }
+
+    /// `root` 参数选中的根目录：别名或 [`ALL_ROOTS`]
+    pub fn select(&self, root: &str) -> Result<Vec<WorkspaceRoot>, FileToolError> {
+        let roots = self.list();
+        if root == ALL_ROOTS && !roots.is_empty() {
+            return Ok(roots);
+        }
+        if let Some(found) = roots.iter().find(|candidate| candidate.alias == root) {
+            return Ok(vec![found.clone()]);
+        }
+        let suggestion = if roots.is_empty() {
+            "omit root to search the working directory".to_string()
+        } else {
+            let aliases: Vec<&str> = roots.iter().map(|root| root.alias.as_str()).collect();
+            format!("use one of: {}, or \"{}\"", aliases.join(", "), ALL_ROOTS)
+        };
+        Err(ToolFailure::new(
+            ErrorCode::InvalidArgs,
+            format!("Unknown workspace root: {}", root),
+        )
+        .with_suggestion(suggestion)
+        .into())
+    }
+
+    /// 路径是否在某个根目录之下（未登记根目录时总是允许）
+    pub fn contains(&self, path: &Path) -> bool {
+        let roots = self.read();
+        if roots.is_empty() {
+            return true;
+        }
+        let path = normalize(path);
+        roots.iter().any(|root| path.starts_with(&root.path))
+    }
+
+    /// 拒绝访问所有根目录之外的路径
+    pub fn check(&self, path: &Path) -> Result<(), FileToolError> {
+        if self.contains(path) {
+            return Ok(());
+        }
+        let aliases: Vec<String> = self.read().iter().map(|root| root.alias.clone()).collect();
+        Err(ToolFailure::new(
+            ErrorCode::PermissionDenied,
+            format!(
+                "{} is outside the workspace roots ({})",
+                path.display(),
+                aliases.join(", ")
+            ),
+        )
+        .with_suggestion("do not retry; ask the user to run /add-dir if this directory is needed")
+        .into())
+    }
+
+    /// 把 `alias:相对路径` 解析为绝对路径，不是这种写法时返回 `None`
+    pub fn resolve_alias(&self, raw: &str) -> Option<PathBuf> {
+        let (alias, rest) = raw.split_once(':')?;
+        let root = self.get(alias)?;
+        Some(root.path.join(rest.trim_start_matches(['/', '\\'])))
+    }
+
+    /// 显示给用户的路径：有多个根目录时写成 `alias:相对路径`
+    pub fn display(&self, path: &Path) -> String {
+        if !self.is_multi() {
+            return path.display().to_string();
+        }
+        let normalized = normalize(path);
+        let roots = self.read();
+        // 根目录可能嵌套（先登记子目录再登记父目录），取最深的一个
+        let root = roots
+            .iter()
+            .filter(|root| normalized.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count());
+        match root {
+            Some(root) => {
+                let relative = normalized.strip_prefix(&root.path).unwrap_or(&normalized);
+                format!("{}:{}", root.alias, relative.display())
+            }
+            None => path.display().to_string(),
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<WorkspaceRoot>> {
+        self.roots.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<WorkspaceRoot>> {
+        self.roots.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 目录名作为别名，重名时加数字后缀
+fn alias_for(path: &Path, existing: &[WorkspaceRoot]) -> String {
+    let base = path
+        .file_name()
+        .map(|name| name.to_string_lossy().replace(':', "-"))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "root".to_string());
+    let taken = |alias: &str| existing.iter().any(|root| root.alias == alias);
+    if !taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|alias| !taken(alias))
+        .unwrap_or(base)
+}
+
+/// 转为绝对路径并消除 `.` / `..`，已存在的部分解析符号链接
+///
+/// 不存在的部分（待创建的文件）原样接在最近的已存在目录之后。
+fn normalize(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut lexical = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                lexical.pop();
+            }
+            other => lexical.push(other),
+        }
+    }
+
+    let mut existing = lexical.as_path();
+    let mut missing: Vec<&OsStr> = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return lexical,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_and_contains() {
+        let temp_dir = TempDir::new().unwrap();
+        let web = temp_dir.path().join("web");
+        let api = temp_dir.path().join("api");
+        fs::create_dir_all(web.join("src")).unwrap();
+        fs::create_dir_all(&api).unwrap();
+
+        let roots = WorkspaceRoots::new(&web);
+        assert!(!roots.is_multi());
+        assert!(roots.contains(&web.join("src/missing.ts")));
+        assert!(!roots.contains(&api.join("main.rs")));
+        assert!(!roots.contains(&web.join("../api/main.rs")));
+
+        let added = roots.add(&api).unwrap();
+        assert_eq!(added.alias, "api");
+        assert!(roots.is_multi());
+        assert!(roots.contains(&web.join("../api/main.rs")));
+        assert!(!roots.contains(temp_dir.path()));
+
+        assert_eq!(
+            roots.add(&web.join("src")).unwrap_err(),
+            WorkspaceError::AlreadyAdded(
+                normalize(&web.join("src")).display().to_string(),
+                "web".to_string()
+            )
+        );
+        assert!(matches!(
+            roots.add(&temp_dir.path().join("missing")),
+            Err(WorkspaceError::NotADirectory(_))
+        ));
+
+        // 未登记根目录时不限制
+        assert!(WorkspaceRoots::default().contains(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_aliases_and_display() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("a/app");
+        let second = temp_dir.path().join("b/app");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        let roots = WorkspaceRoots::new(&first);
+        let file = first.join("src/main.rs");
+        assert_eq!(roots.display(&file), file.display().to_string());
+
+        assert_eq!(roots.add(&second).unwrap().alias, "app-2");
+        assert_eq!(roots.display(&file), "app:src/main.rs");
+        assert_eq!(
+            roots.resolve_alias("app-2:lib.rs"),
+            Some(normalize(&second).join("lib.rs"))
+        );
+        assert_eq!(roots.resolve_alias("other:lib.rs"), None);
+        assert_eq!(roots.resolve_alias("lib.rs"), None);
+
+        assert_eq!(roots.select(ALL_ROOTS).unwrap().len(), 2);
+        assert_eq!(roots.select("app-2").unwrap()[0].alias, "app-2");
+        let error = roots.select("web").unwrap_err().failure();
+        assert_eq!(error.code, ErrorCode::InvalidArgs);
+        assert!(error.suggestion.unwrap().contains("app, app-2"));
+    }
+}
@@ -1,4 +1,9 @@
-use super::FileToolError;
+use super::encoding::{FileEncoding, TextEncoding, ENCODING_LABELS};
+use super::format_on_write::{format_after_write, print_result};
+use super::safe_write::write_with_backup;
+use super::settings::ToolSettings;
+use super::uncommitted::get_overwrite_guard;
+use super::{with_note, FileToolError};
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -9,18 +14,33 @@ use std::path::Path;
 pub struct WriteFileArgs {
     pub file_path: String,
     pub content: String,
+    /// 目标编码，不填时沿用已有文件的编码（新文件为 UTF-8）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct WriteFileOutput {
     pub file_path: String,
     pub bytes_written: u64,
+    /// 写入时使用的编码，如 `utf-8`、`utf-16le with BOM`
+    pub encoding: String,
     pub success: bool,
     pub message: String,
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct WriteFileTool;
+#[derive(Default, Deserialize, Serialize)]
+pub struct WriteFileTool {
+    settings: ToolSettings,
+}
+
+impl WriteFileTool {
+    pub fn new(settings: &ToolSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
 
 impl Tool for WriteFileTool {
     const NAME: &'static str = "write_file";
@@ -32,7 +52,7 @@ impl Tool for WriteFileTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "write_file".to_string(),
-            description: "Write content to a file, creating it if it doesn't exist or overwriting it completely if it does. Creates parent directories if needed.".to_string(),
+            description: "Write content to a file, creating it if it doesn't exist or overwriting it completely if it does. Creates parent directories if needed. When overwriting, the file's existing encoding and byte order mark are kept (e.g. a UTF-16 or Latin-1 file stays UTF-16 or Latin-1); new files are UTF-8. Error codes: InvalidArgs (the path is a directory, an unknown encoding, or content the target encoding cannot represent), PermissionDenied.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -43,6 +63,11 @@ impl Tool for WriteFileTool {
                     "content": {
                         "type": "string",
                         "description": "The content to write to the file. This will completely replace any existing content."
+                    },
+                    "encoding": {
+                        "type": "string",
+                        "enum": ENCODING_LABELS,
+                        "description": "Encoding to write. Omit to keep the existing file's encoding (UTF-8 for new files)."
                     }
                 },
                 "required": ["file_path", "content"]
@@ -51,9 +76,20 @@ impl Tool for WriteFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let file_path = &args.file_path;
+        let file_path = &self.settings.resolve_path(&args.file_path);
         let content = &args.content;
         let path = Path::new(file_path);
+        if path.is_dir() {
+            return Err(FileToolError::NotAFile(file_path.clone()));
+        }
+
+        let encoding = target_encoding(path, args.encoding.as_deref())?;
+        let bytes = encoding.encode(content).map_err(|e| {
+            FileToolError::InvalidInput(format!(
+                "Cannot write '{}': {}; use another encoding or change the content",
+                file_path, e
+            ))
+        })?;
 
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
@@ -63,17 +99,22 @@ impl Tool for WriteFileTool {
         }
 
         // Write the content to the file
-        match fs::write(file_path, content) {
+        match write_with_backup(path, &bytes) {
             Ok(()) => {
-                let bytes_written = content.len() as u64;
+                let bytes_written = bytes.len() as u64;
+                let mut message = format!(
+                    "Successfully wrote {} bytes to '{}'",
+                    bytes_written, file_path
+                );
+                if encoding != FileEncoding::default() {
+                    message.push_str(&format!(" as {}", encoding.describe()));
+                }
                 Ok(WriteFileOutput {
                     file_path: file_path.clone(),
                     bytes_written,
+                    encoding: encoding.describe(),
                     success: true,
-                    message: format!(
-                        "Successfully wrote {} bytes to '{}'",
-                        bytes_written, file_path
-                    ),
+                    message,
                 })
             }
             Err(e) => match e.kind() {
@@ -85,15 +126,37 @@ impl Tool for WriteFileTool {
         }
     }
 }
+/// 写入时使用的编码：指定了 `encoding` 时按指定编码（与原文件相同时保留 BOM），
+/// 否则沿用原文件的编码，新文件或二进制文件为 UTF-8
+fn target_encoding(path: &Path, requested: Option<&str>) -> Result<FileEncoding, FileToolError> {
+    let existing = fs::read(path)
+        .ok()
+        .and_then(|bytes| FileEncoding::detect(&bytes));
+    let Some(requested) = requested else {
+        return Ok(existing.unwrap_or_default());
+    };
+    let encoding = TextEncoding::from_label(requested).ok_or_else(|| {
+        FileToolError::InvalidInput(format!(
+            "Unknown encoding '{}'; expected one of: {}",
+            requested,
+            ENCODING_LABELS.join(", ")
+        ))
+    })?;
+    Ok(match existing {
+        Some(existing) if existing.encoding == encoding => existing,
+        _ => FileEncoding::new(encoding),
+    })
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct WrappedWriteFileTool {
     inner: WriteFileTool,
 }
 
 impl WrappedWriteFileTool {
-    pub fn new() -> Self {
+    pub fn new(settings: &ToolSettings) -> Self {
         Self {
-            inner: WriteFileTool,
+            inner: WriteFileTool::new(settings),
         }
     }
 }
@@ -109,28 +172,126 @@ impl Tool for WrappedWriteFileTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!();
-        println!("{} {}({})", "●".bright_green(), "Write", args.file_path);
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        tool_println!();
+        tool_println!("{} {}({})", "●".bright_green(), "Write", args.file_path);
+        args.file_path = self.inner.settings.resolve_path(&args.file_path);
+
+        // 没读过的文件有未提交的修改时先让用户确认
+        if let Err(e) = get_overwrite_guard().confirm(&args.file_path) {
+            tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
+            tool_println!();
+            return Err(e);
+        }
 
         // Store line count before moving args
         let line_count = args.content.lines().count();
 
-        let result = self.inner.call(args).await;
+        let mut result = self.inner.call(args).await;
+        let format = match &mut result {
+            Ok(output) => {
+                let format = format_after_write(Path::new(&output.file_path)).await;
+                let note = format.as_ref().and_then(|f| f.note());
+                output.message = with_note(std::mem::take(&mut output.message), note);
+                format
+            }
+            Err(_) => None,
+        };
 
         match &result {
             Ok(output) => {
-                println!(
-                    "  └─ {} bytes written, {} lines",
+                let encoding = if output.encoding == "utf-8" {
+                    String::new()
+                } else {
+                    format!(", {}", output.encoding)
+                };
+                tool_println!(
+                    "  └─ {} bytes written, {} lines{}",
                     output.bytes_written.to_string().dimmed(),
-                    line_count.to_string().dimmed()
+                    line_count.to_string().dimmed(),
+                    encoding.dimmed()
                 );
+                get_overwrite_guard().record_seen(Path::new(&output.file_path));
+                if let Some(format) = &format {
+                    print_result(format);
+                }
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                tool_println!("  └─ {}", format!("Error: {}", e.message()).red());
             }
         }
-        println!();
+        tool_println!();
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn write(
+        path: &Path,
+        content: &str,
+        encoding: Option<&str>,
+    ) -> Result<WriteFileOutput, FileToolError> {
+        WriteFileTool::default()
+            .call(WriteFileArgs {
+                file_path: path.to_string_lossy().to_string(),
+                content: content.to_string(),
+                encoding: encoding.map(String::from),
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_keeps_existing_encoding() {
+        let temp_dir = TempDir::new().unwrap();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/encodings");
+        for (fixture, expected) in [
+            ("utf16le_bom.txt", "utf-16le with BOM"),
+            ("utf8_bom.txt", "utf-8 with BOM"),
+            ("windows1252.txt", "windows-1252"),
+        ] {
+            let path = temp_dir.path().join(fixture);
+            fs::copy(fixtures.join(fixture), &path).unwrap();
+
+            let output = write(&path, "Größe: 10 €\n", None).await.unwrap();
+            assert_eq!(output.encoding, expected);
+            assert!(output.message.ends_with(&format!("as {}", expected)));
+            let bytes = fs::read(&path).unwrap();
+            let encoding = FileEncoding::detect(&bytes).unwrap();
+            assert_eq!(encoding.describe(), expected);
+            assert_eq!(encoding.decode(&bytes).unwrap(), "Größe: 10 €\n");
+        }
+
+        let path = temp_dir.path().join("new.txt");
+        let output = write(&path, "café\n", None).await.unwrap();
+        assert_eq!(output.encoding, "utf-8");
+        assert_eq!(fs::read(&path).unwrap(), "café\n".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_explicit_encoding() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("legacy.txt");
+
+        let output = write(&path, "café\n", Some("latin-1")).await.unwrap();
+        assert_eq!(output.encoding, "latin-1");
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xE9\n");
+
+        let err = write(&path, "10 €\n", Some("latin-1")).await.unwrap_err();
+        assert!(
+            err.to_string().contains("cannot be represented in latin-1"),
+            "{}",
+            err
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xE9\n");
+
+        let output = write(&path, "a", Some("utf-16le")).await.unwrap();
+        assert_eq!(output.encoding, "utf-16le with BOM");
+        assert_eq!(fs::read(&path).unwrap(), vec![0xFF, 0xFE, b'a', 0]);
+
+        assert!(write(&path, "a", Some("ebcdic")).await.is_err());
+    }
+}
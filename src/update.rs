@@ -0,0 +1,638 @@
+//! 自动更新（`oxide update` 与 `/update`）
+//!
+//! 从 GitHub Releases 查询所选渠道的最新版本，与内置版本比较后下载当前平台的资产，
+//! 按同一版本中的 `SHA256SUMS` 校验后替换正在运行的可执行文件。
+//!
+//! 发布资产按平台命名为 `oxide-<arch>-<os>`（Windows 为 `oxide-<arch>-windows.exe`），
+//! `SHA256SUMS` 使用 `sha256sum` 的输出格式。
+//!
+//! 启动检查每天最多联网一次，在后台进行，结果缓存在 `~/.oxide/update-check.json`，
+//! 下次启动时据此提示新版本，因此网络问题不会拖慢启动。
+
+use crate::atomic_file::{read_json_or_recover, write_atomic};
+use crate::config::{ConfigLoader, UpdateChannel, UpdateConfig};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 内置版本
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// 校验和文件的资产名
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// 设置后跳过启动检查
+const NO_CHECK_VAR: &str = "OXIDE_NO_UPDATE_CHECK";
+
+/// 启动检查的请求超时
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 建立连接的超时（下载本身不限时）
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// 发布版本
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    pub tag: String,
+    assets: Vec<GithubAsset>,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&GithubAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// 渠道名称
+pub fn channel_name(channel: UpdateChannel) -> &'static str {
+    match channel {
+        UpdateChannel::Stable => "stable",
+        UpdateChannel::Nightly => "nightly",
+    }
+}
+
+/// 版本是否比内置版本新
+pub fn is_newer(version: &Version) -> bool {
+    Version::parse(CURRENT_VERSION).is_ok_and(|current| *version > current)
+}
+
+/// 当前平台的资产名
+pub fn asset_name() -> String {
+    asset_name_for(std::env::consts::ARCH, std::env::consts::OS)
+}
+
+fn asset_name_for(arch: &str, os: &str) -> String {
+    if os == "windows" {
+        format!("oxide-{}-{}.exe", arch, os)
+    } else {
+        format!("oxide-{}-{}", arch, os)
+    }
+}
+
+/// 选出渠道上版本号最高的发布
+///
+/// stable 只考虑正式版本，nightly 同时考虑预发布版本；草稿和版本号无法解析的发布被忽略。
+fn select_release(releases: Vec<GithubRelease>, channel: UpdateChannel) -> Option<Release> {
+    releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .filter(|release| channel == UpdateChannel::Nightly || !release.prerelease)
+        .filter_map(|release| {
+            let version = Version::parse(release.tag_name.trim_start_matches('v')).ok()?;
+            Some(Release {
+                version,
+                tag: release.tag_name,
+                assets: release.assets,
+            })
+        })
+        .max_by(|a, b| a.version.cmp(&b.version))
+}
+
+/// 从 `SHA256SUMS` 中找出资产的校验和
+fn parse_checksum(checksums: &str, asset: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        // `sha256sum -b` 在文件名前加 `*`
+        let name = parts.next()?.trim_start_matches('*');
+        (name == asset).then(|| hash.to_lowercase())
+    })
+}
+
+fn verify_checksum(bytes: &[u8], expected: &str) -> Result<()> {
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual != expected {
+        bail!("Checksum mismatch: expected {}, got {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// 下载中的新版本
+fn staged_path(exe: &Path) -> PathBuf {
+    with_suffix(exe, "new")
+}
+
+/// Windows 上被替换下来的旧版本
+fn old_path(exe: &Path) -> PathBuf {
+    with_suffix(exe, "old")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 用新内容替换可执行文件
+///
+/// 先写到同目录的 `<exe>.new` 再重命名，替换是原子的。Windows 不能覆盖正在运行的
+/// 可执行文件，但可以重命名它：先把当前文件改名为 `<exe>.old`，再把新版本移到原位置，
+/// `<exe>.old` 在下次启动时由 [`cleanup_previous_update`] 删除。
+fn replace_executable(exe: &Path, bytes: &[u8]) -> Result<()> {
+    let staged = staged_path(exe);
+    fs::write(&staged, bytes).with_context(|| format!("Failed to write {}", staged.display()))?;
+    if let Ok(metadata) = fs::metadata(exe) {
+        fs::set_permissions(&staged, metadata.permissions())
+            .with_context(|| format!("Failed to set permissions on {}", staged.display()))?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = old_path(exe);
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| format!("Failed to move {}", exe.display()))?;
+        if let Err(e) = fs::rename(&staged, exe) {
+            let _ = fs::rename(&old, exe);
+            return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+        }
+    }
+    #[cfg(not(windows))]
+    fs::rename(&staged, exe).with_context(|| format!("Failed to replace {}", exe.display()))?;
+
+    Ok(())
+}
+
+/// 删除上次更新留下的旧版本和未完成的下载
+pub fn cleanup_previous_update() {
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = fs::remove_file(old_path(&exe));
+        let _ = fs::remove_file(staged_path(&exe));
+    }
+}
+
+/// 查询和安装发布版本
+pub struct Updater {
+    client: reqwest::Client,
+    api_base: String,
+    repository: String,
+    channel: UpdateChannel,
+}
+
+impl Updater {
+    pub fn new(config: &UpdateConfig) -> Self {
+        Self {
+            client: Self::build_client(None),
+            api_base: GITHUB_API.to_string(),
+            repository: config.repository.clone(),
+            channel: config.channel,
+        }
+    }
+
+    /// 使用其他 API 地址（GitHub Enterprise 或测试）
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_channel(mut self, channel: UpdateChannel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// 限制每个请求的总时长
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Self::build_client(Some(timeout));
+        self
+    }
+
+    fn build_client(timeout: Option<Duration>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!("oxide/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(CONNECT_TIMEOUT);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder.build().unwrap_or_default()
+    }
+
+    pub fn channel(&self) -> UpdateChannel {
+        self.channel
+    }
+
+    /// 渠道上的最新发布，没有可用发布时返回 None
+    pub async fn latest_release(&self) -> Result<Option<Release>> {
+        let url = format!(
+            "{}/repos/{}/releases?per_page=30",
+            self.api_base, self.repository
+        );
+        let releases: Vec<GithubRelease> = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to query releases of {}", self.repository))?
+            .json()
+            .await
+            .context("Failed to parse the releases response")?;
+        Ok(select_release(releases, self.channel))
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let bytes = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to download {}", url))?
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to download {}", url))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// 下载当前平台的资产，校验后替换 `exe`
+    pub async fn install(&self, release: &Release, exe: &Path) -> Result<()> {
+        let name = asset_name();
+        let asset = release
+            .asset(&name)
+            .with_context(|| format!("Release {} has no asset {}", release.tag, name))?;
+        let checksums = release
+            .asset(CHECKSUMS_ASSET)
+            .with_context(|| format!("Release {} has no {}", release.tag, CHECKSUMS_ASSET))?;
+
+        let checksums = self.download(&checksums.browser_download_url).await?;
+        let expected = parse_checksum(&String::from_utf8_lossy(&checksums), &name)
+            .with_context(|| format!("{} does not list {}", CHECKSUMS_ASSET, name))?;
+        let bytes = self.download(&asset.browser_download_url).await?;
+        verify_checksum(&bytes, &expected)?;
+        replace_executable(exe, &bytes)
+    }
+}
+
+/// 读取 `[update]` 配置
+pub fn load_config() -> UpdateConfig {
+    ConfigLoader::new()
+        .load_toml_layers()
+        .ok()
+        .and_then(|config| config.update)
+        .unwrap_or_default()
+}
+
+/// `oxide update`：检查渠道上的最新版本，`check_only` 为 false 时下载安装
+pub async fn run(channel: Option<UpdateChannel>, check_only: bool) -> Result<()> {
+    let config = load_config();
+    let updater = Updater::new(&config).with_channel(channel.unwrap_or(config.channel));
+    let channel = channel_name(updater.channel());
+    println!("{}", t!("update.current", version = CURRENT_VERSION));
+
+    let Some(release) = updater.latest_release().await? else {
+        println!("{}", t!("update.no_release", channel = channel));
+        return Ok(());
+    };
+    if !is_newer(&release.version) {
+        println!("{}", t!("update.up_to_date", channel = channel));
+        return Ok(());
+    }
+    println!(
+        "{}",
+        t!(
+            "update.available",
+            channel = channel,
+            version = release.version
+        )
+    );
+    if check_only {
+        println!("{}", t!("update.install_hint"));
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe().context("Failed to locate the running executable")?;
+    println!("{}", t!("update.downloading", asset = asset_name()));
+    updater.install(&release, &exe).await?;
+    println!("{}", t!("update.installed", version = release.version));
+    Ok(())
+}
+
+/// 启动检查的缓存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CheckState {
+    checked_at: DateTime<Utc>,
+    channel: UpdateChannel,
+    #[serde(default)]
+    latest: Option<String>,
+}
+
+fn state_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".oxide").join("update-check.json"))
+}
+
+fn load_state(path: &Path) -> Option<CheckState> {
+    read_json_or_recover(path).ok().flatten()
+}
+
+fn save_state(path: &Path, state: &CheckState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(path, serde_json::to_string(state)?.as_bytes())?;
+    Ok(())
+}
+
+/// 距上次检查超过一天或切换了渠道时需要重新检查
+fn needs_refresh(state: Option<&CheckState>, channel: UpdateChannel, now: DateTime<Utc>) -> bool {
+    match state {
+        Some(state) => {
+            state.channel != channel || now - state.checked_at >= chrono::Duration::days(1)
+        }
+        None => true,
+    }
+}
+
+/// 缓存中比内置版本新的版本
+fn cached_newer_version(state: Option<&CheckState>, channel: UpdateChannel) -> Option<Version> {
+    state
+        .filter(|state| state.channel == channel)
+        .and_then(|state| state.latest.as_deref())
+        .and_then(|latest| Version::parse(latest).ok())
+        .filter(is_newer)
+}
+
+/// 启动检查：按缓存返回新版本提示，需要时在后台刷新缓存
+///
+/// 不等待网络请求，刷新结果在下次启动时生效。
+pub fn startup_check(config: &UpdateConfig) -> Option<String> {
+    if !config.check_on_startup || std::env::var_os(NO_CHECK_VAR).is_some() {
+        return None;
+    }
+    let path = state_path()?;
+    let state = load_state(&path);
+
+    if needs_refresh(state.as_ref(), config.channel, Utc::now()) {
+        let updater = Updater::new(config).with_timeout(STARTUP_TIMEOUT);
+        let previous = state.clone();
+        let path = path.clone();
+        tokio::spawn(async move {
+            let latest = match updater.latest_release().await {
+                Ok(release) => release.map(|release| release.version.to_string()),
+                Err(e) => {
+                    tracing::debug!(error = %format!("{:#}", e), "update check failed");
+                    // 失败也记录检查时间，避免每次启动都重试
+                    previous
+                        .filter(|state| state.channel == updater.channel())
+                        .and_then(|state| state.latest)
+                }
+            };
+            let state = CheckState {
+                checked_at: Utc::now(),
+                channel: updater.channel(),
+                latest,
+            };
+            if let Err(e) = save_state(&path, &state) {
+                tracing::debug!(error = %e, "failed to save update check state");
+            }
+        });
+    }
+
+    cached_newer_version(state.as_ref(), config.channel).map(|version| {
+        t!(
+            "update.notice",
+            version = version,
+            current = CURRENT_VERSION
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> GithubRelease {
+        GithubRelease {
+            tag_name: tag.to_string(),
+            prerelease,
+            draft,
+            assets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_select_release() {
+        let releases = vec![
+            release("v0.3.0-nightly.20261018", true, false),
+            release("v0.2.1", false, false),
+            release("v0.4.0", false, true),
+            release("latest-build", false, false),
+            release("v0.2.0", false, false),
+        ];
+
+        let stable = select_release(releases.clone(), UpdateChannel::Stable).unwrap();
+        assert_eq!(stable.version, Version::parse("0.2.1").unwrap());
+        assert_eq!(stable.tag, "v0.2.1");
+
+        let nightly = select_release(releases, UpdateChannel::Nightly).unwrap();
+        assert_eq!(nightly.tag, "v0.3.0-nightly.20261018");
+
+        assert!(select_release(
+            vec![release("v1.0.0-rc.1", true, false)],
+            UpdateChannel::Stable
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(asset_name_for("x86_64", "linux"), "oxide-x86_64-linux");
+        assert_eq!(asset_name_for("aarch64", "macos"), "oxide-aarch64-macos");
+        assert_eq!(
+            asset_name_for("x86_64", "windows"),
+            "oxide-x86_64-windows.exe"
+        );
+    }
+
+    #[test]
+    fn test_parse_and_verify_checksum() {
+        let hash = format!("{:x}", Sha256::digest(b"binary"));
+        let checksums = format!(
+            "{}  oxide-x86_64-linux\n{} *oxide-x86_64-windows.exe\n",
+            "0".repeat(64),
+            hash.to_uppercase()
+        );
+
+        assert_eq!(
+            parse_checksum(&checksums, "oxide-x86_64-linux"),
+            Some("0".repeat(64))
+        );
+        let expected = parse_checksum(&checksums, "oxide-x86_64-windows.exe").unwrap();
+        assert_eq!(expected, hash);
+        assert!(parse_checksum(&checksums, "oxide-aarch64-linux").is_none());
+
+        assert!(verify_checksum(b"binary", &expected).is_ok());
+        assert!(verify_checksum(b"tampered", &expected).is_err());
+    }
+
+    #[test]
+    fn test_replace_executable() {
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("oxide");
+        fs::write(&exe, b"old").unwrap();
+
+        replace_executable(&exe, b"new").unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new");
+        assert!(!staged_path(&exe).exists());
+    }
+
+    #[test]
+    fn test_needs_refresh() {
+        let now = Utc::now();
+        let state = CheckState {
+            checked_at: now - chrono::Duration::hours(2),
+            channel: UpdateChannel::Stable,
+            latest: Some("99.0.0".to_string()),
+        };
+
+        assert!(needs_refresh(None, UpdateChannel::Stable, now));
+        assert!(!needs_refresh(Some(&state), UpdateChannel::Stable, now));
+        assert!(needs_refresh(Some(&state), UpdateChannel::Nightly, now));
+        assert!(needs_refresh(
+            Some(&state),
+            UpdateChannel::Stable,
+            now + chrono::Duration::days(1)
+        ));
+    }
+
+    #[test]
+    fn test_cached_newer_version() {
+        let mut state = CheckState {
+            checked_at: Utc::now(),
+            channel: UpdateChannel::Stable,
+            latest: Some("99.0.0".to_string()),
+        };
+        assert_eq!(
+            cached_newer_version(Some(&state), UpdateChannel::Stable),
+            Some(Version::parse("99.0.0").unwrap())
+        );
+        // 其他渠道的缓存不算数
+        assert!(cached_newer_version(Some(&state), UpdateChannel::Nightly).is_none());
+
+        state.latest = Some(CURRENT_VERSION.to_string());
+        assert!(cached_newer_version(Some(&state), UpdateChannel::Stable).is_none());
+        assert!(cached_newer_version(None, UpdateChannel::Stable).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".oxide/update-check.json");
+        let state = CheckState {
+            checked_at: Utc::now(),
+            channel: UpdateChannel::Nightly,
+            latest: None,
+        };
+        save_state(&path, &state).unwrap();
+        assert_eq!(load_state(&path), Some(state));
+
+        // 写了一半的文件移到一旁，之后重新检查
+        fs::write(&path, r#"{"checked_at":"20"#).unwrap();
+        assert!(load_state(&path).is_none());
+        assert!(!path.exists());
+        assert!(load_state(&path).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_install_from_release() {
+        let mut server = mockito::Server::new_async().await;
+        let name = asset_name();
+        let checksums = format!("{:x}  {}\n", Sha256::digest(b"new binary"), name);
+        let releases = serde_json::json!([
+            {
+                "tag_name": "v99.0.0",
+                "assets": [
+                    { "name": name, "browser_download_url": format!("{}/download/oxide", server.url()) },
+                    { "name": "SHA256SUMS", "browser_download_url": format!("{}/download/SHA256SUMS", server.url()) }
+                ]
+            }
+        ]);
+        server
+            .mock("GET", "/repos/zzfn/oxide/releases?per_page=30")
+            .with_body(releases.to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/download/SHA256SUMS")
+            .with_body(checksums)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/download/oxide")
+            .with_body("new binary")
+            .create_async()
+            .await;
+
+        let updater = Updater::new(&UpdateConfig::default()).with_api_base(server.url());
+        let release = updater.latest_release().await.unwrap().unwrap();
+        assert!(is_newer(&release.version));
+
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("oxide");
+        fs::write(&exe, b"old binary").unwrap();
+        updater.install(&release, &exe).await.unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new binary");
+    }
+
+    #[tokio::test]
+    async fn test_install_rejects_bad_checksum() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/download/SHA256SUMS")
+            .with_body(format!("{}  {}\n", "0".repeat(64), asset_name()))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/download/oxide")
+            .with_body("tampered")
+            .create_async()
+            .await;
+
+        let release = Release {
+            version: Version::parse("99.0.0").unwrap(),
+            tag: "v99.0.0".to_string(),
+            assets: vec![
+                GithubAsset {
+                    name: asset_name(),
+                    browser_download_url: format!("{}/download/oxide", server.url()),
+                },
+                GithubAsset {
+                    name: CHECKSUMS_ASSET.to_string(),
+                    browser_download_url: format!("{}/download/SHA256SUMS", server.url()),
+                },
+            ],
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("oxide");
+        fs::write(&exe, b"old binary").unwrap();
+
+        let updater = Updater::new(&UpdateConfig::default()).with_api_base(server.url());
+        assert!(updater.install(&release, &exe).await.is_err());
+        assert_eq!(fs::read(&exe).unwrap(), b"old binary");
+    }
+}
@@ -0,0 +1,580 @@
+//! API 用量记录与统计（`oxide usage`、`/usage`、`/cost`）
+//!
+//! 每轮对话结束后追加一条记录到 `~/.oxide/usage.jsonl`（时间、会话、项目目录、模型、
+//! 输入/输出/缓存 token 与费用）。费用按 [`pricing_for`]（模型信息注册表中的价格）计算，
+//! 与 token 预估共用同一张表。统计按 UTC 日期分组，`--since` 也按 UTC 日期比较。
+
+use crate::token_counter::pricing_for;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// `oxide usage compact` 默认保留的天数
+pub const DEFAULT_RETENTION_DAYS: u32 = 90;
+
+/// 一轮对话的用量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    /// 运行 oxide 的项目目录
+    pub project: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// 输入中命中提示缓存的部分
+    #[serde(default)]
+    pub cached_tokens: u64,
+    /// 费用（美元）
+    pub cost: f64,
+}
+
+impl UsageRecord {
+    pub fn new(
+        session_id: &str,
+        project: &str,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        cached_tokens: u64,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            session_id: session_id.to_string(),
+            project: project.to_string(),
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            cached_tokens,
+            cost: pricing_for(model).cost(input_tokens, output_tokens, cached_tokens),
+        }
+    }
+}
+
+/// 用量记录文件（JSONL，每行一条记录）
+pub struct UsageLog {
+    path: PathBuf,
+}
+
+impl UsageLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `~/.oxide/usage.jsonl`
+    pub fn open_default() -> Option<Self> {
+        dirs::home_dir().map(|home| Self::new(home.join(".oxide").join("usage.jsonl")))
+    }
+
+    /// 追加一条记录
+    pub fn append(&self, record: &UsageRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// 读取全部记录，跳过无法解析的行；文件不存在时为空
+    pub fn read(&self) -> Result<Vec<UsageRecord>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()))
+            }
+        };
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// 删除 `now` 之前超过 `keep_days` 天的记录，返回删除的条数
+    pub fn compact(&self, keep_days: u32, now: DateTime<Utc>) -> Result<usize> {
+        let records = self.read()?;
+        let cutoff = now - Duration::days(i64::from(keep_days));
+        let kept: Vec<&UsageRecord> = records.iter().filter(|r| r.timestamp >= cutoff).collect();
+        let removed = records.len() - kept.len();
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let mut content = String::new();
+        for record in kept {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        crate::atomic_file::write_atomic(&self.path, content.as_bytes())
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(removed)
+    }
+}
+
+/// 记录一轮对话的用量并返回该记录，写入失败只写日志
+pub fn record_turn(session_id: &str, model: &str, usage: &rig::completion::Usage) -> UsageRecord {
+    let project = std::env::current_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+    let record = UsageRecord::new(
+        session_id,
+        &project,
+        model,
+        usage.input_tokens,
+        usage.output_tokens,
+        cached_input_tokens(usage),
+    );
+    if let Some(log) = UsageLog::open_default() {
+        if let Err(e) = log.append(&record) {
+            tracing::warn!("failed to record usage: {:#}", e);
+        }
+    }
+    record
+}
+
+/// provider 报告的缓存命中 token，未报告时为 0
+fn cached_input_tokens(usage: &rig::completion::Usage) -> u64 {
+    serde_json::to_value(usage)
+        .ok()
+        .and_then(|value| value.get("cached_input_tokens").and_then(|v| v.as_u64()))
+        .unwrap_or(0)
+}
+
+/// 统计分组方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    Day,
+    Project,
+    Model,
+    Session,
+}
+
+impl GroupBy {
+    fn key(self, record: &UsageRecord) -> String {
+        match self {
+            Self::Day => record.timestamp.date_naive().to_string(),
+            Self::Project => record.project.clone(),
+            Self::Model => record.model.clone(),
+            Self::Session => record.session_id.clone(),
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Day => t!("usage.column.day"),
+            Self::Project => t!("usage.column.project"),
+            Self::Model => t!("usage.column.model"),
+            Self::Session => t!("usage.column.session"),
+        }
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Self::Day),
+            "project" => Ok(Self::Project),
+            "model" => Ok(Self::Model),
+            "session" => Ok(Self::Session),
+            _ => Err(t!("usage.invalid_group", value = s)),
+        }
+    }
+}
+
+/// 一组记录的合计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub turns: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageTotals {
+    /// 累加一条记录
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.turns += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        self.cached_tokens += record.cached_tokens;
+        self.cost += record.cost;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// 统计结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageReport {
+    pub group_by: GroupBy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+    pub groups: Vec<UsageGroup>,
+    pub total: UsageTotals,
+}
+
+/// 按 `group_by` 汇总 `since`（含）之后的记录
+///
+/// 按日期分组时按日期排序，其它分组按费用从高到低排序。
+pub fn aggregate(
+    records: &[UsageRecord],
+    group_by: GroupBy,
+    since: Option<NaiveDate>,
+) -> UsageReport {
+    let mut groups: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut total = UsageTotals::default();
+    for record in records {
+        if since.is_some_and(|since| record.timestamp.date_naive() < since) {
+            continue;
+        }
+        groups.entry(group_by.key(record)).or_default().add(record);
+        total.add(record);
+    }
+
+    let mut groups: Vec<UsageGroup> = groups
+        .into_iter()
+        .map(|(key, totals)| UsageGroup { key, totals })
+        .collect();
+    if group_by != GroupBy::Day {
+        groups.sort_by(|a, b| {
+            b.totals
+                .cost
+                .total_cmp(&a.totals.cost)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+    }
+    UsageReport {
+        group_by,
+        since,
+        groups,
+        total,
+    }
+}
+
+/// 一个会话的合计（`/cost`）
+pub fn session_totals(records: &[UsageRecord], session_id: &str) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    for record in records.iter().filter(|r| r.session_id == session_id) {
+        totals.add(record);
+    }
+    totals
+}
+
+/// 统计参数
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageQuery {
+    pub group_by: GroupBy,
+    pub since: Option<NaiveDate>,
+    pub json: bool,
+}
+
+impl UsageQuery {
+    /// 解析 `/usage` 的参数：`[--by day|project|model|session] [--since YYYY-MM-DD] [--json]`
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut query = Self::default();
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "--by" => {
+                    let value = tokens
+                        .next()
+                        .ok_or_else(|| t!("usage.missing_value", flag = "--by"))?;
+                    query.group_by = value.parse()?;
+                }
+                "--since" => {
+                    let value = tokens
+                        .next()
+                        .ok_or_else(|| t!("usage.missing_value", flag = "--since"))?;
+                    query.since = Some(parse_date(value)?);
+                }
+                "--json" => query.json = true,
+                _ => return Err(t!("usage.unknown_arg", arg = token)),
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// 解析 `YYYY-MM-DD`
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| t!("usage.invalid_date", value = value))
+}
+
+/// 格式化费用
+fn format_cost(cost: f64) -> String {
+    format!("${:.4}", cost)
+}
+
+impl fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.groups.is_empty() {
+            return write!(f, "{}", t!("usage.empty"));
+        }
+
+        let headers = [
+            self.group_by.column(),
+            t!("usage.column.turns"),
+            t!("usage.column.input"),
+            t!("usage.column.output"),
+            t!("usage.column.cached"),
+            t!("usage.column.cost"),
+        ];
+        let row = |key: &str, totals: &UsageTotals| {
+            [
+                key.to_string(),
+                totals.turns.to_string(),
+                totals.input_tokens.to_string(),
+                totals.output_tokens.to_string(),
+                totals.cached_tokens.to_string(),
+                format_cost(totals.cost),
+            ]
+        };
+        let mut rows: Vec<[String; 6]> =
+            self.groups.iter().map(|g| row(&g.key, &g.totals)).collect();
+        rows.push(row(t!("usage.total"), &self.total));
+
+        let mut widths = headers.map(|h| h.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(widths)
+                .enumerate()
+                .map(|(i, (cell, width))| {
+                    // 第一列左对齐，数字右对齐
+                    if i == 0 {
+                        format!("{:<width$}", cell, width = width)
+                    } else {
+                        format!("{:>width$}", cell, width = width)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+        let separator = widths.map(|w| "-".repeat(w)).join("  ");
+
+        writeln!(f, "{}", line(&headers.map(str::to_string)))?;
+        writeln!(f, "{}", separator)?;
+        let (total, groups) = rows.split_last().expect("rows include the total");
+        for row in groups {
+            writeln!(f, "{}", line(row))?;
+        }
+        writeln!(f, "{}", separator)?;
+        write!(f, "{}", line(total))
+    }
+}
+
+fn open_log() -> Result<UsageLog> {
+    UsageLog::open_default().context("Cannot determine the home directory")
+}
+
+/// 打印用量统计（`oxide usage` / `/usage`）
+pub fn run_report(query: &UsageQuery) -> Result<()> {
+    let records = open_log()?.read()?;
+    let report = aggregate(&records, query.group_by, query.since);
+    if query.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// 删除过期记录（`oxide usage compact`）
+pub fn run_compact(keep_days: u32) -> Result<()> {
+    if keep_days == 0 {
+        bail!("--keep-days must be at least 1");
+    }
+    let log = open_log()?;
+    let removed = log.compact(keep_days, Utc::now())?;
+    println!(
+        "{}",
+        t!("usage.compacted", count = removed, days = keep_days)
+    );
+    Ok(())
+}
+
+/// 当前会话的费用（`/cost`）
+pub fn session_cost_summary(session_id: &str) -> Result<String> {
+    let records = open_log()?.read()?;
+    let totals = session_totals(&records, session_id);
+    Ok(t!(
+        "usage.session_cost",
+        turns = totals.turns,
+        input = totals.input_tokens,
+        output = totals.output_tokens,
+        cached = totals.cached_tokens,
+        cost = format_cost(totals.cost)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/usage/usage.jsonl");
+
+    fn fixture_log(dir: &TempDir) -> UsageLog {
+        let path = dir.path().join("usage.jsonl");
+        fs::write(&path, FIXTURE).unwrap();
+        UsageLog::new(path)
+    }
+
+    fn group<'a>(report: &'a UsageReport, key: &str) -> &'a UsageTotals {
+        &report.groups.iter().find(|g| g.key == key).unwrap().totals
+    }
+
+    #[test]
+    fn test_read_skips_invalid_lines() {
+        let dir = TempDir::new().unwrap();
+        let records = fixture_log(&dir).read().unwrap();
+        assert_eq!(records.len(), 6);
+        assert!(UsageLog::new(dir.path().join("missing.jsonl"))
+            .read()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_aggregate_by_day() {
+        let dir = TempDir::new().unwrap();
+        let records = fixture_log(&dir).read().unwrap();
+        let report = aggregate(&records, GroupBy::Day, None);
+
+        let keys: Vec<&str> = report.groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["2024-01-01", "2024-01-02", "2024-02-10"]);
+        let day = group(&report, "2024-01-01");
+        assert_eq!(day.turns, 2);
+        assert_eq!(day.input_tokens, 3000);
+        assert_eq!(day.output_tokens, 700);
+        assert_eq!(day.cached_tokens, 500);
+        assert!((day.cost - 0.03).abs() < 1e-9);
+
+        assert_eq!(report.total.turns, 6);
+        assert_eq!(report.total.input_tokens, 13_000);
+        assert_eq!(report.total.output_tokens, 2_600);
+        let sum: f64 = report.groups.iter().map(|g| g.totals.cost).sum();
+        assert!((report.total.cost - sum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_aggregate_by_project_and_model_since() {
+        let dir = TempDir::new().unwrap();
+        let records = fixture_log(&dir).read().unwrap();
+
+        let report = aggregate(&records, GroupBy::Project, None);
+        assert_eq!(report.groups.len(), 2);
+        // 按费用从高到低
+        assert!(report.groups[0].totals.cost >= report.groups[1].totals.cost);
+        assert_eq!(group(&report, "/work/api").turns, 4);
+        assert_eq!(group(&report, "/work/web").turns, 2);
+
+        let since = NaiveDate::from_ymd_opt(2024, 1, 2);
+        let report = aggregate(&records, GroupBy::Model, since);
+        assert_eq!(report.total.turns, 4);
+        assert_eq!(group(&report, "claude-sonnet-4-20250514").turns, 2);
+        assert_eq!(group(&report, "gpt-4o").turns, 2);
+        assert_eq!(report.total.input_tokens, 10_000);
+
+        let totals = session_totals(&records, "brave-fox-1a2b");
+        assert_eq!(totals.turns, 3);
+        assert_eq!(totals.output_tokens, 1_200);
+    }
+
+    #[test]
+    fn test_record_cost_uses_pricing_table() {
+        let record = UsageRecord::new(
+            "s",
+            "/p",
+            "claude-sonnet-4-20250514",
+            1_000_000,
+            100_000,
+            400_000,
+        );
+        let expected = pricing_for("claude-sonnet-4-20250514").cost(1_000_000, 100_000, 400_000);
+        assert_eq!(record.cost, expected);
+    }
+
+    #[test]
+    fn test_append_and_compact() {
+        let dir = TempDir::new().unwrap();
+        let log = UsageLog::new(dir.path().join("nested/usage.jsonl"));
+        let mut old = UsageRecord::new("s", "/p", "gpt-4o", 100, 10, 0);
+        old.timestamp = Utc::now() - Duration::days(200);
+        log.append(&old).unwrap();
+        log.append(&UsageRecord::new("s", "/p", "gpt-4o", 200, 20, 0))
+            .unwrap();
+
+        assert_eq!(log.compact(90, Utc::now()).unwrap(), 1);
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].input_tokens, 200);
+        assert_eq!(log.compact(90, Utc::now()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(UsageQuery::parse("").unwrap(), UsageQuery::default());
+        let query = UsageQuery::parse("--by project --since 2024-01-01 --json").unwrap();
+        assert_eq!(query.group_by, GroupBy::Project);
+        assert_eq!(query.since, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert!(query.json);
+
+        assert!(UsageQuery::parse("--by week").is_err());
+        assert!(UsageQuery::parse("--since yesterday").is_err());
+        assert!(UsageQuery::parse("--since").is_err());
+        assert!(UsageQuery::parse("--verbose").is_err());
+    }
+
+    #[test]
+    fn test_report_table() {
+        let dir = TempDir::new().unwrap();
+        let records = fixture_log(&dir).read().unwrap();
+        let table = aggregate(&records, GroupBy::Day, None).to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        // 表头、分隔线、3 天、分隔线、合计
+        assert_eq!(lines.len(), 7);
+        assert!(lines[2].starts_with("2024-01-01"));
+        assert!(lines[6].ends_with(&format_cost(
+            aggregate(&records, GroupBy::Day, None).total.cost
+        )));
+        assert!(
+            aggregate(&[], GroupBy::Day, None)
+                .to_string()
+                .lines()
+                .count()
+                == 1
+        );
+    }
+}
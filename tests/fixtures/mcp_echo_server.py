@@ -0,0 +1,71 @@
+#!/usr/bin/env python3
+"""最小 MCP 服务器（stdio），用于 MCP 客户端集成测试。
+
+工具：
+- echo: 原样返回 text 参数
+- fail: 返回 isError 结果
+- crash: 直接退出进程，模拟服务器崩溃
+"""
+
+import json
+import sys
+
+TOOLS = [
+    {
+        "name": "echo",
+        "description": "Echo the given text",
+        "inputSchema": {
+            "type": "object",
+            "properties": {"text": {"type": "string"}},
+            "required": ["text"],
+        },
+    },
+    {"name": "fail", "description": "Always fails"},
+    {"name": "crash", "description": "Exit immediately"},
+]
+
+
+def reply(request_id, result):
+    sys.stdout.write(json.dumps({"jsonrpc": "2.0", "id": request_id, "result": result}) + "\n")
+    sys.stdout.flush()
+
+
+def main():
+    for line in sys.stdin:
+        message = json.loads(line)
+        method = message.get("method")
+        request_id = message.get("id")
+
+        if request_id is None:
+            continue  # 通知
+
+        if method == "initialize":
+            reply(request_id, {
+                "protocolVersion": "2024-11-05",
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": "echo", "version": "0.1.0"},
+            })
+        elif method == "tools/list":
+            # 在响应前发送一条通知，验证客户端会跳过
+            sys.stdout.write(json.dumps({"jsonrpc": "2.0", "method": "notifications/message"}) + "\n")
+            reply(request_id, {"tools": TOOLS})
+        elif method == "tools/call":
+            name = message["params"]["name"]
+            args = message["params"].get("arguments", {})
+            if name == "echo":
+                reply(request_id, {"content": [{"type": "text", "text": args.get("text", "")}]})
+            elif name == "fail":
+                reply(request_id, {"content": [{"type": "text", "text": "boom"}], "isError": True})
+            elif name == "crash":
+                sys.exit(1)
+        else:
+            sys.stdout.write(json.dumps({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "error": {"code": -32601, "message": "Method not found"},
+            }) + "\n")
+            sys.stdout.flush()
+
+
+if __name__ == "__main__":
+    main()
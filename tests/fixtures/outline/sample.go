@@ -0,0 +1,32 @@
+package store
+
+import "errors"
+
+// ErrNotFound is returned when a key is missing.
+var ErrNotFound = errors.New("not found")
+
+type Store struct {
+	items map[string]string
+}
+
+type Reader interface {
+	Get(key string) (string, error)
+}
+
+type Key = string
+
+func New() *Store {
+	return &Store{items: map[string]string{}}
+}
+
+func (s *Store) Get(key string) (string, error) {
+	value, ok := s.items[key]
+	if !ok {
+		return "", ErrNotFound
+	}
+	return value, nil
+}
+
+func (s *Store) Set(key, value string) {
+	s.items[key] = value
+}
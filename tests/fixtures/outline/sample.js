@@ -0,0 +1,23 @@
+const express = require("express");
+
+class Router {
+  constructor() {
+    this.routes = [];
+  }
+
+  add(path, handler) {
+    this.routes.push({ path, handler });
+  }
+}
+
+function createApp(port) {
+  const app = express();
+  app.listen(port);
+  return app;
+}
+
+const logger = function (message) {
+  console.log(message);
+};
+
+export default createApp;
@@ -0,0 +1,31 @@
+"""Sample module for outline snapshots."""
+
+import dataclasses
+
+MAX_RETRIES = 3
+
+
+@dataclasses.dataclass
+class Config:
+    name: str
+    retries: int = MAX_RETRIES
+
+    def validate(self) -> None:
+        if self.retries < 0:
+            raise ValueError("retries must be >= 0")
+
+    @property
+    def label(self) -> str:
+        return f"{self.name} ({self.retries})"
+
+
+def load_config(path: str) -> Config:
+    with open(path) as f:
+        return Config(name=f.read().strip())
+
+
+async def fetch(url):
+    def parse(body):
+        return body.strip()
+
+    return parse(url)
@@ -0,0 +1,15 @@
+module Billing
+  class Invoice
+    def initialize(total)
+      @total = total
+    end
+
+    def total
+      @total
+    end
+  end
+
+  def self.format(amount)
+    "$#{amount}"
+  end
+end
@@ -0,0 +1,60 @@
+//! Sample module for outline snapshots
+
+use std::fmt;
+
+pub const MAX_ITEMS: usize = 16;
+
+/// A 2D point
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+pub enum Shape {
+    Circle { center: Point, radius: f64 },
+    Polygon(Vec<Point>),
+}
+
+pub trait Area {
+    fn area(&self) -> f64;
+
+    fn is_empty(&self) -> bool {
+        self.area() == 0.0
+    }
+}
+
+impl Area for Shape {
+    fn area(&self) -> f64 {
+        match self {
+            Shape::Circle { radius, .. } => std::f64::consts::PI * radius * radius,
+            Shape::Polygon(_) => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+pub type Points = Vec<Point>;
+
+macro_rules! point {
+    ($x:expr, $y:expr) => {
+        Point { x: $x, y: $y }
+    };
+}
+
+pub fn distance(a: Point, b: Point) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+mod tests {
+    use super::*;
+
+    fn origin() -> Point {
+        point!(0.0, 0.0)
+    }
+}
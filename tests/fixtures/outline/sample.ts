@@ -0,0 +1,42 @@
+import { readFile } from "fs/promises";
+
+export interface User {
+  id: number;
+  name: string;
+}
+
+export type UserId = User["id"];
+
+export enum Role {
+  Admin,
+  Member,
+}
+
+export class UserStore {
+  private users = new Map<UserId, User>();
+
+  constructor(private readonly path: string) {}
+
+  async load(): Promise<void> {
+    const raw = await readFile(this.path, "utf8");
+    for (const user of JSON.parse(raw) as User[]) {
+      this.users.set(user.id, user);
+    }
+  }
+
+  get(id: UserId): User | undefined {
+    return this.users.get(id);
+  }
+}
+
+export function formatUser(user: User): string {
+  return `${user.id}: ${user.name}`;
+}
+
+export const isAdmin = (role: Role): boolean => {
+  return role === Role.Admin;
+};
+
+namespace Internal {
+  export function helper(): void {}
+}
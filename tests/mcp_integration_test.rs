@@ -0,0 +1,92 @@
+//! MCP 客户端集成测试
+//!
+//! 使用 tests/fixtures/mcp_echo_server.py 作为 MCP 服务器（需要 python3）。
+
+use oxide::config::McpServerConfig;
+use oxide::mcp::{McpError, McpManager, McpServer};
+use rig::tool::ToolDyn;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn fixture_server() -> Option<McpServer> {
+    let python = ["python3", "python"].into_iter().find(|cmd| {
+        std::process::Command::new(cmd)
+            .arg("--version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })?;
+
+    let script = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mcp_echo_server.py");
+    let config = McpServerConfig {
+        command: python.to_string(),
+        args: vec![script.to_string_lossy().to_string()],
+        env: BTreeMap::new(),
+        enabled: true,
+    };
+    Some(McpServer::new("echo", config))
+}
+
+#[tokio::test]
+async fn test_mcp_server_lifecycle() {
+    let Some(server) = fixture_server() else {
+        eprintln!("python3 not found, skipping MCP integration test");
+        return;
+    };
+    let manager = McpManager::with_servers(vec![server]);
+
+    assert!(manager.start_all().await.is_empty());
+
+    let tools: Vec<String> = manager.agent_tools(None).iter().map(ToolDyn::name).collect();
+    assert_eq!(tools, vec!["mcp__echo__echo", "mcp__echo__fail", "mcp__echo__crash"]);
+
+    let server = manager.get("echo").unwrap();
+    let output = server.call_tool("echo", json!({ "text": "hello" })).await.unwrap();
+    assert_eq!(output.text, "hello");
+
+    let error = server.call_tool("fail", json!({})).await.unwrap_err();
+    assert!(matches!(error, McpError::ToolFailed(ref text) if text == "boom"));
+
+    // 服务器崩溃后下一次调用自动重启
+    let error = server.call_tool("crash", json!({})).await.unwrap_err();
+    assert!(error.is_connection_error());
+    let output = server.call_tool("echo", json!({ "text": "again" })).await.unwrap();
+    assert_eq!(output.text, "again");
+
+    // 禁用后拒绝调用，重新启用后恢复
+    server.disable().await;
+    assert!(!server.status().await.running);
+    let error = server.call_tool("echo", json!({ "text": "x" })).await.unwrap_err();
+    assert!(matches!(error, McpError::Disabled(_)));
+
+    server.enable().await.unwrap();
+    assert!(server.status().await.running);
+    assert_eq!(server.call_tool("echo", json!({ "text": "ok" })).await.unwrap().text, "ok");
+
+    manager.shutdown().await;
+    assert!(!server.status().await.running);
+}
+
+#[tokio::test]
+async fn test_mcp_server_spawn_failure() {
+    let mut servers = BTreeMap::new();
+    servers.insert(
+        "missing".to_string(),
+        McpServerConfig {
+            command: "oxide-definitely-missing-mcp-server".to_string(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            enabled: true,
+        },
+    );
+    let manager = McpManager::from_config(&servers);
+
+    let errors = manager.start_all().await;
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].1, McpError::Spawn(..)));
+
+    let status = manager.status().await;
+    assert!(status[0].last_error.is_some());
+    assert!(status[0].tools.is_empty());
+}
@@ -0,0 +1,191 @@
+//! `oxide -p` 集成测试
+//!
+//! 用一个返回固定 SSE 流的本地 HTTP 服务器模拟 Anthropic API，运行编译好的 `oxide`，
+//! 检查调用工具的一轮结束后 stdout 中只有回答。
+
+use serde_json::{json, Value};
+use std::fs;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+
+/// 拼接 Anthropic 流式响应
+fn sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {}\n\n",
+                event["type"].as_str().unwrap(),
+                event
+            )
+        })
+        .collect()
+}
+
+fn message_start() -> Value {
+    json!({ "type": "message_start", "message": {
+        "id": "msg_1", "type": "message", "role": "assistant", "model": "claude-test",
+        "content": [], "stop_reason": null, "stop_sequence": null,
+        "usage": { "input_tokens": 12, "output_tokens": 1 } } })
+}
+
+fn message_end(stop_reason: &str) -> [Value; 2] {
+    [
+        json!({ "type": "message_delta",
+            "delta": { "stop_reason": stop_reason, "stop_sequence": null },
+            "usage": { "output_tokens": 7 } }),
+        json!({ "type": "message_stop" }),
+    ]
+}
+
+fn text_response(text: &str) -> String {
+    let mut events = vec![
+        message_start(),
+        json!({ "type": "content_block_start", "index": 0,
+            "content_block": { "type": "text", "text": "" } }),
+        json!({ "type": "content_block_delta", "index": 0,
+            "delta": { "type": "text_delta", "text": text } }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+    ];
+    events.extend(message_end("end_turn"));
+    sse(&events)
+}
+
+fn tool_use_response(id: &str, name: &str, input: Value) -> String {
+    let mut events = vec![
+        message_start(),
+        json!({ "type": "content_block_start", "index": 0,
+            "content_block": { "type": "tool_use", "id": id, "name": name, "input": {} } }),
+        json!({ "type": "content_block_delta", "index": 0,
+            "delta": { "type": "input_json_delta", "partial_json": input.to_string() } }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+    ];
+    events.extend(message_end("tool_use"));
+    sse(&events)
+}
+
+/// 模拟模型服务，`respond` 根据请求体返回 SSE 内容；收到的请求体记录在 `requests` 中
+async fn mock_provider<F>(respond: F, requests: Arc<Mutex<Vec<String>>>) -> String
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let respond = Arc::clone(&respond);
+            let requests = Arc::clone(&requests);
+            tokio::spawn(async move {
+                let body = read_request_body(&mut stream).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    respond(&body)
+                );
+                requests.lock().unwrap().push(body);
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}/anthropic", addr)
+}
+
+async fn read_request_body(stream: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if n == 0 {
+            return String::new();
+        }
+    };
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+    let length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    String::from_utf8_lossy(&buf[header_end..]).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_output_schema_stdout_is_json_after_tool_call() {
+    let project = TempDir::new().unwrap();
+    let home = TempDir::new().unwrap();
+    fs::write(project.path().join("notes.txt"), "remember the milk\n").unwrap();
+    let schema = project.path().join("schema.json");
+    fs::write(
+        &schema,
+        json!({
+            "type": "object",
+            "required": ["summary"],
+            "properties": { "summary": { "type": "string" } }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    // 先读文件，再用 structured_output 提交回答，最后结束本轮
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let base_url = mock_provider(
+        |body| match body.matches("\"tool_result\"").count() {
+            0 => tool_use_response("toolu_1", "read_file", json!({ "file_path": "notes.txt" })),
+            1 => tool_use_response(
+                "toolu_2",
+                "structured_output",
+                json!({ "summary": "a shopping reminder" }),
+            ),
+            _ => text_response("Done."),
+        },
+        Arc::clone(&requests),
+    )
+    .await;
+
+    let run = Command::new(env!("CARGO_BIN_EXE_oxide"))
+        .args(["-p", "summarize notes.txt", "--output-schema"])
+        .arg(&schema)
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .env("OXIDE_AUTH_TOKEN", "sk-ant-test")
+        .env("OXIDE_BASE_URL", &base_url)
+        .env("OXIDE_NO_UPDATE_CHECK", "1")
+        .stdin(Stdio::null())
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(60), run)
+        .await
+        .expect("oxide -p did not finish")
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "stdout: {}\nstderr: {}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let answer: Value = serde_json::from_str(&stdout)
+        .unwrap_or_else(|e| panic!("stdout is not JSON ({}): {:?}", e, stdout));
+    assert_eq!(answer, json!({ "summary": "a shopping reminder" }));
+
+    // 工具确实执行了：第二个请求带着文件内容
+    let requests = requests.lock().unwrap();
+    assert!(requests.len() >= 2, "{} request(s)", requests.len());
+    assert!(requests[1].contains("remember the milk"), "{}", requests[1]);
+}
@@ -0,0 +1,112 @@
+//! shell_execute 系统沙箱集成测试（仅 Linux）
+//!
+//! 内核不支持 Landlock 或无法创建网络命名空间时跳过对应的测试。
+
+#![cfg(target_os = "linux")]
+
+use oxide::config::{SandboxConfig, SandboxMode};
+use oxide::tools::sandbox::Sandbox;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn run(sandbox: &Sandbox, workspace: &Path, script: &str) -> Output {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", script]).current_dir(workspace);
+    sandbox.apply(&mut cmd).unwrap();
+    cmd.output().unwrap()
+}
+
+fn sandbox(mode: SandboxMode, network: bool, workspace: &Path) -> Sandbox {
+    let config = SandboxConfig {
+        mode,
+        network,
+        ..SandboxConfig::default()
+    };
+    Sandbox::new(config, workspace)
+}
+
+#[test]
+fn test_workspace_write_blocks_writes_outside_workspace() {
+    if !Sandbox::filesystem_supported() {
+        eprintln!("skipped: Landlock is not available on this kernel");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let sandbox = sandbox(SandboxMode::WorkspaceWrite, true, workspace.path());
+
+    let output = run(&sandbox, workspace.path(), "echo inside > inside.txt");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("inside.txt")).unwrap(),
+        "inside\n"
+    );
+
+    let target = outside.path().join("outside.txt");
+    let output = run(
+        &sandbox,
+        workspace.path(),
+        &format!("echo outside > '{}'", target.display()),
+    );
+    assert!(!output.status.success());
+    assert!(!target.exists());
+
+    // 读取工作目录外的文件不受限制，重定向到 /dev/null 也可以
+    fs::write(outside.path().join("readable.txt"), "hello").unwrap();
+    let output = run(
+        &sandbox,
+        workspace.path(),
+        &format!(
+            "cat '{}' 2>/dev/null",
+            outside.path().join("readable.txt").display()
+        ),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello");
+}
+
+#[test]
+fn test_read_only_blocks_workspace_writes() {
+    if !Sandbox::filesystem_supported() {
+        eprintln!("skipped: Landlock is not available on this kernel");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    let sandbox = sandbox(SandboxMode::ReadOnly, true, workspace.path());
+
+    let output = run(&sandbox, workspace.path(), "echo data > file.txt");
+    assert!(!output.status.success());
+    assert!(!workspace.path().join("file.txt").exists());
+
+    let output = run(&sandbox, workspace.path(), "ls > /dev/null && echo ok");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+#[test]
+fn test_network_disabled_leaves_only_loopback() {
+    if !Sandbox::network_supported() {
+        eprintln!("skipped: network namespaces are unavailable");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    let sandbox = sandbox(SandboxMode::WorkspaceWrite, false, workspace.path());
+
+    // /proc/net/dev 列出当前网络命名空间中的接口
+    let output = run(
+        &sandbox,
+        workspace.path(),
+        "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "lo\n");
+
+    // 新的用户命名空间里仍然是原来的用户，工作目录照常可写
+    let output = run(&sandbox, workspace.path(), "touch created && id -u");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        unsafe { libc::getuid() }.to_string()
+    );
+}
@@ -0,0 +1,368 @@
+//! `oxide serve` 集成测试
+//!
+//! 用一个返回固定 SSE 流的本地 HTTP 服务器模拟 Anthropic API，
+//! 通过 WebSocket 客户端驱动完整的一轮对话。
+
+use futures::{SinkExt, StreamExt};
+use oxide::config::secret::Secret;
+use oxide::serve::{ServeOptions, Server};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 拼接 Anthropic 流式响应
+fn sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {}\n\n",
+                event["type"].as_str().unwrap(),
+                event
+            )
+        })
+        .collect()
+}
+
+fn message_start() -> Value {
+    json!({ "type": "message_start", "message": {
+        "id": "msg_1", "type": "message", "role": "assistant", "model": "claude-test",
+        "content": [], "stop_reason": null, "stop_sequence": null,
+        "usage": { "input_tokens": 12, "output_tokens": 1 } } })
+}
+
+fn message_end(stop_reason: &str) -> [Value; 2] {
+    [
+        json!({ "type": "message_delta",
+            "delta": { "stop_reason": stop_reason, "stop_sequence": null },
+            "usage": { "output_tokens": 7 } }),
+        json!({ "type": "message_stop" }),
+    ]
+}
+
+fn text_response(chunks: &[&str]) -> String {
+    let mut events = vec![
+        message_start(),
+        json!({ "type": "content_block_start", "index": 0,
+            "content_block": { "type": "text", "text": "" } }),
+    ];
+    for chunk in chunks {
+        events.push(json!({ "type": "content_block_delta", "index": 0,
+            "delta": { "type": "text_delta", "text": chunk } }));
+    }
+    events.push(json!({ "type": "content_block_stop", "index": 0 }));
+    events.extend(message_end("end_turn"));
+    sse(&events)
+}
+
+fn tool_use_response(name: &str, input: Value) -> String {
+    let mut events = vec![
+        message_start(),
+        json!({ "type": "content_block_start", "index": 0,
+            "content_block": { "type": "tool_use", "id": "toolu_1", "name": name, "input": {} } }),
+        json!({ "type": "content_block_delta", "index": 0,
+            "delta": { "type": "input_json_delta", "partial_json": input.to_string() } }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+    ];
+    events.extend(message_end("tool_use"));
+    sse(&events)
+}
+
+/// 模拟模型服务，`respond` 根据请求体返回 SSE 内容
+async fn mock_provider<F>(respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let respond = Arc::clone(&respond);
+            tokio::spawn(async move {
+                let body = read_request_body(&mut stream).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    respond(&body)
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}/anthropic", addr)
+}
+
+async fn read_request_body(stream: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if n == 0 {
+            return String::new();
+        }
+    };
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+    let length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    String::from_utf8_lossy(&buf[header_end..]).to_string()
+}
+
+/// 启动 `oxide serve`，返回地址和令牌
+async fn start_server(base_url: String, sessions_dir: &TempDir) -> (String, String) {
+    let listener = Server::bind(0).await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let server = Arc::new(
+        Server::new(ServeOptions {
+            base_url,
+            auth_token: Secret::new("sk-ant-test".to_string()),
+            model: Some("claude-test".to_string()),
+            sessions_dir: sessions_dir.path().to_path_buf(),
+            mcp: None,
+        })
+        .with_token("test-token"),
+    );
+    let token = server.token().to_string();
+    tokio::spawn(server.serve(listener));
+    (addr, token)
+}
+
+async fn connect(addr: &str, token: &str) -> Client {
+    let mut request = format!("ws://{}/", addr).into_client_request().unwrap();
+    request.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    let (client, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+    client
+}
+
+async fn call(client: &mut Client, id: u64, method: &str, params: Value) {
+    let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    client
+        .send(Message::text(message.to_string()))
+        .await
+        .unwrap();
+}
+
+/// 读取下一条消息
+async fn next(client: &mut Client) -> Value {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(10), client.next())
+            .await
+            .expect("timed out waiting for the server")
+            .unwrap()
+            .unwrap();
+        if let Message::Text(text) = frame {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+/// 读取响应，期间收到的事件放入 `events`
+async fn response(client: &mut Client, id: u64, events: &mut Vec<Value>) -> Value {
+    loop {
+        let message = next(client).await;
+        if message["id"] == id {
+            return message;
+        }
+        events.push(message["params"]["event"].clone());
+    }
+}
+
+/// 读取事件直到本轮结束
+async fn events_until_turn_end(client: &mut Client, events: &mut Vec<Value>) {
+    loop {
+        let message = next(client).await;
+        assert_eq!(message["method"], "session/event");
+        let event = message["params"]["event"].clone();
+        let done = matches!(
+            event["type"].as_str(),
+            Some("turn_completed" | "turn_cancelled" | "turn_failed")
+        );
+        events.push(event);
+        if done {
+            return;
+        }
+    }
+}
+
+fn event_types(events: &[Value]) -> Vec<&str> {
+    events.iter().filter_map(|e| e["type"].as_str()).collect()
+}
+
+#[tokio::test]
+async fn test_serve_streams_a_turn() {
+    let base_url = mock_provider(|_| text_response(&["Hello", " from", " oxide"])).await;
+    let sessions_dir = TempDir::new().unwrap();
+    let (addr, token) = start_server(base_url, &sessions_dir).await;
+    let mut client = connect(&addr, &token).await;
+    let mut events = Vec::new();
+
+    call(&mut client, 1, "session/start", json!({})).await;
+    let started = response(&mut client, 1, &mut events).await;
+    let session_id = started["result"]["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(started["result"]["resumed"], false);
+
+    call(
+        &mut client,
+        2,
+        "session/send",
+        json!({ "session_id": session_id, "message": "hi" }),
+    )
+    .await;
+    let accepted = response(&mut client, 2, &mut events).await;
+    assert_eq!(accepted["result"]["accepted"], true);
+    events_until_turn_end(&mut client, &mut events).await;
+
+    let text: String = events
+        .iter()
+        .filter(|e| e["type"] == "text_delta")
+        .filter_map(|e| e["text"].as_str())
+        .collect();
+    assert_eq!(text, "Hello from oxide");
+    assert!(event_types(&events).contains(&"usage"));
+    let last = events.last().unwrap();
+    assert_eq!(last["type"], "turn_completed");
+    assert_eq!(last["text"], "Hello from oxide");
+
+    // 会话已保存，可以列出并继续
+    call(&mut client, 3, "session/list", json!({})).await;
+    let listed = response(&mut client, 3, &mut events).await;
+    let sessions = listed["result"]["sessions"].as_array().unwrap();
+    let listed = sessions
+        .iter()
+        .find(|s| s["session_id"] == session_id.as_str())
+        .unwrap();
+    assert_eq!(listed["environment"]["mode"], "serve");
+
+    call(
+        &mut client,
+        4,
+        "session/start",
+        json!({ "session_id": session_id }),
+    )
+    .await;
+    let resumed = response(&mut client, 4, &mut events).await;
+    assert_eq!(resumed["result"]["resumed"], true);
+
+    call(
+        &mut client,
+        5,
+        "session/send",
+        json!({ "session_id": "missing", "message": "hi" }),
+    )
+    .await;
+    let error = response(&mut client, 5, &mut events).await;
+    assert_eq!(error["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn test_serve_asks_client_for_permission() {
+    let workspace = TempDir::new_in(env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let file = workspace.path().join("obsolete.txt");
+    std::fs::write(&file, "remove me").unwrap();
+
+    let input = json!({ "file_path": file.to_string_lossy() });
+    let base_url = mock_provider(move |body| {
+        if body.contains("tool_result") {
+            text_response(&["Deleted."])
+        } else {
+            tool_use_response("delete_file", input.clone())
+        }
+    })
+    .await;
+    let sessions_dir = TempDir::new().unwrap();
+    let (addr, token) = start_server(base_url, &sessions_dir).await;
+    let mut client = connect(&addr, &token).await;
+    let mut events = Vec::new();
+
+    call(&mut client, 1, "session/start", json!({})).await;
+    let started = response(&mut client, 1, &mut events).await;
+    let session_id = started["result"]["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    call(
+        &mut client,
+        2,
+        "session/send",
+        json!({ "session_id": session_id, "message": "clean up" }),
+    )
+    .await;
+    response(&mut client, 2, &mut events).await;
+
+    // 删除文件需要确认：服务器发出 permission_request 并等待回答
+    let request = loop {
+        let message = next(&mut client).await;
+        let event = message["params"]["event"].clone();
+        events.push(event.clone());
+        if event["type"] == "permission_request" {
+            break event;
+        }
+    };
+    assert_eq!(request["tool"], "delete_file");
+    assert!(file.exists());
+
+    call(
+        &mut client,
+        3,
+        "permission/respond",
+        json!({
+            "session_id": session_id,
+            "request_id": request["request_id"],
+            "decision": "approve",
+        }),
+    )
+    .await;
+    let answered = response(&mut client, 3, &mut events).await;
+    assert!(answered.get("error").is_none(), "{}", answered);
+    events_until_turn_end(&mut client, &mut events).await;
+
+    let types = event_types(&events);
+    assert!(types.contains(&"tool_started"));
+    assert!(types.contains(&"tool_result"));
+    assert_eq!(types.last(), Some(&"turn_completed"));
+    assert!(!file.exists());
+}
+
+#[tokio::test]
+async fn test_serve_rejects_missing_token() {
+    let sessions_dir = TempDir::new().unwrap();
+    let (addr, _) = start_server("http://127.0.0.1:9/anthropic".to_string(), &sessions_dir).await;
+
+    let result = tokio_tungstenite::connect_async(format!("ws://{}/", addr)).await;
+    assert!(result.is_err());
+    let result = tokio_tungstenite::connect_async(format!("ws://{}/?token=wrong", addr)).await;
+    assert!(result.is_err());
+
+    let (_client, _) = tokio_tungstenite::connect_async(format!("ws://{}/?token=test-token", addr))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,147 @@
+//! `OxideSession` 集成测试
+//!
+//! 用一个返回固定 SSE 流的本地 HTTP 服务器模拟 Anthropic API，
+//! 检查嵌入的会话中工具的相对路径以工作目录为基准。
+
+use futures::StreamExt;
+use oxide::agent::stream_event::StreamEvent;
+use oxide::agent::PermissionPolicy;
+use oxide::config::{Config, Secret};
+use oxide::session::OxideSession;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 拼接 Anthropic 流式响应
+fn sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {}\n\n",
+                event["type"].as_str().unwrap(),
+                event
+            )
+        })
+        .collect()
+}
+
+fn response(block: Value, delta: Value, stop_reason: &str) -> String {
+    sse(&[
+        json!({ "type": "message_start", "message": {
+            "id": "msg_1", "type": "message", "role": "assistant", "model": "claude-test",
+            "content": [], "stop_reason": null, "stop_sequence": null,
+            "usage": { "input_tokens": 12, "output_tokens": 1 } } }),
+        json!({ "type": "content_block_start", "index": 0, "content_block": block }),
+        json!({ "type": "content_block_delta", "index": 0, "delta": delta }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+        json!({ "type": "message_delta",
+            "delta": { "stop_reason": stop_reason, "stop_sequence": null },
+            "usage": { "output_tokens": 7 } }),
+        json!({ "type": "message_stop" }),
+    ])
+}
+
+/// 第一次请求调用 write_file，收到工具结果后回复文本
+async fn mock_provider(input: Value) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let input = Arc::new(input);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let input = Arc::clone(&input);
+            tokio::spawn(async move {
+                let body = read_request(&mut stream).await;
+                let sse = if body.contains("tool_result") {
+                    response(
+                        json!({ "type": "text", "text": "" }),
+                        json!({ "type": "text_delta", "text": "Written." }),
+                        "end_turn",
+                    )
+                } else {
+                    response(
+                        json!({ "type": "tool_use", "id": "toolu_1", "name": "write_file", "input": {} }),
+                        json!({ "type": "input_json_delta", "partial_json": input.to_string() }),
+                        "tool_use",
+                    )
+                };
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    sse
+                );
+                let _ = stream.write_all(reply.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}/anthropic", addr)
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if n == 0 {
+            return String::new();
+        }
+    };
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+    let length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    String::from_utf8_lossy(&buf[header_end..]).to_string()
+}
+
+#[tokio::test]
+async fn test_session_tools_write_under_working_dir() {
+    let relative = "notes/session-integration-output.txt";
+    let base_url =
+        mock_provider(json!({ "file_path": relative, "content": "hello from the agent" })).await;
+    let config = Config {
+        base_url,
+        auth_token: Secret::new("sk-ant-test".to_string()),
+        model: Some("claude-test".to_string()),
+        max_tokens: 1024,
+        stream_chars_per_tick: 0,
+        stream_animation: false,
+        debug: false,
+    };
+    let workdir = TempDir::new().unwrap();
+    let mut session = OxideSession::builder(config)
+        .with_working_dir(workdir.path())
+        .with_permissions(Arc::new(PermissionPolicy::ApproveAll))
+        .build()
+        .unwrap();
+
+    let mut events = session.send("Write the notes");
+    while let Some(event) = events.next().await {
+        if let StreamEvent::Error { message } = event {
+            panic!("turn failed: {}", message);
+        }
+    }
+    drop(events);
+
+    // 文件落在会话的工作目录下，而不是测试进程的当前目录
+    let written = workdir.path().join(relative);
+    assert_eq!(
+        std::fs::read_to_string(&written).unwrap(),
+        "hello from the agent"
+    );
+    assert!(!std::path::Path::new(relative).exists());
+}
@@ -0,0 +1,96 @@
+//! 启动耗时预算测试
+//!
+//! 用 `--profile-startup` 启动 REPL，解析 stderr 中的 "time to prompt" 行，
+//! 多次运行取中位数，与预算（默认 100ms，`OXIDE_STARTUP_BUDGET_MS` 覆盖）比较。
+//! 耗时受构建配置影响，默认忽略，CI 中用 release 构建运行：
+//!
+//! ```text
+//! cargo test --release --test startup_timing_test -- --ignored
+//! ```
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const RUNS: usize = 5;
+const DEFAULT_BUDGET_MS: f64 = 100.0;
+const RUN_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 模拟中等规模的项目目录，文件索引在后台建立，不应计入启动耗时
+fn populate_project(dir: &Path) {
+    for i in 0..50 {
+        let module = dir.join("src").join(format!("module_{}", i));
+        fs::create_dir_all(&module).unwrap();
+        for j in 0..40 {
+            fs::write(module.join(format!("file_{}.rs", j)), "fn main() {}\n").unwrap();
+        }
+    }
+}
+
+/// 运行一次并返回 time to prompt（毫秒）
+fn time_to_prompt(project: &Path, home: &Path) -> f64 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_oxide"))
+        .arg("--profile-startup")
+        .current_dir(project)
+        .env("HOME", home)
+        .env("OXIDE_AUTH_TOKEN", "test-token")
+        .env("OXIDE_BASE_URL", "http://127.0.0.1:9")
+        .env("OXIDE_NO_UPDATE_CHECK", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start oxide");
+
+    let deadline = Instant::now() + RUN_TIMEOUT;
+    while child.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("oxide did not exit within {:?}", RUN_TIMEOUT);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    stderr
+        .lines()
+        .find_map(|line| {
+            let rest = line.strip_prefix("startup")?.trim_start();
+            let value = rest
+                .strip_prefix("time to prompt")?
+                .trim()
+                .strip_suffix("ms")?;
+            value.trim().parse().ok()
+        })
+        .unwrap_or_else(|| panic!("no time-to-prompt line in stderr:\n{}", stderr))
+}
+
+#[test]
+#[ignore = "timing budget, run in CI with --release"]
+fn test_time_to_prompt_within_budget() {
+    let project = TempDir::new().unwrap();
+    let home = TempDir::new().unwrap();
+    populate_project(project.path());
+
+    let budget = std::env::var("OXIDE_STARTUP_BUDGET_MS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_BUDGET_MS);
+
+    let mut samples: Vec<f64> = (0..RUNS)
+        .map(|_| time_to_prompt(project.path(), home.path()))
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    let median = samples[RUNS / 2];
+
+    assert!(
+        median <= budget,
+        "time to prompt {:.1} ms exceeds budget {:.1} ms (samples: {:?})",
+        median,
+        budget,
+        samples
+    );
+}